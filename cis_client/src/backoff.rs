@@ -0,0 +1,82 @@
+use rand::Rng;
+use std::time::Duration;
+
+/// Exponential backoff between the polls of the gateway
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub multiplier: f64,
+    /// Maximum random part of each delay, as a fraction of the delay, so the clients do not all poll at the same time
+    pub jitter: f64,
+    /// Total time after which the polling stops, without limit if `None`
+    pub deadline: Option<Duration>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.1,
+            deadline: Some(Duration::from_secs(30 * 60)),
+        }
+    }
+}
+
+impl Backoff {
+    /// Delay before the poll following the given number of polls, without its jitter
+    pub fn base_delay(&self, nb_polls: u32) -> Duration {
+        let factor = self.multiplier.powi(nb_polls.min(i32::MAX as u32) as i32);
+        self.initial_delay
+            .mul_f64(factor.min(u32::MAX as f64))
+            .min(self.max_delay)
+    }
+
+    /// Delay before the poll following the given number of polls
+    pub fn delay(&self, nb_polls: u32) -> Duration {
+        let delay = self.base_delay(nb_polls);
+        if self.jitter <= 0.0 {
+            return delay;
+        }
+
+        delay.mul_f64(1.0 + rand::thread_rng().gen_range(0.0..self.jitter))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_grow_exponentially_up_to_the_max_delay() {
+        let backoff = Backoff {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: 0.0,
+            deadline: None,
+        };
+
+        let delays: Vec<u64> = (0..6)
+            .map(|nb_polls| backoff.delay(nb_polls).as_secs())
+            .collect();
+
+        assert_eq!(delays, vec![1, 2, 4, 8, 10, 10]);
+        assert_eq!(backoff.delay(u32::MAX), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn jitter_only_lengthens_the_delay() {
+        let backoff = Backoff::default();
+
+        for nb_polls in 0..20 {
+            let base_delay = backoff.base_delay(nb_polls);
+            let delay = backoff.delay(nb_polls);
+
+            assert!(delay >= base_delay);
+            assert!(delay <= base_delay.mul_f64(1.0 + backoff.jitter));
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use futures::{stream, Stream};
+use reqwest::{
+    multipart::{Form, Part},
+    RequestBuilder, Response, Url,
+};
+use rest_gateway::{
+    controllers::{
+        AddSourceFilesResponse, CompleteUploadResponse, GetJobResponse, GetSourceEventsResponse,
+        LogInAccountBodyData, LogInAccountResponse, SearchContentBodyData, SearchContentResponse,
+        SourceEventResponse, StartUploadBodyData, UploadResponse,
+    },
+    domain::entities::ingestion_job::JobStatus,
+    middlewares::jwt_authentication::middleware::API_KEY_HEADER,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::de::DeserializeOwned;
+use std::{
+    collections::{HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::{Backoff, CisClientError};
+
+/// Credentials sent on each request to the gateway
+#[derive(Clone)]
+pub enum Credentials {
+    /// Access token issued on log-in, or by the authentication backend of the gateway
+    Bearer(Secret<String>),
+    ApiKey(Secret<String>),
+}
+
+impl Credentials {
+    pub fn bearer(token: String) -> Self {
+        Credentials::Bearer(Secret::new(token))
+    }
+
+    pub fn api_key(key: String) -> Self {
+        Credentials::ApiKey(Secret::new(key))
+    }
+
+    fn apply(&self, request: RequestBuilder) -> RequestBuilder {
+        match self {
+            Credentials::Bearer(token) => request.bearer_auth(token.expose_secret()),
+            Credentials::ApiKey(key) => request.header(API_KEY_HEADER, key.expose_secret()),
+        }
+    }
+}
+
+/// Source file sent in a multipart upload
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub file_name: String,
+    pub content: Vec<u8>,
+}
+
+impl SourceFile {
+    pub fn new(file_name: impl Into<String>, content: Vec<u8>) -> Self {
+        Self {
+            file_name: file_name.into(),
+            content,
+        }
+    }
+}
+
+/// Events of a subscription to a source, not yielded yet
+struct SubscriptionState {
+    pending_events: VecDeque<SourceEventResponse>,
+    seen_event_ids: HashSet<Uuid>,
+    polled: bool,
+    ended: bool,
+}
+
+/// Progress of a watched job, not yielded yet
+struct JobWatchState {
+    last_progress: Option<(JobStatus, Option<i64>, i64)>,
+    next_delay: Option<Duration>,
+    nb_polls: u32,
+    started_at: Instant,
+    ended: bool,
+}
+
+/// Client of the REST API of the gateway
+#[derive(Clone)]
+pub struct CisClient {
+    http_client: reqwest::Client,
+    base_url: Url,
+    credentials: Credentials,
+}
+
+impl CisClient {
+    pub fn new(base_url: &str, credentials: Credentials) -> Result<Self, CisClientError> {
+        Self::with_http_client(reqwest::Client::new(), base_url, credentials)
+    }
+
+    /// Client sending its requests with the given HTTP client, to set its timeouts or proxies
+    pub fn with_http_client(
+        http_client: reqwest::Client,
+        base_url: &str,
+        credentials: Credentials,
+    ) -> Result<Self, CisClientError> {
+        Ok(Self {
+            http_client,
+            base_url: parse_base_url(base_url)?,
+            credentials,
+        })
+    }
+
+    /// Logs in a user, returning a client authenticated with the issued access token, and the log-in response
+    ///
+    /// The access token is short-lived: the long-running scripts should authenticate with an API key instead.
+    pub async fn log_in(
+        base_url: &str,
+        body: &LogInAccountBodyData,
+    ) -> Result<(Self, LogInAccountResponse), CisClientError> {
+        let http_client = reqwest::Client::new();
+        let url = parse_base_url(base_url)?
+            .join("account/login")
+            .map_err(|_| CisClientError::InvalidBaseUrl(base_url.to_string()))?;
+
+        let response = http_client.post(url).json(body).send().await?;
+        if !response.status().is_success() {
+            return Err(CisClientError::from_response(response).await);
+        }
+        let log_in: LogInAccountResponse = response.json().await?;
+
+        let client = Self::with_http_client(
+            http_client,
+            base_url,
+            Credentials::bearer(log_in.access_token.clone()),
+        )?;
+        Ok((client, log_in))
+    }
+
+    /// Uploads source files in a single multipart request, each file getting the given tags
+    pub async fn add_source_files(
+        &self,
+        files: Vec<SourceFile>,
+        tags: &[String],
+    ) -> Result<AddSourceFilesResponse, CisClientError> {
+        let mut form = Form::new();
+        for file in files {
+            form = form.part("file", Part::bytes(file.content).file_name(file.file_name));
+        }
+        for tag in tags {
+            form = form.text("tag", tag.clone());
+        }
+
+        let request = self.request(reqwest::Method::POST, "add_source_files")?;
+        self.send_json(request.multipart(form)).await
+    }
+
+    /// Uploads a large source file part by part, and completes its upload
+    pub async fn upload_file_in_parts(
+        &self,
+        body: &StartUploadBodyData,
+        content: &[u8],
+    ) -> Result<CompleteUploadResponse, CisClientError> {
+        let upload = self.start_upload(body).await?;
+        info!(
+            "Started the upload {} of {} in parts of {} bytes",
+            upload.id, upload.file_name, upload.max_part_bytes
+        );
+
+        self.resume_upload(upload.id, content).await
+    }
+
+    /// Starts the upload of a large source file, whose parts are then sent with `resume_upload`
+    pub async fn start_upload(
+        &self,
+        body: &StartUploadBodyData,
+    ) -> Result<UploadResponse, CisClientError> {
+        let request = self.request(reqwest::Method::POST, "uploads")?;
+        self.send_json(request.json(body)).await
+    }
+
+    /// Uploads the parts of an upload not received by the gateway yet, and completes the upload
+    ///
+    /// The content must be the same as the one of the interrupted upload.
+    pub async fn resume_upload(
+        &self,
+        upload_id: Uuid,
+        content: &[u8],
+    ) -> Result<CompleteUploadResponse, CisClientError> {
+        let upload = self.get_upload(upload_id).await?;
+        let received_parts: HashSet<i32> =
+            upload.parts.iter().map(|part| part.part_number).collect();
+
+        for (index, chunk) in content.chunks(upload.max_part_bytes.max(1)).enumerate() {
+            let part_number = index as i32 + 1;
+            if received_parts.contains(&part_number) {
+                continue;
+            }
+
+            debug!(
+                "Uploading the part {} of the upload {}",
+                part_number, upload_id
+            );
+            let request = self.request(
+                reqwest::Method::PUT,
+                &format!("uploads/{}/parts/{}", upload_id, part_number),
+            )?;
+            self.send(request.body(chunk.to_vec())).await?;
+        }
+
+        let request = self.request(
+            reqwest::Method::POST,
+            &format!("uploads/{}/complete", upload_id),
+        )?;
+        self.send_json(request).await
+    }
+
+    pub async fn get_upload(&self, upload_id: Uuid) -> Result<UploadResponse, CisClientError> {
+        let request = self.request(reqwest::Method::GET, &format!("uploads/{}", upload_id))?;
+        self.send_json(request).await
+    }
+
+    pub async fn abort_upload(&self, upload_id: Uuid) -> Result<(), CisClientError> {
+        let request = self.request(reqwest::Method::DELETE, &format!("uploads/{}", upload_id))?;
+        self.send(request).await?;
+        Ok(())
+    }
+
+    pub async fn search(
+        &self,
+        body: &SearchContentBodyData,
+    ) -> Result<SearchContentResponse, CisClientError> {
+        let request = self.request(reqwest::Method::POST, "search")?;
+        self.send_json(request.json(body)).await
+    }
+
+    /// Deletes a source with its file and all the contents extracted from it
+    pub async fn delete_source(&self, source_id: Uuid) -> Result<(), CisClientError> {
+        let request = self.request(reqwest::Method::DELETE, &format!("sources/{}", source_id))?;
+        self.send(request).await?;
+        Ok(())
+    }
+
+    pub async fn get_job(&self, job_id: Uuid) -> Result<GetJobResponse, CisClientError> {
+        let request = self.request(reqwest::Method::GET, &format!("jobs/{}", job_id))?;
+        self.send_json(request).await
+    }
+
+    /// Polls an ingestion job until it is completed or failed
+    ///
+    /// A throttled poll is retried after the `Retry-After` of the gateway, instead of the backoff delay.
+    pub async fn wait_for_job(
+        &self,
+        job_id: Uuid,
+        backoff: Backoff,
+    ) -> Result<GetJobResponse, CisClientError> {
+        let started_at = Instant::now();
+        let mut nb_polls = 0;
+
+        loop {
+            let delay = match self.get_job(job_id).await {
+                Ok(job) if job.status.is_final() => return Ok(job),
+                Ok(job) => {
+                    debug!("Job {} is {:?}", job_id, job.status);
+                    backoff.delay(nb_polls)
+                }
+                Err(CisClientError::Throttled { retry_after, .. }) => retry_after,
+                Err(error) => return Err(error),
+            };
+            nb_polls = nb_polls.saturating_add(1);
+
+            if let Some(deadline) = backoff.deadline {
+                if started_at.elapsed() + delay > deadline {
+                    return Err(CisClientError::JobPollingTimeout(job_id));
+                }
+            }
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Stream of the progress of an ingestion job, polled with the given backoff until it is completed or failed
+    ///
+    /// The job is yielded on its first poll, and then each time its status or its number of contents changes.
+    /// The stream ends after the completed or failed job, or on the first error.
+    pub fn watch_job(
+        &self,
+        job_id: Uuid,
+        backoff: Backoff,
+    ) -> impl Stream<Item = Result<GetJobResponse, CisClientError>> + '_ {
+        let state = JobWatchState {
+            last_progress: None,
+            next_delay: None,
+            nb_polls: 0,
+            started_at: Instant::now(),
+            ended: false,
+        };
+
+        stream::unfold(state, move |mut state| {
+            let backoff = backoff.clone();
+            async move {
+                loop {
+                    if state.ended {
+                        return None;
+                    }
+                    if let Some(delay) = state.next_delay.take() {
+                        tokio::time::sleep(delay).await;
+                    }
+
+                    let (job, delay) = match self.get_job(job_id).await {
+                        Ok(job) if job.status.is_final() => {
+                            state.ended = true;
+                            return Some((Ok(job), state));
+                        }
+                        Ok(job) => {
+                            let progress = (job.status, job.nb_contents, job.nb_embedded_contents);
+                            let is_changed = state.last_progress != Some(progress);
+                            state.last_progress = Some(progress);
+                            (is_changed.then_some(job), backoff.delay(state.nb_polls))
+                        }
+                        Err(CisClientError::Throttled { retry_after, .. }) => (None, retry_after),
+                        Err(error) => {
+                            state.ended = true;
+                            return Some((Err(error), state));
+                        }
+                    };
+                    state.nb_polls = state.nb_polls.saturating_add(1);
+
+                    if let Some(deadline) = backoff.deadline {
+                        if state.started_at.elapsed() + delay > deadline {
+                            state.ended = true;
+                            return Some((Err(CisClientError::JobPollingTimeout(job_id)), state));
+                        }
+                    }
+                    state.next_delay = Some(delay);
+
+                    if let Some(job) = job {
+                        return Some((Ok(job), state));
+                    }
+                }
+            }
+        })
+    }
+
+    pub async fn get_source_events(
+        &self,
+        source_id: Uuid,
+    ) -> Result<GetSourceEventsResponse, CisClientError> {
+        let request = self.request(
+            reqwest::Method::GET,
+            &format!("sources/{}/events", source_id),
+        )?;
+        self.send_json(request).await
+    }
+
+    /// Stream of the events of a source, from its first one, polled with the given interval
+    ///
+    /// The gateway has no push endpoint: the events are listed again on each poll, and only the new ones are yielded.
+    /// The stream ends on the first error.
+    pub fn subscribe_source_events(
+        &self,
+        source_id: Uuid,
+        poll_interval: Duration,
+    ) -> impl Stream<Item = Result<SourceEventResponse, CisClientError>> + '_ {
+        let state = SubscriptionState {
+            pending_events: VecDeque::new(),
+            seen_event_ids: HashSet::new(),
+            polled: false,
+            ended: false,
+        };
+
+        stream::unfold(state, move |mut state| async move {
+            loop {
+                if let Some(event) = state.pending_events.pop_front() {
+                    return Some((Ok(event), state));
+                }
+                if state.ended {
+                    return None;
+                }
+
+                if state.polled {
+                    tokio::time::sleep(poll_interval).await;
+                }
+                state.polled = true;
+
+                match self.get_source_events(source_id).await {
+                    Ok(response) => {
+                        let seen_event_ids = &mut state.seen_event_ids;
+                        state.pending_events.extend(
+                            response
+                                .events
+                                .into_iter()
+                                .filter(|event| seen_event_ids.insert(event.id)),
+                        );
+                    }
+                    Err(CisClientError::Throttled { retry_after, .. }) => {
+                        tokio::time::sleep(retry_after).await;
+                    }
+                    Err(error) => {
+                        state.ended = true;
+                        return Some((Err(error), state));
+                    }
+                }
+            }
+        })
+    }
+
+    fn request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+    ) -> Result<RequestBuilder, CisClientError> {
+        let url = self
+            .base_url
+            .join(path)
+            .map_err(|_| CisClientError::InvalidBaseUrl(self.base_url.to_string()))?;
+
+        Ok(self
+            .credentials
+            .apply(self.http_client.request(method, url)))
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response, CisClientError> {
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(CisClientError::from_response(response).await);
+        }
+
+        Ok(response)
+    }
+
+    async fn send_json<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<T, CisClientError> {
+        Ok(self.send(request).await?.json().await?)
+    }
+}
+
+/// Base URL of the gateway, with a trailing slash
+fn parse_base_url(base_url: &str) -> Result<Url, CisClientError> {
+    // Without a trailing slash, the last segment of the base URL would be replaced when joining the paths
+    Url::parse(&format!("{}/", base_url.trim_end_matches('/')))
+        .map_err(|_| CisClientError::InvalidBaseUrl(base_url.to_string()))
+}
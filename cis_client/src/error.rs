@@ -0,0 +1,64 @@
+use common::helper::error_chain_fmt;
+use reqwest::StatusCode;
+use rest_gateway::middlewares::throttling::THROTTLING_REASON_HEADER;
+use std::time::Duration;
+use uuid::Uuid;
+
+#[derive(thiserror::Error)]
+pub enum CisClientError {
+    #[error("Invalid base URL of the gateway: {0}")]
+    InvalidBaseUrl(String),
+    #[error(transparent)]
+    RequestError(#[from] reqwest::Error),
+    #[error("The gateway responded with {status}: {message}")]
+    ApiError { status: StatusCode, message: String },
+    #[error("The request was throttled ({reason}), retry after {retry_after:?}")]
+    Throttled {
+        reason: String,
+        retry_after: Duration,
+    },
+    #[error("Job {0} did not end before the deadline of the polling")]
+    JobPollingTimeout(Uuid),
+}
+
+impl std::fmt::Debug for CisClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl CisClientError {
+    /// Error of a response of the gateway with an unsuccessful status
+    pub(crate) async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status();
+        let headers = response.headers();
+
+        if let Some(retry_after) = headers
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+        {
+            let reason = headers
+                .get(THROTTLING_REASON_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or("unknown")
+                .to_string();
+
+            return CisClientError::Throttled {
+                reason,
+                retry_after: Duration::from_secs(retry_after),
+            };
+        }
+
+        let message = response.text().await.unwrap_or_default();
+        CisClientError::ApiError { status, message }
+    }
+
+    /// Time to wait before retrying the request, only for a throttled request
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            CisClientError::Throttled { retry_after, .. } => Some(*retry_after),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,35 @@
+//! Typed async client of the REST API of the gateway
+//!
+//! The requests and responses are the types of the gateway controllers, so the client follows their changes.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), cis_client::CisClientError> {
+//! use cis_client::{Backoff, CisClient, Credentials, SourceFile};
+//!
+//! let client = CisClient::new("http://localhost:8000", Credentials::api_key("cis_...".into()))?;
+//! let response = client
+//!     .add_source_files(vec![SourceFile::new("book.epub", vec![])], &[])
+//!     .await?;
+//! if let Some(job_id) = response.file_status[0].job_id {
+//!     let job = client.wait_for_job(job_id, Backoff::default()).await?;
+//!     println!("{:?}", job.status);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod backoff;
+pub mod client;
+pub mod error;
+
+pub use backoff::*;
+pub use client::*;
+pub use error::*;
+
+pub use rest_gateway::controllers::{
+    AddSourceFileStatus, AddSourceFilesResponse, CompleteUploadResponse, GetJobResponse,
+    GetSourceEventsResponse, LogInAccountBodyData, LogInAccountResponse, SearchContentBodyData,
+    SearchContentResponse, SearchMode, SourceEventResponse, StartUploadBodyData,
+    Status as AddSourceFileStatusCode, UploadResponse,
+};
+pub use rest_gateway::domain::entities::{ingestion_job::JobStatus, search_result::SearchResult};
@@ -0,0 +1,364 @@
+use cis_client::SearchMode;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use crate::error::CliError;
+
+pub const USAGE: &str = "Usage: cis [--url <url>] [--json] <command>
+
+Options:
+  --url <url>  URL of the gateway, `CIS_URL` or http://localhost:8000 by default
+  --json       Prints each result as a JSON line, rather than as tab-separated columns
+
+Commands:
+  login <email>                      Logs in with the password read from `CIS_PASSWORD` or stdin, and prints the access token
+  upload <dir> [--tag <tag>]... [--concurrency <n>] [--watch]
+                                     Uploads the files of a directory and of its subdirectories, 4 at a time by default,
+                                     and waits for their ingestion with --watch
+  job <job_id>... [--watch]          Prints the status of ingestion jobs, and their progress until they end with --watch
+  search <query> [--mode <fulltext|semantic|hybrid>] [--limit <n>] [--language <code>]
+                                     Searches the contents
+  delete <source_id>...              Deletes sources with their contents
+
+The commands other than login authenticate with the `CIS_API_KEY` API key, or the `CIS_TOKEN` access token.";
+
+pub const DEFAULT_URL: &str = "http://localhost:8000";
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Arguments of the binary
+#[derive(Debug, PartialEq)]
+pub struct Cli {
+    /// URL of the gateway, read from the environment if `None`
+    pub url: Option<String>,
+    pub json: bool,
+    pub command: Command,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    LogIn {
+        email: String,
+    },
+    Upload {
+        dir: PathBuf,
+        tags: Vec<String>,
+        concurrency: usize,
+        watch: bool,
+    },
+    Job {
+        job_ids: Vec<Uuid>,
+        watch: bool,
+    },
+    Search {
+        query: String,
+        mode: SearchMode,
+        limit: Option<usize>,
+        language: Option<String>,
+    },
+    Delete {
+        source_ids: Vec<Uuid>,
+    },
+}
+
+impl Cli {
+    /// Parses the arguments of the binary, without the binary name
+    pub fn try_parse(args: &[String]) -> Result<Self, CliError> {
+        let mut url = None;
+        let mut json = false;
+
+        let mut args = args.iter().map(String::as_str);
+        let command = loop {
+            match args.next() {
+                Some("--url") => url = Some(option_value(&mut args, "--url")?.to_string()),
+                Some("--json") => json = true,
+                Some(command) => break command,
+                None => return Err(CliError::InvalidArguments("Missing command".to_string())),
+            }
+        };
+        let args: Vec<&str> = args.collect();
+
+        let command = match command {
+            "login" => match args.as_slice() {
+                [email] => Command::LogIn {
+                    email: email.to_string(),
+                },
+                _ => return Err(invalid_arguments("login expects an email")),
+            },
+            "upload" => Command::try_parse_upload(&args)?,
+            "job" => Command::try_parse_job(&args)?,
+            "search" => Command::try_parse_search(&args)?,
+            "delete" => {
+                if args.is_empty() {
+                    return Err(invalid_arguments("delete expects source ids"));
+                }
+                Command::Delete {
+                    source_ids: args
+                        .iter()
+                        .map(|id| parse_id(id))
+                        .collect::<Result<_, _>>()?,
+                }
+            }
+            command => {
+                return Err(CliError::InvalidArguments(format!(
+                    "Unknown command: {}",
+                    command
+                )))
+            }
+        };
+
+        Ok(Self { url, json, command })
+    }
+}
+
+impl Command {
+    fn try_parse_upload(args: &[&str]) -> Result<Self, CliError> {
+        let mut tags = vec![];
+        let mut concurrency = DEFAULT_CONCURRENCY;
+        let mut watch = false;
+        let mut positional = vec![];
+
+        let mut args = args.iter().copied();
+        while let Some(arg) = args.next() {
+            match arg {
+                "--tag" => tags.push(option_value(&mut args, "--tag")?.to_string()),
+                "--concurrency" => {
+                    let value = option_value(&mut args, "--concurrency")?;
+                    concurrency = value
+                        .parse()
+                        .ok()
+                        .filter(|concurrency| *concurrency > 0)
+                        .ok_or_else(|| {
+                            CliError::InvalidArguments(format!("Invalid concurrency: {}", value))
+                        })?;
+                }
+                "--watch" => watch = true,
+                _ => positional.push(arg),
+            }
+        }
+
+        match positional.as_slice() {
+            [dir] => Ok(Self::Upload {
+                dir: PathBuf::from(dir),
+                tags,
+                concurrency,
+                watch,
+            }),
+            _ => Err(invalid_arguments("upload expects a directory")),
+        }
+    }
+
+    fn try_parse_job(args: &[&str]) -> Result<Self, CliError> {
+        let watch = args.contains(&"--watch");
+        let job_ids: Vec<Uuid> = args
+            .iter()
+            .filter(|arg| **arg != "--watch")
+            .map(|id| parse_id(id))
+            .collect::<Result<_, _>>()?;
+
+        if job_ids.is_empty() {
+            return Err(invalid_arguments("job expects job ids"));
+        }
+        Ok(Self::Job { job_ids, watch })
+    }
+
+    fn try_parse_search(args: &[&str]) -> Result<Self, CliError> {
+        let mut mode = SearchMode::default();
+        let mut limit = None;
+        let mut language = None;
+        let mut positional = vec![];
+
+        let mut args = args.iter().copied();
+        while let Some(arg) = args.next() {
+            match arg {
+                "--mode" => {
+                    let value = option_value(&mut args, "--mode")?;
+                    mode = serde_json::from_value(serde_json::Value::String(value.to_string()))
+                        .map_err(|_| {
+                            CliError::InvalidArguments(format!("Invalid search mode: {}", value))
+                        })?;
+                }
+                "--limit" => {
+                    let value = option_value(&mut args, "--limit")?;
+                    limit = Some(value.parse().map_err(|_| {
+                        CliError::InvalidArguments(format!("Invalid limit: {}", value))
+                    })?);
+                }
+                "--language" => {
+                    language = Some(option_value(&mut args, "--language")?.to_string());
+                }
+                _ => positional.push(arg),
+            }
+        }
+
+        match positional.as_slice() {
+            [query] => Ok(Self::Search {
+                query: query.to_string(),
+                mode,
+                limit,
+                language,
+            }),
+            _ => Err(invalid_arguments("search expects a single query, quoted")),
+        }
+    }
+}
+
+/// Value following an option
+fn option_value<'a>(
+    args: &mut impl Iterator<Item = &'a str>,
+    option: &str,
+) -> Result<&'a str, CliError> {
+    args.next()
+        .ok_or_else(|| CliError::InvalidArguments(format!("Missing value of {}", option)))
+}
+
+fn parse_id(id: &str) -> Result<Uuid, CliError> {
+    Uuid::parse_str(id).map_err(|_| CliError::InvalidArguments(format!("Invalid id: {}", id)))
+}
+
+fn invalid_arguments(message: &str) -> CliError {
+    CliError::InvalidArguments(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn global_options_are_parsed_before_the_command() {
+        assert_eq!(
+            Cli::try_parse(&args(&[
+                "--url",
+                "https://cis.example.com",
+                "--json",
+                "login",
+                "john@doe.com"
+            ]))
+            .unwrap(),
+            Cli {
+                url: Some("https://cis.example.com".to_string()),
+                json: true,
+                command: Command::LogIn {
+                    email: "john@doe.com".to_string()
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn upload_is_parsed_with_its_options() {
+        assert_eq!(
+            Cli::try_parse(&args(&["upload", "books"])).unwrap().command,
+            Command::Upload {
+                dir: PathBuf::from("books"),
+                tags: vec![],
+                concurrency: DEFAULT_CONCURRENCY,
+                watch: false,
+            }
+        );
+        assert_eq!(
+            Cli::try_parse(&args(&[
+                "upload",
+                "--tag",
+                "novel",
+                "books",
+                "--concurrency",
+                "8",
+                "--tag",
+                "french",
+                "--watch"
+            ]))
+            .unwrap()
+            .command,
+            Command::Upload {
+                dir: PathBuf::from("books"),
+                tags: vec!["novel".to_string(), "french".to_string()],
+                concurrency: 8,
+                watch: true,
+            }
+        );
+    }
+
+    #[test]
+    fn search_is_parsed_with_its_options() {
+        assert_eq!(
+            Cli::try_parse(&args(&[
+                "search",
+                "the old man",
+                "--mode",
+                "hybrid",
+                "--limit",
+                "5",
+                "--language",
+                "en"
+            ]))
+            .unwrap()
+            .command,
+            Command::Search {
+                query: "the old man".to_string(),
+                mode: SearchMode::Hybrid,
+                limit: Some(5),
+                language: Some("en".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn id_commands_are_parsed_with_several_ids() {
+        let (first_id, second_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+        assert_eq!(
+            Cli::try_parse(&args(&[
+                "job",
+                &first_id.to_string(),
+                "--watch",
+                &second_id.to_string()
+            ]))
+            .unwrap()
+            .command,
+            Command::Job {
+                job_ids: vec![first_id, second_id],
+                watch: true,
+            }
+        );
+        assert_eq!(
+            Cli::try_parse(&args(&["delete", &first_id.to_string()]))
+                .unwrap()
+                .command,
+            Command::Delete {
+                source_ids: vec![first_id]
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_arguments_are_rejected() {
+        for invalid_args in [
+            vec![],
+            args(&["--json"]),
+            args(&["--url"]),
+            args(&["unknown"]),
+            args(&["login"]),
+            args(&["upload"]),
+            args(&["upload", "books", "--concurrency", "0"]),
+            args(&["upload", "books", "--tag"]),
+            args(&["job", "--watch"]),
+            args(&["job", "not-a-uuid"]),
+            args(&["search", "too", "many"]),
+            args(&["search", "query", "--mode", "fuzzy"]),
+            args(&["delete"]),
+        ] {
+            assert!(
+                matches!(
+                    Cli::try_parse(&invalid_args),
+                    Err(CliError::InvalidArguments(_))
+                ),
+                "{:?} should be rejected",
+                invalid_args
+            );
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use cis_client::CisClientError;
+use common::helper::error_chain_fmt;
+
+use crate::command::USAGE;
+
+#[derive(thiserror::Error)]
+pub enum CliError {
+    #[error("{0}\n\n{USAGE}")]
+    InvalidArguments(String),
+    #[error("Missing credentials: set the CIS_API_KEY or CIS_TOKEN environment variable")]
+    MissingCredentials,
+    #[error("{0} of the {1} items failed")]
+    PartialFailure(usize, usize),
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+    #[error(transparent)]
+    SerializationError(#[from] serde_json::Error),
+    #[error(transparent)]
+    CisClientError(#[from] CisClientError),
+}
+
+impl std::fmt::Debug for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,32 @@
+mod command;
+mod error;
+mod runner;
+mod source_files;
+
+use common::telemetry::{get_tracing_subscriber, init_tracing_subscriber};
+
+use crate::command::Cli;
+
+/// Command-line client of the gateway, for scripting the ingestion and the search of large libraries
+///
+/// For ex: `CIS_API_KEY=cis_... cargo run --bin cis -- upload ./books --watch`
+#[tokio::main]
+async fn main() {
+    // Logs on stderr, the results of the commands are written on stdout
+    let tracing_subscriber = get_tracing_subscriber("cis".into(), "warn".into(), std::io::stderr);
+    init_tracing_subscriber(tracing_subscriber);
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let cli = match Cli::try_parse(&args) {
+        Ok(cli) => cli,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(error) = runner::run(cli, &mut std::io::stdout()).await {
+        eprintln!("{:?}", error);
+        std::process::exit(1);
+    }
+}
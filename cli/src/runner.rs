@@ -0,0 +1,460 @@
+use cis_client::{
+    AddSourceFileStatus, AddSourceFileStatusCode, Backoff, CisClient, CisClientError, Credentials,
+    GetJobResponse, JobStatus, LogInAccountBodyData, SearchContentBodyData, SearchMode, SourceFile,
+    StartUploadBodyData,
+};
+use futures::{stream, Future, StreamExt};
+use serde::Serialize;
+use std::{io::Write, path::Path};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    command::{Cli, Command, DEFAULT_URL},
+    error::CliError,
+    source_files::list_source_files,
+};
+
+/// Files larger than this are uploaded in parts: a multipart request of the gateway is limited
+/// to 100 MiB by default (`uploads.max_form_bytes`), leaving room for the framing and the tags
+const MAX_MULTIPART_FILE_BYTES: usize = 64 * 1024 * 1024;
+/// Number of times a throttled request is retried, after the `Retry-After` of the gateway
+const MAX_THROTTLED_RETRIES: u32 = 10;
+/// Number of characters of the content of a search result printed as a column
+const CONTENT_EXCERPT_CHARS: usize = 120;
+
+/// Result of the upload of a file of the directory
+#[derive(Serialize)]
+struct UploadReport<'a> {
+    /// Path of the file, relative to the uploaded directory
+    path: &'a str,
+    #[serde(flatten)]
+    status: &'a AddSourceFileStatus,
+}
+
+/// Error of a job or of a source, printed in place of its result
+#[derive(Serialize)]
+struct ItemError<'a> {
+    id: Uuid,
+    error: &'a str,
+}
+
+/// Runs a command against the gateway, writing its results on `out`
+pub async fn run(cli: Cli, out: &mut impl Write) -> Result<(), CliError> {
+    let url = cli
+        .url
+        .or_else(|| std::env::var("CIS_URL").ok())
+        .unwrap_or_else(|| DEFAULT_URL.to_string());
+
+    if let Command::LogIn { email } = cli.command {
+        return log_in(&url, email, cli.json, out).await;
+    }
+
+    let client = CisClient::new(&url, credentials_from_env()?)?;
+    match cli.command {
+        Command::LogIn { .. } => unreachable!("The log-in does not need credentials"),
+        Command::Upload {
+            dir,
+            tags,
+            concurrency,
+            watch,
+        } => upload(&client, &dir, &tags, concurrency, watch, cli.json, out).await,
+        Command::Job { job_ids, watch } => {
+            let nb_jobs = job_ids.len();
+            let concurrency = if watch { nb_jobs } else { 1 };
+            let nb_failed = print_jobs(&client, job_ids, watch, concurrency, cli.json, out).await?;
+            check_failures(nb_failed, nb_jobs)
+        }
+        Command::Search {
+            query,
+            mode,
+            limit,
+            language,
+        } => search(&client, query, mode, limit, language, cli.json, out).await,
+        Command::Delete { source_ids } => delete(&client, source_ids, cli.json, out).await,
+    }
+}
+
+/// Credentials of the environment: an API key, or else an access token
+fn credentials_from_env() -> Result<Credentials, CliError> {
+    if let Ok(api_key) = std::env::var("CIS_API_KEY") {
+        return Ok(Credentials::api_key(api_key));
+    }
+    if let Ok(token) = std::env::var("CIS_TOKEN") {
+        return Ok(Credentials::bearer(token));
+    }
+    Err(CliError::MissingCredentials)
+}
+
+async fn log_in(
+    url: &str,
+    email: String,
+    json: bool,
+    out: &mut impl Write,
+) -> Result<(), CliError> {
+    let password = match std::env::var("CIS_PASSWORD") {
+        Ok(password) => password,
+        Err(_) => {
+            let mut password = String::new();
+            std::io::stdin().read_line(&mut password)?;
+            password.trim_end_matches(['\r', '\n']).to_string()
+        }
+    };
+
+    let body = LogInAccountBodyData {
+        email,
+        password,
+        totp_code: None,
+        recovery_code: None,
+    };
+    let (_, response) = CisClient::log_in(url, &body).await?;
+
+    if json {
+        writeln!(out, "{}", serde_json::to_string(&response)?)?;
+    } else {
+        writeln!(out, "{}", response.access_token)?;
+    }
+    Ok(())
+}
+
+/// Uploads the files of a directory, and with `watch` waits for the end of their ingestion jobs
+async fn upload(
+    client: &CisClient,
+    dir: &Path,
+    tags: &[String],
+    concurrency: usize,
+    watch: bool,
+    json: bool,
+    out: &mut impl Write,
+) -> Result<(), CliError> {
+    let paths = list_source_files(dir)?;
+    let nb_files = paths.len();
+    info!("Uploading the {} files of {}", nb_files, dir.display());
+
+    let mut uploads = stream::iter(paths)
+        .map(|path| async move {
+            let status = upload_file(client, &path, tags).await;
+            (path, status)
+        })
+        .buffer_unordered(concurrency);
+
+    let mut job_ids = vec![];
+    let mut nb_failed = 0;
+    while let Some((path, status)) = uploads.next().await {
+        match status.status {
+            AddSourceFileStatusCode::Success => job_ids.extend(status.job_id),
+            AddSourceFileStatusCode::Duplicate => {}
+            _ => nb_failed += 1,
+        }
+
+        let path = relative_path(dir, &path);
+        if json {
+            let report = UploadReport {
+                path: &path,
+                status: &status,
+            };
+            writeln!(out, "{}", serde_json::to_string(&report)?)?;
+        } else {
+            writeln!(
+                out,
+                "{}\t{}\t{}\t{}\t{}",
+                path,
+                snake_case(&status.status)?,
+                optional_column(status.source_id),
+                optional_column(status.job_id),
+                status.message.as_deref().unwrap_or_default()
+            )?;
+        }
+    }
+
+    if watch {
+        nb_failed += print_jobs(client, job_ids, true, concurrency, json, out).await?;
+    }
+    check_failures(nb_failed, nb_files)
+}
+
+/// Uploads a file, in parts if it is too large for a multipart request
+///
+/// An error is reported as the status of the file, so that the other files are still uploaded.
+async fn upload_file(client: &CisClient, path: &Path, tags: &[String]) -> AddSourceFileStatus {
+    let file_name = path
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    match try_upload_file(client, path, &file_name, tags).await {
+        Ok(status) => status,
+        Err(error) => error_status(file_name, error.to_string()),
+    }
+}
+
+fn error_status(file_name: String, message: String) -> AddSourceFileStatus {
+    AddSourceFileStatus {
+        file_name: Some(file_name),
+        status: AddSourceFileStatusCode::Error,
+        message: Some(message),
+        collection: None,
+        job_id: None,
+        source_id: None,
+    }
+}
+
+async fn try_upload_file(
+    client: &CisClient,
+    path: &Path,
+    file_name: &str,
+    tags: &[String],
+) -> Result<AddSourceFileStatus, CliError> {
+    let content = tokio::fs::read(path).await?;
+
+    if content.len() <= MAX_MULTIPART_FILE_BYTES {
+        let response = retry_throttled(|| {
+            client.add_source_files(vec![SourceFile::new(file_name, content.clone())], tags)
+        })
+        .await?;
+
+        return Ok(response.file_status.into_iter().next().unwrap_or_else(|| {
+            error_status(
+                file_name.to_string(),
+                "No status returned for the uploaded file".to_string(),
+            )
+        }));
+    }
+
+    let body = StartUploadBodyData {
+        file_name: file_name.to_string(),
+        content_type: None,
+        tags: tags.to_vec(),
+    };
+    let upload = retry_throttled(|| client.start_upload(&body)).await?;
+    // A throttled part is sent again with the parts not received by the gateway yet
+    let completed = retry_throttled(|| client.resume_upload(upload.id, &content)).await?;
+
+    Ok(AddSourceFileStatus {
+        file_name: Some(file_name.to_string()),
+        status: AddSourceFileStatusCode::Success,
+        message: None,
+        collection: completed.collection,
+        job_id: Some(completed.job_id),
+        source_id: Some(completed.source_id),
+    })
+}
+
+/// Prints the jobs, or with `watch` their progress until they end, `concurrency` jobs at a time
+///
+/// Returns the number of failed jobs, or of jobs whose request failed.
+async fn print_jobs(
+    client: &CisClient,
+    job_ids: Vec<Uuid>,
+    watch: bool,
+    concurrency: usize,
+    json: bool,
+    out: &mut impl Write,
+) -> Result<usize, CliError> {
+    let mut updates = stream::iter(job_ids)
+        .map(|job_id| {
+            if watch {
+                client
+                    .watch_job(job_id, Backoff::default())
+                    .map(move |result| (job_id, result))
+                    .boxed()
+            } else {
+                stream::once(
+                    async move { (job_id, retry_throttled(|| client.get_job(job_id)).await) },
+                )
+                .boxed()
+            }
+        })
+        .flatten_unordered(concurrency.max(1));
+
+    let mut nb_failed = 0;
+    while let Some((job_id, result)) = updates.next().await {
+        match result {
+            Ok(job) => {
+                if job.status == JobStatus::Failed {
+                    nb_failed += 1;
+                }
+                print_job(&job, json, out)?;
+            }
+            Err(error) => {
+                nb_failed += 1;
+                print_error(job_id, &error.to_string(), json, out)?;
+            }
+        }
+    }
+    Ok(nb_failed)
+}
+
+fn print_job(job: &GetJobResponse, json: bool, out: &mut impl Write) -> Result<(), CliError> {
+    if json {
+        writeln!(out, "{}", serde_json::to_string(job)?)?;
+    } else {
+        writeln!(
+            out,
+            "{}\t{}\t{}/{}\t{}",
+            job.id,
+            snake_case(&job.status)?,
+            job.nb_embedded_contents,
+            optional_column(job.nb_contents),
+            job.error.as_deref().unwrap_or_default()
+        )?;
+    }
+    Ok(())
+}
+
+async fn search(
+    client: &CisClient,
+    query: String,
+    mode: SearchMode,
+    limit: Option<usize>,
+    language: Option<String>,
+    json: bool,
+    out: &mut impl Write,
+) -> Result<(), CliError> {
+    let body = SearchContentBodyData {
+        query,
+        limit,
+        page: None,
+        hits_per_page: None,
+        sort: Default::default(),
+        mode,
+        language,
+        fields: Default::default(),
+        source_ids: vec![],
+        source_type: None,
+        uploaded_after: None,
+        uploaded_before: None,
+        chapter: None,
+        keyword: None,
+        entity: None,
+        rerank: false,
+    };
+    let response = retry_throttled(|| client.search(&body)).await?;
+
+    for result in response.results {
+        if json {
+            writeln!(out, "{}", serde_json::to_string(&result)?)?;
+        } else {
+            writeln!(
+                out,
+                "{:.4}\t{}\t{}",
+                result.score,
+                result.id,
+                content_excerpt(&result.content)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+async fn delete(
+    client: &CisClient,
+    source_ids: Vec<Uuid>,
+    json: bool,
+    out: &mut impl Write,
+) -> Result<(), CliError> {
+    let nb_sources = source_ids.len();
+    let mut nb_failed = 0;
+
+    for source_id in source_ids {
+        match retry_throttled(|| client.delete_source(source_id)).await {
+            Ok(()) if json => writeln!(out, "{}", serde_json::json!({ "id": source_id }))?,
+            Ok(()) => writeln!(out, "{}\tdeleted", source_id)?,
+            Err(error) => {
+                nb_failed += 1;
+                print_error(source_id, &error.to_string(), json, out)?;
+            }
+        }
+    }
+    check_failures(nb_failed, nb_sources)
+}
+
+fn print_error(id: Uuid, error: &str, json: bool, out: &mut impl Write) -> Result<(), CliError> {
+    if json {
+        writeln!(out, "{}", serde_json::to_string(&ItemError { id, error })?)?;
+    } else {
+        writeln!(out, "{}\terror\t{}", id, error)?;
+    }
+    Ok(())
+}
+
+/// Sends a request again while it is throttled, after the `Retry-After` of the gateway
+async fn retry_throttled<T, F, Fut>(mut request: F) -> Result<T, CisClientError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, CisClientError>>,
+{
+    let mut nb_retries = 0;
+    loop {
+        match request().await {
+            Err(CisClientError::Throttled { retry_after, .. })
+                if nb_retries < MAX_THROTTLED_RETRIES =>
+            {
+                nb_retries += 1;
+                tokio::time::sleep(retry_after).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn check_failures(nb_failed: usize, nb_items: usize) -> Result<(), CliError> {
+    if nb_failed > 0 {
+        return Err(CliError::PartialFailure(nb_failed, nb_items));
+    }
+    Ok(())
+}
+
+fn relative_path(dir: &Path, path: &Path) -> String {
+    path.strip_prefix(dir).unwrap_or(path).display().to_string()
+}
+
+/// Name of a unit enum variant, as serialized by the gateway
+fn snake_case(value: &impl Serialize) -> Result<String, CliError> {
+    Ok(serde_json::to_value(value)?
+        .as_str()
+        .unwrap_or_default()
+        .to_string())
+}
+
+fn optional_column(value: Option<impl ToString>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// First characters of a content, on a single line
+fn content_excerpt(content: &str) -> String {
+    let content = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    match content.char_indices().nth(CONTENT_EXCERPT_CHARS) {
+        Some((index, _)) => format!("{}…", &content[..index]),
+        None => content,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_excerpt_is_a_single_truncated_line() {
+        assert_eq!(
+            content_excerpt("The old man\nand the\tsea"),
+            "The old man and the sea"
+        );
+
+        let excerpt = content_excerpt(&"é".repeat(CONTENT_EXCERPT_CHARS + 1));
+        assert_eq!(excerpt.chars().count(), CONTENT_EXCERPT_CHARS + 1);
+        assert!(excerpt.ends_with('…'));
+    }
+
+    #[test]
+    fn statuses_are_printed_as_serialized_by_the_gateway() {
+        assert_eq!(
+            snake_case(&AddSourceFileStatusCode::DrmProtected).unwrap(),
+            "drm_protected"
+        );
+        assert_eq!(
+            snake_case(&JobStatus::CompletedWithWarnings).unwrap(),
+            "completed_with_warnings"
+        );
+    }
+}
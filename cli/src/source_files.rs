@@ -0,0 +1,69 @@
+use std::path::{Path, PathBuf};
+
+/// Lists the files of a directory and of its subdirectories, sorted by path
+///
+/// The hidden files and directories, whose name starts with a dot, are skipped,
+/// and the symbolic links are not followed.
+pub fn list_source_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut dirs = vec![dir.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                dirs.push(entry.path());
+            } else if file_type.is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn files_of_the_subdirectories_are_listed_without_the_hidden_ones() {
+        let dir = std::env::temp_dir().join(format!("cis-cli-{}", Uuid::new_v4()));
+        for path in [
+            "book.epub",
+            "novels/b.pdf",
+            "novels/a.txt",
+            "novels/classics/c.epub",
+            ".hidden",
+            ".git/config",
+        ] {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"content").unwrap();
+        }
+
+        let files = list_source_files(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let files: Vec<PathBuf> = files
+            .iter()
+            .map(|file| file.strip_prefix(&dir).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                PathBuf::from("book.epub"),
+                PathBuf::from("novels/a.txt"),
+                PathBuf::from("novels/b.pdf"),
+                PathBuf::from("novels/classics/c.epub"),
+            ]
+        );
+    }
+}
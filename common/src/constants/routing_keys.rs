@@ -1,3 +1,35 @@
 pub const EXTRACT_CONTENT_TEXT_ROUTING_KEY: &str = "extract_content.text.v1";
 pub const CONTENT_EXTRACTED_ROUTING_KEY: &str = "content_extracted.v1";
 pub const SEARCH_FULLTEXT_ROUTING_KEY: &str = "search_fulltext.v1";
+pub const CONTENT_EXTRACTION_PROGRESS_ROUTING_KEY: &str = "content_extraction.progress.v1";
+pub const CONSUMER_HANDOVER_ROUTING_KEY: &str = "consumer_handover.ready.v1";
+pub const DELETE_CONTENT_ROUTING_KEY: &str = "delete_content.v1";
+/// Deletion of the contents of a source no longer extracted from its new version, consumed by the indexing services
+pub const PRUNE_CONTENT_ROUTING_KEY: &str = "prune_content.v1";
+pub const INGESTION_JOB_STATUS_ROUTING_KEY: &str = "ingestion_job.status.v1";
+pub const SEARCH_SEMANTIC_ROUTING_KEY: &str = "search_semantic.v1";
+/// Usage of the model provider of a tenant, published by its workers and consumed by the gateway
+pub const PROVIDER_USAGE_ROUTING_KEY: &str = "provider_usage.v1";
+/// Fast lane of the small sources, consumed by dedicated consumers
+pub const EXTRACT_CONTENT_TEXT_FAST_ROUTING_KEY: &str = "extract_content.text.fast.v1";
+pub const CONTENT_EXTRACTED_FAST_ROUTING_KEY: &str = "content_extracted.fast.v1";
+/// Control-plane command promoting a standby full-text search service to serve the queries
+pub const PROMOTE_FULLTEXT_STANDBY_ROUTING_KEY: &str = "fulltext_search.promote_standby.v1";
+/// Command reindexing a source, consumed by the gateway
+pub const REINDEX_SOURCE_ROUTING_KEY: &str = "reindex_source.v1";
+/// Command importing an object of an external bucket as a source, consumed by the gateway
+pub const IMPORT_SOURCE_ROUTING_KEY: &str = "import_source.v1";
+/// RPC call getting the normalization rules of the tenant of the caller, answered by the gateway
+pub const GET_NORMALIZATION_RULES_ROUTING_KEY: &str = "normalization_rules.get.v1";
+/// Config-change message invalidating the normalization rules cached by the services of a tenant
+pub const NORMALIZATION_RULES_CHANGED_ROUTING_KEY: &str = "normalization_rules.changed.v1";
+/// RPC call getting the model provider credentials of the tenant of the caller, answered by the gateway
+pub const GET_PROVIDER_CREDENTIALS_ROUTING_KEY: &str = "provider_credentials.get.v1";
+/// Config-change message invalidating the provider credentials cached by the services of a tenant
+pub const PROVIDER_CREDENTIALS_CHANGED_ROUTING_KEY: &str = "provider_credentials.changed.v1";
+/// RPC call listing the contents extracted from a source, answered by the full-text search service
+pub const GET_SOURCE_CHUNKS_ROUTING_KEY: &str = "source_chunks.get.v1";
+/// Prefix of the routing keys of the activity of a user, `user_activity.{user_id}.v1`, published and consumed by the gateway
+pub const USER_ACTIVITY_ROUTING_KEY_PREFIX: &str = "user_activity";
+/// Summaries generated from the extracted contents of a source, published by the embedding worker and consumed by the gateway
+pub const SOURCE_SUMMARIZED_ROUTING_KEY: &str = "source_summarized.v1";
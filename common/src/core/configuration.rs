@@ -0,0 +1,469 @@
+use config::{builder::DefaultState, ConfigBuilder, ValueKind};
+use secrecy::ExposeSecret;
+use serde::de::DeserializeOwned;
+use std::{future::Future, path::PathBuf, sync::Arc};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    task::JoinHandle,
+};
+use tracing::{error, info};
+
+use crate::{
+    core::secret_provider::{
+        SecretProviderError, SecretProviderPort, SecretStoreSettings, VaultSecretProvider,
+        SECRET_REFERENCE_PREFIX,
+    },
+    helper::error_chain_fmt,
+};
+
+/// Command line flag overriding a setting, for ex: `--set rate_limits.search.burst=40`
+pub const OVERRIDE_FLAG: &str = "--set";
+
+/// Prefix of the environment variables of the settings
+const VARIABLE_PREFIX: &str = "APP_";
+/// Separator of the levels of the settings, in the names of the environment variables
+const VARIABLE_SEPARATOR: &str = "__";
+/// Suffix of the environment variables whose value is the path of a file containing the setting,
+/// for ex a Docker or Kubernetes secret: `APP_DATABASE__PASSWORD_FILE=/run/secrets/database_password`
+pub const FILE_VARIABLE_SUFFIX: &str = "_FILE";
+
+/// Section of the settings of the external secret store, whose settings can not be secret references
+const SECRET_STORE_KEY: &str = "secret_store";
+
+/// Layers of the configuration of a service, each one overriding the previous ones:
+/// 1. `base.yml`, the settings shared by all the environments
+/// 2. `<environment>.yml`, for ex `production.yml`
+/// 3. The environment variables, with a prefix of APP and '__' as separator. For ex: `APP_APPLICATION__PORT=5001`.
+///    With a `_FILE` suffix, the setting is read from the file at the given path.
+/// 4. The command line flags, for ex: `--set application.port=5001`
+///
+/// The settings whose value is a secret reference (`secret:<path>#<key>`) are then read from the external secret store.
+#[derive(Clone)]
+pub struct ConfigurationLayers {
+    directory: PathBuf,
+    environment: String,
+    /// Keys (with '.' as separator) and values of the command line overrides
+    overrides: Vec<(String, String)>,
+    /// If not set, the secret store of the `secret_store` settings
+    secret_provider: Option<Arc<dyn SecretProviderPort>>,
+}
+
+impl ConfigurationLayers {
+    pub fn new(directory: PathBuf, environment: &str) -> Self {
+        Self {
+            directory,
+            environment: environment.to_string(),
+            overrides: vec![],
+            secret_provider: None,
+        }
+    }
+
+    /// Reads the secret references from the given secret store, instead of the one of the `secret_store` settings
+    pub fn with_secret_provider(mut self, secret_provider: Arc<dyn SecretProviderPort>) -> Self {
+        self.secret_provider = Some(secret_provider);
+        self
+    }
+
+    /// Adds the `--set key=value` (or `--set=key=value`) overrides of the command line arguments
+    ///
+    /// The other arguments are left to the binary.
+    pub fn with_cli_overrides(
+        mut self,
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Self, ConfigurationError> {
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let assignment = if arg == OVERRIDE_FLAG {
+                args.next().ok_or_else(|| {
+                    ConfigurationError::InvalidOverride(format!("{} without a value", arg))
+                })?
+            } else if let Some(assignment) = arg.strip_prefix(&format!("{}=", OVERRIDE_FLAG)) {
+                assignment.to_string()
+            } else {
+                continue;
+            };
+
+            let (key, value) = assignment
+                .split_once('=')
+                .filter(|(key, _)| !key.is_empty())
+                .ok_or_else(|| ConfigurationError::InvalidOverride(assignment.clone()))?;
+            self.overrides.push((key.to_string(), value.to_string()));
+        }
+
+        Ok(self)
+    }
+
+    /// Reads and deserializes the settings from all the layers, and from the external secret store
+    pub async fn load<T: DeserializeOwned>(&self) -> Result<T, ConfigurationError> {
+        let mut overrides = file_variable_overrides(std::env::vars())?;
+        overrides.extend(self.overrides.iter().cloned());
+        let configuration = self.builder(&overrides)?.build()?;
+
+        let references = secret_references(&configuration.cache.kind);
+        if references.is_empty() {
+            return Ok(configuration.try_deserialize::<T>()?);
+        }
+
+        let secret_provider = match &self.secret_provider {
+            Some(secret_provider) => secret_provider.clone(),
+            None => {
+                let settings = configuration
+                    .get::<SecretStoreSettings>(SECRET_STORE_KEY)
+                    .map_err(|_| ConfigurationError::NoSecretStore(references[0].0.clone()))?;
+                Arc::new(
+                    VaultSecretProvider::try_new(settings.vault).map_err(|error| {
+                        ConfigurationError::SecretStoreError {
+                            key: SECRET_STORE_KEY.to_string(),
+                            source: error,
+                        }
+                    })?,
+                )
+            }
+        };
+
+        for (key, reference) in references {
+            let secret = secret_provider
+                .get_secret(&reference)
+                .await
+                .map_err(|error| ConfigurationError::SecretStoreError {
+                    key: key.clone(),
+                    source: error,
+                })?;
+            overrides.push((key, secret.expose_secret().clone()));
+        }
+
+        Ok(self.builder(&overrides)?.build()?.try_deserialize::<T>()?)
+    }
+
+    fn builder(
+        &self,
+        overrides: &[(String, String)],
+    ) -> Result<ConfigBuilder<DefaultState>, ConfigurationError> {
+        let mut builder = config::Config::builder()
+            .add_source(config::File::from(self.directory.join("base.yml")))
+            .add_source(config::File::from(
+                self.directory.join(format!("{}.yml", self.environment)),
+            ))
+            .add_source(
+                config::Environment::with_prefix("APP")
+                    .prefix_separator("_")
+                    .separator(VARIABLE_SEPARATOR),
+            );
+
+        for (key, value) in overrides.iter() {
+            builder = builder.set_override(key.as_str(), value.as_str())?;
+        }
+
+        Ok(builder)
+    }
+}
+
+/// Settings read from the files of the `APP_..._FILE` environment variables
+fn file_variable_overrides(
+    variables: impl Iterator<Item = (String, String)>,
+) -> Result<Vec<(String, String)>, ConfigurationError> {
+    variables
+        .filter_map(|(name, path)| {
+            let key = name
+                .strip_prefix(VARIABLE_PREFIX)?
+                .strip_suffix(FILE_VARIABLE_SUFFIX)?
+                .to_lowercase()
+                .replace(VARIABLE_SEPARATOR, ".");
+            Some((name, key, path))
+        })
+        .map(|(name, key, path)| {
+            let value = std::fs::read_to_string(&path).map_err(|error| {
+                ConfigurationError::FileVariableError {
+                    name,
+                    source: error,
+                }
+            })?;
+            // The files of the secrets often end with a new line
+            Ok((key, value.trim_end_matches(['\r', '\n']).to_string()))
+        })
+        .collect()
+}
+
+/// Keys and references of the settings whose value is a secret reference, out of the secret store settings
+fn secret_references(value: &ValueKind) -> Vec<(String, String)> {
+    fn collect(key: &str, value: &ValueKind, references: &mut Vec<(String, String)>) {
+        match value {
+            ValueKind::String(value) => {
+                if let Some(reference) = value.strip_prefix(SECRET_REFERENCE_PREFIX) {
+                    references.push((key.to_string(), reference.to_string()));
+                }
+            }
+            ValueKind::Table(table) => {
+                for (name, value) in table.iter() {
+                    let key = if key.is_empty() {
+                        name.clone()
+                    } else {
+                        format!("{}.{}", key, name)
+                    };
+                    if key != SECRET_STORE_KEY {
+                        collect(&key, &value.kind, references);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut references = vec![];
+    collect("", value, &mut references);
+    references.sort();
+    references
+}
+
+/// Reloads the configuration of a service on each SIGHUP, without restarting it
+///
+/// The reloaded settings are given to `on_reload`, applying the tunables that can change while running.
+/// An invalid configuration is logged and ignored: the current settings are kept.
+pub fn reload_on_sighup<T, L, F, R>(load: L, on_reload: R) -> Result<JoinHandle<()>, std::io::Error>
+where
+    T: Send + 'static,
+    L: Fn() -> F + Send + 'static,
+    F: Future<Output = Result<T, ConfigurationError>> + Send,
+    R: Fn(T) + Send + 'static,
+{
+    let mut hangups = signal(SignalKind::hangup())?;
+
+    Ok(tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            match load().await {
+                Ok(settings) => {
+                    on_reload(settings);
+                    info!("Reloaded the configuration");
+                }
+                Err(error) => {
+                    error!(
+                        ?error,
+                        "Failed to reload the configuration, keeping the current one"
+                    );
+                }
+            }
+        }
+    }))
+}
+
+#[derive(thiserror::Error)]
+pub enum ConfigurationError {
+    #[error("Invalid environment: {0}")]
+    InvalidEnvironment(String),
+    #[error("Invalid configuration override `{0}`, expected `{OVERRIDE_FLAG} key=value`")]
+    InvalidOverride(String),
+    #[error("Failed to read the configuration: {0}")]
+    ReadError(#[from] config::ConfigError),
+    #[error("Invalid setting `{key}`: {reason}")]
+    InvalidSetting { key: String, reason: String },
+    #[error("Failed to read the file of the environment variable {name}")]
+    FileVariableError {
+        name: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("The setting `{0}` is a secret reference, but no secret store is configured")]
+    NoSecretStore(String),
+    #[error("Failed to read the secret of the setting `{key}`")]
+    SecretStoreError {
+        key: String,
+        #[source]
+        source: SecretProviderError,
+    },
+}
+
+impl ConfigurationError {
+    pub fn invalid_setting(key: &str, reason: &str) -> Self {
+        Self::InvalidSetting {
+            key: key.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Debug for ConfigurationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Checks that a setting is strictly positive, for ex a rate or a prefetch count
+pub fn ensure_positive<N>(key: &str, value: N) -> Result<(), ConfigurationError>
+where
+    N: PartialOrd + Default,
+{
+    if value > N::default() {
+        Ok(())
+    } else {
+        Err(ConfigurationError::invalid_setting(
+            key,
+            "should be greater than 0",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+    use serde::Deserialize;
+    use std::fs;
+
+    #[derive(Debug, Deserialize)]
+    struct TestSettings {
+        port: u16,
+        host: String,
+        prefetch_count: u16,
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn configuration_directory() -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("configuration-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(
+            directory.join("base.yml"),
+            "port: 4242\nhost: localhost\nprefetch_count: 10\n",
+        )
+        .unwrap();
+        fs::write(directory.join("production.yml"), "host: 0.0.0.0\n").unwrap();
+        directory
+    }
+
+    struct FakeSecretProvider;
+
+    #[async_trait::async_trait]
+    impl SecretProviderPort for FakeSecretProvider {
+        async fn get_secret(&self, reference: &str) -> Result<Secret<String>, SecretProviderError> {
+            match reference {
+                "services/worker#host" => Ok(Secret::new("secret-host".to_string())),
+                _ => Err(SecretProviderError::SecretNotFound(reference.to_string())),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn each_layer_overrides_the_previous_ones() {
+        let directory = configuration_directory();
+
+        let settings: TestSettings = ConfigurationLayers::new(directory.clone(), "production")
+            .with_cli_overrides(args(&["worker", "--set", "port=5001", "--verbose"]))
+            .unwrap()
+            .load()
+            .await
+            .unwrap();
+
+        assert_eq!(settings.port, 5001);
+        assert_eq!(settings.host, "0.0.0.0");
+        assert_eq!(settings.prefetch_count, 10);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn cli_overrides_should_be_assignments() {
+        let overrides = ConfigurationLayers::new(PathBuf::new(), "develop")
+            .with_cli_overrides(args(&[
+                "--set=rate_limits.search.burst=40",
+                "--set",
+                "a.b=x=y",
+            ]))
+            .unwrap()
+            .overrides;
+        assert_eq!(
+            overrides,
+            vec![
+                ("rate_limits.search.burst".to_string(), "40".to_string()),
+                ("a.b".to_string(), "x=y".to_string())
+            ]
+        );
+
+        assert!(matches!(
+            ConfigurationLayers::new(PathBuf::new(), "develop")
+                .with_cli_overrides(args(&["--set", "port"])),
+            Err(ConfigurationError::InvalidOverride(_))
+        ));
+        assert!(matches!(
+            ConfigurationLayers::new(PathBuf::new(), "develop")
+                .with_cli_overrides(args(&["--set"])),
+            Err(ConfigurationError::InvalidOverride(_))
+        ));
+    }
+
+    #[test]
+    fn file_variables_should_override_the_settings_with_the_file_content() {
+        let directory = configuration_directory();
+        let path = directory.join("database_password");
+        fs::write(&path, "password\n").unwrap();
+
+        let overrides = file_variable_overrides(
+            vec![
+                (
+                    "APP_DATABASE__PASSWORD_FILE".to_string(),
+                    path.to_string_lossy().to_string(),
+                ),
+                ("APP_DATABASE__PORT".to_string(), "5432".to_string()),
+                ("HOME_FILE".to_string(), "/root".to_string()),
+            ]
+            .into_iter(),
+        )
+        .unwrap();
+        assert_eq!(
+            overrides,
+            vec![("database.password".to_string(), "password".to_string())]
+        );
+
+        assert!(matches!(
+            file_variable_overrides(
+                vec![(
+                    "APP_DATABASE__PASSWORD_FILE".to_string(),
+                    directory.join("missing").to_string_lossy().to_string(),
+                )]
+                .into_iter()
+            ),
+            Err(ConfigurationError::FileVariableError { .. })
+        ));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn secret_references_should_be_read_from_the_secret_store() {
+        let directory = configuration_directory();
+        fs::write(
+            directory.join("production.yml"),
+            "host: secret:services/worker#host\n",
+        )
+        .unwrap();
+        let layers = ConfigurationLayers::new(directory.clone(), "production");
+
+        let settings: TestSettings = layers
+            .clone()
+            .with_secret_provider(Arc::new(FakeSecretProvider))
+            .load()
+            .await
+            .unwrap();
+        assert_eq!(settings.host, "secret-host");
+
+        // Without the settings of a secret store
+        assert!(matches!(
+            layers.load::<TestSettings>().await,
+            Err(ConfigurationError::NoSecretStore(key)) if key == "host"
+        ));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn settings_should_be_strictly_positive() {
+        assert!(ensure_positive("rabbitmq.prefetch_count", 1u16).is_ok());
+        assert!(matches!(
+            ensure_positive("rabbitmq.prefetch_count", 0u16),
+            Err(ConfigurationError::InvalidSetting { key, .. }) if key == "rabbitmq.prefetch_count"
+        ));
+    }
+}
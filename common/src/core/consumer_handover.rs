@@ -0,0 +1,300 @@
+use futures::StreamExt;
+use lapin::{
+    options::{
+        BasicConsumeOptions, BasicPublishOptions, ExchangeDeclareOptions, QueueBindOptions,
+        QueueDeclareOptions, QueueDeleteOptions,
+    },
+    protocol::{AMQPErrorKind, AMQPSoftError},
+    types::FieldTable,
+    BasicProperties, Channel, Connection, ExchangeKind,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::time::sleep;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{constants::routing_keys::CONSUMER_HANDOVER_ROUTING_KEY, helper::error_chain_fmt};
+
+/// Settings of the handover of the consumption between instances of a service
+#[derive(Debug, Deserialize, Clone)]
+pub struct HandoverSettings {
+    /// If false, instances consume without acquiring the lease, and never hand over the consumption
+    pub enabled: bool,
+    /// Instances with the same lease name hand over the consumption to each other
+    pub lease_name: String,
+    /// Interval at which a starting instance signals its readiness and tries to acquire the lease
+    pub lease_retry_interval_ms: u64,
+}
+
+/// Message published by a starting instance, ready to consume
+#[derive(Debug, Serialize, Deserialize)]
+struct ReadyMessage {
+    lease_name: String,
+    instance_id: Uuid,
+}
+
+impl ReadyMessage {
+    /// A ready instance takes over the consumption of another instance sharing the same lease
+    fn is_successor_of(&self, lease_name: &str, instance_id: Uuid) -> bool {
+        self.lease_name == lease_name && self.instance_id != instance_id
+    }
+}
+
+/// Handover of the consumption of messages between an old and a newly started instance of a service
+///
+/// Only the instance holding the lease consumes messages. The lease is an exclusive RabbitMQ queue:
+/// it is owned by a single connection, and released when this connection is lost.
+///
+/// Protocol, for a rolling deploy:
+/// 1. The new instance signals its readiness on the exchange, and waits for the lease
+/// 2. The old instance receives the readiness signal and stops consuming
+/// 3. The old instance finishes its in-flight messages, releases the lease and exits
+/// 4. The new instance acquires the lease and starts consuming
+pub struct ConsumerHandover {
+    settings: HandoverSettings,
+    instance_id: Uuid,
+    exchange_name: String,
+    lease_channel: Option<Channel>,
+    stop_consuming: CancellationToken,
+}
+
+impl ConsumerHandover {
+    pub fn new(settings: HandoverSettings, exchange_name: &str) -> Self {
+        Self {
+            settings,
+            instance_id: Uuid::new_v4(),
+            exchange_name: exchange_name.to_string(),
+            lease_channel: None,
+            stop_consuming: CancellationToken::new(),
+        }
+    }
+
+    /// Token cancelled when the consumption should stop: a new instance is ready to take over
+    pub fn stop_consuming_token(&self) -> CancellationToken {
+        self.stop_consuming.clone()
+    }
+
+    /// Waits until this instance holds the lease, signaling its readiness to the current holder
+    ///
+    /// Then listens to the readiness of new instances, to hand over the consumption.
+    /// The connection should be kept open as long as this instance consumes messages.
+    #[tracing::instrument(name = "Acquiring the consumer lease", skip(self, connection), fields(lease_name = %self.settings.lease_name, instance_id = %self.instance_id))]
+    pub async fn acquire(&mut self, connection: &Connection) -> Result<(), ConsumerHandoverError> {
+        if !self.settings.enabled {
+            return Ok(());
+        }
+
+        let channel = connection.create_channel().await?;
+        declare_exchange(&channel, &self.exchange_name).await?;
+        let ready_message = serde_json::to_vec(&ReadyMessage {
+            lease_name: self.settings.lease_name.clone(),
+            instance_id: self.instance_id,
+        })?;
+
+        loop {
+            let lease_channel = connection.create_channel().await?;
+            match lease_channel
+                .queue_declare(
+                    &self.lease_queue_name(),
+                    QueueDeclareOptions {
+                        exclusive: true,
+                        ..QueueDeclareOptions::default()
+                    },
+                    FieldTable::default(),
+                )
+                .await
+            {
+                Ok(_) => {
+                    info!("🔑 Acquired the consumer lease");
+                    self.lease_channel = Some(lease_channel);
+                    break;
+                }
+                // Declaring an exclusive queue owned by another connection closes the channel
+                Err(lapin::Error::ProtocolError(error))
+                    if matches!(
+                        error.kind(),
+                        AMQPErrorKind::Soft(AMQPSoftError::RESOURCELOCKED)
+                    ) =>
+                {
+                    info!("Consumer lease held by another instance, signaling readiness");
+                    channel
+                        .basic_publish(
+                            &self.exchange_name,
+                            CONSUMER_HANDOVER_ROUTING_KEY,
+                            BasicPublishOptions::default(),
+                            &ready_message,
+                            BasicProperties::default(),
+                        )
+                        .await?;
+                }
+                Err(error) => return Err(error.into()),
+            }
+
+            sleep(Duration::from_millis(self.settings.lease_retry_interval_ms)).await;
+        }
+
+        self.watch_for_successor(connection).await
+    }
+
+    /// Releases the lease, for the next instance to acquire it
+    pub async fn release(&mut self) {
+        let Some(lease_channel) = self.lease_channel.take() else {
+            return;
+        };
+
+        if let Err(error) = lease_channel
+            .queue_delete(&self.lease_queue_name(), QueueDeleteOptions::default())
+            .await
+        {
+            error!(?error, "Failed to release the consumer lease");
+            return;
+        }
+
+        info!("Released the consumer lease");
+    }
+
+    /// Listens to the readiness of new instances: the consumption stops when one is ready
+    async fn watch_for_successor(
+        &self,
+        connection: &Connection,
+    ) -> Result<(), ConsumerHandoverError> {
+        let channel = connection.create_channel().await?;
+
+        // When supplying an empty string queue name, RabbitMQ generates a name for us
+        let queue = channel
+            .queue_declare(
+                "",
+                QueueDeclareOptions {
+                    exclusive: true,
+                    ..QueueDeclareOptions::default()
+                },
+                FieldTable::default(),
+            )
+            .await?;
+        channel
+            .queue_bind(
+                queue.name().as_str(),
+                &self.exchange_name,
+                CONSUMER_HANDOVER_ROUTING_KEY,
+                QueueBindOptions::default(),
+                FieldTable::default(),
+            )
+            .await?;
+
+        let mut consumer = channel
+            .basic_consume(
+                queue.name().as_str(),
+                "",
+                BasicConsumeOptions {
+                    no_ack: true,
+                    ..BasicConsumeOptions::default()
+                },
+                FieldTable::default(),
+            )
+            .await?;
+
+        let lease_name = self.settings.lease_name.clone();
+        let instance_id = self.instance_id;
+        let stop_consuming = self.stop_consuming.clone();
+
+        tokio::spawn(async move {
+            // Keeps the channel open while watching
+            let _channel = channel;
+
+            while let Some(delivery) = consumer.next().await {
+                let delivery = match delivery {
+                    Ok(delivery) => delivery,
+                    Err(error) => {
+                        error!(?error, "Failed to consume consumer handover message");
+                        continue;
+                    }
+                };
+
+                let ready_message = match serde_json::from_slice::<ReadyMessage>(&delivery.data) {
+                    Ok(ready_message) => ready_message,
+                    Err(error) => {
+                        warn!(?error, "Invalid consumer handover message");
+                        continue;
+                    }
+                };
+
+                if ready_message.is_successor_of(&lease_name, instance_id) {
+                    info!(
+                        "🤝 Instance {} is ready to take over: stopping the consumption",
+                        ready_message.instance_id
+                    );
+                    stop_consuming.cancel();
+                    break;
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn lease_queue_name(&self) -> String {
+        format!("{}_lease_{}", self.exchange_name, self.settings.lease_name)
+    }
+}
+
+async fn declare_exchange(channel: &Channel, exchange_name: &str) -> Result<(), lapin::Error> {
+    channel
+        .exchange_declare(
+            exchange_name,
+            ExchangeKind::Topic,
+            ExchangeDeclareOptions {
+                durable: true,
+                ..ExchangeDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await
+}
+
+#[derive(thiserror::Error)]
+pub enum ConsumerHandoverError {
+    #[error(transparent)]
+    RabbitMQError(#[from] lapin::Error),
+    #[error("Error while serializing message data: {0}")]
+    JsonError(#[from] serde_json::Error),
+}
+
+impl std::fmt::Debug for ConsumerHandoverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ready_message_from_another_instance_with_same_lease_is_successor() {
+        let instance_id = Uuid::new_v4();
+        let ready_message = ReadyMessage {
+            lease_name: "content_ingestion_worker".to_string(),
+            instance_id: Uuid::new_v4(),
+        };
+
+        assert!(ready_message.is_successor_of("content_ingestion_worker", instance_id));
+    }
+
+    #[test]
+    fn ready_message_from_same_instance_or_other_lease_is_not_successor() {
+        let instance_id = Uuid::new_v4();
+        let own_message = ReadyMessage {
+            lease_name: "content_ingestion_worker".to_string(),
+            instance_id,
+        };
+        let other_lease_message = ReadyMessage {
+            lease_name: "embedding_worker".to_string(),
+            instance_id: Uuid::new_v4(),
+        };
+
+        assert!(!own_message.is_successor_of("content_ingestion_worker", instance_id));
+        assert!(!other_lease_message.is_successor_of("content_ingestion_worker", instance_id));
+    }
+}
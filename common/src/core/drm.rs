@@ -0,0 +1,47 @@
+//! Detection of the DRM protecting a source, whose content is encrypted and would be extracted as garbage
+
+/// Path of the rights of an EPUB protected by Adobe ADEPT
+pub const EPUB_RIGHTS_PATH: &str = "META-INF/rights.xml";
+/// Path of the list of the encrypted resources of an EPUB
+pub const EPUB_ENCRYPTION_PATH: &str = "META-INF/encryption.xml";
+
+/// Algorithms of the obfuscation of the embedded fonts, listed in `encryption.xml` without protecting the content
+const FONT_OBFUSCATION_ALGORITHMS: [&str; 2] = [
+    "http://www.idpf.org/2008/embedding",
+    "http://ns.adobe.com/pdf/enc#RC",
+];
+
+/// Checks if an EPUB is protected by a DRM, from its `META-INF/rights.xml` and `META-INF/encryption.xml` files
+///
+/// The obfuscated fonts are also listed in `encryption.xml`, without the EPUB being protected.
+pub fn is_epub_drm_protected(has_rights: bool, encryption: Option<&str>) -> bool {
+    if has_rights {
+        return true;
+    }
+
+    encryption
+        .map(|encryption| {
+            encryption
+                .split("Algorithm=\"")
+                .skip(1)
+                .filter_map(|algorithm| algorithm.split('"').next())
+                .any(|algorithm| !FONT_OBFUSCATION_ALGORITHMS.contains(&algorithm))
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypted_content_is_detected_as_drm_protected() {
+        let font_obfuscation = r#"<encryption><EncryptedData><EncryptionMethod Algorithm="http://www.idpf.org/2008/embedding"/></EncryptedData></encryption>"#;
+        let encrypted_content = r#"<encryption><EncryptedData><EncryptionMethod Algorithm="http://www.w3.org/2001/04/xmlenc#aes128-cbc"/></EncryptedData></encryption>"#;
+
+        assert!(!is_epub_drm_protected(false, None));
+        assert!(!is_epub_drm_protected(false, Some(font_obfuscation)));
+        assert!(is_epub_drm_protected(false, Some(encrypted_content)));
+        assert!(is_epub_drm_protected(true, None));
+    }
+}
@@ -0,0 +1,313 @@
+use lapin::{
+    message::Delivery,
+    options::{BasicAckOptions, BasicPublishOptions, QueueDeclareOptions},
+    types::{AMQPValue, FieldTable, ShortString},
+    Channel,
+};
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    hash::Hash,
+};
+use uuid::Uuid;
+
+/// Settings of the fair share of the in-flight messages of a worker between their owners (the tenants of the jobs)
+#[derive(Debug, Clone, Deserialize)]
+pub struct FairShareSettings {
+    /// If false, the messages are handled in their delivery order, and never deferred
+    pub enabled: bool,
+    /// Maximum ratio of the in-flight messages held by a single owner, even without other owners.
+    /// The other in-flight messages let the messages of the other owners be delivered.
+    pub max_share: f64,
+    /// Delay before a deferred message is delivered again
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub defer_delay_ms: u64,
+}
+
+impl Default for FairShareSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_share: 0.5,
+            defer_delay_ms: 30_000,
+        }
+    }
+}
+
+/// Owner of a job, sharing the in-flight messages of a worker with the other owners
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FairShareOwner {
+    Tenant(String),
+    /// A user without tenant has a share of their own
+    User(Uuid),
+}
+
+impl FairShareOwner {
+    /// Owner of a job: its tenant, or else its user. `None` if the job has neither.
+    pub fn of_job(tenant_id: Option<String>, user_id: Option<Uuid>) -> Option<Self> {
+        tenant_id
+            .map(FairShareOwner::Tenant)
+            .or_else(|| user_id.map(FairShareOwner::User))
+    }
+}
+
+/// In-flight messages of a worker, shared fairly between their owners
+///
+/// The messages delivered to a worker are held until they are handled, up to the prefetch count of its channel.
+/// An owner holding its share of the in-flight messages has its next messages deferred: the messages of
+/// the other owners queued behind them are delivered, instead of waiting for a large import to be handled.
+/// The held messages are handled in turn between their owners, rather than in their delivery order.
+pub struct FairShare<K, T> {
+    /// `None` without fair share: the messages are never deferred
+    max_share: Option<f64>,
+    /// Held messages of each owner, in their delivery order
+    held: HashMap<K, VecDeque<T>>,
+    /// Number of messages being handled of each owner
+    handling: HashMap<K, usize>,
+    /// Owners with held messages, in the order they take turns
+    turns: VecDeque<K>,
+}
+
+impl<K: Eq + Hash + Clone, T> FairShare<K, T> {
+    pub fn new(settings: &FairShareSettings) -> Self {
+        Self {
+            max_share: settings.enabled.then_some(settings.max_share),
+            held: HashMap::new(),
+            handling: HashMap::new(),
+            turns: VecDeque::new(),
+        }
+    }
+
+    /// Number of in-flight messages of an owner: held or being handled
+    pub fn in_flight(&self, owner: &K) -> usize {
+        self.held.get(owner).map_or(0, VecDeque::len)
+            + self.handling.get(owner).copied().unwrap_or(0)
+    }
+
+    /// Maximum number of in-flight messages of an owner, out of the in-flight messages of the worker
+    ///
+    /// The capacity is shared equally between the owners with in-flight messages, up to the maximum share.
+    /// An owner can always hold at least one message.
+    pub fn share(&self, owner: &K, capacity: usize) -> usize {
+        let nb_owners = self
+            .held
+            .keys()
+            .chain(self.handling.keys())
+            .chain(std::iter::once(owner))
+            .collect::<HashSet<&K>>()
+            .len();
+
+        let equal_share = capacity.div_ceil(nb_owners);
+        let max_share = (capacity as f64 * self.max_share.unwrap_or(1.0)).floor() as usize;
+
+        equal_share.min(max_share).max(1)
+    }
+
+    /// Holds a delivered message until it is handled
+    ///
+    /// # Returns
+    /// The message back if its owner already holds its share: it should be deferred
+    pub fn hold(&mut self, owner: K, message: T, capacity: usize) -> Result<(), T> {
+        if self.max_share.is_some() && self.in_flight(&owner) >= self.share(&owner, capacity) {
+            return Err(message);
+        }
+
+        let held = self.held.entry(owner.clone()).or_default();
+        if held.is_empty() {
+            self.turns.push_back(owner);
+        }
+        held.push_back(message);
+
+        Ok(())
+    }
+
+    /// Next message to handle, of the owner whose turn it is. `None` if no message is held.
+    ///
+    /// The message is in-flight until it is released.
+    pub fn next_to_handle(&mut self) -> Option<(K, T)> {
+        let owner = self.turns.pop_front()?;
+        let held = self.held.get_mut(&owner)?;
+        let message = held.pop_front()?;
+
+        if held.is_empty() {
+            self.held.remove(&owner);
+        } else {
+            self.turns.push_back(owner.clone());
+        }
+        *self.handling.entry(owner.clone()).or_default() += 1;
+
+        Some((owner, message))
+    }
+
+    /// Checks if no message is held, the messages being handled aside
+    pub fn is_empty(&self) -> bool {
+        self.held.is_empty()
+    }
+
+    /// Releases a handled message of an owner
+    pub fn release(&mut self, owner: &K) {
+        if let Some(handling) = self.handling.get_mut(owner) {
+            *handling = handling.saturating_sub(1);
+            if *handling == 0 {
+                self.handling.remove(owner);
+            }
+        }
+    }
+}
+
+/// Name of the queue holding the deferred messages of a queue until they are delivered again
+pub fn deferred_queue_name(queue_name: &str) -> String {
+    format!("{}_deferred", queue_name)
+}
+
+/// Declares the queue of the deferred messages of a queue
+///
+/// An expired deferred message is dead-lettered to the exchange with the routing key of the queue:
+/// it is delivered again, at the end of the queue.
+pub async fn declare_deferred_queue(
+    channel: &Channel,
+    queue_name: &str,
+    exchange_name: &str,
+    routing_key: &str,
+) -> Result<(), lapin::Error> {
+    let mut arguments = FieldTable::default();
+    arguments.insert(
+        ShortString::from("x-dead-letter-exchange"),
+        AMQPValue::LongString(exchange_name.into()),
+    );
+    arguments.insert(
+        ShortString::from("x-dead-letter-routing-key"),
+        AMQPValue::LongString(routing_key.into()),
+    );
+
+    channel
+        .queue_declare(
+            &deferred_queue_name(queue_name),
+            QueueDeclareOptions::default(),
+            arguments,
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Defers a delivered message: it is moved to the deferred queue of its queue, where it expires after the given delay
+///
+/// The message keeps its properties and headers (signature, trace context...).
+pub async fn defer_message(
+    channel: &Channel,
+    queue_name: &str,
+    delivery: &Delivery,
+    defer_delay_ms: u64,
+) -> Result<(), lapin::Error> {
+    // The expiration is removed by the broker when the message is dead-lettered
+    let properties = delivery
+        .properties
+        .clone()
+        .with_expiration(ShortString::from(defer_delay_ms.to_string()));
+
+    channel
+        .basic_publish(
+            "",
+            &deferred_queue_name(queue_name),
+            BasicPublishOptions::default(),
+            &delivery.data,
+            properties,
+        )
+        .await?
+        .await?;
+
+    delivery.ack(BasicAckOptions::default()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fair_share(max_share: f64) -> FairShare<&'static str, u32> {
+        FairShare::new(&FairShareSettings {
+            enabled: true,
+            max_share,
+            defer_delay_ms: 30_000,
+        })
+    }
+
+    #[test]
+    fn owner_alone_holds_up_to_the_maximum_share() {
+        let mut fair_share = fair_share(0.5);
+
+        for message in 0..5 {
+            assert!(fair_share.hold("import", message, 10).is_ok());
+        }
+        assert_eq!(fair_share.hold("import", 5, 10), Err(5));
+
+        // A handled message is still in-flight until released
+        assert_eq!(fair_share.next_to_handle(), Some(("import", 0)));
+        assert_eq!(fair_share.hold("import", 5, 10), Err(5));
+        fair_share.release(&"import");
+        assert!(fair_share.hold("import", 5, 10).is_ok());
+    }
+
+    #[test]
+    fn capacity_is_shared_equally_between_the_owners() {
+        let mut fair_share = fair_share(1.0);
+
+        for message in 0..6 {
+            assert!(fair_share.hold("import", message, 10).is_ok());
+        }
+        assert_eq!(fair_share.share(&"other", 10), 5);
+        assert!(fair_share.hold("other", 100, 10).is_ok());
+        // The owner holding more than its share gets its next messages deferred
+        assert_eq!(fair_share.hold("import", 6, 10), Err(6));
+    }
+
+    #[test]
+    fn owners_take_turns() {
+        let mut fair_share = fair_share(1.0);
+
+        for message in 0..3 {
+            fair_share.hold("import", message, 10).unwrap();
+        }
+        fair_share.hold("other", 100, 10).unwrap();
+
+        let handled: Vec<_> = std::iter::from_fn(|| fair_share.next_to_handle()).collect();
+        assert_eq!(
+            handled,
+            vec![("import", 0), ("other", 100), ("import", 1), ("import", 2)]
+        );
+    }
+
+    #[test]
+    fn owner_can_always_hold_one_message() {
+        let mut fair_share = fair_share(0.5);
+
+        assert!(fair_share.hold("import", 0, 1).is_ok());
+        assert_eq!(fair_share.hold("import", 1, 1), Err(1));
+    }
+
+    #[test]
+    fn jobs_are_owned_by_their_tenant_or_else_their_user() {
+        let user_id = Uuid::new_v4();
+
+        assert_eq!(
+            FairShareOwner::of_job(Some("acme".to_string()), Some(user_id)),
+            Some(FairShareOwner::Tenant("acme".to_string()))
+        );
+        assert_eq!(
+            FairShareOwner::of_job(None, Some(user_id)),
+            Some(FairShareOwner::User(user_id))
+        );
+        assert_eq!(FairShareOwner::of_job(None, None), None);
+    }
+
+    #[test]
+    fn messages_are_not_deferred_without_fair_share() {
+        let mut fair_share = FairShare::new(&FairShareSettings::default());
+
+        for message in 0..20 {
+            assert!(fair_share.hold(None::<&str>, message, 10).is_ok());
+        }
+        assert_eq!(fair_share.next_to_handle(), Some((None, 0)));
+    }
+}
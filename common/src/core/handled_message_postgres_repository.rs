@@ -0,0 +1,106 @@
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::{postgres::PgPoolOptions, PgPool};
+use std::time::Duration;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::core::idempotency_store::{IdempotencyStoreError, IdempotencyStorePort};
+
+/// Messages handled by the consumers, recorded in the `handled_messages` table of the database of the gateway
+///
+/// The table is created by the migrations of the gateway. Shared by the workers, with the `postgres` feature.
+#[derive(Clone)]
+pub struct HandledMessagePostgresRepository {
+    db_pool: PgPool,
+}
+
+impl HandledMessagePostgresRepository {
+    /// The connections are opened lazily: the worker starts while the database is unavailable
+    pub fn try_new(database_url: &Secret<String>) -> Result<Self, IdempotencyStoreError> {
+        let db_pool = PgPoolOptions::new()
+            .max_connections(2)
+            .acquire_timeout(Duration::from_secs(2))
+            .connect_lazy(database_url.expose_secret())
+            .map_err(|error| IdempotencyStoreError::StoreError(error.to_string()))?;
+
+        Ok(Self { db_pool })
+    }
+
+    /// Forgets the handled messages older than the retention, at each interval
+    pub async fn run_purge(self, retention: chrono::Duration, interval: Duration) {
+        let mut purges = tokio::time::interval(interval);
+
+        loop {
+            purges.tick().await;
+
+            match self.delete_completed_before(Utc::now() - retention).await {
+                Ok(0) => {}
+                Ok(nb_deleted) => info!(nb_deleted, "Forgot the expired handled messages"),
+                Err(error) => error!(?error, "Failed to forget the expired handled messages"),
+            }
+        }
+    }
+
+    #[tracing::instrument(name = "Deleting expired handled messages in database", skip(self))]
+    pub async fn delete_completed_before(
+        &self,
+        completed_before: DateTime<Utc>,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM handled_messages WHERE completed_at < $1")
+            .bind(completed_before)
+            .execute(&self.db_pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+impl IdempotencyStorePort for HandledMessagePostgresRepository {
+    #[tracing::instrument(name = "Checking handled message in database", skip(self))]
+    fn is_completed<'a>(
+        &'a self,
+        consumer: &'a str,
+        message_id: Uuid,
+    ) -> BoxFuture<'a, Result<bool, IdempotencyStoreError>> {
+        Box::pin(async move {
+            let completed = sqlx::query(
+                "SELECT 1 FROM handled_messages WHERE consumer = $1 AND message_id = $2",
+            )
+            .bind(consumer)
+            .bind(message_id)
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|error| IdempotencyStoreError::StoreError(error.to_string()))?;
+
+            Ok(completed.is_some())
+        })
+    }
+
+    #[tracing::instrument(name = "Setting message as handled in database", skip(self))]
+    fn set_completed<'a>(
+        &'a self,
+        consumer: &'a str,
+        message_id: Uuid,
+        completed_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<(), IdempotencyStoreError>> {
+        Box::pin(async move {
+            sqlx::query(
+                r#"
+    INSERT INTO handled_messages (consumer, message_id, completed_at)
+    VALUES ($1, $2, $3)
+    ON CONFLICT (consumer, message_id) DO NOTHING
+                "#,
+            )
+            .bind(consumer)
+            .bind(message_id)
+            .bind(completed_at)
+            .execute(&self.db_pool)
+            .await
+            .map_err(|error| IdempotencyStoreError::StoreError(error.to_string()))?;
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,248 @@
+use futures::future::{join_all, BoxFuture};
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use lapin::{Connection as RabbitMQConnection, ConnectionStatus};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap, convert::Infallible, net::TcpListener, sync::Arc, time::Duration,
+};
+use tracing::{info, warn};
+
+use crate::core::{memory_ceiling::MemorySettings, memory_debug_server};
+
+/// A dependency taking longer to be checked is reported as unavailable
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Dependency of a service (broker, object storage, search engine...) checked by its probes
+pub trait DependencyCheck: Send + Sync {
+    /// Checks that the dependency is available, or gives the reason why it is not
+    fn check(&self) -> BoxFuture<'_, Result<(), String>>;
+}
+
+/// Probe of an orchestrator (Kubernetes) on a service
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Probe {
+    /// Failing, the service is restarted: it can not recover by itself
+    Liveness,
+    /// Failing, the service is not sent traffic until it passes again
+    Readiness,
+}
+
+/// Checks of the dependencies of a service, run by its probes
+#[derive(Clone, Default)]
+pub struct HealthChecks {
+    liveness: Vec<(&'static str, Arc<dyn DependencyCheck>)>,
+    readiness: Vec<(&'static str, Arc<dyn DependencyCheck>)>,
+}
+
+impl HealthChecks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a dependency the service can not recover without: it fails both probes when unavailable
+    pub fn with_liveness_check(
+        mut self,
+        name: &'static str,
+        check: impl DependencyCheck + 'static,
+    ) -> Self {
+        self.liveness.push((name, Arc::new(check)));
+        self
+    }
+
+    /// Adds a dependency the service waits for: it only fails the readiness probe when unavailable
+    pub fn with_readiness_check(
+        mut self,
+        name: &'static str,
+        check: impl DependencyCheck + 'static,
+    ) -> Self {
+        self.readiness.push((name, Arc::new(check)));
+        self
+    }
+
+    /// Checks the dependencies of a probe, concurrently
+    pub async fn report(&self, probe: Probe) -> HealthReport {
+        let checks = match probe {
+            Probe::Liveness => self.liveness.iter().collect::<Vec<_>>(),
+            Probe::Readiness => self.liveness.iter().chain(self.readiness.iter()).collect(),
+        };
+
+        let statuses = join_all(checks.into_iter().map(|(name, check)| async move {
+            let status = match tokio::time::timeout(CHECK_TIMEOUT, check.check()).await {
+                Ok(Ok(())) => CheckStatus::Ok,
+                Ok(Err(error)) => CheckStatus::Failed { error },
+                Err(_) => CheckStatus::Failed {
+                    error: format!("no answer within {:?}", CHECK_TIMEOUT),
+                },
+            };
+            (*name, status)
+        }))
+        .await;
+
+        HealthReport {
+            is_healthy: statuses
+                .iter()
+                .all(|(_, status)| matches!(status, CheckStatus::Ok)),
+            checks: statuses.into_iter().collect(),
+        }
+    }
+}
+
+/// Statuses of the checked dependencies of a probe
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    pub is_healthy: bool,
+    pub checks: BTreeMap<&'static str, CheckStatus>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Failed { error: String },
+}
+
+/// RabbitMQ connection of a service: the services do not reconnect, so a closed connection fails the liveness
+pub struct RabbitMQConnectionCheck {
+    status: ConnectionStatus,
+}
+
+impl RabbitMQConnectionCheck {
+    pub fn new(connection: &RabbitMQConnection) -> Self {
+        Self {
+            status: connection.status().clone(),
+        }
+    }
+}
+
+impl DependencyCheck for RabbitMQConnectionCheck {
+    fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(async move {
+            if self.status.connected() {
+                Ok(())
+            } else {
+                Err(format!("connection {:?}", self.status.state()))
+            }
+        })
+    }
+}
+
+/// Serves the probes of a service, for an orchestrator to restart it or to stop sending it traffic
+///
+/// - `GET /health/live`: liveness probe, 200 or 503 with the statuses of the checked dependencies as JSON
+/// - `GET /health/ready`: readiness probe, 200 or 503 with the statuses of the checked dependencies as JSON
+///
+/// With memory settings, the memory debug endpoints (see `memory_debug_server`) are served too.
+#[tracing::instrument(name = "Running health server", skip(listener, health_checks))]
+pub async fn run_health_server(
+    listener: TcpListener,
+    health_checks: HealthChecks,
+    memory_debug: Option<MemorySettings>,
+) -> Result<(), hyper::Error> {
+    let address = listener.local_addr().ok();
+    let server = Server::from_tcp(listener)?;
+
+    let make_service = make_service_fn(move |_connection| {
+        let health_checks = health_checks.clone();
+        let memory_debug = memory_debug.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let health_checks = health_checks.clone();
+                let memory_debug = memory_debug.clone();
+                async move {
+                    Ok::<_, Infallible>(
+                        handle_request(request, &health_checks, memory_debug.as_ref()).await,
+                    )
+                }
+            }))
+        }
+    });
+
+    info!("Health probes served on {:?}", address);
+    server.serve(make_service).await
+}
+
+async fn handle_request(
+    request: Request<Body>,
+    health_checks: &HealthChecks,
+    memory_debug: Option<&MemorySettings>,
+) -> Response<Body> {
+    match (request.method(), request.uri().path(), memory_debug) {
+        (&Method::GET, "/health/live", _) => probe(health_checks, Probe::Liveness).await,
+        (&Method::GET, "/health/ready", _) => probe(health_checks, Probe::Readiness).await,
+        (_, path, Some(memory_settings)) if path.starts_with("/debug/") => {
+            memory_debug_server::handle_request(request, memory_settings)
+        }
+        _ => {
+            let mut response = Response::new(Body::empty());
+            *response.status_mut() = StatusCode::NOT_FOUND;
+            response
+        }
+    }
+}
+
+async fn probe(health_checks: &HealthChecks, probe: Probe) -> Response<Body> {
+    let report = health_checks.report(probe).await;
+    if !report.is_healthy {
+        warn!(?probe, ?report, "Failing probe");
+    }
+
+    let mut response = Response::new(Body::from(
+        serde_json::to_string(&report).unwrap_or_default(),
+    ));
+    *response.status_mut() = if report.is_healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, "application/json".parse().unwrap());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeCheck(Result<(), String>);
+
+    impl DependencyCheck for FakeCheck {
+        fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+            Box::pin(async move { self.0.clone() })
+        }
+    }
+
+    #[tokio::test]
+    async fn unavailable_readiness_dependency_only_fails_the_readiness() {
+        let health_checks = HealthChecks::new()
+            .with_liveness_check("rabbitmq", FakeCheck(Ok(())))
+            .with_readiness_check("s3", FakeCheck(Err("connection refused".to_string())));
+
+        let liveness = health_checks.report(Probe::Liveness).await;
+        assert!(liveness.is_healthy);
+        assert_eq!(liveness.checks.len(), 1);
+
+        let readiness = health_checks.report(Probe::Readiness).await;
+        assert!(!readiness.is_healthy);
+        assert_eq!(readiness.checks["rabbitmq"], CheckStatus::Ok);
+        assert_eq!(
+            readiness.checks["s3"],
+            CheckStatus::Failed {
+                error: "connection refused".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn unavailable_liveness_dependency_fails_both_probes() {
+        let health_checks = HealthChecks::new()
+            .with_liveness_check("rabbitmq", FakeCheck(Err("connection Closed".to_string())));
+
+        assert!(!health_checks.report(Probe::Liveness).await.is_healthy);
+        assert!(!health_checks.report(Probe::Readiness).await.is_healthy);
+    }
+}
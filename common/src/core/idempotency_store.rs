@@ -0,0 +1,235 @@
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use secrecy::Secret;
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{dtos::templates::message_envelope::enveloped_message_id, helper::error_chain_fmt};
+
+/// Settings of the idempotency of the consumers of a service
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdempotencySettings {
+    /// If false, the redelivered messages are handled again
+    pub enabled: bool,
+    /// Database where the handled messages are recorded, shared by the instances of the service.
+    /// A secret: `APP_IDEMPOTENCY__DATABASE_URL`
+    #[serde(default)]
+    pub database_url: Option<Secret<String>>,
+    /// Time a handled message is remembered, longer than the redeliveries of a message
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub retention_h: u32,
+    /// Interval at which the handled messages older than the retention are forgotten
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub purge_interval_ms: u64,
+}
+
+impl Default for IdempotencySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            database_url: None,
+            retention_h: 72,
+            purge_interval_ms: 3_600_000,
+        }
+    }
+}
+
+/// Messages already handled by the consumers, for a redelivered message not to be handled twice
+pub trait IdempotencyStorePort: Send + Sync {
+    /// Whether a consumer completed the handling of a message
+    fn is_completed<'a>(
+        &'a self,
+        consumer: &'a str,
+        message_id: Uuid,
+    ) -> BoxFuture<'a, Result<bool, IdempotencyStoreError>>;
+
+    /// Records that a consumer completed the handling of a message
+    fn set_completed<'a>(
+        &'a self,
+        consumer: &'a str,
+        message_id: Uuid,
+        completed_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<(), IdempotencyStoreError>>;
+}
+
+/// Store remembering no message: every delivered message is handled
+pub struct NoOpIdempotencyStore;
+
+impl IdempotencyStorePort for NoOpIdempotencyStore {
+    fn is_completed<'a>(
+        &'a self,
+        _consumer: &'a str,
+        _message_id: Uuid,
+    ) -> BoxFuture<'a, Result<bool, IdempotencyStoreError>> {
+        Box::pin(async { Ok(false) })
+    }
+
+    fn set_completed<'a>(
+        &'a self,
+        _consumer: &'a str,
+        _message_id: Uuid,
+        _completed_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<(), IdempotencyStoreError>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Idempotency of the handling of the messages delivered to a consumer
+///
+/// A redelivered message whose handling was completed is acknowledged without being handled again.
+/// Only the enveloped messages are checked: the bare payloads have no stable id.
+/// The store being unavailable does not stop the consumption: the messages are then handled, at least once.
+#[derive(Clone)]
+pub struct ConsumerIdempotency {
+    store: Arc<dyn IdempotencyStorePort>,
+    /// Name of the consumer, its queue: the consumers of a same message each handle it once
+    consumer: String,
+}
+
+impl ConsumerIdempotency {
+    pub fn new(store: Arc<dyn IdempotencyStorePort>, consumer: &str) -> Self {
+        Self {
+            store,
+            consumer: consumer.to_string(),
+        }
+    }
+
+    /// Whether the handling of a delivered message was already completed by the consumer
+    pub async fn is_completed(&self, data: &[u8]) -> bool {
+        let Some(message_id) = enveloped_message_id(data) else {
+            return false;
+        };
+
+        match self.store.is_completed(&self.consumer, message_id).await {
+            Ok(is_completed) => is_completed,
+            Err(error) => {
+                error!(
+                    ?error,
+                    "Failed to check if message {} was handled, handling it", message_id
+                );
+                false
+            }
+        }
+    }
+
+    /// Records that the consumer completed the handling of a delivered message
+    pub async fn set_completed(&self, data: &[u8]) {
+        let Some(message_id) = enveloped_message_id(data) else {
+            return;
+        };
+
+        if let Err(error) = self
+            .store
+            .set_completed(&self.consumer, message_id, Utc::now())
+            .await
+        {
+            error!(
+                ?error,
+                "Failed to record message {} as handled, it would be handled again if redelivered",
+                message_id
+            );
+        }
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum IdempotencyStoreError {
+    #[error("Error from the idempotency store: {0}")]
+    StoreError(String),
+}
+
+impl std::fmt::Debug for IdempotencyStoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dtos::templates::message_envelope::{
+        MessageEnvelope, MessagePayload, SchemaVersion,
+    };
+    use serde::Serialize;
+    use std::{collections::HashSet, sync::Mutex};
+
+    #[derive(Default)]
+    struct FakeIdempotencyStore {
+        completed: Mutex<HashSet<(String, Uuid)>>,
+    }
+
+    impl IdempotencyStorePort for FakeIdempotencyStore {
+        fn is_completed<'a>(
+            &'a self,
+            consumer: &'a str,
+            message_id: Uuid,
+        ) -> BoxFuture<'a, Result<bool, IdempotencyStoreError>> {
+            let is_completed = self
+                .completed
+                .lock()
+                .unwrap()
+                .contains(&(consumer.to_string(), message_id));
+            Box::pin(async move { Ok(is_completed) })
+        }
+
+        fn set_completed<'a>(
+            &'a self,
+            consumer: &'a str,
+            message_id: Uuid,
+            _completed_at: DateTime<Utc>,
+        ) -> BoxFuture<'a, Result<(), IdempotencyStoreError>> {
+            self.completed
+                .lock()
+                .unwrap()
+                .insert((consumer.to_string(), message_id));
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct TestPayload {
+        name: String,
+    }
+
+    impl MessagePayload for TestPayload {
+        const SCHEMA_VERSION: SchemaVersion = SchemaVersion::new(1, 0);
+    }
+
+    fn test_message() -> Vec<u8> {
+        MessageEnvelope::new(TestPayload {
+            name: "moby_dick.epub".to_string(),
+        })
+        .try_serializing()
+        .unwrap()
+        .into_bytes()
+    }
+
+    #[tokio::test]
+    async fn redelivered_message_is_completed_for_its_consumer_only() {
+        let store: Arc<dyn IdempotencyStorePort> = Arc::new(FakeIdempotencyStore::default());
+        let extraction = ConsumerIdempotency::new(store.clone(), "extract_content");
+        let indexing = ConsumerIdempotency::new(store, "index_content");
+        let message = test_message();
+
+        assert!(!extraction.is_completed(&message).await);
+        extraction.set_completed(&message).await;
+
+        assert!(extraction.is_completed(&message).await);
+        assert!(!indexing.is_completed(&message).await);
+        assert!(!extraction.is_completed(&test_message()).await);
+    }
+
+    #[tokio::test]
+    async fn bare_payloads_are_never_completed() {
+        let store: Arc<dyn IdempotencyStorePort> = Arc::new(FakeIdempotencyStore::default());
+        let idempotency = ConsumerIdempotency::new(store, "extract_content");
+        let message = br#"{"name":"moby_dick.epub"}"#;
+
+        idempotency.set_completed(message).await;
+
+        assert!(!idempotency.is_completed(message).await);
+    }
+}
@@ -0,0 +1,243 @@
+use lapin::{options::BasicQosOptions, Channel};
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Delay between 2 checks of the memory usage while the consumption is paused
+const PAUSED_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Settings of the memory ceiling of a worker
+#[derive(Debug, Clone, Deserialize)]
+pub struct MemorySettings {
+    /// Memory ceiling (resident memory) in MB. 0 disables the ceiling.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub ceiling_mb: u64,
+    /// Ratio of the ceiling above which messages are prefetched one by one
+    pub shed_ratio: f64,
+    /// Ratio of the ceiling above which the consumption of messages is paused
+    pub pause_ratio: f64,
+    /// If true, serves memory stats (and heap profiles with jemalloc) on the application port
+    pub debug_endpoints: bool,
+}
+
+impl Default for MemorySettings {
+    fn default() -> Self {
+        Self {
+            ceiling_mb: 0,
+            shed_ratio: 0.7,
+            pause_ratio: 0.9,
+            debug_endpoints: false,
+        }
+    }
+}
+
+/// Memory pressure relative to the ceiling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryPressure {
+    Normal,
+    /// Approaching the ceiling: fewer messages should be handled at once
+    High,
+    /// Close to the ceiling: no new message should be handled
+    Critical,
+}
+
+impl MemoryPressure {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MemoryPressure::Normal => "normal",
+            MemoryPressure::High => "high",
+            MemoryPressure::Critical => "critical",
+        }
+    }
+}
+
+impl MemorySettings {
+    pub fn ceiling_bytes(&self) -> Option<u64> {
+        (self.ceiling_mb > 0).then_some(self.ceiling_mb * 1024 * 1024)
+    }
+
+    /// Memory pressure for a given memory usage
+    pub fn pressure(&self, usage_bytes: u64) -> MemoryPressure {
+        let Some(ceiling_bytes) = self.ceiling_bytes() else {
+            return MemoryPressure::Normal;
+        };
+
+        let usage_ratio = usage_bytes as f64 / ceiling_bytes as f64;
+        if usage_ratio >= self.pause_ratio {
+            MemoryPressure::Critical
+        } else if usage_ratio >= self.shed_ratio {
+            MemoryPressure::High
+        } else {
+            MemoryPressure::Normal
+        }
+    }
+}
+
+/// Current memory usage of the process, in bytes
+///
+/// With the `jemalloc` feature, the resident memory reported by jemalloc.
+/// Otherwise the resident set size of the process, only available on Linux.
+pub fn current_memory_usage() -> Option<u64> {
+    #[cfg(feature = "jemalloc")]
+    {
+        // Stats are cached by jemalloc: advancing the epoch refreshes them
+        tikv_jemalloc_ctl::epoch::advance().ok()?;
+        tikv_jemalloc_ctl::stats::resident::read()
+            .ok()
+            .map(|resident| resident as u64)
+    }
+
+    #[cfg(not(feature = "jemalloc"))]
+    {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let vm_rss_kb = status
+            .lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))?
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse::<u64>()
+            .ok()?;
+
+        Some(vm_rss_kb * 1024)
+    }
+}
+
+/// Throttles the consumption of messages of a channel when the memory usage approaches the ceiling
+///
+/// Large jobs (big EPUB/PDF files) can make a worker reach the memory limit of its container,
+/// getting it killed. Instead, the worker prefetches messages one by one when approaching the ceiling,
+/// and pauses before handling a new message when it gets too close.
+pub struct ConsumptionThrottle {
+    settings: MemorySettings,
+    /// Number of messages prefetched under normal memory pressure
+    prefetch_count: u16,
+    is_shed: bool,
+}
+
+impl ConsumptionThrottle {
+    pub fn new(settings: MemorySettings, prefetch_count: u16) -> Self {
+        Self {
+            settings,
+            prefetch_count,
+            is_shed: false,
+        }
+    }
+
+    /// Applies the normal prefetch count on a channel, before starting to consume
+    pub async fn init(&mut self, channel: &Channel) -> Result<(), lapin::Error> {
+        self.is_shed = false;
+        channel
+            .basic_qos(self.prefetch_count, BasicQosOptions::default())
+            .await
+    }
+
+    /// Changes the prefetch count under normal memory pressure, for ex when the configuration is reloaded
+    ///
+    /// While shed, the new prefetch count is only applied once the pressure is back to normal.
+    pub async fn set_prefetch_count(
+        &mut self,
+        channel: &Channel,
+        prefetch_count: u16,
+    ) -> Result<(), lapin::Error> {
+        self.prefetch_count = prefetch_count;
+        if self.is_shed {
+            return Ok(());
+        }
+
+        self.init(channel).await
+    }
+
+    /// Waits until there is enough memory to handle a new message
+    ///
+    /// Sheds the prefetch count under high memory pressure, and restores it once the pressure is back to normal.
+    pub async fn wait_for_memory(&mut self, channel: &Channel) -> Result<(), lapin::Error> {
+        if self.settings.ceiling_bytes().is_none() {
+            return Ok(());
+        }
+
+        let mut is_paused = false;
+
+        loop {
+            let Some(usage_bytes) = current_memory_usage() else {
+                return Ok(());
+            };
+
+            match self.settings.pressure(usage_bytes) {
+                MemoryPressure::Critical => {
+                    if !is_paused {
+                        warn!(
+                            usage_bytes,
+                            "Memory close to the ceiling, pausing the consumption"
+                        );
+                        is_paused = true;
+                    }
+                    self.shed(channel).await?;
+                    tokio::time::sleep(PAUSED_CHECK_INTERVAL).await;
+                }
+                MemoryPressure::High => {
+                    self.shed(channel).await?;
+                    break;
+                }
+                MemoryPressure::Normal => {
+                    if self.is_shed {
+                        info!(usage_bytes, "Memory back to normal, restoring the prefetch");
+                        self.init(channel).await?;
+                    }
+                    break;
+                }
+            }
+        }
+
+        if is_paused {
+            info!("Resuming the consumption");
+        }
+
+        Ok(())
+    }
+
+    async fn shed(&mut self, channel: &Channel) -> Result<(), lapin::Error> {
+        if self.is_shed {
+            return Ok(());
+        }
+
+        warn!("Memory approaching the ceiling, prefetching messages one by one");
+        self.is_shed = true;
+        channel.basic_qos(1, BasicQosOptions::default()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pressure_should_depend_on_the_usage_ratio_of_the_ceiling() {
+        let settings = MemorySettings {
+            ceiling_mb: 100,
+            shed_ratio: 0.7,
+            pause_ratio: 0.9,
+            debug_endpoints: false,
+        };
+        let mb = 1024 * 1024;
+
+        assert_eq!(settings.pressure(50 * mb), MemoryPressure::Normal);
+        assert_eq!(settings.pressure(75 * mb), MemoryPressure::High);
+        assert_eq!(settings.pressure(95 * mb), MemoryPressure::Critical);
+    }
+
+    #[test]
+    fn without_ceiling_pressure_should_always_be_normal() {
+        let settings = MemorySettings::default();
+
+        assert_eq!(settings.pressure(u64::MAX), MemoryPressure::Normal);
+    }
+
+    #[test]
+    fn current_memory_usage_should_be_available_on_linux() {
+        if cfg!(target_os = "linux") {
+            assert!(current_memory_usage().unwrap() > 0);
+        }
+    }
+}
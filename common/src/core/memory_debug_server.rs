@@ -0,0 +1,83 @@
+use hyper::{header::CONTENT_TYPE, Body, Method, Request, Response, StatusCode};
+use serde_json::json;
+
+use crate::core::memory_ceiling::{current_memory_usage, MemorySettings};
+
+/// Memory endpoints for operators investigating the memory usage of a worker, served by the health server
+///
+/// - `GET /debug/memory`: memory usage and pressure relative to the ceiling, as JSON
+/// - `GET /debug/heap_profile`: a jemalloc heap profile, to analyze with `jeprof`.
+///   Needs the `jemalloc` feature, and profiling enabled when starting the worker: `MALLOC_CONF=prof:true`
+pub(crate) fn handle_request(request: Request<Body>, settings: &MemorySettings) -> Response<Body> {
+    match (request.method(), request.uri().path()) {
+        (&Method::GET, "/debug/memory") => memory_stats(settings),
+        (&Method::GET, "/debug/heap_profile") => heap_profile(),
+        _ => response(StatusCode::NOT_FOUND, Body::empty()),
+    }
+}
+
+fn memory_stats(settings: &MemorySettings) -> Response<Body> {
+    let usage_bytes = current_memory_usage();
+    let stats = json!({
+        "usage_bytes": usage_bytes,
+        "ceiling_bytes": settings.ceiling_bytes(),
+        "pressure": usage_bytes.map(|usage_bytes| settings.pressure(usage_bytes).as_str()),
+        "jemalloc": cfg!(feature = "jemalloc"),
+    });
+
+    let mut response = response(StatusCode::OK, Body::from(stats.to_string()));
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, "application/json".parse().unwrap());
+    response
+}
+
+#[cfg(feature = "jemalloc")]
+fn heap_profile() -> Response<Body> {
+    use std::ffi::CString;
+
+    // Safe: reading a boolean option of jemalloc
+    let is_profiling_enabled = unsafe { tikv_jemalloc_ctl::raw::read::<bool>(b"opt.prof\0") };
+    if !matches!(is_profiling_enabled, Ok(true)) {
+        return response(
+            StatusCode::CONFLICT,
+            Body::from(
+                "Heap profiling is not enabled: start the worker with MALLOC_CONF=prof:true",
+            ),
+        );
+    }
+
+    let path = std::env::temp_dir().join(format!("heap_{}.prof", uuid::Uuid::new_v4()));
+    let c_path = CString::new(path.to_string_lossy().as_bytes()).unwrap();
+
+    // Safe: `prof.dump` expects a pointer to a null-terminated path, living for the call duration
+    if let Err(error) = unsafe { tikv_jemalloc_ctl::raw::write(b"prof.dump\0", c_path.as_ptr()) } {
+        tracing::error!(%error, "Failed to dump the heap profile");
+        return response(StatusCode::INTERNAL_SERVER_ERROR, Body::empty());
+    }
+
+    let profile = std::fs::read(&path);
+    let _ = std::fs::remove_file(&path);
+
+    match profile {
+        Ok(profile) => response(StatusCode::OK, Body::from(profile)),
+        Err(error) => {
+            tracing::error!(?error, "Failed to read the heap profile");
+            response(StatusCode::INTERNAL_SERVER_ERROR, Body::empty())
+        }
+    }
+}
+
+#[cfg(not(feature = "jemalloc"))]
+fn heap_profile() -> Response<Body> {
+    response(
+        StatusCode::NOT_IMPLEMENTED,
+        Body::from("Heap profiling needs the worker to be built with the jemalloc feature"),
+    )
+}
+
+fn response(status: StatusCode, body: Body) -> Response<Body> {
+    let mut response = Response::new(body);
+    *response.status_mut() = status;
+    response
+}
@@ -0,0 +1,272 @@
+use hmac::{Hmac, Mac};
+use lapin::{
+    types::{AMQPValue, FieldTable, ShortString},
+    BasicProperties,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::collections::HashMap;
+
+use crate::helper::error_chain_fmt;
+
+/// Header of the id of the key that signed a message
+pub const SIGNATURE_KEY_ID_HEADER: &str = "x-signature-key-id";
+/// Header of the hex encoded HMAC-SHA256 signature of a message
+pub const SIGNATURE_HEADER: &str = "x-signature";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Settings of the signing of the messages exchanged between services
+///
+/// The keys are secrets: in production, they should be set from environment variables.
+/// For ex: `APP_MESSAGE_SIGNING__KEYS__2023_10=...`
+#[derive(Debug, Deserialize, Clone)]
+pub struct MessageSigningSettings {
+    /// If false, messages are published unsigned, and consumed without verifying their signature
+    pub enabled: bool,
+    /// Id of the key signing the published messages
+    pub current_key_id: String,
+    /// Keys accepted when verifying a signature, by id.
+    /// To rotate the keys, the previous key is kept until all the services sign with the new one.
+    #[serde(default)]
+    pub keys: HashMap<String, Secret<String>>,
+}
+
+/// Signs published messages and verifies consumed messages, with HMAC-SHA256
+///
+/// The signature covers the routing key and the payload: a signed message can not be replayed on another routing key.
+/// The default signer is disabled.
+#[derive(Clone, Default)]
+pub struct MessageSigner {
+    /// `None` when the signing is disabled
+    keys: Option<SigningKeys>,
+}
+
+#[derive(Clone)]
+struct SigningKeys {
+    current_key_id: String,
+    keys: HashMap<String, Secret<String>>,
+}
+
+impl MessageSigner {
+    pub fn try_new(settings: MessageSigningSettings) -> Result<Self, MessageSigningError> {
+        if !settings.enabled {
+            return Ok(Self::default());
+        }
+
+        if !settings.keys.contains_key(&settings.current_key_id) {
+            return Err(MessageSigningError::UnknownKey(settings.current_key_id));
+        }
+
+        Ok(Self {
+            keys: Some(SigningKeys {
+                current_key_id: settings.current_key_id,
+                keys: settings.keys,
+            }),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.keys.is_some()
+    }
+
+    /// Headers carrying the signature of a message, signed with the current key
+    ///
+    /// Empty if the signing is disabled.
+    pub fn signature_headers(&self, routing_key: &str, data: &[u8]) -> FieldTable {
+        let mut headers = FieldTable::default();
+
+        if let Some(signing_keys) = &self.keys {
+            let signature = signing_keys
+                .mac(&signing_keys.current_key_id, routing_key, data)
+                .expect("The current key was checked at construction")
+                .finalize()
+                .into_bytes();
+
+            headers.insert(
+                SIGNATURE_KEY_ID_HEADER.into(),
+                AMQPValue::LongString(signing_keys.current_key_id.clone().into()),
+            );
+            headers.insert(
+                SIGNATURE_HEADER.into(),
+                AMQPValue::LongString(hex::encode(signature).into()),
+            );
+        }
+
+        headers
+    }
+
+    /// Verifies the signature of a consumed message
+    ///
+    /// Always succeeds if the signing is disabled.
+    pub fn verify(
+        &self,
+        routing_key: &str,
+        data: &[u8],
+        properties: &BasicProperties,
+    ) -> Result<(), MessageSigningError> {
+        let Some(signing_keys) = &self.keys else {
+            return Ok(());
+        };
+
+        let key_id = header_value(properties, SIGNATURE_KEY_ID_HEADER)?;
+        let key_id = String::from_utf8_lossy(key_id);
+        let signature = hex::decode(header_value(properties, SIGNATURE_HEADER)?)
+            .map_err(|_| MessageSigningError::InvalidSignature)?;
+
+        signing_keys
+            .mac(&key_id, routing_key, data)
+            .ok_or_else(|| MessageSigningError::UnknownKey(key_id.to_string()))?
+            .verify_slice(&signature)
+            .map_err(|_| MessageSigningError::InvalidSignature)
+    }
+}
+
+impl SigningKeys {
+    /// HMAC of a message with a given key, `None` if the key is unknown
+    fn mac(&self, key_id: &str, routing_key: &str, data: &[u8]) -> Option<HmacSha256> {
+        let key = self.keys.get(key_id)?;
+
+        let mut mac = HmacSha256::new_from_slice(key.expose_secret().as_bytes())
+            .expect("HMAC can take a key of any size");
+        mac.update(routing_key.as_bytes());
+        // Separates the routing key from the payload
+        mac.update(b"\n");
+        mac.update(data);
+
+        Some(mac)
+    }
+}
+
+fn header_value<'a>(
+    properties: &'a BasicProperties,
+    header: &'static str,
+) -> Result<&'a [u8], MessageSigningError> {
+    properties
+        .headers()
+        .as_ref()
+        .and_then(|headers| headers.inner().get(&ShortString::from(header)))
+        .and_then(|value| value.as_long_string())
+        .map(|value| value.as_bytes())
+        .ok_or(MessageSigningError::MissingHeader(header))
+}
+
+#[derive(thiserror::Error)]
+pub enum MessageSigningError {
+    #[error("The message is not signed: missing header {0}")]
+    MissingHeader(&'static str),
+    #[error("Unknown signing key: {0}")]
+    UnknownKey(String),
+    #[error("The signature of the message is invalid")]
+    InvalidSignature,
+}
+
+impl std::fmt::Debug for MessageSigningError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signer(current_key_id: &str, keys: &[(&str, &str)]) -> MessageSigner {
+        MessageSigner::try_new(MessageSigningSettings {
+            enabled: true,
+            current_key_id: current_key_id.to_string(),
+            keys: keys
+                .iter()
+                .map(|(id, key)| (id.to_string(), Secret::new(key.to_string())))
+                .collect(),
+        })
+        .unwrap()
+    }
+
+    fn signed_properties(
+        signer: &MessageSigner,
+        routing_key: &str,
+        data: &[u8],
+    ) -> BasicProperties {
+        BasicProperties::default().with_headers(signer.signature_headers(routing_key, data))
+    }
+
+    #[test]
+    fn signed_message_is_verified() {
+        let signer = signer("2023_10", &[("2023_10", "key")]);
+        let properties = signed_properties(&signer, "content_extracted.v1", b"{}");
+
+        assert!(signer
+            .verify("content_extracted.v1", b"{}", &properties)
+            .is_ok());
+    }
+
+    #[test]
+    fn message_signed_with_a_previous_key_is_verified_during_a_rotation() {
+        let previous_signer = signer("2023_09", &[("2023_09", "old key")]);
+        let rotated_signer = signer("2023_10", &[("2023_10", "key"), ("2023_09", "old key")]);
+        let properties = signed_properties(&previous_signer, "content_extracted.v1", b"{}");
+
+        assert!(rotated_signer
+            .verify("content_extracted.v1", b"{}", &properties)
+            .is_ok());
+    }
+
+    #[test]
+    fn forged_message_is_not_verified() {
+        let forger = signer("2023_10", &[("2023_10", "guessed key")]);
+        let signer = signer("2023_10", &[("2023_10", "key")]);
+        let properties = signed_properties(&signer, "content_extracted.v1", b"{}");
+
+        // Tampered payload
+        assert!(matches!(
+            signer.verify("content_extracted.v1", b"{\"id\":1}", &properties),
+            Err(MessageSigningError::InvalidSignature)
+        ));
+        // Replayed on another routing key
+        assert!(matches!(
+            signer.verify("delete_content.v1", b"{}", &properties),
+            Err(MessageSigningError::InvalidSignature)
+        ));
+        // Signed with another key
+        assert!(matches!(
+            signer.verify(
+                "content_extracted.v1",
+                b"{}",
+                &signed_properties(&forger, "content_extracted.v1", b"{}")
+            ),
+            Err(MessageSigningError::InvalidSignature)
+        ));
+        // Unsigned
+        assert!(matches!(
+            signer.verify("content_extracted.v1", b"{}", &BasicProperties::default()),
+            Err(MessageSigningError::MissingHeader(_))
+        ));
+    }
+
+    #[test]
+    fn message_signed_with_a_retired_key_is_not_verified() {
+        let previous_signer = signer("2023_09", &[("2023_09", "old key")]);
+        let signer = signer("2023_10", &[("2023_10", "key")]);
+        let properties = signed_properties(&previous_signer, "content_extracted.v1", b"{}");
+
+        assert!(matches!(
+            signer.verify("content_extracted.v1", b"{}", &properties),
+            Err(MessageSigningError::UnknownKey(_))
+        ));
+    }
+
+    #[test]
+    fn disabled_signer_does_not_sign_nor_verify() {
+        let signer = MessageSigner::default();
+
+        assert!(signer
+            .signature_headers("content_extracted.v1", b"{}")
+            .inner()
+            .is_empty());
+        assert!(signer
+            .verify("content_extracted.v1", b"{}", &BasicProperties::default())
+            .is_ok());
+    }
+}
@@ -1 +1,21 @@
+pub mod configuration;
+pub mod consumer_handover;
+pub mod drm;
+pub mod fair_share;
+#[cfg(feature = "postgres")]
+pub mod handled_message_postgres_repository;
+pub mod health_server;
+pub mod idempotency_store;
+pub mod memory_ceiling;
+pub mod memory_debug_server;
+pub mod message_signing;
+pub mod normalization_rules;
+pub mod provider_credentials;
 pub mod rabbitmq_message_repository;
+pub mod retry;
+pub mod rpc_replies;
+pub mod secret_provider;
+pub mod secrets;
+pub mod tenancy;
+pub mod text_normalizer;
+pub mod trace_propagation;
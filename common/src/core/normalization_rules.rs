@@ -0,0 +1,257 @@
+use futures::StreamExt;
+use lapin::{
+    options::{BasicConsumeOptions, ExchangeDeclareOptions, QueueBindOptions, QueueDeclareOptions},
+    types::FieldTable,
+    Connection, ExchangeKind,
+};
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    constants::routing_keys::{
+        GET_NORMALIZATION_RULES_ROUTING_KEY, NORMALIZATION_RULES_CHANGED_ROUTING_KEY,
+    },
+    core::{
+        rabbitmq_message_repository::{RabbitMQMessageRepository, RabbitMQMessageRepositoryError},
+        text_normalizer::{TextNormalizer, TextNormalizerError},
+    },
+    dtos::{
+        normalization_rules::NormalizationRulesResponseDto,
+        templates::rpc_response::{RpcResponse, RpcResponseEncodingError},
+    },
+    helper::error_chain_fmt,
+};
+
+/// Settings of the normalization of the texts with the rules of the tenant of a service
+#[derive(Debug, Clone, Deserialize)]
+pub struct NormalizationRulesSettings {
+    /// If false, the texts are not normalized, and the rules are never fetched
+    pub enabled: bool,
+    /// Time to wait for the gateway to answer with the rules
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub fetch_timeout_ms: u64,
+    /// Time after which the rules are fetched again, when the gateway did not answer
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub retry_delay_ms: u64,
+}
+
+impl Default for NormalizationRulesSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            fetch_timeout_ms: 2000,
+            retry_delay_ms: 60_000,
+        }
+    }
+}
+
+struct CachedNormalizer {
+    normalizer: Arc<TextNormalizer>,
+    /// Set if the rules could not be fetched: they are fetched again from this instant
+    retry_at: Option<Instant>,
+}
+
+/// Normalization rules of the tenant of a service, fetched from the gateway and cached until they change
+///
+/// The rules are fetched with an RPC call on their first use, and on their first use after each change
+/// (see `watch_normalization_rules_changes`). While the gateway does not answer, the texts are not normalized.
+pub struct NormalizationRulesCache {
+    settings: NormalizationRulesSettings,
+    cached: RwLock<Option<CachedNormalizer>>,
+    /// Incremented on each change, for rules fetched before a change not to be cached after it
+    generation: AtomicU64,
+}
+
+impl NormalizationRulesCache {
+    pub fn new(settings: NormalizationRulesSettings) -> Self {
+        // Without normalization, the cache always holds a normalizer without rules
+        let cached = (!settings.enabled).then(|| CachedNormalizer {
+            normalizer: Arc::new(TextNormalizer::default()),
+            retry_at: None,
+        });
+
+        Self {
+            settings,
+            cached: RwLock::new(cached),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Normalizer of the texts of the tenant, with its current rules
+    ///
+    /// # Arguments
+    /// * `message_repository` - initialized repository on which the rules are fetched, publishing on the exchange of the tenant
+    pub async fn get(&self, message_repository: &RabbitMQMessageRepository) -> Arc<TextNormalizer> {
+        if let Some(cached) = self.cached.read().unwrap().as_ref() {
+            if cached
+                .retry_at
+                .is_none_or(|retry_at| Instant::now() < retry_at)
+            {
+                return cached.normalizer.clone();
+            }
+        }
+
+        let generation = self.generation.load(Ordering::SeqCst);
+        let cached = match self.fetch(message_repository).await {
+            Ok(normalizer) => CachedNormalizer {
+                normalizer: Arc::new(normalizer),
+                retry_at: None,
+            },
+            Err(error) => {
+                warn!(
+                    ?error,
+                    "Failed to get the normalization rules: the texts are not normalized until they are fetched again"
+                );
+                CachedNormalizer {
+                    normalizer: Arc::new(TextNormalizer::default()),
+                    retry_at: Some(
+                        Instant::now() + Duration::from_millis(self.settings.retry_delay_ms),
+                    ),
+                }
+            }
+        };
+
+        let normalizer = cached.normalizer.clone();
+        let mut current = self.cached.write().unwrap();
+        if self.generation.load(Ordering::SeqCst) == generation {
+            *current = Some(cached);
+        }
+
+        normalizer
+    }
+
+    /// Drops the cached rules, fetched again on their next use
+    pub fn invalidate(&self) {
+        if !self.settings.enabled {
+            return;
+        }
+
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        *self.cached.write().unwrap() = None;
+    }
+
+    #[tracing::instrument(name = "Fetching normalization rules", skip(self, message_repository))]
+    async fn fetch(
+        &self,
+        message_repository: &RabbitMQMessageRepository,
+    ) -> Result<TextNormalizer, NormalizationRulesError> {
+        // The gateway answers with the rules of the tenant of the exchange: the request has no parameter
+        let response = message_repository
+            .rpc_call(
+                GET_NORMALIZATION_RULES_ROUTING_KEY,
+                b"{}",
+                Some(self.settings.fetch_timeout_ms as usize),
+            )
+            .await?;
+
+        match NormalizationRulesResponseDto::try_parsing(&response)? {
+            RpcResponse::Ok { data } => {
+                info!("Fetched {} normalization rules", data.rules.len());
+                Ok(TextNormalizer::try_new(&data.rules)?)
+            }
+            RpcResponse::Error { message, .. } => {
+                Err(NormalizationRulesError::GatewayError(message))
+            }
+        }
+    }
+}
+
+/// Invalidates the cached rules on each change of the rules of the tenant, published by the gateway on its exchange
+///
+/// Each instance of a service consumes the changes from its own exclusive queue: the cache of every instance is invalidated.
+/// The changes are consumed by a spawned task, once the queue is bound.
+pub async fn watch_normalization_rules_changes(
+    connection: &Connection,
+    exchange_name: &str,
+    cache: Arc<NormalizationRulesCache>,
+) -> Result<(), lapin::Error> {
+    let channel = connection.create_channel().await?;
+
+    channel
+        .exchange_declare(
+            exchange_name,
+            ExchangeKind::Topic,
+            ExchangeDeclareOptions {
+                durable: true,
+                ..ExchangeDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    // When supplying an empty string queue name, RabbitMQ generates a name for us
+    let queue = channel
+        .queue_declare(
+            "",
+            QueueDeclareOptions {
+                exclusive: true,
+                ..QueueDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+    channel
+        .queue_bind(
+            queue.name().as_str(),
+            exchange_name,
+            NORMALIZATION_RULES_CHANGED_ROUTING_KEY,
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    let mut consumer = channel
+        .basic_consume(
+            queue.name().as_str(),
+            "",
+            BasicConsumeOptions {
+                no_ack: true,
+                ..BasicConsumeOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    tokio::spawn(async move {
+        // Keeps the channel open while consuming
+        let _channel = channel;
+
+        while let Some(delivery) = consumer.next().await {
+            match delivery {
+                Ok(_) => {
+                    info!("Normalization rules changed, invalidating the cached ones");
+                    cache.invalidate();
+                }
+                Err(error) => error!(?error, "Failed to consume a normalization rules change"),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[derive(thiserror::Error)]
+pub enum NormalizationRulesError {
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+    #[error(transparent)]
+    RpcResponseEncodingError(#[from] RpcResponseEncodingError),
+    #[error(transparent)]
+    TextNormalizerError(#[from] TextNormalizerError),
+    #[error("The gateway failed to get the rules: {0}")]
+    GatewayError(String),
+}
+
+impl std::fmt::Debug for NormalizationRulesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,315 @@
+use futures::StreamExt;
+use lapin::{
+    options::{BasicConsumeOptions, ExchangeDeclareOptions, QueueBindOptions, QueueDeclareOptions},
+    types::FieldTable,
+    Connection, ExchangeKind,
+};
+use secrecy::Secret;
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, RwLock,
+};
+use tracing::{error, info};
+
+use crate::{
+    constants::routing_keys::{
+        GET_PROVIDER_CREDENTIALS_ROUTING_KEY, PROVIDER_CREDENTIALS_CHANGED_ROUTING_KEY,
+    },
+    core::{
+        rabbitmq_message_repository::{RabbitMQMessageRepository, RabbitMQMessageRepositoryError},
+        secrets::{SecretsCipher, SecretsError},
+    },
+    dtos::{
+        provider_credentials::{
+            ModelProviderDto, ProviderCredentialsData, ProviderCredentialsResponseDto,
+        },
+        provider_usage::ProviderPurposeDto,
+        templates::rpc_response::{RpcResponse, RpcResponseEncodingError},
+    },
+    helper::error_chain_fmt,
+};
+
+/// Settings of the use of the model providers brought by the tenant of a service
+#[derive(Debug, Clone, Deserialize)]
+pub struct TenantProviderSettings {
+    /// If false, the models configured for the service are used, and the credentials of the tenant are never fetched
+    pub enabled: bool,
+    /// Time to wait for the gateway to answer with the credentials
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub fetch_timeout_ms: u64,
+    /// Maximum duration of a request to the provider of the tenant
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub request_timeout_ms: u64,
+}
+
+impl Default for TenantProviderSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            fetch_timeout_ms: 2000,
+            request_timeout_ms: 10_000,
+        }
+    }
+}
+
+/// Model provider brought by a tenant for a purpose, with its decrypted API key
+#[derive(Debug)]
+pub struct TenantProvider {
+    pub purpose: ProviderPurposeDto,
+    pub provider: ModelProviderDto,
+    /// Base URL of the OpenAI compatible API of the provider
+    pub api_url: String,
+    pub model: String,
+    pub api_key: Secret<String>,
+}
+
+/// Provider credentials of the tenant of a service, fetched from the gateway and cached until they change
+///
+/// The credentials are fetched with an RPC call on their first use, and on their first use after each change
+/// (see `watch_provider_credentials_changes`). Unlike the normalization rules, a failure to fetch them is an error:
+/// the contents of a tenant should not be processed by another model than the one it chose.
+pub struct ProviderCredentialsCache {
+    settings: TenantProviderSettings,
+    secrets_cipher: SecretsCipher,
+    cached: RwLock<Option<Arc<Vec<Arc<TenantProvider>>>>>,
+    /// Incremented on each change, for credentials fetched before a change not to be cached after it
+    generation: AtomicU64,
+}
+
+impl ProviderCredentialsCache {
+    /// # Arguments
+    /// * `secrets_cipher` - decrypts the API keys, with the `secrets` keys of the gateway
+    pub fn new(settings: TenantProviderSettings, secrets_cipher: SecretsCipher) -> Self {
+        Self {
+            settings,
+            secrets_cipher,
+            cached: RwLock::new(None),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Provider of the tenant for a purpose, `None` if the tenant did not bring any
+    ///
+    /// # Arguments
+    /// * `message_repository` - initialized repository on which the credentials are fetched, publishing on the exchange of the tenant
+    pub async fn get(
+        &self,
+        message_repository: &RabbitMQMessageRepository,
+        purpose: ProviderPurposeDto,
+    ) -> Result<Option<Arc<TenantProvider>>, ProviderCredentialsError> {
+        let cached = self.cached.read().unwrap().clone();
+        let providers = match cached {
+            Some(providers) => providers,
+            None => {
+                let generation = self.generation.load(Ordering::SeqCst);
+                let providers = Arc::new(self.fetch(message_repository).await?);
+
+                let mut current = self.cached.write().unwrap();
+                if self.generation.load(Ordering::SeqCst) == generation {
+                    *current = Some(providers.clone());
+                }
+                providers
+            }
+        };
+
+        Ok(providers
+            .iter()
+            .find(|provider| provider.purpose == purpose)
+            .cloned())
+    }
+
+    /// Drops the cached credentials, fetched again on their next use
+    pub fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        *self.cached.write().unwrap() = None;
+    }
+
+    #[tracing::instrument(name = "Fetching provider credentials", skip(self, message_repository))]
+    async fn fetch(
+        &self,
+        message_repository: &RabbitMQMessageRepository,
+    ) -> Result<Vec<Arc<TenantProvider>>, ProviderCredentialsError> {
+        // The gateway answers with the credentials of the tenant of the exchange: the request has no parameter
+        let response = message_repository
+            .rpc_call(
+                GET_PROVIDER_CREDENTIALS_ROUTING_KEY,
+                b"{}",
+                Some(self.settings.fetch_timeout_ms as usize),
+            )
+            .await?;
+
+        match ProviderCredentialsResponseDto::try_parsing(&response)? {
+            RpcResponse::Ok { data } => {
+                info!(
+                    "Fetched the provider credentials of the tenant for {} purposes",
+                    data.credentials.len()
+                );
+                decrypt_credentials(&self.secrets_cipher, data)
+            }
+            RpcResponse::Error { message, .. } => {
+                Err(ProviderCredentialsError::GatewayError(message))
+            }
+        }
+    }
+}
+
+fn decrypt_credentials(
+    secrets_cipher: &SecretsCipher,
+    data: ProviderCredentialsData,
+) -> Result<Vec<Arc<TenantProvider>>, ProviderCredentialsError> {
+    data.credentials
+        .into_iter()
+        .map(|credentials| {
+            Ok(Arc::new(TenantProvider {
+                purpose: credentials.purpose,
+                provider: credentials.provider,
+                api_url: credentials.api_url,
+                model: credentials.model,
+                api_key: secrets_cipher.decrypt(&credentials.encrypted_api_key)?,
+            }))
+        })
+        .collect()
+}
+
+/// Invalidates the cached credentials on each change of the credentials of the tenant, published by the gateway on its exchange
+///
+/// Each instance of a service consumes the changes from its own exclusive queue: the cache of every instance is invalidated.
+/// The changes are consumed by a spawned task, once the queue is bound.
+pub async fn watch_provider_credentials_changes(
+    connection: &Connection,
+    exchange_name: &str,
+    cache: Arc<ProviderCredentialsCache>,
+) -> Result<(), lapin::Error> {
+    let channel = connection.create_channel().await?;
+
+    channel
+        .exchange_declare(
+            exchange_name,
+            ExchangeKind::Topic,
+            ExchangeDeclareOptions {
+                durable: true,
+                ..ExchangeDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    // When supplying an empty string queue name, RabbitMQ generates a name for us
+    let queue = channel
+        .queue_declare(
+            "",
+            QueueDeclareOptions {
+                exclusive: true,
+                ..QueueDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+    channel
+        .queue_bind(
+            queue.name().as_str(),
+            exchange_name,
+            PROVIDER_CREDENTIALS_CHANGED_ROUTING_KEY,
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    let mut consumer = channel
+        .basic_consume(
+            queue.name().as_str(),
+            "",
+            BasicConsumeOptions {
+                no_ack: true,
+                ..BasicConsumeOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    tokio::spawn(async move {
+        // Keeps the channel open while consuming
+        let _channel = channel;
+
+        while let Some(delivery) = consumer.next().await {
+            match delivery {
+                Ok(_) => {
+                    info!("Provider credentials changed, invalidating the cached ones");
+                    cache.invalidate();
+                }
+                Err(error) => error!(?error, "Failed to consume a provider credentials change"),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[derive(thiserror::Error)]
+pub enum ProviderCredentialsError {
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+    #[error(transparent)]
+    RpcResponseEncodingError(#[from] RpcResponseEncodingError),
+    #[error("Failed to decrypt the API key of the tenant: {0}")]
+    SecretsError(#[from] SecretsError),
+    #[error("The gateway failed to get the credentials: {0}")]
+    GatewayError(String),
+}
+
+impl std::fmt::Debug for ProviderCredentialsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::secrets::SecretsSettings, dtos::provider_credentials::ProviderCredentialsDto,
+    };
+    use secrecy::ExposeSecret;
+
+    #[test]
+    fn api_keys_of_the_credentials_are_decrypted() {
+        let secrets_cipher = SecretsCipher::try_new(SecretsSettings {
+            current_key_id: "2023_10".to_string(),
+            keys: [(
+                "2023_10".to_string(),
+                Secret::new(
+                    "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f".to_string(),
+                ),
+            )]
+            .into(),
+        })
+        .unwrap();
+        let data = ProviderCredentialsData {
+            credentials: vec![ProviderCredentialsDto {
+                purpose: ProviderPurposeDto::Embedding,
+                provider: ModelProviderDto::OpenAi,
+                api_url: "https://api.openai.com/v1".to_string(),
+                model: "text-embedding-3-small".to_string(),
+                encrypted_api_key: secrets_cipher
+                    .encrypt(&Secret::new("sk-tenant".to_string()))
+                    .unwrap(),
+            }],
+        };
+
+        let providers = decrypt_credentials(&secrets_cipher, data.clone()).unwrap();
+
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].purpose, ProviderPurposeDto::Embedding);
+        assert_eq!(providers[0].model, "text-embedding-3-small");
+        assert_eq!(providers[0].api_key.expose_secret(), "sk-tenant");
+
+        let mut tampered = data;
+        tampered.credentials[0].encrypted_api_key.push('0');
+        assert!(matches!(
+            decrypt_credentials(&secrets_cipher, tampered),
+            Err(ProviderCredentialsError::SecretsError(_))
+        ));
+    }
+}
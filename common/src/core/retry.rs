@@ -0,0 +1,188 @@
+use rand::Rng;
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+use std::{future::Future, time::Duration};
+use tracing::warn;
+
+/// Errors that can be classified as transient: retrying the failed operation could succeed
+///
+/// Network timeouts or 5xx responses are transient. A missing object or an invalid request are permanent.
+pub trait TransientError {
+    fn is_transient(&self) -> bool;
+}
+
+/// Policy to retry an operation on transient failures, with exponential backoff and jitter
+#[derive(Debug, Clone, Deserialize)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one. 1 disables retries.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_attempts: u32,
+    /// Backoff before the first retry, doubling on each following retry
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub initial_backoff_ms: u64,
+    /// Upper bound of the backoff
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 200,
+            max_backoff_ms: 5000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Backoff before the given retry (starting at 1), without jitter
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let backoff_ms = self
+            .initial_backoff_ms
+            .saturating_mul(2_u64.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_backoff_ms);
+
+        Duration::from_millis(backoff_ms)
+    }
+
+    /// Backoff with "equal jitter": half of the backoff, plus a random part up to the other half
+    ///
+    /// Avoids that all the consumers failing at the same time retry at the same time.
+    fn backoff_with_jitter(&self, retry: u32) -> Duration {
+        let backoff = self.backoff(retry);
+        let half_backoff = backoff / 2;
+        let jitter_ms = rand::thread_rng().gen_range(0..=half_backoff.as_millis() as u64);
+
+        half_backoff + Duration::from_millis(jitter_ms)
+    }
+
+    /// Runs an operation, retrying it on transient failures
+    ///
+    /// Permanent failures are returned immediately.
+    ///
+    /// # Returns
+    /// The result of the last attempt
+    pub async fn retry<T, E, F, Fut>(&self, operation_name: &str, mut operation: F) -> Result<T, E>
+    where
+        E: TransientError + std::fmt::Display,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut attempt = 1;
+
+        loop {
+            match operation().await {
+                Err(error) if error.is_transient() && attempt < self.max_attempts => {
+                    let backoff = self.backoff_with_jitter(attempt);
+                    warn!(
+                        %error,
+                        attempt,
+                        max_attempts = self.max_attempts,
+                        "Transient failure of {}, retrying in {:?}",
+                        operation_name,
+                        backoff
+                    );
+
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[derive(Debug)]
+    enum TestError {
+        Transient,
+        Permanent,
+    }
+
+    impl std::fmt::Display for TestError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self)
+        }
+    }
+
+    impl TransientError for TestError {
+        fn is_transient(&self) -> bool {
+            matches!(self, TestError::Transient)
+        }
+    }
+
+    fn fast_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 2,
+        }
+    }
+
+    #[test]
+    fn backoff_should_grow_exponentially_up_to_the_max_backoff() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1000,
+        };
+
+        let backoffs: Vec<u128> = (1..=6)
+            .map(|retry| policy.backoff(retry).as_millis())
+            .collect();
+
+        assert_eq!(backoffs, vec![100, 200, 400, 800, 1000, 1000]);
+    }
+
+    #[tokio::test]
+    async fn on_transient_failures_it_should_retry_until_success() {
+        let nb_calls = Cell::new(0);
+
+        let result = fast_policy(3)
+            .retry("test", || async {
+                nb_calls.set(nb_calls.get() + 1);
+                if nb_calls.get() < 3 {
+                    return Err(TestError::Transient);
+                }
+                Ok(nb_calls.get())
+            })
+            .await;
+
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn on_transient_failures_it_should_stop_after_max_attempts() {
+        let nb_calls = Cell::new(0);
+
+        let result: Result<(), TestError> = fast_policy(4)
+            .retry("test", || async {
+                nb_calls.set(nb_calls.get() + 1);
+                Err(TestError::Transient)
+            })
+            .await;
+
+        assert!(matches!(result, Err(TestError::Transient)));
+        assert_eq!(nb_calls.get(), 4);
+    }
+
+    #[tokio::test]
+    async fn on_permanent_failure_it_should_fail_fast() {
+        let nb_calls = Cell::new(0);
+
+        let result: Result<(), TestError> = fast_policy(4)
+            .retry("test", || async {
+                nb_calls.set(nb_calls.get() + 1);
+                Err(TestError::Permanent)
+            })
+            .await;
+
+        assert!(matches!(result, Err(TestError::Permanent)));
+        assert_eq!(nb_calls.get(), 1);
+    }
+}
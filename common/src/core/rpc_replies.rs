@@ -0,0 +1,166 @@
+use futures::StreamExt;
+use lapin::{options::BasicConsumeOptions, types::FieldTable, Channel};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    OnceCell,
+};
+use tracing::{debug, error};
+
+/// Pseudo-queue of the direct reply-to of RabbitMQ, on which the replies to the RPC calls are consumed
+pub const RPC_REPLY_TO_QUEUE: &str = "amq.rabbitmq.reply-to";
+
+type PendingCalls = Arc<Mutex<HashMap<String, UnboundedSender<Vec<u8>>>>>;
+
+/// Replies to the RPC calls of a channel, dispatched to their calls by correlation id
+///
+/// A channel can only consume once the `amq.rabbitmq.reply-to` pseudo-queue: its consumer is started on the first call,
+/// and shared by all the calls of the channel, which can then be concurrent.
+/// The replies to no pending call, late replies to timed out calls or foreign ones, are skipped.
+#[derive(Clone, Default)]
+pub struct RpcReplies {
+    pending_calls: PendingCalls,
+    consumer: Arc<OnceCell<()>>,
+}
+
+impl RpcReplies {
+    /// Registers a call, to be done before publishing its request so none of its replies is missed
+    ///
+    /// # Arguments
+    /// * `channel` - channel on which the request is published
+    /// * `correlation_id` - correlation id of the request, set by the responder on its replies
+    pub async fn register(
+        &self,
+        channel: &Channel,
+        correlation_id: &str,
+    ) -> Result<PendingCall, lapin::Error> {
+        self.consumer
+            .get_or_try_init(|| self.start_consumer(channel))
+            .await?;
+
+        Ok(self.register_call(correlation_id))
+    }
+
+    fn register_call(&self, correlation_id: &str) -> PendingCall {
+        let (sender, receiver) = unbounded_channel();
+        self.pending_calls
+            .lock()
+            .unwrap()
+            .insert(correlation_id.to_string(), sender);
+
+        PendingCall {
+            correlation_id: correlation_id.to_string(),
+            replies: receiver,
+            pending_calls: self.pending_calls.clone(),
+        }
+    }
+
+    async fn start_consumer(&self, channel: &Channel) -> Result<(), lapin::Error> {
+        // The consumer of the direct reply-to must not acknowledge the replies: its queue is not a real one
+        let mut consumer = channel
+            .basic_consume(
+                RPC_REPLY_TO_QUEUE,
+                "",
+                BasicConsumeOptions {
+                    no_ack: true,
+                    ..BasicConsumeOptions::default()
+                },
+                FieldTable::default(),
+            )
+            .await?;
+
+        let replies = self.clone();
+        tokio::spawn(async move {
+            while let Some(delivery) = consumer.next().await {
+                let delivery = match delivery {
+                    Ok(delivery) => delivery,
+                    Err(error) => {
+                        error!(?error, "Failed to consume a reply to an RPC call");
+                        continue;
+                    }
+                };
+
+                let correlation_id = delivery
+                    .properties
+                    .correlation_id()
+                    .as_ref()
+                    .map(|id| id.as_str());
+                replies.dispatch(correlation_id, delivery.data);
+            }
+
+            // Closes the replies of the pending calls, with the channel
+            replies.pending_calls.lock().unwrap().clear();
+        });
+
+        Ok(())
+    }
+
+    /// Sends a reply to its pending call, returning false if the reply matches no pending call
+    fn dispatch(&self, correlation_id: Option<&str>, data: Vec<u8>) -> bool {
+        let pending_calls = self.pending_calls.lock().unwrap();
+        let call = correlation_id.and_then(|correlation_id| pending_calls.get(correlation_id));
+
+        match call {
+            Some(call) => call.send(data).is_ok(),
+            None => {
+                debug!(?correlation_id, "Skipping a reply to no pending call");
+                false
+            }
+        }
+    }
+}
+
+/// RPC call waiting for its replies, unregistered once dropped
+pub struct PendingCall {
+    correlation_id: String,
+    replies: UnboundedReceiver<Vec<u8>>,
+    pending_calls: PendingCalls,
+}
+
+impl PendingCall {
+    /// Next reply to the call, or `None` if the replies of the channel are closed
+    pub async fn next_reply(&mut self) -> Option<Vec<u8>> {
+        self.replies.recv().await
+    }
+}
+
+impl Drop for PendingCall {
+    fn drop(&mut self) {
+        self.pending_calls
+            .lock()
+            .unwrap()
+            .remove(&self.correlation_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn replies_are_dispatched_to_their_call() {
+        let replies = RpcReplies::default();
+        let mut first_call = replies.register_call("first");
+        let mut second_call = replies.register_call("second");
+
+        assert!(replies.dispatch(Some("second"), b"second reply".to_vec()));
+        assert!(replies.dispatch(Some("first"), b"first reply".to_vec()));
+
+        assert_eq!(first_call.next_reply().await.unwrap(), b"first reply");
+        assert_eq!(second_call.next_reply().await.unwrap(), b"second reply");
+    }
+
+    #[test]
+    fn late_and_uncorrelated_replies_are_skipped() {
+        let replies = RpcReplies::default();
+        let timed_out_call = replies.register_call("timed_out");
+        drop(timed_out_call);
+
+        assert!(!replies.dispatch(Some("timed_out"), b"late reply".to_vec()));
+        assert!(!replies.dispatch(None, b"uncorrelated reply".to_vec()));
+        assert!(replies.pending_calls.lock().unwrap().is_empty());
+    }
+}
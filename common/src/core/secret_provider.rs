@@ -0,0 +1,139 @@
+use async_trait::async_trait;
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+use std::time::Duration;
+
+use crate::helper::error_chain_fmt;
+
+/// Prefix of the settings whose value is the reference of a secret in the external secret store,
+/// for ex: `secret:rest_gateway/database#password`
+pub const SECRET_REFERENCE_PREFIX: &str = "secret:";
+
+/// Separator of the path of a secret and of the key of its value, in a secret reference
+const KEY_SEPARATOR: char = '#';
+
+/// External secret store, from which the secret settings of a service are read at startup
+#[async_trait]
+pub trait SecretProviderPort: Send + Sync {
+    /// Gets a secret from its reference, without the `secret:` prefix: `<path>#<key>`
+    async fn get_secret(&self, reference: &str) -> Result<Secret<String>, SecretProviderError>;
+}
+
+/// External secret store of a service, only needed if some of its settings are secret references
+#[derive(Debug, Deserialize, Clone)]
+pub struct SecretStoreSettings {
+    pub vault: VaultSettings,
+}
+
+/// HashiCorp Vault, with a KV (version 2) secrets engine
+#[derive(Debug, Deserialize, Clone)]
+pub struct VaultSettings {
+    /// For ex: `https://vault.internal:8200`
+    pub address: String,
+    /// Set from a file in production: `APP_SECRET_STORE__VAULT__TOKEN_FILE=/run/secrets/vault_token`
+    pub token: Secret<String>,
+    /// Mount path of the KV secrets engine
+    pub mount: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timeout_ms: u64,
+}
+
+/// Reads the secrets from the KV (version 2) secrets engine of a Vault server
+pub struct VaultSecretProvider {
+    settings: VaultSettings,
+    client: reqwest::Client,
+}
+
+impl VaultSecretProvider {
+    pub fn try_new(settings: VaultSettings) -> Result<Self, SecretProviderError> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(settings.timeout_ms))
+            .build()?;
+
+        Ok(Self { settings, client })
+    }
+}
+
+#[derive(Deserialize)]
+struct VaultKvResponse {
+    data: VaultKvData,
+}
+
+#[derive(Deserialize)]
+struct VaultKvData {
+    data: serde_json::Map<String, serde_json::Value>,
+}
+
+#[async_trait]
+impl SecretProviderPort for VaultSecretProvider {
+    #[tracing::instrument(name = "Getting secret from Vault", skip(self))]
+    async fn get_secret(&self, reference: &str) -> Result<Secret<String>, SecretProviderError> {
+        let (path, key) = parse_reference(reference)?;
+
+        let response = self
+            .client
+            .get(format!(
+                "{}/v1/{}/data/{}",
+                self.settings.address.trim_end_matches('/'),
+                self.settings.mount,
+                path
+            ))
+            .header("X-Vault-Token", self.settings.token.expose_secret())
+            .send()
+            .await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(SecretProviderError::SecretNotFound(reference.to_string()));
+        }
+        let response = response
+            .error_for_status()?
+            .json::<VaultKvResponse>()
+            .await?;
+
+        match response.data.data.get(key) {
+            Some(serde_json::Value::String(value)) => Ok(Secret::new(value.clone())),
+            _ => Err(SecretProviderError::SecretNotFound(reference.to_string())),
+        }
+    }
+}
+
+/// Splits a secret reference into the path of the secret and the key of its value
+pub fn parse_reference(reference: &str) -> Result<(&str, &str), SecretProviderError> {
+    reference
+        .split_once(KEY_SEPARATOR)
+        .filter(|(path, key)| !path.is_empty() && !key.is_empty())
+        .ok_or_else(|| SecretProviderError::InvalidReference(reference.to_string()))
+}
+
+#[derive(thiserror::Error)]
+pub enum SecretProviderError {
+    #[error("Invalid secret reference `{0}`, expected `<path>#<key>`")]
+    InvalidReference(String),
+    #[error("Secret `{0}` not found")]
+    SecretNotFound(String),
+    #[error(transparent)]
+    HttpError(#[from] reqwest::Error),
+}
+
+impl std::fmt::Debug for SecretProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references_should_have_a_path_and_a_key() {
+        assert_eq!(
+            parse_reference("rest_gateway/database#password").unwrap(),
+            ("rest_gateway/database", "password")
+        );
+        assert!(parse_reference("rest_gateway/database").is_err());
+        assert!(parse_reference("#password").is_err());
+        assert!(parse_reference("rest_gateway/database#").is_err());
+    }
+}
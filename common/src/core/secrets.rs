@@ -0,0 +1,228 @@
+use aes_gcm::{
+    aead::{Aead, KeyInit, Nonce, OsRng},
+    AeadCore, Aes256Gcm, Key,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::helper::error_chain_fmt;
+
+/// Size of an AES-256 key, in bytes
+const KEY_SIZE: usize = 32;
+/// Size of an AES-GCM nonce, in bytes
+const NONCE_SIZE: usize = 12;
+/// Separator of the parts of an encrypted secret: `<key id>:<hex nonce>:<hex ciphertext>`
+const SEPARATOR: char = ':';
+
+/// Settings of the encryption of the secrets stored by the services, for ex the API keys of the tenants
+///
+/// The keys are secrets: in production, they should be set from environment variables.
+/// For ex: `APP_SECRETS__KEYS__2023_10=...`
+#[derive(Debug, Deserialize, Clone)]
+pub struct SecretsSettings {
+    /// Id of the key encrypting the new secrets
+    pub current_key_id: String,
+    /// Hex encoded 256-bit keys decrypting the stored secrets, by id.
+    /// To rotate the keys, the previous key is kept until all the secrets are encrypted with the new one.
+    #[serde(default)]
+    pub keys: HashMap<String, Secret<String>>,
+}
+
+/// Encrypts and decrypts secrets with AES-256-GCM
+///
+/// An encrypted secret carries the id of its key: secrets encrypted with a previous key can still be decrypted.
+#[derive(Clone)]
+pub struct SecretsCipher {
+    current_key_id: String,
+    ciphers: HashMap<String, Aes256Gcm>,
+}
+
+impl SecretsCipher {
+    pub fn try_new(settings: SecretsSettings) -> Result<Self, SecretsError> {
+        let ciphers = settings
+            .keys
+            .iter()
+            .map(|(key_id, key)| {
+                let key = hex::decode(key.expose_secret())
+                    .ok()
+                    .filter(|key| key.len() == KEY_SIZE)
+                    .ok_or_else(|| SecretsError::InvalidKey(key_id.clone()))?;
+
+                Ok((
+                    key_id.clone(),
+                    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+                ))
+            })
+            .collect::<Result<HashMap<_, _>, SecretsError>>()?;
+
+        if !ciphers.contains_key(&settings.current_key_id) {
+            return Err(SecretsError::UnknownKey(settings.current_key_id));
+        }
+
+        Ok(Self {
+            current_key_id: settings.current_key_id,
+            ciphers,
+        })
+    }
+
+    /// Encrypts a secret with the current key, with a random nonce
+    pub fn encrypt(&self, secret: &Secret<String>) -> Result<String, SecretsError> {
+        let cipher = &self.ciphers[&self.current_key_id];
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+        let ciphertext = cipher
+            .encrypt(&nonce, secret.expose_secret().as_bytes())
+            .map_err(|_| SecretsError::EncryptionError)?;
+
+        Ok(format!(
+            "{}{SEPARATOR}{}{SEPARATOR}{}",
+            self.current_key_id,
+            hex::encode(nonce),
+            hex::encode(ciphertext)
+        ))
+    }
+
+    /// Decrypts a secret encrypted with any of the known keys
+    pub fn decrypt(&self, encrypted: &str) -> Result<Secret<String>, SecretsError> {
+        let mut parts = encrypted.rsplitn(3, SEPARATOR);
+        let (Some(ciphertext), Some(nonce), Some(key_id)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(SecretsError::InvalidSecret);
+        };
+
+        let cipher = self
+            .ciphers
+            .get(key_id)
+            .ok_or_else(|| SecretsError::UnknownKey(key_id.to_string()))?;
+        let nonce = hex::decode(nonce)
+            .ok()
+            .filter(|nonce| nonce.len() == NONCE_SIZE)
+            .ok_or(SecretsError::InvalidSecret)?;
+        let ciphertext = hex::decode(ciphertext).map_err(|_| SecretsError::InvalidSecret)?;
+
+        let secret = cipher
+            .decrypt(Nonce::<Aes256Gcm>::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|_| SecretsError::InvalidSecret)?;
+
+        String::from_utf8(secret)
+            .map(Secret::new)
+            .map_err(|_| SecretsError::InvalidSecret)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum SecretsError {
+    #[error("Invalid secrets key {0}: a hex encoded 256-bit key is expected")]
+    InvalidKey(String),
+    #[error("Unknown secrets key: {0}")]
+    UnknownKey(String),
+    #[error("Failed to encrypt the secret")]
+    EncryptionError,
+    #[error("The encrypted secret is invalid or was tampered with")]
+    InvalidSecret,
+}
+
+impl std::fmt::Debug for SecretsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_2023_10: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+    const KEY_2023_11: &str = "1f1e1d1c1b1a191817161514131211100f0e0d0c0b0a09080706050403020100";
+
+    fn cipher(current_key_id: &str, keys: &[(&str, &str)]) -> SecretsCipher {
+        SecretsCipher::try_new(SecretsSettings {
+            current_key_id: current_key_id.to_string(),
+            keys: keys
+                .iter()
+                .map(|(id, key)| (id.to_string(), Secret::new(key.to_string())))
+                .collect(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn encrypted_secret_is_decrypted() {
+        let cipher = cipher("2023_10", &[("2023_10", KEY_2023_10)]);
+        let secret = Secret::new("sk-api-key".to_string());
+
+        let encrypted = cipher.encrypt(&secret).unwrap();
+
+        assert!(encrypted.starts_with("2023_10:"));
+        assert!(!encrypted.contains("sk-api-key"));
+        assert_ne!(encrypted, cipher.encrypt(&secret).unwrap());
+        assert_eq!(
+            cipher.decrypt(&encrypted).unwrap().expose_secret(),
+            "sk-api-key"
+        );
+    }
+
+    #[test]
+    fn secret_encrypted_with_a_previous_key_is_decrypted_after_rotation() {
+        let previous_cipher = cipher("2023_10", &[("2023_10", KEY_2023_10)]);
+        let encrypted = previous_cipher
+            .encrypt(&Secret::new("sk-api-key".to_string()))
+            .unwrap();
+
+        let cipher = cipher(
+            "2023_11",
+            &[("2023_10", KEY_2023_10), ("2023_11", KEY_2023_11)],
+        );
+
+        assert_eq!(
+            cipher.decrypt(&encrypted).unwrap().expose_secret(),
+            "sk-api-key"
+        );
+    }
+
+    #[test]
+    fn tampered_or_unknown_secret_is_rejected() {
+        let cipher = cipher("2023_10", &[("2023_10", KEY_2023_10)]);
+        let encrypted = cipher
+            .encrypt(&Secret::new("sk-api-key".to_string()))
+            .unwrap();
+        let mut tampered = encrypted.clone();
+        let last = tampered.pop().unwrap();
+        tampered.push(if last == '0' { '1' } else { '0' });
+
+        assert!(matches!(
+            cipher.decrypt(&tampered),
+            Err(SecretsError::InvalidSecret)
+        ));
+        assert!(matches!(
+            cipher.decrypt(&encrypted.replacen("2023_10", "2023_11", 1)),
+            Err(SecretsError::UnknownKey(_))
+        ));
+        assert!(matches!(
+            cipher.decrypt("not-encrypted"),
+            Err(SecretsError::InvalidSecret)
+        ));
+    }
+
+    #[test]
+    fn invalid_or_missing_current_key_is_rejected() {
+        let settings = |keys: &[(&str, &str)]| SecretsSettings {
+            current_key_id: "2023_10".to_string(),
+            keys: keys
+                .iter()
+                .map(|(id, key)| (id.to_string(), Secret::new(key.to_string())))
+                .collect(),
+        };
+
+        assert!(matches!(
+            SecretsCipher::try_new(settings(&[("2023_10", "short")])),
+            Err(SecretsError::InvalidKey(_))
+        ));
+        assert!(matches!(
+            SecretsCipher::try_new(settings(&[("2023_11", KEY_2023_11)])),
+            Err(SecretsError::UnknownKey(_))
+        ));
+    }
+}
@@ -0,0 +1,149 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::{
+    core::rabbitmq_message_repository::{
+        RabbitMQMessageRepository, RabbitMQMessageRepositoryError,
+    },
+    helper::error_chain_fmt,
+};
+
+/// A tenant (business unit) isolated from the other tenants at the broker level
+///
+/// Each tenant has its own exchanges and queues, optionally on its own RabbitMQ virtual host.
+#[derive(Debug, Deserialize, Clone)]
+pub struct TenantSettings {
+    /// Templated in the names of the exchanges and queues of the tenant
+    pub id: String,
+    /// RabbitMQ virtual host of the tenant. If not set, the default virtual host is used
+    pub vhost: Option<String>,
+}
+
+impl TenantSettings {
+    /// Templates an exchange or queue name prefix with the tenant id
+    pub fn name_prefix(&self, prefix: &str) -> String {
+        format!("{}_{}", prefix, self.id)
+    }
+
+    /// URI of the virtual host of the tenant, from the URI of the broker
+    pub fn amqp_uri(&self, broker_uri: &str) -> String {
+        match &self.vhost {
+            // The virtual host is a path segment: "/" needs to be percent-encoded
+            Some(vhost) => format!(
+                "{}/{}",
+                broker_uri,
+                vhost.replace('%', "%25").replace('/', "%2F")
+            ),
+            None => broker_uri.to_string(),
+        }
+    }
+}
+
+/// Templates an exchange or queue name prefix with the tenant id, if any
+pub fn tenant_name_prefix(prefix: &str, tenant: Option<&TenantSettings>) -> String {
+    match tenant {
+        Some(tenant) => tenant.name_prefix(prefix),
+        None => prefix.to_string(),
+    }
+}
+
+/// Message repositories routing the messages to the connection and exchange of each tenant
+///
+/// Like `RabbitMQMessageRepository`, it should be cloned and initialized inside each thread.
+#[derive(Clone)]
+pub struct TenantMessageRepositories {
+    /// Publishes the messages of the users without tenant
+    default: RabbitMQMessageRepository,
+    tenants: HashMap<String, RabbitMQMessageRepository>,
+}
+
+impl TenantMessageRepositories {
+    pub fn new(
+        default: RabbitMQMessageRepository,
+        tenants: HashMap<String, RabbitMQMessageRepository>,
+    ) -> Self {
+        Self { default, tenants }
+    }
+
+    /// Initializes the repository of every tenant
+    pub async fn try_init(self) -> Result<Self, RabbitMQMessageRepositoryError> {
+        let default = self.default.try_init().await?;
+
+        let mut tenants = HashMap::with_capacity(self.tenants.len());
+        for (tenant_id, repository) in self.tenants {
+            tenants.insert(tenant_id, repository.try_init().await?);
+        }
+
+        Ok(Self { default, tenants })
+    }
+
+    pub fn has_tenant(&self, tenant_id: &str) -> bool {
+        self.tenants.contains_key(tenant_id)
+    }
+
+    /// Routes to the repository of a tenant
+    ///
+    /// # Arguments
+    /// * `tenant_id` - The tenant of the user, `None` for the users without tenant
+    pub fn route(
+        &self,
+        tenant_id: Option<&str>,
+    ) -> Result<&RabbitMQMessageRepository, TenancyError> {
+        match tenant_id {
+            Some(tenant_id) => self
+                .tenants
+                .get(tenant_id)
+                .ok_or_else(|| TenancyError::UnknownTenant(tenant_id.to_string())),
+            None => Ok(&self.default),
+        }
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum TenancyError {
+    #[error("Unknown tenant: {0}")]
+    UnknownTenant(String),
+}
+
+impl std::fmt::Debug for TenancyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tenant_name_prefix_is_templated_with_the_tenant_id() {
+        let tenant = TenantSettings {
+            id: "finance".to_string(),
+            vhost: None,
+        };
+
+        assert_eq!(tenant_name_prefix("prod", Some(&tenant)), "prod_finance");
+        assert_eq!(tenant_name_prefix("prod", None), "prod");
+    }
+
+    #[test]
+    fn tenant_amqp_uri_targets_the_encoded_vhost() {
+        let tenant = TenantSettings {
+            id: "finance".to_string(),
+            vhost: Some("/finance".to_string()),
+        };
+        let tenant_without_vhost = TenantSettings {
+            id: "legal".to_string(),
+            vhost: None,
+        };
+
+        assert_eq!(
+            tenant.amqp_uri("amqp://rabbitmq:5672"),
+            "amqp://rabbitmq:5672/%2Ffinance"
+        );
+        assert_eq!(
+            tenant_without_vhost.amqp_uri("amqp://rabbitmq:5672"),
+            "amqp://rabbitmq:5672"
+        );
+    }
+}
@@ -0,0 +1,286 @@
+use regex::{Regex, RegexBuilder};
+use std::{borrow::Cow, collections::HashMap};
+
+use crate::{
+    dtos::normalization_rules::{NormalizationRuleDto, NormalizationRuleKindDto},
+    helper::error_chain_fmt,
+};
+
+/// Size limit of the compiled regular expressions of the rules of a tenant, for its rules not to exhaust the memory of the services
+const RULES_SIZE_LIMIT: usize = 1 << 20;
+
+/// Normalizes the texts of a tenant with its rules: its extracted contents, and its search queries
+///
+/// The stop patterns are removed first. The abbreviations and the product names are then replaced in a single pass,
+/// as whole words ignoring the case: a replacing term is not replaced again.
+#[derive(Debug, Default)]
+pub struct TextNormalizer {
+    stop_patterns: Option<Regex>,
+    terms: Option<Regex>,
+    /// Replacing term of each replaced term, in lowercase
+    replacements: HashMap<String, String>,
+}
+
+impl TextNormalizer {
+    /// Compiles the rules of a tenant, a later rule replacing the same term as an earlier one
+    pub fn try_new(rules: &[NormalizationRuleDto]) -> Result<Self, TextNormalizerError> {
+        let mut stop_patterns = vec![];
+        let mut replacements = HashMap::new();
+
+        for rule in rules {
+            match rule.kind {
+                NormalizationRuleKindDto::StopPattern => {
+                    // Compiled on its own, for the error to point at the invalid pattern
+                    build_regex(&rule.pattern, false).map_err(|error| {
+                        TextNormalizerError::InvalidStopPattern(rule.pattern.clone(), error)
+                    })?;
+                    stop_patterns.push(format!("(?:{})", rule.pattern));
+                }
+                NormalizationRuleKindDto::Abbreviation
+                | NormalizationRuleKindDto::CanonicalName => {
+                    let term = rule.pattern.trim();
+                    if term.is_empty() {
+                        return Err(TextNormalizerError::EmptyTerm);
+                    }
+                    let replacement = rule
+                        .replacement
+                        .as_deref()
+                        .map(str::trim)
+                        .filter(|replacement| !replacement.is_empty())
+                        .ok_or_else(|| TextNormalizerError::MissingReplacement(term.to_string()))?;
+
+                    replacements.insert(term.to_lowercase(), replacement.to_string());
+                }
+            }
+        }
+
+        let stop_patterns = if stop_patterns.is_empty() {
+            None
+        } else {
+            Some(
+                build_regex(&stop_patterns.join("|"), false)
+                    .map_err(TextNormalizerError::TooLarge)?,
+            )
+        };
+
+        // The first matching alternative is kept: the longest terms first, for `k8s cluster` to win over `k8s`
+        let mut terms = replacements.keys().collect::<Vec<_>>();
+        terms.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+        let terms = if terms.is_empty() {
+            None
+        } else {
+            let pattern = terms
+                .into_iter()
+                .map(|term| whole_word_pattern(term))
+                .collect::<Vec<_>>()
+                .join("|");
+            Some(build_regex(&pattern, true).map_err(TextNormalizerError::TooLarge)?)
+        };
+
+        Ok(Self {
+            stop_patterns,
+            terms,
+            replacements,
+        })
+    }
+
+    /// Checks if the normalizer has no rule, leaving the texts unchanged
+    pub fn is_empty(&self) -> bool {
+        self.stop_patterns.is_none() && self.terms.is_none()
+    }
+
+    pub fn normalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+
+        if let Some(stop_patterns) = &self.stop_patterns {
+            if stop_patterns.is_match(&text) {
+                // Removing a pattern in the middle of a sentence leaves several spaces
+                let removed = stop_patterns.replace_all(&text, " ");
+                text = Cow::Owned(removed.split_whitespace().collect::<Vec<_>>().join(" "));
+            }
+        }
+
+        if let Some(terms) = &self.terms {
+            if let Cow::Owned(replaced) = terms.replace_all(&text, |captures: &regex::Captures| {
+                let term = &captures[0];
+                self.replacements
+                    .get(&term.to_lowercase())
+                    .cloned()
+                    .unwrap_or_else(|| term.to_string())
+            }) {
+                text = Cow::Owned(replaced);
+            }
+        }
+
+        text
+    }
+}
+
+/// Matches a term as a whole word: not inside a longer word
+///
+/// A term starting or ending with a punctuation (`C++`, `.NET`) can not be delimited by a word boundary on this side.
+fn whole_word_pattern(term: &str) -> String {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    let start = if term.starts_with(is_word_char) {
+        r"\b"
+    } else {
+        ""
+    };
+    let end = if term.ends_with(is_word_char) {
+        r"\b"
+    } else {
+        ""
+    };
+
+    format!("{}{}{}", start, regex::escape(term), end)
+}
+
+fn build_regex(pattern: &str, case_insensitive: bool) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(case_insensitive)
+        .size_limit(RULES_SIZE_LIMIT)
+        .build()
+}
+
+#[derive(thiserror::Error)]
+pub enum TextNormalizerError {
+    #[error("Invalid stop pattern {0}: {1}")]
+    InvalidStopPattern(String, regex::Error),
+    #[error("The replaced term should not be empty")]
+    EmptyTerm,
+    #[error("The term {0} should have a replacement")]
+    MissingReplacement(String),
+    #[error("The rules are too large to be compiled: {0}")]
+    TooLarge(regex::Error),
+}
+
+impl std::fmt::Debug for TextNormalizerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(
+        kind: NormalizationRuleKindDto,
+        pattern: &str,
+        replacement: Option<&str>,
+    ) -> NormalizationRuleDto {
+        NormalizationRuleDto {
+            kind,
+            pattern: pattern.to_string(),
+            replacement: replacement.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn terms_are_replaced_as_whole_words_ignoring_the_case() {
+        let normalizer = TextNormalizer::try_new(&[
+            rule(
+                NormalizationRuleKindDto::Abbreviation,
+                "k8s",
+                Some("Kubernetes"),
+            ),
+            rule(
+                NormalizationRuleKindDto::CanonicalName,
+                "postgres",
+                Some("PostgreSQL"),
+            ),
+            rule(NormalizationRuleKindDto::CanonicalName, "C++", Some("CPP")),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            normalizer.normalize("Deploying Postgres on K8S, not on k8sx, with C++."),
+            "Deploying PostgreSQL on Kubernetes, not on k8sx, with CPP."
+        );
+    }
+
+    #[test]
+    fn longest_term_is_replaced_first_and_replacements_are_not_replaced_again() {
+        let normalizer = TextNormalizer::try_new(&[
+            rule(
+                NormalizationRuleKindDto::Abbreviation,
+                "pg",
+                Some("PostgreSQL"),
+            ),
+            rule(
+                NormalizationRuleKindDto::Abbreviation,
+                "pg dump",
+                Some("pg_dump"),
+            ),
+            rule(
+                NormalizationRuleKindDto::CanonicalName,
+                "PostgreSQL",
+                Some("Postgres"),
+            ),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            normalizer.normalize("pg dump of pg"),
+            "pg_dump of PostgreSQL"
+        );
+    }
+
+    #[test]
+    fn stop_patterns_are_removed() {
+        let normalizer = TextNormalizer::try_new(&[
+            rule(
+                NormalizationRuleKindDto::StopPattern,
+                r"(?i)confidential - internal use only",
+                None,
+            ),
+            rule(
+                NormalizationRuleKindDto::StopPattern,
+                r"Page \d+ of \d+",
+                None,
+            ),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            normalizer
+                .normalize("CONFIDENTIAL - internal use only The rollout Page 3 of 12 starts now"),
+            "The rollout starts now"
+        );
+    }
+
+    #[test]
+    fn text_is_unchanged_without_rules() {
+        let normalizer = TextNormalizer::try_new(&[]).unwrap();
+
+        assert!(normalizer.is_empty());
+        assert!(matches!(
+            normalizer.normalize("Nothing  to   normalize"),
+            Cow::Borrowed("Nothing  to   normalize")
+        ));
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        assert!(matches!(
+            TextNormalizer::try_new(&[rule(
+                NormalizationRuleKindDto::StopPattern,
+                "(unclosed",
+                None
+            )]),
+            Err(TextNormalizerError::InvalidStopPattern(..))
+        ));
+        assert!(matches!(
+            TextNormalizer::try_new(&[rule(NormalizationRuleKindDto::Abbreviation, "k8s", None)]),
+            Err(TextNormalizerError::MissingReplacement(_))
+        ));
+        assert!(matches!(
+            TextNormalizer::try_new(&[rule(
+                NormalizationRuleKindDto::CanonicalName,
+                " ",
+                Some("x")
+            )]),
+            Err(TextNormalizerError::EmptyTerm)
+        ));
+    }
+}
@@ -0,0 +1,102 @@
+use lapin::{
+    types::{AMQPValue, FieldTable, ShortString},
+    BasicProperties,
+};
+use opentelemetry::{
+    global,
+    propagation::{Extractor, Injector},
+};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Writes the W3C trace context (`traceparent` and `tracestate`) of the current span in the headers of a message
+///
+/// Nothing is written when the spans are not exported (see `telemetry::get_tracing_subscriber`).
+pub fn inject_trace_context(headers: &mut FieldTable) {
+    let context = Span::current().context();
+
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeadersInjector(headers))
+    });
+}
+
+/// Headers of a message with only the W3C trace context of the current span
+pub fn trace_context_headers() -> FieldTable {
+    let mut headers = FieldTable::default();
+    inject_trace_context(&mut headers);
+    headers
+}
+
+/// Sets the span of the message published with a W3C trace context as parent of the current span
+///
+/// To be called before creating any child span, for the spans handling a message to be part of the trace
+/// of its publisher: the gateway request, the extraction job...
+pub fn continue_trace_from(properties: &BasicProperties) {
+    let Some(headers) = properties.headers() else {
+        return;
+    };
+
+    let context = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeadersExtractor(headers))
+    });
+    Span::current().set_parent(context);
+}
+
+struct HeadersInjector<'a>(&'a mut FieldTable);
+
+impl Injector for HeadersInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        self.0
+            .insert(ShortString::from(key), AMQPValue::LongString(value.into()));
+    }
+}
+
+struct HeadersExtractor<'a>(&'a FieldTable);
+
+impl Extractor for HeadersExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .inner()
+            .get(&ShortString::from(key))
+            .and_then(|value| value.as_long_string())
+            .and_then(|value| std::str::from_utf8(value.as_bytes()).ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.inner().keys().map(|key| key.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::propagation::TextMapPropagator;
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+
+    const TRACEPARENT: &str = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+
+    #[test]
+    fn trace_context_is_extracted_from_the_headers_it_was_injected_in() {
+        let propagator = TraceContextPropagator::new();
+        let mut carrier = std::collections::HashMap::new();
+        carrier.insert("traceparent".to_string(), TRACEPARENT.to_string());
+        let context = propagator.extract(&carrier);
+
+        let mut headers = FieldTable::default();
+        propagator.inject_context(&context, &mut HeadersInjector(&mut headers));
+
+        assert_eq!(
+            HeadersExtractor(&headers).get("traceparent"),
+            Some(TRACEPARENT)
+        );
+        let mut extracted = std::collections::HashMap::new();
+        propagator.inject_context(
+            &propagator.extract(&HeadersExtractor(&headers)),
+            &mut extracted,
+        );
+        assert_eq!(
+            extracted.get("traceparent").map(String::as_str),
+            Some(TRACEPARENT)
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::helper::error_chain_fmt;
+
+/// Requests the deletion of all the contents extracted from a source
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DeleteContentDto {
+    pub source_meta_id: Uuid,
+    /// Shard of the full-text index the contents of the source were saved to
+    #[serde(default)]
+    pub fulltext_shard: u32,
+}
+
+impl DeleteContentDto {
+    pub fn try_parsing(data: &[u8]) -> Result<Self, DeleteContentDtoError> {
+        let data = std::str::from_utf8(data)?;
+        let my_data = serde_json::from_str(data)
+            .map_err(|e| DeleteContentDtoError::InvalidJsonData(e, data.to_string()))?;
+
+        Ok(my_data)
+    }
+}
+
+/// Requests the deletion of some of the contents extracted from a source, after an incremental extraction
+///
+/// Published on its own routing key: a consumer not knowing it never takes it for the deletion of all the contents.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PruneContentDto {
+    pub source_meta_id: Uuid,
+    /// Shard of the full-text index the contents of the source were saved to
+    #[serde(default)]
+    pub fulltext_shard: u32,
+    /// Contents of the source no longer extracted from it
+    pub content_ids: Vec<Uuid>,
+}
+
+impl PruneContentDto {
+    pub fn try_parsing(data: &[u8]) -> Result<Self, DeleteContentDtoError> {
+        let data = std::str::from_utf8(data)?;
+        let my_data = serde_json::from_str(data)
+            .map_err(|e| DeleteContentDtoError::InvalidJsonData(e, data.to_string()))?;
+
+        Ok(my_data)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum DeleteContentDtoError {
+    #[error("Data could not be converted from utf8 u8 vector to string")]
+    InvalidStringData(#[from] std::str::Utf8Error),
+
+    #[error("Data did not represent a valid JSON object: {0}. Data: {1}")]
+    InvalidJsonData(serde_json::Error, String),
+}
+
+impl std::fmt::Debug for DeleteContentDtoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
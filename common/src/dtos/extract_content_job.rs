@@ -1,11 +1,86 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::helper::error_chain_fmt;
+use crate::{
+    constants::routing_keys::{
+        CONTENT_EXTRACTED_FAST_ROUTING_KEY, CONTENT_EXTRACTED_ROUTING_KEY,
+        EXTRACT_CONTENT_TEXT_FAST_ROUTING_KEY, EXTRACT_CONTENT_TEXT_ROUTING_KEY,
+    },
+    dtos::templates::message_envelope::{MessagePayload, SchemaVersion},
+    helper::error_chain_fmt,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SourceTypeDto {
     Epub,
+    Srt,
+    Vtt,
+    Ipynb,
+    Code,
+    Latex,
+    /// A saved web page
+    Html,
+    /// A Word document
+    Docx,
+    /// An OpenDocument text
+    Odt,
+    /// Structured data: a CSV file with a header row
+    Csv,
+    /// Structured data: a JSON Lines file, one object per line
+    Jsonl,
+    /// A zip archive: a LaTeX project or a source code repository
+    Archive,
+}
+
+/// Lane through which a source is ingested
+///
+/// The small sources, like web clips, go through the fast lane: dedicated queues and consumers with a low prefetch,
+/// so that they are not stuck behind the contents of the books going through the bulk lane.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IngestionLaneDto {
+    #[default]
+    Bulk,
+    Fast,
+}
+
+impl IngestionLaneDto {
+    /// Routing key of the extraction jobs of the lane
+    pub fn extract_content_routing_key(&self) -> &'static str {
+        match self {
+            IngestionLaneDto::Bulk => EXTRACT_CONTENT_TEXT_ROUTING_KEY,
+            IngestionLaneDto::Fast => EXTRACT_CONTENT_TEXT_FAST_ROUTING_KEY,
+        }
+    }
+
+    /// Routing key of the contents extracted from the sources of the lane
+    pub fn content_extracted_routing_key(&self) -> &'static str {
+        match self {
+            IngestionLaneDto::Bulk => CONTENT_EXTRACTED_ROUTING_KEY,
+            IngestionLaneDto::Fast => CONTENT_EXTRACTED_FAST_ROUTING_KEY,
+        }
+    }
+}
+
+/// How the text of a source is split into extracted contents
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkSplittingDto {
+    /// A content ends as soon as it reaches its number of words, even in the middle of a sentence
+    #[default]
+    Words,
+    /// Once a content reached its number of words, it ends with the current sentence, within an upper bound of words
+    Sentences,
+}
+
+/// Mapping of the columns of a structured source (CSV or JSONL) to its extracted contents
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnMappingDto {
+    /// Columns whose values are the searchable content of a row, in this order.
+    /// The other columns are kept as metadata fields. Without columns, all the columns are the content.
+    #[serde(default)]
+    pub content_columns: Vec<String>,
 }
 
 /// Represents a request for a job to extract content from a source file
@@ -22,6 +97,55 @@ pub struct ExtractContentJobDto {
 
     /// Initial name of the source
     pub source_initial_name: String,
+
+    /// Id of the user owning the source, added to the metadata of the extracted contents.
+    /// Jobs published before the field existed have no user: their contents can not be found by a search.
+    #[serde(default)]
+    pub user_id: Option<Uuid>,
+    /// Tenant of the user owning the source, sharing the in-flight jobs of the workers with the other tenants.
+    /// `None` for the users without tenant, and the jobs published before the field existed.
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    /// Shard of the full-text index the extracted contents are saved to
+    #[serde(default)]
+    pub fulltext_shard: u32,
+    /// Hex-encoded SHA-256 hash of the uploaded source file, to check its download
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// Lane of the source, through which its extracted contents are published
+    #[serde(default)]
+    pub lane: IngestionLaneDto,
+    /// How the text of the source is split into extracted contents
+    #[serde(default)]
+    pub chunk_splitting: ChunkSplittingDto,
+    /// Mapping of the columns of a structured source, ignored for the other sources
+    #[serde(default)]
+    pub column_mapping: ColumnMappingDto,
+    /// Only the contents changed since the previous extraction of the source are published,
+    /// the contents no longer extracted being pruned. All the contents are published by default
+    #[serde(default)]
+    pub incremental: bool,
+    /// Date the source was added, added to the metadata of the extracted contents to sort them by recency
+    #[serde(default)]
+    pub source_added_at: Option<DateTime<Utc>>,
+    /// The emails, phone numbers and national ids of the extracted contents are replaced by placeholders
+    /// before they are published, see `PII_REDACTED_METADATA_KEY`
+    #[serde(default)]
+    pub redact_pii: bool,
+}
+
+/// Path, in the object storage, of the manifest of the contents extracted from a source
+///
+/// Written by the worker after each extraction, next to the source files of the user.
+pub fn chunk_manifest_path(user_id: Uuid, source_meta_id: Uuid) -> String {
+    format!("{}/{}.chunks.json", user_id, source_meta_id)
+}
+
+/// Path, in the object storage, of the fingerprints of the contents extracted from all the sources of a user
+///
+/// Written by the worker after each extraction detecting the near duplicates, next to the source files of the user.
+pub fn near_duplicate_index_path(user_id: Uuid) -> String {
+    format!("{}/near_duplicates.json", user_id)
 }
 
 impl ExtractContentJobDto {
@@ -34,6 +158,11 @@ impl ExtractContentJobDto {
     }
 }
 
+/// Sent in a `MessageEnvelope`. Its fields added before the envelopes all have a default value.
+impl MessagePayload for ExtractContentJobDto {
+    const SCHEMA_VERSION: SchemaVersion = SchemaVersion::new(1, 0);
+}
+
 #[derive(thiserror::Error)]
 pub enum ExtractContentJobDtoError {
     #[error("Data could not be converted from utf8 u8 vector to string")]
@@ -2,25 +2,216 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use uuid::Uuid;
 
-use crate::helper::error_chain_fmt;
+use crate::{
+    dtos::templates::message_envelope::{MessagePayload, SchemaVersion},
+    helper::error_chain_fmt,
+};
 
+/// Key of the id of the user owning the source, in the metadata of an extracted content
+///
+/// The search services only return the contents of the user making the search.
+pub const USER_ID_METADATA_KEY: &str = "user_id";
+
+/// Key of the ISO 639-1 code of the language of a content, in the metadata of an extracted content
+///
+/// Only set when the language could be detected. The searches can be filtered by language.
+pub const LANGUAGE_METADATA_KEY: &str = "language";
+
+/// Key of the initial name of the source, in the metadata of an extracted content
+///
+/// The searches can sort the contents by the name of their source.
+pub const SOURCE_NAME_METADATA_KEY: &str = "source_name";
+
+/// Key of the date the source was added, as a Unix timestamp in milliseconds, in the metadata of an extracted content
+///
+/// Only set for the sources extracted since it is part of the extraction jobs. The searches can sort the contents by recency.
+pub const SOURCE_ADDED_AT_METADATA_KEY: &str = "source_added_at";
+
+/// Whether a value looks like an ISO 639-1 language code, for ex `en`
+///
+/// Checked before filtering on a language given by a client.
+pub fn is_language_code(value: &str) -> bool {
+    value.len() == 2 && value.chars().all(|c| c.is_ascii_lowercase())
+}
+
+/// Whether a value can be the name of a field of a structured source filtered by a search:
+/// ASCII letters, digits and `_`
+///
+/// Checked before filtering on the fields given by a client.
+pub fn is_structured_field_name(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 64
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Metadata attributes of the chapter of a content, per reader: the id of an EPUB chapter, the title of a LaTeX chapter
+pub const CHAPTER_METADATA_ATTRIBUTES: [&str; 2] = ["epub.chapter_id", "latex.chapter"];
+
+/// Chapter of a content from its metadata, whatever the reader of its source, if any
+pub fn metadata_chapter(metadata: &JsonValue) -> Option<&str> {
+    CHAPTER_METADATA_ATTRIBUTES.iter().find_map(|attribute| {
+        metadata
+            .pointer(&format!("/{}", attribute.replace('.', "/")))
+            .and_then(JsonValue::as_str)
+    })
+}
+
+/// Whether a value can be the chapter filtered by a search: 1 to 256 characters, without control characters
+///
+/// Checked before filtering on a chapter given by a client.
+pub fn is_chapter_filter(value: &str) -> bool {
+    !value.is_empty() && value.chars().count() <= 256 && !value.chars().any(char::is_control)
+}
+
+/// Key of the keywords of a content, in the metadata of an extracted content
+///
+/// Lowercase phrases of the content, from the most relevant. The searches can be filtered by keyword.
+pub const KEYWORDS_METADATA_KEY: &str = "keywords";
+
+/// Key of the named entities of a content, in the metadata of an extracted content
+///
+/// Only set when the entities of the contents are recognized, see `ENTITY_METADATA_ATTRIBUTES`.
+pub const ENTITIES_METADATA_KEY: &str = "entities";
+
+/// Metadata attributes of the named entities of a content, per kind: people, places and organizations
+///
+/// The searches can be filtered by entity, whatever its kind.
+pub const ENTITY_METADATA_ATTRIBUTES: [&str; 3] = [
+    "entities.people",
+    "entities.places",
+    "entities.organizations",
+];
+
+/// Keywords of a content from its metadata
+pub fn metadata_keywords(metadata: &JsonValue) -> impl Iterator<Item = &str> {
+    metadata_strings(metadata, KEYWORDS_METADATA_KEY)
+}
+
+/// Named entities of a content from its metadata, whatever their kind
+pub fn metadata_entities(metadata: &JsonValue) -> impl Iterator<Item = &str> {
+    ENTITY_METADATA_ATTRIBUTES
+        .iter()
+        .flat_map(move |attribute| metadata_strings(metadata, attribute))
+}
+
+/// Strings of an array attribute of the metadata of a content, nested with `.`
+fn metadata_strings<'a>(metadata: &'a JsonValue, attribute: &str) -> impl Iterator<Item = &'a str> {
+    metadata
+        .pointer(&format!("/{}", attribute.replace('.', "/")))
+        .and_then(JsonValue::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(JsonValue::as_str)
+}
+
+/// Whether a value can be the keyword or the named entity filtered by a search, see `is_chapter_filter`
+pub fn is_keyword_or_entity_filter(value: &str) -> bool {
+    is_chapter_filter(value)
+}
+
+/// Key of the id of an already indexed content of the user nearly identical to a content, in the metadata of an extracted content
+///
+/// Only set when the near duplicates are detected by the content ingestion worker.
+pub const NEAR_DUPLICATE_OF_METADATA_KEY: &str = "near_duplicate_of";
+
+/// Key of whether personal data was redacted from a content, in the metadata of an extracted content
+///
+/// Only set for the sources uploaded with the redaction of their personal data: the content is published redacted.
+pub const PII_REDACTED_METADATA_KEY: &str = "pii_redacted";
+
+/// Contract of the `content_extracted` messages
+///
+/// Versions:
+/// 1. Initial contract (no `version` field)
+/// 2. Adds `source_meta_id`
+/// 3. Adds `fulltext_shard`
+/// 4. Adds `provenance`, from which the id is derived
+///
+/// A new version can only add fields with a default value: a consumer accepts messages of its own
+/// version and of the adjacent versions, so services can be deployed one after the other.
+/// Sent in a `MessageEnvelope`, whose minor schema version is the version of the contract.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ExtractedContentDto {
+    /// Version of the contract the message was published with
+    #[serde(default = "ExtractedContentDto::initial_version")]
+    pub version: u16,
+    /// Derived from the provenance of the content when known: the same content extracted again gets the same id
     pub id: Uuid,
     pub metadata: JsonValue,
     pub content: String,
+    /// The content is only meant for full-text search, and should not be embedded.
+    /// For ex: code cells of a notebook.
+    #[serde(default)]
+    pub skip_embedding: bool,
+    /// The content is source code: it can be embedded with a code-specific model
+    #[serde(default)]
+    pub is_code: bool,
+    /// Source from which the content was extracted, to delete the contents of a source
+    #[serde(default)]
+    pub source_meta_id: Option<Uuid>,
+    /// Shard of the full-text index the content is saved to, chosen by the gateway for its source
+    #[serde(default)]
+    pub fulltext_shard: u32,
+    /// Where the content was read in its source, unknown for the contents of the older workers
+    #[serde(default)]
+    pub provenance: Option<ChunkProvenanceDto>,
+}
+
+/// Where a content was read in its source
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ChunkProvenanceDto {
+    /// Section of the source the content was read from, for ex a chapter or a notebook cell:
+    /// the hash of the metadata of the section, with the occurrence of sections with the same metadata
+    pub section: String,
+    /// Offset of the content in its section, in characters
+    pub offset: u64,
+}
+
+impl ChunkProvenanceDto {
+    /// Deterministic id of the content read from this provenance in a source
+    ///
+    /// A content extracted again from the same section and offset gets the same id: it replaces
+    /// the previous content in the indexes, and the links to it stay valid.
+    pub fn chunk_id(&self, source_meta_id: Uuid) -> Uuid {
+        Uuid::new_v5(
+            &source_meta_id,
+            format!("{}:{}", self.section, self.offset).as_bytes(),
+        )
+    }
 }
 
 impl ExtractedContentDto {
+    /// Version of the contract published by this version of the services
+    pub const CURRENT_VERSION: u16 = 4;
+
+    fn initial_version() -> u16 {
+        1
+    }
+
+    /// Whether a message published with a given version of the contract can be consumed
+    pub fn is_compatible_version(version: u16) -> bool {
+        version.abs_diff(Self::CURRENT_VERSION) <= 1
+    }
+
     pub fn try_parsing(data: &[u8]) -> Result<Self, ExtractedContentDtoError> {
         let data = std::str::from_utf8(data)?;
-        let my_data = serde_json::from_str(data)
+        let my_data: Self = serde_json::from_str(data)
             .map_err(|e| ExtractedContentDtoError::InvalidJsonData(e, data.to_string()))?;
 
+        if !Self::is_compatible_version(my_data.version) {
+            return Err(ExtractedContentDtoError::IncompatibleVersion(
+                my_data.version,
+            ));
+        }
+
         Ok(my_data)
     }
 }
 
+impl MessagePayload for ExtractedContentDto {
+    const SCHEMA_VERSION: SchemaVersion = SchemaVersion::new(1, Self::CURRENT_VERSION);
+}
+
 #[derive(thiserror::Error)]
 pub enum ExtractedContentDtoError {
     #[error("Data could not be converted from utf8 u8 vector to string")]
@@ -28,6 +219,12 @@ pub enum ExtractedContentDtoError {
 
     #[error("Data did not represent a valid JSON object: {0}. Data: {1}")]
     InvalidJsonData(serde_json::Error, String),
+
+    #[error(
+        "Incompatible contract version {0}, expected a version adjacent to {}",
+        ExtractedContentDto::CURRENT_VERSION
+    )]
+    IncompatibleVersion(u16),
 }
 
 impl std::fmt::Debug for ExtractedContentDtoError {
@@ -35,3 +232,151 @@ impl std::fmt::Debug for ExtractedContentDtoError {
         error_chain_fmt(self, f)
     }
 }
+
+/// Compatibility between adjacent versions of the services
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Published by the previous version of the services, without the provenance of the content
+    const VERSION_3_MESSAGE: &str = r#"{
+        "version": 3,
+        "id": "a4d6e3e4-1cb4-4bd6-a2a5-3e8e3bd0c6b9",
+        "metadata": { "chapter": "1" },
+        "content": "It was a bright cold day in April",
+        "skip_embedding": false,
+        "is_code": false,
+        "source_meta_id": "0b1a8f6e-8b0e-4c3a-9c5e-2f1d4f0c2b7a",
+        "fulltext_shard": 2
+    }"#;
+
+    /// Published by the next version of the services, with an additional field
+    const VERSION_5_MESSAGE: &str = r#"{
+        "version": 5,
+        "id": "a4d6e3e4-1cb4-4bd6-a2a5-3e8e3bd0c6b9",
+        "metadata": { "chapter": "1" },
+        "content": "It was a bright cold day in April",
+        "skip_embedding": false,
+        "is_code": false,
+        "source_meta_id": "0b1a8f6e-8b0e-4c3a-9c5e-2f1d4f0c2b7a",
+        "fulltext_shard": 2,
+        "provenance": { "section": "5f2c:1", "offset": 0 },
+        "language": "en"
+    }"#;
+
+    #[test]
+    fn message_from_previous_version_is_parsed() {
+        let dto = ExtractedContentDto::try_parsing(VERSION_3_MESSAGE.as_bytes()).unwrap();
+
+        assert_eq!(dto.version, 3);
+        assert_eq!(dto.content, "It was a bright cold day in April");
+        assert_eq!(dto.fulltext_shard, 2);
+        assert_eq!(dto.provenance, None);
+    }
+
+    #[test]
+    fn message_from_next_version_is_parsed_ignoring_its_new_fields() {
+        let dto = ExtractedContentDto::try_parsing(VERSION_5_MESSAGE.as_bytes()).unwrap();
+
+        assert_eq!(dto.version, 5);
+        assert!(dto.source_meta_id.is_some());
+        assert_eq!(
+            dto.provenance,
+            Some(ChunkProvenanceDto {
+                section: "5f2c:1".to_string(),
+                offset: 0
+            })
+        );
+    }
+
+    #[test]
+    fn message_from_current_version_can_be_parsed_by_previous_version() {
+        let dto = ExtractedContentDto {
+            version: ExtractedContentDto::CURRENT_VERSION,
+            id: Uuid::new_v4(),
+            metadata: JsonValue::Null,
+            content: "It was a bright cold day in April".to_string(),
+            skip_embedding: false,
+            is_code: false,
+            source_meta_id: Some(Uuid::new_v4()),
+            fulltext_shard: 1,
+            provenance: None,
+        };
+        let message = serde_json::to_value(&dto).unwrap();
+
+        // Fields known by the version 3 of the contract
+        for field in [
+            "id",
+            "metadata",
+            "content",
+            "skip_embedding",
+            "is_code",
+            "source_meta_id",
+            "fulltext_shard",
+        ] {
+            assert!(message.get(field).is_some(), "missing field {}", field);
+        }
+    }
+
+    #[test]
+    fn message_from_non_adjacent_version_is_rejected() {
+        let message = VERSION_5_MESSAGE.replace(r#""version": 5"#, r#""version": 6"#);
+
+        assert!(matches!(
+            ExtractedContentDto::try_parsing(message.as_bytes()),
+            Err(ExtractedContentDtoError::IncompatibleVersion(6))
+        ));
+    }
+
+    #[test]
+    fn chapter_filter_is_a_short_printable_value() {
+        assert!(is_chapter_filter("Chapter 1: \"Beginnings\""));
+        assert!(!is_chapter_filter(""));
+        assert!(!is_chapter_filter("Chapter\n1"));
+        assert!(!is_chapter_filter(&"a".repeat(257)));
+    }
+
+    #[test]
+    fn keywords_and_entities_are_read_from_the_metadata() {
+        let metadata = serde_json::json!({
+            "keywords": ["white whale", "harpoon"],
+            "entities": { "people": ["Ahab"], "places": ["Nantucket"], "organizations": [] }
+        });
+
+        assert_eq!(
+            metadata_keywords(&metadata).collect::<Vec<_>>(),
+            vec!["white whale", "harpoon"]
+        );
+        assert_eq!(
+            metadata_entities(&metadata).collect::<Vec<_>>(),
+            vec!["Ahab", "Nantucket"]
+        );
+        assert_eq!(metadata_entities(&serde_json::json!({})).count(), 0);
+    }
+
+    #[test]
+    fn chunk_id_is_stable_for_a_provenance_in_a_source() {
+        let source_meta_id = Uuid::new_v4();
+        let provenance = ChunkProvenanceDto {
+            section: "5f2c:1".to_string(),
+            offset: 512,
+        };
+        let next_offset = ChunkProvenanceDto {
+            offset: 1024,
+            ..provenance.clone()
+        };
+
+        assert_eq!(
+            provenance.chunk_id(source_meta_id),
+            provenance.chunk_id(source_meta_id)
+        );
+        assert_ne!(
+            provenance.chunk_id(source_meta_id),
+            next_offset.chunk_id(source_meta_id)
+        );
+        assert_ne!(
+            provenance.chunk_id(source_meta_id),
+            provenance.chunk_id(Uuid::new_v4())
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::helper::error_chain_fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtractionStatusDto {
+    InProgress,
+    Completed,
+    Failed,
+}
+
+/// Represents the progress of a job extracting the contents of a source file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExtractionProgressDto {
+    /// Id of the source meta associated to the file the job is working on
+    pub source_meta_id: Uuid,
+
+    pub status: ExtractionStatusDto,
+
+    /// Index of the next chunk of content to extract: the number of chunks extracted so far
+    pub chunk_index: u64,
+
+    /// Estimated total number of chunks of the source file. Exact once the extraction is completed.
+    pub total_estimated_chunks: u64,
+
+    /// Number of bytes of text extracted so far
+    pub bytes_processed: u64,
+}
+
+impl ExtractionProgressDto {
+    pub fn try_parsing(data: &[u8]) -> Result<Self, ExtractionProgressDtoError> {
+        let data = std::str::from_utf8(data)?;
+        let my_data = serde_json::from_str(data)
+            .map_err(|e| ExtractionProgressDtoError::InvalidJsonData(e, data.to_string()))?;
+
+        Ok(my_data)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum ExtractionProgressDtoError {
+    #[error("Data could not be converted from utf8 u8 vector to string")]
+    InvalidStringData(#[from] std::str::Utf8Error),
+
+    #[error("Data did not represent a valid JSON object: {0}. Data: {1}")]
+    InvalidJsonData(serde_json::Error, String),
+}
+
+impl std::fmt::Debug for ExtractionProgressDtoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
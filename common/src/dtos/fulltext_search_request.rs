@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use uuid::Uuid;
 
 use crate::helper::error_chain_fmt;
 
@@ -8,6 +10,49 @@ pub struct FulltextSearchRequestDto {
     pub metadata: JsonValue,
     pub query: String,
     pub limit: Option<usize>,
+    /// Only the contents of this user are searched
+    pub user_id: Uuid,
+    /// Shard of the full-text index to search: a search over several shards is fanned out by the gateway
+    #[serde(default)]
+    pub shard: u32,
+    /// Only the contents in this language (ISO 639-1 code) are searched
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Only the rows of structured sources whose fields have these values are searched
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+    /// Only the contents of these sources are searched, if any. An empty list finds no content
+    #[serde(default)]
+    pub source_meta_ids: Option<Vec<Uuid>>,
+    /// Only the contents of this chapter are searched, see `is_chapter_filter`
+    #[serde(default)]
+    pub chapter: Option<String>,
+    /// Only the contents with this keyword are searched, see `is_keyword_or_entity_filter`
+    #[serde(default)]
+    pub keyword: Option<String>,
+    /// Only the contents mentioning this named entity, whatever its kind, are searched
+    #[serde(default)]
+    pub entity: Option<String>,
+    /// Page of the found contents (from 1), of `hits_per_page` contents. Without page, the first `limit` contents are found
+    #[serde(default)]
+    pub page: Option<usize>,
+    #[serde(default)]
+    pub hits_per_page: Option<usize>,
+    #[serde(default)]
+    pub sort: SearchSortDto,
+}
+
+/// Order of the found contents
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchSortDto {
+    /// From the most relevant
+    #[default]
+    Relevance,
+    /// From the most recently added source, then from the most relevant
+    Recency,
+    /// By name of their source, then from the most relevant
+    SourceName,
 }
 
 impl FulltextSearchRequestDto {
@@ -1,18 +1,45 @@
 use super::templates::rpc_response::RpcResponse;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
+use std::collections::HashMap;
 use uuid::Uuid;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ResultContent {
     pub id: Uuid,
     pub metadata: JsonValue,
     pub content: String,
+    /// Source from which the content was extracted
+    #[serde(default)]
+    pub source_meta_id: Option<Uuid>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct FulltextSearchResponseData {
     pub results: Vec<ResultContent>,
+    /// Number of contents matching the query per source, counting all the matches and not only the returned ones
+    #[serde(default)]
+    pub source_hit_counts: HashMap<Uuid, u64>,
+    /// Number of contents matching the query per detected language, counted as the sources
+    #[serde(default)]
+    pub language_hit_counts: HashMap<String, u64>,
+    /// Number of contents matching the query per chapter, counted as the sources
+    #[serde(default)]
+    pub chapter_hit_counts: HashMap<String, u64>,
+    /// Number of contents matching the query per keyword, counted as the sources
+    #[serde(default)]
+    pub keyword_hit_counts: HashMap<String, u64>,
+    /// Number of contents matching the query per named entity, whatever its kind, counted as the sources
+    #[serde(default)]
+    pub entity_hit_counts: HashMap<String, u64>,
+    /// Number of contents matching the query, counting all the matches and not only the returned ones.
+    /// `None` from the services not counting them
+    #[serde(default)]
+    pub total_hits: Option<u64>,
+    /// Queries correcting the spelling of a query finding few contents, from the one finding the most contents.
+    /// Computed on the first page only
+    #[serde(default)]
+    pub suggestions: Vec<String>,
 }
 
 pub type FulltextSearchResponseDto = RpcResponse<FulltextSearchResponseData>;
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::helper::error_chain_fmt;
+
+/// Requests the import of an object of an external bucket as a source of a user
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ImportSourceDto {
+    pub user_id: Uuid,
+    pub bucket: String,
+    pub object_key: String,
+    /// Tags given to the source, matched by the auto-filing rules
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl ImportSourceDto {
+    pub fn try_parsing(data: &[u8]) -> Result<Self, ImportSourceDtoError> {
+        let data = std::str::from_utf8(data)?;
+        let my_data = serde_json::from_str(data)
+            .map_err(|e| ImportSourceDtoError::InvalidJsonData(e, data.to_string()))?;
+
+        Ok(my_data)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum ImportSourceDtoError {
+    #[error("Data could not be converted from utf8 u8 vector to string")]
+    InvalidStringData(#[from] std::str::Utf8Error),
+
+    #[error("Data did not represent a valid JSON object: {0}. Data: {1}")]
+    InvalidJsonData(serde_json::Error, String),
+}
+
+impl std::fmt::Debug for ImportSourceDtoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
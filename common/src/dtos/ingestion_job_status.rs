@@ -0,0 +1,206 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::helper::error_chain_fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatusDto {
+    /// The extraction of the contents of the source started, or completed if `nb_contents` is set
+    Extracting,
+    /// One content of the source was embedded, or skipped from the embedding
+    Embedded,
+    /// One content of the source was saved in the full-text index
+    Indexed,
+    /// The ingestion of the source failed
+    Failed,
+}
+
+/// Stable code of the reason of a failed ingestion, explained to the users
+///
+/// The codes unknown to a consumer, for ex sent by a newer worker, are read as `Internal`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IngestionErrorCodeDto {
+    /// The archive of the source (EPUB, ZIP) is corrupted
+    InvalidArchive,
+    /// The source is protected by a DRM
+    DrmProtected,
+    /// The text of the source is not encoded in UTF-8
+    InvalidEncoding,
+    /// The source does not follow the format of its type
+    InvalidFormat,
+    /// The LaTeX project has no main document
+    MissingMainDocument,
+    /// The language of the source code is not supported
+    UnsupportedLanguage,
+    /// The source file could not be read from the object storage
+    SourceUnavailable,
+    /// Failure not caused by the source
+    #[serde(other)]
+    Internal,
+}
+
+/// Item of a source skipped by the extraction, for ex an unreadable chapter of an EPUB
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SkippedItemDto {
+    /// Path of the item in the source, for ex the path of a chapter in the EPUB archive
+    pub path: String,
+    /// Why the item could not be read
+    pub error: String,
+}
+
+/// Status update of the ingestion job of a source, published by the workers
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IngestionJobStatusDto {
+    /// Id of the source meta associated to the file the job is working on
+    pub source_meta_id: Uuid,
+
+    pub status: JobStatusDto,
+
+    /// Number of contents extracted from the source, once the extraction is completed
+    #[serde(default)]
+    pub nb_contents: Option<u64>,
+
+    /// Number of contents of an incremental extraction unchanged since the previous extraction.
+    /// They are not published again, and not counted in `nb_contents`
+    #[serde(default)]
+    pub nb_unchanged_contents: u64,
+
+    /// Reason of a failure
+    #[serde(default)]
+    pub error: Option<String>,
+
+    /// Code of the reason of a failure, not sent by the older workers
+    #[serde(default)]
+    pub error_code: Option<IngestionErrorCodeDto>,
+
+    /// When the worker reported the status, to time the stages of the job without the delay of the status queue
+    #[serde(default)]
+    pub occurred_at: Option<DateTime<Utc>>,
+
+    /// Items of the source skipped by a completed extraction, the other items being extracted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_items: Vec<SkippedItemDto>,
+}
+
+impl IngestionJobStatusDto {
+    /// The extraction started, or completed with a given number of contents
+    pub fn extracting(source_meta_id: Uuid, nb_contents: Option<u64>) -> Self {
+        Self {
+            source_meta_id,
+            status: JobStatusDto::Extracting,
+            nb_contents,
+            nb_unchanged_contents: 0,
+            error: None,
+            error_code: None,
+            occurred_at: Some(Utc::now()),
+            skipped_items: vec![],
+        }
+    }
+
+    pub fn embedded(source_meta_id: Uuid) -> Self {
+        Self {
+            source_meta_id,
+            status: JobStatusDto::Embedded,
+            nb_contents: None,
+            nb_unchanged_contents: 0,
+            error: None,
+            error_code: None,
+            occurred_at: Some(Utc::now()),
+            skipped_items: vec![],
+        }
+    }
+
+    pub fn indexed(source_meta_id: Uuid) -> Self {
+        Self {
+            source_meta_id,
+            status: JobStatusDto::Indexed,
+            nb_contents: None,
+            nb_unchanged_contents: 0,
+            error: None,
+            error_code: None,
+            occurred_at: Some(Utc::now()),
+            skipped_items: vec![],
+        }
+    }
+
+    pub fn failed(source_meta_id: Uuid, error: String, error_code: IngestionErrorCodeDto) -> Self {
+        Self {
+            source_meta_id,
+            status: JobStatusDto::Failed,
+            nb_contents: None,
+            nb_unchanged_contents: 0,
+            error: Some(error),
+            error_code: Some(error_code),
+            occurred_at: Some(Utc::now()),
+            skipped_items: vec![],
+        }
+    }
+
+    /// Sets the number of contents of a completed incremental extraction not published again
+    pub fn with_unchanged_contents(mut self, nb_unchanged_contents: u64) -> Self {
+        self.nb_unchanged_contents = nb_unchanged_contents;
+        self
+    }
+
+    /// Sets the items skipped by the extraction, completed with warnings
+    pub fn with_skipped_items(mut self, skipped_items: Vec<SkippedItemDto>) -> Self {
+        self.skipped_items = skipped_items;
+        self
+    }
+
+    pub fn try_parsing(data: &[u8]) -> Result<Self, IngestionJobStatusDtoError> {
+        let data = std::str::from_utf8(data)?;
+        let my_data = serde_json::from_str(data)
+            .map_err(|e| IngestionJobStatusDtoError::InvalidJsonData(e, data.to_string()))?;
+
+        Ok(my_data)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum IngestionJobStatusDtoError {
+    #[error("Data could not be converted from utf8 u8 vector to string")]
+    InvalidStringData(#[from] std::str::Utf8Error),
+
+    #[error("Data did not represent a valid JSON object: {0}. Data: {1}")]
+    InvalidJsonData(serde_json::Error, String),
+}
+
+impl std::fmt::Debug for IngestionJobStatusDtoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_error_codes_are_read_as_internal() {
+        let data = format!(
+            r#"{{"source_meta_id": "{}", "status": "failed", "error": "Boom", "error_code": "some_new_code"}}"#,
+            Uuid::new_v4()
+        );
+
+        let job_status = IngestionJobStatusDto::try_parsing(data.as_bytes()).unwrap();
+
+        assert_eq!(job_status.error_code, Some(IngestionErrorCodeDto::Internal));
+    }
+
+    #[test]
+    fn status_of_an_older_worker_has_no_error_code() {
+        let data = format!(
+            r#"{{"source_meta_id": "{}", "status": "failed", "error": "Boom"}}"#,
+            Uuid::new_v4()
+        );
+
+        let job_status = IngestionJobStatusDto::try_parsing(data.as_bytes()).unwrap();
+
+        assert_eq!(job_status.error_code, None);
+        assert!(job_status.skipped_items.is_empty());
+    }
+}
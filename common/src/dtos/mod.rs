@@ -1,5 +1,19 @@
+pub mod delete_content;
 pub mod extract_content_job;
 pub mod extracted_content;
+pub mod extraction_progress;
 pub mod fulltext_search_request;
 pub mod fulltext_search_response;
+pub mod import_source;
+pub mod ingestion_job_status;
+pub mod normalization_rules;
+pub mod promote_standby;
+pub mod provider_credentials;
+pub mod provider_usage;
+pub mod reindex_source;
+pub mod semantic_search_request;
+pub mod semantic_search_response;
+pub mod source_chunks_request;
+pub mod source_chunks_response;
+pub mod source_summary;
 pub mod templates;
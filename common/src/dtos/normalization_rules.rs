@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+use super::templates::rpc_response::RpcResponse;
+
+/// Kind of a normalization rule of a tenant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NormalizationRuleKindDto {
+    /// Expands an abbreviation: `k8s` to `Kubernetes`
+    Abbreviation,
+    /// Replaces a variant of a product name by its canonical name: `Postgres` to `PostgreSQL`
+    CanonicalName,
+    /// Removes the text matching a regular expression: a boilerplate, a disclaimer...
+    StopPattern,
+}
+
+/// Rule normalizing the extracted contents and the search queries of a tenant
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NormalizationRuleDto {
+    pub kind: NormalizationRuleKindDto,
+    /// Term replaced, matched as whole words ignoring the case, or regular expression of a stop pattern
+    pub pattern: String,
+    /// Replacing term, `None` for a stop pattern
+    pub replacement: Option<String>,
+}
+
+/// Normalization rules of a tenant, in their creation order
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct NormalizationRulesData {
+    pub rules: Vec<NormalizationRuleDto>,
+}
+
+/// Response to the RPC call getting the normalization rules of a tenant
+pub type NormalizationRulesResponseDto = RpcResponse<NormalizationRulesData>;
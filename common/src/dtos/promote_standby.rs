@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+use crate::helper::error_chain_fmt;
+
+/// Requests a standby instance to start serving the queries, when its primary is lost
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PromoteStandbyDto {
+    /// Name of the promoted standby instance, the other standby instances ignore the command
+    pub standby_name: String,
+}
+
+impl PromoteStandbyDto {
+    pub fn try_parsing(data: &[u8]) -> Result<Self, PromoteStandbyDtoError> {
+        let data = std::str::from_utf8(data)?;
+        let my_data = serde_json::from_str(data)
+            .map_err(|e| PromoteStandbyDtoError::InvalidJsonData(e, data.to_string()))?;
+
+        Ok(my_data)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum PromoteStandbyDtoError {
+    #[error("Data could not be converted from utf8 u8 vector to string")]
+    InvalidStringData(#[from] std::str::Utf8Error),
+
+    #[error("Data did not represent a valid JSON object: {0}. Data: {1}")]
+    InvalidJsonData(serde_json::Error, String),
+}
+
+impl std::fmt::Debug for PromoteStandbyDtoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
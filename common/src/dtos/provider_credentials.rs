@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+use super::{provider_usage::ProviderPurposeDto, templates::rpc_response::RpcResponse};
+
+/// Model provider of the credentials of a tenant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelProviderDto {
+    OpenAi,
+    Mistral,
+}
+
+/// API key and model of a provider brought by a tenant, for a purpose
+///
+/// The key is sent encrypted with the `secrets` keys: it is decrypted by the services calling the provider.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ProviderCredentialsDto {
+    pub purpose: ProviderPurposeDto,
+    pub provider: ModelProviderDto,
+    /// Base URL of the OpenAI compatible API of the provider, for ex `https://api.openai.com/v1`
+    pub api_url: String,
+    pub model: String,
+    pub encrypted_api_key: String,
+}
+
+/// Provider credentials of a tenant, at most one by purpose
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ProviderCredentialsData {
+    pub credentials: Vec<ProviderCredentialsDto>,
+}
+
+/// Response to the RPC call getting the provider credentials of a tenant
+pub type ProviderCredentialsResponseDto = RpcResponse<ProviderCredentialsData>;
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+use crate::helper::error_chain_fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderPurposeDto {
+    Embedding,
+    Completion,
+}
+
+/// Usage of the API key of a tenant, published by the workers after each call to its model provider
+///
+/// Published on the exchanges of the tenant: the tenant is known from the exchange the usage is consumed from.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProviderUsageDto {
+    pub purpose: ProviderPurposeDto,
+    /// Number of requests sent to the provider
+    pub nb_requests: u64,
+    /// Number of tokens billed by the provider
+    pub nb_tokens: u64,
+}
+
+impl ProviderUsageDto {
+    pub fn try_parsing(data: &[u8]) -> Result<Self, ProviderUsageDtoError> {
+        let data = std::str::from_utf8(data)?;
+        let my_data = serde_json::from_str(data)
+            .map_err(|e| ProviderUsageDtoError::InvalidJsonData(e, data.to_string()))?;
+
+        Ok(my_data)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum ProviderUsageDtoError {
+    #[error("Data could not be converted from utf8 u8 vector to string")]
+    InvalidStringData(#[from] std::str::Utf8Error),
+
+    #[error("Data did not represent a valid JSON object: {0}. Data: {1}")]
+    InvalidJsonData(serde_json::Error, String),
+}
+
+impl std::fmt::Debug for ProviderUsageDtoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::helper::error_chain_fmt;
+
+/// Requests a new extraction of a source, for ex after a change of the chunking or embedding parameters
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReindexSourceDto {
+    pub source_meta_id: Uuid,
+    /// Deletes the indexed contents of the source before extracting it again
+    #[serde(default)]
+    pub wipe_index: bool,
+}
+
+impl ReindexSourceDto {
+    pub fn try_parsing(data: &[u8]) -> Result<Self, ReindexSourceDtoError> {
+        let data = std::str::from_utf8(data)?;
+        let my_data = serde_json::from_str(data)
+            .map_err(|e| ReindexSourceDtoError::InvalidJsonData(e, data.to_string()))?;
+
+        Ok(my_data)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum ReindexSourceDtoError {
+    #[error("Data could not be converted from utf8 u8 vector to string")]
+    InvalidStringData(#[from] std::str::Utf8Error),
+
+    #[error("Data did not represent a valid JSON object: {0}. Data: {1}")]
+    InvalidJsonData(serde_json::Error, String),
+}
+
+impl std::fmt::Debug for ReindexSourceDtoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::helper::error_chain_fmt;
+
+/// Request of a search of the contents semantically close to a query
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SemanticSearchRequestDto {
+    pub query: String,
+    pub limit: Option<usize>,
+    /// Only the contents of this user are searched
+    pub user_id: Uuid,
+    /// Only the contents in this language (ISO 639-1 code) are searched
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Only the rows of structured sources whose fields have these values are searched
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+    /// Only the contents of these sources are searched, if any. An empty list finds no content
+    #[serde(default)]
+    pub source_meta_ids: Option<Vec<Uuid>>,
+    /// Only the contents of this chapter are searched, see `is_chapter_filter`
+    #[serde(default)]
+    pub chapter: Option<String>,
+    /// Only the contents with this keyword are searched, see `is_keyword_or_entity_filter`
+    #[serde(default)]
+    pub keyword: Option<String>,
+    /// Only the contents mentioning this named entity, whatever its kind, are searched
+    #[serde(default)]
+    pub entity: Option<String>,
+}
+
+impl SemanticSearchRequestDto {
+    pub fn try_parsing(data: &[u8]) -> Result<Self, SemanticSearchRequestDtoError> {
+        let data = std::str::from_utf8(data)?;
+        let my_data = serde_json::from_str(data)
+            .map_err(|e| SemanticSearchRequestDtoError::InvalidJsonData(e, data.to_string()))?;
+
+        Ok(my_data)
+    }
+
+    pub fn try_serializing(&self) -> Result<String, SemanticSearchRequestDtoError> {
+        serde_json::to_string(self).map_err(SemanticSearchRequestDtoError::InvalidRequest)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum SemanticSearchRequestDtoError {
+    #[error("Data could not be converted from utf8 array to string")]
+    InvalidUtf8Data(#[from] std::str::Utf8Error),
+    #[error("Data did not represent a valid JSON object: {0}. Data: {1}")]
+    InvalidJsonData(serde_json::Error, String),
+    #[error("Request could not be serialized from its JSON representation: {0}")]
+    InvalidRequest(serde_json::Error),
+}
+
+impl std::fmt::Debug for SemanticSearchRequestDtoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,10 @@
+use super::{fulltext_search_response::ResultContent, templates::rpc_response::RpcResponse};
+use serde::{Deserialize, Serialize};
+
+/// Contents found by a semantic search, from the closest to the query
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SemanticSearchResponseData {
+    pub results: Vec<ResultContent>,
+}
+
+pub type SemanticSearchResponseDto = RpcResponse<SemanticSearchResponseData>;
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::helper::error_chain_fmt;
+
+/// Page of the contents extracted from a source, in the order they were indexed
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SourceChunksRequestDto {
+    pub source_meta_id: Uuid,
+    /// Only the contents of this user are listed
+    pub user_id: Uuid,
+    /// Shard of the full-text index the contents of the source are saved to
+    pub shard: u32,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl SourceChunksRequestDto {
+    pub fn try_parsing(data: &[u8]) -> Result<Self, SourceChunksRequestDtoError> {
+        let data = std::str::from_utf8(data)?;
+        let my_data = serde_json::from_str(data)
+            .map_err(|e| SourceChunksRequestDtoError::InvalidJsonData(e, data.to_string()))?;
+
+        Ok(my_data)
+    }
+
+    pub fn try_serializing(&self) -> Result<String, SourceChunksRequestDtoError> {
+        serde_json::to_string(self).map_err(SourceChunksRequestDtoError::InvalidRequest)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum SourceChunksRequestDtoError {
+    #[error("Data could not be converted from utf8 array to string")]
+    InvalidUtf8Data(#[from] std::str::Utf8Error),
+    #[error("Data did not represent a valid JSON object: {0}. Data: {1}")]
+    InvalidJsonData(serde_json::Error, String),
+    #[error("Request could not be serialized from its JSON representation: {0}")]
+    InvalidRequest(serde_json::Error),
+}
+
+impl std::fmt::Debug for SourceChunksRequestDtoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,11 @@
+use super::{fulltext_search_response::ResultContent, templates::rpc_response::RpcResponse};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SourceChunksResponseData {
+    pub chunks: Vec<ResultContent>,
+    /// Number of contents extracted from the source, in all the pages
+    pub total: usize,
+}
+
+pub type SourceChunksResponseDto = RpcResponse<SourceChunksResponseData>;
@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::helper::error_chain_fmt;
+
+/// Summaries of a source and of its chapters, generated once the extraction of the source is completed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceSummaryDto {
+    pub source_meta_id: Uuid,
+    /// Summary of the whole source
+    pub summary: String,
+    /// Summaries of the chapters of the source, in the order of the source.
+    /// Empty for the sources without chapters.
+    #[serde(default)]
+    pub chapters: Vec<ChapterSummaryDto>,
+    /// Model which generated the summaries
+    pub model: String,
+    pub generated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChapterSummaryDto {
+    /// Chapter of the contents: the id of an EPUB chapter, or the title of a LaTeX chapter
+    pub chapter: String,
+    pub summary: String,
+}
+
+impl SourceSummaryDto {
+    pub fn try_parsing(data: &[u8]) -> Result<Self, SourceSummaryDtoError> {
+        let data = std::str::from_utf8(data)?;
+        let my_data = serde_json::from_str(data)
+            .map_err(|e| SourceSummaryDtoError::InvalidJsonData(e, data.to_string()))?;
+
+        Ok(my_data)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum SourceSummaryDtoError {
+    #[error("Data could not be converted from utf8 u8 vector to string")]
+    InvalidStringData(#[from] std::str::Utf8Error),
+
+    #[error("Data did not represent a valid JSON object: {0}. Data: {1}")]
+    InvalidJsonData(serde_json::Error, String),
+}
+
+impl std::fmt::Debug for SourceSummaryDtoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,278 @@
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+
+use crate::helper::error_chain_fmt;
+
+/// Version of the schema of a message payload, serialized as `{major}.{minor}`
+///
+/// A new minor version can only add fields with a default value: its messages can be consumed by the services
+/// of the other minor versions. A new major version breaks the schema: its messages are rejected by the services
+/// of the other major versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SchemaVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl SchemaVersion {
+    pub const fn new(major: u16, minor: u16) -> Self {
+        Self { major, minor }
+    }
+}
+
+impl std::fmt::Display for SchemaVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl From<SchemaVersion> for String {
+    fn from(value: SchemaVersion) -> Self {
+        value.to_string()
+    }
+}
+
+impl TryFrom<String> for SchemaVersion {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let (major, minor) = value
+            .split_once('.')
+            .ok_or_else(|| format!("Invalid schema version {}: expected major.minor", value))?;
+        let parse = |number: &str| {
+            number
+                .parse::<u16>()
+                .map_err(|error| format!("Invalid schema version {}: {}", value, error))
+        };
+
+        Ok(Self::new(parse(major)?, parse(minor)?))
+    }
+}
+
+/// Payload of the messages sent between the services, in a `MessageEnvelope`
+pub trait MessagePayload: Serialize + DeserializeOwned {
+    /// Version of the schema of the payload published by this version of the services
+    const SCHEMA_VERSION: SchemaVersion;
+}
+
+/// Version of the bare payloads, published without envelope by the previous versions of the services
+const BARE_PAYLOAD_SCHEMA_VERSION: SchemaVersion = SchemaVersion::new(1, 0);
+
+/// Message sent between the services: a typed payload, with the version of its schema
+/// and the context to follow it across the services
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MessageEnvelope<T> {
+    pub schema_version: SchemaVersion,
+    pub message_id: Uuid,
+    /// When the event or command of the message happened, before its publication and its possible retries
+    pub occurred_at: DateTime<Utc>,
+    /// Trace of the message: the id of the first message of its chain, for ex the extraction job of a content.
+    /// `None` for the bare payloads.
+    pub trace_context: Option<String>,
+    pub payload: T,
+}
+
+impl<T: MessagePayload> MessageEnvelope<T> {
+    /// Message starting a new trace
+    pub fn new(payload: T) -> Self {
+        Self::in_trace(payload, None)
+    }
+
+    /// Message continuing the trace of the message which caused it, or starting a new trace without one
+    pub fn in_trace(payload: T, trace_context: Option<String>) -> Self {
+        let message_id = Uuid::new_v4();
+
+        Self {
+            schema_version: T::SCHEMA_VERSION,
+            message_id,
+            occurred_at: Utc::now(),
+            trace_context: Some(trace_context.unwrap_or_else(|| message_id.to_string())),
+            payload,
+        }
+    }
+
+    /// Decodes a message, or a bare payload published before the envelopes
+    ///
+    /// The messages of another major version of the schema are rejected.
+    pub fn try_decoding(data: &[u8]) -> Result<Self, MessageEnvelopeError> {
+        let data = std::str::from_utf8(data)?;
+        let invalid_json = |error| MessageEnvelopeError::InvalidJsonData(error, data.to_string());
+
+        let message: JsonValue = serde_json::from_str(data).map_err(invalid_json)?;
+        let is_enveloped =
+            message.get("schema_version").is_some() && message.get("payload").is_some();
+
+        let envelope = if is_enveloped {
+            serde_json::from_value::<Self>(message).map_err(invalid_json)?
+        } else {
+            Self {
+                schema_version: BARE_PAYLOAD_SCHEMA_VERSION,
+                message_id: Uuid::new_v4(),
+                occurred_at: Utc::now(),
+                trace_context: None,
+                payload: serde_json::from_value(message).map_err(invalid_json)?,
+            }
+        };
+
+        if envelope.schema_version.major != T::SCHEMA_VERSION.major {
+            return Err(MessageEnvelopeError::UnknownMajorVersion {
+                version: envelope.schema_version,
+                expected: T::SCHEMA_VERSION,
+            });
+        }
+
+        Ok(envelope)
+    }
+
+    /// Trace continued by the messages caused by this message: its own trace,
+    /// or a trace starting from it for a bare payload
+    pub fn caused_trace_context(&self) -> String {
+        self.trace_context
+            .clone()
+            .unwrap_or_else(|| self.message_id.to_string())
+    }
+
+    pub fn try_serializing(&self) -> Result<String, MessageEnvelopeError> {
+        serde_json::to_string(self).map_err(MessageEnvelopeError::InvalidMessage)
+    }
+}
+
+/// Id of an enveloped message, without decoding its payload. `None` for a bare payload, which has no stable id.
+pub fn enveloped_message_id(data: &[u8]) -> Option<Uuid> {
+    let message: JsonValue = serde_json::from_slice(data).ok()?;
+    if message.get("schema_version").is_none() || message.get("payload").is_none() {
+        return None;
+    }
+
+    serde_json::from_value(message.get("message_id")?.clone()).ok()
+}
+
+#[derive(thiserror::Error)]
+pub enum MessageEnvelopeError {
+    #[error("Data could not be converted from utf8 u8 vector to string")]
+    InvalidStringData(#[from] std::str::Utf8Error),
+
+    #[error("Data did not represent a valid JSON message: {0}. Data: {1}")]
+    InvalidJsonData(serde_json::Error, String),
+
+    #[error("Unknown schema version {version}, expected a version {expected} with the same major version")]
+    UnknownMajorVersion {
+        version: SchemaVersion,
+        expected: SchemaVersion,
+    },
+
+    #[error("Message could not be serialized to JSON: {0}")]
+    InvalidMessage(serde_json::Error),
+}
+
+impl std::fmt::Debug for MessageEnvelopeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct TestPayload {
+        name: String,
+    }
+
+    impl MessagePayload for TestPayload {
+        const SCHEMA_VERSION: SchemaVersion = SchemaVersion::new(1, 2);
+    }
+
+    #[test]
+    fn message_is_decoded_from_its_encoding() {
+        let message = MessageEnvelope::new(TestPayload {
+            name: "moby_dick.epub".to_string(),
+        });
+
+        let decoded = MessageEnvelope::<TestPayload>::try_decoding(
+            message.try_serializing().unwrap().as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(decoded.schema_version, SchemaVersion::new(1, 2));
+        assert_eq!(decoded.message_id, message.message_id);
+        assert_eq!(decoded.payload, message.payload);
+        // A new trace starts from the message
+        assert_eq!(decoded.trace_context, Some(message.message_id.to_string()));
+    }
+
+    #[test]
+    fn message_of_another_minor_version_is_decoded() {
+        let message = json!({
+            "schema_version": "1.7",
+            "message_id": Uuid::new_v4(),
+            "occurred_at": Utc::now(),
+            "trace_context": "trace",
+            "payload": { "name": "moby_dick.epub", "language": "en" },
+        });
+
+        let decoded =
+            MessageEnvelope::<TestPayload>::try_decoding(message.to_string().as_bytes()).unwrap();
+
+        assert_eq!(decoded.schema_version, SchemaVersion::new(1, 7));
+        assert_eq!(decoded.trace_context.as_deref(), Some("trace"));
+    }
+
+    #[test]
+    fn message_of_another_major_version_is_rejected() {
+        let message = json!({
+            "schema_version": "2.0",
+            "message_id": Uuid::new_v4(),
+            "occurred_at": Utc::now(),
+            "trace_context": null,
+            "payload": { "name": "moby_dick.epub" },
+        });
+
+        assert!(matches!(
+            MessageEnvelope::<TestPayload>::try_decoding(message.to_string().as_bytes()),
+            Err(MessageEnvelopeError::UnknownMajorVersion { version, .. })
+                if version == SchemaVersion::new(2, 0)
+        ));
+    }
+
+    #[test]
+    fn bare_payload_is_decoded_as_its_first_version() {
+        let message = json!({ "name": "moby_dick.epub" });
+
+        let decoded =
+            MessageEnvelope::<TestPayload>::try_decoding(message.to_string().as_bytes()).unwrap();
+
+        assert_eq!(decoded.schema_version, BARE_PAYLOAD_SCHEMA_VERSION);
+        assert_eq!(decoded.payload.name, "moby_dick.epub");
+        assert!(decoded.trace_context.is_none());
+    }
+
+    #[test]
+    fn message_id_is_only_read_from_the_enveloped_messages() {
+        let message = MessageEnvelope::new(TestPayload {
+            name: "moby_dick.epub".to_string(),
+        });
+        let bare_payload = json!({ "name": "moby_dick.epub" });
+
+        assert_eq!(
+            enveloped_message_id(message.try_serializing().unwrap().as_bytes()),
+            Some(message.message_id)
+        );
+        assert_eq!(
+            enveloped_message_id(bare_payload.to_string().as_bytes()),
+            None
+        );
+    }
+
+    #[test]
+    fn invalid_schema_version_is_rejected() {
+        for version in ["1", "1.x", "one.two"] {
+            assert!(SchemaVersion::try_from(version.to_string()).is_err());
+        }
+    }
+}
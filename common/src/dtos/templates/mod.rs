@@ -1 +1,3 @@
+pub mod message_envelope;
 pub mod rpc_response;
+pub mod rpc_stream_part;
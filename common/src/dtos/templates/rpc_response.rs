@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::helper::error_chain_fmt;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
 pub enum RpcErrorStatus {
     BadRequest,
     InternalServerError,
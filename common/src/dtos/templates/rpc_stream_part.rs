@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{dtos::templates::rpc_response::RpcErrorStatus, helper::error_chain_fmt};
+
+/// Part of a streamed RPC response
+///
+/// A streamed response is sent as several messages, correlated to the RPC call by its request id:
+/// the items in their order, then an `End` or an `Error` part closing the response.
+/// The caller can handle each item as soon as it is received, before the whole response is produced.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct RpcStreamPart<T> {
+    pub request_id: Uuid,
+    /// Position of the part in the response, from 0: a missing part is detected by the caller
+    pub sequence: u64,
+    pub kind: RpcStreamPartKind<T>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub enum RpcStreamPartKind<T> {
+    Item {
+        data: T,
+    },
+    /// Closes a complete response
+    End,
+    /// Closes a response interrupted by an error, after the items sent before it
+    Error {
+        status: RpcErrorStatus,
+        message: String,
+    },
+}
+
+impl<T> RpcStreamPart<T> {
+    pub fn item(request_id: Uuid, sequence: u64, data: T) -> Self {
+        Self {
+            request_id,
+            sequence,
+            kind: RpcStreamPartKind::Item { data },
+        }
+    }
+
+    pub fn end(request_id: Uuid, sequence: u64) -> Self {
+        Self {
+            request_id,
+            sequence,
+            kind: RpcStreamPartKind::End,
+        }
+    }
+
+    pub fn error(request_id: Uuid, sequence: u64, status: RpcErrorStatus, message: String) -> Self {
+        Self {
+            request_id,
+            sequence,
+            kind: RpcStreamPartKind::Error { status, message },
+        }
+    }
+
+    /// Whether the part closes its response
+    pub fn is_last(&self) -> bool {
+        !matches!(self.kind, RpcStreamPartKind::Item { .. })
+    }
+}
+
+impl<'a, T: Deserialize<'a>> RpcStreamPart<T> {
+    pub fn try_parsing(part: &'a [u8]) -> Result<Self, RpcStreamPartEncodingError> {
+        let part = std::str::from_utf8(part)?;
+        let part = serde_json::from_str(part)
+            .map_err(|e| RpcStreamPartEncodingError::InvalidJsonData(e, part.to_string()))?;
+
+        Ok(part)
+    }
+}
+
+impl<T: Serialize> RpcStreamPart<T> {
+    pub fn try_serializing(&self) -> Result<String, RpcStreamPartEncodingError> {
+        let part = serde_json::to_string(self).map_err(RpcStreamPartEncodingError::InvalidPart)?;
+
+        Ok(part)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum RpcStreamPartEncodingError {
+    #[error("Data could not be converted from utf8 array to string")]
+    InvalidUtf8Data(#[from] std::str::Utf8Error),
+
+    #[error(
+        "Data did not represent a valid JSON RPC response part: {0}. UTF-8 representation: {1}"
+    )]
+    InvalidJsonData(serde_json::Error, String),
+
+    #[error("Response part could not be serialized from its JSON representation: {0}")]
+    InvalidPart(serde_json::Error),
+}
+
+impl std::fmt::Debug for RpcStreamPartEncodingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parts_are_parsed_back_with_their_request_id_and_sequence() {
+        let request_id = Uuid::new_v4();
+
+        for part in [
+            RpcStreamPart::item(request_id, 0, "first chunk".to_string()),
+            RpcStreamPart::end(request_id, 1),
+            RpcStreamPart::error(
+                request_id,
+                1,
+                RpcErrorStatus::InternalServerError,
+                "extraction failed".to_string(),
+            ),
+        ] {
+            let serialized = part.try_serializing().unwrap();
+
+            assert_eq!(
+                RpcStreamPart::<String>::try_parsing(serialized.as_bytes()).unwrap(),
+                part
+            );
+        }
+    }
+
+    #[test]
+    fn only_the_end_and_error_parts_close_the_response() {
+        let request_id = Uuid::new_v4();
+
+        assert!(!RpcStreamPart::item(request_id, 0, ()).is_last());
+        assert!(RpcStreamPart::<()>::end(request_id, 1).is_last());
+        assert!(RpcStreamPart::<()>::error(
+            request_id,
+            1,
+            RpcErrorStatus::BadRequest,
+            "unsupported".to_string()
+        )
+        .is_last());
+    }
+}
@@ -2,4 +2,5 @@ pub mod constants;
 pub mod core;
 pub mod dtos;
 pub mod helper;
+pub mod pagination;
 pub mod telemetry;
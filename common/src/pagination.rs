@@ -0,0 +1,227 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::helper::error_chain_fmt;
+
+/// Direction in which a page is listed from its cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PageDirection {
+    /// Items after the cursor, in the order of the listing
+    #[serde(rename = "n")]
+    Next,
+    /// Items before the cursor
+    #[serde(rename = "p")]
+    Previous,
+}
+
+/// Position in a listing, from the sort key of the item at the edge of a page
+///
+/// Paging from a sort key rather than an offset: the items added or removed while a client
+/// is paging do not shift the next pages, and no item is listed twice or skipped.
+/// The sort key should be unique, for ex `(added_at, id)`.
+///
+/// Clients get the cursor as an opaque string: base64 (URL-safe) of the JSON of the cursor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageCursor<K> {
+    #[serde(rename = "d")]
+    pub direction: PageDirection,
+    #[serde(rename = "k")]
+    pub key: K,
+}
+
+impl<K: Serialize + DeserializeOwned> PageCursor<K> {
+    pub fn next(key: K) -> Self {
+        Self {
+            direction: PageDirection::Next,
+            key,
+        }
+    }
+
+    pub fn previous(key: K) -> Self {
+        Self {
+            direction: PageDirection::Previous,
+            key,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD
+            .encode(serde_json::to_vec(self).expect("a page cursor should be serializable to JSON"))
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, PaginationError> {
+        URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| PaginationError::InvalidCursor(cursor.to_string()))
+    }
+
+    /// Decodes the cursor of a listing query, if any
+    pub fn decode_query(cursor: Option<&str>) -> Result<Option<Self>, PaginationError> {
+        cursor.map(Self::decode).transpose()
+    }
+}
+
+/// Default and maximum number of items of the pages of a listing
+#[derive(Debug, Clone, Copy)]
+pub struct PageLimits {
+    pub default: u32,
+    pub max: u32,
+}
+
+impl PageLimits {
+    pub const fn new(default: u32, max: u32) -> Self {
+        Self { default, max }
+    }
+
+    /// Limit of a page requested by a client, or the default limit
+    pub fn validate(&self, limit: Option<u32>) -> Result<u32, PaginationError> {
+        match limit.unwrap_or(self.default) {
+            limit if limit == 0 || limit > self.max => Err(PaginationError::InvalidLimit {
+                limit,
+                max: self.max,
+            }),
+            limit => Ok(limit),
+        }
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum PaginationError {
+    #[error("Invalid cursor: {0}")]
+    InvalidCursor(String),
+    #[error("Invalid limit {limit}: it should be between 1 and {max}")]
+    InvalidLimit { limit: u32, max: u32 },
+}
+
+impl std::fmt::Debug for PaginationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Page of a listing, with the cursors of its next and previous pages
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// `None` on the last page
+    pub next_cursor: Option<String>,
+    /// `None` on the first page
+    pub prev_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Builds a page from the items fetched from a cursor
+    ///
+    /// # Arguments
+    /// * `fetched` - Up to `limit + 1` items, in the direction of the cursor: the extra item tells
+    ///   that there is a further page. Without cursor, the first page is fetched.
+    /// * `sort_key` - Sort key of an item, encoded in the cursors
+    pub fn from_fetched<K: Serialize + DeserializeOwned>(
+        mut fetched: Vec<T>,
+        limit: u32,
+        cursor_direction: Option<PageDirection>,
+        sort_key: impl Fn(&T) -> K,
+    ) -> Self {
+        let has_further_page = fetched.len() > limit as usize;
+        fetched.truncate(limit as usize);
+
+        let cursor = |item: Option<&T>, into_cursor: fn(K) -> PageCursor<K>| {
+            item.map(|item| into_cursor(sort_key(item)).encode())
+        };
+
+        match cursor_direction {
+            None | Some(PageDirection::Next) => Self {
+                next_cursor: cursor(
+                    fetched.last().filter(|_| has_further_page),
+                    PageCursor::next,
+                ),
+                // Without cursor, this is the first page
+                prev_cursor: cursor(
+                    fetched.first().filter(|_| cursor_direction.is_some()),
+                    PageCursor::previous,
+                ),
+                items: fetched,
+            },
+            Some(PageDirection::Previous) => {
+                // Fetched from the cursor backwards
+                fetched.reverse();
+
+                Self {
+                    // Listed from a further page
+                    next_cursor: cursor(fetched.last(), PageCursor::next),
+                    prev_cursor: cursor(
+                        fetched.first().filter(|_| has_further_page),
+                        PageCursor::previous,
+                    ),
+                    items: fetched,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_is_decoded_from_its_encoding() {
+        let cursor = PageCursor::previous((1_697_700_000_123_456_i64, "id".to_string()));
+
+        let encoded_cursor = cursor.encode();
+
+        assert!(encoded_cursor
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(PageCursor::decode(&encoded_cursor).unwrap(), cursor);
+    }
+
+    #[test]
+    fn invalid_cursor_is_not_decoded() {
+        let key_of_other_type = PageCursor::next("not a number".to_string()).encode();
+
+        for cursor in ["not a cursor", "bm90IGpzb24", key_of_other_type.as_str()] {
+            assert!(matches!(
+                PageCursor::<i64>::decode(cursor),
+                Err(PaginationError::InvalidCursor(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn limit_is_validated_against_the_max() {
+        let limits = PageLimits::new(20, 100);
+
+        assert_eq!(limits.validate(None).unwrap(), 20);
+        assert_eq!(limits.validate(Some(100)).unwrap(), 100);
+        assert!(limits.validate(Some(0)).is_err());
+        assert!(limits.validate(Some(101)).is_err());
+    }
+
+    fn decode(cursor: Option<String>) -> Option<PageCursor<u32>> {
+        cursor.map(|cursor| PageCursor::decode(&cursor).unwrap())
+    }
+
+    #[test]
+    fn pages_link_to_their_next_and_previous_pages() {
+        // First page of 2, with an extra item
+        let page = Page::from_fetched(vec![1, 2, 3], 2, None, |item| *item);
+        assert_eq!(page.items, vec![1, 2]);
+        assert_eq!(decode(page.next_cursor), Some(PageCursor::next(2)));
+        assert_eq!(page.prev_cursor, None);
+
+        // Last page
+        let page = Page::from_fetched(vec![3], 2, Some(PageDirection::Next), |item| *item);
+        assert_eq!(page.items, vec![3]);
+        assert_eq!(page.next_cursor, None);
+        assert_eq!(decode(page.prev_cursor), Some(PageCursor::previous(3)));
+
+        // Back from the last page, fetched backwards, up to the first page
+        let page = Page::from_fetched(vec![2, 1], 2, Some(PageDirection::Previous), |item| *item);
+        assert_eq!(page.items, vec![1, 2]);
+        assert_eq!(decode(page.next_cursor), Some(PageCursor::next(2)));
+        assert_eq!(page.prev_cursor, None);
+    }
+}
@@ -1,3 +1,6 @@
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::Tracer, Resource};
 use tokio::task::JoinHandle;
 use tracing::subscriber::set_global_default;
 use tracing::Subscriber;
@@ -5,6 +8,11 @@ use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
 use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt, EnvFilter, Registry};
 
+/// Environment variable of the endpoint of an OpenTelemetry collector (Jaeger, Tempo...), for ex `http://localhost:4317`
+///
+/// When it is set, the spans are also exported to the collector with OTLP (gRPC).
+pub const OTLP_ENDPOINT_ENV_VAR: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
 /// Composes multiple layers into a `tracing`'s Subscriber.
 ///
 /// The Subscriber trait exposes a variety of methods to manage every stage of the lifecycle of a Span:
@@ -16,7 +24,7 @@ use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt, EnvFilter, Regis
 /// and tracking which spans are active and which are closed.
 ///
 /// # Arguments
-/// - `name`: name of the app
+/// - `name`: name of the app, also the name of the service of its exported spans
 /// - `fallback_env_filter`: filter level for traces if RUST_LOG env variable has not been set
 /// - `sink`: to what the traces will be outputted
 ///
@@ -40,12 +48,17 @@ where
     let env_filter =
         EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(fallback_env_filter));
 
+    // Exports the spans to an OpenTelemetry collector, only if its endpoint is set
+    let otlp_layer =
+        otlp_tracer(&name).map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer));
+
     // Built on top of `JsonStorageLayer` and outputs log records in "bunyan"-compatible JSON format
     let formatting_layer = BunyanFormattingLayer::new(name, sink);
 
     Registry::default()
         // Discards spans based on their log levels and their origins
         .with(env_filter)
+        .with(otlp_layer)
         // Processes spans data and stores the associated metadata in an easy-to-consume JSON format for downstream layers.
         // It also propagates context from parent spans to their children.
         .with(JsonStorageLayer)
@@ -60,6 +73,42 @@ pub fn init_tracing_subscriber(subscriber: impl Subscriber + Send + Sync) {
     LogTracer::init().expect("Failed to set logger");
 
     set_global_default(subscriber).expect("Failed to set subscriber");
+
+    // The trace context is propagated in the messages with the W3C `traceparent` header
+    global::set_text_map_propagator(TraceContextPropagator::new());
+}
+
+/// Exports the spans not exported yet, before stopping the app
+pub fn shutdown_tracing() {
+    global::shutdown_tracer_provider();
+}
+
+/// Tracer exporting the spans in batches to the collector set with `OTLP_ENDPOINT_ENV_VAR`
+///
+/// # Panics
+/// Panics if the exporter could not be installed. It should be called from a Tokio runtime.
+fn otlp_tracer(service_name: &str) -> Option<Tracer> {
+    let endpoint = std::env::var(OTLP_ENDPOINT_ENV_VAR)
+        .ok()
+        .filter(|endpoint| !endpoint.is_empty())?;
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(
+            opentelemetry_sdk::trace::config().with_resource(Resource::new(vec![KeyValue::new(
+                "service.name",
+                service_name.to_string(),
+            )])),
+        )
+        .install_batch(opentelemetry_sdk::runtime::Tokio)
+        .expect("Failed to install the OTLP exporter");
+
+    Some(tracer)
 }
 
 /// Runs the provided closure on a thread where blocking is acceptable.
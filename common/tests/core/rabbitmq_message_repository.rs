@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use chrono::Utc;
 use common::core::rabbitmq_message_repository::{
-    RabbitMQMessageRepository, RabbitMQMessageRepositoryError,
+    PublisherConfirmsSettings, RabbitMQMessageRepository, RabbitMQMessageRepositoryError,
 };
 use futures::StreamExt;
 use tracing::info;
@@ -10,7 +10,10 @@ use uuid::Uuid;
 
 use crate::helpers::init_test;
 use lapin::{
-    options::{BasicAckOptions, BasicConsumeOptions, QueueBindOptions, QueueDeclareOptions},
+    options::{
+        BasicAckOptions, BasicConsumeOptions, BasicGetOptions, QueueBindOptions,
+        QueueDeclareOptions,
+    },
     types::FieldTable,
     Connection, ConnectionProperties,
 };
@@ -118,8 +121,13 @@ async fn rpc_call_should_get_a_response() {
                 );
 
                 // Sends response to the given `reply_to` to mimic a RPC call
+                let correlation_id = delivery
+                    .properties
+                    .correlation_id()
+                    .as_ref()
+                    .map(|id| id.as_str());
                 consumer_rabbitmq_message_repository
-                    .rpc_respond(&reply_to, response_message.as_bytes())
+                    .rpc_respond(&reply_to, correlation_id, response_message.as_bytes())
                     .await
                     .unwrap();
 
@@ -247,10 +255,184 @@ async fn rpc_call_should_timeout_if_no_response() {
 
     assert!(matches!(
         response,
-        Err(RabbitMQMessageRepositoryError::Timeout(_))
+        Err(RabbitMQMessageRepositoryError::RpcTimeout { .. })
     ));
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn concurrent_rpc_calls_get_their_own_response() {
+    init_test();
+
+    let connection = get_rabbitmq_connection("127.0.0.1", "5672").await.unwrap();
+    let connection = Arc::new(connection);
+    let channel = connection.create_channel().await.unwrap();
+
+    let exchange_name = format!(
+        "test_exchange_{}_{}",
+        Utc::now().format("%Y-%m-%d_%H-%M-%S"),
+        Uuid::new_v4()
+    );
+    let queue_name = format!(
+        "test_queue_{}_{}",
+        Utc::now().format("%Y-%m-%d_%H-%M-%S"),
+        Uuid::new_v4()
+    );
+    let routing_key = "test.v1";
+
+    let rabbitmq_message_repository = RabbitMQMessageRepository::new(connection, &exchange_name)
+        .try_init()
+        .await
+        .unwrap();
+    let consumer_rabbitmq_message_repository = rabbitmq_message_repository.clone();
+
+    channel
+        .queue_declare(
+            &queue_name,
+            QueueDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await
+        .unwrap();
+    channel
+        .queue_bind(
+            &queue_name,
+            &exchange_name,
+            routing_key,
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await
+        .unwrap();
+
+    let nb_calls = 5;
+
+    // Consumer responding to all the calls at once, in the reverse order of their requests
+    tokio::spawn(async move {
+        let consumer_rabbitmq_message_repository = consumer_rabbitmq_message_repository
+            .try_init()
+            .await
+            .unwrap();
+        let mut consumer = channel
+            .basic_consume(
+                &queue_name,
+                "",
+                BasicConsumeOptions {
+                    no_ack: true,
+                    ..BasicConsumeOptions::default()
+                },
+                FieldTable::default(),
+            )
+            .await
+            .unwrap();
+
+        let mut requests = vec![];
+        while requests.len() < nb_calls {
+            let delivery = consumer.next().await.unwrap().unwrap();
+            requests.push(delivery);
+        }
+
+        for delivery in requests.iter().rev() {
+            let request = std::str::from_utf8(&delivery.data).unwrap();
+            let reply_to = delivery.properties.reply_to().as_ref().unwrap().as_str();
+            let correlation_id = delivery
+                .properties
+                .correlation_id()
+                .as_ref()
+                .map(|id| id.as_str());
+
+            consumer_rabbitmq_message_repository
+                .rpc_respond(
+                    reply_to,
+                    correlation_id,
+                    format!("response_to_{}", request).as_bytes(),
+                )
+                .await
+                .unwrap();
+        }
+    });
+
+    let responses = futures::future::join_all((0..nb_calls).map(|index| {
+        let rabbitmq_message_repository = &rabbitmq_message_repository;
+        async move {
+            rabbitmq_message_repository
+                .rpc_call(
+                    routing_key,
+                    format!("request_{}", index).as_bytes(),
+                    Some(5000),
+                )
+                .await
+        }
+    }))
+    .await;
+
+    for (index, response) in responses.into_iter().enumerate() {
+        let response = String::from_utf8(response.unwrap()).unwrap();
+        assert_eq!(response, format!("response_to_request_{}", index));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn confirmed_publish_returns_once_the_message_is_queued() {
+    init_test();
+
+    let connection = get_rabbitmq_connection("127.0.0.1", "5672").await.unwrap();
+    let connection = Arc::new(connection);
+    let channel = connection.create_channel().await.unwrap();
+
+    let exchange_name = format!(
+        "test_exchange_{}_{}",
+        Utc::now().format("%Y-%m-%d_%H-%M-%S"),
+        Uuid::new_v4()
+    );
+    let queue_name = format!(
+        "test_queue_{}_{}",
+        Utc::now().format("%Y-%m-%d_%H-%M-%S"),
+        Uuid::new_v4()
+    );
+    let routing_key = "test.v1";
+
+    let rabbitmq_message_repository = RabbitMQMessageRepository::new(connection, &exchange_name)
+        .with_publisher_confirms(PublisherConfirmsSettings {
+            enabled: true,
+            ..PublisherConfirmsSettings::default()
+        })
+        .try_init()
+        .await
+        .unwrap();
+
+    channel
+        .queue_declare(
+            &queue_name,
+            QueueDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await
+        .unwrap();
+    channel
+        .queue_bind(
+            &queue_name,
+            &exchange_name,
+            routing_key,
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await
+        .unwrap();
+
+    rabbitmq_message_repository
+        .publish(routing_key, b"confirmed_test")
+        .await
+        .unwrap();
+
+    // Acknowledged by the broker: the message is already in the queue
+    let message = channel
+        .basic_get(&queue_name, BasicGetOptions { no_ack: true })
+        .await
+        .unwrap()
+        .expect("The confirmed message is not in the queue");
+    assert_eq!(message.delivery.data, b"confirmed_test");
+}
+
 /// Create a connection to RabbitMQ
 pub async fn get_rabbitmq_connection(host: &str, port: &str) -> Result<Connection, lapin::Error> {
     let connection_properties = ConnectionProperties::default()
@@ -0,0 +1,137 @@
+//! Extraction throughput on standard fixtures
+//!
+//! Run with `cargo bench -p content_ingestion_worker`, and check the results against
+//! the performance budgets with `scripts/check_bench_budgets.sh`.
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use genawaiter::GeneratorState;
+use std::io::{Cursor, Read};
+
+use content_ingestion_worker::domain::{
+    entities::meta_read::MetaRead,
+    extractors::extract_content_generator::{extract_content_generator, ChunkSplitting},
+    readers::{
+        epub_reader::EpubReader,
+        latex_reader::{LatexMathFormat, LatexReader},
+        subtitle_reader::SubtitleReader,
+        xml_reader,
+    },
+};
+
+const EPUB_FIXTURES: [&str; 2] = ["minimal_sample.epub", "sample_3_chapters.epub"];
+/// Number of cues/paragraphs of the generated fixtures
+const NB_GENERATED_UNITS: usize = 2000;
+const FIXTURE_SENTENCE: &str =
+    "The water cycle describes how water evaporates, condenses into clouds and falls back as rain.";
+
+/// Extracts every content from a reader, as the extract content job does
+///
+/// # Returns
+/// The number of extracted contents
+fn extract_all(reader: &mut (impl Read + MetaRead)) -> usize {
+    let mut generator = extract_content_generator(reader, None, ChunkSplitting::Words);
+    let mut nb_contents = 0;
+
+    loop {
+        match generator.as_mut().resume() {
+            GeneratorState::Yielded(_content) => nb_contents += 1,
+            GeneratorState::Complete(result) => {
+                result.expect("extraction to complete");
+                return nb_contents;
+            }
+        }
+    }
+}
+
+/// SRT fixture with `NB_GENERATED_UNITS` cues
+fn srt_fixture() -> Vec<u8> {
+    (0..NB_GENERATED_UNITS)
+        .map(|i| {
+            format!(
+                "{}\n00:{:02}:{:02},000 --> 00:{:02}:{:02},500\n{} ({})\n",
+                i + 1,
+                (i / 60) % 60,
+                i % 60,
+                (i / 60) % 60,
+                i % 60,
+                FIXTURE_SENTENCE,
+                i
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+        .into_bytes()
+}
+
+/// LaTeX fixture with a section every 10 paragraphs, and some inline math
+fn latex_fixture() -> Vec<u8> {
+    let body = (0..NB_GENERATED_UNITS)
+        .map(|i| {
+            let section = if i % 10 == 0 {
+                format!("\\section{{Section {}}}\n", i / 10)
+            } else {
+                String::new()
+            };
+            format!("{section}{FIXTURE_SENTENCE} With $x_{{{i}}} = \\frac{{a}}{{b}}$.\n")
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    format!("\\documentclass{{article}}\n\\title{{Benchmark}}\n\\begin{{document}}\n{body}\n\\end{{document}}\n")
+        .into_bytes()
+}
+
+fn extraction_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("extraction");
+
+    for file_name in EPUB_FIXTURES {
+        let content = std::fs::read(format!("tests/resources/{}", file_name))
+            .expect("EPUB fixture to be readable");
+
+        group.throughput(Throughput::Bytes(content.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("epub", file_name),
+            &content,
+            |b, content| {
+                b.iter(|| {
+                    let epub_reader = EpubReader::from_reader(Cursor::new(content), None).unwrap();
+                    let mut xml_reader = xml_reader::build_from_reader(epub_reader);
+                    extract_all(&mut xml_reader)
+                })
+            },
+        );
+    }
+
+    let content = srt_fixture();
+    group.throughput(Throughput::Bytes(content.len() as u64));
+    group.bench_with_input(
+        BenchmarkId::new("subtitle", "generated.srt"),
+        &content,
+        |b, content| {
+            b.iter(|| {
+                let mut reader =
+                    SubtitleReader::try_from_reader(content.as_slice(), None, None).unwrap();
+                extract_all(&mut reader)
+            })
+        },
+    );
+
+    let content = latex_fixture();
+    group.throughput(Throughput::Bytes(content.len() as u64));
+    group.bench_with_input(
+        BenchmarkId::new("latex", "generated.tex"),
+        &content,
+        |b, content| {
+            b.iter(|| {
+                let mut reader =
+                    LatexReader::try_from_reader(content.as_slice(), None, LatexMathFormat::Raw)
+                        .unwrap();
+                extract_all(&mut reader)
+            })
+        },
+    );
+
+    group.finish();
+}
+
+criterion_group!(benches, extraction_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,13 @@
+def split_words(text, nb_words_per_content=100):
+    """Splits a text into contents of a given number of words"""
+    words = text.split()
+    return [
+        " ".join(words[i : i + nb_words_per_content])
+        for i in range(0, len(words), nb_words_per_content)
+    ]
+
+
+class Content:
+    def __init__(self, source, text):
+        self.source = source
+        self.text = text
@@ -1,13 +1,44 @@
+use crate::domain::readers::latex_reader::LatexMathFormat;
+use common::{
+    core::{
+        configuration::{ensure_positive, ConfigurationError, ConfigurationLayers},
+        consumer_handover::HandoverSettings,
+        fair_share::FairShareSettings,
+        idempotency_store::IdempotencySettings,
+        memory_ceiling::MemorySettings,
+        message_signing::MessageSigningSettings,
+        normalization_rules::NormalizationRulesSettings,
+        rabbitmq_message_repository::{broker_uri, BrokerCredentials, PublisherConfirmsSettings},
+        retry::RetryPolicy,
+        tenancy::{tenant_name_prefix, TenantSettings},
+    },
+    dtos::extract_content_job::IngestionLaneDto,
+};
 use lapin::ConnectionProperties;
 use secrecy::Secret;
 use serde::Deserialize;
 use serde_aux::field_attributes::deserialize_number_from_string;
+use tokio::sync::watch;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Settings {
     pub application: ApplicationSettings,
     pub object_storage: ObjectStorageSettings,
     pub rabbitmq: RabbitMQSettings,
+    pub extraction: ExtractionSettings,
+    /// Retry policy on transient failures of the object storage
+    pub retry: RetryPolicy,
+    pub memory: MemorySettings,
+    pub handover: HandoverSettings,
+    /// Share of the in-flight extraction jobs between the users, for a large import not to delay the other users
+    pub fair_share: FairShareSettings,
+    /// Signing of the published messages, and verification of the consumed ones
+    pub message_signing: MessageSigningSettings,
+    /// Normalization of the extracted text contents with the rules of the tenant
+    pub normalization_rules: NormalizationRulesSettings,
+    /// Extraction jobs already extracted, acknowledged without being extracted again when redelivered
+    #[serde(default)]
+    pub idempotency: IdempotencySettings,
 }
 
 // TODO: is it used for our worker ?
@@ -38,8 +69,8 @@ impl ObjectStorageSettings {
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct RabbitMQSettings {
-    // pub username: String,
-    // pub password: Secret<String>,
+    #[serde(default)]
+    pub credentials: Option<BrokerCredentials>,
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub port: u16,
     pub host: String,
@@ -49,11 +80,141 @@ pub struct RabbitMQSettings {
     pub queue_name_prefix: String,
 
     pub content_exchange: String,
+    /// Tenant served by this deployment, isolated at the broker level. If not set, the shared exchanges and queues are used
+    #[serde(default)]
+    pub tenant: Option<TenantSettings>,
+
+    /// Number of messages delivered to a handler before being acknowledged
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub prefetch_count: u16,
+    /// Number of messages delivered to the handler of the fast lane before being acknowledged
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub fast_lane_prefetch_count: u16,
+
+    /// Whether the published messages wait for the broker to acknowledge them
+    #[serde(default)]
+    pub publisher_confirms: PublisherConfirmsSettings,
+}
+
+/// Settings on how contents are extracted from the source files
+#[derive(Debug, Deserialize, Clone)]
+pub struct ExtractionSettings {
+    /// If false, the code cells of notebooks are only full-text indexed, and not embedded
+    pub embed_notebook_code_cells: bool,
+    /// Form in which the math of LaTeX sources is kept in the metadata: `raw` or `normalized`
+    pub latex_math_format: LatexMathFormat,
+    /// If true, the alternative texts of images from EPUB/HTML sources are also extracted as contents, not only as metadata
+    pub image_alts_in_text: bool,
+    pub image_ocr: ImageOcrSettings,
+    /// Keywords and named entities of the text contents, added to their metadata
+    pub enrichment: EnrichmentSettings,
+    pub near_duplicates: NearDuplicateSettings,
+    pub pii_redaction: PiiRedactionSettings,
+    /// Larger source files (in bytes) are downloaded to a temporary file instead of being kept in memory
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub in_memory_source_max_bytes: usize,
+    /// Number of times an interrupted download of a source file is resumed from the received bytes,
+    /// before the download fails and is retried from the start
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_download_resumes: u32,
+    /// The progress of an extraction is published every given number of extracted contents. 0 to only publish it at the end.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub progress_every_nb_contents: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ImageOcrSettings {
+    /// If true, the text of the images bundled in EPUB sources is recognized and extracted
+    pub enabled: bool,
+    /// Smaller images (in bytes) are not recognized
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub min_image_size_bytes: usize,
+    /// `tesseract` executable
+    pub tesseract_command: String,
+    /// Tesseract language(s) of the text to recognize. Ex: `eng` or `eng+fra`
+    pub language: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct EnrichmentSettings {
+    /// Number of keywords extracted per text content. 0 to not extract the keywords.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_keywords: usize,
+    pub entity_recognition: EntityRecognitionSettings,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct EntityRecognitionSettings {
+    /// If true, the people, places and organizations of the text contents are recognized
+    pub enabled: bool,
+    /// Token classification endpoint of the model, see `TokenClassificationEntityRecognizer`
+    pub url: String,
+    #[serde(default)]
+    pub api_key: Option<Secret<String>>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timeout_ms: u64,
+    /// Entities recognized with a lower score are left out
+    pub min_score: f32,
+}
+
+/// Detection of the text contents nearly identical to an already indexed content of the same user, from their SimHash
+#[derive(Deserialize, Debug, Clone)]
+pub struct NearDuplicateSettings {
+    /// If true, the near duplicates are tagged with the id of the content they duplicate in their metadata
+    pub enabled: bool,
+    /// Contents whose fingerprints differ by at most this number of bits (out of 64) are near duplicates
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_hamming_distance: u32,
+    /// Shorter contents (in words) are neither checked nor indexed: their fingerprints are not discriminating
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub min_words: usize,
+    /// If true, the near duplicates are only full-text indexed, and not embedded
+    pub skip_embedding: bool,
+    /// Number of fingerprints kept per user, the oldest ones being dropped
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_fingerprints_per_user: usize,
+}
+
+/// Redaction of the personal data of the contents extracted from the sources uploaded with `redact_pii`
+///
+/// The emails, phone numbers and national ids are always redacted, see `PiiRedactor`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct PiiRedactionSettings {
+    /// Other personal data redacted, for ex the customer numbers of the tenant
+    #[serde(default)]
+    pub patterns: Vec<PiiPatternSettings>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PiiPatternSettings {
+    /// The matches are replaced by the name in uppercase between brackets, for ex `[CUSTOMER_NUMBER]`
+    pub name: String,
+    /// Regular expression of the `regex` crate
+    pub regex: String,
 }
 
 impl RabbitMQSettings {
     pub fn get_uri(&self) -> String {
-        format!("amqp://{}:{}", &self.host, &self.port)
+        let broker_uri = broker_uri(&self.host, self.port, self.credentials.as_ref());
+
+        match &self.tenant {
+            Some(tenant) => tenant.amqp_uri(&broker_uri),
+            None => broker_uri,
+        }
+    }
+
+    /// Name of the content exchange, templated with the tenant id
+    pub fn content_exchange_name(&self) -> String {
+        format!(
+            "{}_{}",
+            tenant_name_prefix(&self.exchange_name_prefix, self.tenant.as_ref()),
+            self.content_exchange
+        )
+    }
+
+    /// Prefix of the queue names, templated with the tenant id
+    pub fn tenant_queue_name_prefix(&self) -> String {
+        tenant_name_prefix(&self.queue_name_prefix, self.tenant.as_ref())
     }
 
     pub fn get_connection_properties(&self) -> ConnectionProperties {
@@ -65,7 +226,7 @@ impl RabbitMQSettings {
     }
 }
 
-/// Extracts app settings from configuration files and env variables
+/// Extracts app settings from configuration files, env variables and command line flags
 ///
 /// `base.yml` should contain shared settings for all environments.
 /// A specific env file should be created for each environment: `develop.yml`,`local.yml` and `production.yml`
@@ -74,7 +235,8 @@ impl RabbitMQSettings {
 ///
 /// Settings are also taken from environment variables: with a prefix of APP and '__' as separator
 /// For ex: `APP_APPLICATION__PORT=5001 would set `Settings.application.port`
-pub fn get_configuration() -> Result<Settings, config::ConfigError> {
+/// And finally from the `--set` flags of the command line. For ex: `--set rabbitmq.prefetch_count=5`
+pub async fn get_configuration() -> Result<Settings, ConfigurationError> {
     let base_path = std::env::current_dir().expect("Failed to determine the current directory");
     let configuration_directory = base_path.join("configuration");
 
@@ -83,23 +245,58 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
     let environment: Environment = std::env::var("APP_ENVIRONMENT")
         .unwrap_or_else(|_| "develop".into())
         .try_into()
-        .expect("Failed to parse APP_ENVIRONMENT.");
-    let environment_filename = format!("{}.yml", environment.as_str());
-
-    let settings = config::Config::builder()
-        .add_source(config::File::from(configuration_directory.join("base.yml")))
-        .add_source(config::File::from(
-            configuration_directory.join(environment_filename),
-        ))
-        // Adds in settings from environment variables (with a prefix of APP and '__' as separator)
-        .add_source(
-            config::Environment::with_prefix("APP")
-                .prefix_separator("_")
-                .separator("__"),
+        .map_err(ConfigurationError::InvalidEnvironment)?;
+
+    let settings: Settings =
+        ConfigurationLayers::new(configuration_directory, environment.as_str())
+            .with_cli_overrides(std::env::args().skip(1))?
+            .load()
+            .await?;
+    settings.validate()?;
+
+    Ok(settings)
+}
+
+impl Settings {
+    /// Checks the settings that can not be checked by their deserialization
+    pub fn validate(&self) -> Result<(), ConfigurationError> {
+        ensure_positive("rabbitmq.prefetch_count", self.rabbitmq.prefetch_count)?;
+        ensure_positive(
+            "rabbitmq.fast_lane_prefetch_count",
+            self.rabbitmq.fast_lane_prefetch_count,
         )
-        .build()?;
+    }
+}
+
+/// Tunables applied without restarting the worker, when its configuration is reloaded
+pub struct ReloadableSettings {
+    prefetch_count: watch::Sender<u16>,
+    fast_lane_prefetch_count: watch::Sender<u16>,
+}
 
-    settings.try_deserialize::<Settings>()
+impl ReloadableSettings {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            prefetch_count: watch::channel(settings.rabbitmq.prefetch_count).0,
+            fast_lane_prefetch_count: watch::channel(settings.rabbitmq.fast_lane_prefetch_count).0,
+        }
+    }
+
+    /// Prefetch count of the handler of a lane
+    pub fn prefetch_count(&self, lane: IngestionLaneDto) -> watch::Receiver<u16> {
+        match lane {
+            IngestionLaneDto::Bulk => self.prefetch_count.subscribe(),
+            IngestionLaneDto::Fast => self.fast_lane_prefetch_count.subscribe(),
+        }
+    }
+
+    /// Applies the tunables of reloaded settings. The other settings are only applied on restart.
+    pub fn update(&self, settings: &Settings) {
+        self.prefetch_count
+            .send_replace(settings.rabbitmq.prefetch_count);
+        self.fast_lane_prefetch_count
+            .send_replace(settings.rabbitmq.fast_lane_prefetch_count);
+    }
 }
 
 /// The possible runtime environment for our application.
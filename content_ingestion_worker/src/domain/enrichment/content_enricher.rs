@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use common::dtos::extracted_content::{
+    ENTITIES_METADATA_KEY, KEYWORDS_METADATA_KEY, LANGUAGE_METADATA_KEY,
+};
+use serde_json::{json, Map, Value as JsonValue};
+use tracing::warn;
+
+use crate::domain::{
+    entities::entity_recognizer::EntityRecognizer,
+    language::rake_keyword_extractor::extract_keywords,
+};
+
+/// Enriches the text contents with their keywords and named entities, in their metadata,
+/// for the searches to be filtered and counted by keyword and by entity
+pub struct ContentEnricher {
+    /// Number of keywords kept per content. 0 to not extract the keywords.
+    max_keywords: usize,
+    /// Set if the named entities of the contents should be recognized
+    entity_recognizer: Option<Arc<dyn EntityRecognizer>>,
+}
+
+impl ContentEnricher {
+    pub fn new(max_keywords: usize, entity_recognizer: Option<Arc<dyn EntityRecognizer>>) -> Self {
+        Self {
+            max_keywords,
+            entity_recognizer,
+        }
+    }
+
+    /// Adds the keywords and the named entities of a text content to its metadata
+    ///
+    /// The keywords are extracted with the stopwords of the language tagged in the metadata, if any.
+    /// A content whose entities could not be recognized is published without entities.
+    pub async fn enrich(&self, content: &str, metadata: &mut Map<String, JsonValue>) {
+        let language = metadata
+            .get(LANGUAGE_METADATA_KEY)
+            .and_then(JsonValue::as_str);
+        let keywords = extract_keywords(content, language, self.max_keywords);
+        if !keywords.is_empty() {
+            metadata.insert(KEYWORDS_METADATA_KEY.to_string(), json!(keywords));
+        }
+
+        if let Some(entity_recognizer) = &self.entity_recognizer {
+            match entity_recognizer.recognize(content).await {
+                Ok(entities) if !entities.is_empty() => {
+                    metadata.insert(ENTITIES_METADATA_KEY.to_string(), json!(entities));
+                }
+                Ok(_) => {}
+                Err(error) => {
+                    warn!(
+                        ?error,
+                        "Failed to recognize the named entities of a content"
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::entity_recognizer::{
+        EntityKind, EntityRecognizerError, NamedEntities,
+    };
+    use futures::future::BoxFuture;
+
+    /// Recognizes the capitalized words as people
+    struct CapitalizedWordsRecognizer;
+
+    impl EntityRecognizer for CapitalizedWordsRecognizer {
+        fn recognize<'a>(
+            &'a self,
+            text: &'a str,
+        ) -> BoxFuture<'a, Result<NamedEntities, EntityRecognizerError>> {
+            Box::pin(async move {
+                let mut entities = NamedEntities::default();
+                text.split_whitespace()
+                    .filter(|word| word.starts_with(char::is_uppercase))
+                    .for_each(|word| entities.add(EntityKind::Person, word));
+                Ok(entities)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn keywords_and_entities_are_added_to_the_metadata() {
+        let enricher = ContentEnricher::new(2, Some(Arc::new(CapitalizedWordsRecognizer)));
+        let mut metadata = json!({ "language": "en" }).as_object().unwrap().clone();
+
+        enricher
+            .enrich(
+                "the harpoon of Ahab and the whale of Ishmael",
+                &mut metadata,
+            )
+            .await;
+
+        assert_eq!(metadata["keywords"], json!(["ahab", "harpoon"]));
+        assert_eq!(
+            metadata["entities"],
+            json!({ "people": ["Ahab", "Ishmael"], "places": [], "organizations": [] })
+        );
+    }
+
+    #[tokio::test]
+    async fn without_recognizer_nor_keywords_the_metadata_is_unchanged() {
+        let enricher = ContentEnricher::new(0, None);
+        let mut metadata = Map::new();
+
+        enricher.enrich("the harpoon of Ahab", &mut metadata).await;
+
+        assert!(metadata.is_empty());
+    }
+}
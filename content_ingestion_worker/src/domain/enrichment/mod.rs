@@ -0,0 +1,2 @@
+pub mod content_enricher;
+pub mod token_classification_entity_recognizer;
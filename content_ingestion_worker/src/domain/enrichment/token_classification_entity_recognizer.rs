@@ -0,0 +1,189 @@
+use futures::future::BoxFuture;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{
+    configuration::EntityRecognitionSettings,
+    domain::entities::entity_recognizer::{
+        EntityKind, EntityRecognizer, EntityRecognizerError, NamedEntities,
+    },
+};
+
+/// Recognizes the named entities with a token classification model served by an API
+///
+/// The API of the Hugging Face inference endpoints, for ex serving `dslim/bert-base-NER`,
+/// also exposed by self-hosted servers. The tokens are grouped into entities by the server.
+pub struct TokenClassificationEntityRecognizer {
+    client: reqwest::Client,
+    url: String,
+    api_key: Option<Secret<String>>,
+    /// Entities recognized with a lower score are left out
+    min_score: f32,
+}
+
+#[derive(Serialize)]
+struct TokenClassificationRequest<'a> {
+    inputs: &'a str,
+    parameters: TokenClassificationParameters,
+}
+
+#[derive(Serialize)]
+struct TokenClassificationParameters {
+    aggregation_strategy: &'static str,
+}
+
+#[derive(Deserialize)]
+struct RecognizedEntity {
+    entity_group: String,
+    score: f32,
+    word: String,
+}
+
+impl TokenClassificationEntityRecognizer {
+    pub fn try_new(settings: &EntityRecognitionSettings) -> Result<Self, reqwest::Error> {
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_millis(settings.timeout_ms))
+                .build()?,
+            url: settings.url.clone(),
+            api_key: settings.api_key.clone(),
+            min_score: settings.min_score,
+        })
+    }
+}
+
+/// Kind of an entity from its label, in the CoNLL (`PER`) or OntoNotes (`PERSON`) scheme of the models
+fn entity_kind(label: &str) -> Option<EntityKind> {
+    match label {
+        "PER" | "PERSON" => Some(EntityKind::Person),
+        "LOC" | "GPE" => Some(EntityKind::Place),
+        "ORG" => Some(EntityKind::Organization),
+        _ => None,
+    }
+}
+
+impl EntityRecognizer for TokenClassificationEntityRecognizer {
+    fn recognize<'a>(
+        &'a self,
+        text: &'a str,
+    ) -> BoxFuture<'a, Result<NamedEntities, EntityRecognizerError>> {
+        Box::pin(async move {
+            let request = self
+                .client
+                .post(&self.url)
+                .json(&TokenClassificationRequest {
+                    inputs: text,
+                    parameters: TokenClassificationParameters {
+                        aggregation_strategy: "simple",
+                    },
+                });
+            // The self-hosted servers may not require any key
+            let request = match &self.api_key {
+                Some(api_key) => request.bearer_auth(api_key.expose_secret()),
+                None => request,
+            };
+
+            let response = request.send().await?.error_for_status()?;
+            let recognized_entities: Vec<RecognizedEntity> = response
+                .json()
+                .await
+                .map_err(|error| EntityRecognizerError::InvalidResponse(error.to_string()))?;
+
+            let mut entities = NamedEntities::default();
+            for recognized_entity in recognized_entities {
+                if recognized_entity.score < self.min_score {
+                    continue;
+                }
+                if let Some(kind) = entity_kind(&recognized_entity.entity_group) {
+                    entities.add(kind, &recognized_entity.word);
+                }
+            }
+
+            Ok(entities)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Serves a single request with a given JSON body, returning the URL and the received request body
+    fn serve_once(body: &'static str) -> (String, std::thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/ner", listener.local_addr().unwrap());
+
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut request_body = vec![0; content_length];
+            reader.read_exact(&mut request_body).unwrap();
+
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+
+            String::from_utf8(request_body).unwrap()
+        });
+
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn entities_are_grouped_per_kind_without_duplicates_and_low_scores() {
+        let (url, handle) = serve_once(
+            r#"[
+                {"entity_group":"PER","score":0.99,"word":"Ahab","start":0,"end":4},
+                {"entity_group":"LOC","score":0.97,"word":"Nantucket","start":20,"end":29},
+                {"entity_group":"PER","score":0.98,"word":"Ahab","start":40,"end":44},
+                {"entity_group":"ORG","score":0.3,"word":"Pequod","start":50,"end":56},
+                {"entity_group":"MISC","score":0.95,"word":"American","start":60,"end":68}
+            ]"#,
+        );
+        let recognizer = TokenClassificationEntityRecognizer::try_new(&EntityRecognitionSettings {
+            enabled: true,
+            url,
+            api_key: None,
+            timeout_ms: 5000,
+            min_score: 0.5,
+        })
+        .unwrap();
+
+        let entities = recognizer
+            .recognize("Ahab sailed from Nantucket.")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            entities,
+            NamedEntities {
+                people: vec!["Ahab".to_string()],
+                places: vec!["Nantucket".to_string()],
+                organizations: vec![],
+            }
+        );
+        let request: serde_json::Value = serde_json::from_str(&handle.join().unwrap()).unwrap();
+        assert_eq!(request["inputs"], "Ahab sailed from Nantucket.");
+        assert_eq!(request["parameters"]["aggregation_strategy"], "simple");
+    }
+}
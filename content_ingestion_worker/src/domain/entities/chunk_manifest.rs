@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+
+use common::dtos::extracted_content::ExtractedContentDto;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::domain::entities::chunk_provenance::without_value;
+
+/// Contents extracted from a source with their hashes, saved after each extraction to diff the next one
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ChunkManifest {
+    pub version: u16,
+    pub contents: Vec<ManifestContent>,
+}
+
+impl ChunkManifest {
+    pub const CURRENT_VERSION: u16 = 1;
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ManifestContent {
+    pub id: Uuid,
+    /// Hex-encoded SHA-256 hash of the content, see `content_hash`
+    pub hash: String,
+}
+
+/// Hashes an extracted content, to find it unchanged in the next extraction of its source
+///
+/// The metadata values naming the object of the source file are left out:
+/// a new version of the source is saved to a new object.
+pub fn content_hash(content: &ExtractedContentDto, object_store_path_name: &str) -> String {
+    let hashed = json!({
+        "content": content.content,
+        "metadata": without_value(&content.metadata, object_store_path_name),
+        "is_code": content.is_code,
+        "skip_embedding": content.skip_embedding,
+    });
+
+    hex::encode(Sha256::digest(hashed.to_string().as_bytes()))
+}
+
+/// Diff of the contents of an extraction against the contents of the previous extraction of the source
+///
+/// The contents are matched by id, derived from their provenance: a content read at the same place of the source
+/// has the same id. Incremental: a content with the same hash as in the previous extraction is not published again.
+/// Otherwise all the contents are published, and the previous contents are left to the caller.
+#[derive(Debug)]
+pub struct ChunkDiff {
+    incremental: bool,
+    /// Object of the extracted version of the source file, left out of the hashes
+    object_store_path_name: String,
+    /// Hashes of the previous contents not extracted again yet, by id
+    previous_hashes_by_id: HashMap<Uuid, String>,
+    contents: Vec<ManifestContent>,
+    nb_unchanged: u64,
+}
+
+impl ChunkDiff {
+    pub fn new(
+        previous_manifest: Option<ChunkManifest>,
+        incremental: bool,
+        object_store_path_name: &str,
+    ) -> Self {
+        let previous_hashes_by_id = match previous_manifest {
+            Some(previous_manifest) if incremental => previous_manifest
+                .contents
+                .into_iter()
+                .map(|content| (content.id, content.hash))
+                .collect(),
+            _ => HashMap::new(),
+        };
+
+        Self {
+            incremental,
+            object_store_path_name: object_store_path_name.to_string(),
+            previous_hashes_by_id,
+            contents: vec![],
+            nb_unchanged: 0,
+        }
+    }
+
+    /// Records an extracted content
+    ///
+    /// # Returns
+    /// True if the content should be published: it is changed or new, or the extraction is not incremental
+    pub fn record(&mut self, content: &ExtractedContentDto) -> bool {
+        let hash = content_hash(content, &self.object_store_path_name);
+        // A changed content replaces the previous one in the indexes, it is not pruned
+        let is_unchanged = self
+            .previous_hashes_by_id
+            .remove(&content.id)
+            .is_some_and(|previous_hash| previous_hash == hash);
+        if is_unchanged {
+            self.nb_unchanged += 1;
+        }
+
+        self.contents.push(ManifestContent {
+            id: content.id,
+            hash,
+        });
+
+        !is_unchanged
+    }
+
+    pub fn is_incremental(&self) -> bool {
+        self.incremental
+    }
+
+    /// Object of the extracted version of the source file
+    pub fn object_store_path_name(&self) -> &str {
+        &self.object_store_path_name
+    }
+
+    /// Number of contents not published, unchanged since the previous extraction
+    pub fn nb_unchanged(&self) -> u64 {
+        self.nb_unchanged
+    }
+
+    /// Ids of the contents of the previous extraction no longer extracted, to prune
+    pub fn removed_content_ids(&self) -> Vec<Uuid> {
+        self.previous_hashes_by_id.keys().copied().collect()
+    }
+
+    /// Manifest of the recorded contents
+    pub fn manifest(&self) -> ChunkManifest {
+        ChunkManifest {
+            version: ChunkManifest::CURRENT_VERSION,
+            contents: self.contents.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extracted_content(id: u128, content: &str, file: &str) -> ExtractedContentDto {
+        ExtractedContentDto {
+            version: ExtractedContentDto::CURRENT_VERSION,
+            id: Uuid::from_u128(id),
+            metadata: json!({ "file": file, "source_initial_name": "notes.txt", "user_id": "42" }),
+            content: content.to_string(),
+            skip_embedding: false,
+            is_code: false,
+            source_meta_id: None,
+            fulltext_shard: 0,
+            provenance: None,
+        }
+    }
+
+    /// Diff of the second version of a source, against the contents of its first version
+    fn diff(previous: &[&str], incremental: bool) -> ChunkDiff {
+        let mut previous_diff = ChunkDiff::new(None, false, "user/v1");
+        for (id, content) in previous.iter().enumerate() {
+            previous_diff.record(&extracted_content(id as u128, content, "user/v1"));
+        }
+
+        ChunkDiff::new(Some(previous_diff.manifest()), incremental, "user/v2")
+    }
+
+    #[test]
+    fn content_hash_ignores_the_object_of_the_source_file() {
+        assert_eq!(
+            content_hash(&extracted_content(0, "Chapter 1", "user/v1"), "user/v1"),
+            content_hash(&extracted_content(0, "Chapter 1", "user/v2"), "user/v2")
+        );
+        assert_ne!(
+            content_hash(&extracted_content(0, "Chapter 1", "user/v1"), "user/v1"),
+            content_hash(&extracted_content(0, "Chapter 2", "user/v1"), "user/v1")
+        );
+    }
+
+    #[test]
+    fn incremental_diff_only_publishes_changed_contents_and_prunes_removed_ones() {
+        let mut chunk_diff = diff(&["Chapter 1", "Chapter 2", "Chapter 3"], true);
+
+        assert!(!chunk_diff.record(&extracted_content(0, "Chapter 1", "user/v2")));
+        assert!(chunk_diff.record(&extracted_content(1, "Chapter 2, revised", "user/v2")));
+        assert!(chunk_diff.record(&extracted_content(5, "Chapter 5", "user/v2")));
+
+        assert_eq!(chunk_diff.nb_unchanged(), 1);
+        assert_eq!(chunk_diff.removed_content_ids(), vec![Uuid::from_u128(2)]);
+        let manifest_ids: Vec<Uuid> = chunk_diff
+            .manifest()
+            .contents
+            .iter()
+            .map(|content| content.id)
+            .collect();
+        assert_eq!(
+            manifest_ids,
+            vec![Uuid::from_u128(0), Uuid::from_u128(1), Uuid::from_u128(5)]
+        );
+    }
+
+    #[test]
+    fn diff_not_incremental_publishes_all_the_contents() {
+        let mut chunk_diff = diff(&["Chapter 1", "Chapter 2"], false);
+
+        assert!(chunk_diff.record(&extracted_content(0, "Chapter 1", "user/v2")));
+
+        assert_eq!(chunk_diff.nb_unchanged(), 0);
+        assert!(chunk_diff.removed_content_ids().is_empty());
+    }
+}
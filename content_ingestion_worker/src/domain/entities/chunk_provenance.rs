@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use common::dtos::extracted_content::ChunkProvenanceDto;
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+
+/// Tracks the sections of a source read by an extraction, to give the provenance of each extracted content
+///
+/// A section is the text read with the same metadata, for ex a chapter. The sections with the same metadata,
+/// for ex the untitled parts of a document, are told apart by their occurrence.
+#[derive(Debug)]
+pub struct SectionTracker {
+    /// Object of the extracted version of the source file, left out of the metadata of the sections
+    object_store_path_name: String,
+    /// Number of sections read so far, by hash of their metadata
+    occurrences: HashMap<String, u32>,
+    /// Hash of the metadata of the current section, with the section
+    current_section: Option<(String, String)>,
+}
+
+impl SectionTracker {
+    pub fn new(object_store_path_name: &str) -> Self {
+        Self {
+            object_store_path_name: object_store_path_name.to_string(),
+            occurrences: HashMap::new(),
+            current_section: None,
+        }
+    }
+
+    /// Provenance of the next extracted content, read with some metadata at an offset of its section
+    ///
+    /// The offsets restart from 0 with each section: a content at the offset 0 begins a new section.
+    pub fn provenance(&mut self, metadata: &JsonValue, offset: u64) -> ChunkProvenanceDto {
+        let metadata_hash = hex::encode(Sha256::digest(
+            without_value(metadata, &self.object_store_path_name)
+                .to_string()
+                .as_bytes(),
+        ))[..16]
+            .to_string();
+
+        let section = match &self.current_section {
+            Some((current_hash, section)) if offset > 0 && *current_hash == metadata_hash => {
+                section.clone()
+            }
+            _ => {
+                let occurrence = self.occurrences.entry(metadata_hash.clone()).or_insert(0);
+                *occurrence += 1;
+                let section = format!("{}:{}", metadata_hash, occurrence);
+                self.current_section = Some((metadata_hash, section.clone()));
+                section
+            }
+        };
+
+        ChunkProvenanceDto { section, offset }
+    }
+}
+
+/// Metadata without a given string value, at any depth
+///
+/// Used to leave out the object of the source file: a new version of the source is saved to a new object.
+pub fn without_value(metadata: &JsonValue, value: &str) -> JsonValue {
+    match metadata {
+        JsonValue::Object(object) => JsonValue::Object(
+            object
+                .iter()
+                .filter(|(_, field)| field.as_str() != Some(value))
+                .map(|(key, field)| (key.clone(), without_value(field, value)))
+                .collect(),
+        ),
+        JsonValue::Array(array) => JsonValue::Array(
+            array
+                .iter()
+                .filter(|item| item.as_str() != Some(value))
+                .map(|item| without_value(item, value))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn contents_of_a_section_share_its_provenance_across_versions_of_the_source() {
+        let mut first_version = SectionTracker::new("user/v1");
+        let mut second_version = SectionTracker::new("user/v2");
+        let chapter = |file: &str| json!({ "file": file, "chapter": "Chapter 1" });
+
+        let first = first_version.provenance(&chapter("user/v1"), 0);
+        let second = first_version.provenance(&chapter("user/v1"), 600);
+
+        assert_eq!(first.section, second.section);
+        assert_eq!(second.offset, 600);
+        assert_eq!(second_version.provenance(&chapter("user/v2"), 0), first);
+    }
+
+    #[test]
+    fn sections_with_the_same_metadata_are_told_apart_by_their_occurrence() {
+        let mut section_tracker = SectionTracker::new("user/v1");
+        let untitled = json!({ "file": "user/v1" });
+        let titled = json!({ "file": "user/v1", "title": "Intro" });
+
+        let first = section_tracker.provenance(&untitled, 0);
+        let intro = section_tracker.provenance(&titled, 0);
+        let second = section_tracker.provenance(&untitled, 0);
+
+        assert_ne!(first.section, intro.section);
+        assert_ne!(first.section, second.section);
+        assert!(first.section.ends_with(":1"));
+        assert!(second.section.ends_with(":2"));
+    }
+}
@@ -0,0 +1,73 @@
+use common::helper::error_chain_fmt;
+use std::path::Path;
+
+/// Programming languages of the source code files that can be split along their symbols
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeLanguage {
+    Rust,
+    Python,
+}
+
+impl CodeLanguage {
+    /// Detects the language of a source code file from its extension
+    pub fn from_file_path(file_path: &str) -> Option<Self> {
+        let extension = Path::new(file_path).extension()?.to_str()?;
+
+        match extension.to_lowercase().as_str() {
+            "rs" => Some(CodeLanguage::Rust),
+            "py" => Some(CodeLanguage::Python),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CodeLanguage::Rust => "rust",
+            CodeLanguage::Python => "python",
+        }
+    }
+
+    /// Separator between a symbol and its parent symbol in a qualified name. Ex: `Type::method` or `Class.method`
+    pub fn symbol_separator(&self) -> &'static str {
+        match self {
+            CodeLanguage::Rust => "::",
+            CodeLanguage::Python => ".",
+        }
+    }
+}
+
+/// A chunk of source code, covering one symbol (function, class etc.) when possible
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeChunk {
+    /// Qualified name of the symbol. None for code outside of any symbol (imports, module level statements etc.)
+    pub symbol_name: Option<String>,
+    /// Kind of the symbol: function, struct, class etc.
+    pub symbol_kind: Option<String>,
+    /// 1-based line numbers, inclusive
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+}
+
+/// Splits source code along symbol boundaries
+///
+/// Port to decouple the code reader from the parsing library.
+pub trait CodeSplitter: Send + Sync {
+    fn split(
+        &self,
+        language: CodeLanguage,
+        source: &str,
+    ) -> Result<Vec<CodeChunk>, CodeSplitterError>;
+}
+
+#[derive(thiserror::Error)]
+pub enum CodeSplitterError {
+    #[error("Could not parse the {0:?} source code: {1}")]
+    ParsingError(CodeLanguage, String),
+}
+
+impl std::fmt::Debug for CodeSplitterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
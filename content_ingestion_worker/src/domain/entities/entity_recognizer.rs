@@ -0,0 +1,63 @@
+use common::helper::error_chain_fmt;
+use futures::future::BoxFuture;
+use serde::Serialize;
+
+/// Kind of a named entity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityKind {
+    Person,
+    Place,
+    Organization,
+}
+
+/// Named entities recognized in a text content, per kind, in the order they were found, without duplicates
+///
+/// Serialized as the `entities` metadata of the content, see `ENTITY_METADATA_ATTRIBUTES`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct NamedEntities {
+    pub people: Vec<String>,
+    pub places: Vec<String>,
+    pub organizations: Vec<String>,
+}
+
+impl NamedEntities {
+    pub fn add(&mut self, kind: EntityKind, name: &str) {
+        let name = name.trim();
+        let names = match kind {
+            EntityKind::Person => &mut self.people,
+            EntityKind::Place => &mut self.places,
+            EntityKind::Organization => &mut self.organizations,
+        };
+        if !name.is_empty() && !names.iter().any(|known_name| known_name == name) {
+            names.push(name.to_string());
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.people.is_empty() && self.places.is_empty() && self.organizations.is_empty()
+    }
+}
+
+/// Recognizes the named entities of a text content: people, places and organizations
+///
+/// Port to decouple the extraction from the model recognizing the entities.
+pub trait EntityRecognizer: Send + Sync {
+    fn recognize<'a>(
+        &'a self,
+        text: &'a str,
+    ) -> BoxFuture<'a, Result<NamedEntities, EntityRecognizerError>>;
+}
+
+#[derive(thiserror::Error)]
+pub enum EntityRecognizerError {
+    #[error(transparent)]
+    RequestError(#[from] reqwest::Error),
+    #[error("Invalid response of the entity recognition model: {0}")]
+    InvalidResponse(String),
+}
+
+impl std::fmt::Debug for EntityRecognizerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -8,6 +8,10 @@ pub struct ExtractedContent {
     pub id: Uuid,
     pub metadata: JsonValue,
     pub content: String,
+    pub skip_embedding: bool,
+    pub is_code: bool,
+    /// Offset of the content in the text extracted with the same metadata, in characters
+    pub offset: u64,
 }
 
 impl ExtractedContent {
@@ -16,16 +20,40 @@ impl ExtractedContent {
             id: Uuid::new_v4(),
             metadata,
             content,
+            skip_embedding: false,
+            is_code: false,
+            offset: 0,
         }
     }
+
+    pub fn with_skip_embedding(mut self, skip_embedding: bool) -> Self {
+        self.skip_embedding = skip_embedding;
+        self
+    }
+
+    pub fn with_is_code(mut self, is_code: bool) -> Self {
+        self.is_code = is_code;
+        self
+    }
+
+    pub fn with_offset(mut self, offset: u64) -> Self {
+        self.offset = offset;
+        self
+    }
 }
 
 impl Into<ExtractedContentDto> for ExtractedContent {
     fn into(self) -> ExtractedContentDto {
         ExtractedContentDto {
+            version: ExtractedContentDto::CURRENT_VERSION,
             id: self.id,
             metadata: self.metadata,
             content: self.content,
+            skip_embedding: self.skip_embedding,
+            is_code: self.is_code,
+            source_meta_id: None,
+            fulltext_shard: 0,
+            provenance: None,
         }
     }
 }
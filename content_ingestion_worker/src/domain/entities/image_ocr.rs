@@ -0,0 +1,24 @@
+use common::helper::error_chain_fmt;
+
+/// Recognizes the text contained in an image
+///
+/// Port to decouple the readers from the OCR engine.
+pub trait ImageOcr: Send + Sync {
+    /// # Returns
+    /// The recognized text. Empty if the image does not contain any text.
+    fn recognize(&self, image: &[u8]) -> Result<String, ImageOcrError>;
+}
+
+#[derive(thiserror::Error)]
+pub enum ImageOcrError {
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error("Text recognition failed: {0}")]
+    RecognitionError(String),
+}
+
+impl std::fmt::Debug for ImageOcrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
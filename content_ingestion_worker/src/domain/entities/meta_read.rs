@@ -3,4 +3,14 @@ use serde_json::Value as JsonValue;
 /// Metadata about the current read
 pub trait MetaRead {
     fn get_current_metadata(&self) -> JsonValue;
+
+    /// Whether the current read content should only be full-text indexed, and not embedded
+    fn should_skip_embedding(&self) -> bool {
+        false
+    }
+
+    /// Whether the current read content is source code
+    fn is_current_content_code(&self) -> bool {
+        false
+    }
 }
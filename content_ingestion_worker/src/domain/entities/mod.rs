@@ -1,2 +1,10 @@
+pub mod chunk_manifest;
+pub mod chunk_provenance;
+pub mod code_splitter;
+pub mod entity_recognizer;
 pub mod extracted_content;
+pub mod image_ocr;
 pub mod meta_read;
+pub mod near_duplicate;
+pub mod progress_event;
+pub mod resource_read;
@@ -0,0 +1,310 @@
+use std::collections::HashSet;
+
+use common::dtos::extracted_content::{ExtractedContentDto, NEAR_DUPLICATE_OF_METADATA_KEY};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::configuration::NearDuplicateSettings;
+
+/// Number of consecutive words of the shingles fingerprinted by `simhash`
+const SHINGLE_NB_WORDS: usize = 3;
+
+/// Fingerprints of the text contents extracted from the sources of a user, saved after each extraction
+/// to find the near duplicates of the next ones
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct NearDuplicateIndex {
+    pub version: u16,
+    /// From the oldest indexed content
+    pub fingerprints: Vec<ContentFingerprint>,
+}
+
+impl NearDuplicateIndex {
+    pub const CURRENT_VERSION: u16 = 1;
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ContentFingerprint {
+    pub content_id: Uuid,
+    pub source_meta_id: Uuid,
+    /// See `simhash`
+    pub simhash: u64,
+}
+
+/// Words of a text, lowercase, without punctuation
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// 64-bit SimHash of a text, over the shingles of its consecutive words
+///
+/// Nearly identical texts have fingerprints differing by a few bits (a word replaced in 100 words flips 4 to 8 bits), while unrelated texts differ by about half of their bits.
+/// The shingles are hashed with SHA-256, for the fingerprints to be stable between versions of the worker.
+pub fn simhash(words: &[String]) -> u64 {
+    let shingles: HashSet<String> = if words.len() < SHINGLE_NB_WORDS {
+        words.iter().cloned().collect()
+    } else {
+        words
+            .windows(SHINGLE_NB_WORDS)
+            .map(|shingle| shingle.join(" "))
+            .collect()
+    };
+
+    let mut bit_weights = [0i64; 64];
+    for shingle in shingles {
+        let digest = Sha256::digest(shingle.as_bytes());
+        let hash = u64::from_be_bytes(digest[..8].try_into().expect("SHA-256 has 32 bytes"));
+        for (bit, weight) in bit_weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    bit_weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0, |simhash, (bit, _)| simhash | (1 << bit))
+}
+
+/// Detects the text contents nearly identical to an already indexed content of the same user, during the extraction of a source
+///
+/// The contents are compared to the fingerprints of the contents of the other sources of the user, and of the contents
+/// of the source extracted before them. The previous fingerprints of the source are replaced by its extracted contents.
+/// A near duplicate is not indexed: it is only compared to the content it duplicates.
+#[derive(Debug)]
+pub struct NearDuplicateDetector {
+    /// Not set if the near duplicates are not detected
+    settings: Option<NearDuplicateSettings>,
+    source_meta_id: Uuid,
+    fingerprints: Vec<ContentFingerprint>,
+    nb_near_duplicates: u64,
+}
+
+impl NearDuplicateDetector {
+    pub fn new(
+        settings: &NearDuplicateSettings,
+        previous_index: Option<NearDuplicateIndex>,
+        source_meta_id: Uuid,
+    ) -> Self {
+        let fingerprints = previous_index
+            .map(|index| index.fingerprints)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|fingerprint| fingerprint.source_meta_id != source_meta_id)
+            .collect();
+
+        Self {
+            settings: Some(settings.clone()),
+            source_meta_id,
+            fingerprints,
+            nb_near_duplicates: 0,
+        }
+    }
+
+    /// Detector of the extractions not detecting the near duplicates
+    pub fn disabled(source_meta_id: Uuid) -> Self {
+        Self {
+            settings: None,
+            source_meta_id,
+            fingerprints: vec![],
+            nb_near_duplicates: 0,
+        }
+    }
+
+    /// Compares a text content to the indexed contents
+    ///
+    /// A near duplicate is tagged with the id of the closest content it duplicates, and is not embedded if configured so.
+    /// Otherwise the content is indexed.
+    pub fn check(&mut self, content: &mut ExtractedContentDto) {
+        let Some(settings) = &self.settings else {
+            return;
+        };
+        let words = words(&content.content);
+        if words.len() < settings.min_words {
+            return;
+        }
+
+        let simhash = simhash(&words);
+        let closest = self
+            .fingerprints
+            .iter()
+            .filter(|fingerprint| fingerprint.content_id != content.id)
+            .map(|fingerprint| (fingerprint, (fingerprint.simhash ^ simhash).count_ones()))
+            .filter(|(_, distance)| *distance <= settings.max_hamming_distance)
+            .min_by_key(|(_, distance)| *distance);
+
+        match (closest, content.metadata.as_object_mut()) {
+            (Some((duplicated, _)), Some(metadata)) => {
+                metadata.insert(
+                    NEAR_DUPLICATE_OF_METADATA_KEY.to_string(),
+                    json!(duplicated.content_id),
+                );
+                if settings.skip_embedding {
+                    content.skip_embedding = true;
+                }
+                self.nb_near_duplicates += 1;
+            }
+            _ => self.fingerprints.push(ContentFingerprint {
+                content_id: content.id,
+                source_meta_id: self.source_meta_id,
+                simhash,
+            }),
+        }
+    }
+
+    /// Number of contents tagged as near duplicates
+    pub fn nb_near_duplicates(&self) -> u64 {
+        self.nb_near_duplicates
+    }
+
+    /// Index of the fingerprints of the user, without the oldest ones beyond the maximum number of fingerprints
+    pub fn index(&self) -> NearDuplicateIndex {
+        let max_fingerprints = self
+            .settings
+            .as_ref()
+            .map_or(0, |settings| settings.max_fingerprints_per_user);
+        let nb_dropped = self.fingerprints.len().saturating_sub(max_fingerprints);
+
+        NearDuplicateIndex {
+            version: NearDuplicateIndex::CURRENT_VERSION,
+            fingerprints: self.fingerprints[nb_dropped..].to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "Call me Ishmael. Some years ago, never mind how long precisely, having little or no money in my purse, \
+        and nothing particular to interest me on shore, I thought I would sail about a little and see the watery part of the world. \
+        It is a way I have of driving off the spleen and regulating the circulation. Whenever I find myself growing grim about the mouth; \
+        whenever it is a damp, drizzly November in my soul; whenever I find myself involuntarily pausing before coffin warehouses, \
+        then, I account it high time to get to sea as soon as I can.";
+
+    const OTHER_TEXT: &str = "It was the best of times, it was the worst of times, it was the age of wisdom, it was the age of foolishness, \
+        it was the epoch of belief, it was the epoch of incredulity, it was the season of Light, it was the season of Darkness, \
+        it was the spring of hope, it was the winter of despair, we had everything before us, we had nothing before us, \
+        we were all going direct to Heaven, we were all going direct the other way.";
+
+    fn settings(skip_embedding: bool) -> NearDuplicateSettings {
+        NearDuplicateSettings {
+            enabled: true,
+            max_hamming_distance: 8,
+            min_words: 20,
+            skip_embedding,
+            max_fingerprints_per_user: 100,
+        }
+    }
+
+    fn extracted_content(id: u128, content: &str) -> ExtractedContentDto {
+        ExtractedContentDto {
+            version: ExtractedContentDto::CURRENT_VERSION,
+            id: Uuid::from_u128(id),
+            metadata: json!({ "file": "user/v1" }),
+            content: content.to_string(),
+            skip_embedding: false,
+            is_code: false,
+            source_meta_id: None,
+            fulltext_shard: 0,
+            provenance: None,
+        }
+    }
+
+    #[test]
+    fn simhash_of_nearly_identical_texts_differ_by_a_few_bits() {
+        let fingerprint = simhash(&words(TEXT));
+        let near_fingerprint = simhash(&words(&TEXT.replace("November", "December")));
+        let other_fingerprint = simhash(&words(OTHER_TEXT));
+
+        assert_eq!(fingerprint, simhash(&words(&TEXT.to_uppercase())));
+        assert!((fingerprint ^ near_fingerprint).count_ones() <= 8);
+        assert!((fingerprint ^ other_fingerprint).count_ones() > 16);
+    }
+
+    #[test]
+    fn near_duplicates_of_the_indexed_contents_are_tagged_and_not_embedded() {
+        let mut detector = NearDuplicateDetector::new(&settings(true), None, Uuid::from_u128(1));
+
+        let mut original = extracted_content(10, TEXT);
+        let mut other = extracted_content(11, OTHER_TEXT);
+        let mut near_duplicate = extracted_content(12, &TEXT.replace("November", "December"));
+        let mut short = extracted_content(13, "Call me Ishmael.");
+        for content in [&mut original, &mut other, &mut near_duplicate, &mut short] {
+            detector.check(content);
+        }
+
+        assert_eq!(
+            near_duplicate.metadata[NEAR_DUPLICATE_OF_METADATA_KEY],
+            json!(Uuid::from_u128(10))
+        );
+        assert!(near_duplicate.skip_embedding);
+        for content in [&original, &other, &short] {
+            assert!(content
+                .metadata
+                .get(NEAR_DUPLICATE_OF_METADATA_KEY)
+                .is_none());
+            assert!(!content.skip_embedding);
+        }
+        assert_eq!(detector.nb_near_duplicates(), 1);
+        // Neither the near duplicate nor the short content are indexed
+        assert_eq!(detector.index().fingerprints.len(), 2);
+    }
+
+    #[test]
+    fn fingerprints_of_the_source_extracted_again_are_replaced() {
+        let mut first_extraction =
+            NearDuplicateDetector::new(&settings(false), None, Uuid::from_u128(1));
+        first_extraction.check(&mut extracted_content(10, TEXT));
+
+        // The content of the source extracted again is not a near duplicate of its previous version
+        let mut detector = NearDuplicateDetector::new(
+            &settings(false),
+            Some(first_extraction.index()),
+            Uuid::from_u128(1),
+        );
+        let mut content = extracted_content(10, TEXT);
+        detector.check(&mut content);
+        assert!(content
+            .metadata
+            .get(NEAR_DUPLICATE_OF_METADATA_KEY)
+            .is_none());
+
+        // The content of another source is
+        let mut detector = NearDuplicateDetector::new(
+            &settings(false),
+            Some(detector.index()),
+            Uuid::from_u128(2),
+        );
+        let mut copy = extracted_content(20, TEXT);
+        detector.check(&mut copy);
+        assert_eq!(
+            copy.metadata[NEAR_DUPLICATE_OF_METADATA_KEY],
+            json!(Uuid::from_u128(10))
+        );
+        assert!(!copy.skip_embedding);
+    }
+
+    #[test]
+    fn index_drops_the_oldest_fingerprints_beyond_the_maximum() {
+        let settings = NearDuplicateSettings {
+            max_fingerprints_per_user: 1,
+            ..settings(false)
+        };
+        let mut detector = NearDuplicateDetector::new(&settings, None, Uuid::from_u128(1));
+        detector.check(&mut extracted_content(10, TEXT));
+        detector.check(&mut extracted_content(11, OTHER_TEXT));
+
+        let index = detector.index();
+        assert_eq!(index.fingerprints.len(), 1);
+        assert_eq!(index.fingerprints[0].content_id, Uuid::from_u128(11));
+    }
+}
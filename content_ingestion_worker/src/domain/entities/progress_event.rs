@@ -0,0 +1,117 @@
+use common::dtos::extraction_progress::{ExtractionProgressDto, ExtractionStatusDto};
+use uuid::Uuid;
+
+/// Rough number of bytes of a source file per extracted chunk of content
+///
+/// Around 100 words per chunk: the text proportion of a source file depends on its type,
+/// the total number of chunks is only an estimate until the extraction is completed.
+pub const ESTIMATED_SOURCE_BYTES_PER_CHUNK: u64 = 600;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExtractionStatus {
+    InProgress,
+    Completed,
+    Failed,
+}
+
+/// Progress of the extraction of the contents of a source file
+#[derive(Debug, Clone)]
+pub struct ProgressEvent {
+    pub source_meta_id: Uuid,
+    pub status: ExtractionStatus,
+    /// Index of the next chunk to extract: the number of chunks extracted so far
+    pub chunk_index: u64,
+    pub total_estimated_chunks: u64,
+    /// Number of bytes of text extracted so far
+    pub bytes_processed: u64,
+}
+
+impl ProgressEvent {
+    pub fn new(source_meta_id: Uuid) -> Self {
+        Self {
+            source_meta_id,
+            status: ExtractionStatus::InProgress,
+            chunk_index: 0,
+            total_estimated_chunks: 0,
+            bytes_processed: 0,
+        }
+    }
+
+    /// Estimates the total number of chunks from the size of the source file
+    pub fn estimate_total_chunks(&mut self, source_size_bytes: u64) {
+        self.total_estimated_chunks = (source_size_bytes / ESTIMATED_SOURCE_BYTES_PER_CHUNK)
+            .max(1)
+            .max(self.chunk_index);
+    }
+
+    /// Records a newly extracted chunk, of a given size in bytes
+    pub fn record_chunk(&mut self, nb_bytes: usize) {
+        self.chunk_index += 1;
+        self.bytes_processed += nb_bytes as u64;
+        self.total_estimated_chunks = self.total_estimated_chunks.max(self.chunk_index);
+    }
+
+    /// The total number of chunks is then known
+    pub fn complete(&mut self) {
+        self.status = ExtractionStatus::Completed;
+        self.total_estimated_chunks = self.chunk_index;
+    }
+
+    pub fn fail(&mut self) {
+        self.status = ExtractionStatus::Failed;
+    }
+}
+
+impl From<ExtractionStatus> for ExtractionStatusDto {
+    fn from(value: ExtractionStatus) -> Self {
+        match value {
+            ExtractionStatus::InProgress => ExtractionStatusDto::InProgress,
+            ExtractionStatus::Completed => ExtractionStatusDto::Completed,
+            ExtractionStatus::Failed => ExtractionStatusDto::Failed,
+        }
+    }
+}
+
+impl From<&ProgressEvent> for ExtractionProgressDto {
+    fn from(value: &ProgressEvent) -> Self {
+        ExtractionProgressDto {
+            source_meta_id: value.source_meta_id,
+            status: value.status.into(),
+            chunk_index: value.chunk_index,
+            total_estimated_chunks: value.total_estimated_chunks,
+            bytes_processed: value.bytes_processed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn on_recorded_chunks_it_should_keep_the_estimate_above_the_number_of_chunks() {
+        let mut progress = ProgressEvent::new(Uuid::new_v4());
+        progress.estimate_total_chunks(2 * ESTIMATED_SOURCE_BYTES_PER_CHUNK);
+        assert_eq!(progress.total_estimated_chunks, 2);
+
+        for _ in 0..3 {
+            progress.record_chunk(10);
+        }
+
+        assert_eq!(progress.chunk_index, 3);
+        assert_eq!(progress.bytes_processed, 30);
+        assert_eq!(progress.total_estimated_chunks, 3);
+    }
+
+    #[test]
+    fn on_completion_it_should_set_the_exact_number_of_chunks() {
+        let mut progress = ProgressEvent::new(Uuid::new_v4());
+        progress.estimate_total_chunks(10 * ESTIMATED_SOURCE_BYTES_PER_CHUNK);
+        progress.record_chunk(10);
+
+        progress.complete();
+
+        assert_eq!(progress.status, ExtractionStatus::Completed);
+        assert_eq!(progress.total_estimated_chunks, 1);
+    }
+}
@@ -0,0 +1,18 @@
+/// A resource (image etc.) bundled with a source, referenced from its content
+#[derive(Debug)]
+pub struct Resource {
+    /// Path of the resource inside the source
+    pub path: String,
+    pub content: Vec<u8>,
+}
+
+/// Access to the resources referenced from the content of the current read
+pub trait ResourceRead {
+    /// Reads a resource from a reference found in the current content (the `src` of an `<img>` for ex)
+    ///
+    /// # Returns
+    /// None if the source does not bundle resources, or if the reference could not be resolved
+    fn read_resource(&mut self, _reference: &str) -> Option<Resource> {
+        None
+    }
+}
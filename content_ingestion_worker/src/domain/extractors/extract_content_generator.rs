@@ -1,3 +1,4 @@
+use common::dtos::extract_content_job::ChunkSplittingDto;
 use common::helper::error_chain_fmt;
 use futures::Future;
 use genawaiter::{
@@ -14,6 +15,34 @@ pub const DEFAULT_NB_WORDS_PER_YIELD: usize = 100;
 
 const SPECIAL_CHARS_FOR_COUNTING_WORDS: [char; 6] = [',', '.', ';', ':', '?', '!'];
 
+const SENTENCE_END_CHARS: [char; 3] = ['.', '?', '!'];
+
+/// Upper bound of the number of words of a content split at sentence ends, as a factor of its number of words
+pub const SENTENCES_MAX_NB_WORDS_FACTOR: usize = 2;
+
+/// How the text read is split into extracted contents
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChunkSplitting {
+    /// Yields as soon as the number of words per yield is reached, even in the middle of a sentence
+    #[default]
+    Words,
+    /// Once the number of words per yield is reached, yields at the end of the current sentence.
+    /// A content without sentence end is yielded at `max_nb_words`.
+    Sentences { max_nb_words: usize },
+}
+
+impl ChunkSplitting {
+    /// Splitting requested by an extraction job, for contents of a given number of words
+    pub fn from_dto(chunk_splitting: ChunkSplittingDto, nb_words_per_yield: usize) -> Self {
+        match chunk_splitting {
+            ChunkSplittingDto::Words => ChunkSplitting::Words,
+            ChunkSplittingDto::Sentences => ChunkSplitting::Sentences {
+                max_nb_words: nb_words_per_yield * SENTENCES_MAX_NB_WORDS_FACTOR,
+            },
+        }
+    }
+}
+
 #[derive(Debug)]
 enum CharState {
     None,
@@ -45,6 +74,7 @@ impl std::fmt::Debug for ExtractContentGeneratorError {
 /// * `reader`: reader from which the content is read from
 /// * `nb_words_per_yield`: limit number of words triggering a new yield (length of an extracted content).
 ///      Default to `DEFAULT_NB_WORDS_PER_YIELD`.
+/// * `chunk_splitting`: whether a content is yielded as soon as its limit is reached, or at the next sentence end.
 ///
 /// # Returns
 /// A generator that progressively yields `ExtractedContent`s read from the reader.
@@ -57,6 +87,7 @@ impl std::fmt::Debug for ExtractContentGeneratorError {
 pub fn extract_content_generator<'box_lt, ReaderType: Read + MetaRead + 'box_lt>(
     reader: &'box_lt mut ReaderType,
     nb_words_per_yield: Option<usize>,
+    chunk_splitting: ChunkSplitting,
 ) -> Pin<
     Box<
         Gen<
@@ -68,7 +99,11 @@ pub fn extract_content_generator<'box_lt, ReaderType: Read + MetaRead + 'box_lt>
 > {
     let nb_words_per_yield = nb_words_per_yield.unwrap_or(DEFAULT_NB_WORDS_PER_YIELD);
     let mut previous_metadata = JsonValue::Null;
+    let mut previous_skip_embedding = false;
+    let mut previous_is_code = false;
     let mut current_extracted_content = String::new();
+    // Offset of the current content in the text read with the same metadata, in characters
+    let mut current_offset: u64 = 0;
     let mut current_nb_words = 0;
     let mut previous_char_state = CharState::None;
     // Arbitrary size for the buffer, could be fine tuned
@@ -96,15 +131,21 @@ pub fn extract_content_generator<'box_lt, ReaderType: Read + MetaRead + 'box_lt>
                             yield_!(ExtractedContent::new(
                                 current_extracted_content,
                                 previous_metadata.clone()
-                            ));
+                            )
+                            .with_skip_embedding(previous_skip_embedding)
+                            .with_is_code(previous_is_code)
+                            .with_offset(current_offset));
 
                             // Resets
                             current_nb_words = 0;
                             current_extracted_content = String::new();
                             previous_char_state = CharState::None;
                         }
+                        current_offset = 0;
 
                         previous_metadata = metadata;
+                        previous_skip_embedding = reader.should_skip_embedding();
+                        previous_is_code = reader.is_current_content_code();
                     }
 
                     let read_content = String::from_utf8(buf[0..read_len].to_vec())?;
@@ -167,21 +208,38 @@ pub fn extract_content_generator<'box_lt, ReaderType: Read + MetaRead + 'box_lt>
                             },
                         };
 
-                        current_extracted_content.push(current_char);
+                        let should_yield = match chunk_splitting {
+                            ChunkSplitting::Words => current_nb_words >= nb_words_per_yield,
+                            ChunkSplitting::Sentences { max_nb_words } => {
+                                current_nb_words >= max_nb_words
+                                    || (current_nb_words >= nb_words_per_yield
+                                        && ends_sentence(&previous_char_state, &current_char_state))
+                            }
+                        };
+
+                        // The space after a sentence end is not kept at the end of the content
+                        if !(should_yield && matches!(current_char_state, CharState::Space)) {
+                            current_extracted_content.push(current_char);
+                        }
 
-                        if current_nb_words >= nb_words_per_yield {
+                        if should_yield {
                             debug!(
                                 "Reached nb_words_per_yield current extracted content: {}",
                                 current_nb_words
                             );
 
+                            let nb_chars = current_extracted_content.chars().count() as u64;
                             yield_!(ExtractedContent::new(
                                 current_extracted_content,
                                 previous_metadata.clone()
-                            ));
+                            )
+                            .with_skip_embedding(previous_skip_embedding)
+                            .with_is_code(previous_is_code)
+                            .with_offset(current_offset));
 
                             // Resets
                             current_nb_words = 0;
+                            current_offset += nb_chars;
 
                             // The value is moved in yield_! above, it can't be clear() to keep the memory capacity of the vector/String
                             // Anyway, as the limit is based on the number of words and not the number of chars, keeping the memory
@@ -206,10 +264,12 @@ pub fn extract_content_generator<'box_lt, ReaderType: Read + MetaRead + 'box_lt>
             current_extracted_content.pop();
         }
 
-        yield_!(ExtractedContent::new(
-            current_extracted_content,
-            previous_metadata
-        ));
+        yield_!(
+            ExtractedContent::new(current_extracted_content, previous_metadata)
+                .with_skip_embedding(previous_skip_embedding)
+                .with_is_code(previous_is_code)
+                .with_offset(current_offset)
+        );
 
         Ok(())
     });
@@ -222,6 +282,12 @@ pub fn extract_content_generator<'box_lt, ReaderType: Read + MetaRead + 'box_lt>
     Box::pin(generator)
 }
 
+/// A sentence ends with a sentence end char followed by a space, so that "3.14" does not end a sentence
+fn ends_sentence(previous_char_state: &CharState, current_char_state: &CharState) -> bool {
+    matches!(previous_char_state, CharState::SpecialForCountingWords(c) if SENTENCE_END_CHARS.contains(c))
+        && matches!(current_char_state, CharState::Space)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::domain::readers::simple_metadata_reader::{
@@ -238,7 +304,8 @@ mod tests {
         let content = "";
         let buf_reader = BufReader::new(content.as_bytes());
         let mut simple_reader = SimpleMetadataReader::new(buf_reader, None);
-        let mut generator = extract_content_generator(&mut simple_reader, Some(100));
+        let mut generator =
+            extract_content_generator(&mut simple_reader, Some(100), ChunkSplitting::Words);
 
         // Checks empty yield
         let extracted_content = match generator.as_mut().resume() {
@@ -260,7 +327,8 @@ mod tests {
         let content = "Test some 1 yield text";
         let buf_reader = BufReader::new(content.as_bytes());
         let mut simple_reader = SimpleMetadataReader::new(buf_reader, None);
-        let mut generator = extract_content_generator(&mut simple_reader, Some(100));
+        let mut generator =
+            extract_content_generator(&mut simple_reader, Some(100), ChunkSplitting::Words);
 
         // Checks 1 yield
         let extracted_content = match generator.as_mut().resume() {
@@ -277,6 +345,28 @@ mod tests {
         assert!(matches!(extracted_result, Ok(())));
     }
 
+    #[test]
+    fn on_source_fitting_in_x_yields_it_should_extract_contents_with_their_offsets() {
+        let content = "one two three four five six";
+        let buf_reader = BufReader::new(content.as_bytes());
+        let mut simple_reader = SimpleMetadataReader::new(buf_reader, None);
+        let mut generator =
+            extract_content_generator(&mut simple_reader, Some(3), ChunkSplitting::Words);
+
+        let mut offsets = vec![];
+        while let GeneratorState::Yielded(extracted_content) = generator.as_mut().resume() {
+            offsets.push((extracted_content.content, extracted_content.offset));
+        }
+
+        assert_eq!(
+            offsets,
+            vec![
+                ("one two three".to_string(), 0),
+                ("four five six".to_string(), 13),
+            ]
+        );
+    }
+
     #[test]
     fn on_source_fitting_in_x_yields_it_should_extract_in_x_yields_and_complete() {
         // Arranges: 8 words per sentence, except the last sentence.
@@ -291,7 +381,45 @@ mod tests {
 
         let buf_reader = BufReader::new(content.as_bytes());
         let mut simple_reader = SimpleMetadataReader::new(buf_reader, None);
-        let mut generator = extract_content_generator(&mut simple_reader, Some(8));
+        let mut generator =
+            extract_content_generator(&mut simple_reader, Some(8), ChunkSplitting::Words);
+
+        // Asserts each yield
+        for expected_content in expected_yielded_contents {
+            let yielded_extracted_content = match generator.as_mut().resume() {
+                GeneratorState::Yielded(content) => content,
+                _ => panic!("Unexpected generator state"),
+            };
+            assert_eq!(yielded_extracted_content.content.trim(), expected_content);
+        }
+
+        // Checks complete
+        let extracted_result = match generator.as_mut().resume() {
+            GeneratorState::Complete(result) => result,
+            _ => panic!("Unexpected generator state"),
+        };
+        assert!(matches!(extracted_result, Ok(())));
+    }
+
+    #[test]
+    fn on_sentences_splitting_it_should_yield_at_sentence_ends_within_the_max_nb_words() {
+        // Arranges: 8 words per yield, at most 12 words for a sentence
+        let expected_yielded_contents = vec![
+            "It is nice to finally meet you.",
+            "Would you like some coffee? I love coffee.",
+            "I drink it every morning. Do you want sugar?",
+            "Some sentences go on and on without any end in sight until",
+            "the very end",
+        ];
+        let content = expected_yielded_contents.join(" ");
+
+        let buf_reader = BufReader::new(content.as_bytes());
+        let mut simple_reader = SimpleMetadataReader::new(buf_reader, None);
+        let mut generator = extract_content_generator(
+            &mut simple_reader,
+            Some(8),
+            ChunkSplitting::Sentences { max_nb_words: 12 },
+        );
 
         // Asserts each yield
         for expected_content in expected_yielded_contents {
@@ -321,7 +449,8 @@ mod tests {
             buf_reader,
             Some(json!({ source_metadata_key: source_metadata_value })),
         );
-        let mut generator = extract_content_generator(&mut simple_reader, Some(100));
+        let mut generator =
+            extract_content_generator(&mut simple_reader, Some(100), ChunkSplitting::Words);
 
         // Checks 1 yield
         let extracted_content = match generator.as_mut().resume() {
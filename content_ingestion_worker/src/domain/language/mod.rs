@@ -0,0 +1,2 @@
+pub mod rake_keyword_extractor;
+pub mod stopwords_language_detector;
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use super::stopwords_language_detector::{is_any_language_stopword, language_stopwords};
+
+/// Longer phrases between two stopwords are not keywords
+const MAX_KEYWORD_NB_WORDS: usize = 3;
+/// Shorter words are not keywords on their own
+const MIN_SINGLE_WORD_CHARS: usize = 4;
+/// Frequent English words splitting the phrases, besides the stopwords detecting the languages
+const EXTRA_STOPWORDS: &[&str] = &[
+    "a", "an", "or", "if", "so", "no", "all", "any", "some", "such", "about", "into", "than",
+    "then", "there", "these", "those", "can", "could", "will", "would", "should", "been", "has",
+    "its", "their", "them", "he", "him", "i", "me", "my", "our", "us", "also", "very", "more",
+    "most",
+];
+
+/// Extracts the keywords of a text content with RAKE (Rapid Automatic Keyword Extraction)
+///
+/// The candidate keywords are the phrases between the stopwords and the punctuation of the content.
+/// Each word is scored by its degree (the number of words of the phrases it appears in) over its frequency,
+/// and a phrase by the sum of the scores of its words: the words appearing in longer phrases rank higher.
+///
+/// # Arguments
+/// * `language` - ISO 639-1 code of the detected language of the content. Without it, the stopwords of all the detected languages are used.
+///
+/// # Returns
+/// Up to `max_keywords` lowercase keywords, from the most relevant
+pub fn extract_keywords(content: &str, language: Option<&str>, max_keywords: usize) -> Vec<String> {
+    if max_keywords == 0 {
+        return vec![];
+    }
+    let stopwords = language.and_then(language_stopwords);
+    let is_stopword = |word: &str| {
+        EXTRA_STOPWORDS.contains(&word)
+            || match stopwords {
+                Some(stopwords) => stopwords.contains(&word),
+                None => is_any_language_stopword(word),
+            }
+    };
+
+    let phrases: Vec<Vec<String>> = candidate_phrases(content, is_stopword)
+        .into_iter()
+        .filter(|phrase| is_keyword_candidate(phrase))
+        .collect();
+
+    let mut frequencies: HashMap<&str, f32> = HashMap::new();
+    let mut degrees: HashMap<&str, f32> = HashMap::new();
+    for phrase in &phrases {
+        for word in phrase {
+            *frequencies.entry(word).or_default() += 1.0;
+            *degrees.entry(word).or_default() += phrase.len() as f32;
+        }
+    }
+
+    let mut scored_keywords: HashMap<String, f32> = HashMap::new();
+    for phrase in &phrases {
+        let score = phrase
+            .iter()
+            .map(|word| degrees[word.as_str()] / frequencies[word.as_str()])
+            .sum();
+        scored_keywords.insert(phrase.join(" "), score);
+    }
+
+    let mut scored_keywords: Vec<(String, f32)> = scored_keywords.into_iter().collect();
+    scored_keywords.sort_by(|(keyword_a, score_a), (keyword_b, score_b)| {
+        score_b.total_cmp(score_a).then(keyword_a.cmp(keyword_b))
+    });
+
+    scored_keywords
+        .into_iter()
+        .take(max_keywords)
+        .map(|(keyword, _)| keyword)
+        .collect()
+}
+
+/// Phrases of lowercase words between the stopwords and the punctuation of a content
+fn candidate_phrases(content: &str, is_stopword: impl Fn(&str) -> bool) -> Vec<Vec<String>> {
+    let mut phrases = vec![];
+
+    // The punctuation ends a phrase, but not the hyphens and apostrophes within words
+    for fragment in content
+        .split(|c: char| !(c.is_alphanumeric() || c.is_whitespace() || c == '-' || c == '\''))
+    {
+        let mut phrase = vec![];
+        for word in fragment.split_whitespace() {
+            let word = word
+                .trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase();
+            if word.is_empty() {
+                continue;
+            }
+            if is_stopword(&word) {
+                phrases.push(std::mem::take(&mut phrase));
+            } else {
+                phrase.push(word);
+            }
+        }
+        phrases.push(phrase);
+    }
+
+    phrases
+}
+
+fn is_keyword_candidate(phrase: &[String]) -> bool {
+    match phrase {
+        [] => false,
+        [word] => {
+            word.chars().count() >= MIN_SINGLE_WORD_CHARS && !word.chars().all(char::is_numeric)
+        }
+        phrase => phrase.len() <= MAX_KEYWORD_NB_WORDS,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keywords_are_the_phrases_between_stopwords_from_the_highest_score() {
+        let content = "The white whale was hunted by Captain Ahab. Captain Ahab hunted the white whale with a harpoon.";
+
+        let keywords = extract_keywords(content, Some("en"), 10);
+
+        assert_eq!(
+            keywords,
+            vec![
+                "captain ahab hunted",
+                "captain ahab",
+                "white whale",
+                "hunted",
+                "harpoon"
+            ]
+        );
+    }
+
+    #[test]
+    fn keywords_are_limited_and_skip_short_words_and_numbers() {
+        let content = "In 1851, Melville wrote about the sea, and about a ship.";
+
+        assert_eq!(
+            extract_keywords(content, Some("en"), 2),
+            vec!["melville wrote", "ship"]
+        );
+        assert!(extract_keywords(content, Some("en"), 0).is_empty());
+    }
+}
@@ -0,0 +1,147 @@
+/// Minimum number of words of a content for its language to be detected
+const MIN_NB_WORDS: usize = 5;
+
+/// Minimum ratio of the words of a content being stopwords of the detected language
+const MIN_STOPWORDS_RATIO: f32 = 0.1;
+
+/// Most frequent words of the detected languages, by ISO 639-1 code
+///
+/// A word can be a stopword of several languages: the language with the most stopwords in a content wins.
+const STOPWORDS: [(&str, &[&str]); 7] = [
+    (
+        "en",
+        &[
+            "the", "and", "of", "to", "is", "in", "that", "it", "was", "for", "with", "as", "his",
+            "on", "be", "at", "by", "this", "had", "not", "are", "but", "from", "they", "you",
+            "which", "she", "have", "were", "her", "we", "what",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "le", "la", "les", "et", "des", "est", "une", "un", "du", "que", "qui", "dans", "pour",
+            "pas", "sur", "il", "elle", "ne", "au", "avec", "se", "ce", "sont", "mais", "nous",
+            "vous", "je", "était", "aux",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "der", "die", "und", "das", "ist", "nicht", "ein", "eine", "zu", "den", "mit", "sich",
+            "des", "auf", "für", "im", "dem", "es", "von", "sie", "ich", "auch", "wie", "war",
+            "wird", "aber", "oder", "aus", "er", "einem", "einer", "eines", "nach", "bei", "hat",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "el", "la", "los", "las", "y", "que", "de", "en", "un", "una", "es", "por", "con",
+            "para", "no", "se", "del", "al", "lo", "como", "más", "pero", "su", "sus", "fue",
+            "está",
+        ],
+    ),
+    (
+        "it",
+        &[
+            "il", "la", "di", "che", "e", "un", "una", "per", "non", "con", "sono", "del", "della",
+            "nel", "gli", "le", "si", "è", "da", "ma", "come", "anche", "più", "questo", "alla",
+            "era",
+        ],
+    ),
+    (
+        "pt",
+        &[
+            "o", "a", "os", "as", "de", "que", "e", "do", "da", "em", "um", "uma", "para", "com",
+            "não", "por", "se", "dos", "das", "no", "na", "mais", "foi", "ao", "é", "mas", "como",
+        ],
+    ),
+    (
+        "nl",
+        &[
+            "de", "het", "een", "en", "van", "is", "dat", "niet", "op", "te", "zijn", "voor",
+            "met", "die", "ook", "er", "maar", "om", "aan", "bij", "was", "wat", "naar", "ik",
+            "je", "hij",
+        ],
+    ),
+];
+
+/// Stopwords of a detected language, by ISO 639-1 code, if it is one of the detected languages
+pub fn language_stopwords(language: &str) -> Option<&'static [&'static str]> {
+    STOPWORDS
+        .iter()
+        .find(|(stopwords_language, _)| *stopwords_language == language)
+        .map(|(_, stopwords)| *stopwords)
+}
+
+/// Whether a lowercase word is a stopword of any of the detected languages
+pub fn is_any_language_stopword(word: &str) -> bool {
+    STOPWORDS
+        .iter()
+        .any(|(_, stopwords)| stopwords.contains(&word))
+}
+
+/// Detects the language of a text content from its stopwords
+///
+/// Only a handful of languages written with the latin alphabet are detected. Stopwords are enough
+/// to tell them apart on the contents of about a hundred words yielded by the extraction.
+///
+/// # Returns
+/// The ISO 639-1 code of the language, or `None` if the content is too short or too ambiguous
+pub fn detect_language(content: &str) -> Option<&'static str> {
+    let words: Vec<String> = content
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.len() < MIN_NB_WORDS {
+        return None;
+    }
+
+    let mut scores: Vec<(&'static str, usize)> = STOPWORDS
+        .iter()
+        .map(|(language, stopwords)| {
+            let nb_stopwords = words
+                .iter()
+                .filter(|word| stopwords.contains(&word.as_str()))
+                .count();
+            (*language, nb_stopwords)
+        })
+        .collect();
+    scores.sort_by(|(_, score), (_, other_score)| other_score.cmp(score));
+
+    let (language, best_score) = scores[0];
+    let second_score = scores[1].1;
+    if best_score == second_score || (best_score as f32) < words.len() as f32 * MIN_STOPWORDS_RATIO
+    {
+        return None;
+    }
+
+    Some(language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn on_text_contents_it_should_detect_their_language() {
+        let contents = [
+            ("en", "It was the best of times, it was the worst of times, it was the age of wisdom."),
+            ("fr", "Longtemps, je me suis couché de bonne heure. Parfois, à peine ma bougie éteinte, mes yeux se fermaient si vite que je n'avais pas le temps de me dire."),
+            ("de", "Als Gregor Samsa eines Morgens aus unruhigen Träumen erwachte, fand er sich in seinem Bett zu einem ungeheueren Ungeziefer verwandelt."),
+            ("es", "En un lugar de la Mancha, de cuyo nombre no quiero acordarme, no ha mucho tiempo que vivía un hidalgo de los de lanza en astillero."),
+            ("it", "Nel mezzo del cammin di nostra vita mi ritrovai per una selva oscura, ché la diritta via era smarrita."),
+        ];
+
+        for (language, content) in contents {
+            assert_eq!(detect_language(content), Some(language), "{}", content);
+        }
+    }
+
+    #[test]
+    fn on_short_or_unknown_contents_it_should_not_detect_a_language() {
+        assert_eq!(detect_language("The end"), None);
+        assert_eq!(detect_language("fn main() { println!(\"{}\", x); }"), None);
+        assert_eq!(detect_language("12 34 56 78 90 12 34"), None);
+    }
+}
@@ -1,3 +1,8 @@
+pub mod enrichment;
 pub mod entities;
 pub mod extractors;
+pub mod language;
+pub mod ocr;
 pub mod readers;
+pub mod redaction;
+pub mod splitters;
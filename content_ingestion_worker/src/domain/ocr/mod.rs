@@ -0,0 +1 @@
+pub mod tesseract_cli_ocr;
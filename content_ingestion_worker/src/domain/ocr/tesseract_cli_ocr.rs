@@ -0,0 +1,53 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use crate::domain::entities::image_ocr::{ImageOcr, ImageOcrError};
+
+/// Recognizes text in images by running the `tesseract` command line
+///
+/// The image is given on the standard input and the text is read from the standard output,
+/// so no temporary file is needed. `tesseract` needs to be installed on the host.
+#[derive(Debug, Clone)]
+pub struct TesseractCliOcr {
+    /// Path or name of the `tesseract` executable
+    command: String,
+    /// Tesseract language(s) of the text to recognize. Ex: `eng` or `eng+fra`
+    language: String,
+}
+
+impl TesseractCliOcr {
+    pub fn new(command: &str, language: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            language: language.to_string(),
+        }
+    }
+}
+
+impl ImageOcr for TesseractCliOcr {
+    #[tracing::instrument(name = "Recognizing text with tesseract", skip(self, image))]
+    fn recognize(&self, image: &[u8]) -> Result<String, ImageOcrError> {
+        let mut child = Command::new(&self.command)
+            .args(["stdin", "stdout", "-l", &self.language])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // Dropping stdin once written closes it, letting tesseract know the whole image was sent
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(image)?;
+        }
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(ImageOcrError::RecognitionError(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
@@ -0,0 +1,75 @@
+/// Content cached by a reader, read as UTF-8 bytes without splitting a char over two reads
+///
+/// The readers of sources split in contents (paragraphs, cells, rows, ...) cache their current content in it,
+/// and go to the next content once it is read.
+#[derive(Debug, Default)]
+pub struct BufferedContent {
+    chars: Vec<char>,
+    char_index: usize,
+}
+
+impl BufferedContent {
+    pub fn new(content: impl Iterator<Item = char>) -> Self {
+        Self {
+            chars: content.collect(),
+            char_index: 0,
+        }
+    }
+
+    /// Number of chars of the content
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    /// Whether all the chars of the content were read
+    pub fn is_read(&self) -> bool {
+        self.char_index >= self.chars.len()
+    }
+
+    /// Fills up as much as possible of a buffer with the next chars of the content
+    ///
+    /// # Returns
+    /// The number of bytes written in the buffer: a buffer of less than 4 bytes may not fit the next char
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let mut nb_bytes = 0;
+
+        while let Some(char) = self.chars.get(self.char_index) {
+            let char_len = char.len_utf8();
+            if nb_bytes + char_len > buf.len() {
+                // Not enough space in the buffer to fill the current char
+                break;
+            }
+
+            char.encode_utf8(&mut buf[nb_bytes..]);
+            nb_bytes += char_len;
+            self.char_index += 1;
+        }
+
+        nb_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_is_read_without_splitting_its_chars() {
+        let mut content = BufferedContent::new("l'été 🐋".chars());
+        let mut buf = [0; 4];
+        let mut read = vec![];
+
+        while !content.is_read() {
+            let nb_bytes = content.read(&mut buf);
+            assert!(nb_bytes > 0);
+            read.push(String::from_utf8(buf[..nb_bytes].to_vec()).unwrap());
+        }
+
+        assert_eq!(read, vec!["l'é", "té ", "🐋"]);
+        assert_eq!(content.read(&mut buf), 0);
+    }
+}
@@ -0,0 +1,394 @@
+use common::helper::error_chain_fmt;
+use serde_json::{json, Map, Value as JsonValue};
+use std::{
+    io::{Cursor, Read},
+    path::{Component, Path},
+};
+use tracing::info;
+
+use crate::domain::{
+    entities::{
+        code_splitter::{CodeChunk, CodeLanguage, CodeSplitter, CodeSplitterError},
+        meta_read::MetaRead,
+    },
+    readers::{buffered_content::BufferedContent, zip_archive},
+};
+
+const CODE_READER_META_KEY: &str = "code";
+const CODE_READER_META_KEY_DEFAULT_INITIAL: &str = "initial";
+const CODE_READER_META_KEY_FILE_PATH: &str = "file_path";
+const CODE_READER_META_KEY_LANGUAGE: &str = "language";
+const CODE_READER_META_KEY_SYMBOL: &str = "symbol";
+const CODE_READER_META_KEY_SYMBOL_KIND: &str = "symbol_kind";
+const CODE_READER_META_KEY_START_LINE: &str = "start_line";
+const CODE_READER_META_KEY_END_LINE: &str = "end_line";
+
+/// Directories of a repository that do not contain the repository own source code
+const IGNORED_DIRECTORIES: [&str; 5] = ["node_modules", "target", "__pycache__", "venv", "vendor"];
+
+/// A chunk of code with the file it comes from
+#[derive(Debug)]
+struct FileCodeChunk {
+    file_path: String,
+    language: CodeLanguage,
+    chunk: CodeChunk,
+}
+
+/// Reader for source code: a single source file, or a repository uploaded as a zip archive
+///
+/// Source files are split along their symbols (functions, classes etc.) by a `CodeSplitter`,
+/// and each chunk is read separately. The language, file path and symbol of the currently read chunk
+/// are kept in the metadata.
+///
+/// Files from an archive in an unsupported language, or in hidden or dependencies directories, are ignored.
+pub struct CodeReader {
+    chunks: Vec<FileCodeChunk>,
+    current_chunk_index: usize,
+
+    current_content: BufferedContent,
+
+    // MetaRead
+    metadata: JsonValue,
+}
+
+#[derive(thiserror::Error)]
+pub enum CodeReaderError {
+    #[error(transparent)]
+    ReadError(#[from] std::io::Error),
+    #[error(transparent)]
+    ZipError(#[from] zip::result::ZipError),
+    #[error(transparent)]
+    CodeSplitterError(#[from] CodeSplitterError),
+    #[error("Unsupported source code language for file: {0}")]
+    UnsupportedLanguage(String),
+}
+
+impl std::fmt::Debug for CodeReaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl CodeReader {
+    /// Create a `CodeReader` from a source reader (implementing Read)
+    ///
+    /// The whole source is read in memory. A zip archive is detected from its magic number.
+    ///
+    /// # Params
+    /// - reader: source reader implementing `Read`
+    /// - file_name: name of the source file, used to detect the language of a single source file
+    /// - initial_meta: (optional) initial metadata as a JSON object
+    /// - code_splitter: splits each source file along its symbols
+    #[tracing::instrument(name = "Creating code reader", skip(reader, code_splitter))]
+    pub fn try_from_reader(
+        mut reader: impl Read,
+        file_name: &str,
+        initial_meta: Option<JsonValue>,
+        code_splitter: &dyn CodeSplitter,
+    ) -> Result<Self, CodeReaderError> {
+        let mut buf = Vec::<u8>::new();
+        reader.read_to_end(&mut buf)?;
+
+        let chunks = if zip_archive::is_zip_archive(&buf) {
+            split_archive(&buf, code_splitter)?
+        } else {
+            let language = CodeLanguage::from_file_path(file_name)
+                .ok_or_else(|| CodeReaderError::UnsupportedLanguage(file_name.to_string()))?;
+            let source = String::from_utf8_lossy(&buf);
+
+            split_file(file_name, language, &source, code_splitter)?
+        };
+
+        let initial_meta = initial_meta.unwrap_or(JsonValue::Null);
+        let metadata = match initial_meta {
+            JsonValue::Object(map) => json!(map),
+            JsonValue::Null => JsonValue::Null,
+            _ => json!({ CODE_READER_META_KEY_DEFAULT_INITIAL: initial_meta }),
+        };
+
+        info!(
+            "Code reader source: nb chunks: {}, initial metadata: {}",
+            chunks.len(),
+            metadata
+        );
+
+        Ok(Self {
+            chunks,
+            current_chunk_index: 0,
+            current_content: BufferedContent::default(),
+            metadata,
+        })
+    }
+
+    /// Gets content chunk by chunk
+    ///
+    /// # Returns
+    /// The number of chars cached. 0 if no more content is available.
+    fn go_next_content(&mut self) -> usize {
+        self.current_content = BufferedContent::default();
+
+        let Some(file_chunk) = self.chunks.get(self.current_chunk_index) else {
+            return 0;
+        };
+        self.current_chunk_index += 1;
+
+        // Lines are separated by spaces, as line feeds are trimmed from the extracted contents.
+        // Separates 2 successive chunks with a space.
+        let text = file_chunk
+            .chunk
+            .text
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ");
+        self.current_content = BufferedContent::new(format!("{} ", text).chars());
+
+        let chunk_metadata = [
+            (CODE_READER_META_KEY_FILE_PATH, json!(file_chunk.file_path)),
+            (
+                CODE_READER_META_KEY_LANGUAGE,
+                json!(file_chunk.language.as_str()),
+            ),
+            (
+                CODE_READER_META_KEY_SYMBOL,
+                json!(file_chunk.chunk.symbol_name),
+            ),
+            (
+                CODE_READER_META_KEY_SYMBOL_KIND,
+                json!(file_chunk.chunk.symbol_kind),
+            ),
+            (
+                CODE_READER_META_KEY_START_LINE,
+                json!(file_chunk.chunk.start_line),
+            ),
+            (
+                CODE_READER_META_KEY_END_LINE,
+                json!(file_chunk.chunk.end_line),
+            ),
+        ];
+        for (key, value) in chunk_metadata {
+            self.update_metadata(key, value);
+        }
+
+        self.current_content.len()
+    }
+
+    /// Updates metadata as a JSON object
+    fn update_metadata(&mut self, key: &str, value: JsonValue) {
+        if let Some(map) = self.metadata.as_object_mut() {
+            map.insert(key.to_owned(), value);
+        } else {
+            let mut map = Map::new();
+            map.insert(key.to_owned(), value);
+            self.metadata = JsonValue::Object(map);
+        }
+    }
+}
+
+impl Read for CodeReader {
+    // Reads bytes as unicode scalar values
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        // There is no more chars to read from the current chunk, tries to get the next one
+        if self.current_content.is_read() && self.go_next_content() == 0 {
+            // No more to read
+            return Ok(0);
+        }
+
+        Ok(self.current_content.read(buf))
+    }
+}
+
+impl MetaRead for CodeReader {
+    fn get_current_metadata(&self) -> JsonValue {
+        json!({ CODE_READER_META_KEY: self.metadata.clone() })
+    }
+
+    fn is_current_content_code(&self) -> bool {
+        true
+    }
+}
+
+fn split_file(
+    file_path: &str,
+    language: CodeLanguage,
+    source: &str,
+    code_splitter: &dyn CodeSplitter,
+) -> Result<Vec<FileCodeChunk>, CodeSplitterError> {
+    Ok(code_splitter
+        .split(language, source)?
+        .into_iter()
+        .map(|chunk| FileCodeChunk {
+            file_path: file_path.to_string(),
+            language,
+            chunk,
+        })
+        .collect())
+}
+
+/// Splits every supported source file of a zip archive, in the archive order
+fn split_archive(
+    buf: &[u8],
+    code_splitter: &dyn CodeSplitter,
+) -> Result<Vec<FileCodeChunk>, CodeReaderError> {
+    let files = zip_archive::read_text_files(Cursor::new(buf), |path| {
+        !is_ignored_path(path) && CodeLanguage::from_file_path(path).is_some()
+    })?;
+
+    let mut chunks = vec![];
+    for file in files {
+        let Some(language) = CodeLanguage::from_file_path(&file.path) else {
+            continue;
+        };
+
+        chunks.extend(split_file(
+            &file.path,
+            language,
+            &file.content,
+            code_splitter,
+        )?);
+    }
+
+    Ok(chunks)
+}
+
+/// Hidden (`.git` for ex) and dependencies directories are ignored
+fn is_ignored_path(file_path: &str) -> bool {
+    Path::new(file_path)
+        .components()
+        .any(|component| match component {
+            Component::Normal(name) => {
+                let name = name.to_string_lossy();
+                name.starts_with('.') || IGNORED_DIRECTORIES.contains(&name.as_ref())
+            }
+            _ => false,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{
+        extractors::extract_content_generator::{extract_content_generator, ChunkSplitting},
+        splitters::tree_sitter_code_splitter::TreeSitterCodeSplitter,
+    };
+    use genawaiter::GeneratorState;
+    use std::io::{Cursor, Write};
+    use zip::write::FileOptions;
+
+    fn read_all(code_reader: &mut CodeReader) -> Vec<(String, JsonValue, bool)> {
+        let mut generator =
+            extract_content_generator(code_reader, Some(100), ChunkSplitting::Words);
+        let mut contents = vec![];
+
+        while let GeneratorState::Yielded(extracted_content) = generator.as_mut().resume() {
+            contents.push((
+                extracted_content.content.trim().to_owned(),
+                extracted_content.metadata,
+                extracted_content.is_code,
+            ));
+        }
+
+        contents
+    }
+
+    fn zip_archive(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(vec![]));
+
+        for (name, content) in files {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn on_single_source_file_it_should_read_chunks_with_symbol_metadata() {
+        let source = "fn first() {\n    1\n}\n\nfn second() {\n    2\n}\n";
+        let mut code_reader = CodeReader::try_from_reader(
+            source.as_bytes(),
+            "lib.rs",
+            None,
+            &TreeSitterCodeSplitter::new(),
+        )
+        .unwrap();
+
+        let contents = read_all(&mut code_reader);
+
+        assert_eq!(contents.len(), 2);
+        assert_eq!(contents[0].0, "fn first() { 1 }");
+        assert_eq!(
+            contents[1].1[CODE_READER_META_KEY],
+            json!({
+                "file_path": "lib.rs",
+                "language": "rust",
+                "symbol": "second",
+                "symbol_kind": "function",
+                "start_line": 5,
+                "end_line": 7,
+            })
+        );
+        assert!(contents.iter().all(|(_, _, is_code)| *is_code));
+    }
+
+    #[test]
+    fn on_unsupported_single_source_file_it_should_fail() {
+        let result = CodeReader::try_from_reader(
+            "some text".as_bytes(),
+            "notes.txt",
+            None,
+            &TreeSitterCodeSplitter::new(),
+        );
+
+        assert!(matches!(
+            result,
+            Err(CodeReaderError::UnsupportedLanguage(_))
+        ));
+    }
+
+    #[test]
+    fn on_archive_it_should_read_supported_files_not_ignored() {
+        let archive = zip_archive(&[
+            ("repo/src/main.rs", "fn main() {}\n"),
+            ("repo/scripts/build.py", "def build():\n    pass\n"),
+            ("repo/README.md", "# Repo\n"),
+            ("repo/.git/hooks/hook.py", "def hook():\n    pass\n"),
+            ("repo/target/debug/build.rs", "fn generated() {}\n"),
+        ]);
+
+        let mut code_reader = CodeReader::try_from_reader(
+            Cursor::new(archive),
+            "repo.zip",
+            Some(json!({ "source_initial_name": "repo.zip" })),
+            &TreeSitterCodeSplitter::new(),
+        )
+        .unwrap();
+
+        let contents = read_all(&mut code_reader);
+
+        let files_and_symbols: Vec<(&JsonValue, &JsonValue, &JsonValue)> = contents
+            .iter()
+            .map(|(_, metadata, _)| {
+                (
+                    &metadata[CODE_READER_META_KEY]["file_path"],
+                    &metadata[CODE_READER_META_KEY]["language"],
+                    &metadata[CODE_READER_META_KEY]["symbol"],
+                )
+            })
+            .collect();
+        assert_eq!(
+            files_and_symbols,
+            vec![
+                (&json!("repo/src/main.rs"), &json!("rust"), &json!("main")),
+                (
+                    &json!("repo/scripts/build.py"),
+                    &json!("python"),
+                    &json!("build")
+                ),
+            ]
+        );
+        assert_eq!(
+            contents[0].1[CODE_READER_META_KEY]["source_initial_name"],
+            json!("repo.zip")
+        );
+    }
+}
@@ -1,10 +1,18 @@
+use common::core::drm::{is_epub_drm_protected, EPUB_ENCRYPTION_PATH, EPUB_RIGHTS_PATH};
 use common::helper::error_chain_fmt;
 use epub::doc::{DocError, EpubDoc};
+use quick_xml::events::Event;
 use serde_json::{json, Map, Value as JsonValue};
 use std::io::{Read, Seek};
-use tracing::{debug, info};
+use tracing::{info, warn};
 
-use crate::domain::entities::meta_read::MetaRead;
+use crate::domain::{
+    entities::{
+        meta_read::MetaRead,
+        resource_read::{Resource, ResourceRead},
+    },
+    readers::zip_archive::normalize_path,
+};
 
 const EPUB_READER_META_KEY: &str = "epub";
 const EPUB_READER_META_KEY_DEFAULT_INITIAL: &str = "initial";
@@ -18,13 +26,16 @@ const EPUB_READER_META_KEY_DEFAULT_INITIAL: &str = "initial";
 ///
 /// [Seek](https://doc.rust-lang.org/stable/std/io/trait.Seek.html) implementation is needed
 ///
+/// A spine item that can not be read, or is not well-formed XHTML, is skipped: the other items are still read.
+/// The skipped items are kept with their errors, to be reported once the EPUB is read.
+///
 /// As we cannot get a reference to the inner reader of `EpubDoc`,
 /// we cannot currently compose with a `SourceReader` implementing `MetaRead`
 pub struct EpubReader<SourceReader: Read + Seek> {
     source: EpubDoc<SourceReader>,
 
-    // Avoids looping in EpubDoc reader
-    previous_content_id: String,
+    /// Spine items skipped until now, in the reading order
+    skipped_items: Vec<SkippedSpineItem>,
 
     current_content_chars: Vec<char>,
     current_char_index: usize,
@@ -40,6 +51,8 @@ pub struct EpubReader<SourceReader: Read + Seek> {
 pub enum EpubReaderError {
     #[error(transparent)]
     EpubDocError(#[from] DocError),
+    #[error("The EPUB is protected by a DRM")]
+    DrmProtected,
 }
 
 impl std::fmt::Debug for EpubReaderError {
@@ -53,6 +66,15 @@ pub enum NextContentError {
     Ended,
 }
 
+/// Spine item of an EPUB skipped by the reader
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedSpineItem {
+    /// Path of the item in the EPUB archive
+    pub path: String,
+    /// Why the item could not be read
+    pub error: String,
+}
+
 impl<SourceReader: Read + Seek> EpubReader<SourceReader> {
     /// Create an `EpubReader` from a source reader (implementing Read + Seek)
     ///
@@ -64,7 +86,14 @@ impl<SourceReader: Read + Seek> EpubReader<SourceReader> {
         reader: SourceReader,
         initial_meta: Option<JsonValue>,
     ) -> Result<Self, EpubReaderError> {
-        let source = EpubDoc::from_reader(reader)?;
+        let mut source = EpubDoc::from_reader(reader)?;
+
+        // The content of a protected EPUB is encrypted: it would be extracted as garbage
+        let has_rights = source.get_resource_by_path(EPUB_RIGHTS_PATH).is_some();
+        let encryption = source.get_resource_str_by_path(EPUB_ENCRYPTION_PATH);
+        if is_epub_drm_protected(has_rights, encryption.as_deref()) {
+            return Err(EpubReaderError::DrmProtected);
+        }
 
         let initial_meta = initial_meta.unwrap_or(JsonValue::Null);
         let metadata = match initial_meta {
@@ -77,7 +106,7 @@ impl<SourceReader: Read + Seek> EpubReader<SourceReader> {
 
         Ok(EpubReader {
             source,
-            previous_content_id: String::from(""),
+            skipped_items: vec![],
             current_content_chars: vec![],
             current_char_index: 0,
             metadata,
@@ -85,6 +114,11 @@ impl<SourceReader: Read + Seek> EpubReader<SourceReader> {
         })
     }
 
+    /// Spine items skipped until now, as they could not be read
+    pub fn skipped_items(&self) -> &[SkippedSpineItem] {
+        &self.skipped_items
+    }
+
     /// Updates metadata as a JSON object
     fn update_metadata(&mut self, key: &str, value: JsonValue) {
         if let Some(map) = self.metadata.as_object_mut() {
@@ -107,29 +141,28 @@ impl<SourceReader: Read + Seek> EpubReader<SourceReader> {
         self.current_char_index = 0;
 
         while content_len == 0 {
-            self.source.go_next();
+            // No more thing to read
+            if !self.source.go_next() {
+                self.is_read_completed = true;
+                return Ok(0);
+            }
 
-            // `source.get_current_path` and then try to read each file with a Reader ?
-            let (current_content, _cur_mime) = match self.source.get_current_str() {
+            let current_content = match self.source.get_current_str() {
+                Some((current_content, _cur_mime)) => current_content,
                 None => {
-                    // No more thing to read
-                    self.is_read_completed = true;
-                    return Ok(0);
+                    self.skip_current_item(
+                        "The item is missing from the archive or is not encoded in UTF-8",
+                    );
+                    continue;
                 }
-                Some(result) => result,
             };
 
-            // To avoid infinitely looping on the last content
-            let current_content_id = self.source.get_current_id().unwrap_or("".to_string());
-
-            if current_content_id.eq(&self.previous_content_id) {
-                debug!("Encountered the same content id: {}", current_content_id);
-                self.is_read_completed = true;
-                return Ok(0);
+            // A malformed item would stop the XML reader reading the following items
+            if let Err(error) = check_well_formed_xml(&current_content) {
+                self.skip_current_item(&error);
+                continue;
             }
 
-            self.previous_content_id = current_content_id;
-
             content_len = current_content.len();
             if content_len == 0 {
                 continue;
@@ -156,6 +189,47 @@ impl<SourceReader: Read + Seek> EpubReader<SourceReader> {
 
         Ok(content_len)
     }
+
+    /// Skips the current spine item, keeping why it could not be read
+    fn skip_current_item(&mut self, error: &str) {
+        let path = self
+            .source
+            .get_current_path()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        warn!(path, error, "Skipping an unreadable EPUB spine item");
+
+        self.skipped_items.push(SkippedSpineItem {
+            path,
+            error: error.to_string(),
+        });
+    }
+}
+
+/// Checks that the content of a spine item is well-formed XML: its tags are valid, matched and closed
+fn check_well_formed_xml(content: &str) -> Result<(), String> {
+    let mut reader = quick_xml::reader::Reader::from_str(content);
+    let mut nb_open_tags: usize = 0;
+
+    loop {
+        match reader.read_event() {
+            Err(error) => {
+                return Err(format!(
+                    "Malformed XHTML at position {}: {}",
+                    reader.buffer_position(),
+                    error
+                ))
+            }
+            Ok(Event::Start(_)) => nb_open_tags += 1,
+            Ok(Event::End(_)) => nb_open_tags = nb_open_tags.saturating_sub(1),
+            Ok(Event::Eof) if nb_open_tags > 0 => {
+                return Err(format!("Malformed XHTML: {} unclosed tags", nb_open_tags))
+            }
+            Ok(Event::Eof) => return Ok(()),
+            _ => (),
+        }
+    }
 }
 
 impl<SourceReader: Read + Seek> Read for EpubReader<SourceReader> {
@@ -217,12 +291,82 @@ impl<SourceReader: Read + Seek> MetaRead for EpubReader<SourceReader> {
     }
 }
 
+/// Reads the resources (images etc.) of the EPUB archive
+///
+/// References are resolved relatively to the path of the current chapter.
+impl<SourceReader: Read + Seek> ResourceRead for EpubReader<SourceReader> {
+    fn read_resource(&mut self, reference: &str) -> Option<Resource> {
+        // Remote and inline (`data:`) resources are not part of the archive
+        if reference.contains(':') {
+            return None;
+        }
+
+        // Removes any fragment or query
+        let reference = reference.split(['#', '?']).next().unwrap_or_default();
+        let chapter_path = self.source.get_current_path()?;
+        let path = normalize_path(chapter_path.parent()?.join(reference));
+
+        let content = self.source.get_resource_by_path(&path)?;
+        Some(Resource { path, content })
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::io::BufReader;
+    use std::io::{BufReader, Cursor, Write};
+    use zip::write::FileOptions;
 
     use super::*;
 
+    /// The 3 chapters sample, with a chapter replaced by a given content, or removed if `None`
+    fn sample_with_chapter_2(chapter_2: Option<&str>) -> Cursor<Vec<u8>> {
+        let file = std::fs::File::open("tests/resources/sample_3_chapters.epub").unwrap();
+        let mut archive = zip::ZipArchive::new(file).unwrap();
+        let mut writer = zip::ZipWriter::new(Cursor::new(vec![]));
+
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).unwrap();
+            let name = file.name().to_string();
+
+            if name == "OEBPS/chapter_2.xhtml" {
+                if let Some(chapter_2) = chapter_2 {
+                    writer.start_file(name, FileOptions::default()).unwrap();
+                    writer.write_all(chapter_2.as_bytes()).unwrap();
+                }
+            } else {
+                let mut content = vec![];
+                file.read_to_end(&mut content).unwrap();
+                writer.start_file(name, FileOptions::default()).unwrap();
+                writer.write_all(&content).unwrap();
+            }
+        }
+
+        let mut epub = writer.finish().unwrap();
+        epub.set_position(0);
+        epub
+    }
+
+    /// Reads the full EPUB, with the number of the chapter of each read content
+    fn read_chapters<R: Read + Seek>(reader: &mut EpubReader<R>) -> Vec<(String, JsonValue)> {
+        let mut chapters = vec![];
+        // Buf big enough to contain every chapter
+        let mut buf = [0; 8000];
+
+        loop {
+            let filling_len = reader.read(&mut buf).unwrap();
+            if filling_len == 0 {
+                break;
+            }
+
+            chapters.push((
+                String::from_utf8(buf[0..filling_len].to_vec()).unwrap(),
+                reader.get_current_metadata()[EPUB_READER_META_KEY]["chapter_number"].clone(),
+            ));
+        }
+
+        chapters
+    }
+
     #[test]
     fn on_correct_epub_it_creates_a_content_reader_with_metadata() {
         let file_name = "sample_3_chapters.epub";
@@ -266,4 +410,42 @@ mod tests {
         // Only 3 chapters in the sample
         assert_eq!(i, 3);
     }
+
+    #[test]
+    fn on_malformed_chapter_it_skips_it_and_reads_the_other_chapters() {
+        let epub = sample_with_chapter_2(Some(
+            "<html><body><h1>Chapter 2</h1><p>Some text in chapter 2</div></body></html>",
+        ));
+        let mut reader = EpubReader::from_reader(epub, None).unwrap();
+
+        let chapters = read_chapters(&mut reader);
+
+        assert_eq!(chapters.len(), 2);
+        assert!(chapters[0].0.contains("<h1>Chapter 1</h1>"));
+        assert!(chapters[1].0.contains("<h1>Chapter 3</h1>"));
+        assert_eq!(chapters[1].1, 3);
+
+        let skipped_items = reader.skipped_items();
+        assert_eq!(skipped_items.len(), 1);
+        assert_eq!(skipped_items[0].path, "OEBPS/chapter_2.xhtml");
+        assert!(skipped_items[0].error.contains("Malformed XHTML"));
+    }
+
+    #[test]
+    fn on_chapter_missing_from_the_archive_it_skips_it_and_reads_the_other_chapters() {
+        let mut reader = EpubReader::from_reader(sample_with_chapter_2(None), None).unwrap();
+
+        let chapters = read_chapters(&mut reader);
+
+        assert_eq!(chapters.len(), 2);
+        assert!(chapters[1].0.contains("<h1>Chapter 3</h1>"));
+        assert_eq!(reader.skipped_items().len(), 1);
+        assert_eq!(reader.skipped_items()[0].path, "OEBPS/chapter_2.xhtml");
+    }
+
+    #[test]
+    fn unclosed_tags_are_not_well_formed_xml() {
+        assert!(check_well_formed_xml("<html><body><p>Text</p></body></html>").is_ok());
+        assert!(check_well_formed_xml("<html><body><p>Text</p>").is_err());
+    }
 }
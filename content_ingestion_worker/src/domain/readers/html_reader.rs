@@ -0,0 +1,654 @@
+use common::helper::error_chain_fmt;
+use once_cell::sync::Lazy;
+use quick_xml::events::{BytesStart, Event};
+use regex::Regex;
+use serde_json::{json, Map, Value as JsonValue};
+use std::{collections::BTreeMap, io::Read};
+use tracing::{info, warn};
+
+use crate::domain::{entities::meta_read::MetaRead, readers::buffered_content::BufferedContent};
+
+const HTML_READER_META_KEY: &str = "html";
+const HTML_READER_META_KEY_DEFAULT_INITIAL: &str = "initial";
+const HTML_READER_META_KEY_TITLE: &str = "title";
+const HTML_READER_META_KEY_AUTHOR: &str = "author";
+
+/// Paragraphs shorter than this are not scored: bylines, captions, buttons etc.
+const MIN_SCORED_PARAGRAPH_CHARS: usize = 25;
+/// Paragraphs mostly made of links are navigation: menus, lists of related articles etc.
+const MAX_LINK_DENSITY: f64 = 0.5;
+
+/// Elements never holding the content of an article
+const BOILERPLATE_TAGS: [&str; 8] = [
+    "nav", "footer", "aside", "form", "button", "select", "iframe", "noscript",
+];
+
+/// Elements separating the text into paragraphs
+const BLOCK_TAGS: [&str; 27] = [
+    "address",
+    "article",
+    "blockquote",
+    "body",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "li",
+    "main",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "td",
+    "th",
+    "ul",
+];
+
+/// Elements without closing tag in HTML
+const VOID_TAGS: [&str; 13] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Elements whose content is not HTML, removed before parsing, as is done for the comments
+static RAW_TEXT_ELEMENTS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?is)<script\b.*?</script\s*>|<style\b.*?</style\s*>|<template\b.*?</template\s*>|<svg\b.*?</svg\s*>|<!--.*?-->",
+    )
+    .unwrap()
+});
+
+/// Classes and ids of the elements unlikely to hold the content of an article, from readability
+static UNLIKELY_CANDIDATES: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)-ad-|ad-break|agegate|banner|breadcrumbs|combx|comment|community|cookie|cover-wrap|disqus|extra|footer|gdpr|header|legends|menu|newsletter|pager|pagination|popup|related|remark|replies|rss|share|shoutbox|sidebar|skyscraper|social|sponsor|subscribe|supplemental").unwrap()
+});
+
+/// Classes and ids overriding `UNLIKELY_CANDIDATES`, for ex `main-header-content`
+static MAYBE_CANDIDATES: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)and|article|body|column|content|main|shadow").unwrap());
+
+/// Reader for HTML sources: saved web pages
+///
+/// The text of a web page is surrounded by boilerplate: menus, headers, footers, sidebars, comments etc.
+/// As readability does, the boilerplate elements are removed, and the paragraphs are scored to find the element
+/// holding the article: only the paragraphs of this element are read.
+///
+/// The title and the author of the article are kept in the metadata.
+pub struct HtmlReader {
+    paragraphs: Vec<String>,
+    current_paragraph_index: usize,
+
+    current_content: BufferedContent,
+
+    // MetaRead
+    metadata: JsonValue,
+}
+
+#[derive(thiserror::Error)]
+pub enum HtmlReaderError {
+    #[error(transparent)]
+    ReadError(#[from] std::io::Error),
+    #[error(transparent)]
+    Utf8Error(#[from] std::string::FromUtf8Error),
+}
+
+impl std::fmt::Debug for HtmlReaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl HtmlReader {
+    /// Create an `HtmlReader` from a source reader (implementing Read)
+    ///
+    /// The whole source is read in memory to find the article of the page.
+    ///
+    /// # Params
+    /// - reader: source reader implementing `Read`
+    /// - initial_meta: (optional) initial metadata as a JSON object
+    #[tracing::instrument(name = "Creating HTML reader", skip(reader))]
+    pub fn try_from_reader(
+        mut reader: impl Read,
+        initial_meta: Option<JsonValue>,
+    ) -> Result<Self, HtmlReaderError> {
+        let mut buf = Vec::<u8>::new();
+        reader.read_to_end(&mut buf)?;
+        let source = String::from_utf8(buf)?;
+
+        let page = parse_page(&source);
+        let paragraphs = select_article_paragraphs(page.paragraphs);
+
+        let initial_meta = initial_meta.unwrap_or(JsonValue::Null);
+        let metadata = match initial_meta {
+            JsonValue::Object(map) => json!(map),
+            JsonValue::Null => JsonValue::Null,
+            _ => json!({ HTML_READER_META_KEY_DEFAULT_INITIAL: initial_meta }),
+        };
+
+        info!(
+            "HTML reader source: nb article paragraphs: {}, initial metadata: {}",
+            paragraphs.len(),
+            metadata
+        );
+
+        let mut html_reader = Self {
+            paragraphs,
+            current_paragraph_index: 0,
+            current_content: BufferedContent::default(),
+            metadata,
+        };
+        if let Some(title) = page.title {
+            html_reader.update_metadata(HTML_READER_META_KEY_TITLE, json!(title));
+        }
+        if let Some(author) = page.author {
+            html_reader.update_metadata(HTML_READER_META_KEY_AUTHOR, json!(author));
+        }
+
+        Ok(html_reader)
+    }
+
+    /// Gets content paragraph by paragraph
+    ///
+    /// # Returns
+    /// The number of chars cached. 0 if no more content is available.
+    fn go_next_content(&mut self) -> usize {
+        self.current_content = BufferedContent::default();
+
+        let Some(paragraph) = self.paragraphs.get(self.current_paragraph_index) else {
+            return 0;
+        };
+        self.current_paragraph_index += 1;
+
+        // The paragraphs are read one after the other, with the same metadata
+        self.current_content = BufferedContent::new(paragraph.chars().chain([' ']));
+
+        self.current_content.len()
+    }
+
+    /// Updates metadata as a JSON object
+    fn update_metadata(&mut self, key: &str, value: JsonValue) {
+        if let Some(map) = self.metadata.as_object_mut() {
+            map.insert(key.to_owned(), value);
+        } else {
+            let mut map = Map::new();
+            map.insert(key.to_owned(), value);
+            self.metadata = JsonValue::Object(map);
+        }
+    }
+}
+
+impl Read for HtmlReader {
+    // Reads bytes as unicode scalar values
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        // There is no more chars to read from the current paragraph, tries to get the next one
+        if self.current_content.is_read() && self.go_next_content() == 0 {
+            // No more to read
+            return Ok(0);
+        }
+
+        Ok(self.current_content.read(buf))
+    }
+}
+
+impl MetaRead for HtmlReader {
+    fn get_current_metadata(&self) -> JsonValue {
+        json!({ HTML_READER_META_KEY: self.metadata.clone() })
+    }
+}
+
+/// Element opened while parsing a page
+#[derive(Debug)]
+struct OpenElement {
+    /// Unique in the page, to score the element
+    id: usize,
+    tag: String,
+    /// Set if the element, or one of its parents, is boilerplate
+    is_boilerplate: bool,
+}
+
+/// Text of a block element, outside of the boilerplate
+#[derive(Debug)]
+struct Paragraph {
+    text: String,
+    /// Number of chars of the text inside links
+    nb_link_chars: usize,
+    /// Ids of the elements containing the paragraph, the outermost first
+    ancestors: Vec<usize>,
+    /// Element containing the block element of the paragraph, and its own parent
+    parent: Option<usize>,
+    grand_parent: Option<usize>,
+}
+
+impl Paragraph {
+    fn link_density(&self) -> f64 {
+        self.nb_link_chars as f64 / self.text.chars().count().max(1) as f64
+    }
+}
+
+/// Page parsed into paragraphs, before finding its article
+#[derive(Debug, Default)]
+struct ParsedPage {
+    title: Option<String>,
+    author: Option<String>,
+    paragraphs: Vec<Paragraph>,
+}
+
+/// Parses the paragraphs of a page, outside of its boilerplate elements, with its title and author
+///
+/// HTML is not XML: the end names are not checked, void elements have no closing tag,
+/// and the elements left open are closed by the closing tag of one of their parents.
+fn parse_page(source: &str) -> ParsedPage {
+    let source = RAW_TEXT_ELEMENTS.replace_all(source, " ");
+    let mut reader = quick_xml::Reader::from_str(&source);
+    reader.check_end_names(false);
+
+    let mut page = ParsedPage::default();
+    let mut meta_title = None;
+    let mut first_h1 = None;
+
+    let mut open_elements: Vec<OpenElement> = vec![];
+    let mut next_element_id = 0;
+    let mut current_text = String::new();
+    let mut current_nb_link_chars = 0;
+
+    loop {
+        let event = match reader.read_event() {
+            Ok(event) => event,
+            Err(error) => {
+                // Keeps the paragraphs parsed until the malformed part
+                warn!(
+                    ?error,
+                    "Stopping to parse the HTML page at position {}",
+                    reader.buffer_position()
+                );
+                break;
+            }
+        };
+
+        match event {
+            Event::Eof => break,
+            Event::Start(tag) => {
+                let name = tag_name(&tag);
+                if VOID_TAGS.contains(&name.as_str()) {
+                    on_void_element(&tag, &name, &mut page, &mut current_text);
+                    continue;
+                }
+
+                if BLOCK_TAGS.contains(&name.as_str()) {
+                    flush_paragraph(
+                        &open_elements,
+                        &mut current_text,
+                        &mut current_nb_link_chars,
+                        &mut page,
+                        &mut first_h1,
+                    );
+
+                    // A paragraph or a list item is implicitly closed by the next one
+                    if matches!(name.as_str(), "p" | "li")
+                        && open_elements.last().map(|element| &element.tag) == Some(&name)
+                    {
+                        open_elements.pop();
+                    }
+                }
+
+                let is_boilerplate = open_elements
+                    .last()
+                    .map(|parent| parent.is_boilerplate)
+                    .unwrap_or(false)
+                    || BOILERPLATE_TAGS.contains(&name.as_str())
+                    || is_unlikely_candidate(&tag);
+                open_elements.push(OpenElement {
+                    id: next_element_id,
+                    tag: name,
+                    is_boilerplate,
+                });
+                next_element_id += 1;
+            }
+            Event::Empty(tag) => {
+                let name = tag_name(&tag);
+                on_void_element(&tag, &name, &mut page, &mut current_text);
+            }
+            Event::End(tag) => {
+                let name = String::from_utf8_lossy(tag.local_name().as_ref()).to_lowercase();
+
+                // A closing tag without opening tag is ignored
+                let Some(position) = open_elements
+                    .iter()
+                    .rposition(|element| element.tag == name)
+                else {
+                    continue;
+                };
+
+                if open_elements[position..]
+                    .iter()
+                    .any(|element| BLOCK_TAGS.contains(&element.tag.as_str()))
+                {
+                    flush_paragraph(
+                        &open_elements,
+                        &mut current_text,
+                        &mut current_nb_link_chars,
+                        &mut page,
+                        &mut first_h1,
+                    );
+                }
+                open_elements.truncate(position);
+            }
+            Event::Text(text) => {
+                let text = text
+                    .unescape_with(resolve_html_entity)
+                    .map(|text| text.into_owned())
+                    .unwrap_or_else(|_| String::from_utf8_lossy(&text).into_owned());
+
+                if open_elements.iter().any(|element| element.tag == "title") {
+                    meta_title.get_or_insert(String::new()).push_str(&text);
+                    continue;
+                }
+                if open_elements
+                    .last()
+                    .map(|element| element.is_boilerplate)
+                    .unwrap_or(false)
+                {
+                    continue;
+                }
+
+                if open_elements.iter().any(|element| element.tag == "a") {
+                    current_nb_link_chars += text.split_whitespace().map(str::len).sum::<usize>();
+                }
+                current_text.push_str(&text);
+            }
+            Event::CData(text) => {
+                current_text.push_str(&String::from_utf8_lossy(&text));
+            }
+            // There are several other `Event`s we do not consider here
+            _ => (),
+        }
+    }
+    flush_paragraph(
+        &open_elements,
+        &mut current_text,
+        &mut current_nb_link_chars,
+        &mut page,
+        &mut first_h1,
+    );
+
+    // The `og:title` of a page does not contain the name of the site, unlike its `<title>`
+    page.title = page
+        .title
+        .or_else(|| meta_title.map(|title| normalize_whitespace(&title)))
+        .or(first_h1)
+        .filter(|title| !title.is_empty());
+
+    page
+}
+
+/// Lowercase local name of a tag
+fn tag_name(tag: &BytesStart) -> String {
+    String::from_utf8_lossy(tag.local_name().as_ref()).to_lowercase()
+}
+
+/// Handles an element without content: a line break, or a `<meta>` describing the article
+fn on_void_element(tag: &BytesStart, name: &str, page: &mut ParsedPage, current_text: &mut String) {
+    match name {
+        "br" => current_text.push(' '),
+        "meta" => {
+            let key = attribute_value(tag, "property")
+                .or_else(|| attribute_value(tag, "name"))
+                .unwrap_or_default()
+                .to_lowercase();
+            let Some(content) = attribute_value(tag, "content")
+                .map(|content| normalize_whitespace(&content))
+                .filter(|content| !content.is_empty())
+            else {
+                return;
+            };
+
+            match key.as_str() {
+                "og:title" => {
+                    page.title.get_or_insert(content);
+                }
+                "author" | "article:author" => {
+                    page.author.get_or_insert(content);
+                }
+                _ => (),
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Saves the text read since the last block element as a paragraph, if it is outside the boilerplate
+fn flush_paragraph(
+    open_elements: &[OpenElement],
+    current_text: &mut String,
+    current_nb_link_chars: &mut usize,
+    page: &mut ParsedPage,
+    first_h1: &mut Option<String>,
+) {
+    let text = normalize_whitespace(&std::mem::take(current_text));
+    let nb_link_chars = std::mem::take(current_nb_link_chars);
+    if text.is_empty() {
+        return;
+    }
+
+    // The element of the paragraph is its innermost block element
+    let block_position = open_elements
+        .iter()
+        .rposition(|element| BLOCK_TAGS.contains(&element.tag.as_str()));
+    if block_position
+        .map(|position| open_elements[position].tag == "h1")
+        .unwrap_or(false)
+    {
+        first_h1.get_or_insert_with(|| text.clone());
+    }
+
+    let parent = block_position
+        .and_then(|position| position.checked_sub(1))
+        .map(|position| open_elements[position].id);
+    let grand_parent = block_position
+        .and_then(|position| position.checked_sub(2))
+        .map(|position| open_elements[position].id);
+
+    page.paragraphs.push(Paragraph {
+        text,
+        nb_link_chars,
+        ancestors: open_elements.iter().map(|element| element.id).collect(),
+        parent,
+        grand_parent,
+    });
+}
+
+/// Selects the paragraphs of the article of a page
+///
+/// As readability does, each long enough paragraph adds a score to its parent, and half of it to its grand parent:
+/// 1 point, 1 per comma, and 1 per 100 chars up to 3. The element with the best score holds the article.
+/// The paragraphs mostly made of links are removed. Without any scored paragraph, all the paragraphs are kept.
+fn select_article_paragraphs(paragraphs: Vec<Paragraph>) -> Vec<String> {
+    let mut scores: BTreeMap<usize, f64> = BTreeMap::new();
+    for paragraph in &paragraphs {
+        let nb_chars = paragraph.text.chars().count();
+        if nb_chars < MIN_SCORED_PARAGRAPH_CHARS || paragraph.link_density() > MAX_LINK_DENSITY {
+            continue;
+        }
+
+        let score =
+            1.0 + paragraph.text.matches(',').count() as f64 + (nb_chars / 100).min(3) as f64;
+        if let Some(parent) = paragraph.parent {
+            *scores.entry(parent).or_default() += score;
+        }
+        if let Some(grand_parent) = paragraph.grand_parent {
+            *scores.entry(grand_parent).or_default() += score / 2.0;
+        }
+    }
+
+    let article_element = scores
+        .into_iter()
+        .max_by(|(_, score), (_, other_score)| score.total_cmp(other_score))
+        .map(|(id, _)| id);
+
+    paragraphs
+        .into_iter()
+        .filter(|paragraph| paragraph.link_density() <= MAX_LINK_DENSITY)
+        .filter(|paragraph| match article_element {
+            Some(article_element) => paragraph.ancestors.contains(&article_element),
+            None => true,
+        })
+        .map(|paragraph| paragraph.text)
+        .collect()
+}
+
+/// Checks the classes and id of an element against the ones of the boilerplate
+fn is_unlikely_candidate(tag: &BytesStart) -> bool {
+    let class_and_id = format!(
+        "{} {}",
+        attribute_value(tag, "class").unwrap_or_default(),
+        attribute_value(tag, "id").unwrap_or_default()
+    );
+
+    UNLIKELY_CANDIDATES.is_match(&class_and_id) && !MAYBE_CANDIDATES.is_match(&class_and_id)
+}
+
+/// Value of an attribute, which can be unquoted in HTML
+fn attribute_value(tag: &BytesStart, name: &str) -> Option<String> {
+    tag.html_attributes()
+        .filter_map(Result::ok)
+        .find(|attribute| {
+            attribute
+                .key
+                .local_name()
+                .as_ref()
+                .eq_ignore_ascii_case(name.as_bytes())
+        })
+        .map(|attribute| {
+            attribute
+                .unescape_value_with(resolve_html_entity)
+                .map(|value| value.into_owned())
+                .unwrap_or_else(|_| String::from_utf8_lossy(&attribute.value).into_owned())
+        })
+}
+
+/// Resolves the HTML entities commonly found in text, unknown to XML
+fn resolve_html_entity(entity: &str) -> Option<&'static str> {
+    match entity {
+        "lt" => Some("<"),
+        "gt" => Some(">"),
+        "amp" => Some("&"),
+        "apos" => Some("'"),
+        "quot" => Some("\""),
+        "nbsp" => Some(" "),
+        "mdash" => Some("—"),
+        "ndash" => Some("–"),
+        "hellip" => Some("…"),
+        "laquo" => Some("«"),
+        "raquo" => Some("»"),
+        "lsquo" => Some("‘"),
+        "rsquo" => Some("’"),
+        "ldquo" => Some("“"),
+        "rdquo" => Some("”"),
+        "copy" => Some("©"),
+        _ => None,
+    }
+}
+
+fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::extractors::extract_content_generator::{
+        extract_content_generator, ChunkSplitting,
+    };
+    use genawaiter::GeneratorState;
+
+    const ARTICLE_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+  <title>Growing tomatoes | The Garden Blog</title>
+  <meta property="og:title" content="Growing tomatoes">
+  <meta name="author" content="Jane Doe">
+  <style>body { color: red; }</style>
+  <script>if (a < b && c) { document.write("<p>Injected</p>"); }</script>
+</head>
+<body>
+  <nav><ul><li><a href="/">Home</a></li><li><a href="/about">About us</a></li></ul></nav>
+  <div class="sidebar"><p>Subscribe to our newsletter, and get the best gardening tips every week!</p></div>
+  <article>
+    <h1>Growing tomatoes</h1>
+    <p>Tomatoes need a lot of sun, at least six hours a day, and a rich soil to grow well.<br>Water them regularly.
+    <p>Prune the suckers growing between the stem and the branches, so the plant focuses on its fruits &amp; grows taller.</p>
+    <p>Read also: <a href="/peppers">Growing peppers in pots, a complete guide for beginners</a></p>
+  </article>
+  <footer><p>Copyright &copy; 2023 The Garden Blog, all rights reserved, no reproduction allowed.</p></footer>
+</body>
+</html>"#;
+
+    #[test]
+    fn it_reads_the_article_without_the_boilerplate() {
+        let page = parse_page(ARTICLE_PAGE);
+        let paragraphs = select_article_paragraphs(page.paragraphs);
+
+        assert_eq!(
+            paragraphs,
+            vec![
+                "Growing tomatoes",
+                "Tomatoes need a lot of sun, at least six hours a day, and a rich soil to grow well. Water them regularly.",
+                "Prune the suckers growing between the stem and the branches, so the plant focuses on its fruits & grows taller.",
+            ]
+        );
+        assert_eq!(page.title.as_deref(), Some("Growing tomatoes"));
+        assert_eq!(page.author.as_deref(), Some("Jane Doe"));
+    }
+
+    #[test]
+    fn it_keeps_all_the_paragraphs_of_a_page_without_article() {
+        let page = parse_page(
+            "<html><head><title>Notes</title></head><body><p>First</p><div>Second</div></body></html>",
+        );
+
+        assert_eq!(page.title.as_deref(), Some("Notes"));
+        assert_eq!(
+            select_article_paragraphs(page.paragraphs),
+            vec!["First", "Second"]
+        );
+    }
+
+    #[test]
+    fn it_extracts_the_article_with_its_metadata() {
+        let mut html_reader = HtmlReader::try_from_reader(
+            ARTICLE_PAGE.as_bytes(),
+            Some(json!({ "file": "tomatoes.html" })),
+        )
+        .unwrap();
+
+        let mut generator =
+            extract_content_generator(&mut html_reader, None, ChunkSplitting::Words);
+        let GeneratorState::Yielded(extracted_content) = generator.as_mut().resume() else {
+            panic!("No content was extracted");
+        };
+
+        assert!(extracted_content
+            .content
+            .starts_with("Growing tomatoes Tomatoes need"));
+        assert!(!extracted_content.content.contains("newsletter"));
+        assert!(!extracted_content.content.contains("Copyright"));
+        assert_eq!(
+            extracted_content.metadata,
+            json!({ "html": {
+                "file": "tomatoes.html",
+                "title": "Growing tomatoes",
+                "author": "Jane Doe",
+            }})
+        );
+    }
+}
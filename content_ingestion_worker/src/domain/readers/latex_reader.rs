@@ -0,0 +1,1090 @@
+use common::helper::error_chain_fmt;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use serde_json::{json, Map, Value as JsonValue};
+use std::{
+    collections::HashMap,
+    io::{Cursor, Read, Seek},
+    path::Path,
+};
+use tracing::{info, warn};
+
+use crate::domain::{
+    entities::meta_read::MetaRead,
+    readers::{buffered_content::BufferedContent, zip_archive},
+};
+
+const LATEX_READER_META_KEY: &str = "latex";
+const LATEX_READER_META_KEY_DEFAULT_INITIAL: &str = "initial";
+const LATEX_READER_META_KEY_TITLE: &str = "title";
+const LATEX_READER_META_KEY_MATH: &str = "math";
+
+/// Sectioning commands, from the highest level to the lowest one.
+/// The title of each current section is kept in the metadata with the command name as key.
+const SECTIONING_COMMANDS: [&str; 5] =
+    ["part", "chapter", "section", "subsection", "subsubsection"];
+
+const MATH_ENVIRONMENTS: [&str; 12] = [
+    "equation",
+    "equation*",
+    "align",
+    "align*",
+    "gather",
+    "gather*",
+    "multline",
+    "multline*",
+    "eqnarray",
+    "eqnarray*",
+    "displaymath",
+    "math",
+];
+
+/// Environments whose content is not readable text
+const DROPPED_ENVIRONMENTS: [&str; 3] = ["tikzpicture", "comment", "filecontents"];
+
+/// Environments followed by an argument that is not readable text (ex: the columns of a table)
+const ENVIRONMENTS_WITH_ARGUMENT: [&str; 6] = [
+    "tabular",
+    "tabularx",
+    "array",
+    "minipage",
+    "multicols",
+    "wrapfigure",
+];
+
+/// Commands dropped with their arguments
+const DROPPED_COMMANDS: [&str; 26] = [
+    "label",
+    "ref",
+    "eqref",
+    "pageref",
+    "autoref",
+    "cref",
+    "cite",
+    "citep",
+    "citet",
+    "nocite",
+    "documentclass",
+    "usepackage",
+    "includegraphics",
+    "bibliography",
+    "bibliographystyle",
+    "newcommand",
+    "renewcommand",
+    "setlength",
+    "vspace",
+    "hspace",
+    "input",
+    "include",
+    "title",
+    "author",
+    "date",
+    "thanks",
+];
+
+/// Math commands replaced by a text form. Other math commands are replaced by their name (ex: `\alpha` by `alpha`)
+const MATH_SYMBOLS: [(&str, &str); 20] = [
+    ("leq", "<="),
+    ("le", "<="),
+    ("geq", ">="),
+    ("ge", ">="),
+    ("neq", "!="),
+    ("ne", "!="),
+    ("approx", "~="),
+    ("times", "*"),
+    ("cdot", "*"),
+    ("div", "/"),
+    ("pm", "+/-"),
+    ("infty", "infinity"),
+    ("to", "->"),
+    ("rightarrow", "->"),
+    ("leftarrow", "<-"),
+    ("Rightarrow", "=>"),
+    ("Leftrightarrow", "<=>"),
+    ("ldots", "..."),
+    ("cdots", "..."),
+    ("partial", "d"),
+];
+
+/// Math commands only changing the style of their argument
+const MATH_STYLE_COMMANDS: [&str; 13] = [
+    "text",
+    "textrm",
+    "textbf",
+    "textit",
+    "mbox",
+    "mathrm",
+    "mathbf",
+    "mathit",
+    "mathsf",
+    "mathtt",
+    "mathcal",
+    "mathbb",
+    "operatorname",
+];
+
+/// Math commands without any text form
+const MATH_IGNORED_COMMANDS: [&str; 9] = [
+    "left",
+    "right",
+    "big",
+    "Big",
+    "bigg",
+    "Bigg",
+    "displaystyle",
+    "nonumber",
+    "quad",
+];
+
+/// Limit of nested `\input`/`\include`, avoiding infinite inclusions
+const MAX_INPUT_DEPTH: usize = 10;
+
+/// Form in which the math found in a LaTeX source is kept in the metadata
+///
+/// In the read content, math is always replaced by its normalized text form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LatexMathFormat {
+    /// The TeX code, ex: `\frac{a}{b}`
+    Raw,
+    /// A text form, ex: `(a) / (b)`
+    Normalized,
+}
+
+/// A paragraph with its sections and math
+#[derive(Debug)]
+struct LatexBlock {
+    /// Titles of the current sections, indexed as `SECTIONING_COMMANDS`
+    sections: [Option<String>; SECTIONING_COMMANDS.len()],
+    text: String,
+    math: Vec<String>,
+}
+
+/// Elements of a LaTeX document body
+#[derive(Debug, PartialEq)]
+enum LatexEvent {
+    Text(String),
+    ParagraphBreak,
+    /// Level is the index of the sectioning command in `SECTIONING_COMMANDS`
+    Section {
+        level: usize,
+        title: String,
+    },
+    /// TeX code of the math, without its delimiters
+    Math(String),
+}
+
+/// Reader for LaTeX sources: a single `.tex` file, or a LaTeX project uploaded as a zip archive
+///
+/// Commands are stripped, keeping the readable text of their arguments.
+/// The document is read paragraph by paragraph, with the titles of the current sections in the metadata.
+/// Math is replaced by a text form in the read content, and kept in the metadata of its paragraph
+/// in the configured `LatexMathFormat`.
+///
+/// In an archive, the main document is the `.tex` file declaring a `\documentclass`,
+/// and its `\input`/`\include` are resolved from the other files of the archive.
+pub struct LatexReader {
+    blocks: Vec<LatexBlock>,
+    current_block_index: usize,
+
+    current_content: BufferedContent,
+
+    // MetaRead
+    metadata: JsonValue,
+}
+
+#[derive(thiserror::Error)]
+pub enum LatexReaderError {
+    #[error(transparent)]
+    ReadError(#[from] std::io::Error),
+    #[error(transparent)]
+    ZipError(#[from] zip::result::ZipError),
+    #[error("No main LaTeX document (with a \\documentclass) found in the archive")]
+    NoMainDocument,
+}
+
+impl std::fmt::Debug for LatexReaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl LatexReader {
+    /// Create a `LatexReader` from a source reader (implementing Read)
+    ///
+    /// The whole source is read in memory. A zip archive is detected from its magic number.
+    ///
+    /// # Params
+    /// - reader: source reader implementing `Read`
+    /// - initial_meta: (optional) initial metadata as a JSON object
+    /// - math_format: form in which the math is kept in the metadata
+    #[tracing::instrument(name = "Creating LaTeX reader", skip(reader))]
+    pub fn try_from_reader(
+        mut reader: impl Read,
+        initial_meta: Option<JsonValue>,
+        math_format: LatexMathFormat,
+    ) -> Result<Self, LatexReaderError> {
+        let mut buf = Vec::<u8>::new();
+        reader.read_to_end(&mut buf)?;
+
+        let source = if zip_archive::is_zip_archive(&buf) {
+            expand_archive(&buf)?
+        } else {
+            let source = strip_comments(&String::from_utf8_lossy(&buf));
+            expand_inputs(&source, "", &HashMap::new(), &mut vec![])
+        };
+
+        let title = extract_title(&source);
+        let blocks = build_blocks(parse_events(document_body(&source)), math_format);
+
+        let initial_meta = initial_meta.unwrap_or(JsonValue::Null);
+        let metadata = match initial_meta {
+            JsonValue::Object(map) => json!(map),
+            JsonValue::Null => JsonValue::Null,
+            _ => json!({ LATEX_READER_META_KEY_DEFAULT_INITIAL: initial_meta }),
+        };
+
+        info!(
+            "LaTeX reader source: title: {:?}, nb blocks: {}, initial metadata: {}",
+            title,
+            blocks.len(),
+            metadata
+        );
+
+        let mut latex_reader = Self {
+            blocks,
+            current_block_index: 0,
+            current_content: BufferedContent::default(),
+            metadata,
+        };
+        if let Some(title) = title {
+            latex_reader.update_metadata(LATEX_READER_META_KEY_TITLE, json!(title));
+        }
+
+        Ok(latex_reader)
+    }
+
+    /// Gets content paragraph by paragraph
+    ///
+    /// # Returns
+    /// The number of chars cached. 0 if no more content is available.
+    fn go_next_content(&mut self) -> usize {
+        self.current_content = BufferedContent::default();
+
+        let Some(block) = self.blocks.get(self.current_block_index) else {
+            return 0;
+        };
+        self.current_block_index += 1;
+
+        self.current_content = BufferedContent::new(block.text.chars());
+
+        let sections = block.sections.clone();
+        let math = block.math.clone();
+        for (command, title) in SECTIONING_COMMANDS.iter().zip(sections) {
+            match title {
+                Some(title) => self.update_metadata(command, json!(title)),
+                None => self.remove_metadata(command),
+            }
+        }
+
+        if math.is_empty() {
+            self.remove_metadata(LATEX_READER_META_KEY_MATH);
+        } else {
+            self.update_metadata(LATEX_READER_META_KEY_MATH, json!(math));
+        }
+
+        self.current_content.len()
+    }
+
+    /// Updates metadata as a JSON object
+    fn update_metadata(&mut self, key: &str, value: JsonValue) {
+        if let Some(map) = self.metadata.as_object_mut() {
+            map.insert(key.to_owned(), value);
+        } else {
+            let mut map = Map::new();
+            map.insert(key.to_owned(), value);
+            self.metadata = JsonValue::Object(map);
+        }
+    }
+
+    fn remove_metadata(&mut self, key: &str) {
+        if let Some(map) = self.metadata.as_object_mut() {
+            map.remove(key);
+        }
+    }
+}
+
+impl Read for LatexReader {
+    // Reads bytes as unicode scalar values
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        // There is no more chars to read from the current paragraph, tries to get the next one
+        if self.current_content.is_read() && self.go_next_content() == 0 {
+            // No more to read
+            return Ok(0);
+        }
+
+        Ok(self.current_content.read(buf))
+    }
+}
+
+impl MetaRead for LatexReader {
+    fn get_current_metadata(&self) -> JsonValue {
+        json!({ LATEX_READER_META_KEY: self.metadata.clone() })
+    }
+}
+
+/// Whether a zip archive contains a LaTeX document
+///
+/// The reader is rewound to its start afterwards.
+pub fn is_latex_archive(reader: &mut (impl Read + Seek)) -> Result<bool, LatexReaderError> {
+    let tex_files = zip_archive::read_text_files(&mut *reader, is_tex_file)?;
+    reader.rewind()?;
+
+    Ok(tex_files.iter().any(|file| is_main_document(&file.content)))
+}
+
+fn is_tex_file(path: &str) -> bool {
+    path.to_lowercase().ends_with(".tex")
+}
+
+fn is_main_document(source: &str) -> bool {
+    strip_comments(source).contains("\\documentclass")
+}
+
+/// Gets the main document of an archive, with its `\input`/`\include` resolved
+///
+/// If several documents are found, the one the closest to the archive root is the main one.
+fn expand_archive(buf: &[u8]) -> Result<String, LatexReaderError> {
+    let tex_files: HashMap<String, String> =
+        zip_archive::read_text_files(Cursor::new(buf), is_tex_file)?
+            .into_iter()
+            .map(|file| (file.path, strip_comments(&file.content)))
+            .collect();
+
+    let main_path = tex_files
+        .iter()
+        .filter(|(_, source)| source.contains("\\documentclass"))
+        .map(|(path, _)| path)
+        .min_by_key(|path| (path.matches('/').count(), path.to_string()))
+        .ok_or(LatexReaderError::NoMainDocument)?;
+
+    let main_directory = Path::new(main_path)
+        .parent()
+        .map(zip_archive::normalize_path)
+        .unwrap_or_default();
+
+    Ok(expand_inputs(
+        &tex_files[main_path],
+        &main_directory,
+        &tex_files,
+        &mut vec![main_path.to_owned()],
+    ))
+}
+
+/// Replaces the `\input{file}` and `\include{file}` commands by the content of the files
+///
+/// The paths are relative to the directory of the main document. The `.tex` extension can be omitted.
+/// Inputs that can not be resolved are removed.
+///
+/// # Params
+/// - source: LaTeX source, without comments
+/// - main_directory: directory of the main document in the archive
+/// - tex_files: LaTeX sources (without comments) of the archive by path
+/// - included_paths: paths of the sources being included, to detect inclusion cycles
+fn expand_inputs(
+    source: &str,
+    main_directory: &str,
+    tex_files: &HashMap<String, String>,
+    included_paths: &mut Vec<String>,
+) -> String {
+    // Panics if the regex cannot be built
+    static INPUT_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"\\(?:input|include)\{([^}]+)\}").unwrap());
+
+    INPUT_RE
+        .replace_all(source, |caps: &Captures| {
+            let mut path =
+                zip_archive::normalize_path(Path::new(main_directory).join(caps[1].trim()));
+            if !is_tex_file(&path) {
+                path.push_str(".tex");
+            }
+
+            let Some(included_source) = tex_files.get(&path) else {
+                warn!("Could not resolve LaTeX input {}", &caps[1]);
+                return String::new();
+            };
+
+            if included_paths.contains(&path) || included_paths.len() > MAX_INPUT_DEPTH {
+                warn!("Skipping recursive LaTeX input {}", path);
+                return String::new();
+            }
+
+            included_paths.push(path);
+            let expanded =
+                expand_inputs(included_source, main_directory, tex_files, included_paths);
+            included_paths.pop();
+
+            // Isolates the included content in its own paragraphs
+            format!("\n\n{}\n\n", expanded)
+        })
+        .into_owned()
+}
+
+/// Removes comments: from an unescaped `%` to the end of the line, including the line break
+fn strip_comments(source: &str) -> String {
+    let mut stripped = String::with_capacity(source.len());
+
+    for line in source.lines() {
+        let mut previous_char = None;
+        let comment_start = line.char_indices().find_map(|(index, current_char)| {
+            let is_comment = current_char == '%' && previous_char != Some('\\');
+            previous_char = Some(current_char);
+            is_comment.then_some(index)
+        });
+
+        match comment_start {
+            Some(comment_start) => stripped.push_str(&line[..comment_start]),
+            None => {
+                stripped.push_str(line);
+                stripped.push('\n');
+            }
+        }
+    }
+
+    stripped
+}
+
+/// Gets the content of the `document` environment, or the whole source if there is none (ex: an included file)
+fn document_body(source: &str) -> &str {
+    let body_start = match source.find("\\begin{document}") {
+        Some(index) => index + "\\begin{document}".len(),
+        None => 0,
+    };
+    let body_end = source[body_start..]
+        .find("\\end{document}")
+        .map_or(source.len(), |index| body_start + index);
+
+    &source[body_start..body_end]
+}
+
+/// Gets the title of the document from a `\title{...}` command
+fn extract_title(source: &str) -> Option<String> {
+    let title_start = source.find("\\title")? + "\\title".len();
+    let chars: Vec<char> = source[title_start..].chars().collect();
+    let (title, _) = read_group(&chars, skip_optional(&chars, 0));
+
+    let title = to_plain_text(&title);
+    (!title.is_empty()).then_some(title)
+}
+
+/// Groups the events of a document into paragraphs, associated to their sections and math
+fn build_blocks(events: Vec<LatexEvent>, math_format: LatexMathFormat) -> Vec<LatexBlock> {
+    let mut blocks = vec![];
+    let mut sections: [Option<String>; SECTIONING_COMMANDS.len()] = Default::default();
+    let mut text = String::new();
+    let mut math = vec![];
+
+    let flush = |blocks: &mut Vec<LatexBlock>,
+                 sections: &[Option<String>; SECTIONING_COMMANDS.len()],
+                 text: &mut String,
+                 math: &mut Vec<String>| {
+        let paragraph = collapse_whitespace(text);
+        text.clear();
+
+        if paragraph.is_empty() {
+            math.clear();
+            return;
+        }
+
+        blocks.push(LatexBlock {
+            sections: sections.clone(),
+            // Separates 2 successive paragraphs
+            text: format!("{} ", paragraph),
+            math: std::mem::take(math),
+        });
+    };
+
+    for event in events {
+        match event {
+            LatexEvent::Text(event_text) => text.push_str(&event_text),
+            LatexEvent::Math(tex) => {
+                let normalized = normalize_math(&tex);
+                text.push_str(&format!(" {} ", normalized));
+
+                math.push(match math_format {
+                    LatexMathFormat::Raw => collapse_whitespace(&tex),
+                    LatexMathFormat::Normalized => normalized,
+                });
+            }
+            LatexEvent::ParagraphBreak => flush(&mut blocks, &sections, &mut text, &mut math),
+            LatexEvent::Section { level, title } => {
+                flush(&mut blocks, &sections, &mut text, &mut math);
+
+                sections[level] = Some(title);
+                for lower_section in sections.iter_mut().skip(level + 1) {
+                    *lower_section = None;
+                }
+            }
+        }
+    }
+    flush(&mut blocks, &sections, &mut text, &mut math);
+
+    blocks
+}
+
+/// Parses a LaTeX source (without comments) into events
+fn parse_events(source: &str) -> Vec<LatexEvent> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut events = vec![];
+    let mut text = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i = parse_command(&chars, i, &mut text, &mut events),
+            '$' => {
+                let delimiter = if chars.get(i + 1) == Some(&'$') {
+                    "$$"
+                } else {
+                    "$"
+                };
+                let (math, next_i) = read_until(&chars, i + delimiter.len(), delimiter);
+
+                push_event(&mut events, &mut text, LatexEvent::Math(math));
+                i = next_i;
+            }
+            '\n' => {
+                // A blank line ends a paragraph
+                let mut next_i = i + 1;
+                while next_i < chars.len() && chars[next_i] != '\n' && chars[next_i].is_whitespace()
+                {
+                    next_i += 1;
+                }
+
+                if chars.get(next_i) == Some(&'\n') {
+                    push_event(&mut events, &mut text, LatexEvent::ParagraphBreak);
+                } else {
+                    text.push(' ');
+                }
+                i = next_i;
+            }
+            // Groups are not readable text, their content is
+            '{' | '}' => i += 1,
+            // Non-breaking space and table columns separator
+            '~' | '&' => {
+                text.push(' ');
+                i += 1;
+            }
+            current_char => {
+                text.push(current_char);
+                i += 1;
+            }
+        }
+    }
+
+    if !text.is_empty() {
+        events.push(LatexEvent::Text(text));
+    }
+
+    events
+}
+
+/// Parses a command starting at `i` (on its `\`)
+///
+/// # Returns
+/// The index following the command
+fn parse_command(
+    chars: &[char],
+    i: usize,
+    text: &mut String,
+    events: &mut Vec<LatexEvent>,
+) -> usize {
+    let name_start = i + 1;
+    let Some(&first_char) = chars.get(name_start) else {
+        return name_start;
+    };
+
+    // Commands made of 1 non-letter char
+    if !first_char.is_ascii_alphabetic() {
+        let next_i = name_start + 1;
+
+        match first_char {
+            '(' => {
+                let (math, next_i) = read_until(chars, next_i, "\\)");
+                push_event(events, text, LatexEvent::Math(math));
+                return next_i;
+            }
+            '[' => {
+                let (math, next_i) = read_until(chars, next_i, "\\]");
+                push_event(events, text, LatexEvent::Math(math));
+                return next_i;
+            }
+            // Line break, with an optional spacing
+            '\\' => {
+                text.push(' ');
+                return skip_optional(chars, next_i);
+            }
+            '%' | '$' | '&' | '_' | '#' | '{' | '}' => text.push(first_char),
+            // Spacing commands
+            _ => text.push(' '),
+        }
+
+        return next_i;
+    }
+
+    let mut next_i = name_start;
+    while next_i < chars.len() && chars[next_i].is_ascii_alphabetic() {
+        next_i += 1;
+    }
+    let name: String = chars[name_start..next_i].iter().collect();
+    // Starred variant of a command
+    if chars.get(next_i) == Some(&'*') {
+        next_i += 1;
+    }
+
+    if let Some(level) = SECTIONING_COMMANDS
+        .iter()
+        .position(|command| *command == name)
+    {
+        let (title, next_i) = read_group(chars, skip_optional(chars, next_i));
+        push_event(
+            events,
+            text,
+            LatexEvent::Section {
+                level,
+                title: to_plain_text(&title),
+            },
+        );
+        return next_i;
+    }
+
+    match name.as_str() {
+        "begin" => {
+            let (environment, next_i) = read_group(chars, next_i);
+
+            if MATH_ENVIRONMENTS.contains(&environment.as_str()) {
+                let (math, next_i) =
+                    read_until(chars, next_i, &format!("\\end{{{}}}", environment));
+                push_event(events, text, LatexEvent::Math(math));
+                return next_i;
+            }
+
+            if DROPPED_ENVIRONMENTS.contains(&environment.as_str()) {
+                let (_, next_i) = read_until(chars, next_i, &format!("\\end{{{}}}", environment));
+                return next_i;
+            }
+
+            push_event(events, text, LatexEvent::ParagraphBreak);
+            let next_i = skip_optional(chars, next_i);
+            if ENVIRONMENTS_WITH_ARGUMENT.contains(&environment.as_str()) {
+                let (_, next_i) = read_group(chars, next_i);
+                return next_i;
+            }
+            next_i
+        }
+        "end" => {
+            let (_, next_i) = read_group(chars, next_i);
+            push_event(events, text, LatexEvent::ParagraphBreak);
+            next_i
+        }
+        "item" => {
+            // Keeps the custom label of the item
+            let (label, next_i) = read_optional(chars, next_i);
+            text.push(' ');
+            text.push_str(&to_plain_text(&label));
+            text.push(' ');
+            next_i
+        }
+        _ if DROPPED_COMMANDS.contains(&name.as_str()) => skip_arguments(chars, next_i),
+        // Keeps the content of the arguments of any other command (ex: `\emph{text}`)
+        _ => skip_optional(chars, next_i),
+    }
+}
+
+/// Pushes the pending text as an event, before pushing the given event
+fn push_event(events: &mut Vec<LatexEvent>, text: &mut String, event: LatexEvent) {
+    if !text.is_empty() {
+        events.push(LatexEvent::Text(std::mem::take(text)));
+    }
+
+    events.push(event);
+}
+
+/// Reads a `{...}` group starting at `i`, handling nested groups
+///
+/// # Returns
+/// The content of the group and the index following it. An empty content if there is no group at `i`.
+fn read_group(chars: &[char], i: usize) -> (String, usize) {
+    read_delimited(chars, i, '{', '}')
+}
+
+/// Reads an optional `[...]` argument starting at `i`
+fn read_optional(chars: &[char], i: usize) -> (String, usize) {
+    read_delimited(chars, i, '[', ']')
+}
+
+fn skip_optional(chars: &[char], i: usize) -> usize {
+    read_optional(chars, i).1
+}
+
+/// Skips all the optional and mandatory arguments following a command
+fn skip_arguments(chars: &[char], mut i: usize) -> usize {
+    while let Some('{' | '[') = chars.get(i) {
+        i = skip_optional(chars, i);
+        i = read_group(chars, i).1;
+    }
+
+    i
+}
+
+fn read_delimited(chars: &[char], i: usize, opening: char, closing: char) -> (String, usize) {
+    if chars.get(i) != Some(&opening) {
+        return (String::new(), i);
+    }
+
+    let mut depth = 0;
+    let mut j = i;
+    while j < chars.len() {
+        match chars[j] {
+            // Escaped char
+            '\\' => j += 1,
+            current_char if current_char == opening => depth += 1,
+            current_char if current_char == closing => {
+                depth -= 1;
+                if depth == 0 {
+                    return (chars[i + 1..j].iter().collect(), j + 1);
+                }
+            }
+            _ => (),
+        }
+        j += 1;
+    }
+
+    // Unbalanced group: takes everything until the end
+    (
+        chars[(i + 1).min(chars.len())..].iter().collect(),
+        chars.len(),
+    )
+}
+
+/// Reads until an unescaped delimiter
+///
+/// # Returns
+/// The content before the delimiter, and the index following the delimiter
+fn read_until(chars: &[char], i: usize, delimiter: &str) -> (String, usize) {
+    let delimiter: Vec<char> = delimiter.chars().collect();
+    let mut j = i;
+
+    while j < chars.len() {
+        if chars[j..].starts_with(&delimiter)
+            && (j == 0 || chars[j - 1] != '\\' || delimiter[0] == '\\')
+        {
+            return (chars[i..j].iter().collect(), j + delimiter.len());
+        }
+        j += 1;
+    }
+
+    (chars[i.min(chars.len())..].iter().collect(), chars.len())
+}
+
+/// Converts a short LaTeX source (a title for ex) into plain text
+fn to_plain_text(source: &str) -> String {
+    let text = parse_events(source)
+        .into_iter()
+        .filter_map(|event| match event {
+            LatexEvent::Text(text) => Some(text),
+            LatexEvent::Math(tex) => Some(normalize_math(&tex)),
+            _ => None,
+        })
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    collapse_whitespace(&text)
+}
+
+/// Converts TeX math into a text form
+///
+/// For ex: `\frac{a}{b} \leq \sqrt{c}` becomes `(a) / (b) <= sqrt(c)`
+pub fn normalize_math(tex: &str) -> String {
+    let chars: Vec<char> = tex.chars().collect();
+    let mut normalized = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                let name_start = i + 1;
+                let mut next_i = name_start;
+                while next_i < chars.len() && chars[next_i].is_ascii_alphabetic() {
+                    next_i += 1;
+                }
+
+                // Commands made of 1 non-letter char
+                if next_i == name_start {
+                    match chars.get(name_start) {
+                        Some('{' | '}' | '%' | '$' | '&' | '_' | '#') => {
+                            normalized.push(chars[name_start])
+                        }
+                        _ => normalized.push(' '),
+                    }
+                    i = name_start + 1;
+                    continue;
+                }
+
+                let name: String = chars[name_start..next_i].iter().collect();
+                i = match name.as_str() {
+                    "frac" | "dfrac" | "tfrac" => {
+                        let (numerator, next_i) = read_group(&chars, next_i);
+                        let (denominator, next_i) = read_group(&chars, next_i);
+                        normalized.push_str(&format!(
+                            " ({}) / ({}) ",
+                            normalize_math(&numerator),
+                            normalize_math(&denominator)
+                        ));
+                        next_i
+                    }
+                    "sqrt" => {
+                        let (degree, next_i) = read_optional(&chars, next_i);
+                        let (radicand, next_i) = read_group(&chars, next_i);
+                        if degree.is_empty() {
+                            normalized.push_str(&format!(" sqrt({}) ", normalize_math(&radicand)));
+                        } else {
+                            normalized.push_str(&format!(
+                                " root{}({}) ",
+                                normalize_math(&degree),
+                                normalize_math(&radicand)
+                            ));
+                        }
+                        next_i
+                    }
+                    "label" => read_group(&chars, next_i).1,
+                    _ if MATH_STYLE_COMMANDS.contains(&name.as_str()) => {
+                        let (argument, next_i) = read_group(&chars, next_i);
+                        normalized.push_str(&format!(" {} ", normalize_math(&argument)));
+                        next_i
+                    }
+                    _ if MATH_IGNORED_COMMANDS.contains(&name.as_str()) => next_i,
+                    _ => {
+                        let symbol = MATH_SYMBOLS
+                            .iter()
+                            .find(|(command, _)| *command == name)
+                            .map_or(name.as_str(), |(_, symbol)| symbol);
+                        normalized.push_str(&format!(" {} ", symbol));
+                        next_i
+                    }
+                };
+            }
+            // Superscript and subscript: wraps a group of several chars in parentheses
+            current_char @ ('^' | '_') => {
+                // Attaches to the previous symbol, ex: `\sum_{i}`
+                normalized.truncate(normalized.trim_end().len());
+                normalized.push(current_char);
+                let (group, next_i) = read_group(&chars, i + 1);
+
+                if next_i == i + 1 {
+                    i += 1;
+                    continue;
+                }
+
+                let group = normalize_math(&group);
+                if group.chars().count() > 1 {
+                    normalized.push_str(&format!("({})", group));
+                } else {
+                    normalized.push_str(&group);
+                }
+                i = next_i;
+            }
+            '{' | '}' => i += 1,
+            // Alignment and line breaks of math environments
+            '&' => {
+                normalized.push(' ');
+                i += 1;
+            }
+            current_char => {
+                normalized.push(current_char);
+                i += 1;
+            }
+        }
+    }
+
+    collapse_whitespace(&normalized)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::extractors::extract_content_generator::{
+        extract_content_generator, ChunkSplitting,
+    };
+    use genawaiter::GeneratorState;
+    use std::io::{Cursor, Write};
+    use zip::write::FileOptions;
+
+    fn read_all(latex_reader: &mut LatexReader) -> Vec<(String, JsonValue)> {
+        let mut generator =
+            extract_content_generator(latex_reader, Some(100), ChunkSplitting::Words);
+        let mut contents = vec![];
+
+        while let GeneratorState::Yielded(extracted_content) = generator.as_mut().resume() {
+            contents.push((
+                extracted_content.content.trim().to_owned(),
+                extracted_content.metadata[LATEX_READER_META_KEY].clone(),
+            ));
+        }
+
+        contents
+    }
+
+    fn zip_archive(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(vec![]));
+
+        for (name, content) in files {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn on_document_it_should_strip_commands_and_keep_section_structure() {
+        let source = r#"\documentclass{article}
+\usepackage{amsmath}
+\title{A \emph{short} paper}
+\begin{document}
+\maketitle
+
+\section{Introduction}\label{sec:intro}
+Some \textbf{important} text, see~\cite[p.~2]{knuth}. % A comment
+It costs 10\% less.
+
+\subsection*{Details}
+\begin{itemize}
+  \item First point
+  \item[b)] Second point
+\end{itemize}
+
+\section{Conclusion}
+The end.
+\end{document}
+"#;
+        let mut latex_reader =
+            LatexReader::try_from_reader(Cursor::new(source), None, LatexMathFormat::Raw).unwrap();
+
+        let contents = read_all(&mut latex_reader);
+
+        assert_eq!(
+            contents,
+            vec![
+                (
+                    "Some important text, see . It costs 10% less.".to_string(),
+                    json!({ "title": "A short paper", "section": "Introduction" })
+                ),
+                (
+                    "First point b) Second point".to_string(),
+                    json!({ "title": "A short paper", "section": "Introduction", "subsection": "Details" })
+                ),
+                (
+                    "The end.".to_string(),
+                    json!({ "title": "A short paper", "section": "Conclusion" })
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn on_math_it_should_keep_raw_tex_in_metadata_and_text_form_in_content() {
+        let source = r#"\section{Physics}
+The energy is $E = mc^2$, with
+\begin{equation}
+  \frac{a}{b} \leq \sqrt{c}
+\end{equation}
+
+A paragraph without math."#;
+        let mut latex_reader =
+            LatexReader::try_from_reader(Cursor::new(source), None, LatexMathFormat::Raw).unwrap();
+
+        let contents = read_all(&mut latex_reader);
+
+        assert_eq!(
+            contents[0].0,
+            "The energy is E = mc^2 , with (a) / (b) <= sqrt(c)"
+        );
+        assert_eq!(
+            contents[0].1[LATEX_READER_META_KEY_MATH],
+            json!(["E = mc^2", "\\frac{a}{b} \\leq \\sqrt{c}"])
+        );
+        assert!(contents[1].1.get(LATEX_READER_META_KEY_MATH).is_none());
+    }
+
+    #[test]
+    fn on_normalized_math_format_it_should_keep_text_form_in_metadata() {
+        let source = r"Let \(x_{i+1} = \alpha x_i\).";
+        let mut latex_reader =
+            LatexReader::try_from_reader(Cursor::new(source), None, LatexMathFormat::Normalized)
+                .unwrap();
+
+        let contents = read_all(&mut latex_reader);
+
+        assert_eq!(
+            contents[0].1[LATEX_READER_META_KEY_MATH],
+            json!(["x_(i+1) = alpha x_i"])
+        );
+    }
+
+    #[test]
+    fn on_archive_it_should_resolve_inputs_and_includes() {
+        let archive = zip_archive(&[
+            ("paper/chapters/intro.tex", "\\section{Intro}\nIntro text."),
+            (
+                "paper/chapters/outro.tex",
+                "\\section{Outro}\nOutro text.\n\\input{chapters/outro}",
+            ),
+            (
+                "paper/main.tex",
+                "\\documentclass{book}\n\\begin{document}\n\\input{chapters/intro}\n% \\input{chapters/missing}\n\\include{./chapters/outro.tex}\n\\end{document}",
+            ),
+        ]);
+        assert!(is_latex_archive(&mut Cursor::new(&archive)).unwrap());
+
+        let mut latex_reader =
+            LatexReader::try_from_reader(Cursor::new(archive), None, LatexMathFormat::Raw).unwrap();
+
+        let contents = read_all(&mut latex_reader);
+
+        assert_eq!(
+            contents,
+            vec![
+                ("Intro text.".to_string(), json!({ "section": "Intro" })),
+                ("Outro text.".to_string(), json!({ "section": "Outro" })),
+            ]
+        );
+    }
+
+    #[test]
+    fn on_archive_without_main_document_it_should_fail() {
+        let archive = zip_archive(&[("src/main.rs", "fn main() {}")]);
+        assert!(!is_latex_archive(&mut Cursor::new(&archive)).unwrap());
+
+        let result = LatexReader::try_from_reader(Cursor::new(archive), None, LatexMathFormat::Raw);
+
+        assert!(matches!(result, Err(LatexReaderError::NoMainDocument)));
+    }
+
+    #[test]
+    fn on_math_it_should_normalize_commands_and_groups() {
+        assert_eq!(
+            normalize_math(r"\sum_{i=1}^{n} \frac{1}{i^2} \approx \infty"),
+            "sum_(i=1)^n (1) / (i^2) ~= infinity"
+        );
+        assert_eq!(
+            normalize_math(r"\sqrt[3]{x} \times \mathbf{v}"),
+            "root3(x) * v"
+        );
+    }
+}
@@ -1,4 +1,13 @@
+pub mod buffered_content;
+pub mod code_reader;
 pub mod epub_reader;
+pub mod html_reader;
+pub mod latex_reader;
+pub mod notebook_reader;
+pub mod office_reader;
 pub mod pdf_reader;
 pub mod simple_metadata_reader;
+pub mod structured_reader;
+pub mod subtitle_reader;
 pub mod xml_reader;
+pub mod zip_archive;
@@ -0,0 +1,440 @@
+use common::helper::error_chain_fmt;
+use serde::Deserialize;
+use serde_json::{json, Map, Value as JsonValue};
+use std::io::Read;
+use tracing::info;
+
+use crate::domain::{entities::meta_read::MetaRead, readers::buffered_content::BufferedContent};
+
+const NOTEBOOK_READER_META_KEY: &str = "notebook";
+const NOTEBOOK_READER_META_KEY_DEFAULT_INITIAL: &str = "initial";
+const NOTEBOOK_READER_META_KEY_CELL: &str = "cell";
+const NOTEBOOK_READER_META_KEY_CELL_TYPE: &str = "cell_type";
+const NOTEBOOK_READER_META_KEY_LANGUAGE: &str = "language";
+
+const CELL_TYPE_MARKDOWN: &str = "markdown";
+const CELL_TYPE_CODE: &str = "code";
+
+/// Minimal representation of a Jupyter notebook (nbformat 4)
+///
+/// Only the needed fields are deserialized: cell outputs are ignored.
+#[derive(Debug, Deserialize)]
+struct Notebook {
+    cells: Vec<NotebookCell>,
+    #[serde(default)]
+    metadata: NotebookMetadata,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct NotebookMetadata {
+    kernelspec: Option<KernelSpec>,
+    language_info: Option<LanguageInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KernelSpec {
+    language: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LanguageInfo {
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NotebookCell {
+    cell_type: String,
+    /// A multi-line string can be either a string or a list of lines
+    source: CellSource,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum CellSource {
+    Text(String),
+    Lines(Vec<String>),
+}
+
+impl CellSource {
+    fn to_text(&self) -> String {
+        match self {
+            CellSource::Text(text) => text.to_owned(),
+            CellSource::Lines(lines) => lines.concat(),
+        }
+    }
+}
+
+/// A markdown or code cell kept for reading
+#[derive(Debug)]
+struct ReadableCell {
+    /// 1-based index of the cell in the notebook (all cell types counted)
+    index: usize,
+    is_code: bool,
+    text: String,
+}
+
+/// Reader for Jupyter notebook sources (`.ipynb`)
+///
+/// Markdown cells are read as prose, code cells are read as code chunks tagged with the notebook language.
+/// Raw cells and cell outputs are ignored.
+/// Each cell is read separately: the metadata changes from one cell to another.
+///
+/// Code cells can be excluded from embedding (semantic search) while still being full-text searchable.
+pub struct NotebookReader {
+    cells: Vec<ReadableCell>,
+    current_cell_index: usize,
+    language: Option<String>,
+    embed_code_cells: bool,
+    is_current_cell_code: bool,
+
+    current_content: BufferedContent,
+
+    // MetaRead
+    metadata: JsonValue,
+}
+
+#[derive(thiserror::Error)]
+pub enum NotebookReaderError {
+    #[error(transparent)]
+    ReadError(#[from] std::io::Error),
+    #[error("Source is not a valid Jupyter notebook: {0}")]
+    InvalidNotebook(#[from] serde_json::Error),
+}
+
+impl std::fmt::Debug for NotebookReaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl NotebookReader {
+    /// Create a `NotebookReader` from a source reader (implementing Read)
+    ///
+    /// The whole source is read in memory, to be parsed as JSON.
+    ///
+    /// # Params
+    /// - reader: source reader implementing `Read`
+    /// - initial_meta: (optional) initial metadata as a JSON object
+    /// - embed_code_cells: if false, contents read from code cells should not be embedded
+    #[tracing::instrument(name = "Creating notebook reader", skip(reader))]
+    pub fn try_from_reader(
+        reader: impl Read,
+        initial_meta: Option<JsonValue>,
+        embed_code_cells: bool,
+    ) -> Result<Self, NotebookReaderError> {
+        let notebook: Notebook = serde_json::from_reader(reader)?;
+
+        // `language_info` is filled by the kernel once the notebook has run, `kernelspec` otherwise
+        let language = notebook
+            .metadata
+            .language_info
+            .and_then(|language_info| language_info.name)
+            .or_else(|| {
+                notebook
+                    .metadata
+                    .kernelspec
+                    .and_then(|kernelspec| kernelspec.language)
+            });
+
+        let cells = notebook
+            .cells
+            .iter()
+            .enumerate()
+            .filter_map(|(index, cell)| {
+                let is_code = match cell.cell_type.as_str() {
+                    CELL_TYPE_MARKDOWN => false,
+                    CELL_TYPE_CODE => true,
+                    _ => return None,
+                };
+
+                // Lines are separated by spaces, as line feeds are trimmed from the extracted contents
+                let text = cell
+                    .source
+                    .to_text()
+                    .split_whitespace()
+                    .collect::<Vec<&str>>()
+                    .join(" ");
+
+                if text.is_empty() {
+                    return None;
+                }
+
+                Some(ReadableCell {
+                    index: index + 1,
+                    is_code,
+                    // Separates 2 successive cells
+                    text: format!("{} ", text),
+                })
+            })
+            .collect::<Vec<ReadableCell>>();
+
+        let initial_meta = initial_meta.unwrap_or(JsonValue::Null);
+        let metadata = match initial_meta {
+            JsonValue::Object(map) => json!(map),
+            JsonValue::Null => JsonValue::Null,
+            _ => json!({ NOTEBOOK_READER_META_KEY_DEFAULT_INITIAL: initial_meta }),
+        };
+
+        info!(
+            "Notebook reader source: language: {:?}, nb readable cells: {}, initial metadata: {}",
+            language,
+            cells.len(),
+            metadata
+        );
+
+        Ok(Self {
+            cells,
+            current_cell_index: 0,
+            language,
+            embed_code_cells,
+            is_current_cell_code: false,
+            current_content: BufferedContent::default(),
+            metadata,
+        })
+    }
+
+    /// Gets content cell by cell
+    ///
+    /// # Returns
+    /// The number of chars cached. 0 if no more content is available.
+    fn go_next_content(&mut self) -> usize {
+        self.current_content = BufferedContent::default();
+
+        let Some(cell) = self.cells.get(self.current_cell_index) else {
+            return 0;
+        };
+        self.current_cell_index += 1;
+
+        self.current_content = BufferedContent::new(cell.text.chars());
+        let (index, is_code) = (cell.index, cell.is_code);
+        self.is_current_cell_code = is_code;
+
+        self.update_metadata(NOTEBOOK_READER_META_KEY_CELL, json!(index));
+        if is_code {
+            self.update_metadata(NOTEBOOK_READER_META_KEY_CELL_TYPE, json!(CELL_TYPE_CODE));
+            self.update_metadata(NOTEBOOK_READER_META_KEY_LANGUAGE, json!(self.language));
+        } else {
+            self.update_metadata(
+                NOTEBOOK_READER_META_KEY_CELL_TYPE,
+                json!(CELL_TYPE_MARKDOWN),
+            );
+            self.remove_metadata(NOTEBOOK_READER_META_KEY_LANGUAGE);
+        }
+
+        self.current_content.len()
+    }
+
+    /// Updates metadata as a JSON object
+    fn update_metadata(&mut self, key: &str, value: JsonValue) {
+        if let Some(map) = self.metadata.as_object_mut() {
+            map.insert(key.to_owned(), value);
+        } else {
+            let mut map = Map::new();
+            map.insert(key.to_owned(), value);
+            self.metadata = JsonValue::Object(map);
+        }
+    }
+
+    fn remove_metadata(&mut self, key: &str) {
+        if let Some(map) = self.metadata.as_object_mut() {
+            map.remove(key);
+        }
+    }
+}
+
+impl Read for NotebookReader {
+    // Reads bytes as unicode scalar values
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        // There is no more chars to read from the current cell, tries to get the next one
+        if self.current_content.is_read() && self.go_next_content() == 0 {
+            // No more to read
+            return Ok(0);
+        }
+
+        Ok(self.current_content.read(buf))
+    }
+}
+
+impl MetaRead for NotebookReader {
+    fn get_current_metadata(&self) -> JsonValue {
+        json!({ NOTEBOOK_READER_META_KEY: self.metadata.clone() })
+    }
+
+    fn should_skip_embedding(&self) -> bool {
+        self.is_current_cell_code && !self.embed_code_cells
+    }
+
+    fn is_current_content_code(&self) -> bool {
+        self.is_current_cell_code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::extractors::extract_content_generator::{
+        extract_content_generator, ChunkSplitting,
+    };
+    use genawaiter::GeneratorState;
+    use std::io::Cursor;
+
+    const NOTEBOOK_SAMPLE: &str = r##"{
+ "cells": [
+  {
+   "cell_type": "markdown",
+   "metadata": {},
+   "source": ["# Loading the data\n", "\n", "We load the dataset."]
+  },
+  {
+   "cell_type": "code",
+   "execution_count": 1,
+   "metadata": {},
+   "outputs": [{"output_type": "stream", "name": "stdout", "text": ["42\n"]}],
+   "source": ["import pandas as pd\n", "df = pd.read_csv(\"data.csv\")"]
+  },
+  {
+   "cell_type": "raw",
+   "metadata": {},
+   "source": "Some raw content"
+  },
+  {
+   "cell_type": "code",
+   "metadata": {},
+   "outputs": [],
+   "source": ""
+  },
+  {
+   "cell_type": "markdown",
+   "metadata": {},
+   "source": "The end."
+  }
+ ],
+ "metadata": {
+  "kernelspec": {"display_name": "Python 3", "language": "python", "name": "python3"}
+ },
+ "nbformat": 4,
+ "nbformat_minor": 5
+}"##;
+
+    fn read_all(notebook_reader: &mut NotebookReader) -> Vec<(String, JsonValue, bool)> {
+        let mut generator =
+            extract_content_generator(notebook_reader, Some(100), ChunkSplitting::Words);
+        let mut contents = vec![];
+
+        while let GeneratorState::Yielded(extracted_content) = generator.as_mut().resume() {
+            contents.push((
+                extracted_content.content.trim().to_owned(),
+                extracted_content.metadata,
+                extracted_content.skip_embedding,
+            ));
+        }
+
+        contents
+    }
+
+    #[test]
+    fn on_notebook_it_should_read_markdown_and_code_cells_separately() {
+        let mut notebook_reader =
+            NotebookReader::try_from_reader(Cursor::new(NOTEBOOK_SAMPLE), None, true).unwrap();
+
+        let contents = read_all(&mut notebook_reader);
+
+        let texts: Vec<&str> = contents.iter().map(|(text, _, _)| text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "# Loading the data We load the dataset.",
+                "import pandas as pd df = pd.read_csv(\"data.csv\")",
+                "The end.",
+            ]
+        );
+    }
+
+    #[test]
+    fn on_code_cell_it_should_record_the_language_in_metadata() {
+        let mut notebook_reader = NotebookReader::try_from_reader(
+            Cursor::new(NOTEBOOK_SAMPLE),
+            Some(json!({ "file": "notebook.ipynb" })),
+            true,
+        )
+        .unwrap();
+
+        let contents = read_all(&mut notebook_reader);
+
+        let (_, markdown_metadata, _) = &contents[0];
+        assert_eq!(
+            markdown_metadata[NOTEBOOK_READER_META_KEY],
+            json!({ "file": "notebook.ipynb", "cell": 1, "cell_type": "markdown" })
+        );
+
+        let (_, code_metadata, _) = &contents[1];
+        assert_eq!(
+            code_metadata[NOTEBOOK_READER_META_KEY],
+            json!({ "file": "notebook.ipynb", "cell": 2, "cell_type": "code", "language": "python" })
+        );
+
+        let (_, last_metadata, _) = &contents[2];
+        assert_eq!(last_metadata[NOTEBOOK_READER_META_KEY]["cell"], json!(5));
+        assert!(last_metadata[NOTEBOOK_READER_META_KEY]
+            .get(NOTEBOOK_READER_META_KEY_LANGUAGE)
+            .is_none());
+    }
+
+    #[test]
+    fn on_code_cells_not_embedded_it_should_only_skip_embedding_code_contents() {
+        let mut notebook_reader =
+            NotebookReader::try_from_reader(Cursor::new(NOTEBOOK_SAMPLE), None, false).unwrap();
+
+        let contents = read_all(&mut notebook_reader);
+
+        let skip_embeddings: Vec<bool> = contents
+            .iter()
+            .map(|(_, _, skip_embedding)| *skip_embedding)
+            .collect();
+        assert_eq!(skip_embeddings, vec![false, true, false]);
+    }
+
+    #[test]
+    fn on_code_cells_embedded_it_should_not_skip_embedding() {
+        let mut notebook_reader =
+            NotebookReader::try_from_reader(Cursor::new(NOTEBOOK_SAMPLE), None, true).unwrap();
+
+        let contents = read_all(&mut notebook_reader);
+
+        assert!(contents
+            .iter()
+            .all(|(_, _, skip_embedding)| !skip_embedding));
+    }
+
+    #[test]
+    fn on_language_info_it_should_prefer_it_over_kernelspec() {
+        let notebook = r#"{
+            "cells": [{"cell_type": "code", "metadata": {}, "outputs": [], "source": "x <- 1"}],
+            "metadata": {
+                "kernelspec": {"language": "python"},
+                "language_info": {"name": "R"}
+            },
+            "nbformat": 4,
+            "nbformat_minor": 5
+        }"#;
+        let mut notebook_reader =
+            NotebookReader::try_from_reader(Cursor::new(notebook), None, true).unwrap();
+
+        let contents = read_all(&mut notebook_reader);
+
+        assert_eq!(
+            contents[0].1[NOTEBOOK_READER_META_KEY][NOTEBOOK_READER_META_KEY_LANGUAGE],
+            json!("R")
+        );
+    }
+
+    #[test]
+    fn on_invalid_json_it_should_fail() {
+        let result = NotebookReader::try_from_reader(Cursor::new("not a notebook"), None, true);
+
+        assert!(matches!(
+            result,
+            Err(NotebookReaderError::InvalidNotebook(_))
+        ));
+    }
+}
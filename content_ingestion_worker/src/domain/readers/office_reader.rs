@@ -0,0 +1,660 @@
+use common::helper::error_chain_fmt;
+use quick_xml::events::{BytesStart, Event};
+use serde_json::{json, Map, Value as JsonValue};
+use std::io::{Read, Seek};
+use tracing::{info, warn};
+
+use crate::domain::{
+    entities::meta_read::MetaRead,
+    readers::{buffered_content::BufferedContent, zip_archive},
+};
+
+const OFFICE_READER_META_KEY: &str = "office";
+const OFFICE_READER_META_KEY_DEFAULT_INITIAL: &str = "initial";
+const OFFICE_READER_META_KEY_FORMAT: &str = "format";
+const OFFICE_READER_META_KEY_TITLE: &str = "title";
+const OFFICE_READER_META_KEY_HEADINGS: &str = "headings";
+
+/// Deepest heading level of the word processors, deeper headings are kept at this level
+const MAX_HEADING_LEVEL: usize = 9;
+
+/// Elements of an ODT document whose text is not part of the document flow
+const ODT_SKIPPED_ELEMENTS: [&str; 5] = [
+    // The number of a footnote, its body being read as a paragraph
+    "note-citation",
+    // Comments of the reviewers
+    "annotation",
+    // Deleted text kept for the review of the changes
+    "tracked-changes",
+    // Title and description of the images and shapes
+    "title",
+    "desc",
+];
+
+/// Word processing formats handled by the `OfficeReader`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfficeFormat {
+    /// Office Open XML, from Microsoft Word (`.docx`)
+    Docx,
+    /// OpenDocument Text, from LibreOffice Writer (`.odt`)
+    Odt,
+}
+
+impl OfficeFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OfficeFormat::Docx => "docx",
+            OfficeFormat::Odt => "odt",
+        }
+    }
+
+    /// Path in the archive of the XML part holding the text of the document
+    fn document_path(&self) -> &'static str {
+        match self {
+            OfficeFormat::Docx => "word/document.xml",
+            OfficeFormat::Odt => "content.xml",
+        }
+    }
+
+    /// Path in the archive of the XML part holding the properties of the document: its title etc.
+    fn properties_path(&self) -> &'static str {
+        match self {
+            OfficeFormat::Docx => "docProps/core.xml",
+            OfficeFormat::Odt => "meta.xml",
+        }
+    }
+}
+
+/// A paragraph of a document, as written in its XML part
+#[derive(Debug, Default, PartialEq)]
+struct OfficeParagraph {
+    /// Level of a heading, from 1. `None` for a body paragraph.
+    heading_level: Option<usize>,
+    /// Paragraph styled as the title of the document
+    is_title: bool,
+    text: String,
+}
+
+/// A body paragraph with its headings
+#[derive(Debug)]
+struct OfficeBlock {
+    /// Texts of the current headings, from the highest level to the lowest one
+    headings: Vec<String>,
+    text: String,
+}
+
+/// Reader for word processing documents: DOCX and ODT
+///
+/// Both formats are zip archives: the text of the document is parsed from its main XML part.
+/// The document is read paragraph by paragraph, with the texts of the current headings in the metadata.
+/// The headings are not read as contents. The title of the document is kept in the metadata,
+/// from its properties or from a paragraph styled as a title.
+///
+/// Only the document flow is read: the headers, the footers and the comments are not.
+pub struct OfficeReader {
+    blocks: Vec<OfficeBlock>,
+    current_block_index: usize,
+
+    current_content: BufferedContent,
+
+    // MetaRead
+    metadata: JsonValue,
+}
+
+#[derive(thiserror::Error)]
+pub enum OfficeReaderError {
+    #[error(transparent)]
+    ReadError(#[from] std::io::Error),
+    #[error(transparent)]
+    ZipError(#[from] zip::result::ZipError),
+    #[error("No {0} part found in the document archive")]
+    MissingDocumentPart(&'static str),
+}
+
+impl std::fmt::Debug for OfficeReaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl OfficeReader {
+    /// Create an `OfficeReader` from a source reader (implementing Read and Seek)
+    ///
+    /// The XML parts of the document are read in memory.
+    ///
+    /// # Params
+    /// - reader: source reader implementing `Read` and `Seek`, as needed by the zip archive
+    /// - format: format of the document
+    /// - initial_meta: (optional) initial metadata as a JSON object
+    #[tracing::instrument(name = "Creating office reader", skip(reader))]
+    pub fn try_from_reader(
+        reader: impl Read + Seek,
+        format: OfficeFormat,
+        initial_meta: Option<JsonValue>,
+    ) -> Result<Self, OfficeReaderError> {
+        let (document_path, properties_path) = (format.document_path(), format.properties_path());
+        let parts = zip_archive::read_text_files(reader, |path| {
+            path == document_path || path == properties_path
+        })?;
+
+        let document = parts
+            .iter()
+            .find(|part| part.path == document_path)
+            .ok_or(OfficeReaderError::MissingDocumentPart(document_path))?;
+        let paragraphs = match format {
+            OfficeFormat::Docx => parse_docx_paragraphs(&document.content),
+            OfficeFormat::Odt => parse_odt_paragraphs(&document.content),
+        };
+
+        // A document often has no title in its properties, but a first paragraph styled as a title
+        let title = parts
+            .iter()
+            .find(|part| part.path == properties_path)
+            .and_then(|properties| extract_title(&properties.content))
+            .or_else(|| {
+                paragraphs
+                    .iter()
+                    .find(|paragraph| paragraph.is_title)
+                    .map(|paragraph| paragraph.text.clone())
+            });
+        let blocks = build_blocks(paragraphs);
+
+        let initial_meta = initial_meta.unwrap_or(JsonValue::Null);
+        let metadata = match initial_meta {
+            JsonValue::Object(map) => json!(map),
+            JsonValue::Null => JsonValue::Null,
+            _ => json!({ OFFICE_READER_META_KEY_DEFAULT_INITIAL: initial_meta }),
+        };
+
+        info!(
+            "Office reader source: format: {}, title: {:?}, nb blocks: {}, initial metadata: {}",
+            format.as_str(),
+            title,
+            blocks.len(),
+            metadata
+        );
+
+        let mut office_reader = Self {
+            blocks,
+            current_block_index: 0,
+            current_content: BufferedContent::default(),
+            metadata,
+        };
+        office_reader.update_metadata(OFFICE_READER_META_KEY_FORMAT, json!(format.as_str()));
+        if let Some(title) = title {
+            office_reader.update_metadata(OFFICE_READER_META_KEY_TITLE, json!(title));
+        }
+
+        Ok(office_reader)
+    }
+
+    /// Gets content paragraph by paragraph
+    ///
+    /// # Returns
+    /// The number of chars cached. 0 if no more content is available.
+    fn go_next_content(&mut self) -> usize {
+        self.current_content = BufferedContent::default();
+
+        let Some(block) = self.blocks.get(self.current_block_index) else {
+            return 0;
+        };
+        self.current_block_index += 1;
+
+        self.current_content = BufferedContent::new(block.text.chars());
+
+        let headings = block.headings.clone();
+        if headings.is_empty() {
+            self.remove_metadata(OFFICE_READER_META_KEY_HEADINGS);
+        } else {
+            self.update_metadata(OFFICE_READER_META_KEY_HEADINGS, json!(headings));
+        }
+
+        self.current_content.len()
+    }
+
+    /// Updates metadata as a JSON object
+    fn update_metadata(&mut self, key: &str, value: JsonValue) {
+        if let Some(map) = self.metadata.as_object_mut() {
+            map.insert(key.to_owned(), value);
+        } else {
+            let mut map = Map::new();
+            map.insert(key.to_owned(), value);
+            self.metadata = JsonValue::Object(map);
+        }
+    }
+
+    fn remove_metadata(&mut self, key: &str) {
+        if let Some(map) = self.metadata.as_object_mut() {
+            map.remove(key);
+        }
+    }
+}
+
+impl Read for OfficeReader {
+    // Reads bytes as unicode scalar values
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        // There is no more chars to read from the current paragraph, tries to get the next one
+        if self.current_content.is_read() && self.go_next_content() == 0 {
+            // No more to read
+            return Ok(0);
+        }
+
+        Ok(self.current_content.read(buf))
+    }
+}
+
+impl MetaRead for OfficeReader {
+    fn get_current_metadata(&self) -> JsonValue {
+        json!({ OFFICE_READER_META_KEY: self.metadata.clone() })
+    }
+}
+
+/// Parses the paragraphs of the `word/document.xml` part of a DOCX document
+///
+/// The text of a paragraph (`<w:p>`) is in the text elements (`<w:t>`) of its runs: the deleted text
+/// (`<w:delText>`) and the field codes (`<w:instrText>`) are not read.
+/// A heading is a paragraph with a `HeadingN` style or an outline level.
+/// The paragraphs of a text box, nested in a paragraph, are parsed before it.
+fn parse_docx_paragraphs(source: &str) -> Vec<OfficeParagraph> {
+    let mut reader = quick_xml::Reader::from_str(source);
+    let mut paragraphs = vec![];
+    let mut open_paragraphs: Vec<OfficeParagraph> = vec![];
+    let mut inside_text = 0;
+
+    loop {
+        let event = match reader.read_event() {
+            Ok(event) => event,
+            Err(error) => {
+                // Keeps the paragraphs parsed until the malformed part
+                warn!(
+                    ?error,
+                    "Stopping to parse the DOCX document at position {}",
+                    reader.buffer_position()
+                );
+                break;
+            }
+        };
+
+        match event {
+            Event::Eof => break,
+            Event::Start(tag) => match tag.local_name().as_ref() {
+                b"p" => open_paragraphs.push(OfficeParagraph::default()),
+                b"t" => inside_text += 1,
+                _ => on_docx_empty_element(&tag, open_paragraphs.last_mut()),
+            },
+            Event::Empty(tag) => on_docx_empty_element(&tag, open_paragraphs.last_mut()),
+            Event::End(tag) => match tag.local_name().as_ref() {
+                b"p" => {
+                    if let Some(paragraph) = open_paragraphs.pop() {
+                        paragraphs.push(paragraph);
+                    }
+                }
+                b"t" => inside_text = usize::saturating_sub(inside_text, 1),
+                _ => (),
+            },
+            Event::Text(text) if inside_text > 0 => {
+                if let Some(paragraph) = open_paragraphs.last_mut() {
+                    paragraph.text.push_str(&unescape_text(&text));
+                }
+            }
+            // There are several other `Event`s we do not consider here
+            _ => (),
+        }
+    }
+
+    paragraphs
+}
+
+/// Handles an element of a DOCX paragraph without text: its style, or a separation
+fn on_docx_empty_element(tag: &BytesStart, paragraph: Option<&mut OfficeParagraph>) {
+    let Some(paragraph) = paragraph else {
+        return;
+    };
+
+    match tag.local_name().as_ref() {
+        b"tab" | b"br" | b"cr" => paragraph.text.push(' '),
+        b"pStyle" => {
+            let style = attribute_value(tag, "val")
+                .unwrap_or_default()
+                .to_lowercase()
+                .replace(' ', "");
+
+            if style == "title" {
+                paragraph.is_title = true;
+            } else if let Some(level) = style
+                .strip_prefix("heading")
+                .and_then(|level| level.parse::<usize>().ok())
+            {
+                paragraph.heading_level = Some(level);
+            }
+        }
+        // From 0, 9 being the level of the body text
+        b"outlineLvl" => {
+            if let Some(level) = attribute_value(tag, "val")
+                .and_then(|level| level.parse::<usize>().ok())
+                .filter(|level| *level < MAX_HEADING_LEVEL)
+            {
+                paragraph.heading_level.get_or_insert(level + 1);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Parses the paragraphs of the `content.xml` part of an ODT document
+///
+/// The body paragraphs are `<text:p>` elements, and the headings are `<text:h>` elements with an outline level.
+/// The paragraphs of a footnote or a frame, nested in a paragraph, are parsed before it.
+fn parse_odt_paragraphs(source: &str) -> Vec<OfficeParagraph> {
+    let mut reader = quick_xml::Reader::from_str(source);
+    let mut paragraphs = vec![];
+    let mut open_paragraphs: Vec<OfficeParagraph> = vec![];
+    let mut inside_skipped = 0;
+
+    loop {
+        let event = match reader.read_event() {
+            Ok(event) => event,
+            Err(error) => {
+                // Keeps the paragraphs parsed until the malformed part
+                warn!(
+                    ?error,
+                    "Stopping to parse the ODT document at position {}",
+                    reader.buffer_position()
+                );
+                break;
+            }
+        };
+
+        match event {
+            Event::Eof => break,
+            Event::Start(tag) => {
+                let name = tag.local_name();
+                if inside_skipped > 0
+                    || ODT_SKIPPED_ELEMENTS
+                        .iter()
+                        .any(|skipped| skipped.as_bytes() == name.as_ref())
+                {
+                    inside_skipped += 1;
+                    continue;
+                }
+
+                match name.as_ref() {
+                    b"p" => open_paragraphs.push(OfficeParagraph::default()),
+                    b"h" => open_paragraphs.push(OfficeParagraph {
+                        heading_level: Some(
+                            attribute_value(&tag, "outline-level")
+                                .and_then(|level| level.parse::<usize>().ok())
+                                .unwrap_or(1),
+                        ),
+                        ..OfficeParagraph::default()
+                    }),
+                    _ => (),
+                }
+            }
+            Event::Empty(tag) if inside_skipped == 0 => {
+                if let (b"s" | b"tab" | b"line-break", Some(paragraph)) =
+                    (tag.local_name().as_ref(), open_paragraphs.last_mut())
+                {
+                    paragraph.text.push(' ');
+                }
+            }
+            Event::End(tag) => {
+                if inside_skipped > 0 {
+                    inside_skipped -= 1;
+                    continue;
+                }
+
+                if matches!(tag.local_name().as_ref(), b"p" | b"h") {
+                    if let Some(paragraph) = open_paragraphs.pop() {
+                        paragraphs.push(paragraph);
+                    }
+                }
+            }
+            Event::Text(text) if inside_skipped == 0 => {
+                if let Some(paragraph) = open_paragraphs.last_mut() {
+                    paragraph.text.push_str(&unescape_text(&text));
+                }
+            }
+            // There are several other `Event`s we do not consider here
+            _ => (),
+        }
+    }
+
+    paragraphs
+}
+
+/// Extracts the title (`<dc:title>`) from the properties of a document
+fn extract_title(source: &str) -> Option<String> {
+    let mut reader = quick_xml::Reader::from_str(source);
+    let mut inside_title = false;
+    let mut title = String::new();
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(tag)) if tag.name().as_ref() == b"dc:title" => inside_title = true,
+            Ok(Event::End(tag)) if tag.name().as_ref() == b"dc:title" => break,
+            Ok(Event::Text(text)) if inside_title => title.push_str(&unescape_text(&text)),
+            Ok(Event::Eof) | Err(_) => break,
+            _ => (),
+        }
+    }
+
+    Some(collapse_whitespace(&title)).filter(|title| !title.is_empty())
+}
+
+/// Groups the body paragraphs of a document with their headings
+fn build_blocks(paragraphs: Vec<OfficeParagraph>) -> Vec<OfficeBlock> {
+    let mut blocks = vec![];
+    // Headings of the higher levels, followed by the current heading
+    let mut headings: Vec<(usize, String)> = vec![];
+
+    for paragraph in paragraphs {
+        let text = collapse_whitespace(&paragraph.text);
+        if text.is_empty() || paragraph.is_title {
+            continue;
+        }
+
+        match paragraph.heading_level {
+            Some(level) => {
+                let level = level.clamp(1, MAX_HEADING_LEVEL);
+                headings.retain(|(heading_level, _)| *heading_level < level);
+                headings.push((level, text));
+            }
+            None => blocks.push(OfficeBlock {
+                headings: headings
+                    .iter()
+                    .map(|(_, heading)| heading.clone())
+                    .collect(),
+                // Separates 2 successive paragraphs
+                text: format!("{} ", text),
+            }),
+        }
+    }
+
+    blocks
+}
+
+fn attribute_value(tag: &BytesStart, name: &str) -> Option<String> {
+    tag.attributes()
+        .filter_map(Result::ok)
+        .find(|attribute| attribute.key.local_name().as_ref() == name.as_bytes())
+        .map(|attribute| {
+            attribute
+                .unescape_value()
+                .map(|value| value.into_owned())
+                .unwrap_or_else(|_| String::from_utf8_lossy(&attribute.value).into_owned())
+        })
+}
+
+fn unescape_text(text: &quick_xml::events::BytesText) -> String {
+    text.unescape()
+        .map(|text| text.into_owned())
+        .unwrap_or_else(|_| String::from_utf8_lossy(text).into_owned())
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::extractors::extract_content_generator::{
+        extract_content_generator, ChunkSplitting,
+    };
+    use genawaiter::GeneratorState;
+    use std::io::{Cursor, Write};
+    use zip::write::FileOptions;
+
+    const DOCX_DOCUMENT: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+  <w:body>
+    <w:p><w:pPr><w:pStyle w:val="Title"/></w:pPr><w:r><w:t>Deployment guide</w:t></w:r></w:p>
+    <w:p><w:r><w:t xml:space="preserve">Read this </w:t></w:r><w:r><w:rPr><w:b/></w:rPr><w:t>first</w:t></w:r><w:r><w:t>.</w:t></w:r></w:p>
+    <w:p><w:pPr><w:pStyle w:val="Heading1"/></w:pPr><w:r><w:t>Installation</w:t></w:r></w:p>
+    <w:p><w:r><w:t>Download</w:t><w:tab/><w:t>the binary</w:t></w:r><w:del><w:r><w:delText>removed text</w:delText></w:r></w:del></w:p>
+    <w:p><w:pPr><w:pStyle w:val="Heading2"/></w:pPr><w:r><w:t>On Linux</w:t></w:r></w:p>
+    <w:p><w:r><w:instrText> PAGE </w:instrText><w:t>Use the package &amp; its service.</w:t></w:r></w:p>
+    <w:p><w:pPr><w:outlineLvl w:val="0"/></w:pPr><w:r><w:t>Usage</w:t></w:r></w:p>
+    <w:p/>
+    <w:p><w:r><w:t>Run it.</w:t></w:r></w:p>
+  </w:body>
+</w:document>"#;
+
+    const ODT_CONTENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0">
+  <office:body>
+    <office:text>
+      <text:h text:outline-level="1">Installation</text:h>
+      <text:p>Download<text:s/>the <text:span>binary</text:span><text:note><text:note-citation>1</text:note-citation><text:note-body><text:p>From the website.</text:p></text:note-body></text:note>.</text:p>
+      <text:h text:outline-level="2">On Linux</text:h>
+      <text:p>Use the package<office:annotation><text:p>A comment</text:p></office:annotation>.</text:p>
+      <text:h text:outline-level="1">Usage</text:h>
+      <text:p>Run it.</text:p>
+    </office:text>
+  </office:body>
+</office:document-content>"#;
+
+    const ODT_META: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-meta xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:dc="http://purl.org/dc/elements/1.1/">
+  <office:meta><dc:title>Deployment guide</dc:title></office:meta>
+</office:document-meta>"#;
+
+    fn read_all(office_reader: &mut OfficeReader) -> Vec<(String, JsonValue)> {
+        let mut generator =
+            extract_content_generator(office_reader, Some(100), ChunkSplitting::Words);
+        let mut contents = vec![];
+
+        while let GeneratorState::Yielded(extracted_content) = generator.as_mut().resume() {
+            contents.push((
+                extracted_content.content.trim().to_owned(),
+                extracted_content.metadata[OFFICE_READER_META_KEY].clone(),
+            ));
+        }
+
+        contents
+    }
+
+    fn zip_archive(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(vec![]));
+
+        for (name, content) in files {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn on_docx_it_should_read_the_paragraphs_with_their_headings() {
+        let archive = zip_archive(&[
+            ("[Content_Types].xml", "<Types/>"),
+            ("word/document.xml", DOCX_DOCUMENT),
+        ]);
+        let mut office_reader =
+            OfficeReader::try_from_reader(Cursor::new(archive), OfficeFormat::Docx, None).unwrap();
+
+        let contents = read_all(&mut office_reader);
+
+        assert_eq!(
+            contents,
+            vec![
+                (
+                    "Read this first.".to_string(),
+                    json!({ "format": "docx", "title": "Deployment guide" })
+                ),
+                (
+                    "Download the binary".to_string(),
+                    json!({ "format": "docx", "title": "Deployment guide", "headings": ["Installation"] })
+                ),
+                (
+                    "Use the package & its service.".to_string(),
+                    json!({ "format": "docx", "title": "Deployment guide", "headings": ["Installation", "On Linux"] })
+                ),
+                (
+                    "Run it.".to_string(),
+                    json!({ "format": "docx", "title": "Deployment guide", "headings": ["Usage"] })
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn on_odt_it_should_read_the_paragraphs_with_their_headings() {
+        let archive = zip_archive(&[
+            ("mimetype", "application/vnd.oasis.opendocument.text"),
+            ("content.xml", ODT_CONTENT),
+            ("meta.xml", ODT_META),
+        ]);
+        let mut office_reader = OfficeReader::try_from_reader(
+            Cursor::new(archive),
+            OfficeFormat::Odt,
+            Some(json!({ "file": "guide.odt" })),
+        )
+        .unwrap();
+
+        let contents = read_all(&mut office_reader);
+
+        assert_eq!(
+            contents,
+            vec![
+                (
+                    "From the website. Download the binary.".to_string(),
+                    json!({ "file": "guide.odt", "format": "odt", "title": "Deployment guide", "headings": ["Installation"] })
+                ),
+                (
+                    "Use the package.".to_string(),
+                    json!({ "file": "guide.odt", "format": "odt", "title": "Deployment guide", "headings": ["Installation", "On Linux"] })
+                ),
+                (
+                    "Run it.".to_string(),
+                    json!({ "file": "guide.odt", "format": "odt", "title": "Deployment guide", "headings": ["Usage"] })
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn on_archive_without_document_part_it_should_fail() {
+        let archive = zip_archive(&[("content.xml", ODT_CONTENT)]);
+
+        let result = OfficeReader::try_from_reader(Cursor::new(archive), OfficeFormat::Docx, None);
+
+        assert!(matches!(
+            result,
+            Err(OfficeReaderError::MissingDocumentPart("word/document.xml"))
+        ));
+    }
+
+    #[test]
+    fn on_source_not_being_an_archive_it_should_fail() {
+        let result = OfficeReader::try_from_reader(
+            Cursor::new(b"not a document".to_vec()),
+            OfficeFormat::Odt,
+            None,
+        );
+
+        assert!(matches!(result, Err(OfficeReaderError::ZipError(_))));
+    }
+}
@@ -1,8 +1,11 @@
-use std::io::Read;
+use std::{collections::HashMap, io::Read};
 
 use serde_json::{json, Value as JsonValue};
 
-use crate::domain::entities::meta_read::MetaRead;
+use crate::domain::entities::{
+    meta_read::MetaRead,
+    resource_read::{Resource, ResourceRead},
+};
 
 pub const SIMPLE_READER_META_KEY: &str = "simple";
 pub const SIMPLE_READER_META_KEY_DEFAULT: &str = "default_key";
@@ -13,6 +16,8 @@ pub const SIMPLE_READER_META_KEY_DEFAULT: &str = "default_key";
 pub struct SimpleMetadataReader<Reader: Read> {
     reader: Reader,
     metadata: JsonValue,
+    /// Resources by their path
+    resources: HashMap<String, Vec<u8>>,
 }
 
 impl<Reader: Read> SimpleMetadataReader<Reader> {
@@ -24,7 +29,17 @@ impl<Reader: Read> SimpleMetadataReader<Reader> {
             _ => json!({ SIMPLE_READER_META_KEY_DEFAULT: metadata }),
         };
 
-        Self { reader, metadata }
+        Self {
+            reader,
+            metadata,
+            resources: HashMap::new(),
+        }
+    }
+
+    /// Adds a resource that can be referenced from the content by its path
+    pub fn with_resource(mut self, path: &str, content: Vec<u8>) -> Self {
+        self.resources.insert(path.to_string(), content);
+        self
     }
 }
 
@@ -39,3 +54,12 @@ impl<Reader: Read> Read for SimpleMetadataReader<Reader> {
         self.reader.read(buf)
     }
 }
+
+impl<Reader: Read> ResourceRead for SimpleMetadataReader<Reader> {
+    fn read_resource(&mut self, reference: &str) -> Option<Resource> {
+        self.resources.get(reference).map(|content| Resource {
+            path: reference.to_string(),
+            content: content.clone(),
+        })
+    }
+}
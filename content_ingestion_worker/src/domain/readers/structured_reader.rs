@@ -0,0 +1,437 @@
+use common::helper::error_chain_fmt;
+use serde_json::{json, Map, Value as JsonValue};
+use std::io::{BufRead, BufReader, ErrorKind, Lines, Read};
+use tracing::{info, warn};
+
+use crate::domain::{entities::meta_read::MetaRead, readers::buffered_content::BufferedContent};
+
+const STRUCTURED_READER_META_KEY: &str = "structured";
+const STRUCTURED_READER_META_KEY_DEFAULT_INITIAL: &str = "initial";
+const STRUCTURED_READER_META_KEY_FORMAT: &str = "format";
+const STRUCTURED_READER_META_KEY_ROW: &str = "row";
+const STRUCTURED_READER_META_KEY_FIELDS: &str = "fields";
+
+/// Structured data formats handled by the `StructuredReader`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StructuredFormat {
+    /// Comma-separated values, with a header row naming the columns
+    Csv,
+    /// JSON Lines: one JSON object per line, whose keys are the columns
+    Jsonl,
+}
+
+impl StructuredFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StructuredFormat::Csv => "csv",
+            StructuredFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// Rows of the source, parsed as they are read
+enum Rows<SourceReader: Read> {
+    Csv {
+        headers: Vec<String>,
+        records: csv::StringRecordsIntoIter<SourceReader>,
+    },
+    Jsonl(Lines<BufReader<SourceReader>>),
+}
+
+/// Reader for structured data: CSV and JSONL files
+///
+/// The source is read row by row (a CSV record or a JSONL object), each row being its own content.
+/// The values of the content columns, in their given order, are the searchable content of a row.
+/// The values of the other columns are kept as strings in the `fields` of the metadata, with the number of the row,
+/// for the searches to filter on them. Without content columns, all the columns are the content.
+///
+/// A row that can not be parsed, or without content, is skipped.
+pub struct StructuredReader<SourceReader: Read> {
+    rows: Rows<SourceReader>,
+    content_columns: Vec<String>,
+    /// Number of the current row, from 1, without the header row of a CSV file
+    current_row: usize,
+
+    current_content: BufferedContent,
+
+    // MetaRead
+    metadata: JsonValue,
+}
+
+#[derive(thiserror::Error)]
+pub enum StructuredReaderError {
+    #[error(transparent)]
+    CsvError(#[from] csv::Error),
+    #[error("None of the content columns {0:?} is a column of the source")]
+    MissingContentColumns(Vec<String>),
+}
+
+impl std::fmt::Debug for StructuredReaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl<SourceReader: Read> StructuredReader<SourceReader> {
+    /// Create a `StructuredReader` from a source reader (implementing Read)
+    ///
+    /// The rows are parsed while the source is read: only the header row of a CSV file is read beforehand,
+    /// to check that the content columns are columns of the source.
+    ///
+    /// # Params
+    /// - reader: source reader implementing `Read`
+    /// - format: format of the source
+    /// - content_columns: columns whose values are the content of the rows. All the columns if empty.
+    /// - initial_meta: (optional) initial metadata as a JSON object
+    #[tracing::instrument(name = "Creating structured reader", skip(reader))]
+    pub fn try_from_reader(
+        reader: SourceReader,
+        format: StructuredFormat,
+        content_columns: Vec<String>,
+        initial_meta: Option<JsonValue>,
+    ) -> Result<Self, StructuredReaderError> {
+        let rows = match format {
+            StructuredFormat::Csv => {
+                let mut csv_reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+                let headers: Vec<String> = csv_reader
+                    .headers()?
+                    .iter()
+                    .map(|header| header.trim_start_matches('\u{feff}').trim().to_string())
+                    .collect();
+
+                if !content_columns.is_empty()
+                    && !content_columns
+                        .iter()
+                        .any(|column| headers.contains(column))
+                {
+                    return Err(StructuredReaderError::MissingContentColumns(
+                        content_columns,
+                    ));
+                }
+
+                Rows::Csv {
+                    headers,
+                    records: csv_reader.into_records(),
+                }
+            }
+            StructuredFormat::Jsonl => Rows::Jsonl(BufReader::new(reader).lines()),
+        };
+
+        let initial_meta = initial_meta.unwrap_or(JsonValue::Null);
+        let metadata = match initial_meta {
+            JsonValue::Object(map) => json!(map),
+            JsonValue::Null => JsonValue::Null,
+            _ => json!({ STRUCTURED_READER_META_KEY_DEFAULT_INITIAL: initial_meta }),
+        };
+
+        info!(
+            "Structured reader source: format: {}, content columns: {:?}, initial metadata: {}",
+            format.as_str(),
+            content_columns,
+            metadata
+        );
+
+        let mut structured_reader = Self {
+            rows,
+            content_columns,
+            current_row: 0,
+            current_content: BufferedContent::default(),
+            metadata,
+        };
+        structured_reader
+            .update_metadata(STRUCTURED_READER_META_KEY_FORMAT, json!(format.as_str()));
+
+        Ok(structured_reader)
+    }
+
+    /// Gets content row by row, skipping the rows without content
+    ///
+    /// # Returns
+    /// The number of chars cached. 0 if no more content is available.
+    fn go_next_content(&mut self) -> usize {
+        self.current_content = BufferedContent::default();
+
+        while let Some(columns) = self.next_row() {
+            let (content, fields) = split_columns(columns, &self.content_columns);
+            if content.is_empty() {
+                continue;
+            }
+
+            // Separates the contents of 2 successive rows
+            self.current_content = BufferedContent::new(format!("{} ", content).chars());
+            self.update_metadata(STRUCTURED_READER_META_KEY_ROW, json!(self.current_row));
+            self.update_metadata(STRUCTURED_READER_META_KEY_FIELDS, JsonValue::Object(fields));
+            break;
+        }
+
+        self.current_content.len()
+    }
+
+    /// Parses the next row into its columns and their values, skipping the rows that can not be parsed
+    fn next_row(&mut self) -> Option<Vec<(String, String)>> {
+        loop {
+            self.current_row += 1;
+
+            match &mut self.rows {
+                Rows::Csv { headers, records } => match records.next()? {
+                    Ok(record) => {
+                        return Some(
+                            headers
+                                .iter()
+                                .cloned()
+                                .zip(record.iter().map(str::to_string))
+                                .collect(),
+                        )
+                    }
+                    Err(error) => {
+                        warn!(
+                            ?error,
+                            "Skipping row {} of the CSV source", self.current_row
+                        )
+                    }
+                },
+                Rows::Jsonl(lines) => {
+                    let line = match lines.next()? {
+                        Ok(line) => line,
+                        // The line is consumed: the next lines can still be read
+                        Err(error) if error.kind() == ErrorKind::InvalidData => {
+                            warn!(
+                                ?error,
+                                "Skipping line {} of the JSONL source", self.current_row
+                            );
+                            continue;
+                        }
+                        Err(error) => {
+                            warn!(?error, "Stopping to read the JSONL source");
+                            return None;
+                        }
+                    };
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+
+                    match serde_json::from_str::<JsonValue>(&line) {
+                        Ok(JsonValue::Object(object)) => {
+                            return Some(
+                                object
+                                    .into_iter()
+                                    .filter_map(|(key, value)| {
+                                        json_value_to_string(value).map(|value| (key, value))
+                                    })
+                                    .collect(),
+                            )
+                        }
+                        Ok(_) => warn!(
+                            "Skipping line {} of the JSONL source: not a JSON object",
+                            self.current_row
+                        ),
+                        Err(error) => warn!(
+                            ?error,
+                            "Skipping line {} of the JSONL source", self.current_row
+                        ),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Updates metadata as a JSON object
+    fn update_metadata(&mut self, key: &str, value: JsonValue) {
+        if let Some(map) = self.metadata.as_object_mut() {
+            map.insert(key.to_owned(), value);
+        } else {
+            let mut map = Map::new();
+            map.insert(key.to_owned(), value);
+            self.metadata = JsonValue::Object(map);
+        }
+    }
+}
+
+impl<SourceReader: Read> Read for StructuredReader<SourceReader> {
+    // Reads bytes as unicode scalar values
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        // There is no more chars to read from the current row, tries to get the next one
+        if self.current_content.is_read() && self.go_next_content() == 0 {
+            // No more to read
+            return Ok(0);
+        }
+
+        Ok(self.current_content.read(buf))
+    }
+}
+
+impl<SourceReader: Read> MetaRead for StructuredReader<SourceReader> {
+    fn get_current_metadata(&self) -> JsonValue {
+        json!({ STRUCTURED_READER_META_KEY: self.metadata.clone() })
+    }
+}
+
+/// Splits the columns of a row into its content, from the values of the content columns in their order,
+/// and its fields, from the other non-empty values
+fn split_columns(
+    columns: Vec<(String, String)>,
+    content_columns: &[String],
+) -> (String, Map<String, JsonValue>) {
+    let mut fields = Map::new();
+    let mut content_values = vec![];
+
+    for (column, value) in columns {
+        let value = value.split_whitespace().collect::<Vec<&str>>().join(" ");
+        if value.is_empty() {
+            continue;
+        }
+
+        if content_columns.is_empty() {
+            content_values.push((0, value));
+        } else if let Some(index) = content_columns.iter().position(|c| *c == column) {
+            content_values.push((index, value));
+        } else {
+            fields.insert(column, JsonValue::String(value));
+        }
+    }
+
+    // Stable sort: without content columns, the columns keep the order of the source
+    content_values.sort_by_key(|(index, _)| *index);
+    let content = content_values
+        .into_iter()
+        .map(|(_, value)| value)
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    (content, fields)
+}
+
+/// Value of a JSONL column as a string, for the values of all the columns to be filtered the same way.
+/// The arrays and objects are kept as JSON.
+fn json_value_to_string(value: JsonValue) -> Option<String> {
+    match value {
+        JsonValue::Null => None,
+        JsonValue::String(value) => Some(value),
+        value => Some(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::extractors::extract_content_generator::{
+        extract_content_generator, ChunkSplitting,
+    };
+    use genawaiter::GeneratorState;
+    use std::io::Cursor;
+
+    fn read_all<R: Read>(structured_reader: &mut StructuredReader<R>) -> Vec<(String, JsonValue)> {
+        let mut generator =
+            extract_content_generator(structured_reader, Some(100), ChunkSplitting::Words);
+        let mut contents = vec![];
+
+        while let GeneratorState::Yielded(extracted_content) = generator.as_mut().resume() {
+            contents.push((
+                extracted_content.content.trim().to_owned(),
+                extracted_content.metadata[STRUCTURED_READER_META_KEY].clone(),
+            ));
+        }
+
+        contents
+    }
+
+    #[test]
+    fn on_csv_it_should_read_the_content_columns_and_keep_the_others_as_fields() {
+        let source = "\u{feff}id,title,description,category\n\
+            1,Rivers,\"Rivers flow, to the sea\",geography\n\
+            2,,,history\n\
+            3,Mountains,Mountains are high,\n";
+        let mut structured_reader = StructuredReader::try_from_reader(
+            Cursor::new(source),
+            StructuredFormat::Csv,
+            vec!["title".to_string(), "description".to_string()],
+            None,
+        )
+        .unwrap();
+
+        let contents = read_all(&mut structured_reader);
+
+        assert_eq!(
+            contents,
+            vec![
+                (
+                    "Rivers Rivers flow, to the sea".to_string(),
+                    json!({ "format": "csv", "row": 1, "fields": { "id": "1", "category": "geography" } })
+                ),
+                (
+                    "Mountains Mountains are high".to_string(),
+                    json!({ "format": "csv", "row": 3, "fields": { "id": "3" } })
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn on_csv_without_content_columns_it_should_read_all_the_columns() {
+        let source = "title,description\nRivers,Rivers flow\n";
+        let mut structured_reader = StructuredReader::try_from_reader(
+            Cursor::new(source),
+            StructuredFormat::Csv,
+            vec![],
+            Some(json!({ "file": "rivers.csv" })),
+        )
+        .unwrap();
+
+        let contents = read_all(&mut structured_reader);
+
+        assert_eq!(
+            contents,
+            vec![(
+                "Rivers Rivers flow".to_string(),
+                json!({ "file": "rivers.csv", "format": "csv", "row": 1, "fields": {} })
+            )]
+        );
+    }
+
+    #[test]
+    fn on_csv_without_any_content_column_it_should_fail() {
+        let result = StructuredReader::try_from_reader(
+            Cursor::new("title,description\nRivers,Rivers flow\n"),
+            StructuredFormat::Csv,
+            vec!["body".to_string()],
+            None,
+        );
+
+        assert!(matches!(
+            result,
+            Err(StructuredReaderError::MissingContentColumns(_))
+        ));
+    }
+
+    #[test]
+    fn on_jsonl_it_should_read_each_object_and_skip_the_invalid_lines() {
+        let source = "{\"text\": \"Rivers flow\", \"year\": 2021, \"tags\": [\"water\"], \"author\": null}\n\
+            \n\
+            not json\n\
+            [\"not an object\"]\n\
+            {\"text\": \"Mountains are high\", \"published\": true}\n";
+        let mut structured_reader = StructuredReader::try_from_reader(
+            Cursor::new(source),
+            StructuredFormat::Jsonl,
+            vec!["text".to_string()],
+            None,
+        )
+        .unwrap();
+
+        let contents = read_all(&mut structured_reader);
+
+        assert_eq!(
+            contents,
+            vec![
+                (
+                    "Rivers flow".to_string(),
+                    json!({ "format": "jsonl", "row": 1, "fields": { "year": "2021", "tags": "[\"water\"]" } })
+                ),
+                (
+                    "Mountains are high".to_string(),
+                    json!({ "format": "jsonl", "row": 5, "fields": { "published": "true" } })
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,494 @@
+use common::helper::error_chain_fmt;
+use once_cell::sync::Lazy;
+use serde_json::{json, Map, Value as JsonValue};
+use std::io::Read;
+use tracing::{info, warn};
+
+use crate::domain::{entities::meta_read::MetaRead, readers::buffered_content::BufferedContent};
+
+const SUBTITLE_READER_META_KEY: &str = "subtitle";
+const SUBTITLE_READER_META_KEY_DEFAULT_INITIAL: &str = "initial";
+const SUBTITLE_READER_META_KEY_FORMAT: &str = "format";
+const SUBTITLE_READER_META_KEY_START_TIME: &str = "start_time";
+const SUBTITLE_READER_META_KEY_END_TIME: &str = "end_time";
+const SUBTITLE_READER_META_KEY_START_MS: &str = "start_ms";
+const SUBTITLE_READER_META_KEY_END_MS: &str = "end_ms";
+const SUBTITLE_READER_META_KEY_FIRST_CUE: &str = "first_cue";
+const SUBTITLE_READER_META_KEY_LAST_CUE: &str = "last_cue";
+
+pub const DEFAULT_NB_WORDS_PER_CUE_GROUP: usize = 50;
+
+const SENTENCE_ENDING_CHARS: [char; 3] = ['.', '?', '!'];
+
+/// Subtitle formats handled by the `SubtitleReader`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+}
+
+impl SubtitleFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "vtt",
+        }
+    }
+}
+
+/// A subtitle cue: a text displayed between 2 timestamps
+#[derive(Debug, Clone, PartialEq)]
+struct Cue {
+    start_ms: u64,
+    end_ms: u64,
+    text: String,
+}
+
+/// Successive cues merged together into a readable chunk
+#[derive(Debug)]
+struct CueGroup {
+    /// 1-based index of the first and last cues of the group
+    first_cue: usize,
+    last_cue: usize,
+    start_ms: u64,
+    end_ms: u64,
+    text: String,
+}
+
+/// Reader for subtitle sources: SubRip (`.srt`) and WebVTT (`.vtt`)
+///
+/// A subtitle file is made of many short cues (often a few words each).
+/// Successive cues are merged into groups of around `nb_words_per_cue_group` words, preferably ending on a sentence end,
+/// so each read chunk is readable on its own.
+///
+/// The start and end timestamps of the currently read group are kept in the metadata,
+/// enabling time-coded search over transcripts.
+pub struct SubtitleReader {
+    cue_groups: Vec<CueGroup>,
+    current_group_index: usize,
+
+    current_content: BufferedContent,
+
+    // MetaRead
+    metadata: JsonValue,
+}
+
+#[derive(thiserror::Error)]
+pub enum SubtitleReaderError {
+    #[error(transparent)]
+    ReadError(#[from] std::io::Error),
+    #[error(transparent)]
+    Utf8Error(#[from] std::string::FromUtf8Error),
+}
+
+impl std::fmt::Debug for SubtitleReaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl SubtitleReader {
+    /// Create a `SubtitleReader` from a source reader (implementing Read)
+    ///
+    /// The whole source is read in memory: subtitle files are small.
+    /// The format (SRT or WebVTT) is detected from the `WEBVTT` header.
+    ///
+    /// # Params
+    /// - reader: source reader implementing `Read`
+    /// - initial_meta: (optional) initial metadata as a JSON object
+    /// - nb_words_per_cue_group: (optional) number of words after which successive cues stop being merged.
+    ///   Default to `DEFAULT_NB_WORDS_PER_CUE_GROUP`.
+    #[tracing::instrument(name = "Creating subtitle reader", skip(reader))]
+    pub fn try_from_reader(
+        mut reader: impl Read,
+        initial_meta: Option<JsonValue>,
+        nb_words_per_cue_group: Option<usize>,
+    ) -> Result<Self, SubtitleReaderError> {
+        let nb_words_per_cue_group =
+            nb_words_per_cue_group.unwrap_or(DEFAULT_NB_WORDS_PER_CUE_GROUP);
+
+        let mut buf = Vec::<u8>::new();
+        reader.read_to_end(&mut buf)?;
+        let source = String::from_utf8(buf)?;
+
+        let format = detect_format(&source);
+        let cues = parse_cues(&source);
+        let cue_groups = group_cues(cues, nb_words_per_cue_group);
+
+        let initial_meta = initial_meta.unwrap_or(JsonValue::Null);
+        let metadata = match initial_meta {
+            JsonValue::Object(map) => json!(map),
+            JsonValue::Null => JsonValue::Null,
+            _ => json!({ SUBTITLE_READER_META_KEY_DEFAULT_INITIAL: initial_meta }),
+        };
+
+        info!(
+            "Subtitle reader source: format: {}, nb cue groups: {}, initial metadata: {}",
+            format.as_str(),
+            cue_groups.len(),
+            metadata
+        );
+
+        let mut subtitle_reader = Self {
+            cue_groups,
+            current_group_index: 0,
+            current_content: BufferedContent::default(),
+            metadata,
+        };
+        subtitle_reader.update_metadata(SUBTITLE_READER_META_KEY_FORMAT, json!(format.as_str()));
+
+        Ok(subtitle_reader)
+    }
+
+    /// Gets content cue group by cue group
+    ///
+    /// # Returns
+    /// The number of chars cached. 0 if no more content is available.
+    fn go_next_content(&mut self) -> usize {
+        self.current_content = BufferedContent::default();
+
+        let Some(cue_group) = self.cue_groups.get(self.current_group_index) else {
+            return 0;
+        };
+        self.current_group_index += 1;
+
+        self.current_content = BufferedContent::new(cue_group.text.chars());
+
+        let (first_cue, last_cue, start_ms, end_ms) = (
+            cue_group.first_cue,
+            cue_group.last_cue,
+            cue_group.start_ms,
+            cue_group.end_ms,
+        );
+        self.update_metadata(
+            SUBTITLE_READER_META_KEY_START_TIME,
+            json!(format_timestamp(start_ms)),
+        );
+        self.update_metadata(
+            SUBTITLE_READER_META_KEY_END_TIME,
+            json!(format_timestamp(end_ms)),
+        );
+        self.update_metadata(SUBTITLE_READER_META_KEY_START_MS, json!(start_ms));
+        self.update_metadata(SUBTITLE_READER_META_KEY_END_MS, json!(end_ms));
+        self.update_metadata(SUBTITLE_READER_META_KEY_FIRST_CUE, json!(first_cue));
+        self.update_metadata(SUBTITLE_READER_META_KEY_LAST_CUE, json!(last_cue));
+
+        self.current_content.len()
+    }
+
+    /// Updates metadata as a JSON object
+    fn update_metadata(&mut self, key: &str, value: JsonValue) {
+        if let Some(map) = self.metadata.as_object_mut() {
+            map.insert(key.to_owned(), value);
+        } else {
+            let mut map = Map::new();
+            map.insert(key.to_owned(), value);
+            self.metadata = JsonValue::Object(map);
+        }
+    }
+}
+
+impl Read for SubtitleReader {
+    // Reads bytes as unicode scalar values
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        // There is no more chars to read from the current cue group, tries to get the next one
+        if self.current_content.is_read() && self.go_next_content() == 0 {
+            // No more to read
+            return Ok(0);
+        }
+
+        Ok(self.current_content.read(buf))
+    }
+}
+
+impl MetaRead for SubtitleReader {
+    fn get_current_metadata(&self) -> JsonValue {
+        json!({ SUBTITLE_READER_META_KEY: self.metadata.clone() })
+    }
+}
+
+/// A WebVTT file must start with `WEBVTT` (after an optional BOM)
+fn detect_format(source: &str) -> SubtitleFormat {
+    if source.trim_start_matches('\u{feff}').starts_with("WEBVTT") {
+        SubtitleFormat::Vtt
+    } else {
+        SubtitleFormat::Srt
+    }
+}
+
+/// Parses the cues of a SRT or WebVTT source
+///
+/// Both formats are made of blocks separated by blank lines. A cue block contains a timing line `<start> --> <end>`
+/// followed by the cue text. Blocks without timing line (WebVTT header, `NOTE`, `STYLE`, `REGION`) are skipped.
+/// Cues with an invalid timing line are skipped.
+fn parse_cues(source: &str) -> Vec<Cue> {
+    let source = source.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut cues = vec![];
+
+    for block in source.split("\n\n") {
+        let lines: Vec<&str> = block.lines().map(str::trim).collect();
+
+        let Some(timing_line_index) = lines.iter().position(|line| line.contains("-->")) else {
+            continue;
+        };
+
+        if lines[0].starts_with("NOTE") || lines[0].starts_with("STYLE") {
+            continue;
+        }
+
+        let (start_ms, end_ms) = match parse_timing_line(lines[timing_line_index]) {
+            Some(timing) => timing,
+            None => {
+                warn!(
+                    "Skipping cue with invalid timing line: {}",
+                    lines[timing_line_index]
+                );
+                continue;
+            }
+        };
+
+        let text = lines[timing_line_index + 1..]
+            .iter()
+            .map(|line| strip_cue_markup(line))
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        if text.is_empty() {
+            continue;
+        }
+
+        cues.push(Cue {
+            start_ms,
+            end_ms,
+            text,
+        });
+    }
+
+    cues
+}
+
+/// Parses a timing line: `00:00:01,000 --> 00:00:04,000` (SRT) or `00:01.000 --> 00:04.000 align:start` (WebVTT)
+fn parse_timing_line(line: &str) -> Option<(u64, u64)> {
+    let (start, end) = line.split_once("-->")?;
+    // WebVTT cue settings can follow the end timestamp
+    let end = end.split_whitespace().next()?;
+
+    Some((parse_timestamp(start.trim())?, parse_timestamp(end)?))
+}
+
+/// Parses a timestamp `[HH:]MM:SS(,|.)mmm` into milliseconds
+fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    let (hms, ms) = timestamp.split_once([',', '.'])?;
+    let ms: u64 = ms.parse().ok()?;
+
+    let parts = hms
+        .split(':')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<u64>>>()?;
+
+    let (hours, minutes, seconds) = match parts.as_slice() {
+        [hours, minutes, seconds] => (*hours, *minutes, *seconds),
+        [minutes, seconds] => (0, *minutes, *seconds),
+        _ => return None,
+    };
+
+    Some(((hours * 60 + minutes) * 60 + seconds) * 1000 + ms)
+}
+
+/// Formats milliseconds into a `HH:MM:SS.mmm` timestamp
+fn format_timestamp(timestamp_ms: u64) -> String {
+    let ms = timestamp_ms % 1000;
+    let seconds = (timestamp_ms / 1000) % 60;
+    let minutes = (timestamp_ms / 60_000) % 60;
+    let hours = timestamp_ms / 3_600_000;
+
+    format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, seconds, ms)
+}
+
+/// Removes the markup of a cue text line: tags like `<i>`, `<v Speaker>`, `<00:00:01.000>`
+/// and SRT positioning like `{\an8}`
+fn strip_cue_markup(line: &str) -> String {
+    static MARKUP: Lazy<regex::Regex> =
+        Lazy::new(|| regex::Regex::new(r"<[^>]*>|\{\\[^}]*\}").unwrap());
+
+    MARKUP.replace_all(line, "").trim().to_string()
+}
+
+/// Merges successive cues into groups
+///
+/// A group is closed once it reached `nb_words_per_cue_group` words,
+/// or half of it if the last cue ends a sentence.
+fn group_cues(cues: Vec<Cue>, nb_words_per_cue_group: usize) -> Vec<CueGroup> {
+    let mut cue_groups = vec![];
+    let mut current_group: Option<CueGroup> = None;
+    let mut current_nb_words = 0;
+
+    for (i, cue) in cues.into_iter().enumerate() {
+        let cue_number = i + 1;
+        current_nb_words += cue.text.split_whitespace().count();
+        let ends_sentence = cue.text.ends_with(SENTENCE_ENDING_CHARS);
+
+        let group = match current_group.as_mut() {
+            Some(group) => {
+                group.text.push(' ');
+                group.text.push_str(&cue.text);
+                group.last_cue = cue_number;
+                group.end_ms = cue.end_ms;
+                group
+            }
+            None => current_group.insert(CueGroup {
+                first_cue: cue_number,
+                last_cue: cue_number,
+                start_ms: cue.start_ms,
+                end_ms: cue.end_ms,
+                text: cue.text,
+            }),
+        };
+
+        if current_nb_words >= nb_words_per_cue_group
+            || (ends_sentence && current_nb_words * 2 >= nb_words_per_cue_group)
+        {
+            group.text.push(' ');
+            cue_groups.extend(current_group.take());
+            current_nb_words = 0;
+        }
+    }
+
+    if let Some(mut group) = current_group {
+        group.text.push(' ');
+        cue_groups.push(group);
+    }
+
+    cue_groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRT_SAMPLE: &str = "1\r\n00:00:01,000 --> 00:00:02,500\r\nHello <i>everyone</i>,\r\n\r\n2\r\n00:00:02,600 --> 00:00:04,000\r\nwelcome to this lecture.\r\n\r\n3\r\n00:00:05,000 --> 00:00:07,000\r\n{\\an8}Today we talk about rivers.\r\n";
+
+    const VTT_SAMPLE: &str = "WEBVTT - Lecture\n\nNOTE This is a comment\n\nintro\n00:01.000 --> 00:02.500 align:start\n<v Professor>Hello everyone,</v>\n\n00:02.600 --> 00:04.000\nwelcome to this lecture.\n\n01:00:05.000 --> 01:00:07.000\nToday we talk about rivers.\n";
+
+    fn read_all(reader: &mut SubtitleReader) -> Vec<(String, JsonValue)> {
+        let mut reads = vec![];
+
+        loop {
+            // Buffer big enough to contain each cue group
+            let mut buf = [0; 1000];
+            let read_len = reader.read(&mut buf).unwrap();
+            if read_len == 0 {
+                break;
+            }
+
+            let read_content = String::from_utf8(buf[0..read_len].to_vec()).unwrap();
+            reads.push((read_content, reader.get_current_metadata()));
+        }
+
+        reads
+    }
+
+    #[test]
+    fn on_srt_source_it_should_merge_cues_and_keep_timestamps_in_metadata() {
+        let mut subtitle_reader = SubtitleReader::try_from_reader(
+            SRT_SAMPLE.as_bytes(),
+            Some(json!({ "file": "lecture.srt" })),
+            Some(6),
+        )
+        .unwrap();
+
+        let reads = read_all(&mut subtitle_reader);
+
+        assert_eq!(reads.len(), 2);
+        assert_eq!(reads[0].0, "Hello everyone, welcome to this lecture. ");
+        assert_eq!(reads[1].0, "Today we talk about rivers. ");
+
+        let metadata = &reads[0].1[SUBTITLE_READER_META_KEY];
+        assert_eq!(metadata["file"], "lecture.srt");
+        assert_eq!(metadata[SUBTITLE_READER_META_KEY_FORMAT], "srt");
+        assert_eq!(
+            metadata[SUBTITLE_READER_META_KEY_START_TIME],
+            "00:00:01.000"
+        );
+        assert_eq!(metadata[SUBTITLE_READER_META_KEY_END_TIME], "00:00:04.000");
+        assert_eq!(metadata[SUBTITLE_READER_META_KEY_START_MS], 1000);
+        assert_eq!(metadata[SUBTITLE_READER_META_KEY_END_MS], 4000);
+        assert_eq!(metadata[SUBTITLE_READER_META_KEY_FIRST_CUE], 1);
+        assert_eq!(metadata[SUBTITLE_READER_META_KEY_LAST_CUE], 2);
+
+        let metadata = &reads[1].1[SUBTITLE_READER_META_KEY];
+        assert_eq!(
+            metadata[SUBTITLE_READER_META_KEY_START_TIME],
+            "00:00:05.000"
+        );
+        assert_eq!(metadata[SUBTITLE_READER_META_KEY_FIRST_CUE], 3);
+    }
+
+    #[test]
+    fn on_vtt_source_it_should_skip_header_and_notes_and_strip_voice_tags() {
+        let mut subtitle_reader =
+            SubtitleReader::try_from_reader(VTT_SAMPLE.as_bytes(), None, Some(6)).unwrap();
+
+        let reads = read_all(&mut subtitle_reader);
+
+        assert_eq!(reads.len(), 2);
+        assert_eq!(reads[0].0, "Hello everyone, welcome to this lecture. ");
+        assert_eq!(reads[1].0, "Today we talk about rivers. ");
+
+        let metadata = &reads[1].1[SUBTITLE_READER_META_KEY];
+        assert_eq!(metadata[SUBTITLE_READER_META_KEY_FORMAT], "vtt");
+        assert_eq!(
+            metadata[SUBTITLE_READER_META_KEY_START_TIME],
+            "01:00:05.000"
+        );
+        assert_eq!(metadata[SUBTITLE_READER_META_KEY_END_MS], 3_607_000);
+    }
+
+    #[test]
+    fn on_large_group_limit_it_should_read_all_cues_in_one_chunk() {
+        let mut subtitle_reader =
+            SubtitleReader::try_from_reader(SRT_SAMPLE.as_bytes(), None, Some(1000)).unwrap();
+
+        let reads = read_all(&mut subtitle_reader);
+
+        assert_eq!(reads.len(), 1);
+        assert_eq!(
+            reads[0].0,
+            "Hello everyone, welcome to this lecture. Today we talk about rivers. "
+        );
+        assert_eq!(
+            reads[0].1[SUBTITLE_READER_META_KEY][SUBTITLE_READER_META_KEY_END_TIME],
+            "00:00:07.000"
+        );
+    }
+
+    #[test]
+    fn on_invalid_timing_line_it_should_skip_the_cue() {
+        let source = "1\n00:00:01,000 --> not a timestamp\nSkipped\n\n2\n00:00:02,000 --> 00:00:03,000\nKept\n";
+        let mut subtitle_reader =
+            SubtitleReader::try_from_reader(source.as_bytes(), None, None).unwrap();
+
+        let reads = read_all(&mut subtitle_reader);
+
+        assert_eq!(reads.len(), 1);
+        assert_eq!(reads[0].0, "Kept ");
+    }
+
+    #[test]
+    fn on_empty_source_it_should_read_an_empty_content() {
+        let mut subtitle_reader =
+            SubtitleReader::try_from_reader("".as_bytes(), None, None).unwrap();
+
+        assert!(read_all(&mut subtitle_reader).is_empty());
+    }
+
+    #[test]
+    fn timestamps_are_parsed_and_formatted_in_both_formats() {
+        assert_eq!(parse_timestamp("00:00:01,250"), Some(1250));
+        assert_eq!(parse_timestamp("01:02:03.004"), Some(3_723_004));
+        assert_eq!(parse_timestamp("02:03.004"), Some(123_004));
+        assert_eq!(parse_timestamp("02:03"), None);
+        assert_eq!(format_timestamp(3_723_004), "01:02:03.004");
+    }
+}
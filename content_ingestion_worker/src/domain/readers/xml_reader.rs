@@ -1,10 +1,16 @@
 use common::helper::error_chain_fmt;
-use quick_xml::events::Event;
+use quick_xml::events::{BytesStart, Event};
 use serde_json::{json, Map, Value as JsonValue};
-use std::io::{BufReader, ErrorKind, Read};
-use tracing::debug;
+use std::{
+    collections::VecDeque,
+    io::{BufReader, ErrorKind, Read},
+    sync::Arc,
+};
+use tracing::{debug, warn};
 
-use crate::domain::entities::meta_read::MetaRead;
+use crate::domain::entities::{
+    image_ocr::ImageOcr, meta_read::MetaRead, resource_read::ResourceRead,
+};
 
 #[derive(thiserror::Error)]
 pub enum XMLReaderError {
@@ -20,22 +26,113 @@ impl std::fmt::Debug for XMLReaderError {
 
 const XML_READER_META_KEY: &str = "xml";
 const XML_READER_META_KEY_TITLE: &str = "title";
+const XML_READER_META_KEY_IMAGE_ALTS: &str = "image_alts";
+const XML_READER_META_KEY_FIGCAPTION: &str = "figcaption";
+const XML_READER_META_KEY_IMAGE_SRC: &str = "image_src";
+const XML_READER_META_KEY_IMAGE_OCR: &str = "image_ocr";
+
+/// Content read from an image, read at the position of the image in the document
+struct ImageContent {
+    text: String,
+    /// Metadata describing the image, only attached to this content
+    metadata: Vec<(&'static str, JsonValue)>,
+}
 
-/// XML reader
+/// Tags driving how an XML source is read
 ///
-/// Currently supports EPUB/HTML like XML syntax.
+/// By default, matches EPUB/XHTML: the content is read inside `<body>`, and `<title>` is captured as the `title` metadata.
+/// Tags are matched on their qualified name, prefix included (`dc:title`).
+#[derive(Debug, Clone)]
+pub struct XMLReaderConfig {
+    /// Tags delimiting the content to read
+    content_tags: Vec<String>,
+    /// Tags whose content is entirely skipped, nested tags included
+    skipped_tags: Vec<String>,
+    /// Tags whose text is captured as metadata, with their metadata key
+    metadata_tags: Vec<(String, String)>,
+}
+
+impl Default for XMLReaderConfig {
+    fn default() -> Self {
+        Self {
+            content_tags: vec!["body".to_string()],
+            skipped_tags: vec![],
+            metadata_tags: vec![("title".to_string(), XML_READER_META_KEY_TITLE.to_string())],
+        }
+    }
+}
+
+impl XMLReaderConfig {
+    /// Sets the tags delimiting the content to read, replacing `<body>`
+    pub fn with_content_tags<T: Into<String>>(mut self, tags: impl IntoIterator<Item = T>) -> Self {
+        self.content_tags = tags.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the tags whose content is skipped, for ex `<table>` or `<code>`
+    pub fn with_skipped_tags<T: Into<String>>(mut self, tags: impl IntoIterator<Item = T>) -> Self {
+        self.skipped_tags = tags.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the tags whose text is captured as metadata, as pairs of (tag, metadata key), replacing `<title>`
+    ///
+    /// A tag can also delimit content: its text is then both read and captured.
+    pub fn with_metadata_tags<T: Into<String>, K: Into<String>>(
+        mut self,
+        tags: impl IntoIterator<Item = (T, K)>,
+    ) -> Self {
+        self.metadata_tags = tags
+            .into_iter()
+            .map(|(tag, key)| (tag.into(), key.into()))
+            .collect();
+        self
+    }
+
+    fn is_content_tag(&self, name: &[u8]) -> bool {
+        self.content_tags.iter().any(|tag| tag.as_bytes() == name)
+    }
+
+    fn is_skipped_tag(&self, name: &[u8]) -> bool {
+        self.skipped_tags.iter().any(|tag| tag.as_bytes() == name)
+    }
+
+    fn metadata_key(&self, name: &[u8]) -> Option<&str> {
+        self.metadata_tags
+            .iter()
+            .find(|(tag, _)| tag.as_bytes() == name)
+            .map(|(_, key)| key.as_str())
+    }
+}
+
+/// XML reader
 ///
-/// Would need to be more generic if the source does not use tags like <body> and <title>
-pub struct XMLReader<SourceReader: Read + MetaRead> {
+/// Supports EPUB/HTML like XML syntax by default, and other XML sources through an `XMLReaderConfig`.
+pub struct XMLReader<SourceReader: Read + MetaRead + ResourceRead> {
     /// XML inner reader, wrapping any `BufReader`
     /// `BufRead` implementation is needed for `read_event_into`
     /// `BufReader` is needed to access to the inner reader (via `get_ref` for ex)
     reader: quick_xml::reader::Reader<BufReader<SourceReader>>,
+    config: XMLReaderConfig,
 
     current_content_chars: Vec<char>,
     current_char_index: usize,
-    current_inside_body: usize,
-    current_inside_title: usize,
+    current_inside_content: usize,
+    current_inside_skipped: usize,
+    /// Metadata keys of the metadata tags currently opened, the innermost last
+    current_metadata_keys: Vec<String>,
+    current_inside_figcaption: usize,
+
+    /// Alternative texts of the images met since the last content, attached to the next content
+    pending_image_alts: Vec<String>,
+    /// If true, the alternative text of an image is read as a content on its own
+    image_alts_in_text: bool,
+    /// Contents read from the last met image, waiting to be read
+    queued_image_contents: VecDeque<ImageContent>,
+    /// If set, the text of the images bundled with the source is recognized and read as contents
+    image_ocr: Option<Arc<dyn ImageOcr>>,
+    /// Smaller images are not worth recognizing: icons, separators etc.
+    ocr_min_image_size_bytes: usize,
 
     // MetaRead
     metadata: JsonValue,
@@ -43,7 +140,7 @@ pub struct XMLReader<SourceReader: Read + MetaRead> {
 
 /// Builds a new XMLReader from a reader not implementing BufRead
 #[tracing::instrument(name = "Creating XML reader", skip(reader))]
-pub fn build_from_reader<SourceReader: Read + MetaRead>(
+pub fn build_from_reader<SourceReader: Read + MetaRead + ResourceRead>(
     reader: SourceReader,
 ) -> XMLReader<SourceReader> {
     // `BufRead` implementation is needed for `read_event_into`
@@ -52,11 +149,19 @@ pub fn build_from_reader<SourceReader: Read + MetaRead>(
 
     XMLReader {
         reader,
+        config: XMLReaderConfig::default(),
         metadata: JsonValue::Null,
         current_content_chars: vec![],
         current_char_index: 0,
-        current_inside_body: 0,
-        current_inside_title: 0,
+        current_inside_content: 0,
+        current_inside_skipped: 0,
+        current_metadata_keys: vec![],
+        current_inside_figcaption: 0,
+        pending_image_alts: vec![],
+        image_alts_in_text: false,
+        queued_image_contents: VecDeque::new(),
+        image_ocr: None,
+        ocr_min_image_size_bytes: 0,
     }
 }
 
@@ -73,7 +178,40 @@ pub fn build_from_reader<SourceReader: Read + MetaRead>(
 //     }
 // }
 
-impl<SourceReader: Read + MetaRead> XMLReader<SourceReader> {
+impl<SourceReader: Read + MetaRead + ResourceRead> XMLReader<SourceReader> {
+    /// Sets the tags delimiting the content, skipped, and captured as metadata
+    pub fn with_config(mut self, config: XMLReaderConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Sets if the alternative texts of images (`alt` attribute of `<img>`) are read as contents
+    ///
+    /// In any case, they are set in the metadata of the content following the image.
+    pub fn with_image_alts_in_text(mut self, image_alts_in_text: bool) -> Self {
+        self.image_alts_in_text = image_alts_in_text;
+        self
+    }
+
+    /// Recognizes the text of the images bundled with the source, reading it as contents
+    ///
+    /// Each recognized text is read at the position of its image, tagged with `image_ocr` in the metadata.
+    /// Images (files) smaller than `min_image_size_bytes` are ignored.
+    pub fn with_image_ocr(
+        mut self,
+        image_ocr: Arc<dyn ImageOcr>,
+        min_image_size_bytes: usize,
+    ) -> Self {
+        self.image_ocr = Some(image_ocr);
+        self.ocr_min_image_size_bytes = min_image_size_bytes;
+        self
+    }
+
+    /// The wrapped source reader, for ex to get what it skipped once the source is read
+    pub fn source_reader(&self) -> &SourceReader {
+        self.reader.get_ref().get_ref()
+    }
+
     /// Caches the content appearing inside the next XML tags
     /// # Returns
     /// The number of char read. 0 if no more content is available.
@@ -83,6 +221,15 @@ impl<SourceReader: Read + MetaRead> XMLReader<SourceReader> {
         // Re-initializes current content variables
         self.current_content_chars = vec![];
         self.current_char_index = 0;
+        // Image descriptions only describe the content they are attached to
+        self.remove_metadata(XML_READER_META_KEY_IMAGE_ALTS);
+        self.remove_metadata(XML_READER_META_KEY_FIGCAPTION);
+        self.remove_metadata(XML_READER_META_KEY_IMAGE_SRC);
+        self.remove_metadata(XML_READER_META_KEY_IMAGE_OCR);
+
+        if let Some(nb_chars) = self.cache_next_image_content() {
+            return Ok(nb_chars);
+        }
 
         // The `Reader` does not implement `Iterator` because it outputs borrowed data (`Cow`s)
         loop {
@@ -96,35 +243,72 @@ impl<SourceReader: Read + MetaRead> XMLReader<SourceReader> {
                 }
                 // Exits the loop when reaching end of file
                 Ok(Event::Eof) => break,
-                Ok(Event::Start(e)) => match e.name().as_ref() {
-                    b"body" => {
-                        debug!("Found <body>");
-                        self.current_inside_body += 1;
-                    }
-                    b"title" => {
-                        debug!("Found <title>");
-                        self.current_inside_title += 1;
+                Ok(Event::Start(e)) => {
+                    let name = e.name();
+                    let name = name.as_ref();
+
+                    if self.config.is_skipped_tag(name) {
+                        self.current_inside_skipped += 1;
                     }
-                    _name => {
-                        // Idea: having a list of tags that define separate documents (like a new <h1>)
-                        // self.update_metadata(
-                        //     "tag",
-                        //     json!(std::str::from_utf8(name).unwrap_or_default()),
-                        // );
+                    // Nested tags of a skipped tag are ignored, until it is closed
+                    else if self.current_inside_skipped == 0 {
+                        if self.config.is_content_tag(name) {
+                            debug!("Found content tag {}", String::from_utf8_lossy(name));
+                            self.current_inside_content += 1;
+                        }
+                        if let Some(key) = self.config.metadata_key(name) {
+                            self.current_metadata_keys.push(key.to_string());
+                        }
+
+                        match name {
+                            b"figcaption" => self.current_inside_figcaption += 1,
+                            b"img" => self.on_image(&e),
+                            _ => (),
+                        }
                     }
-                },
-                Ok(Event::End(e)) => match e.name().as_ref() {
-                    b"body" => self.current_inside_body -= 1,
-                    b"title" => self.current_inside_title -= 1,
-                    _ => {
+                }
+                Ok(Event::End(e)) => {
+                    let name = e.name();
+                    let name = name.as_ref();
+
+                    if self.config.is_skipped_tag(name) {
+                        self.current_inside_skipped = self.current_inside_skipped.saturating_sub(1);
+                    } else if self.current_inside_skipped == 0 {
+                        let is_content_tag = self.config.is_content_tag(name);
+                        if is_content_tag {
+                            self.current_inside_content =
+                                self.current_inside_content.saturating_sub(1);
+                        }
+                        if self.config.metadata_key(name).is_some() {
+                            self.current_metadata_keys.pop();
+                        }
+                        if name == b"figcaption" {
+                            self.current_inside_figcaption =
+                                self.current_inside_figcaption.saturating_sub(1);
+                        }
+
                         // On tag closing: always add a space, if there was no space just before.
-                        if self.current_inside_body > 0 {
+                        if !is_content_tag && self.current_inside_content > 0 {
                             self.current_content_chars.push(' ');
                         }
                     }
-                },
+                }
+                // Self-closing tags like `<img src="..." alt="..." />`
+                Ok(Event::Empty(e))
+                    if e.name().as_ref() == b"img" && self.current_inside_skipped == 0 =>
+                {
+                    self.on_image(&e)
+                }
+                Ok(Event::Text(_)) if self.current_inside_skipped > 0 => (),
                 Ok(Event::Text(e)) => {
-                    if self.current_inside_body > 0 {
+                    if let Some(key) = self.current_metadata_keys.last().cloned() {
+                        let value = e.unescape().unwrap_or_default().to_string();
+                        if !value.trim().is_empty() {
+                            self.update_metadata(&key, json!(value));
+                        }
+                    }
+
+                    if self.current_inside_content > 0 {
                         let next_content: Vec<char> = e
                             .iter()
                             .map(|element| char::from(element.to_owned()))
@@ -140,15 +324,20 @@ impl<SourceReader: Read + MetaRead> XMLReader<SourceReader> {
                             continue;
                         }
 
-                        // Stops once a content inside <body> is read
+                        if !self.pending_image_alts.is_empty() {
+                            let image_alts = std::mem::take(&mut self.pending_image_alts);
+                            self.update_metadata(XML_READER_META_KEY_IMAGE_ALTS, json!(image_alts));
+                        }
+
+                        if self.current_inside_figcaption > 0 {
+                            let figcaption = e.unescape().unwrap_or_default().trim().to_string();
+                            self.update_metadata(XML_READER_META_KEY_FIGCAPTION, json!(figcaption));
+                        }
+
+                        // Stops once a content inside a content tag is read
                         self.current_content_chars.extend(next_content);
                         break;
                     }
-                    // Normally we can't be inside a <title> and <body>
-                    else if self.current_inside_title > 0 {
-                        let title = e.unescape().unwrap_or_default().to_string();
-                        self.update_metadata(XML_READER_META_KEY_TITLE, json!(title));
-                    }
                 }
 
                 // There are several other `Event`s we do not consider here
@@ -156,11 +345,113 @@ impl<SourceReader: Read + MetaRead> XMLReader<SourceReader> {
             }
             // If we don't keep a borrow elsewhere, we can clear the buffer to keep memory usage low
             buf.clear();
+
+            // Stops once an image has been read, its contents are read first
+            if !self.queued_image_contents.is_empty() {
+                break;
+            }
+        }
+
+        if let Some(nb_chars) = self.cache_next_image_content() {
+            return Ok(nb_chars);
         }
 
         Ok(self.current_content_chars.len())
     }
 
+    /// Handles an `<img>` tag inside the body
+    ///
+    /// Queues the contents read from the image: its alternative text and its recognized text.
+    fn on_image(&mut self, tag: &BytesStart) {
+        if self.current_inside_content == 0 {
+            return;
+        }
+
+        let alt = attribute_value(tag, "alt")
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ");
+        let src = attribute_value(tag, "src");
+
+        let image_src_metadata = if src.is_empty() {
+            vec![]
+        } else {
+            vec![(XML_READER_META_KEY_IMAGE_SRC, json!(src))]
+        };
+
+        // An empty `alt` marks a decorative image
+        if !alt.is_empty() {
+            if self.image_alts_in_text {
+                debug!("Reading image alternative text: {}", alt);
+                let mut metadata = vec![(XML_READER_META_KEY_IMAGE_ALTS, json!([alt]))];
+                metadata.extend(image_src_metadata.clone());
+                self.queued_image_contents.push_back(ImageContent {
+                    text: alt,
+                    metadata,
+                });
+            } else {
+                self.pending_image_alts.push(alt);
+            }
+        }
+
+        if let Some(text) = self.recognize_image(&src) {
+            let mut metadata = vec![(XML_READER_META_KEY_IMAGE_OCR, json!(true))];
+            metadata.extend(image_src_metadata);
+            self.queued_image_contents
+                .push_back(ImageContent { text, metadata });
+        }
+    }
+
+    /// Recognizes the text of an image bundled with the source
+    ///
+    /// # Returns
+    /// None if OCR is disabled, the image is not found or too small, or no text was recognized
+    fn recognize_image(&mut self, src: &str) -> Option<String> {
+        let image_ocr = self.image_ocr.clone()?;
+        if src.is_empty() {
+            return None;
+        }
+
+        let resource = self.reader.get_mut().get_mut().read_resource(src)?;
+        if resource.content.len() < self.ocr_min_image_size_bytes {
+            debug!("Image {} too small to be recognized", resource.path);
+            return None;
+        }
+
+        match image_ocr.recognize(&resource.content) {
+            Ok(text) => {
+                let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+                (!text.is_empty()).then_some(text)
+            }
+            Err(error) => {
+                // The image text is a nice to have: it should not stop the extraction
+                warn!(
+                    ?error,
+                    "Could not recognize the text of image {}", resource.path
+                );
+                None
+            }
+        }
+    }
+
+    /// Caches the next queued image content as the current content
+    ///
+    /// # Returns
+    /// The number of char read. None if no image content is queued.
+    fn cache_next_image_content(&mut self) -> Option<usize> {
+        let image_content = self.queued_image_contents.pop_front()?;
+
+        for (key, value) in image_content.metadata {
+            self.update_metadata(key, value);
+        }
+
+        self.current_content_chars = image_content.text.chars().collect();
+        self.current_content_chars.push(' ');
+        self.current_char_index = 0;
+
+        Some(self.current_content_chars.len())
+    }
+
     /// Updates metadata as a JSON object
     fn update_metadata(&mut self, key: &str, value: JsonValue) {
         if let Some(map) = self.metadata.as_object_mut() {
@@ -171,9 +462,27 @@ impl<SourceReader: Read + MetaRead> XMLReader<SourceReader> {
             self.metadata = JsonValue::Object(map);
         }
     }
+
+    /// Removes a key from the metadata
+    fn remove_metadata(&mut self, key: &str) {
+        if let Some(map) = self.metadata.as_object_mut() {
+            map.remove(key);
+        }
+    }
 }
 
-impl<SourceReader: Read + MetaRead> Read for XMLReader<SourceReader> {
+/// Unescaped value of a tag attribute. Empty if the attribute is missing.
+fn attribute_value(tag: &BytesStart, name: &str) -> String {
+    match tag.try_get_attribute(name) {
+        Ok(Some(attribute)) => attribute
+            .unescape_value()
+            .map(|value| value.to_string())
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+impl<SourceReader: Read + MetaRead + ResourceRead> Read for XMLReader<SourceReader> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         // There is no more chars to read from the current content,
         // tries to get next content available from EPUB
@@ -223,7 +532,7 @@ impl<SourceReader: Read + MetaRead> Read for XMLReader<SourceReader> {
 /// Gets the metadata of the currently read chunk
 ///
 /// Adds the current XMLReader metadata to the current metadata of the wrapped source reader
-impl<SourceReader: Read + MetaRead> MetaRead for XMLReader<SourceReader> {
+impl<SourceReader: Read + MetaRead + ResourceRead> MetaRead for XMLReader<SourceReader> {
     fn get_current_metadata(&self) -> JsonValue {
         let mut source_meta = self.reader.get_ref().get_ref().get_current_metadata();
 
@@ -582,4 +891,210 @@ mod tests {
             };
         }
     }
+
+    // ----- Tests on image descriptions -----
+
+    /// Reads the whole content, returning each read content with its metadata
+    fn read_contents<R: Read + MetaRead + ResourceRead>(
+        xml_reader: &mut XMLReader<R>,
+    ) -> Vec<(String, JsonValue)> {
+        let mut contents = vec![];
+        loop {
+            let mut buf = [0; 1000];
+            let read_len = xml_reader.read(&mut buf).unwrap();
+            if read_len == 0 {
+                break;
+            }
+            let read_content = String::from_utf8(buf[0..read_len].to_vec()).unwrap();
+            contents.push((read_content, xml_reader.get_current_metadata()));
+        }
+        contents
+    }
+
+    #[test]
+    fn on_image_with_alt_it_should_read_the_alt_as_content_when_enabled() {
+        let content = "<html><body><p>Before</p><img src=\"a.png\" alt=\"Diagram of the water cycle\"/><p>After</p></body></html>";
+        let source_reader = SimpleMetadataReader::new(content.as_bytes(), None);
+        let mut xml_reader = build_from_reader(source_reader).with_image_alts_in_text(true);
+
+        let contents = read_contents(&mut xml_reader);
+        let image_content = contents
+            .iter()
+            .find(|(content, _)| content.contains("water cycle"))
+            .unwrap();
+
+        assert_eq!(image_content.0.trim(), "Diagram of the water cycle");
+        assert_eq!(
+            image_content.1[XML_READER_META_KEY][XML_READER_META_KEY_IMAGE_ALTS],
+            json!(["Diagram of the water cycle"])
+        );
+
+        // The image description is not attached to the following content
+        let after_content = contents
+            .iter()
+            .find(|(content, _)| content.contains("After"))
+            .unwrap();
+        assert!(after_content.1[XML_READER_META_KEY]
+            .get(XML_READER_META_KEY_IMAGE_ALTS)
+            .is_none());
+    }
+
+    #[test]
+    fn on_image_with_alt_it_should_only_attach_the_alt_to_the_next_content_when_disabled() {
+        let content = "<html><body><img src=\"a.png\" alt=\"A cat\"/><img src=\"b.png\" alt=\"\"/><p>Text</p><p>Other</p></body></html>";
+        let source_reader = SimpleMetadataReader::new(content.as_bytes(), None);
+        let mut xml_reader = build_from_reader(source_reader);
+
+        let contents = read_contents(&mut xml_reader);
+        let text: String = contents
+            .iter()
+            .map(|(content, _)| content.as_str())
+            .collect();
+        assert!(!text.contains("A cat"));
+
+        let text_content = contents
+            .iter()
+            .find(|(content, _)| content.contains("Text"))
+            .unwrap();
+        assert_eq!(
+            text_content.1[XML_READER_META_KEY][XML_READER_META_KEY_IMAGE_ALTS],
+            json!(["A cat"])
+        );
+
+        let other_content = contents
+            .iter()
+            .find(|(content, _)| content.contains("Other"))
+            .unwrap();
+        assert!(other_content.1[XML_READER_META_KEY]
+            .get(XML_READER_META_KEY_IMAGE_ALTS)
+            .is_none());
+    }
+
+    #[test]
+    fn on_figure_with_caption_it_should_set_the_caption_in_metadata() {
+        let content = "<html><body><figure><img src=\"a.png\"/><figcaption>The water cycle</figcaption></figure><p>Next</p></body></html>";
+        let source_reader = SimpleMetadataReader::new(content.as_bytes(), None);
+        let mut xml_reader = build_from_reader(source_reader);
+
+        let contents = read_contents(&mut xml_reader);
+        let caption_content = contents
+            .iter()
+            .find(|(content, _)| content.contains("The water cycle"))
+            .unwrap();
+        assert_eq!(
+            caption_content.1[XML_READER_META_KEY][XML_READER_META_KEY_FIGCAPTION],
+            json!("The water cycle")
+        );
+
+        let next_content = contents
+            .iter()
+            .find(|(content, _)| content.contains("Next"))
+            .unwrap();
+        assert!(next_content.1[XML_READER_META_KEY]
+            .get(XML_READER_META_KEY_FIGCAPTION)
+            .is_none());
+    }
+
+    // ----- Tests on configured tags -----
+
+    #[test]
+    fn on_configured_tags_it_should_read_the_content_tags_and_capture_the_metadata_tags() {
+        let content = "<feed><meta><name>Recipes</name></meta><entry><headline>Tomato sauce</headline><text>Simmer the tomatoes</text></entry></feed>";
+        let source_reader = SimpleMetadataReader::new(content.as_bytes(), None);
+        let mut xml_reader = build_from_reader(source_reader).with_config(
+            XMLReaderConfig::default()
+                .with_content_tags(["text"])
+                .with_metadata_tags([("name", "feed_name"), ("headline", "headline")]),
+        );
+
+        let contents = read_contents(&mut xml_reader);
+        let text: String = contents
+            .iter()
+            .map(|(content, _)| content.as_str())
+            .collect();
+        assert_eq!(text.trim(), "Simmer the tomatoes");
+
+        let text_content = contents
+            .iter()
+            .find(|(content, _)| content.contains("Simmer"))
+            .unwrap();
+        assert_eq!(
+            text_content.1[XML_READER_META_KEY]["feed_name"],
+            json!("Recipes")
+        );
+        assert_eq!(
+            text_content.1[XML_READER_META_KEY]["headline"],
+            json!("Tomato sauce")
+        );
+    }
+
+    #[test]
+    fn on_skipped_tags_it_should_not_read_their_nested_content() {
+        let content = "<html><body><p>Before</p><table><tr><td>Cell</td></tr></table><code>let a = 1;</code><img src=\"a.png\" alt=\"Diagram\"/><p>After</p></body></html>";
+        let source_reader = SimpleMetadataReader::new(content.as_bytes(), None);
+        let mut xml_reader = build_from_reader(source_reader)
+            .with_image_alts_in_text(true)
+            .with_config(XMLReaderConfig::default().with_skipped_tags(["table", "code"]));
+
+        let contents = read_contents(&mut xml_reader);
+        let texts: Vec<&str> = contents
+            .iter()
+            .map(|(content, _)| content.trim())
+            .filter(|content| !content.is_empty())
+            .collect();
+        assert_eq!(texts, vec!["Before", "Diagram", "After"]);
+    }
+
+    // ----- Tests on image OCR -----
+
+    /// Fake OCR "recognizing" the content of the image as text
+    struct FakeImageOcr;
+
+    impl ImageOcr for FakeImageOcr {
+        fn recognize(
+            &self,
+            image: &[u8],
+        ) -> Result<String, crate::domain::entities::image_ocr::ImageOcrError> {
+            Ok(String::from_utf8_lossy(image).to_string())
+        }
+    }
+
+    #[test]
+    fn on_image_large_enough_it_should_read_its_recognized_text_at_its_position() {
+        let content = "<html><body><p>Before</p><img src=\"large.png\"/><img src=\"small.png\"/><p>After</p></body></html>";
+        let source_reader = SimpleMetadataReader::new(content.as_bytes(), None)
+            .with_resource("large.png", b"Text in a large image".to_vec())
+            .with_resource("small.png", b"Icon".to_vec());
+        let mut xml_reader =
+            build_from_reader(source_reader).with_image_ocr(Arc::new(FakeImageOcr), 10);
+
+        let contents = read_contents(&mut xml_reader);
+        let texts: Vec<&str> = contents
+            .iter()
+            .map(|(content, _)| content.trim())
+            .filter(|content| !content.is_empty())
+            .collect();
+        assert_eq!(texts, vec!["Before", "Text in a large image", "After"]);
+
+        let image_content = contents
+            .iter()
+            .find(|(content, _)| content.contains("large image"))
+            .unwrap();
+        assert_eq!(
+            image_content.1[XML_READER_META_KEY][XML_READER_META_KEY_IMAGE_OCR],
+            json!(true)
+        );
+        assert_eq!(
+            image_content.1[XML_READER_META_KEY][XML_READER_META_KEY_IMAGE_SRC],
+            json!("large.png")
+        );
+
+        let after_content = contents
+            .iter()
+            .find(|(content, _)| content.contains("After"))
+            .unwrap();
+        assert!(after_content.1[XML_READER_META_KEY]
+            .get(XML_READER_META_KEY_IMAGE_OCR)
+            .is_none());
+    }
 }
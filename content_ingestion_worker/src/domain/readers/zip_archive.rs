@@ -0,0 +1,71 @@
+use std::{
+    io::{Read, Seek},
+    path::{Component, Path},
+};
+use tracing::warn;
+
+/// Magic number of a zip archive (local file header)
+const ZIP_MAGIC_NUMBER: [u8; 4] = [0x50, 0x4b, 0x03, 0x04];
+
+/// A text file read from a zip archive
+#[derive(Debug)]
+pub struct ArchiveTextFile {
+    /// Normalized path in the archive: `/` separated, without `.` components
+    pub path: String,
+    pub content: String,
+}
+
+/// Detects a zip archive from its magic number
+pub fn is_zip_archive(buf: &[u8]) -> bool {
+    buf.starts_with(&ZIP_MAGIC_NUMBER)
+}
+
+/// Reads the files of a zip archive selected by their path, in the archive order
+///
+/// Files that are not valid UTF-8 are skipped.
+pub fn read_text_files(
+    reader: impl Read + Seek,
+    is_selected: impl Fn(&str) -> bool,
+) -> Result<Vec<ArchiveTextFile>, zip::result::ZipError> {
+    let mut archive = zip::ZipArchive::new(reader)?;
+    let mut files = vec![];
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if !file.is_file() {
+            continue;
+        }
+
+        let path = normalize_path(file.name());
+        if !is_selected(&path) {
+            continue;
+        }
+
+        let mut content = String::new();
+        if let Err(error) = file.read_to_string(&mut content) {
+            warn!(?error, "Skipping archive file {} not readable", path);
+            continue;
+        }
+
+        files.push(ArchiveTextFile { path, content });
+    }
+
+    Ok(files)
+}
+
+/// Normalizes a path inside an archive: `/` separated, `.` and `..` components resolved
+pub fn normalize_path(path: impl AsRef<Path>) -> String {
+    let mut components: Vec<String> = vec![];
+
+    for component in path.as_ref().components() {
+        match component {
+            Component::Normal(name) => components.push(name.to_string_lossy().to_string()),
+            Component::ParentDir => {
+                components.pop();
+            }
+            _ => (),
+        }
+    }
+
+    components.join("/")
+}
@@ -0,0 +1,149 @@
+use std::borrow::Cow;
+
+use regex::{Captures, Regex};
+
+use crate::configuration::PiiPatternSettings;
+
+const EMAIL_PATTERN: &str = r"(?i)\b[a-z0-9._%+-]+@[a-z0-9.-]+\.[a-z]{2,}\b";
+
+/// US social security numbers, French social security numbers (NIR) and UK national insurance numbers (NINO)
+const NATIONAL_ID_PATTERNS: [&str; 3] = [
+    r"\b\d{3}-\d{2}-\d{4}\b",
+    r"\b[12] ?\d{2} ?(?:0[1-9]|1[0-2]) ?(?:\d{2}|2[AB]) ?\d{3} ?\d{3}(?: ?\d{2})?\b",
+    r"\b[A-CEGHJ-PR-TW-Z][A-CEGHJ-NPR-TW-Z] ?\d{2} ?\d{2} ?\d{2} ?[A-D]\b",
+];
+
+/// Groups of digits, optionally after an international prefix and an area code between parentheses.
+/// Only the matches with `PHONE_NUMBER_DIGITS` digits are phone numbers: the dates have 8 digits.
+const PHONE_NUMBER_PATTERN: &str =
+    r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{1,4}\)[ .-]?|\b)\d{1,4}(?:[ .-]?\d{2,4}){1,5}\b";
+const PHONE_NUMBER_DIGITS: std::ops::RangeInclusive<usize> = 9..=15;
+
+/// Personal data replaced by a placeholder
+struct PiiPattern {
+    regex: Regex,
+    placeholder: String,
+    /// Set if the matches are only personal data with this number of digits
+    nb_digits: Option<std::ops::RangeInclusive<usize>>,
+}
+
+/// Redacts the personal data of the contents: emails, phone numbers, national ids and the configured patterns
+///
+/// The personal data is replaced by placeholders (`[EMAIL]`, `[PHONE]`, `[NATIONAL_ID]`, or the name of the pattern),
+/// for the contents to still read as sentences. The configured patterns are redacted first, being the most specific.
+pub struct PiiRedactor {
+    patterns: Vec<PiiPattern>,
+}
+
+impl PiiRedactor {
+    /// # Errors
+    /// If a configured pattern is not a valid regular expression
+    pub fn try_new(custom_patterns: &[PiiPatternSettings]) -> Result<Self, regex::Error> {
+        let mut patterns = custom_patterns
+            .iter()
+            .map(|pattern| {
+                Ok(PiiPattern {
+                    regex: Regex::new(&pattern.regex)?,
+                    placeholder: format!("[{}]", pattern.name.to_uppercase()),
+                    nb_digits: None,
+                })
+            })
+            .collect::<Result<Vec<_>, regex::Error>>()?;
+
+        patterns.push(PiiPattern {
+            regex: Regex::new(EMAIL_PATTERN)?,
+            placeholder: "[EMAIL]".to_string(),
+            nb_digits: None,
+        });
+        // Before the phone numbers, having as many digits
+        for national_id_pattern in NATIONAL_ID_PATTERNS {
+            patterns.push(PiiPattern {
+                regex: Regex::new(national_id_pattern)?,
+                placeholder: "[NATIONAL_ID]".to_string(),
+                nb_digits: None,
+            });
+        }
+        patterns.push(PiiPattern {
+            regex: Regex::new(PHONE_NUMBER_PATTERN)?,
+            placeholder: "[PHONE]".to_string(),
+            nb_digits: Some(PHONE_NUMBER_DIGITS),
+        });
+
+        Ok(Self { patterns })
+    }
+
+    /// Replaces the personal data of a text by placeholders
+    ///
+    /// # Returns
+    /// The text borrowed if it has no personal data
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut redacted = Cow::Borrowed(text);
+
+        for pattern in &self.patterns {
+            let replaced = pattern.regex.replace_all(&redacted, |captures: &Captures| {
+                let matched = &captures[0];
+                let is_personal_data = pattern.nb_digits.as_ref().is_none_or(|nb_digits| {
+                    nb_digits.contains(&matched.chars().filter(char::is_ascii_digit).count())
+                });
+                if is_personal_data {
+                    pattern.placeholder.clone()
+                } else {
+                    matched.to_string()
+                }
+            });
+            if let Cow::Owned(replaced) = replaced {
+                if replaced != redacted {
+                    redacted = Cow::Owned(replaced);
+                }
+            }
+        }
+
+        redacted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emails_phone_numbers_and_national_ids_are_redacted() {
+        let redactor = PiiRedactor::try_new(&[]).unwrap();
+
+        assert_eq!(
+            redactor.redact(
+                "Write to jane.doe@example.com or call +33 6 12 34 56 78 and (555) 123-4567. \
+                SSN 123-45-6789, NIR 1 85 05 78 006 084 36, NINO AB 12 34 56 C."
+            ),
+            "Write to [EMAIL] or call [PHONE] and [PHONE]. \
+            SSN [NATIONAL_ID], NIR [NATIONAL_ID], NINO [NATIONAL_ID]."
+        );
+    }
+
+    #[test]
+    fn dates_and_texts_without_personal_data_are_not_redacted() {
+        let redactor = PiiRedactor::try_new(&[]).unwrap();
+
+        let text = "Published on 2023-11-18, in 3 volumes of 250 pages.";
+        assert!(matches!(redactor.redact(text), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn configured_patterns_are_redacted_with_their_name() {
+        let redactor = PiiRedactor::try_new(&[PiiPatternSettings {
+            name: "customer_number".to_string(),
+            regex: r"\bCUST-\d{8}\b".to_string(),
+        }])
+        .unwrap();
+
+        assert_eq!(
+            redactor.redact("Refund CUST-12345678 by email to ops@example.com"),
+            "Refund [CUSTOMER_NUMBER] by email to [EMAIL]"
+        );
+        assert!(PiiRedactor::try_new(&[PiiPatternSettings {
+            name: "invalid".to_string(),
+            regex: "(".to_string(),
+        }])
+        .is_err());
+    }
+}
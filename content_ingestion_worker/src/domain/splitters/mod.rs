@@ -0,0 +1 @@
+pub mod tree_sitter_code_splitter;
@@ -0,0 +1,348 @@
+use tree_sitter::{Language, Node, Parser};
+
+use crate::domain::entities::code_splitter::{
+    CodeChunk, CodeLanguage, CodeSplitter, CodeSplitterError,
+};
+
+/// Symbols spanning more lines are split into their inner symbols (methods of an impl block or a class for ex)
+pub const MAX_SYMBOL_NB_LINES: usize = 80;
+
+/// Splits source code along symbol boundaries using tree-sitter grammars
+///
+/// Top level symbols (functions, structs, classes etc.) are each put in their own chunk.
+/// Comments and attributes/decorators right before a symbol are kept with it.
+/// Successive code outside of any symbol (imports, module level statements etc.) is grouped in one chunk.
+#[derive(Debug, Default)]
+pub struct TreeSitterCodeSplitter;
+
+impl TreeSitterCodeSplitter {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl CodeSplitter for TreeSitterCodeSplitter {
+    fn split(
+        &self,
+        language: CodeLanguage,
+        source: &str,
+    ) -> Result<Vec<CodeChunk>, CodeSplitterError> {
+        // A parser is not shareable between threads: creating one for each source
+        let mut parser = Parser::new();
+        parser
+            .set_language(tree_sitter_language(language))
+            .map_err(|error| CodeSplitterError::ParsingError(language, error.to_string()))?;
+
+        let tree = parser.parse(source, None).ok_or_else(|| {
+            CodeSplitterError::ParsingError(language, "parsing did not complete".to_string())
+        })?;
+
+        let mut chunks = vec![];
+        split_children(
+            tree.root_node(),
+            language,
+            source,
+            &ParentSymbol::None,
+            &mut chunks,
+        );
+
+        Ok(chunks)
+    }
+}
+
+/// Symbol containing the nodes being split
+enum ParentSymbol {
+    None,
+    Symbol { name: String, kind: String },
+}
+
+fn tree_sitter_language(language: CodeLanguage) -> Language {
+    match language {
+        CodeLanguage::Rust => tree_sitter_rust::language(),
+        CodeLanguage::Python => tree_sitter_python::language(),
+    }
+}
+
+/// Kind of symbol a node represents, if any
+fn symbol_kind(language: CodeLanguage, node: &Node) -> Option<&'static str> {
+    match language {
+        CodeLanguage::Rust => match node.kind() {
+            "function_item" | "function_signature_item" => Some("function"),
+            "struct_item" => Some("struct"),
+            "enum_item" => Some("enum"),
+            "union_item" => Some("union"),
+            "trait_item" => Some("trait"),
+            "impl_item" => Some("impl"),
+            "mod_item" => Some("module"),
+            "macro_definition" => Some("macro"),
+            "const_item" => Some("const"),
+            "static_item" => Some("static"),
+            "type_item" => Some("type"),
+            _ => None,
+        },
+        CodeLanguage::Python => match node.kind() {
+            "function_definition" => Some("function"),
+            "class_definition" => Some("class"),
+            "decorated_definition" => node
+                .child_by_field_name("definition")
+                .and_then(|definition| symbol_kind(language, &definition)),
+            _ => None,
+        },
+    }
+}
+
+/// Nodes attached to the symbol following them: comments (and doc comments), attributes, decorators
+fn is_leading_node(language: CodeLanguage, node: &Node) -> bool {
+    match language {
+        CodeLanguage::Rust => matches!(
+            node.kind(),
+            "line_comment" | "block_comment" | "attribute_item"
+        ),
+        CodeLanguage::Python => node.kind() == "comment",
+    }
+}
+
+/// Name of a symbol node. For a Rust impl block, the name of the implemented type.
+fn symbol_name(language: CodeLanguage, node: &Node, source: &str) -> Option<String> {
+    let name_node = match (language, node.kind()) {
+        (CodeLanguage::Rust, "impl_item") => node.child_by_field_name("type"),
+        (CodeLanguage::Python, "decorated_definition") => node
+            .child_by_field_name("definition")
+            .and_then(|definition| definition.child_by_field_name("name")),
+        _ => node.child_by_field_name("name"),
+    }?;
+
+    name_node
+        .utf8_text(source.as_bytes())
+        .ok()
+        .map(str::to_owned)
+}
+
+/// Body containing the inner symbols of a symbol node, if it can have some
+fn symbol_body<'tree>(language: CodeLanguage, node: &Node<'tree>) -> Option<Node<'tree>> {
+    match (language, node.kind()) {
+        (CodeLanguage::Rust, "impl_item" | "trait_item" | "mod_item") => {
+            node.child_by_field_name("body")
+        }
+        (CodeLanguage::Python, "class_definition") => node.child_by_field_name("body"),
+        (CodeLanguage::Python, "decorated_definition") => node
+            .child_by_field_name("definition")
+            .and_then(|definition| symbol_body(language, &definition)),
+        _ => None,
+    }
+}
+
+/// Splits the children of a node into chunks
+///
+/// Recursive on large symbols having inner symbols.
+fn split_children(
+    parent: Node,
+    language: CodeLanguage,
+    source: &str,
+    parent_symbol: &ParentSymbol,
+    chunks: &mut Vec<CodeChunk>,
+) {
+    // Successive nodes that are not symbols, waiting to be grouped in a chunk
+    let mut pending_nodes: Vec<Node> = vec![];
+    let mut cursor = parent.walk();
+
+    for node in parent.named_children(&mut cursor) {
+        let Some(kind) = symbol_kind(language, &node) else {
+            pending_nodes.push(node);
+            continue;
+        };
+
+        // The trailing comments/attributes of the pending nodes belong to this symbol
+        let nb_non_leading_nodes = pending_nodes
+            .iter()
+            .rposition(|pending_node| !is_leading_node(language, pending_node))
+            .map_or(0, |position| position + 1);
+        let leading_nodes = pending_nodes.split_off(nb_non_leading_nodes);
+        push_outside_symbol_chunk(&pending_nodes, source, parent_symbol, chunks);
+        pending_nodes.clear();
+
+        let name = symbol_name(language, &node, source).map(|name| match parent_symbol {
+            ParentSymbol::Symbol {
+                name: parent_name, ..
+            } => format!("{}{}{}", parent_name, language.symbol_separator(), name),
+            ParentSymbol::None => name,
+        });
+
+        let nb_lines = node.end_position().row - node.start_position().row + 1;
+        if nb_lines > MAX_SYMBOL_NB_LINES {
+            if let (Some(body), Some(name)) = (symbol_body(language, &node), name.as_ref()) {
+                push_outside_symbol_chunk(&leading_nodes, source, parent_symbol, chunks);
+
+                split_children(
+                    body,
+                    language,
+                    source,
+                    &ParentSymbol::Symbol {
+                        name: name.to_owned(),
+                        kind: kind.to_string(),
+                    },
+                    chunks,
+                );
+                continue;
+            }
+        }
+
+        let start_node = leading_nodes.first().unwrap_or(&node);
+        chunks.push(CodeChunk {
+            symbol_name: name,
+            symbol_kind: Some(kind.to_string()),
+            start_line: start_node.start_position().row + 1,
+            end_line: node.end_position().row + 1,
+            text: source[start_node.start_byte()..node.end_byte()].to_string(),
+        });
+    }
+
+    push_outside_symbol_chunk(&pending_nodes, source, parent_symbol, chunks);
+}
+
+/// Groups successive nodes that are not symbols into one chunk
+///
+/// Inside a split symbol, the chunk is associated to this symbol.
+fn push_outside_symbol_chunk(
+    nodes: &[Node],
+    source: &str,
+    parent_symbol: &ParentSymbol,
+    chunks: &mut Vec<CodeChunk>,
+) {
+    let (Some(first_node), Some(last_node)) = (nodes.first(), nodes.last()) else {
+        return;
+    };
+
+    let (symbol_name, symbol_kind) = match parent_symbol {
+        ParentSymbol::None => (None, None),
+        ParentSymbol::Symbol { name, kind } => (Some(name.to_owned()), Some(kind.to_owned())),
+    };
+
+    chunks.push(CodeChunk {
+        symbol_name,
+        symbol_kind,
+        start_line: first_node.start_position().row + 1,
+        end_line: last_node.end_position().row + 1,
+        text: source[first_node.start_byte()..last_node.end_byte()].to_string(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbols(chunks: &[CodeChunk]) -> Vec<(Option<&str>, Option<&str>)> {
+        chunks
+            .iter()
+            .map(|chunk| (chunk.symbol_name.as_deref(), chunk.symbol_kind.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn on_rust_source_it_should_split_along_top_level_symbols() {
+        let source = r#"use std::io::Read;
+
+/// Adds 2 numbers
+#[inline]
+pub fn add(a: u32, b: u32) -> u32 {
+    a + b
+}
+
+struct Point {
+    x: u32,
+}
+
+impl Point {
+    fn x(&self) -> u32 {
+        self.x
+    }
+}
+"#;
+
+        let chunks = TreeSitterCodeSplitter::new()
+            .split(CodeLanguage::Rust, source)
+            .unwrap();
+
+        assert_eq!(
+            symbols(&chunks),
+            vec![
+                (None, None),
+                (Some("add"), Some("function")),
+                (Some("Point"), Some("struct")),
+                (Some("Point"), Some("impl")),
+            ]
+        );
+
+        assert_eq!(chunks[0].text, "use std::io::Read;");
+        assert_eq!(
+            chunks[1].text,
+            "/// Adds 2 numbers\n#[inline]\npub fn add(a: u32, b: u32) -> u32 {\n    a + b\n}"
+        );
+        assert_eq!((chunks[1].start_line, chunks[1].end_line), (3, 7));
+        assert_eq!((chunks[3].start_line, chunks[3].end_line), (13, 17));
+    }
+
+    #[test]
+    fn on_python_source_it_should_split_along_top_level_symbols() {
+        let source = r#"import os
+
+CONSTANT = 1
+
+@decorator
+def greet(name):
+    return f"Hello {name}"
+
+class Greeter:
+    def greet(self):
+        pass
+"#;
+
+        let chunks = TreeSitterCodeSplitter::new()
+            .split(CodeLanguage::Python, source)
+            .unwrap();
+
+        assert_eq!(
+            symbols(&chunks),
+            vec![
+                (None, None),
+                (Some("greet"), Some("function")),
+                (Some("Greeter"), Some("class")),
+            ]
+        );
+        assert_eq!(chunks[0].text, "import os\n\nCONSTANT = 1");
+        assert!(chunks[1].text.starts_with("@decorator"));
+    }
+
+    #[test]
+    fn on_large_symbol_with_inner_symbols_it_should_split_along_inner_symbols() {
+        let methods = (0..MAX_SYMBOL_NB_LINES / 2)
+            .map(|i| format!("    def method_{i}(self):\n        return {i}\n"))
+            .collect::<Vec<String>>()
+            .join("\n");
+        let source = format!("class Large:\n    attribute = 1\n\n{methods}");
+
+        let chunks = TreeSitterCodeSplitter::new()
+            .split(CodeLanguage::Python, &source)
+            .unwrap();
+
+        assert_eq!(chunks.len(), 1 + MAX_SYMBOL_NB_LINES / 2);
+        assert_eq!(
+            symbols(&chunks[0..2]),
+            vec![
+                (Some("Large"), Some("class")),
+                (Some("Large.method_0"), Some("function")),
+            ]
+        );
+        assert_eq!(chunks[0].text, "attribute = 1");
+        assert_eq!(chunks[1].text, "def method_0(self):\n        return 0");
+    }
+
+    #[test]
+    fn on_empty_source_it_should_not_return_any_chunk() {
+        let chunks = TreeSitterCodeSplitter::new()
+            .split(CodeLanguage::Rust, "")
+            .unwrap();
+
+        assert!(chunks.is_empty());
+    }
+}
@@ -1,5 +1,14 @@
-use futures::StreamExt;
-use std::{io::Cursor, sync::Arc};
+use chrono::{DateTime, Utc};
+use epub::doc::DocError;
+use futures::{FutureExt, StreamExt};
+use std::{
+    borrow::Cow,
+    io::{Read, Seek},
+    sync::Arc,
+};
+use tempfile::SpooledTempFile;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
 
 use genawaiter::GeneratorState;
 use lapin::{
@@ -11,28 +20,113 @@ use lapin::{
     types::FieldTable,
     Connection as RabbitMQConnection, ExchangeKind,
 };
-use serde_json::json;
-use tracing::{error, info, info_span, Instrument};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use tracing::{error, info, info_span, warn, Instrument};
+use uuid::Uuid;
 
 use crate::{
+    configuration::ExtractionSettings,
     domain::{
-        extractors::extract_content_generator::extract_content_generator,
-        readers::{epub_reader::EpubReader, xml_reader},
+        enrichment::content_enricher::ContentEnricher,
+        entities::{
+            chunk_manifest::{ChunkDiff, ChunkManifest},
+            chunk_provenance::SectionTracker,
+            code_splitter::CodeSplitter,
+            image_ocr::ImageOcr,
+            meta_read::MetaRead,
+            near_duplicate::{NearDuplicateDetector, NearDuplicateIndex},
+            progress_event::ProgressEvent,
+        },
+        extractors::extract_content_generator::{extract_content_generator, ChunkSplitting},
+        language::stopwords_language_detector::detect_language,
+        readers::{
+            code_reader::{CodeReader, CodeReaderError},
+            epub_reader::{EpubReader, EpubReaderError},
+            html_reader::{HtmlReader, HtmlReaderError},
+            latex_reader::{self, LatexReader, LatexReaderError},
+            notebook_reader::{NotebookReader, NotebookReaderError},
+            office_reader::{OfficeFormat, OfficeReader, OfficeReaderError},
+            structured_reader::{StructuredFormat, StructuredReader, StructuredReaderError},
+            subtitle_reader::{SubtitleReader, SubtitleReaderError},
+            xml_reader,
+        },
+        redaction::pii_redactor::PiiRedactor,
     },
     repositories::source_file_s3_repository::{S3Repository, S3RepositoryError},
 };
 
 use common::{
-    constants::routing_keys::{CONTENT_EXTRACTED_ROUTING_KEY, EXTRACT_CONTENT_TEXT_ROUTING_KEY},
-    core::rabbitmq_message_repository::{
-        RabbitMQMessageRepository, RabbitMQMessageRepositoryError,
+    constants::routing_keys::{
+        CONTENT_EXTRACTION_PROGRESS_ROUTING_KEY, DELETE_CONTENT_ROUTING_KEY,
+        EXTRACT_CONTENT_TEXT_ROUTING_KEY, INGESTION_JOB_STATUS_ROUTING_KEY,
+        PRUNE_CONTENT_ROUTING_KEY,
+    },
+    core::{
+        fair_share::{
+            declare_deferred_queue, defer_message, FairShare, FairShareOwner, FairShareSettings,
+        },
+        idempotency_store::{ConsumerIdempotency, IdempotencyStorePort},
+        memory_ceiling::{ConsumptionThrottle, MemorySettings},
+        normalization_rules::NormalizationRulesCache,
+        rabbitmq_message_repository::{RabbitMQMessageRepository, RabbitMQMessageRepositoryError},
+        retry::RetryPolicy,
+        text_normalizer::TextNormalizer,
+        trace_propagation::continue_trace_from,
+    },
+    dtos::{
+        delete_content::{DeleteContentDto, PruneContentDto},
+        extract_content_job::{
+            chunk_manifest_path, near_duplicate_index_path, ChunkSplittingDto,
+            ExtractContentJobDto, IngestionLaneDto, SourceTypeDto,
+        },
+        extracted_content::{
+            ExtractedContentDto, LANGUAGE_METADATA_KEY, PII_REDACTED_METADATA_KEY,
+            SOURCE_ADDED_AT_METADATA_KEY, SOURCE_NAME_METADATA_KEY, USER_ID_METADATA_KEY,
+        },
+        extraction_progress::ExtractionProgressDto,
+        ingestion_job_status::{IngestionErrorCodeDto, IngestionJobStatusDto, SkippedItemDto},
+        templates::message_envelope::{MessageEnvelope, MessageEnvelopeError},
     },
-    dtos::{extract_content_job::ExtractContentJobDto, extracted_content::ExtractedContentDto},
     helper::error_chain_fmt,
 };
 
+/// Routing key of the extraction jobs of the bulk lane
 pub const ROUTING_KEY: &str = EXTRACT_CONTENT_TEXT_ROUTING_KEY;
 
+/// Settings of the handler, from the configuration
+#[derive(Debug, Clone)]
+pub struct HandlerSettings {
+    pub extraction: ExtractionSettings,
+    /// Retry policy on transient failures of the object storage
+    pub retry_policy: RetryPolicy,
+    pub memory: MemorySettings,
+    /// Share of the in-flight jobs between the users
+    pub fair_share: FairShareSettings,
+    /// Number of messages delivered before being acknowledged, under normal memory pressure.
+    /// Changes when the configuration is reloaded.
+    pub prefetch_count: watch::Receiver<u16>,
+    /// Cancelled when the consumption is handed over to a newly started instance
+    pub stop_consuming: CancellationToken,
+    /// Lane whose extraction jobs are consumed by the handler
+    pub lane: IngestionLaneDto,
+}
+
+/// Services used by the handler and its readers, shared between the handled messages
+#[derive(Clone)]
+pub struct ReaderServices {
+    pub code_splitter: Arc<dyn CodeSplitter>,
+    /// Set if the text of images should be recognized
+    pub image_ocr: Option<Arc<dyn ImageOcr>>,
+    /// Normalization rules of the tenant, applied to the extracted text contents
+    pub normalization_rules: Arc<NormalizationRulesCache>,
+    /// Adds the keywords and the named entities of the text contents to their metadata
+    pub content_enricher: Arc<ContentEnricher>,
+    /// Redacts the personal data of the contents of the sources uploaded with its redaction
+    pub pii_redactor: Arc<PiiRedactor>,
+    /// Extraction jobs already extracted, acknowledged without being extracted again when redelivered
+    pub idempotency_store: Arc<dyn IdempotencyStorePort>,
+}
+
 #[derive(thiserror::Error)]
 pub enum RegisterHandlerExtractContentJobError {
     #[error(transparent)]
@@ -51,6 +145,8 @@ impl std::fmt::Debug for RegisterHandlerExtractContentJobError {
 ///
 /// It declares a queue and binds it to the given exchange.
 /// It handles messages one by one, there is no handling messages in parallel.
+/// The prefetched messages are handled in turn between their users, and the messages of a user
+/// holding their share of the prefetched messages are deferred.
 ///
 /// Some repositories (RabbitMQMessageRepository) are initialized inside the handler
 /// to avoid sharing some instances (ex: RabbitMQ channel) between each thread
@@ -59,7 +155,8 @@ impl std::fmt::Debug for RegisterHandlerExtractContentJobError {
     skip(
         rabbitmq_consuming_connection,
         s3_repository,
-        message_rabbitmq_repository
+        message_rabbitmq_repository,
+        reader_services
     )
 )]
 pub async fn register_handler(
@@ -69,6 +166,8 @@ pub async fn register_handler(
     s3_repository: Arc<S3Repository>,
     // Not an `Arc` shared reference as we want to initialize a new repository for each thread (or at least for each handler)
     message_rabbitmq_repository: RabbitMQMessageRepository,
+    mut handler_settings: HandlerSettings,
+    reader_services: ReaderServices,
 ) -> Result<(), RegisterHandlerExtractContentJobError> {
     let channel = rabbitmq_consuming_connection.create_channel().await?;
 
@@ -84,8 +183,10 @@ pub async fn register_handler(
         )
         .await?;
 
+    // Each lane has its own queue, for the small sources not to wait behind the books
+    let routing_key = handler_settings.lane.extract_content_routing_key();
     // In order to have several nodes of this service as consumers of the same queue: use a specific queue name
-    let queue_name = queue_name(&queue_name_prefix);
+    let queue_name = queue_name(&queue_name_prefix, handler_settings.lane);
 
     // When supplying an empty string queue name, RabbitMQ generates a name for us, returned from the queue declaration request
     let _ = channel
@@ -98,19 +199,32 @@ pub async fn register_handler(
 
     info!(
         "Declared queue {} on exchange {}, binding on {}",
-        queue_name, exchange_name, EXTRACT_CONTENT_TEXT_ROUTING_KEY
+        queue_name, exchange_name, routing_key
     );
 
     channel
         .queue_bind(
             &queue_name,
             &exchange_name,
-            ROUTING_KEY,
+            routing_key,
             QueueBindOptions::default(),
             FieldTable::default(),
         )
         .await?;
 
+    // The deferred messages of a user are delivered again after a delay, at the end of the queue
+    declare_deferred_queue(&channel, &queue_name, &exchange_name, routing_key).await?;
+
+    // The jobs extracted by any instance consuming the queue are not extracted again
+    let idempotency =
+        ConsumerIdempotency::new(reader_services.idempotency_store.clone(), &queue_name);
+
+    // Limits the number of messages delivered at once, shed when approaching the memory ceiling
+    let prefetch_count = *handler_settings.prefetch_count.borrow_and_update();
+    let mut consumption_throttle =
+        ConsumptionThrottle::new(handler_settings.memory.clone(), prefetch_count);
+    consumption_throttle.init(&channel).await?;
+
     let consumer_options = BasicConsumeOptions {
         no_ack: false,
         ..BasicConsumeOptions::default()
@@ -134,11 +248,53 @@ pub async fn register_handler(
 
     info!(
         "📡 Handler consuming from queue {}, bound to {} with {}, waiting for messages ...",
-        queue_name, exchange_name, ROUTING_KEY,
+        queue_name, exchange_name, routing_key,
     );
 
-    while let Some(delivery) = consumer.next().await {
-        async {
+    // Messages delivered and not yet handled, shared between their tenants
+    let mut fair_share = FairShare::new(&handler_settings.fair_share);
+    let mut capacity = usize::from(prefetch_count);
+
+    loop {
+        // A new instance is ready to take over: stops consuming before the next message
+        if handler_settings.stop_consuming.is_cancelled() {
+            break;
+        }
+
+        if handler_settings
+            .prefetch_count
+            .has_changed()
+            .unwrap_or(false)
+        {
+            let prefetch_count = *handler_settings.prefetch_count.borrow_and_update();
+            info!(prefetch_count, "Prefetch count reloaded");
+            capacity = usize::from(prefetch_count);
+            if let Err(error) = consumption_throttle
+                .set_prefetch_count(&channel, prefetch_count)
+                .await
+            {
+                error!(?error, "Failed to apply the reloaded prefetch count");
+            }
+        }
+
+        // Holds the already prefetched messages, without waiting, to handle them in turn between their users
+        let mut deliveries = Vec::new();
+        while let Some(Some(delivery)) = consumer.next().now_or_never() {
+            deliveries.push(delivery);
+        }
+        if deliveries.is_empty() && fair_share.is_empty() {
+            let delivery = tokio::select! {
+                biased;
+                _ = handler_settings.stop_consuming.cancelled() => break,
+                delivery = consumer.next() => delivery,
+            };
+            let Some(delivery) = delivery else {
+                break;
+            };
+            deliveries.push(delivery);
+        }
+
+        for delivery in deliveries {
             let delivery = match delivery {
                 // Carries the delivery alongside its channel
                 Ok(delivery) => delivery,
@@ -148,18 +304,79 @@ pub async fn register_handler(
                         ?error,
                         "Failed to consume queue message on queue {}", queue_name
                     );
-                    return;
+                    continue;
                 }
             };
 
+            // Without fair share, the messages have no owner: they are handled in their delivery order
+            let owner = if handler_settings.fair_share.enabled {
+                job_fair_share_owner(&delivery)
+            } else {
+                None
+            };
+            if let Err(delivery) = fair_share.hold(owner.clone(), delivery, capacity) {
+                info!(
+                    "Deferring message with delivery tag {}: {:?} holds its share of the in-flight jobs",
+                    delivery.delivery_tag, owner
+                );
+                if let Err(error) = defer_message(
+                    &channel,
+                    &queue_name,
+                    &delivery,
+                    handler_settings.fair_share.defer_delay_ms,
+                )
+                .await
+                {
+                    error!(?error, "Failed to defer extract_content_job message");
+
+                    // Not deferred: requeued to be delivered again, instead of staying unacknowledged
+                    let requeue = BasicNackOptions {
+                        requeue: true,
+                        ..BasicNackOptions::default()
+                    };
+                    if let Err(error) = delivery.nack(requeue).await {
+                        error!(?error, "Failed to nack extract_content_job message");
+                    }
+                }
+            }
+        }
+
+        let Some((owner, delivery)) = fair_share.next_to_handle() else {
+            continue;
+        };
+
+        // Large jobs can make the worker run out of memory: waits for enough memory before handling a new one
+        if let Err(error) = consumption_throttle.wait_for_memory(&channel).await {
+            error!(?error, "Failed to throttle the consumption");
+        }
+
+        async {
+            // The spans handling the message continue the trace of its publisher
+            continue_trace_from(&delivery.properties);
+
+            if idempotency.is_completed(&delivery.data).await {
+                info!(
+                    "Acknowledging already handled message with delivery tag {}",
+                    delivery.delivery_tag
+                );
+                if let Err(error) = delivery.ack(BasicAckOptions::default()).await {
+                    error!(?error, "Failed to ack extract_content_job message");
+                }
+                return;
+            }
+
             match execute_handler(
                 s3_repository.clone(),
                 &message_rabbitmq_repository,
+                &handler_settings,
+                &reader_services,
                 &delivery,
             )
             .await
             {
                 Ok(()) => {
+                    idempotency.set_completed(&delivery.data).await;
+
                     info!(
                         "Acknowledging message with delivery tag {}",
                         delivery.delivery_tag
@@ -184,19 +401,40 @@ pub async fn register_handler(
         }
         .instrument(info_span!(
             "Handling consumed message",
-            routing_key = ROUTING_KEY,
+            routing_key = routing_key,
             exchange = exchange_name,
             queue = queue_name,
             message_id = %uuid::Uuid::new_v4(),
         ))
-        .await
+        .await;
+
+        fair_share.release(&owner);
+    }
+
+    if handler_settings.stop_consuming.is_cancelled() {
+        // Prefetched messages that were not handled are requeued for the next instance
+        channel.close(200, "Handing over the consumption").await?;
+        info!("Stopped consuming from queue {}", queue_name);
     }
 
     Ok(())
 }
 
-pub fn queue_name(queue_name_prefix: &str) -> String {
-    format!("{}_{}", queue_name_prefix, ROUTING_KEY)
+/// Owner of the job of a message, sharing the in-flight jobs with the other owners. `None` if it can not be decoded.
+fn job_fair_share_owner(delivery: &Delivery) -> Option<FairShareOwner> {
+    MessageEnvelope::<ExtractContentJobDto>::try_decoding(&delivery.data)
+        .ok()
+        .and_then(|message| {
+            FairShareOwner::of_job(message.payload.tenant_id, message.payload.user_id)
+        })
+}
+
+pub fn queue_name(queue_name_prefix: &str, lane: IngestionLaneDto) -> String {
+    format!(
+        "{}_{}",
+        queue_name_prefix,
+        lane.extract_content_routing_key()
+    )
 }
 
 #[derive(thiserror::Error)]
@@ -207,8 +445,26 @@ pub enum ExecuteHandlerExtractContentJobError {
     RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
     #[error("Error while serializing message data: {0}")]
     JsonError(#[from] serde_json::Error),
+    #[error(transparent)]
+    MessageEnvelopeError(#[from] MessageEnvelopeError),
     #[error("{0}")]
     MessageParsingError(String),
+    #[error(transparent)]
+    EpubReaderError(#[from] EpubReaderError),
+    #[error(transparent)]
+    SubtitleReaderError(#[from] SubtitleReaderError),
+    #[error(transparent)]
+    NotebookReaderError(#[from] NotebookReaderError),
+    #[error(transparent)]
+    CodeReaderError(#[from] CodeReaderError),
+    #[error(transparent)]
+    LatexReaderError(#[from] LatexReaderError),
+    #[error(transparent)]
+    HtmlReaderError(#[from] HtmlReaderError),
+    #[error(transparent)]
+    OfficeReaderError(#[from] OfficeReaderError),
+    #[error(transparent)]
+    StructuredReaderError(#[from] StructuredReaderError),
 }
 
 impl std::fmt::Debug for ExecuteHandlerExtractContentJobError {
@@ -217,51 +473,614 @@ impl std::fmt::Debug for ExecuteHandlerExtractContentJobError {
     }
 }
 
+impl ExecuteHandlerExtractContentJobError {
+    /// Stable code of the error, explained to the user on the failed job
+    pub fn error_code(&self) -> IngestionErrorCodeDto {
+        match self {
+            Self::S3RepositoryError(_) => IngestionErrorCodeDto::SourceUnavailable,
+            Self::EpubReaderError(EpubReaderError::DrmProtected) => {
+                IngestionErrorCodeDto::DrmProtected
+            }
+            Self::EpubReaderError(EpubReaderError::EpubDocError(DocError::ArchiveError(_))) => {
+                IngestionErrorCodeDto::InvalidArchive
+            }
+            Self::EpubReaderError(_) => IngestionErrorCodeDto::InvalidFormat,
+            Self::SubtitleReaderError(SubtitleReaderError::Utf8Error(_))
+            | Self::HtmlReaderError(HtmlReaderError::Utf8Error(_)) => {
+                IngestionErrorCodeDto::InvalidEncoding
+            }
+            Self::NotebookReaderError(NotebookReaderError::InvalidNotebook(_)) => {
+                IngestionErrorCodeDto::InvalidFormat
+            }
+            Self::CodeReaderError(CodeReaderError::ZipError(_))
+            | Self::LatexReaderError(LatexReaderError::ZipError(_))
+            | Self::OfficeReaderError(OfficeReaderError::ZipError(_)) => {
+                IngestionErrorCodeDto::InvalidArchive
+            }
+            Self::CodeReaderError(CodeReaderError::UnsupportedLanguage(_)) => {
+                IngestionErrorCodeDto::UnsupportedLanguage
+            }
+            Self::LatexReaderError(LatexReaderError::NoMainDocument) => {
+                IngestionErrorCodeDto::MissingMainDocument
+            }
+            Self::OfficeReaderError(OfficeReaderError::MissingDocumentPart(_))
+            | Self::StructuredReaderError(StructuredReaderError::MissingContentColumns(_)) => {
+                IngestionErrorCodeDto::InvalidFormat
+            }
+            Self::StructuredReaderError(StructuredReaderError::CsvError(error)) => {
+                match error.kind() {
+                    csv::ErrorKind::Utf8 { .. } => IngestionErrorCodeDto::InvalidEncoding,
+                    _ => IngestionErrorCodeDto::InvalidFormat,
+                }
+            }
+            _ => IngestionErrorCodeDto::Internal,
+        }
+    }
+}
+
 #[tracing::instrument(
     name = "Executing handler on extract content job",
-    skip(s3_repository, message_rabbitmq_repository, message)
+    skip(s3_repository, message_rabbitmq_repository, reader_services, message)
 )]
 pub async fn execute_handler(
     s3_repository: Arc<S3Repository>,
     message_rabbitmq_repository: &RabbitMQMessageRepository,
+    handler_settings: &HandlerSettings,
+    reader_services: &ReaderServices,
     message: &Delivery,
 ) -> Result<(), ExecuteHandlerExtractContentJobError> {
-    let job = ExtractContentJobDto::try_parsing(&message.data).map_err(|error| {
-        ExecuteHandlerExtractContentJobError::MessageParsingError(format!(
-            "Failed to parse extract content job message data: {}",
-            error
-        ))
-    })?;
-    info!(?job, "Received extract content job");
+    let message =
+        MessageEnvelope::<ExtractContentJobDto>::try_decoding(&message.data).map_err(|error| {
+            ExecuteHandlerExtractContentJobError::MessageParsingError(format!(
+                "Failed to parse extract content job message data: {}",
+                error
+            ))
+        })?;
+    // The extracted contents continue the trace of their job
+    let trace_context = message.caused_trace_context();
+    let job = message.payload;
+    info!(?job, trace_context, "Received extract content job");
+
+    let source_meta_id = job.source_meta_id;
+    publish_job_status(
+        message_rabbitmq_repository,
+        &IngestionJobStatusDto::extracting(source_meta_id, None),
+    )
+    .await;
+
+    let mut progress = ProgressEvent::new(source_meta_id);
+    let extraction_result = extract_contents(
+        s3_repository,
+        message_rabbitmq_repository,
+        handler_settings,
+        reader_services,
+        job,
+        trace_context,
+        &mut progress,
+    )
+    .await;
+
+    let job_status = match &extraction_result {
+        Ok(extracted_source) => {
+            progress.complete();
+            let nb_unchanged_contents = extracted_source.nb_unchanged_contents;
+            IngestionJobStatusDto::extracting(
+                source_meta_id,
+                Some(progress.chunk_index - nb_unchanged_contents),
+            )
+            .with_unchanged_contents(nb_unchanged_contents)
+            .with_skipped_items(extracted_source.skipped_items.clone())
+        }
+        Err(error) => {
+            progress.fail();
+            IngestionJobStatusDto::failed(source_meta_id, error.to_string(), error.error_code())
+        }
+    };
+    publish_progress(message_rabbitmq_repository, &progress).await;
+    publish_job_status(message_rabbitmq_repository, &job_status).await;
+
+    extraction_result.map(|_| ())
+}
+
+/// Contents extracted from a source, once published
+struct ExtractedSource {
+    /// Items of the source skipped as they could not be read, the other items being extracted
+    skipped_items: Vec<SkippedItemDto>,
+    /// Contents of an incremental extraction unchanged since the previous extraction, not published
+    nb_unchanged_contents: u64,
+}
+
+/// Extracts the contents of the source file of a job and publishes them, with the progress of the extraction
+///
+/// The contents are diffed against the manifest of the previous extraction of the source, saved next to its file.
+/// An incremental extraction only publishes the changed and new contents, and prunes the contents no longer extracted.
+/// Without manifest, the source was last extracted before the manifests: its contents are all wiped beforehand,
+/// as by a reindexing wiping the index.
+async fn extract_contents(
+    s3_repository: Arc<S3Repository>,
+    message_rabbitmq_repository: &RabbitMQMessageRepository,
+    handler_settings: &HandlerSettings,
+    reader_services: &ReaderServices,
+    job: ExtractContentJobDto,
+    trace_context: String,
+    progress: &mut ProgressEvent,
+) -> Result<ExtractedSource, ExecuteHandlerExtractContentJobError> {
+    let source_meta_id = job.source_meta_id;
+    let fulltext_shard = job.fulltext_shard;
+    let retry_policy = &handler_settings.retry_policy;
+
+    // The sources of the older jobs, without user, have no manifest
+    let manifest_path = job
+        .user_id
+        .map(|user_id| chunk_manifest_path(user_id, source_meta_id));
+    let previous_manifest = match &manifest_path {
+        Some(manifest_path) if job.incremental => {
+            load_manifest(&s3_repository, retry_policy, manifest_path).await?
+        }
+        _ => None,
+    };
+    let incremental = job.incremental && previous_manifest.is_some();
+    if job.incremental && !incremental {
+        info!("No manifest of the previous extraction, wiping the contents of the source");
+        let json_message = serde_json::to_string(&DeleteContentDto {
+            source_meta_id,
+            fulltext_shard,
+        })?;
+        message_rabbitmq_repository
+            .publish(DELETE_CONTENT_ROUTING_KEY, json_message.as_bytes())
+            .await?;
+    }
+
+    let mut chunk_diff =
+        ChunkDiff::new(previous_manifest, incremental, &job.object_store_path_name);
+
+    let near_duplicate_settings = &handler_settings.extraction.near_duplicates;
+    let near_duplicate_index_path = job
+        .user_id
+        .filter(|_| near_duplicate_settings.enabled)
+        .map(near_duplicate_index_path);
+    let mut near_duplicates = match &near_duplicate_index_path {
+        Some(index_path) => {
+            let previous_index =
+                load_near_duplicate_index(&s3_repository, retry_policy, index_path).await?;
+            NearDuplicateDetector::new(near_duplicate_settings, previous_index, source_meta_id)
+        }
+        None => NearDuplicateDetector::disabled(source_meta_id),
+    };
+
+    let skipped_items = read_contents(
+        s3_repository.clone(),
+        message_rabbitmq_repository,
+        handler_settings,
+        reader_services,
+        job,
+        trace_context,
+        &mut ExtractionState {
+            progress,
+            chunk_diff: &mut chunk_diff,
+            near_duplicates: &mut near_duplicates,
+        },
+    )
+    .await?;
+
+    // Saved before the pruning: a pruned content is never found unchanged by the next extraction
+    if let Some(manifest_path) = &manifest_path {
+        let json_manifest = serde_json::to_vec(&chunk_diff.manifest())?;
+        retry_policy
+            .retry("saving the manifest of the extracted contents", || {
+                s3_repository.put_file(manifest_path, &json_manifest)
+            })
+            .await?;
+    }
+
+    // Another extraction of a source of the user saving the index meanwhile loses its fingerprints
+    if let Some(index_path) = &near_duplicate_index_path {
+        info!(
+            "Tagged {} near duplicate contents",
+            near_duplicates.nb_near_duplicates()
+        );
+        let json_index = serde_json::to_vec(&near_duplicates.index())?;
+        retry_policy
+            .retry(
+                "saving the fingerprints of the contents of the user",
+                || s3_repository.put_file(index_path, &json_index),
+            )
+            .await?;
+    }
+
+    let removed_content_ids = chunk_diff.removed_content_ids();
+    if chunk_diff.is_incremental() && !removed_content_ids.is_empty() {
+        info!(
+            "Pruning {} contents no longer extracted from the source",
+            removed_content_ids.len()
+        );
+        let json_message = serde_json::to_string(&PruneContentDto {
+            source_meta_id,
+            fulltext_shard,
+            content_ids: removed_content_ids,
+        })?;
+        message_rabbitmq_repository
+            .publish(PRUNE_CONTENT_ROUTING_KEY, json_message.as_bytes())
+            .await?;
+    }
+
+    Ok(ExtractedSource {
+        skipped_items,
+        nb_unchanged_contents: chunk_diff.nb_unchanged(),
+    })
+}
+
+/// Loads the manifest of the previous extraction of a source, if any
+///
+/// An unreadable manifest, from a future version for ex, is ignored: the source is then extracted as without manifest.
+async fn load_manifest(
+    s3_repository: &S3Repository,
+    retry_policy: &RetryPolicy,
+    manifest_path: &str,
+) -> Result<Option<ChunkManifest>, S3RepositoryError> {
+    let json_manifest = match retry_policy
+        .retry("loading the manifest of the previous extraction", || {
+            s3_repository.get_file(manifest_path)
+        })
+        .await
+    {
+        Ok(json_manifest) => json_manifest,
+        Err(S3RepositoryError::ObjectNotFound(_)) => return Ok(None),
+        Err(error) => return Err(error),
+    };
+
+    match serde_json::from_slice::<ChunkManifest>(&json_manifest) {
+        Ok(manifest) if manifest.version == ChunkManifest::CURRENT_VERSION => Ok(Some(manifest)),
+        Ok(manifest) => {
+            warn!(
+                version = manifest.version,
+                "Ignoring the manifest {} of another version", manifest_path
+            );
+            Ok(None)
+        }
+        Err(error) => {
+            warn!(?error, "Ignoring the unreadable manifest {}", manifest_path);
+            Ok(None)
+        }
+    }
+}
+
+/// Loads the fingerprints of the contents of a user, if any
+///
+/// Like a manifest, an unreadable index is ignored: the contents are then compared to the contents of the source only.
+async fn load_near_duplicate_index(
+    s3_repository: &S3Repository,
+    retry_policy: &RetryPolicy,
+    index_path: &str,
+) -> Result<Option<NearDuplicateIndex>, S3RepositoryError> {
+    let json_index = match retry_policy
+        .retry(
+            "loading the fingerprints of the contents of the user",
+            || s3_repository.get_file(index_path),
+        )
+        .await
+    {
+        Ok(json_index) => json_index,
+        Err(S3RepositoryError::ObjectNotFound(_)) => return Ok(None),
+        Err(error) => return Err(error),
+    };
+
+    match serde_json::from_slice::<NearDuplicateIndex>(&json_index) {
+        Ok(index) if index.version == NearDuplicateIndex::CURRENT_VERSION => Ok(Some(index)),
+        Ok(index) => {
+            warn!(
+                version = index.version,
+                "Ignoring the near duplicate index {} of another version", index_path
+            );
+            Ok(None)
+        }
+        Err(error) => {
+            warn!(
+                ?error,
+                "Ignoring the unreadable near duplicate index {}", index_path
+            );
+            Ok(None)
+        }
+    }
+}
+
+/// Metadata of a source added to the metadata of each of its extracted contents
+#[derive(Debug)]
+struct SourceTags {
+    user_id: Option<Uuid>,
+    source_name: String,
+    source_added_at: Option<DateTime<Utc>>,
+}
+
+impl SourceTags {
+    fn tag(&self, metadata: &mut JsonMap<String, JsonValue>) {
+        // Only the owner of the source can find the content with a search
+        if let Some(user_id) = self.user_id {
+            metadata.insert(USER_ID_METADATA_KEY.to_string(), json!(user_id));
+        }
+        // The searches can sort the contents by source name and recency
+        metadata.insert(
+            SOURCE_NAME_METADATA_KEY.to_string(),
+            json!(self.source_name),
+        );
+        if let Some(source_added_at) = self.source_added_at {
+            metadata.insert(
+                SOURCE_ADDED_AT_METADATA_KEY.to_string(),
+                json!(source_added_at.timestamp_millis()),
+            );
+        }
+    }
+}
+
+/// State of an extraction, updated with each extracted content
+struct ExtractionState<'a> {
+    progress: &'a mut ProgressEvent,
+    /// Diff against the previous extraction, only the contents to publish being published
+    chunk_diff: &'a mut ChunkDiff,
+    /// Tags the text contents nearly identical to an already indexed content of the user
+    near_duplicates: &'a mut NearDuplicateDetector,
+}
 
+/// How the contents extracted from a source are transformed and published, the same for all its contents
+struct ContentPublishing<'a> {
+    /// Repository used to publish the extracted contents
+    message_rabbitmq_repository: &'a RabbitMQMessageRepository,
+    /// Rules of the tenant normalizing the text contents, the code contents being published as read
+    normalizer: &'a TextNormalizer,
+    /// Adds the keywords and the named entities of the text contents to their metadata
+    enricher: &'a ContentEnricher,
+    /// Set if the personal data of the contents should be redacted
+    pii_redactor: Option<&'a PiiRedactor>,
+    source_tags: SourceTags,
+    /// Shard of the full-text index the contents are saved to
+    fulltext_shard: u32,
+    /// Ingestion lane of the source, through which the contents are published
+    lane: IngestionLaneDto,
+    /// How the text of the source is split into contents
+    chunk_splitting: ChunkSplittingDto,
+    /// Trace of the extraction job, continued by the messages of the contents
+    trace_context: &'a str,
+    /// The progress is published every given number of contents. 0 to only publish it at the end.
+    progress_every_nb_contents: u64,
+}
+
+/// Reads the contents of the source file of a job and publishes them, with the progress of the extraction
+///
+/// # Returns
+/// The items of the source skipped as they could not be read, the other items being extracted
+async fn read_contents(
+    s3_repository: Arc<S3Repository>,
+    message_rabbitmq_repository: &RabbitMQMessageRepository,
+    handler_settings: &HandlerSettings,
+    reader_services: &ReaderServices,
+    job: ExtractContentJobDto,
+    trace_context: String,
+    state: &mut ExtractionState<'_>,
+) -> Result<Vec<SkippedItemDto>, ExecuteHandlerExtractContentJobError> {
     let ExtractContentJobDto {
         object_store_path_name,
         source_type,
         source_initial_name,
+        user_id,
+        fulltext_shard,
+        content_hash,
+        lane,
+        chunk_splitting,
+        column_mapping,
+        source_added_at,
+        redact_pii,
         ..
     } = job;
 
-    // There is probably a way to stream the content of the file from the S3 bucket,
-    // and not put it into memory. Or stream saving the content in a temp file, and
-    // access the content with a BufReader.
-    let file_content = s3_repository.get_file(&object_store_path_name).await?;
+    let extraction_settings = &handler_settings.extraction;
+    // Large files are spilled to a temporary file instead of being kept in memory
+    let (mut file_reader, source_size_bytes) = handler_settings
+        .retry_policy
+        .retry(
+            "downloading the source file from the object storage",
+            || {
+                download_source_file(
+                    &s3_repository,
+                    &object_store_path_name,
+                    content_hash.as_deref(),
+                    extraction_settings,
+                )
+            },
+        )
+        .await?;
+    state.progress.estimate_total_chunks(source_size_bytes);
+    publish_progress(message_rabbitmq_repository, state.progress).await;
 
-    // In-memory file-like object/reader implementing `Seek`.
-    // Note: for EPUB (or any format needing a `Seek` impl), we will always need to load the file in-memory ?)
-    // TODO: should we wrap it in a `BufReader` ?
-    // let file_reader = BufReader::new(file_content.as_slice());
-    let file_reader = Cursor::new(file_content);
+    // The same rules for all the contents of the source, even if they change during its extraction
+    let normalizer = reader_services
+        .normalization_rules
+        .get(message_rabbitmq_repository)
+        .await;
 
-    let epub_reader = EpubReader::from_reader(
-        file_reader,
-        Some(json!({ "file": object_store_path_name, "source_initial_name": source_initial_name, "source_type": source_type })),
-    )
-    .unwrap();
-    let mut xml_reader = xml_reader::build_from_reader(epub_reader);
+    let initial_metadata = json!({ "file": object_store_path_name, "source_initial_name": source_initial_name, "source_type": source_type });
+    let publishing = ContentPublishing {
+        message_rabbitmq_repository,
+        normalizer: &normalizer,
+        enricher: &reader_services.content_enricher,
+        pii_redactor: redact_pii.then_some(reader_services.pii_redactor.as_ref()),
+        source_tags: SourceTags {
+            user_id,
+            source_name: source_initial_name.clone(),
+            source_added_at,
+        },
+        fulltext_shard,
+        lane,
+        chunk_splitting,
+        trace_context: &trace_context,
+        progress_every_nb_contents: extraction_settings.progress_every_nb_contents,
+    };
+
+    // Each source type is read by its own stack of readers
+    match source_type {
+        SourceTypeDto::Epub => {
+            let epub_reader = EpubReader::from_reader(file_reader, Some(initial_metadata))?;
+            let mut xml_reader = xml_reader::build_from_reader(epub_reader)
+                .with_image_alts_in_text(extraction_settings.image_alts_in_text);
+            if let Some(image_ocr) = &reader_services.image_ocr {
+                xml_reader = xml_reader.with_image_ocr(
+                    image_ocr.clone(),
+                    extraction_settings.image_ocr.min_image_size_bytes,
+                );
+            }
+
+            publish_extracted_contents(&publishing, &mut xml_reader, state).await?;
+
+            let skipped_items = xml_reader
+                .source_reader()
+                .skipped_items()
+                .iter()
+                .map(|skipped_item| SkippedItemDto {
+                    path: skipped_item.path.clone(),
+                    error: skipped_item.error.clone(),
+                })
+                .collect();
+            return Ok(skipped_items);
+        }
+        SourceTypeDto::Srt | SourceTypeDto::Vtt => {
+            let mut subtitle_reader =
+                SubtitleReader::try_from_reader(file_reader, Some(initial_metadata), None)?;
+
+            publish_extracted_contents(&publishing, &mut subtitle_reader, state).await?;
+        }
+        SourceTypeDto::Ipynb => {
+            let mut notebook_reader = NotebookReader::try_from_reader(
+                file_reader,
+                Some(initial_metadata),
+                extraction_settings.embed_notebook_code_cells,
+            )?;
+
+            publish_extracted_contents(&publishing, &mut notebook_reader, state).await?;
+        }
+        SourceTypeDto::Latex => {
+            let mut latex_reader = LatexReader::try_from_reader(
+                file_reader,
+                Some(initial_metadata),
+                extraction_settings.latex_math_format,
+            )?;
+
+            publish_extracted_contents(&publishing, &mut latex_reader, state).await?;
+        }
+        SourceTypeDto::Html => {
+            let mut html_reader = HtmlReader::try_from_reader(file_reader, Some(initial_metadata))?;
+
+            publish_extracted_contents(&publishing, &mut html_reader, state).await?;
+        }
+        SourceTypeDto::Docx | SourceTypeDto::Odt => {
+            let format = match source_type {
+                SourceTypeDto::Docx => OfficeFormat::Docx,
+                _ => OfficeFormat::Odt,
+            };
+            let mut office_reader =
+                OfficeReader::try_from_reader(file_reader, format, Some(initial_metadata))?;
+
+            publish_extracted_contents(&publishing, &mut office_reader, state).await?;
+        }
+        SourceTypeDto::Csv | SourceTypeDto::Jsonl => {
+            let format = match source_type {
+                SourceTypeDto::Csv => StructuredFormat::Csv,
+                _ => StructuredFormat::Jsonl,
+            };
+            let mut structured_reader = StructuredReader::try_from_reader(
+                file_reader,
+                format,
+                column_mapping.content_columns,
+                Some(initial_metadata),
+            )?;
+
+            publish_extracted_contents(&publishing, &mut structured_reader, state).await?;
+        }
+        // An archive is either a LaTeX project or a source code repository
+        SourceTypeDto::Archive if latex_reader::is_latex_archive(&mut file_reader)? => {
+            let mut latex_reader = LatexReader::try_from_reader(
+                file_reader,
+                Some(initial_metadata),
+                extraction_settings.latex_math_format,
+            )?;
+
+            publish_extracted_contents(&publishing, &mut latex_reader, state).await?;
+        }
+        SourceTypeDto::Code | SourceTypeDto::Archive => {
+            let mut code_reader = CodeReader::try_from_reader(
+                file_reader,
+                &source_initial_name,
+                Some(initial_metadata),
+                reader_services.code_splitter.as_ref(),
+            )?;
+
+            publish_extracted_contents(&publishing, &mut code_reader, state).await?;
+        }
+    }
+
+    Ok(vec![])
+}
+
+/// Downloads a source file from the object storage
+///
+/// The file is kept in memory up to a given size, and spilled to a temporary file beyond.
+/// An interrupted download is resumed from the received bytes, and checked against the hash of the uploaded file.
+/// The returned file is rewound to its start, returned with its size in bytes.
+async fn download_source_file(
+    s3_repository: &S3Repository,
+    object_store_path_name: &str,
+    content_hash: Option<&str>,
+    extraction_settings: &ExtractionSettings,
+) -> Result<(SpooledTempFile, u64), S3RepositoryError> {
+    let mut source_file = SpooledTempFile::new(extraction_settings.in_memory_source_max_bytes);
+    let size_bytes = s3_repository
+        .download_file(
+            object_store_path_name,
+            &mut source_file,
+            extraction_settings.max_download_resumes,
+            content_hash,
+        )
+        .await?;
+    source_file.rewind()?;
+
+    Ok((source_file, size_bytes))
+}
+
+/// Extracts the contents from a reader and publishes each of them
+///
+/// # Arguments
+/// * `publishing` - how the contents of the source are transformed and published
+/// * `reader` - reader on the source file, with its metadata
+/// * `state` - state of the extraction, updated for each extracted content
+async fn publish_extracted_contents<ReaderType: Read + MetaRead + Send>(
+    publishing: &ContentPublishing<'_>,
+    reader: &mut ReaderType,
+    state: &mut ExtractionState<'_>,
+) -> Result<(), ExecuteHandlerExtractContentJobError> {
+    let ContentPublishing {
+        message_rabbitmq_repository,
+        normalizer,
+        enricher,
+        pii_redactor,
+        ref source_tags,
+        fulltext_shard,
+        lane,
+        chunk_splitting,
+        trace_context,
+        progress_every_nb_contents,
+    } = *publishing;
+    let ExtractionState {
+        progress,
+        chunk_diff,
+        near_duplicates,
+    } = state;
 
     let nb_words_per_content = 100;
-    let mut generator = extract_content_generator(&mut xml_reader, Some(nb_words_per_content));
+    let mut generator = extract_content_generator(
+        reader,
+        Some(nb_words_per_content),
+        ChunkSplitting::from_dto(chunk_splitting, nb_words_per_content),
+    );
 
+    let mut section_tracker = SectionTracker::new(chunk_diff.object_store_path_name());
     let mut i = 0;
     // Is a limit needed to avoid infinite loop ?
     loop {
@@ -278,15 +1097,117 @@ pub async fn execute_handler(
             extracted_content.metadata, extracted_content.content
         );
 
-        let json_dto =
-            serde_json::to_string(&Into::<ExtractedContentDto>::into(extracted_content))?;
+        let nb_bytes = extracted_content.content.len();
+        let offset = extracted_content.offset;
+        let mut dto: ExtractedContentDto = extracted_content.into();
+        // Identified by where it was read: the same content extracted again replaces the previous one in the indexes
+        let provenance = section_tracker.provenance(&dto.metadata, offset);
+        dto.id = provenance.chunk_id(progress.source_meta_id);
+        dto.provenance = Some(provenance);
+        // Links the content to its source, for the content to be deleted with its source
+        dto.source_meta_id = Some(progress.source_meta_id);
+        dto.fulltext_shard = fulltext_shard;
+        if let Some(metadata) = dto.metadata.as_object_mut() {
+            source_tags.tag(metadata);
+        }
+        // Redacted before anything is read from the content, for its personal data not to end up in its metadata
+        if let Some(pii_redactor) = pii_redactor {
+            let is_redacted = match pii_redactor.redact(&dto.content) {
+                Cow::Owned(redacted) => {
+                    dto.content = redacted;
+                    true
+                }
+                Cow::Borrowed(_) => false,
+            };
+            if let Some(metadata) = dto.metadata.as_object_mut() {
+                metadata.insert(PII_REDACTED_METADATA_KEY.to_string(), json!(is_redacted));
+            }
+        }
+        if !dto.is_code {
+            // Normalized before tagging its language, for a removed boilerplate not to be taken into account
+            if let Cow::Owned(normalized) = normalizer.normalize(&dto.content) {
+                dto.content = normalized;
+            }
+            // Tags the language of the text contents, for the embedding model and the search filters
+            if let (Some(language), Some(metadata)) =
+                (detect_language(&dto.content), dto.metadata.as_object_mut())
+            {
+                metadata.insert(LANGUAGE_METADATA_KEY.to_string(), json!(language));
+            }
+            // Enriched before being diffed, for the contents extracted before the enrichment to be published again
+            if let Some(metadata) = dto.metadata.as_object_mut() {
+                enricher.enrich(&dto.content, metadata).await;
+            }
+            // Tagged before being diffed, like the enrichment
+            near_duplicates.check(&mut dto);
+        }
+        // An unchanged content is still embedded and indexed from the previous extraction
+        if chunk_diff.record(&dto) {
+            let json_dto = MessageEnvelope::in_trace(dto, Some(trace_context.to_string()))
+                .try_serializing()?;
 
-        message_rabbitmq_repository
-            .publish(CONTENT_EXTRACTED_ROUTING_KEY, json_dto.as_bytes())
-            .await?;
+            message_rabbitmq_repository
+                .publish(lane.content_extracted_routing_key(), json_dto.as_bytes())
+                .await?;
+        }
+
+        progress.record_chunk(nb_bytes);
+        if progress_every_nb_contents > 0
+            && progress
+                .chunk_index
+                .is_multiple_of(progress_every_nb_contents)
+        {
+            publish_progress(message_rabbitmq_repository, progress).await;
+        }
 
         i += 1;
     }
 
     Ok(())
 }
+
+/// Publishes the progress of an extraction
+///
+/// The progress is only informative: a failure to publish it does not fail the extraction.
+async fn publish_progress(
+    message_rabbitmq_repository: &RabbitMQMessageRepository,
+    progress: &ProgressEvent,
+) {
+    let json_dto = match serde_json::to_string(&ExtractionProgressDto::from(progress)) {
+        Ok(json_dto) => json_dto,
+        Err(error) => {
+            error!(?error, "Failed to serialize the extraction progress");
+            return;
+        }
+    };
+
+    if let Err(error) = message_rabbitmq_repository
+        .publish(CONTENT_EXTRACTION_PROGRESS_ROUTING_KEY, json_dto.as_bytes())
+        .await
+    {
+        error!(?error, "Failed to publish the extraction progress");
+    }
+}
+
+/// Publishes the status of the ingestion job of a source
+///
+/// Like the progress, a failure to publish it does not fail the extraction.
+async fn publish_job_status(
+    message_rabbitmq_repository: &RabbitMQMessageRepository,
+    job_status: &IngestionJobStatusDto,
+) {
+    let json_dto = match serde_json::to_string(job_status) {
+        Ok(json_dto) => json_dto,
+        Err(error) => {
+            error!(?error, "Failed to serialize the ingestion job status");
+            return;
+        }
+    };
+
+    if let Err(error) = message_rabbitmq_repository
+        .publish(INGESTION_JOB_STATUS_ROUTING_KEY, json_dto.as_bytes())
+        .await
+    {
+        error!(?error, "Failed to publish the ingestion job status");
+    }
+}
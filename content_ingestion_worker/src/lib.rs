@@ -2,4 +2,5 @@ pub mod configuration;
 pub mod domain;
 pub mod handlers;
 pub mod repositories;
+pub mod self_test;
 pub mod startup;
@@ -1,8 +1,32 @@
-use common::telemetry::{get_tracing_subscriber, init_tracing_subscriber};
-use content_ingestion_worker::{configuration::get_configuration, startup::Application};
+use common::{
+    core::configuration::reload_on_sighup,
+    telemetry::{get_tracing_subscriber, init_tracing_subscriber, shutdown_tracing},
+};
+use content_ingestion_worker::{
+    configuration::get_configuration,
+    self_test::{run_self_test, FixtureOutcome, SELF_TEST_ARG},
+    startup::Application,
+};
+
+#[cfg(feature = "jemalloc")]
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
+    // Checks the reader stack on the bundled corpus, without any configuration
+    if std::env::args().any(|arg| arg == SELF_TEST_ARG) {
+        let results = run_self_test();
+        for result in results.iter() {
+            println!("{}", result);
+        }
+
+        let is_success = results
+            .iter()
+            .all(|result| result.outcome == FixtureOutcome::Passed);
+        std::process::exit(if is_success { 0 } else { 1 });
+    }
+
     let tracing_subscriber = get_tracing_subscriber(
         "content_ingestion_worker".into(),
         "info".into(),
@@ -11,14 +35,23 @@ async fn main() -> std::io::Result<()> {
     init_tracing_subscriber(tracing_subscriber);
 
     // Panics if the configuration can't be read
-    let configuration = get_configuration().expect("Failed to read configuration.");
+    let configuration = get_configuration()
+        .await
+        .expect("Failed to read configuration.");
 
     let application = match Application::build(configuration).await {
         Ok(application) => application,
         Err(error) => panic!("Failed to build application: {:?}", error),
     };
 
+    // The prefetch counts are reloaded on SIGHUP, without restarting the worker
+    let reloadable_settings = application.reloadable_settings();
+    reload_on_sighup(get_configuration, move |settings| {
+        reloadable_settings.update(&settings)
+    })?;
+
     application.run_until_stopped().await.unwrap();
+    shutdown_tracing();
 
     Ok(())
 }
@@ -1,6 +1,23 @@
-use common::helper::error_chain_fmt;
+use common::{core::retry::TransientError, helper::error_chain_fmt};
 use s3::Bucket;
-use tracing::{error, info};
+use sha2::{Digest, Sha256};
+use std::{
+    future::Future,
+    io::{self, Write},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, DuplexStream, ReadBuf},
+    task::JoinHandle,
+};
+use tracing::{error, info, warn};
+
+/// Size of the buffer between the download of a file stream and its reads
+const STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Size of the chunks read from a file stream
+const DOWNLOAD_CHUNK_SIZE: usize = 64 * 1024;
 
 /// Simple Storage Service (S3) client to store source files
 pub struct S3Repository {
@@ -13,6 +30,8 @@ pub struct S3Repository {
 pub enum S3RepositoryError {
     #[error("The object could not be found in the bucket: {0}")]
     ObjectNotFound(String),
+    #[error("The downloaded file does not match the uploaded file: expected the hash {expected}, got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
     #[error(transparent)]
     IOError(#[from] std::io::Error),
     #[error(transparent)]
@@ -25,32 +44,118 @@ impl std::fmt::Debug for S3RepositoryError {
     }
 }
 
+/// Network failures, timeouts, throttling and server errors are transient
+impl TransientError for S3RepositoryError {
+    fn is_transient(&self) -> bool {
+        match self {
+            S3RepositoryError::ObjectNotFound(_) => false,
+            // The resumed ranges do not add up to the file: downloading it again from the start can succeed
+            S3RepositoryError::ChecksumMismatch { .. } => true,
+            // A failed file stream wraps the download error
+            S3RepositoryError::IOError(error) => error
+                .get_ref()
+                .and_then(|error| error.downcast_ref::<S3RepositoryError>())
+                .is_none_or(|error| error.is_transient()),
+            S3RepositoryError::Other(error) => match error {
+                s3::error::S3Error::Http(code, _) => *code >= 500 || *code == 408 || *code == 429,
+                s3::error::S3Error::Reqwest(error) => {
+                    error.is_timeout() || error.is_connect() || error.is_request()
+                }
+                s3::error::S3Error::Io(_) => true,
+                _ => false,
+            },
+        }
+    }
+}
+
 impl S3Repository {
     pub fn new(bucket: Bucket) -> Self {
         Self { bucket }
     }
 
-    /// Get a given stream of a file from a bucket in the object storage
+    /// Get a stream of a file from a bucket in the object storage
     ///
-    /// WIP - not sure how to transform this into a buffer reader
+    /// The file is downloaded by a spawned task as it is read, without being fully loaded in memory.
+    /// Download failures are returned by the stream reads, wrapping a `S3RepositoryError`.
     ///
     /// # Arguments
     /// * `object_path_name` - The path (with the object name) of the file to get
+    #[tracing::instrument(name = "Get file stream from bucket", skip(self))]
+    pub fn get_file_stream(&self, object_path_name: &str) -> S3FileStream {
+        self.get_file_stream_from(object_path_name, 0)
+    }
+
+    /// Get a stream of a file from a bucket in the object storage, starting at a byte offset
     ///
-    /// # Return
-    /// A stream of Bytes from the S3 bucket
-    // #[tracing::instrument(name = "Get file from bucket", skip(self))]
-    // pub async fn get_file_stream(
-    //     &self,
-    //     object_path_name: &str,
-    // ) -> Result<Pin<Box<dyn Stream<Item = Bytes>>>, S3RepositoryError> {
+    /// A stream starting at the end of the file is empty.
+    ///
+    /// # Arguments
+    /// * `object_path_name` - The path (with the object name) of the file to get
+    /// * `offset` - Number of bytes of the file to skip
+    #[tracing::instrument(name = "Get file stream from bucket from offset", skip(self))]
+    pub fn get_file_stream_from(&self, object_path_name: &str, offset: u64) -> S3FileStream {
+        let (mut writer, reader) = tokio::io::duplex(STREAM_BUFFER_SIZE);
+        let bucket = self.bucket.clone();
+        let object_path_name = object_path_name.to_string();
+
+        let download = tokio::spawn(async move {
+            let result = match offset {
+                0 => {
+                    bucket
+                        .get_object_to_writer(&object_path_name, &mut writer)
+                        .await
+                }
+                offset => {
+                    bucket
+                        .get_object_range_to_writer(&object_path_name, offset, None, &mut writer)
+                        .await
+                }
+            };
+
+            let status_code = match result {
+                Ok(status_code) => status_code,
+                // Range Not Satisfiable: the offset is the end of the file
+                Err(s3::error::S3Error::Http(416, _)) if offset > 0 => 416,
+                Err(s3::error::S3Error::Http(404, _)) => {
+                    return Err(S3RepositoryError::ObjectNotFound(object_path_name));
+                }
+                Err(error) => return Err(S3RepositoryError::Other(error)),
+            };
+            info!("🦄 Get stream from bucket response: {}", status_code);
 
-    //     let stream = self.bucket.get_object_stream(object_path_name).await?;
-    //     // Check stream status
-    //     info!("Stream status: {}", stream.status_code);
+            Ok(())
+        });
 
-    //     Ok(stream.bytes)
-    // }
+        S3FileStream {
+            reader,
+            download: Some(download),
+        }
+    }
+
+    /// Downloads a file into a writer, resuming from the received bytes after a transient failure
+    ///
+    /// A large file is not downloaded again from its start after a network failure: the rest of the file
+    /// is requested with a ranged GET, up to `max_resumes` times.
+    /// The file is checked against the hex-encoded SHA-256 hash of the uploaded file, when known.
+    ///
+    /// # Returns
+    /// The size of the file in bytes
+    #[tracing::instrument(name = "Download file from bucket", skip(self, writer))]
+    pub async fn download_file(
+        &self,
+        object_path_name: &str,
+        writer: &mut impl Write,
+        max_resumes: u32,
+        expected_content_hash: Option<&str>,
+    ) -> Result<u64, S3RepositoryError> {
+        download_resuming(
+            |offset| self.get_file_stream_from(object_path_name, offset),
+            writer,
+            max_resumes,
+            expected_content_hash,
+        )
+        .await
+    }
 
     /// Get a file from a bucket in the object storage
     ///
@@ -61,10 +166,238 @@ impl S3Repository {
     /// The name (not the full path) of the file given on the object storage
     #[tracing::instrument(name = "Get file from bucket", skip(self))]
     pub async fn get_file(&self, object_path_name: &str) -> Result<Vec<u8>, S3RepositoryError> {
-        let response = self.bucket.get_object(object_path_name).await?;
+        let response =
+            self.bucket
+                .get_object(object_path_name)
+                .await
+                .map_err(|error| match error {
+                    s3::error::S3Error::Http(404, _) => {
+                        S3RepositoryError::ObjectNotFound(object_path_name.to_string())
+                    }
+                    _ => S3RepositoryError::Other(error),
+                })?;
         // Check stream status
         info!("🦄 Get from bucket response: {}", response.status_code());
 
         Ok(response.to_vec())
     }
+
+    /// Saves a file to a bucket in the object storage, replacing the file at the same path
+    ///
+    /// # Arguments
+    /// * `object_path_name` - The path (with the object name) of the file to save
+    /// * `content` - The content of the file
+    #[tracing::instrument(name = "Put file to bucket", skip(self, content))]
+    pub async fn put_file(
+        &self,
+        object_path_name: &str,
+        content: &[u8],
+    ) -> Result<(), S3RepositoryError> {
+        let response = self.bucket.put_object(object_path_name, content).await?;
+        info!("🦄 Put to bucket response: {}", response.status_code());
+
+        Ok(())
+    }
+}
+
+/// Reads the streams of a file into a writer, opening a stream from the received bytes after a transient failure
+async fn download_resuming<S: AsyncRead + Unpin>(
+    mut open_stream: impl FnMut(u64) -> S,
+    writer: &mut impl Write,
+    max_resumes: u32,
+    expected_content_hash: Option<&str>,
+) -> Result<u64, S3RepositoryError> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; DOWNLOAD_CHUNK_SIZE];
+    let mut size_bytes = 0;
+    let mut nb_resumes = 0;
+    let mut file_stream = open_stream(0);
+
+    loop {
+        let nb_read = match file_stream.read(&mut buf).await {
+            Ok(0) => break,
+            Ok(nb_read) => nb_read,
+            Err(error) => {
+                let error = S3RepositoryError::from(error);
+                if nb_resumes >= max_resumes || !error.is_transient() {
+                    return Err(error);
+                }
+
+                nb_resumes += 1;
+                warn!(
+                    ?error,
+                    size_bytes,
+                    nb_resumes,
+                    "Download interrupted, resuming from the received bytes"
+                );
+                file_stream = open_stream(size_bytes);
+                continue;
+            }
+        };
+
+        writer.write_all(&buf[..nb_read])?;
+        hasher.update(&buf[..nb_read]);
+        size_bytes += nb_read as u64;
+    }
+
+    if let Some(expected_content_hash) = expected_content_hash {
+        let content_hash = hex::encode(hasher.finalize());
+        if content_hash != expected_content_hash {
+            return Err(S3RepositoryError::ChecksumMismatch {
+                expected: expected_content_hash.to_string(),
+                actual: content_hash,
+            });
+        }
+    }
+
+    Ok(size_bytes)
+}
+
+/// Stream of a file being downloaded from the object storage
+pub struct S3FileStream {
+    reader: DuplexStream,
+    /// Download task writing the file into the stream, until its outcome is known
+    download: Option<JoinHandle<Result<(), S3RepositoryError>>>,
+}
+
+impl AsyncRead for S3FileStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let nb_filled = buf.filled().len();
+
+        ready!(Pin::new(&mut this.reader).poll_read(cx, buf))?;
+        if buf.filled().len() > nb_filled {
+            return Poll::Ready(Ok(()));
+        }
+
+        // End of the stream: complete only if the download succeeded
+        let Some(download) = this.download.as_mut() else {
+            return Poll::Ready(Ok(()));
+        };
+        let result = ready!(Pin::new(download).poll(cx));
+        this.download = None;
+
+        Poll::Ready(match result {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(error)) => Err(io::Error::other(error)),
+            Err(error) => Err(io::Error::other(error)),
+        })
+    }
+}
+
+impl Drop for S3FileStream {
+    fn drop(&mut self) {
+        if let Some(download) = &self.download {
+            download.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stream of the bytes of a file from an offset, failing after a given number of bytes
+    struct FlakyStream {
+        remaining: Vec<u8>,
+        nb_bytes_before_failure: Option<usize>,
+    }
+
+    impl AsyncRead for FlakyStream {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            let this = self.get_mut();
+            let mut nb_bytes = this.remaining.len().min(buf.remaining());
+
+            if let Some(nb_bytes_before_failure) = this.nb_bytes_before_failure.as_mut() {
+                if *nb_bytes_before_failure == 0 {
+                    return Poll::Ready(Err(io::Error::from(io::ErrorKind::ConnectionReset)));
+                }
+                nb_bytes = nb_bytes.min(*nb_bytes_before_failure);
+                *nb_bytes_before_failure -= nb_bytes;
+            }
+
+            buf.put_slice(&this.remaining[..nb_bytes]);
+            this.remaining.drain(..nb_bytes);
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Opens streams of a file, the first ones failing after 3 bytes
+    fn flaky_streams(
+        file: &[u8],
+        nb_failing_streams: usize,
+    ) -> impl FnMut(u64) -> FlakyStream + '_ {
+        let mut nb_opened = 0;
+
+        move |offset: u64| {
+            nb_opened += 1;
+            FlakyStream {
+                remaining: file[offset as usize..].to_vec(),
+                nb_bytes_before_failure: (nb_opened <= nb_failing_streams).then_some(3),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn download_resumes_from_the_received_bytes() {
+        let file = b"It was a bright cold day in April".to_vec();
+        let content_hash = hex::encode(Sha256::digest(&file));
+        let mut offsets = vec![];
+        let mut open_stream = flaky_streams(&file, 2);
+        let mut downloaded = vec![];
+
+        let size_bytes = download_resuming(
+            |offset| {
+                offsets.push(offset);
+                open_stream(offset)
+            },
+            &mut downloaded,
+            2,
+            Some(&content_hash),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(offsets, vec![0, 3, 6]);
+        assert_eq!(size_bytes, file.len() as u64);
+        assert_eq!(downloaded, file);
+    }
+
+    #[tokio::test]
+    async fn download_fails_once_out_of_resumes() {
+        let file = b"It was a bright cold day in April".to_vec();
+        let open_stream = flaky_streams(&file, 2);
+
+        let result = download_resuming(open_stream, &mut vec![], 1, None).await;
+
+        assert!(matches!(result, Err(S3RepositoryError::IOError(_))));
+    }
+
+    #[tokio::test]
+    async fn download_not_matching_the_uploaded_file_is_rejected() {
+        let file = b"It was a bright cold day in April".to_vec();
+        let open_stream = flaky_streams(&file, 0);
+
+        let result = download_resuming(
+            open_stream,
+            &mut vec![],
+            0,
+            Some(&hex::encode(Sha256::digest(b""))),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(S3RepositoryError::ChecksumMismatch { .. })
+        ));
+        assert!(result.unwrap_err().is_transient());
+    }
 }
@@ -0,0 +1,266 @@
+//! Self-test of the reader stack, run with `content_ingestion_worker --self-test`
+//!
+//! Runs a corpus bundled in the binary through the readers and the chunker, and checks the extracted contents
+//! against their expected number and hash. It needs no configuration, broker or object storage:
+//! a quick sanity check of a package, or of a dependency upgrade, where the worker is deployed.
+
+use common::helper::error_chain_fmt;
+use genawaiter::GeneratorState;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::io::{Cursor, Read};
+
+use crate::domain::{
+    entities::meta_read::MetaRead,
+    extractors::extract_content_generator::{
+        extract_content_generator, ChunkSplitting, ExtractContentGeneratorError,
+    },
+    readers::{
+        code_reader::{CodeReader, CodeReaderError},
+        epub_reader::{EpubReader, EpubReaderError},
+        html_reader::{HtmlReader, HtmlReaderError},
+        latex_reader::{LatexMathFormat, LatexReader, LatexReaderError},
+        notebook_reader::{NotebookReader, NotebookReaderError},
+        subtitle_reader::{SubtitleReader, SubtitleReaderError},
+        xml_reader,
+    },
+    splitters::tree_sitter_code_splitter::TreeSitterCodeSplitter,
+};
+
+/// Argument of the binary running the self-test instead of the worker
+pub const SELF_TEST_ARG: &str = "--self-test";
+
+/// Same number of words per content as the extraction jobs
+const NB_WORDS_PER_CONTENT: usize = 100;
+
+#[derive(Debug, Clone, Copy)]
+enum FixtureKind {
+    Epub,
+    Subtitles,
+    Notebook,
+    Latex,
+    Html,
+    Code,
+}
+
+struct Fixture {
+    name: &'static str,
+    kind: FixtureKind,
+    data: &'static [u8],
+    expected_nb_contents: usize,
+    /// Hex-encoded SHA-256 hash of the extracted contents, with their metadata
+    expected_hash: &'static str,
+}
+
+const FIXTURES: &[Fixture] = &[
+    Fixture {
+        name: "minimal_sample.epub",
+        kind: FixtureKind::Epub,
+        data: include_bytes!("../tests/resources/minimal_sample.epub"),
+        expected_nb_contents: 19,
+        expected_hash: "1b4c6e96714b30231c00ea7b8e6c6b740e70962cea4739208e931aec4fcadb68",
+    },
+    Fixture {
+        name: "sample_3_chapters.epub",
+        kind: FixtureKind::Epub,
+        data: include_bytes!("../tests/resources/sample_3_chapters.epub"),
+        expected_nb_contents: 3,
+        expected_hash: "177b028f81b1c8da97331ce7f571b5eef5a69d286d6bbcd1d3173358b6e2f568",
+    },
+    Fixture {
+        name: "sample.srt",
+        kind: FixtureKind::Subtitles,
+        data: include_bytes!("../self_test_corpus/sample.srt"),
+        expected_nb_contents: 1,
+        expected_hash: "3de1365d7781e8b54dd467bb9fb861bb2c265e02656f7e3648ee607d816ed037",
+    },
+    Fixture {
+        name: "sample.vtt",
+        kind: FixtureKind::Subtitles,
+        data: include_bytes!("../self_test_corpus/sample.vtt"),
+        expected_nb_contents: 1,
+        expected_hash: "d8fbdddd1eeb6888c35c4fd4a3179fdc140bf237243884adedabe9d954c80016",
+    },
+    Fixture {
+        name: "sample.ipynb",
+        kind: FixtureKind::Notebook,
+        data: include_bytes!("../self_test_corpus/sample.ipynb"),
+        expected_nb_contents: 2,
+        expected_hash: "f3aeba85ef48b4d758d1704e841e61e0a10dfdaa47ba2336c2e0698dc85a4334",
+    },
+    Fixture {
+        name: "sample.tex",
+        kind: FixtureKind::Latex,
+        data: include_bytes!("../self_test_corpus/sample.tex"),
+        expected_nb_contents: 2,
+        expected_hash: "483ac88a461c637c866abec8892fbbf2986dbb7a687dbafa2ffef803faa40689",
+    },
+    Fixture {
+        name: "sample.html",
+        kind: FixtureKind::Html,
+        data: include_bytes!("../self_test_corpus/sample.html"),
+        expected_nb_contents: 1,
+        expected_hash: "2806c917c1fbb624f14b74ebadb78de474416840fdb73f79826e75edda6d9dba",
+    },
+    Fixture {
+        name: "sample.py",
+        kind: FixtureKind::Code,
+        data: include_bytes!("../self_test_corpus/sample.py"),
+        expected_nb_contents: 2,
+        expected_hash: "0be088e3e322729aef9e21dd04523ad29b8105cdc50b76345391e3f9a046017d",
+    },
+];
+
+#[derive(Debug, PartialEq)]
+pub enum FixtureOutcome {
+    Passed,
+    Mismatch { nb_contents: usize, hash: String },
+    Failed(String),
+}
+
+#[derive(Debug)]
+pub struct FixtureResult {
+    pub name: &'static str,
+    pub outcome: FixtureOutcome,
+}
+
+impl std::fmt::Display for FixtureResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.outcome {
+            FixtureOutcome::Passed => write!(f, "ok     {}", self.name),
+            FixtureOutcome::Mismatch { nb_contents, hash } => write!(
+                f,
+                "FAILED {}: {} contents with hash {}",
+                self.name, nb_contents, hash
+            ),
+            FixtureOutcome::Failed(error) => write!(f, "FAILED {}: {}", self.name, error),
+        }
+    }
+}
+
+/// Runs the bundled corpus through the reader stack
+pub fn run_self_test() -> Vec<FixtureResult> {
+    FIXTURES
+        .iter()
+        .map(|fixture| {
+            let outcome = match extract_fixture(fixture) {
+                Ok((nb_contents, hash))
+                    if nb_contents == fixture.expected_nb_contents
+                        && hash == fixture.expected_hash =>
+                {
+                    FixtureOutcome::Passed
+                }
+                Ok((nb_contents, hash)) => FixtureOutcome::Mismatch { nb_contents, hash },
+                Err(error) => FixtureOutcome::Failed(error.to_string()),
+            };
+
+            FixtureResult {
+                name: fixture.name,
+                outcome,
+            }
+        })
+        .collect()
+}
+
+/// Reads a fixture with the readers of its kind, like an extraction job without image OCR
+///
+/// # Returns
+/// The number of extracted contents, and their hash
+fn extract_fixture(fixture: &Fixture) -> Result<(usize, String), SelfTestError> {
+    let data = Cursor::new(fixture.data);
+    let initial_metadata = Some(json!({ "source_initial_name": fixture.name }));
+
+    match fixture.kind {
+        FixtureKind::Epub => {
+            let epub_reader = EpubReader::from_reader(data, initial_metadata)?;
+            digest_contents(&mut xml_reader::build_from_reader(epub_reader))
+        }
+        FixtureKind::Subtitles => digest_contents(&mut SubtitleReader::try_from_reader(
+            data,
+            initial_metadata,
+            None,
+        )?),
+        FixtureKind::Notebook => digest_contents(&mut NotebookReader::try_from_reader(
+            data,
+            initial_metadata,
+            true,
+        )?),
+        FixtureKind::Latex => digest_contents(&mut LatexReader::try_from_reader(
+            data,
+            initial_metadata,
+            LatexMathFormat::Raw,
+        )?),
+        FixtureKind::Html => {
+            digest_contents(&mut HtmlReader::try_from_reader(data, initial_metadata)?)
+        }
+        FixtureKind::Code => digest_contents(&mut CodeReader::try_from_reader(
+            data,
+            fixture.name,
+            initial_metadata,
+            &TreeSitterCodeSplitter::new(),
+        )?),
+    }
+}
+
+/// Extracts all the contents of a reader, and hashes them with their metadata
+fn digest_contents<ReaderType: Read + MetaRead>(
+    reader: &mut ReaderType,
+) -> Result<(usize, String), SelfTestError> {
+    let mut generator =
+        extract_content_generator(reader, Some(NB_WORDS_PER_CONTENT), ChunkSplitting::Words);
+    let mut hasher = Sha256::new();
+    let mut nb_contents = 0;
+
+    loop {
+        match generator.as_mut().resume() {
+            GeneratorState::Yielded(content) => {
+                hasher.update(content.content.as_bytes());
+                hasher.update(content.metadata.to_string().as_bytes());
+                hasher.update([content.is_code as u8, content.skip_embedding as u8]);
+                nb_contents += 1;
+            }
+            GeneratorState::Complete(result) => {
+                result?;
+                break;
+            }
+        }
+    }
+
+    Ok((nb_contents, hex::encode(hasher.finalize())))
+}
+
+#[derive(thiserror::Error)]
+pub enum SelfTestError {
+    #[error(transparent)]
+    ExtractContentGeneratorError(#[from] ExtractContentGeneratorError),
+    #[error(transparent)]
+    EpubReaderError(#[from] EpubReaderError),
+    #[error(transparent)]
+    SubtitleReaderError(#[from] SubtitleReaderError),
+    #[error(transparent)]
+    NotebookReaderError(#[from] NotebookReaderError),
+    #[error(transparent)]
+    LatexReaderError(#[from] LatexReaderError),
+    #[error(transparent)]
+    HtmlReaderError(#[from] HtmlReaderError),
+    #[error(transparent)]
+    CodeReaderError(#[from] CodeReaderError),
+}
+
+impl std::fmt::Debug for SelfTestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_corpus_passes_the_self_test() {
+        for result in run_self_test() {
+            assert_eq!(result.outcome, FixtureOutcome::Passed, "{}", result);
+        }
+    }
+}
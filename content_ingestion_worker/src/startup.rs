@@ -1,12 +1,44 @@
-use std::sync::Arc;
+use std::{net::TcpListener, sync::Arc};
 
 use crate::{
-    configuration::{ObjectStorageSettings, RabbitMQSettings, Settings},
-    handlers::handler_extract_content_job::{self, RegisterHandlerExtractContentJobError},
+    configuration::{ObjectStorageSettings, RabbitMQSettings, ReloadableSettings, Settings},
+    domain::{
+        enrichment::{
+            content_enricher::ContentEnricher,
+            token_classification_entity_recognizer::TokenClassificationEntityRecognizer,
+        },
+        entities::{
+            code_splitter::CodeSplitter, entity_recognizer::EntityRecognizer, image_ocr::ImageOcr,
+        },
+        ocr::tesseract_cli_ocr::TesseractCliOcr,
+        redaction::pii_redactor::PiiRedactor,
+        splitters::tree_sitter_code_splitter::TreeSitterCodeSplitter,
+    },
+    handlers::handler_extract_content_job::{
+        self, HandlerSettings, ReaderServices, RegisterHandlerExtractContentJobError,
+    },
     repositories::source_file_s3_repository::S3Repository,
 };
-use common::core::rabbitmq_message_repository::RabbitMQMessageRepository;
-use futures::{future::join_all, TryFutureExt};
+use common::{
+    core::{
+        consumer_handover::{ConsumerHandover, ConsumerHandoverError},
+        handled_message_postgres_repository::HandledMessagePostgresRepository,
+        health_server::{
+            run_health_server, DependencyCheck, HealthChecks, RabbitMQConnectionCheck,
+        },
+        idempotency_store::{
+            IdempotencySettings, IdempotencyStoreError, IdempotencyStorePort, NoOpIdempotencyStore,
+        },
+        message_signing::{MessageSigner, MessageSigningError},
+        normalization_rules::{watch_normalization_rules_changes, NormalizationRulesCache},
+        rabbitmq_message_repository::RabbitMQMessageRepository,
+    },
+    dtos::extract_content_job::IngestionLaneDto,
+};
+use futures::{
+    future::{join_all, BoxFuture},
+    TryFutureExt,
+};
 use lapin::Connection as RabbitMQConnection;
 use s3::{creds::Credentials, Bucket, BucketConfiguration, Region};
 use secrecy::ExposeSecret;
@@ -19,11 +51,19 @@ pub struct Application {
     rabbitmq_publishing_connection: Arc<RabbitMQConnection>,
     rabbitmq_content_exchange_name: String,
     rabbitmq_queue_name_prefix: String,
+    // Only the instance holding the lease consumes messages
+    consumer_handover: ConsumerHandover,
 
     // S3
     // Used for integration tests
     s3_bucket: Bucket,
 
+    // Port of the health probes (and memory debug endpoints), useful when binding a random port
+    health_port: u16,
+
+    // Tunables of the running handlers, updated when the configuration is reloaded
+    reloadable_settings: Arc<ReloadableSettings>,
+
     // handlers: Vec<Box<dyn Future<Output = Result<(), ApplicationError>>>>,
     handlers: Vec<JoinHandle<Result<(), ApplicationError>>>,
 }
@@ -34,37 +74,157 @@ impl Application {
         let s3_bucket = set_up_s3(&settings.object_storage).await?;
 
         // TODO: handle connections with a re-connection strategy
-        // One connection for consuming messages, one for publishing messages
+        // One connection for consuming messages of each lane, one for publishing messages
         let rabbitmq_consuming_connection = get_rabbitmq_connection(&settings.rabbitmq).await?;
+        let rabbitmq_fast_lane_consuming_connection =
+            get_rabbitmq_connection(&settings.rabbitmq).await?;
         let rabbitmq_publishing_connection =
             Arc::new(get_rabbitmq_connection(&settings.rabbitmq).await?);
 
-        let rabbitmq_content_exchange_name = format!(
-            "{}_{}",
-            settings.rabbitmq.exchange_name_prefix, settings.rabbitmq.content_exchange
+        // Serves the probes before waiting for the consumer lease, for the instance not to be restarted meanwhile
+        let health_checks = HealthChecks::new()
+            .with_liveness_check(
+                "rabbitmq_consuming",
+                RabbitMQConnectionCheck::new(&rabbitmq_consuming_connection),
+            )
+            .with_liveness_check(
+                "rabbitmq_fast_lane_consuming",
+                RabbitMQConnectionCheck::new(&rabbitmq_fast_lane_consuming_connection),
+            )
+            .with_liveness_check(
+                "rabbitmq_publishing",
+                RabbitMQConnectionCheck::new(&rabbitmq_publishing_connection),
+            )
+            .with_readiness_check("s3", S3BucketCheck(s3_bucket.clone()));
+        let listener = TcpListener::bind(format!(
+            "{}:{}",
+            settings.application.host, settings.application.port
+        ))?;
+        let health_port = listener.local_addr()?.port();
+        let memory_debug = settings
+            .memory
+            .debug_endpoints
+            .then(|| settings.memory.clone());
+
+        tokio::spawn(
+            run_health_server(listener, health_checks, memory_debug).inspect_err(|error| {
+                error!(?error, "Health server stopped");
+            }),
         );
 
+        let rabbitmq_content_exchange_name = settings.rabbitmq.content_exchange_name();
+        let reloadable_settings = Arc::new(ReloadableSettings::new(&settings));
+
         let message_rabbitmq_repository = RabbitMQMessageRepository::new(
             rabbitmq_publishing_connection.clone(),
             &rabbitmq_content_exchange_name,
-        );
+        )
+        .with_signer(MessageSigner::try_new(settings.message_signing)?)
+        .with_publisher_confirms(settings.rabbitmq.publisher_confirms);
+
+        // Waits for the previous instance, if any, to hand over the consumption
+        let mut consumer_handover =
+            ConsumerHandover::new(settings.handover, &rabbitmq_content_exchange_name);
+        consumer_handover
+            .acquire(&rabbitmq_publishing_connection)
+            .await?;
+        let stop_consuming = consumer_handover.stop_consuming_token();
 
         let s3_repository = S3Repository::new(s3_bucket.clone());
         // Sharing the same S3 repository with parallel handlers/threads
         let s3_repository = Arc::new(s3_repository);
 
+        let code_splitter: Arc<dyn CodeSplitter> = Arc::new(TreeSitterCodeSplitter::new());
+
+        // The rules of the tenant are cached until the gateway publishes a change
+        let normalization_rules = Arc::new(NormalizationRulesCache::new(
+            settings.normalization_rules.clone(),
+        ));
+        if settings.normalization_rules.enabled {
+            watch_normalization_rules_changes(
+                &rabbitmq_publishing_connection,
+                &rabbitmq_content_exchange_name,
+                normalization_rules.clone(),
+            )
+            .await?;
+        }
+
+        let image_ocr_settings = &settings.extraction.image_ocr;
+        let image_ocr: Option<Arc<dyn ImageOcr>> = if image_ocr_settings.enabled {
+            Some(Arc::new(TesseractCliOcr::new(
+                &image_ocr_settings.tesseract_command,
+                &image_ocr_settings.language,
+            )))
+        } else {
+            None
+        };
+
+        let enrichment_settings = &settings.extraction.enrichment;
+        let entity_recognizer: Option<Arc<dyn EntityRecognizer>> =
+            if enrichment_settings.entity_recognition.enabled {
+                Some(Arc::new(TokenClassificationEntityRecognizer::try_new(
+                    &enrichment_settings.entity_recognition,
+                )?))
+            } else {
+                None
+            };
+        let content_enricher = Arc::new(ContentEnricher::new(
+            enrichment_settings.max_keywords,
+            entity_recognizer,
+        ));
+
+        let pii_redactor = Arc::new(PiiRedactor::try_new(
+            &settings.extraction.pii_redaction.patterns,
+        )?);
+
+        // Extraction jobs delivered again once extracted are acknowledged without being extracted again
+        let idempotency_store = get_idempotency_store(&settings.idempotency)?;
+
         let mut app = Self {
             rabbitmq_publishing_connection,
             rabbitmq_content_exchange_name,
-            rabbitmq_queue_name_prefix: settings.rabbitmq.queue_name_prefix,
+            rabbitmq_queue_name_prefix: settings.rabbitmq.tenant_queue_name_prefix(),
+            consumer_handover,
             s3_bucket,
+            health_port,
+            reloadable_settings: reloadable_settings.clone(),
             handlers: vec![],
         };
 
+        let bulk_lane_handler_settings = HandlerSettings {
+            extraction: settings.extraction,
+            retry_policy: settings.retry,
+            memory: settings.memory.clone(),
+            fair_share: settings.fair_share,
+            prefetch_count: reloadable_settings.prefetch_count(IngestionLaneDto::Bulk),
+            stop_consuming,
+            lane: IngestionLaneDto::Bulk,
+        };
+        // A low prefetch, for a small source not to wait behind the ones prefetched by a busy consumer
+        let fast_lane_handler_settings = HandlerSettings {
+            prefetch_count: reloadable_settings.prefetch_count(IngestionLaneDto::Fast),
+            lane: IngestionLaneDto::Fast,
+            ..bulk_lane_handler_settings.clone()
+        };
+
         app.prepare_message_handlers(
-            rabbitmq_consuming_connection,
+            vec![
+                (rabbitmq_consuming_connection, bulk_lane_handler_settings),
+                (
+                    rabbitmq_fast_lane_consuming_connection,
+                    fast_lane_handler_settings,
+                ),
+            ],
             message_rabbitmq_repository,
             s3_repository,
+            ReaderServices {
+                code_splitter,
+                image_ocr,
+                normalization_rules,
+                content_enricher,
+                pii_redactor,
+                idempotency_store,
+            },
         )
         .await?;
 
@@ -74,39 +234,44 @@ impl Application {
     /// Prepares the asynchronous tasks on which our message handlers will run.
     ///
     /// A "message handler" consumes messages from a (generated) queue bound to with a specific binding key to the given exchange
+    ///
+    /// # Arguments
+    /// * `lane_handlers` - for each ingestion lane, the connection consuming its messages and the settings of its handler
     #[tracing::instrument(
         name = "Preparing the messages handlers",
         skip(
             self,
-            rabbitmq_consuming_connection,
+            lane_handlers,
             message_rabbitmq_repository,
             s3_repository,
+            reader_services,
         )
     )]
     pub async fn prepare_message_handlers(
         &mut self,
-        rabbitmq_consuming_connection: RabbitMQConnection,
+        lane_handlers: Vec<(RabbitMQConnection, HandlerSettings)>,
         message_rabbitmq_repository: RabbitMQMessageRepository,
         s3_repository: Arc<S3Repository>,
+        reader_services: ReaderServices,
     ) -> Result<(), ApplicationError> {
-        let s3_repository = s3_repository.clone();
-        let exchange_name = self.rabbitmq_content_exchange_name.clone();
-        let queue_name_prefix = self.rabbitmq_queue_name_prefix.clone();
-
         // We could have several message handlers running in parallel bound with the same binding key to the same exchange.
         // Or other message handlers bound with a different binding key to the same or another exchange.
-        let handler = tokio::spawn(
-            handler_extract_content_job::register_handler(
-                rabbitmq_consuming_connection,
-                exchange_name,
-                queue_name_prefix,
-                s3_repository,
-                message_rabbitmq_repository.clone(),
-            )
-            .map_err(|e| e.into()),
-        );
+        for (rabbitmq_consuming_connection, handler_settings) in lane_handlers {
+            let handler = tokio::spawn(
+                handler_extract_content_job::register_handler(
+                    rabbitmq_consuming_connection,
+                    self.rabbitmq_content_exchange_name.clone(),
+                    self.rabbitmq_queue_name_prefix.clone(),
+                    s3_repository.clone(),
+                    message_rabbitmq_repository.clone(),
+                    handler_settings,
+                    reader_services.clone(),
+                )
+                .map_err(|e| e.into()),
+            );
 
-        self.handlers.push(handler);
+            self.handlers.push(handler);
+        }
 
         Ok(())
     }
@@ -115,7 +280,7 @@ impl Application {
     ///
     /// self is moved in order for the application not to drop out of scope
     /// and move into a thread for ex
-    pub async fn run_until_stopped(self) -> Result<(), ApplicationError> {
+    pub async fn run_until_stopped(mut self) -> Result<(), ApplicationError> {
         let handler_results = join_all(self.handlers).await;
 
         info!(
@@ -123,6 +288,9 @@ impl Application {
             handler_results
         );
 
+        // In-flight messages have been handled: the next instance can start consuming
+        self.consumer_handover.release().await;
+
         info!("👋 Bye!");
         Ok(())
     }
@@ -130,6 +298,30 @@ impl Application {
     pub fn s3_bucket(&self) -> Bucket {
         self.s3_bucket.clone()
     }
+
+    pub fn health_port(&self) -> u16 {
+        self.health_port
+    }
+
+    /// Applies the tunables of reloaded settings to the running handlers
+    pub fn reloadable_settings(&self) -> Arc<ReloadableSettings> {
+        self.reloadable_settings.clone()
+    }
+}
+
+/// S3 object storage of the worker: the source files are read from its bucket
+struct S3BucketCheck(Bucket);
+
+impl DependencyCheck for S3BucketCheck {
+    fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(async move {
+            self.0
+                .location()
+                .await
+                .map(|_| ())
+                .map_err(|error| error.to_string())
+        })
+    }
 }
 
 /// Creates a connection to RabbitMQ
@@ -139,6 +331,30 @@ pub async fn get_rabbitmq_connection(
     RabbitMQConnection::connect(&config.get_uri(), config.get_connection_properties()).await
 }
 
+/// Gets the store of the handled messages, remembering no message without idempotency
+///
+/// The expired handled messages are forgotten periodically.
+pub fn get_idempotency_store(
+    settings: &IdempotencySettings,
+) -> Result<Arc<dyn IdempotencyStorePort>, ApplicationError> {
+    if !settings.enabled {
+        return Ok(Arc::new(NoOpIdempotencyStore));
+    }
+
+    let database_url = settings.database_url.as_ref().ok_or_else(|| {
+        ApplicationError::ConfigurationError(
+            "idempotency.database_url should be set when the idempotency is enabled".to_string(),
+        )
+    })?;
+    let repository = HandledMessagePostgresRepository::try_new(database_url)?;
+    tokio::spawn(repository.clone().run_purge(
+        chrono::Duration::hours(settings.retention_h.into()),
+        std::time::Duration::from_millis(settings.purge_interval_ms),
+    ));
+
+    Ok(Arc::new(repository))
+}
+
 /// Sets up the S3 object storage
 ///
 /// Each environment will use 1 bucket.
@@ -206,4 +422,16 @@ pub enum ApplicationError {
     RabbitMQError(#[from] lapin::Error),
     #[error(transparent)]
     ContentExtractJobError(#[from] RegisterHandlerExtractContentJobError),
+    #[error(transparent)]
+    ConsumerHandoverError(#[from] ConsumerHandoverError),
+    #[error(transparent)]
+    MessageSigningError(#[from] MessageSigningError),
+    #[error("Entity recognition client error: {0}")]
+    EntityRecognitionClientError(#[from] reqwest::Error),
+    #[error("Invalid PII redaction pattern: {0}")]
+    PiiRedactionPatternError(#[from] regex::Error),
+    #[error("Invalid configuration: {0}")]
+    ConfigurationError(String),
+    #[error(transparent)]
+    IdempotencyStoreError(#[from] IdempotencyStoreError),
 }
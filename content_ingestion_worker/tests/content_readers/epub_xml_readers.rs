@@ -5,7 +5,9 @@ use std::io::BufReader;
 use std::io::Write;
 use uuid::Uuid;
 
-use content_ingestion_worker::domain::extractors::extract_content_generator::extract_content_generator;
+use content_ingestion_worker::domain::extractors::extract_content_generator::{
+    extract_content_generator, ChunkSplitting,
+};
 use content_ingestion_worker::domain::readers::{epub_reader::EpubReader, xml_reader};
 
 use crate::helpers::init_test;
@@ -23,7 +25,11 @@ fn on_correct_epub_it_should_be_able_to_extract_expected_contents() {
     let mut xml_reader = xml_reader::build_from_reader(epub_reader);
 
     let nb_words_per_content = 100;
-    let mut generator = extract_content_generator(&mut xml_reader, Some(nb_words_per_content));
+    let mut generator = extract_content_generator(
+        &mut xml_reader,
+        Some(nb_words_per_content),
+        ChunkSplitting::Words,
+    );
 
     let mut is_extraction_completed = false;
 
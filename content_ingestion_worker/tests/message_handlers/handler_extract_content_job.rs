@@ -1,6 +1,11 @@
 use common::{
     constants::routing_keys::CONTENT_EXTRACTED_ROUTING_KEY,
-    dtos::extract_content_job::{ExtractContentJobDto, SourceTypeDto},
+    dtos::{
+        extract_content_job::{
+            ChunkSplittingDto, ExtractContentJobDto, IngestionLaneDto, SourceTypeDto,
+        },
+        templates::message_envelope::MessageEnvelope,
+    },
 };
 use futures::lock::Mutex;
 use std::sync::Arc;
@@ -42,11 +47,22 @@ async fn handler_binds_queue_to_exchange_and_acknowledges_extract_content_job_wh
             )
         });
 
+    let user_id = Uuid::new_v4();
     let job = ExtractContentJobDto {
         source_meta_id: Uuid::new_v4(),
         source_type: SourceTypeDto::Epub,
-        object_store_path_name: format!("{}/{}", Uuid::new_v4(), "test.epub"),
+        object_store_path_name: format!("{}/{}", user_id, "test.epub"),
         source_initial_name: "test.epub".to_string(),
+        user_id: Some(user_id),
+        tenant_id: None,
+        fulltext_shard: 0,
+        content_hash: None,
+        lane: IngestionLaneDto::Bulk,
+        chunk_splitting: ChunkSplittingDto::Words,
+        column_mapping: Default::default(),
+        incremental: false,
+        source_added_at: None,
+        redact_pii: false,
     };
 
     // Adding the associated test file to the S3 bucket
@@ -56,7 +72,7 @@ async fn handler_binds_queue_to_exchange_and_acknowledges_extract_content_job_wh
         .await
         .unwrap();
 
-    let job = serde_json::to_string(&job).unwrap();
+    let job = MessageEnvelope::new(job).try_serializing().unwrap();
 
     // Sends the job message to the worker binding key
     let routing_key = ROUTING_KEY;
@@ -117,13 +133,24 @@ async fn handler_negative_acknowledges_extract_content_job_when_file_not_in_s3()
             )
         });
 
+    let user_id = Uuid::new_v4();
     let job = ExtractContentJobDto {
         source_meta_id: Uuid::new_v4(),
         source_type: SourceTypeDto::Epub,
-        object_store_path_name: format!("{}/{}", Uuid::new_v4(), "test.epub"),
+        object_store_path_name: format!("{}/{}", user_id, "test.epub"),
         source_initial_name: "test.epub".to_string(),
+        user_id: Some(user_id),
+        tenant_id: None,
+        fulltext_shard: 0,
+        content_hash: None,
+        lane: IngestionLaneDto::Bulk,
+        chunk_splitting: ChunkSplittingDto::Words,
+        column_mapping: Default::default(),
+        incremental: false,
+        source_added_at: None,
+        redact_pii: false,
     };
-    let job = serde_json::to_string(&job).unwrap();
+    let job = MessageEnvelope::new(job).try_serializing().unwrap();
 
     let routing_key = ROUTING_KEY;
 
@@ -180,11 +207,22 @@ async fn handler_publishes_extract_contented_on_correct_job() {
     )
     .await;
 
+    let user_id = Uuid::new_v4();
     let job = ExtractContentJobDto {
         source_meta_id: Uuid::new_v4(),
         source_type: SourceTypeDto::Epub,
-        object_store_path_name: format!("{}/{}", Uuid::new_v4(), "test.epub"),
+        object_store_path_name: format!("{}/{}", user_id, "test.epub"),
         source_initial_name: "test.epub".to_string(),
+        user_id: Some(user_id),
+        tenant_id: None,
+        fulltext_shard: 0,
+        content_hash: None,
+        lane: IngestionLaneDto::Bulk,
+        chunk_splitting: ChunkSplittingDto::Words,
+        column_mapping: Default::default(),
+        incremental: false,
+        source_added_at: None,
+        redact_pii: false,
     };
 
     // Adding the associated test file to the S3 bucket
@@ -194,7 +232,7 @@ async fn handler_publishes_extract_contented_on_correct_job() {
         .await
         .unwrap();
 
-    let job = serde_json::to_string(&job).unwrap();
+    let job = MessageEnvelope::new(job).try_serializing().unwrap();
 
     // Sends the job message to the worker binding key
     let routing_key = ROUTING_KEY;
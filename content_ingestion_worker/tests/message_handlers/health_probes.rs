@@ -0,0 +1,31 @@
+use serde_json::Value as JsonValue;
+
+use crate::helpers::spawn_app;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn health_probes_report_the_dependencies_of_the_worker() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!("{}/health/live", &app.health_address))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert!(response.status().is_success());
+    let report: JsonValue = response.json().await.unwrap();
+    assert_eq!(report["checks"]["rabbitmq_publishing"]["status"], "ok");
+    assert!(report["checks"].get("s3").is_none());
+
+    let response = client
+        .get(format!("{}/health/ready", &app.health_address))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert!(response.status().is_success());
+    let report: JsonValue = response.json().await.unwrap();
+    assert_eq!(report["is_healthy"], true);
+    assert_eq!(report["checks"]["s3"]["status"], "ok");
+}
@@ -54,6 +54,9 @@ pub struct TestApp {
 
     /// S3 bucket used to setup tests thanks to requests to the S3 API
     pub s3_bucket: Bucket,
+
+    /// Base URL of the health probes of the worker
+    pub health_address: String,
 }
 
 #[derive(Debug)]
@@ -246,7 +249,14 @@ pub async fn spawn_app() -> TestApp {
 
     // Randomizes configuration to ensure test isolation: random prefix for our queue names
     let configuration = {
-        let mut c = get_configuration().expect("Failed to read configuration.");
+        let mut c = get_configuration()
+            .await
+            .expect("Failed to read configuration.");
+
+        // Binds a random port for the health probes, for the tests to run in parallel
+        c.application.port = 0;
+        // No gateway answers with the normalization rules
+        c.normalization_rules.enabled = false;
 
         // Uses a different exchange and queue names for each test case
         c.rabbitmq.exchange_name_prefix = format!(
@@ -285,6 +295,11 @@ pub async fn spawn_app() -> TestApp {
 
     // Gets the S3 bucket before spawning the application
     let s3_bucket = application.s3_bucket();
+    let health_address = format!(
+        "http://{}:{}",
+        configuration.application.host,
+        application.health_port()
+    );
 
     // RabbitMQ connection used by the test suite
     let rabbitmq_connection = get_rabbitmq_connection(&configuration.rabbitmq)
@@ -305,5 +320,6 @@ pub async fn spawn_app() -> TestApp {
         rabbitmq_channel,
         rabbitmq_management_api_config,
         s3_bucket,
+        health_address,
     }
 }
@@ -1,2 +1,3 @@
 pub mod handler_extract_content_job;
+pub mod health_probes;
 pub mod helpers;
@@ -0,0 +1,62 @@
+//! Embeddings generation throughput on standard fixtures
+//!
+//! Downloads the text model on the first run. Run with `cargo bench -p embedding_worker`.
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use tokio::runtime::Runtime;
+
+use embedding_worker::{
+    domain::services::embeddings_service::EmbeddingsService,
+    repositories::local_embedding_model::LocalEmbeddingModel,
+};
+
+/// Number of sentences of the embedded contents
+const CONTENT_NB_SENTENCES: [usize; 2] = [1, 10];
+
+const FIXTURE_SENTENCES: [&str; 4] = [
+    "The water cycle describes how water evaporates, condenses into clouds and falls back as rain.",
+    "Plants release water vapor into the atmosphere through a process called transpiration.",
+    "Rivers carry water from the mountains to the oceans, shaping the landscape on their way.",
+    "Groundwater is stored in aquifers and can take thousands of years to be renewed.",
+];
+
+fn fixture_content(nb_sentences: usize) -> String {
+    (0..nb_sentences)
+        .map(|i| FIXTURE_SENTENCES[i % FIXTURE_SENTENCES.len()])
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn embeddings_benchmark(c: &mut Criterion) {
+    // The local model blocks in place while sending to its runner: needs a multi-threaded runtime
+    let runtime = Runtime::new().unwrap();
+    let embeddings_service = EmbeddingsService::new(Box::new(LocalEmbeddingModel::new(None, None)));
+
+    // Waits for the model to be loaded, so it is not part of the measurements
+    runtime
+        .block_on(embeddings_service.generate_embeddings(FIXTURE_SENTENCES[0], None))
+        .expect("embeddings model to be loaded");
+
+    let mut group = c.benchmark_group("embeddings");
+    for nb_sentences in CONTENT_NB_SENTENCES {
+        let content = fixture_content(nb_sentences);
+
+        group.throughput(Throughput::Elements(nb_sentences as u64));
+        group.bench_with_input(
+            BenchmarkId::new("text_sentences", nb_sentences),
+            &content,
+            |b, content| {
+                b.to_async(&runtime).iter(|| async {
+                    embeddings_service
+                        .generate_embeddings(content, None)
+                        .await
+                        .unwrap()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, embeddings_benchmark);
+criterion_main!(benches);
@@ -1,12 +1,51 @@
+use common::core::{
+    configuration::{ensure_positive, ConfigurationError, ConfigurationLayers},
+    consumer_handover::HandoverSettings,
+    idempotency_store::IdempotencySettings,
+    memory_ceiling::MemorySettings,
+    message_signing::MessageSigningSettings,
+    normalization_rules::NormalizationRulesSettings,
+    provider_credentials::TenantProviderSettings,
+    rabbitmq_message_repository::{broker_uri, BrokerCredentials, PublisherConfirmsSettings},
+    secrets::SecretsSettings,
+    tenancy::{tenant_name_prefix, TenantSettings},
+};
 use lapin::ConnectionProperties;
+use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
 use serde_aux::field_attributes::deserialize_number_from_string;
+use sqlx::{
+    postgres::{PgConnectOptions, PgSslMode},
+    ConnectOptions,
+};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Settings {
     pub application: ApplicationSettings,
     pub rabbitmq: RabbitMQSettings,
     pub qdrant: QdrantSettings,
+    #[serde(default)]
+    pub vector_store: VectorStoreSettings,
+    #[serde(default)]
+    pub embeddings: EmbeddingsSettings,
+    pub memory: MemorySettings,
+    pub handover: HandoverSettings,
+    /// Signing of the published messages, and verification of the consumed ones
+    pub message_signing: MessageSigningSettings,
+    /// Normalization of the semantic search queries with the rules of the tenant
+    pub normalization_rules: NormalizationRulesSettings,
+    /// Summaries of the sources and of their chapters, generated once their extraction is completed
+    #[serde(default)]
+    pub summarization: SummarizationSettings,
+    /// Extracted contents already embedded, acknowledged without being embedded again when redelivered
+    #[serde(default)]
+    pub idempotency: IdempotencySettings,
+    /// Embeddings with the model provider brought by the tenant, instead of the configured backend
+    #[serde(default)]
+    pub tenant_provider: TenantProviderSettings,
+    /// Keys of the gateway, decrypting the API keys of the tenant
+    #[serde(default)]
+    pub secrets: Option<SecretsSettings>,
 }
 
 // TODO: do we need to define a host and port for the workers ?
@@ -19,8 +58,8 @@ pub struct ApplicationSettings {
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct RabbitMQSettings {
-    // pub username: String,
-    // pub password: Secret<String>,
+    #[serde(default)]
+    pub credentials: Option<BrokerCredentials>,
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub port: u16,
     pub host: String,
@@ -30,11 +69,44 @@ pub struct RabbitMQSettings {
     pub queue_name_prefix: String,
 
     pub content_exchange: String,
+    /// Tenant served by this deployment, isolated at the broker level. If not set, the shared exchanges and queues are used
+    #[serde(default)]
+    pub tenant: Option<TenantSettings>,
+
+    /// Number of messages delivered to a handler before being acknowledged
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub prefetch_count: u16,
+    /// Number of contents of the fast lane delivered to its handler before being acknowledged
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub fast_lane_prefetch_count: u16,
+
+    /// Whether the published messages wait for the broker to acknowledge them
+    #[serde(default)]
+    pub publisher_confirms: PublisherConfirmsSettings,
 }
 
 impl RabbitMQSettings {
     pub fn get_uri(&self) -> String {
-        format!("amqp://{}:{}", &self.host, &self.port)
+        let broker_uri = broker_uri(&self.host, self.port, self.credentials.as_ref());
+
+        match &self.tenant {
+            Some(tenant) => tenant.amqp_uri(&broker_uri),
+            None => broker_uri,
+        }
+    }
+
+    /// Name of the content exchange, templated with the tenant id
+    pub fn content_exchange_name(&self) -> String {
+        format!(
+            "{}_{}",
+            tenant_name_prefix(&self.exchange_name_prefix, self.tenant.as_ref()),
+            self.content_exchange
+        )
+    }
+
+    /// Prefix of the queue names, templated with the tenant id
+    pub fn tenant_queue_name_prefix(&self) -> String {
+        tenant_name_prefix(&self.queue_name_prefix, self.tenant.as_ref())
     }
 
     pub fn get_connection_properties(&self) -> ConnectionProperties {
@@ -57,13 +129,172 @@ pub struct QdrantSettings {
     pub collection_vector_size: u64,
 }
 
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct VectorStoreSettings {
+    /// Database of the content points: `qdrant` (default) or `pgvector`
+    #[serde(default)]
+    pub backend: VectorStoreBackend,
+    /// Postgres database of the `pgvector` backend
+    pub pgvector: Option<PgvectorSettings>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum VectorStoreBackend {
+    #[default]
+    Qdrant,
+    /// Postgres with the pgvector extension, for the small deployments not running Qdrant
+    Pgvector,
+}
+
+/// Postgres database with the pgvector extension
+///
+/// Its vectors have the size and the distance of the Qdrant collection settings.
+#[derive(Deserialize, Debug, Clone)]
+pub struct PgvectorSettings {
+    pub username: String,
+    pub password: Secret<String>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    pub host: String,
+    pub database_name: String,
+    // Determines if we demand the connection to be encrypted or not
+    pub require_ssl: bool,
+    /// Table of the content points, created if it does not exist
+    pub table: String,
+}
+
+impl PgvectorSettings {
+    pub fn with_db(&self) -> PgConnectOptions {
+        let ssl_mode = if self.require_ssl {
+            PgSslMode::Require
+        } else {
+            // Try an encrypted connection, fallback to unencrypted if it fails
+            PgSslMode::Prefer
+        };
+        let mut options = PgConnectOptions::new()
+            .host(&self.host)
+            .username(&self.username)
+            .password(self.password.expose_secret())
+            .port(self.port)
+            .ssl_mode(ssl_mode)
+            .database(&self.database_name);
+        // Lowers sqlx logs from INFO to TRACE level.
+        options.log_statements(tracing::log::LevelFilter::Trace);
+        options
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct EmbeddingsSettings {
+    /// Path to a local sentence embeddings model specialized in source code.
+    /// Its vectors should have the same size as the Qdrant collection ones.
+    /// Source code contents are embedded with the default model if not set.
+    pub code_model_path: Option<String>,
+    /// Path to a local multilingual sentence embeddings model, for the contents detected in another
+    /// language than English. Its vectors should have the same size as the Qdrant collection ones.
+    /// These contents are embedded with the default model if not set.
+    pub multilingual_model_path: Option<String>,
+    /// Backend generating the embeddings: `huggingface` (default), `remote` or `simulation`
+    #[serde(default)]
+    pub backend: EmbeddingsBackend,
+    /// Embeddings API of the `remote` backend
+    pub remote: Option<RemoteEmbeddingsSettings>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddingsBackend {
+    /// Models from Hugging Face, run locally
+    #[default]
+    HuggingFace,
+    /// Models served by a remote embeddings API
+    Remote,
+    /// Deterministic pseudo-embeddings seeded by the hash of the words, without any model.
+    /// For environments without access to the models, for ex demos or end-to-end tests.
+    Simulation,
+}
+
+/// Remote embeddings API following the OpenAI embeddings API
+///
+/// Its models should map to a vector space of the size of the Qdrant collection.
+#[derive(Deserialize, Debug, Clone)]
+pub struct RemoteEmbeddingsSettings {
+    /// Base URL of the API, for ex `https://api.openai.com/v1`
+    pub url: String,
+    /// Sent as a bearer token, if any
+    pub api_key: Option<Secret<String>>,
+    pub text_model: String,
+    /// Model of the source code contents. The text model if not set
+    pub code_model: Option<String>,
+    /// Model of the contents in another language than English. The text model if not set
+    pub multilingual_model: Option<String>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timeout_ms: u64,
+    /// Size of the embeddings requested from the models supporting it, for ex the OpenAI `text-embedding-3` models.
+    /// Not sent if not set: the models then generate embeddings of their own size
+    #[serde(default)]
+    pub dimensions: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct SummarizationSettings {
+    /// The sources are not summarized if false (default)
+    #[serde(default)]
+    pub enabled: bool,
+    /// Backend generating the summaries: `remote` (default) or `simulation`
+    #[serde(default)]
+    pub backend: SummarizationBackend,
+    /// Chat completions API of the `remote` backend
+    pub remote: Option<RemoteSummarizationSettings>,
+    /// Characters of a chapter kept to be summarized: the end of the longer chapters is not summarized
+    #[serde(default = "SummarizationSettings::default_max_chapter_chars")]
+    pub max_chapter_chars: usize,
+    /// Chapters of a source summarized: the contents of the next chapters are not summarized
+    #[serde(default = "SummarizationSettings::default_max_chapters")]
+    pub max_chapters: usize,
+}
+
+impl SummarizationSettings {
+    fn default_max_chapter_chars() -> usize {
+        20_000
+    }
+
+    fn default_max_chapters() -> usize {
+        50
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SummarizationBackend {
+    /// LLM served by a remote chat completions API
+    #[default]
+    Remote,
+    /// First sentences of the summarized text, without any model.
+    /// For environments without access to an LLM, for ex demos or end-to-end tests.
+    Simulation,
+}
+
+/// Remote LLM API following the OpenAI chat completions API
+#[derive(Deserialize, Debug, Clone)]
+pub struct RemoteSummarizationSettings {
+    /// Base URL of the API, for ex `https://api.openai.com/v1`
+    pub url: String,
+    /// Sent as a bearer token, if any
+    pub api_key: Option<Secret<String>>,
+    pub model: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timeout_ms: u64,
+}
+
 impl QdrantSettings {
     pub fn get_grpc_base_url(&self) -> String {
         format!("http://{}:{}", &self.host, &self.grpc_port)
     }
 }
 
-/// Extracts app settings from configuration files and env variables
+/// Extracts app settings from configuration files, env variables and command line flags
 ///
 /// `base.yml` should contain shared settings for all environments.
 /// A specific env file should be created for each environment: `develop.yml`,`local.yml` and `production.yml`
@@ -72,7 +303,8 @@ impl QdrantSettings {
 ///
 /// Settings are also taken from environment variables: with a prefix of APP and '__' as separator
 /// For ex: `APP_APPLICATION__PORT=5001 would set `Settings.application.port`
-pub fn get_configuration() -> Result<Settings, config::ConfigError> {
+/// And finally from the `--set` flags of the command line. For ex: `--set application.port=5001`
+pub async fn get_configuration() -> Result<Settings, ConfigurationError> {
     let base_path = std::env::current_dir().expect("Failed to determine the current directory");
     let configuration_directory = base_path.join("configuration");
 
@@ -81,23 +313,45 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
     let environment: Environment = std::env::var("APP_ENVIRONMENT")
         .unwrap_or_else(|_| "develop".into())
         .try_into()
-        .expect("Failed to parse APP_ENVIRONMENT.");
-    let environment_filename = format!("{}.yml", environment.as_str());
-
-    let settings = config::Config::builder()
-        .add_source(config::File::from(configuration_directory.join("base.yml")))
-        .add_source(config::File::from(
-            configuration_directory.join(environment_filename),
-        ))
-        // Adds in settings from environment variables (with a prefix of APP and '__' as separator)
-        .add_source(
-            config::Environment::with_prefix("APP")
-                .prefix_separator("_")
-                .separator("__"),
-        )
-        .build()?;
+        .map_err(ConfigurationError::InvalidEnvironment)?;
+
+    let settings: Settings =
+        ConfigurationLayers::new(configuration_directory, environment.as_str())
+            .with_cli_overrides(std::env::args().skip(1))?
+            .load()
+            .await?;
+    settings.validate()?;
 
-    settings.try_deserialize::<Settings>()
+    Ok(settings)
+}
+
+impl Settings {
+    /// Checks the settings that can not be checked by their deserialization
+    pub fn validate(&self) -> Result<(), ConfigurationError> {
+        ensure_positive("rabbitmq.prefetch_count", self.rabbitmq.prefetch_count)?;
+        ensure_positive(
+            "rabbitmq.fast_lane_prefetch_count",
+            self.rabbitmq.fast_lane_prefetch_count,
+        )?;
+
+        if self.tenant_provider.enabled {
+            // The credentials are the ones of the tenant of the exchange
+            if self.rabbitmq.tenant.is_none() {
+                return Err(ConfigurationError::invalid_setting(
+                    "tenant_provider.enabled",
+                    "should only be set for a deployment of a tenant, with rabbitmq.tenant",
+                ));
+            }
+            if self.secrets.is_none() {
+                return Err(ConfigurationError::invalid_setting(
+                    "secrets",
+                    "should be set to decrypt the API keys of the tenant",
+                ));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 /// The possible runtime environment for our application.
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 use uuid::Uuid;
 
 pub type Embeddings = Vec<f32>;
@@ -12,6 +13,22 @@ pub struct ContentPoint {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ContentPointPayload {
+    /// Extracted content the point was generated from, shared by all the points of its sentences
+    pub content_id: Uuid,
+    pub metadata: JsonValue,
     pub content: String,
+    /// Source from which the content was extracted, to delete the points with their source
+    pub source_meta_id: Option<Uuid>,
     // TODO: enforces that extracted content metadata should have at least source_name and user_id
 }
+
+/// Content found by a semantic search, with the similarity score of its closest point
+#[derive(Debug)]
+pub struct ScoredContent {
+    pub id: Uuid,
+    pub metadata: JsonValue,
+    pub content: String,
+    /// Source from which the content was extracted, to group the found contents by source
+    pub source_meta_id: Option<Uuid>,
+    pub score: f32,
+}
@@ -1,2 +1,3 @@
 pub mod content;
 pub mod content_point;
+pub mod source_text;
@@ -0,0 +1,124 @@
+/// Separator of the contents accumulated in a text
+const CONTENT_SEPARATOR: &str = "\n\n";
+
+/// Text of a source accumulated from its extracted contents, by chapter, until its extraction is completed
+///
+/// The texts are bounded: the contents beyond the limits are not kept, and are then not summarized.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SourceText {
+    /// Text of the contents outside any chapter
+    pub unchaptered: String,
+    /// Text of each chapter, in the order of their first content
+    pub chapters: Vec<ChapterText>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChapterText {
+    pub chapter: String,
+    pub text: String,
+}
+
+impl SourceText {
+    /// Adds an extracted content to the text of its chapter
+    ///
+    /// # Params
+    /// - max_chapter_chars: characters kept of each chapter, and of the contents outside any chapter
+    /// - max_chapters: chapters kept, the contents of the next ones being left out
+    pub fn add_content(
+        &mut self,
+        chapter: Option<&str>,
+        content: &str,
+        max_chapter_chars: usize,
+        max_chapters: usize,
+    ) {
+        let text = match chapter {
+            None => &mut self.unchaptered,
+            Some(chapter) => {
+                let index = match self
+                    .chapters
+                    .iter()
+                    .position(|chapter_text| chapter_text.chapter == chapter)
+                {
+                    Some(index) => index,
+                    None if self.chapters.len() < max_chapters => {
+                        self.chapters.push(ChapterText {
+                            chapter: chapter.to_string(),
+                            text: String::new(),
+                        });
+                        self.chapters.len() - 1
+                    }
+                    None => return,
+                };
+                &mut self.chapters[index].text
+            }
+        };
+
+        push_bounded(text, content.trim(), max_chapter_chars);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.unchaptered.is_empty() && self.chapters.iter().all(|chapter| chapter.text.is_empty())
+    }
+}
+
+/// Appends a content to a text, up to a number of characters
+fn push_bounded(text: &mut String, content: &str, max_chars: usize) {
+    if content.is_empty() {
+        return;
+    }
+
+    let mut nb_chars = text.chars().count();
+    if nb_chars > 0 {
+        if nb_chars + CONTENT_SEPARATOR.len() >= max_chars {
+            return;
+        }
+        text.push_str(CONTENT_SEPARATOR);
+        nb_chars += CONTENT_SEPARATOR.len();
+    }
+
+    text.extend(content.chars().take(max_chars.saturating_sub(nb_chars)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contents_are_accumulated_by_chapter_in_their_order() {
+        let mut source_text = SourceText::default();
+
+        source_text.add_content(Some("ch2"), "Pods run containers.", 100, 10);
+        source_text.add_content(None, "Preface.", 100, 10);
+        source_text.add_content(Some("ch1"), "Nodes run pods.", 100, 10);
+        source_text.add_content(Some("ch2"), " They are scheduled. ", 100, 10);
+
+        assert_eq!(source_text.unchaptered, "Preface.");
+        assert_eq!(
+            source_text.chapters,
+            vec![
+                ChapterText {
+                    chapter: "ch2".to_string(),
+                    text: "Pods run containers.\n\nThey are scheduled.".to_string(),
+                },
+                ChapterText {
+                    chapter: "ch1".to_string(),
+                    text: "Nodes run pods.".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn texts_are_bounded() {
+        let mut source_text = SourceText::default();
+
+        source_text.add_content(Some("ch1"), "Pods run containers.", 10, 1);
+        source_text.add_content(Some("ch1"), "They are scheduled.", 10, 1);
+        source_text.add_content(Some("ch2"), "Nodes run pods.", 10, 1);
+
+        assert_eq!(source_text.chapters.len(), 1);
+        assert_eq!(source_text.chapters[0].text, "Pods run c");
+        assert!(!source_text.is_empty());
+        assert!(SourceText::default().is_empty());
+    }
+}
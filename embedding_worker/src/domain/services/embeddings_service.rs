@@ -0,0 +1,86 @@
+use crate::{
+    domain::{entities::content_point::Embeddings, services::helpers::split_sentences},
+    repositories::embedding_model_port::{
+        EmbeddingModelError, EmbeddingModelPort, EmbeddingsModelKind,
+    },
+};
+use common::helper::error_chain_fmt;
+use tracing::debug;
+
+/// Service to generate embeddings from a text content, with the models of a configured backend
+///
+/// Splits the text contents into sentences, and picks the model of each content:
+/// the code model for source code, the multilingual model for the contents detected in another language than English.
+pub struct EmbeddingsService {
+    embedding_model: Box<dyn EmbeddingModelPort>,
+}
+
+impl EmbeddingsService {
+    pub fn new(embedding_model: Box<dyn EmbeddingModelPort>) -> Self {
+        Self { embedding_model }
+    }
+
+    /// Generates the embeddings of a text content, in its detected language if any
+    #[tracing::instrument(name = "Generate embeddings", skip(self))]
+    pub async fn generate_embeddings(
+        &self,
+        content: &str,
+        language: Option<&str>,
+    ) -> Result<Vec<Embeddings>, EmbeddingsServiceError> {
+        // A content could have one or more sentences
+        let sentences = split_sentences(content);
+        debug!(?sentences, "Splitted content");
+
+        Ok(self
+            .embedding_model
+            .encode(sentences, EmbeddingsModelKind::for_language(language))
+            .await?)
+    }
+
+    /// Generates the embeddings of a source code content
+    ///
+    /// Source code is not split into sentences: one embeddings is generated for the whole content.
+    #[tracing::instrument(name = "Generate code embeddings", skip(self))]
+    pub async fn generate_code_embeddings(
+        &self,
+        content: &str,
+    ) -> Result<Vec<Embeddings>, EmbeddingsServiceError> {
+        Ok(self
+            .embedding_model
+            .encode(vec![content.to_string()], EmbeddingsModelKind::Code)
+            .await?)
+    }
+
+    /// Generates the embeddings of a search query
+    ///
+    /// The query is not split into sentences: one embeddings is generated for the whole query,
+    /// with the model of the contents of the searched language: the text model without language.
+    #[tracing::instrument(name = "Generate query embeddings", skip(self))]
+    pub async fn generate_query_embeddings(
+        &self,
+        query: &str,
+        language: Option<&str>,
+    ) -> Result<Embeddings, EmbeddingsServiceError> {
+        let mut embeddings_list = self
+            .embedding_model
+            .encode(
+                vec![query.to_string()],
+                EmbeddingsModelKind::for_language(language),
+            )
+            .await?;
+
+        Ok(embeddings_list.pop().unwrap_or_default())
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum EmbeddingsServiceError {
+    #[error(transparent)]
+    EmbeddingModelError(#[from] EmbeddingModelError),
+}
+
+impl std::fmt::Debug for EmbeddingsServiceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
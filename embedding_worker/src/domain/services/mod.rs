@@ -1,2 +1,4 @@
+pub mod embeddings_service;
 pub mod helpers;
-pub mod huggingface_embedding;
+pub mod simulated_embeddings;
+pub mod summarization_service;
@@ -0,0 +1,88 @@
+use crate::domain::entities::content_point::Embeddings;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Generates deterministic pseudo-embeddings of a text, without any model
+///
+/// Each word of the text is mapped to a pseudo-random vector seeded by the hash of the word,
+/// and the vectors of the words are summed then normalized.
+/// The same text always gets the same embeddings, across runs and platforms,
+/// and texts sharing words get close embeddings: a search still finds the contents containing the words of its query.
+pub fn simulated_embeddings(text: &str, vector_size: usize) -> Embeddings {
+    let mut embeddings = vec![0.0_f32; vector_size];
+
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        let mut state = fnv1a_hash(&word.to_lowercase());
+        for value in embeddings.iter_mut() {
+            *value += unit_interval_value(splitmix64(&mut state));
+        }
+    }
+
+    let norm = embeddings
+        .iter()
+        .map(|value| value * value)
+        .sum::<f32>()
+        .sqrt();
+    if norm > 0.0 {
+        embeddings.iter_mut().for_each(|value| *value /= norm);
+    }
+
+    embeddings
+}
+
+/// FNV-1a hash: unlike the hasher of the standard library, its values are stable across Rust versions
+fn fnv1a_hash(word: &str) -> u64 {
+    word.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Next value of a SplitMix64 pseudo-random generator
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Maps a random value to [-1, 1]
+fn unit_interval_value(random: u64) -> f32 {
+    ((random >> 11) as f64 / (1_u64 << 53) as f64 * 2.0 - 1.0) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dot(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(a, b)| a * b).sum()
+    }
+
+    #[test]
+    fn same_text_gets_the_same_normalized_embeddings() {
+        let embeddings = simulated_embeddings("The water cycle.", 384);
+
+        assert_eq!(embeddings.len(), 384);
+        assert_eq!(embeddings, simulated_embeddings("the WATER cycle", 384));
+        assert!((dot(&embeddings, &embeddings) - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn texts_sharing_words_are_closer_than_unrelated_texts() {
+        let query = simulated_embeddings("water cycle", 384);
+        let related = simulated_embeddings("The water cycle describes how water evaporates", 384);
+        let unrelated = simulated_embeddings("Groundwater is stored in aquifers", 384);
+
+        assert!(dot(&query, &related) > dot(&query, &unrelated));
+    }
+
+    #[test]
+    fn text_without_words_gets_null_embeddings() {
+        assert_eq!(simulated_embeddings(" ... ", 3), vec![0.0, 0.0, 0.0]);
+    }
+}
@@ -0,0 +1,135 @@
+use chrono::Utc;
+use common::dtos::source_summary::{ChapterSummaryDto, SourceSummaryDto};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    domain::entities::source_text::SourceText,
+    repositories::summarization_model_port::{SummarizationModelError, SummarizationModelPort},
+};
+
+/// Service to summarize a source and its chapters, with the model of a configured backend
+///
+/// Each chapter is summarized from its text. The source is summarized from the summaries of its chapters,
+/// preceded by its contents outside any chapter, or from its whole text if it has no chapters.
+pub struct SummarizationService {
+    summarization_model: Box<dyn SummarizationModelPort>,
+}
+
+impl SummarizationService {
+    pub fn new(summarization_model: Box<dyn SummarizationModelPort>) -> Self {
+        Self {
+            summarization_model,
+        }
+    }
+
+    #[tracing::instrument(name = "Summarize source", skip(self, source_text))]
+    pub async fn summarize_source(
+        &self,
+        source_meta_id: Uuid,
+        source_text: &SourceText,
+    ) -> Result<SourceSummaryDto, SummarizationModelError> {
+        let mut chapters = vec![];
+        for chapter_text in &source_text.chapters {
+            if chapter_text.text.is_empty() {
+                continue;
+            }
+
+            let summary = self
+                .summarization_model
+                .summarize(chapter_text.text.clone())
+                .await?;
+            chapters.push(ChapterSummaryDto {
+                chapter: chapter_text.chapter.clone(),
+                summary,
+            });
+        }
+        info!("Summarized {} chapters", chapters.len());
+
+        let chapter_summaries = chapters
+            .iter()
+            .map(|chapter| chapter.summary.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let source_text = match (source_text.unchaptered.is_empty(), chapters.is_empty()) {
+            (_, true) => source_text.unchaptered.clone(),
+            (true, false) => chapter_summaries,
+            (false, false) => format!("{}\n\n{}", source_text.unchaptered, chapter_summaries),
+        };
+        let summary = self.summarization_model.summarize(source_text).await?;
+
+        Ok(SourceSummaryDto {
+            source_meta_id,
+            summary,
+            chapters,
+            model: self.summarization_model.model_name().to_string(),
+            generated_at: Utc::now(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::BoxFuture;
+
+    /// "Summarizes" a text to its first line
+    struct FirstLineModel;
+
+    impl SummarizationModelPort for FirstLineModel {
+        fn model_name(&self) -> &str {
+            "first-line"
+        }
+
+        fn summarize(
+            &self,
+            text: String,
+        ) -> BoxFuture<'_, Result<String, SummarizationModelError>> {
+            Box::pin(async move { Ok(text.lines().next().unwrap_or_default().to_string()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn source_is_summarized_from_the_summaries_of_its_chapters() {
+        let mut source_text = SourceText::default();
+        source_text.add_content(Some("ch1"), "Pods run containers.\nOn nodes.", 100, 10);
+        source_text.add_content(Some("ch2"), "Services expose pods.", 100, 10);
+        let service = SummarizationService::new(Box::new(FirstLineModel));
+
+        let summary = service
+            .summarize_source(Uuid::new_v4(), &source_text)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            summary.chapters,
+            vec![
+                ChapterSummaryDto {
+                    chapter: "ch1".to_string(),
+                    summary: "Pods run containers.".to_string(),
+                },
+                ChapterSummaryDto {
+                    chapter: "ch2".to_string(),
+                    summary: "Services expose pods.".to_string(),
+                },
+            ]
+        );
+        assert_eq!(summary.summary, "Pods run containers.");
+        assert_eq!(summary.model, "first-line");
+    }
+
+    #[tokio::test]
+    async fn source_without_chapters_is_summarized_from_its_text() {
+        let mut source_text = SourceText::default();
+        source_text.add_content(None, "Kubernetes orchestrates containers.", 100, 10);
+        let service = SummarizationService::new(Box::new(FirstLineModel));
+
+        let summary = service
+            .summarize_source(Uuid::new_v4(), &source_text)
+            .await
+            .unwrap();
+
+        assert!(summary.chapters.is_empty());
+        assert_eq!(summary.summary, "Kubernetes orchestrates containers.");
+    }
+}
@@ -1,11 +1,20 @@
 use std::sync::Arc;
 
 use common::{
-    constants::routing_keys::CONTENT_EXTRACTED_ROUTING_KEY,
-    core::rabbitmq_message_repository::{
-        RabbitMQMessageRepository, RabbitMQMessageRepositoryError,
+    constants::routing_keys::{CONTENT_EXTRACTED_ROUTING_KEY, INGESTION_JOB_STATUS_ROUTING_KEY},
+    core::{
+        idempotency_store::{ConsumerIdempotency, IdempotencyStorePort},
+        memory_ceiling::ConsumptionThrottle,
+        message_signing::MessageSigningError,
+        rabbitmq_message_repository::{RabbitMQMessageRepository, RabbitMQMessageRepositoryError},
+        trace_propagation::continue_trace_from,
+    },
+    dtos::{
+        extract_content_job::IngestionLaneDto,
+        extracted_content::{ExtractedContentDto, LANGUAGE_METADATA_KEY},
+        ingestion_job_status::IngestionJobStatusDto,
+        templates::message_envelope::MessageEnvelope,
     },
-    dtos::extracted_content::ExtractedContentDto,
     helper::error_chain_fmt,
 };
 use futures::StreamExt;
@@ -19,6 +28,7 @@ use lapin::{
     types::FieldTable,
     Connection as RabbitMQConnection, ExchangeKind,
 };
+use tokio_util::sync::CancellationToken;
 use tracing::{error, info, info_span, Instrument};
 use uuid::Uuid;
 
@@ -28,15 +38,12 @@ use crate::{
             content::ContentEntity,
             content_point::{ContentPoint, ContentPointPayload},
         },
-        services::huggingface_embedding::{
-            HuggingFaceEmbeddingsService, HuggingFaceEmbeddingsServiceError,
-        },
-    },
-    repositories::content_point_qdrant_repository::{
-        ContentPointQdrantRepository, ContentPointQdrantRepositoryError,
+        services::embeddings_service::{EmbeddingsService, EmbeddingsServiceError},
     },
+    repositories::vector_store_port::{VectorStoreError, VectorStorePort},
 };
 
+/// Routing key of the contents extracted from the sources of the bulk lane
 pub const ROUTING_KEY: &str = CONTENT_EXTRACTED_ROUTING_KEY;
 
 #[derive(thiserror::Error)]
@@ -53,6 +60,18 @@ impl std::fmt::Debug for RegisterHandlerContentExtractedError {
     }
 }
 
+/// Controls of the consumption of messages, from the application
+pub struct ConsumptionControl {
+    /// Limits the number of messages delivered at once, shed when approaching the memory ceiling
+    pub throttle: ConsumptionThrottle,
+    /// Cancelled when the consumption is handed over to a newly started instance
+    pub stop_consuming: CancellationToken,
+    /// Ingestion lane whose contents are consumed, from its own queue
+    pub lane: IngestionLaneDto,
+    /// Contents already embedded, acknowledged without being embedded again when redelivered
+    pub idempotency_store: Arc<dyn IdempotencyStorePort>,
+}
+
 /// Registers the message handler to a given exchange with a specific binding key
 ///
 /// It declares a queue and binds it to the given exchange.
@@ -65,19 +84,28 @@ impl std::fmt::Debug for RegisterHandlerContentExtractedError {
     skip(
         rabbitmq_consuming_connection,
         message_repository,
-        content_point_qdrant_repository,
-        embeddings_service
+        vector_store,
+        embeddings_service,
+        consumption_control
     )
 )]
 pub async fn register_handler(
-    rabbitmq_consuming_connection: RabbitMQConnection,
+    rabbitmq_consuming_connection: Arc<RabbitMQConnection>,
     exchange_name: String,
     queue_name_prefix: String,
     // Not an `Arc` shared reference as we want to initialize a new repository for each thread (or at least for each handler)
     message_repository: RabbitMQMessageRepository,
-    content_point_qdrant_repository: Arc<ContentPointQdrantRepository>,
-    embeddings_service: Arc<HuggingFaceEmbeddingsService>,
+    vector_store: Arc<dyn VectorStorePort>,
+    embeddings_service: Arc<EmbeddingsService>,
+    consumption_control: ConsumptionControl,
 ) -> Result<(), RegisterHandlerContentExtractedError> {
+    let ConsumptionControl {
+        throttle: mut consumption_throttle,
+        stop_consuming,
+        lane,
+        idempotency_store,
+    } = consumption_control;
+
     let channel = rabbitmq_consuming_connection.create_channel().await?;
 
     channel
@@ -92,8 +120,9 @@ pub async fn register_handler(
         )
         .await?;
 
+    let routing_key = lane.content_extracted_routing_key();
     // In order to have several nodes of this service as consumers of the same queue: use a specific queue name
-    let queue_name = queue_name(&queue_name_prefix);
+    let queue_name = queue_name(&queue_name_prefix, lane);
 
     channel
         .queue_declare(
@@ -105,19 +134,24 @@ pub async fn register_handler(
 
     info!(
         "Declared queue {} on exchange {}, binding on {}",
-        queue_name, exchange_name, ROUTING_KEY
+        queue_name, exchange_name, routing_key
     );
 
     channel
         .queue_bind(
             &queue_name,
             &exchange_name,
-            ROUTING_KEY,
+            routing_key,
             QueueBindOptions::default(),
             FieldTable::default(),
         )
         .await?;
 
+    consumption_throttle.init(&channel).await?;
+
+    // The contents embedded by any instance consuming the queue are not embedded again
+    let idempotency = ConsumerIdempotency::new(idempotency_store, &queue_name);
+
     let consumer_options = BasicConsumeOptions {
         no_ack: false,
         ..BasicConsumeOptions::default()
@@ -132,10 +166,25 @@ pub async fn register_handler(
 
     info!(
         "📡 Handler consuming from queue {}, bound to {} with {}, waiting for messages ...",
-        queue_name, exchange_name, ROUTING_KEY,
+        queue_name, exchange_name, routing_key,
     );
 
-    while let Some(delivery) = consumer.next().await {
+    loop {
+        let delivery = tokio::select! {
+            biased;
+            // A new instance is ready to take over: stops consuming before the next message
+            _ = stop_consuming.cancelled() => break,
+            delivery = consumer.next() => delivery,
+        };
+        let Some(delivery) = delivery else {
+            break;
+        };
+
+        // Embedding large contents can make the worker run out of memory: waits for enough memory before handling new ones
+        if let Err(error) = consumption_throttle.wait_for_memory(&channel).await {
+            error!(?error, "Failed to throttle the consumption");
+        }
+
         async {
             let delivery = match delivery {
                 // Carries the delivery alongside its channel
@@ -149,6 +198,8 @@ pub async fn register_handler(
                     return;
                 }
             };
+            // The spans handling the message continue the trace of its publisher
+            continue_trace_from(&delivery.properties);
 
             // let extracted_content = match ExtractedContent::try_parsing(&delivery.data) {
             //     Ok(job) => job,
@@ -163,15 +214,28 @@ pub async fn register_handler(
 
             // info!(?extracted_content, "Received extracted content");
 
+            if idempotency.is_completed(&delivery.data).await {
+                info!(
+                    "Acknowledging already handled message with delivery tag {}",
+                    delivery.delivery_tag
+                );
+                if let Err(error) = delivery.ack(BasicAckOptions::default()).await {
+                    error!(?error, "Failed to ack extracted content message");
+                }
+                return;
+            }
+
             match execute_handler(
                 &message_repository,
-                content_point_qdrant_repository.clone(),
+                vector_store.clone(),
                 embeddings_service.clone(),
                 &delivery,
             )
             .await
             {
                 Ok(()) => {
+                    idempotency.set_completed(&delivery.data).await;
+
                     info!(
                         "Acknowledging message with delivery tag {}",
                         delivery.delivery_tag
@@ -188,7 +252,15 @@ pub async fn register_handler(
                         "Not acknowledging message with delivery tag {}",
                         delivery.delivery_tag
                     );
-                    if let Err(error) = delivery.nack(BasicNackOptions::default()).await {
+                    // A message with an invalid signature would be rejected again: it is not requeued
+                    let nack_options = BasicNackOptions {
+                        requeue: !matches!(
+                            error,
+                            ExecuteHandlerContentExtractedError::MessageSigningError(_)
+                        ),
+                        ..BasicNackOptions::default()
+                    };
+                    if let Err(error) = delivery.nack(nack_options).await {
                         error!(?error, "Failed to nack extracted content message");
                     }
                 }
@@ -196,7 +268,7 @@ pub async fn register_handler(
         }
         .instrument(info_span!(
             "Handling consumed message",
-            routing_key = ROUTING_KEY,
+            routing_key = routing_key,
             exchange = exchange_name,
             queue = queue_name,
             message_id = %uuid::Uuid::new_v4(),
@@ -204,11 +276,21 @@ pub async fn register_handler(
         .await
     }
 
+    if stop_consuming.is_cancelled() {
+        // Prefetched messages that were not handled are requeued for the next instance
+        channel.close(200, "Handing over the consumption").await?;
+        info!("Stopped consuming from queue {}", queue_name);
+    }
+
     Ok(())
 }
 
-pub fn queue_name(queue_name_prefix: &str) -> String {
-    format!("{}_{}", queue_name_prefix, ROUTING_KEY)
+pub fn queue_name(queue_name_prefix: &str, lane: IngestionLaneDto) -> String {
+    format!(
+        "{}_{}",
+        queue_name_prefix,
+        lane.content_extracted_routing_key()
+    )
 }
 
 #[derive(thiserror::Error)]
@@ -216,9 +298,11 @@ pub enum ExecuteHandlerContentExtractedError {
     #[error(transparent)]
     RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
     #[error(transparent)]
-    HuggingFaceEmbeddingsServiceError(#[from] HuggingFaceEmbeddingsServiceError),
+    EmbeddingsServiceError(#[from] EmbeddingsServiceError),
+    #[error(transparent)]
+    VectorStoreError(#[from] VectorStoreError),
     #[error(transparent)]
-    ContentPointQdrantRepositoryError(#[from] ContentPointQdrantRepositoryError),
+    MessageSigningError(#[from] MessageSigningError),
     #[error("{0}")]
     MessageParsingError(String),
 }
@@ -231,51 +315,116 @@ impl std::fmt::Debug for ExecuteHandlerContentExtractedError {
 
 #[tracing::instrument(
     name = "Executing handler on extracted content",
-    skip(
-        _message_repository,
-        content_point_qdrant_repository,
-        embeddings_service
-    )
+    skip(message_repository, vector_store, embeddings_service)
 )]
 pub async fn execute_handler(
-    _message_repository: &RabbitMQMessageRepository,
-    content_point_qdrant_repository: Arc<ContentPointQdrantRepository>,
-    embeddings_service: Arc<HuggingFaceEmbeddingsService>,
+    message_repository: &RabbitMQMessageRepository,
+    vector_store: Arc<dyn VectorStorePort>,
+    embeddings_service: Arc<EmbeddingsService>,
     message: &Delivery,
 ) -> Result<(), ExecuteHandlerContentExtractedError> {
-    let extracted_content = ExtractedContentDto::try_parsing(&message.data).map_err(|error| {
-        ExecuteHandlerContentExtractedError::MessageParsingError(format!(
-            "Failed to parse extracted content message data: {}",
-            error
-        ))
-    })?;
+    // Rejects messages not published by our services
+    message_repository.verify(message)?;
+
+    let message =
+        MessageEnvelope::<ExtractedContentDto>::try_decoding(&message.data).map_err(|error| {
+            ExecuteHandlerContentExtractedError::MessageParsingError(format!(
+                "Failed to parse extracted content message data: {}",
+                error
+            ))
+        })?;
+    let extracted_content = message.payload;
+
+    info!(
+        ?extracted_content,
+        trace_context = ?message.trace_context,
+        "Received extracted content"
+    );
 
-    info!(?extracted_content, "Received extracted content");
+    if extracted_content.skip_embedding {
+        info!(
+            "Skipping embeddings for extracted content {}",
+            extracted_content.id
+        );
+        publish_job_status(message_repository, extracted_content.source_meta_id).await;
+        return Ok(());
+    }
 
+    let is_code = extracted_content.is_code;
+    let language = extracted_content
+        .metadata
+        .get(LANGUAGE_METADATA_KEY)
+        .and_then(|language| language.as_str())
+        .map(str::to_string);
     let content: ContentEntity = extracted_content.into();
 
-    let embeddings_list = embeddings_service
-        .generate_embeddings(&content.content)
-        .await?;
+    let embeddings_list = if is_code {
+        embeddings_service
+            .generate_code_embeddings(&content.content)
+            .await?
+    } else {
+        embeddings_service
+            .generate_embeddings(&content.content, language.as_deref())
+            .await?
+    };
 
     // Extracted content for all the generated embeddings from content sentences ?
+    // The points are identified by their content and sentence: a content extracted again replaces its points
     let content_points: Vec<ContentPoint> = embeddings_list
         .iter()
-        .map(|embeddings| ContentPoint {
-            id: Uuid::new_v4(),
+        .enumerate()
+        .map(|(sentence_index, embeddings)| ContentPoint {
+            id: Uuid::new_v5(&content.id, &sentence_index.to_le_bytes()),
             vector: embeddings.to_vec(),
             payload: ContentPointPayload {
+                content_id: content.id,
+                metadata: content.metadata.clone(),
                 content: content.content.to_string(),
+                source_meta_id: content.source_meta_id,
             },
         })
         .collect();
 
     info!(?content_points, "Generated embeddings");
 
-    content_point_qdrant_repository
-        .batch_save(content_points)
+    let point_ids = content_points.iter().map(|point| point.id).collect();
+    vector_store.upsert(content_points).await?;
+    // A previous version of the content can have had more sentences
+    vector_store
+        .delete_stale_points(content.id, point_ids)
         .await?;
 
+    publish_job_status(message_repository, content.source_meta_id).await;
+
     info!("Successfully handled extract_content_job message");
     Ok(())
 }
+
+/// Publishes that one more content of a source went through the embedding
+///
+/// The status is only informative: a failure to publish it does not fail the handling,
+/// the embeddings are already saved.
+async fn publish_job_status(
+    message_repository: &RabbitMQMessageRepository,
+    source_meta_id: Option<Uuid>,
+) {
+    // Contents published before the contents were linked to their source
+    let Some(source_meta_id) = source_meta_id else {
+        return;
+    };
+
+    let json_dto = match serde_json::to_string(&IngestionJobStatusDto::embedded(source_meta_id)) {
+        Ok(json_dto) => json_dto,
+        Err(error) => {
+            error!(?error, "Failed to serialize the ingestion job status");
+            return;
+        }
+    };
+
+    if let Err(error) = message_repository
+        .publish(INGESTION_JOB_STATUS_ROUTING_KEY, json_dto.as_bytes())
+        .await
+    {
+        error!(?error, "Failed to publish the ingestion job status");
+    }
+}
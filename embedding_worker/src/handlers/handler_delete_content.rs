@@ -0,0 +1,247 @@
+use futures::StreamExt;
+use lapin::{
+    message::Delivery,
+    options::{
+        BasicAckOptions, BasicConsumeOptions, BasicNackOptions, ExchangeDeclareOptions,
+        QueueBindOptions, QueueDeclareOptions,
+    },
+    types::FieldTable,
+    Connection as RabbitMQConnection, ExchangeKind,
+};
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, info_span, Instrument};
+
+use crate::repositories::vector_store_port::{VectorStoreError, VectorStorePort};
+use common::{
+    constants::routing_keys::{DELETE_CONTENT_ROUTING_KEY, PRUNE_CONTENT_ROUTING_KEY},
+    core::trace_propagation::continue_trace_from,
+    dtos::delete_content::{DeleteContentDto, PruneContentDto},
+    helper::error_chain_fmt,
+};
+
+pub const ROUTING_KEY: &str = DELETE_CONTENT_ROUTING_KEY;
+/// The prunings of the contents of a source are consumed from the same queue as the deletions
+pub const PRUNE_ROUTING_KEY: &str = PRUNE_CONTENT_ROUTING_KEY;
+
+#[derive(thiserror::Error)]
+pub enum RegisterHandlerDeleteContentError {
+    #[error(transparent)]
+    RabbitMQError(#[from] lapin::Error),
+}
+
+impl std::fmt::Debug for RegisterHandlerDeleteContentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Registers the message handler to a given exchange with a specific binding key
+///
+/// It declares a queue and binds it to the given exchange.
+/// It handles messages one by one, there is no handling messages in parallel.
+#[tracing::instrument(
+    name = "Register message handler",
+    skip(rabbitmq_consuming_connection, vector_store, stop_consuming)
+)]
+pub async fn register_handler(
+    rabbitmq_consuming_connection: Arc<RabbitMQConnection>,
+    exchange_name: String,
+    queue_name_prefix: String,
+    vector_store: Arc<dyn VectorStorePort>,
+    // Cancelled when the consumption is handed over to a newly started instance
+    stop_consuming: CancellationToken,
+) -> Result<(), RegisterHandlerDeleteContentError> {
+    let channel = rabbitmq_consuming_connection.create_channel().await?;
+
+    channel
+        .exchange_declare(
+            &exchange_name,
+            ExchangeKind::Topic,
+            ExchangeDeclareOptions {
+                durable: true,
+                ..ExchangeDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    // In order to have several nodes of this service as consumers of the same queue: use a specific queue name
+    let queue_name = queue_name(&queue_name_prefix);
+
+    channel
+        .queue_declare(
+            &queue_name,
+            QueueDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    for routing_key in [ROUTING_KEY, PRUNE_ROUTING_KEY] {
+        info!(
+            "Declared queue {} on exchange {}, binding on {}",
+            queue_name, exchange_name, routing_key
+        );
+
+        channel
+            .queue_bind(
+                &queue_name,
+                &exchange_name,
+                routing_key,
+                QueueBindOptions::default(),
+                FieldTable::default(),
+            )
+            .await?;
+    }
+
+    let consumer_options = BasicConsumeOptions {
+        no_ack: false,
+        ..BasicConsumeOptions::default()
+    };
+
+    let mut consumer = channel
+        .basic_consume(&queue_name, "", consumer_options, FieldTable::default())
+        .await?;
+
+    info!(
+        "📡 Handler consuming from queue {}, bound to {} with {}, waiting for messages ...",
+        queue_name, exchange_name, ROUTING_KEY,
+    );
+
+    loop {
+        let delivery = tokio::select! {
+            biased;
+            // A new instance is ready to take over: stops consuming before the next message
+            _ = stop_consuming.cancelled() => break,
+            delivery = consumer.next() => delivery,
+        };
+        let Some(delivery) = delivery else {
+            break;
+        };
+
+        async {
+            let delivery = match delivery {
+                // Carries the delivery alongside its channel
+                Ok(delivery) => delivery,
+                // Carries the error and is always followed by Ok(None)
+                Err(error) => {
+                    error!(
+                        ?error,
+                        "Failed to consume queue message on queue {}", queue_name
+                    );
+                    return;
+                }
+            };
+            // The spans handling the message continue the trace of its publisher
+            continue_trace_from(&delivery.properties);
+
+            match execute_handler(vector_store.clone(), &delivery).await {
+                Ok(()) => {
+                    info!(
+                        "Acknowledging message with delivery tag {}",
+                        delivery.delivery_tag
+                    );
+                    if let Err(error) = delivery.ack(BasicAckOptions::default()).await {
+                        error!(?error, "Failed to ack delete_content message");
+                    }
+                }
+                Err(error) => {
+                    error!(?error, "Failed to handle delete_content message");
+
+                    info!(
+                        "Not acknowledging message with delivery tag {}",
+                        delivery.delivery_tag
+                    );
+                    if let Err(error) = delivery.nack(BasicNackOptions::default()).await {
+                        error!(?error, "Failed to nack delete_content message");
+                    }
+                }
+            }
+        }
+        .instrument(info_span!(
+            "Handling consumed message",
+            routing_key = ROUTING_KEY,
+            exchange = exchange_name,
+            queue = queue_name,
+            message_id = %uuid::Uuid::new_v4(),
+        ))
+        .await
+    }
+
+    if stop_consuming.is_cancelled() {
+        // Prefetched messages that were not handled are requeued for the next instance
+        channel.close(200, "Handing over the consumption").await?;
+        info!("Stopped consuming from queue {}", queue_name);
+    }
+
+    Ok(())
+}
+
+pub fn queue_name(queue_name_prefix: &str) -> String {
+    format!("{}_{}", queue_name_prefix, ROUTING_KEY)
+}
+
+#[derive(thiserror::Error)]
+pub enum ExecuteHandlerDeleteContentError {
+    #[error(transparent)]
+    VectorStoreError(#[from] VectorStoreError),
+    #[error("{0}")]
+    MessageParsingError(String),
+}
+
+impl std::fmt::Debug for ExecuteHandlerDeleteContentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[tracing::instrument(
+    name = "Executing handler on content deletion",
+    skip(vector_store, message)
+)]
+pub async fn execute_handler(
+    vector_store: Arc<dyn VectorStorePort>,
+    message: &Delivery,
+) -> Result<(), ExecuteHandlerDeleteContentError> {
+    if message.routing_key.as_str() == PRUNE_ROUTING_KEY {
+        return execute_prune(vector_store, message).await;
+    }
+
+    let delete_content = DeleteContentDto::try_parsing(&message.data).map_err(|error| {
+        ExecuteHandlerDeleteContentError::MessageParsingError(format!(
+            "Failed to parse delete content message data: {}",
+            error
+        ))
+    })?;
+
+    info!(?delete_content, "Received content deletion");
+
+    vector_store
+        .delete_by_source_meta_id(delete_content.source_meta_id)
+        .await?;
+
+    info!("Successfully handled delete_content message");
+    Ok(())
+}
+
+/// Deletes the content points of the contents of a source no longer extracted from it, after an incremental extraction
+async fn execute_prune(
+    vector_store: Arc<dyn VectorStorePort>,
+    message: &Delivery,
+) -> Result<(), ExecuteHandlerDeleteContentError> {
+    let prune_content = PruneContentDto::try_parsing(&message.data).map_err(|error| {
+        ExecuteHandlerDeleteContentError::MessageParsingError(format!(
+            "Failed to parse prune content message data: {}",
+            error
+        ))
+    })?;
+
+    info!(?prune_content, "Received content pruning");
+
+    vector_store
+        .delete_by_content_ids(prune_content.source_meta_id, prune_content.content_ids)
+        .await?;
+
+    info!("Successfully handled prune_content message");
+    Ok(())
+}
@@ -0,0 +1,375 @@
+use futures::StreamExt;
+use lapin::{
+    options::{
+        BasicAckOptions, BasicConsumeOptions, BasicNackOptions, ExchangeDeclareOptions,
+        QueueBindOptions, QueueDeclareOptions,
+    },
+    types::FieldTable,
+    Connection as RabbitMQConnection, ExchangeKind,
+};
+use std::sync::Arc;
+use tracing::{error, info, info_span, Instrument};
+
+use crate::{
+    domain::services::embeddings_service::{EmbeddingsService, EmbeddingsServiceError},
+    repositories::vector_store_port::{VectorSearchFilter, VectorStoreError, VectorStorePort},
+};
+use common::{
+    constants::routing_keys::SEARCH_SEMANTIC_ROUTING_KEY,
+    core::{
+        normalization_rules::NormalizationRulesCache,
+        rabbitmq_message_repository::{RabbitMQMessageRepository, RabbitMQMessageRepositoryError},
+        trace_propagation::continue_trace_from,
+    },
+    dtos::{
+        fulltext_search_response::ResultContent,
+        semantic_search_request::SemanticSearchRequestDto,
+        semantic_search_response::{SemanticSearchResponseData, SemanticSearchResponseDto},
+        templates::rpc_response::RpcErrorStatus,
+    },
+    helper::error_chain_fmt,
+};
+
+pub const ROUTING_KEY: &str = SEARCH_SEMANTIC_ROUTING_KEY;
+
+const DEFAULT_SEARCH_LIMIT: usize = 10;
+
+#[derive(thiserror::Error)]
+pub enum RegisterHandlerSearchSemanticError {
+    #[error(transparent)]
+    RabbitMQError(#[from] lapin::Error),
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+}
+
+impl std::fmt::Debug for RegisterHandlerSearchSemanticError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Registers the RPC message handler to a given exchange with a specific binding key
+///
+/// The handler will respond to the message on the given `reply-to`.
+///
+/// It handles messages one by one, there is no handling messages in parallel.
+#[tracing::instrument(
+    name = "Register search semantic RPC handler",
+    skip(
+        rabbitmq_consuming_connection,
+        message_repository,
+        vector_store,
+        embeddings_service,
+        normalization_rules
+    )
+)]
+pub async fn register_handler(
+    rabbitmq_consuming_connection: Arc<RabbitMQConnection>,
+    exchange_name: String,
+    queue_name_prefix: String,
+    // Not an `Arc` shared reference as we want to initialize a new repository for each thread (or at least for each handler)
+    message_repository: RabbitMQMessageRepository,
+    vector_store: Arc<dyn VectorStorePort>,
+    embeddings_service: Arc<EmbeddingsService>,
+    normalization_rules: Arc<NormalizationRulesCache>,
+) -> Result<(), RegisterHandlerSearchSemanticError> {
+    let channel = rabbitmq_consuming_connection.create_channel().await?;
+
+    channel
+        .exchange_declare(
+            &exchange_name,
+            ExchangeKind::Topic,
+            ExchangeDeclareOptions {
+                durable: true,
+                ..ExchangeDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    // In order to have several nodes of this service as consumers of the same queue: use a specific queue name
+    let queue_name = queue_name(&queue_name_prefix);
+
+    channel
+        .queue_declare(
+            &queue_name,
+            QueueDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    channel
+        .queue_bind(
+            &queue_name,
+            &exchange_name,
+            ROUTING_KEY,
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    info!(
+        "Declared queue {} on exchange {}, binding on {}",
+        queue_name, exchange_name, ROUTING_KEY
+    );
+
+    let consumer_options = BasicConsumeOptions {
+        no_ack: false,
+        ..BasicConsumeOptions::default()
+    };
+
+    let mut consumer = channel
+        .basic_consume(&queue_name, "", consumer_options, FieldTable::default())
+        .await?;
+
+    // Inits for this specific handler
+    let message_repository = message_repository.try_init().await?;
+
+    info!(
+        "📡 Handler consuming from queue {}, bound to {} with {}, waiting for messages ...",
+        queue_name, exchange_name, ROUTING_KEY,
+    );
+
+    while let Some(delivery) = consumer.next().await {
+        async {
+            let delivery = match delivery {
+                // Carries the delivery alongside its channel
+                Ok(delivery) => delivery,
+                // Carries the error and is always followed by Ok(None)
+                Err(error) => {
+                    error!(
+                        ?error,
+                        "Failed to consume queue message on queue {}", queue_name
+                    );
+                    return;
+                }
+            };
+            // The spans handling the message continue the trace of its publisher
+            continue_trace_from(&delivery.properties);
+
+            let reply_to = match delivery.properties.reply_to().as_ref() {
+                Some(reply_to) => reply_to,
+                None => {
+                    error!(
+                        "No `reply-to` attribute necessary for RPC call on queue: {}",
+                        queue_name
+                    );
+
+                    // Disables requeue if there is no way to reply to the RPC call
+                    if let Err(error) = delivery
+                        .nack(BasicNackOptions {
+                            requeue: false,
+                            ..BasicNackOptions::default()
+                        })
+                        .await
+                    {
+                        error!(?error, "Failed to nack message");
+                    }
+                    return;
+                }
+            };
+
+            // Set on the response, for the caller to match it with its request
+            let correlation_id = delivery
+                .properties
+                .correlation_id()
+                .as_ref()
+                .map(|id| id.as_str());
+
+            match execute_handler(
+                &message_repository,
+                vector_store.as_ref(),
+                &embeddings_service,
+                &normalization_rules,
+                &delivery.data,
+                reply_to.as_str(),
+                correlation_id,
+            )
+            .await
+            {
+                Ok(()) => {
+                    info!(
+                        "Acknowledging message with delivery tag {}",
+                        delivery.delivery_tag
+                    );
+                    if let Err(error) = delivery.ack(BasicAckOptions::default()).await {
+                        error!(?error, "Failed to ack message");
+                    }
+                }
+                Err(error) => {
+                    error!(?error, "Failed to handle semantic search request");
+
+                    let status = match error {
+                        ExecuteHandlerSearchSemanticError::MessageParsingError(_) => {
+                            RpcErrorStatus::BadRequest
+                        }
+                        _ => RpcErrorStatus::InternalServerError,
+                    };
+                    let response = SemanticSearchResponseDto::Error {
+                        status,
+                        message: error.to_string(),
+                    };
+                    if let Ok(response) = response.try_serializing() {
+                        // Sends response to the given `reply_to` to mimic a RPC call
+                        let _ = message_repository
+                            .rpc_respond(reply_to.as_str(), correlation_id, response.as_bytes())
+                            .await;
+                    }
+
+                    info!(
+                        "Not acknowledging message with delivery tag {}",
+                        delivery.delivery_tag
+                    );
+                    if let Err(error) = delivery.nack(BasicNackOptions::default()).await {
+                        error!(?error, "Failed to nack message");
+                    }
+                }
+            }
+        }
+        .instrument(info_span!(
+            "Handling consumed message",
+            routing_key = ROUTING_KEY,
+            exchange = exchange_name,
+            queue = queue_name,
+            message_id = %uuid::Uuid::new_v4(),
+        ))
+        .await
+    }
+
+    Ok(())
+}
+
+pub fn queue_name(queue_name_prefix: &str) -> String {
+    format!("{}_{}", queue_name_prefix, ROUTING_KEY)
+}
+
+#[derive(thiserror::Error)]
+pub enum ExecuteHandlerSearchSemanticError {
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+    #[error(transparent)]
+    VectorStoreError(#[from] VectorStoreError),
+    #[error(transparent)]
+    EmbeddingsServiceError(#[from] EmbeddingsServiceError),
+    #[error("Error while serializing message data: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Error while deserializing input message: {0}")]
+    MessageParsingError(String),
+}
+
+impl std::fmt::Debug for ExecuteHandlerSearchSemanticError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Embeds the query and responds with the contents the closest to it
+#[tracing::instrument(
+    name = "Executing handler on semantic search request",
+    skip(
+        message_repository,
+        vector_store,
+        embeddings_service,
+        normalization_rules,
+        data
+    )
+)]
+pub async fn execute_handler(
+    message_repository: &RabbitMQMessageRepository,
+    vector_store: &dyn VectorStorePort,
+    embeddings_service: &EmbeddingsService,
+    normalization_rules: &NormalizationRulesCache,
+    data: &[u8],
+    reply_to: &str,
+    correlation_id: Option<&str>,
+) -> Result<(), ExecuteHandlerSearchSemanticError> {
+    let search_request = SemanticSearchRequestDto::try_parsing(data).map_err(|error| {
+        ExecuteHandlerSearchSemanticError::MessageParsingError(format!(
+            "Failed to parse semantic search request data: {}",
+            error
+        ))
+    })?;
+
+    info!(
+        ?search_request,
+        ?reply_to,
+        ?correlation_id,
+        "Received semantic search request, executing..."
+    );
+
+    let SemanticSearchRequestDto {
+        query,
+        limit,
+        user_id,
+        language,
+        fields,
+        source_meta_ids,
+        chapter,
+        keyword,
+        entity,
+    } = search_request;
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT) as u64;
+
+    // None of the sources to search
+    if source_meta_ids.as_ref().is_some_and(Vec::is_empty) {
+        return respond(message_repository, reply_to, correlation_id, vec![]).await;
+    }
+
+    // Normalized as the extracted contents, for the query to be embedded with the same terms
+    let normalizer = normalization_rules.get(message_repository).await;
+    let query = normalizer.normalize(&query);
+
+    let query_embeddings = embeddings_service
+        .generate_query_embeddings(&query, language.as_deref())
+        .await?;
+    let results = vector_store
+        .search(
+            query_embeddings,
+            limit,
+            VectorSearchFilter {
+                user_id,
+                language,
+                fields,
+                source_meta_ids,
+                chapter,
+                keyword,
+                entity,
+            },
+        )
+        .await?;
+    let results = results
+        .into_iter()
+        .map(|result| ResultContent {
+            id: result.id,
+            metadata: result.metadata,
+            content: result.content,
+            source_meta_id: result.source_meta_id,
+        })
+        .collect();
+
+    respond(message_repository, reply_to, correlation_id, results).await
+}
+
+/// Responds with the found contents
+async fn respond(
+    message_repository: &RabbitMQMessageRepository,
+    reply_to: &str,
+    correlation_id: Option<&str>,
+    results: Vec<ResultContent>,
+) -> Result<(), ExecuteHandlerSearchSemanticError> {
+    let response = SemanticSearchResponseDto::Ok {
+        data: SemanticSearchResponseData { results },
+    };
+
+    // Sends response to the given `reply_to` to mimic a RPC call
+    message_repository
+        .rpc_respond(
+            reply_to,
+            correlation_id,
+            serde_json::to_string(&response)?.as_bytes(),
+        )
+        .await?;
+
+    info!("Successfully handled {} message", ROUTING_KEY);
+    Ok(())
+}
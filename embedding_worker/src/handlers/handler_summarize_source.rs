@@ -0,0 +1,352 @@
+use std::{collections::HashMap, sync::Arc};
+
+use common::{
+    constants::routing_keys::{
+        CONTENT_EXTRACTED_FAST_ROUTING_KEY, CONTENT_EXTRACTED_ROUTING_KEY,
+        CONTENT_EXTRACTION_PROGRESS_ROUTING_KEY, SOURCE_SUMMARIZED_ROUTING_KEY,
+    },
+    core::{
+        message_signing::MessageSigningError,
+        rabbitmq_message_repository::{RabbitMQMessageRepository, RabbitMQMessageRepositoryError},
+        trace_propagation::continue_trace_from,
+    },
+    dtos::{
+        extracted_content::{metadata_chapter, ExtractedContentDto},
+        extraction_progress::{ExtractionProgressDto, ExtractionStatusDto},
+        templates::message_envelope::MessageEnvelope,
+    },
+    helper::error_chain_fmt,
+};
+use futures::StreamExt;
+use lapin::{
+    message::Delivery,
+    options::{
+        BasicAckOptions, BasicConsumeOptions, BasicNackOptions, ExchangeDeclareOptions,
+        QueueBindOptions, QueueDeclareOptions,
+    },
+    types::FieldTable,
+    Connection as RabbitMQConnection, ExchangeKind,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, info_span, Instrument};
+use uuid::Uuid;
+
+use crate::{
+    configuration::SummarizationSettings,
+    domain::{
+        entities::source_text::SourceText, services::summarization_service::SummarizationService,
+    },
+    repositories::summarization_model_port::SummarizationModelError,
+};
+
+/// Routing key of the completions of the extractions, triggering the summarization of their source
+pub const ROUTING_KEY: &str = CONTENT_EXTRACTION_PROGRESS_ROUTING_KEY;
+/// The contents of both ingestion lanes are accumulated from the same queue as the extraction progresses:
+/// the contents of a source are received before the completion of its extraction
+const ROUTING_KEYS: [&str; 3] = [
+    CONTENT_EXTRACTED_ROUTING_KEY,
+    CONTENT_EXTRACTED_FAST_ROUTING_KEY,
+    CONTENT_EXTRACTION_PROGRESS_ROUTING_KEY,
+];
+
+#[derive(thiserror::Error)]
+pub enum RegisterHandlerSummarizeSourceError {
+    #[error(transparent)]
+    RabbitMQError(#[from] lapin::Error),
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+}
+
+impl std::fmt::Debug for RegisterHandlerSummarizeSourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Registers the message handler summarizing the sources once their extraction is completed
+///
+/// It declares a queue and binds it to the given exchange.
+/// The extracted contents are accumulated in memory, by source and chapter, until the extraction of their source
+/// is completed: the contents of the sources being extracted when the worker stops are lost, and these sources
+/// are not summarized.
+/// It handles messages one by one, there is no handling messages in parallel.
+#[tracing::instrument(
+    name = "Register message handler",
+    skip(
+        rabbitmq_consuming_connection,
+        message_repository,
+        summarization_service,
+        settings,
+        stop_consuming
+    )
+)]
+pub async fn register_handler(
+    rabbitmq_consuming_connection: Arc<RabbitMQConnection>,
+    exchange_name: String,
+    queue_name_prefix: String,
+    // Not an `Arc` shared reference as we want to initialize a new repository for each thread (or at least for each handler)
+    message_repository: RabbitMQMessageRepository,
+    summarization_service: Arc<SummarizationService>,
+    settings: SummarizationSettings,
+    // Cancelled when the consumption is handed over to a newly started instance
+    stop_consuming: CancellationToken,
+) -> Result<(), RegisterHandlerSummarizeSourceError> {
+    let channel = rabbitmq_consuming_connection.create_channel().await?;
+
+    channel
+        .exchange_declare(
+            &exchange_name,
+            ExchangeKind::Topic,
+            ExchangeDeclareOptions {
+                durable: true,
+                ..ExchangeDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    // In order to have several nodes of this service as consumers of the same queue: use a specific queue name
+    let queue_name = queue_name(&queue_name_prefix);
+
+    channel
+        .queue_declare(
+            &queue_name,
+            QueueDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    for routing_key in ROUTING_KEYS {
+        info!(
+            "Declared queue {} on exchange {}, binding on {}",
+            queue_name, exchange_name, routing_key
+        );
+
+        channel
+            .queue_bind(
+                &queue_name,
+                &exchange_name,
+                routing_key,
+                QueueBindOptions::default(),
+                FieldTable::default(),
+            )
+            .await?;
+    }
+
+    let consumer_options = BasicConsumeOptions {
+        no_ack: false,
+        ..BasicConsumeOptions::default()
+    };
+
+    let mut consumer = channel
+        .basic_consume(&queue_name, "", consumer_options, FieldTable::default())
+        .await?;
+
+    // Inits for this specific handler
+    let message_repository = message_repository.try_init().await?;
+    // Texts of the sources being extracted
+    let mut sources: HashMap<Uuid, SourceText> = HashMap::new();
+
+    info!(
+        "📡 Handler consuming from queue {}, bound to {} with {:?}, waiting for messages ...",
+        queue_name, exchange_name, ROUTING_KEYS,
+    );
+
+    loop {
+        let delivery = tokio::select! {
+            biased;
+            // A new instance is ready to take over: stops consuming before the next message
+            _ = stop_consuming.cancelled() => break,
+            delivery = consumer.next() => delivery,
+        };
+        let Some(delivery) = delivery else {
+            break;
+        };
+
+        async {
+            let delivery = match delivery {
+                // Carries the delivery alongside its channel
+                Ok(delivery) => delivery,
+                // Carries the error and is always followed by Ok(None)
+                Err(error) => {
+                    error!(
+                        ?error,
+                        "Failed to consume queue message on queue {}", queue_name
+                    );
+                    return;
+                }
+            };
+            // The spans handling the message continue the trace of its publisher
+            continue_trace_from(&delivery.properties);
+
+            match execute_handler(
+                &message_repository,
+                &summarization_service,
+                &settings,
+                &mut sources,
+                &delivery,
+            )
+            .await
+            {
+                Ok(()) => {
+                    debug!(
+                        "Acknowledging message with delivery tag {}",
+                        delivery.delivery_tag
+                    );
+                    if let Err(error) = delivery.ack(BasicAckOptions::default()).await {
+                        error!(?error, "Failed to ack message of the summarization");
+                    }
+                }
+                Err(error) => {
+                    error!(?error, "Failed to handle message of the summarization");
+
+                    info!(
+                        "Not acknowledging message with delivery tag {}",
+                        delivery.delivery_tag
+                    );
+                    // A message with an invalid signature would be rejected again: it is not requeued
+                    let nack_options = BasicNackOptions {
+                        requeue: !matches!(
+                            error,
+                            ExecuteHandlerSummarizeSourceError::MessageSigningError(_)
+                        ),
+                        ..BasicNackOptions::default()
+                    };
+                    if let Err(error) = delivery.nack(nack_options).await {
+                        error!(?error, "Failed to nack message of the summarization");
+                    }
+                }
+            }
+        }
+        .instrument(info_span!(
+            "Handling consumed message",
+            routing_keys = ?ROUTING_KEYS,
+            exchange = exchange_name,
+            queue = queue_name,
+            message_id = %uuid::Uuid::new_v4(),
+        ))
+        .await
+    }
+
+    if stop_consuming.is_cancelled() {
+        // Prefetched messages that were not handled are requeued for the next instance
+        channel.close(200, "Handing over the consumption").await?;
+        info!("Stopped consuming from queue {}", queue_name);
+    }
+
+    Ok(())
+}
+
+pub fn queue_name(queue_name_prefix: &str) -> String {
+    format!("{}_summarize_sources", queue_name_prefix)
+}
+
+#[derive(thiserror::Error)]
+pub enum ExecuteHandlerSummarizeSourceError {
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+    #[error(transparent)]
+    SummarizationModelError(#[from] SummarizationModelError),
+    #[error(transparent)]
+    MessageSigningError(#[from] MessageSigningError),
+    #[error("{0}")]
+    MessageParsingError(String),
+}
+
+impl std::fmt::Debug for ExecuteHandlerSummarizeSourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Accumulates an extracted content in the text of its source,
+/// or summarizes a source once its extraction is completed and publishes its summaries
+///
+/// A source is summarized from the contents received since the worker started. Its text is kept
+/// until its summaries are published: a failed summarization is retried with the requeued completion.
+#[tracing::instrument(
+    name = "Executing handler summarizing sources",
+    skip(message_repository, summarization_service, settings, sources, message),
+    fields(routing_key = %message.routing_key)
+)]
+pub async fn execute_handler(
+    message_repository: &RabbitMQMessageRepository,
+    summarization_service: &SummarizationService,
+    settings: &SummarizationSettings,
+    sources: &mut HashMap<Uuid, SourceText>,
+    message: &Delivery,
+) -> Result<(), ExecuteHandlerSummarizeSourceError> {
+    // Rejects messages not published by our services
+    message_repository.verify(message)?;
+
+    if message.routing_key.as_str() != ROUTING_KEY {
+        let message = MessageEnvelope::<ExtractedContentDto>::try_decoding(&message.data).map_err(
+            |error| {
+                ExecuteHandlerSummarizeSourceError::MessageParsingError(format!(
+                    "Failed to parse extracted content message data: {}",
+                    error
+                ))
+            },
+        )?;
+        let content = message.payload;
+
+        // Contents published before the contents were linked to their source
+        let Some(source_meta_id) = content.source_meta_id else {
+            return Ok(());
+        };
+        sources.entry(source_meta_id).or_default().add_content(
+            metadata_chapter(&content.metadata),
+            &content.content,
+            settings.max_chapter_chars,
+            settings.max_chapters,
+        );
+
+        return Ok(());
+    }
+
+    let progress = ExtractionProgressDto::try_parsing(&message.data).map_err(|error| {
+        ExecuteHandlerSummarizeSourceError::MessageParsingError(format!(
+            "Failed to parse extraction progress message data: {}",
+            error
+        ))
+    })?;
+    let source_meta_id = progress.source_meta_id;
+
+    match progress.status {
+        ExtractionStatusDto::InProgress => Ok(()),
+        ExtractionStatusDto::Failed => {
+            sources.remove(&source_meta_id);
+            Ok(())
+        }
+        ExtractionStatusDto::Completed => {
+            let Some(source_text) = sources.get(&source_meta_id) else {
+                info!(
+                    ?source_meta_id,
+                    "No content of the source received since the worker started, not summarized"
+                );
+                return Ok(());
+            };
+            if source_text.is_empty() {
+                sources.remove(&source_meta_id);
+                return Ok(());
+            }
+
+            let summary = summarization_service
+                .summarize_source(source_meta_id, source_text)
+                .await?;
+            let json_dto = serde_json::to_string(&summary).map_err(|error| {
+                ExecuteHandlerSummarizeSourceError::MessageParsingError(format!(
+                    "Failed to serialize the source summary: {}",
+                    error
+                ))
+            })?;
+            message_repository
+                .publish(SOURCE_SUMMARIZED_ROUTING_KEY, json_dto.as_bytes())
+                .await?;
+
+            sources.remove(&source_meta_id);
+            info!(?source_meta_id, "Successfully summarized the source");
+            Ok(())
+        }
+    }
+}
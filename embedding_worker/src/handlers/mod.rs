@@ -1 +1,4 @@
 pub mod handler_content_extracted;
+pub mod handler_delete_content;
+pub mod handler_search_semantic;
+pub mod handler_summarize_source;
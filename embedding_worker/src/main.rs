@@ -1,6 +1,10 @@
-use common::telemetry::{get_tracing_subscriber, init_tracing_subscriber};
+use common::telemetry::{get_tracing_subscriber, init_tracing_subscriber, shutdown_tracing};
 use embedding_worker::{configuration::get_configuration, startup::Application};
 
+#[cfg(feature = "jemalloc")]
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
     let tracing_subscriber =
@@ -8,7 +12,9 @@ async fn main() -> std::io::Result<()> {
     init_tracing_subscriber(tracing_subscriber);
 
     // Panics if the configuration can't be read
-    let configuration = get_configuration().expect("Failed to read configuration.");
+    let configuration = get_configuration()
+        .await
+        .expect("Failed to read configuration.");
 
     let application = match Application::build(configuration).await {
         Ok(application) => application,
@@ -16,6 +22,7 @@ async fn main() -> std::io::Result<()> {
     };
 
     application.run_until_stopped().await.unwrap();
+    shutdown_tracing();
 
     Ok(())
 }
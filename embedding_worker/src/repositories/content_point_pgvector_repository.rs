@@ -0,0 +1,385 @@
+use std::collections::HashSet;
+
+use common::dtos::extracted_content::{
+    CHAPTER_METADATA_ATTRIBUTES, ENTITY_METADATA_ATTRIBUTES, KEYWORDS_METADATA_KEY,
+};
+use futures::future::BoxFuture;
+use serde_json::{json, Value as JsonValue};
+use sqlx::{PgPool, Row};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    domain::entities::content_point::{ContentPoint, Embeddings, ScoredContent},
+    repositories::vector_store_port::{VectorSearchFilter, VectorStoreError, VectorStorePort},
+};
+
+/// Repository for (extracted) content vectors persisted in Postgres, with the pgvector extension
+///
+/// For the small deployments not running Qdrant. The points are searched without vector index:
+/// the search scans all the points of the user.
+pub struct ContentPointPgvectorRepository {
+    db_pool: PgPool,
+    table_name: String,
+    distance: PgvectorDistance,
+}
+
+impl ContentPointPgvectorRepository {
+    /// Creates the pgvector extension and the table of the points if they do not exist yet
+    ///
+    /// # Params
+    /// - distance: distance between the vectors, named like the Qdrant distances: `Dot`, `Cosine` or `Euclid`
+    /// - vector_size: size of the vectors of the table
+    #[tracing::instrument(name = "Initializing pgvector and the associated table", skip(db_pool))]
+    pub async fn try_new(
+        db_pool: PgPool,
+        table_name: &str,
+        distance: &str,
+        vector_size: u64,
+    ) -> Result<Self, VectorStoreError> {
+        let distance = PgvectorDistance::from_str_name(distance).ok_or_else(|| {
+            VectorStoreError::ConfigurationError(format!("Invalid pgvector distance: {}", distance))
+        })?;
+
+        sqlx::query("CREATE EXTENSION IF NOT EXISTS vector")
+            .execute(&db_pool)
+            .await?;
+        sqlx::query(&format!(
+            r#"
+    CREATE TABLE IF NOT EXISTS {table_name} (
+        id UUID PRIMARY KEY,
+        content_id UUID NOT NULL,
+        source_meta_id UUID,
+        metadata JSONB NOT NULL,
+        content TEXT NOT NULL,
+        embedding vector({vector_size}) NOT NULL
+    )
+            "#
+        ))
+        .execute(&db_pool)
+        .await?;
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS {table_name}_user_id_idx ON {table_name} ((metadata->>'user_id'))"
+        ))
+        .execute(&db_pool)
+        .await?;
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS {table_name}_source_meta_id_idx ON {table_name} (source_meta_id)"
+        ))
+        .execute(&db_pool)
+        .await?;
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS {table_name}_content_id_idx ON {table_name} (content_id)"
+        ))
+        .execute(&db_pool)
+        .await?;
+
+        Ok(Self {
+            db_pool,
+            table_name: table_name.to_string(),
+            distance,
+        })
+    }
+}
+
+impl VectorStorePort for ContentPointPgvectorRepository {
+    #[tracing::instrument(name = "Saving content points to pgvector", skip(self))]
+    fn upsert(
+        &self,
+        content_points: Vec<ContentPoint>,
+    ) -> BoxFuture<'_, Result<(), VectorStoreError>> {
+        Box::pin(async move {
+            let mut transaction = self.db_pool.begin().await?;
+
+            for content_point in content_points {
+                sqlx::query(&format!(
+                    r#"
+    INSERT INTO {} (id, content_id, source_meta_id, metadata, content, embedding)
+    VALUES ($1, $2, $3, $4, $5, $6::vector)
+    ON CONFLICT (id) DO UPDATE
+    SET content_id = $2, source_meta_id = $3, metadata = $4, content = $5, embedding = $6::vector
+                    "#,
+                    self.table_name
+                ))
+                .bind(content_point.id)
+                .bind(content_point.payload.content_id)
+                .bind(content_point.payload.source_meta_id)
+                .bind(content_point.payload.metadata)
+                .bind(content_point.payload.content)
+                .bind(vector_literal(&content_point.vector))
+                .execute(&mut transaction)
+                .await?;
+            }
+
+            transaction.commit().await?;
+
+            info!("Saved content points");
+            Ok(())
+        })
+    }
+
+    #[tracing::instrument(name = "Deleting content points of a source from pgvector", skip(self))]
+    fn delete_by_source_meta_id(
+        &self,
+        source_meta_id: Uuid,
+    ) -> BoxFuture<'_, Result<(), VectorStoreError>> {
+        Box::pin(async move {
+            sqlx::query(&format!(
+                "DELETE FROM {} WHERE source_meta_id = $1",
+                self.table_name
+            ))
+            .bind(source_meta_id)
+            .execute(&self.db_pool)
+            .await?;
+
+            info!("Deleted content points");
+            Ok(())
+        })
+    }
+
+    #[tracing::instrument(name = "Deleting stale points of a content from pgvector", skip(self))]
+    fn delete_stale_points(
+        &self,
+        content_id: Uuid,
+        point_ids: Vec<Uuid>,
+    ) -> BoxFuture<'_, Result<(), VectorStoreError>> {
+        Box::pin(async move {
+            let result = sqlx::query(&format!(
+                "DELETE FROM {} WHERE content_id = $1 AND id <> ALL($2)",
+                self.table_name
+            ))
+            .bind(content_id)
+            .bind(point_ids)
+            .execute(&self.db_pool)
+            .await?;
+
+            if result.rows_affected() > 0 {
+                info!("Deleted {} stale content points", result.rows_affected());
+            }
+            Ok(())
+        })
+    }
+
+    #[tracing::instrument(
+        name = "Deleting content points by content ids from pgvector",
+        skip(self)
+    )]
+    fn delete_by_content_ids(
+        &self,
+        source_meta_id: Uuid,
+        content_ids: Vec<Uuid>,
+    ) -> BoxFuture<'_, Result<(), VectorStoreError>> {
+        Box::pin(async move {
+            sqlx::query(&format!(
+                "DELETE FROM {} WHERE source_meta_id = $1 AND content_id = ANY($2)",
+                self.table_name
+            ))
+            .bind(source_meta_id)
+            .bind(content_ids)
+            .execute(&self.db_pool)
+            .await?;
+
+            info!("Deleted content points");
+            Ok(())
+        })
+    }
+
+    #[tracing::instrument(name = "Searching content points in pgvector", skip(self, vector))]
+    fn search(
+        &self,
+        vector: Embeddings,
+        limit: u64,
+        filter: VectorSearchFilter,
+    ) -> BoxFuture<'_, Result<Vec<ScoredContent>, VectorStoreError>> {
+        Box::pin(async move {
+            let rows = sqlx::query(&format!(
+                r#"
+    SELECT content_id, source_meta_id, metadata, content, {score} AS score
+    FROM {table_name}
+    WHERE metadata->>'user_id' = $2
+        AND ($3::TEXT IS NULL OR metadata->>'language' = $3)
+        AND ($5::JSONB = '{{}}'::JSONB OR metadata->'structured'->'fields' @> $5)
+        AND ($6::UUID[] IS NULL OR source_meta_id = ANY($6))
+        AND ($7::TEXT IS NULL OR {chapter_condition})
+        AND ($8::TEXT IS NULL OR metadata->'{keywords_key}' @> jsonb_build_array($8::TEXT))
+        AND ($9::TEXT IS NULL OR {entity_condition})
+    ORDER BY embedding {operator} $1::vector
+    LIMIT $4
+                "#,
+                score = self.distance.score_expression(),
+                table_name = self.table_name,
+                operator = self.distance.operator(),
+                chapter_condition = chapter_condition("$7"),
+                keywords_key = KEYWORDS_METADATA_KEY,
+                entity_condition = entity_condition("$9"),
+            ))
+            .bind(vector_literal(&vector))
+            .bind(filter.user_id.to_string())
+            .bind(filter.language)
+            .bind(limit as i64)
+            .bind(json!(filter.fields))
+            .bind(filter.source_meta_ids)
+            .bind(filter.chapter)
+            .bind(filter.keyword)
+            .bind(filter.entity)
+            .fetch_all(&self.db_pool)
+            .await?;
+
+            let mut found_content_ids = HashSet::new();
+            let mut contents = vec![];
+            for row in rows {
+                let content = ScoredContent {
+                    id: row.try_get("content_id")?,
+                    metadata: row.try_get::<JsonValue, _>("metadata")?,
+                    content: row.try_get("content")?,
+                    source_meta_id: row.try_get("source_meta_id")?,
+                    score: row.try_get::<f64, _>("score")? as f32,
+                };
+
+                if found_content_ids.insert(content.id) {
+                    contents.push(content);
+                }
+            }
+
+            Ok(contents)
+        })
+    }
+
+    fn check_health(&self) -> BoxFuture<'_, Result<(), VectorStoreError>> {
+        Box::pin(async move {
+            sqlx::query("SELECT 1").execute(&self.db_pool).await?;
+            Ok(())
+        })
+    }
+}
+
+/// Condition on the chapter of a content being a parameter, whatever the reader of its source,
+/// see `CHAPTER_METADATA_ATTRIBUTES`
+fn chapter_condition(parameter: &str) -> String {
+    let conditions: Vec<String> = CHAPTER_METADATA_ATTRIBUTES
+        .iter()
+        .map(|attribute| {
+            format!(
+                "metadata #>> '{{{}}}' = {}",
+                attribute.replace('.', ","),
+                parameter
+            )
+        })
+        .collect();
+
+    format!("({})", conditions.join(" OR "))
+}
+
+/// Condition on a named entity of a content being a parameter, whatever its kind,
+/// see `ENTITY_METADATA_ATTRIBUTES`
+fn entity_condition(parameter: &str) -> String {
+    let conditions: Vec<String> = ENTITY_METADATA_ATTRIBUTES
+        .iter()
+        .map(|attribute| {
+            format!(
+                "metadata #> '{{{}}}' @> jsonb_build_array({}::TEXT)",
+                attribute.replace('.', ","),
+                parameter
+            )
+        })
+        .collect();
+
+    format!("({})", conditions.join(" OR "))
+}
+
+/// Distance between the vectors, with the scores of the Qdrant distances
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PgvectorDistance {
+    Dot,
+    Cosine,
+    Euclid,
+}
+
+impl PgvectorDistance {
+    fn from_str_name(name: &str) -> Option<Self> {
+        match name {
+            "Dot" => Some(Self::Dot),
+            "Cosine" => Some(Self::Cosine),
+            "Euclid" => Some(Self::Euclid),
+            _ => None,
+        }
+    }
+
+    /// pgvector operator ordering the vectors from the closest
+    fn operator(&self) -> &'static str {
+        match self {
+            Self::Dot => "<#>",
+            Self::Cosine => "<=>",
+            Self::Euclid => "<->",
+        }
+    }
+
+    /// Score of a point, like Qdrant: the dot product, the cosine similarity or the euclidean distance to the vector `$1`
+    fn score_expression(&self) -> &'static str {
+        match self {
+            Self::Dot => "(embedding <#> $1::vector) * -1",
+            Self::Cosine => "1 - (embedding <=> $1::vector)",
+            Self::Euclid => "embedding <-> $1::vector",
+        }
+    }
+}
+
+/// Text representation of a vector, cast to a pgvector `vector`
+fn vector_literal(vector: &[f32]) -> String {
+    format!(
+        "[{}]",
+        vector
+            .iter()
+            .map(f32::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vector_is_represented_as_a_pgvector_literal() {
+        assert_eq!(vector_literal(&[1.0, -0.5, 0.25]), "[1,-0.5,0.25]");
+        assert_eq!(vector_literal(&[]), "[]");
+    }
+
+    #[test]
+    fn chapter_condition_matches_the_chapter_of_any_reader() {
+        assert_eq!(
+            chapter_condition("$7"),
+            "(metadata #>> '{epub,chapter_id}' = $7 OR metadata #>> '{latex,chapter}' = $7)"
+        );
+    }
+
+    #[test]
+    fn entity_condition_matches_the_entities_of_any_kind() {
+        assert_eq!(
+            entity_condition("$9"),
+            concat!(
+                "(metadata #> '{entities,people}' @> jsonb_build_array($9::TEXT)",
+                " OR metadata #> '{entities,places}' @> jsonb_build_array($9::TEXT)",
+                " OR metadata #> '{entities,organizations}' @> jsonb_build_array($9::TEXT))"
+            )
+        );
+    }
+
+    #[test]
+    fn distance_is_named_like_the_qdrant_distances() {
+        assert_eq!(
+            PgvectorDistance::from_str_name("Dot"),
+            Some(PgvectorDistance::Dot)
+        );
+        assert_eq!(
+            PgvectorDistance::from_str_name("Cosine"),
+            Some(PgvectorDistance::Cosine)
+        );
+        assert_eq!(
+            PgvectorDistance::from_str_name("Euclid"),
+            Some(PgvectorDistance::Euclid)
+        );
+        assert_eq!(PgvectorDistance::from_str_name("dot"), None);
+    }
+}
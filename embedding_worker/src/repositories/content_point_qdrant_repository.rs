@@ -1,16 +1,41 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use common::helper::error_chain_fmt;
+use common::dtos::extracted_content::{
+    CHAPTER_METADATA_ATTRIBUTES, ENTITY_METADATA_ATTRIBUTES, KEYWORDS_METADATA_KEY,
+};
+use futures::future::BoxFuture;
 use qdrant_client::{
     prelude::QdrantClient,
     qdrant::{
-        self, vectors_config::Config, CreateCollection, Distance, PointStruct, VectorParams,
-        VectorsConfig,
+        self, point_id::PointIdOptions, vectors_config::Config, Condition, CreateCollection,
+        Distance, Filter, PointStruct, ScoredPoint, SearchPoints, VectorParams, VectorsConfig,
     },
 };
+use serde_json::Value as JsonValue;
 use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    domain::entities::content_point::{
+        ContentPoint, ContentPointPayload, Embeddings, ScoredContent,
+    },
+    repositories::vector_store_port::{VectorSearchFilter, VectorStoreError, VectorStorePort},
+};
+
+/// Payload key of the id of the user owning a content, from its metadata
+const USER_ID_PAYLOAD_KEY: &str = "metadata.user_id";
+
+/// Payload key of the id of the content a point was generated from
+const CONTENT_ID_PAYLOAD_KEY: &str = "content_id";
+
+/// Payload key of the detected language of a content, from its metadata
+const LANGUAGE_PAYLOAD_KEY: &str = "metadata.language";
+
+/// Payload key of the source of a content
+const SOURCE_META_ID_PAYLOAD_KEY: &str = "source_meta_id";
 
-use crate::domain::entities::content_point::{ContentPoint, ContentPointPayload};
+/// Payload key of the fields of a row of a structured source, from its metadata
+const STRUCTURED_FIELDS_PAYLOAD_KEY: &str = "metadata.structured.fields";
 
 /// Repository for (extracted) content vectors (ContentVector) persisted in Qdrant
 pub struct ContentPointQdrantRepository {
@@ -28,9 +53,9 @@ impl ContentPointQdrantRepository {
         collection_name: &str,
         collection_distance: &str,
         collection_vector_size: u64,
-    ) -> Result<Self, ContentPointQdrantRepositoryError> {
+    ) -> Result<Self, VectorStoreError> {
         let collection_distance = Distance::from_str_name(&collection_distance).ok_or(
-            ContentPointQdrantRepositoryError::QdrantConfigurationError(
+            VectorStoreError::ConfigurationError(
                 "Invalid Qdrant distance from configuration, using Dot distance".into(),
             ),
         )?;
@@ -56,9 +81,7 @@ impl ContentPointQdrantRepository {
                 // Qdrant client only returns anyhow errors for now
                 if !error.to_string().contains("already exists") {
                     info!(?error, "Error on config");
-                    return Err(ContentPointQdrantRepositoryError::QdrantError(
-                        error.to_string(),
-                    ));
+                    return Err(VectorStoreError::QdrantError(error.to_string()));
                 }
             }
         };
@@ -68,38 +91,191 @@ impl ContentPointQdrantRepository {
             collection_name: collection_name.to_string(),
         })
     }
+}
 
+impl VectorStorePort for ContentPointQdrantRepository {
     #[tracing::instrument(name = "Saving content points to Qdrant", skip(self))]
-    pub async fn batch_save(
+    fn upsert(
         &self,
         content_points: Vec<ContentPoint>,
-    ) -> Result<(), ContentPointQdrantRepositoryError> {
-        self.client
-            .upsert_points(
-                &self.collection_name,
-                content_points.into_iter().map(PointStruct::from).collect(),
-                None,
-            )
-            .await
-            .map_err(|e| ContentPointQdrantRepositoryError::QdrantError(e.to_string()))?;
+    ) -> BoxFuture<'_, Result<(), VectorStoreError>> {
+        Box::pin(async move {
+            self.client
+                .upsert_points(
+                    &self.collection_name,
+                    content_points.into_iter().map(PointStruct::from).collect(),
+                    None,
+                )
+                .await
+                .map_err(|e| VectorStoreError::QdrantError(e.to_string()))?;
 
-        info!("Saved content points");
-        Ok(())
+            info!("Saved content points");
+            Ok(())
+        })
     }
-}
 
-#[derive(thiserror::Error)]
-pub enum ContentPointQdrantRepositoryError {
-    #[error("Error from Qdrant: {0}")]
-    QdrantError(String),
+    #[tracing::instrument(name = "Deleting content points of a source from Qdrant", skip(self))]
+    fn delete_by_source_meta_id(
+        &self,
+        source_meta_id: Uuid,
+    ) -> BoxFuture<'_, Result<(), VectorStoreError>> {
+        Box::pin(async move {
+            let filter = Filter::must([Condition::matches(
+                "source_meta_id",
+                source_meta_id.to_string(),
+            )]);
 
-    #[error("Error from Qdrant config: {0}")]
-    QdrantConfigurationError(String),
-}
+            self.client
+                .delete_points(&self.collection_name, &filter.into(), None)
+                .await
+                .map_err(|e| VectorStoreError::QdrantError(e.to_string()))?;
+
+            info!("Deleted content points");
+            Ok(())
+        })
+    }
+
+    #[tracing::instrument(name = "Deleting stale points of a content from Qdrant", skip(self))]
+    fn delete_stale_points(
+        &self,
+        content_id: Uuid,
+        point_ids: Vec<Uuid>,
+    ) -> BoxFuture<'_, Result<(), VectorStoreError>> {
+        Box::pin(async move {
+            let filter = Filter {
+                must: vec![Condition::matches(
+                    CONTENT_ID_PAYLOAD_KEY,
+                    content_id.to_string(),
+                )],
+                must_not: vec![Condition::has_id(
+                    point_ids.iter().map(|point_id| point_id.to_string()),
+                )],
+                ..Default::default()
+            };
+
+            self.client
+                .delete_points(&self.collection_name, &filter.into(), None)
+                .await
+                .map_err(|e| VectorStoreError::QdrantError(e.to_string()))?;
+
+            Ok(())
+        })
+    }
+
+    #[tracing::instrument(
+        name = "Deleting content points by content ids from Qdrant",
+        skip(self)
+    )]
+    fn delete_by_content_ids(
+        &self,
+        source_meta_id: Uuid,
+        content_ids: Vec<Uuid>,
+    ) -> BoxFuture<'_, Result<(), VectorStoreError>> {
+        Box::pin(async move {
+            let content_ids: Vec<String> = content_ids.iter().map(Uuid::to_string).collect();
+            let filter = Filter::must([
+                Condition::matches("source_meta_id", source_meta_id.to_string()),
+                Condition::matches(CONTENT_ID_PAYLOAD_KEY, content_ids),
+            ]);
+
+            self.client
+                .delete_points(&self.collection_name, &filter.into(), None)
+                .await
+                .map_err(|e| VectorStoreError::QdrantError(e.to_string()))?;
+
+            info!("Deleted content points");
+            Ok(())
+        })
+    }
+
+    #[tracing::instrument(name = "Searching content points in Qdrant", skip(self, vector))]
+    fn search(
+        &self,
+        vector: Embeddings,
+        limit: u64,
+        filter: VectorSearchFilter,
+    ) -> BoxFuture<'_, Result<Vec<ScoredContent>, VectorStoreError>> {
+        Box::pin(async move {
+            let mut conditions = vec![Condition::matches(
+                USER_ID_PAYLOAD_KEY,
+                filter.user_id.to_string(),
+            )];
+            if let Some(language) = filter.language {
+                conditions.push(Condition::matches(LANGUAGE_PAYLOAD_KEY, language));
+            }
+            for (name, value) in filter.fields {
+                conditions.push(Condition::matches(
+                    format!("{}.{}", STRUCTURED_FIELDS_PAYLOAD_KEY, name),
+                    value,
+                ));
+            }
+            if let Some(source_meta_ids) = filter.source_meta_ids {
+                conditions.push(Condition::matches(
+                    SOURCE_META_ID_PAYLOAD_KEY,
+                    source_meta_ids
+                        .iter()
+                        .map(Uuid::to_string)
+                        .collect::<Vec<String>>(),
+                ));
+            }
+            if let Some(chapter) = filter.chapter {
+                // The chapter of a content is in the metadata of the reader of its source
+                conditions.push(
+                    Filter::should(CHAPTER_METADATA_ATTRIBUTES.iter().map(|attribute| {
+                        Condition::matches(format!("metadata.{}", attribute), chapter.clone())
+                    }))
+                    .into(),
+                );
+            }
+            if let Some(keyword) = filter.keyword {
+                // Matches any of the keywords of a content
+                conditions.push(Condition::matches(
+                    format!("metadata.{}", KEYWORDS_METADATA_KEY),
+                    keyword,
+                ));
+            }
+            if let Some(entity) = filter.entity {
+                conditions.push(
+                    Filter::should(ENTITY_METADATA_ATTRIBUTES.iter().map(|attribute| {
+                        Condition::matches(format!("metadata.{}", attribute), entity.clone())
+                    }))
+                    .into(),
+                );
+            }
+
+            let response = self
+                .client
+                .search_points(&SearchPoints {
+                    collection_name: self.collection_name.clone(),
+                    vector,
+                    filter: Some(Filter::must(conditions)),
+                    limit,
+                    with_payload: Some(true.into()),
+                    ..Default::default()
+                })
+                .await
+                .map_err(|e| VectorStoreError::QdrantError(e.to_string()))?;
+
+            let mut found_content_ids = HashSet::new();
+            let contents = response
+                .result
+                .into_iter()
+                .map(ScoredContent::from)
+                .filter(|content| found_content_ids.insert(content.id))
+                .collect();
+
+            Ok(contents)
+        })
+    }
 
-impl std::fmt::Debug for ContentPointQdrantRepositoryError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        error_chain_fmt(self, f)
+    fn check_health(&self) -> BoxFuture<'_, Result<(), VectorStoreError>> {
+        Box::pin(async move {
+            self.client
+                .health_check()
+                .await
+                .map_err(|e| VectorStoreError::QdrantError(e.to_string()))?;
+            Ok(())
+        })
     }
 }
 
@@ -115,6 +291,60 @@ impl From<ContentPoint> for PointStruct {
 
 impl From<ContentPointPayload> for HashMap<String, qdrant::Value> {
     fn from(payload: ContentPointPayload) -> Self {
-        HashMap::from([("content".into(), qdrant::Value::from(payload.content))])
+        let mut payload_map = HashMap::from([
+            (
+                "content_id".into(),
+                qdrant::Value::from(payload.content_id.to_string()),
+            ),
+            ("metadata".into(), qdrant::Value::from(payload.metadata)),
+            ("content".into(), qdrant::Value::from(payload.content)),
+        ]);
+
+        if let Some(source_meta_id) = payload.source_meta_id {
+            payload_map.insert(
+                SOURCE_META_ID_PAYLOAD_KEY.into(),
+                qdrant::Value::from(source_meta_id.to_string()),
+            );
+        }
+
+        payload_map
+    }
+}
+
+impl From<ScoredPoint> for ScoredContent {
+    /// Points saved before the content id was part of their payload are identified by their own id
+    fn from(mut point: ScoredPoint) -> Self {
+        let mut take_json = |key: &str| {
+            point
+                .payload
+                .remove(key)
+                .map(JsonValue::from)
+                .unwrap_or_default()
+        };
+
+        let content_id = take_json("content_id");
+        let metadata = take_json("metadata");
+        let content = take_json("content");
+        let source_meta_id = take_json("source_meta_id");
+
+        let point_id = match point.id.and_then(|id| id.point_id_options) {
+            Some(PointIdOptions::Uuid(id)) => Uuid::parse_str(&id).ok(),
+            _ => None,
+        };
+        let id = content_id
+            .as_str()
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .or(point_id)
+            .unwrap_or_default();
+
+        Self {
+            id,
+            metadata,
+            content: content.as_str().unwrap_or_default().to_string(),
+            source_meta_id: source_meta_id
+                .as_str()
+                .and_then(|id| Uuid::parse_str(id).ok()),
+            score: point.score,
+        }
     }
 }
@@ -0,0 +1,60 @@
+use common::{core::provider_credentials::ProviderCredentialsError, helper::error_chain_fmt};
+use futures::future::BoxFuture;
+
+use crate::domain::entities::content_point::Embeddings;
+
+/// Generates the embeddings of sentences with an embeddings model
+///
+/// Port to decouple the embeddings generation from the backend running the models:
+/// a local model, a remote embeddings API, or a simulation.
+/// All the models of a backend should map to a vector space of the size of the Qdrant collection.
+pub trait EmbeddingModelPort: Send + Sync {
+    /// # Returns
+    /// The embeddings of each sentence, in the order of the sentences
+    fn encode(
+        &self,
+        sentences: Vec<String>,
+        model_kind: EmbeddingsModelKind,
+    ) -> BoxFuture<'_, Result<Vec<Embeddings>, EmbeddingModelError>>;
+}
+
+/// Model of a backend used to generate embeddings
+///
+/// A backend without a specific model for a kind uses its text model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddingsModelKind {
+    Text,
+    Code,
+    /// For the contents in another language than English
+    Multilingual,
+}
+
+impl EmbeddingsModelKind {
+    /// Model of a text content in a given language (ISO 639-1 code), the text model if unknown
+    pub fn for_language(language: Option<&str>) -> Self {
+        match language {
+            Some(language) if language != "en" => EmbeddingsModelKind::Multilingual,
+            _ => EmbeddingsModelKind::Text,
+        }
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum EmbeddingModelError {
+    #[error("Embeddings model error: {0}")]
+    ModelError(String),
+    #[error("The embeddings model runner stopped")]
+    RunnerStopped,
+    #[error(transparent)]
+    RequestError(#[from] reqwest::Error),
+    #[error("Invalid response from the embeddings API: {0}")]
+    InvalidResponse(String),
+    #[error(transparent)]
+    ProviderCredentialsError(#[from] ProviderCredentialsError),
+}
+
+impl std::fmt::Debug for EmbeddingModelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
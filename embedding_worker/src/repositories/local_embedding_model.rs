@@ -0,0 +1,138 @@
+use futures::future::BoxFuture;
+use rust_bert::{
+    pipelines::sentence_embeddings::{SentenceEmbeddingsBuilder, SentenceEmbeddingsModelType},
+    RustBertError,
+};
+use std::{
+    sync::mpsc,
+    thread::{self, JoinHandle},
+};
+use tokio::{sync::oneshot, task};
+use tracing::info;
+
+use crate::{
+    domain::entities::content_point::Embeddings,
+    repositories::embedding_model_port::{
+        EmbeddingModelError, EmbeddingModelPort, EmbeddingsModelKind,
+    },
+};
+
+/// Embeddings models run locally, from Hugging Face
+///
+/// Using model AllMiniLmL12V2, and optionally a code-specific model for source code contents
+/// and a multilingual model for the contents detected in another language than English.
+/// The models run on the worker: no access to an external API is needed once they are downloaded.
+pub struct LocalEmbeddingModel {
+    sender_to_runner: mpsc::SyncSender<RunnerMessage>,
+    _thread_handle: JoinHandle<Result<(), RustBertError>>,
+}
+
+impl LocalEmbeddingModel {
+    /// Spawns a runner of the models on a separate thread
+    ///
+    /// # Params
+    /// - code_model_path: (optional) path to a local sentence embeddings model used for source code contents
+    /// - multilingual_model_path: (optional) path to a local sentence embeddings model used for the contents
+    ///   in another language than English
+    pub fn new(code_model_path: Option<String>, multilingual_model_path: Option<String>) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(100);
+        let handle =
+            thread::spawn(move || Self::runner(receiver, code_model_path, multilingual_model_path));
+
+        Self {
+            _thread_handle: handle,
+            sender_to_runner: sender,
+        }
+    }
+
+    /// The embeddings generator runner itself
+    ///
+    /// As running extensive calculations like running embeddings generation in a future should be avoided,
+    /// the runner needs to be in sync runtime.
+    ///
+    /// The message received by this runner contains the sentences to work on
+    /// and a sender to communicate the resulting embeddings
+    ///
+    /// Currently using all-MiniLM-L12-v2: maps sentences to a 384 dimensional dense vector space
+    ///
+    /// The code and multilingual models, if any, need to map to a vector space of the same dimension.
+    /// Without them, source code contents and contents in other languages are embedded with the text model.
+    #[tracing::instrument(name = "Runner", skip(receiver))]
+    fn runner(
+        receiver: mpsc::Receiver<RunnerMessage>,
+        code_model_path: Option<String>,
+        multilingual_model_path: Option<String>,
+    ) -> Result<(), RustBertError> {
+        let text_model =
+            SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllMiniLmL12V2)
+                .create_model()?;
+        info!("Embeddings model loaded ✅");
+
+        let code_model = match code_model_path {
+            Some(code_model_path) => {
+                let code_model =
+                    SentenceEmbeddingsBuilder::local(code_model_path).create_model()?;
+                info!("Code embeddings model loaded ✅");
+                Some(code_model)
+            }
+            None => None,
+        };
+
+        let multilingual_model = match multilingual_model_path {
+            Some(multilingual_model_path) => {
+                let multilingual_model =
+                    SentenceEmbeddingsBuilder::local(multilingual_model_path).create_model()?;
+                info!("Multilingual embeddings model loaded ✅");
+                Some(multilingual_model)
+            }
+            None => None,
+        };
+
+        while let Ok((sentences, model_kind, sender)) = receiver.recv() {
+            let model = match model_kind {
+                EmbeddingsModelKind::Text => &text_model,
+                EmbeddingsModelKind::Code => code_model.as_ref().unwrap_or(&text_model),
+                EmbeddingsModelKind::Multilingual => {
+                    multilingual_model.as_ref().unwrap_or(&text_model)
+                }
+            };
+
+            let sentences: Vec<&str> = sentences.iter().map(String::as_str).collect();
+            let embeddings = model
+                .encode(&sentences)
+                .map_err(|error| EmbeddingModelError::ModelError(error.to_string()));
+
+            // The requester may have stopped waiting
+            let _ = sender.send(embeddings);
+        }
+
+        Ok(())
+    }
+}
+
+impl EmbeddingModelPort for LocalEmbeddingModel {
+    fn encode(
+        &self,
+        sentences: Vec<String>,
+        model_kind: EmbeddingsModelKind,
+    ) -> BoxFuture<'_, Result<Vec<Embeddings>, EmbeddingModelError>> {
+        Box::pin(async move {
+            let (sender, receiver) = oneshot::channel();
+
+            // The runner stops if a model could not be loaded
+            task::block_in_place(|| self.sender_to_runner.send((sentences, model_kind, sender)))
+                .map_err(|_| EmbeddingModelError::RunnerStopped)?;
+
+            receiver
+                .await
+                .map_err(|_| EmbeddingModelError::RunnerStopped)?
+        })
+    }
+}
+
+/// Message type for internal channel, passing around input sentences, the model to use and generated embeddings
+type RunnerMessage = (
+    Vec<String>,
+    EmbeddingsModelKind,
+    oneshot::Sender<Result<Vec<Embeddings>, EmbeddingModelError>>,
+);
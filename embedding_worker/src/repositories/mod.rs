@@ -1 +1,11 @@
+pub mod content_point_pgvector_repository;
 pub mod content_point_qdrant_repository;
+pub mod embedding_model_port;
+pub mod local_embedding_model;
+pub mod remote_embedding_model;
+pub mod remote_summarization_model;
+pub mod simulated_embedding_model;
+pub mod simulated_summarization_model;
+pub mod summarization_model_port;
+pub mod tenant_embedding_model;
+pub mod vector_store_port;
@@ -0,0 +1,195 @@
+use futures::future::BoxFuture;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{
+    configuration::RemoteEmbeddingsSettings,
+    domain::entities::content_point::Embeddings,
+    repositories::embedding_model_port::{
+        EmbeddingModelError, EmbeddingModelPort, EmbeddingsModelKind,
+    },
+};
+
+#[derive(Debug, Serialize)]
+struct EmbeddingsRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dimensions: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingsResponseItem>,
+    #[serde(default)]
+    usage: Option<EmbeddingsUsage>,
+}
+
+/// Tokens billed for a request
+#[derive(Debug, Deserialize)]
+struct EmbeddingsUsage {
+    total_tokens: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingsResponseItem {
+    /// Index of the embedded input
+    index: usize,
+    embedding: Embeddings,
+}
+
+/// Embeddings of sentences, with the tokens billed by the API to generate them
+pub struct RemoteEmbeddings {
+    pub embeddings: Vec<Embeddings>,
+    /// 0 if the API does not report its usage
+    pub nb_tokens: u64,
+}
+
+/// Embeddings models served by a remote HTTP API
+///
+/// The API follows the OpenAI embeddings API, implemented by most providers and inference servers:
+/// `POST {url}/embeddings` with the `model` and the sentences as `input`.
+pub struct RemoteEmbeddingModel {
+    client: reqwest::Client,
+    settings: RemoteEmbeddingsSettings,
+}
+
+impl RemoteEmbeddingModel {
+    pub fn try_new(settings: RemoteEmbeddingsSettings) -> Result<Self, EmbeddingModelError> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(settings.timeout_ms))
+            .build()?;
+
+        Ok(Self { client, settings })
+    }
+
+    /// Name of the model of the API used for a kind of content, the text model by default
+    fn model_name(&self, model_kind: EmbeddingsModelKind) -> &str {
+        let model_name = match model_kind {
+            EmbeddingsModelKind::Text => None,
+            EmbeddingsModelKind::Code => self.settings.code_model.as_ref(),
+            EmbeddingsModelKind::Multilingual => self.settings.multilingual_model.as_ref(),
+        };
+
+        model_name.unwrap_or(&self.settings.text_model)
+    }
+
+    /// Generates the embeddings of sentences, with the usage of the API
+    pub async fn encode_with_usage(
+        &self,
+        sentences: Vec<String>,
+        model_kind: EmbeddingsModelKind,
+    ) -> Result<RemoteEmbeddings, EmbeddingModelError> {
+        let mut request = self
+            .client
+            .post(format!(
+                "{}/embeddings",
+                self.settings.url.trim_end_matches('/')
+            ))
+            .json(&EmbeddingsRequest {
+                model: self.model_name(model_kind),
+                input: &sentences,
+                dimensions: self.settings.dimensions,
+            });
+        if let Some(api_key) = &self.settings.api_key {
+            request = request.bearer_auth(api_key.expose_secret());
+        }
+
+        let response = request
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<EmbeddingsResponse>()
+            .await?;
+        let nb_tokens = response
+            .usage
+            .as_ref()
+            .map_or(0, |usage| usage.total_tokens);
+
+        Ok(RemoteEmbeddings {
+            embeddings: embeddings_from_response(response, sentences.len())?,
+            nb_tokens,
+        })
+    }
+}
+
+impl EmbeddingModelPort for RemoteEmbeddingModel {
+    fn encode(
+        &self,
+        sentences: Vec<String>,
+        model_kind: EmbeddingsModelKind,
+    ) -> BoxFuture<'_, Result<Vec<Embeddings>, EmbeddingModelError>> {
+        Box::pin(async move {
+            Ok(self
+                .encode_with_usage(sentences, model_kind)
+                .await?
+                .embeddings)
+        })
+    }
+}
+
+/// Embeddings of the sentences, in the order of the sentences
+///
+/// The API may not return the embeddings in the order of its inputs.
+fn embeddings_from_response(
+    mut response: EmbeddingsResponse,
+    nb_sentences: usize,
+) -> Result<Vec<Embeddings>, EmbeddingModelError> {
+    if response.data.len() != nb_sentences {
+        return Err(EmbeddingModelError::InvalidResponse(format!(
+            "{} embeddings for {} sentences",
+            response.data.len(),
+            nb_sentences
+        )));
+    }
+
+    response.data.sort_by_key(|item| item.index);
+
+    Ok(response
+        .data
+        .into_iter()
+        .map(|item| item.embedding)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn embeddings_are_ordered_as_the_sentences() {
+        let response: EmbeddingsResponse = serde_json::from_value(json!({
+            "object": "list",
+            "data": [
+                { "object": "embedding", "index": 1, "embedding": [0.0, 1.0] },
+                { "object": "embedding", "index": 0, "embedding": [1.0, 0.0] }
+            ],
+            "model": "text-embedding",
+            "usage": { "prompt_tokens": 12, "total_tokens": 12 },
+        }))
+        .unwrap();
+        assert_eq!(response.usage.as_ref().unwrap().total_tokens, 12);
+
+        let embeddings = embeddings_from_response(response, 2).unwrap();
+
+        assert_eq!(embeddings, vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+    }
+
+    #[test]
+    fn response_missing_embeddings_is_invalid() {
+        let response = EmbeddingsResponse {
+            data: vec![EmbeddingsResponseItem {
+                index: 0,
+                embedding: vec![1.0, 0.0],
+            }],
+            usage: None,
+        };
+
+        assert!(matches!(
+            embeddings_from_response(response, 2),
+            Err(EmbeddingModelError::InvalidResponse(_))
+        ));
+    }
+}
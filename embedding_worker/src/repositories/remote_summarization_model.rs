@@ -0,0 +1,153 @@
+use futures::future::BoxFuture;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{
+    configuration::RemoteSummarizationSettings,
+    repositories::summarization_model_port::{SummarizationModelError, SummarizationModelPort},
+};
+
+/// Instructions of the LLM summarizing a text
+const SUMMARY_INSTRUCTIONS: &str =
+    "Summarize the following text in a single paragraph of at most 150 words, \
+in the language of the text. Only answer with the summary.";
+
+#[derive(Debug, Serialize)]
+struct ChatCompletionRequest<'a> {
+    model: &'a str,
+    messages: [ChatMessage<'a>; 2],
+}
+
+#[derive(Debug, Serialize)]
+struct ChatMessage<'a> {
+    role: &'static str,
+    content: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionResponse {
+    choices: Vec<ChatCompletionChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionChoice {
+    message: ChatCompletionMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionMessage {
+    content: Option<String>,
+}
+
+/// LLM served by a remote HTTP API
+///
+/// The API follows the OpenAI chat completions API, implemented by most providers and inference servers:
+/// `POST {url}/chat/completions` with the `model` and the `messages`.
+pub struct RemoteSummarizationModel {
+    client: reqwest::Client,
+    settings: RemoteSummarizationSettings,
+}
+
+impl RemoteSummarizationModel {
+    pub fn try_new(settings: RemoteSummarizationSettings) -> Result<Self, SummarizationModelError> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(settings.timeout_ms))
+            .build()?;
+
+        Ok(Self { client, settings })
+    }
+}
+
+impl SummarizationModelPort for RemoteSummarizationModel {
+    fn model_name(&self) -> &str {
+        &self.settings.model
+    }
+
+    fn summarize(&self, text: String) -> BoxFuture<'_, Result<String, SummarizationModelError>> {
+        Box::pin(async move {
+            let mut request = self
+                .client
+                .post(format!(
+                    "{}/chat/completions",
+                    self.settings.url.trim_end_matches('/')
+                ))
+                .json(&ChatCompletionRequest {
+                    model: &self.settings.model,
+                    messages: [
+                        ChatMessage {
+                            role: "system",
+                            content: SUMMARY_INSTRUCTIONS,
+                        },
+                        ChatMessage {
+                            role: "user",
+                            content: &text,
+                        },
+                    ],
+                });
+            if let Some(api_key) = &self.settings.api_key {
+                request = request.bearer_auth(api_key.expose_secret());
+            }
+
+            let response = request
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<ChatCompletionResponse>()
+                .await?;
+
+            summary_from_response(response)
+        })
+    }
+}
+
+/// Content of the first choice of the completion, trimmed
+fn summary_from_response(
+    response: ChatCompletionResponse,
+) -> Result<String, SummarizationModelError> {
+    response
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.message.content)
+        .map(|content| content.trim().to_string())
+        .filter(|content| !content.is_empty())
+        .ok_or_else(|| {
+            SummarizationModelError::InvalidResponse("the completion has no content".to_string())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn summary_is_the_content_of_the_first_choice() {
+        let response: ChatCompletionResponse = serde_json::from_value(json!({
+            "id": "chatcmpl-1",
+            "choices": [
+                { "index": 0, "message": { "role": "assistant", "content": " Pods run containers. \n" } }
+            ],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            summary_from_response(response).unwrap(),
+            "Pods run containers."
+        );
+    }
+
+    #[test]
+    fn completion_without_content_is_invalid() {
+        let response: ChatCompletionResponse = serde_json::from_value(json!({
+            "choices": [{ "index": 0, "message": { "role": "assistant", "content": null } }],
+        }))
+        .unwrap();
+
+        assert!(matches!(
+            summary_from_response(response),
+            Err(SummarizationModelError::InvalidResponse(_))
+        ));
+    }
+}
@@ -0,0 +1,43 @@
+use futures::future::BoxFuture;
+use tracing::warn;
+
+use crate::{
+    domain::{
+        entities::content_point::Embeddings, services::simulated_embeddings::simulated_embeddings,
+    },
+    repositories::embedding_model_port::{
+        EmbeddingModelError, EmbeddingModelPort, EmbeddingsModelKind,
+    },
+};
+
+/// Deterministic pseudo-embeddings, the same for text, source code and multilingual contents
+///
+/// For environments without access to the models, for ex demos or end-to-end tests.
+pub struct SimulatedEmbeddingModel {
+    vector_size: usize,
+}
+
+impl SimulatedEmbeddingModel {
+    /// # Params
+    /// - vector_size: size of the generated vectors, the size of the vectors of the Qdrant collection
+    pub fn new(vector_size: usize) -> Self {
+        warn!("Embeddings are simulated: the semantic search only matches words");
+
+        Self { vector_size }
+    }
+}
+
+impl EmbeddingModelPort for SimulatedEmbeddingModel {
+    fn encode(
+        &self,
+        sentences: Vec<String>,
+        _model_kind: EmbeddingsModelKind,
+    ) -> BoxFuture<'_, Result<Vec<Embeddings>, EmbeddingModelError>> {
+        let embeddings = sentences
+            .iter()
+            .map(|sentence| simulated_embeddings(sentence, self.vector_size))
+            .collect();
+
+        Box::pin(async move { Ok(embeddings) })
+    }
+}
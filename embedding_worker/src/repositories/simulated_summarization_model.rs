@@ -0,0 +1,77 @@
+use futures::future::BoxFuture;
+use tracing::warn;
+
+use crate::repositories::summarization_model_port::{
+    SummarizationModelError, SummarizationModelPort,
+};
+
+/// Number of first words of a text kept as its simulated summary
+const SIMULATED_SUMMARY_NB_WORDS: usize = 60;
+
+/// Extractive pseudo-summaries: the first sentences of the text
+///
+/// For environments without access to an LLM, for ex demos or end-to-end tests.
+pub struct SimulatedSummarizationModel;
+
+impl SimulatedSummarizationModel {
+    pub fn new() -> Self {
+        warn!("Summaries are simulated: they are the first sentences of the sources");
+
+        Self
+    }
+}
+
+impl Default for SimulatedSummarizationModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SummarizationModelPort for SimulatedSummarizationModel {
+    fn model_name(&self) -> &str {
+        "simulation"
+    }
+
+    fn summarize(&self, text: String) -> BoxFuture<'_, Result<String, SummarizationModelError>> {
+        Box::pin(async move { Ok(first_sentences(&text, SIMULATED_SUMMARY_NB_WORDS)) })
+    }
+}
+
+/// First sentences of a text, up to the one reaching a number of words
+fn first_sentences(text: &str, nb_words: usize) -> String {
+    let mut words = vec![];
+
+    for word in text.split_whitespace() {
+        words.push(word);
+        if words.len() >= nb_words && word.ends_with(['.', '!', '?']) {
+            break;
+        }
+        // A text without sentence end is cut
+        if words.len() >= 2 * nb_words {
+            break;
+        }
+    }
+
+    words.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_ends_with_the_sentence_reaching_the_number_of_words() {
+        assert_eq!(
+            first_sentences("Pods run\n containers. They are scheduled. On nodes.", 3),
+            "Pods run containers."
+        );
+        assert_eq!(
+            first_sentences("Pods run containers. They are scheduled.", 4),
+            "Pods run containers. They are scheduled."
+        );
+        assert_eq!(
+            first_sentences("one two three four five", 2),
+            "one two three four"
+        );
+    }
+}
@@ -0,0 +1,29 @@
+use common::helper::error_chain_fmt;
+use futures::future::BoxFuture;
+
+/// Summarizes texts with a language model
+///
+/// Port to decouple the summarization from the backend running the model:
+/// a remote LLM API, or a simulation.
+pub trait SummarizationModelPort: Send + Sync {
+    /// Name of the model, saved with the summaries it generated
+    fn model_name(&self) -> &str;
+
+    /// # Params
+    /// - text: the contents of a chapter, or the summaries of the chapters of a source
+    fn summarize(&self, text: String) -> BoxFuture<'_, Result<String, SummarizationModelError>>;
+}
+
+#[derive(thiserror::Error)]
+pub enum SummarizationModelError {
+    #[error(transparent)]
+    RequestError(#[from] reqwest::Error),
+    #[error("Invalid response from the summarization API: {0}")]
+    InvalidResponse(String),
+}
+
+impl std::fmt::Debug for SummarizationModelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,184 @@
+use common::{
+    constants::routing_keys::PROVIDER_USAGE_ROUTING_KEY,
+    core::{
+        provider_credentials::{ProviderCredentialsCache, TenantProvider, TenantProviderSettings},
+        rabbitmq_message_repository::RabbitMQMessageRepository,
+    },
+    dtos::{
+        provider_credentials::ModelProviderDto,
+        provider_usage::{ProviderPurposeDto, ProviderUsageDto},
+    },
+};
+use futures::future::BoxFuture;
+use std::sync::{Arc, RwLock};
+use tracing::{error, info};
+
+use crate::{
+    configuration::RemoteEmbeddingsSettings,
+    domain::entities::content_point::Embeddings,
+    repositories::{
+        embedding_model_port::{EmbeddingModelError, EmbeddingModelPort, EmbeddingsModelKind},
+        remote_embedding_model::{RemoteEmbeddingModel, RemoteEmbeddings},
+    },
+};
+
+/// Embeddings model of the tenant of the worker: the model of the provider it brought (BYOK),
+/// or the model of the configured backend when it brought none
+///
+/// The credentials of the tenant are fetched from the gateway, and cached until they change.
+/// The usage of its provider is published for the gateway to attribute it to the tenant.
+pub struct TenantEmbeddingModel {
+    default_model: Box<dyn EmbeddingModelPort>,
+    provider_credentials: Arc<ProviderCredentialsCache>,
+    /// Initialized repository fetching the credentials and publishing the usage, on the exchange of the tenant
+    message_repository: RabbitMQMessageRepository,
+    settings: TenantProviderSettings,
+    /// Size of the embeddings of the vector store: the model of the tenant should generate embeddings of this size
+    vector_size: u64,
+    /// Model of the current credentials of the tenant, built again when they change
+    provider_model: RwLock<Option<(Arc<TenantProvider>, Arc<RemoteEmbeddingModel>)>>,
+}
+
+impl TenantEmbeddingModel {
+    pub fn new(
+        default_model: Box<dyn EmbeddingModelPort>,
+        provider_credentials: Arc<ProviderCredentialsCache>,
+        message_repository: RabbitMQMessageRepository,
+        settings: TenantProviderSettings,
+        vector_size: u64,
+    ) -> Self {
+        Self {
+            default_model,
+            provider_credentials,
+            message_repository,
+            settings,
+            vector_size,
+            provider_model: RwLock::new(None),
+        }
+    }
+
+    /// Model of the provider of the tenant, with its API key
+    fn provider_model(
+        &self,
+        provider: &Arc<TenantProvider>,
+    ) -> Result<Arc<RemoteEmbeddingModel>, EmbeddingModelError> {
+        if let Some((current_provider, model)) = self.provider_model.read().unwrap().as_ref() {
+            if Arc::ptr_eq(current_provider, provider) {
+                return Ok(model.clone());
+            }
+        }
+
+        info!(
+            "Embedding the contents with the model {} of the tenant",
+            provider.model
+        );
+        let model = Arc::new(RemoteEmbeddingModel::try_new(RemoteEmbeddingsSettings {
+            url: provider.api_url.clone(),
+            api_key: Some(provider.api_key.clone()),
+            // The tenant chooses a single model, for all the kinds of contents
+            text_model: provider.model.clone(),
+            code_model: None,
+            multilingual_model: None,
+            timeout_ms: self.settings.request_timeout_ms,
+            // Only the OpenAI models can be shortened to the size of the vector store
+            dimensions: (provider.provider == ModelProviderDto::OpenAi).then_some(self.vector_size),
+        })?);
+        *self.provider_model.write().unwrap() = Some((provider.clone(), model.clone()));
+
+        Ok(model)
+    }
+
+    /// Publishes a request to the provider of the tenant, for the gateway to attribute its usage to the tenant
+    ///
+    /// The usage is only informative: a failure to publish it does not fail the embedding.
+    async fn publish_usage(&self, nb_tokens: u64) {
+        let usage = ProviderUsageDto {
+            purpose: ProviderPurposeDto::Embedding,
+            nb_requests: 1,
+            nb_tokens,
+        };
+        let json_dto = match serde_json::to_string(&usage) {
+            Ok(json_dto) => json_dto,
+            Err(error) => {
+                error!(?error, "Failed to serialize the provider usage");
+                return;
+            }
+        };
+
+        if let Err(error) = self
+            .message_repository
+            .publish(PROVIDER_USAGE_ROUTING_KEY, json_dto.as_bytes())
+            .await
+        {
+            error!(?error, "Failed to publish the provider usage");
+        }
+    }
+}
+
+impl EmbeddingModelPort for TenantEmbeddingModel {
+    fn encode(
+        &self,
+        sentences: Vec<String>,
+        model_kind: EmbeddingsModelKind,
+    ) -> BoxFuture<'_, Result<Vec<Embeddings>, EmbeddingModelError>> {
+        Box::pin(async move {
+            let provider = self
+                .provider_credentials
+                .get(&self.message_repository, ProviderPurposeDto::Embedding)
+                .await?;
+            let Some(provider) = provider else {
+                return self.default_model.encode(sentences, model_kind).await;
+            };
+
+            let RemoteEmbeddings {
+                embeddings,
+                nb_tokens,
+            } = self
+                .provider_model(&provider)?
+                .encode_with_usage(sentences, model_kind)
+                .await?;
+            self.publish_usage(nb_tokens).await;
+
+            check_embeddings_size(&embeddings, self.vector_size, &provider.model)?;
+
+            Ok(embeddings)
+        })
+    }
+}
+
+/// Checks that the model of the tenant maps to the vector space of the vector store
+fn check_embeddings_size(
+    embeddings: &[Embeddings],
+    vector_size: u64,
+    model: &str,
+) -> Result<(), EmbeddingModelError> {
+    match embeddings
+        .iter()
+        .find(|embeddings| embeddings.len() as u64 != vector_size)
+    {
+        Some(embeddings) => Err(EmbeddingModelError::InvalidResponse(format!(
+            "embeddings of size {} from the model {} of the tenant, the vector store expects {}",
+            embeddings.len(),
+            model,
+            vector_size
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embeddings_of_another_size_than_the_vector_store_are_rejected() {
+        let embeddings = vec![vec![0.5; 384], vec![0.5; 384]];
+        assert!(check_embeddings_size(&embeddings, 384, "text-embedding-3-small").is_ok());
+
+        let embeddings = vec![vec![0.5; 1024]];
+        assert!(matches!(
+            check_embeddings_size(&embeddings, 384, "mistral-embed"),
+            Err(EmbeddingModelError::InvalidResponse(_))
+        ));
+    }
+}
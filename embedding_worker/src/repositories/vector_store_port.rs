@@ -0,0 +1,88 @@
+use common::helper::error_chain_fmt;
+use futures::future::BoxFuture;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::domain::entities::content_point::{ContentPoint, Embeddings, ScoredContent};
+
+/// Persists the content points and searches them by similarity
+///
+/// Port to decouple the handlers from the vector database: Qdrant, or Postgres with pgvector
+/// for the small deployments not running Qdrant.
+pub trait VectorStorePort: Send + Sync {
+    /// Saves the content points, replacing the points with the same id
+    fn upsert(
+        &self,
+        content_points: Vec<ContentPoint>,
+    ) -> BoxFuture<'_, Result<(), VectorStoreError>>;
+
+    /// Deletes all the content points of the contents extracted from a source
+    fn delete_by_source_meta_id(
+        &self,
+        source_meta_id: Uuid,
+    ) -> BoxFuture<'_, Result<(), VectorStoreError>>;
+
+    /// Deletes the points of a content other than the given points, left by a previous version of the content
+    /// with more sentences
+    fn delete_stale_points(
+        &self,
+        content_id: Uuid,
+        point_ids: Vec<Uuid>,
+    ) -> BoxFuture<'_, Result<(), VectorStoreError>>;
+
+    /// Deletes the content points of some contents extracted from a source, the points of its other contents being kept
+    fn delete_by_content_ids(
+        &self,
+        source_meta_id: Uuid,
+        content_ids: Vec<Uuid>,
+    ) -> BoxFuture<'_, Result<(), VectorStoreError>>;
+
+    /// Searches the contents matching a filter with the points the closest to a given vector, from the closest
+    ///
+    /// A content has one point per sentence: only its closest point is kept,
+    /// so fewer than `limit` contents can be returned.
+    fn search(
+        &self,
+        vector: Embeddings,
+        limit: u64,
+        filter: VectorSearchFilter,
+    ) -> BoxFuture<'_, Result<Vec<ScoredContent>, VectorStoreError>>;
+
+    /// Checks that the vector database answers, for the readiness probe of the worker
+    fn check_health(&self) -> BoxFuture<'_, Result<(), VectorStoreError>>;
+}
+
+/// Filter on the metadata of the searched contents
+#[derive(Debug, Clone)]
+pub struct VectorSearchFilter {
+    /// Only the contents of this user are searched
+    pub user_id: Uuid,
+    /// Only the contents detected in this language (ISO 639-1 code) are searched, if any
+    pub language: Option<String>,
+    /// Only the rows of structured sources whose fields have these values are searched, if any
+    pub fields: BTreeMap<String, String>,
+    /// Only the contents of these sources are searched, if any
+    pub source_meta_ids: Option<Vec<Uuid>>,
+    /// Only the contents of this chapter are searched, whatever the reader of their source, if any
+    pub chapter: Option<String>,
+    /// Only the contents with this keyword are searched, if any
+    pub keyword: Option<String>,
+    /// Only the contents mentioning this named entity, whatever its kind, are searched, if any
+    pub entity: Option<String>,
+}
+
+#[derive(thiserror::Error)]
+pub enum VectorStoreError {
+    #[error("Error from Qdrant: {0}")]
+    QdrantError(String),
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error("Invalid vector store configuration: {0}")]
+    ConfigurationError(String),
+}
+
+impl std::fmt::Debug for VectorStoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
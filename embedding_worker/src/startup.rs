@@ -1,18 +1,60 @@
 use crate::{
-    configuration::{QdrantSettings, RabbitMQSettings, Settings},
-    domain::services::huggingface_embedding::{
-        HuggingFaceEmbeddingsService, HuggingFaceEmbeddingsServiceError,
+    configuration::{
+        EmbeddingsBackend, QdrantSettings, RabbitMQSettings, Settings, SummarizationBackend,
+        SummarizationSettings, VectorStoreBackend,
     },
-    handlers::handler_content_extracted::{self, RegisterHandlerContentExtractedError},
-    repositories::content_point_qdrant_repository::{
-        ContentPointQdrantRepository, ContentPointQdrantRepositoryError,
+    domain::services::{
+        embeddings_service::EmbeddingsService, summarization_service::SummarizationService,
     },
+    handlers::{
+        handler_content_extracted::{
+            self, ConsumptionControl, RegisterHandlerContentExtractedError,
+        },
+        handler_delete_content::{self, RegisterHandlerDeleteContentError},
+        handler_search_semantic::{self, RegisterHandlerSearchSemanticError},
+        handler_summarize_source::{self, RegisterHandlerSummarizeSourceError},
+    },
+    repositories::{
+        content_point_pgvector_repository::ContentPointPgvectorRepository,
+        content_point_qdrant_repository::ContentPointQdrantRepository,
+        embedding_model_port::{EmbeddingModelError, EmbeddingModelPort},
+        local_embedding_model::LocalEmbeddingModel,
+        remote_embedding_model::RemoteEmbeddingModel,
+        remote_summarization_model::RemoteSummarizationModel,
+        simulated_embedding_model::SimulatedEmbeddingModel,
+        simulated_summarization_model::SimulatedSummarizationModel,
+        summarization_model_port::{SummarizationModelError, SummarizationModelPort},
+        tenant_embedding_model::TenantEmbeddingModel,
+        vector_store_port::{VectorStoreError, VectorStorePort},
+    },
+};
+use common::{
+    core::{
+        consumer_handover::{ConsumerHandover, ConsumerHandoverError},
+        handled_message_postgres_repository::HandledMessagePostgresRepository,
+        health_server::{
+            run_health_server, DependencyCheck, HealthChecks, RabbitMQConnectionCheck,
+        },
+        idempotency_store::{
+            IdempotencySettings, IdempotencyStoreError, IdempotencyStorePort, NoOpIdempotencyStore,
+        },
+        memory_ceiling::{ConsumptionThrottle, MemorySettings},
+        message_signing::{MessageSigner, MessageSigningError},
+        normalization_rules::{watch_normalization_rules_changes, NormalizationRulesCache},
+        provider_credentials::{watch_provider_credentials_changes, ProviderCredentialsCache},
+        rabbitmq_message_repository::{RabbitMQMessageRepository, RabbitMQMessageRepositoryError},
+        secrets::{SecretsCipher, SecretsError},
+    },
+    dtos::extract_content_job::IngestionLaneDto,
+};
+use futures::{
+    future::{join_all, BoxFuture},
+    TryFutureExt,
 };
-use common::core::rabbitmq_message_repository::RabbitMQMessageRepository;
-use futures::{future::join_all, TryFutureExt};
 use lapin::Connection as RabbitMQConnection;
 use qdrant_client::prelude::{QdrantClient, QdrantClientConfig};
-use std::sync::Arc;
+use sqlx::postgres::PgPoolOptions;
+use std::{net::TcpListener, sync::Arc};
 use tokio::task::JoinHandle;
 use tracing::{error, info};
 
@@ -22,6 +64,16 @@ pub struct Application {
     _rabbitmq_publishing_connection: Arc<RabbitMQConnection>,
     rabbitmq_content_exchange_name: String,
     rabbitmq_queue_name_prefix: String,
+    rabbitmq_prefetch_count: u16,
+    rabbitmq_fast_lane_prefetch_count: u16,
+    memory_settings: MemorySettings,
+    summarization_settings: SummarizationSettings,
+    // Only the instance holding the lease consumes messages
+    consumer_handover: ConsumerHandover,
+    // Port of the health probes (and memory debug endpoints), useful when binding a random port
+    health_port: u16,
+    // Contents delivered again once embedded are acknowledged without being embedded again
+    idempotency_store: Arc<dyn IdempotencyStorePort>,
 
     // handlers: Vec<Box<dyn Future<Output = Result<(), ApplicationError>>>>,
     handlers: Vec<JoinHandle<Result<(), ApplicationError>>>,
@@ -32,49 +84,178 @@ impl Application {
     pub async fn build(settings: Settings) -> Result<Self, ApplicationError> {
         // TODO: handle connections with a re-connection strategy
         // One connection for consuming messages, one for publishing messages
-        let rabbitmq_consuming_connection = get_rabbitmq_connection(&settings.rabbitmq).await?;
+        let rabbitmq_consuming_connection =
+            Arc::new(get_rabbitmq_connection(&settings.rabbitmq).await?);
         let rabbitmq_publishing_connection =
             Arc::new(get_rabbitmq_connection(&settings.rabbitmq).await?);
 
-        let rabbitmq_content_exchange_name = format!(
-            "{}_{}",
-            settings.rabbitmq.exchange_name_prefix, settings.rabbitmq.content_exchange
-        );
+        let rabbitmq_content_exchange_name = settings.rabbitmq.content_exchange_name();
 
         let message_repository = RabbitMQMessageRepository::new(
             rabbitmq_publishing_connection.clone(),
             &rabbitmq_content_exchange_name,
+        )
+        .with_signer(MessageSigner::try_new(settings.message_signing)?)
+        .with_publisher_confirms(settings.rabbitmq.publisher_confirms);
+
+        // Sharing the same vector store with parallel handlers/threads
+        let vector_store: Arc<dyn VectorStorePort> = match settings.vector_store.backend {
+            VectorStoreBackend::Qdrant => {
+                // TODO: Qdrant client is using grpc channel (?): should we have 1 channel per thread ?
+                // And do the same initialization than with RabbitMQ ?
+                // If use Qdrant during integration test: create several qdrant client
+                let qdrant_client = get_qdrant_client(&settings.qdrant)?;
+                Arc::new(
+                    ContentPointQdrantRepository::try_new(
+                        qdrant_client,
+                        &settings.qdrant.collection,
+                        &settings.qdrant.collection_distance,
+                        settings.qdrant.collection_vector_size,
+                    )
+                    .await?,
+                )
+            }
+            VectorStoreBackend::Pgvector => {
+                let pgvector_settings =
+                    settings.vector_store.pgvector.clone().ok_or_else(|| {
+                        ApplicationError::ConfigurationError(
+                            "vector_store.pgvector should be set for the pgvector backend"
+                                .to_string(),
+                        )
+                    })?;
+                let db_pool = PgPoolOptions::new()
+                    .acquire_timeout(std::time::Duration::from_secs(2))
+                    .connect_lazy_with(pgvector_settings.with_db());
+                Arc::new(
+                    ContentPointPgvectorRepository::try_new(
+                        db_pool,
+                        &pgvector_settings.table,
+                        &settings.qdrant.collection_distance,
+                        settings.qdrant.collection_vector_size,
+                    )
+                    .await?,
+                )
+            }
+        };
+
+        // Serves the probes before waiting for the consumer lease, for the instance not to be restarted meanwhile
+        let health_checks = HealthChecks::new()
+            .with_liveness_check(
+                "rabbitmq_consuming",
+                RabbitMQConnectionCheck::new(&rabbitmq_consuming_connection),
+            )
+            .with_liveness_check(
+                "rabbitmq_publishing",
+                RabbitMQConnectionCheck::new(&rabbitmq_publishing_connection),
+            )
+            .with_readiness_check("vector_store", VectorStoreCheck(vector_store.clone()));
+        let listener = TcpListener::bind(format!(
+            "{}:{}",
+            settings.application.host, settings.application.port
+        ))?;
+        let health_port = listener.local_addr()?.port();
+        let memory_debug = settings
+            .memory
+            .debug_endpoints
+            .then(|| settings.memory.clone());
+
+        tokio::spawn(
+            run_health_server(listener, health_checks, memory_debug).inspect_err(|error| {
+                error!(?error, "Health server stopped");
+            }),
         );
 
-        // TODO: Qdrant client is using grpc channel (?): should we have 1 channel per thread ?
-        // And do the same initialization than with RabbitMQ ?
-        // If use Qdrant during integration test: create several qdrant client
-        let qdrant_client = get_qdrant_client(&settings.qdrant)?;
-        let content_point_qdrant_repository = ContentPointQdrantRepository::try_new(
-            qdrant_client,
-            &settings.qdrant.collection,
-            &settings.qdrant.collection_distance,
-            settings.qdrant.collection_vector_size,
-        )
-        .await?;
-        // Sharing the same qdrant repository with parallel handlers/threads
-        let content_point_qdrant_repository = Arc::new(content_point_qdrant_repository);
+        // Waits for the previous instance, if any, to hand over the consumption
+        let mut consumer_handover =
+            ConsumerHandover::new(settings.handover, &rabbitmq_content_exchange_name);
+        consumer_handover
+            .acquire(&rabbitmq_publishing_connection)
+            .await?;
 
-        // The model type could come from the configuration
-        let embeddings_service = HuggingFaceEmbeddingsService::new();
+        // Simulated embeddings do not need any model to be loaded
+        let embedding_model: Box<dyn EmbeddingModelPort> = match settings.embeddings.backend {
+            EmbeddingsBackend::HuggingFace => Box::new(LocalEmbeddingModel::new(
+                settings.embeddings.code_model_path.clone(),
+                settings.embeddings.multilingual_model_path.clone(),
+            )),
+            EmbeddingsBackend::Remote => {
+                let remote_settings = settings.embeddings.remote.clone().ok_or_else(|| {
+                    ApplicationError::ConfigurationError(
+                        "embeddings.remote should be set for the remote backend".to_string(),
+                    )
+                })?;
+                Box::new(RemoteEmbeddingModel::try_new(remote_settings)?)
+            }
+            EmbeddingsBackend::Simulation => Box::new(SimulatedEmbeddingModel::new(
+                settings.qdrant.collection_vector_size as usize,
+            )),
+        };
+        // The contents of a tenant having brought its model provider are embedded with its model
+        let embedding_model: Box<dyn EmbeddingModelPort> = if settings.tenant_provider.enabled {
+            let secrets_settings = settings.secrets.clone().ok_or_else(|| {
+                ApplicationError::ConfigurationError(
+                    "secrets should be set for the tenant provider".to_string(),
+                )
+            })?;
+            let provider_credentials = Arc::new(ProviderCredentialsCache::new(
+                settings.tenant_provider.clone(),
+                SecretsCipher::try_new(secrets_settings)?,
+            ));
+            watch_provider_credentials_changes(
+                &rabbitmq_publishing_connection,
+                &rabbitmq_content_exchange_name,
+                provider_credentials.clone(),
+            )
+            .await?;
+
+            Box::new(TenantEmbeddingModel::new(
+                embedding_model,
+                provider_credentials,
+                message_repository.clone().try_init().await?,
+                settings.tenant_provider.clone(),
+                settings.qdrant.collection_vector_size,
+            ))
+        } else {
+            embedding_model
+        };
+        let embeddings_service = EmbeddingsService::new(embedding_model);
+        let summarization_service = get_summarization_service(&settings.summarization)?;
+        let idempotency_store = get_idempotency_store(&settings.idempotency)?;
+
+        // The rules of the tenant are cached until the gateway publishes a change
+        let normalization_rules = Arc::new(NormalizationRulesCache::new(
+            settings.normalization_rules.clone(),
+        ));
+        if settings.normalization_rules.enabled {
+            watch_normalization_rules_changes(
+                &rabbitmq_publishing_connection,
+                &rabbitmq_content_exchange_name,
+                normalization_rules.clone(),
+            )
+            .await?;
+        }
 
         let mut app = Self {
             _rabbitmq_publishing_connection: rabbitmq_publishing_connection,
             rabbitmq_content_exchange_name,
-            rabbitmq_queue_name_prefix: settings.rabbitmq.queue_name_prefix,
+            rabbitmq_queue_name_prefix: settings.rabbitmq.tenant_queue_name_prefix(),
+            rabbitmq_prefetch_count: settings.rabbitmq.prefetch_count,
+            rabbitmq_fast_lane_prefetch_count: settings.rabbitmq.fast_lane_prefetch_count,
+            memory_settings: settings.memory,
+            summarization_settings: settings.summarization,
+            consumer_handover,
+            health_port,
+            idempotency_store,
             handlers: vec![],
         };
 
         app.prepare_message_handlers(
             rabbitmq_consuming_connection,
             message_repository,
-            content_point_qdrant_repository,
+            vector_store,
             embeddings_service,
+            summarization_service,
+            normalization_rules,
         )
         .await?;
 
@@ -90,17 +271,22 @@ impl Application {
             self,
             rabbitmq_consuming_connection,
             message_repository,
-            content_point_qdrant_repository,
-            embeddings_service
+            vector_store,
+            embeddings_service,
+            summarization_service,
+            normalization_rules
         )
     )]
     pub async fn prepare_message_handlers(
         &mut self,
-        rabbitmq_consuming_connection: RabbitMQConnection,
+        rabbitmq_consuming_connection: Arc<RabbitMQConnection>,
         // Not an `Arc` shared reference as we want to initialize a new repository for each thread (or at least for each handler)
         message_repository: RabbitMQMessageRepository,
-        content_point_qdrant_repository: Arc<ContentPointQdrantRepository>,
-        embeddings_service: HuggingFaceEmbeddingsService,
+        vector_store: Arc<dyn VectorStorePort>,
+        embeddings_service: EmbeddingsService,
+        // Set when the sources are summarized
+        summarization_service: Option<SummarizationService>,
+        normalization_rules: Arc<NormalizationRulesCache>,
     ) -> Result<(), ApplicationError> {
         let exchange_name = self.rabbitmq_content_exchange_name.clone();
         let queue_name_prefix = self.rabbitmq_queue_name_prefix.clone();
@@ -108,14 +294,81 @@ impl Application {
 
         // We could have several message handlers running in parallel bound with the same binding key to the same exchange.
         // Or other message handlers bound with a different binding key to the same or another exchange.
+        // The contents of each ingestion lane are embedded by their own handler,
+        // with a low prefetch on the fast lane for a small source not to wait behind prefetched contents
+        for (lane, prefetch_count) in [
+            (IngestionLaneDto::Bulk, self.rabbitmq_prefetch_count),
+            (
+                IngestionLaneDto::Fast,
+                self.rabbitmq_fast_lane_prefetch_count,
+            ),
+        ] {
+            let handler = tokio::spawn(
+                handler_content_extracted::register_handler(
+                    rabbitmq_consuming_connection.clone(),
+                    exchange_name.clone(),
+                    queue_name_prefix.clone(),
+                    message_repository.clone(),
+                    vector_store.clone(),
+                    embeddings_service.clone(),
+                    ConsumptionControl {
+                        throttle: ConsumptionThrottle::new(
+                            self.memory_settings.clone(),
+                            prefetch_count,
+                        ),
+                        stop_consuming: self.consumer_handover.stop_consuming_token(),
+                        lane,
+                        idempotency_store: self.idempotency_store.clone(),
+                    },
+                )
+                .map_err(|e| e.into()),
+            );
+
+            self.handlers.push(handler);
+        }
+
+        // Summarizes the sources once their extraction is completed
+        if let Some(summarization_service) = summarization_service {
+            let handler = tokio::spawn(
+                handler_summarize_source::register_handler(
+                    rabbitmq_consuming_connection.clone(),
+                    exchange_name.clone(),
+                    queue_name_prefix.clone(),
+                    message_repository.clone(),
+                    Arc::new(summarization_service),
+                    self.summarization_settings.clone(),
+                    self.consumer_handover.stop_consuming_token(),
+                )
+                .map_err(|e| e.into()),
+            );
+
+            self.handlers.push(handler);
+        }
+
+        // Responds to the semantic search RPC calls
         let handler = tokio::spawn(
-            handler_content_extracted::register_handler(
+            handler_search_semantic::register_handler(
+                rabbitmq_consuming_connection.clone(),
+                exchange_name.clone(),
+                queue_name_prefix.clone(),
+                message_repository,
+                vector_store.clone(),
+                embeddings_service,
+                normalization_rules,
+            )
+            .map_err(|e| e.into()),
+        );
+
+        self.handlers.push(handler);
+
+        // Deletes the content points of deleted sources
+        let handler = tokio::spawn(
+            handler_delete_content::register_handler(
                 rabbitmq_consuming_connection,
                 exchange_name,
                 queue_name_prefix,
-                message_repository.clone(),
-                content_point_qdrant_repository.clone(),
-                embeddings_service.clone(),
+                vector_store,
+                self.consumer_handover.stop_consuming_token(),
             )
             .map_err(|e| e.into()),
         );
@@ -129,7 +382,7 @@ impl Application {
     ///
     /// self is moved in order for the application not to drop out of scope
     /// and move into a thread for ex
-    pub async fn run_until_stopped(self) -> Result<(), ApplicationError> {
+    pub async fn run_until_stopped(mut self) -> Result<(), ApplicationError> {
         let handler_results = join_all(self.handlers).await;
 
         info!(
@@ -137,9 +390,81 @@ impl Application {
             handler_results
         );
 
+        // In-flight messages have been handled: the next instance can start consuming
+        self.consumer_handover.release().await;
+
         info!("👋 Bye!");
         Ok(())
     }
+
+    pub fn health_port(&self) -> u16 {
+        self.health_port
+    }
+}
+
+/// Vector database of the worker: the embeddings are saved and searched in it
+struct VectorStoreCheck(Arc<dyn VectorStorePort>);
+
+impl DependencyCheck for VectorStoreCheck {
+    fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(async move {
+            self.0
+                .check_health()
+                .await
+                .map_err(|error| error.to_string())
+        })
+    }
+}
+
+/// Sets up the summarization of the sources with the model of the configured backend, if enabled
+pub fn get_summarization_service(
+    settings: &SummarizationSettings,
+) -> Result<Option<SummarizationService>, ApplicationError> {
+    if !settings.enabled {
+        return Ok(None);
+    }
+
+    let summarization_model: Box<dyn SummarizationModelPort> = match settings.backend {
+        SummarizationBackend::Remote => {
+            let remote_settings = settings.remote.clone().ok_or_else(|| {
+                ApplicationError::ConfigurationError(
+                    "summarization.remote should be set for the remote backend".to_string(),
+                )
+            })?;
+            Box::new(RemoteSummarizationModel::try_new(remote_settings)?)
+        }
+        SummarizationBackend::Simulation => Box::new(SimulatedSummarizationModel::new()),
+    };
+    info!(
+        "Summarizing the sources with the model {}",
+        summarization_model.model_name()
+    );
+
+    Ok(Some(SummarizationService::new(summarization_model)))
+}
+
+/// Gets the store of the handled messages, remembering no message without idempotency
+///
+/// The expired handled messages are forgotten periodically.
+pub fn get_idempotency_store(
+    settings: &IdempotencySettings,
+) -> Result<Arc<dyn IdempotencyStorePort>, ApplicationError> {
+    if !settings.enabled {
+        return Ok(Arc::new(NoOpIdempotencyStore));
+    }
+
+    let database_url = settings.database_url.as_ref().ok_or_else(|| {
+        ApplicationError::ConfigurationError(
+            "idempotency.database_url should be set when the idempotency is enabled".to_string(),
+        )
+    })?;
+    let repository = HandledMessagePostgresRepository::try_new(database_url)?;
+    tokio::spawn(repository.clone().run_purge(
+        chrono::Duration::hours(settings.retention_h.into()),
+        std::time::Duration::from_millis(settings.purge_interval_ms),
+    ));
+
+    Ok(Arc::new(repository))
 }
 
 /// Creates a connection to RabbitMQ
@@ -164,9 +489,29 @@ pub enum ApplicationError {
     #[error(transparent)]
     RegisterHandlerContentExtractedError(#[from] RegisterHandlerContentExtractedError),
     #[error(transparent)]
-    HuggingFaceEmbeddingsServiceError(#[from] HuggingFaceEmbeddingsServiceError),
+    RegisterHandlerDeleteContentError(#[from] RegisterHandlerDeleteContentError),
+    #[error(transparent)]
+    RegisterHandlerSearchSemanticError(#[from] RegisterHandlerSearchSemanticError),
+    #[error(transparent)]
+    RegisterHandlerSummarizeSourceError(#[from] RegisterHandlerSummarizeSourceError),
+    #[error(transparent)]
+    ConsumerHandoverError(#[from] ConsumerHandoverError),
+    #[error(transparent)]
+    EmbeddingModelError(#[from] EmbeddingModelError),
+    #[error(transparent)]
+    SummarizationModelError(#[from] SummarizationModelError),
+    #[error("Invalid configuration: {0}")]
+    ConfigurationError(String),
     #[error("Error from Qdrant: {0}")]
     QdrantError(String),
     #[error(transparent)]
-    ContentPointQdrantRepositoryError(#[from] ContentPointQdrantRepositoryError),
+    VectorStoreError(#[from] VectorStoreError),
+    #[error(transparent)]
+    MessageSigningError(#[from] MessageSigningError),
+    #[error(transparent)]
+    IdempotencyStoreError(#[from] IdempotencyStoreError),
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+    #[error(transparent)]
+    SecretsError(#[from] SecretsError),
 }
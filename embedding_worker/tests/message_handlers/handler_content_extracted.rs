@@ -1,6 +1,8 @@
 use crate::helpers::{spawn_app, TestApp};
 use chrono::Utc;
-use common::dtos::extracted_content::ExtractedContentDto;
+use common::dtos::{
+    extracted_content::ExtractedContentDto, templates::message_envelope::MessageEnvelope,
+};
 use embedding_worker::handlers::handler_content_extracted::ROUTING_KEY;
 use fake::{faker::lorem::en::Sentences, Fake};
 use futures::lock::Mutex;
@@ -40,12 +42,20 @@ async fn handler_binds_queue_to_exchange_and_acknowledges_content_extracted_mess
         });
 
     let extracted_content = ExtractedContentDto {
+        version: ExtractedContentDto::CURRENT_VERSION,
         id: Uuid::new_v4(),
         metadata: json!({}),
         content: Sentences(3..10).fake::<Vec<String>>().join(" "),
+        skip_embedding: false,
+        is_code: false,
+        source_meta_id: None,
+        fulltext_shard: 0,
+        provenance: None,
     };
 
-    let message = serde_json::to_string(&extracted_content).unwrap();
+    let message = MessageEnvelope::new(extracted_content)
+        .try_serializing()
+        .unwrap();
     info!("Extracted content message: {}", message);
 
     // Sends the job message to the worker binding key
@@ -191,7 +191,14 @@ pub async fn spawn_app() -> TestApp {
 
     // Randomizes configuration to ensure test isolation: random prefix for our queue names
     let configuration = {
-        let mut c = get_configuration().expect("Failed to read configuration.");
+        let mut c = get_configuration()
+            .await
+            .expect("Failed to read configuration.");
+
+        // Binds a random port for the health probes, for the tests to run in parallel
+        c.application.port = 0;
+        // No gateway answers with the normalization rules
+        c.normalization_rules.enabled = false;
 
         // Uses a different exchange and queue names for each test case
         c.rabbitmq.exchange_name_prefix = format!(
@@ -0,0 +1,164 @@
+//! Indexing rate and search latency against Meilisearch on standard fixtures
+//!
+//! Needs the Meilisearch instance of the `bench` docker-compose profile:
+//! `docker compose --profile bench up -d meilisearch-bench`
+//! Its URL and key can be overridden with `BENCH_MEILISEARCH_URL` and `BENCH_MEILISEARCH_API_KEY`.
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use meilisearch_sdk::Client;
+use serde_json::json;
+use std::{collections::BTreeMap, time::Duration};
+use tokio::runtime::Runtime;
+use uuid::Uuid;
+
+use fulltext_search_service::{
+    domain::entities::content::ContentEntity,
+    repositories::meilisearch_content_repository::{
+        ContentSearchFilter, ContentSearchPage, MeilisearchContentRepository,
+    },
+};
+
+const DEFAULT_MEILISEARCH_URL: &str = "http://127.0.0.1:7701";
+const DEFAULT_MEILISEARCH_API_KEY: &str = "masterkey";
+const INDEXING_BATCH_SIZES: [usize; 2] = [100, 1000];
+const NB_SEARCHED_CONTENTS: usize = 5000;
+/// Indexing the searched contents can take longer than the default wait timeout
+const INDEXING_TIMEOUT: Duration = Duration::from_secs(120);
+const SEARCH_QUERIES: [&str; 3] = ["water cycle", "evaporates clouds rain", "condensation"];
+
+const FIXTURE_SENTENCES: [&str; 4] = [
+    "The water cycle describes how water evaporates, condenses into clouds and falls back as rain.",
+    "Plants release water vapor into the atmosphere through a process called transpiration.",
+    "Rivers carry water from the mountains to the oceans, shaping the landscape on their way.",
+    "Groundwater is stored in aquifers and can take thousands of years to be renewed.",
+];
+
+fn meilisearch_client() -> Client {
+    let url = std::env::var("BENCH_MEILISEARCH_URL").unwrap_or(DEFAULT_MEILISEARCH_URL.to_string());
+    let api_key = std::env::var("BENCH_MEILISEARCH_API_KEY")
+        .unwrap_or(DEFAULT_MEILISEARCH_API_KEY.to_string());
+
+    Client::new(url, Some(api_key))
+}
+
+/// Contents of a user made of 3 fixture sentences, as extracted contents would be
+fn fixture_contents(nb_contents: usize, user_id: Uuid) -> Vec<ContentEntity> {
+    (0..nb_contents)
+        .map(|i| ContentEntity {
+            id: Uuid::new_v4(),
+            metadata: json!({ "benchmark": { "content_number": i }, "user_id": user_id }),
+            content: (0..3)
+                .map(|j| FIXTURE_SENTENCES[(i + j) % FIXTURE_SENTENCES.len()])
+                .collect::<Vec<&str>>()
+                .join(" "),
+            source_meta_id: None,
+        })
+        .collect()
+}
+
+/// Creates an empty index, named uniquely to not depend on previous runs
+async fn create_index(client: &Client) -> String {
+    let index = format!("benchmark_{}", Uuid::new_v4());
+    client
+        .create_index(&index, Some("id"))
+        .await
+        .expect("Meilisearch to be reachable")
+        .wait_for_completion(client, None, None)
+        .await
+        .unwrap();
+    index
+}
+
+async fn index_contents(client: &Client, index: &str, contents: &[ContentEntity]) {
+    client
+        .index(index)
+        .add_or_replace(contents, None)
+        .await
+        .unwrap()
+        .wait_for_completion(client, None, Some(INDEXING_TIMEOUT))
+        .await
+        .unwrap();
+}
+
+fn indexing_benchmark(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let client = meilisearch_client();
+    let index = runtime.block_on(create_index(&client));
+
+    let mut group = c.benchmark_group("fulltext_indexing");
+    // Indexing is slow: reduces the number of samples to keep the run under a few minutes
+    group.sample_size(10);
+
+    for batch_size in INDEXING_BATCH_SIZES {
+        group.throughput(Throughput::Elements(batch_size as u64));
+        group.bench_function(BenchmarkId::new("contents", batch_size), |b| {
+            b.to_async(&runtime).iter_batched(
+                || fixture_contents(batch_size, Uuid::new_v4()),
+                |contents| {
+                    let client = &client;
+                    let index = &index;
+                    async move { index_contents(client, index, &contents).await }
+                },
+                criterion::BatchSize::PerIteration,
+            )
+        });
+    }
+
+    group.finish();
+    runtime.block_on(delete_index(&client, &index));
+}
+
+fn search_benchmark(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let client = meilisearch_client();
+    let user_id = Uuid::new_v4();
+    let index = runtime.block_on(create_index(&client));
+
+    // Searches as the search handler does
+    let repository = MeilisearchContentRepository::new(client.clone(), index.clone());
+    runtime.block_on(async {
+        // The tasks of an index are processed in order: the index is set up once the contents are indexed
+        repository.set_up_index().await.unwrap();
+        index_contents(
+            &client,
+            &index,
+            &fixture_contents(NB_SEARCHED_CONTENTS, user_id),
+        )
+        .await;
+    });
+
+    let fields = BTreeMap::new();
+    let filter = ContentSearchFilter {
+        language: None,
+        fields: &fields,
+        source_meta_ids: None,
+        chapter: None,
+        keyword: None,
+        entity: None,
+    };
+    let page = ContentSearchPage {
+        page: 1,
+        hits_per_page: 10,
+        sort: Default::default(),
+    };
+    let mut group = c.benchmark_group("fulltext_search_latency");
+    for query in SEARCH_QUERIES {
+        group.bench_with_input(BenchmarkId::new("query", query), query, |b, query| {
+            b.to_async(&runtime).iter(|| async {
+                repository
+                    .search(query, &page, user_id, 0, &filter)
+                    .await
+                    .unwrap()
+            })
+        });
+    }
+
+    group.finish();
+    runtime.block_on(delete_index(&client, &index));
+}
+
+async fn delete_index(client: &Client, index: &str) {
+    client.index(index).delete().await.unwrap();
+}
+
+criterion_group!(benches, indexing_benchmark, search_benchmark);
+criterion_main!(benches);
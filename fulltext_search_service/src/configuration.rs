@@ -1,3 +1,11 @@
+use common::core::{
+    configuration::{ConfigurationError, ConfigurationLayers},
+    message_signing::MessageSigningSettings,
+    normalization_rules::NormalizationRulesSettings,
+    rabbitmq_message_repository::{broker_uri, BrokerCredentials},
+    retry::RetryPolicy,
+    tenancy::{tenant_name_prefix, TenantSettings},
+};
 use lapin::ConnectionProperties;
 use secrecy::Secret;
 use serde::Deserialize;
@@ -8,6 +16,14 @@ pub struct Settings {
     pub application: ApplicationSettings,
     pub rabbitmq: RabbitMQSettings,
     pub meilisearch: MeilisearchSettings,
+    /// Retry policy on transient failures of Meilisearch
+    pub retry: RetryPolicy,
+    /// Signing of the published messages, and verification of the consumed ones
+    pub message_signing: MessageSigningSettings,
+    pub search_cache: SearchCacheSettings,
+    pub standby: StandbySettings,
+    /// Normalization of the search queries with the rules of the tenant
+    pub normalization_rules: NormalizationRulesSettings,
 }
 
 // TODO: is it used for our worker ?
@@ -20,8 +36,8 @@ pub struct ApplicationSettings {
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct RabbitMQSettings {
-    // pub username: String,
-    // pub password: Secret<String>,
+    #[serde(default)]
+    pub credentials: Option<BrokerCredentials>,
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub port: u16,
     pub host: String,
@@ -31,11 +47,33 @@ pub struct RabbitMQSettings {
     pub queue_name_prefix: String,
 
     pub content_exchange: String,
+    /// Tenant served by this deployment, isolated at the broker level. If not set, the shared exchanges and queues are used
+    #[serde(default)]
+    pub tenant: Option<TenantSettings>,
 }
 
 impl RabbitMQSettings {
     pub fn get_uri(&self) -> String {
-        format!("amqp://{}:{}", &self.host, &self.port)
+        let broker_uri = broker_uri(&self.host, self.port, self.credentials.as_ref());
+
+        match &self.tenant {
+            Some(tenant) => tenant.amqp_uri(&broker_uri),
+            None => broker_uri,
+        }
+    }
+
+    /// Name of the content exchange, templated with the tenant id
+    pub fn content_exchange_name(&self) -> String {
+        format!(
+            "{}_{}",
+            tenant_name_prefix(&self.exchange_name_prefix, self.tenant.as_ref()),
+            self.content_exchange
+        )
+    }
+
+    /// Prefix of the queue names, templated with the tenant id
+    pub fn tenant_queue_name_prefix(&self) -> String {
+        tenant_name_prefix(&self.queue_name_prefix, self.tenant.as_ref())
     }
 
     pub fn get_connection_properties(&self) -> ConnectionProperties {
@@ -62,7 +100,37 @@ impl MeilisearchSettings {
     }
 }
 
-/// Extracts app settings from configuration files and env variables
+/// Cache of the search results, invalidated when the contents of a shard change
+#[derive(Debug, Deserialize, Clone)]
+pub struct SearchCacheSettings {
+    /// How long the results of a search are reused. 0 disables the cache
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub ttl_ms: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_entries: usize,
+}
+
+/// Warm standby of the primary instance, indexing into a replica Meilisearch
+///
+/// A standby instance consumes the indexing messages from its own queues (its `rabbitmq.queue_name_prefix`
+/// should differ from the primary one), but only serves the queries once promoted.
+#[derive(Debug, Deserialize, Clone)]
+pub struct StandbySettings {
+    pub enabled: bool,
+    /// Name of this standby instance in the promotion commands
+    pub name: String,
+    /// Queue name prefix of the primary instance, whose search requests are consumed once promoted
+    pub primary_queue_name_prefix: String,
+}
+
+impl StandbySettings {
+    /// Prefix of the queue names of the primary instance, templated with the tenant id
+    pub fn tenant_primary_queue_name_prefix(&self, tenant: Option<&TenantSettings>) -> String {
+        tenant_name_prefix(&self.primary_queue_name_prefix, tenant)
+    }
+}
+
+/// Extracts app settings from configuration files, env variables and command line flags
 ///
 /// `base.yml` should contain shared settings for all environments.
 /// A specific env file should be created for each environment: `develop.yml`,`local.yml` and `production.yml`
@@ -71,7 +139,8 @@ impl MeilisearchSettings {
 ///
 /// Settings are also taken from environment variables: with a prefix of APP and '__' as separator
 /// For ex: `APP_APPLICATION__PORT=5001 would set `Settings.application.port`
-pub fn get_configuration() -> Result<Settings, config::ConfigError> {
+/// And finally from the `--set` flags of the command line. For ex: `--set application.port=5001`
+pub async fn get_configuration() -> Result<Settings, ConfigurationError> {
     let base_path = std::env::current_dir().expect("Failed to determine the current directory");
     let configuration_directory = base_path.join("configuration");
 
@@ -80,23 +149,12 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
     let environment: Environment = std::env::var("APP_ENVIRONMENT")
         .unwrap_or_else(|_| "develop".into())
         .try_into()
-        .expect("Failed to parse APP_ENVIRONMENT.");
-    let environment_filename = format!("{}.yml", environment.as_str());
-
-    let settings = config::Config::builder()
-        .add_source(config::File::from(configuration_directory.join("base.yml")))
-        .add_source(config::File::from(
-            configuration_directory.join(environment_filename),
-        ))
-        // Adds in settings from environment variables (with a prefix of APP and '__' as separator)
-        .add_source(
-            config::Environment::with_prefix("APP")
-                .prefix_separator("_")
-                .separator("__"),
-        )
-        .build()?;
+        .map_err(ConfigurationError::InvalidEnvironment)?;
 
-    settings.try_deserialize::<Settings>()
+    ConfigurationLayers::new(configuration_directory, environment.as_str())
+        .with_cli_overrides(std::env::args().skip(1))?
+        .load()
+        .await
 }
 
 /// The possible runtime environment for our application.
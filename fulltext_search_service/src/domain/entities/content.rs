@@ -8,6 +8,9 @@ pub struct ContentEntity {
     pub id: Uuid,
     pub metadata: JsonValue,
     pub content: String,
+    /// Source from which the content was extracted
+    #[serde(default)]
+    pub source_meta_id: Option<Uuid>,
 }
 
 impl From<ExtractedContentDto> for ContentEntity {
@@ -16,6 +19,7 @@ impl From<ExtractedContentDto> for ContentEntity {
             id: value.id,
             metadata: value.metadata,
             content: value.content,
+            source_meta_id: value.source_meta_id,
         }
     }
 }
@@ -1 +1,3 @@
 pub mod content;
+pub mod search_cache;
+pub mod spelling;
@@ -0,0 +1,313 @@
+use common::dtos::{
+    fulltext_search_request::SearchSortDto, fulltext_search_response::FulltextSearchResponseData,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+use crate::configuration::SearchCacheSettings;
+
+/// Key of a cached search
+///
+/// A deployment serves a single tenant: the tenant is implied by the shard.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SearchCacheKey {
+    pub shard: u32,
+    pub user_id: Uuid,
+    /// Query lowercased with collapsed whitespaces, as Meilisearch does not make a difference
+    pub query: String,
+    pub limit: Option<usize>,
+    pub language: Option<String>,
+    pub fields: BTreeMap<String, String>,
+    pub source_meta_ids: Option<Vec<Uuid>>,
+    pub chapter: Option<String>,
+    pub keyword: Option<String>,
+    pub entity: Option<String>,
+    /// Page of the results, `limit` being the number of results per page
+    pub page: usize,
+    pub sort: SearchSortDto,
+}
+
+impl SearchCacheKey {
+    pub fn new(
+        shard: u32,
+        user_id: Uuid,
+        query: &str,
+        limit: Option<usize>,
+        language: Option<String>,
+    ) -> Self {
+        Self {
+            shard,
+            user_id,
+            query: query
+                .split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" ")
+                .to_lowercase(),
+            limit,
+            language,
+            fields: BTreeMap::new(),
+            source_meta_ids: None,
+            chapter: None,
+            keyword: None,
+            entity: None,
+            page: 1,
+            sort: SearchSortDto::default(),
+        }
+    }
+
+    /// Key of a search filtered on the fields of the rows of structured sources
+    pub fn with_fields(mut self, fields: BTreeMap<String, String>) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// Key of a search filtered on some sources
+    pub fn with_source_meta_ids(mut self, source_meta_ids: Option<Vec<Uuid>>) -> Self {
+        self.source_meta_ids = source_meta_ids;
+        self
+    }
+
+    /// Key of a page of the results of a search, in a given order
+    pub fn with_page(mut self, page: usize, sort: SearchSortDto) -> Self {
+        self.page = page;
+        self.sort = sort;
+        self
+    }
+
+    /// Key of a search filtered on a chapter
+    pub fn with_chapter(mut self, chapter: Option<String>) -> Self {
+        self.chapter = chapter;
+        self
+    }
+
+    /// Key of a search filtered on a keyword and a named entity
+    pub fn with_keyword_and_entity(
+        mut self,
+        keyword: Option<String>,
+        entity: Option<String>,
+    ) -> Self {
+        self.keyword = keyword;
+        self.entity = entity;
+        self
+    }
+}
+
+#[derive(Debug)]
+struct CachedSearch {
+    data: FulltextSearchResponseData,
+    cached_at: Instant,
+}
+
+/// Short-lived cache of the search results, for the searches repeated by dashboards
+///
+/// The entries of a shard are invalidated when contents are saved to or deleted from it.
+/// Meilisearch indexes the contents asynchronously: a search made between the invalidation and the indexing
+/// caches the previous results, until they expire.
+pub struct SearchCache {
+    /// Zero disables the cache
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<SearchCacheKey, CachedSearch>>,
+}
+
+impl SearchCache {
+    pub fn new(settings: &SearchCacheSettings) -> Self {
+        Self {
+            ttl: Duration::from_millis(settings.ttl_ms),
+            max_entries: settings.max_entries,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Results of a search cached since less than the TTL
+    pub fn get(&self, key: &SearchCacheKey) -> Option<FulltextSearchResponseData> {
+        let mut entries = self.entries.lock().expect("search cache lock poisoned");
+
+        match entries.get(key) {
+            Some(cached_search) if cached_search.cached_at.elapsed() < self.ttl => {
+                Some(cached_search.data.clone())
+            }
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Caches the results of a search
+    ///
+    /// Once the cache is full, the expired entries are dropped, and then the oldest one.
+    pub fn insert(&self, key: SearchCacheKey, data: FulltextSearchResponseData) {
+        if self.ttl.is_zero() || self.max_entries == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().expect("search cache lock poisoned");
+
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            entries.retain(|_, cached_search| cached_search.cached_at.elapsed() < self.ttl);
+        }
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            let oldest_key = entries
+                .iter()
+                .min_by_key(|(_, cached_search)| cached_search.cached_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest_key) = oldest_key {
+                entries.remove(&oldest_key);
+            }
+        }
+
+        entries.insert(
+            key,
+            CachedSearch {
+                data,
+                cached_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Invalidates the searches of a shard whose contents changed: of a given user, or of all the users
+    pub fn invalidate(&self, shard: u32, user_id: Option<Uuid>) {
+        self.entries
+            .lock()
+            .expect("search cache lock poisoned")
+            .retain(|key, _| {
+                key.shard != shard
+                    || user_id
+                        .map(|user_id| key.user_id != user_id)
+                        .unwrap_or(false)
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::dtos::fulltext_search_response::ResultContent;
+    use serde_json::json;
+
+    fn search_cache(ttl_ms: u64, max_entries: usize) -> SearchCache {
+        SearchCache::new(&SearchCacheSettings {
+            ttl_ms,
+            max_entries,
+        })
+    }
+
+    fn results(content: &str) -> FulltextSearchResponseData {
+        FulltextSearchResponseData {
+            results: vec![ResultContent {
+                id: Uuid::new_v4(),
+                metadata: json!({}),
+                content: content.to_string(),
+                source_meta_id: None,
+            }],
+            source_hit_counts: HashMap::new(),
+            language_hit_counts: HashMap::new(),
+            chapter_hit_counts: HashMap::new(),
+            keyword_hit_counts: HashMap::new(),
+            entity_hit_counts: HashMap::new(),
+            total_hits: None,
+            suggestions: vec![],
+        }
+    }
+
+    #[test]
+    fn same_normalized_search_is_cached() {
+        let cache = search_cache(60_000, 10);
+        let user_id = Uuid::new_v4();
+
+        cache.insert(
+            SearchCacheKey::new(0, user_id, "Tomato  sauce", Some(5), None),
+            results("tomato"),
+        );
+
+        let cached_results = cache
+            .get(&SearchCacheKey::new(
+                0,
+                user_id,
+                " tomato sauce ",
+                Some(5),
+                None,
+            ))
+            .unwrap();
+        assert_eq!(cached_results.results[0].content, "tomato");
+        assert!(cache
+            .get(&SearchCacheKey::new(
+                0,
+                user_id,
+                "tomato sauce",
+                Some(10),
+                None
+            ))
+            .is_none());
+        assert!(cache
+            .get(&SearchCacheKey::new(
+                0,
+                Uuid::new_v4(),
+                "tomato sauce",
+                Some(5),
+                None
+            ))
+            .is_none());
+        assert!(cache
+            .get(&SearchCacheKey::new(
+                0,
+                user_id,
+                "tomato sauce",
+                Some(5),
+                Some("fr".to_string())
+            ))
+            .is_none());
+        assert!(cache
+            .get(
+                &SearchCacheKey::new(0, user_id, "tomato sauce", Some(5), None)
+                    .with_fields(BTreeMap::from([("year".to_string(), "2021".to_string())]))
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn searches_of_a_changed_shard_are_invalidated() {
+        let cache = search_cache(60_000, 10);
+        let (user_id, other_user_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let key = SearchCacheKey::new(0, user_id, "tomato", None, None);
+        let other_user_key = SearchCacheKey::new(0, other_user_id, "tomato", None, None);
+        let other_shard_key = SearchCacheKey::new(1, user_id, "tomato", None, None);
+        for key in [&key, &other_user_key, &other_shard_key] {
+            cache.insert(key.clone(), results("tomato"));
+        }
+
+        cache.invalidate(0, Some(user_id));
+        assert!(cache.get(&key).is_none());
+        assert!(cache.get(&other_user_key).is_some());
+
+        cache.invalidate(0, None);
+        assert!(cache.get(&other_user_key).is_none());
+        assert!(cache.get(&other_shard_key).is_some());
+    }
+
+    #[test]
+    fn oldest_search_is_dropped_once_full_and_nothing_is_cached_without_ttl() {
+        let cache = search_cache(60_000, 2);
+        let user_id = Uuid::new_v4();
+        let keys: Vec<SearchCacheKey> = ["a", "b", "c"]
+            .iter()
+            .map(|query| SearchCacheKey::new(0, user_id, query, None, None))
+            .collect();
+        for key in &keys {
+            cache.insert(key.clone(), results("tomato"));
+        }
+
+        assert!(cache.get(&keys[0]).is_none());
+        assert!(cache.get(&keys[2]).is_some());
+
+        let disabled_cache = search_cache(0, 2);
+        disabled_cache.insert(keys[0].clone(), results("tomato"));
+        assert!(disabled_cache.get(&keys[0]).is_none());
+    }
+}
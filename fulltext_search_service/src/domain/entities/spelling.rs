@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use uuid::Uuid;
+
+/// Minimum number of characters of a word to be indexed as a term, and to be corrected
+pub const MIN_TERM_LENGTH: usize = 4;
+
+/// Maximum number of characters of a word to be indexed as a term
+pub const MAX_TERM_LENGTH: usize = 32;
+
+/// Number of characters from which a word can be corrected with 2 typos instead of 1,
+/// as the typo tolerance of the terms indexes
+pub const MIN_TERM_LENGTH_FOR_TWO_TYPOS: usize = 8;
+
+/// Maximum number of suggested queries of a search
+pub const MAX_SUGGESTIONS: usize = 3;
+
+/// Word of the contents of a user, to correct the spelling of their queries
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TermEntity {
+    /// Derived from the user and the term, for a term to be saved once per user
+    pub id: String,
+    pub user_id: Uuid,
+    pub term: String,
+}
+
+impl TermEntity {
+    pub fn new(user_id: Uuid, term: String) -> Self {
+        // Hex-encoded: a Meilisearch document id only contains ASCII letters, digits, `-` and `_`
+        let encoded_term: String = term.bytes().map(|byte| format!("{:02x}", byte)).collect();
+
+        Self {
+            id: format!("{}_{}", user_id.simple(), encoded_term),
+            user_id,
+            term,
+        }
+    }
+}
+
+/// Whether a word of a query or of a content can be a term: only letters, within the term lengths
+fn is_term(word: &str) -> bool {
+    let length = word.chars().count();
+
+    (MIN_TERM_LENGTH..=MAX_TERM_LENGTH).contains(&length) && word.chars().all(char::is_alphabetic)
+}
+
+/// Distinct lowercased words of a content, indexed to correct the spelling of the queries
+pub fn indexed_terms(content: &str) -> BTreeSet<String> {
+    content
+        .split(|character: char| !character.is_alphanumeric())
+        .filter(|word| is_term(word))
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Lowercased words of a query, and whether each one can be corrected
+pub fn query_words(query: &str) -> Vec<(String, bool)> {
+    query
+        .split_whitespace()
+        .map(|word| (word.to_lowercase(), is_term(word)))
+        .collect()
+}
+
+/// Number of insertions, deletions, substitutions and transpositions of adjacent characters from a word to another
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // Distances between the prefixes of `a` and `b`
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+/// Corrections of a word among the terms similar to it, from the closest one
+///
+/// A word found among the terms is spelled correctly: it has no correction.
+/// The terms with the same distance keep their given order.
+pub fn corrections(word: &str, similar_terms: &[String]) -> Vec<String> {
+    if similar_terms.iter().any(|term| term == word) {
+        return vec![];
+    }
+    let max_distance = match word.chars().count() < MIN_TERM_LENGTH_FOR_TWO_TYPOS {
+        true => 1,
+        false => 2,
+    };
+
+    let mut corrections: Vec<(usize, &String)> = similar_terms
+        .iter()
+        .map(|term| (edit_distance(word, term), term))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    corrections.sort_by_key(|(distance, _)| *distance);
+
+    corrections
+        .into_iter()
+        .map(|(_, term)| term.clone())
+        .take(MAX_SUGGESTIONS)
+        .collect()
+}
+
+/// Queries suggested from the corrections of the words of a query, from the most likely one
+///
+/// The n-th suggestion replaces each corrected word by its n-th correction, or by its closest one when it has fewer.
+pub fn suggested_queries(words: &[(String, Vec<String>)]) -> Vec<String> {
+    let suggestion_count = words
+        .iter()
+        .map(|(_, corrections)| corrections.len())
+        .max()
+        .unwrap_or(0);
+    let mut suggestions: Vec<String> = vec![];
+
+    for index in 0..suggestion_count {
+        let suggestion = words
+            .iter()
+            .map(|(word, corrections)| {
+                corrections
+                    .get(index)
+                    .or(corrections.first())
+                    .unwrap_or(word)
+                    .as_str()
+            })
+            .collect::<Vec<&str>>()
+            .join(" ");
+        if !suggestions.contains(&suggestion) {
+            suggestions.push(suggestion);
+        }
+    }
+
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(terms: &[&str]) -> Vec<String> {
+        terms.iter().map(|term| term.to_string()).collect()
+    }
+
+    #[test]
+    fn indexed_terms_are_the_distinct_lowercased_words() {
+        let terms = indexed_terms("The Kubernetes cluster, the k8s nodes: cluster nodes 2024!");
+
+        assert_eq!(
+            terms.into_iter().collect::<Vec<_>>(),
+            vec!["cluster", "kubernetes", "nodes"]
+        );
+    }
+
+    #[test]
+    fn edit_distance_counts_transpositions_as_one_edit() {
+        assert_eq!(edit_distance("hello", "hello"), 0);
+        assert_eq!(edit_distance("helo", "hello"), 1);
+        assert_eq!(edit_distance("hlelo", "hello"), 1);
+        assert_eq!(edit_distance("cluster", "clsuter"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("éte", "été"), 1);
+    }
+
+    #[test]
+    fn corrections_are_the_closest_terms_within_the_typo_tolerance() {
+        assert_eq!(
+            corrections("clustr", &terms(&["clusters", "cluster", "clustering"])),
+            terms(&["cluster"])
+        );
+        assert_eq!(
+            corrections("clusterr", &terms(&["clustering", "clusters", "cluster"])),
+            terms(&["clusters", "cluster"])
+        );
+        assert_eq!(
+            corrections("kuberntees", &terms(&["kubernetes"])),
+            terms(&["kubernetes"])
+        );
+        // Only one typo for a short word
+        assert!(corrections("nde", &terms(&["nodes"])).is_empty());
+        // Spelled correctly
+        assert!(corrections("cluster", &terms(&["clusters", "cluster"])).is_empty());
+    }
+
+    #[test]
+    fn suggested_queries_replace_the_corrected_words() {
+        let words = vec![
+            ("kubernetse".to_string(), terms(&["kubernetes"])),
+            ("clustr".to_string(), terms(&["cluster", "clusters"])),
+            ("the".to_string(), vec![]),
+        ];
+
+        assert_eq!(
+            suggested_queries(&words),
+            terms(&["kubernetes cluster the", "kubernetes clusters the"])
+        );
+        assert!(suggested_queries(&[("cluster".to_string(), vec![])]).is_empty());
+    }
+}
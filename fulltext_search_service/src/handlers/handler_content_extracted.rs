@@ -10,22 +10,35 @@ use lapin::{
 };
 use std::sync::Arc;
 use tracing::{error, info, info_span, Instrument};
+use uuid::Uuid;
 
 use crate::{
-    domain::entities::content::ContentEntity,
+    domain::entities::{
+        content::ContentEntity, search_cache::SearchCache, spelling::indexed_terms,
+    },
+    handlers::IndexServices,
     repositories::meilisearch_content_repository::{
         MeilisearchContentRepository, MeilisearchContentRepositoryError,
     },
 };
 use common::{
-    constants::routing_keys::CONTENT_EXTRACTED_ROUTING_KEY,
-    core::rabbitmq_message_repository::{
-        RabbitMQMessageRepository, RabbitMQMessageRepositoryError,
+    constants::routing_keys::{CONTENT_EXTRACTED_ROUTING_KEY, INGESTION_JOB_STATUS_ROUTING_KEY},
+    core::{
+        message_signing::MessageSigningError,
+        rabbitmq_message_repository::{RabbitMQMessageRepository, RabbitMQMessageRepositoryError},
+        retry::RetryPolicy,
+        trace_propagation::continue_trace_from,
+    },
+    dtos::{
+        extract_content_job::IngestionLaneDto,
+        extracted_content::{ExtractedContentDto, USER_ID_METADATA_KEY},
+        ingestion_job_status::IngestionJobStatusDto,
+        templates::message_envelope::MessageEnvelope,
     },
-    dtos::extracted_content::ExtractedContentDto,
     helper::error_chain_fmt,
 };
 
+/// Routing key of the contents extracted from the sources of the bulk lane
 pub const ROUTING_KEY: &str = CONTENT_EXTRACTED_ROUTING_KEY;
 
 #[derive(thiserror::Error)]
@@ -49,9 +62,12 @@ impl std::fmt::Debug for RegisterHandlerContentExtractedError {
 ///
 /// Some repositories (MessageRabbitMQRepository) are initialized inside the handler
 /// to avoid sharing some instances (ex: RabbitMQ channel) between each thread
+///
+/// Each ingestion lane has its own queue and handler: the contents of a small source are not indexed behind
+/// the thousands of contents of a book.
 #[tracing::instrument(
     name = "Register message handler",
-    skip(rabbitmq_consuming_connection, message_repository, content_repository)
+    skip(rabbitmq_consuming_connection, message_repository, index_services)
 )]
 pub async fn register_handler(
     rabbitmq_consuming_connection: Arc<RabbitMQConnection>,
@@ -59,8 +75,15 @@ pub async fn register_handler(
     queue_name_prefix: String,
     // Not an `Arc` shared reference as we want to initialize a new repository for each thread (or at least for each handler)
     message_repository: RabbitMQMessageRepository,
-    content_repository: Arc<MeilisearchContentRepository>,
+    index_services: IndexServices,
+    retry_policy: RetryPolicy,
+    lane: IngestionLaneDto,
 ) -> Result<(), RegisterHandlerContentExtractedError> {
+    let IndexServices {
+        content_repository,
+        search_cache,
+    } = index_services;
+
     let channel = rabbitmq_consuming_connection.create_channel().await?;
 
     channel
@@ -75,8 +98,9 @@ pub async fn register_handler(
         )
         .await?;
 
+    let routing_key = lane.content_extracted_routing_key();
     // In order to have several nodes of this service as consumers of the same queue: use a specific queue name
-    let queue_name = queue_name(&queue_name_prefix);
+    let queue_name = queue_name(&queue_name_prefix, lane);
 
     channel
         .queue_declare(
@@ -88,14 +112,14 @@ pub async fn register_handler(
 
     info!(
         "Declared queue {} on exchange {}, binding on {}",
-        queue_name, exchange_name, ROUTING_KEY
+        queue_name, exchange_name, routing_key
     );
 
     channel
         .queue_bind(
             &queue_name,
             &exchange_name,
-            ROUTING_KEY,
+            routing_key,
             QueueBindOptions::default(),
             FieldTable::default(),
         )
@@ -115,7 +139,7 @@ pub async fn register_handler(
 
     info!(
         "📡 Handler consuming from queue {}, bound to {} with {}, waiting for messages ...",
-        queue_name, exchange_name, ROUTING_KEY,
+        queue_name, exchange_name, routing_key,
     );
 
     while let Some(delivery) = consumer.next().await {
@@ -132,8 +156,17 @@ pub async fn register_handler(
                     return;
                 }
             };
+            // The spans handling the message continue the trace of its publisher
+            continue_trace_from(&delivery.properties);
 
-            match execute_handler(&message_repository, content_repository.clone(), &delivery).await
+            match execute_handler(
+                &message_repository,
+                content_repository.clone(),
+                &search_cache,
+                &retry_policy,
+                &delivery,
+            )
+            .await
             {
                 Ok(()) => {
                     info!(
@@ -152,7 +185,15 @@ pub async fn register_handler(
                         "Not acknowledging message with delivery tag {}",
                         delivery.delivery_tag
                     );
-                    if let Err(error) = delivery.nack(BasicNackOptions::default()).await {
+                    // A message with an invalid signature would be rejected again: it is not requeued
+                    let nack_options = BasicNackOptions {
+                        requeue: !matches!(
+                            error,
+                            ExecuteHandlerContentExtractedError::MessageSigningError(_)
+                        ),
+                        ..BasicNackOptions::default()
+                    };
+                    if let Err(error) = delivery.nack(nack_options).await {
                         error!(?error, "Failed to nack extracted content message");
                     }
                 }
@@ -160,7 +201,7 @@ pub async fn register_handler(
         }
         .instrument(info_span!(
             "Handling consumed message",
-            routing_key = ROUTING_KEY,
+            routing_key = routing_key,
             exchange = exchange_name,
             queue = queue_name,
             message_id = %uuid::Uuid::new_v4(),
@@ -171,8 +212,12 @@ pub async fn register_handler(
     Ok(())
 }
 
-pub fn queue_name(queue_name_prefix: &str) -> String {
-    format!("{}_{}", queue_name_prefix, ROUTING_KEY)
+pub fn queue_name(queue_name_prefix: &str, lane: IngestionLaneDto) -> String {
+    format!(
+        "{}_{}",
+        queue_name_prefix,
+        lane.content_extracted_routing_key()
+    )
 }
 
 #[derive(thiserror::Error)]
@@ -181,6 +226,8 @@ pub enum ExecuteHandlerContentExtractedError {
     RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
     #[error(transparent)]
     MeilisearchContentRepositoryError(#[from] MeilisearchContentRepositoryError),
+    #[error(transparent)]
+    MessageSigningError(#[from] MessageSigningError),
     #[error("Error while serializing message data: {0}")]
     JsonError(#[from] serde_json::Error),
     #[error("{0}")]
@@ -195,24 +242,58 @@ impl std::fmt::Debug for ExecuteHandlerContentExtractedError {
 
 #[tracing::instrument(
     name = "Executing handler on extracted content",
-    skip(message_repository, content_repository, message)
+    skip(message_repository, content_repository, search_cache, message)
 )]
 pub async fn execute_handler(
     message_repository: &RabbitMQMessageRepository,
     content_repository: Arc<MeilisearchContentRepository>,
+    search_cache: &SearchCache,
+    retry_policy: &RetryPolicy,
     message: &Delivery,
 ) -> Result<(), ExecuteHandlerContentExtractedError> {
-    let extracted_content = ExtractedContentDto::try_parsing(&message.data).map_err(|error| {
-        ExecuteHandlerContentExtractedError::MessageParsingError(format!(
-            "Failed to parse extracted content message data: {}",
-            error
-        ))
-    })?;
+    // Rejects messages not published by our services
+    message_repository.verify(message)?;
 
-    info!(?extracted_content, "Received extracted content");
+    let message =
+        MessageEnvelope::<ExtractedContentDto>::try_decoding(&message.data).map_err(|error| {
+            ExecuteHandlerContentExtractedError::MessageParsingError(format!(
+                "Failed to parse extracted content message data: {}",
+                error
+            ))
+        })?;
+    let extracted_content = message.payload;
+
+    info!(
+        ?extracted_content,
+        trace_context = ?message.trace_context,
+        "Received extracted content"
+    );
+    let shard = extracted_content.fulltext_shard;
     let content: ContentEntity = extracted_content.into();
 
-    content_repository.save(&content).await?;
+    retry_policy
+        .retry("saving the content to Meilisearch", || {
+            content_repository.save(&content, shard)
+        })
+        .await?;
+
+    // Only the searches of the owner of the content can find it
+    let user_id = content
+        .metadata
+        .get(USER_ID_METADATA_KEY)
+        .and_then(|user_id| user_id.as_str())
+        .and_then(|user_id| Uuid::parse_str(user_id).ok());
+    search_cache.invalidate(shard, user_id);
+
+    // The words of the content can correct the spelling of the searches of its owner
+    if let Some(user_id) = user_id {
+        let terms = indexed_terms(&content.content);
+        retry_policy
+            .retry("saving the terms of the content to Meilisearch", || {
+                content_repository.save_terms(user_id, &terms, shard)
+            })
+            .await?;
+    }
 
     // To inform on progress. Not used currently.
     message_repository
@@ -222,6 +303,37 @@ pub async fn execute_handler(
         )
         .await?;
 
+    publish_job_status(message_repository, content.source_meta_id).await;
+
     info!("Successfully handled extract_content_job message");
     Ok(())
 }
+
+/// Publishes that one more content of a source was saved in the full-text index, to time the indexing
+///
+/// The status is only informative: a failure to publish it does not fail the handling,
+/// the content is already saved.
+async fn publish_job_status(
+    message_repository: &RabbitMQMessageRepository,
+    source_meta_id: Option<Uuid>,
+) {
+    // Contents published before the contents were linked to their source
+    let Some(source_meta_id) = source_meta_id else {
+        return;
+    };
+
+    let json_dto = match serde_json::to_string(&IngestionJobStatusDto::indexed(source_meta_id)) {
+        Ok(json_dto) => json_dto,
+        Err(error) => {
+            error!(?error, "Failed to serialize the ingestion job status");
+            return;
+        }
+    };
+
+    if let Err(error) = message_repository
+        .publish(INGESTION_JOB_STATUS_ROUTING_KEY, json_dto.as_bytes())
+        .await
+    {
+        error!(?error, "Failed to publish the ingestion job status");
+    }
+}
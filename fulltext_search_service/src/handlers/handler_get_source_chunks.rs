@@ -0,0 +1,309 @@
+use futures::StreamExt;
+use lapin::{
+    options::{
+        BasicAckOptions, BasicConsumeOptions, BasicNackOptions, ExchangeDeclareOptions,
+        QueueBindOptions, QueueDeclareOptions,
+    },
+    types::FieldTable,
+    Connection as RabbitMQConnection, ExchangeKind,
+};
+use std::sync::Arc;
+use tracing::{error, info, info_span, Instrument};
+
+use crate::repositories::meilisearch_content_repository::{
+    MeilisearchContentRepository, MeilisearchContentRepositoryError,
+};
+use common::{
+    constants::routing_keys::GET_SOURCE_CHUNKS_ROUTING_KEY,
+    core::{
+        rabbitmq_message_repository::{RabbitMQMessageRepository, RabbitMQMessageRepositoryError},
+        trace_propagation::continue_trace_from,
+    },
+    dtos::{
+        fulltext_search_response::ResultContent,
+        source_chunks_request::SourceChunksRequestDto,
+        source_chunks_response::{SourceChunksResponseData, SourceChunksResponseDto},
+        templates::rpc_response::RpcErrorStatus,
+    },
+    helper::error_chain_fmt,
+};
+
+pub const ROUTING_KEY: &str = GET_SOURCE_CHUNKS_ROUTING_KEY;
+
+/// Maximum number of contents listed by a call
+const MAX_LIMIT: usize = 1000;
+
+#[derive(thiserror::Error)]
+pub enum RegisterHandlerGetSourceChunksError {
+    #[error(transparent)]
+    RabbitMQError(#[from] lapin::Error),
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+}
+
+impl std::fmt::Debug for RegisterHandlerGetSourceChunksError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Registers the RPC message handler listing the contents extracted from a source, to preview them
+///
+/// The handler will respond to the message on the given `reply-to`.
+/// As the search handler, it is only registered by a primary instance, or by a promoted standby instance.
+#[tracing::instrument(
+    name = "Register get source chunks RPC handler",
+    skip(rabbitmq_consuming_connection, message_repository, content_repository)
+)]
+pub async fn register_handler(
+    rabbitmq_consuming_connection: Arc<RabbitMQConnection>,
+    exchange_name: String,
+    queue_name_prefix: String,
+    // Not an `Arc` shared reference as we want to initialize a new repository for each thread (or at least for each handler)
+    message_repository: RabbitMQMessageRepository,
+    content_repository: Arc<MeilisearchContentRepository>,
+) -> Result<(), RegisterHandlerGetSourceChunksError> {
+    let channel = rabbitmq_consuming_connection.create_channel().await?;
+
+    channel
+        .exchange_declare(
+            &exchange_name,
+            ExchangeKind::Topic,
+            ExchangeDeclareOptions {
+                durable: true,
+                ..ExchangeDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    // In order to have several nodes of this service as consumers of the same queue: use a specific queue name
+    let queue_name = queue_name(&queue_name_prefix);
+
+    let _ = channel
+        .queue_declare(
+            &queue_name,
+            QueueDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    channel
+        .queue_bind(
+            &queue_name,
+            &exchange_name,
+            ROUTING_KEY,
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    info!(
+        "Declared queue {} on exchange {}, binding on {}",
+        queue_name, exchange_name, ROUTING_KEY
+    );
+
+    let consumer_options = BasicConsumeOptions {
+        no_ack: false,
+        ..BasicConsumeOptions::default()
+    };
+
+    let mut consumer = channel
+        .basic_consume(&queue_name, "", consumer_options, FieldTable::default())
+        .await?;
+
+    // Inits for this specific handler
+    let message_repository = message_repository.try_init().await?;
+
+    info!(
+        "📡 Handler consuming from queue {}, bound to {} with {}, waiting for messages ...",
+        queue_name, exchange_name, ROUTING_KEY,
+    );
+
+    while let Some(delivery) = consumer.next().await {
+        async {
+            let delivery = match delivery {
+                // Carries the delivery alongside its channel
+                Ok(delivery) => delivery,
+                // Carries the error and is always followed by Ok(None)
+                Err(error) => {
+                    error!(
+                        ?error,
+                        "Failed to consume queue message on queue {}", queue_name
+                    );
+                    return;
+                }
+            };
+            // The spans handling the message continue the trace of its publisher
+            continue_trace_from(&delivery.properties);
+
+            let reply_to = match delivery.properties.reply_to().as_ref() {
+                Some(reply_to) => reply_to,
+                None => {
+                    error!(
+                        "No `reply-to` attribute necessary for RPC call on queue: {}",
+                        queue_name
+                    );
+
+                    // Disables requeue if there is no way to reply to the RPC call
+                    if let Err(error) = delivery
+                        .nack(BasicNackOptions {
+                            requeue: false,
+                            ..BasicNackOptions::default()
+                        })
+                        .await
+                    {
+                        error!(?error, "Failed to nack message");
+                    }
+
+                    return;
+                }
+            };
+
+            // Set on the response, for the caller to match it with its request
+            let correlation_id = delivery
+                .properties
+                .correlation_id()
+                .as_ref()
+                .map(|id| id.as_str());
+
+            match execute_handler(
+                &message_repository,
+                &content_repository,
+                &delivery.data,
+                reply_to.as_str(),
+                correlation_id,
+            )
+            .await
+            {
+                Ok(()) => {
+                    if let Err(error) = delivery.ack(BasicAckOptions::default()).await {
+                        error!(?error, "Failed to ack message");
+                    }
+                }
+                Err(error) => {
+                    error!(?error, "Failed to handle get source chunks request");
+
+                    let status = match error {
+                        ExecuteHandlerGetSourceChunksError::MessageParsingError(_) => {
+                            RpcErrorStatus::BadRequest
+                        }
+                        _ => RpcErrorStatus::InternalServerError,
+                    };
+                    let response = SourceChunksResponseDto::Error {
+                        status,
+                        message: error.to_string(),
+                    };
+
+                    if let Ok(response) = response.try_serializing() {
+                        // Sends response to the given `reply_to` to mimic a RPC call
+                        let _ = message_repository
+                            .rpc_respond(reply_to.as_str(), correlation_id, response.as_bytes())
+                            .await;
+                    }
+
+                    // Answered with an error: the caller does not wait for another response
+                    if let Err(error) = delivery
+                        .nack(BasicNackOptions {
+                            requeue: false,
+                            ..BasicNackOptions::default()
+                        })
+                        .await
+                    {
+                        error!(?error, "Failed to nack message");
+                    }
+                }
+            }
+        }
+        .instrument(info_span!(
+            "Handling consumed message",
+            routing_key = ROUTING_KEY,
+            exchange = exchange_name,
+            queue = queue_name,
+            message_id = %uuid::Uuid::new_v4(),
+        ))
+        .await
+    }
+
+    Ok(())
+}
+
+pub fn queue_name(queue_name_prefix: &str) -> String {
+    format!("{}_{}", queue_name_prefix, ROUTING_KEY)
+}
+
+#[derive(thiserror::Error)]
+pub enum ExecuteHandlerGetSourceChunksError {
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+    #[error(transparent)]
+    MeilisearchContentRepositoryError(#[from] MeilisearchContentRepositoryError),
+    #[error("Error while serializing message data: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Error while deserializing input message: {0}")]
+    MessageParsingError(String),
+}
+
+impl std::fmt::Debug for ExecuteHandlerGetSourceChunksError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[tracing::instrument(
+    name = "Executing handler on get source chunks request",
+    skip(message_repository, content_repository, data)
+)]
+pub async fn execute_handler(
+    message_repository: &RabbitMQMessageRepository,
+    content_repository: &MeilisearchContentRepository,
+    data: &[u8],
+    reply_to: &str,
+    correlation_id: Option<&str>,
+) -> Result<(), ExecuteHandlerGetSourceChunksError> {
+    let request = SourceChunksRequestDto::try_parsing(data).map_err(|error| {
+        ExecuteHandlerGetSourceChunksError::MessageParsingError(format!(
+            "Failed to parse get source chunks message data: {}",
+            error
+        ))
+    })?;
+
+    info!(?request, "Received get source chunks request, executing...");
+
+    let (contents, total) = content_repository
+        .list_by_source_meta_id(
+            request.source_meta_id,
+            request.user_id,
+            request.shard,
+            request.offset,
+            request.limit.min(MAX_LIMIT),
+        )
+        .await?;
+
+    let response = SourceChunksResponseDto::Ok {
+        data: SourceChunksResponseData {
+            chunks: contents
+                .into_iter()
+                .map(|content| ResultContent {
+                    id: content.id,
+                    metadata: content.metadata,
+                    content: content.content,
+                    source_meta_id: content.source_meta_id,
+                })
+                .collect(),
+            total,
+        },
+    };
+
+    // Sends response to the given `reply_to` to mimic a RPC call
+    message_repository
+        .rpc_respond(
+            reply_to,
+            correlation_id,
+            serde_json::to_string(&response)?.as_bytes(),
+        )
+        .await?;
+
+    info!("Successfully handled {} message", ROUTING_KEY);
+    Ok(())
+}
@@ -0,0 +1,293 @@
+use futures::{try_join, StreamExt, TryFutureExt};
+use lapin::{
+    message::Delivery,
+    options::{
+        BasicAckOptions, BasicCancelOptions, BasicConsumeOptions, BasicNackOptions,
+        ExchangeDeclareOptions, QueueBindOptions, QueueDeclareOptions,
+    },
+    types::FieldTable,
+    Connection as RabbitMQConnection, ExchangeKind,
+};
+use std::sync::Arc;
+use tracing::{error, info, info_span, warn, Instrument};
+
+use crate::handlers::{
+    handler_get_source_chunks::{self, RegisterHandlerGetSourceChunksError},
+    handler_search_fulltext::{self, RegisterHandlerSearchFulltextError},
+    IndexServices,
+};
+use common::{
+    constants::routing_keys::PROMOTE_FULLTEXT_STANDBY_ROUTING_KEY,
+    core::{
+        message_signing::MessageSigningError, normalization_rules::NormalizationRulesCache,
+        rabbitmq_message_repository::RabbitMQMessageRepository,
+        trace_propagation::continue_trace_from,
+    },
+    dtos::promote_standby::PromoteStandbyDto,
+    helper::error_chain_fmt,
+};
+
+pub const ROUTING_KEY: &str = PROMOTE_FULLTEXT_STANDBY_ROUTING_KEY;
+
+#[derive(thiserror::Error)]
+pub enum RegisterHandlerPromoteStandbyError {
+    #[error(transparent)]
+    RabbitMQError(#[from] lapin::Error),
+    #[error(transparent)]
+    SearchFulltextHandlerError(#[from] RegisterHandlerSearchFulltextError),
+    #[error(transparent)]
+    GetSourceChunksHandlerError(#[from] RegisterHandlerGetSourceChunksError),
+}
+
+impl std::fmt::Debug for RegisterHandlerPromoteStandbyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Standby instance, waiting for its promotion
+#[derive(Debug)]
+pub struct Standby {
+    /// Name of the instance in the promotion commands
+    pub name: String,
+    /// Queue prefix of the primary instance, whose search requests are consumed once promoted
+    pub primary_queue_name_prefix: String,
+}
+
+/// Registers the handler waiting for the promotion of this standby instance, then serving the queries
+///
+/// A standby instance indexes the contents into its own Meilisearch, from its own queues,
+/// but does not consume the search requests. Once promoted, it consumes the search requests
+/// from the queue of the primary instance, which keeps receiving them when the primary is lost.
+///
+/// It handles messages one by one, there is no handling messages in parallel.
+#[tracing::instrument(
+    name = "Register promote standby handler",
+    skip(
+        rabbitmq_consuming_connection,
+        message_repository,
+        index_services,
+        normalization_rules
+    )
+)]
+pub async fn register_handler(
+    rabbitmq_consuming_connection: Arc<RabbitMQConnection>,
+    exchange_name: String,
+    queue_name_prefix: String,
+    standby: Standby,
+    message_repository: RabbitMQMessageRepository,
+    index_services: IndexServices,
+    normalization_rules: Arc<NormalizationRulesCache>,
+) -> Result<(), RegisterHandlerPromoteStandbyError> {
+    let Standby {
+        name: standby_name,
+        primary_queue_name_prefix,
+    } = standby;
+    let IndexServices {
+        content_repository,
+        search_cache,
+    } = index_services;
+    let channel = rabbitmq_consuming_connection.create_channel().await?;
+
+    channel
+        .exchange_declare(
+            &exchange_name,
+            ExchangeKind::Topic,
+            ExchangeDeclareOptions {
+                durable: true,
+                ..ExchangeDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    // Each standby instance has its own queue prefix: all of them receive the promotions
+    let queue_name = queue_name(&queue_name_prefix);
+
+    channel
+        .queue_declare(
+            &queue_name,
+            QueueDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    channel
+        .queue_bind(
+            &queue_name,
+            &exchange_name,
+            ROUTING_KEY,
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    let consumer_options = BasicConsumeOptions {
+        no_ack: false,
+        ..BasicConsumeOptions::default()
+    };
+
+    let mut consumer = channel
+        .basic_consume(&queue_name, "", consumer_options, FieldTable::default())
+        .await?;
+
+    info!(
+        "💤 Standby {} consuming from queue {}, bound to {} with {}, waiting for its promotion ...",
+        standby_name, queue_name, exchange_name, ROUTING_KEY,
+    );
+
+    let mut is_promoted = false;
+    while let Some(delivery) = consumer.next().await {
+        is_promoted = async {
+            let delivery = match delivery {
+                // Carries the delivery alongside its channel
+                Ok(delivery) => delivery,
+                // Carries the error and is always followed by Ok(None)
+                Err(error) => {
+                    error!(
+                        ?error,
+                        "Failed to consume queue message on queue {}", queue_name
+                    );
+                    return false;
+                }
+            };
+            // The spans handling the message continue the trace of its publisher
+            continue_trace_from(&delivery.properties);
+
+            match execute_handler(&message_repository, &standby_name, &delivery) {
+                Ok(is_promoted) => {
+                    info!(
+                        "Acknowledging message with delivery tag {}",
+                        delivery.delivery_tag
+                    );
+                    if let Err(error) = delivery.ack(BasicAckOptions::default()).await {
+                        error!(?error, "Failed to ack promote_standby message");
+                    }
+
+                    is_promoted
+                }
+                Err(error) => {
+                    error!(?error, "Failed to handle promote_standby message");
+
+                    info!(
+                        "Not acknowledging message with delivery tag {}",
+                        delivery.delivery_tag
+                    );
+                    // A malformed or forged promotion would fail again
+                    if let Err(error) = delivery
+                        .nack(BasicNackOptions {
+                            requeue: false,
+                            ..BasicNackOptions::default()
+                        })
+                        .await
+                    {
+                        error!(?error, "Failed to nack promote_standby message");
+                    }
+
+                    false
+                }
+            }
+        }
+        .instrument(info_span!(
+            "Handling consumed message",
+            routing_key = ROUTING_KEY,
+            exchange = exchange_name,
+            queue = queue_name,
+            message_id = %uuid::Uuid::new_v4(),
+        ))
+        .await;
+
+        if is_promoted {
+            break;
+        }
+    }
+
+    if !is_promoted {
+        return Ok(());
+    }
+
+    // Only promoted once: a restarted instance is a standby again, unless its configuration is changed
+    channel
+        .basic_cancel(consumer.tag().as_str(), BasicCancelOptions::default())
+        .await?;
+
+    info!(
+        "🚀 Standby {} promoted, serving the queries from the queues of {}",
+        standby_name, primary_queue_name_prefix
+    );
+
+    try_join!(
+        handler_search_fulltext::register_handler(
+            rabbitmq_consuming_connection.clone(),
+            exchange_name.clone(),
+            primary_queue_name_prefix.clone(),
+            message_repository.clone(),
+            content_repository.clone(),
+            search_cache,
+            normalization_rules,
+        )
+        .map_err(RegisterHandlerPromoteStandbyError::from),
+        handler_get_source_chunks::register_handler(
+            rabbitmq_consuming_connection,
+            exchange_name,
+            primary_queue_name_prefix,
+            message_repository,
+            content_repository,
+        )
+        .map_err(RegisterHandlerPromoteStandbyError::from),
+    )?;
+
+    Ok(())
+}
+
+pub fn queue_name(queue_name_prefix: &str) -> String {
+    format!("{}_{}", queue_name_prefix, ROUTING_KEY)
+}
+
+#[derive(thiserror::Error)]
+pub enum ExecuteHandlerPromoteStandbyError {
+    #[error(transparent)]
+    MessageSigningError(#[from] MessageSigningError),
+    #[error("{0}")]
+    MessageParsingError(String),
+}
+
+impl std::fmt::Debug for ExecuteHandlerPromoteStandbyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// # Returns
+/// Whether this standby instance is promoted: the promotions of other standby instances are ignored
+#[tracing::instrument(
+    name = "Executing handler on standby promotion",
+    skip(message_repository, message)
+)]
+pub fn execute_handler(
+    message_repository: &RabbitMQMessageRepository,
+    standby_name: &str,
+    message: &Delivery,
+) -> Result<bool, ExecuteHandlerPromoteStandbyError> {
+    // Rejects promotions not published by our services
+    message_repository.verify(message)?;
+
+    let promote_standby = PromoteStandbyDto::try_parsing(&message.data).map_err(|error| {
+        ExecuteHandlerPromoteStandbyError::MessageParsingError(format!(
+            "Failed to parse promote standby message data: {}",
+            error
+        ))
+    })?;
+
+    info!(?promote_standby, "Received standby promotion");
+
+    if promote_standby.standby_name != standby_name {
+        warn!(
+            "Ignoring the promotion of the standby {}",
+            promote_standby.standby_name
+        );
+        return Ok(false);
+    }
+
+    Ok(true)
+}
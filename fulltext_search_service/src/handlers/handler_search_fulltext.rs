@@ -9,17 +9,31 @@ use lapin::{
 };
 use std::sync::Arc;
 use tracing::{error, info, info_span, Instrument};
+use uuid::Uuid;
 
-use crate::repositories::meilisearch_content_repository::{
-    MeilisearchContentRepository, MeilisearchContentRepositoryError,
+use crate::{
+    domain::entities::{
+        search_cache::{SearchCache, SearchCacheKey},
+        spelling::{corrections, query_words, suggested_queries},
+    },
+    repositories::meilisearch_content_repository::{
+        ContentSearchFilter, ContentSearchPage, MeilisearchContentRepository,
+        MeilisearchContentRepositoryError,
+    },
 };
 use common::{
     constants::routing_keys::SEARCH_FULLTEXT_ROUTING_KEY,
-    core::rabbitmq_message_repository::{
-        RabbitMQMessageRepository, RabbitMQMessageRepositoryError,
+    core::{
+        normalization_rules::NormalizationRulesCache,
+        rabbitmq_message_repository::{RabbitMQMessageRepository, RabbitMQMessageRepositoryError},
+        trace_propagation::continue_trace_from,
     },
     dtos::{
-        fulltext_search_request::FulltextSearchRequestDto,
+        extracted_content::{
+            is_chapter_filter, is_keyword_or_entity_filter, is_language_code,
+            is_structured_field_name,
+        },
+        fulltext_search_request::{FulltextSearchRequestDto, SearchSortDto},
         fulltext_search_response::{
             FulltextSearchResponseData, FulltextSearchResponseDto, ResultContent,
         },
@@ -30,6 +44,14 @@ use common::{
 
 pub const ROUTING_KEY: &str = SEARCH_FULLTEXT_ROUTING_KEY;
 
+const DEFAULT_SEARCH_LIMIT: usize = 10;
+
+/// Number of found contents under which the spelling of a query is corrected
+const MAX_HITS_FOR_SUGGESTIONS: u64 = 3;
+
+/// Number of terms similar to a word of a query among which its corrections are chosen
+const SIMILAR_TERMS_LIMIT: usize = 10;
+
 #[derive(thiserror::Error)]
 pub enum RegisterHandlerSearchFulltextError {
     #[error(transparent)]
@@ -54,7 +76,13 @@ impl std::fmt::Debug for RegisterHandlerSearchFulltextError {
 /// to avoid sharing some instances (ex: RabbitMQ channel) between each thread
 #[tracing::instrument(
     name = "Register search fulltext RPC handler",
-    skip(rabbitmq_consuming_connection, message_repository, content_repository)
+    skip(
+        rabbitmq_consuming_connection,
+        message_repository,
+        content_repository,
+        search_cache,
+        normalization_rules
+    )
 )]
 pub async fn register_handler(
     rabbitmq_consuming_connection: Arc<RabbitMQConnection>,
@@ -63,6 +91,8 @@ pub async fn register_handler(
     // Not an `Arc` shared reference as we want to initialize a new repository for each thread (or at least for each handler)
     message_repository: RabbitMQMessageRepository,
     content_repository: Arc<MeilisearchContentRepository>,
+    search_cache: Arc<SearchCache>,
+    normalization_rules: Arc<NormalizationRulesCache>,
 ) -> Result<(), RegisterHandlerSearchFulltextError> {
     let channel = rabbitmq_consuming_connection.create_channel().await?;
 
@@ -135,6 +165,8 @@ pub async fn register_handler(
                     return;
                 }
             };
+            // The spans handling the message continue the trace of its publisher
+            continue_trace_from(&delivery.properties);
 
             let reply_to = match delivery.properties.reply_to().as_ref() {
                 Some(reply_to) => reply_to,
@@ -159,11 +191,21 @@ pub async fn register_handler(
                 }
             };
 
+            // Set on the response, for the caller to match it with its request
+            let correlation_id = delivery
+                .properties
+                .correlation_id()
+                .as_ref()
+                .map(|id| id.as_str());
+
             match execute_handler(
                 &message_repository,
                 content_repository.clone(),
+                &search_cache,
+                &normalization_rules,
                 &delivery.data,
                 reply_to.as_str(),
+                correlation_id,
             )
             .await
             {
@@ -188,7 +230,7 @@ pub async fn register_handler(
                     if let Ok(response) = response.try_serializing() {
                         // Sends response to the given `reply_to` to mimic a RPC call
                         let _ = message_repository
-                            .rpc_respond(reply_to.as_str(), response.as_bytes())
+                            .rpc_respond(reply_to.as_str(), correlation_id, response.as_bytes())
                             .await;
                     }
 
@@ -239,13 +281,22 @@ impl std::fmt::Debug for ExecuteHandlerContentExtractedError {
 
 #[tracing::instrument(
     name = "Executing handler on fulltext search request",
-    skip(message_repository, content_repository, data)
+    skip(
+        message_repository,
+        content_repository,
+        search_cache,
+        normalization_rules,
+        data
+    )
 )]
 pub async fn execute_handler(
     message_repository: &RabbitMQMessageRepository,
     content_repository: Arc<MeilisearchContentRepository>,
+    search_cache: &SearchCache,
+    normalization_rules: &NormalizationRulesCache,
     data: &[u8],
     reply_to: &str,
+    correlation_id: Option<&str>,
 ) -> Result<(), ExecuteHandlerContentExtractedError> {
     let search_request = FulltextSearchRequestDto::try_parsing(data).map_err(|error| {
         ExecuteHandlerContentExtractedError::MessageParsingError(format!(
@@ -257,37 +308,223 @@ pub async fn execute_handler(
     info!(
         ?search_request,
         ?reply_to,
+        ?correlation_id,
         "Received fulltext search request, executing..."
     );
-    let FulltextSearchRequestDto { query, limit, .. } = search_request;
-
-    let results = content_repository.search(&query, limit).await?;
-
-    info!(?results, "Full result from search");
+    let FulltextSearchRequestDto {
+        query,
+        limit,
+        user_id,
+        shard,
+        language,
+        fields,
+        source_meta_ids,
+        chapter,
+        keyword,
+        entity,
+        page,
+        hits_per_page,
+        sort,
+        ..
+    } = search_request;
+    let page = ContentSearchPage {
+        page: page.unwrap_or(1),
+        hits_per_page: hits_per_page.or(limit).unwrap_or(DEFAULT_SEARCH_LIMIT),
+        sort,
+    };
+    if page.page == 0 || page.hits_per_page == 0 {
+        return Err(ExecuteHandlerContentExtractedError::MessageParsingError(
+            format!("Invalid page: {:?}", page),
+        ));
+    }
 
-    let response_data: Vec<ResultContent> = results
+    // The language is interpolated in the Meilisearch filter
+    if let Some(language) = language
+        .as_deref()
+        .filter(|language| !is_language_code(language))
+    {
+        return Err(ExecuteHandlerContentExtractedError::MessageParsingError(
+            format!("Invalid language code: {}", language),
+        ));
+    }
+    // The field names are interpolated in the Meilisearch filter
+    if let Some(name) = fields.keys().find(|name| !is_structured_field_name(name)) {
+        return Err(ExecuteHandlerContentExtractedError::MessageParsingError(
+            format!("Invalid field name: {}", name),
+        ));
+    }
+    if let Some(chapter) = chapter
+        .as_deref()
+        .filter(|chapter| !is_chapter_filter(chapter))
+    {
+        return Err(ExecuteHandlerContentExtractedError::MessageParsingError(
+            format!("Invalid chapter: {}", chapter),
+        ));
+    }
+    if let Some(value) = [&keyword, &entity]
         .into_iter()
-        .map(|result| {
-            let content_entity = result.result;
-            return ResultContent {
-                id: content_entity.id,
-                metadata: content_entity.metadata,
-                content: content_entity.content,
+        .flatten()
+        .find(|value| !is_keyword_or_entity_filter(value))
+    {
+        return Err(ExecuteHandlerContentExtractedError::MessageParsingError(
+            format!("Invalid keyword or entity: {}", value),
+        ));
+    }
+
+    // Normalized as the extracted contents, for `k8s` to find the contents mentioning `Kubernetes`
+    let query = normalization_rules
+        .get(message_repository)
+        .await
+        .normalize(&query)
+        .into_owned();
+
+    // Dashboards repeat the same searches: their results are reused until the shard changes
+    let cache_key = SearchCacheKey::new(
+        shard,
+        user_id,
+        &query,
+        Some(page.hits_per_page),
+        language.clone(),
+    )
+    .with_page(page.page, page.sort)
+    .with_fields(fields.clone())
+    .with_source_meta_ids(source_meta_ids.clone())
+    .with_chapter(chapter.clone())
+    .with_keyword_and_entity(keyword.clone(), entity.clone());
+    let response_data = match search_cache.get(&cache_key) {
+        Some(cached_response_data) => {
+            info!("Reusing the cached results of the search");
+            cached_response_data
+        }
+        // None of the sources to search
+        None if source_meta_ids.as_ref().is_some_and(Vec::is_empty) => {
+            FulltextSearchResponseData::default()
+        }
+        None => {
+            let filter = ContentSearchFilter {
+                language: language.as_deref(),
+                fields: &fields,
+                source_meta_ids: source_meta_ids.as_deref(),
+                chapter: chapter.as_deref(),
+                keyword: keyword.as_deref(),
+                entity: entity.as_deref(),
+            };
+            let found_contents = content_repository
+                .search(&query, &page, user_id, shard, &filter)
+                .await?;
+
+            info!(?found_contents, "Full result from search");
+
+            // The suggestions are only informative: the results are returned without them on a failure
+            let suggestions =
+                match page.page == 1 && found_contents.total_hits < MAX_HITS_FOR_SUGGESTIONS {
+                    true => spelling_suggestions(
+                        &content_repository,
+                        &query,
+                        user_id,
+                        shard,
+                        &filter,
+                        found_contents.total_hits,
+                    )
+                    .await
+                    .unwrap_or_else(|error| {
+                        error!(?error, "Failed to suggest spelling corrections");
+                        vec![]
+                    }),
+                    false => vec![],
+                };
+
+            let response_data = FulltextSearchResponseData {
+                results: found_contents
+                    .hits
+                    .into_iter()
+                    .map(|result| {
+                        let content_entity = result.result;
+                        return ResultContent {
+                            id: content_entity.id,
+                            metadata: content_entity.metadata,
+                            content: content_entity.content,
+                            source_meta_id: content_entity.source_meta_id,
+                        };
+                    })
+                    .collect(),
+                source_hit_counts: found_contents.source_hit_counts,
+                language_hit_counts: found_contents.language_hit_counts,
+                chapter_hit_counts: found_contents.chapter_hit_counts,
+                keyword_hit_counts: found_contents.keyword_hit_counts,
+                entity_hit_counts: found_contents.entity_hit_counts,
+                total_hits: Some(found_contents.total_hits),
+                suggestions,
             };
-        })
-        .collect();
+            search_cache.insert(cache_key, response_data.clone());
+
+            response_data
+        }
+    };
 
     let response = FulltextSearchResponseDto::Ok {
-        data: FulltextSearchResponseData {
-            results: response_data,
-        },
+        data: response_data,
     };
 
     // Sends response to the given `reply_to` to mimic a RPC call
     message_repository
-        .rpc_respond(reply_to, serde_json::to_string(&response)?.as_bytes())
+        .rpc_respond(
+            reply_to,
+            correlation_id,
+            serde_json::to_string(&response)?.as_bytes(),
+        )
         .await?;
 
     info!("Successfully handled {} message", ROUTING_KEY);
     Ok(())
 }
+
+/// Queries correcting the spelling of a query with the terms of the contents of the user, from the one finding the most contents
+///
+/// Each word of the query is corrected by the closest terms found by Meilisearch within its typo tolerance.
+/// Only the suggestions finding contents, and at least as many as the query, are kept:
+/// the query may already find the contents of a suggestion with the typos tolerated by Meilisearch.
+async fn spelling_suggestions(
+    content_repository: &MeilisearchContentRepository,
+    query: &str,
+    user_id: Uuid,
+    shard: u32,
+    filter: &ContentSearchFilter<'_>,
+    total_hits: u64,
+) -> Result<Vec<String>, MeilisearchContentRepositoryError> {
+    let mut words = vec![];
+    for (word, is_term) in query_words(query) {
+        let word_corrections = match is_term {
+            true => corrections(
+                &word,
+                &content_repository
+                    .search_similar_terms(&word, user_id, shard, SIMILAR_TERMS_LIMIT)
+                    .await?,
+            ),
+            false => vec![],
+        };
+        words.push((word, word_corrections));
+    }
+
+    // Only counts the contents found by each suggestion
+    let count_page = ContentSearchPage {
+        page: 1,
+        hits_per_page: 1,
+        sort: SearchSortDto::Relevance,
+    };
+    let mut suggestions = vec![];
+    for suggestion in suggested_queries(&words) {
+        let found_contents = content_repository
+            .search(&suggestion, &count_page, user_id, shard, filter)
+            .await?;
+        if found_contents.total_hits > 0 && found_contents.total_hits >= total_hits {
+            suggestions.push((suggestion, found_contents.total_hits));
+        }
+    }
+    suggestions.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+    Ok(suggestions
+        .into_iter()
+        .map(|(suggestion, _)| suggestion)
+        .collect())
+}
@@ -1,2 +1,20 @@
+use std::sync::Arc;
+
+use crate::{
+    domain::entities::search_cache::SearchCache,
+    repositories::meilisearch_content_repository::MeilisearchContentRepository,
+};
+
 pub mod handler_content_extracted;
+pub mod handler_delete_content;
+pub mod handler_get_source_chunks;
+pub mod handler_promote_standby;
 pub mod handler_search_fulltext;
+
+/// Services of the full-text index, shared between the handlers
+#[derive(Clone)]
+pub struct IndexServices {
+    pub content_repository: Arc<MeilisearchContentRepository>,
+    /// Cached search results, invalidated when the indexed contents of a user change
+    pub search_cache: Arc<SearchCache>,
+}
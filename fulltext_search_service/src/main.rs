@@ -1,17 +1,19 @@
-use common::telemetry::{get_tracing_subscriber, init_tracing_subscriber};
+use common::telemetry::{get_tracing_subscriber, init_tracing_subscriber, shutdown_tracing};
 use fulltext_search_service::{configuration::get_configuration, startup::Application};
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
     let tracing_subscriber = get_tracing_subscriber(
-        "content_ingestion_worker".into(),
+        "fulltext_search_service".into(),
         "info".into(),
         std::io::stdout,
     );
     init_tracing_subscriber(tracing_subscriber);
 
     // Panics if the configuration can't be read
-    let configuration = get_configuration().expect("Failed to read configuration.");
+    let configuration = get_configuration()
+        .await
+        .expect("Failed to read configuration.");
 
     let application = match Application::build(configuration).await {
         Ok(application) => application,
@@ -19,6 +21,7 @@ async fn main() -> std::io::Result<()> {
     };
 
     application.run_until_stopped().await.unwrap();
+    shutdown_tracing();
 
     Ok(())
 }
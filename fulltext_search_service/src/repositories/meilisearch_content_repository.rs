@@ -1,30 +1,333 @@
-use common::helper::error_chain_fmt;
-use meilisearch_sdk::{task_info::TaskInfo, Client};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    sync::Mutex,
+};
+
+use common::{
+    core::retry::TransientError,
+    dtos::{
+        extracted_content::{
+            CHAPTER_METADATA_ATTRIBUTES, ENTITY_METADATA_ATTRIBUTES, KEYWORDS_METADATA_KEY,
+            SOURCE_ADDED_AT_METADATA_KEY, SOURCE_NAME_METADATA_KEY,
+        },
+        fulltext_search_request::SearchSortDto,
+    },
+    helper::error_chain_fmt,
+};
+use meilisearch_sdk::{
+    documents::{DocumentDeletionQuery, DocumentsQuery},
+    errors::{Error, ErrorCode},
+    search::{SearchResult, Selectors},
+    settings::{MinWordSizeForTypos, TypoToleranceSettings},
+    task_info::TaskInfo,
+    Client,
+};
 use tracing::info;
+use uuid::Uuid;
+
+use crate::domain::entities::{
+    content::ContentEntity,
+    spelling::{TermEntity, MIN_TERM_LENGTH, MIN_TERM_LENGTH_FOR_TWO_TYPOS},
+};
+
+/// Attribute of the id of the user owning a content, from its metadata
+const USER_ID_ATTRIBUTE: &str = "metadata.user_id";
+
+/// Attribute of the detected language of a content, from its metadata
+const LANGUAGE_ATTRIBUTE: &str = "metadata.language";
 
-use crate::domain::entities::content::ContentEntity;
+/// Attribute of the fields of a row of a structured source, from its metadata.
+/// Its nested fields are filterable as `metadata.structured.fields.{name}`.
+const STRUCTURED_FIELDS_ATTRIBUTE: &str = "metadata.structured.fields";
 
-const DEFAULT_SEARCH_LIMIT: usize = 10;
+/// Attribute of the source of a content, counted by the searches
+const SOURCE_META_ID_ATTRIBUTE: &str = "source_meta_id";
+
+/// Attribute of the id of the user of a term, in the terms indexes
+const TERM_USER_ID_ATTRIBUTE: &str = "user_id";
+
+/// Ranking rules of the indexes: the default rules, with the sort first
+///
+/// The searches sorted by recency or source name are sorted before their relevance.
+/// The sort rule is ignored by the searches without sort.
+const RANKING_RULES: [&str; 6] = [
+    "sort",
+    "words",
+    "typo",
+    "proximity",
+    "attribute",
+    "exactness",
+];
+
+/// Meilisearch sort of the found contents, if any
+fn sort_expression(sort: SearchSortDto) -> Option<String> {
+    match sort {
+        SearchSortDto::Relevance => None,
+        SearchSortDto::Recency => Some(format!("metadata.{}:desc", SOURCE_ADDED_AT_METADATA_KEY)),
+        SearchSortDto::SourceName => Some(format!("metadata.{}:asc", SOURCE_NAME_METADATA_KEY)),
+    }
+}
+
+/// Attributes of the chapter of a content, from its metadata, see `CHAPTER_METADATA_ATTRIBUTES`
+fn chapter_attributes() -> impl Iterator<Item = String> {
+    CHAPTER_METADATA_ATTRIBUTES
+        .iter()
+        .map(|attribute| format!("metadata.{}", attribute))
+}
+
+/// Attribute of the keywords of a content, from its metadata
+fn keywords_attribute() -> String {
+    format!("metadata.{}", KEYWORDS_METADATA_KEY)
+}
+
+/// Attributes of the named entities of a content, from its metadata, see `ENTITY_METADATA_ATTRIBUTES`
+fn entity_attributes() -> impl Iterator<Item = String> {
+    ENTITY_METADATA_ATTRIBUTES
+        .iter()
+        .map(|attribute| format!("metadata.{}", attribute))
+}
+
+/// Escapes a value given by a client, to be interpolated within quotes in a filter
+fn escape_filter_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Filters of a search on the contents of a user, besides the user
+///
+/// The language should be checked to be a language code, and the field names to be structured field names, beforehand.
+#[derive(Debug)]
+pub struct ContentSearchFilter<'a> {
+    pub language: Option<&'a str>,
+    /// Values of the fields of the rows of structured sources
+    pub fields: &'a BTreeMap<String, String>,
+    /// Only the contents of these sources, if any
+    pub source_meta_ids: Option<&'a [Uuid]>,
+    /// Only the contents of this chapter, whatever the reader of their source, if any
+    pub chapter: Option<&'a str>,
+    /// Only the contents with this keyword, if any
+    pub keyword: Option<&'a str>,
+    /// Only the contents mentioning this named entity, whatever its kind, if any
+    pub entity: Option<&'a str>,
+}
+
+impl ContentSearchFilter<'_> {
+    /// Meilisearch filter expression on the contents of a user
+    pub fn expression(&self, user_id: Uuid) -> String {
+        let mut filter = format!("{} = \"{}\"", USER_ID_ATTRIBUTE, user_id);
+        if let Some(language) = self.language {
+            filter.push_str(&format!(" AND {} = \"{}\"", LANGUAGE_ATTRIBUTE, language));
+        }
+        for (name, value) in self.fields {
+            filter.push_str(&format!(
+                " AND {}.{} = \"{}\"",
+                STRUCTURED_FIELDS_ATTRIBUTE,
+                name,
+                escape_filter_value(value)
+            ));
+        }
+        if let Some(source_meta_ids) = self.source_meta_ids {
+            let source_meta_ids: Vec<String> = source_meta_ids
+                .iter()
+                .map(|source_meta_id| format!("\"{}\"", source_meta_id))
+                .collect();
+            filter.push_str(&format!(
+                " AND {} IN [{}]",
+                SOURCE_META_ID_ATTRIBUTE,
+                source_meta_ids.join(", ")
+            ));
+        }
+        if let Some(chapter) = self.chapter {
+            let chapter = escape_filter_value(chapter);
+            let conditions: Vec<String> = chapter_attributes()
+                .map(|attribute| format!("{} = \"{}\"", attribute, chapter))
+                .collect();
+            filter.push_str(&format!(" AND ({})", conditions.join(" OR ")));
+        }
+        if let Some(keyword) = self.keyword {
+            filter.push_str(&format!(
+                " AND {} = \"{}\"",
+                keywords_attribute(),
+                escape_filter_value(keyword)
+            ));
+        }
+        if let Some(entity) = self.entity {
+            let entity = escape_filter_value(entity);
+            let conditions: Vec<String> = entity_attributes()
+                .map(|attribute| format!("{} = \"{}\"", attribute, entity))
+                .collect();
+            filter.push_str(&format!(" AND ({})", conditions.join(" OR ")));
+        }
+
+        filter
+    }
+}
+
+/// Page of the found contents, in a given order
+#[derive(Debug, Clone, Copy)]
+pub struct ContentSearchPage {
+    /// From 1
+    pub page: usize,
+    pub hits_per_page: usize,
+    pub sort: SearchSortDto,
+}
+
+/// Contents of a user found by a search in a shard
+#[derive(Debug, Default)]
+pub struct FoundContents {
+    /// Number of contents matching the query, not only the returned ones
+    pub total_hits: u64,
+    pub hits: Vec<SearchResult<ContentEntity>>,
+    /// Number of contents matching the query per source, from the facet distribution of the search:
+    /// all the matches are counted, not only the returned hits
+    pub source_hit_counts: HashMap<Uuid, u64>,
+    /// Number of contents matching the query per detected language, counted as the sources
+    pub language_hit_counts: HashMap<String, u64>,
+    /// Number of contents matching the query per chapter, whatever the reader of their source, counted as the sources
+    pub chapter_hit_counts: HashMap<String, u64>,
+    /// Number of contents matching the query per keyword, counted as the sources
+    pub keyword_hit_counts: HashMap<String, u64>,
+    /// Number of contents matching the query per named entity, whatever its kind, counted as the sources
+    pub entity_hit_counts: HashMap<String, u64>,
+}
 
 /// Repository for `ContentEntity` persisted in Meilisearch
+///
+/// The contents of a tenant are split into shards, each one in its own index, to stay below the
+/// practical size limits of an index. The gateway routes each source to a shard.
 pub struct MeilisearchContentRepository {
     client: Client,
     index: String,
+    /// Shards whose index was set up by this instance
+    set_up_shards: Mutex<HashSet<u32>>,
 }
 
 impl MeilisearchContentRepository {
     pub fn new(client: Client, index: String) -> Self {
-        Self { client, index }
+        Self {
+            client,
+            index,
+            set_up_shards: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Name of the index of a shard: `{index}_{shard}`
+    ///
+    /// The first shard keeps the name of the index, for the contents saved before the sharding
+    /// to stay searchable.
+    pub fn shard_index(&self, shard: u32) -> String {
+        match shard {
+            0 => self.index.clone(),
+            shard => format!("{}_{}", self.index, shard),
+        }
     }
 
+    /// Name of the index of the terms of the contents of a shard: `{shard_index}_terms`
+    pub fn terms_index(&self, shard: u32) -> String {
+        format!("{}_terms", self.shard_index(shard))
+    }
+
+    /// Sets up the index of the first shard, see `set_up_shard`
+    #[tracing::instrument(name = "Setting up Meilisearch index", skip(self))]
+    pub async fn set_up_index(&self) -> Result<(), MeilisearchContentRepositoryError> {
+        self.set_up_shard(0).await
+    }
+
+    /// Sets up the index of a shard: the contents can be filtered by source, to be deleted with their source,
+    /// by user, to only search the contents of a user, by language, by chapter, by keyword, by named entity,
+    /// and by the fields of the rows of structured sources.
+    /// They can be sorted by recency and by source name, see `RANKING_RULES`.
+    /// The terms of the contents can be filtered by user, and are found with the typos tolerated by the spelling corrections.
+    ///
+    /// Idempotent
+    #[tracing::instrument(name = "Setting up Meilisearch shard index", skip(self))]
+    pub async fn set_up_shard(&self, shard: u32) -> Result<(), MeilisearchContentRepositoryError> {
+        let task: TaskInfo = self
+            .client
+            .index(self.shard_index(shard))
+            .set_filterable_attributes(
+                [
+                    SOURCE_META_ID_ATTRIBUTE,
+                    USER_ID_ATTRIBUTE,
+                    LANGUAGE_ATTRIBUTE,
+                    STRUCTURED_FIELDS_ATTRIBUTE,
+                ]
+                .into_iter()
+                .map(String::from)
+                .chain(chapter_attributes())
+                .chain([keywords_attribute()])
+                .chain(entity_attributes()),
+            )
+            .await?;
+        info!(?task, "Set up the filterable attributes");
+
+        let index = self.client.index(self.shard_index(shard));
+        let task: TaskInfo = index
+            .set_sortable_attributes(
+                [SOURCE_ADDED_AT_METADATA_KEY, SOURCE_NAME_METADATA_KEY]
+                    .map(|key| format!("metadata.{}", key)),
+            )
+            .await?;
+        info!(?task, "Set up the sortable attributes");
+        let task: TaskInfo = index.set_ranking_rules(RANKING_RULES).await?;
+        info!(?task, "Set up the ranking rules");
+
+        let terms_index = self.client.index(self.terms_index(shard));
+        let task: TaskInfo = terms_index
+            .set_filterable_attributes([TERM_USER_ID_ATTRIBUTE])
+            .await?;
+        info!(?task, "Set up the filterable attributes of the terms");
+        let task: TaskInfo = terms_index.set_searchable_attributes(["term"]).await?;
+        info!(?task, "Set up the searchable attributes of the terms");
+        let task: TaskInfo = terms_index
+            .set_typo_tolerance(&TypoToleranceSettings {
+                min_word_size_for_typos: Some(MinWordSizeForTypos {
+                    one_typo: Some(MIN_TERM_LENGTH as u8),
+                    two_typos: Some(MIN_TERM_LENGTH_FOR_TWO_TYPOS as u8),
+                }),
+                ..TypoToleranceSettings::default()
+            })
+            .await?;
+
+        info!(?task, "Set up index");
+        self.set_up_shards
+            .lock()
+            .expect("set up shards lock poisoned")
+            .insert(shard);
+
+        Ok(())
+    }
+
+    /// Sets up the index of a shard if it was not set up by this instance yet
+    async fn ensure_shard_set_up(
+        &self,
+        shard: u32,
+    ) -> Result<(), MeilisearchContentRepositoryError> {
+        let is_set_up = self
+            .set_up_shards
+            .lock()
+            .expect("set up shards lock poisoned")
+            .contains(&shard);
+        if !is_set_up {
+            self.set_up_shard(shard).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Saves a content to the index of its shard, setting up the index of a new shard beforehand
+    ///
+    /// The tasks of an index are processed in order: the settings apply before the content is added.
     #[tracing::instrument(name = "Saving content to Meilishearch", skip(self))]
     pub async fn save(
         &self,
         content: &ContentEntity,
+        shard: u32,
     ) -> Result<(), MeilisearchContentRepositoryError> {
+        self.ensure_shard_set_up(shard).await?;
+
         let task: TaskInfo = self
             .client
-            .index(&self.index)
+            .index(self.shard_index(shard))
             .add_or_replace(&[content], None)
             .await?;
 
@@ -33,29 +336,243 @@ impl MeilisearchContentRepository {
         Ok(())
     }
 
+    /// Saves the terms of the contents of a user to the terms index of their shard
+    ///
+    /// A term already saved for the user is replaced.
+    #[tracing::instrument(name = "Saving terms to Meilisearch", skip(self, terms))]
+    pub async fn save_terms(
+        &self,
+        user_id: Uuid,
+        terms: &BTreeSet<String>,
+        shard: u32,
+    ) -> Result<(), MeilisearchContentRepositoryError> {
+        if terms.is_empty() {
+            return Ok(());
+        }
+        self.ensure_shard_set_up(shard).await?;
+
+        let terms: Vec<TermEntity> = terms
+            .iter()
+            .map(|term| TermEntity::new(user_id, term.clone()))
+            .collect();
+        // Both `id` and `user_id` could be the primary key
+        let task: TaskInfo = self
+            .client
+            .index(self.terms_index(shard))
+            .add_or_replace(&terms, Some("id"))
+            .await?;
+
+        info!(?task, "Saved {} terms", terms.len());
+
+        Ok(())
+    }
+
+    /// Searches the terms of the contents of a user in a shard similar to a word, with the typos tolerated by Meilisearch
+    #[tracing::instrument(name = "Searching similar terms from Meilisearch", skip(self))]
+    pub async fn search_similar_terms(
+        &self,
+        word: &str,
+        user_id: Uuid,
+        shard: u32,
+        limit: usize,
+    ) -> Result<Vec<String>, MeilisearchContentRepositoryError> {
+        let filter = format!("{} = \"{}\"", TERM_USER_ID_ATTRIBUTE, user_id);
+        let index = self.client.index(self.terms_index(shard));
+
+        let result = index
+            .search()
+            .with_query(word)
+            .with_filter(&filter)
+            .with_limit(limit)
+            .execute::<TermEntity>()
+            .await;
+
+        match result {
+            Ok(result) => Ok(result.hits.into_iter().map(|hit| hit.result.term).collect()),
+            // The terms index of a shard is only created with its first content
+            Err(Error::Meilisearch(error)) if error.error_code == ErrorCode::IndexNotFound => {
+                Ok(vec![])
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Searches the contents of a user in a shard matching a filter
+    ///
+    /// The matching contents are counted per source, language, chapter, keyword and named entity,
+    /// with the facet distribution of the search.
+    ///
+    /// The chapter, keyword and entity attributes were made filterable after the first shards were created:
+    /// a shard is set up by this instance before being searched, to filter and count the contents by them.
     #[tracing::instrument(name = "Searching content from Meilishearch", skip(self))]
     pub async fn search(
         &self,
         query: &str,
-        limit: Option<usize>,
-    ) -> Result<
-        Vec<meilisearch_sdk::search::SearchResult<ContentEntity>>,
-        MeilisearchContentRepositoryError,
-    > {
-        let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
-
-        let result = self
-            .client
-            .index(&self.index)
-            .search()
+        page: &ContentSearchPage,
+        user_id: Uuid,
+        shard: u32,
+        filter: &ContentSearchFilter<'_>,
+    ) -> Result<FoundContents, MeilisearchContentRepositoryError> {
+        self.ensure_shard_set_up(shard).await?;
+        let filter = filter.expression(user_id);
+        let chapter_attributes: Vec<String> = chapter_attributes().collect();
+        let keywords_attribute = keywords_attribute();
+        let entity_attributes: Vec<String> = entity_attributes().collect();
+        let facets: Vec<&str> = [SOURCE_META_ID_ATTRIBUTE, LANGUAGE_ATTRIBUTE]
+            .into_iter()
+            .chain(chapter_attributes.iter().map(String::as_str))
+            .chain([keywords_attribute.as_str()])
+            .chain(entity_attributes.iter().map(String::as_str))
+            .collect();
+
+        let sort = sort_expression(page.sort);
+        let sort: Vec<&str> = sort.iter().map(String::as_str).collect();
+
+        // Paginated with a page number rather than an offset, for Meilisearch to count all the matches
+        let index = self.client.index(self.shard_index(shard));
+        let mut search = index.search();
+        search
             .with_query(query)
+            .with_filter(&filter)
+            .with_page(page.page)
+            .with_hits_per_page(page.hits_per_page)
+            .with_facets(Selectors::Some(&facets));
+        if !sort.is_empty() {
+            search.with_sort(&sort);
+        }
+        let result = search.execute::<ContentEntity>().await;
+
+        let result = match result {
+            Ok(result) => result,
+            Err(Error::Meilisearch(error)) if error.error_code == ErrorCode::IndexNotFound => {
+                return Ok(FoundContents::default());
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        info!(?result, "Result:");
+
+        let mut facet_distribution = result.facet_distribution.unwrap_or_default();
+        let mut facet_counts = |attribute: &str| -> HashMap<String, u64> {
+            facet_distribution
+                .remove(attribute)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(value, count)| (value, count as u64))
+                .collect()
+        };
+
+        let source_hit_counts = facet_counts(SOURCE_META_ID_ATTRIBUTE)
+            .into_iter()
+            .filter_map(|(source_meta_id, count)| {
+                Uuid::parse_str(&source_meta_id)
+                    .ok()
+                    .map(|source_meta_id| (source_meta_id, count))
+            })
+            .collect();
+        let language_hit_counts = facet_counts(LANGUAGE_ATTRIBUTE);
+        // A content only has the chapter attribute of the reader of its source
+        let mut chapter_hit_counts = HashMap::new();
+        for attribute in &chapter_attributes {
+            for (chapter, count) in facet_counts(attribute) {
+                *chapter_hit_counts.entry(chapter).or_default() += count;
+            }
+        }
+        let keyword_hit_counts = facet_counts(&keywords_attribute);
+        // The same name can be recognized as several kinds of entities
+        let mut entity_hit_counts: HashMap<String, u64> = HashMap::new();
+        for attribute in &entity_attributes {
+            for (entity, count) in facet_counts(attribute) {
+                let entity_count = entity_hit_counts.entry(entity).or_default();
+                *entity_count = (*entity_count).max(count);
+            }
+        }
+
+        Ok(FoundContents {
+            total_hits: result.total_hits.unwrap_or_default() as u64,
+            hits: result.hits,
+            source_hit_counts,
+            language_hit_counts,
+            chapter_hit_counts,
+            keyword_hit_counts,
+            entity_hit_counts,
+        })
+    }
+
+    /// Lists a page of the contents extracted from a source of a user, in the order they were saved
+    ///
+    /// # Returns
+    /// The contents of the page, and the number of contents of the source
+    #[tracing::instrument(name = "Listing contents of a source from Meilisearch", skip(self))]
+    pub async fn list_by_source_meta_id(
+        &self,
+        source_meta_id: Uuid,
+        user_id: Uuid,
+        shard: u32,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<ContentEntity>, usize), MeilisearchContentRepositoryError> {
+        let filter = format!(
+            "{} = \"{}\" AND {} = \"{}\"",
+            SOURCE_META_ID_ATTRIBUTE, source_meta_id, USER_ID_ATTRIBUTE, user_id
+        );
+        let index = self.client.index(self.shard_index(shard));
+
+        let result = DocumentsQuery::new(&index)
+            .with_filter(&filter)
+            .with_offset(offset)
             .with_limit(limit)
             .execute::<ContentEntity>()
+            .await;
+
+        match result {
+            Ok(result) => Ok((result.results, result.total as usize)),
+            // The index of a shard is only created with its first content
+            Err(Error::Meilisearch(error)) if error.error_code == ErrorCode::IndexNotFound => {
+                Ok((vec![], 0))
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Deletes all the contents extracted from a source, from the shard they were saved to
+    #[tracing::instrument(name = "Deleting contents of a source from Meilisearch", skip(self))]
+    pub async fn delete_by_source_meta_id(
+        &self,
+        source_meta_id: Uuid,
+        shard: u32,
+    ) -> Result<(), MeilisearchContentRepositoryError> {
+        let filter = format!("source_meta_id = \"{}\"", source_meta_id);
+        let index = self.client.index(self.shard_index(shard));
+
+        let task: TaskInfo = DocumentDeletionQuery::new(&index)
+            .with_filter(&filter)
+            .execute::<ContentEntity>()
             .await?;
 
-        info!(?result, "Result:");
+        info!(?task, "Deleted contents");
 
-        Ok(result.hits)
+        Ok(())
+    }
+
+    /// Deletes some contents by their ids, from the shard they were saved to
+    ///
+    /// The ids not found in the index are ignored.
+    #[tracing::instrument(name = "Deleting contents by ids from Meilisearch", skip(self))]
+    pub async fn delete_by_ids(
+        &self,
+        content_ids: &[Uuid],
+        shard: u32,
+    ) -> Result<(), MeilisearchContentRepositoryError> {
+        let task: TaskInfo = self
+            .client
+            .index(self.shard_index(shard))
+            .delete_documents(content_ids)
+            .await?;
+
+        info!(?task, "Deleted contents");
+
+        Ok(())
     }
 
     pub fn index(&self) -> String {
@@ -69,8 +586,79 @@ pub enum MeilisearchContentRepositoryError {
     MeilisearchError(#[from] meilisearch_sdk::errors::Error),
 }
 
+/// Unreachable server, timeouts, throttling and internal errors of Meilisearch are transient
+impl TransientError for MeilisearchContentRepositoryError {
+    fn is_transient(&self) -> bool {
+        use meilisearch_sdk::errors::ErrorType;
+
+        match self {
+            MeilisearchContentRepositoryError::MeilisearchError(error) => match error {
+                Error::UnreachableServer | Error::Timeout | Error::HttpError(_) => true,
+                Error::MeilisearchCommunication(error) => {
+                    error.status_code >= 500 || error.status_code == 408 || error.status_code == 429
+                }
+                Error::Meilisearch(error) => error.error_type == ErrorType::Internal,
+                _ => false,
+            },
+        }
+    }
+}
+
 impl std::fmt::Debug for MeilisearchContentRepositoryError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         error_chain_fmt(self, f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_expression_combines_the_filters_escaping_the_client_values() {
+        let user_id = Uuid::from_u128(1);
+        let source_meta_ids = [Uuid::from_u128(2), Uuid::from_u128(3)];
+        let fields = BTreeMap::from([("category".to_string(), "a \"b\"".to_string())]);
+        let filter = ContentSearchFilter {
+            language: Some("fr"),
+            fields: &fields,
+            source_meta_ids: Some(&source_meta_ids),
+            chapter: Some("Chapter \"1\""),
+            keyword: Some("white whale"),
+            entity: Some("Ahab"),
+        };
+
+        assert_eq!(
+            filter.expression(user_id),
+            format!(
+                concat!(
+                    r#"metadata.user_id = "{}" AND metadata.language = "fr""#,
+                    r#" AND metadata.structured.fields.category = "a \"b\"""#,
+                    r#" AND source_meta_id IN ["{}", "{}"]"#,
+                    r#" AND (metadata.epub.chapter_id = "Chapter \"1\"" OR metadata.latex.chapter = "Chapter \"1\"")"#,
+                    r#" AND metadata.keywords = "white whale""#,
+                    r#" AND (metadata.entities.people = "Ahab" OR metadata.entities.places = "Ahab" OR metadata.entities.organizations = "Ahab")"#
+                ),
+                user_id, source_meta_ids[0], source_meta_ids[1]
+            )
+        );
+    }
+
+    #[test]
+    fn filter_expression_without_filters_only_filters_the_user() {
+        let user_id = Uuid::from_u128(1);
+        let filter = ContentSearchFilter {
+            language: None,
+            fields: &BTreeMap::new(),
+            source_meta_ids: None,
+            chapter: None,
+            keyword: None,
+            entity: None,
+        };
+
+        assert_eq!(
+            filter.expression(user_id),
+            format!(r#"metadata.user_id = "{}""#, user_id)
+        );
+    }
+}
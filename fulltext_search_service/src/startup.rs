@@ -1,15 +1,36 @@
-use std::sync::Arc;
+use std::{net::TcpListener, sync::Arc};
 
 use crate::{
-    configuration::{MeilisearchSettings, RabbitMQSettings, Settings},
+    configuration::{MeilisearchSettings, RabbitMQSettings, Settings, StandbySettings},
+    domain::entities::search_cache::SearchCache,
     handlers::{
         handler_content_extracted::{self, RegisterHandlerContentExtractedError},
+        handler_delete_content::{self, RegisterHandlerDeleteContentError},
+        handler_get_source_chunks::{self, RegisterHandlerGetSourceChunksError},
+        handler_promote_standby::{self, RegisterHandlerPromoteStandbyError, Standby},
         handler_search_fulltext::{self, RegisterHandlerSearchFulltextError},
+        IndexServices,
     },
-    repositories::meilisearch_content_repository::MeilisearchContentRepository,
+    repositories::meilisearch_content_repository::{
+        MeilisearchContentRepository, MeilisearchContentRepositoryError,
+    },
+};
+use common::{
+    core::{
+        health_server::{
+            run_health_server, DependencyCheck, HealthChecks, RabbitMQConnectionCheck,
+        },
+        message_signing::{MessageSigner, MessageSigningError},
+        normalization_rules::{watch_normalization_rules_changes, NormalizationRulesCache},
+        rabbitmq_message_repository::RabbitMQMessageRepository,
+        retry::RetryPolicy,
+    },
+    dtos::extract_content_job::IngestionLaneDto,
+};
+use futures::{
+    future::{join_all, BoxFuture},
+    TryFutureExt,
 };
-use common::core::rabbitmq_message_repository::RabbitMQMessageRepository;
-use futures::{future::join_all, TryFutureExt};
 use lapin::Connection as RabbitMQConnection;
 use meilisearch_sdk::Client as MeilisearchClient;
 use secrecy::ExposeSecret;
@@ -22,10 +43,15 @@ pub struct Application {
     rabbitmq_publishing_connection: Arc<RabbitMQConnection>,
     rabbitmq_content_exchange_name: String,
     rabbitmq_queue_name_prefix: String,
+    /// Queue name prefix of the primary instance, if this instance is a standby
+    rabbitmq_primary_queue_name_prefix: Option<String>,
 
     // Meilisearch
     meilisearch_client: MeilisearchClient,
 
+    // Port of the health probes, useful when binding a random port
+    health_port: u16,
+
     // handlers: Vec<Box<dyn Future<Output = Result<(), ApplicationError>>>>,
     handlers: Vec<JoinHandle<Result<(), ApplicationError>>>,
 }
@@ -40,36 +66,82 @@ impl Application {
         let rabbitmq_publishing_connection =
             Arc::new(get_rabbitmq_connection(&settings.rabbitmq).await?);
 
-        let rabbitmq_content_exchange_name = format!(
-            "{}_{}",
-            settings.rabbitmq.exchange_name_prefix, settings.rabbitmq.content_exchange
-        );
+        let rabbitmq_content_exchange_name = settings.rabbitmq.content_exchange_name();
+        let rabbitmq_queue_name_prefix = settings.rabbitmq.tenant_queue_name_prefix();
+        let rabbitmq_primary_queue_name_prefix = primary_queue_name_prefix(&settings)?;
 
         let message_repository = RabbitMQMessageRepository::new(
             rabbitmq_publishing_connection.clone(),
             &rabbitmq_content_exchange_name,
-        );
+        )
+        .with_signer(MessageSigner::try_new(settings.message_signing)?);
 
         let meilisearch_client = get_meilisearch_client(&settings.meilisearch);
         let content_repository = MeilisearchContentRepository::new(
             meilisearch_client.clone(),
             settings.meilisearch.contents_index,
         );
+        content_repository.set_up_index().await?;
         // Sharing the same meilisearch repository with parallel handlers/threads
         let content_repository = Arc::new(content_repository);
+        // Shared by the search handler, and the handlers invalidating it
+        let search_cache = Arc::new(SearchCache::new(&settings.search_cache));
+
+        // The rules of the tenant are cached until the gateway publishes a change
+        let normalization_rules = Arc::new(NormalizationRulesCache::new(
+            settings.normalization_rules.clone(),
+        ));
+        if settings.normalization_rules.enabled {
+            watch_normalization_rules_changes(
+                &rabbitmq_publishing_connection,
+                &rabbitmq_content_exchange_name,
+                normalization_rules.clone(),
+            )
+            .await?;
+        }
+
+        let health_checks = HealthChecks::new()
+            .with_liveness_check(
+                "rabbitmq_consuming",
+                RabbitMQConnectionCheck::new(&rabbitmq_consuming_connection),
+            )
+            .with_liveness_check(
+                "rabbitmq_publishing",
+                RabbitMQConnectionCheck::new(&rabbitmq_publishing_connection),
+            )
+            .with_readiness_check("meilisearch", MeilisearchCheck(meilisearch_client.clone()));
+        let listener = TcpListener::bind(format!(
+            "{}:{}",
+            settings.application.host, settings.application.port
+        ))?;
+        let health_port = listener.local_addr()?.port();
+
+        tokio::spawn(
+            run_health_server(listener, health_checks, None).inspect_err(|error| {
+                error!(?error, "Health server stopped");
+            }),
+        );
 
         let mut app = Self {
             rabbitmq_publishing_connection,
             rabbitmq_content_exchange_name,
-            rabbitmq_queue_name_prefix: settings.rabbitmq.queue_name_prefix,
+            rabbitmq_queue_name_prefix,
+            rabbitmq_primary_queue_name_prefix,
             meilisearch_client,
+            health_port,
             handlers: vec![],
         };
 
         app.prepare_message_handlers(
             rabbitmq_consuming_connection,
             message_repository,
-            content_repository,
+            IndexServices {
+                content_repository,
+                search_cache,
+            },
+            normalization_rules,
+            settings.retry,
+            settings.standby,
         )
         .await?;
 
@@ -88,7 +160,8 @@ impl Application {
             self,
             rabbitmq_consuming_connection,
             message_repository,
-            content_repository
+            index_services,
+            normalization_rules
         )
     )]
     pub async fn prepare_message_handlers(
@@ -96,36 +169,93 @@ impl Application {
         rabbitmq_consuming_connection: Arc<RabbitMQConnection>,
         // Not an `Arc` shared reference as we want to initialize a new repository for each thread (or at least for each handler)
         message_repository: RabbitMQMessageRepository,
-        content_repository: Arc<MeilisearchContentRepository>,
+        index_services: IndexServices,
+        normalization_rules: Arc<NormalizationRulesCache>,
+        retry_policy: RetryPolicy,
+        standby_settings: StandbySettings,
     ) -> Result<(), ApplicationError> {
         let exchange_name = self.rabbitmq_content_exchange_name.clone();
         let queue_name_prefix = self.rabbitmq_queue_name_prefix.clone();
 
         // We could have several message handlers running in parallel bound with the same binding key to the same exchange.
         // Or other message handlers bound with a different binding key to the same or another exchange.
+        // The contents of each ingestion lane are indexed by their own handler
+        for lane in [IngestionLaneDto::Bulk, IngestionLaneDto::Fast] {
+            let spawn_handler = tokio::spawn(
+                handler_content_extracted::register_handler(
+                    rabbitmq_consuming_connection.clone(),
+                    exchange_name.clone(),
+                    queue_name_prefix.clone(),
+                    message_repository.clone(),
+                    index_services.clone(),
+                    retry_policy.clone(),
+                    lane,
+                )
+                .map_err(|e| e.into()),
+            );
+
+            self.handlers.push(spawn_handler);
+        }
+
         let spawn_handler = tokio::spawn(
-            handler_content_extracted::register_handler(
+            handler_delete_content::register_handler(
                 rabbitmq_consuming_connection.clone(),
                 exchange_name.clone(),
                 queue_name_prefix.clone(),
-                message_repository.clone(),
-                content_repository.clone(),
+                index_services.content_repository.clone(),
+                index_services.search_cache.clone(),
+                retry_policy,
             )
             .map_err(|e| e.into()),
         );
 
         self.handlers.push(spawn_handler);
 
-        let spawn_handler = tokio::spawn(
-            handler_search_fulltext::register_handler(
-                rabbitmq_consuming_connection.clone(),
-                exchange_name,
-                queue_name_prefix,
-                message_repository.clone(),
-                content_repository.clone(),
-            )
-            .map_err(|e| e.into()),
-        );
+        // A standby instance only serves the queries once promoted
+        let spawn_handler = match self.rabbitmq_primary_queue_name_prefix.clone() {
+            Some(primary_queue_name_prefix) => tokio::spawn(
+                handler_promote_standby::register_handler(
+                    rabbitmq_consuming_connection.clone(),
+                    exchange_name,
+                    queue_name_prefix,
+                    Standby {
+                        name: standby_settings.name,
+                        primary_queue_name_prefix,
+                    },
+                    message_repository.clone(),
+                    index_services,
+                    normalization_rules,
+                )
+                .map_err(|e| e.into()),
+            ),
+            None => {
+                // The previews of the extracted contents are served as the queries
+                let spawn_handler = tokio::spawn(
+                    handler_get_source_chunks::register_handler(
+                        rabbitmq_consuming_connection.clone(),
+                        exchange_name.clone(),
+                        queue_name_prefix.clone(),
+                        message_repository.clone(),
+                        index_services.content_repository.clone(),
+                    )
+                    .map_err(|e| e.into()),
+                );
+                self.handlers.push(spawn_handler);
+
+                tokio::spawn(
+                    handler_search_fulltext::register_handler(
+                        rabbitmq_consuming_connection.clone(),
+                        exchange_name,
+                        queue_name_prefix,
+                        message_repository.clone(),
+                        index_services.content_repository.clone(),
+                        index_services.search_cache,
+                        normalization_rules,
+                    )
+                    .map_err(|e| e.into()),
+                )
+            }
+        };
 
         self.handlers.push(spawn_handler);
 
@@ -147,6 +277,25 @@ impl Application {
         info!("👋 Bye!");
         Ok(())
     }
+
+    pub fn health_port(&self) -> u16 {
+        self.health_port
+    }
+}
+
+/// Meilisearch instance of the service: the contents are indexed and searched in it
+struct MeilisearchCheck(MeilisearchClient);
+
+impl DependencyCheck for MeilisearchCheck {
+    fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(async move {
+            self.0
+                .health()
+                .await
+                .map(|_| ())
+                .map_err(|error| error.to_string())
+        })
+    }
 }
 
 /// Create a connection to RabbitMQ
@@ -156,6 +305,26 @@ pub async fn get_rabbitmq_connection(
     RabbitMQConnection::connect(&config.get_uri(), config.get_connection_properties()).await
 }
 
+/// Queue name prefix of the primary instance if this instance is a standby, `None` for a primary instance
+///
+/// A standby instance sharing the queues of the primary would split the indexing messages with it,
+/// instead of receiving all of them.
+fn primary_queue_name_prefix(settings: &Settings) -> Result<Option<String>, ApplicationError> {
+    if !settings.standby.enabled {
+        return Ok(None);
+    }
+
+    if settings.standby.primary_queue_name_prefix == settings.rabbitmq.queue_name_prefix {
+        return Err(ApplicationError::ConfigurationError(
+            "a standby instance should have another queue name prefix than its primary".to_string(),
+        ));
+    }
+
+    Ok(Some(settings.standby.tenant_primary_queue_name_prefix(
+        settings.rabbitmq.tenant.as_ref(),
+    )))
+}
+
 /// Set up a client to Meilisearch
 pub fn get_meilisearch_client(config: &MeilisearchSettings) -> MeilisearchClient {
     MeilisearchClient::new(config.endpoint(), Some(config.api_key.expose_secret()))
@@ -171,4 +340,16 @@ pub enum ApplicationError {
     ContentExtractedHandlerError(#[from] RegisterHandlerContentExtractedError),
     #[error(transparent)]
     SearchFulltextHandlerError(#[from] RegisterHandlerSearchFulltextError),
+    #[error(transparent)]
+    GetSourceChunksHandlerError(#[from] RegisterHandlerGetSourceChunksError),
+    #[error(transparent)]
+    DeleteContentHandlerError(#[from] RegisterHandlerDeleteContentError),
+    #[error(transparent)]
+    PromoteStandbyHandlerError(#[from] RegisterHandlerPromoteStandbyError),
+    #[error(transparent)]
+    MeilisearchContentRepositoryError(#[from] MeilisearchContentRepositoryError),
+    #[error(transparent)]
+    MessageSigningError(#[from] MessageSigningError),
+    #[error("Invalid configuration: {0}")]
+    ConfigurationError(String),
 }
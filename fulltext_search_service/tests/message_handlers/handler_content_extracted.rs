@@ -1,5 +1,8 @@
 use chrono::Utc;
-use common::dtos::extracted_content::ExtractedContentDto;
+use common::dtos::{
+    extract_content_job::IngestionLaneDto, extracted_content::ExtractedContentDto,
+    templates::message_envelope::MessageEnvelope,
+};
 use fake::{faker::lorem::en::Sentences, Fake};
 use fulltext_search_service::handlers::handler_content_extracted::{queue_name, ROUTING_KEY};
 use lapin::{options::BasicPublishOptions, BasicProperties};
@@ -14,7 +17,7 @@ use crate::helpers::spawn_app;
 async fn handler_binds_queue_to_exchange_and_acknowledges_content_extracted_message_when_correct() {
     // Arrange
     let app = spawn_app().await;
-    let queue_name = queue_name(&app.rabbitmq_queue_name_prefix);
+    let queue_name = queue_name(&app.rabbitmq_queue_name_prefix, IngestionLaneDto::Bulk);
 
     // Checks that the service declared and bound queue to the exchange.
     // Test fails if not found after max retries.
@@ -34,12 +37,20 @@ async fn handler_binds_queue_to_exchange_and_acknowledges_content_extracted_mess
     );
 
     let extracted_content = ExtractedContentDto {
+        version: ExtractedContentDto::CURRENT_VERSION,
         id: Uuid::new_v4(),
         metadata: json!({}),
         content: Sentences(3..10).fake::<Vec<String>>().join(" "),
+        skip_embedding: false,
+        is_code: false,
+        source_meta_id: None,
+        fulltext_shard: 0,
+        provenance: None,
     };
 
-    let message = serde_json::to_string(&extracted_content).unwrap();
+    let message = MessageEnvelope::new(extracted_content)
+        .try_serializing()
+        .unwrap();
     info!("Extracted content message: {}", message);
 
     // Sends the job message to the worker binding key
@@ -82,7 +93,7 @@ async fn handler_binds_queue_to_exchange_and_acknowledges_content_extracted_mess
 async fn handler_negative_acknowledges_content_extracted_message_when_incorrect() {
     // Arrange
     let app = spawn_app().await;
-    let queue_name = queue_name(&app.rabbitmq_queue_name_prefix);
+    let queue_name = queue_name(&app.rabbitmq_queue_name_prefix, IngestionLaneDto::Bulk);
 
     // Checks that the service declared and bound queue to the exchange.
     // Test fails if not found after max retries.
@@ -36,10 +36,12 @@ async fn handler_binds_queue_to_exchange_and_acknowledges_search_fulltext_reques
     // The query content is parts of the fake content saved in our db.
     let content_in_db = Sentences(3..10).fake::<Vec<String>>().join(" ");
     let content_query = (&content_in_db[..3]).to_string(); // Just the 3 first letters
+    let user_id = Uuid::new_v4();
     app.save_content_to_meilisearch(&ContentEntity {
         id: Uuid::new_v4(),
-        metadata: JsonValue::Null,
+        metadata: json!({ "user_id": user_id }),
         content: content_in_db,
+        source_meta_id: None,
     })
     .await
     .unwrap();
@@ -48,6 +50,17 @@ async fn handler_binds_queue_to_exchange_and_acknowledges_search_fulltext_reques
         metadata: json!({}),
         query: content_query,
         limit: None,
+        user_id,
+        shard: 0,
+        language: None,
+        fields: Default::default(),
+        source_meta_ids: None,
+        chapter: None,
+        keyword: None,
+        entity: None,
+        page: None,
+        hits_per_page: None,
+        sort: Default::default(),
     };
     let search_request = serde_json::to_string(&search_request).unwrap();
     info!("Fulltext Search request message: {}", search_request);
@@ -85,6 +98,143 @@ async fn handler_binds_queue_to_exchange_and_acknowledges_search_fulltext_reques
     assert_eq!(nb_ack, 1);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn handler_only_returns_the_contents_of_the_requesting_user() {
+    // Arrange
+    let app = spawn_app().await;
+    let queue_name = queue_name(&app.rabbitmq_queue_name_prefix);
+
+    app.wait_until_queue_declared_and_bound_to_exchange(
+        &app.rabbitmq_content_exchange_name,
+        &queue_name,
+        ROUTING_KEY,
+        10,
+    )
+    .await
+    .unwrap();
+
+    // The same content is owned by 2 users
+    let content_in_db = Sentences(3..10).fake::<Vec<String>>().join(" ");
+    let content_query = content_in_db[..3].to_string();
+    let user_id = Uuid::new_v4();
+    let user_content_id = Uuid::new_v4();
+    for (content_id, owner_id) in [(user_content_id, user_id), (Uuid::new_v4(), Uuid::new_v4())] {
+        app.save_content_to_meilisearch(&ContentEntity {
+            id: content_id,
+            metadata: json!({ "user_id": owner_id }),
+            content: content_in_db.clone(),
+            source_meta_id: None,
+        })
+        .await
+        .unwrap();
+    }
+
+    // A content extracted before the contents were linked to their user
+    app.save_content_to_meilisearch(&ContentEntity {
+        id: Uuid::new_v4(),
+        metadata: JsonValue::Null,
+        content: content_in_db,
+        source_meta_id: None,
+    })
+    .await
+    .unwrap();
+
+    let search_request = FulltextSearchRequestDto {
+        metadata: json!({}),
+        query: content_query,
+        limit: None,
+        user_id,
+        shard: 0,
+        language: None,
+        fields: Default::default(),
+        source_meta_ids: None,
+        chapter: None,
+        keyword: None,
+        entity: None,
+        page: None,
+        hits_per_page: None,
+        sort: Default::default(),
+    };
+    let search_request = serde_json::to_string(&search_request).unwrap();
+
+    // Act
+    let response = app
+        .rabbitmq_message_repository
+        .rpc_call(ROUTING_KEY, search_request.as_bytes(), None)
+        .await
+        .unwrap();
+
+    // Assert
+    let response = FulltextSearchResponseDto::try_parsing(&response).unwrap();
+    let data = match response {
+        FulltextSearchResponseDto::Ok { data } => data,
+        response => panic!("Expected a successful search, got: {:?}", response),
+    };
+    let found_ids: Vec<Uuid> = data.results.iter().map(|result| result.id).collect();
+    assert_eq!(found_ids, vec![user_content_id]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn handler_suggests_the_spelling_of_a_query_from_the_terms_of_the_user() {
+    // Arrange
+    let app = spawn_app().await;
+    let queue_name = queue_name(&app.rabbitmq_queue_name_prefix);
+
+    app.wait_until_queue_declared_and_bound_to_exchange(
+        &app.rabbitmq_content_exchange_name,
+        &queue_name,
+        ROUTING_KEY,
+        10,
+    )
+    .await
+    .unwrap();
+
+    let user_id = Uuid::new_v4();
+    app.save_content_to_meilisearch(&ContentEntity {
+        id: Uuid::new_v4(),
+        metadata: json!({ "user_id": user_id }),
+        content: "The kubernetes cluster restarted".to_string(),
+        source_meta_id: None,
+    })
+    .await
+    .unwrap();
+    app.save_terms_to_meilisearch(user_id, &["kubernetes", "cluster", "restarted"])
+        .await;
+
+    let search_request = FulltextSearchRequestDto {
+        metadata: json!({}),
+        query: "kubernetse clustr".to_string(),
+        limit: None,
+        user_id,
+        shard: 0,
+        language: None,
+        fields: Default::default(),
+        source_meta_ids: None,
+        chapter: None,
+        keyword: None,
+        entity: None,
+        page: None,
+        hits_per_page: None,
+        sort: Default::default(),
+    };
+    let search_request = serde_json::to_string(&search_request).unwrap();
+
+    // Act
+    let response = app
+        .rabbitmq_message_repository
+        .rpc_call(ROUTING_KEY, search_request.as_bytes(), None)
+        .await
+        .unwrap();
+
+    // Assert
+    let response = FulltextSearchResponseDto::try_parsing(&response).unwrap();
+    let data = match response {
+        FulltextSearchResponseDto::Ok { data } => data,
+        response => panic!("Expected a successful search, got: {:?}", response),
+    };
+    assert_eq!(data.suggestions, vec!["kubernetes cluster".to_string()]);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn handler_returns_error_response_on_incorrect_search_fulltext_request_and_nacks() {
     // Arrange
@@ -7,7 +7,7 @@ use common::{
 };
 use fulltext_search_service::{
     configuration::get_configuration,
-    domain::entities::content::ContentEntity,
+    domain::entities::{content::ContentEntity, spelling::TermEntity},
     startup::{get_meilisearch_client, get_rabbitmq_connection, Application},
 };
 use lapin::{Channel, Connection as RabbitMQConnection};
@@ -219,6 +219,31 @@ impl TestApp {
 
         Ok(())
     }
+
+    /// Save the terms of the contents of a user to the terms index of the first shard for tests.
+    /// It waits for the tasks to be processed.
+    pub async fn save_terms_to_meilisearch(&self, user_id: Uuid, terms: &[&str]) {
+        let index = self
+            .meilisearch_client
+            .index(format!("{}_terms", self.meilisearch_content_index));
+        let terms: Vec<TermEntity> = terms
+            .iter()
+            .map(|term| TermEntity::new(user_id, term.to_string()))
+            .collect();
+
+        let task = index.set_filterable_attributes(["user_id"]).await.unwrap();
+        self.meilisearch_client
+            .wait_for_task(task, None, None)
+            .await
+            .unwrap();
+        let task = index.add_or_replace(&terms, Some("id")).await.unwrap();
+        let status = self
+            .meilisearch_client
+            .wait_for_task(task, None, None)
+            .await
+            .unwrap();
+        assert!(matches!(status, Task::Succeeded { .. }));
+    }
 }
 
 /// Launches the worker/server/RabbitMQ connection as a background task
@@ -229,7 +254,14 @@ pub async fn spawn_app() -> TestApp {
 
     // Randomizes configuration to ensure test isolation: random prefix for our queue names
     let configuration = {
-        let mut c = get_configuration().expect("Failed to read configuration.");
+        let mut c = get_configuration()
+            .await
+            .expect("Failed to read configuration.");
+
+        // Binds a random port for the health probes, for the tests to run in parallel
+        c.application.port = 0;
+        // No gateway answers with the normalization rules
+        c.normalization_rules.enabled = false;
 
         // Uses a different exchange and queue names for each test case
         c.rabbitmq.exchange_name_prefix = format!(
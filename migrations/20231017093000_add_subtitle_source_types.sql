@@ -0,0 +1,4 @@
+-- Adds the subtitle source types (SubRip and WebVTT) to the `source_type` enum type
+
+ALTER TYPE source_type ADD VALUE 'srt';
+ALTER TYPE source_type ADD VALUE 'vtt';
@@ -0,0 +1,3 @@
+-- Adds the source code type (single source files or repository archives) to the `source_type` enum type
+
+ALTER TYPE source_type ADD VALUE 'code';
@@ -0,0 +1,4 @@
+-- Adds the LaTeX and archive (LaTeX project or source code repository) source types to the `source_type` enum type
+
+ALTER TYPE source_type ADD VALUE 'latex';
+ALTER TYPE source_type ADD VALUE 'archive';
@@ -0,0 +1,14 @@
+-- Create the `extraction_progresses` table and `extraction_status` enum type
+-- Holds the latest known progress of the content extraction of each source
+
+CREATE TYPE extraction_status AS ENUM ('in_progress', 'completed', 'failed');
+
+CREATE TABLE extraction_progresses(
+   source_meta_id uuid NOT NULL REFERENCES source_metas (id),
+   PRIMARY KEY (source_meta_id),
+   status extraction_status NOT NULL,
+   chunk_index BIGINT NOT NULL,
+   total_estimated_chunks BIGINT NOT NULL,
+   bytes_processed BIGINT NOT NULL,
+   updated_at timestamptz NOT NULL
+);
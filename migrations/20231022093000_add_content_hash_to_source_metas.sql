@@ -0,0 +1,7 @@
+-- Add the SHA-256 hash of the content of a source file, to detect files already uploaded by a user
+
+-- Hex-encoded SHA-256 hash. NULL for the source files uploaded before its introduction
+ALTER TABLE source_metas ADD COLUMN content_hash CHAR(64);
+
+-- A user uploads the same file only once
+CREATE UNIQUE INDEX source_metas_user_id_content_hash_idx ON source_metas (user_id, content_hash);
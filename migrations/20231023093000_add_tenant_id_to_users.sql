@@ -0,0 +1,4 @@
+-- Add the tenant (business unit) of a user, isolated at the broker level
+
+-- NULL for the users without tenant, using the shared exchanges and queues
+ALTER TABLE users ADD COLUMN tenant_id TEXT;
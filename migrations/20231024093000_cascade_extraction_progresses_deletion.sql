@@ -0,0 +1,6 @@
+-- Delete the extraction progress of a source with its source meta
+
+ALTER TABLE extraction_progresses
+   DROP CONSTRAINT extraction_progresses_source_meta_id_fkey,
+   ADD CONSTRAINT extraction_progresses_source_meta_id_fkey
+      FOREIGN KEY (source_meta_id) REFERENCES source_metas (id) ON DELETE CASCADE;
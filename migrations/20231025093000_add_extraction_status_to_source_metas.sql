@@ -0,0 +1,11 @@
+-- Add the extraction status of a source, updated from the extraction progresses, to list the sources by status
+
+-- NULL while the extraction has not started yet
+ALTER TABLE source_metas ADD COLUMN extraction_status extraction_status;
+
+UPDATE source_metas SET extraction_status = extraction_progresses.status
+FROM extraction_progresses
+WHERE extraction_progresses.source_meta_id = source_metas.id;
+
+-- The sources of a user are listed from the most recently added, paginated with a cursor on (added_at, id)
+CREATE INDEX source_metas_user_id_added_at_id_idx ON source_metas (user_id, added_at DESC, id DESC);
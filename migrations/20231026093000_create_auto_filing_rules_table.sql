@@ -0,0 +1,26 @@
+-- Add collections to the sources, and the rules auto-filing the uploaded sources in a collection
+
+-- Collection in which the uploaded sources of a user are filed when no auto-filing rule matches
+ALTER TABLE users ADD COLUMN default_collection TEXT;
+
+CREATE TABLE auto_filing_rules(
+   id uuid PRIMARY KEY,
+   user_id uuid NOT NULL,
+   -- The rules of a user are evaluated by ascending position: the first matching rule files the source
+   position INTEGER NOT NULL,
+   -- Conditions, all the set conditions should match. Wildcard pattern on the file name, ex: `*.srt`
+   file_name_pattern TEXT,
+   -- MIME type of the file, ex: `text/vtt` or `text/*`
+   mime_type TEXT,
+   -- Tag given to the file when uploaded
+   tag TEXT,
+   collection TEXT NOT NULL,
+   created_at timestamptz NOT NULL,
+   updated_at timestamptz NOT NULL
+);
+
+CREATE INDEX auto_filing_rules_user_id_position_idx ON auto_filing_rules (user_id, position);
+
+ALTER TABLE source_metas ADD COLUMN collection TEXT;
+-- Rule that filed the source. NULL if filed in the default collection, or if the rule was deleted
+ALTER TABLE source_metas ADD COLUMN auto_filing_rule_id uuid REFERENCES auto_filing_rules (id) ON DELETE SET NULL;
@@ -0,0 +1,18 @@
+-- Create the `ingestion_jobs` table, tracking the extraction and embedding of each uploaded source
+
+CREATE TYPE job_status AS ENUM ('pending', 'extracting', 'embedded', 'failed');
+
+CREATE TABLE ingestion_jobs(
+   id uuid PRIMARY KEY,
+   -- A job is deleted with its source
+   source_meta_id uuid NOT NULL UNIQUE REFERENCES source_metas (id) ON DELETE CASCADE,
+   status job_status NOT NULL,
+   -- Number of contents extracted from the source, known once the extraction is completed
+   nb_contents BIGINT,
+   -- Number of contents that went through the embedding
+   nb_embedded_contents BIGINT NOT NULL DEFAULT 0,
+   -- Reason of the failure of the job
+   error TEXT,
+   created_at timestamptz NOT NULL,
+   updated_at timestamptz NOT NULL
+);
@@ -0,0 +1,16 @@
+-- Create the `refresh_tokens` table, storing the refresh tokens of the log-in sessions of the users
+
+CREATE TABLE refresh_tokens(
+   id uuid PRIMARY KEY,
+   user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+   -- Log-in session of the token: a refresh token is rotated at each use, the new token keeps its session
+   session_id uuid NOT NULL,
+   -- SHA-256 hash of the token, the token itself is only known by the client
+   token_hash TEXT NOT NULL UNIQUE,
+   expires_at timestamptz NOT NULL,
+   -- Set once the token is used to get a new one, or when its session is logged out
+   revoked_at timestamptz,
+   created_at timestamptz NOT NULL
+);
+
+CREATE INDEX refresh_tokens_session_id_idx ON refresh_tokens (session_id);
@@ -0,0 +1,24 @@
+-- Create the `tenant_provider_credentials` table, storing the model provider API keys brought by the tenants (BYOK)
+
+CREATE TYPE model_provider AS ENUM ('openai', 'mistral');
+CREATE TYPE provider_purpose AS ENUM ('embedding', 'completion');
+
+CREATE TABLE tenant_provider_credentials(
+   tenant_id TEXT NOT NULL,
+   -- A tenant has one key and one model for each purpose
+   purpose provider_purpose NOT NULL,
+   provider model_provider NOT NULL,
+   model TEXT NOT NULL,
+   -- Encrypted with the secrets key: `<key id>:<nonce>:<ciphertext>`
+   encrypted_api_key TEXT NOT NULL,
+   -- Last characters of the key, to identify it without exposing it
+   api_key_hint TEXT NOT NULL,
+   -- Usage of the key, reported by the workers calling the provider
+   nb_requests BIGINT NOT NULL DEFAULT 0,
+   nb_tokens BIGINT NOT NULL DEFAULT 0,
+   last_used_at timestamptz,
+   updated_by uuid REFERENCES users (id) ON DELETE SET NULL,
+   created_at timestamptz NOT NULL,
+   updated_at timestamptz NOT NULL,
+   PRIMARY KEY (tenant_id, purpose)
+);
@@ -0,0 +1,20 @@
+-- Create the `api_keys` table, storing the long-lived API keys used by services and scripts on behalf of a user
+
+CREATE TYPE api_key_scope AS ENUM ('upload', 'search');
+
+CREATE TABLE api_keys(
+   id uuid PRIMARY KEY,
+   -- Owner of the key: the uploads and searches made with the key are attributed to them
+   user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+   name TEXT NOT NULL,
+   -- SHA-256 hash of the key, the key itself is only returned once at its creation
+   key_hash TEXT NOT NULL UNIQUE,
+   -- First characters of the key, to identify it without exposing it
+   key_prefix TEXT NOT NULL,
+   -- Endpoints the key gives access to
+   scopes api_key_scope[] NOT NULL,
+   last_used_at timestamptz,
+   created_at timestamptz NOT NULL
+);
+
+CREATE INDEX api_keys_user_id_idx ON api_keys (user_id);
@@ -0,0 +1,22 @@
+-- Add the timing of the stages of the ingestion jobs, to track the time for a source to be searchable
+
+ALTER TABLE ingestion_jobs
+   -- When the gateway started handling the uploaded file, unknown for a reindexed source
+   ADD COLUMN upload_started_at timestamptz,
+   -- When the extraction job was queued, on upload or reindex
+   ADD COLUMN queued_at timestamptz,
+   ADD COLUMN extraction_started_at timestamptz,
+   ADD COLUMN extraction_completed_at timestamptz,
+   -- When all the contents of the source were saved in the full-text index
+   ADD COLUMN indexed_at timestamptz,
+   -- When all the contents of the source went through the embedding
+   ADD COLUMN embedded_at timestamptz,
+   -- Number of contents saved in the full-text index
+   ADD COLUMN nb_indexed_contents BIGINT NOT NULL DEFAULT 0;
+
+UPDATE ingestion_jobs SET queued_at = created_at;
+
+ALTER TABLE ingestion_jobs ALTER COLUMN queued_at SET NOT NULL;
+
+-- The SLO data is computed from the jobs queued during a time window
+CREATE INDEX ingestion_jobs_queued_at_idx ON ingestion_jobs (queued_at);
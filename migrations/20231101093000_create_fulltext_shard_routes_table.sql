@@ -0,0 +1,16 @@
+-- Create the `fulltext_shard_routes` table, routing each source to a shard of the full-text index of its tenant
+--
+-- The contents of a tenant are split into several Meilisearch indexes (shards) to stay below the practical
+-- size limits of an index. Sources uploaded before the sharding have no route: their contents are in the first shard.
+
+CREATE TABLE fulltext_shard_routes(
+   -- A route is deleted with its source
+   source_meta_id uuid PRIMARY KEY REFERENCES source_metas (id) ON DELETE CASCADE,
+   -- Tenant of the owner of the source, none for the shared deployment
+   tenant_id TEXT,
+   shard INTEGER NOT NULL,
+   created_at timestamptz NOT NULL
+);
+
+-- The shards of a tenant are listed to fan out the searches, and the last one is filled by the uploads
+CREATE INDEX fulltext_shard_routes_tenant_id_shard_idx ON fulltext_shard_routes (COALESCE(tenant_id, ''), shard);
@@ -0,0 +1,37 @@
+-- Create the `upload_sessions` and `upload_session_parts` tables, tracking the chunked uploads of large source files
+--
+-- The parts are uploaded to the object storage as the parts of a multipart upload, assembled once the session is completed.
+
+CREATE TABLE upload_sessions(
+   id uuid PRIMARY KEY,
+   user_id uuid NOT NULL,
+   -- File name received from the user
+   file_name TEXT NOT NULL,
+   -- MIME type declared by the user, matched by the auto-filing rules
+   content_type TEXT,
+   -- Tags given to the file, matched by the auto-filing rules
+   tags TEXT[] NOT NULL,
+   -- Name of the assembled file in the object store
+   object_store_name TEXT NOT NULL,
+   -- Id of the multipart upload in the object storage
+   s3_upload_id TEXT NOT NULL,
+   -- Source registered once the parts are assembled
+   source_meta_id uuid REFERENCES source_metas (id) ON DELETE SET NULL,
+   completed_at timestamptz,
+   created_at timestamptz NOT NULL
+);
+
+CREATE INDEX upload_sessions_user_id_idx ON upload_sessions (user_id);
+
+CREATE TABLE upload_session_parts(
+   -- A part is deleted with its session
+   upload_session_id uuid NOT NULL REFERENCES upload_sessions (id) ON DELETE CASCADE,
+   -- Starts at 1, in the order of the file
+   part_number INTEGER NOT NULL,
+   -- Returned by the object storage, to assemble the part
+   etag TEXT NOT NULL,
+   size_bytes BIGINT NOT NULL,
+   uploaded_at timestamptz NOT NULL,
+   -- A part uploaded again replaces the previous one
+   PRIMARY KEY (upload_session_id, part_number)
+);
@@ -0,0 +1,15 @@
+-- Add the stable codes of the reasons of the failed ingestion jobs, explained to the users
+
+CREATE TYPE ingestion_error_code AS ENUM (
+   'invalid_archive',
+   'drm_protected',
+   'invalid_encoding',
+   'invalid_format',
+   'missing_main_document',
+   'unsupported_language',
+   'source_unavailable',
+   'internal'
+);
+
+-- Stable code of the reason of a failed job, unknown for the jobs failed before the codes
+ALTER TABLE ingestion_jobs ADD COLUMN error_code ingestion_error_code;
@@ -0,0 +1,3 @@
+-- Adds the HTML (saved web page) source type to the `source_type` enum type
+
+ALTER TYPE source_type ADD VALUE 'html';
@@ -0,0 +1,6 @@
+-- Add the lane through which each source is ingested: the small sources go through a dedicated fast lane
+
+CREATE TYPE ingestion_lane AS ENUM ('bulk', 'fast');
+
+-- The jobs created before the lanes went through the only lane, now the bulk one
+ALTER TABLE ingestion_jobs ADD COLUMN lane ingestion_lane NOT NULL DEFAULT 'bulk';
@@ -0,0 +1,23 @@
+-- Create the `source_events` table, the append-only audit of what happened to each source
+--
+-- The events are kept when their source is deleted: they are not linked to the `source_metas` table.
+
+CREATE TYPE source_event_type AS ENUM ('uploaded', 'extracted', 'indexed', 'embedded', 'updated', 'failed', 'deleted');
+
+CREATE TABLE source_events(
+   id uuid PRIMARY KEY,
+   source_meta_id uuid NOT NULL,
+   -- Owner of the source, only them can list its events
+   user_id uuid NOT NULL,
+   event_type source_event_type NOT NULL,
+   -- Snapshot of the source or of its ingestion job when the event occurred
+   payload jsonb NOT NULL,
+   occurred_at timestamptz NOT NULL,
+   recorded_at timestamptz NOT NULL
+);
+
+CREATE INDEX source_events_source_meta_id_idx ON source_events (source_meta_id, occurred_at);
+
+-- Append-only: the events are never updated nor deleted
+CREATE RULE source_events_no_update AS ON UPDATE TO source_events DO INSTEAD NOTHING;
+CREATE RULE source_events_no_delete AS ON DELETE TO source_events DO INSTEAD NOTHING;
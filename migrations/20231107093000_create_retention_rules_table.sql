@@ -0,0 +1,16 @@
+-- Create the `retention_rules` table: the sources of a collection of a user are deleted once older than its retention
+
+CREATE TABLE retention_rules(
+   id uuid PRIMARY KEY,
+   user_id uuid NOT NULL,
+   collection TEXT NOT NULL,
+   -- The sources are deleted this number of days after they were added
+   retention_days INTEGER NOT NULL CHECK (retention_days > 0),
+   created_at timestamptz NOT NULL,
+   updated_at timestamptz NOT NULL,
+   -- A single retention per collection of a user
+   UNIQUE (user_id, collection)
+);
+
+-- The sources about to expire are warned once, with an event recorded in their stream
+ALTER TYPE source_event_type ADD VALUE 'expiring';
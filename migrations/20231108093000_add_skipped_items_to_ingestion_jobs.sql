@@ -0,0 +1,11 @@
+-- Record the items of the sources skipped by the extraction, for ex the unreadable chapters of an EPUB
+--
+-- A job whose source had skipped items completes with warnings instead of being embedded.
+
+ALTER TYPE job_status ADD VALUE 'completed_with_warnings';
+
+-- Skipped items with their errors, reported by the worker once the extraction is completed
+ALTER TABLE ingestion_jobs ADD COLUMN skipped_items jsonb NOT NULL DEFAULT '[]';
+
+-- The skipped items are recorded in the stream of the source
+ALTER TYPE source_event_type ADD VALUE 'warning';
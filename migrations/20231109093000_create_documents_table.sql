@@ -0,0 +1,15 @@
+-- Create the `documents` table: summaries of the sources, projected from their extracted contents,
+-- so the sources are listed without aggregating their contents
+
+CREATE TABLE documents(
+   source_meta_id uuid PRIMARY KEY REFERENCES source_metas (id) ON DELETE CASCADE,
+   -- First title and author found in the metadata of the contents
+   title TEXT,
+   author TEXT,
+   -- First words of the source
+   excerpt TEXT NOT NULL DEFAULT '',
+   nb_chunks BIGINT NOT NULL DEFAULT 0,
+   -- Occurrences of the most frequent words of the source, pruned to bound the size of the summary
+   keyword_counts jsonb NOT NULL DEFAULT '{}',
+   updated_at timestamptz NOT NULL
+);
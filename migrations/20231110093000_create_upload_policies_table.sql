@@ -0,0 +1,24 @@
+-- Create the `upload_policies` table: the validation of the files uploaded by the users of a tenant (organization)
+--
+-- The policies are versioned: a change of the policy of a tenant adds a new version, the latest one is applied.
+-- The previous versions are kept, as the sources record the version they were uploaded under.
+
+CREATE TABLE upload_policies(
+   id uuid PRIMARY KEY,
+   -- NULL for the users without tenant
+   tenant_id TEXT,
+   -- From 1, incremented for each change of the policy of the tenant
+   version INTEGER NOT NULL CHECK (version > 0),
+   -- MIME types (ex: `application/epub+zip` or `text/*`) of the accepted files, all the types if empty
+   allowed_mime_types TEXT[] NOT NULL,
+   -- Maximum size of the accepted files, not limited if NULL
+   max_size_bytes BIGINT CHECK (max_size_bytes > 0),
+   -- The files should be scanned for viruses before being accepted
+   scan_required BOOLEAN NOT NULL,
+   created_at timestamptz NOT NULL
+);
+
+CREATE UNIQUE INDEX upload_policies_tenant_id_version_idx ON upload_policies (COALESCE(tenant_id, ''), version);
+
+-- Policy the source was uploaded under. NULL if uploaded without policy
+ALTER TABLE source_metas ADD COLUMN upload_policy_id uuid REFERENCES upload_policies (id);
@@ -0,0 +1,18 @@
+-- Create the `normalization_rules` table: the dictionary of a tenant normalizing its extracted contents and its search queries
+
+CREATE TYPE normalization_rule_kind AS ENUM ('abbreviation', 'canonical_name', 'stop_pattern');
+
+CREATE TABLE normalization_rules(
+   id uuid PRIMARY KEY,
+   -- NULL for the users without tenant
+   tenant_id TEXT,
+   kind normalization_rule_kind NOT NULL,
+   -- Replaced term, matched as whole words ignoring the case, or regular expression of a stop pattern
+   pattern TEXT NOT NULL,
+   -- Replacing term, NULL for a stop pattern
+   replacement TEXT,
+   created_at timestamptz NOT NULL,
+   CHECK ((kind = 'stop_pattern') = (replacement IS NULL))
+);
+
+CREATE INDEX normalization_rules_tenant_id_idx ON normalization_rules (COALESCE(tenant_id, ''), created_at);
@@ -0,0 +1,4 @@
+-- Adds the Word (DOCX) and OpenDocument text (ODT) source types to the `source_type` enum type
+
+ALTER TYPE source_type ADD VALUE 'docx';
+ALTER TYPE source_type ADD VALUE 'odt';
@@ -0,0 +1,7 @@
+-- Adds the structured data (CSV and JSON Lines) source types to the `source_type` enum type,
+-- with the columns whose values are the content of their rows
+
+ALTER TYPE source_type ADD VALUE 'csv';
+ALTER TYPE source_type ADD VALUE 'jsonl';
+
+ALTER TABLE source_metas ADD COLUMN content_columns TEXT[] NOT NULL DEFAULT '{}';
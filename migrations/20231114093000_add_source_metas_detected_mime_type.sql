@@ -0,0 +1,4 @@
+-- Adds the MIME type detected from the first bytes of the uploaded files, regardless of their name.
+-- NULL for the sources uploaded before the detection, or uploaded in chunks
+
+ALTER TABLE source_metas ADD COLUMN detected_mime_type TEXT;
@@ -0,0 +1,13 @@
+-- Create the `user_storage_usage` table: the storage used by the source files of each user, checked against
+-- their quota on upload
+--
+-- The usage is updated on each upload and deletion of a source, from the size recorded on the source.
+-- The sources uploaded before have no size: they are not counted.
+
+ALTER TABLE source_metas ADD COLUMN size_bytes BIGINT CHECK (size_bytes >= 0);
+
+CREATE TABLE user_storage_usage(
+   user_id uuid PRIMARY KEY,
+   used_bytes BIGINT NOT NULL CHECK (used_bytes >= 0),
+   updated_at timestamptz NOT NULL
+);
@@ -0,0 +1,19 @@
+-- Create the `source_url_schedules` table: the sources added from a URL re-downloaded periodically by the gateway,
+-- and extracted again when their content changed
+--
+-- The headers sent to download the URL are stored encrypted, as a JSON object, with the secrets cipher of the gateway.
+-- A schedule is deleted with its source.
+
+CREATE TABLE source_url_schedules(
+   source_meta_id uuid PRIMARY KEY REFERENCES source_metas (id) ON DELETE CASCADE,
+   user_id uuid NOT NULL,
+   url TEXT NOT NULL,
+   encrypted_headers TEXT,
+   interval_h INTEGER NOT NULL CHECK (interval_h > 0),
+   next_crawl_at timestamptz NOT NULL,
+   last_crawled_at timestamptz,
+   last_changed_at timestamptz,
+   created_at timestamptz NOT NULL
+);
+
+CREATE INDEX source_url_schedules_next_crawl_at_idx ON source_url_schedules (next_crawl_at);
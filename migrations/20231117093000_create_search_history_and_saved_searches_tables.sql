@@ -0,0 +1,25 @@
+-- Create the `search_history` table: the searches run by each user, with the body of their request,
+-- and the `saved_searches` table: the searches saved by a user under a name, to run them again by id
+--
+-- Only the latest searches of a user are kept in their history (`search_history.max_entries_per_user`).
+-- The searches are not recorded when the history is disabled.
+
+CREATE TABLE search_history(
+   id uuid PRIMARY KEY,
+   user_id uuid NOT NULL,
+   query TEXT NOT NULL,
+   request jsonb NOT NULL,
+   searched_at timestamptz NOT NULL
+);
+
+CREATE INDEX search_history_user_id_searched_at_idx ON search_history (user_id, searched_at DESC, id DESC);
+
+CREATE TABLE saved_searches(
+   id uuid PRIMARY KEY,
+   user_id uuid NOT NULL,
+   name TEXT NOT NULL,
+   request jsonb NOT NULL,
+   created_at timestamptz NOT NULL
+);
+
+CREATE INDEX saved_searches_user_id_idx ON saved_searches (user_id);
@@ -0,0 +1,13 @@
+-- Create the `source_summaries` table: summaries of the sources and of their chapters,
+-- generated by a model once the extraction of the sources is completed
+
+CREATE TABLE source_summaries(
+   source_meta_id uuid PRIMARY KEY REFERENCES source_metas (id) ON DELETE CASCADE,
+   -- Summary of the whole source
+   summary TEXT NOT NULL,
+   -- Summaries of the chapters of the source, in the order of the source
+   chapters jsonb NOT NULL DEFAULT '[]',
+   -- Model which generated the summaries
+   model TEXT NOT NULL,
+   generated_at timestamptz NOT NULL
+);
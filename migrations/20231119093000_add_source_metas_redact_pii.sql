@@ -0,0 +1,4 @@
+-- Adds whether the personal data (emails, phone numbers, national ids) of the extracted contents of a source is redacted,
+-- as requested on its upload, for the source to be redacted again when it is reindexed
+
+ALTER TABLE source_metas ADD COLUMN redact_pii BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,22 @@
+-- Creates the tables of the two-factor authentication (TOTP) of the users
+
+CREATE TABLE user_two_factors(
+   user_id uuid PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+   -- TOTP secret, encrypted with the secrets keys of the gateway
+   encrypted_secret TEXT NOT NULL,
+   -- Set once a code of the secret is verified: until then, the log-ins do not require a code
+   enabled_at timestamptz,
+   -- Time step of the last code used to log in, for a code not to be used twice
+   last_used_step BIGINT,
+   created_at timestamptz NOT NULL
+);
+
+CREATE TABLE user_recovery_codes(
+   user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+   -- SHA-256 hash of the code, the code itself is only shown once to the user
+   code_hash TEXT NOT NULL,
+   -- Set once the code is used to log in: a code can only be used once
+   used_at timestamptz,
+   created_at timestamptz NOT NULL,
+   PRIMARY KEY (user_id, code_hash)
+);
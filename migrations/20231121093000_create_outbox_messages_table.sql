@@ -0,0 +1,19 @@
+-- Creates the table of the outbox: the messages written in the transactions of the gateway,
+-- and published on the broker once committed
+
+CREATE TABLE outbox_messages(
+   -- Id of the envelope of the message
+   id uuid PRIMARY KEY,
+   -- Tenant whose exchange the message is published on, NULL for the shared exchange
+   tenant_id TEXT,
+   routing_key TEXT NOT NULL,
+   payload TEXT NOT NULL,
+   created_at timestamptz NOT NULL,
+   -- Pushed back on each attempt: a message not sent by then is published again
+   next_attempt_at timestamptz NOT NULL,
+   nb_attempts INT NOT NULL DEFAULT 0,
+   last_error TEXT,
+   sent_at timestamptz
+);
+
+CREATE INDEX outbox_messages_pending_idx ON outbox_messages (next_attempt_at) WHERE sent_at IS NULL;
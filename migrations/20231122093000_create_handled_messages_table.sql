@@ -0,0 +1,13 @@
+-- Creates the table of the messages handled by the consumers of the workers,
+-- for a redelivered message to be acknowledged without being handled again
+
+CREATE TABLE handled_messages(
+   -- Queue of the consumer: the consumers of a same message each handle it once
+   consumer TEXT NOT NULL,
+   -- Id of the envelope of the message
+   message_id uuid NOT NULL,
+   completed_at timestamptz NOT NULL,
+   PRIMARY KEY (consumer, message_id)
+);
+
+CREATE INDEX handled_messages_completed_at_idx ON handled_messages (completed_at);
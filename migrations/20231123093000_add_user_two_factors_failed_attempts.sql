@@ -0,0 +1,7 @@
+-- Counts the failed two-factor authentication codes of the users, to lock out the guessing of their codes
+
+ALTER TABLE user_two_factors
+   -- Invalid codes since the last valid one, reset once the account is locked
+   ADD COLUMN failed_attempts INTEGER NOT NULL DEFAULT 0,
+   -- Until then, the log-ins of the user are rejected, even with a valid code
+   ADD COLUMN locked_until timestamptz;
@@ -0,0 +1,34 @@
+use common::telemetry::{get_tracing_subscriber, init_tracing_subscriber};
+use rest_gateway::{
+    configuration::get_configuration,
+    ops::{self, OpsCommand},
+};
+
+/// Operator CLI, run from the `rest_gateway` directory to read its configuration
+///
+/// For ex: `cargo run --bin ops -- queues`
+#[tokio::main]
+async fn main() {
+    // Logs on stderr, the reports of the commands are written on stdout
+    let tracing_subscriber = get_tracing_subscriber("ops".into(), "warn".into(), std::io::stderr);
+    init_tracing_subscriber(tracing_subscriber);
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match OpsCommand::try_parse(&args) {
+        Ok(command) => command,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(2);
+        }
+    };
+
+    // Panics if the configuration can't be read
+    let configuration = get_configuration()
+        .await
+        .expect("Failed to read configuration.");
+
+    if let Err(error) = ops::run(&configuration, command, &mut std::io::stdout()).await {
+        eprintln!("{:?}", error);
+        std::process::exit(1);
+    }
+}
@@ -1,3 +1,11 @@
+use common::core::{
+    configuration::{ensure_positive, ConfigurationError, ConfigurationLayers},
+    rabbitmq_message_repository::{
+        broker_uri, BrokerCredentials, PublisherConfirmsSettings, RpcSettings,
+    },
+    secrets::SecretsSettings,
+    tenancy::{tenant_name_prefix, TenantSettings},
+};
 use lapin::ConnectionProperties;
 use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
@@ -6,6 +14,9 @@ use sqlx::{
     postgres::{PgConnectOptions, PgSslMode},
     ConnectOptions,
 };
+use tokio::sync::watch;
+
+use crate::domain::entities::provider_credentials::ModelProvider;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Settings {
@@ -14,6 +25,31 @@ pub struct Settings {
     pub object_storage: ObjectStorageSettings,
     pub rabbitmq: RabbitMQSettings,
     pub jwt: JWTSettings,
+    pub search_quota: RequestQuotaSettings,
+    pub rate_limits: RateLimitsSettings,
+    /// Encryption of the secrets stored in the database
+    pub secrets: SecretsSettings,
+    pub provider_credentials: ProviderCredentialsSettings,
+    pub admin: AdminSettings,
+    pub two_factor: TwoFactorSettings,
+    pub fulltext_sharding: FulltextShardingSettings,
+    pub ingestion_lanes: IngestionLanesSettings,
+    pub uploads: UploadsSettings,
+    pub url_downloads: UrlDownloadsSettings,
+    pub imports: ImportsSettings,
+    pub source_downloads: SourceDownloadsSettings,
+    pub activity_stream: ActivityStreamSettings,
+    pub retention: RetentionSettings,
+    pub recrawl: RecrawlSettings,
+    pub outbox: OutboxSettings,
+    pub search_history: SearchHistorySettings,
+    pub answer_generation: AnswerGenerationSettings,
+    pub rerank: RerankSettings,
+    pub virus_scan: VirusScanSettings,
+    /// Backend authenticating the users, the access tokens issued by the gateway by default
+    #[serde(default)]
+    pub authentication: AuthenticationSettings,
+    pub ops: OpsSettings,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -26,10 +62,51 @@ pub struct ApplicationSettings {
 #[derive(Debug, Deserialize, Clone)]
 pub struct JWTSettings {
     pub secret: Secret<String>,
+    /// Lifetime of the access tokens: kept short, clients get new ones with their refresh token
     pub expire_in_s: u64,
+    /// Lifetime of the refresh tokens, ie the maximum duration of a log-in session without activity
+    pub refresh_token_expire_in_s: u64,
     pub cookie_max_age_s: u16,
 }
 
+/// Quota of requests of a set of endpoints, for each gateway instance
+#[derive(Debug, Deserialize, Clone)]
+pub struct RequestQuotaSettings {
+    /// Sustained rate of requests
+    pub requests_per_s: u32,
+    /// Requests accepted at once above the sustained rate
+    pub burst: u32,
+    pub max_concurrent_requests: usize,
+    /// Quota of the anonymous requests, without valid credentials, shed before the authenticated requests
+    pub anonymous: AnonymousRequestQuotaSettings,
+}
+
+/// Quota of the anonymous requests of a set of endpoints, taking a part of their concurrent requests
+#[derive(Debug, Deserialize, Clone)]
+pub struct AnonymousRequestQuotaSettings {
+    pub requests_per_s: u32,
+    pub burst: u32,
+    /// Lower than the concurrent requests of the quota, the others being kept for the authenticated requests
+    pub max_concurrent_requests: usize,
+}
+
+/// Rate limits of each client (user or API key) on the groups of endpoints, for each gateway instance
+#[derive(Debug, Deserialize, Clone)]
+pub struct RateLimitsSettings {
+    pub search: RateLimitSettings,
+    pub upload: RateLimitSettings,
+    /// Log-ins of each client address, not authenticated yet
+    pub login: RateLimitSettings,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub struct RateLimitSettings {
+    /// Sustained rate of requests of a client
+    pub requests_per_min: u32,
+    /// Requests of a client accepted at once above the sustained rate
+    pub burst: u32,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct DatabaseSettings {
     pub username: String,
@@ -40,6 +117,42 @@ pub struct DatabaseSettings {
     pub database_name: String,
     // Determines if we demand the connection to be encrypted or not
     pub require_ssl: bool,
+    /// If true, the pending migrations are run at startup, instead of with `sqlx migrate run`
+    #[serde(default)]
+    pub run_migrations: bool,
+    #[serde(default)]
+    pub pool: DatabasePoolSettings,
+}
+
+/// Connection pool of a gateway instance to the database
+#[derive(Debug, Deserialize, Clone)]
+pub struct DatabasePoolSettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_connections: u32,
+    /// Time to wait for a connection of the pool, before failing the request
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub acquire_timeout_ms: u64,
+    /// Time after which an idle connection is closed. 0 keeps the idle connections open.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub idle_timeout_s: u64,
+    /// Time after which a statement is aborted by Postgres. 0 disables the timeout.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub statement_timeout_ms: u64,
+    /// Interval between 2 probes of the pool, reported by the readiness endpoint and the metrics
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub health_check_interval_ms: u64,
+}
+
+impl Default for DatabasePoolSettings {
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            acquire_timeout_ms: 2000,
+            idle_timeout_s: 600,
+            statement_timeout_ms: 0,
+            health_check_interval_ms: 10_000,
+        }
+    }
 }
 
 impl DatabaseSettings {
@@ -60,6 +173,12 @@ impl DatabaseSettings {
 
     pub fn with_db(&self) -> PgConnectOptions {
         let mut options = self.without_db().database(&self.database_name);
+        if self.pool.statement_timeout_ms > 0 {
+            options = options.options([(
+                "statement_timeout",
+                self.pool.statement_timeout_ms.to_string(),
+            )]);
+        }
         // Lowers sqlx logs from INFO to TRACE level.
         options.log_statements(tracing::log::LevelFilter::Trace);
         options
@@ -84,22 +203,357 @@ impl ObjectStorageSettings {
     }
 }
 
+/// Model providers of the API keys brought by the tenants (BYOK)
+#[derive(Debug, Deserialize, Clone)]
+pub struct ProviderCredentialsSettings {
+    /// Base URL of the OpenAI API
+    pub openai_api_url: String,
+    /// Base URL of the Mistral AI API
+    pub mistral_api_url: String,
+    /// Timeout of the request checking a key when it is saved
+    pub validation_timeout_s: u64,
+}
+
+impl ProviderCredentialsSettings {
+    /// Base URL of the API of a provider
+    pub fn api_url(&self, provider: ModelProvider) -> &str {
+        match provider {
+            ModelProvider::OpenAi => &self.openai_api_url,
+            ModelProvider::Mistral => &self.mistral_api_url,
+        }
+    }
+}
+
+/// Lockout of the log-ins of a user after too many invalid two-factor authentication codes
+#[derive(Debug, Deserialize, Clone)]
+pub struct TwoFactorSettings {
+    /// Invalid codes (TOTP or recovery codes) tried in a row before the log-ins are locked out
+    pub max_failed_attempts: u32,
+    pub lockout_s: u64,
+}
+
+/// Endpoints of the operators: metrics and SLO data of the ingestion
+#[derive(Debug, Deserialize, Clone)]
+pub struct AdminSettings {
+    /// Bearer token of the admin endpoints, for ex for the Prometheus scraper
+    pub token: Secret<String>,
+    /// Objective of the time for an uploaded source to be searchable
+    pub time_to_searchable_target_s: u64,
+}
+
+/// Sharding of the full-text index of each tenant into several Meilisearch indexes
+#[derive(Debug, Deserialize, Clone)]
+pub struct FulltextShardingSettings {
+    /// Number of indexed contents over which the new sources of a tenant are routed to a new shard
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_contents_per_shard: u64,
+}
+
+/// Routing of the new sources between the bulk and the fast ingestion lanes
+#[derive(Debug, Deserialize, Clone)]
+pub struct IngestionLanesSettings {
+    /// Size up to which a source that is not a book goes through the fast lane. 0 disables the fast lane
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub fast_lane_max_bytes: u64,
+}
+
+/// Uploads of the source files, whole or in chunks
+#[derive(Debug, Deserialize, Clone)]
+pub struct UploadsSettings {
+    /// Maximum size of a part of a chunked upload
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_part_bytes: usize,
+    /// Maximum size of a source file: checked while a file of `/add_source_files` is streamed,
+    /// and on the completion of a chunked upload
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_file_bytes: usize,
+    /// Maximum size of the multipart body of `/add_source_files`, all its files included
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_form_bytes: usize,
+    /// Maximum storage used by the source files of a user. 0 for no quota
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub storage_quota_bytes: u64,
+}
+
+impl UploadsSettings {
+    /// Storage quota of each user, `None` if not limited
+    pub fn storage_quota(&self) -> Option<u64> {
+        (self.storage_quota_bytes > 0).then_some(self.storage_quota_bytes)
+    }
+}
+
+/// Sweeper deleting the sources expired with the retention of their collection
+#[derive(Debug, Deserialize, Clone)]
+pub struct RetentionSettings {
+    pub sweep_interval_ms: u64,
+    /// The sources expiring within this number of days are warned with an `expiring` event
+    pub warning_days: u32,
+    /// Maximum number of sources warned, and deleted, by a sweep
+    pub max_sources_per_sweep: u32,
+}
+
+/// Scheduler re-crawling the sources added from a URL with a re-crawl interval
+#[derive(Debug, Deserialize, Clone)]
+pub struct RecrawlSettings {
+    pub scan_interval_ms: u64,
+    /// Maximum number of sources re-crawled by a scan, by each gateway instance
+    pub max_sources_per_scan: u32,
+    /// Minimum re-crawl interval of a source, in hours
+    pub min_interval_h: u32,
+}
+
+/// Relay of the outbox, publishing the messages their writer could not publish after their transaction
+#[derive(Debug, Deserialize, Clone)]
+pub struct OutboxSettings {
+    pub relay_interval_ms: u64,
+    /// Time before a message claimed by a relay, and still not sent, is published again
+    pub retry_delay_ms: u64,
+    /// Maximum number of messages published by a relay, by each gateway instance
+    pub max_messages_per_relay: u32,
+    /// Time the sent messages are kept, for the investigations
+    pub sent_retention_h: u32,
+}
+
+/// History of the searches run by each user
+#[derive(Debug, Deserialize, Clone)]
+pub struct SearchHistorySettings {
+    /// If false, the searches are not recorded: the history of the users stays as it was
+    pub enabled: bool,
+    /// Number of the latest searches kept in the history of a user
+    pub max_entries_per_user: u32,
+}
+
+/// Generation of the answers of `/ask` by an LLM, from the chunks semantically close to the questions
+#[derive(Debug, Deserialize, Clone)]
+pub struct AnswerGenerationSettings {
+    /// If false, the questions are not answered: `/ask` responds with a 503
+    pub enabled: bool,
+    /// Base URL of an OpenAI compatible API, for ex `https://api.openai.com/v1` or a self-hosted vLLM server
+    pub api_url: String,
+    /// Not needed by the self-hosted servers without authentication
+    pub api_key: Option<Secret<String>>,
+    pub model: String,
+    /// Maximum duration of the generation of an answer, streamed or not
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timeout_s: u64,
+    /// Number of chunks retrieved to answer a question, when not given by the request
+    pub default_top_k: usize,
+    pub max_top_k: usize,
+    /// Interval of the keep-alive comments of a streamed answer, while the LLM does not generate any token
+    pub keep_alive_interval_s: u64,
+}
+
+/// Reranking of the search candidates by a cross-encoder, requested with `rerank: true`
+#[derive(Debug, Deserialize, Clone)]
+pub struct RerankSettings {
+    /// If false, the searches requesting a reranking respond with a 503
+    pub enabled: bool,
+    /// Base URL of a Cohere compatible rerank API, for ex `https://api.cohere.com/v2` or a self-hosted Infinity server
+    pub api_url: String,
+    /// Not needed by the self-hosted servers without authentication
+    pub api_key: Option<Secret<String>>,
+    pub model: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timeout_s: u64,
+    /// Number of the best fused candidates rescored by the cross-encoder
+    pub candidates: usize,
+}
+
+/// Downloads of the sources added from a URL
+#[derive(Debug, Deserialize, Clone)]
+pub struct UrlDownloadsSettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_bytes: u64,
+    /// Maximum duration of a download, redirections included
+    pub timeout_s: u64,
+    pub max_redirects: u32,
+    /// Allows the URLs of the private networks and of the host, for ex for the local development
+    pub allow_private_networks: bool,
+}
+
+/// Object storage of the document archives imported as sources by the admins
+#[derive(Debug, Deserialize, Clone)]
+pub struct ImportsSettings {
+    /// S3 endpoint, for ex `https://s3.eu-west-3.amazonaws.com`
+    pub endpoint: String,
+    pub region: String,
+    pub username: String,
+    pub password: Secret<String>,
+    /// Maximum number of objects listed by an import: a larger archive is imported prefix by prefix
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_objects: usize,
+}
+
+/// Downloads of the original source files by their users
+#[derive(Debug, Deserialize, Clone)]
+pub struct SourceDownloadsSettings {
+    /// Validity of the pre-signed URLs of the object storage, at most 7 days
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub presigned_url_expiry_s: u32,
+}
+
+/// Live stream of the activity of the users, sent as Server-Sent Events
+#[derive(Debug, Deserialize, Clone)]
+pub struct ActivityStreamSettings {
+    /// Interval of the keep-alive comments sent on an idle stream
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub keep_alive_interval_s: u64,
+}
+
+/// Scanning of the uploaded files for viruses, before they are stored
+#[derive(Debug, Deserialize, Clone)]
+pub struct VirusScanSettings {
+    /// If false, the files are not scanned, and the uploads whose policy requires a scan are rejected
+    pub enabled: bool,
+    pub clamav: ClamavSettings,
+}
+
+/// ClamAV daemon (clamd) listening on TCP
+#[derive(Debug, Deserialize, Clone)]
+pub struct ClamavSettings {
+    pub host: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    /// Maximum duration of the scan of a file, its streaming included
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timeout_ms: u64,
+}
+
+/// Backend authenticating the users on the routes requiring an authentication
+///
+/// Only the settings of the selected backend are needed.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct AuthenticationSettings {
+    #[serde(default)]
+    pub backend: AuthenticationBackend,
+    pub oidc: Option<OidcSettings>,
+    pub mtls: Option<MtlsSettings>,
+    pub static_token: Option<StaticTokenSettings>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthenticationBackend {
+    /// Access tokens issued by the gateway on log-in
+    #[default]
+    Jwt,
+    /// Access tokens of an OIDC provider, checked on its token introspection endpoint
+    Oidc,
+    /// Client certificates verified by a TLS-terminating reverse proxy
+    Mtls,
+    /// A single static token: for development only
+    StaticToken,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct OidcSettings {
+    /// Token introspection endpoint (RFC 7662) of the provider
+    pub introspection_url: String,
+    pub client_id: String,
+    pub client_secret: Secret<String>,
+    /// Issuer of the tokens, namespacing the ids of the users whose subject is not a uuid
+    pub issuer: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timeout_ms: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MtlsSettings {
+    /// Header in which the reverse proxy forwards the subject of the verified client certificate
+    pub subject_header: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct StaticTokenSettings {
+    pub token: Secret<String>,
+    /// User to which all the requests with the token are attributed
+    pub user_id: uuid::Uuid,
+}
+
+/// Settings of the operator CLI (`ops` binary), not used by the server
+#[derive(Debug, Deserialize, Clone)]
+pub struct OpsSettings {
+    pub rabbitmq_management: RabbitMQManagementSettings,
+    pub meilisearch: MeilisearchSettings,
+}
+
+/// RabbitMQ management HTTP API, on the host of the broker
+#[derive(Debug, Deserialize, Clone)]
+pub struct RabbitMQManagementSettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    pub username: String,
+    pub password: Secret<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MeilisearchSettings {
+    /// Only needed if Meilisearch is protected by a master key
+    #[serde(default)]
+    pub api_key: Option<Secret<String>>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    pub host: String,
+    pub contents_index: String,
+}
+
+impl MeilisearchSettings {
+    pub fn endpoint(&self) -> String {
+        format!("http://{}:{}", self.host, self.port)
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct RabbitMQSettings {
-    // pub username: String,
-    // pub password: Secret<String>,
+    #[serde(default)]
+    pub credentials: Option<BrokerCredentials>,
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub port: u16,
     pub host: String,
 
     /// To separate tests, development and production exchanges
     pub exchange_name_prefix: String,
+    pub queue_name_prefix: String,
     pub content_exchange: String,
+
+    /// Tenants isolated at the broker level: the messages of their users are routed to their own exchanges
+    #[serde(default)]
+    pub tenants: Vec<TenantSettings>,
+
+    /// Whether the published messages wait for the broker to acknowledge them
+    #[serde(default)]
+    pub publisher_confirms: PublisherConfirmsSettings,
+
+    /// Timeout of the RPC calls to the search services
+    #[serde(default)]
+    pub rpc: RpcSettings,
 }
 
 impl RabbitMQSettings {
     pub fn get_uri(&self) -> String {
-        format!("amqp://{}:{}", &self.host, &self.port)
+        broker_uri(&self.host, self.port, self.credentials.as_ref())
+    }
+
+    /// Base URL of the management HTTP API of the broker
+    pub fn get_management_url(&self, management: &RabbitMQManagementSettings) -> String {
+        format!("http://{}:{}/api", &self.host, management.port)
+    }
+
+    /// URI of the virtual host of a tenant, or of the shared virtual host
+    pub fn get_tenant_uri(&self, tenant: Option<&TenantSettings>) -> String {
+        match tenant {
+            Some(tenant) => tenant.amqp_uri(&self.get_uri()),
+            None => self.get_uri(),
+        }
+    }
+
+    /// Name of the content exchange of a tenant, or of the shared one
+    pub fn content_exchange_name(&self, tenant: Option<&TenantSettings>) -> String {
+        format!(
+            "{}_{}",
+            tenant_name_prefix(&self.exchange_name_prefix, tenant),
+            self.content_exchange
+        )
     }
 
     pub fn get_connection_properties(&self) -> ConnectionProperties {
@@ -111,7 +565,7 @@ impl RabbitMQSettings {
     }
 }
 
-/// Extracts app settings from configuration files and env variables
+/// Extracts app settings from configuration files, env variables and command line flags
 ///
 /// `base.yml` should contain shared settings for all environments.
 /// A specific env file should be created for each environment: `develop.yml`, `local.yml` and `production.yml`
@@ -120,7 +574,8 @@ impl RabbitMQSettings {
 ///
 /// Settings are also taken from environment variables: with a prefix of APP and '__' as separator
 /// For ex: `APP_APPLICATION__PORT=5001 would set `Settings.application.port`
-pub fn get_configuration() -> Result<Settings, config::ConfigError> {
+/// And finally from the `--set` flags of the command line. For ex: `--set application.port=5001`
+pub async fn get_configuration() -> Result<Settings, ConfigurationError> {
     let base_path = std::env::current_dir().expect("Failed to determine the current directory");
     let configuration_directory = base_path.join("configuration");
 
@@ -129,23 +584,126 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
     let environment: Environment = std::env::var("APP_ENVIRONMENT")
         .unwrap_or_else(|_| "develop".into())
         .try_into()
-        .expect("Failed to parse APP_ENVIRONMENT.");
-    let environment_filename = format!("{}.yml", environment.as_str());
-
-    let settings = config::Config::builder()
-        .add_source(config::File::from(configuration_directory.join("base.yml")))
-        .add_source(config::File::from(
-            configuration_directory.join(environment_filename),
-        ))
-        // Adds in settings from environment variables (with a prefix of APP and '__' as separator)
-        .add_source(
-            config::Environment::with_prefix("APP")
-                .prefix_separator("_")
-                .separator("__"),
-        )
-        .build()?;
+        .map_err(ConfigurationError::InvalidEnvironment)?;
+
+    let settings: Settings =
+        ConfigurationLayers::new(configuration_directory, environment.as_str())
+            .with_cli_overrides(std::env::args().skip(1))?
+            .load()
+            .await?;
+    settings.validate()?;
+
+    Ok(settings)
+}
+
+impl Settings {
+    /// Checks the settings that can not be checked by their deserialization
+    pub fn validate(&self) -> Result<(), ConfigurationError> {
+        ensure_positive(
+            "database.pool.max_connections",
+            self.database.pool.max_connections,
+        )?;
+        ensure_positive(
+            "database.pool.health_check_interval_ms",
+            self.database.pool.health_check_interval_ms,
+        )?;
+        ensure_positive(
+            "search_quota.max_concurrent_requests",
+            self.search_quota.max_concurrent_requests,
+        )?;
+        if self.search_quota.anonymous.max_concurrent_requests
+            >= self.search_quota.max_concurrent_requests
+        {
+            return Err(ConfigurationError::invalid_setting(
+                "search_quota.anonymous.max_concurrent_requests",
+                "should be lower than search_quota.max_concurrent_requests",
+            ));
+        }
+        self.admin.validate()?;
+        ensure_positive(
+            "two_factor.max_failed_attempts",
+            self.two_factor.max_failed_attempts,
+        )?;
+        ensure_positive("two_factor.lockout_s", self.two_factor.lockout_s)?;
+        self.rate_limits.validate()
+    }
+}
+
+impl AdminSettings {
+    fn validate(&self) -> Result<(), ConfigurationError> {
+        let token = self.token.expose_secret().trim();
+        if token.is_empty() {
+            return Err(ConfigurationError::invalid_setting(
+                "admin.token",
+                "should not be empty",
+            ));
+        }
+        if token == "admin" {
+            return Err(ConfigurationError::invalid_setting(
+                "admin.token",
+                "should not be the former default token `admin`",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl RateLimitsSettings {
+    fn validate(&self) -> Result<(), ConfigurationError> {
+        for (group, rate_limit) in [
+            ("search", &self.search),
+            ("upload", &self.upload),
+            ("login", &self.login),
+        ] {
+            ensure_positive(
+                &format!("rate_limits.{}.requests_per_min", group),
+                rate_limit.requests_per_min,
+            )?;
+            ensure_positive(&format!("rate_limits.{}.burst", group), rate_limit.burst)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Tunables applied without restarting the gateway, when its configuration is reloaded
+pub struct ReloadableSettings {
+    search_rate_limit: watch::Sender<RateLimitSettings>,
+    upload_rate_limit: watch::Sender<RateLimitSettings>,
+    login_rate_limit: watch::Sender<RateLimitSettings>,
+}
+
+impl ReloadableSettings {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            search_rate_limit: watch::channel(settings.rate_limits.search.clone()).0,
+            upload_rate_limit: watch::channel(settings.rate_limits.upload.clone()).0,
+            login_rate_limit: watch::channel(settings.rate_limits.login.clone()).0,
+        }
+    }
+
+    pub fn search_rate_limit(&self) -> watch::Receiver<RateLimitSettings> {
+        self.search_rate_limit.subscribe()
+    }
+
+    pub fn upload_rate_limit(&self) -> watch::Receiver<RateLimitSettings> {
+        self.upload_rate_limit.subscribe()
+    }
+
+    pub fn login_rate_limit(&self) -> watch::Receiver<RateLimitSettings> {
+        self.login_rate_limit.subscribe()
+    }
 
-    settings.try_deserialize::<Settings>()
+    /// Applies the tunables of reloaded settings. The other settings are only applied on restart.
+    pub fn update(&self, settings: &Settings) {
+        self.search_rate_limit
+            .send_replace(settings.rate_limits.search.clone());
+        self.upload_rate_limit
+            .send_replace(settings.rate_limits.upload.clone());
+        self.login_rate_limit
+            .send_replace(settings.rate_limits.login.clone());
+    }
 }
 
 /// The possible runtime environment for our application.
@@ -180,3 +738,34 @@ impl TryFrom<String> for Environment {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layers(environment: &str) -> ConfigurationLayers {
+        let directory = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("configuration");
+        ConfigurationLayers::new(directory, environment)
+    }
+
+    #[tokio::test]
+    async fn production_settings_require_an_admin_token() {
+        let result = layers("production").load::<Settings>().await;
+
+        assert!(matches!(result, Err(ConfigurationError::ReadError(_))));
+    }
+
+    #[tokio::test]
+    async fn admin_token_should_not_be_empty_or_the_former_default() {
+        let mut settings: Settings = layers("develop").load().await.unwrap();
+        settings.validate().unwrap();
+
+        for token in ["", "  ", "admin"] {
+            settings.admin.token = Secret::new(token.to_string());
+            assert!(matches!(
+                settings.validate(),
+                Err(ConfigurationError::InvalidSetting { key, .. }) if key == "admin.token"
+            ));
+        }
+    }
+}
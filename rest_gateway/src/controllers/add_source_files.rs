@@ -1,37 +1,157 @@
+use crate::configuration::{FulltextShardingSettings, Settings, UploadsSettings};
+use crate::controllers::add_source_url::SourceIntake;
+use crate::domain::entities::auto_filing_rule::{file_in_collection, FilingFile};
+use crate::domain::entities::fulltext_shard::shard_for_new_source;
+use crate::domain::entities::in_flight_upload::IN_FLIGHT_UPLOAD_WAIT;
+use crate::domain::entities::ingestion_job::{IngestionJob, IngestionLane, IngestionStage};
+use crate::domain::entities::outbox_message::OutboxMessage;
+use crate::domain::entities::sniffed_content::SniffedContent;
+use crate::domain::entities::source_event::SourceEvent;
 use crate::domain::entities::source_meta::{SourceMeta, SourceType};
+use crate::domain::entities::storage_usage::StorageUsage;
+use crate::metrics::IngestionMetrics;
 use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
-use crate::repositories::source_file_s3_repository::S3Repository;
+use crate::repositories::fulltext_shard_postgres_repository::FulltextShardPostgresRepository;
+use crate::repositories::ingestion_job_postgres_repository::IngestionJobPostgresRepository;
+use crate::repositories::outbox_message_postgres_repository::OutboxMessagePostgresRepository;
+use crate::repositories::scan_port::{ScanPort, ScanVerdict};
+use crate::repositories::source_event_postgres_repository::SourceEventPostgresRepository;
 use crate::repositories::source_meta_postgres_repository::SourceMetaPostgresRepository;
-use actix_multipart::form::{tempfile::TempFile, MultipartForm};
+use crate::repositories::upload_policy_postgres_repository::UploadPolicyPostgresRepository;
+use crate::repositories::user_storage_usage_postgres_repository::UserStorageUsagePostgresRepository;
+use actix_multipart::form::{
+    tempfile::TempFile, text::Text, FieldReader, Limits, MultipartForm, MultipartFormConfig,
+};
+use actix_multipart::{Field, MultipartError};
+use actix_web::error::PayloadError;
 use actix_web::http::StatusCode;
-use actix_web::{web, HttpResponse, ResponseError};
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError};
 use anyhow::Context;
-use common::constants::routing_keys::EXTRACT_CONTENT_TEXT_ROUTING_KEY;
+use chrono::{DateTime, Utc};
+use common::core::drm::{is_epub_drm_protected, EPUB_ENCRYPTION_PATH, EPUB_RIGHTS_PATH};
 use common::core::rabbitmq_message_repository::RabbitMQMessageRepository;
-use common::dtos::extract_content_job::ExtractContentJobDto;
+use common::core::tenancy::TenantMessageRepositories;
+use common::dtos::{
+    extract_content_job::{ChunkSplittingDto, ColumnMappingDto, ExtractContentJobDto},
+    templates::message_envelope::MessageEnvelope,
+};
 use common::helper::error_chain_fmt;
+use futures::future::LocalBoxFuture;
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use std::io::{Read, Seek};
+use std::ops::{Deref, DerefMut};
 use std::path::Path;
 use std::str::FromStr;
-use tracing::{error, info};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
 
-#[derive(Debug, MultipartForm)]
+/// Documentation of the DRM-protected sources, linked from their rejection
+pub const DRM_PROTECTED_DOC_URL: &str =
+    "https://github.com/alexandremgo/content_ingestion_service#drm-protected-sources";
+
+#[derive(Debug, MultipartForm, ToSchema)]
 pub struct UploadForm {
+    /// Uploaded files, whose type is given by their extension
     #[multipart(rename = "file")]
-    files: Vec<TempFile>,
+    #[schema(rename = "file", value_type = Vec<String>, format = Binary)]
+    files: Vec<LimitedTempFile>,
+    /// Tags given to all the uploaded files, matched by the auto-filing rules
+    #[multipart(rename = "tag")]
+    #[schema(rename = "tag", value_type = Vec<String>)]
+    tags: Vec<Text<String>>,
+    /// Columns whose values are the searchable content of the rows of the uploaded CSV and JSONL files,
+    /// the other columns being kept as filterable fields. All the columns if none is given
+    #[multipart(rename = "content_column")]
+    #[schema(rename = "content_column", value_type = Vec<String>)]
+    content_columns: Vec<Text<String>>,
+    /// If true, the emails, phone numbers and national ids of the extracted contents of the uploaded files are redacted
+    /// before they are indexed
+    #[schema(value_type = Option<bool>)]
+    redact_pii: Option<Text<bool>>,
+}
+
+/// Uploaded file whose size is limited while it is streamed, to `max_file_bytes` of the uploads settings
+#[derive(Debug)]
+pub struct LimitedTempFile(TempFile);
+
+impl Deref for LimitedTempFile {
+    type Target = TempFile;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for LimitedTempFile {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<'t> FieldReader<'t> for LimitedTempFile {
+    type Future = LocalBoxFuture<'t, Result<Self, MultipartError>>;
+
+    fn read_field(req: &'t HttpRequest, field: Field, limits: &'t mut Limits) -> Self::Future {
+        Box::pin(async move {
+            let max_file_bytes = req
+                .app_data::<web::Data<UploadsSettings>>()
+                .map(|uploads_settings| uploads_settings.max_file_bytes);
+
+            // Any limit of all the files of the field still applies
+            let field_limit = limits.field_limit_remaining;
+            limits.field_limit_remaining = match (field_limit, max_file_bytes) {
+                (Some(field_limit), Some(max_file_bytes)) => Some(field_limit.min(max_file_bytes)),
+                (field_limit, max_file_bytes) => field_limit.or(max_file_bytes),
+            };
+
+            let temp_file = TempFile::read_field(req, field, &mut *limits).await?;
+            limits.field_limit_remaining =
+                field_limit.map(|field_limit| field_limit.saturating_sub(temp_file.size));
+
+            Ok(LimitedTempFile(temp_file))
+        })
+    }
+}
+
+/// Configuration of the multipart body of `/add_source_files`, whose overflow is answered with a 413
+pub fn upload_form_config(uploads_settings: &UploadsSettings) -> MultipartFormConfig {
+    let max_file_bytes = uploads_settings.max_file_bytes;
+    let max_form_bytes = uploads_settings.max_form_bytes;
+
+    MultipartFormConfig::default()
+        .total_limit(max_form_bytes)
+        .error_handler(move |error, _request| match error {
+            MultipartError::Payload(PayloadError::Overflow) => AddSourceFilesError::TooLarge {
+                max_file_bytes,
+                max_form_bytes,
+            }
+            .into(),
+            error => error.into(),
+        })
 }
 
 #[derive(thiserror::Error)]
 pub enum AddSourceFilesError {
     #[error("No source files were uploaded")]
     NoSourceFiles,
+    #[error("The uploaded files are too large: at most {max_file_bytes} bytes per file and {max_form_bytes} bytes per request")]
+    TooLarge {
+        max_file_bytes: usize,
+        max_form_bytes: usize,
+    },
+    #[error("The {upload_bytes} uploaded bytes exceed the storage quota of the user: {used_bytes} of {quota_bytes} bytes are used")]
+    StorageQuotaExceeded {
+        upload_bytes: u64,
+        used_bytes: u64,
+        quota_bytes: u64,
+    },
     #[error("{0}")]
     RepositoryAccessError(String),
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
-    #[error("Error while serializing message data: {0}")]
-    JsonError(#[from] serde_json::Error),
 }
 
 impl std::fmt::Debug for AddSourceFilesError {
@@ -44,55 +164,183 @@ impl ResponseError for AddSourceFilesError {
     fn status_code(&self) -> StatusCode {
         match self {
             AddSourceFilesError::UnexpectedError(_)
-            | AddSourceFilesError::RepositoryAccessError(_)
-            | AddSourceFilesError::JsonError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            | AddSourceFilesError::RepositoryAccessError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             AddSourceFilesError::NoSourceFiles => StatusCode::BAD_REQUEST,
+            AddSourceFilesError::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            AddSourceFilesError::StorageQuotaExceeded { .. } => StatusCode::FORBIDDEN,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum Status {
     Success,
+    /// The same file was already uploaded by the user: it is not extracted again
+    Duplicate,
+    /// The file is protected by a DRM: its encrypted content can not be extracted
+    #[serde(rename = "drm_protected")]
+    DrmProtected,
+    /// The file is rejected by the upload policy of the tenant of the user, by the antivirus,
+    /// or for exceeding the storage quota of the user
+    Rejected,
+    /// The content of the file, detected from its first bytes, does not match the type given by its extension
+    #[serde(rename = "content_mismatch")]
+    ContentMismatch,
     Error,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct AddSourceFileStatus {
     pub file_name: Option<String>,
     pub status: Status,
     pub message: Option<String>,
+    /// Collection in which the source was filed
+    #[serde(default)]
+    pub collection: Option<String>,
+    /// Ingestion job of the source, to follow its extraction and embedding
+    #[serde(default)]
+    pub job_id: Option<Uuid>,
+    /// Source of the file, the already uploaded one for a duplicate
+    #[serde(default)]
+    pub source_id: Option<Uuid>,
 }
 
-#[derive(Serialize, Deserialize)]
+impl AddSourceFileStatus {
+    pub(crate) fn drm_protected(file_name: String) -> Self {
+        Self {
+            file_name: Some(file_name),
+            status: Status::DrmProtected,
+            message: Some(format!(
+                "The file is protected by a DRM, see {}",
+                DRM_PROTECTED_DOC_URL
+            )),
+            collection: None,
+            job_id: None,
+            source_id: None,
+        }
+    }
+
+    pub(crate) fn content_mismatch(
+        file_name: String,
+        source_type: &SourceType,
+        sniffed_content: SniffedContent,
+    ) -> Self {
+        Self {
+            file_name: Some(file_name),
+            status: Status::ContentMismatch,
+            message: Some(format!(
+                "The content of the file is detected as {}, which is not a valid {:?} source",
+                sniffed_content.mime_type(),
+                source_type
+            )),
+            collection: None,
+            job_id: None,
+            source_id: None,
+        }
+    }
+
+    pub(crate) fn storage_quota_exceeded(file_name: String) -> Self {
+        Self {
+            file_name: Some(file_name),
+            status: Status::Rejected,
+            message: Some("The file exceeds the storage quota of the user".to_string()),
+            collection: None,
+            job_id: None,
+            source_id: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct AddSourceFilesResponse {
     pub file_status: Vec<AddSourceFileStatus>,
 }
 
 /// Add source files to the object storage for a user
+///
+/// Each source is filed in the collection of the first matching auto-filing rule of the user,
+/// or in their default collection.
+#[utoipa::path(
+    post,
+    path = "/add_source_files",
+    tag = "sources",
+    request_body(content = UploadForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Status of each uploaded file", body = AddSourceFilesResponse),
+        (status = 400, description = "No source files were uploaded"),
+        (status = 403, description = "The uploaded files exceed the storage quota of the user"),
+        (status = 413, description = "The uploaded files are too large"),
+    ),
+    security(("access_token" = []), ("api_key" = ["upload"]))
+)]
 #[tracing::instrument(
     name = "Add source files",
     skip(
         form,
         pool,
-        s3_repository,
-        source_meta_repository,
-        message_rabbitmq_repository
+        intake,
+        upload_policy_repository,
+        scanner,
+        message_repositories
     ),
     err
 )]
 pub async fn add_source_files(
     MultipartForm(mut form): MultipartForm<UploadForm>,
     pool: web::Data<PgPool>,
-    s3_repository: web::Data<S3Repository>,
-    source_meta_repository: web::Data<SourceMetaPostgresRepository>,
-    message_rabbitmq_repository: web::Data<RabbitMQMessageRepository>,
+    intake: web::Data<SourceIntake>,
+    upload_policy_repository: web::Data<UploadPolicyPostgresRepository>,
+    scanner: web::Data<dyn ScanPort>,
+    message_repositories: web::Data<TenantMessageRepositories>,
     user_id: web::ReqData<UserIdFromToken>,
 ) -> Result<HttpResponse, AddSourceFilesError> {
     let user_id = user_id.into_inner().0;
     info!("Request for user_id: {}", user_id);
 
+    let SourceIntake {
+        s3_repository,
+        auto_filing_rule_repository,
+        user_repository,
+        in_flight_uploads,
+        source_registration,
+        fast_lane_max_bytes,
+    } = intake.get_ref();
+    let source_meta_repository = &source_registration.source_meta_repository;
+
+    // The extraction jobs are published on the exchange of the tenant of the user
+    let tenant_id = user_repository
+        .get_user_tenant_id(pool.get_ref(), user_id)
+        .await
+        .context("Could not get the tenant of the user")?;
+    let message_rabbitmq_repository = message_repositories
+        .route(tenant_id.as_deref())
+        .context("Could not route the messages of the user")?;
+    let upload_policy = upload_policy_repository
+        .get_tenant_policy(pool.get_ref(), tenant_id.as_deref())
+        .await
+        .context("Could not get the upload policy of the tenant of the user")?;
+
+    let auto_filing_rules = auto_filing_rule_repository
+        .list_user_rules(pool.get_ref(), user_id)
+        .await
+        .context("Could not list the auto-filing rules of the user")?;
+    let default_collection = user_repository
+        .get_user_default_collection(pool.get_ref(), user_id)
+        .await
+        .context("Could not get the default collection of the user")?;
+    let tags: Vec<String> = form.tags.iter().map(|tag| tag.0.clone()).collect();
+    let content_columns: Vec<String> = form
+        .content_columns
+        .iter()
+        .map(|column| column.0.trim().to_string())
+        .filter(|column| !column.is_empty())
+        .collect();
+    let redact_pii = form
+        .redact_pii
+        .as_ref()
+        .is_some_and(|redact_pii| redact_pii.0);
+
     let mut response = AddSourceFilesResponse {
         file_status: Vec::new(),
     };
@@ -101,7 +349,27 @@ pub async fn add_source_files(
         return Err(AddSourceFilesError::NoSourceFiles);
     }
 
+    // Nothing is stored if all the files can not fit in the storage quota of the user.
+    // Each file is then counted in the quota when it is registered.
+    let upload_bytes = form.files.iter().map(|file| file.size as u64).sum();
+    let storage_usage = source_registration
+        .storage_usage(pool.get_ref(), user_id)
+        .await?;
+    if let (false, Some(quota_bytes)) = (
+        storage_usage.can_store(upload_bytes),
+        storage_usage.quota_bytes,
+    ) {
+        return Err(AddSourceFilesError::StorageQuotaExceeded {
+            upload_bytes,
+            used_bytes: storage_usage.used_bytes,
+            quota_bytes,
+        });
+    }
+
     for (idx, temp_file) in form.files.iter_mut().enumerate() {
+        // Times the upload stage of the job, once the multipart body is received
+        let upload_started_at = Utc::now();
+
         // 1. Parsing step
 
         // File name coming from the HTTP Content-Disposition header:
@@ -116,6 +384,9 @@ pub async fn add_source_files(
                     file_name: None,
                     status: Status::Error,
                     message: Some("No file name".to_string()),
+                    collection: None,
+                    job_id: None,
+                    source_id: None,
                 });
                 continue;
             }
@@ -133,6 +404,9 @@ pub async fn add_source_files(
                         file_name: Some(file_name),
                         status: Status::Error,
                         message: Some("No unicode representation for the extension".to_string()),
+                        collection: None,
+                        job_id: None,
+                        source_id: None,
                     });
                     continue;
                 }
@@ -144,6 +418,9 @@ pub async fn add_source_files(
                     file_name: Some(file_name),
                     status: Status::Error,
                     message: Some("Could not extract extension".to_string()),
+                    collection: None,
+                    job_id: None,
+                    source_id: None,
                 });
                 continue;
             }
@@ -159,10 +436,102 @@ pub async fn add_source_files(
                     file_name: Some(file_name),
                     status: Status::Error,
                     message: Some("Invalid source type for {}".to_string()),
+                    collection: None,
+                    job_id: None,
+                    source_id: None,
+                });
+                continue;
+            }
+        };
+
+        let mime_type = temp_file
+            .content_type
+            .as_ref()
+            .map(|mime_type| mime_type.essence_str().to_string());
+
+        if let Some(upload_policy) = &upload_policy {
+            if let Err(violation) = upload_policy.check(
+                mime_type.as_deref(),
+                bytes_size as u64,
+                scanner.is_enabled(),
+            ) {
+                info!(
+                    "{}: {} is rejected by version {} of the upload policy: {}",
+                    idx, file_name, upload_policy.version, violation
+                );
+
+                response.file_status.push(AddSourceFileStatus {
+                    file_name: Some(file_name),
+                    status: Status::Rejected,
+                    message: Some(format!(
+                        "{}, see version {} of the upload policy",
+                        violation, upload_policy.version
+                    )),
+                    collection: None,
+                    job_id: None,
+                    source_id: None,
                 });
                 continue;
             }
+        }
+
+        // The extension and the MIME type are given by the client: the content is checked from its magic bytes
+        let sniffed_content = SniffedContent::sniff_file(temp_file.file.as_file_mut())
+            .context(format!("Could not detect the content of {}", file_name))?;
+        if !sniffed_content.matches(&source_type) {
+            info!(
+                "{}: {} is detected as {}, not matching its type {:?}",
+                idx,
+                file_name,
+                sniffed_content.mime_type(),
+                source_type
+            );
+
+            response
+                .file_status
+                .push(AddSourceFileStatus::content_mismatch(
+                    file_name,
+                    &source_type,
+                    sniffed_content,
+                ));
+            continue;
+        }
+
+        // Rejected before being stored, rather than extracting garbage from its encrypted content
+        let is_drm_protected = is_drm_protected(temp_file.file.as_file_mut(), &source_type)
+            .context(format!("Could not check if {} is DRM-protected", file_name))?;
+        if is_drm_protected {
+            info!("{}: {} is DRM-protected", idx, file_name);
+
+            response
+                .file_status
+                .push(AddSourceFileStatus::drm_protected(file_name));
+            continue;
+        }
+
+        // Scanned for viruses before being stored. A file that could not be scanned is not accepted
+        let scan_rejection = match scanner.scan(temp_file.file.as_file_mut()).await {
+            Ok(ScanVerdict::Clean) => None,
+            Ok(ScanVerdict::Infected(signature)) => {
+                info!("{}: {} is infected by {}", idx, file_name, signature);
+                Some(format!("The file is infected by {}", signature))
+            }
+            Err(error) => {
+                error!(?error, "{}: {} could not be scanned", idx, file_name);
+                Some("The file could not be scanned for viruses".to_string())
+            }
         };
+        if let Some(message) = scan_rejection {
+            response.file_status.push(AddSourceFileStatus {
+                file_name: Some(file_name),
+                status: Status::Rejected,
+                message: Some(message),
+                collection: None,
+                job_id: None,
+                source_id: None,
+            });
+            continue;
+        }
 
         info!(
             "Saving file {}, of size {} and of type {:?}",
@@ -170,33 +539,126 @@ pub async fn add_source_files(
         );
 
         // 2. Storing step
+        let (object_name, object_path_name, content_hash) = s3_repository
+            .save_file(&user_id.to_string(), temp_file.file.as_file_mut())
+            .await
+            .context(format!(
+                "The file {} could not be uploaded to object storage",
+                file_name
+            ))?;
+
+        // A concurrent upload of the same file is registered first, and then found as a duplicate.
+        // The claim is held until the source is committed.
+        let _in_flight_upload = in_flight_uploads
+            .claim(user_id, &content_hash, IN_FLIGHT_UPLOAD_WAIT)
+            .await;
+
         let mut transaction = pool
             .begin()
             .await
             .context("Failed to acquire a Postgres connection from the pool")?;
 
-        let (object_name, object_path_name) = s3_repository
-            .save_file(&user_id.to_string(), temp_file.file.as_file_mut())
+        // Re-uploading the same file should not duplicate its extracted contents
+        let duplicated_source_meta_id = source_meta_repository
+            .find_user_source_meta_id_by_content_hash(&mut transaction, user_id, &content_hash)
             .await
             .context(format!(
-                "The file {} could not be uploaded to object storage",
+                "Could not check if the file {} was already uploaded",
                 file_name
             ))?;
 
+        if let Some(duplicated_source_meta_id) = duplicated_source_meta_id {
+            info!(
+                "{}: {} was already uploaded as source {}",
+                idx, file_name, duplicated_source_meta_id
+            );
+
+            s3_repository
+                .remove_file(&object_path_name)
+                .await
+                .context(format!(
+                    "The duplicated object {} could not be removed from the object storage",
+                    object_path_name
+                ))?;
+
+            response.file_status.push(AddSourceFileStatus {
+                file_name: Some(file_name),
+                status: Status::Duplicate,
+                message: Some(format!(
+                    "Already uploaded as source {}",
+                    duplicated_source_meta_id
+                )),
+                collection: None,
+                job_id: None,
+                source_id: Some(duplicated_source_meta_id),
+            });
+            continue;
+        }
+
+        let filing = file_in_collection(
+            &auto_filing_rules,
+            default_collection.as_deref(),
+            &FilingFile {
+                file_name: &file_name,
+                mime_type: mime_type.as_deref(),
+                tags: &tags,
+            },
+        );
+
         let source_meta = SourceMeta::builder()
             .user_id(user_id.to_owned())
             .initial_name(file_name.clone())
-            .source_type(SourceType::Epub)
+            .source_type(source_type.clone())
             .object_store_name(object_name.clone())
+            .content_hash(Some(content_hash))
+            .collection(filing.as_ref().map(|filing| filing.collection.clone()))
+            .auto_filing_rule_id(filing.as_ref().and_then(|filing| filing.rule_id))
+            .upload_policy_id(upload_policy.as_ref().map(|upload_policy| upload_policy.id))
+            .detected_mime_type(Some(sniffed_content.mime_type().to_string()))
+            .size_bytes(Some(bytes_size as i64))
+            .redact_pii(redact_pii)
+            .content_columns(if source_type.is_structured() {
+                content_columns.clone()
+            } else {
+                vec![]
+            })
             .build();
 
-        source_meta_repository
-            .add_source_meta(&mut transaction, &source_meta)
-            .await
-            .context(format!(
-                "Could not save the file information of {}",
-                file_name
-            ))?;
+        // Files uploaded concurrently by the same user can exceed the quota checked for the whole request
+        if !source_registration
+            .reserve_storage(&mut transaction, &source_meta)
+            .await?
+        {
+            info!(
+                "{}: {} exceeds the storage quota of the user",
+                idx, file_name
+            );
+
+            s3_repository
+                .remove_file(&object_path_name)
+                .await
+                .context(format!(
+                    "The object {} exceeding the storage quota could not be removed from the object storage",
+                    object_path_name
+                ))?;
+
+            response
+                .file_status
+                .push(AddSourceFileStatus::storage_quota_exceeded(file_name));
+            continue;
+        }
+
+        let lane = IngestionLane::for_source(&source_type, bytes_size as u64, *fast_lane_max_bytes);
+        let registered_source = source_registration
+            .register(
+                &mut transaction,
+                tenant_id.as_deref(),
+                &source_meta,
+                object_path_name,
+                lane,
+                upload_started_at,
+            )
+            .await?;
 
         transaction.commit().await.context(format!(
             "Failed to commit SQL transaction to store the file {}",
@@ -213,29 +675,282 @@ pub async fn add_source_files(
         //         object_name
         //     ))?;
 
+        source_registration
+            .send_to_extraction(
+                pool.get_ref(),
+                message_rabbitmq_repository,
+                &registered_source,
+            )
+            .await;
+
+        response.file_status.push(AddSourceFileStatus {
+            file_name: Some(file_name),
+            status: Status::Success,
+            message: None,
+            collection: source_meta.collection,
+            job_id: Some(registered_source.ingestion_job.id),
+            source_id: Some(source_meta.id),
+        });
+    }
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Checks if a source file is protected by a DRM, from the markers of its format
+///
+/// Only the EPUBs are checked. A file that is not a valid archive is not protected:
+/// its extraction fails with a dedicated error. The file is rewound to be stored.
+pub(crate) fn is_drm_protected(
+    file: &mut std::fs::File,
+    source_type: &SourceType,
+) -> Result<bool, std::io::Error> {
+    if *source_type != SourceType::Epub {
+        return Ok(false);
+    }
+
+    file.rewind()?;
+    let is_drm_protected = match zip::ZipArchive::new(file.try_clone()?) {
+        Ok(mut archive) => {
+            let has_rights = archive.by_name(EPUB_RIGHTS_PATH).is_ok();
+            let mut encryption = String::new();
+            let has_encryption = match archive.by_name(EPUB_ENCRYPTION_PATH) {
+                Ok(mut encryption_file) => encryption_file.read_to_string(&mut encryption).is_ok(),
+                Err(_) => false,
+            };
+
+            is_epub_drm_protected(has_rights, has_encryption.then_some(encryption.as_str()))
+        }
+        Err(_) => false,
+    };
+    file.rewind()?;
+
+    Ok(is_drm_protected)
+}
+
+/// Source saved with its ingestion job, waiting to be sent to the extraction
+pub(crate) struct RegisteredSource {
+    pub ingestion_job: IngestionJob,
+    /// Extraction job of the source, written in the outbox
+    pub extraction_message: OutboxMessage,
+}
+
+/// Registers the sources stored in the object storage and sends them to the extraction
+///
+/// Shared by the uploads of whole files and the chunked uploads.
+pub struct SourceRegistration {
+    pub source_meta_repository: SourceMetaPostgresRepository,
+    pub ingestion_job_repository: IngestionJobPostgresRepository,
+    pub source_event_repository: SourceEventPostgresRepository,
+    pub fulltext_shard_repository: FulltextShardPostgresRepository,
+    pub fulltext_sharding: FulltextShardingSettings,
+    pub ingestion_metrics: Arc<IngestionMetrics>,
+    pub user_storage_usage_repository: UserStorageUsagePostgresRepository,
+    pub outbox_message_repository: OutboxMessagePostgresRepository,
+    /// `None` if the storage of the users is not limited
+    pub storage_quota_bytes: Option<u64>,
+}
+
+impl SourceRegistration {
+    pub fn new(ingestion_metrics: Arc<IngestionMetrics>, settings: &Settings) -> Self {
+        Self {
+            source_meta_repository: SourceMetaPostgresRepository::new(),
+            ingestion_job_repository: IngestionJobPostgresRepository::new(),
+            source_event_repository: SourceEventPostgresRepository::new(),
+            fulltext_shard_repository: FulltextShardPostgresRepository::new(),
+            fulltext_sharding: settings.fulltext_sharding.clone(),
+            ingestion_metrics,
+            user_storage_usage_repository: UserStorageUsagePostgresRepository::new(),
+            outbox_message_repository: OutboxMessagePostgresRepository::new(),
+            storage_quota_bytes: settings.uploads.storage_quota(),
+        }
+    }
+
+    /// Storage used by a user, against their quota
+    pub(crate) async fn storage_usage(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+    ) -> Result<StorageUsage, anyhow::Error> {
+        let used_bytes = self
+            .user_storage_usage_repository
+            .get_used_bytes(db_executor, user_id)
+            .await
+            .context("Could not get the storage used by the user")?;
+
+        Ok(StorageUsage {
+            used_bytes,
+            quota_bytes: self.storage_quota_bytes,
+        })
+    }
+
+    /// Counts the file of a source in the storage used by its user, if it fits in their quota
+    ///
+    /// # Returns
+    /// False if the file exceeds the quota: the source should not be registered
+    pub(crate) async fn reserve_storage(
+        &self,
+        transaction: &mut Transaction<'_, Postgres>,
+        source_meta: &SourceMeta,
+    ) -> Result<bool, anyhow::Error> {
+        self.user_storage_usage_repository
+            .try_add_usage(
+                &mut *transaction,
+                source_meta.user_id,
+                source_meta.size_bytes.unwrap_or(0) as u64,
+                self.storage_quota_bytes,
+            )
+            .await
+            .context(format!(
+                "Could not count {} in the storage used by the user",
+                source_meta.initial_name
+            ))
+    }
+
+    /// Saves a source with its ingestion job, routes it to a full-text shard of its tenant,
+    /// and writes its extraction job in the outbox
+    ///
+    /// The transaction should be committed before sending the source to the extraction.
+    pub(crate) async fn register(
+        &self,
+        transaction: &mut Transaction<'_, Postgres>,
+        tenant_id: Option<&str>,
+        source_meta: &SourceMeta,
+        object_path_name: String,
+        lane: IngestionLane,
+        upload_started_at: DateTime<Utc>,
+    ) -> Result<RegisteredSource, anyhow::Error> {
+        let file_name = &source_meta.initial_name;
+
+        self.source_meta_repository
+            .add_source_meta(&mut *transaction, source_meta)
+            .await
+            .context(format!(
+                "Could not save the file information of {}",
+                file_name
+            ))?;
+
+        // The contents are saved in the last full-text shard of the tenant, or in a new one once it is full
+        let last_fulltext_shard = self
+            .fulltext_shard_repository
+            .get_last_tenant_shard(&mut *transaction, tenant_id)
+            .await
+            .context("Could not get the last full-text shard of the tenant")?;
+        let fulltext_shard = shard_for_new_source(
+            last_fulltext_shard.as_ref(),
+            self.fulltext_sharding.max_contents_per_shard,
+        );
+        self.fulltext_shard_repository
+            .add_route(&mut *transaction, source_meta.id, tenant_id, fulltext_shard)
+            .await
+            .context(format!(
+                "Could not route the file {} to a full-text shard",
+                file_name
+            ))?;
+
+        let ingestion_job = IngestionJob::new(source_meta.id)
+            .with_lane(lane)
+            .with_upload_started_at(upload_started_at);
+        self.ingestion_job_repository
+            .add_job(&mut *transaction, &ingestion_job)
+            .await
+            .context(format!("Could not save the ingestion job of {}", file_name))?;
+        self.source_event_repository
+            .add_event(
+                &mut *transaction,
+                &SourceEvent::uploaded(source_meta, &ingestion_job),
+            )
+            .await
+            .context(format!("Could not record the upload of {}", file_name))?;
+
         let job = ExtractContentJobDto {
             source_meta_id: source_meta.id,
-            source_type: source_type.into(),
+            source_type: source_meta.source_type.clone().into(),
             object_store_path_name: object_path_name,
-            source_initial_name: file_name.clone(),
+            source_initial_name: source_meta.initial_name.clone(),
+            user_id: Some(source_meta.user_id),
+            tenant_id: tenant_id.map(str::to_string),
+            fulltext_shard,
+            content_hash: source_meta.content_hash.clone(),
+            lane: ingestion_job.lane.into(),
+            chunk_splitting: ChunkSplittingDto::default(),
+            column_mapping: ColumnMappingDto {
+                content_columns: source_meta.content_columns.clone(),
+            },
+            incremental: false,
+            source_added_at: Some(source_meta.added_at),
+            redact_pii: source_meta.redact_pii,
         };
+        let routing_key = job.lane.extract_content_routing_key();
+        let envelope = MessageEnvelope::new(job);
+        let json_job = envelope
+            .try_serializing()
+            .context("Could not serialize the content extraction job request")?;
 
-        let json_job = serde_json::to_string(&job)?;
-
-        message_rabbitmq_repository
-            .publish(EXTRACT_CONTENT_TEXT_ROUTING_KEY, json_job.as_bytes())
+        // Published once the transaction is committed, at least once
+        let extraction_message =
+            OutboxMessage::new(envelope.message_id, tenant_id, routing_key, json_job);
+        self.outbox_message_repository
+            .add_message(&mut *transaction, &extraction_message)
             .await
             .context(format!(
-                "Could not send content extraction job request for the file {}",
+                "Could not save the content extraction job request of {}",
                 file_name
             ))?;
 
-        response.file_status.push(AddSourceFileStatus {
-            file_name: Some(file_name),
-            status: Status::Success,
-            message: None,
-        });
+        Ok(RegisteredSource {
+            ingestion_job,
+            extraction_message,
+        })
     }
 
-    Ok(HttpResponse::Ok().json(response))
+    /// Publishes the extraction job of a registered source on the lane of its ingestion job,
+    /// ending the upload stage of the job
+    ///
+    /// The transaction registering the source should be committed. A job that could not be published
+    /// is published later by the outbox relay: the source is registered anyway.
+    pub(crate) async fn send_to_extraction(
+        &self,
+        db_pool: &PgPool,
+        message_rabbitmq_repository: &RabbitMQMessageRepository,
+        registered_source: &RegisteredSource,
+    ) {
+        let message = &registered_source.extraction_message;
+
+        match message_rabbitmq_repository
+            .publish(&message.routing_key, message.payload.as_bytes())
+            .await
+        {
+            Ok(()) => {
+                if let Err(error) = self
+                    .outbox_message_repository
+                    .set_sent(db_pool, message.id, Utc::now())
+                    .await
+                {
+                    // Published again by the relay
+                    warn!(?error, message_id = %message.id, "Could not set the extraction job as sent");
+                }
+            }
+            Err(error) => {
+                warn!(
+                    ?error,
+                    message_id = %message.id,
+                    "Could not send the extraction job, left to the outbox relay"
+                );
+            }
+        }
+
+        // The next stages are observed when the workers report them
+        if let Some((stage, duration)) = registered_source
+            .ingestion_job
+            .stage_durations()
+            .into_iter()
+            .find(|(stage, _)| *stage == IngestionStage::Upload)
+        {
+            self.ingestion_metrics.observe_stage(
+                registered_source.ingestion_job.lane,
+                stage,
+                duration,
+            );
+        }
+    }
 }
@@ -0,0 +1,530 @@
+use crate::configuration::{RecrawlSettings, Settings};
+use crate::controllers::add_source_files::{
+    is_drm_protected, AddSourceFileStatus, SourceRegistration, Status,
+};
+use crate::domain::entities::auto_filing_rule::{file_in_collection, FilingFile};
+use crate::domain::entities::in_flight_upload::{InFlightUploads, IN_FLIGHT_UPLOAD_WAIT};
+use crate::domain::entities::ingestion_job::IngestionLane;
+use crate::domain::entities::sniffed_content::SniffedContent;
+use crate::domain::entities::source_meta::{SourceMeta, SourceType};
+use crate::domain::entities::source_url_schedule::SourceUrlSchedule;
+use crate::metrics::IngestionMetrics;
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::auto_filing_rule_postgres_repository::AutoFilingRulePostgresRepository;
+use crate::repositories::source_file_s3_repository::S3Repository;
+use crate::repositories::source_url_repository::{SourceUrlRepository, SourceUrlRepositoryError};
+use crate::repositories::source_url_schedule_postgres_repository::SourceUrlSchedulePostgresRepository;
+use crate::repositories::user_postgres_repository::UserPostgresRepository;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use common::core::secrets::SecretsCipher;
+use common::core::{
+    rabbitmq_message_repository::RabbitMQMessageRepository, tenancy::TenantMessageRepositories,
+};
+use common::helper::error_chain_fmt;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::io::Seek;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(thiserror::Error)]
+pub enum AddSourceUrlError {
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("The source could not be downloaded")]
+    DownloadError(#[from] SourceUrlRepositoryError),
+    #[error("Invalid source type for {0}")]
+    InvalidSourceType(String),
+    #[error("The {0} downloaded bytes exceed the storage quota of the user")]
+    StorageQuotaExceeded(u64),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for AddSourceUrlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for AddSourceUrlError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AddSourceUrlError::InvalidRequest(_) | AddSourceUrlError::InvalidSourceType(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            AddSourceUrlError::DownloadError(error) => match error {
+                SourceUrlRepositoryError::InvalidUrl(_)
+                | SourceUrlRepositoryError::ForbiddenHost(_)
+                | SourceUrlRepositoryError::TooManyRedirects
+                | SourceUrlRepositoryError::TooLarge(_)
+                | SourceUrlRepositoryError::UnexpectedStatus(_) => StatusCode::BAD_REQUEST,
+                SourceUrlRepositoryError::Unreachable(_)
+                | SourceUrlRepositoryError::RequestError(_) => StatusCode::BAD_GATEWAY,
+                SourceUrlRepositoryError::TimedOut => StatusCode::GATEWAY_TIMEOUT,
+                SourceUrlRepositoryError::IOError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            AddSourceUrlError::StorageQuotaExceeded(_) => StatusCode::FORBIDDEN,
+            AddSourceUrlError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct AddSourceUrlBodyData {
+    /// HTTP(S) URL of the source file
+    pub url: String,
+    /// Headers sent to download the file, for ex to authenticate
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Name of the file. By default, the name given by the server, or the last segment of the URL
+    #[serde(default)]
+    pub file_name: Option<String>,
+    /// Tags given to the file, matched by the auto-filing rules
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Re-downloads the URL every this number of hours, extracting the source again when its content changed.
+    /// Not re-downloaded by default
+    #[serde(default)]
+    pub recrawl_interval_h: Option<u32>,
+}
+
+/// Add a source file downloaded from a URL by the gateway, for ex a public-domain EPUB
+///
+/// The file is downloaded with a size and a time limit, and then stored and filed like the sources
+/// added with `/add_source_files`.
+/// With a re-crawl interval, the URL is downloaded again periodically with the same headers: the source is
+/// replaced and extracted again only when its content changed.
+#[utoipa::path(
+    post,
+    path = "/add_source_url",
+    tag = "sources",
+    request_body = AddSourceUrlBodyData,
+    responses(
+        (status = 200, description = "Status of the downloaded file", body = AddSourceFileStatus),
+        (status = 400, description = "Invalid URL, headers or re-crawl interval, or the server answered with an error"),
+        (status = 403, description = "The downloaded file exceeds the storage quota of the user"),
+        (status = 502, description = "The file could not be downloaded"),
+        (status = 504, description = "The download timed out"),
+    ),
+    security(("access_token" = []), ("api_key" = ["upload"]))
+)]
+#[tracing::instrument(
+    name = "Add source URL",
+    skip(
+        body,
+        pool,
+        source_url_repository,
+        recrawl_scheduling,
+        intake,
+        message_repositories
+    ),
+    fields(url = %body.url),
+    err
+)]
+pub async fn add_source_url(
+    body: web::Json<AddSourceUrlBodyData>,
+    pool: web::Data<PgPool>,
+    source_url_repository: web::Data<SourceUrlRepository>,
+    recrawl_scheduling: web::Data<RecrawlScheduling>,
+    intake: web::Data<SourceIntake>,
+    message_repositories: web::Data<TenantMessageRepositories>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, AddSourceUrlError> {
+    let user_id = user_id.into_inner().0;
+    let AddSourceUrlBodyData {
+        url,
+        headers,
+        file_name,
+        tags,
+        recrawl_interval_h,
+    } = body.into_inner();
+    let RecrawlScheduling {
+        source_url_schedule_repository,
+        secrets_cipher,
+        settings: recrawl_settings,
+    } = recrawl_scheduling.get_ref();
+
+    if let Some(recrawl_interval_h) = recrawl_interval_h {
+        if !(recrawl_settings.min_interval_h..=i32::MAX as u32).contains(&recrawl_interval_h) {
+            return Err(AddSourceUrlError::InvalidRequest(format!(
+                "the re-crawl interval should be of at least {} hours",
+                recrawl_settings.min_interval_h
+            )));
+        }
+    }
+    // Sent again on each re-crawl
+    let encrypted_headers = match recrawl_interval_h {
+        Some(_) if !headers.is_empty() => Some(
+            secrets_cipher
+                .encrypt(&Secret::new(
+                    serde_json::to_string(&headers).context("Could not serialize the headers")?,
+                ))
+                .context("Could not encrypt the headers")?,
+        ),
+        _ => None,
+    };
+    let headers = parse_headers(headers)?;
+
+    // The extraction jobs are published on the exchange of the tenant of the user
+    let tenant_id = intake
+        .user_repository
+        .get_user_tenant_id(&**pool, user_id)
+        .await
+        .context("Could not get the tenant of the user")?;
+    let message_rabbitmq_repository = message_repositories
+        .route(tenant_id.as_deref())
+        .context("Could not route the messages of the user")?;
+
+    // Times the upload stage of the job from the start of the download
+    let upload_started_at = Utc::now();
+
+    let mut file = tempfile::tempfile().context("Could not create a temporary file")?;
+    let downloaded_source = source_url_repository
+        .download(&url, headers, &mut file)
+        .await?;
+
+    let file_name = file_name
+        .or(downloaded_source.file_name)
+        .or_else(|| {
+            downloaded_source
+                .url
+                .path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .filter(|segment| !segment.is_empty())
+                .map(str::to_string)
+        })
+        .ok_or_else(|| AddSourceUrlError::InvalidSourceType(downloaded_source.url.to_string()))?;
+
+    let status = intake
+        .add_source(
+            &pool,
+            user_id,
+            tenant_id.as_deref(),
+            message_rabbitmq_repository,
+            &mut file,
+            FetchedSource {
+                file_name,
+                origin: downloaded_source.url.to_string(),
+                content_type: downloaded_source.content_type,
+                size_bytes: downloaded_source.size_bytes,
+                tags,
+                upload_started_at,
+            },
+        )
+        .await?;
+
+    if let (Some(recrawl_interval_h), Status::Success, Some(source_id)) =
+        (recrawl_interval_h, &status.status, status.source_id)
+    {
+        // The URL given by the user is crawled again, its redirections can change
+        source_url_schedule_repository
+            .add_schedule(
+                &**pool,
+                &SourceUrlSchedule::new(
+                    source_id,
+                    user_id,
+                    url,
+                    encrypted_headers,
+                    recrawl_interval_h as i32,
+                ),
+            )
+            .await
+            .context("Could not save the re-crawl schedule of the source")?;
+    }
+
+    Ok(HttpResponse::Ok().json(status))
+}
+
+/// Headers sent to download a URL, checked to be valid HTTP headers
+pub(crate) fn parse_headers(
+    headers: HashMap<String, String>,
+) -> Result<HeaderMap, AddSourceUrlError> {
+    headers
+        .into_iter()
+        .map(|(name, value)| {
+            let name = HeaderName::from_str(&name).map_err(|_| {
+                AddSourceUrlError::InvalidRequest(format!("invalid header name {}", name))
+            })?;
+            let value = HeaderValue::from_str(&value).map_err(|_| {
+                AddSourceUrlError::InvalidRequest(format!("invalid value for header {}", name))
+            })?;
+            Ok((name, value))
+        })
+        .collect()
+}
+
+/// Source file downloaded by the gateway, not stored yet
+pub(crate) struct FetchedSource {
+    pub file_name: String,
+    /// Where the file was downloaded from, for ex its URL
+    pub origin: String,
+    /// MIME type given by the origin, matched by the auto-filing rules
+    pub content_type: Option<String>,
+    pub size_bytes: u64,
+    /// Tags given to the file, matched by the auto-filing rules
+    pub tags: Vec<String>,
+    /// Start of the download, timing the upload stage of the job
+    pub upload_started_at: DateTime<Utc>,
+}
+
+/// Schedules the re-crawls of the sources added from a URL
+pub struct RecrawlScheduling {
+    pub source_url_schedule_repository: SourceUrlSchedulePostgresRepository,
+    /// Encrypts the headers sent again on each re-crawl
+    pub secrets_cipher: SecretsCipher,
+    pub settings: RecrawlSettings,
+}
+
+impl RecrawlScheduling {
+    pub fn new(secrets_cipher: SecretsCipher, settings: RecrawlSettings) -> Self {
+        Self {
+            source_url_schedule_repository: SourceUrlSchedulePostgresRepository::new(),
+            secrets_cipher,
+            settings,
+        }
+    }
+}
+
+/// Stores the source files, and sends them to the extraction
+///
+/// Shared by the uploaded files, the sources added from a URL and the objects imported from an external bucket.
+pub struct SourceIntake {
+    pub s3_repository: S3Repository,
+    pub auto_filing_rule_repository: AutoFilingRulePostgresRepository,
+    pub user_repository: UserPostgresRepository,
+    /// Coalesces the concurrent additions of the same file
+    pub in_flight_uploads: InFlightUploads,
+    pub source_registration: SourceRegistration,
+    pub fast_lane_max_bytes: u64,
+}
+
+impl SourceIntake {
+    pub fn new(
+        s3_repository: S3Repository,
+        ingestion_metrics: Arc<IngestionMetrics>,
+        settings: &Settings,
+    ) -> Self {
+        Self {
+            s3_repository,
+            auto_filing_rule_repository: AutoFilingRulePostgresRepository::new(),
+            user_repository: UserPostgresRepository::new(),
+            in_flight_uploads: InFlightUploads::new(),
+            source_registration: SourceRegistration::new(ingestion_metrics, settings),
+            fast_lane_max_bytes: settings.ingestion_lanes.fast_lane_max_bytes,
+        }
+    }
+
+    /// Checks a downloaded file, stores it and registers it as a source of a user, unless it was already added
+    ///
+    /// # Returns
+    /// The status of the file: a content not matching its type, or a DRM-protected file, is not stored
+    pub(crate) async fn add_source(
+        &self,
+        pool: &PgPool,
+        user_id: Uuid,
+        tenant_id: Option<&str>,
+        message_rabbitmq_repository: &RabbitMQMessageRepository,
+        file: &mut std::fs::File,
+        source: FetchedSource,
+    ) -> Result<AddSourceFileStatus, AddSourceUrlError> {
+        let FetchedSource {
+            file_name,
+            origin,
+            content_type,
+            size_bytes,
+            tags,
+            upload_started_at,
+        } = source;
+
+        let source_type = Path::new(&file_name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| SourceType::from_str(extension).ok())
+            .ok_or_else(|| AddSourceUrlError::InvalidSourceType(file_name.clone()))?;
+
+        info!(
+            "Saving file {} downloaded from {}, of size {} and of type {:?}",
+            file_name, origin, size_bytes, source_type,
+        );
+
+        // The extension is given by the client or the origin: the content is checked from its magic bytes
+        let sniffed_content = SniffedContent::sniff_file(file)
+            .context(format!("Could not detect the content of {}", file_name))?;
+        if !sniffed_content.matches(&source_type) {
+            info!(
+                "{} is detected as {}, not matching its type {:?}",
+                file_name,
+                sniffed_content.mime_type(),
+                source_type
+            );
+            return Ok(AddSourceFileStatus::content_mismatch(
+                file_name,
+                &source_type,
+                sniffed_content,
+            ));
+        }
+
+        // Rejected before being stored, rather than extracting garbage from its encrypted content
+        let is_drm_protected = is_drm_protected(file, &source_type)
+            .context(format!("Could not check if {} is DRM-protected", file_name))?;
+        if is_drm_protected {
+            info!("{} is DRM-protected", file_name);
+            return Ok(AddSourceFileStatus::drm_protected(file_name));
+        }
+
+        file.rewind()
+            .context("Could not read the downloaded file from its start")?;
+        let (object_name, object_path_name, content_hash) = self
+            .s3_repository
+            .save_file(&user_id.to_string(), file)
+            .await
+            .context(format!(
+                "The file {} could not be uploaded to object storage",
+                file_name
+            ))?;
+
+        // A concurrent addition of the same file is registered first, and then found as a duplicate.
+        // The claim is held until the source is committed.
+        let _in_flight_upload = self
+            .in_flight_uploads
+            .claim(user_id, &content_hash, IN_FLIGHT_UPLOAD_WAIT)
+            .await;
+
+        let mut transaction = pool
+            .begin()
+            .await
+            .context("Failed to acquire a Postgres connection from the pool")?;
+
+        // Adding the same file again should not duplicate its extracted contents
+        let duplicated_source_meta_id = self
+            .source_registration
+            .source_meta_repository
+            .find_user_source_meta_id_by_content_hash(&mut transaction, user_id, &content_hash)
+            .await
+            .context(format!(
+                "Could not check if the file {} was already uploaded",
+                file_name
+            ))?;
+
+        if let Some(duplicated_source_meta_id) = duplicated_source_meta_id {
+            info!(
+                "{} was already uploaded as source {}",
+                file_name, duplicated_source_meta_id
+            );
+
+            self.s3_repository
+                .remove_file(&object_path_name)
+                .await
+                .context(format!(
+                    "The duplicated object {} could not be removed from the object storage",
+                    object_path_name
+                ))?;
+
+            return Ok(AddSourceFileStatus {
+                file_name: Some(file_name),
+                status: Status::Duplicate,
+                message: Some(format!(
+                    "Already uploaded as source {}",
+                    duplicated_source_meta_id
+                )),
+                collection: None,
+                job_id: None,
+                source_id: Some(duplicated_source_meta_id),
+            });
+        }
+
+        let auto_filing_rules = self
+            .auto_filing_rule_repository
+            .list_user_rules(&mut transaction, user_id)
+            .await
+            .context("Could not list the auto-filing rules of the user")?;
+        let default_collection = self
+            .user_repository
+            .get_user_default_collection(&mut transaction, user_id)
+            .await
+            .context("Could not get the default collection of the user")?;
+        let filing = file_in_collection(
+            &auto_filing_rules,
+            default_collection.as_deref(),
+            &FilingFile {
+                file_name: &file_name,
+                mime_type: content_type.as_deref(),
+                tags: &tags,
+            },
+        );
+
+        let source_meta = SourceMeta::builder()
+            .user_id(user_id)
+            .initial_name(file_name.clone())
+            .source_type(source_type)
+            .object_store_name(object_name)
+            .content_hash(Some(content_hash))
+            .collection(filing.as_ref().map(|filing| filing.collection.clone()))
+            .auto_filing_rule_id(filing.as_ref().and_then(|filing| filing.rule_id))
+            .detected_mime_type(Some(sniffed_content.mime_type().to_string()))
+            .size_bytes(Some(size_bytes as i64))
+            .build();
+
+        if !self
+            .source_registration
+            .reserve_storage(&mut transaction, &source_meta)
+            .await?
+        {
+            self.s3_repository
+                .remove_file(&object_path_name)
+                .await
+                .context(format!(
+                    "The object {} exceeding the storage quota could not be removed from the object storage",
+                    object_path_name
+                ))?;
+
+            return Err(AddSourceUrlError::StorageQuotaExceeded(size_bytes));
+        }
+
+        let lane = IngestionLane::for_source(
+            &source_meta.source_type,
+            size_bytes,
+            self.fast_lane_max_bytes,
+        );
+        let registered_source = self
+            .source_registration
+            .register(
+                &mut transaction,
+                tenant_id,
+                &source_meta,
+                object_path_name,
+                lane,
+                upload_started_at,
+            )
+            .await?;
+
+        transaction.commit().await.context(format!(
+            "Failed to commit SQL transaction to store the file {}",
+            file_name
+        ))?;
+
+        self.source_registration
+            .send_to_extraction(pool, message_rabbitmq_repository, &registered_source)
+            .await;
+
+        Ok(AddSourceFileStatus {
+            file_name: Some(file_name),
+            status: Status::Success,
+            message: None,
+            collection: source_meta.collection,
+            job_id: Some(registered_source.ingestion_job.id),
+            source_id: Some(source_meta.id),
+        })
+    }
+}
@@ -0,0 +1,241 @@
+use crate::domain::entities::api_key::{ApiKey, ApiKeyScope};
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::api_key_postgres_repository::{ApiKeyCursor, ApiKeyPostgresRepository};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use common::helper::error_chain_fmt;
+use common::pagination::{Page, PageCursor, PageLimits, PaginationError};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+const PAGE_LIMITS: PageLimits = PageLimits::new(20, 100);
+
+#[derive(thiserror::Error)]
+pub enum ApiKeyError {
+    #[error("Invalid API key: {0}")]
+    InvalidApiKey(String),
+    #[error("API key {0} not found")]
+    ApiKeyNotFound(Uuid),
+    #[error(transparent)]
+    InvalidPagination(#[from] PaginationError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for ApiKeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for ApiKeyError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiKeyError::InvalidApiKey(_) | ApiKeyError::InvalidPagination(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            ApiKeyError::ApiKeyNotFound(_) => StatusCode::NOT_FOUND,
+            ApiKeyError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct CreateApiKeyBodyData {
+    /// Name of the service or script using the key
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+}
+
+/// API key of a user, without the key itself
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ApiKeyResponse {
+    pub id: Uuid,
+    pub name: String,
+    /// First characters of the key
+    pub key_prefix: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(value: ApiKey) -> Self {
+        Self {
+            id: value.id,
+            name: value.name,
+            key_prefix: value.key_prefix,
+            scopes: value.scopes,
+            last_used_at: value.last_used_at,
+            created_at: value.created_at,
+        }
+    }
+}
+
+/// Created API key, with the key itself: it can not be retrieved afterwards
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct CreateApiKeyResponse {
+    /// To send in the `X-Api-Key` header
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKeyResponse,
+}
+
+/// Create an API key for a user, used by services and scripts on their behalf
+///
+/// The key only gives access to the endpoints of its scopes.
+#[utoipa::path(
+    post,
+    path = "/api_keys",
+    tag = "api_keys",
+    request_body = CreateApiKeyBodyData,
+    responses(
+        (status = 201, description = "Created API key", body = CreateApiKeyResponse),
+        (status = 400, description = "Empty name or no scope"),
+        (status = 403, description = "The user has no account of the gateway"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(name = "Create API key", skip(pool, api_key_repository), err)]
+pub async fn create_api_key(
+    body: web::Json<CreateApiKeyBodyData>,
+    pool: web::Data<PgPool>,
+    api_key_repository: web::Data<ApiKeyPostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, ApiKeyError> {
+    let user_id = user_id.into_inner().0;
+    let CreateApiKeyBodyData { name, mut scopes } = body.into_inner();
+
+    if name.trim().is_empty() {
+        return Err(ApiKeyError::InvalidApiKey(
+            "the name should not be empty".to_string(),
+        ));
+    }
+
+    scopes.sort_by_key(|scope| *scope as u8);
+    scopes.dedup();
+    if scopes.is_empty() {
+        return Err(ApiKeyError::InvalidApiKey(
+            "at least one scope is required".to_string(),
+        ));
+    }
+
+    let (key, api_key) = ApiKey::generate(user_id, name, scopes);
+
+    api_key_repository
+        .add_api_key(&**pool, &api_key)
+        .await
+        .context("Failed to save the API key")?;
+
+    info!(api_key_id = %api_key.id, "Created API key");
+
+    Ok(HttpResponse::Created().json(CreateApiKeyResponse {
+        key,
+        api_key: api_key.into(),
+    }))
+}
+
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListApiKeysQuery {
+    /// `next_cursor` or `prev_cursor` of a listed page. The first page is listed without cursor.
+    pub cursor: Option<String>,
+    /// Maximum number of API keys in the page
+    pub limit: Option<u32>,
+}
+
+/// Page of API keys
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ListApiKeysResponse {
+    pub api_keys: Vec<ApiKeyResponse>,
+    /// Cursor to list the next page. `None` on the last page.
+    pub next_cursor: Option<String>,
+    /// Cursor to list the previous page. `None` on the first page.
+    pub prev_cursor: Option<String>,
+}
+
+/// List the API keys of a user, from the most recent
+#[utoipa::path(
+    get,
+    path = "/api_keys",
+    tag = "api_keys",
+    params(ListApiKeysQuery),
+    responses(
+        (status = 200, description = "Page of API keys", body = ListApiKeysResponse),
+        (status = 400, description = "Invalid cursor or limit"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(name = "List API keys", skip(pool, api_key_repository), err)]
+pub async fn list_api_keys(
+    query: web::Query<ListApiKeysQuery>,
+    pool: web::Data<PgPool>,
+    api_key_repository: web::Data<ApiKeyPostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, ApiKeyError> {
+    let user_id = user_id.into_inner().0;
+
+    let limit = PAGE_LIMITS.validate(query.limit)?;
+    let cursor = PageCursor::<ApiKeyCursor>::decode_query(query.cursor.as_deref())?;
+
+    // Lists one more API key to know if there is a further page
+    let api_keys = api_key_repository
+        .list_user_api_keys(&**pool, user_id, cursor.as_ref(), i64::from(limit) + 1)
+        .await
+        .context("Failed to list the API keys")?;
+
+    let page = Page::from_fetched(
+        api_keys,
+        limit,
+        cursor.map(|cursor| cursor.direction),
+        |api_key| ApiKeyCursor {
+            created_at: api_key.created_at,
+            id: api_key.id,
+        },
+    );
+
+    Ok(HttpResponse::Ok().json(ListApiKeysResponse {
+        api_keys: page.items.into_iter().map(Into::into).collect(),
+        next_cursor: page.next_cursor,
+        prev_cursor: page.prev_cursor,
+    }))
+}
+
+/// Delete an API key of a user, revoking it
+#[utoipa::path(
+    delete,
+    path = "/api_keys/{api_key_id}",
+    tag = "api_keys",
+    params(("api_key_id" = Uuid, Path, description = "ID of the API key")),
+    responses(
+        (status = 204, description = "Deleted API key"),
+        (status = 404, description = "API key not found"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(name = "Delete API key", skip(pool, api_key_repository), err)]
+pub async fn delete_api_key(
+    api_key_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    api_key_repository: web::Data<ApiKeyPostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, ApiKeyError> {
+    let user_id = user_id.into_inner().0;
+    let api_key_id = api_key_id.into_inner();
+
+    let is_deleted = api_key_repository
+        .delete_user_api_key(&**pool, user_id, api_key_id)
+        .await
+        .context("Failed to delete the API key")?;
+
+    if !is_deleted {
+        return Err(ApiKeyError::ApiKeyNotFound(api_key_id));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
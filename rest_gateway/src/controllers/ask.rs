@@ -0,0 +1,502 @@
+use crate::configuration::{AnswerGenerationSettings, ProviderCredentialsSettings};
+use crate::controllers::search_content::{
+    search_semantic_contents, SearchContentBodyData, SearchContentError, SearchMode, SearchServices,
+};
+use crate::domain::entities::answer::{cited_chunks, AnswerPrompt, Citation};
+use crate::domain::entities::provider_credentials::{ModelProvider, ProviderPurpose};
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::answer_generation_port::{
+    AnswerChunk, AnswerGenerationError, AnswerGenerationPort,
+};
+use crate::repositories::openai_answer_generator::OpenAiAnswerGenerator;
+use crate::repositories::provider_credentials_postgres_repository::ProviderCredentialsPostgresRepository;
+use crate::repositories::user_postgres_repository::UserPostgresRepository;
+use crate::responders::sse::{sse_response, SseEvent};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use common::core::secrets::SecretsCipher;
+use common::core::tenancy::TenantMessageRepositories;
+use common::helper::error_chain_fmt;
+use futures::{future, stream, stream::LocalBoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(thiserror::Error)]
+pub enum AskError {
+    #[error("The question should not be empty")]
+    EmptyQuestion,
+    #[error("Invalid top_k {0}: it should be from 1 to {1}")]
+    InvalidTopK(usize, usize),
+    #[error("Questions are not answered: no LLM backend is configured")]
+    AnswerGenerationDisabled,
+    #[error(transparent)]
+    SearchError(#[from] SearchContentError),
+    #[error(transparent)]
+    AnswerGenerationError(#[from] AnswerGenerationError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for AskError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for AskError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AskError::EmptyQuestion | AskError::InvalidTopK(_, _) => StatusCode::BAD_REQUEST,
+            AskError::AnswerGenerationDisabled => StatusCode::SERVICE_UNAVAILABLE,
+            AskError::SearchError(error) => error.status_code(),
+            AskError::AnswerGenerationError(_) => StatusCode::BAD_GATEWAY,
+            AskError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AskBodyData {
+    pub question: String,
+    /// Number of chunks semantically closest to the question retrieved to answer it,
+    /// `answer_generation.default_top_k` by default
+    #[serde(default)]
+    pub top_k: Option<usize>,
+    /// Only the contents detected in this language (ISO 639-1 code, for ex `fr`) are retrieved
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Only the contents of these sources are retrieved, if any
+    #[serde(default)]
+    pub source_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AskResponse {
+    /// `None` when no content was retrieved to answer from: the LLM is then not called
+    pub answer: Option<String>,
+    /// Retrieved chunks cited in the answer, by increasing index
+    pub citations: Vec<Citation>,
+}
+
+/// Token of a streamed answer
+#[derive(Debug, Serialize, Deserialize)]
+struct AnswerTokenEvent {
+    text: String,
+}
+
+/// Streamed answer, accumulated from its tokens to send its citations once generated
+struct StreamedAnswer {
+    chunks: LocalBoxStream<'static, Result<AnswerChunk, AnswerGenerationError>>,
+    text: String,
+    citations: Vec<Citation>,
+    /// Usage reported by the LLM at the end of the stream
+    nb_tokens: u64,
+    tenant_usage: Option<TenantUsage>,
+    pool: PgPool,
+}
+
+/// Error ending a streamed answer
+#[derive(Debug, Serialize, Deserialize)]
+struct AnswerErrorEvent {
+    message: String,
+}
+
+/// Generates the answers with the completion provider brought by the tenant of a user (BYOK),
+/// or with the LLM backend of the deployment when the tenant brought none
+pub struct AnswerGenerators {
+    default_generator: Arc<dyn AnswerGenerationPort>,
+    /// Client of the providers of the tenants, with the timeout of the answers
+    client: reqwest::Client,
+    user_repository: UserPostgresRepository,
+    provider_credentials_repository: Arc<ProviderCredentialsPostgresRepository>,
+    /// Decrypts the API keys of the tenants
+    secrets_cipher: SecretsCipher,
+    provider_settings: ProviderCredentialsSettings,
+}
+
+impl AnswerGenerators {
+    pub fn try_new(
+        default_generator: Arc<dyn AnswerGenerationPort>,
+        secrets_cipher: SecretsCipher,
+        settings: &AnswerGenerationSettings,
+        provider_settings: ProviderCredentialsSettings,
+    ) -> Result<Self, reqwest::Error> {
+        Ok(Self {
+            default_generator,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(settings.timeout_s))
+                .build()?,
+            user_repository: UserPostgresRepository::new(),
+            provider_credentials_repository: Arc::new(ProviderCredentialsPostgresRepository::new()),
+            secrets_cipher,
+            provider_settings,
+        })
+    }
+
+    /// Generator of the answers of a user
+    async fn for_user(
+        &self,
+        pool: &PgPool,
+        user_id: Uuid,
+    ) -> Result<UserAnswerGenerator, AskError> {
+        let default_generator = UserAnswerGenerator {
+            generator: self.default_generator.clone(),
+            tenant_usage: None,
+        };
+
+        let Some(tenant_id) = self
+            .user_repository
+            .get_user_tenant_id(pool, user_id)
+            .await
+            .context("Failed to get the tenant of the user")?
+        else {
+            return Ok(default_generator);
+        };
+        let Some(credentials) = self
+            .provider_credentials_repository
+            .list_tenant_credentials(pool, &tenant_id)
+            .await
+            .context("Failed to get the provider credentials of the tenant")?
+            .into_iter()
+            .find(|credentials| credentials.purpose == ProviderPurpose::Completion)
+        else {
+            return Ok(default_generator);
+        };
+
+        let api_key = self
+            .secrets_cipher
+            .decrypt(&credentials.encrypted_api_key)
+            .context("Failed to decrypt the API key of the tenant")?;
+        info!(
+            tenant_id,
+            "Answering with the model {} of the tenant", credentials.model
+        );
+
+        Ok(UserAnswerGenerator {
+            generator: Arc::new(OpenAiAnswerGenerator::with_model(
+                self.client.clone(),
+                self.provider_settings.api_url(credentials.provider),
+                api_key,
+                &credentials.model,
+                credentials.provider == ModelProvider::OpenAi,
+            )),
+            tenant_usage: Some(TenantUsage {
+                tenant_id,
+                provider_credentials_repository: self.provider_credentials_repository.clone(),
+            }),
+        })
+    }
+}
+
+/// LLM answering the questions of a user
+struct UserAnswerGenerator {
+    generator: Arc<dyn AnswerGenerationPort>,
+    /// Set when the answers are generated by the provider of the tenant of the user
+    tenant_usage: Option<TenantUsage>,
+}
+
+/// Attributes the usage of its completion provider to a tenant
+struct TenantUsage {
+    tenant_id: String,
+    provider_credentials_repository: Arc<ProviderCredentialsPostgresRepository>,
+}
+
+impl TenantUsage {
+    /// Records the generation of an answer
+    ///
+    /// The answer is generated: a failure to record its usage is only logged.
+    async fn record(&self, pool: &PgPool, nb_tokens: u64) {
+        let result = self
+            .provider_credentials_repository
+            .record_usage(
+                pool,
+                &self.tenant_id,
+                ProviderPurpose::Completion,
+                1,
+                nb_tokens as i64,
+            )
+            .await;
+
+        match result {
+            Ok(true) => {}
+            // The credentials were deleted while the answer was generated
+            Ok(false) => warn!(
+                "No completion provider credentials for tenant {}",
+                self.tenant_id
+            ),
+            Err(error) => error!(
+                ?error,
+                "Failed to record the completion provider usage of tenant {}", self.tenant_id
+            ),
+        }
+    }
+}
+
+/// Retrieves the chunks semantically closest to a question, numbered to be cited in the answer
+async fn retrieve_citations(
+    pool: &PgPool,
+    search_services: &SearchServices,
+    message_repositories: &TenantMessageRepositories,
+    answer_generator: &dyn AnswerGenerationPort,
+    settings: &AnswerGenerationSettings,
+    body: &AskBodyData,
+    user_id: Uuid,
+) -> Result<Vec<Citation>, AskError> {
+    if body.question.trim().is_empty() {
+        return Err(AskError::EmptyQuestion);
+    }
+    let top_k = body.top_k.unwrap_or(settings.default_top_k);
+    if !(1..=settings.max_top_k).contains(&top_k) {
+        return Err(AskError::InvalidTopK(top_k, settings.max_top_k));
+    }
+    if !answer_generator.is_enabled() {
+        return Err(AskError::AnswerGenerationDisabled);
+    }
+
+    let search = SearchContentBodyData {
+        query: body.question.clone(),
+        limit: None,
+        page: None,
+        hits_per_page: Some(top_k),
+        sort: Default::default(),
+        mode: SearchMode::Semantic,
+        language: body.language.clone(),
+        fields: Default::default(),
+        source_ids: body.source_ids.clone(),
+        source_type: None,
+        uploaded_after: None,
+        uploaded_before: None,
+        chapter: None,
+        keyword: None,
+        entity: None,
+        rerank: false,
+    };
+    let contents = search_semantic_contents(
+        pool,
+        search_services,
+        message_repositories,
+        &search,
+        user_id,
+    )
+    .await?;
+
+    let source_meta_ids: Vec<Uuid> = contents
+        .iter()
+        .filter_map(|content| content.source_meta_id)
+        .collect();
+    let source_names: HashMap<Uuid, String> = search_services
+        .source_meta_repository
+        .list_user_source_metas_by_ids(pool, user_id, &source_meta_ids)
+        .await
+        .map_err(SearchContentError::from)?
+        .into_iter()
+        .map(|source_meta| (source_meta.id, source_meta.initial_name))
+        .collect();
+    info!("Retrieved {} chunks to answer the question", contents.len());
+
+    Ok(contents
+        .into_iter()
+        .enumerate()
+        .map(|(index, content)| Citation {
+            index: index + 1,
+            chunk_id: content.id,
+            source_name: content
+                .source_meta_id
+                .and_then(|source_meta_id| source_names.get(&source_meta_id).cloned()),
+            source_id: content.source_meta_id,
+            content: content.content,
+        })
+        .collect())
+}
+
+/// Answer a question from the contents of a user
+///
+/// The chunks semantically closest to the question are retrieved, then given to an LLM answering from them only,
+/// citing them by their index. A request accepting `text/event-stream` gets the answer streamed as Server-Sent Events:
+/// a `token` event (`{ "text": ... }`) per generated token, then an `answer` event with the whole `AskResponse`,
+/// or an `error` event (`{ "message": ... }`) if the generation fails.
+#[utoipa::path(
+    post,
+    path = "/ask",
+    tag = "search",
+    request_body = AskBodyData,
+    responses(
+        (status = 200, description = "Answer with its citations", body = AskResponse),
+        (status = 400, description = "Empty question, invalid top_k, language or source ids"),
+        (status = 502, description = "The LLM backend failed to answer"),
+        (status = 503, description = "No LLM backend is configured"),
+        (status = 504, description = "The semantic search did not answer in time"),
+    ),
+    security(("access_token" = []), ("api_key" = ["search"]))
+)]
+#[tracing::instrument(
+    name = "Ask",
+    skip(
+        pool,
+        search_services,
+        message_repositories,
+        answer_generators,
+        settings
+    ),
+    err
+)]
+pub async fn ask(
+    pool: web::Data<PgPool>,
+    search_services: web::Data<SearchServices>,
+    message_repositories: web::Data<TenantMessageRepositories>,
+    answer_generators: web::Data<AnswerGenerators>,
+    settings: web::Data<AnswerGenerationSettings>,
+    body: web::Json<AskBodyData>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, AskError> {
+    let user_id = user_id.into_inner().0;
+    let answer_generator = answer_generators.for_user(&pool, user_id).await?;
+    let citations = retrieve_citations(
+        &pool,
+        &search_services,
+        &message_repositories,
+        answer_generator.generator.as_ref(),
+        &settings,
+        &body,
+        user_id,
+    )
+    .await?;
+    if citations.is_empty() {
+        return Ok(HttpResponse::Ok().json(AskResponse {
+            answer: None,
+            citations,
+        }));
+    }
+
+    let prompt = AnswerPrompt::new(&body.question, &citations);
+    let answer = answer_generator.generator.generate(&prompt).await?;
+    if let Some(tenant_usage) = &answer_generator.tenant_usage {
+        tenant_usage.record(&pool, answer.nb_tokens).await;
+    }
+
+    Ok(HttpResponse::Ok().json(AskResponse {
+        citations: cited_chunks(citations, &answer.text),
+        answer: Some(answer.text),
+    }))
+}
+
+/// Streams the answer of a question as Server-Sent Events, see `ask`
+///
+/// The chunks are retrieved before the response is sent: a failed retrieval is answered with its error status.
+#[tracing::instrument(
+    name = "Ask with a streamed answer",
+    skip(
+        pool,
+        search_services,
+        message_repositories,
+        answer_generators,
+        settings
+    ),
+    err
+)]
+pub async fn ask_stream(
+    pool: web::Data<PgPool>,
+    search_services: web::Data<SearchServices>,
+    message_repositories: web::Data<TenantMessageRepositories>,
+    answer_generators: web::Data<AnswerGenerators>,
+    settings: web::Data<AnswerGenerationSettings>,
+    body: web::Json<AskBodyData>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, AskError> {
+    let user_id = user_id.into_inner().0;
+    let answer_generator = answer_generators.for_user(&pool, user_id).await?;
+    let citations = retrieve_citations(
+        &pool,
+        &search_services,
+        &message_repositories,
+        answer_generator.generator.as_ref(),
+        &settings,
+        &body,
+        user_id,
+    )
+    .await?;
+    let keep_alive_interval = Duration::from_secs(settings.keep_alive_interval_s);
+
+    if citations.is_empty() {
+        let response = AskResponse {
+            answer: None,
+            citations,
+        };
+        let events = stream::iter(SseEvent::json("answer", &response));
+        return Ok(sse_response(events, keep_alive_interval));
+    }
+
+    let prompt = AnswerPrompt::new(&body.question, &citations);
+    let streamed_answer = StreamedAnswer {
+        chunks: answer_generator.generator.generate_stream(&prompt),
+        text: String::new(),
+        citations,
+        nb_tokens: 0,
+        tenant_usage: answer_generator.tenant_usage,
+        pool: pool.get_ref().clone(),
+    };
+    let events = stream::unfold(Some(streamed_answer), |state| async move {
+        let mut streamed_answer = state?;
+
+        loop {
+            match streamed_answer.chunks.next().await {
+                Some(Ok(AnswerChunk::Token(token))) => {
+                    let event = SseEvent::json(
+                        "token",
+                        &AnswerTokenEvent {
+                            text: token.clone(),
+                        },
+                    );
+                    streamed_answer.text.push_str(&token);
+                    return Some((event, Some(streamed_answer)));
+                }
+                Some(Ok(AnswerChunk::Usage { nb_tokens })) => {
+                    streamed_answer.nb_tokens = nb_tokens;
+                }
+                Some(Err(error)) => {
+                    warn!(?error, "Failed to generate the streamed answer");
+                    let event = SseEvent::json(
+                        "error",
+                        &AnswerErrorEvent {
+                            message: error.to_string(),
+                        },
+                    );
+                    return Some((event, None));
+                }
+                None => {
+                    if let Some(tenant_usage) = &streamed_answer.tenant_usage {
+                        tenant_usage
+                            .record(&streamed_answer.pool, streamed_answer.nb_tokens)
+                            .await;
+                    }
+                    let response = AskResponse {
+                        citations: cited_chunks(streamed_answer.citations, &streamed_answer.text),
+                        answer: Some(streamed_answer.text),
+                    };
+                    return Some((SseEvent::json("answer", &response), None));
+                }
+            }
+        }
+    })
+    .filter_map(|event| {
+        let event = match event {
+            Ok(event) => Some(event),
+            Err(error) => {
+                warn!(
+                    ?error,
+                    "Skipping an answer event that could not be serialized"
+                );
+                None
+            }
+        };
+
+        future::ready(event)
+    });
+
+    Ok(sse_response(events, keep_alive_interval))
+}
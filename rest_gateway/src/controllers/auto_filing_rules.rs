@@ -0,0 +1,263 @@
+use crate::domain::entities::auto_filing_rule::AutoFilingRule;
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::auto_filing_rule_postgres_repository::AutoFilingRulePostgresRepository;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use common::helper::error_chain_fmt;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(thiserror::Error)]
+pub enum AutoFilingRuleError {
+    #[error("Invalid auto-filing rule: {0}")]
+    InvalidRule(String),
+    #[error("Auto-filing rule {0} not found")]
+    RuleNotFound(Uuid),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for AutoFilingRuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for AutoFilingRuleError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AutoFilingRuleError::InvalidRule(_) => StatusCode::BAD_REQUEST,
+            AutoFilingRuleError::RuleNotFound(_) => StatusCode::NOT_FOUND,
+            AutoFilingRuleError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct AutoFilingRuleBodyData {
+    /// The rules are evaluated by ascending position
+    pub position: i32,
+    pub file_name_pattern: Option<String>,
+    pub mime_type: Option<String>,
+    pub tag: Option<String>,
+    pub collection: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct AutoFilingRuleResponse {
+    pub id: Uuid,
+    pub position: i32,
+    pub file_name_pattern: Option<String>,
+    pub mime_type: Option<String>,
+    pub tag: Option<String>,
+    pub collection: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<AutoFilingRule> for AutoFilingRuleResponse {
+    fn from(value: AutoFilingRule) -> Self {
+        Self {
+            id: value.id,
+            position: value.position,
+            file_name_pattern: value.file_name_pattern,
+            mime_type: value.mime_type,
+            tag: value.tag,
+            collection: value.collection,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+impl AutoFilingRuleBodyData {
+    fn try_into_rule(self, id: Uuid, user_id: Uuid) -> Result<AutoFilingRule, AutoFilingRuleError> {
+        if self.collection.trim().is_empty() {
+            return Err(AutoFilingRuleError::InvalidRule(
+                "the collection should not be empty".to_string(),
+            ));
+        }
+
+        let now = Utc::now();
+        let rule = AutoFilingRule {
+            id,
+            user_id,
+            position: self.position,
+            file_name_pattern: self.file_name_pattern,
+            mime_type: self.mime_type,
+            tag: self.tag,
+            collection: self.collection,
+            created_at: now,
+            updated_at: now,
+        };
+
+        // A rule without condition would file every source
+        if !rule.has_condition() {
+            return Err(AutoFilingRuleError::InvalidRule(
+                "at least one condition on the file name, MIME type or tag is required".to_string(),
+            ));
+        }
+
+        Ok(rule)
+    }
+}
+
+/// List the auto-filing rules of a user, in the order they are evaluated
+#[utoipa::path(
+    get,
+    path = "/auto_filing_rules",
+    tag = "auto_filing_rules",
+    responses(
+        (status = 200, description = "Auto-filing rules of the user", body = [AutoFilingRuleResponse]),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "List auto-filing rules",
+    skip(pool, auto_filing_rule_repository),
+    err
+)]
+pub async fn list_auto_filing_rules(
+    pool: web::Data<PgPool>,
+    auto_filing_rule_repository: web::Data<AutoFilingRulePostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, AutoFilingRuleError> {
+    let user_id = user_id.into_inner().0;
+    info!("Request for user_id: {}", user_id);
+
+    let rules = auto_filing_rule_repository
+        .list_user_rules(pool.get_ref(), user_id)
+        .await
+        .context("Could not list the auto-filing rules of the user")?;
+
+    Ok(HttpResponse::Ok().json(
+        rules
+            .into_iter()
+            .map(AutoFilingRuleResponse::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Create an auto-filing rule, filing the next uploaded sources of a user in a collection
+#[utoipa::path(
+    post,
+    path = "/auto_filing_rules",
+    tag = "auto_filing_rules",
+    request_body = AutoFilingRuleBodyData,
+    responses(
+        (status = 201, description = "Created auto-filing rule", body = AutoFilingRuleResponse),
+        (status = 400, description = "Invalid auto-filing rule"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "Create auto-filing rule",
+    skip(pool, auto_filing_rule_repository),
+    err
+)]
+pub async fn create_auto_filing_rule(
+    body: web::Json<AutoFilingRuleBodyData>,
+    pool: web::Data<PgPool>,
+    auto_filing_rule_repository: web::Data<AutoFilingRulePostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, AutoFilingRuleError> {
+    let user_id = user_id.into_inner().0;
+    info!("Request for user_id: {}", user_id);
+
+    let rule = body.into_inner().try_into_rule(Uuid::new_v4(), user_id)?;
+
+    auto_filing_rule_repository
+        .add_rule(pool.get_ref(), &rule)
+        .await
+        .context("Could not save the auto-filing rule")?;
+
+    Ok(HttpResponse::Created().json(AutoFilingRuleResponse::from(rule)))
+}
+
+/// Update an auto-filing rule of a user. The already filed sources stay in their collection.
+#[utoipa::path(
+    put,
+    path = "/auto_filing_rules/{rule_id}",
+    tag = "auto_filing_rules",
+    params(("rule_id" = Uuid, Path, description = "ID of the auto-filing rule")),
+    request_body = AutoFilingRuleBodyData,
+    responses(
+        (status = 204, description = "Updated auto-filing rule"),
+        (status = 400, description = "Invalid auto-filing rule"),
+        (status = 404, description = "Auto-filing rule not found"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "Update auto-filing rule",
+    skip(pool, auto_filing_rule_repository),
+    err
+)]
+pub async fn update_auto_filing_rule(
+    rule_id: web::Path<Uuid>,
+    body: web::Json<AutoFilingRuleBodyData>,
+    pool: web::Data<PgPool>,
+    auto_filing_rule_repository: web::Data<AutoFilingRulePostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, AutoFilingRuleError> {
+    let user_id = user_id.into_inner().0;
+    let rule_id = rule_id.into_inner();
+    info!("Request for user_id: {}", user_id);
+
+    let rule = body.into_inner().try_into_rule(rule_id, user_id)?;
+
+    let updated = auto_filing_rule_repository
+        .update_user_rule(pool.get_ref(), &rule)
+        .await
+        .context("Could not update the auto-filing rule")?;
+
+    if !updated {
+        return Err(AutoFilingRuleError::RuleNotFound(rule_id));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Delete an auto-filing rule of a user. The sources it filed stay in their collection.
+#[utoipa::path(
+    delete,
+    path = "/auto_filing_rules/{rule_id}",
+    tag = "auto_filing_rules",
+    params(("rule_id" = Uuid, Path, description = "ID of the auto-filing rule")),
+    responses(
+        (status = 204, description = "Deleted auto-filing rule"),
+        (status = 404, description = "Auto-filing rule not found"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "Delete auto-filing rule",
+    skip(pool, auto_filing_rule_repository),
+    err
+)]
+pub async fn delete_auto_filing_rule(
+    rule_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    auto_filing_rule_repository: web::Data<AutoFilingRulePostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, AutoFilingRuleError> {
+    let user_id = user_id.into_inner().0;
+    let rule_id = rule_id.into_inner();
+    info!("Request for user_id: {}", user_id);
+
+    let deleted = auto_filing_rule_repository
+        .delete_user_rule(pool.get_ref(), user_id, rule_id)
+        .await
+        .context("Could not delete the auto-filing rule")?;
+
+    if !deleted {
+        return Err(AutoFilingRuleError::RuleNotFound(rule_id));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -2,11 +2,13 @@ use actix_web::http::header::ContentType;
 use actix_web::http::StatusCode;
 use actix_web::{web, HttpResponse, ResponseError};
 use anyhow::Context;
+use common::core::tenancy::TenantMessageRepositories;
 use common::helper::error_chain_fmt;
 use secrecy::Secret;
 use serde_json::json;
 use sqlx::PgPool;
 use tracing::info;
+use utoipa::ToSchema;
 
 use crate::domain::entities::user::UserError;
 use crate::repositories::user_postgres_repository::UserPostgresRepositoryError;
@@ -14,15 +16,36 @@ use crate::{
     domain::entities::user::User, repositories::user_postgres_repository::UserPostgresRepository,
 };
 
-#[tracing::instrument(name = "Create user account", skip(pool, user_repository, body))]
+#[utoipa::path(
+    post,
+    path = "/account/create",
+    tag = "account",
+    request_body = CreateAccountBodyData,
+    responses(
+        (status = 200, description = "Created account", body = Object, example = json!({ "message": "Account john@doe.com created" })),
+        (status = 400, description = "Invalid email or password, or unknown tenant", body = Object, example = json!({ "error": "Invalid password" })),
+    )
+)]
+#[tracing::instrument(
+    name = "Create user account",
+    skip(pool, user_repository, message_repositories, body)
+)]
 pub async fn create_account(
     pool: web::Data<PgPool>,
     user_repository: web::Data<UserPostgresRepository>,
+    message_repositories: web::Data<TenantMessageRepositories>,
     body: web::Json<CreateAccountBodyData>,
 ) -> Result<HttpResponse, CreateAccountError> {
     info!(email = body.email, "Creating account");
 
-    let user = User::create(&body.email, Secret::new(body.password.clone()))
+    // The messages of a user are routed to the exchanges of their tenant: it needs to be configured
+    if let Some(tenant_id) = &body.tenant_id {
+        if !message_repositories.has_tenant(tenant_id) {
+            return Err(CreateAccountError::UnknownTenant(tenant_id.clone()));
+        }
+    }
+
+    let mut user = User::create(&body.email, Secret::new(body.password.clone()))
         .await
         .map_err(|error| match error {
             UserError::EmailError(_) => CreateAccountError::InvalidEmail(body.email.clone()),
@@ -31,6 +54,7 @@ pub async fn create_account(
                 CreateAccountError::InternalError(error.into())
             }
         })?;
+    user.tenant_id = body.tenant_id.clone();
 
     let mut transaction = pool
         .begin()
@@ -48,10 +72,13 @@ pub async fn create_account(
     Ok(HttpResponse::Ok().json(json!({ "message": format!("Account {} created", body.email)})))
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, serde::Deserialize, serde::Serialize, ToSchema)]
 pub struct CreateAccountBodyData {
     pub email: String,
     pub password: String,
+    /// Tenant (business unit) of the user, isolated at the broker level
+    #[serde(default)]
+    pub tenant_id: Option<String>,
 }
 
 #[derive(thiserror::Error)]
@@ -60,6 +87,8 @@ pub enum CreateAccountError {
     InvalidEmail(String),
     #[error("Invalid password")]
     InvalidPassword(String),
+    #[error("Unknown tenant: {0}")]
+    UnknownTenant(String),
     #[error("{0}")]
     UserInternalError(String),
     #[error(transparent)]
@@ -77,9 +106,9 @@ impl std::fmt::Debug for CreateAccountError {
 impl ResponseError for CreateAccountError {
     fn status_code(&self) -> StatusCode {
         match self {
-            CreateAccountError::InvalidEmail(_) | CreateAccountError::InvalidPassword(_) => {
-                StatusCode::BAD_REQUEST
-            }
+            CreateAccountError::InvalidEmail(_)
+            | CreateAccountError::InvalidPassword(_)
+            | CreateAccountError::UnknownTenant(_) => StatusCode::BAD_REQUEST,
             CreateAccountError::InternalError(_)
             | CreateAccountError::UserInternalError(_)
             | CreateAccountError::RepositoryInternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
@@ -0,0 +1,71 @@
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::source_url_schedule_postgres_repository::SourceUrlSchedulePostgresRepository;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use common::helper::error_chain_fmt;
+use sqlx::PgPool;
+use tracing::info;
+use uuid::Uuid;
+
+#[derive(thiserror::Error)]
+pub enum DeleteRecrawlScheduleError {
+    #[error("No re-crawl schedule for the source {0}")]
+    ScheduleNotFound(Uuid),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for DeleteRecrawlScheduleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for DeleteRecrawlScheduleError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            DeleteRecrawlScheduleError::ScheduleNotFound(_) => StatusCode::NOT_FOUND,
+            DeleteRecrawlScheduleError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Stop the re-crawls of a source added from a URL: the source and its contents are kept
+#[utoipa::path(
+    delete,
+    path = "/sources/{source_id}/recrawl_schedule",
+    tag = "sources",
+    params(("source_id" = Uuid, Path, description = "Id of the source")),
+    responses(
+        (status = 204, description = "Deleted re-crawl schedule"),
+        (status = 404, description = "The source of the user has no re-crawl schedule"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "Delete re-crawl schedule",
+    skip(pool, source_url_schedule_repository),
+    err
+)]
+pub async fn delete_recrawl_schedule(
+    source_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    source_url_schedule_repository: web::Data<SourceUrlSchedulePostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, DeleteRecrawlScheduleError> {
+    let user_id = user_id.into_inner().0;
+    let source_id = source_id.into_inner();
+    info!("Request for user_id: {}", user_id);
+
+    let deleted = source_url_schedule_repository
+        .delete_user_schedule(pool.get_ref(), user_id, source_id)
+        .await
+        .context("Could not delete the re-crawl schedule")?;
+
+    if !deleted {
+        return Err(DeleteRecrawlScheduleError::ScheduleNotFound(source_id));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
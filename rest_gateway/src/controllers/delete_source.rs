@@ -0,0 +1,228 @@
+use crate::domain::entities::source_event::SourceEvent;
+use crate::domain::entities::source_meta::SourceMeta;
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::fulltext_shard_postgres_repository::FulltextShardPostgresRepository;
+use crate::repositories::source_event_postgres_repository::SourceEventPostgresRepository;
+use crate::repositories::source_file_s3_repository::{S3Repository, S3RepositoryError};
+use crate::repositories::source_meta_postgres_repository::SourceMetaPostgresRepository;
+use crate::repositories::user_postgres_repository::UserPostgresRepository;
+use crate::repositories::user_storage_usage_postgres_repository::UserStorageUsagePostgresRepository;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use common::constants::routing_keys::DELETE_CONTENT_ROUTING_KEY;
+use common::core::tenancy::TenantMessageRepositories;
+use common::dtos::delete_content::DeleteContentDto;
+use common::dtos::extract_content_job::chunk_manifest_path;
+use common::helper::error_chain_fmt;
+use sqlx::PgPool;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+#[derive(thiserror::Error)]
+pub enum DeleteSourceError {
+    #[error("Source {0} not found")]
+    SourceNotFound(Uuid),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for DeleteSourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for DeleteSourceError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            DeleteSourceError::SourceNotFound(_) => StatusCode::NOT_FOUND,
+            DeleteSourceError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Delete a user source: its file, its meta and all the contents extracted from it
+///
+/// The extracted contents are deleted asynchronously by the full-text search and embedding workers.
+#[utoipa::path(
+    delete,
+    path = "/sources/{source_id}",
+    tag = "sources",
+    params(("source_id" = Uuid, Path, description = "ID of the source")),
+    responses(
+        (status = 204, description = "Deleted source"),
+        (status = 404, description = "Source not found"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "Delete source",
+    skip(pool, source_deletion, message_repositories),
+    err
+)]
+pub async fn delete_source(
+    source_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    source_deletion: web::Data<SourceDeletion>,
+    message_repositories: web::Data<TenantMessageRepositories>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, DeleteSourceError> {
+    let user_id = user_id.into_inner().0;
+    let source_id = source_id.into_inner();
+    info!("Request for user_id: {}", user_id);
+
+    let deleted = source_deletion
+        .delete(
+            &pool,
+            &message_repositories,
+            user_id,
+            source_id,
+            SourceEvent::deleted,
+        )
+        .await?;
+
+    if !deleted {
+        return Err(DeleteSourceError::SourceNotFound(source_id));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Deletes the sources of the users: their file, their meta and all the contents extracted from them
+///
+/// Shared by the deletions requested by the users and the retention sweeper.
+pub struct SourceDeletion {
+    s3_repository: S3Repository,
+    source_meta_repository: SourceMetaPostgresRepository,
+    source_event_repository: SourceEventPostgresRepository,
+    fulltext_shard_repository: FulltextShardPostgresRepository,
+    user_repository: UserPostgresRepository,
+    user_storage_usage_repository: UserStorageUsagePostgresRepository,
+}
+
+impl SourceDeletion {
+    pub fn new(s3_repository: S3Repository) -> Self {
+        Self {
+            s3_repository,
+            source_meta_repository: SourceMetaPostgresRepository::new(),
+            source_event_repository: SourceEventPostgresRepository::new(),
+            fulltext_shard_repository: FulltextShardPostgresRepository::new(),
+            user_repository: UserPostgresRepository::new(),
+            user_storage_usage_repository: UserStorageUsagePostgresRepository::new(),
+        }
+    }
+
+    /// Deletes a source of a user, recording its deletion with a given event
+    ///
+    /// # Returns
+    /// False if the user has no such source
+    pub(crate) async fn delete(
+        &self,
+        pool: &PgPool,
+        message_repositories: &TenantMessageRepositories,
+        user_id: Uuid,
+        source_id: Uuid,
+        deleted_event: impl FnOnce(&SourceMeta) -> SourceEvent,
+    ) -> Result<bool, anyhow::Error> {
+        let mut transaction = pool
+            .begin()
+            .await
+            .context("Failed to acquire a Postgres connection from the pool")?;
+
+        // The route of the source is deleted with it: the shard of its contents is kept to delete them
+        let fulltext_shard = self
+            .fulltext_shard_repository
+            .get_source_shard(&mut transaction, source_id)
+            .await
+            .context(format!(
+                "Could not get the full-text shard of the source {}",
+                source_id
+            ))?;
+
+        // The extraction progress of the source is deleted with it, its events are kept
+        let source_meta = self
+            .source_meta_repository
+            .delete_user_source_meta(&mut transaction, user_id, source_id)
+            .await
+            .context(format!("Could not delete the source {}", source_id))?;
+        let Some(source_meta) = source_meta else {
+            return Ok(false);
+        };
+        self.source_event_repository
+            .add_event(&mut transaction, &deleted_event(&source_meta))
+            .await
+            .context(format!(
+                "Could not record the deletion of the source {}",
+                source_id
+            ))?;
+        // The sources registered before the storage usage was tracked have no size
+        if let Some(size_bytes) = source_meta.size_bytes {
+            self.user_storage_usage_repository
+                .remove_usage(&mut transaction, user_id, size_bytes as u64)
+                .await
+                .context(format!(
+                    "Could not remove the source {} from the storage used by the user",
+                    source_id
+                ))?;
+        }
+
+        // Removes the file before committing, so a failure keeps the source meta
+        let object_path_name = format!("{}/{}", user_id, source_meta.object_store_name);
+        match self.s3_repository.remove_file(&object_path_name).await {
+            Ok(()) => (),
+            Err(S3RepositoryError::ObjectNotFound(_)) => {
+                warn!("The file of the source {} was already removed", source_id);
+            }
+            Err(error) => {
+                return Err(anyhow::Error::new(error).context(format!(
+                    "The file {} could not be removed from the object storage",
+                    object_path_name
+                )))
+            }
+        }
+
+        transaction.commit().await.context(format!(
+            "Failed to commit SQL transaction to delete the source {}",
+            source_id
+        ))?;
+
+        // The manifest of the extracted contents is only read by the next extraction of the source
+        let manifest_path = chunk_manifest_path(user_id, source_id);
+        match self.s3_repository.remove_file(&manifest_path).await {
+            Ok(()) | Err(S3RepositoryError::ObjectNotFound(_)) => (),
+            Err(error) => {
+                warn!(
+                    ?error,
+                    "The manifest {} could not be removed from the object storage", manifest_path
+                );
+            }
+        }
+
+        // The extracted contents are on the exchanges of the tenant of the user
+        let tenant_id = self
+            .user_repository
+            .get_user_tenant_id(pool, user_id)
+            .await
+            .context("Could not get the tenant of the user")?;
+        let message_rabbitmq_repository = message_repositories
+            .route(tenant_id.as_deref())
+            .context("Could not route the messages of the user")?;
+
+        let json_message = serde_json::to_string(&DeleteContentDto {
+            source_meta_id: source_id,
+            fulltext_shard,
+        })
+        .context("Could not serialize the deletion of the contents")?;
+
+        message_rabbitmq_repository
+            .publish(DELETE_CONTENT_ROUTING_KEY, json_message.as_bytes())
+            .await
+            .context(format!(
+                "Could not send the deletion of the contents of the source {}",
+                source_id
+            ))?;
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,156 @@
+use crate::configuration::SourceDownloadsSettings;
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::source_file_s3_repository::{S3Repository, S3RepositoryError};
+use crate::repositories::source_meta_postgres_repository::SourceMetaPostgresRepository;
+use actix_web::http::header::{ContentDisposition, CONTENT_TYPE};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use common::helper::error_chain_fmt;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+/// MIME type of the downloaded sources whose content was not detected when they were uploaded
+const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+
+#[derive(thiserror::Error)]
+pub enum DownloadSourceError {
+    #[error("Source {0} not found")]
+    SourceNotFound(Uuid),
+    #[error("The file of the source {0} is not in the object storage")]
+    SourceFileNotFound(Uuid),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for DownloadSourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for DownloadSourceError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            DownloadSourceError::SourceNotFound(_) | DownloadSourceError::SourceFileNotFound(_) => {
+                StatusCode::NOT_FOUND
+            }
+            DownloadSourceError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DownloadSourceQuery {
+    /// Returns a pre-signed URL of the object storage, rather than streaming the file through the gateway
+    #[serde(default)]
+    pub presigned: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct DownloadSourceUrlResponse {
+    /// URL downloading the file directly from the object storage, without authentication
+    pub url: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Download the original file of a user source, with its initial name
+///
+/// The file is streamed through the gateway, or downloaded directly from the object storage
+/// with a pre-signed URL expiring after `presigned_url_expiry_s`.
+#[utoipa::path(
+    get,
+    path = "/sources/{source_id}/download",
+    tag = "sources",
+    params(
+        ("source_id" = Uuid, Path, description = "ID of the source"),
+        DownloadSourceQuery
+    ),
+    responses(
+        (
+            status = 200,
+            description = "File of the source, or its pre-signed URL",
+            content(
+                ("application/octet-stream" = String),
+                ("application/json" = DownloadSourceUrlResponse)
+            )
+        ),
+        (status = 404, description = "Source or its file not found"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "Download source",
+    skip(pool, s3_repository, source_meta_repository, source_downloads),
+    err
+)]
+pub async fn download_source(
+    source_id: web::Path<Uuid>,
+    query: web::Query<DownloadSourceQuery>,
+    pool: web::Data<PgPool>,
+    s3_repository: web::Data<S3Repository>,
+    source_meta_repository: web::Data<SourceMetaPostgresRepository>,
+    source_downloads: web::Data<SourceDownloadsSettings>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, DownloadSourceError> {
+    let user_id = user_id.into_inner().0;
+    let source_id = source_id.into_inner();
+    info!("Request for user_id: {}", user_id);
+
+    // The source of another user is not found, rather than forbidden, to not disclose it
+    let source_meta = source_meta_repository
+        .get_source_meta(pool.get_ref(), source_id)
+        .await
+        .context("Could not get the source")?
+        .filter(|source_meta| source_meta.user_id == user_id)
+        .ok_or(DownloadSourceError::SourceNotFound(source_id))?;
+
+    let object_path_name = format!("{}/{}", user_id, source_meta.object_store_name);
+    let content_disposition = ContentDisposition::attachment(source_meta.initial_name);
+
+    if query.presigned {
+        let expiry_s = source_downloads.presigned_url_expiry_s;
+        let url = s3_repository
+            .presign_file_download(&object_path_name, content_disposition.to_string(), expiry_s)
+            .context("Could not pre-sign the download of the source")?;
+
+        return Ok(HttpResponse::Ok().json(DownloadSourceUrlResponse {
+            url,
+            expires_at: Utc::now() + Duration::seconds(expiry_s.into()),
+        }));
+    }
+
+    let file_stream = s3_repository
+        .get_file_stream(&object_path_name)
+        .await
+        .map_err(|error| match error {
+            S3RepositoryError::ObjectNotFound(_) => {
+                DownloadSourceError::SourceFileNotFound(source_id)
+            }
+            error => DownloadSourceError::UnexpectedError(
+                anyhow::Error::new(error).context("Could not get the file of the source"),
+            ),
+        })?;
+
+    let mut response = HttpResponse::Ok();
+    response
+        .insert_header((
+            CONTENT_TYPE,
+            source_meta
+                .detected_mime_type
+                .unwrap_or_else(|| DEFAULT_MIME_TYPE.to_string()),
+        ))
+        .insert_header(content_disposition);
+    // The sources added before their size was saved are sent in chunks
+    if let Some(size_bytes) = source_meta.size_bytes {
+        response.no_chunking(size_bytes as u64);
+    }
+
+    Ok(response.streaming(file_stream.map(Ok::<_, actix_web::Error>)))
+}
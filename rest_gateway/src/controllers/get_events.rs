@@ -0,0 +1,131 @@
+use crate::configuration::ActivityStreamSettings;
+use crate::controllers::{GetJobResponse, GetSourceProgressResponse};
+use crate::domain::entities::user_activity::UserActivity;
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::{
+    user_activity_rabbitmq_repository::{
+        UserActivityRabbitMQRepository, UserActivityRabbitMQRepositoryError,
+    },
+    user_postgres_repository::{UserPostgresRepository, UserPostgresRepositoryError},
+};
+use crate::responders::sse::{sse_response, SseEvent};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use common::{
+    core::tenancy::{TenancyError, TenantMessageRepositories},
+    helper::error_chain_fmt,
+};
+use futures::{future, StreamExt};
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::{info, warn};
+
+#[derive(thiserror::Error)]
+pub enum GetEventsError {
+    #[error(transparent)]
+    UserRepositoryError(#[from] UserPostgresRepositoryError),
+    #[error(transparent)]
+    TenancyError(#[from] TenancyError),
+    #[error(transparent)]
+    UserActivityRepositoryError(#[from] UserActivityRabbitMQRepositoryError),
+}
+
+impl std::fmt::Debug for GetEventsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for GetEventsError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            GetEventsError::UserRepositoryError(_)
+            | GetEventsError::TenancyError(_)
+            | GetEventsError::UserActivityRepositoryError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Event streamed to the client for an activity of the user
+///
+/// Its data is the response of the endpoint polled for the same information:
+/// `extraction_progress` as `GET /sources/{source_id}/progress` and `job_status` as `GET /jobs/{job_id}`.
+fn activity_event(activity: UserActivity) -> Result<SseEvent, serde_json::Error> {
+    match activity {
+        UserActivity::ExtractionProgress(progress) => SseEvent::json(
+            "extraction_progress",
+            &GetSourceProgressResponse::from(progress),
+        ),
+        UserActivity::JobUpdated(job) => SseEvent::json("job_status", &GetJobResponse::from(job)),
+    }
+}
+
+/// Streams the ingestion activity of the user as Server-Sent Events, from the connection on
+///
+/// The progress of the extractions and the status of the ingestion jobs of the sources of the user
+/// are sent as they are received, from any instance of the gateway.
+/// The activity happening while the client is disconnected is not sent again on reconnection.
+#[utoipa::path(
+    get,
+    path = "/events",
+    tag = "events",
+    responses(
+        (
+            status = 200,
+            description = "Stream of `extraction_progress` and `job_status` events, their data being \
+                a `GetSourceProgressResponse` and a `GetJobResponse`",
+            body = String,
+            content_type = "text/event-stream"
+        ),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "Get events",
+    skip(
+        pool,
+        user_repository,
+        user_activity_repository,
+        message_repositories,
+        activity_stream
+    ),
+    err
+)]
+pub async fn get_events(
+    pool: web::Data<PgPool>,
+    user_repository: web::Data<UserPostgresRepository>,
+    user_activity_repository: web::Data<UserActivityRabbitMQRepository>,
+    message_repositories: web::Data<TenantMessageRepositories>,
+    activity_stream: web::Data<ActivityStreamSettings>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, GetEventsError> {
+    let user_id = user_id.into_inner().0;
+    info!("Request for user_id: {}", user_id);
+
+    // The activity is published on the exchange of the tenant of the user
+    let tenant_id = user_repository
+        .get_user_tenant_id(pool.get_ref(), user_id)
+        .await?;
+    let message_repository = message_repositories.route(tenant_id.as_deref())?;
+
+    let activities = user_activity_repository
+        .subscribe(message_repository, user_id)
+        .await?;
+
+    let events = activities.filter_map(|activity| {
+        let event = match activity_event(activity) {
+            Ok(event) => Some(event),
+            Err(error) => {
+                warn!(?error, "Skipping an activity that could not be serialized");
+                None
+            }
+        };
+
+        future::ready(event)
+    });
+
+    Ok(sse_response(
+        events,
+        Duration::from_secs(activity_stream.keep_alive_interval_s),
+    ))
+}
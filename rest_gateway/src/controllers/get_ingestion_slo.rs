@@ -0,0 +1,135 @@
+use crate::configuration::AdminSettings;
+use crate::domain::entities::ingestion_job::{IngestionStage, JobStatus};
+use crate::domain::entities::latency_summary::LatencySummary;
+use crate::repositories::ingestion_job_postgres_repository::IngestionJobPostgresRepository;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use common::helper::error_chain_fmt;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use utoipa::{IntoParams, ToSchema};
+
+/// Default time window of the SLO data
+const DEFAULT_WINDOW_H: u32 = 24;
+/// The jobs of the window are loaded in memory
+const MAX_WINDOW_H: u32 = 24 * 7;
+
+#[derive(thiserror::Error)]
+pub enum GetIngestionSloError {
+    #[error("The window should be between 1 and {MAX_WINDOW_H} hours")]
+    InvalidWindow,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for GetIngestionSloError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for GetIngestionSloError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            GetIngestionSloError::InvalidWindow => StatusCode::BAD_REQUEST,
+            GetIngestionSloError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GetIngestionSloQuery {
+    /// Number of hours before now during which the jobs were queued
+    pub window_h: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct GetIngestionSloResponse {
+    pub since: DateTime<Utc>,
+    pub nb_jobs: usize,
+    pub nb_failed_jobs: usize,
+    /// Latency of each stage, over the jobs which completed it
+    pub stages: BTreeMap<IngestionStage, LatencySummary>,
+    /// Time for the sources to be searchable, over the searchable sources
+    pub time_to_searchable: Option<LatencySummary>,
+    pub time_to_searchable_target_s: u64,
+    /// Ratio of the searchable sources within the target
+    pub ratio_within_target: Option<f64>,
+}
+
+/// Get the latency of the stages of the ingestion jobs queued during a time window,
+/// to find which stage is responsible when the time for the sources to be searchable regresses
+#[utoipa::path(
+    get,
+    path = "/admin/ingestion_slo",
+    tag = "admin",
+    params(GetIngestionSloQuery),
+    responses(
+        (status = 200, description = "Latency of the ingestion stages", body = GetIngestionSloResponse),
+        (status = 400, description = "Invalid time window"),
+    ),
+    security(("admin_token" = []))
+)]
+#[tracing::instrument(
+    name = "Get ingestion SLO",
+    skip(pool, ingestion_job_repository, admin_settings),
+    err
+)]
+pub async fn get_ingestion_slo(
+    query: web::Query<GetIngestionSloQuery>,
+    pool: web::Data<PgPool>,
+    ingestion_job_repository: web::Data<IngestionJobPostgresRepository>,
+    admin_settings: web::Data<AdminSettings>,
+) -> Result<HttpResponse, GetIngestionSloError> {
+    let window_h = query.window_h.unwrap_or(DEFAULT_WINDOW_H);
+    if window_h == 0 || window_h > MAX_WINDOW_H {
+        return Err(GetIngestionSloError::InvalidWindow);
+    }
+    let since = Utc::now() - Duration::hours(window_h.into());
+
+    let jobs = ingestion_job_repository
+        .list_jobs_queued_since(pool.get_ref(), since)
+        .await
+        .context("Could not list the ingestion jobs")?;
+
+    let mut stage_durations: BTreeMap<IngestionStage, Vec<Duration>> = BTreeMap::new();
+    for (stage, duration) in jobs.iter().flat_map(|job| job.stage_durations()) {
+        stage_durations.entry(stage).or_default().push(duration);
+    }
+    let stages = stage_durations
+        .into_iter()
+        .filter_map(|(stage, durations)| {
+            LatencySummary::from_durations(durations).map(|summary| (stage, summary))
+        })
+        .collect();
+
+    let times_to_searchable: Vec<Duration> = jobs
+        .iter()
+        .filter_map(|job| job.time_to_searchable())
+        .collect();
+    let target = Duration::seconds(admin_settings.time_to_searchable_target_s as i64);
+    let ratio_within_target = (!times_to_searchable.is_empty()).then(|| {
+        let nb_within_target = times_to_searchable
+            .iter()
+            .filter(|duration| **duration <= target)
+            .count();
+        nb_within_target as f64 / times_to_searchable.len() as f64
+    });
+
+    Ok(HttpResponse::Ok().json(GetIngestionSloResponse {
+        since,
+        nb_jobs: jobs.len(),
+        nb_failed_jobs: jobs
+            .iter()
+            .filter(|job| job.status == JobStatus::Failed)
+            .count(),
+        stages,
+        time_to_searchable: LatencySummary::from_durations(times_to_searchable),
+        time_to_searchable_target_s: admin_settings.time_to_searchable_target_s,
+        ratio_within_target,
+    }))
+}
@@ -0,0 +1,130 @@
+use crate::domain::entities::ingestion_job::{
+    IngestionErrorCode, IngestionJob, IngestionLane, IngestionStage, JobStatus, SkippedItem,
+};
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::ingestion_job_postgres_repository::IngestionJobPostgresRepository;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use common::helper::error_chain_fmt;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(thiserror::Error)]
+pub enum GetJobError {
+    #[error("Job {0} not found")]
+    JobNotFound(Uuid),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for GetJobError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for GetJobError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            GetJobError::JobNotFound(_) => StatusCode::NOT_FOUND,
+            GetJobError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct GetJobResponse {
+    pub id: Uuid,
+    pub source_id: Uuid,
+    pub status: JobStatus,
+    /// Lane through which the source is ingested
+    pub lane: IngestionLane,
+    /// Number of contents extracted from the source, once the extraction is completed
+    pub nb_contents: Option<i64>,
+    /// Number of contents that went through the embedding
+    pub nb_embedded_contents: i64,
+    /// Reason of the failure of the job
+    pub error: Option<String>,
+    /// Stable code of the reason of the failure, to handle it programmatically
+    pub error_code: Option<IngestionErrorCode>,
+    /// What the user can do to ingest the source after the failure
+    pub error_hint: Option<String>,
+    /// Items of the source skipped by the extraction, with their errors, when the job completed with warnings
+    pub skipped_items: Vec<SkippedItem>,
+    /// Duration of the completed stages of the job
+    pub stage_durations_ms: BTreeMap<IngestionStage, i64>,
+    /// Time for the source to be searchable, once all its contents are indexed and embedded
+    pub time_to_searchable_ms: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<IngestionJob> for GetJobResponse {
+    fn from(value: IngestionJob) -> Self {
+        let stage_durations_ms = value
+            .stage_durations()
+            .into_iter()
+            .map(|(stage, duration)| (stage, duration.num_milliseconds()))
+            .collect();
+        let time_to_searchable_ms = value
+            .time_to_searchable()
+            .map(|duration| duration.num_milliseconds());
+
+        Self {
+            id: value.id,
+            source_id: value.source_meta_id,
+            status: value.status,
+            lane: value.lane,
+            nb_contents: value.nb_contents,
+            nb_embedded_contents: value.nb_embedded_contents,
+            error: value.error,
+            error_code: value.error_code,
+            error_hint: value
+                .error_code
+                .map(|error_code| error_code.hint().to_string()),
+            skipped_items: value.skipped_items.0,
+            stage_durations_ms,
+            time_to_searchable_ms,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+/// Get the status of the ingestion job of a user source
+#[utoipa::path(
+    get,
+    path = "/jobs/{job_id}",
+    tag = "jobs",
+    params(("job_id" = Uuid, Path, description = "ID of the ingestion job")),
+    responses(
+        (status = 200, description = "Status of the ingestion job", body = GetJobResponse),
+        (status = 404, description = "Ingestion job not found"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(name = "Get job", skip(pool, ingestion_job_repository), err)]
+pub async fn get_job(
+    job_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    ingestion_job_repository: web::Data<IngestionJobPostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, GetJobError> {
+    let user_id = user_id.into_inner().0;
+    let job_id = job_id.into_inner();
+    info!("Request for user_id: {}", user_id);
+
+    let job = ingestion_job_repository
+        .get_user_job(pool.get_ref(), user_id, job_id)
+        .await
+        .context("Could not get the ingestion job of the user")?
+        .ok_or(GetJobError::JobNotFound(job_id))?;
+
+    Ok(HttpResponse::Ok().json(GetJobResponse::from(job)))
+}
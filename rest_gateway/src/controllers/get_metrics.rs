@@ -0,0 +1,27 @@
+use crate::metrics::IngestionMetrics;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+
+/// Expose the metrics of the ingestion in the Prometheus text format
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Metrics of the ingestion", body = String, content_type = "text/plain; version=0.0.4"),
+    ),
+    security(("admin_token" = []))
+)]
+#[tracing::instrument(name = "Get metrics", skip(ingestion_metrics))]
+pub async fn get_metrics(
+    ingestion_metrics: web::Data<IngestionMetrics>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let encoded = ingestion_metrics
+        .encode()
+        .context("Failed to encode the metrics")
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(encoded))
+}
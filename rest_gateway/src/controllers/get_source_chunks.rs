@@ -0,0 +1,216 @@
+use crate::controllers::search_content::SearchServices;
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::{
+    fulltext_shard_postgres_repository::FulltextShardPostgresRepositoryError,
+    source_meta_postgres_repository::SourceMetaPostgresRepositoryError,
+    user_postgres_repository::UserPostgresRepositoryError,
+};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use common::{
+    constants::routing_keys::GET_SOURCE_CHUNKS_ROUTING_KEY,
+    core::{
+        rabbitmq_message_repository::RabbitMQMessageRepositoryError,
+        tenancy::{TenancyError, TenantMessageRepositories},
+    },
+    dtos::{
+        source_chunks_request::{SourceChunksRequestDto, SourceChunksRequestDtoError},
+        source_chunks_response::SourceChunksResponseDto,
+        templates::rpc_response::{RpcErrorStatus, RpcResponse, RpcResponseEncodingError},
+    },
+    helper::error_chain_fmt,
+    pagination::{PageLimits, PaginationError},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::PgPool;
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+const PAGE_LIMITS: PageLimits = PageLimits::new(20, 100);
+
+#[derive(thiserror::Error)]
+pub enum GetSourceChunksError {
+    #[error("Source {0} not found")]
+    SourceNotFound(Uuid),
+    #[error("Invalid page {0}: pages start at 1")]
+    InvalidPage(u32),
+    #[error(transparent)]
+    PaginationError(#[from] PaginationError),
+    #[error(transparent)]
+    SourceMetaRepositoryError(#[from] SourceMetaPostgresRepositoryError),
+    #[error(transparent)]
+    FulltextShardRepositoryError(#[from] FulltextShardPostgresRepositoryError),
+    #[error(transparent)]
+    UserRepositoryError(#[from] UserPostgresRepositoryError),
+    #[error(transparent)]
+    TenancyError(#[from] TenancyError),
+    #[error("Error while generating the source chunks internal request: {0}")]
+    SourceChunksRequestError(#[from] SourceChunksRequestDtoError),
+    #[error("Error while publishing messages on RabbitMQ broker: {0}")]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+    #[error("Error while parsing response: {0}")]
+    RpcResponseEncodingError(#[from] RpcResponseEncodingError),
+    #[error("Listing the chunks of the source failed: {1}")]
+    SourceChunksError(RpcErrorStatus, String),
+}
+
+impl std::fmt::Debug for GetSourceChunksError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for GetSourceChunksError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            GetSourceChunksError::SourceNotFound(_) => StatusCode::NOT_FOUND,
+            GetSourceChunksError::InvalidPage(_) | GetSourceChunksError::PaginationError(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            GetSourceChunksError::RabbitMQMessageRepositoryError(
+                RabbitMQMessageRepositoryError::RpcTimeout { .. },
+            ) => StatusCode::GATEWAY_TIMEOUT,
+            GetSourceChunksError::SourceChunksError(status, _) => match status {
+                RpcErrorStatus::BadRequest => StatusCode::BAD_REQUEST,
+                RpcErrorStatus::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            GetSourceChunksError::SourceMetaRepositoryError(_)
+            | GetSourceChunksError::FulltextShardRepositoryError(_)
+            | GetSourceChunksError::UserRepositoryError(_)
+            | GetSourceChunksError::TenancyError(_)
+            | GetSourceChunksError::SourceChunksRequestError(_)
+            | GetSourceChunksError::RabbitMQMessageRepositoryError(_)
+            | GetSourceChunksError::RpcResponseEncodingError(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GetSourceChunksQuery {
+    /// Starts at 1
+    pub page: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct SourceChunkResponse {
+    pub id: Uuid,
+    pub content: String,
+    #[schema(value_type = Object)]
+    pub metadata: JsonValue,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct GetSourceChunksResponse {
+    pub source_id: Uuid,
+    pub chunks: Vec<SourceChunkResponse>,
+    pub page: u32,
+    pub total_chunks: u64,
+    pub total_pages: u64,
+}
+
+/// Get a page of the chunks extracted from a user source, as they were indexed for the full-text search
+///
+/// A source whose extraction is still in progress only has the chunks indexed so far.
+#[utoipa::path(
+    get,
+    path = "/sources/{source_id}/chunks",
+    tag = "sources",
+    params(
+        ("source_id" = Uuid, Path, description = "ID of the source"),
+        GetSourceChunksQuery
+    ),
+    responses(
+        (status = 200, description = "Page of the chunks of the source", body = GetSourceChunksResponse),
+        (status = 400, description = "Invalid page or limit"),
+        (status = 404, description = "Source not found"),
+        (status = 504, description = "The full-text search service did not answer in time"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "Get source chunks",
+    skip(pool, search_services, message_repositories),
+    err
+)]
+pub async fn get_source_chunks(
+    source_id: web::Path<Uuid>,
+    query: web::Query<GetSourceChunksQuery>,
+    pool: web::Data<PgPool>,
+    search_services: web::Data<SearchServices>,
+    message_repositories: web::Data<TenantMessageRepositories>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, GetSourceChunksError> {
+    let user_id = user_id.into_inner().0;
+    let source_id = source_id.into_inner();
+    let SearchServices {
+        user_repository,
+        fulltext_shard_repository,
+        source_meta_repository,
+        ..
+    } = search_services.get_ref();
+    info!("Request for user_id: {}", user_id);
+
+    let page = query.page.unwrap_or(1);
+    if page == 0 {
+        return Err(GetSourceChunksError::InvalidPage(page));
+    }
+    let limit = PAGE_LIMITS.validate(query.limit)?;
+
+    if !source_meta_repository
+        .is_user_source_meta(pool.get_ref(), user_id, source_id)
+        .await?
+    {
+        return Err(GetSourceChunksError::SourceNotFound(source_id));
+    }
+
+    let shard = fulltext_shard_repository
+        .get_source_shard(pool.get_ref(), source_id)
+        .await?;
+    let tenant_id = user_repository
+        .get_user_tenant_id(pool.get_ref(), user_id)
+        .await?;
+    let message_rabbitmq_repository = message_repositories.route(tenant_id.as_deref())?;
+
+    let request = SourceChunksRequestDto {
+        source_meta_id: source_id,
+        user_id,
+        shard,
+        offset: (page as usize - 1) * limit as usize,
+        limit: limit as usize,
+    };
+    let request = request.try_serializing()?;
+
+    let response = message_rabbitmq_repository
+        .rpc_call(GET_SOURCE_CHUNKS_ROUTING_KEY, request.as_bytes(), None)
+        .await?;
+
+    let data = match SourceChunksResponseDto::try_parsing(&response)? {
+        RpcResponse::Ok { data } => data,
+        RpcResponse::Error { status, message } => {
+            return Err(GetSourceChunksError::SourceChunksError(status, message))
+        }
+    };
+
+    let total_chunks = data.total as u64;
+    Ok(HttpResponse::Ok().json(GetSourceChunksResponse {
+        source_id,
+        chunks: data
+            .chunks
+            .into_iter()
+            .map(|chunk| SourceChunkResponse {
+                id: chunk.id,
+                content: chunk.content,
+                metadata: chunk.metadata,
+            })
+            .collect(),
+        page,
+        total_chunks,
+        total_pages: total_chunks.div_ceil(limit as u64),
+    }))
+}
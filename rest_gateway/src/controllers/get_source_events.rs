@@ -0,0 +1,114 @@
+use crate::domain::entities::source_event::{SourceEvent, SourceEventType};
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::source_event_postgres_repository::SourceEventPostgresRepository;
+use crate::repositories::source_meta_postgres_repository::SourceMetaPostgresRepository;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use common::helper::error_chain_fmt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::PgPool;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(thiserror::Error)]
+pub enum GetSourceEventsError {
+    #[error("Source {0} not found")]
+    SourceNotFound(Uuid),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for GetSourceEventsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for GetSourceEventsError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            GetSourceEventsError::SourceNotFound(_) => StatusCode::NOT_FOUND,
+            GetSourceEventsError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct SourceEventResponse {
+    pub id: Uuid,
+    pub event_type: SourceEventType,
+    /// Snapshot of the source or of its ingestion job when the event occurred
+    #[schema(value_type = Object)]
+    pub payload: JsonValue,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl From<SourceEvent> for SourceEventResponse {
+    fn from(value: SourceEvent) -> Self {
+        Self {
+            id: value.id,
+            event_type: value.event_type,
+            payload: value.payload,
+            occurred_at: value.occurred_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct GetSourceEventsResponse {
+    pub events: Vec<SourceEventResponse>,
+}
+
+/// Get what happened to a user source, from its upload, even once deleted
+///
+/// The sources added before the events were recorded have no events.
+#[utoipa::path(
+    get,
+    path = "/sources/{source_id}/events",
+    tag = "sources",
+    params(("source_id" = Uuid, Path, description = "ID of the source")),
+    responses(
+        (status = 200, description = "Events of the source, from the oldest", body = GetSourceEventsResponse),
+        (status = 404, description = "Source not found"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "Get source events",
+    skip(pool, source_event_repository, source_meta_repository),
+    err
+)]
+pub async fn get_source_events(
+    source_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    source_event_repository: web::Data<SourceEventPostgresRepository>,
+    source_meta_repository: web::Data<SourceMetaPostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, GetSourceEventsError> {
+    let user_id = user_id.into_inner().0;
+    let source_id = source_id.into_inner();
+    info!("Request for user_id: {}", user_id);
+
+    let events = source_event_repository
+        .list_user_source_events(pool.get_ref(), user_id, source_id)
+        .await
+        .context("Could not list the events of the source")?;
+
+    // The events are kept once the source is deleted: a source without events may not exist
+    if events.is_empty()
+        && !source_meta_repository
+            .is_user_source_meta(pool.get_ref(), user_id, source_id)
+            .await
+            .context("Could not check the source of the user")?
+    {
+        return Err(GetSourceEventsError::SourceNotFound(source_id));
+    }
+
+    Ok(HttpResponse::Ok().json(GetSourceEventsResponse {
+        events: events.into_iter().map(Into::into).collect(),
+    }))
+}
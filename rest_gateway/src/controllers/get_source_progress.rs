@@ -0,0 +1,146 @@
+use crate::domain::entities::extraction_progress::{ExtractionProgress, ExtractionStatus};
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::extraction_progress_postgres_repository::ExtractionProgressPostgresRepository;
+use crate::repositories::source_meta_postgres_repository::SourceMetaPostgresRepository;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use common::helper::error_chain_fmt;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(thiserror::Error)]
+pub enum GetSourceProgressError {
+    #[error("Source {0} not found")]
+    SourceNotFound(Uuid),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for GetSourceProgressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for GetSourceProgressError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            GetSourceProgressError::SourceNotFound(_) => StatusCode::NOT_FOUND,
+            GetSourceProgressError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceProgressStatus {
+    /// The extraction of the source has not started yet
+    Pending,
+    InProgress,
+    Completed,
+    Failed,
+}
+
+impl From<Option<ExtractionStatus>> for SourceProgressStatus {
+    fn from(value: Option<ExtractionStatus>) -> Self {
+        match value {
+            None => SourceProgressStatus::Pending,
+            Some(ExtractionStatus::InProgress) => SourceProgressStatus::InProgress,
+            Some(ExtractionStatus::Completed) => SourceProgressStatus::Completed,
+            Some(ExtractionStatus::Failed) => SourceProgressStatus::Failed,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct GetSourceProgressResponse {
+    pub source_id: Uuid,
+    pub status: SourceProgressStatus,
+    /// Number of chunks of content extracted so far
+    pub chunk_index: i64,
+    /// Estimated total number of chunks. Exact once the extraction is completed.
+    pub total_estimated_chunks: i64,
+    /// Number of bytes of text extracted so far
+    pub bytes_processed: i64,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl GetSourceProgressResponse {
+    fn pending(source_id: Uuid) -> Self {
+        Self {
+            source_id,
+            status: SourceProgressStatus::Pending,
+            chunk_index: 0,
+            total_estimated_chunks: 0,
+            bytes_processed: 0,
+            updated_at: None,
+        }
+    }
+}
+
+impl From<ExtractionProgress> for GetSourceProgressResponse {
+    fn from(value: ExtractionProgress) -> Self {
+        Self {
+            source_id: value.source_meta_id,
+            status: Some(value.status).into(),
+            chunk_index: value.chunk_index,
+            total_estimated_chunks: value.total_estimated_chunks,
+            bytes_processed: value.bytes_processed,
+            updated_at: Some(value.updated_at),
+        }
+    }
+}
+
+/// Get the latest progress of the content extraction of a user source
+#[utoipa::path(
+    get,
+    path = "/sources/{source_id}/progress",
+    tag = "sources",
+    params(("source_id" = Uuid, Path, description = "ID of the source")),
+    responses(
+        (status = 200, description = "Progress of the extraction of the source", body = GetSourceProgressResponse),
+        (status = 404, description = "Source not found"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "Get source progress",
+    skip(pool, source_meta_repository, extraction_progress_repository),
+    err
+)]
+pub async fn get_source_progress(
+    source_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    source_meta_repository: web::Data<SourceMetaPostgresRepository>,
+    extraction_progress_repository: web::Data<ExtractionProgressPostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, GetSourceProgressError> {
+    let user_id = user_id.into_inner().0;
+    let source_id = source_id.into_inner();
+    info!("Request for user_id: {}", user_id);
+
+    let is_user_source = source_meta_repository
+        .is_user_source_meta(pool.get_ref(), user_id, source_id)
+        .await
+        .context("Could not check the source of the user")?;
+    if !is_user_source {
+        return Err(GetSourceProgressError::SourceNotFound(source_id));
+    }
+
+    let progress = extraction_progress_repository
+        .get_extraction_progress(pool.get_ref(), source_id)
+        .await
+        .context("Could not get the extraction progress of the source")?;
+
+    let response = match progress {
+        Some(progress) => progress.into(),
+        None => GetSourceProgressResponse::pending(source_id),
+    };
+
+    Ok(HttpResponse::Ok().json(response))
+}
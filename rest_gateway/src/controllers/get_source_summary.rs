@@ -0,0 +1,110 @@
+use crate::domain::entities::source_summary::{ChapterSummary, SourceSummary};
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::source_meta_postgres_repository::SourceMetaPostgresRepository;
+use crate::repositories::source_summary_postgres_repository::SourceSummaryPostgresRepository;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use common::helper::error_chain_fmt;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(thiserror::Error)]
+pub enum GetSourceSummaryError {
+    #[error("Source {0} not found")]
+    SourceNotFound(Uuid),
+    #[error("Source {0} has not been summarized yet")]
+    SummaryNotFound(Uuid),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for GetSourceSummaryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for GetSourceSummaryError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            GetSourceSummaryError::SourceNotFound(_) => StatusCode::NOT_FOUND,
+            GetSourceSummaryError::SummaryNotFound(_) => StatusCode::NOT_FOUND,
+            GetSourceSummaryError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct GetSourceSummaryResponse {
+    pub source_id: Uuid,
+    /// Summary of the whole source
+    pub summary: String,
+    /// Summaries of the chapters of the source, in the order of the source.
+    /// Empty for the sources without chapters.
+    pub chapters: Vec<ChapterSummary>,
+    /// Model which generated the summaries
+    pub model: String,
+    pub generated_at: DateTime<Utc>,
+}
+
+impl From<SourceSummary> for GetSourceSummaryResponse {
+    fn from(value: SourceSummary) -> Self {
+        Self {
+            source_id: value.source_meta_id,
+            summary: value.summary,
+            chapters: value.chapters.0,
+            model: value.model,
+            generated_at: value.generated_at,
+        }
+    }
+}
+
+/// Get the summaries of a user source and of its chapters, generated once its extraction is completed
+#[utoipa::path(
+    get,
+    path = "/sources/{source_id}/summary",
+    tag = "sources",
+    params(("source_id" = Uuid, Path, description = "ID of the source")),
+    responses(
+        (status = 200, description = "Summaries of the source", body = GetSourceSummaryResponse),
+        (status = 404, description = "Source not found, or not summarized yet"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "Get source summary",
+    skip(pool, source_meta_repository, source_summary_repository),
+    err
+)]
+pub async fn get_source_summary(
+    source_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    source_meta_repository: web::Data<SourceMetaPostgresRepository>,
+    source_summary_repository: web::Data<SourceSummaryPostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, GetSourceSummaryError> {
+    let user_id = user_id.into_inner().0;
+    let source_id = source_id.into_inner();
+    info!("Request for user_id: {}", user_id);
+
+    let is_user_source = source_meta_repository
+        .is_user_source_meta(pool.get_ref(), user_id, source_id)
+        .await
+        .context("Could not check the source of the user")?;
+    if !is_user_source {
+        return Err(GetSourceSummaryError::SourceNotFound(source_id));
+    }
+
+    let source_summary = source_summary_repository
+        .get_source_summary(pool.get_ref(), source_id)
+        .await
+        .context("Could not get the summary of the source")?
+        .ok_or(GetSourceSummaryError::SummaryNotFound(source_id))?;
+
+    Ok(HttpResponse::Ok().json(GetSourceSummaryResponse::from(source_summary)))
+}
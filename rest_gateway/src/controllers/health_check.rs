@@ -1,6 +1,35 @@
-use actix_web::HttpResponse;
+use actix_web::{web, HttpResponse};
+use common::core::health_server::{HealthChecks, Probe};
+use tracing::warn;
 
+#[utoipa::path(
+    get,
+    path = "/health_check",
+    tag = "health",
+    responses((status = 200, description = "The gateway is up"))
+)]
 #[tracing::instrument(name = "Health check handler")]
 pub async fn health_check() -> HttpResponse {
     HttpResponse::Ok().finish()
 }
+
+/// Readiness probe: the gateway should not be sent traffic while its dependencies (the database pool) are unavailable
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    tag = "health",
+    responses(
+        (status = 200, description = "The gateway is ready, with the statuses of its dependencies"),
+        (status = 503, description = "A dependency of the gateway is unavailable"),
+    )
+)]
+#[tracing::instrument(name = "Readiness handler", skip(health_checks))]
+pub async fn readiness(health_checks: web::Data<HealthChecks>) -> HttpResponse {
+    let report = health_checks.report(Probe::Readiness).await;
+    if !report.is_healthy {
+        warn!(?report, "Failing readiness");
+        return HttpResponse::ServiceUnavailable().json(report);
+    }
+
+    HttpResponse::Ok().json(report)
+}
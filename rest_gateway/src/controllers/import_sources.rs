@@ -0,0 +1,178 @@
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use common::constants::routing_keys::IMPORT_SOURCE_ROUTING_KEY;
+use common::core::tenancy::TenantMessageRepositories;
+use common::dtos::import_source::ImportSourceDto;
+use common::helper::error_chain_fmt;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::str::FromStr;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::entities::source_meta::SourceType;
+use crate::repositories::import_s3_repository::{ImportS3Repository, ImportS3RepositoryError};
+
+#[derive(thiserror::Error)]
+pub enum ImportSourcesError {
+    #[error("Invalid import: {0}")]
+    InvalidImport(String),
+    #[error("The objects to import could not be listed")]
+    ListingError(#[from] ImportS3RepositoryError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for ImportSourcesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for ImportSourcesError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ImportSourcesError::InvalidImport(_) => StatusCode::BAD_REQUEST,
+            ImportSourcesError::ListingError(error) => match error {
+                ImportS3RepositoryError::BucketNotFound(_)
+                | ImportS3RepositoryError::ObjectNotFound(_) => StatusCode::NOT_FOUND,
+                ImportS3RepositoryError::TooManyObjects(_) => StatusCode::BAD_REQUEST,
+                ImportS3RepositoryError::IOError(_) | ImportS3RepositoryError::Other(_) => {
+                    StatusCode::BAD_GATEWAY
+                }
+            },
+            ImportSourcesError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct ImportSourcesBodyData {
+    /// User owning the imported sources
+    pub user_id: Uuid,
+    /// Bucket of the object storage of the imports settings
+    pub bucket: String,
+    /// Only the objects whose key starts with this prefix are imported, all the objects of the bucket by default
+    #[serde(default)]
+    pub prefix: String,
+    /// Tags given to the imported files, matched by the auto-filing rules
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ImportSourcesResponse {
+    /// Number of objects whose import is requested
+    pub nb_sources: usize,
+    /// Keys of the objects not imported, whose extension is not a supported source type
+    pub unsupported_keys: Vec<String>,
+}
+
+/// Import the objects of a bucket as sources of a user, for ex to onboard an existing document archive
+///
+/// The import is asynchronous: an `import_source` command is sent for each object of a supported type,
+/// consumed by the gateway instances which copy the object to the bucket of the sources and send it to the extraction.
+/// The imported files are checked and filed like the sources added from a URL: the files already added by the user
+/// are not imported again.
+#[utoipa::path(
+    post,
+    path = "/imports",
+    tag = "admin",
+    request_body = ImportSourcesBodyData,
+    responses(
+        (status = 202, description = "Number of objects whose import is requested", body = ImportSourcesResponse),
+        (status = 400, description = "Invalid import, or too many objects under the prefix"),
+        (status = 404, description = "Bucket not found"),
+        (status = 502, description = "The objects of the bucket could not be listed"),
+    ),
+    security(("admin_token" = []))
+)]
+#[tracing::instrument(
+    name = "Import sources",
+    skip(import_s3_repository, message_repositories),
+    err
+)]
+pub async fn import_sources(
+    body: web::Json<ImportSourcesBodyData>,
+    import_s3_repository: web::Data<ImportS3Repository>,
+    message_repositories: web::Data<TenantMessageRepositories>,
+) -> Result<HttpResponse, ImportSourcesError> {
+    let ImportSourcesBodyData {
+        user_id,
+        bucket,
+        prefix,
+        tags,
+    } = body.into_inner();
+
+    if bucket.trim().is_empty() {
+        return Err(ImportSourcesError::InvalidImport(
+            "the bucket should not be empty".to_string(),
+        ));
+    }
+
+    let (object_keys, unsupported_keys): (Vec<String>, Vec<String>) = import_s3_repository
+        .list_objects(&bucket, &prefix)
+        .await?
+        .into_iter()
+        .map(|object| object.key)
+        .partition(|key| is_supported_source(key));
+
+    // The commands are consumed by the gateway, which routes the extraction jobs to the tenant of the user
+    let message_rabbitmq_repository = message_repositories
+        .route(None)
+        .context("Could not route the import commands")?;
+
+    for object_key in object_keys.iter() {
+        let json_message = serde_json::to_string(&ImportSourceDto {
+            user_id,
+            bucket: bucket.clone(),
+            object_key: object_key.clone(),
+            tags: tags.clone(),
+        })
+        .context("Could not serialize the import command")?;
+
+        message_rabbitmq_repository
+            .publish(IMPORT_SOURCE_ROUTING_KEY, json_message.as_bytes())
+            .await
+            .context(format!(
+                "Could not send the import of object {}",
+                object_key
+            ))?;
+    }
+
+    info!(
+        "Sent the import of {} objects of {} for user {}, skipping {} unsupported objects",
+        object_keys.len(),
+        bucket,
+        user_id,
+        unsupported_keys.len()
+    );
+
+    Ok(HttpResponse::Accepted().json(ImportSourcesResponse {
+        nb_sources: object_keys.len(),
+        unsupported_keys,
+    }))
+}
+
+/// Whether the extension of an object key is a supported source type
+fn is_supported_source(object_key: &str) -> bool {
+    Path::new(object_key)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| SourceType::from_str(extension).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn objects_are_imported_on_their_extension() {
+        assert!(is_supported_source("archive/2023/report.docx"));
+        assert!(is_supported_source("book.epub"));
+        assert!(!is_supported_source("archive/scan.pdf"));
+        assert!(!is_supported_source("archive/README"));
+    }
+}
@@ -0,0 +1,414 @@
+use crate::configuration::UploadsSettings;
+use crate::controllers::SourceProgressStatus;
+use crate::domain::entities::document::Document;
+use crate::domain::entities::extraction_progress::ExtractionStatus;
+use crate::domain::entities::source_meta::{SourceMeta, SourceType};
+use crate::domain::entities::storage_usage::StorageUsage;
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::document_postgres_repository::{
+    DocumentPostgresRepository, DocumentPostgresRepositoryError,
+};
+use crate::repositories::source_meta_postgres_repository::{
+    ExtractionStatusFilter, SourceMetaCursor, SourceMetaFilters, SourceMetaPostgresRepository,
+};
+use crate::repositories::user_storage_usage_postgres_repository::UserStorageUsagePostgresRepository;
+use crate::responders::{
+    ndjson::{ndjson_response, spawn_producer},
+    sparse_fields::{FieldSet, InvalidFieldsError, Sparse},
+};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use common::helper::error_chain_fmt;
+use common::pagination::{Page, PageCursor, PageDirection, PageLimits, PaginationError};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool};
+use std::collections::HashMap;
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+const PAGE_LIMITS: PageLimits = PageLimits::new(20, 100);
+/// Number of streamed sources whose summaries are loaded together
+const STREAM_DOCUMENTS_BATCH_SIZE: usize = 100;
+
+#[derive(thiserror::Error)]
+pub enum ListSourcesError {
+    #[error(transparent)]
+    InvalidPagination(#[from] PaginationError),
+    #[error("Invalid cursor {0}: the sources are streamed from a next cursor")]
+    InvalidStreamCursor(String),
+    #[error("Invalid limit {0}: it should be at least 1")]
+    InvalidStreamLimit(u32),
+    #[error(transparent)]
+    InvalidFields(#[from] InvalidFieldsError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for ListSourcesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for ListSourcesError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ListSourcesError::InvalidPagination(_)
+            | ListSourcesError::InvalidStreamCursor(_)
+            | ListSourcesError::InvalidStreamLimit(_)
+            | ListSourcesError::InvalidFields(_) => StatusCode::BAD_REQUEST,
+            ListSourcesError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListSourcesQuery {
+    /// `next_cursor` or `prev_cursor` of a listed page. The first page is listed without cursor.
+    pub cursor: Option<String>,
+    /// Maximum number of sources in the page
+    pub limit: Option<u32>,
+    pub source_type: Option<SourceType>,
+    pub status: Option<SourceProgressStatus>,
+    /// Comma-separated fields of the sources to return, all by default. For ex: `id,status`
+    pub fields: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct SourceResponse {
+    pub id: Uuid,
+    pub initial_name: String,
+    pub source_type: SourceType,
+    pub added_at: DateTime<Utc>,
+    pub status: SourceProgressStatus,
+    /// Collection in which the source is filed
+    pub collection: Option<String>,
+    /// Summary of the extracted contents of the source. `None` until its first content is extracted.
+    #[serde(default)]
+    pub document: Option<DocumentSummaryResponse>,
+}
+
+impl From<SourceMeta> for SourceResponse {
+    fn from(value: SourceMeta) -> Self {
+        Self {
+            id: value.id,
+            initial_name: value.initial_name,
+            source_type: value.source_type,
+            added_at: value.added_at,
+            status: value.extraction_status.into(),
+            collection: value.collection,
+            document: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct DocumentSummaryResponse {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// First words of the source
+    pub excerpt: String,
+    /// Number of contents extracted from the source
+    pub nb_chunks: i64,
+    /// Most frequent words of the source, from the most frequent
+    pub keywords: Vec<String>,
+}
+
+impl From<Document> for DocumentSummaryResponse {
+    fn from(value: Document) -> Self {
+        Self {
+            keywords: value.top_keywords(),
+            title: value.title,
+            author: value.author,
+            excerpt: value.excerpt,
+            nb_chunks: value.nb_chunks,
+        }
+    }
+}
+
+/// Storage used by the source files of a user
+#[derive(Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+pub struct StorageUsageResponse {
+    pub used_bytes: u64,
+    /// `None` if the storage of the user is not limited
+    pub quota_bytes: Option<u64>,
+}
+
+impl From<StorageUsage> for StorageUsageResponse {
+    fn from(value: StorageUsage) -> Self {
+        Self {
+            used_bytes: value.used_bytes,
+            quota_bytes: value.quota_bytes,
+        }
+    }
+}
+
+/// Page of sources, with only the requested fields of the sources when listed with a field set
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[aliases(SourcesPage = ListSourcesResponse<SourceResponse>)]
+pub struct ListSourcesResponse<S = SourceResponse> {
+    pub sources: Vec<S>,
+    /// Cursor to list the next page. `None` on the last page.
+    pub next_cursor: Option<String>,
+    /// Cursor to list the previous page. `None` on the first page.
+    pub prev_cursor: Option<String>,
+    /// Storage used by all the sources of the user, against their quota
+    pub storage: StorageUsageResponse,
+}
+
+/// List the sources of a user, from the most recently added
+///
+/// Also documents `list_sources_ndjson`, routed on the same path when NDJSON is accepted.
+#[utoipa::path(
+    get,
+    path = "/sources",
+    tag = "sources",
+    params(ListSourcesQuery),
+    responses(
+        (
+            status = 200,
+            description = "Page of sources, or all the sources streamed as NDJSON when it is accepted",
+            content(
+                ("application/json" = SourcesPage),
+                ("application/x-ndjson" = SourceResponse)
+            )
+        ),
+        (status = 400, description = "Invalid cursor, limit or fields"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "List sources",
+    skip(
+        pool,
+        source_meta_repository,
+        document_repository,
+        user_storage_usage_repository,
+        uploads_settings
+    ),
+    err
+)]
+pub async fn list_sources(
+    query: web::Query<ListSourcesQuery>,
+    pool: web::Data<PgPool>,
+    source_meta_repository: web::Data<SourceMetaPostgresRepository>,
+    document_repository: web::Data<DocumentPostgresRepository>,
+    user_storage_usage_repository: web::Data<UserStorageUsagePostgresRepository>,
+    uploads_settings: web::Data<UploadsSettings>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, ListSourcesError> {
+    let user_id = user_id.into_inner().0;
+    let query = query.into_inner();
+    info!("Request for user_id: {}", user_id);
+
+    let limit = PAGE_LIMITS.validate(query.limit)?;
+    let fields = FieldSet::try_parse_query::<SourceResponse>(query.fields.as_deref())?;
+    let cursor = PageCursor::<SourceMetaCursor>::decode_query(query.cursor.as_deref())?;
+    let filters = parse_filters(&query);
+
+    // Lists one more source to know if there is a further page
+    let source_metas = source_meta_repository
+        .list_user_source_metas(
+            pool.get_ref(),
+            user_id,
+            &filters,
+            cursor.as_ref(),
+            i64::from(limit) + 1,
+        )
+        .await
+        .context("Could not list the sources of the user")?;
+
+    let page = Page::from_fetched(
+        source_metas,
+        limit,
+        cursor.map(|cursor| cursor.direction),
+        |source_meta| SourceMetaCursor {
+            added_at: source_meta.added_at,
+            id: source_meta.id,
+        },
+    );
+
+    let sources = with_documents(pool.get_ref(), &document_repository, page.items)
+        .await
+        .context("Could not list the summaries of the sources")?;
+
+    let used_bytes = user_storage_usage_repository
+        .get_used_bytes(pool.get_ref(), user_id)
+        .await
+        .context("Could not get the storage used by the user")?;
+    let storage = StorageUsage {
+        used_bytes,
+        quota_bytes: uploads_settings.storage_quota(),
+    };
+
+    Ok(HttpResponse::Ok().json(ListSourcesResponse {
+        sources: sources
+            .into_iter()
+            .map(|source| Sparse::new(source, fields.clone()))
+            .collect(),
+        next_cursor: page.next_cursor,
+        prev_cursor: page.prev_cursor,
+        storage: storage.into(),
+    }))
+}
+
+/// Stream the sources of a user as NDJSON, from the most recently added
+///
+/// The sources are not paginated: all the sources are streamed, or up to `limit` if set.
+/// They can be streamed from the `next_cursor` of a listed page.
+#[tracing::instrument(
+    name = "List sources as NDJSON",
+    skip(pool, source_meta_repository, document_repository),
+    err
+)]
+pub async fn list_sources_ndjson(
+    query: web::Query<ListSourcesQuery>,
+    pool: web::Data<PgPool>,
+    source_meta_repository: web::Data<SourceMetaPostgresRepository>,
+    document_repository: web::Data<DocumentPostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, ListSourcesError> {
+    let user_id = user_id.into_inner().0;
+    let query = query.into_inner();
+    info!("Request for user_id: {}", user_id);
+
+    let limit = match query.limit {
+        Some(0) => return Err(ListSourcesError::InvalidStreamLimit(0)),
+        limit => limit.map(i64::from),
+    };
+    let fields = FieldSet::try_parse_query::<SourceResponse>(query.fields.as_deref())?;
+    let after = match PageCursor::<SourceMetaCursor>::decode_query(query.cursor.as_deref())? {
+        Some(PageCursor {
+            direction: PageDirection::Previous,
+            ..
+        }) => {
+            return Err(ListSourcesError::InvalidStreamCursor(
+                query.cursor.unwrap_or_default(),
+            ))
+        }
+        cursor => cursor.map(|cursor| cursor.key),
+    };
+    let filters = parse_filters(&query);
+
+    let pool = pool.into_inner();
+    let source_meta_repository = source_meta_repository.into_inner();
+    let document_repository = document_repository.into_inner();
+
+    let sources = spawn_producer(move |sender| async move {
+        // The summaries of the streamed sources are loaded by batch
+        let mut source_metas = source_meta_repository
+            .stream_user_source_metas(pool.as_ref(), user_id, &filters, after.as_ref(), limit)
+            .ready_chunks(STREAM_DOCUMENTS_BATCH_SIZE);
+
+        while let Some(source_metas) = source_metas.next().await {
+            let sources = match source_metas
+                .into_iter()
+                .collect::<Result<Vec<_>, _>>()
+                .context("Could not stream the sources of the user")
+            {
+                Ok(source_metas) => {
+                    with_documents(pool.as_ref(), &document_repository, source_metas)
+                        .await
+                        .context("Could not list the summaries of the sources")
+                }
+                Err(error) => Err(error),
+            };
+
+            let sources = match sources {
+                Ok(sources) => sources,
+                Err(error) => {
+                    let _ = sender.send(Err(error)).await;
+                    break;
+                }
+            };
+
+            for source in sources {
+                // The client disconnected
+                if sender
+                    .send(Ok(Sparse::new(source, fields.clone())))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        }
+    });
+
+    Ok(ndjson_response(sources))
+}
+
+/// Responses of the given sources, with their summary
+async fn with_documents(
+    db_executor: impl PgExecutor<'_>,
+    document_repository: &DocumentPostgresRepository,
+    source_metas: Vec<SourceMeta>,
+) -> Result<Vec<SourceResponse>, DocumentPostgresRepositoryError> {
+    let source_meta_ids: Vec<Uuid> = source_metas
+        .iter()
+        .map(|source_meta| source_meta.id)
+        .collect();
+    let mut documents: HashMap<Uuid, Document> = document_repository
+        .list_documents(db_executor, &source_meta_ids)
+        .await?
+        .into_iter()
+        .map(|document| (document.source_meta_id, document))
+        .collect();
+
+    Ok(source_metas
+        .into_iter()
+        .map(|source_meta| {
+            let document = documents.remove(&source_meta.id);
+            SourceResponse {
+                document: document.map(DocumentSummaryResponse::from),
+                ..SourceResponse::from(source_meta)
+            }
+        })
+        .collect())
+}
+
+fn parse_filters(query: &ListSourcesQuery) -> SourceMetaFilters {
+    SourceMetaFilters {
+        source_type: query.source_type.clone(),
+        extraction_status: query.status.map(|status| match status {
+            SourceProgressStatus::Pending => ExtractionStatusFilter::NotStarted,
+            SourceProgressStatus::InProgress => {
+                ExtractionStatusFilter::Is(ExtractionStatus::InProgress)
+            }
+            SourceProgressStatus::Completed => {
+                ExtractionStatusFilter::Is(ExtractionStatus::Completed)
+            }
+            SourceProgressStatus::Failed => ExtractionStatusFilter::Is(ExtractionStatus::Failed),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDateTime, TimeZone};
+
+    #[test]
+    fn cursor_is_decoded_from_its_encoding() {
+        let cursor = PageCursor::next(SourceMetaCursor {
+            added_at: Utc.from_utc_datetime(
+                &NaiveDateTime::from_timestamp_micros(1_697_700_000_123_456).unwrap(),
+            ),
+            id: Uuid::new_v4(),
+        });
+
+        assert_eq!(PageCursor::decode(&cursor.encode()).unwrap(), cursor);
+    }
+
+    #[test]
+    fn invalid_cursor_is_not_decoded() {
+        for cursor in ["not a cursor", "1697700000123456_not-a-uuid"] {
+            assert!(PageCursor::<SourceMetaCursor>::decode(cursor).is_err());
+        }
+    }
+}
@@ -1,34 +1,81 @@
 use actix_web::http::header::ContentType;
 use actix_web::http::StatusCode;
 use actix_web::{web, HttpResponse, ResponseError};
+use chrono::Utc;
+use common::core::secrets::SecretsCipher;
 use common::helper::error_chain_fmt;
 use secrecy::Secret;
 use serde_json::json;
 use sqlx::PgPool;
 use tracing::{error, info};
+use utoipa::ToSchema;
+use uuid::Uuid;
 
+use crate::configuration::TwoFactorSettings;
+use crate::controllers::two_factor::decrypt_totp_secret;
+use crate::domain::entities::refresh_token::RefreshToken;
+use crate::domain::entities::two_factor::{hash_recovery_code, verify_totp_code};
 use crate::domain::entities::user::UserError;
+use crate::middlewares::throttling::{Throttling, ThrottlingReason};
 use crate::repositories::jwt_authentication_repository::{
     JwtAuthenticationRepository, JwtAuthenticationRepositoryError,
 };
+use crate::repositories::refresh_token_postgres_repository::{
+    RefreshTokenPostgresRepository, RefreshTokenPostgresRepositoryError,
+};
+use crate::repositories::two_factor_postgres_repository::{
+    TwoFactorPostgresRepository, TwoFactorPostgresRepositoryError,
+};
 use crate::repositories::user_postgres_repository::UserPostgresRepository;
 use crate::repositories::user_postgres_repository::UserPostgresRepositoryError;
 
 /// Log in user account controller
 ///
+/// Starts a new log-in session: a short-lived access token is returned with a refresh token,
+/// used to get new access tokens on `/refresh_token` until the session is logged out.
+///
+/// With the two-factor authentication enabled, a code of the authenticator app (`totp_code`) or an unused
+/// recovery code (`recovery_code`) is also required. Without any, a 401 with `two_factor_required` is returned.
+/// After too many invalid codes in a row, the log-ins of the account are rejected with a 429 for a while.
+///
 /// Improvements:
 /// - enforce almost constant time by using a default user if the email does not exist, in order to avoid email guessing via timing attacks
+#[utoipa::path(
+    post,
+    path = "/account/login",
+    tag = "account",
+    request_body = LogInAccountBodyData,
+    responses(
+        (status = 200, description = "Tokens of the new log-in session", body = LogInAccountResponse),
+        (status = 401, description = "Invalid credentials, or invalid or missing two-factor authentication code", body = Object, example = json!({ "error": "Invalid credentials" })),
+        (status = 429, description = "Too many log-ins from the client, or too many invalid two-factor authentication codes", body = Object),
+    )
+)]
 #[tracing::instrument(
     name = "Log in user account",
-    skip(pool, user_repository, auth_repository, body)
+    skip(
+        pool,
+        user_repository,
+        refresh_token_repository,
+        two_factor_log_in,
+        auth_repository,
+        body
+    )
 )]
 pub async fn log_in_account(
     pool: web::Data<PgPool>,
     user_repository: web::Data<UserPostgresRepository>,
+    refresh_token_repository: web::Data<RefreshTokenPostgresRepository>,
+    two_factor_log_in: web::Data<TwoFactorLogIn>,
     auth_repository: web::Data<JwtAuthenticationRepository>,
     body: web::Json<LogInAccountBodyData>,
 ) -> Result<HttpResponse, LogInAccountError> {
-    let LogInAccountBodyData { email, password } = body.into_inner();
+    let LogInAccountBodyData {
+        email,
+        password,
+        totp_code,
+        recovery_code,
+    } = body.into_inner();
     let password = Secret::new(password);
 
     info!(email, "Login attempt");
@@ -63,23 +110,144 @@ pub async fn log_in_account(
             }
         })?;
 
-    let jwt_token = auth_repository.create_token(&stored_user.id.to_string())?;
+    two_factor_log_in
+        .verify(&pool, stored_user.id, &email, totp_code, recovery_code)
+        .await?;
+
+    let session_id = Uuid::new_v4();
+    let (refresh_token, stored_refresh_token) = RefreshToken::generate(
+        stored_user.id,
+        session_id,
+        auth_repository.refresh_token_expire_in_s(),
+    );
+    refresh_token_repository
+        .add_token(&**pool, &stored_refresh_token)
+        .await?;
+
+    let jwt_token =
+        auth_repository.create_session_token(&stored_user.id.to_string(), session_id)?;
 
     Ok(HttpResponse::Ok().json(LogInAccountResponse {
         access_token: jwt_token,
+        refresh_token,
         message: format!("Successfully logged in {}", email),
     }))
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+/// Second step of the log-ins of the users having enabled the two-factor authentication
+pub struct TwoFactorLogIn {
+    pub two_factor_repository: TwoFactorPostgresRepository,
+    pub secrets_cipher: SecretsCipher,
+    pub settings: TwoFactorSettings,
+}
+
+impl TwoFactorLogIn {
+    pub fn new(secrets_cipher: SecretsCipher, settings: TwoFactorSettings) -> Self {
+        Self {
+            two_factor_repository: TwoFactorPostgresRepository::new(),
+            secrets_cipher,
+            settings,
+        }
+    }
+
+    /// Checks the code of a log-in, if the user enabled the two-factor authentication
+    ///
+    /// The invalid codes are counted: too many in a row lock out the log-ins of the user for a while.
+    pub(crate) async fn verify(
+        &self,
+        pool: &PgPool,
+        user_id: Uuid,
+        email: &str,
+        totp_code: Option<String>,
+        recovery_code: Option<String>,
+    ) -> Result<(), LogInAccountError> {
+        let two_factor = self
+            .two_factor_repository
+            .get_user_two_factor(pool, user_id)
+            .await?
+            .filter(|two_factor| two_factor.is_enabled());
+        let Some(two_factor) = two_factor else {
+            return Ok(());
+        };
+        if let Some(remaining_lockout) = two_factor.remaining_lockout(Utc::now()) {
+            info!(
+                email,
+                "Log-in to an account locked out after invalid two-factor authentication codes"
+            );
+            return Err(LogInAccountError::LockedOut(Throttling::new(
+                ThrottlingReason::LockedOut,
+                remaining_lockout,
+                self.settings.max_failed_attempts.into(),
+                0,
+            )));
+        }
+
+        let is_verified = match (totp_code, recovery_code) {
+            (Some(totp_code), _) => {
+                let secret =
+                    decrypt_totp_secret(&self.secrets_cipher, &two_factor.encrypted_secret)?;
+                match verify_totp_code(&secret, &totp_code, Utc::now()) {
+                    // A code already used is rejected
+                    Some(step) => {
+                        self.two_factor_repository
+                            .use_step(pool, user_id, step)
+                            .await?
+                    }
+                    None => false,
+                }
+            }
+            (None, Some(recovery_code)) => {
+                let is_used = self
+                    .two_factor_repository
+                    .use_recovery_code(pool, user_id, &hash_recovery_code(&recovery_code))
+                    .await?;
+                if is_used {
+                    info!(email, "Logged in with a recovery code");
+                }
+                is_used
+            }
+            (None, None) => return Err(LogInAccountError::TwoFactorRequired()),
+        };
+
+        if !is_verified {
+            info!(email, "Invalid two-factor authentication code during login");
+            self.two_factor_repository
+                .record_failed_attempt(
+                    pool,
+                    user_id,
+                    self.settings.max_failed_attempts,
+                    self.settings.lockout_s,
+                )
+                .await?;
+            return Err(LogInAccountError::InvalidTwoFactorCode());
+        }
+
+        if two_factor.failed_attempts > 0 {
+            self.two_factor_repository
+                .reset_failed_attempts(pool, user_id)
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, ToSchema)]
 pub struct LogInAccountBodyData {
     pub email: String,
     pub password: String,
+    /// Code of the authenticator app, if the two-factor authentication is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp_code: Option<String>,
+    /// Unused recovery code, instead of `totp_code`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery_code: Option<String>,
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, serde::Deserialize, serde::Serialize, ToSchema)]
 pub struct LogInAccountResponse {
     pub access_token: String,
+    pub refresh_token: String,
     pub message: String,
 }
 
@@ -88,11 +256,21 @@ pub enum LogInAccountError {
     #[error(transparent)]
     RepositoryInternalError(#[from] UserPostgresRepositoryError),
     #[error(transparent)]
+    RefreshTokenRepositoryError(#[from] RefreshTokenPostgresRepositoryError),
+    #[error(transparent)]
     InternalError(#[from] anyhow::Error),
     #[error("Invalid credentials")]
     InvalidCredentials(),
     #[error(transparent)]
     JwtAuthenticationRepositoryError(#[from] JwtAuthenticationRepositoryError),
+    #[error(transparent)]
+    TwoFactorRepositoryError(#[from] TwoFactorPostgresRepositoryError),
+    #[error("Two-factor authentication code required")]
+    TwoFactorRequired(),
+    #[error("Invalid two-factor authentication code")]
+    InvalidTwoFactorCode(),
+    #[error("Too many invalid two-factor authentication codes, retry after {}s", .0.retry_after_s)]
+    LockedOut(Throttling),
 }
 
 impl std::fmt::Debug for LogInAccountError {
@@ -104,19 +282,33 @@ impl std::fmt::Debug for LogInAccountError {
 impl ResponseError for LogInAccountError {
     fn status_code(&self) -> StatusCode {
         match self {
-            LogInAccountError::InvalidCredentials() => StatusCode::UNAUTHORIZED,
+            LogInAccountError::InvalidCredentials()
+            | LogInAccountError::TwoFactorRequired()
+            | LogInAccountError::InvalidTwoFactorCode() => StatusCode::UNAUTHORIZED,
+            LogInAccountError::LockedOut(throttling) => throttling.status_code(),
             LogInAccountError::InternalError(_)
             | LogInAccountError::RepositoryInternalError(_)
-            | LogInAccountError::JwtAuthenticationRepositoryError(_) => {
-                StatusCode::INTERNAL_SERVER_ERROR
-            }
+            | LogInAccountError::RefreshTokenRepositoryError(_)
+            | LogInAccountError::JwtAuthenticationRepositoryError(_)
+            | LogInAccountError::TwoFactorRepositoryError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
     #[tracing::instrument(name = "Response error from create_account controller", skip(self), fields(error = %self))]
     fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        if let LogInAccountError::LockedOut(throttling) = self {
+            return throttling.error_response(&self.to_string());
+        }
+
+        let body = match self {
+            LogInAccountError::TwoFactorRequired() => {
+                json!({ "error": self.to_string(), "two_factor_required": true })
+            }
+            _ => json!({ "error": self.to_string() }),
+        };
+
         HttpResponse::build(self.status_code())
             .insert_header(ContentType::json())
-            .json(json!({ "error": self.to_string() }))
+            .json(body)
     }
 }
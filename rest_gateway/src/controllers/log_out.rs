@@ -0,0 +1,99 @@
+use actix_web::http::header::ContentType;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use common::helper::error_chain_fmt;
+use serde_json::json;
+use sqlx::PgPool;
+use tracing::info;
+use utoipa::ToSchema;
+
+use crate::domain::entities::refresh_token::RefreshToken;
+use crate::repositories::refresh_token_postgres_repository::{
+    RefreshTokenPostgresRepository, RefreshTokenPostgresRepositoryError,
+};
+
+/// Log out controller: revokes the log-in session of a refresh token
+///
+/// All the refresh tokens of the session are revoked, and its access tokens are rejected
+/// by the authentication middleware, even before their expiration.
+#[utoipa::path(
+    post,
+    path = "/log_out",
+    tag = "account",
+    request_body = LogOutBodyData,
+    responses(
+        (status = 204, description = "Revoked log-in session"),
+        (status = 401, description = "Invalid refresh token"),
+    )
+)]
+#[tracing::instrument(name = "Log out", skip(pool, refresh_token_repository, body))]
+pub async fn log_out(
+    pool: web::Data<PgPool>,
+    refresh_token_repository: web::Data<RefreshTokenPostgresRepository>,
+    body: web::Json<LogOutBodyData>,
+) -> Result<HttpResponse, LogOutError> {
+    let token_hash = RefreshToken::hash(&body.refresh_token);
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    let stored_token = refresh_token_repository
+        .get_token_by_hash_for_update(&mut transaction, &token_hash)
+        .await?
+        .ok_or(LogOutError::InvalidRefreshToken())?;
+
+    refresh_token_repository
+        .revoke_session(&mut transaction, stored_token.session_id)
+        .await?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to revoke a session")?;
+
+    info!(session_id = ?stored_token.session_id, "Session logged out");
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, ToSchema)]
+pub struct LogOutBodyData {
+    pub refresh_token: String,
+}
+
+#[derive(thiserror::Error)]
+pub enum LogOutError {
+    #[error("Invalid refresh token")]
+    InvalidRefreshToken(),
+    #[error(transparent)]
+    RepositoryError(#[from] RefreshTokenPostgresRepositoryError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for LogOutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for LogOutError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            LogOutError::InvalidRefreshToken() => StatusCode::UNAUTHORIZED,
+            LogOutError::RepositoryError(_) | LogOutError::UnexpectedError(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    #[tracing::instrument(name = "Response error from log_out controller", skip(self), fields(error = %self))]
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        HttpResponse::build(self.status_code())
+            .insert_header(ContentType::json())
+            .json(json!({ "error": self.to_string() }))
+    }
+}
@@ -1,11 +1,71 @@
 pub mod add_source_files;
+pub mod add_source_url;
+pub mod api_keys;
+pub mod ask;
+pub mod auto_filing_rules;
 pub mod create_account;
+pub mod delete_recrawl_schedule;
+pub mod delete_source;
+pub mod download_source;
+pub mod get_events;
+pub mod get_ingestion_slo;
+pub mod get_job;
+pub mod get_metrics;
+pub mod get_source_chunks;
+pub mod get_source_events;
+pub mod get_source_progress;
+pub mod get_source_summary;
 pub mod health_check;
+pub mod import_sources;
+pub mod list_sources;
 pub mod log_in_account;
+pub mod log_out;
+pub mod normalization_rules;
+pub mod promote_fulltext_standby;
+pub mod provider_credentials;
+pub mod refresh_token;
+pub mod reindex_sources;
+pub mod retention_rules;
+pub mod saved_searches;
 pub mod search_content;
+pub mod search_history;
+pub mod set_default_collection;
+pub mod two_factor;
+pub mod upload_policies;
+pub mod uploads;
 
 pub use add_source_files::*;
+pub use add_source_url::*;
+pub use api_keys::*;
+pub use ask::*;
+pub use auto_filing_rules::*;
 pub use create_account::*;
+pub use delete_recrawl_schedule::*;
+pub use delete_source::*;
+pub use download_source::*;
+pub use get_events::*;
+pub use get_ingestion_slo::*;
+pub use get_job::*;
+pub use get_metrics::*;
+pub use get_source_chunks::*;
+pub use get_source_events::*;
+pub use get_source_progress::*;
+pub use get_source_summary::*;
 pub use health_check::*;
+pub use import_sources::*;
+pub use list_sources::*;
 pub use log_in_account::*;
+pub use log_out::*;
+pub use normalization_rules::*;
+pub use promote_fulltext_standby::*;
+pub use provider_credentials::*;
+pub use refresh_token::*;
+pub use reindex_sources::*;
+pub use retention_rules::*;
+pub use saved_searches::*;
 pub use search_content::*;
+pub use search_history::*;
+pub use set_default_collection::*;
+pub use two_factor::*;
+pub use upload_policies::*;
+pub use uploads::*;
@@ -0,0 +1,247 @@
+use crate::domain::entities::normalization_rule::{NormalizationRule, NormalizationRuleKind};
+use crate::repositories::normalization_rule_postgres_repository::NormalizationRulePostgresRepository;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use common::constants::routing_keys::NORMALIZATION_RULES_CHANGED_ROUTING_KEY;
+use common::core::tenancy::TenantMessageRepositories;
+use common::core::text_normalizer::TextNormalizer;
+use common::dtos::normalization_rules::NormalizationRuleDto;
+use common::helper::error_chain_fmt;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::{error, info};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+#[derive(thiserror::Error)]
+pub enum NormalizationRuleError {
+    #[error("Invalid normalization rule: {0}")]
+    InvalidRule(String),
+    #[error("Unknown tenant: {0}")]
+    UnknownTenant(String),
+    #[error("Normalization rule {0} not found")]
+    RuleNotFound(Uuid),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for NormalizationRuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for NormalizationRuleError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            NormalizationRuleError::InvalidRule(_) | NormalizationRuleError::UnknownTenant(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            NormalizationRuleError::RuleNotFound(_) => StatusCode::NOT_FOUND,
+            NormalizationRuleError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct NormalizationRuleBodyData {
+    /// Tenant (organization) of the rule, `None` for the users without tenant
+    pub tenant: Option<String>,
+    pub kind: NormalizationRuleKind,
+    /// Replaced term, or regular expression of a stop pattern
+    pub pattern: String,
+    /// Replacing term, not set for a stop pattern
+    pub replacement: Option<String>,
+}
+
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NormalizationRulesQuery {
+    /// Tenant of the rules, `None` for the users without tenant
+    pub tenant: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct NormalizationRuleResponse {
+    pub id: Uuid,
+    pub tenant: Option<String>,
+    pub kind: NormalizationRuleKind,
+    pub pattern: String,
+    pub replacement: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<NormalizationRule> for NormalizationRuleResponse {
+    fn from(value: NormalizationRule) -> Self {
+        Self {
+            id: value.id,
+            tenant: value.tenant_id,
+            kind: value.kind,
+            pattern: value.pattern,
+            replacement: value.replacement,
+            created_at: value.created_at,
+        }
+    }
+}
+
+/// List the normalization rules of a tenant, in the order they are applied
+#[utoipa::path(
+    get,
+    path = "/admin/normalization_rules",
+    tag = "admin",
+    params(NormalizationRulesQuery),
+    responses(
+        (status = 200, description = "Normalization rules of the tenant", body = [NormalizationRuleResponse]),
+    ),
+    security(("admin_token" = []))
+)]
+#[tracing::instrument(
+    name = "List normalization rules",
+    skip(pool, normalization_rule_repository),
+    err
+)]
+pub async fn list_normalization_rules(
+    query: web::Query<NormalizationRulesQuery>,
+    pool: web::Data<PgPool>,
+    normalization_rule_repository: web::Data<NormalizationRulePostgresRepository>,
+) -> Result<HttpResponse, NormalizationRuleError> {
+    let rules = normalization_rule_repository
+        .list_tenant_rules(pool.get_ref(), query.tenant.as_deref())
+        .await
+        .context("Could not list the normalization rules of the tenant")?;
+
+    Ok(HttpResponse::Ok().json(
+        rules
+            .into_iter()
+            .map(NormalizationRuleResponse::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Add a rule to the dictionary of a tenant, applied to the contents extracted and the searches made from then on
+///
+/// The contents already extracted are not normalized again: their sources should be reindexed.
+#[utoipa::path(
+    post,
+    path = "/admin/normalization_rules",
+    tag = "admin",
+    request_body = NormalizationRuleBodyData,
+    responses(
+        (status = 201, description = "Added normalization rule", body = NormalizationRuleResponse),
+        (status = 400, description = "Invalid rule or unknown tenant"),
+    ),
+    security(("admin_token" = []))
+)]
+#[tracing::instrument(
+    name = "Add normalization rule",
+    skip(pool, normalization_rule_repository, message_repositories),
+    err
+)]
+pub async fn add_normalization_rule(
+    body: web::Json<NormalizationRuleBodyData>,
+    pool: web::Data<PgPool>,
+    normalization_rule_repository: web::Data<NormalizationRulePostgresRepository>,
+    message_repositories: web::Data<TenantMessageRepositories>,
+) -> Result<HttpResponse, NormalizationRuleError> {
+    let NormalizationRuleBodyData {
+        tenant,
+        kind,
+        pattern,
+        replacement,
+    } = body.into_inner();
+
+    if let Some(tenant) = &tenant {
+        if !message_repositories.has_tenant(tenant) {
+            return Err(NormalizationRuleError::UnknownTenant(tenant.clone()));
+        }
+    }
+
+    let rule = NormalizationRule {
+        id: Uuid::new_v4(),
+        tenant_id: tenant,
+        kind,
+        pattern: pattern.trim().to_string(),
+        replacement: match kind {
+            NormalizationRuleKind::StopPattern => None,
+            _ => replacement.map(|replacement| replacement.trim().to_string()),
+        },
+        created_at: Utc::now(),
+    };
+    // Compiled as the services will, for an invalid rule not to disable the normalization of the tenant
+    TextNormalizer::try_new(&[NormalizationRuleDto::from(rule.clone())])
+        .map_err(|error| NormalizationRuleError::InvalidRule(error.to_string()))?;
+
+    normalization_rule_repository
+        .add_rule(pool.get_ref(), &rule)
+        .await
+        .context("Could not save the normalization rule")?;
+
+    info!(
+        "Added normalization rule {} of tenant {:?}",
+        rule.id, rule.tenant_id
+    );
+    publish_rules_change(&message_repositories, rule.tenant_id.as_deref()).await;
+
+    Ok(HttpResponse::Created().json(NormalizationRuleResponse::from(rule)))
+}
+
+/// Delete a rule from the dictionary of its tenant
+#[utoipa::path(
+    delete,
+    path = "/admin/normalization_rules/{rule_id}",
+    tag = "admin",
+    params(("rule_id" = Uuid, Path, description = "ID of the normalization rule")),
+    responses(
+        (status = 204, description = "Deleted normalization rule"),
+        (status = 404, description = "Normalization rule not found"),
+    ),
+    security(("admin_token" = []))
+)]
+#[tracing::instrument(
+    name = "Delete normalization rule",
+    skip(pool, normalization_rule_repository, message_repositories),
+    err
+)]
+pub async fn delete_normalization_rule(
+    rule_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    normalization_rule_repository: web::Data<NormalizationRulePostgresRepository>,
+    message_repositories: web::Data<TenantMessageRepositories>,
+) -> Result<HttpResponse, NormalizationRuleError> {
+    let rule_id = rule_id.into_inner();
+
+    let rule = normalization_rule_repository
+        .delete_rule(pool.get_ref(), rule_id)
+        .await
+        .context("Could not delete the normalization rule")?
+        .ok_or(NormalizationRuleError::RuleNotFound(rule_id))?;
+
+    publish_rules_change(&message_repositories, rule.tenant_id.as_deref()).await;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Invalidates the rules cached by the services of a tenant
+///
+/// The change is saved: a failure to publish it is only logged, the services keeping their cached rules until restarted.
+async fn publish_rules_change(
+    message_repositories: &TenantMessageRepositories,
+    tenant_id: Option<&str>,
+) {
+    let result = match message_repositories.route(tenant_id) {
+        Ok(message_repository) => message_repository
+            .publish(NORMALIZATION_RULES_CHANGED_ROUTING_KEY, b"{}")
+            .await
+            .map_err(|error| error.to_string()),
+        Err(error) => Err(error.to_string()),
+    };
+
+    if let Err(error) = result {
+        error!(
+            %error,
+            "Failed to publish the change of the normalization rules of tenant {:?}", tenant_id
+        );
+    }
+}
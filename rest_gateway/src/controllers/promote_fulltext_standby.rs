@@ -0,0 +1,97 @@
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use common::constants::routing_keys::PROMOTE_FULLTEXT_STANDBY_ROUTING_KEY;
+use common::core::tenancy::TenantMessageRepositories;
+use common::dtos::promote_standby::PromoteStandbyDto;
+use common::helper::error_chain_fmt;
+use serde::Deserialize;
+use tracing::info;
+use utoipa::ToSchema;
+
+#[derive(thiserror::Error)]
+pub enum PromoteFulltextStandbyError {
+    #[error("Invalid promotion: {0}")]
+    InvalidPromotion(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for PromoteFulltextStandbyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for PromoteFulltextStandbyError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            PromoteFulltextStandbyError::InvalidPromotion(_) => StatusCode::BAD_REQUEST,
+            PromoteFulltextStandbyError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct PromoteFulltextStandbyBodyData {
+    /// Name of the standby full-text search service to promote
+    pub standby_name: String,
+    /// Tenant served by the standby, `None` for the shared full-text search service
+    pub tenant: Option<String>,
+}
+
+/// Promote a standby full-text search service to serve the queries, when its primary Meilisearch is lost
+///
+/// The promotion is asynchronous: the standby consumes the search requests once it receives the command.
+/// The primary instance should be stopped, otherwise both instances serve the queries.
+#[utoipa::path(
+    post,
+    path = "/admin/fulltext_search/promote",
+    tag = "admin",
+    request_body = PromoteFulltextStandbyBodyData,
+    responses(
+        (status = 202, description = "The promotion command is sent to the standby"),
+        (status = 400, description = "Invalid promotion"),
+    ),
+    security(("admin_token" = []))
+)]
+#[tracing::instrument(name = "Promote fulltext standby", skip(message_repositories), err)]
+pub async fn promote_fulltext_standby(
+    body: web::Json<PromoteFulltextStandbyBodyData>,
+    message_repositories: web::Data<TenantMessageRepositories>,
+) -> Result<HttpResponse, PromoteFulltextStandbyError> {
+    let PromoteFulltextStandbyBodyData {
+        standby_name,
+        tenant,
+    } = body.into_inner();
+
+    if standby_name.trim().is_empty() {
+        return Err(PromoteFulltextStandbyError::InvalidPromotion(
+            "the standby name should not be empty".to_string(),
+        ));
+    }
+
+    let message_rabbitmq_repository = message_repositories
+        .route(tenant.as_deref())
+        .map_err(|error| PromoteFulltextStandbyError::InvalidPromotion(error.to_string()))?;
+
+    let json_message = serde_json::to_string(&PromoteStandbyDto {
+        standby_name: standby_name.clone(),
+    })
+    .context("Could not serialize the promotion")?;
+
+    message_rabbitmq_repository
+        .publish(
+            PROMOTE_FULLTEXT_STANDBY_ROUTING_KEY,
+            json_message.as_bytes(),
+        )
+        .await
+        .context(format!(
+            "Could not send the promotion of the standby {}",
+            standby_name
+        ))?;
+
+    info!("Sent the promotion of the standby {}", standby_name);
+
+    Ok(HttpResponse::Accepted().finish())
+}
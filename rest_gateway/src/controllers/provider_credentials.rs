@@ -0,0 +1,340 @@
+use crate::domain::entities::provider_credentials::{
+    api_key_hint, ModelProvider, ProviderCredentials, ProviderPurpose,
+};
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::provider_api_repository::{
+    ProviderApiRepository, ProviderApiRepositoryError,
+};
+use crate::repositories::provider_credentials_postgres_repository::ProviderCredentialsPostgresRepository;
+use crate::repositories::user_postgres_repository::UserPostgresRepository;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use common::constants::routing_keys::PROVIDER_CREDENTIALS_CHANGED_ROUTING_KEY;
+use common::core::secrets::SecretsCipher;
+use common::core::tenancy::TenantMessageRepositories;
+use common::helper::error_chain_fmt;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::{error, info};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Services managing the provider credentials of the tenants
+pub struct ProviderCredentialsServices {
+    pub provider_credentials_repository: ProviderCredentialsPostgresRepository,
+    pub user_repository: UserPostgresRepository,
+    /// Checks the API keys against the providers before they are saved
+    pub provider_api_repository: ProviderApiRepository,
+    /// Encrypts the API keys before they are saved
+    pub secrets_cipher: SecretsCipher,
+}
+
+#[derive(thiserror::Error)]
+pub enum ProviderCredentialsError {
+    #[error("Only the users of a tenant can manage its provider credentials")]
+    NoTenant,
+    #[error("Invalid provider credentials: {0}")]
+    InvalidCredentials(String),
+    #[error("Invalid provider credentials: {0}")]
+    RejectedCredentials(#[source] ProviderApiRepositoryError),
+    #[error("The provider could not check the credentials: {0}")]
+    ProviderUnavailable(#[source] ProviderApiRepositoryError),
+    #[error("No provider credentials for {0:?}")]
+    CredentialsNotFound(ProviderPurpose),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for ProviderCredentialsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for ProviderCredentialsError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ProviderCredentialsError::NoTenant => StatusCode::FORBIDDEN,
+            ProviderCredentialsError::InvalidCredentials(_)
+            | ProviderCredentialsError::RejectedCredentials(_) => StatusCode::BAD_REQUEST,
+            ProviderCredentialsError::ProviderUnavailable(_) => StatusCode::BAD_GATEWAY,
+            ProviderCredentialsError::CredentialsNotFound(_) => StatusCode::NOT_FOUND,
+            ProviderCredentialsError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<ProviderApiRepositoryError> for ProviderCredentialsError {
+    fn from(error: ProviderApiRepositoryError) -> Self {
+        match error {
+            ProviderApiRepositoryError::InvalidApiKey
+            | ProviderApiRepositoryError::UnknownModel(_) => {
+                ProviderCredentialsError::RejectedCredentials(error)
+            }
+            ProviderApiRepositoryError::Unreachable(_)
+            | ProviderApiRepositoryError::UnexpectedStatus(_) => {
+                ProviderCredentialsError::ProviderUnavailable(error)
+            }
+        }
+    }
+}
+
+/// The API key is wrapped in a `Secret` to avoid leaks in logs
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct ProviderCredentialsBodyData {
+    pub provider: ModelProvider,
+    /// Model of the provider used for the purpose, ex: `text-embedding-3-small`
+    pub model: String,
+    #[schema(value_type = String)]
+    pub api_key: Secret<String>,
+}
+
+impl ProviderCredentialsBodyData {
+    fn validate(&self) -> Result<(), ProviderCredentialsError> {
+        // The model is part of the URL of the provider API
+        let is_valid_model = !self.model.is_empty()
+            && self
+                .model
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'));
+        if !is_valid_model {
+            return Err(ProviderCredentialsError::InvalidCredentials(format!(
+                "invalid model name {:?}",
+                self.model
+            )));
+        }
+
+        if self.api_key.expose_secret().trim().is_empty() {
+            return Err(ProviderCredentialsError::InvalidCredentials(
+                "the API key should not be empty".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Provider credentials of a tenant, without its API key
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ProviderCredentialsResponse {
+    pub purpose: ProviderPurpose,
+    pub provider: ModelProvider,
+    pub model: String,
+    /// Last characters of the API key
+    pub api_key_hint: String,
+    pub nb_requests: i64,
+    pub nb_tokens: i64,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<ProviderCredentials> for ProviderCredentialsResponse {
+    fn from(value: ProviderCredentials) -> Self {
+        Self {
+            purpose: value.purpose,
+            provider: value.provider,
+            model: value.model,
+            api_key_hint: value.api_key_hint,
+            nb_requests: value.nb_requests,
+            nb_tokens: value.nb_tokens,
+            last_used_at: value.last_used_at,
+            updated_by: value.updated_by,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+/// Gets the tenant of a user: only the users of a tenant can manage its credentials
+async fn get_user_tenant_id(
+    pool: &PgPool,
+    user_repository: &UserPostgresRepository,
+    user_id: Uuid,
+) -> Result<String, ProviderCredentialsError> {
+    user_repository
+        .get_user_tenant_id(pool, user_id)
+        .await
+        .context("Failed to get the tenant of the user")?
+        .ok_or(ProviderCredentialsError::NoTenant)
+}
+
+/// List the provider credentials of the tenant of a user, with their usage
+#[utoipa::path(
+    get,
+    path = "/tenant/provider_credentials",
+    tag = "tenant",
+    responses(
+        (status = 200, description = "Provider credentials of the tenant", body = [ProviderCredentialsResponse]),
+        (status = 403, description = "The user has no tenant"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(name = "List provider credentials", skip(pool, services), err)]
+pub async fn list_provider_credentials(
+    pool: web::Data<PgPool>,
+    services: web::Data<ProviderCredentialsServices>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, ProviderCredentialsError> {
+    let user_id = user_id.into_inner().0;
+    let tenant_id = get_user_tenant_id(&pool, &services.user_repository, user_id).await?;
+
+    let credentials = services
+        .provider_credentials_repository
+        .list_tenant_credentials(&**pool, &tenant_id)
+        .await
+        .context("Failed to list the provider credentials")?;
+
+    let credentials: Vec<ProviderCredentialsResponse> =
+        credentials.into_iter().map(Into::into).collect();
+
+    Ok(HttpResponse::Ok().json(credentials))
+}
+
+/// Save the provider credentials of the tenant of a user for a purpose (BYOK)
+///
+/// The API key is checked against the provider before being saved encrypted:
+/// a key rejected by the provider, or without access to the model, is not saved.
+#[utoipa::path(
+    put,
+    path = "/tenant/provider_credentials/{purpose}",
+    tag = "tenant",
+    params(("purpose" = ProviderPurpose, Path, description = "Purpose of the credentials")),
+    request_body = ProviderCredentialsBodyData,
+    responses(
+        (status = 200, description = "Saved provider credentials", body = ProviderCredentialsResponse),
+        (status = 400, description = "Invalid credentials, or rejected by the provider"),
+        (status = 403, description = "The user has no tenant, or no account of the gateway"),
+        (status = 502, description = "The provider is unavailable"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "Save provider credentials",
+    skip(pool, services, message_repositories),
+    err
+)]
+pub async fn save_provider_credentials(
+    purpose: web::Path<ProviderPurpose>,
+    body: web::Json<ProviderCredentialsBodyData>,
+    pool: web::Data<PgPool>,
+    services: web::Data<ProviderCredentialsServices>,
+    message_repositories: web::Data<TenantMessageRepositories>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, ProviderCredentialsError> {
+    let user_id = user_id.into_inner().0;
+    let purpose = purpose.into_inner();
+    let body = body.into_inner();
+    body.validate()?;
+
+    let tenant_id = get_user_tenant_id(&pool, &services.user_repository, user_id).await?;
+
+    services
+        .provider_api_repository
+        .check_api_key(body.provider, &body.api_key, &body.model)
+        .await?;
+
+    let now = Utc::now();
+    let credentials = ProviderCredentials {
+        tenant_id,
+        purpose,
+        provider: body.provider,
+        model: body.model,
+        encrypted_api_key: services
+            .secrets_cipher
+            .encrypt(&body.api_key)
+            .context("Failed to encrypt the API key")?,
+        api_key_hint: api_key_hint(body.api_key.expose_secret()),
+        nb_requests: 0,
+        nb_tokens: 0,
+        last_used_at: None,
+        updated_by: Some(user_id),
+        created_at: now,
+        updated_at: now,
+    };
+
+    services
+        .provider_credentials_repository
+        .save_credentials(&**pool, &credentials)
+        .await
+        .context("Failed to save the provider credentials")?;
+
+    publish_credentials_change(&message_repositories, &credentials.tenant_id).await;
+
+    info!(
+        tenant_id = credentials.tenant_id,
+        ?purpose,
+        "Saved provider credentials"
+    );
+
+    Ok(HttpResponse::Ok().json(ProviderCredentialsResponse::from(credentials)))
+}
+
+/// Delete the provider credentials of the tenant of a user for a purpose
+#[utoipa::path(
+    delete,
+    path = "/tenant/provider_credentials/{purpose}",
+    tag = "tenant",
+    params(("purpose" = ProviderPurpose, Path, description = "Purpose of the credentials")),
+    responses(
+        (status = 204, description = "Deleted provider credentials"),
+        (status = 403, description = "The user has no tenant"),
+        (status = 404, description = "Provider credentials not found"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "Delete provider credentials",
+    skip(pool, services, message_repositories),
+    err
+)]
+pub async fn delete_provider_credentials(
+    purpose: web::Path<ProviderPurpose>,
+    pool: web::Data<PgPool>,
+    services: web::Data<ProviderCredentialsServices>,
+    message_repositories: web::Data<TenantMessageRepositories>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, ProviderCredentialsError> {
+    let user_id = user_id.into_inner().0;
+    let purpose = purpose.into_inner();
+    let tenant_id = get_user_tenant_id(&pool, &services.user_repository, user_id).await?;
+
+    let is_deleted = services
+        .provider_credentials_repository
+        .delete_credentials(&**pool, &tenant_id, purpose)
+        .await
+        .context("Failed to delete the provider credentials")?;
+
+    if !is_deleted {
+        return Err(ProviderCredentialsError::CredentialsNotFound(purpose));
+    }
+
+    publish_credentials_change(&message_repositories, &tenant_id).await;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Invalidates the credentials cached by the services of a tenant
+///
+/// The change is saved: a failure to publish it is only logged, the services keeping their cached credentials until restarted.
+async fn publish_credentials_change(
+    message_repositories: &TenantMessageRepositories,
+    tenant_id: &str,
+) {
+    let result = match message_repositories.route(Some(tenant_id)) {
+        Ok(message_repository) => message_repository
+            .publish(PROVIDER_CREDENTIALS_CHANGED_ROUTING_KEY, b"{}")
+            .await
+            .map_err(|error| error.to_string()),
+        Err(error) => Err(error.to_string()),
+    };
+
+    if let Err(error) = result {
+        error!(
+            %error,
+            "Failed to publish the change of the provider credentials of tenant {}", tenant_id
+        );
+    }
+}
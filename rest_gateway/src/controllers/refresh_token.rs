@@ -0,0 +1,151 @@
+use actix_web::http::header::ContentType;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use common::helper::error_chain_fmt;
+use serde_json::json;
+use sqlx::PgPool;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::domain::entities::refresh_token::RefreshToken;
+use crate::repositories::jwt_authentication_repository::{
+    JwtAuthenticationRepository, JwtAuthenticationRepositoryError,
+};
+use crate::repositories::refresh_token_postgres_repository::{
+    RefreshTokenPostgresRepository, RefreshTokenPostgresRepositoryError,
+};
+
+/// Refresh token controller: exchanges a refresh token for a new access token
+///
+/// The refresh token is rotated: it is revoked and a new refresh token of the same session is returned.
+/// A refresh token used twice was likely stolen: its whole session is then revoked,
+/// logging out both the client and the attacker.
+#[utoipa::path(
+    post,
+    path = "/refresh_token",
+    tag = "account",
+    request_body = RefreshTokenBodyData,
+    responses(
+        (status = 200, description = "New access and refresh tokens", body = RefreshTokenResponse),
+        (status = 401, description = "Invalid, expired or revoked refresh token"),
+    )
+)]
+#[tracing::instrument(
+    name = "Refresh access token",
+    skip(pool, refresh_token_repository, auth_repository, body)
+)]
+pub async fn refresh_token(
+    pool: web::Data<PgPool>,
+    refresh_token_repository: web::Data<RefreshTokenPostgresRepository>,
+    auth_repository: web::Data<JwtAuthenticationRepository>,
+    body: web::Json<RefreshTokenBodyData>,
+) -> Result<HttpResponse, RefreshTokenError> {
+    let token_hash = RefreshToken::hash(&body.refresh_token);
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    let stored_token = refresh_token_repository
+        .get_token_by_hash_for_update(&mut transaction, &token_hash)
+        .await?
+        .ok_or(RefreshTokenError::InvalidRefreshToken())?;
+
+    if stored_token.revoked_at.is_some() {
+        warn!(
+            session_id = ?stored_token.session_id,
+            user_id = ?stored_token.user_id,
+            "Reuse of a revoked refresh token, revoking its session"
+        );
+
+        refresh_token_repository
+            .revoke_session(&mut transaction, stored_token.session_id)
+            .await?;
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit SQL transaction to revoke a session")?;
+
+        return Err(RefreshTokenError::InvalidRefreshToken());
+    }
+
+    if stored_token.is_expired() {
+        info!(session_id = ?stored_token.session_id, "Expired refresh token");
+        return Err(RefreshTokenError::InvalidRefreshToken());
+    }
+
+    let (refresh_token, new_stored_token) = RefreshToken::generate(
+        stored_token.user_id,
+        stored_token.session_id,
+        auth_repository.refresh_token_expire_in_s(),
+    );
+
+    refresh_token_repository
+        .revoke_token(&mut transaction, stored_token.id)
+        .await?;
+    refresh_token_repository
+        .add_token(&mut transaction, &new_stored_token)
+        .await?;
+
+    let access_token = auth_repository
+        .create_session_token(&stored_token.user_id.to_string(), stored_token.session_id)?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to rotate a refresh token")?;
+
+    Ok(HttpResponse::Ok().json(RefreshTokenResponse {
+        access_token,
+        refresh_token,
+    }))
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, ToSchema)]
+pub struct RefreshTokenBodyData {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, ToSchema)]
+pub struct RefreshTokenResponse {
+    pub access_token: String,
+    pub refresh_token: String,
+}
+
+#[derive(thiserror::Error)]
+pub enum RefreshTokenError {
+    #[error("Invalid refresh token")]
+    InvalidRefreshToken(),
+    #[error(transparent)]
+    RepositoryError(#[from] RefreshTokenPostgresRepositoryError),
+    #[error(transparent)]
+    JwtAuthenticationRepositoryError(#[from] JwtAuthenticationRepositoryError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for RefreshTokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for RefreshTokenError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            RefreshTokenError::InvalidRefreshToken() => StatusCode::UNAUTHORIZED,
+            RefreshTokenError::RepositoryError(_)
+            | RefreshTokenError::JwtAuthenticationRepositoryError(_)
+            | RefreshTokenError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    #[tracing::instrument(name = "Response error from refresh_token controller", skip(self), fields(error = %self))]
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        HttpResponse::build(self.status_code())
+            .insert_header(ContentType::json())
+            .json(json!({ "error": self.to_string() }))
+    }
+}
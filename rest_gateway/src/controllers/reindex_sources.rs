@@ -0,0 +1,154 @@
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use common::constants::routing_keys::REINDEX_SOURCE_ROUTING_KEY;
+use common::core::tenancy::TenantMessageRepositories;
+use common::dtos::reindex_source::ReindexSourceDto;
+use common::helper::error_chain_fmt;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::repositories::source_meta_postgres_repository::SourceMetaPostgresRepository;
+
+#[derive(thiserror::Error)]
+pub enum ReindexSourcesError {
+    #[error("Invalid reindexing: {0}")]
+    InvalidReindexing(String),
+    #[error("Sources not found for the user: {0:?}")]
+    SourcesNotFound(Vec<Uuid>),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for ReindexSourcesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for ReindexSourcesError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ReindexSourcesError::InvalidReindexing(_) => StatusCode::BAD_REQUEST,
+            ReindexSourcesError::SourcesNotFound(_) => StatusCode::NOT_FOUND,
+            ReindexSourcesError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct ReindexSourcesBodyData {
+    pub user_id: Uuid,
+    /// Sources of the user to reindex, all its sources if `None`
+    pub source_ids: Option<Vec<Uuid>>,
+    /// Deletes the indexed contents of the sources before extracting them again
+    #[serde(default)]
+    pub wipe_index: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ReindexSourcesResponse {
+    pub nb_sources: usize,
+}
+
+/// Reindex all or some of the sources of a user, for ex after a change of the chunking or embedding parameters
+///
+/// The reindexing is asynchronous: a `reindex_source` command is sent for each source,
+/// consumed by the gateway instances which publish a new extraction job for the source.
+#[utoipa::path(
+    post,
+    path = "/admin/reindex",
+    tag = "admin",
+    request_body = ReindexSourcesBodyData,
+    responses(
+        (status = 202, description = "Number of sources whose reindexing is requested", body = ReindexSourcesResponse),
+        (status = 400, description = "Invalid reindexing"),
+        (status = 404, description = "Sources not found"),
+    ),
+    security(("admin_token" = []))
+)]
+#[tracing::instrument(
+    name = "Reindex sources",
+    skip(pool, source_meta_repository, message_repositories),
+    err
+)]
+pub async fn reindex_sources(
+    body: web::Json<ReindexSourcesBodyData>,
+    pool: web::Data<PgPool>,
+    source_meta_repository: web::Data<SourceMetaPostgresRepository>,
+    message_repositories: web::Data<TenantMessageRepositories>,
+) -> Result<HttpResponse, ReindexSourcesError> {
+    let ReindexSourcesBodyData {
+        user_id,
+        source_ids,
+        wipe_index,
+    } = body.into_inner();
+
+    let source_meta_ids = match source_ids {
+        None => source_meta_repository
+            .list_user_source_meta_ids(&**pool, user_id)
+            .await
+            .context("Failed to list the sources of the user")?,
+        Some(source_ids) if source_ids.is_empty() => {
+            return Err(ReindexSourcesError::InvalidReindexing(
+                "the source ids should not be empty, omit them to reindex all the sources"
+                    .to_string(),
+            ));
+        }
+        Some(source_ids) => {
+            let source_metas = source_meta_repository
+                .list_user_source_metas_by_ids(&**pool, user_id, &source_ids)
+                .await
+                .context("Failed to get the sources of the user")?;
+
+            let missing_ids: Vec<Uuid> = source_ids
+                .iter()
+                .filter(|id| {
+                    !source_metas
+                        .iter()
+                        .any(|source_meta| source_meta.id == **id)
+                })
+                .copied()
+                .collect();
+            if !missing_ids.is_empty() {
+                return Err(ReindexSourcesError::SourcesNotFound(missing_ids));
+            }
+
+            source_ids
+        }
+    };
+
+    // The commands are consumed by the gateway, which routes the extraction jobs to the tenant of the user
+    let message_rabbitmq_repository = message_repositories
+        .route(None)
+        .context("Could not route the reindexing commands")?;
+
+    for source_meta_id in source_meta_ids.iter() {
+        let json_message = serde_json::to_string(&ReindexSourceDto {
+            source_meta_id: *source_meta_id,
+            wipe_index,
+        })
+        .context("Could not serialize the reindexing command")?;
+
+        message_rabbitmq_repository
+            .publish(REINDEX_SOURCE_ROUTING_KEY, json_message.as_bytes())
+            .await
+            .context(format!(
+                "Could not send the reindexing of source {}",
+                source_meta_id
+            ))?;
+    }
+
+    info!(
+        "Sent the reindexing of {} sources of user {}",
+        source_meta_ids.len(),
+        user_id
+    );
+
+    Ok(HttpResponse::Accepted().json(ReindexSourcesResponse {
+        nb_sources: source_meta_ids.len(),
+    }))
+}
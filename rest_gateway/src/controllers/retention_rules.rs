@@ -0,0 +1,202 @@
+use crate::domain::entities::retention_rule::RetentionRule;
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::retention_rule_postgres_repository::RetentionRulePostgresRepository;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use common::helper::error_chain_fmt;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(thiserror::Error)]
+pub enum RetentionRuleError {
+    #[error("Invalid retention rule: {0}")]
+    InvalidRule(String),
+    #[error("No retention rule for the collection {0}")]
+    RuleNotFound(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for RetentionRuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for RetentionRuleError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            RetentionRuleError::InvalidRule(_) => StatusCode::BAD_REQUEST,
+            RetentionRuleError::RuleNotFound(_) => StatusCode::NOT_FOUND,
+            RetentionRuleError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct RetentionRuleBodyData {
+    /// The sources of the collection are deleted this number of days after they were added
+    pub retention_days: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct RetentionRuleResponse {
+    pub id: Uuid,
+    pub collection: String,
+    pub retention_days: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<RetentionRule> for RetentionRuleResponse {
+    fn from(value: RetentionRule) -> Self {
+        Self {
+            id: value.id,
+            collection: value.collection,
+            retention_days: value.retention_days,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+/// List the retention rules of the collections of a user
+#[utoipa::path(
+    get,
+    path = "/retention_rules",
+    tag = "retention_rules",
+    responses(
+        (status = 200, description = "Retention rules of the user", body = [RetentionRuleResponse]),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "List retention rules",
+    skip(pool, retention_rule_repository),
+    err
+)]
+pub async fn list_retention_rules(
+    pool: web::Data<PgPool>,
+    retention_rule_repository: web::Data<RetentionRulePostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, RetentionRuleError> {
+    let user_id = user_id.into_inner().0;
+    info!("Request for user_id: {}", user_id);
+
+    let rules = retention_rule_repository
+        .list_user_rules(pool.get_ref(), user_id)
+        .await
+        .context("Could not list the retention rules of the user")?;
+
+    Ok(HttpResponse::Ok().json(
+        rules
+            .into_iter()
+            .map(RetentionRuleResponse::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Set the retention of a collection of a user, replacing its previous retention
+///
+/// The already added sources of the collection expire with the new retention.
+#[utoipa::path(
+    put,
+    path = "/retention_rules/{collection}",
+    tag = "retention_rules",
+    params(("collection" = String, Path, description = "Collection of the sources")),
+    request_body = RetentionRuleBodyData,
+    responses(
+        (status = 200, description = "Saved retention rule", body = RetentionRuleResponse),
+        (status = 400, description = "Invalid retention rule"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "Save retention rule",
+    skip(pool, retention_rule_repository),
+    err
+)]
+pub async fn save_retention_rule(
+    collection: web::Path<String>,
+    body: web::Json<RetentionRuleBodyData>,
+    pool: web::Data<PgPool>,
+    retention_rule_repository: web::Data<RetentionRulePostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, RetentionRuleError> {
+    let user_id = user_id.into_inner().0;
+    let collection = collection.into_inner();
+    info!("Request for user_id: {}", user_id);
+
+    if collection.trim().is_empty() {
+        return Err(RetentionRuleError::InvalidRule(
+            "the collection should not be empty".to_string(),
+        ));
+    }
+    if body.retention_days <= 0 {
+        return Err(RetentionRuleError::InvalidRule(
+            "the retention should be of at least 1 day".to_string(),
+        ));
+    }
+
+    let now = Utc::now();
+    let rule = retention_rule_repository
+        .save_rule(
+            pool.get_ref(),
+            &RetentionRule {
+                id: Uuid::new_v4(),
+                user_id,
+                collection,
+                retention_days: body.retention_days,
+                created_at: now,
+                updated_at: now,
+            },
+        )
+        .await
+        .context("Could not save the retention rule")?;
+
+    Ok(HttpResponse::Ok().json(RetentionRuleResponse::from(rule)))
+}
+
+/// Delete the retention of a collection of a user: its sources are kept
+#[utoipa::path(
+    delete,
+    path = "/retention_rules/{collection}",
+    tag = "retention_rules",
+    params(("collection" = String, Path, description = "Collection of the sources")),
+    responses(
+        (status = 204, description = "Deleted retention rule"),
+        (status = 404, description = "Retention rule not found"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "Delete retention rule",
+    skip(pool, retention_rule_repository),
+    err
+)]
+pub async fn delete_retention_rule(
+    collection: web::Path<String>,
+    pool: web::Data<PgPool>,
+    retention_rule_repository: web::Data<RetentionRulePostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, RetentionRuleError> {
+    let user_id = user_id.into_inner().0;
+    let collection = collection.into_inner();
+    info!("Request for user_id: {}", user_id);
+
+    let deleted = retention_rule_repository
+        .delete_user_rule(pool.get_ref(), user_id, &collection)
+        .await
+        .context("Could not delete the retention rule")?;
+
+    if !deleted {
+        return Err(RetentionRuleError::RuleNotFound(collection));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,253 @@
+use crate::controllers::search_content::{
+    search_response, validate_search, SearchContentBodyData, SearchContentError, SearchHistory,
+    SearchServices,
+};
+use crate::domain::entities::{saved_search::SavedSearch, search_result::SearchResult};
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::saved_search_postgres_repository::SavedSearchPostgresRepository;
+use crate::responders::sparse_fields::{FieldSet, FieldsQuery};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use common::core::tenancy::TenantMessageRepositories;
+use common::helper::error_chain_fmt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::PgPool;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(thiserror::Error)]
+pub enum SavedSearchError {
+    #[error("Invalid saved search: {0}")]
+    InvalidSavedSearch(String),
+    #[error("Saved search {0} not found")]
+    SavedSearchNotFound(Uuid),
+    #[error(transparent)]
+    SearchError(#[from] SearchContentError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for SavedSearchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for SavedSearchError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            SavedSearchError::InvalidSavedSearch(_) => StatusCode::BAD_REQUEST,
+            SavedSearchError::SavedSearchNotFound(_) => StatusCode::NOT_FOUND,
+            SavedSearchError::SearchError(error) => error.status_code(),
+            SavedSearchError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct SaveSearchBodyData {
+    pub name: String,
+    /// Search to run again, as sent to `/search`
+    pub search: SearchContentBodyData,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct SavedSearchResponse {
+    pub id: Uuid,
+    pub name: String,
+    /// Search run again, as sent to `/search`
+    #[schema(value_type = Object)]
+    pub search: JsonValue,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<SavedSearch> for SavedSearchResponse {
+    fn from(value: SavedSearch) -> Self {
+        Self {
+            id: value.id,
+            name: value.name,
+            search: value.request,
+            created_at: value.created_at,
+        }
+    }
+}
+
+/// Save a search of a user under a name, to run it again by its id
+///
+/// The search is checked as when it is run.
+#[utoipa::path(
+    post,
+    path = "/saved_searches",
+    tag = "search",
+    request_body = SaveSearchBodyData,
+    responses(
+        (status = 201, description = "Saved search", body = SavedSearchResponse),
+        (status = 400, description = "Empty name, or invalid query, fields or filters"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(name = "Save search", skip(pool, saved_search_repository), err)]
+pub async fn save_search(
+    body: web::Json<SaveSearchBodyData>,
+    pool: web::Data<PgPool>,
+    saved_search_repository: web::Data<SavedSearchPostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, SavedSearchError> {
+    let user_id = user_id.into_inner().0;
+    let SaveSearchBodyData { name, search } = body.into_inner();
+    info!("Request for user_id: {}", user_id);
+
+    if name.trim().is_empty() {
+        return Err(SavedSearchError::InvalidSavedSearch(
+            "the name should not be empty".to_string(),
+        ));
+    }
+    validate_search(&search)?;
+
+    let saved_search = SavedSearch {
+        id: Uuid::new_v4(),
+        user_id,
+        name,
+        request: serde_json::to_value(&search).context("Failed to serialize the search")?,
+        created_at: Utc::now(),
+    };
+    saved_search_repository
+        .add_saved_search(pool.get_ref(), &saved_search)
+        .await
+        .context("Failed to save the search")?;
+
+    Ok(HttpResponse::Created().json(SavedSearchResponse::from(saved_search)))
+}
+
+/// List the saved searches of a user, from the most recent
+#[utoipa::path(
+    get,
+    path = "/saved_searches",
+    tag = "search",
+    responses(
+        (status = 200, description = "Saved searches of the user", body = [SavedSearchResponse]),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(name = "List saved searches", skip(pool, saved_search_repository), err)]
+pub async fn list_saved_searches(
+    pool: web::Data<PgPool>,
+    saved_search_repository: web::Data<SavedSearchPostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, SavedSearchError> {
+    let user_id = user_id.into_inner().0;
+    info!("Request for user_id: {}", user_id);
+
+    let saved_searches = saved_search_repository
+        .list_user_saved_searches(pool.get_ref(), user_id)
+        .await
+        .context("Failed to list the saved searches")?;
+
+    Ok(HttpResponse::Ok().json(
+        saved_searches
+            .into_iter()
+            .map(SavedSearchResponse::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Delete a saved search of a user
+#[utoipa::path(
+    delete,
+    path = "/saved_searches/{saved_search_id}",
+    tag = "search",
+    params(("saved_search_id" = Uuid, Path, description = "ID of the saved search")),
+    responses(
+        (status = 204, description = "Deleted saved search"),
+        (status = 404, description = "Saved search not found"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(name = "Delete saved search", skip(pool, saved_search_repository), err)]
+pub async fn delete_saved_search(
+    saved_search_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    saved_search_repository: web::Data<SavedSearchPostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, SavedSearchError> {
+    let user_id = user_id.into_inner().0;
+    let saved_search_id = saved_search_id.into_inner();
+
+    let is_deleted = saved_search_repository
+        .delete_user_saved_search(pool.get_ref(), user_id, saved_search_id)
+        .await
+        .context("Failed to delete the saved search")?;
+
+    if !is_deleted {
+        return Err(SavedSearchError::SavedSearchNotFound(saved_search_id));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Run a saved search of a user again, as `/search` with its saved body
+///
+/// The search is recorded in the search history of the user, as any other search.
+#[utoipa::path(
+    post,
+    path = "/saved_searches/{saved_search_id}/search",
+    tag = "search",
+    params(
+        ("saved_search_id" = Uuid, Path, description = "ID of the saved search"),
+        FieldsQuery
+    ),
+    responses(
+        (status = 200, description = "Found contents", body = SearchResults),
+        (status = 400, description = "Invalid fields"),
+        (status = 404, description = "Saved search not found"),
+        (status = 502, description = "The reranker failed to rescore the found contents"),
+        (status = 503, description = "The saved search is reranked but no reranker is configured"),
+        (status = 504, description = "A search backend did not answer in time"),
+    ),
+    security(("access_token" = []), ("api_key" = ["search"]))
+)]
+#[tracing::instrument(
+    name = "Run saved search",
+    skip(pool, search_services, message_repositories, search_history),
+    err
+)]
+pub async fn run_saved_search(
+    saved_search_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    search_services: web::Data<SearchServices>,
+    message_repositories: web::Data<TenantMessageRepositories>,
+    search_history: web::Data<SearchHistory>,
+    query: web::Query<FieldsQuery>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, SavedSearchError> {
+    let user_id = user_id.into_inner().0;
+    let saved_search_id = saved_search_id.into_inner();
+    let fields = FieldSet::try_parse_query::<SearchResult>(query.fields.as_deref())
+        .map_err(SearchContentError::from)?;
+
+    let saved_search = search_history
+        .saved_search_repository
+        .get_user_saved_search(pool.get_ref(), user_id, saved_search_id)
+        .await
+        .context("Failed to get the saved search")?
+        .ok_or(SavedSearchError::SavedSearchNotFound(saved_search_id))?;
+    let search: SearchContentBodyData =
+        serde_json::from_value(saved_search.request).context("Failed to parse the saved search")?;
+
+    let response = search_response(
+        &pool,
+        &search_services,
+        &message_repositories,
+        &search,
+        fields,
+        user_id,
+    )
+    .await?;
+    search_history.record(&pool, user_id, &search).await;
+
+    Ok(HttpResponse::Ok().json(response))
+}
@@ -1,28 +1,685 @@
 use actix_web::http::StatusCode;
 use actix_web::{web, HttpResponse, ResponseError};
-use common::core::rabbitmq_message_repository::RabbitMQMessageRepositoryError;
-use common::dtos::fulltext_search_response::FulltextSearchResponseDto;
-use common::dtos::templates::rpc_response::RpcResponseEncodingError;
+use chrono::{DateTime, Utc};
+use common::core::rabbitmq_message_repository::{
+    RabbitMQMessageRepository, RabbitMQMessageRepositoryError,
+};
+use common::dtos::fulltext_search_response::{
+    FulltextSearchResponseData, FulltextSearchResponseDto, ResultContent,
+};
+use common::dtos::semantic_search_response::SemanticSearchResponseDto;
+use common::dtos::templates::rpc_response::{
+    RpcErrorStatus, RpcResponse, RpcResponseEncodingError,
+};
 use common::{
-    constants::routing_keys::SEARCH_FULLTEXT_ROUTING_KEY,
-    core::rabbitmq_message_repository::RabbitMQMessageRepository,
-    dtos::fulltext_search_request::{FulltextSearchRequestDto, FulltextSearchRequestDtoError},
+    constants::routing_keys::{SEARCH_FULLTEXT_ROUTING_KEY, SEARCH_SEMANTIC_ROUTING_KEY},
+    core::tenancy::{TenancyError, TenantMessageRepositories},
+    dtos::extracted_content::{
+        is_chapter_filter, is_keyword_or_entity_filter, is_language_code, is_structured_field_name,
+    },
+    dtos::fulltext_search_request::{
+        FulltextSearchRequestDto, FulltextSearchRequestDtoError, SearchSortDto,
+    },
+    dtos::semantic_search_request::{SemanticSearchRequestDto, SemanticSearchRequestDtoError},
     helper::error_chain_fmt,
 };
+use futures::{future::try_join_all, stream, try_join};
+use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
-use tracing::info;
+use sqlx::PgPool;
+use std::{collections::BTreeMap, convert::Infallible, sync::Arc};
+use tracing::{error, info};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    configuration::SearchHistorySettings,
+    domain::entities::{
+        saved_search::SearchHistoryEntry,
+        search_result::{
+            fuse_rankings, merge_shard_rankings, rerank_results, sort_by_metadata,
+            BackendHitCounts, SearchAggregations, SearchPage, SearchResult, SearchSource,
+        },
+        source_meta::SourceType,
+    },
+    middlewares::jwt_authentication::middleware::UserIdFromToken,
+    repositories::{
+        fulltext_shard_postgres_repository::{
+            FulltextShardPostgresRepository, FulltextShardPostgresRepositoryError,
+        },
+        rerank_port::{RerankError, RerankPort},
+        saved_search_postgres_repository::SavedSearchPostgresRepository,
+        source_meta_postgres_repository::{
+            SourceMetaPostgresRepository, SourceMetaPostgresRepositoryError,
+            SourceMetaSearchFilters,
+        },
+        user_postgres_repository::{UserPostgresRepository, UserPostgresRepositoryError},
+    },
+    responders::{
+        ndjson::ndjson_response,
+        sparse_fields::{FieldSet, FieldsQuery, InvalidFieldsError, Sparse},
+    },
+};
 
-#[tracing::instrument(name = "Search content handler", skip(message_rabbitmq_repository))]
+/// Maximum number of source ids a search can be filtered on
+const MAX_FILTERED_SOURCE_IDS: usize = 1000;
+
+/// Number of results per page when neither `hits_per_page` nor `limit` is given
+const DEFAULT_HITS_PER_PAGE: usize = 10;
+
+/// Maximum number of results per page
+const MAX_HITS_PER_PAGE: usize = 100;
+
+/// Maximum number of results up to the end of a page: the number of hits the full-text index can page through
+const MAX_PAGED_HITS: usize = 1000;
+
+/// Resolves the contents searched by a user: their tenant, its full-text shards and the sources they filter on
+///
+/// Shared by the searches, the saved searches, the chunks of a source and the questions.
+pub struct SearchServices {
+    pub user_repository: UserPostgresRepository,
+    pub fulltext_shard_repository: FulltextShardPostgresRepository,
+    pub source_meta_repository: SourceMetaPostgresRepository,
+    pub reranker: Arc<dyn RerankPort>,
+}
+
+impl SearchServices {
+    pub fn new(reranker: Arc<dyn RerankPort>) -> Self {
+        Self {
+            user_repository: UserPostgresRepository::new(),
+            fulltext_shard_repository: FulltextShardPostgresRepository::new(),
+            source_meta_repository: SourceMetaPostgresRepository::new(),
+            reranker,
+        }
+    }
+}
+
+/// Searches the contents of the user, with their number of hits per source, collection and source type
+///
+/// Also documents `search_content_ndjson`, routed on the same path when NDJSON is accepted.
+#[utoipa::path(
+    post,
+    path = "/search",
+    tag = "search",
+    params(FieldsQuery),
+    request_body = SearchContentBodyData,
+    responses(
+        (
+            status = 200,
+            description = "Found contents, or the found contents streamed as NDJSON when it is accepted",
+            content(
+                ("application/json" = SearchResults),
+                ("application/x-ndjson" = SearchResult)
+            )
+        ),
+        (status = 400, description = "Invalid query, fields or filters"),
+        (status = 502, description = "The reranker failed to rescore the found contents"),
+        (status = 503, description = "A reranking is requested but no reranker is configured"),
+        (status = 504, description = "A search backend did not answer in time"),
+    ),
+    security(("access_token" = []), ("api_key" = ["search"]))
+)]
+#[tracing::instrument(
+    name = "Search content handler",
+    skip(pool, search_services, message_repositories, search_history)
+)]
 pub async fn search_content(
-    message_rabbitmq_repository: web::Data<RabbitMQMessageRepository>,
+    pool: web::Data<PgPool>,
+    search_services: web::Data<SearchServices>,
+    message_repositories: web::Data<TenantMessageRepositories>,
+    search_history: web::Data<SearchHistory>,
     body: web::Json<SearchContentBodyData>,
+    query: web::Query<FieldsQuery>,
+    user_id: web::ReqData<UserIdFromToken>,
 ) -> Result<HttpResponse, SearchContentError> {
-    info!("Searching contents for query: {}", body.query);
+    let user_id = user_id.into_inner().0;
+    let fields = FieldSet::try_parse_query::<SearchResult>(query.fields.as_deref())?;
+    let response = search_response(
+        &pool,
+        &search_services,
+        &message_repositories,
+        &body,
+        fields,
+        user_id,
+    )
+    .await?;
+    search_history.record(&pool, user_id, &body).await;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Searches the contents of a user, with their number of hits per source, collection and source type,
+/// keeping only the requested fields of the found contents
+pub(crate) async fn search_response(
+    pool: &PgPool,
+    services: &SearchServices,
+    message_repositories: &TenantMessageRepositories,
+    body: &SearchContentBodyData,
+    fields: Option<Arc<FieldSet>>,
+    user_id: Uuid,
+) -> Result<SearchContentResponse<Sparse<SearchResult>>, SearchContentError> {
+    let found_results = search(pool, services, message_repositories, body, user_id).await?;
+
+    let source_meta_ids: Vec<Uuid> = found_results
+        .hit_counts
+        .iter()
+        .flat_map(|hit_counts| hit_counts.sources.keys())
+        .copied()
+        .collect();
+    let source_metas = services
+        .source_meta_repository
+        .list_user_source_metas_by_ids(pool, user_id, &source_meta_ids)
+        .await?;
+
+    Ok(SearchContentResponse {
+        aggregations: SearchAggregations::from_hit_counts(&found_results.hit_counts, &source_metas),
+        total_hits: found_results.total_hits,
+        total_pages: found_results.page.total_pages(found_results.total_hits),
+        suggestions: found_results.suggestions,
+        results: found_results
+            .results
+            .into_iter()
+            .map(|result| Sparse::new(result, fields.clone()))
+            .collect(),
+    })
+}
+
+/// Search history of the users, keeping only their latest searches
+pub struct SearchHistory {
+    pub saved_search_repository: SavedSearchPostgresRepository,
+    pub settings: SearchHistorySettings,
+}
+
+impl SearchHistory {
+    pub fn new(settings: SearchHistorySettings) -> Self {
+        Self {
+            saved_search_repository: SavedSearchPostgresRepository::new(),
+            settings,
+        }
+    }
+
+    /// Records a search of a user, unless the history is disabled
+    ///
+    /// The history is only informative: a search is answered even if it could not be recorded.
+    pub(crate) async fn record(&self, pool: &PgPool, user_id: Uuid, body: &SearchContentBodyData) {
+        if !self.settings.enabled {
+            return;
+        }
+
+        let request = match serde_json::to_value(body) {
+            Ok(request) => request,
+            Err(error) => {
+                error!(?error, "Failed to serialize the search to record");
+                return;
+            }
+        };
+        let entry = SearchHistoryEntry {
+            id: Uuid::new_v4(),
+            user_id,
+            query: body.query.clone(),
+            request,
+            searched_at: Utc::now(),
+        };
+
+        if let Err(error) = self
+            .saved_search_repository
+            .add_search_history_entry(pool, &entry)
+            .await
+        {
+            error!(?error, "Failed to record the search in the search history");
+            return;
+        }
+        if let Err(error) = self
+            .saved_search_repository
+            .prune_user_search_history(pool, user_id, self.settings.max_entries_per_user.into())
+            .await
+        {
+            error!(?error, "Failed to prune the search history");
+        }
+    }
+}
+
+/// Streams the found contents as NDJSON, one content per line, without their aggregations
+#[tracing::instrument(
+    name = "Search content as NDJSON handler",
+    skip(pool, search_services, message_repositories, search_history)
+)]
+pub async fn search_content_ndjson(
+    pool: web::Data<PgPool>,
+    search_services: web::Data<SearchServices>,
+    message_repositories: web::Data<TenantMessageRepositories>,
+    search_history: web::Data<SearchHistory>,
+    body: web::Json<SearchContentBodyData>,
+    query: web::Query<FieldsQuery>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, SearchContentError> {
+    let user_id = user_id.into_inner().0;
+    let fields = FieldSet::try_parse_query::<SearchResult>(query.fields.as_deref())?;
+    let found_results = search(
+        &pool,
+        &search_services,
+        &message_repositories,
+        &body,
+        user_id,
+    )
+    .await?;
+    search_history.record(&pool, user_id, &body).await;
+
+    Ok(ndjson_response(stream::iter(
+        found_results
+            .results
+            .into_iter()
+            .map(move |result| Ok::<_, Infallible>(Sparse::new(result, fields.clone()))),
+    )))
+}
+
+/// Results of the backends of a search
+struct FoundResults {
+    /// Results of the requested page
+    results: Vec<SearchResult>,
+    page: SearchPage,
+    /// Number of found contents, approximated from the first found ones when searched semantically
+    total_hits: u64,
+    /// Queries correcting the spelling of the query, from the full-text search
+    suggestions: Vec<String>,
+    /// Number of hits counted by each backend
+    hit_counts: Vec<BackendHitCounts>,
+}
+
+/// Searches with the backends of the requested mode, merging their results with reciprocal rank fusion
+///
+/// The filters on the sources are resolved to the ids of the matching sources of the user, searched by the backends.
+/// Unless only searched in a single full-text shard, the backends return all the results up to the end of the page,
+/// which are merged and sorted before the page is sliced from them.
+/// When reranked, the backends return at least the candidates of the reranker, rescored before being sorted.
+async fn search(
+    pool: &PgPool,
+    services: &SearchServices,
+    message_repositories: &TenantMessageRepositories,
+    body: &SearchContentBodyData,
+    user_id: Uuid,
+) -> Result<FoundResults, SearchContentError> {
+    let SearchServices {
+        user_repository,
+        fulltext_shard_repository,
+        source_meta_repository,
+        reranker,
+    } = services;
+    info!(
+        "Searching contents in {:?} mode for query: {}",
+        body.mode, body.query
+    );
+    let page = validate_search(body)?;
+    if body.rerank && !reranker.is_enabled() {
+        return Err(SearchContentError::RerankDisabled);
+    }
+
+    let source_meta_ids =
+        filtered_source_meta_ids(pool, source_meta_repository, body, user_id).await?;
+    if source_meta_ids.as_ref().is_some_and(Vec::is_empty) {
+        info!("No source matches the filters of the search");
+        return Ok(FoundResults {
+            results: vec![],
+            page,
+            total_hits: 0,
+            suggestions: vec![],
+            hit_counts: vec![],
+        });
+    }
+    let source_meta_ids = source_meta_ids.as_deref();
+
+    // Only searches the contents of the tenant of the user, and the search services only return the contents of the user
+    let tenant_id = user_repository.get_user_tenant_id(pool, user_id).await?;
+    let message_rabbitmq_repository = message_repositories.route(tenant_id.as_deref())?;
+    let fulltext_shards = match body.mode {
+        SearchMode::Fulltext | SearchMode::Hybrid => {
+            fulltext_shard_repository
+                .list_tenant_shards(pool, tenant_id.as_deref())
+                .await?
+        }
+        SearchMode::Semantic => vec![],
+    };
+
+    let window_page = match body.rerank {
+        true => {
+            let mut candidates_page = page.first_page_of_candidates(reranker.candidates());
+            candidates_page.hits_per_page = candidates_page.hits_per_page.min(MAX_PAGED_HITS);
+            candidates_page
+        }
+        false => page.first_page_of_window(),
+    };
+    let (results, total_hits, suggestions, hit_counts) = match body.mode {
+        SearchMode::Fulltext => {
+            // The full-text search already returns the results of the page, merged from its shards,
+            // unless they are reranked
+            let fulltext_page = if body.rerank { &window_page } else { &page };
+            let mut fulltext_data = search_fulltext(
+                message_rabbitmq_repository,
+                body,
+                fulltext_page,
+                source_meta_ids,
+                user_id,
+                &fulltext_shards,
+            )
+            .await?;
+            let suggestions = std::mem::take(&mut fulltext_data.suggestions);
+            let (fulltext_results, total_hits, fulltext_hit_counts) =
+                split_hit_counts(fulltext_data);
+
+            (
+                fuse_rankings(vec![(SearchSource::Fulltext, fulltext_results)]),
+                total_hits,
+                suggestions,
+                vec![fulltext_hit_counts],
+            )
+        }
+        SearchMode::Semantic => {
+            let semantic_results = search_semantic(
+                message_rabbitmq_repository,
+                body,
+                &window_page,
+                source_meta_ids,
+                user_id,
+            )
+            .await?;
+            // Only the found vector hits are counted
+            let semantic_hit_counts = BackendHitCounts::from_found_contents(&semantic_results);
+            let total_hits = semantic_results.len() as u64;
+
+            (
+                fuse_rankings(vec![(SearchSource::Semantic, semantic_results)]),
+                total_hits,
+                vec![],
+                vec![semantic_hit_counts],
+            )
+        }
+        SearchMode::Hybrid => {
+            // The RPC responses are matched to their calls by correlation id: the calls can share the repository
+            let (mut fulltext_data, semantic_results) = try_join!(
+                search_fulltext(
+                    message_rabbitmq_repository,
+                    body,
+                    &window_page,
+                    source_meta_ids,
+                    user_id,
+                    &fulltext_shards,
+                ),
+                search_semantic(
+                    message_rabbitmq_repository,
+                    body,
+                    &window_page,
+                    source_meta_ids,
+                    user_id
+                )
+            )?;
+            let suggestions = std::mem::take(&mut fulltext_data.suggestions);
+            let (fulltext_results, fulltext_total_hits, fulltext_hit_counts) =
+                split_hit_counts(fulltext_data);
+            let semantic_hit_counts = BackendHitCounts::from_found_contents(&semantic_results);
+
+            let fused_results = fuse_rankings(vec![
+                (SearchSource::Fulltext, fulltext_results),
+                (SearchSource::Semantic, semantic_results),
+            ]);
+            let total_hits = fulltext_total_hits.max(fused_results.len() as u64);
+
+            (
+                fused_results,
+                total_hits,
+                suggestions,
+                vec![fulltext_hit_counts, semantic_hit_counts],
+            )
+        }
+    };
+    let results = match (body.mode, body.rerank) {
+        (SearchMode::Fulltext, false) => results,
+        (_, false) => page_of_results(results, &page),
+        (_, true) => page_of_results(
+            rerank(reranker.as_ref(), &body.query, results).await?,
+            &page,
+        ),
+    };
+
+    Ok(FoundResults {
+        results,
+        page,
+        total_hits,
+        suggestions,
+        hit_counts,
+    })
+}
+
+/// Checks the query parameters and filters of a search, before running or saving it
+///
+/// # Returns
+/// The requested page of the search
+pub(crate) fn validate_search(
+    body: &SearchContentBodyData,
+) -> Result<SearchPage, SearchContentError> {
+    if let Some(language) = body
+        .language
+        .as_deref()
+        .filter(|language| !is_language_code(language))
+    {
+        return Err(SearchContentError::InvalidLanguage(language.to_string()));
+    }
+    if let Some(name) = body
+        .fields
+        .keys()
+        .find(|name| !is_structured_field_name(name))
+    {
+        return Err(SearchContentError::InvalidFieldName(name.to_string()));
+    }
+    if let Some(chapter) = body
+        .chapter
+        .as_deref()
+        .filter(|chapter| !is_chapter_filter(chapter))
+    {
+        return Err(SearchContentError::InvalidChapter(chapter.to_string()));
+    }
+    if let Some(keyword) = body
+        .keyword
+        .as_deref()
+        .filter(|keyword| !is_keyword_or_entity_filter(keyword))
+    {
+        return Err(SearchContentError::InvalidKeyword(keyword.to_string()));
+    }
+    if let Some(entity) = body
+        .entity
+        .as_deref()
+        .filter(|entity| !is_keyword_or_entity_filter(entity))
+    {
+        return Err(SearchContentError::InvalidEntity(entity.to_string()));
+    }
+    if body.source_ids.len() > MAX_FILTERED_SOURCE_IDS {
+        return Err(SearchContentError::TooManySourceIds(body.source_ids.len()));
+    }
+    if let (Some(uploaded_after), Some(uploaded_before)) =
+        (body.uploaded_after, body.uploaded_before)
+    {
+        if uploaded_after >= uploaded_before {
+            return Err(SearchContentError::InvalidUploadRange {
+                uploaded_after,
+                uploaded_before,
+            });
+        }
+    }
 
+    search_page(body)
+}
+
+/// Requested page of the search, with `limit` as its number of results when `hits_per_page` is not given
+fn search_page(body: &SearchContentBodyData) -> Result<SearchPage, SearchContentError> {
+    let page = SearchPage {
+        page: body.page.unwrap_or(1),
+        hits_per_page: body
+            .hits_per_page
+            .or(body.limit)
+            .unwrap_or(DEFAULT_HITS_PER_PAGE),
+        sort: body.sort.into(),
+    };
+
+    if page.page == 0 {
+        return Err(SearchContentError::InvalidPage(page.page));
+    }
+    if !(1..=MAX_HITS_PER_PAGE).contains(&page.hits_per_page) {
+        return Err(SearchContentError::InvalidHitsPerPage(page.hits_per_page));
+    }
+    if page.window() > MAX_PAGED_HITS {
+        return Err(SearchContentError::PageOutOfRange {
+            page: page.page,
+            hits_per_page: page.hits_per_page,
+        });
+    }
+
+    Ok(page)
+}
+
+/// Rescores the fused results with the reranker, from the most relevant to the query
+async fn rerank(
+    reranker: &dyn RerankPort,
+    query: &str,
+    mut results: Vec<SearchResult>,
+) -> Result<Vec<SearchResult>, SearchContentError> {
+    if results.is_empty() {
+        return Ok(results);
+    }
+
+    let documents: Vec<&str> = results
+        .iter()
+        .map(|result| result.content.as_str())
+        .collect();
+    let scores = reranker.rerank(query, &documents).await?;
+    info!("Reranked {} candidates", scores.len());
+    rerank_results(&mut results, &scores);
+
+    Ok(results)
+}
+
+fn page_of_results(mut results: Vec<SearchResult>, page: &SearchPage) -> Vec<SearchResult> {
+    sort_by_metadata(&mut results, page.sort, |result| &result.metadata);
+    page.slice(results)
+}
+
+/// Found contents of the full-text search, their total number, and the hits it counted over all the matches
+fn split_hit_counts(
+    data: FulltextSearchResponseData,
+) -> (Vec<ResultContent>, u64, BackendHitCounts) {
+    // The hits per source are counted over all the matches, by a service not returning their total yet
+    let total_hits = data
+        .total_hits
+        .unwrap_or_else(|| data.source_hit_counts.values().sum());
+    let hit_counts = BackendHitCounts {
+        sources: data.source_hit_counts,
+        languages: data.language_hit_counts,
+        chapters: data.chapter_hit_counts,
+        keywords: data.keyword_hit_counts,
+        entities: data.entity_hit_counts,
+    };
+
+    (data.results, total_hits, hit_counts)
+}
+
+/// Searches all the shards of the full-text index of the tenant, merging their rankings into the page
+/// and summing their hits per source, language, chapter, keyword and entity
+async fn search_fulltext(
+    message_rabbitmq_repository: &RabbitMQMessageRepository,
+    body: &SearchContentBodyData,
+    page: &SearchPage,
+    source_meta_ids: Option<&[Uuid]>,
+    user_id: Uuid,
+    shards: &[u32],
+) -> Result<FulltextSearchResponseData, SearchContentError> {
+    if let [shard] = shards {
+        return search_fulltext_shard(
+            message_rabbitmq_repository,
+            body,
+            page,
+            source_meta_ids,
+            user_id,
+            *shard,
+        )
+        .await;
+    }
+
+    // The results of the page are among the first ones of each shard
+    let window_page = page.first_page_of_window();
+    let shard_data = try_join_all(shards.iter().map(|shard| {
+        search_fulltext_shard(
+            message_rabbitmq_repository,
+            body,
+            &window_page,
+            source_meta_ids,
+            user_id,
+            *shard,
+        )
+    }))
+    .await?;
+
+    let mut merged_data = FulltextSearchResponseData {
+        total_hits: shard_data.iter().map(|data| data.total_hits).sum(),
+        ..Default::default()
+    };
+    let mut shard_rankings = vec![];
+    for data in shard_data {
+        // A source is routed to a single shard
+        merged_data.source_hit_counts.extend(data.source_hit_counts);
+        for (language, count) in data.language_hit_counts {
+            *merged_data.language_hit_counts.entry(language).or_default() += count;
+        }
+        for (chapter, count) in data.chapter_hit_counts {
+            *merged_data.chapter_hit_counts.entry(chapter).or_default() += count;
+        }
+        for (keyword, count) in data.keyword_hit_counts {
+            *merged_data.keyword_hit_counts.entry(keyword).or_default() += count;
+        }
+        for (entity, count) in data.entity_hit_counts {
+            *merged_data.entity_hit_counts.entry(entity).or_default() += count;
+        }
+        for suggestion in data.suggestions {
+            if !merged_data.suggestions.contains(&suggestion) {
+                merged_data.suggestions.push(suggestion);
+            }
+        }
+        shard_rankings.push(data.results);
+    }
+    let merged_results = match page.sort {
+        SearchSortDto::Relevance => merge_shard_rankings(shard_rankings, Some(page.window())),
+        sort => {
+            // The shards sort their contents by the same metadata
+            let mut results: Vec<ResultContent> = shard_rankings.into_iter().flatten().collect();
+            sort_by_metadata(&mut results, sort, |content| &content.metadata);
+            results
+        }
+    };
+    merged_data.results = page.slice(merged_results);
+
+    Ok(merged_data)
+}
+
+async fn search_fulltext_shard(
+    message_rabbitmq_repository: &RabbitMQMessageRepository,
+    body: &SearchContentBodyData,
+    page: &SearchPage,
+    source_meta_ids: Option<&[Uuid]>,
+    user_id: Uuid,
+    shard: u32,
+) -> Result<FulltextSearchResponseData, SearchContentError> {
     let request = FulltextSearchRequestDto {
         metadata: JsonValue::Null,
         query: body.query.clone(),
-        limit: body.limit,
+        limit: None,
+        page: Some(page.page),
+        hits_per_page: Some(page.hits_per_page),
+        sort: page.sort,
+        user_id,
+        shard,
+        language: body.language.clone(),
+        fields: body.fields.clone(),
+        source_meta_ids: source_meta_ids.map(<[Uuid]>::to_vec),
+        chapter: body.chapter.clone(),
+        keyword: body.keyword.as_deref().map(str::to_lowercase),
+        entity: body.entity.clone(),
     };
     let request = request.try_serializing()?;
 
@@ -30,15 +687,202 @@ pub async fn search_content(
         .rpc_call(SEARCH_FULLTEXT_ROUTING_KEY, request.as_bytes(), None)
         .await?;
 
-    let response = FulltextSearchResponseDto::try_parsing(&response)?;
+    match FulltextSearchResponseDto::try_parsing(&response)? {
+        RpcResponse::Ok { data } => Ok(data),
+        RpcResponse::Error { status, message } => {
+            Err(SearchContentError::FulltextSearchError(status, message))
+        }
+    }
+}
 
-    Ok(HttpResponse::Ok().json(response))
+/// Ids of the sources of the user matching the filters of a search, `None` without filter on the sources
+async fn filtered_source_meta_ids(
+    pool: &PgPool,
+    source_meta_repository: &SourceMetaPostgresRepository,
+    body: &SearchContentBodyData,
+    user_id: Uuid,
+) -> Result<Option<Vec<Uuid>>, SearchContentError> {
+    let source_filters = SourceMetaSearchFilters {
+        source_meta_ids: body.source_ids.clone(),
+        source_type: body.source_type.clone(),
+        added_after: body.uploaded_after,
+        added_before: body.uploaded_before,
+    };
+    if source_filters.is_empty() {
+        return Ok(None);
+    }
+
+    let source_meta_ids = source_meta_repository
+        .filter_user_source_meta_ids(pool, user_id, &source_filters)
+        .await?;
+    Ok(Some(source_meta_ids))
+}
+
+/// Searches the contents of a user semantically closest to the query, on the first page of a search,
+/// with the sources they were extracted from
+pub(crate) async fn search_semantic_contents(
+    pool: &PgPool,
+    services: &SearchServices,
+    message_repositories: &TenantMessageRepositories,
+    body: &SearchContentBodyData,
+    user_id: Uuid,
+) -> Result<Vec<ResultContent>, SearchContentError> {
+    let page = validate_search(body)?;
+
+    let source_meta_ids =
+        filtered_source_meta_ids(pool, &services.source_meta_repository, body, user_id).await?;
+    if source_meta_ids.as_ref().is_some_and(Vec::is_empty) {
+        info!("No source matches the filters of the search");
+        return Ok(vec![]);
+    }
+
+    let tenant_id = services
+        .user_repository
+        .get_user_tenant_id(pool, user_id)
+        .await?;
+    let message_rabbitmq_repository = message_repositories.route(tenant_id.as_deref())?;
+
+    search_semantic(
+        message_rabbitmq_repository,
+        body,
+        &page.first_page_of_window(),
+        source_meta_ids.as_deref(),
+        user_id,
+    )
+    .await
+}
+
+/// Searches the first semantic matches, up to the end of the page
+async fn search_semantic(
+    message_rabbitmq_repository: &RabbitMQMessageRepository,
+    body: &SearchContentBodyData,
+    window_page: &SearchPage,
+    source_meta_ids: Option<&[Uuid]>,
+    user_id: Uuid,
+) -> Result<Vec<ResultContent>, SearchContentError> {
+    let request = SemanticSearchRequestDto {
+        query: body.query.clone(),
+        limit: Some(window_page.window()),
+        user_id,
+        language: body.language.clone(),
+        fields: body.fields.clone(),
+        source_meta_ids: source_meta_ids.map(<[Uuid]>::to_vec),
+        chapter: body.chapter.clone(),
+        keyword: body.keyword.as_deref().map(str::to_lowercase),
+        entity: body.entity.clone(),
+    };
+    let request = request.try_serializing()?;
+
+    let response = message_rabbitmq_repository
+        .rpc_call(SEARCH_SEMANTIC_ROUTING_KEY, request.as_bytes(), None)
+        .await?;
+
+    match SemanticSearchResponseDto::try_parsing(&response)? {
+        RpcResponse::Ok { data } => Ok(data.results),
+        RpcResponse::Error { status, message } => {
+            Err(SearchContentError::SemanticSearchError(status, message))
+        }
+    }
+}
+
+/// Search backends used to find the contents
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+    #[default]
+    Fulltext,
+    Semantic,
+    /// Both full-text and semantic searches, with merged results
+    Hybrid,
+}
+
+/// Order of the found contents
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchSort {
+    #[default]
+    Relevance,
+    /// From the most recently uploaded source
+    Recency,
+    /// By the name of their source
+    SourceName,
 }
 
-#[derive(Debug, serde::Deserialize)]
+impl From<SearchSort> for SearchSortDto {
+    fn from(sort: SearchSort) -> Self {
+        match sort {
+            SearchSort::Relevance => SearchSortDto::Relevance,
+            SearchSort::Recency => SearchSortDto::Recency,
+            SearchSort::SourceName => SearchSortDto::SourceName,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct SearchContentBodyData {
-    query: String,
-    limit: Option<usize>,
+    pub query: String,
+    /// Number of results of the page, when `hits_per_page` is not given
+    pub limit: Option<usize>,
+    /// Page of the results, from 1
+    #[serde(default)]
+    pub page: Option<usize>,
+    /// Number of results per page, from 1 to 100
+    #[serde(default)]
+    pub hits_per_page: Option<usize>,
+    #[serde(default)]
+    pub sort: SearchSort,
+    #[serde(default)]
+    pub mode: SearchMode,
+    /// Only the contents detected in this language (ISO 639-1 code, for ex `fr`) are found
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Only the rows of the structured sources (CSV or JSONL) whose fields have these values are found,
+    /// for ex `{ "category": "geography" }`
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+    /// Only the contents of these sources are found, if any
+    #[serde(default)]
+    pub source_ids: Vec<Uuid>,
+    /// Only the contents of the sources of this type are found
+    #[serde(default)]
+    pub source_type: Option<SourceType>,
+    /// Only the contents of the sources uploaded from this date, included, are found
+    #[serde(default)]
+    pub uploaded_after: Option<DateTime<Utc>>,
+    /// Only the contents of the sources uploaded before this date, excluded, are found
+    #[serde(default)]
+    pub uploaded_before: Option<DateTime<Utc>>,
+    /// Only the contents of this chapter are found: the id of an EPUB chapter, or the title of a LaTeX chapter
+    #[serde(default)]
+    pub chapter: Option<String>,
+    /// Only the contents with this keyword are found, case-insensitive
+    #[serde(default)]
+    pub keyword: Option<String>,
+    /// Only the contents mentioning this named entity are found: a person, a place or an organization
+    #[serde(default)]
+    pub entity: Option<String>,
+    /// Rescores the best found contents by their relevance to the query with the cross-encoder of the deployment,
+    /// before they are sorted and paged
+    #[serde(default)]
+    pub rerank: bool,
+}
+
+/// Found contents, with only their requested fields when searched with a field set
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[aliases(SearchResults = SearchContentResponse<SearchResult>)]
+pub struct SearchContentResponse<R = SearchResult> {
+    pub results: Vec<R>,
+    /// Hits of all the found contents, not only the returned ones for the full-text search
+    #[serde(default)]
+    pub aggregations: SearchAggregations,
+    /// Number of found contents: approximated from the first found ones in semantic and hybrid modes
+    #[serde(default)]
+    pub total_hits: u64,
+    #[serde(default)]
+    pub total_pages: u64,
+    /// Queries correcting the spelling of a query finding few contents, from the one finding the most contents
+    #[serde(default)]
+    pub suggestions: Vec<String>,
 }
 
 #[derive(thiserror::Error)]
@@ -47,8 +891,57 @@ pub enum SearchContentError {
     RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
     #[error("Error while generation full-text search internal request: {0}")]
     FulltextSearchRequestError(#[from] FulltextSearchRequestDtoError),
+    #[error("Error while generation semantic search internal request: {0}")]
+    SemanticSearchRequestError(#[from] SemanticSearchRequestDtoError),
     #[error("Error while parsing response: {0}")]
     RpcResponseEncodingError(#[from] RpcResponseEncodingError),
+    #[error(transparent)]
+    UserRepositoryError(#[from] UserPostgresRepositoryError),
+    #[error(transparent)]
+    FulltextShardRepositoryError(#[from] FulltextShardPostgresRepositoryError),
+    #[error(transparent)]
+    SourceMetaRepositoryError(#[from] SourceMetaPostgresRepositoryError),
+    #[error(transparent)]
+    TenancyError(#[from] TenancyError),
+    #[error(transparent)]
+    InvalidFields(#[from] InvalidFieldsError),
+    #[error("Invalid language {0}: it should be an ISO 639-1 code, for ex `en`")]
+    InvalidLanguage(String),
+    #[error("Invalid field name {0}: it should only contain ASCII letters, digits and `_`")]
+    InvalidFieldName(String),
+    #[error(
+        "Invalid chapter {0:?}: it should have 1 to 256 characters, without control characters"
+    )]
+    InvalidChapter(String),
+    #[error(
+        "Invalid keyword {0:?}: it should have 1 to 256 characters, without control characters"
+    )]
+    InvalidKeyword(String),
+    #[error(
+        "Invalid entity {0:?}: it should have 1 to 256 characters, without control characters"
+    )]
+    InvalidEntity(String),
+    #[error("Too many source ids: {0}, at most {MAX_FILTERED_SOURCE_IDS} sources can be searched")]
+    TooManySourceIds(usize),
+    #[error("Invalid upload range: uploaded_after {uploaded_after} should be before uploaded_before {uploaded_before}")]
+    InvalidUploadRange {
+        uploaded_after: DateTime<Utc>,
+        uploaded_before: DateTime<Utc>,
+    },
+    #[error("Invalid page {0}: pages start at 1")]
+    InvalidPage(usize),
+    #[error("Invalid hits per page {0}: it should be from 1 to {MAX_HITS_PER_PAGE}")]
+    InvalidHitsPerPage(usize),
+    #[error("Page {page} out of range: at most the first {MAX_PAGED_HITS} results can be paged through, not {hits_per_page} per page")]
+    PageOutOfRange { page: usize, hits_per_page: usize },
+    #[error("Full-text search failed: {1}")]
+    FulltextSearchError(RpcErrorStatus, String),
+    #[error("Semantic search failed: {1}")]
+    SemanticSearchError(RpcErrorStatus, String),
+    #[error("Searches are not reranked: no reranker is configured")]
+    RerankDisabled,
+    #[error(transparent)]
+    RerankError(#[from] RerankError),
 }
 
 impl std::fmt::Debug for SearchContentError {
@@ -60,11 +953,35 @@ impl std::fmt::Debug for SearchContentError {
 impl ResponseError for SearchContentError {
     fn status_code(&self) -> StatusCode {
         match self {
+            SearchContentError::RabbitMQMessageRepositoryError(
+                RabbitMQMessageRepositoryError::RpcTimeout { .. },
+            ) => StatusCode::GATEWAY_TIMEOUT,
             SearchContentError::FulltextSearchRequestError(_)
+            | SearchContentError::SemanticSearchRequestError(_)
             | SearchContentError::RpcResponseEncodingError(_)
-            | SearchContentError::RabbitMQMessageRepositoryError(_) => {
-                StatusCode::INTERNAL_SERVER_ERROR
-            } // AddSourceFilesError::NoSourceFiles => StatusCode::BAD_REQUEST,
+            | SearchContentError::RabbitMQMessageRepositoryError(_)
+            | SearchContentError::UserRepositoryError(_)
+            | SearchContentError::FulltextShardRepositoryError(_)
+            | SearchContentError::SourceMetaRepositoryError(_)
+            | SearchContentError::TenancyError(_) => StatusCode::INTERNAL_SERVER_ERROR, // AddSourceFilesError::NoSourceFiles => StatusCode::BAD_REQUEST,
+            SearchContentError::InvalidFields(_)
+            | SearchContentError::InvalidLanguage(_)
+            | SearchContentError::InvalidFieldName(_)
+            | SearchContentError::InvalidChapter(_)
+            | SearchContentError::InvalidKeyword(_)
+            | SearchContentError::InvalidEntity(_)
+            | SearchContentError::TooManySourceIds(_)
+            | SearchContentError::InvalidUploadRange { .. }
+            | SearchContentError::InvalidPage(_)
+            | SearchContentError::InvalidHitsPerPage(_)
+            | SearchContentError::PageOutOfRange { .. } => StatusCode::BAD_REQUEST,
+            SearchContentError::FulltextSearchError(status, _)
+            | SearchContentError::SemanticSearchError(status, _) => match status {
+                RpcErrorStatus::BadRequest => StatusCode::BAD_REQUEST,
+                RpcErrorStatus::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            SearchContentError::RerankDisabled => StatusCode::SERVICE_UNAVAILABLE,
+            SearchContentError::RerankError(_) => StatusCode::BAD_GATEWAY,
         }
     }
 }
@@ -0,0 +1,138 @@
+use crate::domain::entities::saved_search::SearchHistoryEntry;
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::saved_search_postgres_repository::{
+    SavedSearchPostgresRepository, SearchHistoryCursor,
+};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use common::helper::error_chain_fmt;
+use common::pagination::{Page, PageCursor, PageLimits, PaginationError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::PgPool;
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+const PAGE_LIMITS: PageLimits = PageLimits::new(20, 100);
+
+#[derive(thiserror::Error)]
+pub enum SearchHistoryError {
+    #[error(transparent)]
+    InvalidPagination(#[from] PaginationError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for SearchHistoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for SearchHistoryError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            SearchHistoryError::InvalidPagination(_) => StatusCode::BAD_REQUEST,
+            SearchHistoryError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListSearchHistoryQuery {
+    /// `next_cursor` or `prev_cursor` of a listed page. The first page is listed without cursor.
+    pub cursor: Option<String>,
+    /// Maximum number of searches in the page
+    pub limit: Option<u32>,
+}
+
+/// Search run by a user
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct SearchHistoryEntryResponse {
+    pub id: Uuid,
+    pub query: String,
+    /// Search as sent to `/search`, to run it again
+    #[schema(value_type = Object)]
+    pub search: JsonValue,
+    pub searched_at: DateTime<Utc>,
+}
+
+impl From<SearchHistoryEntry> for SearchHistoryEntryResponse {
+    fn from(value: SearchHistoryEntry) -> Self {
+        Self {
+            id: value.id,
+            query: value.query,
+            search: value.request,
+            searched_at: value.searched_at,
+        }
+    }
+}
+
+/// Page of the search history
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ListSearchHistoryResponse {
+    pub searches: Vec<SearchHistoryEntryResponse>,
+    /// Cursor to list the next page. `None` on the last page.
+    pub next_cursor: Option<String>,
+    /// Cursor to list the previous page. `None` on the first page.
+    pub prev_cursor: Option<String>,
+}
+
+/// List the searches run by a user, from the latest
+///
+/// Only the latest searches are kept, and none are recorded when the search history is disabled.
+#[utoipa::path(
+    get,
+    path = "/search/history",
+    tag = "search",
+    params(ListSearchHistoryQuery),
+    responses(
+        (status = 200, description = "Page of the search history", body = ListSearchHistoryResponse),
+        (status = 400, description = "Invalid cursor or limit"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(name = "List search history", skip(pool, saved_search_repository), err)]
+pub async fn list_search_history(
+    query: web::Query<ListSearchHistoryQuery>,
+    pool: web::Data<PgPool>,
+    saved_search_repository: web::Data<SavedSearchPostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, SearchHistoryError> {
+    let user_id = user_id.into_inner().0;
+    info!("Request for user_id: {}", user_id);
+
+    let limit = PAGE_LIMITS.validate(query.limit)?;
+    let cursor = PageCursor::<SearchHistoryCursor>::decode_query(query.cursor.as_deref())?;
+
+    // Lists one more search to know if there is a further page
+    let entries = saved_search_repository
+        .list_user_search_history(
+            pool.get_ref(),
+            user_id,
+            cursor.as_ref(),
+            i64::from(limit) + 1,
+        )
+        .await
+        .context("Failed to list the search history")?;
+
+    let page = Page::from_fetched(
+        entries,
+        limit,
+        cursor.map(|cursor| cursor.direction),
+        |entry| SearchHistoryCursor {
+            searched_at: entry.searched_at,
+            id: entry.id,
+        },
+    );
+
+    Ok(HttpResponse::Ok().json(ListSearchHistoryResponse {
+        searches: page.items.into_iter().map(Into::into).collect(),
+        next_cursor: page.next_cursor,
+        prev_cursor: page.prev_cursor,
+    }))
+}
@@ -0,0 +1,86 @@
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::user_postgres_repository::{
+    UserPostgresRepository, UserPostgresRepositoryError,
+};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use common::helper::error_chain_fmt;
+use serde::Deserialize;
+use sqlx::PgPool;
+use tracing::info;
+use utoipa::ToSchema;
+
+#[derive(thiserror::Error)]
+pub enum SetDefaultCollectionError {
+    #[error("The default collection should not be empty")]
+    EmptyCollection,
+    #[error("User {0} not found")]
+    UserNotFound(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for SetDefaultCollectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for SetDefaultCollectionError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            SetDefaultCollectionError::EmptyCollection => StatusCode::BAD_REQUEST,
+            SetDefaultCollectionError::UserNotFound(_) => StatusCode::NOT_FOUND,
+            SetDefaultCollectionError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct SetDefaultCollectionBodyData {
+    /// `None` to stop filing the sources not matching any auto-filing rule
+    pub default_collection: Option<String>,
+}
+
+/// Set the collection in which the uploaded sources of a user are filed when no auto-filing rule matches
+#[utoipa::path(
+    put,
+    path = "/account/default_collection",
+    tag = "account",
+    request_body = SetDefaultCollectionBodyData,
+    responses(
+        (status = 204, description = "Saved default collection"),
+        (status = 400, description = "Empty collection"),
+        (status = 404, description = "User not found"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(name = "Set default collection", skip(pool, user_repository), err)]
+pub async fn set_default_collection(
+    body: web::Json<SetDefaultCollectionBodyData>,
+    pool: web::Data<PgPool>,
+    user_repository: web::Data<UserPostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, SetDefaultCollectionError> {
+    let user_id = user_id.into_inner().0;
+    info!("Request for user_id: {}", user_id);
+
+    let default_collection = body.into_inner().default_collection;
+    if matches!(&default_collection, Some(collection) if collection.trim().is_empty()) {
+        return Err(SetDefaultCollectionError::EmptyCollection);
+    }
+
+    user_repository
+        .set_user_default_collection(pool.get_ref(), user_id, default_collection.as_deref())
+        .await
+        .map_err(|error| match error {
+            UserPostgresRepositoryError::UserDoesNotExist(user_id) => {
+                SetDefaultCollectionError::UserNotFound(user_id)
+            }
+            error => SetDefaultCollectionError::UnexpectedError(
+                anyhow::Error::new(error).context("Could not set the default collection"),
+            ),
+        })?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,221 @@
+use crate::domain::entities::two_factor::{
+    base32_encode, generate_recovery_codes, generate_totp_secret, hash_recovery_code, otpauth_uri,
+    verify_totp_code,
+};
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::two_factor_postgres_repository::TwoFactorPostgresRepository;
+use crate::repositories::user_postgres_repository::UserPostgresRepository;
+use actix_web::http::header::ContentType;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::Utc;
+use common::core::secrets::SecretsCipher;
+use common::helper::error_chain_fmt;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use tracing::info;
+use utoipa::ToSchema;
+
+#[derive(thiserror::Error)]
+pub enum TwoFactorError {
+    #[error("The two-factor authentication is already enabled")]
+    TwoFactorAlreadyEnabled,
+    #[error("No pending two-factor authentication: enable it first")]
+    NoPendingTwoFactor,
+    #[error("Invalid two-factor authentication code")]
+    InvalidCode,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for TwoFactorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for TwoFactorError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            TwoFactorError::TwoFactorAlreadyEnabled | TwoFactorError::NoPendingTwoFactor => {
+                StatusCode::CONFLICT
+            }
+            TwoFactorError::InvalidCode => StatusCode::BAD_REQUEST,
+            TwoFactorError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        HttpResponse::build(self.status_code())
+            .insert_header(ContentType::json())
+            .json(json!({ "error": self.to_string() }))
+    }
+}
+
+/// TOTP secret of a pending two-factor authentication, to add to an authenticator app
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct EnableTwoFactorResponse {
+    /// Base32-encoded secret, to type in the authenticator app
+    pub secret: String,
+    /// `otpauth://` URI of the secret, to show as a QR code
+    pub otpauth_uri: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct VerifyTwoFactorBodyData {
+    /// Current code of the authenticator app
+    pub code: String,
+}
+
+/// Enabled two-factor authentication
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct VerifyTwoFactorResponse {
+    /// Single-use codes to log in without the authenticator app: they can not be retrieved afterwards
+    pub recovery_codes: Vec<String>,
+}
+
+/// Start enabling the two-factor authentication (TOTP) of a user
+///
+/// A new secret is generated, replacing any pending one. The two-factor authentication is only enabled once
+/// a code of the secret is verified on `/2fa/verify`.
+#[utoipa::path(
+    post,
+    path = "/2fa/enable",
+    tag = "account",
+    responses(
+        (status = 200, description = "Secret of the pending two-factor authentication", body = EnableTwoFactorResponse),
+        (status = 403, description = "The user has no account of the gateway"),
+        (status = 409, description = "The two-factor authentication is already enabled"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "Enable two-factor authentication",
+    skip(pool, user_repository, two_factor_repository, secrets_cipher),
+    err
+)]
+pub async fn enable_two_factor(
+    pool: web::Data<PgPool>,
+    user_repository: web::Data<UserPostgresRepository>,
+    two_factor_repository: web::Data<TwoFactorPostgresRepository>,
+    secrets_cipher: web::Data<SecretsCipher>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, TwoFactorError> {
+    let user_id = user_id.into_inner().0;
+
+    let secret = generate_totp_secret();
+    let encrypted_secret = secrets_cipher
+        .encrypt(&Secret::new(hex::encode(&secret)))
+        .context("Failed to encrypt the TOTP secret")?;
+
+    let is_saved = two_factor_repository
+        .save_pending_secret(&**pool, user_id, &encrypted_secret)
+        .await
+        .context("Failed to save the TOTP secret")?;
+    if !is_saved {
+        return Err(TwoFactorError::TwoFactorAlreadyEnabled);
+    }
+
+    let email = user_repository
+        .get_user_email(&**pool, user_id)
+        .await
+        .context("Failed to get the email of the user")?;
+
+    info!("Pending two-factor authentication");
+
+    Ok(HttpResponse::Ok().json(EnableTwoFactorResponse {
+        secret: base32_encode(&secret),
+        otpauth_uri: otpauth_uri(&secret, &email),
+    }))
+}
+
+/// Verify a code of the pending two-factor authentication of a user, enabling it
+///
+/// From then on, the log-ins require a code of the authenticator app, or one of the returned recovery codes.
+#[utoipa::path(
+    post,
+    path = "/2fa/verify",
+    tag = "account",
+    request_body = VerifyTwoFactorBodyData,
+    responses(
+        (status = 200, description = "Recovery codes of the enabled two-factor authentication", body = VerifyTwoFactorResponse),
+        (status = 400, description = "Invalid code"),
+        (status = 403, description = "The user has no account of the gateway"),
+        (status = 409, description = "No pending two-factor authentication"),
+    ),
+    security(("access_token" = []))
+)]
+#[tracing::instrument(
+    name = "Verify two-factor authentication",
+    skip(pool, two_factor_repository, secrets_cipher, body),
+    err
+)]
+pub async fn verify_two_factor(
+    body: web::Json<VerifyTwoFactorBodyData>,
+    pool: web::Data<PgPool>,
+    two_factor_repository: web::Data<TwoFactorPostgresRepository>,
+    secrets_cipher: web::Data<SecretsCipher>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, TwoFactorError> {
+    let user_id = user_id.into_inner().0;
+
+    let two_factor = two_factor_repository
+        .get_user_two_factor(&**pool, user_id)
+        .await
+        .context("Failed to get the two-factor authentication")?
+        .filter(|two_factor| !two_factor.is_enabled())
+        .ok_or(TwoFactorError::NoPendingTwoFactor)?;
+
+    let secret = decrypt_totp_secret(&secrets_cipher, &two_factor.encrypted_secret)?;
+    let step =
+        verify_totp_code(&secret, &body.code, Utc::now()).ok_or(TwoFactorError::InvalidCode)?;
+
+    let recovery_codes = generate_recovery_codes();
+    let code_hashes: Vec<String> = recovery_codes
+        .iter()
+        .map(|code| hash_recovery_code(code))
+        .collect();
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to start a transaction")?;
+
+    // Enabled concurrently by another verification
+    let is_enabled = two_factor_repository
+        .enable(&mut transaction, user_id, step)
+        .await
+        .context("Failed to enable the two-factor authentication")?;
+    if !is_enabled {
+        return Err(TwoFactorError::NoPendingTwoFactor);
+    }
+
+    two_factor_repository
+        .replace_recovery_codes(&mut transaction, user_id, &code_hashes)
+        .await
+        .context("Failed to save the recovery codes")?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the transaction")?;
+
+    info!("Enabled two-factor authentication");
+
+    Ok(HttpResponse::Ok().json(VerifyTwoFactorResponse { recovery_codes }))
+}
+
+/// Decrypts the hex-encoded TOTP secret of a user
+pub fn decrypt_totp_secret(
+    secrets_cipher: &SecretsCipher,
+    encrypted_secret: &str,
+) -> Result<Vec<u8>, anyhow::Error> {
+    let secret = secrets_cipher
+        .decrypt(encrypted_secret)
+        .context("Failed to decrypt the TOTP secret")?;
+
+    hex::decode(secret.expose_secret()).context("Invalid TOTP secret")
+}
@@ -0,0 +1,201 @@
+use crate::domain::entities::upload_policy::{is_valid_mime_type, UploadPolicy};
+use crate::repositories::upload_policy_postgres_repository::UploadPolicyPostgresRepository;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use common::core::tenancy::TenantMessageRepositories;
+use common::helper::error_chain_fmt;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+#[derive(thiserror::Error)]
+pub enum UploadPolicyError {
+    #[error("Invalid upload policy: {0}")]
+    InvalidPolicy(String),
+    #[error("Unknown tenant: {0}")]
+    UnknownTenant(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for UploadPolicyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for UploadPolicyError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            UploadPolicyError::InvalidPolicy(_) | UploadPolicyError::UnknownTenant(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            UploadPolicyError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct UploadPolicyBodyData {
+    /// Tenant (organization) of the policy, `None` for the users without tenant
+    pub tenant: Option<String>,
+    /// MIME types of the accepted files, for ex `application/epub+zip` or `text/*`. All the types if empty
+    #[serde(default)]
+    pub allowed_mime_types: Vec<String>,
+    /// Maximum size of the accepted files, not limited if `None`
+    pub max_size_bytes: Option<i64>,
+    /// The files should be scanned for viruses before being accepted
+    #[serde(default)]
+    pub scan_required: bool,
+}
+
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UploadPoliciesQuery {
+    /// Tenant of the policies, `None` for the users without tenant
+    pub tenant: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct UploadPolicyResponse {
+    pub id: Uuid,
+    pub tenant: Option<String>,
+    pub version: i32,
+    pub allowed_mime_types: Vec<String>,
+    pub max_size_bytes: Option<i64>,
+    pub scan_required: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<UploadPolicy> for UploadPolicyResponse {
+    fn from(value: UploadPolicy) -> Self {
+        Self {
+            id: value.id,
+            tenant: value.tenant_id,
+            version: value.version,
+            allowed_mime_types: value.allowed_mime_types,
+            max_size_bytes: value.max_size_bytes,
+            scan_required: value.scan_required,
+            created_at: value.created_at,
+        }
+    }
+}
+
+/// List the versions of the upload policy of a tenant, from the latest one which is applied to the uploads
+#[utoipa::path(
+    get,
+    path = "/admin/upload_policies",
+    tag = "admin",
+    params(UploadPoliciesQuery),
+    responses(
+        (status = 200, description = "Versions of the upload policy of the tenant", body = [UploadPolicyResponse]),
+    ),
+    security(("admin_token" = []))
+)]
+#[tracing::instrument(
+    name = "List upload policies",
+    skip(pool, upload_policy_repository),
+    err
+)]
+pub async fn list_upload_policies(
+    query: web::Query<UploadPoliciesQuery>,
+    pool: web::Data<PgPool>,
+    upload_policy_repository: web::Data<UploadPolicyPostgresRepository>,
+) -> Result<HttpResponse, UploadPolicyError> {
+    let policies = upload_policy_repository
+        .list_tenant_policies(pool.get_ref(), query.tenant.as_deref())
+        .await
+        .context("Could not list the upload policies of the tenant")?;
+
+    Ok(HttpResponse::Ok().json(
+        policies
+            .into_iter()
+            .map(UploadPolicyResponse::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Change the upload policy of a tenant, applied to the next uploads of its users
+///
+/// The change is saved as a new version of the policy: the previous versions are kept,
+/// as the sources record the version they were uploaded under.
+#[utoipa::path(
+    post,
+    path = "/admin/upload_policies",
+    tag = "admin",
+    request_body = UploadPolicyBodyData,
+    responses(
+        (status = 201, description = "New version of the upload policy", body = UploadPolicyResponse),
+        (status = 400, description = "Invalid policy or unknown tenant"),
+    ),
+    security(("admin_token" = []))
+)]
+#[tracing::instrument(
+    name = "Save upload policy",
+    skip(pool, upload_policy_repository, message_repositories),
+    err
+)]
+pub async fn save_upload_policy(
+    body: web::Json<UploadPolicyBodyData>,
+    pool: web::Data<PgPool>,
+    upload_policy_repository: web::Data<UploadPolicyPostgresRepository>,
+    message_repositories: web::Data<TenantMessageRepositories>,
+) -> Result<HttpResponse, UploadPolicyError> {
+    let UploadPolicyBodyData {
+        tenant,
+        allowed_mime_types,
+        max_size_bytes,
+        scan_required,
+    } = body.into_inner();
+
+    if let Some(tenant) = &tenant {
+        if !message_repositories.has_tenant(tenant) {
+            return Err(UploadPolicyError::UnknownTenant(tenant.clone()));
+        }
+    }
+    if let Some(mime_type) = allowed_mime_types
+        .iter()
+        .find(|mime_type| !is_valid_mime_type(mime_type))
+    {
+        return Err(UploadPolicyError::InvalidPolicy(format!(
+            "invalid MIME type {}, expected type/subtype or type/*",
+            mime_type
+        )));
+    }
+    if max_size_bytes.is_some_and(|max_size_bytes| max_size_bytes <= 0) {
+        return Err(UploadPolicyError::InvalidPolicy(
+            "the maximum size should be of at least 1 byte".to_string(),
+        ));
+    }
+
+    let policy = upload_policy_repository
+        .add_policy(
+            pool.get_ref(),
+            &UploadPolicy {
+                id: Uuid::new_v4(),
+                tenant_id: tenant,
+                // Set by the repository
+                version: 0,
+                allowed_mime_types: allowed_mime_types
+                    .iter()
+                    .map(|mime_type| mime_type.to_ascii_lowercase())
+                    .collect(),
+                max_size_bytes,
+                scan_required,
+                created_at: Utc::now(),
+            },
+        )
+        .await
+        .context("Could not save the upload policy")?;
+
+    info!(
+        "Saved version {} of the upload policy of tenant {:?}",
+        policy.version, policy.tenant_id
+    );
+
+    Ok(HttpResponse::Created().json(UploadPolicyResponse::from(policy)))
+}
@@ -0,0 +1,631 @@
+use crate::configuration::UploadsSettings;
+use crate::controllers::add_source_url::SourceIntake;
+use crate::domain::entities::auto_filing_rule::{file_in_collection, FilingFile};
+use crate::domain::entities::ingestion_job::IngestionLane;
+use crate::domain::entities::sniffed_content::{SniffedContent, SNIFFED_BYTES};
+use crate::domain::entities::source_meta::{SourceMeta, SourceType};
+use crate::domain::entities::upload_session::{
+    first_missing_part, UploadPart, UploadSession, MAX_UPLOAD_PARTS,
+};
+use crate::middlewares::jwt_authentication::middleware::UserIdFromToken;
+use crate::repositories::source_file_s3_repository::{S3Repository, S3RepositoryError};
+use crate::repositories::upload_session_postgres_repository::UploadSessionPostgresRepository;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use common::core::tenancy::TenantMessageRepositories;
+use common::helper::error_chain_fmt;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::path::Path;
+use std::str::FromStr;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Content type of the parts, when the client did not declare the type of the file
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+#[derive(thiserror::Error)]
+pub enum UploadError {
+    #[error("Invalid upload: {0}")]
+    InvalidUpload(String),
+    #[error("Upload {0} not found")]
+    UploadNotFound(Uuid),
+    #[error("Upload {0} is already completed")]
+    UploadAlreadyCompleted(Uuid),
+    #[error("Part {0} is missing")]
+    MissingPart(i32),
+    #[error("The parts could not be assembled: {0}")]
+    PartsRejected(String),
+    #[error("The content of the file is detected as {0}, which is not a valid {1:?} source")]
+    ContentMismatch(&'static str, SourceType),
+    #[error("The file of {0} bytes is larger than the maximum of {1} bytes")]
+    FileTooLarge(u64, usize),
+    #[error("The file of {0} bytes exceeds the storage quota of the user")]
+    StorageQuotaExceeded(u64),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for UploadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for UploadError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            UploadError::InvalidUpload(_)
+            | UploadError::MissingPart(_)
+            | UploadError::PartsRejected(_)
+            | UploadError::ContentMismatch(_, _) => StatusCode::BAD_REQUEST,
+            UploadError::UploadNotFound(_) => StatusCode::NOT_FOUND,
+            UploadError::UploadAlreadyCompleted(_) => StatusCode::CONFLICT,
+            UploadError::FileTooLarge(_, _) => StatusCode::PAYLOAD_TOO_LARGE,
+            UploadError::StorageQuotaExceeded(_) => StatusCode::FORBIDDEN,
+            UploadError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct StartUploadBodyData {
+    pub file_name: String,
+    /// MIME type of the file, matched by the auto-filing rules
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// Tags given to the file, matched by the auto-filing rules
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct UploadPartResponse {
+    pub part_number: i32,
+    pub size_bytes: i64,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+impl From<UploadPart> for UploadPartResponse {
+    fn from(value: UploadPart) -> Self {
+        Self {
+            part_number: value.part_number,
+            size_bytes: value.size_bytes,
+            uploaded_at: value.uploaded_at,
+        }
+    }
+}
+
+/// Chunked upload with its received parts, to know which parts to upload when resuming it
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct UploadResponse {
+    pub id: Uuid,
+    pub file_name: String,
+    /// Maximum size of a part
+    pub max_part_bytes: usize,
+    pub parts: Vec<UploadPartResponse>,
+    /// Source registered once the parts are assembled
+    pub source_id: Option<Uuid>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UploadResponse {
+    fn new(session: UploadSession, parts: Vec<UploadPart>, max_part_bytes: usize) -> Self {
+        Self {
+            id: session.id,
+            file_name: session.file_name,
+            max_part_bytes,
+            parts: parts.into_iter().map(Into::into).collect(),
+            source_id: session.source_meta_id,
+            completed_at: session.completed_at,
+            created_at: session.created_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct CompleteUploadResponse {
+    pub source_id: Uuid,
+    /// Collection in which the source was filed
+    pub collection: Option<String>,
+    /// Ingestion job of the source, to follow its extraction and embedding
+    pub job_id: Uuid,
+}
+
+/// Gets an upload of a user that is not completed yet
+async fn get_pending_session(
+    pool: &PgPool,
+    upload_session_repository: &UploadSessionPostgresRepository,
+    user_id: Uuid,
+    upload_id: Uuid,
+) -> Result<UploadSession, UploadError> {
+    let session = upload_session_repository
+        .get_user_session(pool, user_id, upload_id)
+        .await
+        .context("Failed to get the upload")?
+        .ok_or(UploadError::UploadNotFound(upload_id))?;
+
+    if session.completed_at.is_some() {
+        return Err(UploadError::UploadAlreadyCompleted(upload_id));
+    }
+
+    Ok(session)
+}
+
+/// Start a chunked upload of a large source file
+///
+/// The file is then uploaded part by part, and an interrupted upload is resumed by uploading its missing parts.
+#[utoipa::path(
+    post,
+    path = "/uploads",
+    tag = "uploads",
+    request_body = StartUploadBodyData,
+    responses(
+        (status = 201, description = "Started upload", body = UploadResponse),
+        (status = 400, description = "Invalid upload"),
+        (status = 403, description = "The file exceeds the storage quota of the user"),
+    ),
+    security(("access_token" = []), ("api_key" = ["upload"]))
+)]
+#[tracing::instrument(
+    name = "Start upload",
+    skip(pool, s3_repository, upload_session_repository, uploads_settings),
+    err
+)]
+pub async fn start_upload(
+    body: web::Json<StartUploadBodyData>,
+    pool: web::Data<PgPool>,
+    s3_repository: web::Data<S3Repository>,
+    upload_session_repository: web::Data<UploadSessionPostgresRepository>,
+    uploads_settings: web::Data<UploadsSettings>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, UploadError> {
+    let user_id = user_id.into_inner().0;
+    let StartUploadBodyData {
+        file_name,
+        content_type,
+        tags,
+    } = body.into_inner();
+
+    // Rejects the unsupported files before any part is uploaded
+    let extension = Path::new(&file_name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .ok_or_else(|| UploadError::InvalidUpload("could not extract extension".to_string()))?;
+    SourceType::from_str(extension).map_err(|_| {
+        UploadError::InvalidUpload(format!("invalid source type for {}", file_name))
+    })?;
+
+    // Matched by the auto-filing rules without its parameters, like the content type of the uploaded files
+    let content_type = content_type.and_then(|content_type| {
+        let essence = content_type.split(';').next().unwrap_or_default().trim();
+        (!essence.is_empty()).then(|| essence.to_lowercase())
+    });
+
+    let (object_name, s3_upload_id) = s3_repository
+        .start_multipart_upload(
+            &user_id.to_string(),
+            content_type.as_deref().unwrap_or(DEFAULT_CONTENT_TYPE),
+        )
+        .await
+        .context("Could not start the upload to the object storage")?;
+
+    let session = UploadSession::new(
+        user_id,
+        file_name,
+        content_type,
+        tags,
+        object_name,
+        s3_upload_id,
+    );
+
+    upload_session_repository
+        .add_session(&**pool, &session)
+        .await
+        .context("Failed to save the upload")?;
+
+    info!(upload_id = %session.id, "Started upload");
+
+    Ok(HttpResponse::Created().json(UploadResponse::new(
+        session,
+        Vec::new(),
+        uploads_settings.max_part_bytes,
+    )))
+}
+
+/// Get a chunked upload of a user with its received parts
+#[utoipa::path(
+    get,
+    path = "/uploads/{upload_id}",
+    tag = "uploads",
+    params(("upload_id" = Uuid, Path, description = "ID of the upload")),
+    responses(
+        (status = 200, description = "Upload with its received parts", body = UploadResponse),
+        (status = 404, description = "Upload not found"),
+    ),
+    security(("access_token" = []), ("api_key" = ["upload"]))
+)]
+#[tracing::instrument(
+    name = "Get upload",
+    skip(pool, upload_session_repository, uploads_settings),
+    err
+)]
+pub async fn get_upload(
+    upload_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    upload_session_repository: web::Data<UploadSessionPostgresRepository>,
+    uploads_settings: web::Data<UploadsSettings>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, UploadError> {
+    let user_id = user_id.into_inner().0;
+    let upload_id = upload_id.into_inner();
+
+    let session = upload_session_repository
+        .get_user_session(&**pool, user_id, upload_id)
+        .await
+        .context("Failed to get the upload")?
+        .ok_or(UploadError::UploadNotFound(upload_id))?;
+
+    let parts = upload_session_repository
+        .list_parts(&**pool, upload_id)
+        .await
+        .context("Failed to list the parts of the upload")?;
+
+    Ok(HttpResponse::Ok().json(UploadResponse::new(
+        session,
+        parts,
+        uploads_settings.max_part_bytes,
+    )))
+}
+
+/// Upload a part of a chunked upload
+///
+/// The parts are numbered from 1 in the order of the file. A part uploaded again replaces the previous one.
+/// The first part is rejected if its content does not match the type given by the extension of the file.
+/// The size of a part is limited by the payload limit of the gateway.
+#[utoipa::path(
+    put,
+    path = "/uploads/{upload_id}/parts/{part_number}",
+    tag = "uploads",
+    params(
+        ("upload_id" = Uuid, Path, description = "ID of the upload"),
+        ("part_number" = i32, Path, description = "Number of the part, from 1")
+    ),
+    request_body(content = String, content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "Received part", body = UploadPartResponse),
+        (status = 400, description = "Invalid part, or content not matching the type of the file"),
+        (status = 404, description = "Upload not found"),
+        (status = 409, description = "The upload is already completed"),
+        (status = 413, description = "The file is too large"),
+    ),
+    security(("access_token" = []), ("api_key" = ["upload"]))
+)]
+#[tracing::instrument(
+    name = "Upload part",
+    skip(body, pool, s3_repository, upload_session_repository),
+    fields(size_bytes = body.len()),
+    err
+)]
+pub async fn upload_part(
+    path: web::Path<(Uuid, i32)>,
+    body: web::Bytes,
+    pool: web::Data<PgPool>,
+    s3_repository: web::Data<S3Repository>,
+    upload_session_repository: web::Data<UploadSessionPostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, UploadError> {
+    let user_id = user_id.into_inner().0;
+    let (upload_id, part_number) = path.into_inner();
+
+    if !(1..=MAX_UPLOAD_PARTS).contains(&part_number) {
+        return Err(UploadError::InvalidUpload(format!(
+            "the part number should be between 1 and {}",
+            MAX_UPLOAD_PARTS
+        )));
+    }
+    if body.is_empty() {
+        return Err(UploadError::InvalidUpload(
+            "the part should not be empty".to_string(),
+        ));
+    }
+
+    let session =
+        get_pending_session(&pool, &upload_session_repository, user_id, upload_id).await?;
+
+    // The first part holds the magic bytes of the file: its content is checked against its extension
+    if part_number == 1 {
+        let source_type = Path::new(&session.file_name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| SourceType::from_str(extension).ok())
+            .context("The source type of the upload could not be parsed")?;
+        let sniffed_content =
+            SniffedContent::sniff(&body[..body.len().min(SNIFFED_BYTES as usize)]);
+
+        if !sniffed_content.matches(&source_type) {
+            return Err(UploadError::ContentMismatch(
+                sniffed_content.mime_type(),
+                source_type,
+            ));
+        }
+    }
+
+    let size_bytes = body.len() as i64;
+    let etag = s3_repository
+        .upload_part(
+            &session.object_path_name(),
+            &session.s3_upload_id,
+            part_number as u32,
+            session
+                .content_type
+                .as_deref()
+                .unwrap_or(DEFAULT_CONTENT_TYPE),
+            body.to_vec(),
+        )
+        .await
+        .context(format!(
+            "The part {} could not be uploaded to object storage",
+            part_number
+        ))?;
+
+    let part = UploadPart {
+        part_number,
+        etag,
+        size_bytes,
+        uploaded_at: Utc::now(),
+    };
+
+    upload_session_repository
+        .save_part(&**pool, upload_id, &part)
+        .await
+        .context("Failed to save the part of the upload")?;
+
+    Ok(HttpResponse::Ok().json(UploadPartResponse::from(part)))
+}
+
+/// Complete a chunked upload: its parts are assembled into the source file, which is sent to the extraction
+///
+/// The source is filed like the sources added with `/add_source_files`. The duplicated files are not detected:
+/// the content of an assembled file is not hashed by the gateway.
+#[utoipa::path(
+    post,
+    path = "/uploads/{upload_id}/complete",
+    tag = "uploads",
+    params(("upload_id" = Uuid, Path, description = "ID of the upload")),
+    responses(
+        (status = 200, description = "Source of the assembled file", body = CompleteUploadResponse),
+        (status = 400, description = "Missing or rejected parts"),
+        (status = 403, description = "The file exceeds the storage quota of the user"),
+        (status = 404, description = "Upload not found"),
+        (status = 409, description = "The upload is already completed"),
+    ),
+    security(("access_token" = []), ("api_key" = ["upload"]))
+)]
+#[tracing::instrument(
+    name = "Complete upload",
+    skip(
+        pool,
+        intake,
+        upload_session_repository,
+        message_repositories,
+        uploads_settings
+    ),
+    err
+)]
+pub async fn complete_upload(
+    upload_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    intake: web::Data<SourceIntake>,
+    upload_session_repository: web::Data<UploadSessionPostgresRepository>,
+    message_repositories: web::Data<TenantMessageRepositories>,
+    uploads_settings: web::Data<UploadsSettings>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, UploadError> {
+    let user_id = user_id.into_inner().0;
+    let upload_id = upload_id.into_inner();
+    let SourceIntake {
+        s3_repository,
+        auto_filing_rule_repository,
+        user_repository,
+        source_registration,
+        ..
+    } = intake.get_ref();
+
+    let session =
+        get_pending_session(&pool, &upload_session_repository, user_id, upload_id).await?;
+    let source_type = Path::new(&session.file_name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(|extension| SourceType::from_str(extension).ok())
+        .context("The source type of the upload could not be parsed")?;
+
+    let parts = upload_session_repository
+        .list_parts(&**pool, upload_id)
+        .await
+        .context("Failed to list the parts of the upload")?;
+    if let Some(missing_part_number) = first_missing_part(&parts) {
+        return Err(UploadError::MissingPart(missing_part_number));
+    }
+
+    // The upload stays pending: it can be aborted to remove its parts
+    let size_bytes = parts.iter().map(|part| part.size_bytes as u64).sum();
+    if size_bytes > uploads_settings.max_file_bytes as u64 {
+        return Err(UploadError::FileTooLarge(
+            size_bytes,
+            uploads_settings.max_file_bytes,
+        ));
+    }
+
+    let storage_usage = source_registration.storage_usage(&**pool, user_id).await?;
+    if !storage_usage.can_store(size_bytes) {
+        return Err(UploadError::StorageQuotaExceeded(size_bytes));
+    }
+
+    // Times the upload stage of the job from the start of the upload, as for the uploads of whole files
+    let upload_started_at = session.created_at;
+    let object_path_name = session.object_path_name();
+
+    s3_repository
+        .complete_multipart_upload(
+            &object_path_name,
+            &session.s3_upload_id,
+            parts
+                .iter()
+                .map(|part| (part.part_number as u32, part.etag.clone()))
+                .collect(),
+        )
+        .await
+        .map_err(|error| match error {
+            S3RepositoryError::MultipartUploadRejected(message) => {
+                UploadError::PartsRejected(message)
+            }
+            error => UploadError::UnexpectedError(
+                anyhow::Error::new(error).context("The parts could not be assembled"),
+            ),
+        })?;
+
+    // The extraction jobs are published on the exchange of the tenant of the user
+    let tenant_id = user_repository
+        .get_user_tenant_id(&**pool, user_id)
+        .await
+        .context("Could not get the tenant of the user")?;
+    let message_rabbitmq_repository = message_repositories
+        .route(tenant_id.as_deref())
+        .context("Could not route the messages of the user")?;
+
+    let auto_filing_rules = auto_filing_rule_repository
+        .list_user_rules(&**pool, user_id)
+        .await
+        .context("Could not list the auto-filing rules of the user")?;
+    let default_collection = user_repository
+        .get_user_default_collection(&**pool, user_id)
+        .await
+        .context("Could not get the default collection of the user")?;
+    let filing = file_in_collection(
+        &auto_filing_rules,
+        default_collection.as_deref(),
+        &FilingFile {
+            file_name: &session.file_name,
+            mime_type: session.content_type.as_deref(),
+            tags: &session.tags,
+        },
+    );
+
+    let source_meta = SourceMeta::builder()
+        .user_id(user_id)
+        .initial_name(session.file_name.clone())
+        .source_type(source_type)
+        .object_store_name(session.object_store_name.clone())
+        .collection(filing.as_ref().map(|filing| filing.collection.clone()))
+        .auto_filing_rule_id(filing.as_ref().and_then(|filing| filing.rule_id))
+        .size_bytes(Some(size_bytes as i64))
+        .build();
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    // Files uploaded concurrently by the same user can exceed the quota checked before assembling the parts
+    if !source_registration
+        .reserve_storage(&mut transaction, &source_meta)
+        .await?
+    {
+        s3_repository
+            .remove_file(&object_path_name)
+            .await
+            .context(format!(
+                "The object {} exceeding the storage quota could not be removed from the object storage",
+                object_path_name
+            ))?;
+
+        return Err(UploadError::StorageQuotaExceeded(size_bytes));
+    }
+
+    let registered_source = source_registration
+        .register(
+            &mut transaction,
+            tenant_id.as_deref(),
+            &source_meta,
+            object_path_name,
+            // Chunked uploads are meant for the large files
+            IngestionLane::Bulk,
+            upload_started_at,
+        )
+        .await?;
+
+    // A concurrent completion of the same upload already registered its source
+    let is_completed = upload_session_repository
+        .complete_session(&mut transaction, upload_id, source_meta.id)
+        .await
+        .context("Failed to complete the upload")?;
+    if !is_completed {
+        return Err(UploadError::UploadAlreadyCompleted(upload_id));
+    }
+
+    transaction.commit().await.context(format!(
+        "Failed to commit SQL transaction to store the file {}",
+        session.file_name
+    ))?;
+
+    source_registration
+        .send_to_extraction(&pool, message_rabbitmq_repository, &registered_source)
+        .await;
+
+    info!(upload_id = %upload_id, source_meta_id = %source_meta.id, "Completed upload");
+
+    Ok(HttpResponse::Ok().json(CompleteUploadResponse {
+        source_id: source_meta.id,
+        collection: source_meta.collection,
+        job_id: registered_source.ingestion_job.id,
+    }))
+}
+
+/// Abort a chunked upload that is not completed, removing its uploaded parts
+#[utoipa::path(
+    delete,
+    path = "/uploads/{upload_id}",
+    tag = "uploads",
+    params(("upload_id" = Uuid, Path, description = "ID of the upload")),
+    responses(
+        (status = 204, description = "Aborted upload"),
+        (status = 404, description = "Upload not found"),
+        (status = 409, description = "The upload is already completed"),
+    ),
+    security(("access_token" = []), ("api_key" = ["upload"]))
+)]
+#[tracing::instrument(
+    name = "Abort upload",
+    skip(pool, s3_repository, upload_session_repository),
+    err
+)]
+pub async fn abort_upload(
+    upload_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    s3_repository: web::Data<S3Repository>,
+    upload_session_repository: web::Data<UploadSessionPostgresRepository>,
+    user_id: web::ReqData<UserIdFromToken>,
+) -> Result<HttpResponse, UploadError> {
+    let user_id = user_id.into_inner().0;
+    let upload_id = upload_id.into_inner();
+
+    let session =
+        get_pending_session(&pool, &upload_session_repository, user_id, upload_id).await?;
+
+    s3_repository
+        .abort_multipart_upload(&session.object_path_name(), &session.s3_upload_id)
+        .await
+        .context("The upload could not be aborted on the object storage")?;
+
+    upload_session_repository
+        .delete_session(&**pool, upload_id)
+        .await
+        .context("Failed to delete the upload")?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
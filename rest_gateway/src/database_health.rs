@@ -0,0 +1,80 @@
+use common::core::health_server::DependencyCheck;
+use futures::future::BoxFuture;
+use sqlx::PgPool;
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use crate::metrics::IngestionMetrics;
+
+/// Probes periodically the connection pool of the database
+///
+/// The readiness endpoint reports the last probe: it does not wait for a connection of an exhausted pool.
+/// Each probe also updates the gauges of the connections of the pool.
+#[derive(Clone)]
+pub struct DatabasePoolProbe {
+    db_pool: PgPool,
+    ingestion_metrics: Arc<IngestionMetrics>,
+    last_status: Arc<RwLock<Result<(), String>>>,
+}
+
+impl DatabasePoolProbe {
+    pub fn new(db_pool: PgPool, ingestion_metrics: Arc<IngestionMetrics>) -> Self {
+        Self {
+            db_pool,
+            ingestion_metrics,
+            last_status: Arc::new(RwLock::new(Err("not probed yet".to_string()))),
+        }
+    }
+
+    /// Probes the pool at each interval
+    pub async fn run(self, interval: Duration) {
+        let mut probes = tokio::time::interval_at(Instant::now() + interval, interval);
+
+        loop {
+            probes.tick().await;
+            self.probe().await;
+        }
+    }
+
+    /// Checks that a connection of the pool answers, and updates the gauges of the connections
+    pub async fn probe(&self) {
+        let status = sqlx::query("SELECT 1")
+            .execute(&self.db_pool)
+            .await
+            .map(|_| ())
+            .map_err(|error| error.to_string());
+
+        let mut last_status = self
+            .last_status
+            .write()
+            .expect("database probe status lock poisoned");
+        match (&*last_status, &status) {
+            (_, Err(error)) => warn!(error, "Database unavailable"),
+            (Err(_), Ok(())) => info!("Database available"),
+            _ => {}
+        }
+        *last_status = status;
+
+        let nb_idle = self.db_pool.num_idle();
+        self.ingestion_metrics.set_database_pool_connections(
+            (self.db_pool.size() as usize).saturating_sub(nb_idle),
+            nb_idle,
+        );
+    }
+}
+
+impl DependencyCheck for DatabasePoolProbe {
+    fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+        let status = self
+            .last_status
+            .read()
+            .expect("database probe status lock poisoned")
+            .clone();
+
+        Box::pin(async move { status })
+    }
+}
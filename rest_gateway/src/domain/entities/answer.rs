@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Instructions of the LLM answering a question from the retrieved chunks
+const ANSWER_INSTRUCTIONS: &str = "Answer the question using only the numbered sources below. \
+Cite the sources supporting each statement with their number in brackets, for ex [1] or [2][3]. \
+If the sources do not contain the answer, say that you do not know.";
+
+/// Chunk retrieved to answer a question, cited in the answer by its index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Citation {
+    /// Number of the chunk in the prompt, from 1, as cited in the answer: `[1]`
+    pub index: usize,
+    pub chunk_id: Uuid,
+    /// Source from which the chunk was extracted
+    pub source_id: Option<Uuid>,
+    /// Name of the source file, when the source still exists
+    pub source_name: Option<String>,
+    pub content: String,
+}
+
+/// Prompt of the LLM answering a question from the retrieved chunks
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnswerPrompt {
+    /// System instructions
+    pub instructions: String,
+    /// Numbered chunks followed by the question
+    pub question: String,
+}
+
+impl AnswerPrompt {
+    pub fn new(question: &str, citations: &[Citation]) -> Self {
+        let sources: Vec<String> = citations
+            .iter()
+            .map(|citation| match &citation.source_name {
+                Some(source_name) => format!(
+                    "[{}] (from {})\n{}",
+                    citation.index, source_name, citation.content
+                ),
+                None => format!("[{}]\n{}", citation.index, citation.content),
+            })
+            .collect();
+
+        Self {
+            instructions: ANSWER_INSTRUCTIONS.to_string(),
+            question: format!(
+                "Sources:\n\n{}\n\nQuestion: {}",
+                sources.join("\n\n"),
+                question
+            ),
+        }
+    }
+}
+
+/// Indexes cited in an answer, as `[1]`, `[1][3]` or `[1, 3]`
+pub fn cited_indexes(answer: &str) -> BTreeSet<usize> {
+    let mut indexes = BTreeSet::new();
+
+    for (start, _) in answer.match_indices('[') {
+        let Some(length) = answer[start + 1..].find(']') else {
+            continue;
+        };
+        let numbers: Option<Vec<usize>> = answer[start + 1..start + 1 + length]
+            .split(',')
+            .map(|number| number.trim().parse().ok())
+            .collect();
+        indexes.extend(numbers.unwrap_or_default());
+    }
+
+    indexes
+}
+
+/// Retrieved chunks cited in an answer, by increasing index
+pub fn cited_chunks(citations: Vec<Citation>, answer: &str) -> Vec<Citation> {
+    let indexes = cited_indexes(answer);
+
+    citations
+        .into_iter()
+        .filter(|citation| indexes.contains(&citation.index))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn citation(index: usize, source_name: Option<&str>) -> Citation {
+        Citation {
+            index,
+            chunk_id: Uuid::new_v4(),
+            source_id: None,
+            source_name: source_name.map(str::to_string),
+            content: format!("Content {}", index),
+        }
+    }
+
+    #[test]
+    fn prompt_numbers_the_chunks_before_the_question() {
+        let prompt = AnswerPrompt::new(
+            "What is a pod?",
+            &[citation(1, Some("k8s.epub")), citation(2, None)],
+        );
+
+        assert_eq!(
+            prompt.question,
+            "Sources:\n\n[1] (from k8s.epub)\nContent 1\n\n[2]\nContent 2\n\nQuestion: What is a pod?"
+        );
+    }
+
+    #[test]
+    fn cited_indexes_are_read_from_the_brackets() {
+        assert_eq!(
+            cited_indexes("Pods [1] run containers [2][4], scheduled [3, 5]. See [note] or [ 6 ]"),
+            BTreeSet::from([1, 2, 3, 4, 5, 6])
+        );
+        assert!(cited_indexes("No citation [a, 1] [").is_empty());
+    }
+
+    #[test]
+    fn only_the_cited_chunks_are_kept() {
+        let citations = vec![citation(1, None), citation(2, None), citation(3, None)];
+
+        let cited = cited_chunks(citations, "Pods run containers [3][1][7].");
+
+        assert_eq!(
+            cited
+                .iter()
+                .map(|citation| citation.index)
+                .collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::postgres::{PgHasArrayType, PgTypeInfo};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Prefix of the API keys, to recognize them for ex in leaked secrets scans
+const API_KEY_PREFIX: &str = "cis_";
+/// Number of random bytes of an API key
+const API_KEY_NB_BYTES: usize = 32;
+/// Number of characters of an API key kept to identify it, with its prefix
+const API_KEY_DISPLAYED_LENGTH: usize = 12;
+
+/// Endpoints an API key gives access to
+#[derive(Debug, Clone, Copy, PartialEq, sqlx::Type, Serialize, Deserialize, ToSchema)]
+#[sqlx(type_name = "api_key_scope", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyScope {
+    /// Adding source files
+    Upload,
+    Search,
+}
+
+impl PgHasArrayType for ApiKeyScope {
+    fn array_type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("_api_key_scope")
+    }
+}
+
+/// Long-lived key used by services and scripts on behalf of a user, instead of logging in
+///
+/// The key is an opaque random value only known by the client: only its hash is stored.
+#[derive(Debug, Clone)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub key_hash: String,
+    /// First characters of the key, to identify it without exposing it
+    pub key_prefix: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ApiKey {
+    /// Generates a new API key of a user
+    ///
+    /// # Returns
+    /// A tuple (key to send to the client, API key to store)
+    pub fn generate(user_id: Uuid, name: String, scopes: Vec<ApiKeyScope>) -> (String, Self) {
+        let mut bytes = [0u8; API_KEY_NB_BYTES];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let key = format!("{}{}", API_KEY_PREFIX, hex::encode(bytes));
+
+        let api_key = Self {
+            id: Uuid::new_v4(),
+            user_id,
+            name,
+            key_hash: Self::hash(&key),
+            key_prefix: key[..API_KEY_DISPLAYED_LENGTH].to_string(),
+            scopes,
+            last_used_at: None,
+            created_at: Utc::now(),
+        };
+
+        (key, api_key)
+    }
+
+    /// Hashes a key sent by a client, to find its stored API key
+    pub fn hash(key: &str) -> String {
+        hex::encode(Sha256::digest(key.as_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_key_is_only_stored_as_a_hash() {
+        let (key, api_key) = ApiKey::generate(
+            Uuid::new_v4(),
+            "Nightly import".to_string(),
+            vec![ApiKeyScope::Upload],
+        );
+
+        assert!(key.starts_with(API_KEY_PREFIX));
+        assert!(key.starts_with(&api_key.key_prefix));
+        assert_eq!(api_key.key_hash, ApiKey::hash(&key));
+        assert_ne!(api_key.key_hash, key);
+    }
+
+    #[test]
+    fn generated_keys_are_unique() {
+        let user_id = Uuid::new_v4();
+        let (first_key, _) = ApiKey::generate(user_id, "first".to_string(), vec![]);
+        let (second_key, _) = ApiKey::generate(user_id, "second".to_string(), vec![]);
+
+        assert_ne!(first_key, second_key);
+    }
+}
@@ -0,0 +1,247 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// Rule filing the uploaded sources of a user in a collection
+///
+/// A rule matches a file if all its set conditions match. A rule has at least one condition.
+#[derive(Debug, Clone)]
+pub struct AutoFilingRule {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// The rules of a user are evaluated by ascending position
+    pub position: i32,
+    /// Wildcard pattern on the file name, case insensitive: `*` matches any characters, `?` a single character
+    pub file_name_pattern: Option<String>,
+    /// MIME type of the file, ex: `text/vtt`. `text/*` matches any text type.
+    pub mime_type: Option<String>,
+    /// Tag given to the file when uploaded
+    pub tag: Option<String>,
+    pub collection: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Uploaded file to file in a collection
+#[derive(Debug)]
+pub struct FilingFile<'a> {
+    pub file_name: &'a str,
+    /// MIME type given by the client, without its parameters
+    pub mime_type: Option<&'a str>,
+    pub tags: &'a [String],
+}
+
+/// Collection in which a file is filed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filing {
+    pub collection: String,
+    /// Matching rule. `None` if filed in the default collection of the user
+    pub rule_id: Option<Uuid>,
+}
+
+impl AutoFilingRule {
+    pub fn has_condition(&self) -> bool {
+        self.file_name_pattern.is_some() || self.mime_type.is_some() || self.tag.is_some()
+    }
+
+    pub fn matches(&self, file: &FilingFile) -> bool {
+        if !self.has_condition() {
+            return false;
+        }
+
+        let file_name_matches = self
+            .file_name_pattern
+            .as_deref()
+            .is_none_or(|pattern| matches_wildcard(pattern, file.file_name));
+        let mime_type_matches = self.mime_type.as_deref().is_none_or(|mime_type| {
+            file.mime_type
+                .is_some_and(|file_mime_type| matches_mime_type(mime_type, file_mime_type))
+        });
+        let tag_matches = self
+            .tag
+            .as_deref()
+            .is_none_or(|tag| file.tags.iter().any(|file_tag| file_tag == tag));
+
+        file_name_matches && mime_type_matches && tag_matches
+    }
+}
+
+/// Files an uploaded file in the collection of the first matching rule
+///
+/// # Arguments
+/// * `rules` - Rules of the user, ordered by position
+/// * `default_collection` - Collection of the user used if no rule matches
+///
+/// # Returns
+/// The collection of the file, or `None` if no rule matches and the user has no default collection
+pub fn file_in_collection(
+    rules: &[AutoFilingRule],
+    default_collection: Option<&str>,
+    file: &FilingFile,
+) -> Option<Filing> {
+    match rules.iter().find(|rule| rule.matches(file)) {
+        Some(rule) => Some(Filing {
+            collection: rule.collection.clone(),
+            rule_id: Some(rule.id),
+        }),
+        None => default_collection.map(|collection| Filing {
+            collection: collection.to_string(),
+            rule_id: None,
+        }),
+    }
+}
+
+/// Matches a text against a wildcard pattern, case insensitive
+fn matches_wildcard(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let (mut pattern_idx, mut text_idx) = (0, 0);
+    // Position of the last `*` in the pattern, and of the text it was matched from
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while text_idx < text.len() {
+        match pattern.get(pattern_idx) {
+            Some('*') => {
+                backtrack = Some((pattern_idx, text_idx));
+                pattern_idx += 1;
+            }
+            Some(&c) if c == '?' || c == text[text_idx] => {
+                pattern_idx += 1;
+                text_idx += 1;
+            }
+            // Extends the text matched by the last `*` by one character
+            _ => match backtrack {
+                Some((star_idx, star_text_idx)) => {
+                    backtrack = Some((star_idx, star_text_idx + 1));
+                    pattern_idx = star_idx + 1;
+                    text_idx = star_text_idx + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[pattern_idx..].iter().all(|&c| c == '*')
+}
+
+/// Matches the MIME type of a file against the MIME type of a rule, which can match any subtype: `text/*`
+pub(crate) fn matches_mime_type(rule_mime_type: &str, file_mime_type: &str) -> bool {
+    match rule_mime_type.strip_suffix("/*") {
+        Some(rule_type) => file_mime_type
+            .split_once('/')
+            .is_some_and(|(file_type, _)| file_type.eq_ignore_ascii_case(rule_type)),
+        None => rule_mime_type.eq_ignore_ascii_case(file_mime_type),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(
+        file_name_pattern: Option<&str>,
+        mime_type: Option<&str>,
+        tag: Option<&str>,
+        collection: &str,
+    ) -> AutoFilingRule {
+        AutoFilingRule {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            position: 0,
+            file_name_pattern: file_name_pattern.map(str::to_string),
+            mime_type: mime_type.map(str::to_string),
+            tag: tag.map(str::to_string),
+            collection: collection.to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn file<'a>(
+        file_name: &'a str,
+        mime_type: Option<&'a str>,
+        tags: &'a [String],
+    ) -> FilingFile<'a> {
+        FilingFile {
+            file_name,
+            mime_type,
+            tags,
+        }
+    }
+
+    #[test]
+    fn wildcard_pattern_matches_file_names() {
+        assert!(matches_wildcard("*.srt", "episode_1.srt"));
+        assert!(matches_wildcard("*.srt", "Episode_1.SRT"));
+        assert!(matches_wildcard("episode_?.*", "episode_1.vtt"));
+        assert!(matches_wildcard(
+            "*lecture*notes*",
+            "2023_lecture_3_notes.tex"
+        ));
+        assert!(matches_wildcard("*", ""));
+        assert!(!matches_wildcard("*.srt", "episode_1.srt.zip"));
+        assert!(!matches_wildcard("episode_?.srt", "episode_10.srt"));
+        assert!(!matches_wildcard("book.epub", "other_book.epub"));
+    }
+
+    #[test]
+    fn mime_type_matches_exactly_or_any_subtype() {
+        assert!(matches_mime_type("text/vtt", "text/vtt"));
+        assert!(matches_mime_type("text/*", "text/vtt"));
+        assert!(!matches_mime_type("text/*", "application/epub+zip"));
+        assert!(!matches_mime_type("text/vtt", "text/plain"));
+    }
+
+    #[test]
+    fn rule_matches_only_if_all_its_conditions_match() {
+        let tags = vec!["course".to_string()];
+        let rule = rule(Some("*.vtt"), Some("text/*"), Some("course"), "Courses");
+
+        assert!(rule.matches(&file("lecture.vtt", Some("text/vtt"), &tags)));
+        assert!(!rule.matches(&file("lecture.vtt", Some("text/vtt"), &[])));
+        assert!(!rule.matches(&file("lecture.vtt", None, &tags)));
+        assert!(!rule.matches(&file("lecture.srt", Some("text/vtt"), &tags)));
+    }
+
+    #[test]
+    fn rule_without_condition_does_not_match() {
+        let rule = rule(None, None, None, "Everything");
+
+        assert!(!rule.matches(&file("book.epub", None, &[])));
+    }
+
+    #[test]
+    fn file_is_filed_by_the_first_matching_rule_or_in_the_default_collection() {
+        let rules = vec![
+            rule(None, None, Some("work"), "Work"),
+            rule(Some("*.epub"), None, None, "Books"),
+        ];
+        let tags = vec!["work".to_string()];
+
+        assert_eq!(
+            file_in_collection(&rules, Some("Inbox"), &file("book.epub", None, &tags)),
+            Some(Filing {
+                collection: "Work".to_string(),
+                rule_id: Some(rules[0].id),
+            })
+        );
+        assert_eq!(
+            file_in_collection(&rules, Some("Inbox"), &file("book.epub", None, &[])),
+            Some(Filing {
+                collection: "Books".to_string(),
+                rule_id: Some(rules[1].id),
+            })
+        );
+        assert_eq!(
+            file_in_collection(&rules, Some("Inbox"), &file("talk.srt", None, &[])),
+            Some(Filing {
+                collection: "Inbox".to_string(),
+                rule_id: None,
+            })
+        );
+        assert_eq!(
+            file_in_collection(&rules, None, &file("talk.srt", None, &[])),
+            None
+        );
+    }
+}
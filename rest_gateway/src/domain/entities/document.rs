@@ -0,0 +1,229 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value as JsonValue;
+use sqlx::types::Json;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Number of first words of a source kept in its summary
+pub const EXCERPT_NB_WORDS: usize = 50;
+/// Number of keywords returned with the summary of a source
+pub const NB_TOP_KEYWORDS: usize = 10;
+/// Number of words counted in the summary of a source: the least frequent ones are pruned
+const MAX_COUNTED_KEYWORDS: usize = 200;
+/// Shorter words are not keywords
+const MIN_KEYWORD_CHARS: usize = 4;
+
+const TITLE_METADATA_KEY: &str = "title";
+const AUTHOR_METADATA_KEY: &str = "author";
+
+/// Frequent words of at least `MIN_KEYWORD_CHARS` characters, in English and French, which are not keywords
+const STOP_WORDS: &[&str] = &[
+    "about", "after", "also", "avec", "been", "before", "being", "cette", "comme", "could", "dans",
+    "does", "each", "elle", "elles", "from", "have", "into", "just", "leur", "leurs", "like",
+    "mais", "more", "nous", "only", "other", "over", "pour", "said", "sans", "some", "sont",
+    "such", "than", "that", "their", "them", "then", "there", "these", "they", "this", "those",
+    "tout", "tous", "very", "vous", "were", "what", "when", "where", "which", "while", "will",
+    "with", "would", "your",
+];
+
+/// Summary of a source, projected from its extracted contents as they are received
+///
+/// The sources are listed with their summary, without aggregating their contents.
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub source_meta_id: Uuid,
+    /// First title found in the metadata of the contents
+    pub title: Option<String>,
+    /// First author found in the metadata of the contents
+    pub author: Option<String>,
+    /// First `EXCERPT_NB_WORDS` words of the source
+    pub excerpt: String,
+    /// Number of contents extracted from the source
+    pub nb_chunks: i64,
+    /// Occurrences of the most frequent words of the source.
+    /// Approximate: the words pruned from the counts start again from zero when found again.
+    pub keyword_counts: Json<BTreeMap<String, i64>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Document {
+    pub fn new(source_meta_id: Uuid) -> Self {
+        Self {
+            source_meta_id,
+            title: None,
+            author: None,
+            excerpt: String::new(),
+            nb_chunks: 0,
+            keyword_counts: Json(BTreeMap::new()),
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Adds an extracted content of the source to its summary
+    pub fn add_chunk(&mut self, content: &str, metadata: &JsonValue) {
+        if self.title.is_none() {
+            self.title = find_metadata_string(metadata, TITLE_METADATA_KEY);
+        }
+        if self.author.is_none() {
+            self.author = find_metadata_string(metadata, AUTHOR_METADATA_KEY);
+        }
+
+        let nb_excerpt_words = self.excerpt.split_whitespace().count();
+        if nb_excerpt_words < EXCERPT_NB_WORDS {
+            let words: Vec<&str> = content
+                .split_whitespace()
+                .take(EXCERPT_NB_WORDS - nb_excerpt_words)
+                .collect();
+            if !words.is_empty() {
+                if !self.excerpt.is_empty() {
+                    self.excerpt.push(' ');
+                }
+                self.excerpt.push_str(&words.join(" "));
+            }
+        }
+
+        for keyword in keywords(content) {
+            *self.keyword_counts.0.entry(keyword).or_default() += 1;
+        }
+        self.prune_keyword_counts();
+
+        self.nb_chunks += 1;
+        self.updated_at = Utc::now();
+    }
+
+    /// Most frequent words of the source, from the most frequent
+    pub fn top_keywords(&self) -> Vec<String> {
+        self.sorted_keyword_counts()
+            .into_iter()
+            .take(NB_TOP_KEYWORDS)
+            .map(|(keyword, _)| keyword.clone())
+            .collect()
+    }
+
+    fn prune_keyword_counts(&mut self) {
+        if self.keyword_counts.0.len() <= MAX_COUNTED_KEYWORDS {
+            return;
+        }
+
+        let kept: BTreeMap<String, i64> = self
+            .sorted_keyword_counts()
+            .into_iter()
+            .take(MAX_COUNTED_KEYWORDS)
+            .map(|(keyword, count)| (keyword.clone(), *count))
+            .collect();
+        self.keyword_counts = Json(kept);
+    }
+
+    /// Counted words from the most frequent, in alphabetical order for the same count
+    fn sorted_keyword_counts(&self) -> Vec<(&String, &i64)> {
+        let mut counts: Vec<(&String, &i64)> = self.keyword_counts.0.iter().collect();
+        counts.sort_by(|(keyword_a, count_a), (keyword_b, count_b)| {
+            count_b.cmp(count_a).then(keyword_a.cmp(keyword_b))
+        });
+        counts
+    }
+}
+
+/// Lowercase words of a content which can be keywords
+fn keywords(content: &str) -> impl Iterator<Item = String> + '_ {
+    content
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= MIN_KEYWORD_CHARS)
+        .filter(|word| !word.chars().all(|c| c.is_numeric()))
+        .map(str::to_lowercase)
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+}
+
+/// First non-empty string of a key in the metadata of a content, nested in the metadata of its reader
+fn find_metadata_string(metadata: &JsonValue, key: &str) -> Option<String> {
+    match metadata {
+        JsonValue::Object(object) => object
+            .get(key)
+            .and_then(JsonValue::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+            .or_else(|| {
+                object
+                    .values()
+                    .find_map(|value| find_metadata_string(value, key))
+            }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn summary_keeps_the_first_title_author_and_words() {
+        let mut document = Document::new(Uuid::new_v4());
+
+        document.add_chunk(
+            "It was a bright cold day in April",
+            &json!({ "html": { "title": "Nineteen Eighty-Four", "initial": { "file": "1984.html" } } }),
+        );
+        document.add_chunk(
+            "and the clocks were striking thirteen",
+            &json!({ "html": { "title": "Chapter 2", "author": "George Orwell" } }),
+        );
+
+        assert_eq!(document.title.as_deref(), Some("Nineteen Eighty-Four"));
+        assert_eq!(document.author.as_deref(), Some("George Orwell"));
+        assert_eq!(
+            document.excerpt,
+            "It was a bright cold day in April and the clocks were striking thirteen"
+        );
+        assert_eq!(document.nb_chunks, 2);
+    }
+
+    #[test]
+    fn excerpt_is_limited_to_its_number_of_words() {
+        let mut document = Document::new(Uuid::new_v4());
+        let content = vec!["word"; EXCERPT_NB_WORDS + 10].join(" ");
+
+        document.add_chunk(&content, &json!({}));
+        document.add_chunk("more words", &json!({}));
+
+        assert_eq!(
+            document.excerpt.split_whitespace().count(),
+            EXCERPT_NB_WORDS
+        );
+    }
+
+    #[test]
+    fn top_keywords_are_the_most_frequent_words_without_stop_words() {
+        let mut document = Document::new(Uuid::new_v4());
+
+        document.add_chunk(
+            "The whale, the whale! Ahab hunts the whale with his harpoon.",
+            &json!({}),
+        );
+        document.add_chunk("Ahab and the harpoon, 1851.", &json!({}));
+
+        assert_eq!(
+            document.top_keywords(),
+            vec!["whale", "ahab", "harpoon", "hunts"]
+        );
+    }
+
+    #[test]
+    fn least_frequent_keywords_are_pruned() {
+        let mut document = Document::new(Uuid::new_v4());
+        let frequent_words = "frequent ".repeat(3);
+        let rare_words: Vec<String> = (0..MAX_COUNTED_KEYWORDS + 50)
+            .map(|index| format!("rare{}", index))
+            .collect();
+
+        document.add_chunk(
+            &format!("{} {}", frequent_words, rare_words.join(" ")),
+            &json!({}),
+        );
+
+        assert_eq!(document.keyword_counts.0.len(), MAX_COUNTED_KEYWORDS);
+        assert_eq!(document.keyword_counts.0.get("frequent"), Some(&3));
+        assert_eq!(document.top_keywords()[0], "frequent");
+    }
+}
@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use common::dtos::extraction_progress::{ExtractionProgressDto, ExtractionStatusDto};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(type_name = "extraction_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ExtractionStatus {
+    InProgress,
+    Completed,
+    Failed,
+}
+
+impl From<ExtractionStatusDto> for ExtractionStatus {
+    fn from(value: ExtractionStatusDto) -> Self {
+        match value {
+            ExtractionStatusDto::InProgress => ExtractionStatus::InProgress,
+            ExtractionStatusDto::Completed => ExtractionStatus::Completed,
+            ExtractionStatusDto::Failed => ExtractionStatus::Failed,
+        }
+    }
+}
+
+/// Latest known progress of the content extraction of a source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractionProgress {
+    pub source_meta_id: Uuid,
+    pub status: ExtractionStatus,
+    /// Number of chunks of content extracted so far
+    pub chunk_index: i64,
+    /// Estimated total number of chunks. Exact once the extraction is completed.
+    pub total_estimated_chunks: i64,
+    /// Number of bytes of text extracted so far
+    pub bytes_processed: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<ExtractionProgressDto> for ExtractionProgress {
+    fn from(value: ExtractionProgressDto) -> Self {
+        Self {
+            source_meta_id: value.source_meta_id,
+            status: value.status.into(),
+            chunk_index: value.chunk_index as i64,
+            total_estimated_chunks: value.total_estimated_chunks as i64,
+            bytes_processed: value.bytes_processed as i64,
+            updated_at: Utc::now(),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+/// Shard of the full-text index of a tenant, with the number of contents indexed in it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FulltextShardSize {
+    pub shard: u32,
+    pub nb_contents: u64,
+}
+
+/// Shard a new source is routed to: the last shard of the tenant, or a new one once it is full
+///
+/// The contents of a source are never split between shards: a shard can exceed the limit
+/// with the contents of the sources routed to it before being full.
+pub fn shard_for_new_source(
+    last_shard: Option<&FulltextShardSize>,
+    max_contents_per_shard: u64,
+) -> u32 {
+    match last_shard {
+        None => 0,
+        Some(last_shard) if last_shard.nb_contents >= max_contents_per_shard => {
+            last_shard.shard + 1
+        }
+        Some(last_shard) => last_shard.shard,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_source_of_a_tenant_is_routed_to_the_first_shard() {
+        assert_eq!(shard_for_new_source(None, 100), 0);
+    }
+
+    #[test]
+    fn source_is_routed_to_the_last_shard_until_it_is_full() {
+        let last_shard = FulltextShardSize {
+            shard: 2,
+            nb_contents: 99,
+        };
+        assert_eq!(shard_for_new_source(Some(&last_shard), 100), 2);
+
+        let last_shard = FulltextShardSize {
+            shard: 2,
+            nb_contents: 100,
+        };
+        assert_eq!(shard_for_new_source(Some(&last_shard), 100), 3);
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+use uuid::Uuid;
+
+/// How long an upload waits for a concurrent upload of the same file before being registered anyway
+pub const IN_FLIGHT_UPLOAD_WAIT: Duration = Duration::from_secs(30);
+
+type InFlightUploadKey = (Uuid, String);
+
+/// Registry of the files being registered as sources, by user and digest of their content
+///
+/// The same file uploaded twice concurrently by a user, for ex on a double click, would not be detected as a duplicate:
+/// none of the uploads is committed when the other one checks for it.
+/// The second upload waits for the first one to be registered, then finds it as a duplicate of the same source.
+///
+/// The registry is in memory: the concurrent uploads reaching different instances of the gateway are not coalesced.
+#[derive(Default)]
+pub struct InFlightUploads {
+    locks: Arc<Mutex<HashMap<InFlightUploadKey, Arc<AsyncMutex<()>>>>>,
+}
+
+impl InFlightUploads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Waits for the concurrent uploads of the same file by a user to be registered, and claims the file
+    ///
+    /// The claim is released when the returned guard is dropped, once the source is committed.
+    /// After `wait`, the file is registered without waiting for the claim: `None` is returned.
+    pub async fn claim(
+        &self,
+        user_id: Uuid,
+        content_hash: &str,
+        wait: Duration,
+    ) -> Option<InFlightUploadGuard> {
+        let key = (user_id, content_hash.to_string());
+        let lock = self
+            .locks
+            .lock()
+            .expect("in-flight uploads lock poisoned")
+            .entry(key.clone())
+            .or_default()
+            .clone();
+
+        let guard = tokio::time::timeout(wait, lock.lock_owned()).await.ok();
+        if guard.is_none() {
+            release(&self.locks, &key);
+        }
+
+        guard.map(|guard| InFlightUploadGuard {
+            key,
+            locks: self.locks.clone(),
+            guard: Some(guard),
+        })
+    }
+
+    #[cfg(test)]
+    fn nb_in_flight(&self) -> usize {
+        self.locks
+            .lock()
+            .expect("in-flight uploads lock poisoned")
+            .len()
+    }
+}
+
+/// Forgets a file once no upload claims it or waits for it anymore
+fn release(
+    locks: &Mutex<HashMap<InFlightUploadKey, Arc<AsyncMutex<()>>>>,
+    key: &InFlightUploadKey,
+) {
+    let mut locks = locks.lock().expect("in-flight uploads lock poisoned");
+
+    if locks
+        .get(key)
+        .map(|lock| Arc::strong_count(lock) == 1)
+        .unwrap_or(false)
+    {
+        locks.remove(key);
+    }
+}
+
+/// Claim on a file being registered, released when dropped
+pub struct InFlightUploadGuard {
+    key: InFlightUploadKey,
+    locks: Arc<Mutex<HashMap<InFlightUploadKey, Arc<AsyncMutex<()>>>>>,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl Drop for InFlightUploadGuard {
+    fn drop(&mut self) {
+        // Unlocks before checking whether an upload still waits for the file
+        self.guard.take();
+        release(&self.locks, &self.key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn concurrent_upload_of_the_same_file_waits_for_the_claim_to_be_released() {
+        let in_flight_uploads = Arc::new(InFlightUploads::new());
+        let user_id = Uuid::new_v4();
+
+        let guard = in_flight_uploads
+            .claim(user_id, "hash", IN_FLIGHT_UPLOAD_WAIT)
+            .await
+            .unwrap();
+
+        // Other files and other users are not waiting
+        assert!(in_flight_uploads
+            .claim(user_id, "other_hash", Duration::from_millis(10))
+            .await
+            .is_some());
+        assert!(in_flight_uploads
+            .claim(Uuid::new_v4(), "hash", Duration::from_millis(10))
+            .await
+            .is_some());
+
+        let waiting_upload = tokio::spawn({
+            let in_flight_uploads = in_flight_uploads.clone();
+            async move {
+                in_flight_uploads
+                    .claim(user_id, "hash", IN_FLIGHT_UPLOAD_WAIT)
+                    .await
+                    .is_some()
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting_upload.is_finished());
+
+        drop(guard);
+
+        assert!(waiting_upload.await.unwrap());
+        assert_eq!(in_flight_uploads.nb_in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn upload_is_not_claimed_after_waiting() {
+        let in_flight_uploads = InFlightUploads::new();
+        let user_id = Uuid::new_v4();
+        let _guard = in_flight_uploads
+            .claim(user_id, "hash", IN_FLIGHT_UPLOAD_WAIT)
+            .await
+            .unwrap();
+
+        let claim = in_flight_uploads
+            .claim(user_id, "hash", Duration::from_millis(10))
+            .await;
+
+        assert!(claim.is_none());
+        assert_eq!(in_flight_uploads.nb_in_flight(), 1);
+    }
+}
@@ -0,0 +1,697 @@
+use chrono::{DateTime, Duration, Utc};
+use common::dtos::{
+    extract_content_job::IngestionLaneDto,
+    ingestion_job_status::{
+        IngestionErrorCodeDto, IngestionJobStatusDto, JobStatusDto, SkippedItemDto,
+    },
+};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::entities::source_meta::SourceType;
+
+/// Status of the ingestion of a source
+///
+/// Transitions: `Pending` → `Extracting` → `Embedded` or `CompletedWithWarnings`, and `Pending` or `Extracting` → `Failed`.
+/// `Embedded`, `CompletedWithWarnings` and `Failed` are final.
+#[derive(Debug, Clone, Copy, PartialEq, sqlx::Type, Serialize, Deserialize, ToSchema)]
+#[sqlx(type_name = "job_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// The source is uploaded, waiting for its extraction
+    Pending,
+    Extracting,
+    /// All the contents of the source went through the embedding
+    Embedded,
+    /// All the extracted contents went through the embedding, but some items of the source were skipped
+    CompletedWithWarnings,
+    Failed,
+}
+
+impl JobStatus {
+    pub fn is_final(&self) -> bool {
+        self.is_completed() || *self == JobStatus::Failed
+    }
+
+    /// All the extracted contents of the source went through the embedding
+    pub fn is_completed(&self) -> bool {
+        matches!(self, JobStatus::Embedded | JobStatus::CompletedWithWarnings)
+    }
+}
+
+/// Item of a source skipped by the extraction, for ex an unreadable chapter of an EPUB
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SkippedItem {
+    /// Path of the item in the source
+    pub path: String,
+    /// Why the item could not be read
+    pub error: String,
+}
+
+impl From<SkippedItemDto> for SkippedItem {
+    fn from(value: SkippedItemDto) -> Self {
+        Self {
+            path: value.path,
+            error: value.error,
+        }
+    }
+}
+
+/// Stable code of the reason of a failed ingestion, explained to the users with a remediation hint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, Serialize, Deserialize, ToSchema)]
+#[sqlx(type_name = "ingestion_error_code", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum IngestionErrorCode {
+    InvalidArchive,
+    DrmProtected,
+    InvalidEncoding,
+    InvalidFormat,
+    MissingMainDocument,
+    UnsupportedLanguage,
+    SourceUnavailable,
+    Internal,
+}
+
+impl IngestionErrorCode {
+    /// What the user can do to ingest the source
+    pub fn hint(&self) -> &'static str {
+        match self {
+            IngestionErrorCode::InvalidArchive => {
+                "The file is corrupted or is not a valid archive: export it again and upload the new file"
+            }
+            IngestionErrorCode::DrmProtected => {
+                "The file is protected by a DRM: upload a DRM-free version of it"
+            }
+            IngestionErrorCode::InvalidEncoding => {
+                "The text of the file is not encoded in UTF-8: convert it to UTF-8 and upload it again"
+            }
+            IngestionErrorCode::InvalidFormat => {
+                "The file does not follow the format of its extension: check that it opens in a reader of this format"
+            }
+            IngestionErrorCode::MissingMainDocument => {
+                "No main LaTeX document (with a \\documentclass) was found: add it to the archive"
+            }
+            IngestionErrorCode::UnsupportedLanguage => {
+                "The programming language of the files is not supported"
+            }
+            IngestionErrorCode::SourceUnavailable => {
+                "The uploaded file could not be read: reindex the source, or upload it again"
+            }
+            IngestionErrorCode::Internal => {
+                "The ingestion failed on our side: reindex the source, and contact the support if it keeps failing"
+            }
+        }
+    }
+}
+
+impl From<IngestionErrorCodeDto> for IngestionErrorCode {
+    fn from(value: IngestionErrorCodeDto) -> Self {
+        match value {
+            IngestionErrorCodeDto::InvalidArchive => IngestionErrorCode::InvalidArchive,
+            IngestionErrorCodeDto::DrmProtected => IngestionErrorCode::DrmProtected,
+            IngestionErrorCodeDto::InvalidEncoding => IngestionErrorCode::InvalidEncoding,
+            IngestionErrorCodeDto::InvalidFormat => IngestionErrorCode::InvalidFormat,
+            IngestionErrorCodeDto::MissingMainDocument => IngestionErrorCode::MissingMainDocument,
+            IngestionErrorCodeDto::UnsupportedLanguage => IngestionErrorCode::UnsupportedLanguage,
+            IngestionErrorCodeDto::SourceUnavailable => IngestionErrorCode::SourceUnavailable,
+            IngestionErrorCodeDto::Internal => IngestionErrorCode::Internal,
+        }
+    }
+}
+
+/// Lane through which a source is ingested, with its own queues, consumers and metrics
+///
+/// The small sources that are not books, like web clips, go through the fast lane to be searchable within seconds,
+/// while the books, archives and large files go through the bulk lane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, Serialize, Deserialize, ToSchema)]
+#[sqlx(type_name = "ingestion_lane", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum IngestionLane {
+    Bulk,
+    Fast,
+}
+
+impl IngestionLane {
+    pub const ALL: [IngestionLane; 2] = [IngestionLane::Bulk, IngestionLane::Fast];
+
+    /// Lane of a new source, from its type and its size. A `fast_lane_max_bytes` of 0 disables the fast lane.
+    pub fn for_source(source_type: &SourceType, size_bytes: u64, fast_lane_max_bytes: u64) -> Self {
+        let is_book_like = matches!(source_type, SourceType::Epub | SourceType::Archive);
+
+        if !is_book_like && size_bytes <= fast_lane_max_bytes {
+            IngestionLane::Fast
+        } else {
+            IngestionLane::Bulk
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IngestionLane::Bulk => "bulk",
+            IngestionLane::Fast => "fast",
+        }
+    }
+}
+
+impl From<IngestionLane> for IngestionLaneDto {
+    fn from(value: IngestionLane) -> Self {
+        match value {
+            IngestionLane::Bulk => IngestionLaneDto::Bulk,
+            IngestionLane::Fast => IngestionLaneDto::Fast,
+        }
+    }
+}
+
+/// Timed stage of the ingestion of a source, to find which one is responsible when the time
+/// for a source to be searchable regresses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IngestionStage {
+    /// Storing the uploaded file and queuing its extraction
+    Upload,
+    /// Waiting for a worker to start the extraction
+    QueueWait,
+    Extraction,
+    /// Indexing the remaining contents once the extraction completed:
+    /// the contents are indexed while the source is being extracted
+    Indexing,
+    /// Embedding the remaining contents once the extraction completed
+    Embedding,
+}
+
+impl IngestionStage {
+    pub const ALL: [IngestionStage; 5] = [
+        IngestionStage::Upload,
+        IngestionStage::QueueWait,
+        IngestionStage::Extraction,
+        IngestionStage::Indexing,
+        IngestionStage::Embedding,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IngestionStage::Upload => "upload",
+            IngestionStage::QueueWait => "queue_wait",
+            IngestionStage::Extraction => "extraction",
+            IngestionStage::Indexing => "indexing",
+            IngestionStage::Embedding => "embedding",
+        }
+    }
+}
+
+/// Status update of an ingestion job, reported by the workers
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobStatusUpdate {
+    ExtractionStarted,
+    ExtractionCompleted {
+        /// Number of contents published by the extraction
+        nb_contents: u64,
+        /// Number of contents of an incremental extraction unchanged since the previous extraction,
+        /// still embedded and indexed but not published again
+        nb_unchanged_contents: u64,
+        /// Items of the source skipped by the extraction, empty if all were extracted
+        skipped_items: Vec<SkippedItem>,
+    },
+    ContentEmbedded,
+    ContentIndexed,
+    Failed {
+        error: String,
+        /// Unknown for the failures reported by the older workers
+        error_code: Option<IngestionErrorCode>,
+    },
+}
+
+impl From<IngestionJobStatusDto> for JobStatusUpdate {
+    fn from(value: IngestionJobStatusDto) -> Self {
+        match (value.status, value.nb_contents) {
+            (JobStatusDto::Extracting, None) => JobStatusUpdate::ExtractionStarted,
+            (JobStatusDto::Extracting, Some(nb_contents)) => JobStatusUpdate::ExtractionCompleted {
+                nb_contents,
+                nb_unchanged_contents: value.nb_unchanged_contents,
+                skipped_items: value.skipped_items.into_iter().map(Into::into).collect(),
+            },
+            (JobStatusDto::Embedded, _) => JobStatusUpdate::ContentEmbedded,
+            (JobStatusDto::Indexed, _) => JobStatusUpdate::ContentIndexed,
+            (JobStatusDto::Failed, _) => JobStatusUpdate::Failed {
+                error: value.error.unwrap_or_else(|| "Unknown error".to_string()),
+                error_code: value.error_code.map(IngestionErrorCode::from),
+            },
+        }
+    }
+}
+
+/// Tracks the extraction and embedding of an uploaded source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngestionJob {
+    pub id: Uuid,
+    pub source_meta_id: Uuid,
+    pub status: JobStatus,
+    pub lane: IngestionLane,
+    /// Number of contents extracted from the source, known once the extraction is completed
+    pub nb_contents: Option<i64>,
+    /// Number of contents that went through the embedding
+    pub nb_embedded_contents: i64,
+    pub error: Option<String>,
+    /// Stable code of the error, to explain it to the user
+    pub error_code: Option<IngestionErrorCode>,
+    /// Items of the source skipped by the extraction, with their errors
+    pub skipped_items: Json<Vec<SkippedItem>>,
+    /// Number of contents saved in the full-text index
+    pub nb_indexed_contents: i64,
+    /// When the gateway started handling the uploaded file, unknown for a reindexed source
+    pub upload_started_at: Option<DateTime<Utc>>,
+    /// When the extraction was queued, on upload or reindex
+    pub queued_at: DateTime<Utc>,
+    pub extraction_started_at: Option<DateTime<Utc>>,
+    pub extraction_completed_at: Option<DateTime<Utc>>,
+    /// When all the contents were saved in the full-text index
+    pub indexed_at: Option<DateTime<Utc>>,
+    /// When all the contents went through the embedding
+    pub embedded_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl IngestionJob {
+    pub fn new(source_meta_id: Uuid) -> Self {
+        let now = Utc::now();
+
+        Self {
+            id: Uuid::new_v4(),
+            source_meta_id,
+            status: JobStatus::Pending,
+            lane: IngestionLane::Bulk,
+            nb_contents: None,
+            nb_embedded_contents: 0,
+            error: None,
+            error_code: None,
+            skipped_items: Json(vec![]),
+            nb_indexed_contents: 0,
+            upload_started_at: None,
+            queued_at: now,
+            extraction_started_at: None,
+            extraction_completed_at: None,
+            indexed_at: None,
+            embedded_at: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Sets when the gateway started handling the uploaded file of the job
+    pub fn with_upload_started_at(mut self, upload_started_at: DateTime<Utc>) -> Self {
+        self.upload_started_at = Some(upload_started_at);
+        self
+    }
+
+    /// Sets the lane through which the source of the job is ingested
+    pub fn with_lane(mut self, lane: IngestionLane) -> Self {
+        self.lane = lane;
+        self
+    }
+
+    /// Restarts the job for a new ingestion of its source, for ex to reindex it
+    ///
+    /// Reindexing is a batch operation: the source goes through the bulk lane.
+    pub fn restart(&mut self) {
+        let now = Utc::now();
+
+        self.status = JobStatus::Pending;
+        self.lane = IngestionLane::Bulk;
+        self.nb_contents = None;
+        self.nb_embedded_contents = 0;
+        self.error = None;
+        self.error_code = None;
+        self.skipped_items = Json(vec![]);
+        self.nb_indexed_contents = 0;
+        self.upload_started_at = None;
+        self.queued_at = now;
+        self.extraction_started_at = None;
+        self.extraction_completed_at = None;
+        self.indexed_at = None;
+        self.embedded_at = None;
+        self.updated_at = now;
+    }
+
+    /// Applies a status update reported by a worker, received now
+    pub fn apply(&mut self, update: JobStatusUpdate) -> Result<(), InvalidJobTransition> {
+        self.apply_at(update, Utc::now())
+    }
+
+    /// Applies a status update reported by a worker, timing the stages of the job
+    ///
+    /// The updates of the extraction and of the embedding are published by different workers:
+    /// the contents of a source can be embedded before its extraction is reported as completed.
+    /// The job is embedded once both the extraction is completed and all its contents are embedded.
+    ///
+    /// The full-text indexing is only timed, it does not change the status:
+    /// the last contents of an embedded job can still be indexed.
+    pub fn apply_at(
+        &mut self,
+        update: JobStatusUpdate,
+        occurred_at: DateTime<Utc>,
+    ) -> Result<(), InvalidJobTransition> {
+        let is_late_indexing =
+            self.status.is_completed() && update == JobStatusUpdate::ContentIndexed;
+        if self.status.is_final() && !is_late_indexing {
+            return Err(InvalidJobTransition {
+                from: self.status,
+                update,
+            });
+        }
+
+        match update {
+            JobStatusUpdate::ExtractionStarted => {
+                self.extraction_started_at.get_or_insert(occurred_at);
+            }
+            JobStatusUpdate::ExtractionCompleted {
+                nb_contents,
+                nb_unchanged_contents,
+                skipped_items,
+            } => {
+                // The unchanged contents are already embedded and indexed, counted once on a redelivered update
+                if self.extraction_completed_at.is_none() {
+                    self.nb_embedded_contents += nb_unchanged_contents as i64;
+                    self.nb_indexed_contents += nb_unchanged_contents as i64;
+                }
+                self.nb_contents = Some((nb_contents + nb_unchanged_contents) as i64);
+                self.skipped_items = Json(skipped_items);
+                self.extraction_completed_at.get_or_insert(occurred_at);
+            }
+            JobStatusUpdate::ContentEmbedded => {
+                self.nb_embedded_contents += 1;
+            }
+            JobStatusUpdate::ContentIndexed => {
+                self.nb_indexed_contents += 1;
+            }
+            JobStatusUpdate::Failed { error, error_code } => {
+                self.status = JobStatus::Failed;
+                self.error = Some(error);
+                self.error_code = error_code;
+                self.updated_at = Utc::now();
+                return Ok(());
+            }
+        }
+
+        self.status = match self.nb_contents {
+            Some(nb_contents) if self.nb_embedded_contents >= nb_contents => {
+                if self.skipped_items.is_empty() {
+                    JobStatus::Embedded
+                } else {
+                    JobStatus::CompletedWithWarnings
+                }
+            }
+            _ => JobStatus::Extracting,
+        };
+
+        if matches!(self.nb_contents, Some(nb_contents) if self.nb_indexed_contents >= nb_contents)
+        {
+            self.indexed_at.get_or_insert(occurred_at);
+        }
+        if self.status.is_completed() {
+            self.embedded_at.get_or_insert(occurred_at);
+        }
+        self.updated_at = Utc::now();
+
+        Ok(())
+    }
+
+    /// Durations of the completed stages of the job
+    ///
+    /// The stages are timed by different services: negative durations, from clock drifts, are reported as zero.
+    pub fn stage_durations(&self) -> Vec<(IngestionStage, Duration)> {
+        IngestionStage::ALL
+            .into_iter()
+            .filter_map(|stage| {
+                let (from, to) = match stage {
+                    IngestionStage::Upload => (self.upload_started_at, Some(self.queued_at)),
+                    IngestionStage::QueueWait => (Some(self.queued_at), self.extraction_started_at),
+                    IngestionStage::Extraction => {
+                        (self.extraction_started_at, self.extraction_completed_at)
+                    }
+                    IngestionStage::Indexing => (self.extraction_completed_at, self.indexed_at),
+                    IngestionStage::Embedding => (self.extraction_completed_at, self.embedded_at),
+                };
+
+                Some((stage, (to? - from?).max(Duration::zero())))
+            })
+            .collect()
+    }
+
+    /// Time for the source to be searchable with both the full-text and the semantic searches,
+    /// from its upload, or from its queuing for a reindexed source
+    pub fn time_to_searchable(&self) -> Option<Duration> {
+        let searchable_at = self.indexed_at?.max(self.embedded_at?);
+        let started_at = self.upload_started_at.unwrap_or(self.queued_at);
+
+        Some((searchable_at - started_at).max(Duration::zero()))
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Invalid transition of an ingestion job from {from:?} with {update:?}")]
+pub struct InvalidJobTransition {
+    pub from: JobStatus,
+    pub update: JobStatusUpdate,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_sources_that_are_not_books_go_through_the_fast_lane() {
+        assert_eq!(
+            IngestionLane::for_source(&SourceType::Html, 1_000, 262_144),
+            IngestionLane::Fast
+        );
+        assert_eq!(
+            IngestionLane::for_source(&SourceType::Html, 1_000_000, 262_144),
+            IngestionLane::Bulk
+        );
+        assert_eq!(
+            IngestionLane::for_source(&SourceType::Epub, 1_000, 262_144),
+            IngestionLane::Bulk
+        );
+        // The fast lane is disabled
+        assert_eq!(
+            IngestionLane::for_source(&SourceType::Srt, 1_000, 0),
+            IngestionLane::Bulk
+        );
+    }
+
+    #[test]
+    fn job_is_embedded_once_extracted_and_all_its_contents_are_embedded() {
+        let mut job = IngestionJob::new(Uuid::new_v4());
+
+        job.apply(JobStatusUpdate::ExtractionStarted).unwrap();
+        assert_eq!(job.status, JobStatus::Extracting);
+
+        job.apply(JobStatusUpdate::ContentEmbedded).unwrap();
+        job.apply(JobStatusUpdate::ExtractionCompleted {
+            nb_contents: 2,
+            nb_unchanged_contents: 0,
+            skipped_items: vec![],
+        })
+        .unwrap();
+        assert_eq!(job.status, JobStatus::Extracting);
+
+        job.apply(JobStatusUpdate::ContentEmbedded).unwrap();
+        assert_eq!(job.status, JobStatus::Embedded);
+        assert_eq!(job.nb_embedded_contents, 2);
+    }
+
+    #[test]
+    fn job_without_content_is_embedded_once_extracted() {
+        let mut job = IngestionJob::new(Uuid::new_v4());
+
+        job.apply(JobStatusUpdate::ExtractionCompleted {
+            nb_contents: 0,
+            nb_unchanged_contents: 0,
+            skipped_items: vec![],
+        })
+        .unwrap();
+
+        assert_eq!(job.status, JobStatus::Embedded);
+    }
+
+    #[test]
+    fn unchanged_contents_of_an_incremental_extraction_are_counted_as_embedded_and_indexed() {
+        let mut job = IngestionJob::new(Uuid::new_v4());
+        let update = JobStatusUpdate::ExtractionCompleted {
+            nb_contents: 1,
+            nb_unchanged_contents: 3,
+            skipped_items: vec![],
+        };
+
+        job.apply(update.clone()).unwrap();
+        job.apply(update).unwrap();
+        assert_eq!(job.nb_contents, Some(4));
+        assert_eq!(job.nb_embedded_contents, 3);
+        assert_eq!(job.status, JobStatus::Extracting);
+
+        job.apply(JobStatusUpdate::ContentIndexed).unwrap();
+        job.apply(JobStatusUpdate::ContentEmbedded).unwrap();
+        assert_eq!(job.nb_indexed_contents, 4);
+        assert_eq!(job.status, JobStatus::Embedded);
+        assert!(job.indexed_at.is_some());
+    }
+
+    #[test]
+    fn failed_job_keeps_its_error_and_is_final() {
+        let mut job = IngestionJob::new(Uuid::new_v4());
+
+        job.apply(JobStatusUpdate::ExtractionStarted).unwrap();
+        job.apply(JobStatusUpdate::Failed {
+            error: "Invalid EPUB".to_string(),
+            error_code: Some(IngestionErrorCode::InvalidFormat),
+        })
+        .unwrap();
+
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.error.as_deref(), Some("Invalid EPUB"));
+        assert_eq!(job.error_code, Some(IngestionErrorCode::InvalidFormat));
+        assert!(job.apply(JobStatusUpdate::ContentEmbedded).is_err());
+        assert_eq!(job.nb_embedded_contents, 0);
+    }
+
+    #[test]
+    fn restarted_job_accepts_updates_again() {
+        let mut job = IngestionJob::new(Uuid::new_v4());
+        job.apply(JobStatusUpdate::Failed {
+            error: "Invalid EPUB".to_string(),
+            error_code: Some(IngestionErrorCode::InvalidFormat),
+        })
+        .unwrap();
+
+        job.restart();
+        job.apply(JobStatusUpdate::ExtractionStarted).unwrap();
+
+        assert_eq!(job.status, JobStatus::Extracting);
+        assert_eq!(job.error, None);
+        assert_eq!(job.error_code, None);
+    }
+
+    #[test]
+    fn stages_are_timed_from_the_reported_times() {
+        let uploaded_at = Utc::now() - Duration::seconds(60);
+        let mut job = IngestionJob::new(Uuid::new_v4()).with_upload_started_at(uploaded_at);
+        job.queued_at = uploaded_at + Duration::seconds(1);
+        let at = |s: i64| uploaded_at + Duration::seconds(s);
+
+        job.apply_at(JobStatusUpdate::ExtractionStarted, at(5))
+            .unwrap();
+        job.apply_at(JobStatusUpdate::ContentIndexed, at(8))
+            .unwrap();
+        job.apply_at(
+            JobStatusUpdate::ExtractionCompleted {
+                nb_contents: 1,
+                nb_unchanged_contents: 0,
+                skipped_items: vec![],
+            },
+            at(10),
+        )
+        .unwrap();
+        assert_eq!(job.indexed_at, Some(at(10)));
+        assert_eq!(job.time_to_searchable(), None);
+
+        job.apply_at(JobStatusUpdate::ContentEmbedded, at(30))
+            .unwrap();
+
+        assert_eq!(
+            job.stage_durations(),
+            vec![
+                (IngestionStage::Upload, Duration::seconds(1)),
+                (IngestionStage::QueueWait, Duration::seconds(4)),
+                (IngestionStage::Extraction, Duration::seconds(5)),
+                (IngestionStage::Indexing, Duration::zero()),
+                (IngestionStage::Embedding, Duration::seconds(20)),
+            ]
+        );
+        assert_eq!(job.time_to_searchable(), Some(Duration::seconds(30)));
+    }
+
+    #[test]
+    fn contents_can_be_indexed_after_the_job_is_embedded() {
+        let mut job = IngestionJob::new(Uuid::new_v4());
+        job.apply(JobStatusUpdate::ExtractionCompleted {
+            nb_contents: 1,
+            nb_unchanged_contents: 0,
+            skipped_items: vec![],
+        })
+        .unwrap();
+        job.apply(JobStatusUpdate::ContentEmbedded).unwrap();
+        assert_eq!(job.status, JobStatus::Embedded);
+        assert_eq!(job.indexed_at, None);
+
+        job.apply(JobStatusUpdate::ContentIndexed).unwrap();
+
+        assert_eq!(job.status, JobStatus::Embedded);
+        assert_eq!(job.nb_indexed_contents, 1);
+        assert!(job.indexed_at.is_some());
+        assert!(job.time_to_searchable().is_some());
+    }
+
+    #[test]
+    fn restarted_job_is_timed_again_without_upload() {
+        let mut job = IngestionJob::new(Uuid::new_v4()).with_upload_started_at(Utc::now());
+        job.apply(JobStatusUpdate::ExtractionCompleted {
+            nb_contents: 0,
+            nb_unchanged_contents: 0,
+            skipped_items: vec![],
+        })
+        .unwrap();
+
+        job.restart();
+
+        assert_eq!(job.upload_started_at, None);
+        assert_eq!(job.embedded_at, None);
+        assert!(job.stage_durations().is_empty());
+    }
+
+    #[test]
+    fn embedded_job_is_final() {
+        let mut job = IngestionJob::new(Uuid::new_v4());
+        job.apply(JobStatusUpdate::ExtractionCompleted {
+            nb_contents: 0,
+            nb_unchanged_contents: 0,
+            skipped_items: vec![],
+        })
+        .unwrap();
+
+        assert!(job
+            .apply(JobStatusUpdate::Failed {
+                error: "Late failure".to_string(),
+                error_code: None,
+            })
+            .is_err());
+        assert_eq!(job.status, JobStatus::Embedded);
+    }
+
+    #[test]
+    fn job_with_skipped_items_completes_with_warnings() {
+        let mut job = IngestionJob::new(Uuid::new_v4());
+        let skipped_items = vec![SkippedItem {
+            path: "OEBPS/chapter_2.xhtml".to_string(),
+            error: "Malformed XHTML: 1 unclosed tags".to_string(),
+        }];
+
+        job.apply(JobStatusUpdate::ExtractionCompleted {
+            nb_contents: 1,
+            nb_unchanged_contents: 0,
+            skipped_items: skipped_items.clone(),
+        })
+        .unwrap();
+        assert_eq!(job.status, JobStatus::Extracting);
+
+        job.apply(JobStatusUpdate::ContentEmbedded).unwrap();
+
+        assert_eq!(job.status, JobStatus::CompletedWithWarnings);
+        assert_eq!(job.skipped_items.0, skipped_items);
+        assert!(job.embedded_at.is_some());
+        assert!(job.apply(JobStatusUpdate::ContentIndexed).is_ok());
+        assert!(job.apply(JobStatusUpdate::ContentEmbedded).is_err());
+    }
+}
@@ -0,0 +1,72 @@
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Percentiles of a set of latencies, in milliseconds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct LatencySummary {
+    pub nb_samples: usize,
+    pub p50_ms: i64,
+    pub p95_ms: i64,
+    pub p99_ms: i64,
+    pub max_ms: i64,
+}
+
+impl LatencySummary {
+    /// Summarizes latencies with nearest-rank percentiles
+    ///
+    /// # Returns
+    /// `None` without any latency
+    pub fn from_durations(durations: impl IntoIterator<Item = Duration>) -> Option<Self> {
+        let mut latencies_ms: Vec<i64> = durations
+            .into_iter()
+            .map(|duration| duration.num_milliseconds())
+            .collect();
+        latencies_ms.sort_unstable();
+
+        let max_ms = *latencies_ms.last()?;
+        let percentile = |percent: f64| {
+            let rank = (percent / 100.0 * latencies_ms.len() as f64).ceil() as usize;
+            latencies_ms[rank.max(1) - 1]
+        };
+
+        Some(Self {
+            nb_samples: latencies_ms.len(),
+            p50_ms: percentile(50.0),
+            p95_ms: percentile(95.0),
+            p99_ms: percentile(99.0),
+            max_ms,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_are_nearest_ranks() {
+        let summary =
+            LatencySummary::from_durations((1..=100).rev().map(Duration::milliseconds)).unwrap();
+
+        assert_eq!(
+            summary,
+            LatencySummary {
+                nb_samples: 100,
+                p50_ms: 50,
+                p95_ms: 95,
+                p99_ms: 99,
+                max_ms: 100,
+            }
+        );
+    }
+
+    #[test]
+    fn single_latency_is_all_the_percentiles() {
+        let summary = LatencySummary::from_durations([Duration::seconds(2)]).unwrap();
+
+        assert_eq!(summary.p50_ms, 2000);
+        assert_eq!(summary.p99_ms, 2000);
+        assert!(LatencySummary::from_durations([]).is_none());
+    }
+}
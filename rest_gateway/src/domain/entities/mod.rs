@@ -1,4 +1,29 @@
+pub mod answer;
+pub mod api_key;
+pub mod auto_filing_rule;
+pub mod document;
+pub mod extraction_progress;
+pub mod fulltext_shard;
+pub mod in_flight_upload;
+pub mod ingestion_job;
+pub mod latency_summary;
+pub mod normalization_rule;
+pub mod outbox_message;
+pub mod provider_credentials;
+pub mod refresh_token;
+pub mod retention_rule;
+pub mod saved_search;
+pub mod search_result;
+pub mod sniffed_content;
+pub mod source_event;
 pub mod source_meta;
+pub mod source_summary;
+pub mod source_url_schedule;
+pub mod storage_usage;
+pub mod two_factor;
+pub mod upload_policy;
+pub mod upload_session;
 pub mod user;
+pub mod user_activity;
 pub mod user_email;
 pub mod user_password;
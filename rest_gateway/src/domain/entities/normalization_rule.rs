@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use common::dtos::normalization_rules::{NormalizationRuleDto, NormalizationRuleKindDto};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, Serialize, Deserialize, ToSchema)]
+#[sqlx(type_name = "normalization_rule_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum NormalizationRuleKind {
+    /// Expands an abbreviation: `k8s` to `Kubernetes`
+    Abbreviation,
+    /// Replaces a variant of a product name by its canonical name: `Postgres` to `PostgreSQL`
+    CanonicalName,
+    /// Removes the text matching a regular expression
+    StopPattern,
+}
+
+/// Rule of the dictionary of a tenant (organization), normalizing its extracted contents and its search queries
+///
+/// The rules are applied by the content ingestion worker and the search services, which cache them
+/// until a change of the rules of their tenant.
+#[derive(Debug, Clone)]
+pub struct NormalizationRule {
+    pub id: Uuid,
+    /// `None` for the users without tenant
+    pub tenant_id: Option<String>,
+    pub kind: NormalizationRuleKind,
+    /// Replaced term, matched as whole words ignoring the case, or regular expression of a stop pattern
+    pub pattern: String,
+    /// Replacing term, `None` for a stop pattern
+    pub replacement: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<NormalizationRuleKind> for NormalizationRuleKindDto {
+    fn from(value: NormalizationRuleKind) -> Self {
+        match value {
+            NormalizationRuleKind::Abbreviation => NormalizationRuleKindDto::Abbreviation,
+            NormalizationRuleKind::CanonicalName => NormalizationRuleKindDto::CanonicalName,
+            NormalizationRuleKind::StopPattern => NormalizationRuleKindDto::StopPattern,
+        }
+    }
+}
+
+impl From<NormalizationRule> for NormalizationRuleDto {
+    fn from(value: NormalizationRule) -> Self {
+        Self {
+            kind: value.kind.into(),
+            pattern: value.pattern,
+            replacement: value.replacement,
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+/// Time left to the writer of a message to publish it right after its transaction, before the relay does
+const PUBLISHING_GRACE_S: i64 = 30;
+
+/// Message written in the outbox in the same transaction as the changes it announces
+///
+/// A message is only published once its transaction is committed: right after it by its writer,
+/// or by the outbox relay if the writer failed to. It can be published more than once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxMessage {
+    /// Id of the envelope of the message, kept when it is published again
+    pub id: Uuid,
+    /// Tenant whose exchange the message is published on, `None` for the shared exchange
+    pub tenant_id: Option<String>,
+    pub routing_key: String,
+    /// Serialized envelope of the message
+    pub payload: String,
+    pub created_at: DateTime<Utc>,
+    /// Time from which the relay publishes the message if it was not sent
+    pub next_attempt_at: DateTime<Utc>,
+}
+
+impl OutboxMessage {
+    pub fn new(id: Uuid, tenant_id: Option<&str>, routing_key: &str, payload: String) -> Self {
+        let now = Utc::now();
+
+        Self {
+            id,
+            tenant_id: tenant_id.map(str::to_string),
+            routing_key: routing_key.to_string(),
+            payload,
+            created_at: now,
+            next_attempt_at: now + Duration::seconds(PUBLISHING_GRACE_S),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_messages_should_be_left_to_their_writer_first() {
+        let message = OutboxMessage::new(Uuid::new_v4(), Some("finance"), "extract", "{}".into());
+
+        assert_eq!(message.tenant_id.as_deref(), Some("finance"));
+        assert!(message.next_attempt_at > message.created_at);
+    }
+}
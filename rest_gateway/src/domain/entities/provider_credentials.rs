@@ -0,0 +1,113 @@
+use chrono::{DateTime, Utc};
+use common::dtos::{provider_credentials::ModelProviderDto, provider_usage::ProviderPurposeDto};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Number of last characters of an API key kept to identify it
+const API_KEY_HINT_LENGTH: usize = 4;
+
+/// Model provider of an API key brought by a tenant
+#[derive(Debug, Clone, Copy, PartialEq, sqlx::Type, Serialize, Deserialize, ToSchema)]
+#[sqlx(type_name = "model_provider", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ModelProvider {
+    #[serde(rename = "openai")]
+    #[sqlx(rename = "openai")]
+    OpenAi,
+    Mistral,
+}
+
+/// What a tenant uses a provider for
+#[derive(Debug, Clone, Copy, PartialEq, sqlx::Type, Serialize, Deserialize, ToSchema)]
+#[sqlx(type_name = "provider_purpose", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderPurpose {
+    /// Embeddings of the extracted contents and of the semantic search queries
+    Embedding,
+    Completion,
+}
+
+impl From<ModelProvider> for ModelProviderDto {
+    fn from(value: ModelProvider) -> Self {
+        match value {
+            ModelProvider::OpenAi => ModelProviderDto::OpenAi,
+            ModelProvider::Mistral => ModelProviderDto::Mistral,
+        }
+    }
+}
+
+impl From<ProviderPurpose> for ProviderPurposeDto {
+    fn from(value: ProviderPurpose) -> Self {
+        match value {
+            ProviderPurpose::Embedding => ProviderPurposeDto::Embedding,
+            ProviderPurpose::Completion => ProviderPurposeDto::Completion,
+        }
+    }
+}
+
+impl From<ProviderPurposeDto> for ProviderPurpose {
+    fn from(value: ProviderPurposeDto) -> Self {
+        match value {
+            ProviderPurposeDto::Embedding => ProviderPurpose::Embedding,
+            ProviderPurposeDto::Completion => ProviderPurpose::Completion,
+        }
+    }
+}
+
+/// API key and model of a provider brought by a tenant (BYOK), used for the contents of its users
+///
+/// The key is only stored encrypted: it is decrypted by the services calling the provider,
+/// the embedding workers for the embeddings and the gateway for the answers.
+#[derive(Debug, Clone)]
+pub struct ProviderCredentials {
+    pub tenant_id: String,
+    pub purpose: ProviderPurpose,
+    pub provider: ModelProvider,
+    pub model: String,
+    pub encrypted_api_key: String,
+    /// Last characters of the key, to identify it without exposing it
+    pub api_key_hint: String,
+    /// Usage of the provider, reported by the services calling it
+    pub nb_requests: i64,
+    pub nb_tokens: i64,
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// User who last saved the key
+    pub updated_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Hint identifying an API key: its last characters, the rest being masked
+pub fn api_key_hint(api_key: &str) -> String {
+    let nb_chars = api_key.chars().count();
+    let hint: String = api_key
+        .chars()
+        .skip(nb_chars.saturating_sub(API_KEY_HINT_LENGTH))
+        .collect();
+
+    format!("…{}", hint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_key_hint_only_keeps_the_last_characters_of_the_key() {
+        assert_eq!(api_key_hint("sk-proj-abcdef123456"), "…3456");
+        assert_eq!(api_key_hint("abc"), "…abc");
+    }
+
+    #[test]
+    fn providers_and_purposes_are_named_in_snake_case() {
+        assert_eq!(
+            serde_json::to_string(&ModelProvider::OpenAi).unwrap(),
+            "\"openai\""
+        );
+        assert_eq!(
+            serde_json::from_str::<ProviderPurpose>("\"embedding\"").unwrap(),
+            ProviderPurpose::Embedding
+        );
+    }
+}
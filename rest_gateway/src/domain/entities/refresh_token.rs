@@ -0,0 +1,87 @@
+use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Number of random bytes of a refresh token
+const REFRESH_TOKEN_NB_BYTES: usize = 32;
+
+/// Long-lived token of a log-in session, exchanged for new access tokens
+///
+/// The token is an opaque random value only known by the client: only its hash is stored.
+/// A token can be used only once: it is revoked and replaced by a new token of the same session on each refresh.
+#[derive(Debug, Clone)]
+pub struct RefreshToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub session_id: Uuid,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl RefreshToken {
+    /// Generates a new refresh token of a session
+    ///
+    /// # Returns
+    /// A tuple (token to send to the client, refresh token to store)
+    pub fn generate(user_id: Uuid, session_id: Uuid, expire_in_s: i64) -> (String, Self) {
+        let mut bytes = [0u8; REFRESH_TOKEN_NB_BYTES];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let token = hex::encode(bytes);
+
+        let now = Utc::now();
+        let refresh_token = Self {
+            id: Uuid::new_v4(),
+            user_id,
+            session_id,
+            token_hash: Self::hash(&token),
+            expires_at: now + Duration::seconds(expire_in_s),
+            revoked_at: None,
+            created_at: now,
+        };
+
+        (token, refresh_token)
+    }
+
+    /// Hashes a token sent by a client, to find its stored refresh token
+    pub fn hash(token: &str) -> String {
+        hex::encode(Sha256::digest(token.as_bytes()))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_token_is_only_stored_as_a_hash() {
+        let (token, refresh_token) = RefreshToken::generate(Uuid::new_v4(), Uuid::new_v4(), 60);
+
+        assert_eq!(token.len(), REFRESH_TOKEN_NB_BYTES * 2);
+        assert_ne!(refresh_token.token_hash, token);
+        assert_eq!(refresh_token.token_hash, RefreshToken::hash(&token));
+        assert!(!refresh_token.is_expired());
+    }
+
+    #[test]
+    fn generated_tokens_are_unique() {
+        let session_id = Uuid::new_v4();
+        let (first_token, _) = RefreshToken::generate(Uuid::new_v4(), session_id, 60);
+        let (second_token, _) = RefreshToken::generate(Uuid::new_v4(), session_id, 60);
+
+        assert_ne!(first_token, second_token);
+    }
+
+    #[test]
+    fn token_with_negative_expire_in_is_expired() {
+        let (_, refresh_token) = RefreshToken::generate(Uuid::new_v4(), Uuid::new_v4(), -1);
+
+        assert!(refresh_token.is_expired());
+    }
+}
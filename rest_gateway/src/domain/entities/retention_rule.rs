@@ -0,0 +1,55 @@
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+/// Retention of the sources of a collection of a user, deleted once older than it
+///
+/// A collection has at most one retention rule. The sources without collection are kept.
+#[derive(Debug, Clone)]
+pub struct RetentionRule {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub collection: String,
+    /// The sources are deleted this number of days after they were added
+    pub retention_days: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl RetentionRule {
+    /// When a source of the collection added at a given time expires
+    pub fn expires_at(&self, added_at: DateTime<Utc>) -> DateTime<Utc> {
+        added_at + Duration::days(self.retention_days.into())
+    }
+}
+
+/// Source expiring soon with the retention of its collection
+#[derive(Debug, Clone)]
+pub struct ExpiringSource {
+    pub source_meta_id: Uuid,
+    pub user_id: Uuid,
+    pub collection: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_expires_after_the_retention_of_its_collection() {
+        let now = Utc::now();
+        let rule = RetentionRule {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            collection: "web clips".to_string(),
+            retention_days: 90,
+            created_at: now,
+            updated_at: now,
+        };
+
+        assert_eq!(
+            rule.expires_at(now - Duration::days(100)),
+            now - Duration::days(10)
+        );
+    }
+}
@@ -0,0 +1,25 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+
+/// Search saved by a user under a name, to be run again by its id
+#[derive(Debug, Clone)]
+pub struct SavedSearch {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    /// Body of the search, as sent to `/search`
+    pub request: JsonValue,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Search run by a user, recorded in their search history
+#[derive(Debug, Clone)]
+pub struct SearchHistoryEntry {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub query: String,
+    /// Body of the search, as sent to `/search`
+    pub request: JsonValue,
+    pub searched_at: DateTime<Utc>,
+}
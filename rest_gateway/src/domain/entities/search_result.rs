@@ -0,0 +1,664 @@
+use common::dtos::{
+    extracted_content::{
+        metadata_chapter, metadata_entities, metadata_keywords, LANGUAGE_METADATA_KEY,
+        SOURCE_ADDED_AT_METADATA_KEY, SOURCE_NAME_METADATA_KEY,
+    },
+    fulltext_search_request::SearchSortDto,
+    fulltext_search_response::ResultContent,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::{cmp::Ordering, collections::HashMap};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::source_meta::{SourceMeta, SourceType};
+
+/// Constant of the reciprocal rank fusion, damping the weight of the top ranked results
+pub const RRF_K: f64 = 60.0;
+
+/// Search backend a result was found by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchSource {
+    Fulltext,
+    Semantic,
+}
+
+/// Content found by one or several search backends
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SearchResult {
+    pub id: Uuid,
+    #[schema(value_type = Object)]
+    pub metadata: JsonValue,
+    pub content: String,
+    /// Reciprocal rank fusion score: the sum of `1 / (RRF_K + rank)` over the backends that found it
+    pub score: f64,
+    /// Backends that found the content
+    pub sources: Vec<SearchSource>,
+    /// Timestamp (`hh:mm:ss.mmm`) of the first cue of a content read from subtitles
+    pub start_time: Option<String>,
+    /// Timestamp (`hh:mm:ss.mmm`) of the end of the last cue of a content read from subtitles
+    pub end_time: Option<String>,
+    /// Temporal media fragment (`t=<start>,<end>`, in seconds) of a content read from subtitles:
+    /// appended as `#t=...` to the URL of the lecture or podcast, it deep-links to the transcript timestamp
+    pub media_fragment: Option<String>,
+    /// Relevance of the content to the query scored by the cross-encoder, when the search is reranked
+    pub rerank_score: Option<f64>,
+}
+
+/// Merges the ranked results of several search backends with reciprocal rank fusion
+///
+/// A content found by several backends is returned once, with the metadata of the first backend that found it.
+/// Results are sorted from the highest score, ties keep the order of the given rankings.
+pub fn fuse_rankings(rankings: Vec<(SearchSource, Vec<ResultContent>)>) -> Vec<SearchResult> {
+    let mut results: Vec<SearchResult> = vec![];
+
+    for (source, ranking) in rankings {
+        for (index, content) in ranking.into_iter().enumerate() {
+            let score = 1.0 / (RRF_K + (index + 1) as f64);
+
+            match results.iter_mut().find(|result| result.id == content.id) {
+                Some(result) => {
+                    result.score += score;
+                    if !result.sources.contains(&source) {
+                        result.sources.push(source);
+                    }
+                }
+                None => results.push(SearchResult {
+                    id: content.id,
+                    start_time: subtitle_timestamp(&content.metadata, "start_time"),
+                    end_time: subtitle_timestamp(&content.metadata, "end_time"),
+                    media_fragment: media_fragment(&content.metadata),
+                    metadata: content.metadata,
+                    content: content.content,
+                    score,
+                    sources: vec![source],
+                    rerank_score: None,
+                }),
+            }
+        }
+    }
+
+    // Stable sort: ties keep their insertion order
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results
+}
+
+/// Sorts results by the relevance scores of the reranker, in the order of the results, from the highest score
+///
+/// Ties keep their fused rank.
+pub fn rerank_results(results: &mut [SearchResult], scores: &[f64]) {
+    for (result, score) in results.iter_mut().zip(scores) {
+        result.rerank_score = Some(*score);
+    }
+
+    // Stable sort: ties keep their insertion order
+    results.sort_by(|a, b| {
+        b.rerank_score
+            .unwrap_or(f64::MIN)
+            .total_cmp(&a.rerank_score.unwrap_or(f64::MIN))
+    });
+}
+
+/// Timestamp of a content set in its metadata by the subtitle reader
+fn subtitle_timestamp(metadata: &JsonValue, key: &str) -> Option<String> {
+    let timestamp = metadata.get("subtitle")?.get(key)?.as_str()?;
+
+    Some(timestamp.to_string())
+}
+
+/// Builds the temporal media fragment of a content from the timestamps set in its metadata by the subtitle reader
+fn media_fragment(metadata: &JsonValue) -> Option<String> {
+    let subtitle = metadata.get("subtitle")?;
+    let start_ms = subtitle.get("start_ms")?.as_u64()?;
+    let end_ms = subtitle.get("end_ms")?.as_u64()?;
+
+    Some(format!(
+        "t={}.{:03},{}.{:03}",
+        start_ms / 1000,
+        start_ms % 1000,
+        end_ms / 1000,
+        end_ms % 1000
+    ))
+}
+
+/// Page of the results of a search, in a given order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchPage {
+    /// From 1
+    pub page: usize,
+    pub hits_per_page: usize,
+    pub sort: SearchSortDto,
+}
+
+impl SearchPage {
+    /// Number of results up to the end of the page: the results of the page are among the first ones of each backend
+    pub fn window(&self) -> usize {
+        self.page * self.hits_per_page
+    }
+
+    /// First page holding all the results up to the end of the page
+    pub fn first_page_of_window(&self) -> Self {
+        Self {
+            page: 1,
+            hits_per_page: self.window(),
+            sort: self.sort,
+        }
+    }
+
+    /// First page holding at least a number of candidates to rerank, and all the results up to the end of the page
+    pub fn first_page_of_candidates(&self, candidates: usize) -> Self {
+        Self {
+            page: 1,
+            hits_per_page: self.window().max(candidates),
+            sort: self.sort,
+        }
+    }
+
+    /// Results of the page, from all the results up to its end
+    pub fn slice<T>(&self, mut results: Vec<T>) -> Vec<T> {
+        results.truncate(self.window());
+        results
+            .into_iter()
+            .skip((self.page - 1) * self.hits_per_page)
+            .collect()
+    }
+
+    /// Number of pages of a number of results
+    pub fn total_pages(&self, total_hits: u64) -> u64 {
+        total_hits.div_ceil(self.hits_per_page as u64)
+    }
+}
+
+/// Sorts ranked results by the metadata of their contents, the results with the same value keeping their rank
+///
+/// Sorted as the full-text search service sorts them: from the most recently added source,
+/// or by the name of their source. The results without the metadata, extracted before it was added, are last.
+pub fn sort_by_metadata<T>(
+    results: &mut [T],
+    sort: SearchSortDto,
+    metadata: impl Fn(&T) -> &JsonValue,
+) {
+    match sort {
+        SearchSortDto::Relevance => {}
+        SearchSortDto::Recency => results.sort_by(|a, b| {
+            compare_present_first(
+                metadata(a)[SOURCE_ADDED_AT_METADATA_KEY].as_i64(),
+                metadata(b)[SOURCE_ADDED_AT_METADATA_KEY].as_i64(),
+                |a, b| b.cmp(a),
+            )
+        }),
+        SearchSortDto::SourceName => results.sort_by(|a, b| {
+            compare_present_first(
+                metadata(a)[SOURCE_NAME_METADATA_KEY].as_str(),
+                metadata(b)[SOURCE_NAME_METADATA_KEY].as_str(),
+                |a, b| a.to_lowercase().cmp(&b.to_lowercase()),
+            )
+        }),
+    }
+}
+
+fn compare_present_first<V>(
+    a: Option<V>,
+    b: Option<V>,
+    compare: impl Fn(&V, &V) -> Ordering,
+) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => compare(&a, &b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Merges the rankings of the shards of the full-text index into one ranking, before their fusion with the other backends
+///
+/// The shards hold different contents and return no score: the results are interleaved by rank.
+/// The merged ranking is as long as the ranking of a single shard.
+pub fn merge_shard_rankings(
+    shard_rankings: Vec<Vec<ResultContent>>,
+    limit: Option<usize>,
+) -> Vec<ResultContent> {
+    let limit = limit.unwrap_or_else(|| shard_rankings.iter().map(Vec::len).max().unwrap_or(0));
+    let mut shard_rankings: Vec<_> = shard_rankings.into_iter().map(Vec::into_iter).collect();
+    let mut results: Vec<ResultContent> = vec![];
+
+    while results.len() < limit {
+        let rank_results: Vec<ResultContent> = shard_rankings
+            .iter_mut()
+            .filter_map(Iterator::next)
+            .collect();
+        if rank_results.is_empty() {
+            break;
+        }
+
+        results.extend(rank_results);
+    }
+
+    results.truncate(limit);
+    results
+}
+
+/// Number of contents matching a search counted by a search backend, per source, language, chapter,
+/// keyword and named entity
+#[derive(Debug, Default, PartialEq)]
+pub struct BackendHitCounts {
+    pub sources: HashMap<Uuid, u64>,
+    pub languages: HashMap<String, u64>,
+    pub chapters: HashMap<String, u64>,
+    pub keywords: HashMap<String, u64>,
+    pub entities: HashMap<String, u64>,
+}
+
+impl BackendHitCounts {
+    /// Counts the found contents, for the backends only returning the found contents
+    pub fn from_found_contents(contents: &[ResultContent]) -> Self {
+        let mut hit_counts = Self::default();
+
+        for content in contents {
+            if let Some(source_meta_id) = content.source_meta_id {
+                *hit_counts.sources.entry(source_meta_id).or_default() += 1;
+            }
+            if let Some(language) = content.metadata[LANGUAGE_METADATA_KEY].as_str() {
+                *hit_counts
+                    .languages
+                    .entry(language.to_string())
+                    .or_default() += 1;
+            }
+            if let Some(chapter) = metadata_chapter(&content.metadata) {
+                *hit_counts.chapters.entry(chapter.to_string()).or_default() += 1;
+            }
+            for keyword in metadata_keywords(&content.metadata) {
+                *hit_counts.keywords.entry(keyword.to_string()).or_default() += 1;
+            }
+            for entity in metadata_entities(&content.metadata) {
+                *hit_counts.entities.entry(entity.to_string()).or_default() += 1;
+            }
+        }
+
+        hit_counts
+    }
+}
+
+/// Number of contents matching a search per source, collection, source type, language and chapter,
+/// to render the filters of the results with their counts without searching again
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SearchAggregations {
+    pub sources: HashMap<Uuid, u64>,
+    /// The sources without collection are not counted
+    pub collections: HashMap<String, u64>,
+    pub source_types: HashMap<SourceType, u64>,
+    /// The contents whose language was not detected are not counted
+    #[serde(default)]
+    pub languages: HashMap<String, u64>,
+    /// Chapters of the contents: the ids of the EPUB chapters and the titles of the LaTeX chapters
+    #[serde(default)]
+    pub chapters: HashMap<String, u64>,
+    /// Keywords extracted from the contents, lowercase
+    #[serde(default)]
+    pub keywords: HashMap<String, u64>,
+    /// Named entities mentioned by the contents: people, places and organizations together
+    #[serde(default)]
+    pub entities: HashMap<String, u64>,
+}
+
+impl SearchAggregations {
+    /// Aggregates the hits counted by each search backend, from the sources of the user
+    ///
+    /// A content may be found by several backends, without their hits being told apart:
+    /// the hits of a source, a language or a chapter are its highest count among the backends.
+    /// The sources not listed, for ex deleted since their contents were indexed, are not counted.
+    pub fn from_hit_counts(
+        backend_hit_counts: &[BackendHitCounts],
+        source_metas: &[SourceMeta],
+    ) -> Self {
+        let mut aggregations = Self {
+            languages: highest_counts(backend_hit_counts.iter().map(|counts| &counts.languages)),
+            chapters: highest_counts(backend_hit_counts.iter().map(|counts| &counts.chapters)),
+            keywords: highest_counts(backend_hit_counts.iter().map(|counts| &counts.keywords)),
+            entities: highest_counts(backend_hit_counts.iter().map(|counts| &counts.entities)),
+            ..Self::default()
+        };
+
+        for source_meta in source_metas {
+            let count = backend_hit_counts
+                .iter()
+                .filter_map(|hit_counts| hit_counts.sources.get(&source_meta.id))
+                .max()
+                .copied()
+                .unwrap_or(0);
+            if count == 0 {
+                continue;
+            }
+
+            aggregations.sources.insert(source_meta.id, count);
+            if let Some(collection) = &source_meta.collection {
+                *aggregations
+                    .collections
+                    .entry(collection.clone())
+                    .or_default() += count;
+            }
+            *aggregations
+                .source_types
+                .entry(source_meta.source_type.clone())
+                .or_default() += count;
+        }
+
+        aggregations
+    }
+}
+
+/// Highest count of each value among the counts of the backends
+fn highest_counts<'a>(
+    backend_counts: impl Iterator<Item = &'a HashMap<String, u64>>,
+) -> HashMap<String, u64> {
+    let mut counts: HashMap<String, u64> = HashMap::new();
+
+    for backend_counts in backend_counts {
+        for (value, count) in backend_counts {
+            let highest_count = counts.entry(value.clone()).or_default();
+            *highest_count = (*highest_count).max(*count);
+        }
+    }
+
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents(ids: &[Uuid]) -> Vec<ResultContent> {
+        ids.iter()
+            .map(|id| ResultContent {
+                id: *id,
+                metadata: JsonValue::Null,
+                content: format!("content {}", id),
+                source_meta_id: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn contents_found_by_both_backends_are_ranked_first() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        let results = fuse_rankings(vec![
+            (SearchSource::Fulltext, contents(&[a, b])),
+            (SearchSource::Semantic, contents(&[c, b])),
+        ]);
+
+        assert_eq!(
+            results.iter().map(|result| result.id).collect::<Vec<_>>(),
+            vec![b, a, c]
+        );
+        assert_eq!(
+            results[0].sources,
+            vec![SearchSource::Fulltext, SearchSource::Semantic]
+        );
+        assert_eq!(results[1].sources, vec![SearchSource::Fulltext]);
+        assert_eq!(results[2].sources, vec![SearchSource::Semantic]);
+        assert_eq!(results[0].score, 2.0 / (RRF_K + 2.0));
+    }
+
+    #[test]
+    fn single_ranking_keeps_its_order() {
+        let ids = vec![Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+
+        let results = fuse_rankings(vec![(SearchSource::Semantic, contents(&ids))]);
+
+        assert_eq!(
+            results.iter().map(|result| result.id).collect::<Vec<_>>(),
+            ids
+        );
+        assert!(results
+            .iter()
+            .all(|result| result.sources == vec![SearchSource::Semantic]));
+    }
+
+    #[test]
+    fn reranked_results_are_sorted_by_their_rerank_score() {
+        let ids = vec![Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+        let mut results = fuse_rankings(vec![(SearchSource::Semantic, contents(&ids))]);
+
+        rerank_results(&mut results, &[0.1, 0.8, 0.1]);
+
+        assert_eq!(
+            results.iter().map(|result| result.id).collect::<Vec<_>>(),
+            vec![ids[1], ids[0], ids[2]]
+        );
+        assert_eq!(results[0].rerank_score, Some(0.8));
+    }
+
+    #[test]
+    fn candidates_page_holds_the_results_up_to_the_end_of_the_page() {
+        let page = SearchPage {
+            page: 3,
+            hits_per_page: 20,
+            sort: SearchSortDto::Relevance,
+        };
+
+        assert_eq!(page.first_page_of_candidates(50).window(), 60);
+        assert_eq!(page.first_page_of_candidates(100).window(), 100);
+    }
+
+    #[test]
+    fn contents_read_from_subtitles_have_a_media_fragment() {
+        let (subtitle, book) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut ranking = contents(&[subtitle, book]);
+        ranking[0].metadata = serde_json::json!({
+            "subtitle": {
+                "format": "vtt",
+                "start_time": "01:00:05.000",
+                "end_time": "01:00:07.250",
+                "start_ms": 3_605_000,
+                "end_ms": 3_607_250
+            }
+        });
+        ranking[1].metadata = serde_json::json!({ "epub": { "chapter": 2 } });
+
+        let results = fuse_rankings(vec![(SearchSource::Fulltext, ranking)]);
+
+        assert_eq!(
+            results[0].media_fragment.as_deref(),
+            Some("t=3605.000,3607.250")
+        );
+        assert_eq!(results[1].media_fragment, None);
+
+        let output = serde_json::to_value(&results).unwrap();
+        assert_eq!(output[0]["start_time"], "01:00:05.000");
+        assert_eq!(output[0]["end_time"], "01:00:07.250");
+        assert_eq!(output[0]["media_fragment"], "t=3605.000,3607.250");
+        assert_eq!(output[1]["start_time"], serde_json::Value::Null);
+        assert_eq!(output[1]["end_time"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn results_are_sorted_by_their_source_with_the_ones_without_metadata_last() {
+        let mut results = contents(&[Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()]);
+        results[0].metadata = serde_json::json!({ "source_name": "b.epub", "source_added_at": 1 });
+        results[2].metadata = serde_json::json!({ "source_name": "A.pdf", "source_added_at": 2 });
+        let ids: Vec<Uuid> = results.iter().map(|result| result.id).collect();
+
+        sort_by_metadata(&mut results, SearchSortDto::SourceName, |content| {
+            &content.metadata
+        });
+        assert_eq!(
+            results.iter().map(|result| result.id).collect::<Vec<_>>(),
+            vec![ids[2], ids[0], ids[1]]
+        );
+
+        sort_by_metadata(&mut results, SearchSortDto::Recency, |content| {
+            &content.metadata
+        });
+        assert_eq!(
+            results.iter().map(|result| result.id).collect::<Vec<_>>(),
+            vec![ids[2], ids[0], ids[1]]
+        );
+
+        results.reverse();
+        sort_by_metadata(&mut results, SearchSortDto::Relevance, |content| {
+            &content.metadata
+        });
+        assert_eq!(
+            results.iter().map(|result| result.id).collect::<Vec<_>>(),
+            vec![ids[1], ids[0], ids[2]]
+        );
+    }
+
+    #[test]
+    fn page_is_sliced_from_the_results_up_to_its_end() {
+        let page = SearchPage {
+            page: 2,
+            hits_per_page: 3,
+            sort: SearchSortDto::Relevance,
+        };
+
+        assert_eq!(page.window(), 6);
+        assert_eq!(page.slice((1..=10).collect()), vec![4, 5, 6]);
+        assert_eq!(page.slice((1..=5).collect()), vec![4, 5]);
+        assert_eq!(page.slice((1..=2).collect::<Vec<u32>>()), Vec::<u32>::new());
+        assert_eq!(page.total_pages(0), 0);
+        assert_eq!(page.total_pages(7), 3);
+    }
+
+    #[test]
+    fn shard_rankings_are_interleaved_by_rank() {
+        let (a1, a2, a3) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let (b1, c1) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let results = merge_shard_rankings(
+            vec![contents(&[a1, a2, a3]), contents(&[b1]), contents(&[c1])],
+            None,
+        );
+
+        assert_eq!(
+            results.iter().map(|result| result.id).collect::<Vec<_>>(),
+            vec![a1, b1, c1]
+        );
+
+        let results = merge_shard_rankings(
+            vec![contents(&[a1, a2, a3]), contents(&[b1]), contents(&[c1])],
+            Some(10),
+        );
+
+        assert_eq!(
+            results.iter().map(|result| result.id).collect::<Vec<_>>(),
+            vec![a1, b1, c1, a2, a3]
+        );
+    }
+
+    fn source_meta(source_type: SourceType, collection: Option<&str>) -> SourceMeta {
+        SourceMeta::builder()
+            .user_id(Uuid::new_v4())
+            .initial_name("example".to_string())
+            .object_store_name("example".to_string())
+            .source_type(source_type)
+            .collection(collection.map(str::to_string))
+            .build()
+    }
+
+    #[test]
+    fn hits_are_aggregated_per_source_collection_and_source_type() {
+        let book = source_meta(SourceType::Epub, Some("books"));
+        let other_book = source_meta(SourceType::Epub, None);
+        let notebook = source_meta(SourceType::Ipynb, Some("books"));
+        let deleted_source_id = Uuid::new_v4();
+
+        let fulltext_counts = BackendHitCounts {
+            sources: HashMap::from([(book.id, 12), (notebook.id, 1), (deleted_source_id, 3)]),
+            ..Default::default()
+        };
+        let semantic_counts = BackendHitCounts {
+            sources: HashMap::from([(notebook.id, 2), (other_book.id, 1)]),
+            ..Default::default()
+        };
+
+        let aggregations = SearchAggregations::from_hit_counts(
+            &[fulltext_counts, semantic_counts],
+            &[book.clone(), other_book.clone(), notebook.clone()],
+        );
+
+        assert_eq!(
+            aggregations.sources,
+            HashMap::from([(book.id, 12), (other_book.id, 1), (notebook.id, 2)])
+        );
+        assert_eq!(
+            aggregations.collections,
+            HashMap::from([("books".to_string(), 14)])
+        );
+        assert_eq!(
+            aggregations.source_types,
+            HashMap::from([(SourceType::Epub, 13), (SourceType::Ipynb, 2)])
+        );
+    }
+
+    #[test]
+    fn hits_are_aggregated_per_language_and_chapter_with_the_highest_count_among_backends() {
+        let counts = |languages: &[(&str, u64)], chapters: &[(&str, u64)]| BackendHitCounts {
+            languages: languages
+                .iter()
+                .map(|(language, count)| (language.to_string(), *count))
+                .collect(),
+            chapters: chapters
+                .iter()
+                .map(|(chapter, count)| (chapter.to_string(), *count))
+                .collect(),
+            ..Default::default()
+        };
+
+        let aggregations = SearchAggregations::from_hit_counts(
+            &[
+                counts(&[("en", 8), ("fr", 1)], &[("Introduction", 3)]),
+                counts(&[("fr", 2)], &[("Introduction", 1), ("chapter_2", 1)]),
+            ],
+            &[],
+        );
+
+        assert_eq!(
+            aggregations.languages,
+            HashMap::from([("en".to_string(), 8), ("fr".to_string(), 2)])
+        );
+        assert_eq!(
+            aggregations.chapters,
+            HashMap::from([
+                ("Introduction".to_string(), 3),
+                ("chapter_2".to_string(), 1)
+            ])
+        );
+    }
+
+    #[test]
+    fn found_contents_are_counted_per_source_language_chapter_keyword_and_entity() {
+        let source_meta_id = Uuid::new_v4();
+        let mut found_contents = contents(&[Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()]);
+        found_contents[0].source_meta_id = Some(source_meta_id);
+        found_contents[0].metadata = serde_json::json!({
+            "language": "en",
+            "epub": { "chapter_id": "ch1" },
+            "keywords": ["white whale", "harpoon"],
+            "entities": { "people": ["Ahab"], "places": ["Nantucket"] }
+        });
+        found_contents[2].source_meta_id = Some(source_meta_id);
+        found_contents[2].metadata = serde_json::json!({
+            "language": "en",
+            "latex": { "chapter": "Results" },
+            "keywords": ["white whale"]
+        });
+
+        assert_eq!(
+            BackendHitCounts::from_found_contents(&found_contents),
+            BackendHitCounts {
+                sources: HashMap::from([(source_meta_id, 2)]),
+                languages: HashMap::from([("en".to_string(), 2)]),
+                chapters: HashMap::from([("ch1".to_string(), 1), ("Results".to_string(), 1)]),
+                keywords: HashMap::from([
+                    ("white whale".to_string(), 2),
+                    ("harpoon".to_string(), 1)
+                ]),
+                entities: HashMap::from([("Ahab".to_string(), 1), ("Nantucket".to_string(), 1)]),
+            }
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use crate::domain::entities::source_meta::SourceType;
+use std::io::{Read, Seek};
+
+/// Number of first bytes of a file from which its content is detected
+pub const SNIFFED_BYTES: u64 = 8192;
+
+/// Signatures of a zip archive: a local file header, or the end of an empty archive
+const ZIP_SIGNATURES: [&[u8]; 2] = [b"PK\x03\x04", b"PK\x05\x06"];
+const PDF_SIGNATURE: &[u8] = b"%PDF-";
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Kind of content detected from the first bytes of a file (its magic bytes), regardless of its name
+///
+/// The name and the MIME type of an uploaded file are given by the client: the detected content
+/// is checked against the type of the source inferred from the name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SniffedContent {
+    /// A zip archive whose first entry is the stored `mimetype` of an EPUB
+    Epub,
+    /// A zip archive whose first entry is the stored `mimetype` of an OpenDocument text
+    OpenDocumentText,
+    /// Any other zip archive, for ex a DOCX or a repository
+    Zip,
+    Pdf,
+    /// Valid UTF-8 without NUL byte
+    Text,
+    Binary,
+}
+
+impl SniffedContent {
+    /// Detects the content of a file from its first bytes
+    pub fn sniff(head: &[u8]) -> Self {
+        if ZIP_SIGNATURES
+            .iter()
+            .any(|signature| head.starts_with(signature))
+        {
+            return match zip_mimetype(head) {
+                Some(b"application/epub+zip") => SniffedContent::Epub,
+                Some(b"application/vnd.oasis.opendocument.text") => {
+                    SniffedContent::OpenDocumentText
+                }
+                _ => SniffedContent::Zip,
+            };
+        }
+
+        if head.starts_with(PDF_SIGNATURE) {
+            return SniffedContent::Pdf;
+        }
+
+        if is_text(head.strip_prefix(UTF8_BOM).unwrap_or(head)) {
+            SniffedContent::Text
+        } else {
+            SniffedContent::Binary
+        }
+    }
+
+    /// Detects the content of a file from its first bytes. The file is rewound to be stored.
+    pub fn sniff_file(file: &mut std::fs::File) -> Result<Self, std::io::Error> {
+        file.rewind()?;
+        let mut head = Vec::new();
+        file.by_ref().take(SNIFFED_BYTES).read_to_end(&mut head)?;
+        file.rewind()?;
+
+        Ok(Self::sniff(&head))
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            SniffedContent::Epub => "application/epub+zip",
+            SniffedContent::OpenDocumentText => "application/vnd.oasis.opendocument.text",
+            SniffedContent::Zip => "application/zip",
+            SniffedContent::Pdf => "application/pdf",
+            SniffedContent::Text => "text/plain",
+            SniffedContent::Binary => "application/octet-stream",
+        }
+    }
+
+    /// Whether the content can be the one of a source of a given type
+    ///
+    /// An EPUB is also accepted as any zip archive: its `mimetype` entry is not always stored first.
+    pub fn matches(&self, source_type: &SourceType) -> bool {
+        match source_type {
+            SourceType::Epub => matches!(self, SniffedContent::Epub | SniffedContent::Zip),
+            SourceType::Odt => {
+                matches!(self, SniffedContent::OpenDocumentText | SniffedContent::Zip)
+            }
+            SourceType::Docx | SourceType::Archive => *self == SniffedContent::Zip,
+            SourceType::Srt
+            | SourceType::Vtt
+            | SourceType::Ipynb
+            | SourceType::Code
+            | SourceType::Latex
+            | SourceType::Html
+            | SourceType::Csv
+            | SourceType::Jsonl => *self == SniffedContent::Text,
+        }
+    }
+}
+
+/// Content of the `mimetype` entry of a zip archive, if it is its first entry and is stored uncompressed,
+/// as required for an EPUB or an OpenDocument file
+fn zip_mimetype(head: &[u8]) -> Option<&[u8]> {
+    // Local file header: compression method at 8, compressed size at 18, name length at 26,
+    // extra field length at 28, and the name from 30
+    let u16_at = |offset: usize| -> Option<usize> {
+        let bytes = head.get(offset..offset + 2)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+    };
+    let u32_at = |offset: usize| -> Option<usize> {
+        let bytes = head.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    };
+
+    let is_stored = u16_at(8)? == 0;
+    let content_length = u32_at(18)?;
+    let name_length = u16_at(26)?;
+    let extra_length = u16_at(28)?;
+
+    if !is_stored || head.get(30..30 + name_length)? != b"mimetype" {
+        return None;
+    }
+
+    let content_start = 30 + name_length + extra_length;
+    head.get(content_start..content_start + content_length)
+}
+
+/// Whether bytes are valid UTF-8 without NUL byte. The last character can be cut by the end of the sniffed bytes.
+fn is_text(bytes: &[u8]) -> bool {
+    if bytes.contains(&0) {
+        return false;
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(_) => true,
+        // The bytes end in the middle of a character
+        Err(error) => error.error_len().is_none() && bytes.len() - error.valid_up_to() < 4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn zip_with_first_entry(name: &str, content: &[u8]) -> Vec<u8> {
+        let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+
+        archive.start_file(name, options).unwrap();
+        archive.write_all(content).unwrap();
+        archive.start_file("content.xml", options).unwrap();
+        archive.write_all(b"<document/>").unwrap();
+
+        archive.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn zip_archives_are_detected_from_their_mimetype_entry() {
+        let epub = zip_with_first_entry("mimetype", b"application/epub+zip");
+        let odt = zip_with_first_entry("mimetype", b"application/vnd.oasis.opendocument.text");
+        let docx = zip_with_first_entry("[Content_Types].xml", b"<Types/>");
+
+        assert_eq!(SniffedContent::sniff(&epub), SniffedContent::Epub);
+        assert_eq!(
+            SniffedContent::sniff(&odt),
+            SniffedContent::OpenDocumentText
+        );
+        assert_eq!(SniffedContent::sniff(&docx), SniffedContent::Zip);
+    }
+
+    #[test]
+    fn pdf_and_binary_files_are_detected() {
+        assert_eq!(
+            SniffedContent::sniff(b"%PDF-1.7\n%\xE2\xE3\xCF\xD3"),
+            SniffedContent::Pdf
+        );
+        assert_eq!(
+            SniffedContent::sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            SniffedContent::Binary
+        );
+        assert_eq!(
+            SniffedContent::sniff(b"\xFF\xFE\xFDnot utf-8"),
+            SniffedContent::Binary
+        );
+    }
+
+    #[test]
+    fn text_can_be_cut_in_the_middle_of_a_character() {
+        let text = "1\n00:00:01,000 --> 00:00:02,000\nÉté".as_bytes();
+
+        assert_eq!(SniffedContent::sniff(text), SniffedContent::Text);
+        assert_eq!(
+            SniffedContent::sniff(&text[..text.len() - 1]),
+            SniffedContent::Text
+        );
+        assert_eq!(
+            SniffedContent::sniff(&[UTF8_BOM, b"id,title"].concat()),
+            SniffedContent::Text
+        );
+    }
+
+    #[test]
+    fn content_matches_the_types_of_sources_it_can_be() {
+        assert!(SniffedContent::Epub.matches(&SourceType::Epub));
+        assert!(SniffedContent::Zip.matches(&SourceType::Epub));
+        assert!(SniffedContent::Zip.matches(&SourceType::Docx));
+        assert!(SniffedContent::Text.matches(&SourceType::Csv));
+
+        assert!(!SniffedContent::Text.matches(&SourceType::Epub));
+        assert!(!SniffedContent::Pdf.matches(&SourceType::Epub));
+        assert!(!SniffedContent::Epub.matches(&SourceType::Archive));
+        assert!(!SniffedContent::Zip.matches(&SourceType::Code));
+        assert!(!SniffedContent::Binary.matches(&SourceType::Srt));
+    }
+}
@@ -0,0 +1,337 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::entities::{
+    ingestion_job::{IngestionJob, JobStatus},
+    retention_rule::ExpiringSource,
+    source_meta::SourceMeta,
+};
+
+/// What happened to a source
+#[derive(Debug, Clone, Copy, PartialEq, sqlx::Type, Serialize, Deserialize, ToSchema)]
+#[sqlx(type_name = "source_event_type", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum SourceEventType {
+    /// The source file was stored, and sent to the extraction
+    Uploaded,
+    /// All the contents of the source were extracted
+    Extracted,
+    /// All the contents of the source were saved in the full-text index
+    Indexed,
+    /// All the contents of the source went through the embedding
+    Embedded,
+    /// The source was changed, for ex reindexed
+    Updated,
+    /// The ingestion of the source failed
+    Failed,
+    Deleted,
+    /// The source will soon be deleted with the retention of its collection
+    Expiring,
+    /// The source was ingested, but some of its items were skipped
+    Warning,
+}
+
+/// Event of the lifecycle of a source, recorded in an append-only stream
+///
+/// The stream is an audit of what happened to a source, kept after its deletion.
+/// The payload is a snapshot of the source or of its ingestion job when the event occurred.
+#[derive(Debug, Clone)]
+pub struct SourceEvent {
+    pub id: Uuid,
+    pub source_meta_id: Uuid,
+    pub user_id: Uuid,
+    pub event_type: SourceEventType,
+    pub payload: JsonValue,
+    pub occurred_at: DateTime<Utc>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl SourceEvent {
+    pub fn new(
+        source_meta_id: Uuid,
+        user_id: Uuid,
+        event_type: SourceEventType,
+        payload: JsonValue,
+        occurred_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            source_meta_id,
+            user_id,
+            event_type,
+            payload,
+            occurred_at,
+            recorded_at: Utc::now(),
+        }
+    }
+
+    /// The source was stored, with its ingestion job
+    pub fn uploaded(source_meta: &SourceMeta, ingestion_job: &IngestionJob) -> Self {
+        Self::new(
+            source_meta.id,
+            source_meta.user_id,
+            SourceEventType::Uploaded,
+            json!({
+                "source": source_snapshot(source_meta),
+                "job_id": ingestion_job.id,
+                "lane": ingestion_job.lane,
+            }),
+            ingestion_job.queued_at,
+        )
+    }
+
+    /// The source was queued again for its ingestion, from its stored file
+    pub fn reindexed(source_meta: &SourceMeta, ingestion_job: &IngestionJob) -> Self {
+        Self::new(
+            source_meta.id,
+            source_meta.user_id,
+            SourceEventType::Updated,
+            json!({
+                "change": "reindexed",
+                "job_id": ingestion_job.id,
+                "lane": ingestion_job.lane,
+            }),
+            ingestion_job.queued_at,
+        )
+    }
+
+    /// The stored file of the source was replaced with the changed content of its URL
+    pub fn recrawled(
+        source_meta: &SourceMeta,
+        content_hash: &str,
+        crawled_at: DateTime<Utc>,
+    ) -> Self {
+        Self::new(
+            source_meta.id,
+            source_meta.user_id,
+            SourceEventType::Updated,
+            json!({
+                "change": "recrawled",
+                "previous_content_hash": source_meta.content_hash,
+                "content_hash": content_hash,
+            }),
+            crawled_at,
+        )
+    }
+
+    pub fn deleted(source_meta: &SourceMeta) -> Self {
+        Self::new(
+            source_meta.id,
+            source_meta.user_id,
+            SourceEventType::Deleted,
+            json!({ "source": source_snapshot(source_meta) }),
+            Utc::now(),
+        )
+    }
+
+    /// The source was deleted once expired with the retention of its collection
+    pub fn expired(source_meta: &SourceMeta) -> Self {
+        Self::new(
+            source_meta.id,
+            source_meta.user_id,
+            SourceEventType::Deleted,
+            json!({
+                "source": source_snapshot(source_meta),
+                "reason": "expired",
+            }),
+            Utc::now(),
+        )
+    }
+
+    pub fn expiring(expiring_source: &ExpiringSource) -> Self {
+        Self::new(
+            expiring_source.source_meta_id,
+            expiring_source.user_id,
+            SourceEventType::Expiring,
+            json!({
+                "collection": expiring_source.collection,
+                "expires_at": expiring_source.expires_at,
+            }),
+            Utc::now(),
+        )
+    }
+
+    /// Events of the stages of an ingestion job completed by a status update
+    ///
+    /// A stage completes once: the updates received afterwards do not record it again.
+    pub fn from_job_update(
+        user_id: Uuid,
+        previous_job: &IngestionJob,
+        job: &IngestionJob,
+    ) -> Vec<Self> {
+        let job_snapshot = json!({
+            "job_id": job.id,
+            "nb_contents": job.nb_contents,
+            "nb_indexed_contents": job.nb_indexed_contents,
+            "nb_embedded_contents": job.nb_embedded_contents,
+        });
+        let stages = [
+            (
+                SourceEventType::Extracted,
+                previous_job.extraction_completed_at,
+                job.extraction_completed_at,
+            ),
+            (
+                SourceEventType::Indexed,
+                previous_job.indexed_at,
+                job.indexed_at,
+            ),
+            (
+                SourceEventType::Embedded,
+                previous_job.embedded_at,
+                job.embedded_at,
+            ),
+        ];
+
+        let mut events: Vec<Self> = stages
+            .into_iter()
+            .filter_map(|(event_type, previous_at, at)| match (previous_at, at) {
+                (None, Some(at)) => Some(Self::new(
+                    job.source_meta_id,
+                    user_id,
+                    event_type,
+                    job_snapshot.clone(),
+                    at,
+                )),
+                _ => None,
+            })
+            .collect();
+
+        if let (None, Some(extraction_completed_at)) = (
+            previous_job.extraction_completed_at,
+            job.extraction_completed_at,
+        ) {
+            if !job.skipped_items.is_empty() {
+                events.push(Self::new(
+                    job.source_meta_id,
+                    user_id,
+                    SourceEventType::Warning,
+                    json!({
+                        "job_id": job.id,
+                        "skipped_items": job.skipped_items,
+                    }),
+                    extraction_completed_at,
+                ));
+            }
+        }
+
+        if previous_job.status != JobStatus::Failed && job.status == JobStatus::Failed {
+            events.push(Self::new(
+                job.source_meta_id,
+                user_id,
+                SourceEventType::Failed,
+                json!({
+                    "job_id": job.id,
+                    "error": job.error,
+                    "error_code": job.error_code,
+                }),
+                job.updated_at,
+            ));
+        }
+
+        events
+    }
+}
+
+fn source_snapshot(source_meta: &SourceMeta) -> JsonValue {
+    json!({
+        "initial_name": source_meta.initial_name,
+        "source_type": source_meta.source_type,
+        "content_hash": source_meta.content_hash,
+        "collection": source_meta.collection,
+        "added_at": source_meta.added_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::ingestion_job::{JobStatusUpdate, SkippedItem};
+
+    fn event_types(events: &[SourceEvent]) -> Vec<SourceEventType> {
+        events.iter().map(|event| event.event_type).collect()
+    }
+
+    #[test]
+    fn completed_stages_are_recorded_once() {
+        let user_id = Uuid::new_v4();
+        let mut job = IngestionJob::new(Uuid::new_v4());
+        let mut recorded_event_types = vec![];
+
+        for update in [
+            JobStatusUpdate::ExtractionStarted,
+            JobStatusUpdate::ContentIndexed,
+            JobStatusUpdate::ExtractionCompleted {
+                nb_contents: 1,
+                nb_unchanged_contents: 0,
+                skipped_items: vec![],
+            },
+            JobStatusUpdate::ContentEmbedded,
+            JobStatusUpdate::ContentIndexed,
+        ] {
+            let previous_job = job.clone();
+            job.apply(update).unwrap();
+            recorded_event_types.push(event_types(&SourceEvent::from_job_update(
+                user_id,
+                &previous_job,
+                &job,
+            )));
+        }
+
+        assert_eq!(
+            recorded_event_types,
+            vec![
+                vec![],
+                vec![],
+                vec![SourceEventType::Extracted, SourceEventType::Indexed],
+                vec![SourceEventType::Embedded],
+                vec![],
+            ]
+        );
+    }
+
+    #[test]
+    fn failure_is_recorded_with_its_error() {
+        let mut job = IngestionJob::new(Uuid::new_v4());
+        let previous_job = job.clone();
+        job.apply(JobStatusUpdate::Failed {
+            error: "Invalid archive".to_string(),
+            error_code: None,
+        })
+        .unwrap();
+
+        let events = SourceEvent::from_job_update(Uuid::new_v4(), &previous_job, &job);
+
+        assert_eq!(event_types(&events), vec![SourceEventType::Failed]);
+        assert_eq!(events[0].payload["error"], json!("Invalid archive"));
+    }
+
+    #[test]
+    fn skipped_items_are_recorded_as_a_warning_once_extracted() {
+        let mut job = IngestionJob::new(Uuid::new_v4());
+        let previous_job = job.clone();
+        job.apply(JobStatusUpdate::ExtractionCompleted {
+            nb_contents: 1,
+            nb_unchanged_contents: 0,
+            skipped_items: vec![SkippedItem {
+                path: "OEBPS/chapter_2.xhtml".to_string(),
+                error: "Malformed XHTML: 1 unclosed tags".to_string(),
+            }],
+        })
+        .unwrap();
+
+        let events = SourceEvent::from_job_update(Uuid::new_v4(), &previous_job, &job);
+
+        assert_eq!(
+            event_types(&events),
+            vec![SourceEventType::Extracted, SourceEventType::Warning]
+        );
+        assert_eq!(
+            events[1].payload["skipped_items"][0]["path"],
+            "OEBPS/chapter_2.xhtml"
+        );
+    }
+}
@@ -1,13 +1,29 @@
+use crate::domain::entities::extraction_progress::ExtractionStatus;
 use chrono::{DateTime, Utc};
 use common::dtos::extract_content_job::SourceTypeDto;
 use std::str::FromStr;
 use typed_builder::TypedBuilder;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, sqlx::Type, serde::Serialize)]
+#[derive(
+    Debug, Clone, PartialEq, Eq, Hash, sqlx::Type, serde::Serialize, serde::Deserialize, ToSchema,
+)]
 #[sqlx(type_name = "source_type", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
 pub enum SourceType {
     Epub,
+    Srt,
+    Vtt,
+    Ipynb,
+    Code,
+    Latex,
+    Html,
+    Docx,
+    Odt,
+    Csv,
+    Jsonl,
+    Archive,
 }
 
 impl FromStr for SourceType {
@@ -16,15 +32,45 @@ impl FromStr for SourceType {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "epub" => Ok(SourceType::Epub),
+            "srt" => Ok(SourceType::Srt),
+            "vtt" => Ok(SourceType::Vtt),
+            "ipynb" => Ok(SourceType::Ipynb),
+            "rs" | "py" => Ok(SourceType::Code),
+            "tex" => Ok(SourceType::Latex),
+            "html" | "htm" => Ok(SourceType::Html),
+            "docx" => Ok(SourceType::Docx),
+            "odt" => Ok(SourceType::Odt),
+            "csv" => Ok(SourceType::Csv),
+            "jsonl" | "ndjson" => Ok(SourceType::Jsonl),
+            // A LaTeX project or a source code repository
+            "zip" => Ok(SourceType::Archive),
             _ => Err(format!("Invalid SourceType: {}", s)),
         }
     }
 }
 
+impl SourceType {
+    /// Whether the source is structured data, read row by row
+    pub fn is_structured(&self) -> bool {
+        matches!(self, SourceType::Csv | SourceType::Jsonl)
+    }
+}
+
 impl From<SourceType> for SourceTypeDto {
     fn from(value: SourceType) -> Self {
         match value {
             SourceType::Epub => SourceTypeDto::Epub,
+            SourceType::Srt => SourceTypeDto::Srt,
+            SourceType::Vtt => SourceTypeDto::Vtt,
+            SourceType::Ipynb => SourceTypeDto::Ipynb,
+            SourceType::Code => SourceTypeDto::Code,
+            SourceType::Latex => SourceTypeDto::Latex,
+            SourceType::Html => SourceTypeDto::Html,
+            SourceType::Docx => SourceTypeDto::Docx,
+            SourceType::Odt => SourceTypeDto::Odt,
+            SourceType::Csv => SourceTypeDto::Csv,
+            SourceType::Jsonl => SourceTypeDto::Jsonl,
+            SourceType::Archive => SourceTypeDto::Archive,
         }
     }
 }
@@ -44,9 +90,46 @@ pub struct SourceMeta {
 
     pub source_type: SourceType,
 
+    /// Hex-encoded SHA-256 hash of the file content
+    #[builder(default)]
+    pub content_hash: Option<String>,
+
     #[builder(default=Utc::now())]
     pub added_at: DateTime<Utc>,
 
     #[builder(default)]
     pub extracted_at: Option<DateTime<Utc>>,
+
+    /// Status of the latest known extraction progress. `None` while the extraction has not started
+    #[builder(default)]
+    pub extraction_status: Option<ExtractionStatus>,
+
+    /// Collection in which the source is filed. `None` if the source is not filed
+    #[builder(default)]
+    pub collection: Option<String>,
+
+    /// Auto-filing rule that filed the source in its collection
+    #[builder(default)]
+    pub auto_filing_rule_id: Option<Uuid>,
+
+    /// Version of the upload policy of the tenant the source was uploaded under. `None` if uploaded without policy
+    #[builder(default)]
+    pub upload_policy_id: Option<Uuid>,
+
+    /// Columns whose values are the searchable content of the rows of a structured source (CSV or JSONL).
+    /// Empty for the other sources, or for all the columns to be the content
+    #[builder(default)]
+    pub content_columns: Vec<String>,
+
+    /// MIME type detected from the first bytes of the file. `None` if the content of the file was not sniffed
+    #[builder(default)]
+    pub detected_mime_type: Option<String>,
+
+    /// Size of the file, counted in the storage used by the user. `None` for the sources uploaded before it was recorded
+    #[builder(default)]
+    pub size_bytes: Option<i64>,
+
+    /// Whether the personal data of the extracted contents is redacted, as requested on the upload of the source
+    #[builder(default)]
+    pub redact_pii: bool,
 }
@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use common::dtos::source_summary::{ChapterSummaryDto, SourceSummaryDto};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Summaries of a source and of its chapters, generated once the extraction of the source is completed
+#[derive(Debug, Clone)]
+pub struct SourceSummary {
+    pub source_meta_id: Uuid,
+    /// Summary of the whole source
+    pub summary: String,
+    /// Summaries of the chapters of the source, in the order of the source
+    pub chapters: Json<Vec<ChapterSummary>>,
+    /// Model which generated the summaries
+    pub model: String,
+    pub generated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ChapterSummary {
+    /// Chapter of the contents: the id of an EPUB chapter, or the title of a LaTeX chapter
+    pub chapter: String,
+    pub summary: String,
+}
+
+impl From<SourceSummaryDto> for SourceSummary {
+    fn from(value: SourceSummaryDto) -> Self {
+        Self {
+            source_meta_id: value.source_meta_id,
+            summary: value.summary,
+            chapters: Json(value.chapters.into_iter().map(Into::into).collect()),
+            model: value.model,
+            generated_at: value.generated_at,
+        }
+    }
+}
+
+impl From<ChapterSummaryDto> for ChapterSummary {
+    fn from(value: ChapterSummaryDto) -> Self {
+        Self {
+            chapter: value.chapter,
+            summary: value.summary,
+        }
+    }
+}
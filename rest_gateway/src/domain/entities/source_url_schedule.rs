@@ -0,0 +1,72 @@
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+/// Periodic re-crawl of a source added from a URL
+///
+/// The URL is downloaded again every `interval_h` hours: the source is extracted again only when its content changed.
+#[derive(Debug, Clone)]
+pub struct SourceUrlSchedule {
+    pub source_meta_id: Uuid,
+    pub user_id: Uuid,
+    pub url: String,
+    /// Headers sent to download the URL, as a JSON object encrypted with the secrets cipher
+    pub encrypted_headers: Option<String>,
+    pub interval_h: i32,
+    pub next_crawl_at: DateTime<Utc>,
+    pub last_crawled_at: Option<DateTime<Utc>>,
+    /// Last re-crawl which found a changed content
+    pub last_changed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SourceUrlSchedule {
+    /// Schedules the re-crawls of a source just added from a URL, the first one an interval from now
+    pub fn new(
+        source_meta_id: Uuid,
+        user_id: Uuid,
+        url: String,
+        encrypted_headers: Option<String>,
+        interval_h: i32,
+    ) -> Self {
+        let now = Utc::now();
+
+        Self {
+            source_meta_id,
+            user_id,
+            url,
+            encrypted_headers,
+            interval_h,
+            next_crawl_at: next_crawl_at(now, interval_h),
+            last_crawled_at: None,
+            last_changed_at: None,
+            created_at: now,
+        }
+    }
+}
+
+/// When a source crawled at a given time is crawled again
+pub fn next_crawl_at(crawled_at: DateTime<Utc>, interval_h: i32) -> DateTime<Utc> {
+    crawled_at + Duration::hours(interval_h.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_crawl_is_an_interval_after_the_addition() {
+        let schedule = SourceUrlSchedule::new(
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            "https://www.gutenberg.org/ebooks/84.epub.noimages".to_string(),
+            None,
+            24,
+        );
+
+        assert_eq!(
+            schedule.next_crawl_at,
+            schedule.created_at + Duration::hours(24)
+        );
+        assert_eq!(schedule.last_crawled_at, None);
+    }
+}
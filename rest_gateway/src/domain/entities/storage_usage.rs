@@ -0,0 +1,42 @@
+/// Storage used by the source files of a user, against their quota
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageUsage {
+    pub used_bytes: u64,
+    /// `None` if the storage of the user is not limited
+    pub quota_bytes: Option<u64>,
+}
+
+impl StorageUsage {
+    /// Whether files of a given size fit in the remaining storage of the user
+    pub fn can_store(&self, size_bytes: u64) -> bool {
+        self.quota_bytes
+            .is_none_or(|quota_bytes| self.used_bytes.saturating_add(size_bytes) <= quota_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_are_stored_up_to_the_quota() {
+        let usage = StorageUsage {
+            used_bytes: 900,
+            quota_bytes: Some(1_000),
+        };
+
+        assert!(usage.can_store(100));
+        assert!(!usage.can_store(101));
+        assert!(!usage.can_store(u64::MAX));
+    }
+
+    #[test]
+    fn any_file_is_stored_without_quota() {
+        let usage = StorageUsage {
+            used_bytes: 900,
+            quota_bytes: None,
+        };
+
+        assert!(usage.can_store(u64::MAX));
+    }
+}
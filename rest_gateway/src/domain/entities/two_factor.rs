@@ -0,0 +1,252 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use rand::{distributions::Uniform, Rng, RngCore};
+use reqwest::Url;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Issuer of the TOTP secrets, naming the service in the authenticator apps
+const TOTP_ISSUER: &str = "Content Ingestion Service";
+/// Number of random bytes of a TOTP secret (160 bits, as recommended by RFC 4226)
+const TOTP_SECRET_NB_BYTES: usize = 20;
+/// Duration of a time step of the TOTP codes, in seconds
+const TOTP_STEP_S: i64 = 30;
+const TOTP_NB_DIGITS: u32 = 6;
+/// Number of time steps before and after the current one whose codes are accepted, for the clock drift of the phones
+const TOTP_ACCEPTED_STEP_DRIFT: i64 = 1;
+
+/// Number of recovery codes generated when the two-factor authentication is enabled
+pub const NB_RECOVERY_CODES: usize = 10;
+/// Alphabet of the recovery codes, without the characters easily confused (0/o, 1/l)
+const RECOVERY_CODE_ALPHABET: &[u8] = b"23456789abcdefghijkmnpqrstuvwxyz";
+/// Number of characters of a recovery code, shown in two groups
+const RECOVERY_CODE_LENGTH: usize = 10;
+
+/// RFC 4648 base32 alphabet, in which the TOTP secrets are shown to the users
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Two-factor authentication (TOTP, RFC 6238) of a user
+///
+/// The secret is only stored encrypted. Until a code of the secret is verified, the two-factor authentication
+/// is pending: the log-ins do not require a code yet.
+#[derive(Debug, Clone)]
+pub struct UserTwoFactor {
+    pub user_id: Uuid,
+    /// Hex-encoded secret, encrypted with the `SecretsCipher` of the gateway
+    pub encrypted_secret: String,
+    pub enabled_at: Option<DateTime<Utc>>,
+    /// Time step of the last code used to log in: a code can not be used twice
+    pub last_used_step: Option<i64>,
+    /// Invalid codes since the last valid one, reset once the account is locked
+    pub failed_attempts: i32,
+    /// Until then, the log-ins are rejected, even with a valid code
+    pub locked_until: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UserTwoFactor {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled_at.is_some()
+    }
+
+    /// # Returns
+    /// The time remaining before the log-ins are accepted again, if too many invalid codes were tried
+    pub fn remaining_lockout(&self, now: DateTime<Utc>) -> Option<std::time::Duration> {
+        self.locked_until
+            .and_then(|locked_until| (locked_until - now).to_std().ok())
+            .filter(|remaining| !remaining.is_zero())
+    }
+}
+
+/// Generates a random TOTP secret
+pub fn generate_totp_secret() -> Vec<u8> {
+    let mut secret = vec![0u8; TOTP_SECRET_NB_BYTES];
+    rand::thread_rng().fill_bytes(&mut secret);
+    secret
+}
+
+/// Code of a TOTP secret at a time step (RFC 4226 HOTP of the step, with HMAC-SHA1)
+pub fn totp_code(secret: &[u8], step: i64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    // Dynamic truncation
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(TOTP_NB_DIGITS),
+        width = TOTP_NB_DIGITS as usize
+    )
+}
+
+/// Verifies a code of a TOTP secret, accepting the codes of the time steps around the given time
+///
+/// # Returns
+/// The time step of the code if it is valid, to not accept it again
+pub fn verify_totp_code(secret: &[u8], code: &str, at: DateTime<Utc>) -> Option<i64> {
+    let code = code.trim();
+    let current_step = at.timestamp().div_euclid(TOTP_STEP_S);
+
+    (current_step - TOTP_ACCEPTED_STEP_DRIFT..=current_step + TOTP_ACCEPTED_STEP_DRIFT)
+        .find(|step| totp_code(secret, *step) == code)
+}
+
+/// `otpauth://` URI of a TOTP secret, scanned as a QR code by the authenticator apps
+pub fn otpauth_uri(secret: &[u8], account_name: &str) -> String {
+    let mut uri = Url::parse("otpauth://totp").expect("The otpauth URI is valid");
+    uri.path_segments_mut()
+        .expect("The otpauth URI has a path")
+        .push(&format!("{}:{}", TOTP_ISSUER, account_name));
+    uri.query_pairs_mut()
+        .append_pair("secret", &base32_encode(secret))
+        .append_pair("issuer", TOTP_ISSUER)
+        .append_pair("algorithm", "SHA1")
+        .append_pair("digits", &TOTP_NB_DIGITS.to_string())
+        .append_pair("period", &TOTP_STEP_S.to_string());
+
+    uri.to_string()
+}
+
+/// Encodes bytes in base32 (RFC 4648), without padding, as the authenticator apps expect the secrets
+pub fn base32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let mut buffer: u32 = 0;
+    let mut nb_buffered_bits = 0;
+
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u32;
+        nb_buffered_bits += 8;
+        while nb_buffered_bits >= 5 {
+            nb_buffered_bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> nb_buffered_bits) & 0x1f) as usize] as char);
+        }
+    }
+    if nb_buffered_bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - nb_buffered_bits)) & 0x1f) as usize] as char);
+    }
+
+    encoded
+}
+
+/// Generates the single-use recovery codes of a user, logging in without their authenticator app
+///
+/// Like the refresh tokens, the codes are only shown once: only their hashes are stored.
+pub fn generate_recovery_codes() -> Vec<String> {
+    let alphabet = Uniform::from(0..RECOVERY_CODE_ALPHABET.len());
+    let mut rng = rand::thread_rng();
+
+    (0..NB_RECOVERY_CODES)
+        .map(|_| {
+            let code: String = (0..RECOVERY_CODE_LENGTH)
+                .map(|_| RECOVERY_CODE_ALPHABET[rng.sample(alphabet)] as char)
+                .collect();
+            let (first_group, second_group) = code.split_at(RECOVERY_CODE_LENGTH / 2);
+            format!("{}-{}", first_group, second_group)
+        })
+        .collect()
+}
+
+/// Hashes a recovery code, as generated or typed by the user: without its separator, case-insensitive
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+
+    hex::encode(Sha256::digest(normalized.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Secret of the test vectors of RFC 6238
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn totp_codes_match_the_rfc_test_vectors() {
+        // The RFC gives 8-digit codes: `94287082` at 59 s, `07081804` at 1111111109 s
+        assert_eq!(totp_code(RFC_SECRET, 59 / TOTP_STEP_S), "287082");
+        assert_eq!(totp_code(RFC_SECRET, 1111111109 / TOTP_STEP_S), "081804");
+    }
+
+    #[test]
+    fn log_ins_are_locked_out_until_the_end_of_the_lockout() {
+        let now = Utc.timestamp_opt(1111111109, 0).unwrap();
+        let mut two_factor = UserTwoFactor {
+            user_id: Uuid::new_v4(),
+            encrypted_secret: String::new(),
+            enabled_at: Some(now),
+            last_used_step: None,
+            failed_attempts: 0,
+            locked_until: None,
+            created_at: now,
+        };
+        assert_eq!(two_factor.remaining_lockout(now), None);
+
+        two_factor.locked_until = Some(now + chrono::Duration::seconds(60));
+        assert_eq!(
+            two_factor.remaining_lockout(now),
+            Some(std::time::Duration::from_secs(60))
+        );
+        assert_eq!(
+            two_factor.remaining_lockout(now + chrono::Duration::seconds(60)),
+            None
+        );
+    }
+
+    #[test]
+    fn codes_of_the_adjacent_time_steps_are_accepted() {
+        let at = Utc.timestamp_opt(1111111109, 0).unwrap();
+        let step = 1111111109 / TOTP_STEP_S;
+
+        assert_eq!(verify_totp_code(RFC_SECRET, "081804", at), Some(step));
+        assert_eq!(
+            verify_totp_code(RFC_SECRET, &totp_code(RFC_SECRET, step - 1), at),
+            Some(step - 1)
+        );
+        assert_eq!(
+            verify_totp_code(RFC_SECRET, &totp_code(RFC_SECRET, step + 2), at),
+            None
+        );
+        assert_eq!(verify_totp_code(RFC_SECRET, "not a code", at), None);
+    }
+
+    #[test]
+    fn secrets_are_shown_in_base32_in_the_otpauth_uri() {
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(
+            base32_encode(RFC_SECRET),
+            "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"
+        );
+
+        let uri = otpauth_uri(RFC_SECRET, "jane@example.com");
+        assert!(uri.starts_with("otpauth://totp/Content%20Ingestion%20Service:jane@example.com?"));
+        assert!(uri.contains("secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"));
+    }
+
+    #[test]
+    fn recovery_codes_are_unique_and_hashed_without_their_separator() {
+        let codes = generate_recovery_codes();
+
+        assert_eq!(codes.len(), NB_RECOVERY_CODES);
+        assert_eq!(codes[0].len(), RECOVERY_CODE_LENGTH + 1);
+        assert_ne!(codes[0], codes[1]);
+        assert_eq!(
+            hash_recovery_code(&codes[0]),
+            hash_recovery_code(&codes[0].replace('-', "").to_uppercase())
+        );
+        assert_ne!(hash_recovery_code(&codes[0]), hash_recovery_code(&codes[1]));
+    }
+}
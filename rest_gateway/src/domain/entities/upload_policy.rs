@@ -0,0 +1,174 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::domain::entities::auto_filing_rule::matches_mime_type;
+
+/// Validation of the files uploaded by the users of a tenant (organization)
+///
+/// The policies are versioned: a change of the policy of a tenant adds a new version, the latest one is applied.
+/// The users of a tenant without policy can upload any file.
+#[derive(Debug, Clone)]
+pub struct UploadPolicy {
+    pub id: Uuid,
+    /// `None` for the users without tenant
+    pub tenant_id: Option<String>,
+    /// From 1, incremented for each change of the policy of the tenant
+    pub version: i32,
+    /// MIME types of the accepted files, which can match any subtype: `text/*`. All the types if empty
+    pub allowed_mime_types: Vec<String>,
+    /// Maximum size of the accepted files, not limited if `None`
+    pub max_size_bytes: Option<i64>,
+    /// The files should be scanned for viruses before being accepted: they are rejected when the gateway has no antivirus
+    pub scan_required: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Reason why an uploaded file is rejected by an upload policy
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum UploadPolicyViolation {
+    #[error("The MIME type {} is not allowed", .0.as_deref().unwrap_or("(none)"))]
+    MimeTypeNotAllowed(Option<String>),
+    #[error("The file of {size} bytes is larger than the maximum of {max_size} bytes")]
+    TooLarge { size: u64, max_size: u64 },
+    /// No virus scanner is available to the gateway: the files can not be accepted when a scan is required
+    #[error("The file should be scanned for viruses, but no virus scanner is available")]
+    ScanUnavailable,
+}
+
+impl UploadPolicy {
+    /// Checks an uploaded file against the policy
+    ///
+    /// # Arguments
+    /// * `is_scan_available` - whether the file is scanned for viruses by the gateway before being stored
+    pub fn check(
+        &self,
+        mime_type: Option<&str>,
+        size: u64,
+        is_scan_available: bool,
+    ) -> Result<(), UploadPolicyViolation> {
+        let is_mime_type_allowed = self.allowed_mime_types.is_empty()
+            || mime_type.is_some_and(|mime_type| {
+                self.allowed_mime_types
+                    .iter()
+                    .any(|allowed_mime_type| matches_mime_type(allowed_mime_type, mime_type))
+            });
+        if !is_mime_type_allowed {
+            return Err(UploadPolicyViolation::MimeTypeNotAllowed(
+                mime_type.map(str::to_string),
+            ));
+        }
+
+        if let Some(max_size) = self.max_size_bytes {
+            let max_size = max_size as u64;
+            if size > max_size {
+                return Err(UploadPolicyViolation::TooLarge { size, max_size });
+            }
+        }
+
+        if self.scan_required && !is_scan_available {
+            return Err(UploadPolicyViolation::ScanUnavailable);
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks if a MIME type of a policy is valid: `type/subtype` or `type/*`
+pub fn is_valid_mime_type(mime_type: &str) -> bool {
+    match mime_type.split_once('/') {
+        Some((mime_type, subtype)) => {
+            let is_token = |part: &str| {
+                !part.is_empty()
+                    && part
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "!#$&-^_.+".contains(c))
+            };
+            is_token(mime_type) && (subtype == "*" || is_token(subtype))
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(
+        allowed_mime_types: &[&str],
+        max_size_bytes: Option<i64>,
+        scan_required: bool,
+    ) -> UploadPolicy {
+        UploadPolicy {
+            id: Uuid::new_v4(),
+            tenant_id: Some("acme".to_string()),
+            version: 1,
+            allowed_mime_types: allowed_mime_types
+                .iter()
+                .map(|mime_type| mime_type.to_string())
+                .collect(),
+            max_size_bytes,
+            scan_required,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn file_of_an_allowed_mime_type_is_accepted() {
+        let policy = policy(&["application/epub+zip", "text/*"], None, false);
+
+        assert!(policy
+            .check(Some("application/epub+zip"), 1_000, false)
+            .is_ok());
+        assert!(policy.check(Some("text/vtt"), 1_000, false).is_ok());
+        assert_eq!(
+            policy.check(Some("application/zip"), 1_000, false),
+            Err(UploadPolicyViolation::MimeTypeNotAllowed(Some(
+                "application/zip".to_string()
+            )))
+        );
+        assert_eq!(
+            policy.check(None, 1_000, false),
+            Err(UploadPolicyViolation::MimeTypeNotAllowed(None))
+        );
+    }
+
+    #[test]
+    fn file_of_any_mime_type_is_accepted_without_allowed_mime_types() {
+        assert!(policy(&[], None, false).check(None, 1_000, false).is_ok());
+    }
+
+    #[test]
+    fn file_larger_than_the_maximum_size_is_rejected() {
+        let policy = policy(&[], Some(1_000), false);
+
+        assert!(policy.check(None, 1_000, false).is_ok());
+        assert_eq!(
+            policy.check(None, 1_001, false),
+            Err(UploadPolicyViolation::TooLarge {
+                size: 1_001,
+                max_size: 1_000
+            })
+        );
+    }
+
+    #[test]
+    fn file_is_rejected_when_a_scan_is_required_without_antivirus() {
+        let policy = policy(&[], None, true);
+
+        assert_eq!(
+            policy.check(None, 1_000, false),
+            Err(UploadPolicyViolation::ScanUnavailable)
+        );
+        assert!(policy.check(None, 1_000, true).is_ok());
+    }
+
+    #[test]
+    fn mime_type_of_a_policy_is_validated() {
+        for mime_type in ["application/epub+zip", "text/*", "text/vtt"] {
+            assert!(is_valid_mime_type(mime_type), "{}", mime_type);
+        }
+        for mime_type in ["", "text", "text/", "/vtt", "*/*", "text/v tt"] {
+            assert!(!is_valid_mime_type(mime_type), "{}", mime_type);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// Maximum number of parts of a chunked upload, the limit of the S3 multipart uploads
+pub const MAX_UPLOAD_PARTS: i32 = 10_000;
+
+/// Chunked upload of a large source file, uploaded part by part and resumable after a failure
+///
+/// The parts are uploaded to the object storage as the parts of a multipart upload,
+/// and assembled into the source file once the session is completed.
+#[derive(Debug, Clone)]
+pub struct UploadSession {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// File name received from the user
+    pub file_name: String,
+    /// MIME type declared by the user
+    pub content_type: Option<String>,
+    pub tags: Vec<String>,
+    /// Name of the assembled file in the object store
+    pub object_store_name: String,
+    /// Id of the multipart upload in the object storage
+    pub s3_upload_id: String,
+    /// Source registered once the parts are assembled
+    pub source_meta_id: Option<Uuid>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UploadSession {
+    pub fn new(
+        user_id: Uuid,
+        file_name: String,
+        content_type: Option<String>,
+        tags: Vec<String>,
+        object_store_name: String,
+        s3_upload_id: String,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            user_id,
+            file_name,
+            content_type,
+            tags,
+            object_store_name,
+            s3_upload_id,
+            source_meta_id: None,
+            completed_at: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Path of the assembled file in the object store
+    pub fn object_path_name(&self) -> String {
+        format!("{}/{}", self.user_id, self.object_store_name)
+    }
+}
+
+/// Part of a chunked upload received by the object storage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadPart {
+    /// Starts at 1, in the order of the file
+    pub part_number: i32,
+    pub etag: String,
+    pub size_bytes: i64,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+/// Checks that the received parts form the whole file: numbered from 1 without gap
+///
+/// # Arguments
+/// * `parts` - The received parts, sorted by part number
+///
+/// # Returns
+/// The first missing part number if a part is missing
+pub fn first_missing_part(parts: &[UploadPart]) -> Option<i32> {
+    if parts.is_empty() {
+        return Some(1);
+    }
+
+    parts
+        .iter()
+        .zip(1..)
+        .find(|(part, expected_number)| part.part_number != *expected_number)
+        .map(|(_, expected_number)| expected_number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(part_number: i32) -> UploadPart {
+        UploadPart {
+            part_number,
+            etag: format!("etag-{}", part_number),
+            size_bytes: 10,
+            uploaded_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn contiguous_parts_form_the_whole_file() {
+        assert_eq!(first_missing_part(&[part(1)]), None);
+        assert_eq!(first_missing_part(&[part(1), part(2), part(3)]), None);
+    }
+
+    #[test]
+    fn missing_parts_are_detected() {
+        assert_eq!(first_missing_part(&[]), Some(1));
+        assert_eq!(first_missing_part(&[part(2), part(3)]), Some(1));
+        assert_eq!(first_missing_part(&[part(1), part(3)]), Some(2));
+    }
+}
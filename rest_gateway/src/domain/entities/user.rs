@@ -26,6 +26,8 @@ pub struct CreatingUser {
     pub id: Uuid,
     pub email: UserEmail,
     pub password_hash: UserPassword,
+    /// Tenant (business unit) of the user. If not set, the user uses the shared exchanges and queues
+    pub tenant_id: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -58,6 +60,7 @@ impl User {
             id: Uuid::new_v4(),
             email,
             password_hash,
+            tenant_id: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         })
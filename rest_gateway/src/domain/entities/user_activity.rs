@@ -0,0 +1,74 @@
+use common::constants::routing_keys::USER_ACTIVITY_ROUTING_KEY_PREFIX;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::domain::entities::{
+    extraction_progress::ExtractionProgress, ingestion_job::IngestionJob,
+};
+
+/// Activity of the sources of a user, streamed live to the clients of the user
+///
+/// It is published by the gateway instance saving it, with the routing key of the user,
+/// for the gateway instances streaming the events of the user to receive it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum UserActivity {
+    /// The extraction of the contents of a source progressed
+    ExtractionProgress(ExtractionProgress),
+    /// The ingestion job of a source was updated
+    JobUpdated(IngestionJob),
+}
+
+impl UserActivity {
+    /// Routing key of the activity of a user
+    pub fn routing_key(user_id: Uuid) -> String {
+        format!("{}.{}.v1", USER_ACTIVITY_ROUTING_KEY_PREFIX, user_id)
+    }
+
+    pub fn try_parsing(data: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(data)
+    }
+
+    pub fn try_serializing(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::domain::entities::extraction_progress::ExtractionStatus;
+
+    #[test]
+    fn routing_key_is_scoped_to_the_user() {
+        let user_id = Uuid::new_v4();
+
+        assert_eq!(
+            UserActivity::routing_key(user_id),
+            format!("user_activity.{}.v1", user_id)
+        );
+    }
+
+    #[test]
+    fn activity_is_parsed_from_its_serialization() {
+        let activity = UserActivity::ExtractionProgress(ExtractionProgress {
+            source_meta_id: Uuid::new_v4(),
+            status: ExtractionStatus::InProgress,
+            chunk_index: 3,
+            total_estimated_chunks: 10,
+            bytes_processed: 1024,
+            updated_at: Utc::now(),
+        });
+
+        let parsed =
+            UserActivity::try_parsing(activity.try_serializing().unwrap().as_bytes()).unwrap();
+
+        let UserActivity::ExtractionProgress(progress) = parsed else {
+            panic!("Expected an extraction progress, got {:?}", parsed);
+        };
+        assert_eq!(progress.chunk_index, 3);
+        assert_eq!(progress.status, ExtractionStatus::InProgress);
+    }
+}
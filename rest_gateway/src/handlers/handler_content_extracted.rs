@@ -0,0 +1,251 @@
+use std::sync::Arc;
+
+use common::{
+    constants::routing_keys::{CONTENT_EXTRACTED_FAST_ROUTING_KEY, CONTENT_EXTRACTED_ROUTING_KEY},
+    core::trace_propagation::continue_trace_from,
+    dtos::{extracted_content::ExtractedContentDto, templates::message_envelope::MessageEnvelope},
+    helper::error_chain_fmt,
+};
+use futures::StreamExt;
+use lapin::{
+    message::Delivery,
+    options::{
+        BasicAckOptions, BasicConsumeOptions, BasicNackOptions, ExchangeDeclareOptions,
+        QueueBindOptions, QueueDeclareOptions,
+    },
+    types::FieldTable,
+    Connection as RabbitMQConnection, ExchangeKind,
+};
+use sqlx::PgPool;
+use tracing::{debug, error, info, info_span, warn, Instrument};
+
+use crate::repositories::document_postgres_repository::{
+    DocumentPostgresRepository, DocumentPostgresRepositoryError,
+};
+
+pub const ROUTING_KEY: &str = CONTENT_EXTRACTED_ROUTING_KEY;
+/// The contents of both ingestion lanes are summarized, from the same queue
+const ROUTING_KEYS: [&str; 2] = [
+    CONTENT_EXTRACTED_ROUTING_KEY,
+    CONTENT_EXTRACTED_FAST_ROUTING_KEY,
+];
+
+#[derive(thiserror::Error)]
+pub enum RegisterHandlerContentExtractedError {
+    #[error(transparent)]
+    RabbitMQError(#[from] lapin::Error),
+}
+
+impl std::fmt::Debug for RegisterHandlerContentExtractedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Registers the message handler projecting the extracted contents into the summaries of their sources
+///
+/// It declares a queue and binds it to the given exchange.
+/// It handles messages one by one, there is no handling messages in parallel.
+#[tracing::instrument(
+    name = "Register message handler",
+    skip(rabbitmq_consuming_connection, db_pool, document_repository)
+)]
+pub async fn register_handler(
+    rabbitmq_consuming_connection: RabbitMQConnection,
+    exchange_name: String,
+    queue_name_prefix: String,
+    db_pool: PgPool,
+    document_repository: Arc<DocumentPostgresRepository>,
+) -> Result<(), RegisterHandlerContentExtractedError> {
+    let channel = rabbitmq_consuming_connection.create_channel().await?;
+
+    channel
+        .exchange_declare(
+            &exchange_name,
+            ExchangeKind::Topic,
+            ExchangeDeclareOptions {
+                durable: true,
+                ..ExchangeDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    // In order to have several nodes of this service as consumers of the same queue: use a specific queue name
+    let queue_name = queue_name(&queue_name_prefix);
+
+    channel
+        .queue_declare(
+            &queue_name,
+            QueueDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    for routing_key in ROUTING_KEYS {
+        info!(
+            "Declared queue {} on exchange {}, binding on {}",
+            queue_name, exchange_name, routing_key
+        );
+
+        channel
+            .queue_bind(
+                &queue_name,
+                &exchange_name,
+                routing_key,
+                QueueBindOptions::default(),
+                FieldTable::default(),
+            )
+            .await?;
+    }
+
+    let consumer_options = BasicConsumeOptions {
+        no_ack: false,
+        ..BasicConsumeOptions::default()
+    };
+
+    let mut consumer = channel
+        .basic_consume(&queue_name, "", consumer_options, FieldTable::default())
+        .await?;
+
+    info!(
+        "📡 Handler consuming from queue {}, bound to {} with {:?}, waiting for messages ...",
+        queue_name, exchange_name, ROUTING_KEYS,
+    );
+
+    while let Some(delivery) = consumer.next().await {
+        async {
+            let delivery = match delivery {
+                // Carries the delivery alongside its channel
+                Ok(delivery) => delivery,
+                // Carries the error and is always followed by Ok(None)
+                Err(error) => {
+                    error!(
+                        ?error,
+                        "Failed to consume queue message on queue {}", queue_name
+                    );
+                    return;
+                }
+            };
+            // The spans handling the message continue the trace of its publisher
+            continue_trace_from(&delivery.properties);
+
+            match execute_handler(&db_pool, &document_repository, &delivery).await {
+                Ok(()) => {
+                    if let Err(error) = delivery.ack(BasicAckOptions::default()).await {
+                        error!(?error, "Failed to ack extracted content message");
+                    }
+                }
+                Err(error) => {
+                    error!(?error, "Failed to handle extracted content message");
+
+                    info!(
+                        "Not acknowledging message with delivery tag {}",
+                        delivery.delivery_tag
+                    );
+                    // An invalid message would fail again: it is not requeued
+                    let nack_options = BasicNackOptions {
+                        requeue: !matches!(
+                            error,
+                            ExecuteHandlerContentExtractedError::MessageParsingError(_)
+                        ),
+                        ..BasicNackOptions::default()
+                    };
+                    if let Err(error) = delivery.nack(nack_options).await {
+                        error!(?error, "Failed to nack extracted content message");
+                    }
+                }
+            }
+        }
+        .instrument(info_span!(
+            "Handling consumed message",
+            routing_keys = ?ROUTING_KEYS,
+            exchange = exchange_name,
+            queue = queue_name,
+            message_id = %uuid::Uuid::new_v4(),
+        ))
+        .await
+    }
+
+    Ok(())
+}
+
+pub fn queue_name(queue_name_prefix: &str) -> String {
+    format!("{}_{}", queue_name_prefix, ROUTING_KEY)
+}
+
+#[derive(thiserror::Error)]
+pub enum ExecuteHandlerContentExtractedError {
+    #[error("{0}")]
+    MessageParsingError(String),
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+    #[error(transparent)]
+    DocumentPostgresRepositoryError(#[from] DocumentPostgresRepositoryError),
+}
+
+impl std::fmt::Debug for ExecuteHandlerContentExtractedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Adds an extracted content to the summary of its source
+///
+/// The contents without source, published before the contents were linked to their source,
+/// and the contents of a deleted source are acknowledged and ignored.
+#[tracing::instrument(
+    name = "Executing handler on extracted content",
+    skip(db_pool, document_repository, message)
+)]
+pub async fn execute_handler(
+    db_pool: &PgPool,
+    document_repository: &DocumentPostgresRepository,
+    message: &Delivery,
+) -> Result<(), ExecuteHandlerContentExtractedError> {
+    let message =
+        MessageEnvelope::<ExtractedContentDto>::try_decoding(&message.data).map_err(|error| {
+            ExecuteHandlerContentExtractedError::MessageParsingError(format!(
+                "Failed to parse extracted content message data: {}",
+                error
+            ))
+        })?;
+    let content = message.payload;
+
+    let Some(source_meta_id) = content.source_meta_id else {
+        warn!(content_id = ?content.id, "Extracted content without source, not summarized");
+        return Ok(());
+    };
+    debug!(
+        content_id = ?content.id,
+        ?source_meta_id,
+        trace_context = ?message.trace_context,
+        "Received extracted content"
+    );
+
+    let mut transaction = db_pool.begin().await?;
+
+    document_repository
+        .create_document_if_missing(&mut transaction, source_meta_id)
+        .await?;
+
+    let Some(mut document) = document_repository
+        .get_document_for_update(&mut transaction, source_meta_id)
+        .await?
+    else {
+        info!(
+            ?source_meta_id,
+            "Source of the extracted content deleted since"
+        );
+        return Ok(());
+    };
+
+    document.add_chunk(&content.content, &content.metadata);
+    document_repository
+        .save_document(&mut transaction, &document)
+        .await?;
+
+    transaction.commit().await?;
+
+    Ok(())
+}
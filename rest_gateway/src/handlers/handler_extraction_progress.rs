@@ -0,0 +1,281 @@
+use std::sync::Arc;
+
+use common::{
+    constants::routing_keys::CONTENT_EXTRACTION_PROGRESS_ROUTING_KEY,
+    core::{
+        rabbitmq_message_repository::RabbitMQMessageRepositoryError,
+        trace_propagation::continue_trace_from,
+    },
+    dtos::extraction_progress::ExtractionProgressDto,
+    helper::error_chain_fmt,
+};
+use futures::StreamExt;
+use lapin::{
+    message::Delivery,
+    options::{
+        BasicAckOptions, BasicConsumeOptions, BasicNackOptions, ExchangeDeclareOptions,
+        QueueBindOptions, QueueDeclareOptions,
+    },
+    types::FieldTable,
+    Connection as RabbitMQConnection, ExchangeKind,
+};
+use sqlx::PgPool;
+use tracing::{error, info, info_span, Instrument};
+
+use crate::{
+    domain::entities::{
+        extraction_progress::{ExtractionProgress, ExtractionStatus},
+        user_activity::UserActivity,
+    },
+    handlers::UserActivityPublisher,
+    repositories::{
+        extraction_progress_postgres_repository::{
+            ExtractionProgressPostgresRepository, ExtractionProgressPostgresRepositoryError,
+        },
+        source_meta_postgres_repository::{
+            SourceMetaPostgresRepository, SourceMetaPostgresRepositoryError,
+        },
+    },
+};
+
+pub const ROUTING_KEY: &str = CONTENT_EXTRACTION_PROGRESS_ROUTING_KEY;
+
+#[derive(thiserror::Error)]
+pub enum RegisterHandlerExtractionProgressError {
+    #[error(transparent)]
+    RabbitMQError(#[from] lapin::Error),
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+}
+
+impl std::fmt::Debug for RegisterHandlerExtractionProgressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Registers the message handler saving the progress of the content extractions
+///
+/// It declares a queue and binds it to the given exchange.
+/// The progresses are published as the activity of their user, for the gateway instances streaming it.
+/// It handles messages one by one, there is no handling messages in parallel.
+#[tracing::instrument(
+    name = "Register message handler",
+    skip(
+        rabbitmq_consuming_connection,
+        db_pool,
+        extraction_progress_repository,
+        source_meta_repository,
+        user_activity
+    )
+)]
+pub async fn register_handler(
+    rabbitmq_consuming_connection: RabbitMQConnection,
+    exchange_name: String,
+    queue_name_prefix: String,
+    db_pool: PgPool,
+    extraction_progress_repository: Arc<ExtractionProgressPostgresRepository>,
+    source_meta_repository: Arc<SourceMetaPostgresRepository>,
+    user_activity: UserActivityPublisher,
+) -> Result<(), RegisterHandlerExtractionProgressError> {
+    let user_activity = user_activity.try_init().await?;
+    let channel = rabbitmq_consuming_connection.create_channel().await?;
+
+    channel
+        .exchange_declare(
+            &exchange_name,
+            ExchangeKind::Topic,
+            ExchangeDeclareOptions {
+                durable: true,
+                ..ExchangeDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    // In order to have several nodes of this service as consumers of the same queue: use a specific queue name
+    let queue_name = queue_name(&queue_name_prefix);
+
+    channel
+        .queue_declare(
+            &queue_name,
+            QueueDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    info!(
+        "Declared queue {} on exchange {}, binding on {}",
+        queue_name, exchange_name, ROUTING_KEY
+    );
+
+    channel
+        .queue_bind(
+            &queue_name,
+            &exchange_name,
+            ROUTING_KEY,
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    let consumer_options = BasicConsumeOptions {
+        no_ack: false,
+        ..BasicConsumeOptions::default()
+    };
+
+    let mut consumer = channel
+        .basic_consume(&queue_name, "", consumer_options, FieldTable::default())
+        .await?;
+
+    info!(
+        "📡 Handler consuming from queue {}, bound to {} with {}, waiting for messages ...",
+        queue_name, exchange_name, ROUTING_KEY,
+    );
+
+    while let Some(delivery) = consumer.next().await {
+        async {
+            let delivery = match delivery {
+                // Carries the delivery alongside its channel
+                Ok(delivery) => delivery,
+                // Carries the error and is always followed by Ok(None)
+                Err(error) => {
+                    error!(
+                        ?error,
+                        "Failed to consume queue message on queue {}", queue_name
+                    );
+                    return;
+                }
+            };
+            // The spans handling the message continue the trace of its publisher
+            continue_trace_from(&delivery.properties);
+
+            match execute_handler(
+                &db_pool,
+                &extraction_progress_repository,
+                &source_meta_repository,
+                &user_activity,
+                &delivery,
+            )
+            .await
+            {
+                Ok(()) => {
+                    if let Err(error) = delivery.ack(BasicAckOptions::default()).await {
+                        error!(?error, "Failed to ack extraction progress message");
+                    }
+                }
+                Err(error) => {
+                    error!(?error, "Failed to handle extraction progress message");
+
+                    info!(
+                        "Not acknowledging message with delivery tag {}",
+                        delivery.delivery_tag
+                    );
+                    if let Err(error) = delivery.nack(BasicNackOptions::default()).await {
+                        error!(?error, "Failed to nack extraction progress message");
+                    }
+                }
+            }
+        }
+        .instrument(info_span!(
+            "Handling consumed message",
+            routing_key = ROUTING_KEY,
+            exchange = exchange_name,
+            queue = queue_name,
+            message_id = %uuid::Uuid::new_v4(),
+        ))
+        .await
+    }
+
+    Ok(())
+}
+
+pub fn queue_name(queue_name_prefix: &str) -> String {
+    format!("{}_{}", queue_name_prefix, ROUTING_KEY)
+}
+
+#[derive(thiserror::Error)]
+pub enum ExecuteHandlerExtractionProgressError {
+    #[error("{0}")]
+    MessageParsingError(String),
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+    #[error(transparent)]
+    ExtractionProgressPostgresRepositoryError(#[from] ExtractionProgressPostgresRepositoryError),
+    #[error(transparent)]
+    SourceMetaPostgresRepositoryError(#[from] SourceMetaPostgresRepositoryError),
+}
+
+impl std::fmt::Debug for ExecuteHandlerExtractionProgressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Saves the latest progress of an extraction, and the extraction status of its source.
+/// A completed extraction also marks its source as extracted.
+#[tracing::instrument(
+    name = "Executing handler on extraction progress",
+    skip(
+        db_pool,
+        extraction_progress_repository,
+        source_meta_repository,
+        user_activity,
+        message
+    )
+)]
+pub async fn execute_handler(
+    db_pool: &PgPool,
+    extraction_progress_repository: &ExtractionProgressPostgresRepository,
+    source_meta_repository: &SourceMetaPostgresRepository,
+    user_activity: &UserActivityPublisher,
+    message: &Delivery,
+) -> Result<(), ExecuteHandlerExtractionProgressError> {
+    let progress = ExtractionProgressDto::try_parsing(&message.data).map_err(|error| {
+        ExecuteHandlerExtractionProgressError::MessageParsingError(format!(
+            "Failed to parse extraction progress message data: {}",
+            error
+        ))
+    })?;
+    info!(?progress, "Received extraction progress");
+
+    let progress: ExtractionProgress = progress.into();
+
+    let mut transaction = db_pool.begin().await?;
+
+    extraction_progress_repository
+        .save_extraction_progress(&mut transaction, &progress)
+        .await?;
+
+    source_meta_repository
+        .set_extraction_status(&mut transaction, progress.source_meta_id, progress.status)
+        .await?;
+
+    if progress.status == ExtractionStatus::Completed {
+        source_meta_repository
+            .set_extracted_at(
+                &mut transaction,
+                progress.source_meta_id,
+                progress.updated_at,
+            )
+            .await?;
+    }
+
+    let source_meta = source_meta_repository
+        .get_source_meta(&mut transaction, progress.source_meta_id)
+        .await?;
+
+    transaction.commit().await?;
+
+    // Only streamed live: the progress is saved even if it could not be published
+    if let Some(source_meta) = source_meta {
+        user_activity
+            .publish(
+                source_meta.user_id,
+                &UserActivity::ExtractionProgress(progress),
+            )
+            .await;
+    }
+
+    Ok(())
+}
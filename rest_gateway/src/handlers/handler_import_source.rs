@@ -0,0 +1,233 @@
+use common::{
+    constants::routing_keys::IMPORT_SOURCE_ROUTING_KEY,
+    core::{
+        rabbitmq_message_repository::RabbitMQMessageRepositoryError,
+        tenancy::TenantMessageRepositories, trace_propagation::continue_trace_from,
+    },
+    dtos::import_source::ImportSourceDto,
+    helper::error_chain_fmt,
+};
+use futures::StreamExt;
+use lapin::{
+    message::Delivery,
+    options::{
+        BasicAckOptions, BasicConsumeOptions, BasicNackOptions, ExchangeDeclareOptions,
+        QueueBindOptions, QueueDeclareOptions,
+    },
+    types::FieldTable,
+    Connection as RabbitMQConnection, ExchangeKind,
+};
+use tracing::{error, info, info_span, warn, Instrument};
+
+use crate::{
+    controllers::add_source_url::AddSourceUrlError,
+    importing::{ImportingError, SourceImporter},
+    repositories::import_s3_repository::ImportS3RepositoryError,
+};
+
+pub const ROUTING_KEY: &str = IMPORT_SOURCE_ROUTING_KEY;
+
+#[derive(thiserror::Error)]
+pub enum RegisterHandlerImportSourceError {
+    #[error(transparent)]
+    RabbitMQError(#[from] lapin::Error),
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+}
+
+impl std::fmt::Debug for RegisterHandlerImportSourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Registers the message handler importing the objects of external buckets as sources, on the commands sent from the admin API
+///
+/// The commands are published on the shared exchange: the extraction jobs are routed to the tenant of each user.
+/// It declares a queue and binds it to the given exchange.
+/// It handles messages one by one, there is no handling messages in parallel.
+///
+/// # Params
+/// - message_repositories: not initialized, the handler initializes its own repositories
+#[tracing::instrument(
+    name = "Register message handler",
+    skip(rabbitmq_consuming_connection, source_importer, message_repositories)
+)]
+pub async fn register_handler(
+    rabbitmq_consuming_connection: RabbitMQConnection,
+    exchange_name: String,
+    queue_name_prefix: String,
+    source_importer: SourceImporter,
+    message_repositories: TenantMessageRepositories,
+) -> Result<(), RegisterHandlerImportSourceError> {
+    let message_repositories = message_repositories.try_init().await?;
+    let channel = rabbitmq_consuming_connection.create_channel().await?;
+
+    channel
+        .exchange_declare(
+            &exchange_name,
+            ExchangeKind::Topic,
+            ExchangeDeclareOptions {
+                durable: true,
+                ..ExchangeDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    // In order to have several nodes of this service as consumers of the same queue: use a specific queue name
+    let queue_name = queue_name(&queue_name_prefix);
+
+    channel
+        .queue_declare(
+            &queue_name,
+            QueueDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    info!(
+        "Declared queue {} on exchange {}, binding on {}",
+        queue_name, exchange_name, ROUTING_KEY
+    );
+
+    channel
+        .queue_bind(
+            &queue_name,
+            &exchange_name,
+            ROUTING_KEY,
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    let consumer_options = BasicConsumeOptions {
+        no_ack: false,
+        ..BasicConsumeOptions::default()
+    };
+
+    let mut consumer = channel
+        .basic_consume(&queue_name, "", consumer_options, FieldTable::default())
+        .await?;
+
+    info!(
+        "📡 Handler consuming from queue {}, bound to {} with {}, waiting for messages ...",
+        queue_name, exchange_name, ROUTING_KEY,
+    );
+
+    while let Some(delivery) = consumer.next().await {
+        async {
+            let delivery = match delivery {
+                // Carries the delivery alongside its channel
+                Ok(delivery) => delivery,
+                // Carries the error and is always followed by Ok(None)
+                Err(error) => {
+                    error!(
+                        ?error,
+                        "Failed to consume queue message on queue {}", queue_name
+                    );
+                    return;
+                }
+            };
+            // The spans handling the message continue the trace of its publisher
+            continue_trace_from(&delivery.properties);
+
+            match execute_handler(&source_importer, &message_repositories, &delivery).await {
+                Ok(()) => {
+                    if let Err(error) = delivery.ack(BasicAckOptions::default()).await {
+                        error!(?error, "Failed to ack import source message");
+                    }
+                }
+                Err(error) => {
+                    error!(?error, "Failed to handle import source message");
+
+                    info!(
+                        "Not acknowledging message with delivery tag {}",
+                        delivery.delivery_tag
+                    );
+                    if let Err(error) = delivery.nack(BasicNackOptions::default()).await {
+                        error!(?error, "Failed to nack import source message");
+                    }
+                }
+            }
+        }
+        .instrument(info_span!(
+            "Handling consumed message",
+            routing_key = ROUTING_KEY,
+            exchange = exchange_name,
+            queue = queue_name,
+            message_id = %uuid::Uuid::new_v4(),
+        ))
+        .await
+    }
+
+    Ok(())
+}
+
+pub fn queue_name(queue_name_prefix: &str) -> String {
+    format!("{}_{}", queue_name_prefix, ROUTING_KEY)
+}
+
+#[derive(thiserror::Error)]
+pub enum ExecuteHandlerImportSourceError {
+    #[error("{0}")]
+    MessageParsingError(String),
+    #[error(transparent)]
+    ImportingError(#[from] ImportingError),
+}
+
+impl std::fmt::Debug for ExecuteHandlerImportSourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Imports an object as a source
+///
+/// An object deleted since the import was requested, or that can not be added as a source,
+/// is acknowledged and skipped: importing it again would fail the same way.
+#[tracing::instrument(
+    name = "Executing handler on import source",
+    skip(source_importer, message_repositories, message)
+)]
+pub async fn execute_handler(
+    source_importer: &SourceImporter,
+    message_repositories: &TenantMessageRepositories,
+    message: &Delivery,
+) -> Result<(), ExecuteHandlerImportSourceError> {
+    let command = ImportSourceDto::try_parsing(&message.data).map_err(|error| {
+        ExecuteHandlerImportSourceError::MessageParsingError(format!(
+            "Failed to parse import source message data: {}",
+            error
+        ))
+    })?;
+    info!(?command, "Received import source command");
+
+    match source_importer
+        .import_source(message_repositories, command)
+        .await
+    {
+        Ok(status) => {
+            info!(
+                file_name = ?status.file_name,
+                status = ?status.status,
+                source_id = ?status.source_id,
+                "Imported object"
+            );
+            Ok(())
+        }
+        Err(
+            error @ (ImportingError::ImportS3RepositoryError(
+                ImportS3RepositoryError::ObjectNotFound(_),
+            )
+            | ImportingError::AddSourceError(
+                AddSourceUrlError::InvalidSourceType(_)
+                | AddSourceUrlError::StorageQuotaExceeded(_),
+            )),
+        ) => {
+            warn!(?error, "Object not imported");
+            Ok(())
+        }
+        Err(error) => Err(error.into()),
+    }
+}
@@ -0,0 +1,294 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use common::{
+    constants::routing_keys::INGESTION_JOB_STATUS_ROUTING_KEY,
+    core::{
+        rabbitmq_message_repository::RabbitMQMessageRepositoryError,
+        trace_propagation::continue_trace_from,
+    },
+    dtos::ingestion_job_status::IngestionJobStatusDto,
+    helper::error_chain_fmt,
+};
+use futures::StreamExt;
+use lapin::{
+    message::Delivery,
+    options::{
+        BasicAckOptions, BasicConsumeOptions, BasicNackOptions, ExchangeDeclareOptions,
+        QueueBindOptions, QueueDeclareOptions,
+    },
+    types::FieldTable,
+    Connection as RabbitMQConnection, ExchangeKind,
+};
+use sqlx::PgPool;
+use tracing::{error, info, info_span, warn, Instrument};
+
+use crate::{
+    domain::entities::{
+        ingestion_job::JobStatusUpdate, source_event::SourceEvent, user_activity::UserActivity,
+    },
+    handlers::UserActivityPublisher,
+    metrics::IngestionMetrics,
+    repositories::{
+        ingestion_job_postgres_repository::{
+            IngestionJobPostgresRepository, IngestionJobPostgresRepositoryError,
+        },
+        source_event_postgres_repository::{
+            SourceEventPostgresRepository, SourceEventPostgresRepositoryError,
+        },
+        source_meta_postgres_repository::{
+            SourceMetaPostgresRepository, SourceMetaPostgresRepositoryError,
+        },
+    },
+};
+
+pub const ROUTING_KEY: &str = INGESTION_JOB_STATUS_ROUTING_KEY;
+
+#[derive(thiserror::Error)]
+pub enum RegisterHandlerIngestionJobStatusError {
+    #[error(transparent)]
+    RabbitMQError(#[from] lapin::Error),
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+}
+
+impl std::fmt::Debug for RegisterHandlerIngestionJobStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Repositories and metrics of the ingestion jobs, shared between the handled messages
+pub struct IngestionJobServices {
+    pub ingestion_job_repository: IngestionJobPostgresRepository,
+    pub source_meta_repository: SourceMetaPostgresRepository,
+    /// Records the events of the stages completed by the jobs
+    pub source_event_repository: SourceEventPostgresRepository,
+    pub ingestion_metrics: Arc<IngestionMetrics>,
+}
+
+/// Registers the message handler updating the status of the ingestion jobs
+///
+/// It declares a queue and binds it to the given exchange.
+/// The updated jobs are published as the activity of their user, for the gateway instances streaming it.
+/// It handles messages one by one, there is no handling messages in parallel.
+#[tracing::instrument(
+    name = "Register message handler",
+    skip(rabbitmq_consuming_connection, db_pool, services, user_activity)
+)]
+pub async fn register_handler(
+    rabbitmq_consuming_connection: RabbitMQConnection,
+    exchange_name: String,
+    queue_name_prefix: String,
+    db_pool: PgPool,
+    services: IngestionJobServices,
+    user_activity: UserActivityPublisher,
+) -> Result<(), RegisterHandlerIngestionJobStatusError> {
+    let user_activity = user_activity.try_init().await?;
+    let channel = rabbitmq_consuming_connection.create_channel().await?;
+
+    channel
+        .exchange_declare(
+            &exchange_name,
+            ExchangeKind::Topic,
+            ExchangeDeclareOptions {
+                durable: true,
+                ..ExchangeDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    // In order to have several nodes of this service as consumers of the same queue: use a specific queue name
+    let queue_name = queue_name(&queue_name_prefix);
+
+    channel
+        .queue_declare(
+            &queue_name,
+            QueueDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    info!(
+        "Declared queue {} on exchange {}, binding on {}",
+        queue_name, exchange_name, ROUTING_KEY
+    );
+
+    channel
+        .queue_bind(
+            &queue_name,
+            &exchange_name,
+            ROUTING_KEY,
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    let consumer_options = BasicConsumeOptions {
+        no_ack: false,
+        ..BasicConsumeOptions::default()
+    };
+
+    let mut consumer = channel
+        .basic_consume(&queue_name, "", consumer_options, FieldTable::default())
+        .await?;
+
+    info!(
+        "📡 Handler consuming from queue {}, bound to {} with {}, waiting for messages ...",
+        queue_name, exchange_name, ROUTING_KEY,
+    );
+
+    while let Some(delivery) = consumer.next().await {
+        async {
+            let delivery = match delivery {
+                // Carries the delivery alongside its channel
+                Ok(delivery) => delivery,
+                // Carries the error and is always followed by Ok(None)
+                Err(error) => {
+                    error!(
+                        ?error,
+                        "Failed to consume queue message on queue {}", queue_name
+                    );
+                    return;
+                }
+            };
+            // The spans handling the message continue the trace of its publisher
+            continue_trace_from(&delivery.properties);
+
+            match execute_handler(&db_pool, &services, &user_activity, &delivery).await {
+                Ok(()) => {
+                    if let Err(error) = delivery.ack(BasicAckOptions::default()).await {
+                        error!(?error, "Failed to ack ingestion job status message");
+                    }
+                }
+                Err(error) => {
+                    error!(?error, "Failed to handle ingestion job status message");
+
+                    info!(
+                        "Not acknowledging message with delivery tag {}",
+                        delivery.delivery_tag
+                    );
+                    if let Err(error) = delivery.nack(BasicNackOptions::default()).await {
+                        error!(?error, "Failed to nack ingestion job status message");
+                    }
+                }
+            }
+        }
+        .instrument(info_span!(
+            "Handling consumed message",
+            routing_key = ROUTING_KEY,
+            exchange = exchange_name,
+            queue = queue_name,
+            message_id = %uuid::Uuid::new_v4(),
+        ))
+        .await
+    }
+
+    Ok(())
+}
+
+pub fn queue_name(queue_name_prefix: &str) -> String {
+    format!("{}_{}", queue_name_prefix, ROUTING_KEY)
+}
+
+#[derive(thiserror::Error)]
+pub enum ExecuteHandlerIngestionJobStatusError {
+    #[error("{0}")]
+    MessageParsingError(String),
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+    #[error(transparent)]
+    IngestionJobPostgresRepositoryError(#[from] IngestionJobPostgresRepositoryError),
+    #[error(transparent)]
+    SourceMetaPostgresRepositoryError(#[from] SourceMetaPostgresRepositoryError),
+    #[error(transparent)]
+    SourceEventPostgresRepositoryError(#[from] SourceEventPostgresRepositoryError),
+}
+
+impl std::fmt::Debug for ExecuteHandlerIngestionJobStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Applies a status update to the ingestion job of a source, timing its stages
+/// and recording the events of the completed stages
+///
+/// Updates that can not be applied are acknowledged and ignored: the job of a deleted source,
+/// or an update received after the job ended.
+#[tracing::instrument(
+    name = "Executing handler on ingestion job status",
+    skip(db_pool, services, user_activity, message)
+)]
+pub async fn execute_handler(
+    db_pool: &PgPool,
+    services: &IngestionJobServices,
+    user_activity: &UserActivityPublisher,
+    message: &Delivery,
+) -> Result<(), ExecuteHandlerIngestionJobStatusError> {
+    let job_status = IngestionJobStatusDto::try_parsing(&message.data).map_err(|error| {
+        ExecuteHandlerIngestionJobStatusError::MessageParsingError(format!(
+            "Failed to parse ingestion job status message data: {}",
+            error
+        ))
+    })?;
+    info!(?job_status, "Received ingestion job status");
+
+    let source_meta_id = job_status.source_meta_id;
+    // Statuses published before the workers reported when they occurred are timed on reception
+    let occurred_at = job_status.occurred_at.unwrap_or_else(Utc::now);
+    let IngestionJobServices {
+        ingestion_job_repository,
+        source_meta_repository,
+        source_event_repository,
+        ingestion_metrics,
+    } = services;
+    let update: JobStatusUpdate = job_status.into();
+
+    // The job is locked until the update is saved: the updates of a job can be received concurrently
+    let mut transaction = db_pool.begin().await?;
+
+    let Some(mut job) = ingestion_job_repository
+        .get_job_by_source_meta_id_for_update(&mut transaction, source_meta_id)
+        .await?
+    else {
+        warn!("No ingestion job for source {}", source_meta_id);
+        return Ok(());
+    };
+
+    let previous_job = job.clone();
+    if let Err(error) = job.apply_at(update, occurred_at) {
+        warn!(?error, "Ignoring the ingestion job status");
+        return Ok(());
+    }
+
+    ingestion_job_repository
+        .save_job_status(&mut transaction, &job)
+        .await?;
+
+    // The events are recorded with the update of the job: a redelivered update does not record them twice
+    let source_meta = source_meta_repository
+        .get_source_meta(&mut transaction, source_meta_id)
+        .await?;
+    if let Some(source_meta) = &source_meta {
+        for event in SourceEvent::from_job_update(source_meta.user_id, &previous_job, &job) {
+            source_event_repository
+                .add_event(&mut transaction, &event)
+                .await?;
+        }
+    }
+
+    transaction.commit().await?;
+
+    ingestion_metrics.observe_job_update(&previous_job, &job);
+
+    // Only streamed live: the update is saved even if it could not be published
+    if let Some(source_meta) = source_meta {
+        user_activity
+            .publish(source_meta.user_id, &UserActivity::JobUpdated(job))
+            .await;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,286 @@
+use std::sync::Arc;
+
+use common::{
+    constants::routing_keys::GET_NORMALIZATION_RULES_ROUTING_KEY,
+    core::{
+        rabbitmq_message_repository::{RabbitMQMessageRepository, RabbitMQMessageRepositoryError},
+        trace_propagation::continue_trace_from,
+    },
+    dtos::{
+        normalization_rules::{
+            NormalizationRuleDto, NormalizationRulesData, NormalizationRulesResponseDto,
+        },
+        templates::rpc_response::{RpcErrorStatus, RpcResponseEncodingError},
+    },
+    helper::error_chain_fmt,
+};
+use futures::StreamExt;
+use lapin::{
+    options::{
+        BasicAckOptions, BasicConsumeOptions, BasicNackOptions, ExchangeDeclareOptions,
+        QueueBindOptions, QueueDeclareOptions,
+    },
+    types::FieldTable,
+    Connection as RabbitMQConnection, ExchangeKind,
+};
+use sqlx::PgPool;
+use tracing::{error, info, info_span, Instrument};
+
+use crate::repositories::normalization_rule_postgres_repository::{
+    NormalizationRulePostgresRepository, NormalizationRulePostgresRepositoryError,
+};
+
+pub const ROUTING_KEY: &str = GET_NORMALIZATION_RULES_ROUTING_KEY;
+
+#[derive(thiserror::Error)]
+pub enum RegisterHandlerNormalizationRulesError {
+    #[error(transparent)]
+    RabbitMQError(#[from] lapin::Error),
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+}
+
+impl std::fmt::Debug for RegisterHandlerNormalizationRulesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Registers the RPC message handler answering the services of a tenant with its normalization rules
+///
+/// The services fetch the rules on the exchange of their tenant: the request has no parameter.
+/// The handler will respond to the message on the given `reply-to`.
+///
+/// It handles messages one by one, there is no handling messages in parallel.
+#[tracing::instrument(
+    name = "Register normalization rules RPC handler",
+    skip(rabbitmq_consuming_connection, db_pool, normalization_rule_repository)
+)]
+pub async fn register_handler(
+    rabbitmq_consuming_connection: RabbitMQConnection,
+    exchange_name: String,
+    queue_name_prefix: String,
+    tenant_id: Option<String>,
+    db_pool: PgPool,
+    normalization_rule_repository: Arc<NormalizationRulePostgresRepository>,
+) -> Result<(), RegisterHandlerNormalizationRulesError> {
+    let rabbitmq_consuming_connection = Arc::new(rabbitmq_consuming_connection);
+    let channel = rabbitmq_consuming_connection.create_channel().await?;
+
+    channel
+        .exchange_declare(
+            &exchange_name,
+            ExchangeKind::Topic,
+            ExchangeDeclareOptions {
+                durable: true,
+                ..ExchangeDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    // In order to have several nodes of this service as consumers of the same queue: use a specific queue name
+    let queue_name = queue_name(&queue_name_prefix);
+
+    channel
+        .queue_declare(
+            &queue_name,
+            QueueDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    channel
+        .queue_bind(
+            &queue_name,
+            &exchange_name,
+            ROUTING_KEY,
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    info!(
+        "Declared queue {} on exchange {}, binding on {}",
+        queue_name, exchange_name, ROUTING_KEY
+    );
+
+    let consumer_options = BasicConsumeOptions {
+        no_ack: false,
+        ..BasicConsumeOptions::default()
+    };
+
+    let mut consumer = channel
+        .basic_consume(&queue_name, "", consumer_options, FieldTable::default())
+        .await?;
+
+    // Responds on its own channel, not shared with the other handlers
+    let message_repository =
+        RabbitMQMessageRepository::new(rabbitmq_consuming_connection.clone(), &exchange_name)
+            .try_init()
+            .await?;
+
+    info!(
+        "📡 Handler consuming from queue {}, bound to {} with {}, waiting for messages ...",
+        queue_name, exchange_name, ROUTING_KEY,
+    );
+
+    while let Some(delivery) = consumer.next().await {
+        async {
+            let delivery = match delivery {
+                // Carries the delivery alongside its channel
+                Ok(delivery) => delivery,
+                // Carries the error and is always followed by Ok(None)
+                Err(error) => {
+                    error!(
+                        ?error,
+                        "Failed to consume queue message on queue {}", queue_name
+                    );
+                    return;
+                }
+            };
+            // The spans handling the message continue the trace of its publisher
+            continue_trace_from(&delivery.properties);
+
+            let reply_to = match delivery.properties.reply_to().as_ref() {
+                Some(reply_to) => reply_to,
+                None => {
+                    error!(
+                        "No `reply-to` attribute necessary for RPC call on queue: {}",
+                        queue_name
+                    );
+
+                    // Disables requeue if there is no way to reply to the RPC call
+                    if let Err(error) = delivery
+                        .nack(BasicNackOptions {
+                            requeue: false,
+                            ..BasicNackOptions::default()
+                        })
+                        .await
+                    {
+                        error!(?error, "Failed to nack message");
+                    }
+
+                    return;
+                }
+            };
+
+            // Set on the response, for the caller to match it with its request
+            let correlation_id = delivery
+                .properties
+                .correlation_id()
+                .as_ref()
+                .map(|id| id.as_str());
+
+            match execute_handler(
+                &message_repository,
+                &db_pool,
+                &normalization_rule_repository,
+                tenant_id.as_deref(),
+                reply_to.as_str(),
+                correlation_id,
+            )
+            .await
+            {
+                Ok(()) => {
+                    if let Err(error) = delivery.ack(BasicAckOptions::default()).await {
+                        error!(?error, "Failed to ack normalization rules request");
+                    }
+                }
+                Err(error) => {
+                    error!(?error, "Failed to handle normalization rules request");
+
+                    let response = NormalizationRulesResponseDto::Error {
+                        status: RpcErrorStatus::InternalServerError,
+                        message: error.to_string(),
+                    };
+
+                    if let Ok(response) = response.try_serializing() {
+                        // Sends response to the given `reply_to` to mimic a RPC call
+                        let _ = message_repository
+                            .rpc_respond(reply_to.as_str(), correlation_id, response.as_bytes())
+                            .await;
+                    }
+
+                    // The caller already got an answer: the request is not requeued
+                    if let Err(error) = delivery
+                        .nack(BasicNackOptions {
+                            requeue: false,
+                            ..BasicNackOptions::default()
+                        })
+                        .await
+                    {
+                        error!(?error, "Failed to nack normalization rules request");
+                    }
+                }
+            }
+        }
+        .instrument(info_span!(
+            "Handling consumed message",
+            routing_key = ROUTING_KEY,
+            exchange = exchange_name,
+            queue = queue_name,
+            message_id = %uuid::Uuid::new_v4(),
+        ))
+        .await
+    }
+
+    Ok(())
+}
+
+pub fn queue_name(queue_name_prefix: &str) -> String {
+    format!("{}_{}", queue_name_prefix, ROUTING_KEY)
+}
+
+#[derive(thiserror::Error)]
+pub enum ExecuteHandlerNormalizationRulesError {
+    #[error(transparent)]
+    NormalizationRulePostgresRepositoryError(#[from] NormalizationRulePostgresRepositoryError),
+    #[error(transparent)]
+    RpcResponseEncodingError(#[from] RpcResponseEncodingError),
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+}
+
+impl std::fmt::Debug for ExecuteHandlerNormalizationRulesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Responds with the normalization rules of the tenant, in their creation order
+#[tracing::instrument(
+    name = "Executing handler on normalization rules request",
+    skip(message_repository, db_pool, normalization_rule_repository)
+)]
+pub async fn execute_handler(
+    message_repository: &RabbitMQMessageRepository,
+    db_pool: &PgPool,
+    normalization_rule_repository: &NormalizationRulePostgresRepository,
+    tenant_id: Option<&str>,
+    reply_to: &str,
+    correlation_id: Option<&str>,
+) -> Result<(), ExecuteHandlerNormalizationRulesError> {
+    let rules = normalization_rule_repository
+        .list_tenant_rules(db_pool, tenant_id)
+        .await?;
+
+    info!(
+        "Responding with {} normalization rules of tenant {:?}",
+        rules.len(),
+        tenant_id
+    );
+
+    let response = NormalizationRulesResponseDto::Ok {
+        data: NormalizationRulesData {
+            rules: rules.into_iter().map(NormalizationRuleDto::from).collect(),
+        },
+    }
+    .try_serializing()?;
+
+    message_repository
+        .rpc_respond(reply_to, correlation_id, response.as_bytes())
+        .await?;
+
+    Ok(())
+}
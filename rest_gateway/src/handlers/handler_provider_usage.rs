@@ -0,0 +1,224 @@
+use std::sync::Arc;
+
+use common::{
+    constants::routing_keys::PROVIDER_USAGE_ROUTING_KEY,
+    core::trace_propagation::continue_trace_from, dtos::provider_usage::ProviderUsageDto,
+    helper::error_chain_fmt,
+};
+use futures::StreamExt;
+use lapin::{
+    message::Delivery,
+    options::{
+        BasicAckOptions, BasicConsumeOptions, BasicNackOptions, ExchangeDeclareOptions,
+        QueueBindOptions, QueueDeclareOptions,
+    },
+    types::FieldTable,
+    Connection as RabbitMQConnection, ExchangeKind,
+};
+use sqlx::PgPool;
+use tracing::{error, info, info_span, warn, Instrument};
+
+use crate::repositories::provider_credentials_postgres_repository::{
+    ProviderCredentialsPostgresRepository, ProviderCredentialsPostgresRepositoryError,
+};
+
+pub const ROUTING_KEY: &str = PROVIDER_USAGE_ROUTING_KEY;
+
+#[derive(thiserror::Error)]
+pub enum RegisterHandlerProviderUsageError {
+    #[error(transparent)]
+    RabbitMQError(#[from] lapin::Error),
+}
+
+impl std::fmt::Debug for RegisterHandlerProviderUsageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Registers the message handler attributing the usage of the model providers to a tenant
+///
+/// The usage is published by the workers of the tenant on its own exchanges.
+/// It declares a queue and binds it to the given exchange.
+/// It handles messages one by one, there is no handling messages in parallel.
+#[tracing::instrument(
+    name = "Register message handler",
+    skip(
+        rabbitmq_consuming_connection,
+        db_pool,
+        provider_credentials_repository
+    )
+)]
+pub async fn register_handler(
+    rabbitmq_consuming_connection: RabbitMQConnection,
+    exchange_name: String,
+    queue_name_prefix: String,
+    tenant_id: String,
+    db_pool: PgPool,
+    provider_credentials_repository: Arc<ProviderCredentialsPostgresRepository>,
+) -> Result<(), RegisterHandlerProviderUsageError> {
+    let channel = rabbitmq_consuming_connection.create_channel().await?;
+
+    channel
+        .exchange_declare(
+            &exchange_name,
+            ExchangeKind::Topic,
+            ExchangeDeclareOptions {
+                durable: true,
+                ..ExchangeDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    // In order to have several nodes of this service as consumers of the same queue: use a specific queue name
+    let queue_name = queue_name(&queue_name_prefix);
+
+    channel
+        .queue_declare(
+            &queue_name,
+            QueueDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    info!(
+        "Declared queue {} on exchange {}, binding on {}",
+        queue_name, exchange_name, ROUTING_KEY
+    );
+
+    channel
+        .queue_bind(
+            &queue_name,
+            &exchange_name,
+            ROUTING_KEY,
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    let consumer_options = BasicConsumeOptions {
+        no_ack: false,
+        ..BasicConsumeOptions::default()
+    };
+
+    let mut consumer = channel
+        .basic_consume(&queue_name, "", consumer_options, FieldTable::default())
+        .await?;
+
+    info!(
+        "📡 Handler consuming from queue {}, bound to {} with {}, waiting for messages ...",
+        queue_name, exchange_name, ROUTING_KEY,
+    );
+
+    while let Some(delivery) = consumer.next().await {
+        async {
+            let delivery = match delivery {
+                // Carries the delivery alongside its channel
+                Ok(delivery) => delivery,
+                // Carries the error and is always followed by Ok(None)
+                Err(error) => {
+                    error!(
+                        ?error,
+                        "Failed to consume queue message on queue {}", queue_name
+                    );
+                    return;
+                }
+            };
+            // The spans handling the message continue the trace of its publisher
+            continue_trace_from(&delivery.properties);
+
+            match execute_handler(
+                &db_pool,
+                &provider_credentials_repository,
+                &tenant_id,
+                &delivery,
+            )
+            .await
+            {
+                Ok(()) => {
+                    if let Err(error) = delivery.ack(BasicAckOptions::default()).await {
+                        error!(?error, "Failed to ack provider usage message");
+                    }
+                }
+                Err(error) => {
+                    error!(?error, "Failed to handle provider usage message");
+
+                    info!(
+                        "Not acknowledging message with delivery tag {}",
+                        delivery.delivery_tag
+                    );
+                    if let Err(error) = delivery.nack(BasicNackOptions::default()).await {
+                        error!(?error, "Failed to nack provider usage message");
+                    }
+                }
+            }
+        }
+        .instrument(info_span!(
+            "Handling consumed message",
+            routing_key = ROUTING_KEY,
+            exchange = exchange_name,
+            queue = queue_name,
+            message_id = %uuid::Uuid::new_v4(),
+        ))
+        .await
+    }
+
+    Ok(())
+}
+
+pub fn queue_name(queue_name_prefix: &str) -> String {
+    format!("{}_{}", queue_name_prefix, ROUTING_KEY)
+}
+
+#[derive(thiserror::Error)]
+pub enum ExecuteHandlerProviderUsageError {
+    #[error("{0}")]
+    MessageParsingError(String),
+    #[error(transparent)]
+    ProviderCredentialsPostgresRepositoryError(#[from] ProviderCredentialsPostgresRepositoryError),
+}
+
+impl std::fmt::Debug for ExecuteHandlerProviderUsageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Attributes a usage of its model provider to a tenant
+///
+/// The usage of a tenant without credentials, deleted since the call to its provider, is acknowledged and ignored.
+#[tracing::instrument(
+    name = "Executing handler on provider usage",
+    skip(db_pool, provider_credentials_repository, message)
+)]
+pub async fn execute_handler(
+    db_pool: &PgPool,
+    provider_credentials_repository: &ProviderCredentialsPostgresRepository,
+    tenant_id: &str,
+    message: &Delivery,
+) -> Result<(), ExecuteHandlerProviderUsageError> {
+    let usage = ProviderUsageDto::try_parsing(&message.data).map_err(|error| {
+        ExecuteHandlerProviderUsageError::MessageParsingError(format!(
+            "Failed to parse provider usage message data: {}",
+            error
+        ))
+    })?;
+    info!(?usage, "Received provider usage");
+
+    let is_recorded = provider_credentials_repository
+        .record_usage(
+            db_pool,
+            tenant_id,
+            usage.purpose.into(),
+            usage.nb_requests as i64,
+            usage.nb_tokens as i64,
+        )
+        .await?;
+
+    if !is_recorded {
+        warn!(?usage.purpose, "No provider credentials for tenant {}", tenant_id);
+    }
+
+    Ok(())
+}
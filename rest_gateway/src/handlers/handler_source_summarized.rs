@@ -0,0 +1,216 @@
+use std::sync::Arc;
+
+use common::{
+    constants::routing_keys::SOURCE_SUMMARIZED_ROUTING_KEY,
+    core::trace_propagation::continue_trace_from, dtos::source_summary::SourceSummaryDto,
+    helper::error_chain_fmt,
+};
+use futures::StreamExt;
+use lapin::{
+    message::Delivery,
+    options::{
+        BasicAckOptions, BasicConsumeOptions, BasicNackOptions, ExchangeDeclareOptions,
+        QueueBindOptions, QueueDeclareOptions,
+    },
+    types::FieldTable,
+    Connection as RabbitMQConnection, ExchangeKind,
+};
+use sqlx::PgPool;
+use tracing::{error, info, info_span, Instrument};
+
+use crate::{
+    domain::entities::source_summary::SourceSummary,
+    repositories::source_summary_postgres_repository::{
+        SourceSummaryPostgresRepository, SourceSummaryPostgresRepositoryError,
+    },
+};
+
+pub const ROUTING_KEY: &str = SOURCE_SUMMARIZED_ROUTING_KEY;
+
+#[derive(thiserror::Error)]
+pub enum RegisterHandlerSourceSummarizedError {
+    #[error(transparent)]
+    RabbitMQError(#[from] lapin::Error),
+}
+
+impl std::fmt::Debug for RegisterHandlerSourceSummarizedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Registers the message handler saving the summaries of the sources, generated by the embedding workers
+///
+/// It declares a queue and binds it to the given exchange.
+/// It handles messages one by one, there is no handling messages in parallel.
+#[tracing::instrument(
+    name = "Register message handler",
+    skip(rabbitmq_consuming_connection, db_pool, source_summary_repository)
+)]
+pub async fn register_handler(
+    rabbitmq_consuming_connection: RabbitMQConnection,
+    exchange_name: String,
+    queue_name_prefix: String,
+    db_pool: PgPool,
+    source_summary_repository: Arc<SourceSummaryPostgresRepository>,
+) -> Result<(), RegisterHandlerSourceSummarizedError> {
+    let channel = rabbitmq_consuming_connection.create_channel().await?;
+
+    channel
+        .exchange_declare(
+            &exchange_name,
+            ExchangeKind::Topic,
+            ExchangeDeclareOptions {
+                durable: true,
+                ..ExchangeDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    // In order to have several nodes of this service as consumers of the same queue: use a specific queue name
+    let queue_name = queue_name(&queue_name_prefix);
+
+    channel
+        .queue_declare(
+            &queue_name,
+            QueueDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    info!(
+        "Declared queue {} on exchange {}, binding on {}",
+        queue_name, exchange_name, ROUTING_KEY
+    );
+
+    channel
+        .queue_bind(
+            &queue_name,
+            &exchange_name,
+            ROUTING_KEY,
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    let consumer_options = BasicConsumeOptions {
+        no_ack: false,
+        ..BasicConsumeOptions::default()
+    };
+
+    let mut consumer = channel
+        .basic_consume(&queue_name, "", consumer_options, FieldTable::default())
+        .await?;
+
+    info!(
+        "📡 Handler consuming from queue {}, bound to {} with {}, waiting for messages ...",
+        queue_name, exchange_name, ROUTING_KEY,
+    );
+
+    while let Some(delivery) = consumer.next().await {
+        async {
+            let delivery = match delivery {
+                // Carries the delivery alongside its channel
+                Ok(delivery) => delivery,
+                // Carries the error and is always followed by Ok(None)
+                Err(error) => {
+                    error!(
+                        ?error,
+                        "Failed to consume queue message on queue {}", queue_name
+                    );
+                    return;
+                }
+            };
+            // The spans handling the message continue the trace of its publisher
+            continue_trace_from(&delivery.properties);
+
+            match execute_handler(&db_pool, &source_summary_repository, &delivery).await {
+                Ok(()) => {
+                    if let Err(error) = delivery.ack(BasicAckOptions::default()).await {
+                        error!(?error, "Failed to ack source summarized message");
+                    }
+                }
+                Err(error) => {
+                    error!(?error, "Failed to handle source summarized message");
+
+                    info!(
+                        "Not acknowledging message with delivery tag {}",
+                        delivery.delivery_tag
+                    );
+                    // An invalid message would fail again: it is not requeued
+                    let nack_options = BasicNackOptions {
+                        requeue: !matches!(
+                            error,
+                            ExecuteHandlerSourceSummarizedError::MessageParsingError(_)
+                        ),
+                        ..BasicNackOptions::default()
+                    };
+                    if let Err(error) = delivery.nack(nack_options).await {
+                        error!(?error, "Failed to nack source summarized message");
+                    }
+                }
+            }
+        }
+        .instrument(info_span!(
+            "Handling consumed message",
+            routing_key = ROUTING_KEY,
+            exchange = exchange_name,
+            queue = queue_name,
+            message_id = %uuid::Uuid::new_v4(),
+        ))
+        .await
+    }
+
+    Ok(())
+}
+
+pub fn queue_name(queue_name_prefix: &str) -> String {
+    format!("{}_{}", queue_name_prefix, ROUTING_KEY)
+}
+
+#[derive(thiserror::Error)]
+pub enum ExecuteHandlerSourceSummarizedError {
+    #[error("{0}")]
+    MessageParsingError(String),
+    #[error(transparent)]
+    SourceSummaryPostgresRepositoryError(#[from] SourceSummaryPostgresRepositoryError),
+}
+
+impl std::fmt::Debug for ExecuteHandlerSourceSummarizedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Saves the summaries of a source, replacing the ones of its previous extraction
+#[tracing::instrument(
+    name = "Executing handler on source summarized",
+    skip(db_pool, source_summary_repository, message)
+)]
+pub async fn execute_handler(
+    db_pool: &PgPool,
+    source_summary_repository: &SourceSummaryPostgresRepository,
+    message: &Delivery,
+) -> Result<(), ExecuteHandlerSourceSummarizedError> {
+    let source_summary = SourceSummaryDto::try_parsing(&message.data).map_err(|error| {
+        ExecuteHandlerSourceSummarizedError::MessageParsingError(format!(
+            "Failed to parse source summarized message data: {}",
+            error
+        ))
+    })?;
+    info!(
+        source_meta_id = %source_summary.source_meta_id,
+        nb_chapters = source_summary.chapters.len(),
+        model = source_summary.model,
+        "Received source summary"
+    );
+
+    let source_summary: SourceSummary = source_summary.into();
+
+    source_summary_repository
+        .save_source_summary(db_pool, &source_summary)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use common::core::rabbitmq_message_repository::{
+    RabbitMQMessageRepository, RabbitMQMessageRepositoryError,
+};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    domain::entities::user_activity::UserActivity,
+    repositories::user_activity_rabbitmq_repository::UserActivityRabbitMQRepository,
+};
+
+pub mod handler_content_extracted;
+pub mod handler_extraction_progress;
+pub mod handler_import_source;
+pub mod handler_ingestion_job_status;
+pub mod handler_normalization_rules;
+pub mod handler_provider_credentials;
+pub mod handler_provider_usage;
+pub mod handler_reindex_source;
+pub mod handler_source_summarized;
+
+/// Publishes the activity of the users handled by a message handler, for the gateway instances streaming it
+#[derive(Clone)]
+pub struct UserActivityPublisher {
+    pub user_activity_repository: Arc<UserActivityRabbitMQRepository>,
+    /// Not an `Arc` shared reference as we want to initialize a new repository for each handler
+    pub message_repository: RabbitMQMessageRepository,
+}
+
+impl UserActivityPublisher {
+    pub async fn try_init(self) -> Result<Self, RabbitMQMessageRepositoryError> {
+        Ok(Self {
+            message_repository: self.message_repository.try_init().await?,
+            ..self
+        })
+    }
+
+    /// Publishes an activity of a user
+    ///
+    /// Only streamed live: a failure to publish it does not fail the handling of the message.
+    pub async fn publish(&self, user_id: Uuid, activity: &UserActivity) {
+        if let Err(error) = self
+            .user_activity_repository
+            .publish(&self.message_repository, user_id, activity)
+            .await
+        {
+            warn!(?error, "Failed to publish the activity of the user");
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Imports of the document archives of external buckets, whose objects are added as sources
+
+use anyhow::Context;
+use chrono::Utc;
+use common::{
+    core::tenancy::{TenancyError, TenantMessageRepositories},
+    dtos::import_source::ImportSourceDto,
+    helper::error_chain_fmt,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::{
+    configuration::Settings,
+    controllers::{
+        add_source_files::AddSourceFileStatus,
+        add_source_url::{AddSourceUrlError, FetchedSource, SourceIntake},
+    },
+    metrics::IngestionMetrics,
+    repositories::{
+        import_s3_repository::{ImportS3Repository, ImportS3RepositoryError},
+        source_file_s3_repository::S3Repository,
+        user_postgres_repository::UserPostgresRepositoryError,
+    },
+};
+
+/// Imports the objects of external buckets as sources, like the sources added from a URL
+///
+/// Each object is copied into the bucket of the sources: the extraction reads it from there, as any other source.
+pub struct SourceImporter {
+    db_pool: PgPool,
+    import_s3_repository: ImportS3Repository,
+    // Only guards the imports against each other, not against the concurrent uploads of the same files
+    intake: SourceIntake,
+}
+
+impl SourceImporter {
+    pub fn new(
+        db_pool: PgPool,
+        s3_repository: S3Repository,
+        ingestion_metrics: Arc<IngestionMetrics>,
+        settings: &Settings,
+    ) -> Self {
+        Self {
+            db_pool,
+            import_s3_repository: ImportS3Repository::new(&settings.imports),
+            intake: SourceIntake::new(s3_repository, ingestion_metrics, settings),
+        }
+    }
+
+    /// Imports an object as a source of a user, and sends it to the extraction
+    ///
+    /// # Returns
+    /// The status of the imported file: as for an upload, a file already added by the user is a duplicate
+    #[tracing::instrument(name = "Importing source", skip(self, message_repositories))]
+    pub async fn import_source(
+        &self,
+        message_repositories: &TenantMessageRepositories,
+        command: ImportSourceDto,
+    ) -> Result<AddSourceFileStatus, ImportingError> {
+        let ImportSourceDto {
+            user_id,
+            bucket,
+            object_key,
+            tags,
+        } = command;
+
+        // The extraction jobs are published on the exchange of the tenant of the user
+        let tenant_id = self
+            .intake
+            .user_repository
+            .get_user_tenant_id(&self.db_pool, user_id)
+            .await?;
+        let message_repository = message_repositories.route(tenant_id.as_deref())?;
+
+        // Times the upload stage of the job from the start of the download
+        let upload_started_at = Utc::now();
+
+        let mut file = tempfile::tempfile().context("Could not create a temporary file")?;
+        let size_bytes = self
+            .import_s3_repository
+            .download(&bucket, &object_key, &mut file)
+            .await?;
+        let file_name = object_key
+            .rsplit('/')
+            .next()
+            .unwrap_or(&object_key)
+            .to_string();
+
+        let status = self
+            .intake
+            .add_source(
+                &self.db_pool,
+                user_id,
+                tenant_id.as_deref(),
+                message_repository,
+                &mut file,
+                FetchedSource {
+                    file_name,
+                    origin: format!("s3://{}/{}", bucket, object_key),
+                    content_type: None,
+                    size_bytes,
+                    tags,
+                    upload_started_at,
+                },
+            )
+            .await?;
+
+        Ok(status)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum ImportingError {
+    #[error(transparent)]
+    ImportS3RepositoryError(#[from] ImportS3RepositoryError),
+    #[error("The object could not be added as a source: {0}")]
+    AddSourceError(#[from] AddSourceUrlError),
+    #[error(transparent)]
+    TenancyError(#[from] TenancyError),
+    #[error(transparent)]
+    UserRepositoryError(#[from] UserPostgresRepositoryError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for ImportingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
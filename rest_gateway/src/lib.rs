@@ -1,6 +1,18 @@
 pub mod configuration;
 pub mod controllers;
+pub mod database_health;
 pub mod domain;
+pub mod handlers;
+pub mod importing;
+pub mod metrics;
 pub mod middlewares;
+pub mod migrations;
+pub mod openapi;
+pub mod ops;
+pub mod outbox_relay;
+pub mod recrawl_scheduler;
+pub mod reindexing;
 pub mod repositories;
+pub mod responders;
+pub mod retention_sweeper;
 pub mod startup;
@@ -1,5 +1,12 @@
-use common::telemetry::{get_tracing_subscriber, init_tracing_subscriber};
-use rest_gateway::{configuration::get_configuration, startup::Application};
+use common::{
+    core::configuration::reload_on_sighup,
+    telemetry::{get_tracing_subscriber, init_tracing_subscriber, shutdown_tracing},
+};
+use rest_gateway::{
+    configuration::get_configuration,
+    migrations::{run_migrations, MIGRATE_ONLY_FLAG},
+    startup::{get_connection_pool, Application},
+};
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
@@ -8,13 +15,32 @@ async fn main() -> std::io::Result<()> {
     init_tracing_subscriber(tracing_subscriber);
 
     // Panics if the configuration can't be read
-    let configuration = get_configuration().expect("Failed to read configuration.");
+    let configuration = get_configuration()
+        .await
+        .expect("Failed to read configuration.");
+
+    // Migrates the database, for ex from a job of the deployment, without serving
+    if std::env::args().any(|arg| arg == MIGRATE_ONLY_FLAG) {
+        run_migrations(&get_connection_pool(&configuration.database))
+            .await
+            .expect("Failed to migrate the database.");
+        shutdown_tracing();
+        return Ok(());
+    }
 
     let application = match Application::build(configuration, None).await {
         Ok(application) => application,
         Err(error) => panic!("Failed to build application: {:?}", error),
     };
 
+    // The rate limits are reloaded on SIGHUP, without restarting the server
+    let reloadable_settings = application.reloadable_settings();
+    reload_on_sighup(get_configuration, move |settings| {
+        reloadable_settings.update(&settings)
+    })?;
+
     application.run_until_stopped().await?;
+
+    shutdown_tracing();
     Ok(())
 }
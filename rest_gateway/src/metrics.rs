@@ -0,0 +1,168 @@
+use chrono::Duration;
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntGaugeVec, Opts, Registry, TextEncoder};
+
+use crate::domain::entities::ingestion_job::{IngestionJob, IngestionLane, IngestionStage};
+
+/// Buckets of the durations of the ingestion, in seconds:
+/// from the upload of a small file to the embedding of a large book
+const DURATION_BUCKETS_S: &[f64] = &[
+    0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0, 3600.0,
+];
+
+/// Histograms of the latency of the ingestion of the sources, exposed in the Prometheus text format
+///
+/// Each gateway instance only observes the jobs it handled: the scraper aggregates the instances.
+/// The histograms are labelled by lane, as the fast lane has its own latency target.
+/// The connections of the database pool of the instance are exposed along, updated by its probe.
+pub struct IngestionMetrics {
+    registry: Registry,
+    stage_duration_s: HistogramVec,
+    time_to_searchable_s: HistogramVec,
+    database_pool_connections: IntGaugeVec,
+}
+
+impl IngestionMetrics {
+    pub fn try_new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new();
+
+        let stage_duration_s = HistogramVec::new(
+            HistogramOpts::new(
+                "ingestion_stage_duration_seconds",
+                "Duration of the stages of the ingestion jobs",
+            )
+            .buckets(DURATION_BUCKETS_S.to_vec()),
+            &["lane", "stage"],
+        )?;
+        registry.register(Box::new(stage_duration_s.clone()))?;
+
+        let time_to_searchable_s = HistogramVec::new(
+            HistogramOpts::new(
+                "ingestion_time_to_searchable_seconds",
+                "Time for an uploaded source to be searchable with the full-text and semantic searches",
+            )
+            .buckets(DURATION_BUCKETS_S.to_vec()),
+            &["lane"],
+        )?;
+        registry.register(Box::new(time_to_searchable_s.clone()))?;
+        // Exposed from the start, for the alerts on each lane
+        for lane in IngestionLane::ALL {
+            time_to_searchable_s.with_label_values(&[lane.as_str()]);
+        }
+
+        let database_pool_connections = IntGaugeVec::new(
+            Opts::new(
+                "database_pool_connections",
+                "Connections of the database pool, in use or idle",
+            ),
+            &["state"],
+        )?;
+        registry.register(Box::new(database_pool_connections.clone()))?;
+
+        Ok(Self {
+            registry,
+            stage_duration_s,
+            time_to_searchable_s,
+            database_pool_connections,
+        })
+    }
+
+    pub fn observe_stage(&self, lane: IngestionLane, stage: IngestionStage, duration: Duration) {
+        self.stage_duration_s
+            .with_label_values(&[lane.as_str(), stage.as_str()])
+            .observe(as_secs(duration));
+    }
+
+    /// Observes the stages of a job completed by a status update, and its time to be searchable once known
+    pub fn observe_job_update(&self, previous_job: &IngestionJob, job: &IngestionJob) {
+        let previous_stages: Vec<IngestionStage> = previous_job
+            .stage_durations()
+            .into_iter()
+            .map(|(stage, _)| stage)
+            .collect();
+
+        for (stage, duration) in job.stage_durations() {
+            if !previous_stages.contains(&stage) {
+                self.observe_stage(job.lane, stage, duration);
+            }
+        }
+
+        if let (None, Some(duration)) =
+            (previous_job.time_to_searchable(), job.time_to_searchable())
+        {
+            self.time_to_searchable_s
+                .with_label_values(&[job.lane.as_str()])
+                .observe(as_secs(duration));
+        }
+    }
+
+    pub fn set_database_pool_connections(&self, nb_in_use: usize, nb_idle: usize) {
+        self.database_pool_connections
+            .with_label_values(&["in_use"])
+            .set(nb_in_use as i64);
+        self.database_pool_connections
+            .with_label_values(&["idle"])
+            .set(nb_idle as i64);
+    }
+
+    /// Encodes the metrics in the Prometheus text format
+    pub fn encode(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = vec![];
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
+
+fn as_secs(duration: Duration) -> f64 {
+    duration.num_milliseconds() as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::ingestion_job::JobStatusUpdate;
+    use uuid::Uuid;
+
+    #[test]
+    fn stages_are_observed_once_when_completed() {
+        let metrics = IngestionMetrics::try_new().unwrap();
+        let mut job = IngestionJob::new(Uuid::new_v4()).with_lane(IngestionLane::Fast);
+
+        for update in [
+            JobStatusUpdate::ExtractionStarted,
+            JobStatusUpdate::ExtractionCompleted {
+                nb_contents: 0,
+                nb_unchanged_contents: 0,
+                skipped_items: vec![],
+            },
+        ] {
+            let previous_job = job.clone();
+            job.apply(update).unwrap();
+            metrics.observe_job_update(&previous_job, &job);
+        }
+
+        let encoded = metrics.encode().unwrap();
+        assert!(encoded.contains(
+            r#"ingestion_stage_duration_seconds_count{lane="fast",stage="extraction"} 1"#
+        ));
+        assert!(encoded.contains(
+            r#"ingestion_stage_duration_seconds_count{lane="fast",stage="queue_wait"} 1"#
+        ));
+        assert!(encoded.contains(r#"ingestion_time_to_searchable_seconds_count{lane="fast"} 1"#));
+        assert!(encoded.contains(r#"ingestion_time_to_searchable_seconds_count{lane="bulk"} 0"#));
+        assert!(!encoded.contains(r#"lane="bulk",stage"#));
+        assert!(!encoded.contains(r#"stage="upload""#));
+    }
+
+    #[test]
+    fn database_pool_connections_are_gauged_by_state() {
+        let metrics = IngestionMetrics::try_new().unwrap();
+
+        metrics.set_database_pool_connections(3, 2);
+        metrics.set_database_pool_connections(1, 4);
+
+        let encoded = metrics.encode().unwrap();
+        assert!(encoded.contains(r#"database_pool_connections{state="in_use"} 1"#));
+        assert!(encoded.contains(r#"database_pool_connections{state="idle"} 4"#));
+    }
+}
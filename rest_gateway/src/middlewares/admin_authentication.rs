@@ -0,0 +1,103 @@
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    error::ErrorUnauthorized,
+    http,
+};
+use futures::{future::LocalBoxFuture, FutureExt};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use std::{
+    future::{ready, Ready},
+    rc::Rc,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tracing::warn;
+
+/// Middleware factory restricting endpoints to the operators, sending the admin token as a bearer token
+#[derive(Clone)]
+pub struct RequireAdmin {
+    /// Only the hash of the token is kept: comparing hashes does not leak the token through the comparison time
+    token_hash: Arc<Vec<u8>>,
+}
+
+impl RequireAdmin {
+    pub fn new(token: &Secret<String>) -> Self {
+        Self {
+            token_hash: Arc::new(Sha256::digest(token.expose_secret().as_bytes()).to_vec()),
+        }
+    }
+
+    fn is_admin_token(&self, token: &str) -> bool {
+        Sha256::digest(token.as_bytes()).as_slice() == self.token_hash.as_slice()
+    }
+}
+
+impl<S> Transform<S, ServiceRequest> for RequireAdmin
+where
+    S: Service<
+            ServiceRequest,
+            Response = ServiceResponse<actix_web::body::BoxBody>,
+            Error = actix_web::Error,
+        > + 'static,
+{
+    type Response = ServiceResponse<actix_web::body::BoxBody>;
+    type Error = actix_web::Error;
+    type Transform = AdminAuthMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AdminAuthMiddleware {
+            service: Rc::new(service),
+            require_admin: self.clone(),
+        }))
+    }
+}
+
+/// Middleware rejecting the requests without the admin token
+pub struct AdminAuthMiddleware<S> {
+    service: Rc<S>,
+    require_admin: RequireAdmin,
+}
+
+impl<S> Service<ServiceRequest> for AdminAuthMiddleware<S>
+where
+    S: Service<
+            ServiceRequest,
+            Response = ServiceResponse<actix_web::body::BoxBody>,
+            Error = actix_web::Error,
+        > + 'static,
+{
+    type Response = ServiceResponse<actix_web::body::BoxBody>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, actix_web::Error>>;
+
+    fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let is_admin = req
+            .headers()
+            .get(http::header::AUTHORIZATION)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .map(|token| self.require_admin.is_admin_token(token))
+            .unwrap_or(false);
+
+        if !is_admin {
+            warn!(
+                path = req.path(),
+                "Request to an admin endpoint without the admin token"
+            );
+            return Box::pin(ready(Err(ErrorUnauthorized(
+                "A valid admin token is required",
+            ))));
+        }
+
+        let srv = Rc::clone(&self.service);
+
+        async move { srv.call(req).await }.boxed_local()
+    }
+}
@@ -1,27 +1,41 @@
 use actix_web::{
     dev::{Service, ServiceRequest, ServiceResponse, Transform},
-    error::ErrorUnauthorized,
-    http, web, HttpMessage,
+    error::{ErrorForbidden, ErrorInternalServerError, ErrorUnauthorized},
+    web, HttpMessage,
 };
 use futures::{future::LocalBoxFuture, FutureExt};
 use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
 use std::{
     future::{ready, Ready},
     rc::Rc,
     task::{Context, Poll},
 };
-use tracing::error;
+use tracing::{error, info};
 use uuid::Uuid;
 
-use crate::repositories::jwt_authentication_repository::JwtAuthenticationRepository;
+use crate::domain::entities::api_key::{ApiKey, ApiKeyScope};
+use crate::repositories::{
+    api_key_postgres_repository::{ApiKeyPostgresRepository, AuthenticatedApiKey},
+    authenticator_port::AuthenticatorPort,
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct UserIdFromToken(pub Uuid);
 
+/// Id of the API key a request was authenticated with, alongside the `UserIdFromToken` of its owner
+#[derive(Clone, Debug)]
+pub struct ApiKeyIdFromKey(pub Uuid);
+
+/// Header of the API keys, used by services and scripts instead of an access token
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
 /// Middleware responsible for handling authentication and user information extraction.
 pub struct AuthMiddleware<S> {
     service: Rc<S>,
-    auth_repository: web::Data<JwtAuthenticationRepository>,
+    authenticator: web::Data<dyn AuthenticatorPort>,
+    api_key_scope: Option<ApiKeyScope>,
+    requires_local_account: bool,
 }
 
 impl<S> Service<ServiceRequest> for AuthMiddleware<S>
@@ -42,46 +56,57 @@ where
     }
 
     /// Handles incoming requests.
+    ///
+    /// The user is authenticated by the authenticator of the deployment, from the headers of the request.
+    ///
+    /// An API key can be provided instead of an access token, only on the routes accepting its scopes.
+    ///
+    /// The routes requiring an account of the gateway reject the identities of an external provider.
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        // Attempt to extract token from authorization header only
-        let token = req
-            .headers()
-            .get(http::header::AUTHORIZATION)
-            .map(|h| h.to_str().unwrap().split_at(7).1.to_string());
-
-        // If token is missing, return unauthorized error
-        let token = match token {
-            Some(token) => token,
-            None => {
-                return Box::pin(ready(Err(ErrorUnauthorized(
-                    "No access token was provided",
-                ))));
-            }
-        };
-
-        // Decode token and handle errors
-        let user_id = match self.auth_repository.decode_token(&token) {
-            Ok(id) => id,
-            Err(e) => return Box::pin(ready(Err(e.into()))),
-        };
-
-        let user_id = match Uuid::parse_str(user_id.as_str()) {
-            Ok(user_id) => user_id,
-            Err(error) => {
-                error!(?error, "Provided user id could not be parsed to uuid");
-
-                return Box::pin(ready(Err(ErrorUnauthorized(
-                    "Provided user id is not valid",
-                ))));
-            }
-        };
+        if req.headers().contains_key(API_KEY_HEADER) && self.api_key_scope.is_none() {
+            return Box::pin(ready(Err(ErrorForbidden(
+                "API keys are not accepted on this endpoint",
+            ))));
+        }
 
+        let authenticator = self.authenticator.clone();
+        let api_key_scope = self.api_key_scope;
+        let requires_local_account = self.requires_local_account;
         let srv = Rc::clone(&self.service);
 
         // Handles user id extraction, insertion into request extensions and continue the request processing
         async move {
-            req.extensions_mut()
-                .insert::<UserIdFromToken>(UserIdFromToken(user_id));
+            match check_credentials(&req, &**authenticator).await? {
+                CheckedCredentials::User(user_id) => {
+                    if requires_local_account && !authenticator.has_local_accounts() {
+                        info!(?user_id, "External identity on an endpoint of the accounts");
+                        return Err(ErrorForbidden(
+                            "This endpoint is only available to the accounts of the gateway",
+                        ));
+                    }
+
+                    req.extensions_mut()
+                        .insert::<UserIdFromToken>(UserIdFromToken(user_id));
+                }
+                CheckedCredentials::ApiKey(api_key) => {
+                    let has_required_scope = matches!(
+                        api_key_scope,
+                        Some(required_scope) if api_key.scopes.contains(&required_scope)
+                    );
+
+                    if !has_required_scope {
+                        info!(api_key_id = ?api_key.id, ?api_key_scope, "API key without the required scope");
+                        return Err(ErrorForbidden(
+                            "The API key does not give access to this endpoint",
+                        ));
+                    }
+
+                    req.extensions_mut()
+                        .insert::<UserIdFromToken>(UserIdFromToken(api_key.user_id));
+                    req.extensions_mut()
+                        .insert::<ApiKeyIdFromKey>(ApiKeyIdFromKey(api_key.id));
+                }
+            }
 
             // Calls the wrapped service to handle the request
             let res = srv.call(req).await?;
@@ -91,14 +116,95 @@ where
     }
 }
 
+/// Credentials of a request checked by [`check_credentials`], before the route authorizes them
+#[derive(Clone, Debug)]
+pub enum CheckedCredentials {
+    /// User authenticated by the authenticator of the deployment
+    User(Uuid),
+    ApiKey(AuthenticatedApiKey),
+}
+
+/// Checks the credentials of a request: its API key, or else the credentials read by the authenticator
+///
+/// The checked credentials are kept in the extensions of the request: the credentials checked by a middleware
+/// (ie the `RequestQuota`) are not checked again by the authentication middleware.
+pub async fn check_credentials(
+    req: &ServiceRequest,
+    authenticator: &dyn AuthenticatorPort,
+) -> Result<CheckedCredentials, actix_web::Error> {
+    if let Some(credentials) = req.extensions().get::<CheckedCredentials>() {
+        return Ok(credentials.clone());
+    }
+
+    let credentials = match req.headers().get(API_KEY_HEADER) {
+        Some(api_key) => {
+            let api_key_hash = ApiKey::hash(api_key.to_str().unwrap_or_default());
+            CheckedCredentials::ApiKey(check_api_key(req, &api_key_hash).await?)
+        }
+        None => CheckedCredentials::User(authenticator.authenticate(req.headers()).await?),
+    };
+
+    req.extensions_mut()
+        .insert::<CheckedCredentials>(credentials.clone());
+    Ok(credentials)
+}
+
+/// # Returns
+/// The API key of the hash, attributed to its owner
+async fn check_api_key(
+    req: &ServiceRequest,
+    api_key_hash: &str,
+) -> Result<AuthenticatedApiKey, actix_web::Error> {
+    let (pool, api_key_repository) = match (
+        req.app_data::<web::Data<PgPool>>(),
+        req.app_data::<web::Data<ApiKeyPostgresRepository>>(),
+    ) {
+        (Some(pool), Some(api_key_repository)) => (pool, api_key_repository),
+        _ => {
+            error!("Missing database pool or API key repository to check the API key");
+            return Err(ErrorInternalServerError("Internal error"));
+        }
+    };
+
+    let api_key = api_key_repository
+        .authenticate(&***pool, api_key_hash)
+        .await
+        .map_err(|error| {
+            error!(?error, "Failed to check the API key");
+            ErrorInternalServerError("Internal error")
+        })?;
+
+    api_key.ok_or_else(|| ErrorUnauthorized("Provided API key is not valid"))
+}
+
 /// Middleware factory for requiring authentication.
 pub struct RequireAuth {
-    auth_repository: web::Data<JwtAuthenticationRepository>,
+    authenticator: web::Data<dyn AuthenticatorPort>,
+    api_key_scope: Option<ApiKeyScope>,
+    requires_local_account: bool,
 }
 
 impl RequireAuth {
-    pub fn new(auth_repository: web::Data<JwtAuthenticationRepository>) -> Self {
-        Self { auth_repository }
+    pub fn new(authenticator: web::Data<dyn AuthenticatorPort>) -> Self {
+        Self {
+            authenticator,
+            api_key_scope: None,
+            requires_local_account: false,
+        }
+    }
+
+    /// Also accepts the API keys having the given scope
+    pub fn with_api_key_scope(mut self, scope: ApiKeyScope) -> Self {
+        self.api_key_scope = Some(scope);
+        self
+    }
+
+    /// Only accepts the users having an account of the gateway, referenced by the data of the route
+    ///
+    /// The users authenticated by an external provider (OIDC, mTLS...) are rejected with a 403.
+    pub fn with_local_account(mut self) -> Self {
+        self.requires_local_account = true;
+        self
     }
 }
 
@@ -120,7 +226,9 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ready(Ok(AuthMiddleware {
             service: Rc::new(service),
-            auth_repository: self.auth_repository.clone(),
+            authenticator: self.authenticator.clone(),
+            api_key_scope: self.api_key_scope,
+            requires_local_account: self.requires_local_account,
         }))
     }
 }
@@ -1 +1,5 @@
+pub mod admin_authentication;
 pub mod jwt_authentication;
+pub mod rate_limit;
+pub mod request_quota;
+pub mod throttling;
@@ -0,0 +1,306 @@
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    http::StatusCode,
+    HttpMessage, HttpResponse, ResponseError,
+};
+use common::helper::error_chain_fmt;
+use futures::{future::LocalBoxFuture, FutureExt};
+use std::{
+    collections::HashMap,
+    future::{ready, Ready},
+    net::IpAddr,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tokio::sync::watch;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    configuration::RateLimitSettings,
+    middlewares::{
+        jwt_authentication::middleware::{ApiKeyIdFromKey, UserIdFromToken},
+        request_quota::TokenBucket,
+        throttling::{Throttling, ThrottlingReason},
+    },
+};
+
+/// Number of tracked clients over which the buckets back to their capacity are dropped
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Client a rate limit applies to: an API key has its own budget, separate from the one of its owner
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    User(Uuid),
+    ApiKey(Uuid),
+    /// Address of a client not authenticated, for ex logging in
+    Address(IpAddr),
+}
+
+#[derive(thiserror::Error)]
+pub enum RateLimitError {
+    #[error("Too many requests, retry after {}s", .0.retry_after_s)]
+    TooManyRequests(Throttling),
+}
+
+impl std::fmt::Debug for RateLimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for RateLimitError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            RateLimitError::TooManyRequests(throttling) => throttling.status_code(),
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        let RateLimitError::TooManyRequests(throttling) = self;
+
+        throttling.error_response(&self.to_string())
+    }
+}
+
+/// Middleware factory limiting the rate of requests of each client on a group of endpoints
+///
+/// Each client (user or API key) has its own token bucket, shared by all the actix-web workers.
+/// The limit is local to a gateway instance, like the `RequestQuota`.
+///
+/// Registered inside the authentication middleware, to know the client of the request.
+/// The requests of the endpoints without authentication (the log-ins) are limited for each peer address:
+/// behind a reverse proxy, they share the budget of the proxy.
+/// The limit follows the reloads of the configuration: on a change, the clients start again with a full bucket.
+#[derive(Clone)]
+pub struct RateLimit {
+    state: Arc<Mutex<RateLimitState>>,
+}
+
+struct RateLimitState {
+    settings: watch::Receiver<RateLimitSettings>,
+    buckets: HashMap<RateLimitKey, TokenBucket>,
+}
+
+impl RateLimit {
+    pub fn new(settings: watch::Receiver<RateLimitSettings>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(RateLimitState {
+                settings,
+                buckets: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Takes a token from the bucket of a client, or returns the time to wait for the next token
+    pub fn try_take(&self, key: RateLimitKey, now: Instant) -> Result<(), Duration> {
+        let mut state = self.state.lock().expect("rate limit state lock poisoned");
+        let RateLimitState { settings, buckets } = &mut *state;
+
+        // The buckets were filled at the previous rate
+        if settings.has_changed().unwrap_or(false) {
+            let reloaded_settings = settings.borrow_and_update().clone();
+            info!(settings = ?reloaded_settings, "Rate limit reloaded");
+            buckets.clear();
+        }
+
+        // A full bucket is the same as no bucket: dropping them bounds the memory
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            buckets.retain(|_, bucket| !bucket.is_full(now));
+        }
+
+        let settings = settings.borrow();
+        buckets
+            .entry(key)
+            .or_insert_with(|| {
+                TokenBucket::per_minute(settings.burst, settings.requests_per_min, now)
+            })
+            .try_take(now)
+    }
+
+    /// Number of requests of a client accepted at once
+    fn burst(&self) -> u32 {
+        let state = self.state.lock().expect("rate limit state lock poisoned");
+        let burst = state.settings.borrow().burst;
+        burst
+    }
+
+    /// Client of a request: the API key it was authenticated with, its user, or else its peer address
+    fn key(req: &ServiceRequest) -> Option<RateLimitKey> {
+        let extensions = req.extensions();
+
+        if let Some(ApiKeyIdFromKey(api_key_id)) = extensions.get::<ApiKeyIdFromKey>() {
+            return Some(RateLimitKey::ApiKey(*api_key_id));
+        }
+
+        extensions
+            .get::<UserIdFromToken>()
+            .map(|UserIdFromToken(user_id)| RateLimitKey::User(*user_id))
+            .or_else(|| req.peer_addr().map(|addr| RateLimitKey::Address(addr.ip())))
+    }
+}
+
+impl<S> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<
+            ServiceRequest,
+            Response = ServiceResponse<actix_web::body::BoxBody>,
+            Error = actix_web::Error,
+        > + 'static,
+{
+    type Response = ServiceResponse<actix_web::body::BoxBody>;
+    type Error = actix_web::Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            rate_limit: self.clone(),
+        }))
+    }
+}
+
+/// Middleware rejecting the requests of the clients over their rate limit with a `429 Too Many Requests`
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    rate_limit: RateLimit,
+}
+
+impl<S> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<
+            ServiceRequest,
+            Response = ServiceResponse<actix_web::body::BoxBody>,
+            Error = actix_web::Error,
+        > + 'static,
+{
+    type Response = ServiceResponse<actix_web::body::BoxBody>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, actix_web::Error>>;
+
+    fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if let Some(key) = RateLimit::key(&req) {
+            if let Err(wait) = self.rate_limit.try_take(key, Instant::now()) {
+                warn!(path = req.path(), ?key, "Client over its rate limit");
+                // Less than a token remains in the bucket of the client
+                let error = RateLimitError::TooManyRequests(Throttling::new(
+                    ThrottlingReason::RateLimited,
+                    wait,
+                    self.rate_limit.burst().into(),
+                    0,
+                ));
+                return Box::pin(ready(Err(error.into())));
+            }
+        }
+
+        self.service.call(req).boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn rate_limit(requests_per_min: u32, burst: u32) -> RateLimit {
+        RateLimit::new(
+            watch::channel(RateLimitSettings {
+                requests_per_min,
+                burst,
+            })
+            .1,
+        )
+    }
+
+    #[test]
+    fn each_client_has_its_own_budget() {
+        let rate_limit = rate_limit(60, 1);
+        let user_id = Uuid::new_v4();
+        let now = Instant::now();
+
+        assert!(rate_limit
+            .try_take(RateLimitKey::User(user_id), now)
+            .is_ok());
+        assert_eq!(
+            rate_limit.try_take(RateLimitKey::User(user_id), now),
+            Err(Duration::from_secs(1))
+        );
+
+        // An API key of the same user is limited separately
+        assert!(rate_limit
+            .try_take(RateLimitKey::ApiKey(Uuid::new_v4()), now)
+            .is_ok());
+        assert!(rate_limit
+            .try_take(RateLimitKey::User(Uuid::new_v4()), now)
+            .is_ok());
+    }
+
+    #[test]
+    fn requests_without_authentication_are_limited_for_each_peer_address() {
+        let authenticated = TestRequest::default()
+            .peer_addr("127.0.0.1:8000".parse().unwrap())
+            .to_srv_request();
+        let user_id = Uuid::new_v4();
+        authenticated
+            .extensions_mut()
+            .insert(UserIdFromToken(user_id));
+        let anonymous = TestRequest::default()
+            .peer_addr("127.0.0.1:8000".parse().unwrap())
+            .to_srv_request();
+
+        assert_eq!(
+            RateLimit::key(&authenticated),
+            Some(RateLimitKey::User(user_id))
+        );
+        assert_eq!(
+            RateLimit::key(&anonymous),
+            Some(RateLimitKey::Address("127.0.0.1".parse().unwrap()))
+        );
+    }
+
+    #[test]
+    fn budget_is_refilled_per_minute() {
+        let rate_limit = rate_limit(30, 1);
+        let key = RateLimitKey::User(Uuid::new_v4());
+        let now = Instant::now();
+
+        assert!(rate_limit.try_take(key, now).is_ok());
+        assert!(rate_limit
+            .try_take(key, now + Duration::from_secs(1))
+            .is_err());
+        assert!(rate_limit
+            .try_take(key, now + Duration::from_secs(2))
+            .is_ok());
+    }
+
+    #[test]
+    fn reloaded_settings_apply_to_the_next_requests() {
+        let (settings, receiver) = watch::channel(RateLimitSettings {
+            requests_per_min: 60,
+            burst: 1,
+        });
+        let rate_limit = RateLimit::new(receiver);
+        let key = RateLimitKey::User(Uuid::new_v4());
+        let now = Instant::now();
+
+        assert!(rate_limit.try_take(key, now).is_ok());
+        assert!(rate_limit.try_take(key, now).is_err());
+
+        settings.send_replace(RateLimitSettings {
+            requests_per_min: 60,
+            burst: 2,
+        });
+        assert!(rate_limit.try_take(key, now).is_ok());
+        assert!(rate_limit.try_take(key, now).is_ok());
+        assert!(rate_limit.try_take(key, now).is_err());
+        assert_eq!(rate_limit.burst(), 2);
+    }
+}
@@ -0,0 +1,384 @@
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    http::StatusCode,
+    web, HttpResponse, ResponseError,
+};
+use common::helper::error_chain_fmt;
+use futures::{future::LocalBoxFuture, FutureExt};
+use std::{
+    future::{ready, Ready},
+    rc::Rc,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::warn;
+
+use crate::{
+    configuration::RequestQuotaSettings,
+    middlewares::{
+        jwt_authentication::middleware::{check_credentials, API_KEY_HEADER},
+        throttling::{Throttling, ThrottlingReason},
+    },
+    repositories::authenticator_port::AuthenticatorPort,
+};
+
+/// Token bucket: holds up to `capacity` tokens, refilled at `refill_per_s` tokens per second
+#[derive(Debug)]
+pub struct TokenBucket {
+    capacity: f64,
+    refill_per_s: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    /// Creates a full bucket
+    pub fn new(capacity: u32, refill_per_s: u32, now: Instant) -> Self {
+        Self {
+            capacity: f64::from(capacity),
+            refill_per_s: f64::from(refill_per_s),
+            tokens: f64::from(capacity),
+            refilled_at: now,
+        }
+    }
+
+    /// Creates a full bucket refilled at `refill_per_min` tokens per minute
+    pub fn per_minute(capacity: u32, refill_per_min: u32, now: Instant) -> Self {
+        Self {
+            refill_per_s: f64::from(refill_per_min) / 60.0,
+            ..Self::new(capacity, 0, now)
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed_s = now
+            .saturating_duration_since(self.refilled_at)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed_s * self.refill_per_s).min(self.capacity);
+        self.refilled_at = now;
+    }
+
+    /// Whether the bucket is back to its capacity: it behaves as a new bucket
+    pub fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.capacity
+    }
+
+    /// Takes a token from the bucket, or returns the time to wait for the next token
+    pub fn try_take(&mut self, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+
+        if self.refill_per_s <= 0.0 {
+            return Err(Duration::MAX);
+        }
+
+        Err(Duration::from_secs_f64(
+            (1.0 - self.tokens) / self.refill_per_s,
+        ))
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum RequestQuotaError {
+    #[error("Too many requests are being served, retry after {}s", .0.retry_after_s)]
+    Overloaded(Throttling),
+}
+
+impl std::fmt::Debug for RequestQuotaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for RequestQuotaError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            RequestQuotaError::Overloaded(throttling) => throttling.status_code(),
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        let RequestQuotaError::Overloaded(throttling) = self;
+
+        throttling.error_response(&self.to_string())
+    }
+}
+
+/// Share of the quota of a tier of requests: a token bucket, and a number of requests served at the same time
+struct QuotaTier {
+    bucket: Mutex<TokenBucket>,
+    concurrent_requests: Arc<Semaphore>,
+    /// Capacity of the bucket, returned to the shed clients
+    burst: u32,
+    max_concurrent_requests: usize,
+}
+
+impl QuotaTier {
+    fn new(requests_per_s: u32, burst: u32, max_concurrent_requests: usize) -> Self {
+        Self {
+            bucket: Mutex::new(TokenBucket::new(burst, requests_per_s, Instant::now())),
+            concurrent_requests: Arc::new(Semaphore::new(max_concurrent_requests)),
+            burst,
+            max_concurrent_requests,
+        }
+    }
+
+    /// Takes a slot of the concurrent requests, without taking from the bucket
+    fn try_acquire(&self, path: &str) -> Result<OwnedSemaphorePermit, RequestQuotaError> {
+        self.concurrent_requests
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| {
+                warn!(path, "Too many concurrent requests, shedding");
+                RequestQuotaError::Overloaded(Throttling::new(
+                    ThrottlingReason::Overloaded,
+                    Duration::ZERO,
+                    self.max_concurrent_requests as u64,
+                    0,
+                ))
+            })
+    }
+
+    /// Takes a slot of the concurrent requests and a token from the bucket
+    ///
+    /// # Returns
+    /// The slot, held until the response is built
+    fn try_admit(&self, path: &str) -> Result<OwnedSemaphorePermit, RequestQuotaError> {
+        let permit = self.try_acquire(path)?;
+
+        let taken = self
+            .bucket
+            .lock()
+            .expect("request quota bucket lock poisoned")
+            .try_take(Instant::now());
+        if let Err(wait) = taken {
+            warn!(path, "Request rate over the quota, shedding");
+            // Less than a token remains in the bucket
+            return Err(RequestQuotaError::Overloaded(Throttling::new(
+                ThrottlingReason::QuotaExceeded,
+                wait,
+                self.burst.into(),
+                0,
+            )));
+        }
+
+        Ok(permit)
+    }
+}
+
+/// Middleware factory guarding a set of endpoints with a quota shared by all the actix-web workers
+///
+/// Requests are shed with a `503 Service Unavailable` when the rate of requests exceeds the token bucket,
+/// or when too many requests are being served at the same time.
+/// The quota is local to a gateway instance: the quota of the service is the sum of the quotas of its instances.
+///
+/// Registered outside the authentication middleware, the anonymous requests are shed first:
+/// they have their own smaller bucket, and only a part of the concurrent requests. The remaining concurrent requests
+/// are kept for the authenticated requests.
+/// A request is only admitted as authenticated once its credentials are checked: the requests without credentials,
+/// or with invalid ones, are anonymous. The checked credentials are not checked again by the authentication middleware.
+#[derive(Clone)]
+pub struct RequestQuota {
+    authenticated: Arc<QuotaTier>,
+    anonymous: Arc<QuotaTier>,
+    authenticator: web::Data<dyn AuthenticatorPort>,
+}
+
+impl RequestQuota {
+    pub fn new(
+        settings: &RequestQuotaSettings,
+        authenticator: web::Data<dyn AuthenticatorPort>,
+    ) -> Self {
+        Self {
+            authenticated: Arc::new(QuotaTier::new(
+                settings.requests_per_s,
+                settings.burst,
+                settings.max_concurrent_requests,
+            )),
+            anonymous: Arc::new(QuotaTier::new(
+                settings.anonymous.requests_per_s,
+                settings.anonymous.burst,
+                settings.anonymous.max_concurrent_requests,
+            )),
+            authenticator,
+        }
+    }
+
+    /// Admits a request in its tier
+    ///
+    /// # Returns
+    /// The slots of the concurrent requests taken by the request, held until its response is built
+    async fn try_admit(
+        &self,
+        req: &ServiceRequest,
+    ) -> Result<Vec<OwnedSemaphorePermit>, RequestQuotaError> {
+        let path = req.path();
+        let has_credentials = req.headers().contains_key(API_KEY_HEADER)
+            || self.authenticator.has_credentials(req.headers());
+
+        // The requests without credentials are not checked: they cannot be authenticated
+        if has_credentials && check_credentials(req, &**self.authenticator).await.is_ok() {
+            return Ok(vec![self.authenticated.try_admit(path)?]);
+        }
+
+        // An anonymous request also takes one of the concurrent requests of all the tiers
+        let anonymous_permit = self.anonymous.try_admit(path)?;
+        let permit = self.authenticated.try_acquire(path)?;
+        Ok(vec![anonymous_permit, permit])
+    }
+}
+
+impl<S> Transform<S, ServiceRequest> for RequestQuota
+where
+    S: Service<
+            ServiceRequest,
+            Response = ServiceResponse<actix_web::body::BoxBody>,
+            Error = actix_web::Error,
+        > + 'static,
+{
+    type Response = ServiceResponse<actix_web::body::BoxBody>;
+    type Error = actix_web::Error;
+    type Transform = RequestQuotaMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestQuotaMiddleware {
+            service: Rc::new(service),
+            quota: self.clone(),
+        }))
+    }
+}
+
+/// Middleware shedding the requests exceeding the quota
+pub struct RequestQuotaMiddleware<S> {
+    service: Rc<S>,
+    quota: RequestQuota,
+}
+
+impl<S> Service<ServiceRequest> for RequestQuotaMiddleware<S>
+where
+    S: Service<
+            ServiceRequest,
+            Response = ServiceResponse<actix_web::body::BoxBody>,
+            Error = actix_web::Error,
+        > + 'static,
+{
+    type Response = ServiceResponse<actix_web::body::BoxBody>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, actix_web::Error>>;
+
+    fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let quota = self.quota.clone();
+        let srv = Rc::clone(&self.service);
+
+        async move {
+            // Held until the response is built
+            let permits = quota.try_admit(&req).await?;
+
+            let res = srv.call(req).await;
+            drop(permits);
+            res
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        configuration::{AnonymousRequestQuotaSettings, StaticTokenSettings},
+        repositories::static_token_authenticator::StaticTokenAuthenticator,
+    };
+    use actix_web::{http::header::AUTHORIZATION, test::TestRequest};
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    #[test]
+    fn bucket_allows_a_burst_then_waits_for_the_refill() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(2, 10, now);
+
+        assert!(bucket.try_take(now).is_ok());
+        assert!(bucket.try_take(now).is_ok());
+        assert_eq!(bucket.try_take(now), Err(Duration::from_millis(100)));
+
+        assert!(bucket.try_take(now + Duration::from_millis(100)).is_ok());
+    }
+
+    #[test]
+    fn anonymous_tier_is_shed_before_the_concurrent_requests_of_the_authenticated_tier() {
+        let authenticated = QuotaTier::new(100, 100, 2);
+        let anonymous = QuotaTier::new(100, 100, 1);
+
+        let anonymous_permits = (
+            anonymous.try_admit("/search").unwrap(),
+            authenticated.try_acquire("/search").unwrap(),
+        );
+        assert!(anonymous.try_admit("/search").is_err());
+
+        // A slot is kept for the authenticated requests
+        let _authenticated_permit = authenticated.try_admit("/search").unwrap();
+        assert!(authenticated.try_admit("/search").is_err());
+
+        drop(anonymous_permits);
+        assert!(anonymous.try_admit("/search").is_ok());
+    }
+
+    #[tokio::test]
+    async fn requests_with_invalid_credentials_are_admitted_as_anonymous() {
+        let authenticator: Arc<dyn AuthenticatorPort> =
+            Arc::new(StaticTokenAuthenticator::new(&StaticTokenSettings {
+                token: Secret::new("valid-token".to_string()),
+                user_id: Uuid::new_v4(),
+            }));
+        let quota = RequestQuota::new(
+            &RequestQuotaSettings {
+                requests_per_s: 100,
+                burst: 100,
+                max_concurrent_requests: 2,
+                anonymous: AnonymousRequestQuotaSettings {
+                    requests_per_s: 100,
+                    burst: 100,
+                    max_concurrent_requests: 1,
+                },
+            },
+            web::Data::from(authenticator),
+        );
+        let request = |token: &str| {
+            TestRequest::post()
+                .uri("/search")
+                .insert_header((AUTHORIZATION, format!("Bearer {}", token)))
+                .to_srv_request()
+        };
+
+        let anonymous_permits = quota.try_admit(&request("forged-token")).await.unwrap();
+        assert_eq!(anonymous_permits.len(), 2);
+        assert!(quota.try_admit(&request("forged-token")).await.is_err());
+
+        let authenticated_permits = quota.try_admit(&request("valid-token")).await.unwrap();
+        assert_eq!(authenticated_permits.len(), 1);
+    }
+
+    #[test]
+    fn bucket_does_not_refill_over_its_capacity() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(1, 10, now);
+
+        let later = now + Duration::from_secs(60);
+        assert!(bucket.try_take(later).is_ok());
+        assert!(bucket.try_take(later).is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use actix_web::{
+    http::{
+        header::{ContentType, RETRY_AFTER},
+        StatusCode,
+    },
+    HttpResponse,
+};
+use rand::Rng;
+use serde::Serialize;
+use serde_json::json;
+use std::time::Duration;
+
+/// Header of the machine-readable reason of a throttled request
+pub const THROTTLING_REASON_HEADER: &str = "X-Throttling-Reason";
+/// Header of the limit the throttled request was over
+pub const RATE_LIMIT_LIMIT_HEADER: &str = "X-RateLimit-Limit";
+/// Header of what remains of the limit when the request was throttled
+pub const RATE_LIMIT_REMAINING_HEADER: &str = "X-RateLimit-Remaining";
+
+/// Maximum random delay added to the `Retry-After` of a throttled request,
+/// so the throttled clients do not all retry at the same time
+const MAX_RETRY_JITTER_S: u64 = 2;
+
+/// Why a request was throttled, returned to the clients as a stable code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThrottlingReason {
+    /// The client is over its own rate limit
+    RateLimited,
+    /// The rate of requests of all the clients is over the quota of the endpoints
+    QuotaExceeded,
+    /// Too many requests are being served at the same time
+    Overloaded,
+    /// Too many invalid two-factor authentication codes were tried to log in to the account
+    LockedOut,
+}
+
+impl ThrottlingReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThrottlingReason::RateLimited => "rate_limited",
+            ThrottlingReason::QuotaExceeded => "quota_exceeded",
+            ThrottlingReason::Overloaded => "overloaded",
+            ThrottlingReason::LockedOut => "locked_out",
+        }
+    }
+}
+
+/// Throttled request, built into the same response by all the throttling paths
+///
+/// The response always has a `Retry-After`, the reason of the throttling and the counters of the limit,
+/// in its headers and its body: the clients back off the same way whatever throttled them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Throttling {
+    pub reason: ThrottlingReason,
+    pub retry_after_s: u64,
+    /// Limit the request was over: a number of requests in a burst, or of concurrent requests
+    pub limit: u64,
+    /// What remained of the limit when the request was throttled
+    pub remaining: u64,
+}
+
+impl Throttling {
+    /// # Params
+    /// - wait: time before the limit allows a request again. The `Retry-After` adds some jitter to it.
+    pub fn new(reason: ThrottlingReason, wait: Duration, limit: u64, remaining: u64) -> Self {
+        Self {
+            reason,
+            retry_after_s: retry_after_s(wait),
+            limit,
+            remaining,
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self.reason {
+            ThrottlingReason::RateLimited | ThrottlingReason::LockedOut => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
+            ThrottlingReason::QuotaExceeded | ThrottlingReason::Overloaded => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+        }
+    }
+
+    /// Response of the throttled request, with a human-readable `error`
+    pub fn error_response(&self, error: &str) -> HttpResponse<actix_web::body::BoxBody> {
+        HttpResponse::build(self.status_code())
+            .insert_header(ContentType::json())
+            .insert_header((RETRY_AFTER, self.retry_after_s.to_string()))
+            .insert_header((THROTTLING_REASON_HEADER, self.reason.as_str()))
+            .insert_header((RATE_LIMIT_LIMIT_HEADER, self.limit.to_string()))
+            .insert_header((RATE_LIMIT_REMAINING_HEADER, self.remaining.to_string()))
+            .json(json!({
+                "error": error,
+                "reason": self.reason,
+                "retry_after_s": self.retry_after_s,
+                "limit": self.limit,
+                "remaining": self.remaining,
+            }))
+    }
+}
+
+/// Seconds a throttled client should wait before retrying, rounded up, with some jitter
+fn retry_after_s(wait: Duration) -> u64 {
+    let wait_s = wait.as_secs().saturating_add(1);
+    wait_s.saturating_add(rand::thread_rng().gen_range(0..=MAX_RETRY_JITTER_S))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttled_response_has_the_retry_after_reason_and_counters() {
+        let throttling = Throttling::new(
+            ThrottlingReason::RateLimited,
+            Duration::from_millis(1500),
+            10,
+            0,
+        );
+
+        let response = throttling.error_response("Too many requests");
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let header = |name| response.headers().get(name).unwrap().to_str().unwrap();
+        let retry_after_s: u64 = header(RETRY_AFTER.as_str()).parse().unwrap();
+        assert!((2..=2 + MAX_RETRY_JITTER_S).contains(&retry_after_s));
+        assert_eq!(header(THROTTLING_REASON_HEADER), "rate_limited");
+        assert_eq!(header(RATE_LIMIT_LIMIT_HEADER), "10");
+        assert_eq!(header(RATE_LIMIT_REMAINING_HEADER), "0");
+    }
+
+    #[test]
+    fn shed_requests_are_service_unavailable() {
+        for reason in [
+            ThrottlingReason::QuotaExceeded,
+            ThrottlingReason::Overloaded,
+        ] {
+            let throttling = Throttling::new(reason, Duration::ZERO, 1, 0);
+
+            assert_eq!(throttling.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use common::helper::error_chain_fmt;
+use sqlx::{migrate::Migrator, Connection, PgPool};
+use tracing::info;
+
+/// Command line flag running the migrations of the database, and exiting without serving
+pub const MIGRATE_ONLY_FLAG: &str = "--migrate-only";
+
+/// Migrations of the database, embedded in the binary at compile time
+static MIGRATOR: Migrator = sqlx::migrate!("../migrations");
+
+#[derive(thiserror::Error)]
+pub enum MigrationError {
+    #[error(transparent)]
+    MigrateError(#[from] sqlx::migrate::MigrateError),
+    #[error(transparent)]
+    SqlxError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for MigrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Runs the pending migrations of the database
+///
+/// Several gateway instances can start at the same time: the first one to get the advisory lock of the migrator
+/// migrates the database, the others wait for it and find no pending migration.
+/// The connection of the migrations is detached from the pool and closed once migrated, whatever the outcome:
+/// the lock is never left held by a pooled connection, and is released with the connection if the instance dies.
+#[tracing::instrument(name = "Running database migrations", skip(db_pool))]
+pub async fn run_migrations(db_pool: &PgPool) -> Result<(), MigrationError> {
+    let mut connection = db_pool.acquire().await?.detach();
+
+    // A migration can take longer than the statement timeout of the pool
+    sqlx::query("SET statement_timeout = 0")
+        .execute(&mut connection)
+        .await?;
+    MIGRATOR.run(&mut connection).await?;
+    connection.close().await?;
+    info!(nb_migrations = MIGRATOR.iter().count(), "Database migrated");
+
+    Ok(())
+}
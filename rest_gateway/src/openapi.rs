@@ -0,0 +1,265 @@
+//! OpenAPI specification of the gateway, generated from the annotations of its controllers
+//!
+//! Served on `/openapi.json`, with a Swagger UI on `/swagger-ui/`.
+
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+
+use crate::{
+    controllers::{self, *},
+    domain::entities::{
+        answer::Citation,
+        api_key::ApiKeyScope,
+        ingestion_job::{IngestionErrorCode, IngestionLane, JobStatus, SkippedItem},
+        latency_summary::LatencySummary,
+        normalization_rule::NormalizationRuleKind,
+        provider_credentials::{ModelProvider, ProviderPurpose},
+        search_result::{SearchAggregations, SearchResult, SearchSource},
+        source_event::SourceEventType,
+        source_meta::SourceType,
+        source_summary::ChapterSummary,
+    },
+    middlewares::jwt_authentication::middleware::API_KEY_HEADER,
+};
+
+/// Route of the OpenAPI specification
+pub const OPENAPI_JSON_PATH: &str = "/openapi.json";
+
+/// OpenAPI specification of the routes of the gateway
+///
+/// A controller routed in `startup::run` should be listed in the paths, with the schemas of its DTOs.
+/// The NDJSON variants of `GET /sources` and `POST /search`, and the Server-Sent Events variant of `POST /ask`,
+/// are documented by their JSON controller,
+/// an operation being identified by its path and method.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Content ingestion service",
+        description = "Ingests source files, extracts their contents and searches them"
+    ),
+    paths(
+        controllers::health_check::health_check,
+        controllers::health_check::readiness,
+        controllers::add_source_files::add_source_files,
+        controllers::add_source_url::add_source_url,
+        controllers::uploads::start_upload,
+        controllers::uploads::get_upload,
+        controllers::uploads::abort_upload,
+        controllers::uploads::upload_part,
+        controllers::uploads::complete_upload,
+        controllers::search_content::search_content,
+        controllers::ask::ask,
+        controllers::search_history::list_search_history,
+        controllers::saved_searches::list_saved_searches,
+        controllers::saved_searches::save_search,
+        controllers::saved_searches::delete_saved_search,
+        controllers::saved_searches::run_saved_search,
+        controllers::list_sources::list_sources,
+        controllers::delete_source::delete_source,
+        controllers::delete_recrawl_schedule::delete_recrawl_schedule,
+        controllers::download_source::download_source,
+        controllers::get_source_chunks::get_source_chunks,
+        controllers::get_source_progress::get_source_progress,
+        controllers::get_source_summary::get_source_summary,
+        controllers::get_source_events::get_source_events,
+        controllers::auto_filing_rules::list_auto_filing_rules,
+        controllers::auto_filing_rules::create_auto_filing_rule,
+        controllers::auto_filing_rules::update_auto_filing_rule,
+        controllers::auto_filing_rules::delete_auto_filing_rule,
+        controllers::retention_rules::list_retention_rules,
+        controllers::retention_rules::save_retention_rule,
+        controllers::retention_rules::delete_retention_rule,
+        controllers::set_default_collection::set_default_collection,
+        controllers::get_job::get_job,
+        controllers::get_events::get_events,
+        controllers::provider_credentials::list_provider_credentials,
+        controllers::provider_credentials::save_provider_credentials,
+        controllers::provider_credentials::delete_provider_credentials,
+        controllers::api_keys::list_api_keys,
+        controllers::api_keys::create_api_key,
+        controllers::api_keys::delete_api_key,
+        controllers::get_metrics::get_metrics,
+        controllers::get_ingestion_slo::get_ingestion_slo,
+        controllers::promote_fulltext_standby::promote_fulltext_standby,
+        controllers::reindex_sources::reindex_sources,
+        controllers::import_sources::import_sources,
+        controllers::upload_policies::list_upload_policies,
+        controllers::upload_policies::save_upload_policy,
+        controllers::normalization_rules::list_normalization_rules,
+        controllers::normalization_rules::add_normalization_rule,
+        controllers::normalization_rules::delete_normalization_rule,
+        controllers::create_account::create_account,
+        controllers::log_in_account::log_in_account,
+        controllers::two_factor::enable_two_factor,
+        controllers::two_factor::verify_two_factor,
+        controllers::refresh_token::refresh_token,
+        controllers::log_out::log_out,
+    ),
+    components(schemas(
+        UploadForm,
+        Status,
+        AddSourceFileStatus,
+        AddSourceFilesResponse,
+        AddSourceUrlBodyData,
+        StartUploadBodyData,
+        UploadResponse,
+        UploadPartResponse,
+        CompleteUploadResponse,
+        SearchMode,
+        SearchSort,
+        SearchContentBodyData,
+        SearchResults,
+        SearchResult,
+        SearchSource,
+        SearchAggregations,
+        AskBodyData,
+        AskResponse,
+        Citation,
+        SearchHistoryEntryResponse,
+        ListSearchHistoryResponse,
+        SaveSearchBodyData,
+        SavedSearchResponse,
+        SourcesPage,
+        SourceResponse,
+        SourceType,
+        SourceProgressStatus,
+        DocumentSummaryResponse,
+        StorageUsageResponse,
+        DownloadSourceUrlResponse,
+        GetSourceChunksResponse,
+        SourceChunkResponse,
+        GetSourceProgressResponse,
+        GetSourceSummaryResponse,
+        ChapterSummary,
+        GetSourceEventsResponse,
+        SourceEventResponse,
+        SourceEventType,
+        AutoFilingRuleBodyData,
+        AutoFilingRuleResponse,
+        RetentionRuleBodyData,
+        RetentionRuleResponse,
+        SetDefaultCollectionBodyData,
+        GetJobResponse,
+        JobStatus,
+        IngestionLane,
+        IngestionErrorCode,
+        SkippedItem,
+        ProviderCredentialsBodyData,
+        ProviderCredentialsResponse,
+        ModelProvider,
+        ProviderPurpose,
+        CreateApiKeyBodyData,
+        ApiKeyResponse,
+        CreateApiKeyResponse,
+        ListApiKeysResponse,
+        ApiKeyScope,
+        GetIngestionSloResponse,
+        LatencySummary,
+        PromoteFulltextStandbyBodyData,
+        ReindexSourcesBodyData,
+        ReindexSourcesResponse,
+        ImportSourcesBodyData,
+        ImportSourcesResponse,
+        UploadPolicyBodyData,
+        UploadPolicyResponse,
+        NormalizationRuleBodyData,
+        NormalizationRuleResponse,
+        NormalizationRuleKind,
+        CreateAccountBodyData,
+        LogInAccountBodyData,
+        LogInAccountResponse,
+        EnableTwoFactorResponse,
+        VerifyTwoFactorBodyData,
+        VerifyTwoFactorResponse,
+        RefreshTokenBodyData,
+        RefreshTokenResponse,
+        LogOutBodyData,
+    )),
+    modifiers(&SecuritySchemes)
+)]
+pub struct ApiDoc;
+
+/// Declares the authentication schemes referenced by the `security` of the controllers
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+
+        components.add_security_scheme(
+            "access_token",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .description(Some("Access token returned by `/account/login`"))
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                API_KEY_HEADER,
+                "API key created on `/api_keys`, only accepted by the routes of its scopes",
+            ))),
+        );
+        components.add_security_scheme(
+            "admin_token",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("Admin token of the gateway configuration"))
+                    .build(),
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn security_schemes_of_the_controllers_are_declared() {
+        let openapi = ApiDoc::openapi();
+        let security_schemes = &openapi.components.as_ref().unwrap().security_schemes;
+
+        for (path, path_item) in &openapi.paths.paths {
+            for operation in path_item.operations.values() {
+                let value = serde_json::to_value(&operation.security).unwrap();
+                let names = value
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|requirement| requirement.as_object())
+                    .flat_map(|requirement| requirement.keys());
+
+                for name in names {
+                    assert!(
+                        security_schemes.contains_key(name),
+                        "{} uses the undeclared security scheme {}",
+                        path,
+                        name
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn schemas_referenced_by_the_controllers_are_declared() {
+        let spec = ApiDoc::openapi().to_json().unwrap();
+        let schemas = &ApiDoc::openapi().components.unwrap().schemas;
+
+        for reference in spec.split("\"#/components/schemas/").skip(1) {
+            let name = reference.split('"').next().unwrap();
+            assert!(
+                schemas.contains_key(name),
+                "The schema {} is referenced but not declared",
+                name
+            );
+        }
+    }
+}
@@ -0,0 +1,400 @@
+//! Operator commands of the `ops` binary: inspects the queues and the full-text search index,
+//! and triggers the reindexing of sources
+
+use common::{
+    core::{
+        rabbitmq_message_repository::{RabbitMQMessageRepository, RabbitMQMessageRepositoryError},
+        tenancy::TenantMessageRepositories,
+    },
+    helper::error_chain_fmt,
+};
+use std::{collections::HashMap, io::Write, sync::Arc};
+use uuid::Uuid;
+
+use crate::{
+    configuration::{RabbitMQSettings, Settings},
+    domain::entities::ingestion_job::JobStatus,
+    reindexing::{reindex_source, ReindexMode, ReindexingError},
+    repositories::{
+        ingestion_job_postgres_repository::{
+            IngestionJobPostgresRepository, IngestionJobPostgresRepositoryError,
+        },
+        meilisearch_admin_repository::{
+            MeilisearchAdminRepository, MeilisearchAdminRepositoryError,
+        },
+        rabbitmq_management_repository::{
+            RabbitMQManagementRepository, RabbitMQManagementRepositoryError,
+        },
+    },
+    startup::{get_connection_pool, get_tenant_rabbitmq_connection},
+};
+
+pub const USAGE: &str = "Usage: ops <command>
+
+Commands:
+  queues                                 Lists the queues of all the virtual hosts, with their depth
+  peek <queue> [count] [--vhost <vhost>] Reads the first messages of a queue (10 by default), and requeues them
+  tasks                                  Lists the enqueued and processing Meilisearch tasks
+  chunks [source_id]                     Counts the indexed contents of a source, or of the sources with the most contents
+  reindex <source_id>                    Deletes the contents of a source and extracts them again
+  backfill                               Reindexes the sources whose ingestion failed";
+
+const DEFAULT_PEEK_COUNT: u32 = 10;
+
+#[derive(Debug, PartialEq)]
+pub enum OpsCommand {
+    Queues,
+    Peek {
+        vhost: String,
+        queue_name: String,
+        count: u32,
+    },
+    Tasks,
+    Chunks {
+        source_meta_id: Option<Uuid>,
+    },
+    Reindex {
+        source_meta_id: Uuid,
+    },
+    Backfill,
+}
+
+impl OpsCommand {
+    /// Parses a command from the arguments of the binary, without the binary name
+    pub fn try_parse(args: &[String]) -> Result<Self, OpsError> {
+        let (command, args) = args
+            .split_first()
+            .ok_or_else(|| OpsError::InvalidArguments("Missing command".to_string()))?;
+
+        match (command.as_str(), args) {
+            ("queues", []) => Ok(Self::Queues),
+            ("peek", args) => Self::try_parse_peek(args),
+            ("tasks", []) => Ok(Self::Tasks),
+            ("chunks", []) => Ok(Self::Chunks {
+                source_meta_id: None,
+            }),
+            ("chunks", [source_meta_id]) => Ok(Self::Chunks {
+                source_meta_id: Some(parse_source_meta_id(source_meta_id)?),
+            }),
+            ("reindex", [source_meta_id]) => Ok(Self::Reindex {
+                source_meta_id: parse_source_meta_id(source_meta_id)?,
+            }),
+            ("backfill", []) => Ok(Self::Backfill),
+            (command, _) => Err(OpsError::InvalidArguments(format!(
+                "Unknown command or invalid arguments for: {}",
+                command
+            ))),
+        }
+    }
+
+    fn try_parse_peek(args: &[String]) -> Result<Self, OpsError> {
+        let mut vhost = "/".to_string();
+        let mut positional = vec![];
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--vhost" => {
+                    vhost = args
+                        .next()
+                        .ok_or_else(|| OpsError::InvalidArguments("Missing vhost".to_string()))?
+                        .clone();
+                }
+                _ => positional.push(arg),
+            }
+        }
+
+        match positional.as_slice() {
+            [queue_name] => Ok(Self::Peek {
+                vhost,
+                queue_name: queue_name.to_string(),
+                count: DEFAULT_PEEK_COUNT,
+            }),
+            [queue_name, count] => Ok(Self::Peek {
+                vhost,
+                queue_name: queue_name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| OpsError::InvalidArguments(format!("Invalid count: {}", count)))?,
+            }),
+            _ => Err(OpsError::InvalidArguments(
+                "peek expects a queue name and an optional count".to_string(),
+            )),
+        }
+    }
+}
+
+fn parse_source_meta_id(source_meta_id: &str) -> Result<Uuid, OpsError> {
+    Uuid::parse_str(source_meta_id)
+        .map_err(|_| OpsError::InvalidArguments(format!("Invalid source id: {}", source_meta_id)))
+}
+
+/// Runs a command, writing its report to `out`
+#[tracing::instrument(name = "Running ops command", skip(settings, out))]
+pub async fn run(
+    settings: &Settings,
+    command: OpsCommand,
+    out: &mut impl Write,
+) -> Result<(), OpsError> {
+    match command {
+        OpsCommand::Queues => {
+            let queues = rabbitmq_management_repository(settings)
+                .list_queues()
+                .await?;
+
+            writeln!(
+                out,
+                "{:<10} {:<60} {:>8} {:>8} {:>8} {:>9}",
+                "VHOST", "QUEUE", "MESSAGES", "READY", "UNACKED", "CONSUMERS"
+            )?;
+            for queue in queues {
+                writeln!(
+                    out,
+                    "{:<10} {:<60} {:>8} {:>8} {:>8} {:>9}",
+                    queue.vhost,
+                    queue.name,
+                    queue.messages,
+                    queue.messages_ready,
+                    queue.messages_unacknowledged,
+                    queue.consumers
+                )?;
+            }
+        }
+        OpsCommand::Peek {
+            vhost,
+            queue_name,
+            count,
+        } => {
+            let messages = rabbitmq_management_repository(settings)
+                .peek_messages(&vhost, &queue_name, count)
+                .await?;
+
+            for message in messages {
+                writeln!(
+                    out,
+                    "exchange={} routing_key={} redelivered={} encoding={}\n{}\n",
+                    message.exchange,
+                    message.routing_key,
+                    message.redelivered,
+                    message.payload_encoding,
+                    message.payload
+                )?;
+            }
+        }
+        OpsCommand::Tasks => {
+            let tasks = meilisearch_admin_repository(settings)
+                .list_pending_tasks()
+                .await?;
+
+            writeln!(out, "{} pending task(s)", tasks.len())?;
+            for task in tasks {
+                writeln!(
+                    out,
+                    "{:>8} {:<12} {:<24} {:<16} enqueued at {}",
+                    task.uid,
+                    task.status,
+                    task.task_type,
+                    task.index_uid.unwrap_or_default(),
+                    task.enqueued_at
+                )?;
+            }
+        }
+        OpsCommand::Chunks {
+            source_meta_id: Some(source_meta_id),
+        } => {
+            let count = meilisearch_admin_repository(settings)
+                .count_source_contents(source_meta_id)
+                .await?;
+
+            writeln!(out, "{} {}", source_meta_id, count)?;
+        }
+        OpsCommand::Chunks {
+            source_meta_id: None,
+        } => {
+            let counts = meilisearch_admin_repository(settings)
+                .count_contents_per_source()
+                .await?;
+
+            for (source_meta_id, count) in counts {
+                writeln!(out, "{} {}", source_meta_id, count)?;
+            }
+        }
+        OpsCommand::Reindex { source_meta_id } => {
+            let db_pool = get_connection_pool(&settings.database);
+            let message_repositories = get_message_repositories(&settings.rabbitmq).await?;
+
+            reindex_source(
+                &db_pool,
+                &message_repositories,
+                source_meta_id,
+                ReindexMode::WipeIndex,
+            )
+            .await?;
+            writeln!(out, "Reindexing {}", source_meta_id)?;
+        }
+        OpsCommand::Backfill => {
+            let db_pool = get_connection_pool(&settings.database);
+            let message_repositories = get_message_repositories(&settings.rabbitmq).await?;
+
+            let source_meta_ids = IngestionJobPostgresRepository::new()
+                .list_source_meta_ids_by_status(&db_pool, JobStatus::Failed)
+                .await?;
+
+            for source_meta_id in source_meta_ids {
+                reindex_source(
+                    &db_pool,
+                    &message_repositories,
+                    source_meta_id,
+                    ReindexMode::WipeIndex,
+                )
+                .await?;
+                writeln!(out, "Reindexing {}", source_meta_id)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn rabbitmq_management_repository(settings: &Settings) -> RabbitMQManagementRepository {
+    let management = &settings.ops.rabbitmq_management;
+
+    RabbitMQManagementRepository::new(
+        settings.rabbitmq.get_management_url(management),
+        management.username.clone(),
+        management.password.clone(),
+    )
+}
+
+fn meilisearch_admin_repository(settings: &Settings) -> MeilisearchAdminRepository {
+    let meilisearch = &settings.ops.meilisearch;
+
+    MeilisearchAdminRepository::new(
+        meilisearch.endpoint(),
+        meilisearch.api_key.clone(),
+        meilisearch.contents_index.clone(),
+    )
+}
+
+/// Initialized message repositories of all the tenants
+async fn get_message_repositories(
+    settings: &RabbitMQSettings,
+) -> Result<TenantMessageRepositories, OpsError> {
+    let default = RabbitMQMessageRepository::new(
+        Arc::new(get_tenant_rabbitmq_connection(settings, None).await?),
+        &settings.content_exchange_name(None),
+    )
+    .with_publisher_confirms(settings.publisher_confirms)
+    .with_rpc_settings(settings.rpc);
+
+    let mut tenants = HashMap::new();
+    for tenant in settings.tenants.iter() {
+        tenants.insert(
+            tenant.id.clone(),
+            RabbitMQMessageRepository::new(
+                Arc::new(get_tenant_rabbitmq_connection(settings, Some(tenant)).await?),
+                &settings.content_exchange_name(Some(tenant)),
+            )
+            .with_publisher_confirms(settings.publisher_confirms)
+            .with_rpc_settings(settings.rpc),
+        );
+    }
+
+    Ok(TenantMessageRepositories::new(default, tenants)
+        .try_init()
+        .await?)
+}
+
+#[derive(thiserror::Error)]
+pub enum OpsError {
+    #[error("{0}\n\n{USAGE}")]
+    InvalidArguments(String),
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+    #[error(transparent)]
+    RabbitMQError(#[from] lapin::Error),
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+    #[error(transparent)]
+    RabbitMQManagementRepositoryError(#[from] RabbitMQManagementRepositoryError),
+    #[error(transparent)]
+    MeilisearchAdminRepositoryError(#[from] MeilisearchAdminRepositoryError),
+    #[error(transparent)]
+    ReindexingError(#[from] ReindexingError),
+    #[error(transparent)]
+    IngestionJobRepositoryError(#[from] IngestionJobPostgresRepositoryError),
+}
+
+impl std::fmt::Debug for OpsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn peek_is_parsed_with_an_optional_count_and_vhost() {
+        assert_eq!(
+            OpsCommand::try_parse(&args(&["peek", "dead_letters"])).unwrap(),
+            OpsCommand::Peek {
+                vhost: "/".to_string(),
+                queue_name: "dead_letters".to_string(),
+                count: DEFAULT_PEEK_COUNT,
+            }
+        );
+        assert_eq!(
+            OpsCommand::try_parse(&args(&["peek", "--vhost", "finance", "dead_letters", "3"]))
+                .unwrap(),
+            OpsCommand::Peek {
+                vhost: "finance".to_string(),
+                queue_name: "dead_letters".to_string(),
+                count: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn source_commands_are_parsed_with_a_source_id() {
+        let source_meta_id = Uuid::new_v4();
+
+        assert_eq!(
+            OpsCommand::try_parse(&args(&["reindex", &source_meta_id.to_string()])).unwrap(),
+            OpsCommand::Reindex { source_meta_id }
+        );
+        assert_eq!(
+            OpsCommand::try_parse(&args(&["chunks"])).unwrap(),
+            OpsCommand::Chunks {
+                source_meta_id: None
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_arguments_are_rejected() {
+        for invalid_args in [
+            vec![],
+            args(&["unknown"]),
+            args(&["reindex"]),
+            args(&["reindex", "not-a-uuid"]),
+            args(&["peek"]),
+            args(&["peek", "queue", "many"]),
+            args(&["queues", "extra"]),
+        ] {
+            assert!(
+                matches!(
+                    OpsCommand::try_parse(&invalid_args),
+                    Err(OpsError::InvalidArguments(_))
+                ),
+                "{:?} should be rejected",
+                invalid_args
+            );
+        }
+    }
+}
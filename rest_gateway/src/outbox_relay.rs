@@ -0,0 +1,153 @@
+//! Relay of the outbox, publishing the messages their writer could not publish after their transaction
+
+use chrono::{DateTime, Duration, Utc};
+use common::{
+    core::{
+        rabbitmq_message_repository::RabbitMQMessageRepositoryError,
+        tenancy::{TenancyError, TenantMessageRepositories},
+    },
+    helper::error_chain_fmt,
+};
+use sqlx::PgPool;
+use tracing::{error, info};
+
+use crate::{
+    configuration::OutboxSettings,
+    domain::entities::outbox_message::OutboxMessage,
+    repositories::outbox_message_postgres_repository::{
+        OutboxMessagePostgresRepository, OutboxMessagePostgresRepositoryError,
+    },
+};
+
+/// Publishes the pending messages of the outbox
+///
+/// A message is written in the outbox in the same transaction as the changes it announces, and published by its
+/// writer once the transaction is committed. If the broker was unavailable, or the writer stopped before publishing it,
+/// the message is published by the relay once its next attempt is due.
+/// Each gateway instance runs a relay: a message claimed by another instance is skipped.
+pub struct OutboxRelay {
+    db_pool: PgPool,
+    message_repositories: TenantMessageRepositories,
+    settings: OutboxSettings,
+    outbox_message_repository: OutboxMessagePostgresRepository,
+}
+
+/// Messages handled by a relay
+#[derive(Debug, Default, PartialEq)]
+pub struct OutboxRelayReport {
+    pub nb_sent: usize,
+    pub nb_failed: usize,
+    pub nb_deleted: u64,
+}
+
+impl OutboxRelay {
+    /// # Params
+    /// - message_repositories: not initialized, the relay initializes its own repositories
+    pub fn new(
+        db_pool: PgPool,
+        message_repositories: TenantMessageRepositories,
+        settings: OutboxSettings,
+    ) -> Self {
+        Self {
+            db_pool,
+            message_repositories,
+            settings,
+            outbox_message_repository: OutboxMessagePostgresRepository::new(),
+        }
+    }
+
+    /// Relays the pending messages every `relay_interval_ms`, from now
+    pub async fn run(mut self) -> Result<(), OutboxRelayError> {
+        self.message_repositories = self.message_repositories.clone().try_init().await?;
+
+        let mut interval = tokio::time::interval(std::time::Duration::from_millis(
+            self.settings.relay_interval_ms,
+        ));
+
+        loop {
+            interval.tick().await;
+
+            match self.relay(Utc::now()).await {
+                Ok(report) if report == OutboxRelayReport::default() => {}
+                Ok(report) => info!(?report, "Relayed the pending outbox messages"),
+                Err(error) => error!(?error, "Failed to relay the pending outbox messages"),
+            }
+        }
+    }
+
+    /// Publishes the messages pending at a given time, and deletes the messages sent before the retention
+    ///
+    /// A message failing to be published does not stop the relay: it is published again after the retry delay.
+    #[tracing::instrument(name = "Relaying outbox messages", skip(self))]
+    pub async fn relay(&self, now: DateTime<Utc>) -> Result<OutboxRelayReport, OutboxRelayError> {
+        let mut report = OutboxRelayReport::default();
+
+        let messages = self
+            .outbox_message_repository
+            .claim_pending_messages(
+                &self.db_pool,
+                now,
+                self.settings.retry_delay_ms as i64,
+                self.settings.max_messages_per_relay.into(),
+            )
+            .await?;
+        for message in messages {
+            match self.publish(&message).await {
+                Ok(()) => {
+                    self.outbox_message_repository
+                        .set_sent(&self.db_pool, message.id, Utc::now())
+                        .await?;
+                    report.nb_sent += 1;
+                }
+                Err(error) => {
+                    error!(
+                        ?error,
+                        "Failed to publish the outbox message {} on {}",
+                        message.id,
+                        message.routing_key
+                    );
+                    self.outbox_message_repository
+                        .set_failed(&self.db_pool, message.id, &error.to_string())
+                        .await?;
+                    report.nb_failed += 1;
+                }
+            }
+        }
+
+        report.nb_deleted = self
+            .outbox_message_repository
+            .delete_sent_messages(
+                &self.db_pool,
+                now - Duration::hours(self.settings.sent_retention_h.into()),
+            )
+            .await?;
+
+        Ok(report)
+    }
+
+    /// Publishes a message on the exchange of its tenant
+    async fn publish(&self, message: &OutboxMessage) -> Result<(), OutboxRelayError> {
+        self.message_repositories
+            .route(message.tenant_id.as_deref())?
+            .publish(&message.routing_key, message.payload.as_bytes())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum OutboxRelayError {
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+    #[error(transparent)]
+    OutboxMessageRepositoryError(#[from] OutboxMessagePostgresRepositoryError),
+    #[error(transparent)]
+    TenancyError(#[from] TenancyError),
+}
+
+impl std::fmt::Debug for OutboxRelayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
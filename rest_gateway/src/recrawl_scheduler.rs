@@ -0,0 +1,399 @@
+//! Periodic re-crawls of the sources added from a URL, extracted again when their content changed
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use common::{
+    core::{
+        rabbitmq_message_repository::RabbitMQMessageRepositoryError,
+        secrets::{SecretsCipher, SecretsError},
+        tenancy::TenantMessageRepositories,
+    },
+    helper::error_chain_fmt,
+};
+use reqwest::header::HeaderMap;
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::io::Seek;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{
+    configuration::RecrawlSettings,
+    controllers::add_source_url::{parse_headers, AddSourceUrlError},
+    domain::entities::{
+        sniffed_content::SniffedContent, source_event::SourceEvent,
+        source_url_schedule::SourceUrlSchedule,
+    },
+    reindexing::{reindex_source, ReindexMode, ReindexingError},
+    repositories::{
+        source_event_postgres_repository::{
+            SourceEventPostgresRepository, SourceEventPostgresRepositoryError,
+        },
+        source_file_s3_repository::{S3Repository, S3RepositoryError},
+        source_meta_postgres_repository::{
+            SourceMetaPostgresRepository, SourceMetaPostgresRepositoryError,
+        },
+        source_url_repository::{SourceUrlRepository, SourceUrlRepositoryError},
+        source_url_schedule_postgres_repository::{
+            SourceUrlSchedulePostgresRepository, SourceUrlSchedulePostgresRepositoryError,
+        },
+        user_storage_usage_postgres_repository::{
+            UserStorageUsagePostgresRepository, UserStorageUsagePostgresRepositoryError,
+        },
+    },
+};
+
+/// Re-crawls the sources added from a URL on their schedule
+///
+/// Periodically downloads again the URLs of the due sources, and compares the hash of the downloaded content
+/// with the hash of the stored file. An unchanged source is left as is. A changed source has its stored file
+/// replaced, and is reindexed: its previous contents are deleted from the indexes, and the new file is extracted.
+/// Each gateway instance runs a scheduler: a schedule claimed by another instance is skipped.
+pub struct RecrawlScheduler {
+    db_pool: PgPool,
+    s3_repository: S3Repository,
+    source_url_repository: SourceUrlRepository,
+    secrets_cipher: SecretsCipher,
+    message_repositories: TenantMessageRepositories,
+    settings: RecrawlSettings,
+    storage_quota_bytes: Option<u64>,
+    source_url_schedule_repository: SourceUrlSchedulePostgresRepository,
+    source_meta_repository: SourceMetaPostgresRepository,
+    source_event_repository: SourceEventPostgresRepository,
+    user_storage_usage_repository: UserStorageUsagePostgresRepository,
+}
+
+/// Sources re-crawled by a scan
+#[derive(Debug, Default, PartialEq)]
+pub struct RecrawlScanReport {
+    pub nb_unchanged: usize,
+    pub nb_changed: usize,
+    pub nb_failed: usize,
+}
+
+impl RecrawlScheduler {
+    /// # Params
+    /// - message_repositories: not initialized, the scheduler initializes its own repositories
+    pub fn new(
+        db_pool: PgPool,
+        s3_repository: S3Repository,
+        source_url_repository: SourceUrlRepository,
+        secrets_cipher: SecretsCipher,
+        message_repositories: TenantMessageRepositories,
+        settings: RecrawlSettings,
+        storage_quota_bytes: Option<u64>,
+    ) -> Self {
+        Self {
+            db_pool,
+            s3_repository,
+            source_url_repository,
+            secrets_cipher,
+            message_repositories,
+            settings,
+            storage_quota_bytes,
+            source_url_schedule_repository: SourceUrlSchedulePostgresRepository::new(),
+            source_meta_repository: SourceMetaPostgresRepository::new(),
+            source_event_repository: SourceEventPostgresRepository::new(),
+            user_storage_usage_repository: UserStorageUsagePostgresRepository::new(),
+        }
+    }
+
+    /// Scans the due schedules every `scan_interval_ms`, from now
+    pub async fn run(mut self) -> Result<(), RecrawlSchedulerError> {
+        self.message_repositories = self.message_repositories.clone().try_init().await?;
+
+        let mut interval = tokio::time::interval(std::time::Duration::from_millis(
+            self.settings.scan_interval_ms,
+        ));
+
+        loop {
+            interval.tick().await;
+
+            match self.scan(Utc::now()).await {
+                Ok(report) => info!(?report, "Re-crawled the due sources"),
+                Err(error) => error!(?error, "Failed to re-crawl the due sources"),
+            }
+        }
+    }
+
+    /// Re-crawls the sources due at a given time
+    ///
+    /// A source failing to be re-crawled does not stop the scan: it is re-crawled again after its interval.
+    #[tracing::instrument(name = "Scanning source URL schedules", skip(self))]
+    pub async fn scan(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<RecrawlScanReport, RecrawlSchedulerError> {
+        let mut report = RecrawlScanReport::default();
+
+        let schedules = self
+            .source_url_schedule_repository
+            .claim_due_schedules(
+                &self.db_pool,
+                now,
+                self.settings.max_sources_per_scan.into(),
+            )
+            .await?;
+        for schedule in schedules {
+            match self.recrawl(&schedule).await {
+                Ok(true) => report.nb_changed += 1,
+                Ok(false) => report.nb_unchanged += 1,
+                Err(error) => {
+                    error!(
+                        ?error,
+                        "Failed to re-crawl the source {} from {}",
+                        schedule.source_meta_id,
+                        schedule.url
+                    );
+                    report.nb_failed += 1;
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Downloads the URL of a source again, and replaces and reindexes the source if its content changed
+    ///
+    /// # Returns
+    /// Whether the content of the source changed
+    #[tracing::instrument(
+        name = "Re-crawling source URL",
+        skip(self, schedule),
+        fields(source_meta_id = %schedule.source_meta_id, url = %schedule.url)
+    )]
+    pub async fn recrawl(&self, schedule: &SourceUrlSchedule) -> Result<bool, RecrawlError> {
+        let crawled_at = Utc::now();
+        let source_meta_id = schedule.source_meta_id;
+
+        let source_meta = self
+            .source_meta_repository
+            .get_source_meta(&self.db_pool, source_meta_id)
+            .await?
+            .ok_or(RecrawlError::SourceNotFound(source_meta_id))?;
+
+        let headers = self.decrypt_headers(schedule)?;
+        let mut file = tempfile::tempfile().context("Could not create a temporary file")?;
+        let downloaded_source = self
+            .source_url_repository
+            .download(&schedule.url, headers, &mut file)
+            .await?;
+
+        let content_hash = hash_file(&mut file).context("Could not hash the downloaded file")?;
+        if source_meta.content_hash.as_deref() == Some(content_hash.as_str()) {
+            info!("The content of source {} did not change", source_meta_id);
+            self.source_url_schedule_repository
+                .set_crawled(&self.db_pool, source_meta_id, crawled_at, false)
+                .await?;
+            return Ok(false);
+        }
+
+        // Another source of the user already has this content: the source is left as is
+        if let Some(duplicated_source_meta_id) = self
+            .source_meta_repository
+            .find_user_source_meta_id_by_content_hash(
+                &self.db_pool,
+                source_meta.user_id,
+                &content_hash,
+            )
+            .await?
+        {
+            return Err(RecrawlError::DuplicatedContent(duplicated_source_meta_id));
+        }
+
+        let sniffed_content = SniffedContent::sniff_file(&mut file)
+            .context("Could not detect the content of the downloaded file")?;
+        if !sniffed_content.matches(&source_meta.source_type) {
+            return Err(RecrawlError::ContentMismatch(
+                sniffed_content.mime_type().to_string(),
+            ));
+        }
+
+        file.rewind()
+            .context("Could not read the downloaded file from its start")?;
+        let (object_name, object_path_name, content_hash) = self
+            .s3_repository
+            .save_file(&source_meta.user_id.to_string(), &mut file)
+            .await?;
+
+        let mut transaction = self.db_pool.begin().await?;
+
+        // The new file replaces the previous one in the storage used by the user
+        if let Some(previous_size_bytes) = source_meta.size_bytes {
+            self.user_storage_usage_repository
+                .remove_usage(
+                    &mut transaction,
+                    source_meta.user_id,
+                    previous_size_bytes as u64,
+                )
+                .await?;
+        }
+        if !self
+            .user_storage_usage_repository
+            .try_add_usage(
+                &mut transaction,
+                source_meta.user_id,
+                downloaded_source.size_bytes,
+                self.storage_quota_bytes,
+            )
+            .await?
+        {
+            transaction.rollback().await?;
+            self.s3_repository.remove_file(&object_path_name).await?;
+            return Err(RecrawlError::StorageQuotaExceeded(
+                downloaded_source.size_bytes,
+            ));
+        }
+
+        self.source_meta_repository
+            .replace_source_file(
+                &mut transaction,
+                source_meta_id,
+                &object_name,
+                &content_hash,
+                sniffed_content.mime_type(),
+                downloaded_source.size_bytes,
+            )
+            .await?;
+        self.source_event_repository
+            .add_event(
+                &mut transaction,
+                &SourceEvent::recrawled(&source_meta, &content_hash, crawled_at),
+            )
+            .await?;
+        self.source_url_schedule_repository
+            .set_crawled(&mut transaction, source_meta_id, crawled_at, true)
+            .await?;
+
+        transaction.commit().await?;
+
+        let previous_object_path_name =
+            format!("{}/{}", source_meta.user_id, source_meta.object_store_name);
+        if let Err(error) = self
+            .s3_repository
+            .remove_file(&previous_object_path_name)
+            .await
+        {
+            error!(
+                ?error,
+                "The replaced object {} could not be removed from the object storage",
+                previous_object_path_name
+            );
+        }
+
+        info!(
+            "The content of source {} changed, extracting it again",
+            source_meta_id
+        );
+        // Only the contents changed since the previous crawl are indexed again
+        reindex_source(
+            &self.db_pool,
+            &self.message_repositories,
+            source_meta_id,
+            ReindexMode::Incremental,
+        )
+        .await?;
+
+        Ok(true)
+    }
+
+    fn decrypt_headers(&self, schedule: &SourceUrlSchedule) -> Result<HeaderMap, RecrawlError> {
+        let Some(encrypted_headers) = &schedule.encrypted_headers else {
+            return Ok(HeaderMap::new());
+        };
+
+        let headers = self.secrets_cipher.decrypt(encrypted_headers)?;
+        let headers: HashMap<String, String> = serde_json::from_str(headers.expose_secret())
+            .context("Could not parse the headers of the schedule")?;
+
+        Ok(parse_headers(headers)?)
+    }
+}
+
+/// Hex-encoded SHA-256 hash of a file, as computed when storing it
+fn hash_file(file: &mut std::fs::File) -> Result<String, std::io::Error> {
+    file.rewind()?;
+    let mut hasher = Sha256::new();
+    std::io::copy(file, &mut hasher)?;
+    file.rewind()?;
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[derive(thiserror::Error)]
+pub enum RecrawlSchedulerError {
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+    #[error(transparent)]
+    SourceUrlScheduleRepositoryError(#[from] SourceUrlSchedulePostgresRepositoryError),
+}
+
+impl std::fmt::Debug for RecrawlSchedulerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum RecrawlError {
+    #[error("Source {0} not found")]
+    SourceNotFound(Uuid),
+    #[error("The source could not be downloaded")]
+    DownloadError(#[from] SourceUrlRepositoryError),
+    #[error("The headers of the schedule could not be decrypted")]
+    SecretsError(#[from] SecretsError),
+    #[error("Invalid headers")]
+    InvalidHeaders(#[from] AddSourceUrlError),
+    #[error("The new content is already uploaded as source {0}")]
+    DuplicatedContent(Uuid),
+    #[error("The new content is detected as {0}, not matching the type of the source")]
+    ContentMismatch(String),
+    #[error("The {0} downloaded bytes exceed the storage quota of the user")]
+    StorageQuotaExceeded(u64),
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+    #[error(transparent)]
+    S3RepositoryError(#[from] S3RepositoryError),
+    #[error(transparent)]
+    SourceMetaRepositoryError(#[from] SourceMetaPostgresRepositoryError),
+    #[error(transparent)]
+    SourceEventRepositoryError(#[from] SourceEventPostgresRepositoryError),
+    #[error(transparent)]
+    SourceUrlScheduleRepositoryError(#[from] SourceUrlSchedulePostgresRepositoryError),
+    #[error(transparent)]
+    UserStorageUsageRepositoryError(#[from] UserStorageUsagePostgresRepositoryError),
+    #[error("The changed source could not be reindexed")]
+    ReindexingError(#[from] ReindexingError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for RecrawlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn file_is_hashed_from_its_start() {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(b"abc").unwrap();
+
+        assert_eq!(
+            hash_file(&mut file).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        // Read again from its start, for ex to be stored
+        assert_eq!(
+            hash_file(&mut file).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
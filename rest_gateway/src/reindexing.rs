@@ -0,0 +1,199 @@
+//! Reindexing of sources, after a change of the chunking or embedding parameters for ex
+
+use common::{
+    constants::routing_keys::{DELETE_CONTENT_ROUTING_KEY, EXTRACT_CONTENT_TEXT_ROUTING_KEY},
+    core::{
+        rabbitmq_message_repository::RabbitMQMessageRepositoryError,
+        tenancy::{TenancyError, TenantMessageRepositories},
+    },
+    dtos::{
+        delete_content::DeleteContentDto,
+        extract_content_job::{
+            ChunkSplittingDto, ColumnMappingDto, ExtractContentJobDto, IngestionLaneDto,
+        },
+        templates::message_envelope::{MessageEnvelope, MessageEnvelopeError},
+    },
+    helper::error_chain_fmt,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    domain::entities::{ingestion_job::IngestionJob, source_event::SourceEvent},
+    repositories::{
+        document_postgres_repository::{
+            DocumentPostgresRepository, DocumentPostgresRepositoryError,
+        },
+        fulltext_shard_postgres_repository::{
+            FulltextShardPostgresRepository, FulltextShardPostgresRepositoryError,
+        },
+        ingestion_job_postgres_repository::{
+            IngestionJobPostgresRepository, IngestionJobPostgresRepositoryError,
+        },
+        source_event_postgres_repository::{
+            SourceEventPostgresRepository, SourceEventPostgresRepositoryError,
+        },
+        source_meta_postgres_repository::{
+            SourceMetaPostgresRepository, SourceMetaPostgresRepositoryError,
+        },
+        user_postgres_repository::{UserPostgresRepository, UserPostgresRepositoryError},
+    },
+};
+
+/// What happens to the indexed contents of the previous extraction of a reindexed source
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReindexMode {
+    /// The contents of the previous extraction stay indexed alongside the new ones
+    Append,
+    /// The contents of the previous extraction are deleted first
+    WipeIndex,
+    /// Only the contents changed since the previous extraction are indexed again,
+    /// the ones no longer extracted being pruned by the worker
+    Incremental,
+}
+
+impl ReindexMode {
+    /// Mode of a reindexing requested with or without the wiping of the index
+    pub fn from_wipe_index(wipe_index: bool) -> Self {
+        if wipe_index {
+            Self::WipeIndex
+        } else {
+            Self::Append
+        }
+    }
+}
+
+/// Publishes a new extraction job for a source, optionally deleting its indexed contents first
+///
+/// The deletion and the extraction are handled by different queues: the contents of the new extraction
+/// are only safe from the deletion because the extraction takes longer.
+#[tracing::instrument(name = "Reindexing source", skip(db_pool, message_repositories))]
+pub async fn reindex_source(
+    db_pool: &PgPool,
+    message_repositories: &TenantMessageRepositories,
+    source_meta_id: Uuid,
+    mode: ReindexMode,
+) -> Result<(), ReindexingError> {
+    let ingestion_job_repository = IngestionJobPostgresRepository::new();
+
+    let source_meta = SourceMetaPostgresRepository::new()
+        .get_source_meta(db_pool, source_meta_id)
+        .await?
+        .ok_or(ReindexingError::SourceNotFound(source_meta_id))?;
+    let tenant_id = UserPostgresRepository::new()
+        .get_user_tenant_id(db_pool, source_meta.user_id)
+        .await?;
+    let message_repository = message_repositories.route(tenant_id.as_deref())?;
+    // The contents are indexed again in the same shard
+    let fulltext_shard = FulltextShardPostgresRepository::new()
+        .get_source_shard(db_pool, source_meta_id)
+        .await?;
+
+    let mut transaction = db_pool.begin().await?;
+
+    // Sources uploaded before the ingestion jobs were tracked have no job
+    let job = match ingestion_job_repository
+        .get_job_by_source_meta_id_for_update(&mut transaction, source_meta_id)
+        .await?
+    {
+        Some(mut job) => {
+            job.restart();
+            ingestion_job_repository
+                .save_job_status(&mut transaction, &job)
+                .await?;
+            job
+        }
+        None => {
+            let job = IngestionJob::new(source_meta_id);
+            ingestion_job_repository
+                .add_job(&mut transaction, &job)
+                .await?;
+            job
+        }
+    };
+    SourceEventPostgresRepository::new()
+        .add_event(
+            &mut transaction,
+            &SourceEvent::reindexed(&source_meta, &job),
+        )
+        .await?;
+    // Summarized again from the contents of the new extraction
+    DocumentPostgresRepository::new()
+        .delete_document(&mut transaction, source_meta_id)
+        .await?;
+
+    if mode == ReindexMode::WipeIndex {
+        let json_message = serde_json::to_string(&DeleteContentDto {
+            source_meta_id,
+            fulltext_shard,
+        })?;
+        message_repository
+            .publish(DELETE_CONTENT_ROUTING_KEY, json_message.as_bytes())
+            .await?;
+    }
+
+    let json_job = MessageEnvelope::new(ExtractContentJobDto {
+        source_meta_id,
+        object_store_path_name: format!(
+            "{}/{}",
+            source_meta.user_id, source_meta.object_store_name
+        ),
+        source_type: source_meta.source_type.into(),
+        source_initial_name: source_meta.initial_name,
+        user_id: Some(source_meta.user_id),
+        tenant_id,
+        fulltext_shard,
+        content_hash: source_meta.content_hash,
+        // Restarted jobs go through the bulk lane
+        lane: IngestionLaneDto::Bulk,
+        chunk_splitting: ChunkSplittingDto::default(),
+        column_mapping: ColumnMappingDto {
+            content_columns: source_meta.content_columns,
+        },
+        incremental: mode == ReindexMode::Incremental,
+        source_added_at: Some(source_meta.added_at),
+        redact_pii: source_meta.redact_pii,
+    })
+    .try_serializing()?;
+    message_repository
+        .publish(EXTRACT_CONTENT_TEXT_ROUTING_KEY, json_job.as_bytes())
+        .await?;
+
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+#[derive(thiserror::Error)]
+pub enum ReindexingError {
+    #[error("Source {0} not found")]
+    SourceNotFound(Uuid),
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+    #[error(transparent)]
+    TenancyError(#[from] TenancyError),
+    #[error(transparent)]
+    SourceMetaRepositoryError(#[from] SourceMetaPostgresRepositoryError),
+    #[error(transparent)]
+    IngestionJobRepositoryError(#[from] IngestionJobPostgresRepositoryError),
+    #[error(transparent)]
+    UserRepositoryError(#[from] UserPostgresRepositoryError),
+    #[error(transparent)]
+    FulltextShardRepositoryError(#[from] FulltextShardPostgresRepositoryError),
+    #[error(transparent)]
+    SourceEventRepositoryError(#[from] SourceEventPostgresRepositoryError),
+    #[error(transparent)]
+    DocumentRepositoryError(#[from] DocumentPostgresRepositoryError),
+    #[error("Error while serializing message data: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error(transparent)]
+    MessageEnvelopeError(#[from] MessageEnvelopeError),
+}
+
+impl std::fmt::Debug for ReindexingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
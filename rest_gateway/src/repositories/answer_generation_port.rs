@@ -0,0 +1,61 @@
+use common::helper::error_chain_fmt;
+use futures::{future::LocalBoxFuture, stream::LocalBoxStream};
+
+use crate::domain::entities::answer::AnswerPrompt;
+
+/// Generates the answers of the questions of the users, from the chunks retrieved for them
+///
+/// Port to decouple the answer endpoint from the LLM backend of a deployment.
+/// Without backend, the questions are not answered.
+pub trait AnswerGenerationPort: Send + Sync {
+    /// Whether an LLM backend is configured
+    fn is_enabled(&self) -> bool;
+
+    /// Generates a whole answer
+    fn generate<'a>(
+        &'a self,
+        prompt: &'a AnswerPrompt,
+    ) -> LocalBoxFuture<'a, Result<GeneratedAnswer, AnswerGenerationError>>;
+
+    /// Generates an answer token by token, each one being sent as soon as it is generated
+    fn generate_stream(
+        &self,
+        prompt: &AnswerPrompt,
+    ) -> LocalBoxStream<'static, Result<AnswerChunk, AnswerGenerationError>>;
+}
+
+/// Answer generated by an LLM backend
+#[derive(Debug, PartialEq)]
+pub struct GeneratedAnswer {
+    pub text: String,
+    /// Tokens of the prompt and of the answer billed by the backend, 0 if it does not report them
+    pub nb_tokens: u64,
+}
+
+/// Part of a streamed answer
+#[derive(Debug, PartialEq)]
+pub enum AnswerChunk {
+    Token(String),
+    /// Tokens of the prompt and of the answer billed by the backend, reported at the end of the stream if at all
+    Usage {
+        nb_tokens: u64,
+    },
+}
+
+#[derive(thiserror::Error)]
+pub enum AnswerGenerationError {
+    #[error("No LLM backend is configured")]
+    Disabled,
+    #[error("The LLM backend could not be reached: {0}")]
+    Unreachable(reqwest::Error),
+    #[error("Unexpected response status from the LLM backend: {0}")]
+    UnexpectedStatus(reqwest::StatusCode),
+    #[error("Invalid response from the LLM backend: {0}")]
+    InvalidResponse(String),
+}
+
+impl std::fmt::Debug for AnswerGenerationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,193 @@
+use chrono::{DateTime, Utc};
+use common::helper::error_chain_fmt;
+use common::pagination::{PageCursor, PageDirection};
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::domain::entities::api_key::{ApiKey, ApiKeyScope};
+
+/// API key repository implemented using Postgres
+pub struct ApiKeyPostgresRepository {}
+
+/// Owner and scopes of an API key used to authenticate a request
+#[derive(Debug, Clone)]
+pub struct AuthenticatedApiKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub scopes: Vec<ApiKeyScope>,
+}
+
+/// Sort key of the listed API keys, encoded in the page cursors
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiKeyCursor {
+    #[serde(with = "chrono::serde::ts_microseconds")]
+    pub created_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl Default for ApiKeyPostgresRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ApiKeyPostgresRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    #[tracing::instrument(
+        name = "Saving new API key in database",
+        skip(self, db_executor, api_key),
+        fields(api_key_id = %api_key.id)
+    )]
+    pub async fn add_api_key(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        api_key: &ApiKey,
+    ) -> Result<(), ApiKeyPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    INSERT INTO api_keys (id, user_id, name, key_hash, key_prefix, scopes, last_used_at, created_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            api_key.id,
+            api_key.user_id,
+            api_key.name,
+            api_key.key_hash,
+            api_key.key_prefix,
+            &api_key.scopes as &[ApiKeyScope],
+            api_key.last_used_at,
+            api_key.created_at
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Lists the API keys of a user, from the most recent
+    ///
+    /// # Arguments
+    /// * `cursor` - Lists the API keys created before the cursor, or after it backwards
+    ///   (from the oldest) when listing the previous page
+    /// * `limit` - Maximum number of listed API keys
+    #[tracing::instrument(name = "Listing user API keys from database", skip(self, db_executor))]
+    pub async fn list_user_api_keys(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        cursor: Option<&PageCursor<ApiKeyCursor>>,
+        limit: i64,
+    ) -> Result<Vec<ApiKey>, ApiKeyPostgresRepositoryError> {
+        let api_keys = match cursor {
+            Some(PageCursor {
+                direction: PageDirection::Previous,
+                key: before,
+            }) => {
+                sqlx::query_as!(
+                    ApiKey,
+                    r#"
+    SELECT id, user_id, name, key_hash, key_prefix, scopes AS "scopes: Vec<ApiKeyScope>",
+        last_used_at, created_at
+    FROM api_keys
+    WHERE user_id = $1 AND (created_at, id) > ($2, $3)
+    ORDER BY created_at, id
+    LIMIT $4
+                    "#,
+                    user_id,
+                    before.created_at,
+                    before.id,
+                    limit,
+                )
+                .fetch_all(db_executor)
+                .await?
+            }
+            after => {
+                let after = after.map(|cursor| &cursor.key);
+
+                sqlx::query_as!(
+                    ApiKey,
+                    r#"
+    SELECT id, user_id, name, key_hash, key_prefix, scopes AS "scopes: Vec<ApiKeyScope>",
+        last_used_at, created_at
+    FROM api_keys
+    WHERE user_id = $1 AND ($2::timestamptz IS NULL OR (created_at, id) < ($2, $3))
+    ORDER BY created_at DESC, id DESC
+    LIMIT $4
+                    "#,
+                    user_id,
+                    after.map(|cursor| cursor.created_at),
+                    after.map(|cursor| cursor.id),
+                    limit,
+                )
+                .fetch_all(db_executor)
+                .await?
+            }
+        };
+
+        Ok(api_keys)
+    }
+
+    /// Finds the API key of a hash, marking it as used
+    #[tracing::instrument(name = "Authenticating API key from database", skip_all)]
+    pub async fn authenticate(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        key_hash: &str,
+    ) -> Result<Option<AuthenticatedApiKey>, ApiKeyPostgresRepositoryError> {
+        let api_key = sqlx::query_as!(
+            AuthenticatedApiKey,
+            r#"
+    UPDATE api_keys
+    SET last_used_at = $2
+    WHERE key_hash = $1
+    RETURNING id, user_id, scopes AS "scopes: Vec<ApiKeyScope>"
+            "#,
+            key_hash,
+            Utc::now(),
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(api_key)
+    }
+
+    /// Deletes an API key of a user: the key is immediately rejected
+    ///
+    /// # Returns
+    /// `false` if the user has no such key
+    #[tracing::instrument(name = "Deleting user API key from database", skip(self, db_executor))]
+    pub async fn delete_user_api_key(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        api_key_id: Uuid,
+    ) -> Result<bool, ApiKeyPostgresRepositoryError> {
+        let result = sqlx::query!(
+            r#"
+    DELETE FROM api_keys
+    WHERE id = $1 AND user_id = $2
+            "#,
+            api_key_id,
+            user_id,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum ApiKeyPostgresRepositoryError {
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for ApiKeyPostgresRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
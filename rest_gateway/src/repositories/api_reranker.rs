@@ -0,0 +1,204 @@
+use futures::future::LocalBoxFuture;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::info;
+
+use crate::{
+    configuration::RerankSettings,
+    repositories::rerank_port::{RerankError, RerankPort},
+};
+
+/// Reranks the candidates with a cross-encoder served by a rerank API
+///
+/// The API of Cohere is also exposed by Jina, Voyage, and by the self-hosted servers, for ex vLLM or Infinity.
+pub struct ApiReranker {
+    client: reqwest::Client,
+    api_url: String,
+    api_key: Option<Secret<String>>,
+    model: String,
+    candidates: usize,
+}
+
+#[derive(Serialize)]
+struct RerankRequest<'a> {
+    model: &'a str,
+    query: &'a str,
+    documents: &'a [&'a str],
+}
+
+#[derive(Deserialize)]
+struct RerankResponse {
+    results: Vec<RerankResult>,
+}
+
+#[derive(Deserialize)]
+struct RerankResult {
+    /// Index of the document in the request
+    index: usize,
+    relevance_score: f64,
+}
+
+impl ApiReranker {
+    pub fn try_new(settings: &RerankSettings) -> Result<Self, reqwest::Error> {
+        info!(
+            "Reranking the searches with the model {} on {}",
+            settings.model, settings.api_url
+        );
+
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(settings.timeout_s))
+                .build()?,
+            api_url: settings.api_url.clone(),
+            api_key: settings.api_key.clone(),
+            model: settings.model.clone(),
+            candidates: settings.candidates,
+        })
+    }
+}
+
+impl RerankPort for ApiReranker {
+    fn is_enabled(&self) -> bool {
+        true
+    }
+
+    fn candidates(&self) -> usize {
+        self.candidates
+    }
+
+    fn rerank<'a>(
+        &'a self,
+        query: &'a str,
+        documents: &'a [&'a str],
+    ) -> LocalBoxFuture<'a, Result<Vec<f64>, RerankError>> {
+        Box::pin(async move {
+            let request =
+                self.client
+                    .post(format!("{}/rerank", self.api_url))
+                    .json(&RerankRequest {
+                        model: &self.model,
+                        query,
+                        documents,
+                    });
+            // The self-hosted servers may not require any key
+            let request = match &self.api_key {
+                Some(api_key) => request.bearer_auth(api_key.expose_secret()),
+                None => request,
+            };
+
+            let response = request.send().await.map_err(RerankError::Unreachable)?;
+            if !response.status().is_success() {
+                return Err(RerankError::UnexpectedStatus(response.status()));
+            }
+            let response: RerankResponse = response
+                .json()
+                .await
+                .map_err(|error| RerankError::InvalidResponse(error.to_string()))?;
+
+            // The results are sorted by score: they are put back in the order of the documents
+            let mut scores: Vec<Option<f64>> = vec![None; documents.len()];
+            for result in response.results {
+                match scores.get_mut(result.index) {
+                    Some(score) => *score = Some(result.relevance_score),
+                    None => {
+                        return Err(RerankError::InvalidResponse(format!(
+                            "unknown document index {}",
+                            result.index
+                        )))
+                    }
+                }
+            }
+
+            scores
+                .into_iter()
+                .collect::<Option<Vec<f64>>>()
+                .ok_or_else(|| RerankError::InvalidResponse("a document was not scored".into()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Serves a single request with a given JSON body, returning the base URL and the received request body
+    fn serve_once(body: &'static str) -> (String, std::thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut request_body = vec![0; content_length];
+            reader.read_exact(&mut request_body).unwrap();
+
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+
+            String::from_utf8(request_body).unwrap()
+        });
+
+        (url, handle)
+    }
+
+    fn reranker(url: &str) -> ApiReranker {
+        ApiReranker::try_new(&RerankSettings {
+            enabled: true,
+            api_url: url.to_string(),
+            api_key: None,
+            model: "rerank-v3.5".to_string(),
+            timeout_s: 5,
+            candidates: 50,
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn scores_are_returned_in_the_order_of_the_documents() {
+        let (url, handle) = serve_once(
+            r#"{"results":[{"index":1,"relevance_score":0.9},{"index":0,"relevance_score":0.2}]}"#,
+        );
+
+        let scores = reranker(&url)
+            .rerank("What is a pod?", &["Nodes", "Pods"])
+            .await
+            .unwrap();
+
+        assert_eq!(scores, vec![0.2, 0.9]);
+        let request: serde_json::Value = serde_json::from_str(&handle.join().unwrap()).unwrap();
+        assert_eq!(request["model"], "rerank-v3.5");
+        assert_eq!(request["documents"], serde_json::json!(["Nodes", "Pods"]));
+    }
+
+    #[tokio::test]
+    async fn unscored_documents_are_reported() {
+        let (url, _) = serve_once(r#"{"results":[{"index":0,"relevance_score":0.2}]}"#);
+
+        let result = reranker(&url)
+            .rerank("What is a pod?", &["Nodes", "Pods"])
+            .await;
+
+        assert!(matches!(result, Err(RerankError::InvalidResponse(_))));
+    }
+}
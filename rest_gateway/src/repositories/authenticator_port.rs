@@ -0,0 +1,119 @@
+use actix_web::{
+    http::{header::HeaderMap, StatusCode},
+    ResponseError,
+};
+use common::helper::error_chain_fmt;
+use futures::future::LocalBoxFuture;
+use uuid::Uuid;
+
+/// Authenticates the users of the requests, from their headers
+///
+/// Port to decouple the authentication middleware from the identity provider of a deployment:
+/// the access tokens issued by the gateway (JWT), an OIDC provider, client certificates (mTLS) or a static token.
+/// The API keys are checked by the middleware, whatever the authenticator.
+pub trait AuthenticatorPort: Send + Sync {
+    /// # Returns
+    /// The id of the authenticated user
+    fn authenticate(
+        &self,
+        headers: &HeaderMap,
+    ) -> LocalBoxFuture<'_, Result<Uuid, AuthenticationError>>;
+
+    /// Whether the headers carry credentials, valid or not, without checking them
+    ///
+    /// The requests without credentials are anonymous, shed first by the request quotas.
+    fn has_credentials(&self, headers: &HeaderMap) -> bool {
+        bearer_token(headers).is_some()
+    }
+
+    /// Whether the authenticated users have an account of the gateway, ie a row of `users`
+    ///
+    /// The identities of an external provider have no account: the endpoints of the accounts are rejected for them.
+    fn has_local_accounts(&self) -> bool {
+        false
+    }
+}
+
+/// Bearer token of the `Authorization` header, if any
+pub fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(actix_web::http::header::AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .map(str::to_string)
+}
+
+/// Id of the user of an identity from an external identity provider
+///
+/// The subjects which are not a uuid get a stable id derived from the provider and the subject.
+/// The id has no row in `users`: see [`AuthenticatorPort::has_local_accounts`].
+pub fn external_user_id(provider: &str, subject: &str) -> Uuid {
+    Uuid::parse_str(subject).unwrap_or_else(|_| {
+        Uuid::new_v5(
+            &Uuid::NAMESPACE_URL,
+            format!("{}:{}", provider, subject).as_bytes(),
+        )
+    })
+}
+
+#[derive(thiserror::Error)]
+pub enum AuthenticationError {
+    #[error("No access token was provided")]
+    MissingCredentials,
+    #[error("Invalid credentials: {0}")]
+    InvalidCredentials(String),
+    #[error("The session of the access token was revoked")]
+    RevokedSession,
+    #[error("Authentication backend error: {0}")]
+    BackendError(String),
+}
+
+impl std::fmt::Debug for AuthenticationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for AuthenticationError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AuthenticationError::MissingCredentials
+            | AuthenticationError::InvalidCredentials(_)
+            | AuthenticationError::RevokedSession => StatusCode::UNAUTHORIZED,
+            AuthenticationError::BackendError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::{HeaderValue, AUTHORIZATION};
+
+    #[test]
+    fn bearer_token_is_read_from_the_authorization_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(bearer_token(&headers), None);
+
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Basic abc"));
+        assert_eq!(bearer_token(&headers), None);
+
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer abc"));
+        assert_eq!(bearer_token(&headers), Some("abc".to_string()));
+    }
+
+    #[test]
+    fn external_user_id_is_stable_for_a_subject() {
+        let user_id = Uuid::new_v4();
+
+        assert_eq!(external_user_id("oidc", &user_id.to_string()), user_id);
+        assert_eq!(
+            external_user_id("oidc", "alice"),
+            external_user_id("oidc", "alice")
+        );
+        assert_ne!(
+            external_user_id("oidc", "alice"),
+            external_user_id("mtls", "alice")
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use chrono::Utc;
+use common::helper::error_chain_fmt;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::domain::entities::auto_filing_rule::AutoFilingRule;
+
+/// Auto-filing rule repository implemented using Postgres
+pub struct AutoFilingRulePostgresRepository {}
+
+impl Default for AutoFilingRulePostgresRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AutoFilingRulePostgresRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    #[tracing::instrument(
+        name = "Saving new auto-filing rule in database",
+        skip(self, db_executor)
+    )]
+    pub async fn add_rule(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        rule: &AutoFilingRule,
+    ) -> Result<(), AutoFilingRulePostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    INSERT INTO auto_filing_rules (id, user_id, position, file_name_pattern, mime_type, tag, collection, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+            rule.id,
+            rule.user_id,
+            rule.position,
+            rule.file_name_pattern,
+            rule.mime_type,
+            rule.tag,
+            rule.collection,
+            rule.created_at,
+            rule.updated_at
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Lists the rules of a user, in the order they are evaluated
+    #[tracing::instrument(
+        name = "Listing user auto-filing rules in database",
+        skip(self, db_executor)
+    )]
+    pub async fn list_user_rules(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+    ) -> Result<Vec<AutoFilingRule>, AutoFilingRulePostgresRepositoryError> {
+        let rules = sqlx::query_as!(
+            AutoFilingRule,
+            r#"
+    SELECT id, user_id, position, file_name_pattern, mime_type, tag, collection, created_at, updated_at
+    FROM auto_filing_rules
+    WHERE user_id = $1
+    ORDER BY position, created_at
+            "#,
+            user_id,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(rules)
+    }
+
+    /// Updates the conditions, position and collection of a rule of a user
+    ///
+    /// # Returns
+    /// False if the user has no such rule
+    #[tracing::instrument(
+        name = "Updating user auto-filing rule in database",
+        skip(self, db_executor)
+    )]
+    pub async fn update_user_rule(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        rule: &AutoFilingRule,
+    ) -> Result<bool, AutoFilingRulePostgresRepositoryError> {
+        let result = sqlx::query!(
+            r#"
+    UPDATE auto_filing_rules
+    SET position = $3, file_name_pattern = $4, mime_type = $5, tag = $6, collection = $7, updated_at = $8
+    WHERE id = $1 AND user_id = $2
+            "#,
+            rule.id,
+            rule.user_id,
+            rule.position,
+            rule.file_name_pattern,
+            rule.mime_type,
+            rule.tag,
+            rule.collection,
+            Utc::now()
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Deletes a rule of a user. The sources it filed stay in their collection.
+    ///
+    /// # Returns
+    /// False if the user has no such rule
+    #[tracing::instrument(
+        name = "Deleting user auto-filing rule in database",
+        skip(self, db_executor)
+    )]
+    pub async fn delete_user_rule(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        rule_id: Uuid,
+    ) -> Result<bool, AutoFilingRulePostgresRepositoryError> {
+        let result = sqlx::query!(
+            r#"
+    DELETE FROM auto_filing_rules
+    WHERE id = $1 AND user_id = $2
+            "#,
+            rule_id,
+            user_id,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum AutoFilingRulePostgresRepositoryError {
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for AutoFilingRulePostgresRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
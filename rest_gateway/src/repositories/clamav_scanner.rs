@@ -0,0 +1,161 @@
+use futures::future::LocalBoxFuture;
+use std::{
+    io::{Read, Seek},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+use tracing::info;
+
+use crate::{
+    configuration::ClamavSettings,
+    repositories::scan_port::{ScanError, ScanPort, ScanVerdict},
+};
+
+/// Size of the chunks of a file streamed to clamd, below its default `StreamMaxLength`
+const CHUNK_BYTES: usize = 64 * 1024;
+
+/// Scans the files with a ClamAV daemon (clamd), over TCP
+///
+/// A file is streamed with the `INSTREAM` command: clamd does not need to access the files of the gateway.
+/// The maximum size of a streamed file is set by the `StreamMaxLength` of clamd, larger files fail to be scanned.
+pub struct ClamavScanner {
+    address: String,
+    timeout_ms: u64,
+}
+
+impl ClamavScanner {
+    pub fn new(settings: &ClamavSettings) -> Self {
+        Self {
+            address: format!("{}:{}", settings.host, settings.port),
+            timeout_ms: settings.timeout_ms,
+        }
+    }
+
+    async fn scan_file(&self, file: &mut std::fs::File) -> Result<ScanVerdict, ScanError> {
+        file.rewind()?;
+        let mut stream = TcpStream::connect(&self.address).await?;
+
+        // The `z` prefix delimits the command and its response with a NUL character
+        stream.write_all(b"zINSTREAM\0").await?;
+        let mut chunk = vec![0; CHUNK_BYTES];
+        loop {
+            let nb_read = file.read(&mut chunk)?;
+            if nb_read == 0 {
+                break;
+            }
+
+            stream.write_all(&(nb_read as u32).to_be_bytes()).await?;
+            stream.write_all(&chunk[..nb_read]).await?;
+        }
+        // A chunk of length 0 ends the stream
+        stream.write_all(&0u32.to_be_bytes()).await?;
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await?;
+        file.rewind()?;
+
+        parse_response(&String::from_utf8_lossy(&response))
+    }
+}
+
+impl ScanPort for ClamavScanner {
+    fn is_enabled(&self) -> bool {
+        true
+    }
+
+    fn scan<'a>(
+        &'a self,
+        file: &'a mut std::fs::File,
+    ) -> LocalBoxFuture<'a, Result<ScanVerdict, ScanError>> {
+        Box::pin(async move {
+            let verdict =
+                tokio::time::timeout(Duration::from_millis(self.timeout_ms), self.scan_file(file))
+                    .await
+                    .map_err(|_| ScanError::Timeout(self.timeout_ms))??;
+
+            info!(?verdict, "File scanned by ClamAV");
+            Ok(verdict)
+        })
+    }
+}
+
+/// Parses the response of clamd to a streamed file: `stream: OK`, `stream: <signature> FOUND` or `<reason> ERROR`
+fn parse_response(response: &str) -> Result<ScanVerdict, ScanError> {
+    let response = response.trim_end_matches('\0').trim();
+    let result = response.strip_prefix("stream: ").unwrap_or(response);
+
+    if result == "OK" {
+        Ok(ScanVerdict::Clean)
+    } else if let Some(signature) = result.strip_suffix(" FOUND") {
+        Ok(ScanVerdict::Infected(signature.to_string()))
+    } else {
+        Err(ScanError::ScannerError(response.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn responses_of_clamd_are_parsed() {
+        assert_eq!(parse_response("stream: OK\0").unwrap(), ScanVerdict::Clean);
+        assert_eq!(
+            parse_response("stream: Eicar-Test-Signature FOUND\0").unwrap(),
+            ScanVerdict::Infected("Eicar-Test-Signature".to_string())
+        );
+        assert!(matches!(
+            parse_response("INSTREAM size limit exceeded. ERROR\0"),
+            Err(ScanError::ScannerError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn file_is_streamed_in_chunks_ended_by_an_empty_chunk() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // Fake clamd, answering with the content it received
+        let clamd = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut command = [0; 10];
+            stream.read_exact(&mut command).await.unwrap();
+            assert_eq!(&command, b"zINSTREAM\0");
+
+            let mut content = Vec::new();
+            loop {
+                let length = stream.read_u32().await.unwrap() as usize;
+                if length == 0 {
+                    break;
+                }
+                let mut chunk = vec![0; length];
+                stream.read_exact(&mut chunk).await.unwrap();
+                content.extend(chunk);
+            }
+
+            stream.write_all(b"stream: OK\0").await.unwrap();
+            content
+        });
+
+        let content = vec![b'a'; CHUNK_BYTES + 10];
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(&content).unwrap();
+
+        let scanner = ClamavScanner::new(&ClamavSettings {
+            host: "127.0.0.1".to_string(),
+            port,
+            timeout_ms: 5_000,
+        });
+        let verdict = scanner.scan(&mut file).await.unwrap();
+
+        assert_eq!(verdict, ScanVerdict::Clean);
+        assert_eq!(clamd.await.unwrap(), content);
+        // Rewound to be stored
+        assert_eq!(file.stream_position().unwrap(), 0);
+    }
+}
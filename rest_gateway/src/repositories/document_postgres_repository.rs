@@ -0,0 +1,166 @@
+use common::helper::error_chain_fmt;
+use sqlx::{types::Json, PgExecutor};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::domain::entities::document::Document;
+
+/// Document repository implemented using Postgres
+pub struct DocumentPostgresRepository {}
+
+impl Default for DocumentPostgresRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DocumentPostgresRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Creates the empty summary of a source, if the source exists and has no summary yet
+    ///
+    /// Created before being locked with `get_document_for_update`:
+    /// two contents of a new source are then not summarized concurrently.
+    #[tracing::instrument(
+        name = "Creating document if missing in database",
+        skip(self, db_executor)
+    )]
+    pub async fn create_document_if_missing(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        source_meta_id: Uuid,
+    ) -> Result<(), DocumentPostgresRepositoryError> {
+        let document = Document::new(source_meta_id);
+
+        sqlx::query!(
+            r#"
+    INSERT INTO documents (source_meta_id, updated_at)
+    SELECT $1, $2
+    WHERE EXISTS (SELECT 1 FROM source_metas WHERE id = $1)
+    ON CONFLICT (source_meta_id) DO NOTHING
+            "#,
+            document.source_meta_id,
+            document.updated_at,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "Getting document for update from database",
+        skip(self, db_executor)
+    )]
+    pub async fn get_document_for_update(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        source_meta_id: Uuid,
+    ) -> Result<Option<Document>, DocumentPostgresRepositoryError> {
+        let document = sqlx::query_as!(
+            Document,
+            r#"
+    SELECT source_meta_id, title, author, excerpt, nb_chunks,
+        keyword_counts AS "keyword_counts: Json<BTreeMap<String, i64>>", updated_at
+    FROM documents
+    WHERE source_meta_id = $1
+    FOR UPDATE
+            "#,
+            source_meta_id,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(document)
+    }
+
+    #[tracing::instrument(
+        name = "Saving document in database",
+        skip(self, db_executor, document),
+        fields(source_meta_id = %document.source_meta_id)
+    )]
+    pub async fn save_document(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        document: &Document,
+    ) -> Result<(), DocumentPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    UPDATE documents
+    SET title = $2, author = $3, excerpt = $4, nb_chunks = $5, keyword_counts = $6, updated_at = $7
+    WHERE source_meta_id = $1
+            "#,
+            document.source_meta_id,
+            document.title,
+            document.author,
+            document.excerpt,
+            document.nb_chunks,
+            &document.keyword_counts as _,
+            document.updated_at,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Lists the summaries of the given sources, the sources without summary being left out
+    #[tracing::instrument(
+        name = "Listing documents from database",
+        skip(self, db_executor, source_meta_ids)
+    )]
+    pub async fn list_documents(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        source_meta_ids: &[Uuid],
+    ) -> Result<Vec<Document>, DocumentPostgresRepositoryError> {
+        let documents = sqlx::query_as!(
+            Document,
+            r#"
+    SELECT source_meta_id, title, author, excerpt, nb_chunks,
+        keyword_counts AS "keyword_counts: Json<BTreeMap<String, i64>>", updated_at
+    FROM documents
+    WHERE source_meta_id = ANY($1)
+            "#,
+            source_meta_ids,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(documents)
+    }
+
+    /// Deletes the summary of a source, to summarize it again from a new extraction
+    #[tracing::instrument(name = "Deleting document from database", skip(self, db_executor))]
+    pub async fn delete_document(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        source_meta_id: Uuid,
+    ) -> Result<(), DocumentPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    DELETE FROM documents
+    WHERE source_meta_id = $1
+            "#,
+            source_meta_id,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum DocumentPostgresRepositoryError {
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for DocumentPostgresRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
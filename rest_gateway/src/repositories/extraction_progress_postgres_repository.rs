@@ -0,0 +1,95 @@
+use common::helper::error_chain_fmt;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::domain::entities::extraction_progress::{ExtractionProgress, ExtractionStatus};
+
+/// Extraction progress repository implemented using Postgres
+pub struct ExtractionProgressPostgresRepository {}
+
+impl Default for ExtractionProgressPostgresRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExtractionProgressPostgresRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Saves the latest progress of the extraction of a source
+    ///
+    /// Progresses can be received out of order: an older progress does not replace a newer one,
+    /// and a completed or failed extraction is not set back in progress.
+    #[tracing::instrument(
+        name = "Saving extraction progress in database",
+        skip(self, db_executor)
+    )]
+    pub async fn save_extraction_progress(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        progress: &ExtractionProgress,
+    ) -> Result<(), ExtractionProgressPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    INSERT INTO extraction_progresses (source_meta_id, status, chunk_index, total_estimated_chunks, bytes_processed, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6)
+    ON CONFLICT (source_meta_id) DO UPDATE
+    SET status = EXCLUDED.status,
+        chunk_index = EXCLUDED.chunk_index,
+        total_estimated_chunks = EXCLUDED.total_estimated_chunks,
+        bytes_processed = EXCLUDED.bytes_processed,
+        updated_at = EXCLUDED.updated_at
+    WHERE extraction_progresses.status = 'in_progress'
+        AND (extraction_progresses.chunk_index <= EXCLUDED.chunk_index OR EXCLUDED.status <> 'in_progress')
+            "#,
+            progress.source_meta_id,
+            progress.status as ExtractionStatus,
+            progress.chunk_index,
+            progress.total_estimated_chunks,
+            progress.bytes_processed,
+            progress.updated_at
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "Getting extraction progress from database",
+        skip(self, db_executor)
+    )]
+    pub async fn get_extraction_progress(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        source_meta_id: Uuid,
+    ) -> Result<Option<ExtractionProgress>, ExtractionProgressPostgresRepositoryError> {
+        let progress = sqlx::query_as!(
+            ExtractionProgress,
+            r#"
+    SELECT source_meta_id, status AS "status: ExtractionStatus", chunk_index, total_estimated_chunks, bytes_processed, updated_at
+    FROM extraction_progresses
+    WHERE source_meta_id = $1
+            "#,
+            source_meta_id,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(progress)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum ExtractionProgressPostgresRepositoryError {
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for ExtractionProgressPostgresRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,148 @@
+use chrono::Utc;
+use common::helper::error_chain_fmt;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::domain::entities::fulltext_shard::FulltextShardSize;
+
+/// Routing map of the sources to the shards of the full-text index of their tenant, implemented using Postgres
+///
+/// The size of a shard is the number of indexed contents of the jobs of its sources:
+/// it decreases when a source is deleted, and is counted again when a source is re-indexed.
+pub struct FulltextShardPostgresRepository {}
+
+impl Default for FulltextShardPostgresRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FulltextShardPostgresRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    #[tracing::instrument(
+        name = "Saving full-text shard route in database",
+        skip(self, db_executor)
+    )]
+    pub async fn add_route(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        source_meta_id: Uuid,
+        tenant_id: Option<&str>,
+        shard: u32,
+    ) -> Result<(), FulltextShardPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    INSERT INTO fulltext_shard_routes (source_meta_id, tenant_id, shard, created_at)
+    VALUES ($1, $2, $3, $4)
+            "#,
+            source_meta_id,
+            tenant_id,
+            shard as i32,
+            Utc::now(),
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Shard of the contents of a source, the first shard for the sources uploaded before the sharding
+    #[tracing::instrument(
+        name = "Getting full-text shard of source from database",
+        skip(self, db_executor)
+    )]
+    pub async fn get_source_shard(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        source_meta_id: Uuid,
+    ) -> Result<u32, FulltextShardPostgresRepositoryError> {
+        let shard = sqlx::query_scalar!(
+            r#"
+    SELECT shard
+    FROM fulltext_shard_routes
+    WHERE source_meta_id = $1
+            "#,
+            source_meta_id,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(shard.unwrap_or(0) as u32)
+    }
+
+    /// Lists the shards of a tenant, always including the first shard, in ascending order
+    #[tracing::instrument(
+        name = "Listing tenant full-text shards from database",
+        skip(self, db_executor)
+    )]
+    pub async fn list_tenant_shards(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<u32>, FulltextShardPostgresRepositoryError> {
+        let shards = sqlx::query_scalar!(
+            r#"
+    SELECT DISTINCT shard
+    FROM fulltext_shard_routes
+    WHERE COALESCE(tenant_id, '') = COALESCE($1, '') AND shard > 0
+    ORDER BY shard
+            "#,
+            tenant_id,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(std::iter::once(0)
+            .chain(shards.into_iter().map(|shard| shard as u32))
+            .collect())
+    }
+
+    /// Gets the last shard of a tenant with the number of contents indexed in it, `None` if the tenant has no route yet
+    #[tracing::instrument(
+        name = "Getting last tenant full-text shard from database",
+        skip(self, db_executor)
+    )]
+    pub async fn get_last_tenant_shard(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<FulltextShardSize>, FulltextShardPostgresRepositoryError> {
+        let last_shard = sqlx::query!(
+            r#"
+    SELECT routes.shard, COALESCE(SUM(ingestion_jobs.nb_indexed_contents), 0)::BIGINT AS "nb_contents!"
+    FROM fulltext_shard_routes routes
+    LEFT JOIN ingestion_jobs ON ingestion_jobs.source_meta_id = routes.source_meta_id
+    WHERE COALESCE(routes.tenant_id, '') = COALESCE($1, '')
+        AND routes.shard = (
+            SELECT MAX(shard)
+            FROM fulltext_shard_routes
+            WHERE COALESCE(tenant_id, '') = COALESCE($1, '')
+        )
+    GROUP BY routes.shard
+            "#,
+            tenant_id,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(last_shard.map(|last_shard| FulltextShardSize {
+            shard: last_shard.shard as u32,
+            nb_contents: last_shard.nb_contents.max(0) as u64,
+        }))
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum FulltextShardPostgresRepositoryError {
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for FulltextShardPostgresRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,149 @@
+use common::helper::error_chain_fmt;
+use s3::{creds::Credentials, Bucket, Region};
+use secrecy::ExposeSecret;
+use tokio::io::AsyncWriteExt;
+use tracing::info;
+
+use crate::configuration::ImportsSettings;
+
+/// Object listed in a bucket to import
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportObject {
+    pub key: String,
+    pub size_bytes: u64,
+}
+
+/// Simple Storage Service (S3) client reading the document archives imported as sources
+///
+/// The buckets are read with the credentials of the settings, and are never written to.
+pub struct ImportS3Repository {
+    region: Region,
+    credentials: Credentials,
+    max_objects: usize,
+}
+
+#[derive(thiserror::Error)]
+pub enum ImportS3RepositoryError {
+    #[error("The bucket could not be found: {0}")]
+    BucketNotFound(String),
+    #[error("The object could not be found in the bucket: {0}")]
+    ObjectNotFound(String),
+    #[error("More than {0} objects to import: import a narrower prefix")]
+    TooManyObjects(usize),
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+    #[error(transparent)]
+    Other(#[from] s3::error::S3Error),
+}
+
+impl std::fmt::Debug for ImportS3RepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ImportS3Repository {
+    pub fn new(settings: &ImportsSettings) -> Self {
+        Self {
+            region: Region::Custom {
+                region: settings.region.clone(),
+                endpoint: settings.endpoint.clone(),
+            },
+            credentials: Credentials {
+                access_key: Some(settings.username.clone()),
+                secret_key: Some(settings.password.expose_secret().clone()),
+                security_token: None,
+                session_token: None,
+                expiration: None,
+            },
+            max_objects: settings.max_objects,
+        }
+    }
+
+    /// Lists the objects of a bucket whose key starts with a prefix, in the order of their key
+    ///
+    /// The "directories" (keys ending with a `/`) are not listed.
+    #[tracing::instrument(name = "Listing objects to import", skip(self))]
+    pub async fn list_objects(
+        &self,
+        bucket_name: &str,
+        prefix: &str,
+    ) -> Result<Vec<ImportObject>, ImportS3RepositoryError> {
+        let bucket = self.bucket(bucket_name)?;
+
+        let mut objects = vec![];
+        let mut continuation_token = None;
+        loop {
+            let (page, _) = bucket
+                .list_page(prefix.to_string(), None, continuation_token, None, None)
+                .await
+                .map_err(|error| match error {
+                    s3::error::S3Error::Http(404, _) => {
+                        ImportS3RepositoryError::BucketNotFound(bucket_name.to_string())
+                    }
+                    _ => ImportS3RepositoryError::Other(error),
+                })?;
+
+            objects.extend(
+                page.contents
+                    .into_iter()
+                    .filter(|object| !object.key.ends_with('/'))
+                    .map(|object| ImportObject {
+                        key: object.key,
+                        size_bytes: object.size,
+                    }),
+            );
+            if objects.len() > self.max_objects {
+                return Err(ImportS3RepositoryError::TooManyObjects(self.max_objects));
+            }
+
+            continuation_token = page.next_continuation_token;
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+
+        info!(
+            "Listed {} objects in {} under {:?}",
+            objects.len(),
+            bucket_name,
+            prefix
+        );
+        Ok(objects)
+    }
+
+    /// Downloads an object into a given file
+    ///
+    /// # Return
+    /// The size of the object
+    #[tracing::instrument(name = "Downloading object to import", skip(self, file))]
+    pub async fn download(
+        &self,
+        bucket_name: &str,
+        object_key: &str,
+        file: &mut std::fs::File,
+    ) -> Result<u64, ImportS3RepositoryError> {
+        let bucket = self.bucket(bucket_name)?;
+        let mut writer = tokio::fs::File::from_std(file.try_clone()?);
+
+        bucket
+            .get_object_to_writer(object_key, &mut writer)
+            .await
+            .map_err(|error| match error {
+                s3::error::S3Error::Http(404, _) => {
+                    ImportS3RepositoryError::ObjectNotFound(object_key.to_string())
+                }
+                _ => ImportS3RepositoryError::Other(error),
+            })?;
+        writer.flush().await?;
+
+        Ok(file.metadata()?.len())
+    }
+
+    fn bucket(&self, bucket_name: &str) -> Result<Bucket, ImportS3RepositoryError> {
+        Ok(
+            Bucket::new(bucket_name, self.region.clone(), self.credentials.clone())?
+                .with_path_style(),
+        )
+    }
+}
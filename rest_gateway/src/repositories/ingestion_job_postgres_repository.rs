@@ -0,0 +1,231 @@
+use chrono::{DateTime, Utc};
+use common::helper::error_chain_fmt;
+use sqlx::{types::Json, PgExecutor};
+use uuid::Uuid;
+
+use crate::domain::entities::ingestion_job::{
+    IngestionErrorCode, IngestionJob, IngestionLane, JobStatus, SkippedItem,
+};
+
+/// Ingestion job repository implemented using Postgres
+pub struct IngestionJobPostgresRepository {}
+
+impl Default for IngestionJobPostgresRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IngestionJobPostgresRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    #[tracing::instrument(name = "Saving new ingestion job in database", skip(self, db_executor))]
+    pub async fn add_job(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        job: &IngestionJob,
+    ) -> Result<(), IngestionJobPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    INSERT INTO ingestion_jobs (id, source_meta_id, status, nb_contents, nb_embedded_contents, error,
+        nb_indexed_contents, upload_started_at, queued_at, extraction_started_at, extraction_completed_at,
+        indexed_at, embedded_at, created_at, updated_at, error_code, lane, skipped_items)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
+            "#,
+            job.id,
+            job.source_meta_id,
+            job.status as JobStatus,
+            job.nb_contents,
+            job.nb_embedded_contents,
+            job.error,
+            job.nb_indexed_contents,
+            job.upload_started_at,
+            job.queued_at,
+            job.extraction_started_at,
+            job.extraction_completed_at,
+            job.indexed_at,
+            job.embedded_at,
+            job.created_at,
+            job.updated_at,
+            job.error_code as Option<IngestionErrorCode>,
+            job.lane as IngestionLane,
+            &job.skipped_items as _
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Gets a job of a user, from the user owning its source
+    #[tracing::instrument(
+        name = "Getting user ingestion job from database",
+        skip(self, db_executor)
+    )]
+    pub async fn get_user_job(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        job_id: Uuid,
+    ) -> Result<Option<IngestionJob>, IngestionJobPostgresRepositoryError> {
+        let job = sqlx::query_as!(
+            IngestionJob,
+            r#"
+    SELECT ingestion_jobs.id, source_meta_id, status AS "status: JobStatus", lane AS "lane: IngestionLane", nb_contents,
+        nb_embedded_contents, error, error_code AS "error_code: IngestionErrorCode",
+        skipped_items AS "skipped_items: Json<Vec<SkippedItem>>", nb_indexed_contents, upload_started_at, queued_at,
+        extraction_started_at, extraction_completed_at, indexed_at, embedded_at,
+        ingestion_jobs.created_at, ingestion_jobs.updated_at
+    FROM ingestion_jobs
+    JOIN source_metas ON source_metas.id = ingestion_jobs.source_meta_id
+    WHERE ingestion_jobs.id = $1 AND source_metas.user_id = $2
+            "#,
+            job_id,
+            user_id,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(job)
+    }
+
+    /// Gets the job of a source, locking it until the end of the transaction to apply a status update
+    #[tracing::instrument(
+        name = "Getting ingestion job of source for update from database",
+        skip(self, db_executor)
+    )]
+    pub async fn get_job_by_source_meta_id_for_update(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        source_meta_id: Uuid,
+    ) -> Result<Option<IngestionJob>, IngestionJobPostgresRepositoryError> {
+        let job = sqlx::query_as!(
+            IngestionJob,
+            r#"
+    SELECT id, source_meta_id, status AS "status: JobStatus", lane AS "lane: IngestionLane", nb_contents, nb_embedded_contents,
+        error, error_code AS "error_code: IngestionErrorCode",
+        skipped_items AS "skipped_items: Json<Vec<SkippedItem>>", nb_indexed_contents, upload_started_at,
+        queued_at, extraction_started_at,
+        extraction_completed_at, indexed_at, embedded_at, created_at, updated_at
+    FROM ingestion_jobs
+    WHERE source_meta_id = $1
+    FOR UPDATE
+            "#,
+            source_meta_id,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(job)
+    }
+
+    /// Lists the jobs queued since a given time, to compute the latency of their stages
+    #[tracing::instrument(
+        name = "Listing ingestion jobs queued since from database",
+        skip(self, db_executor)
+    )]
+    pub async fn list_jobs_queued_since(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<IngestionJob>, IngestionJobPostgresRepositoryError> {
+        let jobs = sqlx::query_as!(
+            IngestionJob,
+            r#"
+    SELECT id, source_meta_id, status AS "status: JobStatus", lane AS "lane: IngestionLane", nb_contents, nb_embedded_contents,
+        error, error_code AS "error_code: IngestionErrorCode",
+        skipped_items AS "skipped_items: Json<Vec<SkippedItem>>", nb_indexed_contents, upload_started_at,
+        queued_at, extraction_started_at,
+        extraction_completed_at, indexed_at, embedded_at, created_at, updated_at
+    FROM ingestion_jobs
+    WHERE queued_at >= $1
+            "#,
+            since,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(jobs)
+    }
+
+    /// Lists the sources with an ingestion job in a given status
+    #[tracing::instrument(
+        name = "Listing source meta ids by ingestion job status from database",
+        skip(self, db_executor)
+    )]
+    pub async fn list_source_meta_ids_by_status(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        status: JobStatus,
+    ) -> Result<Vec<Uuid>, IngestionJobPostgresRepositoryError> {
+        let records = sqlx::query!(
+            r#"
+    SELECT source_meta_id FROM ingestion_jobs
+    WHERE status = $1
+    ORDER BY created_at
+            "#,
+            status as JobStatus,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(records
+            .into_iter()
+            .map(|record| record.source_meta_id)
+            .collect())
+    }
+
+    #[tracing::instrument(
+        name = "Saving ingestion job status in database",
+        skip(self, db_executor)
+    )]
+    pub async fn save_job_status(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        job: &IngestionJob,
+    ) -> Result<(), IngestionJobPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    UPDATE ingestion_jobs
+    SET status = $2, nb_contents = $3, nb_embedded_contents = $4, error = $5, nb_indexed_contents = $6,
+        upload_started_at = $7, queued_at = $8, extraction_started_at = $9, extraction_completed_at = $10,
+        indexed_at = $11, embedded_at = $12, updated_at = $13, error_code = $14, lane = $15, skipped_items = $16
+    WHERE id = $1
+            "#,
+            job.id,
+            job.status as JobStatus,
+            job.nb_contents,
+            job.nb_embedded_contents,
+            job.error,
+            job.nb_indexed_contents,
+            job.upload_started_at,
+            job.queued_at,
+            job.extraction_started_at,
+            job.extraction_completed_at,
+            job.indexed_at,
+            job.embedded_at,
+            job.updated_at,
+            job.error_code as Option<IngestionErrorCode>,
+            job.lane as IngestionLane,
+            &job.skipped_items as _
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum IngestionJobPostgresRepositoryError {
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for IngestionJobPostgresRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -5,6 +5,7 @@ use common::helper::error_chain_fmt;
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 /// Repository to handle JWT tokens
 ///
@@ -13,6 +14,7 @@ use serde::{Deserialize, Serialize};
 pub struct JwtAuthenticationRepository {
     secret: Secret<String>,
     default_expire_in_s: i64,
+    refresh_token_expire_in_s: i64,
 }
 
 // TODO: iss: issuer in claims ?
@@ -26,21 +28,47 @@ pub struct TokenClaims {
 
     /// Expires At
     pub exp: usize,
+
+    /// Log-in session of the token, which can be revoked.
+    /// Tokens without session are only bound to their expiration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<Uuid>,
 }
 
 impl JwtAuthenticationRepository {
-    pub fn new(secret: Secret<String>, default_expire_in_s: i64) -> Self {
+    pub fn new(
+        secret: Secret<String>,
+        default_expire_in_s: i64,
+        refresh_token_expire_in_s: i64,
+    ) -> Self {
         Self {
             secret,
             default_expire_in_s,
+            refresh_token_expire_in_s,
         }
     }
 
+    /// Lifetime of the refresh tokens of the log-in sessions, in seconds
+    pub fn refresh_token_expire_in_s(&self) -> i64 {
+        self.refresh_token_expire_in_s
+    }
+
     /// Creates a new JWT token with default expire in
     pub fn create_token(&self, user_id: &str) -> Result<String, JwtAuthenticationRepositoryError> {
         self.create_token_with_expire_in(user_id, self.default_expire_in_s)
     }
 
+    /// Creates a new JWT token of a log-in session with default expire in
+    ///
+    /// The token is rejected once its session is revoked, even before its expiration.
+    pub fn create_session_token(
+        &self,
+        user_id: &str,
+        session_id: Uuid,
+    ) -> Result<String, JwtAuthenticationRepositoryError> {
+        self.encode_token(user_id, Some(session_id), self.default_expire_in_s)
+    }
+
     /// Creates a new JWT token with given expire in
     ///
     /// # Params
@@ -51,6 +79,15 @@ impl JwtAuthenticationRepository {
         &self,
         user_id: &str,
         expire_in_s: i64,
+    ) -> Result<String, JwtAuthenticationRepositoryError> {
+        self.encode_token(user_id, None, expire_in_s)
+    }
+
+    fn encode_token(
+        &self,
+        user_id: &str,
+        session_id: Option<Uuid>,
+        expire_in_s: i64,
     ) -> Result<String, JwtAuthenticationRepositoryError> {
         if user_id.is_empty() {
             return Err(JwtAuthenticationRepositoryError::InvalidData(
@@ -65,6 +102,7 @@ impl JwtAuthenticationRepository {
             sub: user_id.to_string(),
             exp,
             iat,
+            sid: session_id,
         };
 
         encode(
@@ -82,6 +120,14 @@ impl JwtAuthenticationRepository {
     /// - leeway set to 60s by default
     #[tracing::instrument(name = "Decode JWT token", skip(self))]
     pub fn decode_token(&self, token: &str) -> Result<String, JwtAuthenticationRepositoryError> {
+        self.decode_token_claims(token).map(|claims| claims.sub)
+    }
+
+    /// Decodes a JWT token, with the same validation as `decode_token`, returning all its claims
+    pub fn decode_token_claims(
+        &self,
+        token: &str,
+    ) -> Result<TokenClaims, JwtAuthenticationRepositoryError> {
         let decoded = decode::<TokenClaims>(
             token.into(),
             &DecodingKey::from_secret(self.secret.expose_secret().as_bytes()),
@@ -89,7 +135,7 @@ impl JwtAuthenticationRepository {
         );
 
         match decoded {
-            Ok(token) => Ok(token.claims.sub),
+            Ok(token) => Ok(token.claims),
             Err(err) => Err(JwtAuthenticationRepositoryError::DecodingError(err)),
         }
     }
@@ -132,7 +178,7 @@ mod tests {
     fn on_valid_token_it_should_create_and_decode_correctly() {
         let user_id = "user123";
         let secret = Secret::new("my-secret-key".to_string());
-        let auth_repo = JwtAuthenticationRepository::new(secret, 60);
+        let auth_repo = JwtAuthenticationRepository::new(secret, 60, 3600);
 
         let token = auth_repo.create_token(user_id).unwrap();
         let decoded_user_id = auth_repo.decode_token(&token).unwrap();
@@ -140,11 +186,28 @@ mod tests {
         assert_eq!(decoded_user_id, user_id);
     }
 
+    #[test]
+    fn session_token_should_be_decoded_with_its_session() {
+        let secret = Secret::new("my-secret-key".to_string());
+        let auth_repo = JwtAuthenticationRepository::new(secret, 60, 3600);
+        let session_id = Uuid::new_v4();
+
+        let session_token = auth_repo
+            .create_session_token("user123", session_id)
+            .unwrap();
+        let token = auth_repo.create_token("user123").unwrap();
+
+        let claims = auth_repo.decode_token_claims(&session_token).unwrap();
+        assert_eq!(claims.sub, "user123");
+        assert_eq!(claims.sid, Some(session_id));
+        assert_eq!(auth_repo.decode_token_claims(&token).unwrap().sid, None);
+    }
+
     #[test]
     fn on_empty_user_id_token_create_should_fail() {
         let user_id = "";
         let secret = Secret::new("my-secret-key".to_string());
-        let auth_repo = JwtAuthenticationRepository::new(secret, 60);
+        let auth_repo = JwtAuthenticationRepository::new(secret, 60, 3600);
 
         let result = auth_repo.create_token(user_id);
 
@@ -159,7 +222,7 @@ mod tests {
     fn on_invalid_token_decode_should_fail() {
         let secret = Secret::new("my-secret-key".to_string());
         let invalid_token = "invalid-token";
-        let auth_repo = JwtAuthenticationRepository::new(secret, 60);
+        let auth_repo = JwtAuthenticationRepository::new(secret, 60, 3600);
 
         let result = auth_repo.decode_token(invalid_token);
 
@@ -174,7 +237,7 @@ mod tests {
     #[test]
     fn on_expired_token_decode_should_fail() {
         let secret = Secret::new("my-secret-key".to_string());
-        let auth_repo = JwtAuthenticationRepository::new(secret, 60);
+        let auth_repo = JwtAuthenticationRepository::new(secret, 60, 3600);
 
         // Leeway of 60s by default
         let expired_token = auth_repo
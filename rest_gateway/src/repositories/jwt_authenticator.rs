@@ -0,0 +1,77 @@
+use actix_web::http::header::HeaderMap;
+use futures::future::LocalBoxFuture;
+use sqlx::PgPool;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::repositories::{
+    authenticator_port::{bearer_token, AuthenticationError, AuthenticatorPort},
+    jwt_authentication_repository::JwtAuthenticationRepository,
+    refresh_token_postgres_repository::RefreshTokenPostgresRepository,
+};
+
+/// Authenticates the users with the access tokens issued by the gateway on log-in
+///
+/// The access tokens of a log-in session are rejected once the session is revoked (logged out for ex),
+/// which is checked against the database on each request.
+pub struct JwtAuthenticator {
+    auth_repository: JwtAuthenticationRepository,
+    db_pool: PgPool,
+    refresh_token_repository: RefreshTokenPostgresRepository,
+}
+
+impl JwtAuthenticator {
+    pub fn new(auth_repository: JwtAuthenticationRepository, db_pool: PgPool) -> Self {
+        Self {
+            auth_repository,
+            db_pool,
+            refresh_token_repository: RefreshTokenPostgresRepository::new(),
+        }
+    }
+}
+
+impl AuthenticatorPort for JwtAuthenticator {
+    fn authenticate(
+        &self,
+        headers: &HeaderMap,
+    ) -> LocalBoxFuture<'_, Result<Uuid, AuthenticationError>> {
+        let token = bearer_token(headers);
+
+        Box::pin(async move {
+            let token = token.ok_or(AuthenticationError::MissingCredentials)?;
+
+            let claims = self
+                .auth_repository
+                .decode_token_claims(&token)
+                .map_err(|error| AuthenticationError::InvalidCredentials(error.to_string()))?;
+
+            let user_id = Uuid::parse_str(claims.sub.as_str()).map_err(|error| {
+                error!(?error, "Provided user id could not be parsed to uuid");
+                AuthenticationError::InvalidCredentials("Provided user id is not valid".to_string())
+            })?;
+
+            if let Some(session_id) = claims.sid {
+                let is_session_active = self
+                    .refresh_token_repository
+                    .is_session_active(&self.db_pool, user_id, session_id)
+                    .await
+                    .map_err(|error| {
+                        error!(?error, "Failed to check the session of the access token");
+                        AuthenticationError::BackendError(error.to_string())
+                    })?;
+
+                if !is_session_active {
+                    info!(?session_id, "Access token of a revoked session");
+                    return Err(AuthenticationError::RevokedSession);
+                }
+            }
+
+            Ok(user_id)
+        })
+    }
+
+    /// The access tokens are issued on the log-in of the accounts
+    fn has_local_accounts(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,158 @@
+use common::helper::error_chain_fmt;
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Maximum number of pending tasks listed at once
+const MAX_LISTED_TASKS: u32 = 1000;
+
+/// Client of the Meilisearch HTTP API, to inspect the index of the extracted contents
+///
+/// The contents are indexed by the `fulltext_search_service`: this client only reads from Meilisearch.
+pub struct MeilisearchAdminRepository {
+    client: reqwest::Client,
+    endpoint: String,
+    api_key: Option<Secret<String>>,
+    contents_index: String,
+}
+
+/// Asynchronous operation of Meilisearch, for ex the indexing of a batch of contents
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeilisearchTask {
+    pub uid: u64,
+    pub index_uid: Option<String>,
+    pub status: String,
+    #[serde(rename = "type")]
+    pub task_type: String,
+    pub enqueued_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TasksResponse {
+    results: Vec<MeilisearchTask>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchResponse {
+    #[serde(default)]
+    total_hits: Option<u64>,
+    #[serde(default)]
+    facet_distribution: HashMap<String, HashMap<String, u64>>,
+}
+
+impl MeilisearchAdminRepository {
+    pub fn new(endpoint: String, api_key: Option<Secret<String>>, contents_index: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint,
+            api_key,
+            contents_index,
+        }
+    }
+
+    /// Lists the enqueued and processing tasks, from the most recent
+    #[tracing::instrument(name = "Listing pending tasks from Meilisearch", skip(self))]
+    pub async fn list_pending_tasks(
+        &self,
+    ) -> Result<Vec<MeilisearchTask>, MeilisearchAdminRepositoryError> {
+        let response: TasksResponse = self
+            .authorize(self.client.get(format!(
+                "{}/tasks?statuses=enqueued,processing&limit={}",
+                self.endpoint, MAX_LISTED_TASKS
+            )))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(response.results)
+    }
+
+    /// Counts the contents extracted from a source
+    #[tracing::instrument(name = "Counting source contents in Meilisearch", skip(self))]
+    pub async fn count_source_contents(
+        &self,
+        source_meta_id: Uuid,
+    ) -> Result<u64, MeilisearchAdminRepositoryError> {
+        // Exhaustive pagination returns the exact number of hits
+        let response = self
+            .search(json!({
+                "q": "",
+                "filter": format!("source_meta_id = \"{}\"", source_meta_id),
+                "hitsPerPage": 1,
+                "page": 1,
+            }))
+            .await?;
+
+        Ok(response.total_hits.unwrap_or_default())
+    }
+
+    /// Counts the contents of the sources with the most contents
+    ///
+    /// The number of sources is capped by the `maxValuesPerFacet` setting of the index (100 by default).
+    #[tracing::instrument(name = "Counting contents per source in Meilisearch", skip(self))]
+    pub async fn count_contents_per_source(
+        &self,
+    ) -> Result<Vec<(String, u64)>, MeilisearchAdminRepositoryError> {
+        let mut response = self
+            .search(json!({
+                "q": "",
+                "limit": 0,
+                "facets": ["source_meta_id"],
+            }))
+            .await?;
+
+        let mut counts: Vec<(String, u64)> = response
+            .facet_distribution
+            .remove("source_meta_id")
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        Ok(counts)
+    }
+
+    async fn search(
+        &self,
+        body: JsonValue,
+    ) -> Result<SearchResponse, MeilisearchAdminRepositoryError> {
+        let response = self
+            .authorize(self.client.post(format!(
+                "{}/indexes/{}/search",
+                self.endpoint, self.contents_index
+            )))
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(response)
+    }
+
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key.expose_secret()),
+            None => request,
+        }
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum MeilisearchAdminRepositoryError {
+    #[error(transparent)]
+    RequestError(#[from] reqwest::Error),
+}
+
+impl std::fmt::Debug for MeilisearchAdminRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
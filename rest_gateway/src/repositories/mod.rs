@@ -1,4 +1,43 @@
+pub mod answer_generation_port;
+pub mod api_key_postgres_repository;
+pub mod api_reranker;
+pub mod authenticator_port;
+pub mod auto_filing_rule_postgres_repository;
+pub mod clamav_scanner;
+pub mod document_postgres_repository;
+pub mod extraction_progress_postgres_repository;
+pub mod fulltext_shard_postgres_repository;
+pub mod import_s3_repository;
+pub mod ingestion_job_postgres_repository;
 pub mod jwt_authentication_repository;
+pub mod jwt_authenticator;
+pub mod meilisearch_admin_repository;
+pub mod mtls_authenticator;
+pub mod noop_answer_generator;
+pub mod noop_reranker;
+pub mod noop_scanner;
+pub mod normalization_rule_postgres_repository;
+pub mod oidc_introspection_authenticator;
+pub mod openai_answer_generator;
+pub mod outbox_message_postgres_repository;
+pub mod provider_api_repository;
+pub mod provider_credentials_postgres_repository;
+pub mod rabbitmq_management_repository;
+pub mod refresh_token_postgres_repository;
+pub mod rerank_port;
+pub mod retention_rule_postgres_repository;
+pub mod saved_search_postgres_repository;
+pub mod scan_port;
+pub mod source_event_postgres_repository;
 pub mod source_file_s3_repository;
 pub mod source_meta_postgres_repository;
+pub mod source_summary_postgres_repository;
+pub mod source_url_repository;
+pub mod source_url_schedule_postgres_repository;
+pub mod static_token_authenticator;
+pub mod two_factor_postgres_repository;
+pub mod upload_policy_postgres_repository;
+pub mod upload_session_postgres_repository;
+pub mod user_activity_rabbitmq_repository;
 pub mod user_postgres_repository;
+pub mod user_storage_usage_postgres_repository;
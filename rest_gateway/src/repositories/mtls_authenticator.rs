@@ -0,0 +1,77 @@
+use actix_web::http::header::HeaderMap;
+use futures::future::{ready, LocalBoxFuture};
+use uuid::Uuid;
+
+use crate::repositories::authenticator_port::{
+    external_user_id, AuthenticationError, AuthenticatorPort,
+};
+
+/// Authenticates the users with their client certificate (mTLS)
+///
+/// The TLS connection is terminated by a reverse proxy verifying the client certificates,
+/// which forwards the subject of the verified certificate in a header.
+/// The proxy should drop this header from the incoming requests: the gateway trusts it.
+pub struct MtlsAuthenticator {
+    subject_header: String,
+}
+
+impl MtlsAuthenticator {
+    pub fn new(subject_header: String) -> Self {
+        Self { subject_header }
+    }
+
+    /// Subject of the verified certificate, forwarded by the proxy
+    fn subject<'a>(&self, headers: &'a HeaderMap) -> Option<&'a str> {
+        headers
+            .get(self.subject_header.as_str())
+            .and_then(|header| header.to_str().ok())
+            .map(str::trim)
+            .filter(|subject| !subject.is_empty())
+    }
+}
+
+impl AuthenticatorPort for MtlsAuthenticator {
+    fn authenticate(
+        &self,
+        headers: &HeaderMap,
+    ) -> LocalBoxFuture<'_, Result<Uuid, AuthenticationError>> {
+        let subject = self.subject(headers);
+
+        let user_id = match subject {
+            Some(subject) => Ok(external_user_id("mtls", subject)),
+            None => Err(AuthenticationError::MissingCredentials),
+        };
+
+        Box::pin(ready(user_id))
+    }
+
+    fn has_credentials(&self, headers: &HeaderMap) -> bool {
+        self.subject(headers).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::{HeaderName, HeaderValue};
+
+    #[tokio::test]
+    async fn user_is_authenticated_from_the_subject_of_the_certificate() {
+        let authenticator = MtlsAuthenticator::new("X-Client-Cert-Subject".to_string());
+        let mut headers = HeaderMap::new();
+
+        assert!(matches!(
+            authenticator.authenticate(&headers).await,
+            Err(AuthenticationError::MissingCredentials)
+        ));
+
+        headers.insert(
+            HeaderName::from_static("x-client-cert-subject"),
+            HeaderValue::from_static("CN=alice,O=Example"),
+        );
+        assert_eq!(
+            authenticator.authenticate(&headers).await.unwrap(),
+            external_user_id("mtls", "CN=alice,O=Example")
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use futures::{
+    future::{ready, LocalBoxFuture},
+    stream::{self, LocalBoxStream},
+    StreamExt,
+};
+
+use crate::{
+    domain::entities::answer::AnswerPrompt,
+    repositories::answer_generation_port::{
+        AnswerChunk, AnswerGenerationError, AnswerGenerationPort, GeneratedAnswer,
+    },
+};
+
+/// Answers no question, when no LLM backend is configured
+pub struct NoOpAnswerGenerator;
+
+impl AnswerGenerationPort for NoOpAnswerGenerator {
+    fn is_enabled(&self) -> bool {
+        false
+    }
+
+    fn generate<'a>(
+        &'a self,
+        _prompt: &'a AnswerPrompt,
+    ) -> LocalBoxFuture<'a, Result<GeneratedAnswer, AnswerGenerationError>> {
+        Box::pin(ready(Err(AnswerGenerationError::Disabled)))
+    }
+
+    fn generate_stream(
+        &self,
+        _prompt: &AnswerPrompt,
+    ) -> LocalBoxStream<'static, Result<AnswerChunk, AnswerGenerationError>> {
+        stream::once(ready(Err(AnswerGenerationError::Disabled))).boxed_local()
+    }
+}
@@ -0,0 +1,24 @@
+use futures::future::{ready, LocalBoxFuture};
+
+use crate::repositories::rerank_port::{RerankError, RerankPort};
+
+/// Reranks no search, when no reranker is configured
+pub struct NoOpReranker;
+
+impl RerankPort for NoOpReranker {
+    fn is_enabled(&self) -> bool {
+        false
+    }
+
+    fn candidates(&self) -> usize {
+        0
+    }
+
+    fn rerank<'a>(
+        &'a self,
+        _query: &'a str,
+        _documents: &'a [&'a str],
+    ) -> LocalBoxFuture<'a, Result<Vec<f64>, RerankError>> {
+        Box::pin(ready(Err(RerankError::Disabled)))
+    }
+}
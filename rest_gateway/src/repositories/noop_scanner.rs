@@ -0,0 +1,19 @@
+use futures::future::{ready, LocalBoxFuture};
+
+use crate::repositories::scan_port::{ScanError, ScanPort, ScanVerdict};
+
+/// Accepts every file without scanning it, when no antivirus is configured
+pub struct NoOpScanner;
+
+impl ScanPort for NoOpScanner {
+    fn is_enabled(&self) -> bool {
+        false
+    }
+
+    fn scan<'a>(
+        &'a self,
+        _file: &'a mut std::fs::File,
+    ) -> LocalBoxFuture<'a, Result<ScanVerdict, ScanError>> {
+        Box::pin(ready(Ok(ScanVerdict::Clean)))
+    }
+}
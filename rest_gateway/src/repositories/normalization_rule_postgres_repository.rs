@@ -0,0 +1,113 @@
+use common::helper::error_chain_fmt;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::domain::entities::normalization_rule::{NormalizationRule, NormalizationRuleKind};
+
+/// Normalization rule repository implemented using Postgres
+pub struct NormalizationRulePostgresRepository {}
+
+impl Default for NormalizationRulePostgresRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NormalizationRulePostgresRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    #[tracing::instrument(
+        name = "Saving normalization rule in database",
+        skip(self, db_executor)
+    )]
+    pub async fn add_rule(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        rule: &NormalizationRule,
+    ) -> Result<(), NormalizationRulePostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    INSERT INTO normalization_rules (id, tenant_id, kind, pattern, replacement, created_at)
+    VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            rule.id,
+            rule.tenant_id,
+            rule.kind as NormalizationRuleKind,
+            rule.pattern,
+            rule.replacement,
+            rule.created_at
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Lists the rules of a tenant, in their creation order: a later rule replacing a term overrides an earlier one
+    #[tracing::instrument(
+        name = "Listing tenant normalization rules in database",
+        skip(self, db_executor)
+    )]
+    pub async fn list_tenant_rules(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<NormalizationRule>, NormalizationRulePostgresRepositoryError> {
+        let rules = sqlx::query_as!(
+            NormalizationRule,
+            r#"
+    SELECT id, tenant_id, kind AS "kind: NormalizationRuleKind", pattern, replacement, created_at
+    FROM normalization_rules
+    WHERE COALESCE(tenant_id, '') = COALESCE($1, '')
+    ORDER BY created_at, id
+            "#,
+            tenant_id,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(rules)
+    }
+
+    /// Deletes a rule
+    ///
+    /// # Returns
+    /// The deleted rule, `None` if it does not exist
+    #[tracing::instrument(
+        name = "Deleting normalization rule in database",
+        skip(self, db_executor)
+    )]
+    pub async fn delete_rule(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        rule_id: Uuid,
+    ) -> Result<Option<NormalizationRule>, NormalizationRulePostgresRepositoryError> {
+        let rule = sqlx::query_as!(
+            NormalizationRule,
+            r#"
+    DELETE FROM normalization_rules
+    WHERE id = $1
+    RETURNING id, tenant_id, kind AS "kind: NormalizationRuleKind", pattern, replacement, created_at
+            "#,
+            rule_id,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(rule)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum NormalizationRulePostgresRepositoryError {
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for NormalizationRulePostgresRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
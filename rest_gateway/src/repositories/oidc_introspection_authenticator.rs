@@ -0,0 +1,131 @@
+use actix_web::http::header::HeaderMap;
+use futures::future::LocalBoxFuture;
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    configuration::OidcSettings,
+    repositories::authenticator_port::{
+        bearer_token, external_user_id, AuthenticationError, AuthenticatorPort,
+    },
+};
+
+/// Response of an OAuth 2.0 token introspection endpoint (RFC 7662)
+#[derive(Debug, Deserialize)]
+struct IntrospectionResponse {
+    active: bool,
+    sub: Option<String>,
+}
+
+/// Authenticates the users with the access tokens of an OIDC provider, checked on its introspection endpoint
+///
+/// Each request is introspected: a revoked token is rejected right away.
+pub struct OidcIntrospectionAuthenticator {
+    client: reqwest::Client,
+    settings: OidcSettings,
+}
+
+impl OidcIntrospectionAuthenticator {
+    pub fn try_new(settings: OidcSettings) -> Result<Self, reqwest::Error> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(settings.timeout_ms))
+            .build()?;
+
+        Ok(Self { client, settings })
+    }
+}
+
+impl AuthenticatorPort for OidcIntrospectionAuthenticator {
+    fn authenticate(
+        &self,
+        headers: &HeaderMap,
+    ) -> LocalBoxFuture<'_, Result<Uuid, AuthenticationError>> {
+        let token = bearer_token(headers);
+
+        Box::pin(async move {
+            let token = token.ok_or(AuthenticationError::MissingCredentials)?;
+
+            let response = self
+                .client
+                .post(&self.settings.introspection_url)
+                .basic_auth(
+                    &self.settings.client_id,
+                    Some(self.settings.client_secret.expose_secret()),
+                )
+                .form(&[("token", token.as_str())])
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|error| {
+                    error!(?error, "Failed to introspect the access token");
+                    AuthenticationError::BackendError(error.to_string())
+                })?
+                .json::<IntrospectionResponse>()
+                .await
+                .map_err(|error| AuthenticationError::BackendError(error.to_string()))?;
+
+            user_id_from_introspection(response, &self.settings.issuer)
+        })
+    }
+}
+
+/// User of an introspected token, from its subject
+fn user_id_from_introspection(
+    response: IntrospectionResponse,
+    issuer: &str,
+) -> Result<Uuid, AuthenticationError> {
+    if !response.active {
+        return Err(AuthenticationError::InvalidCredentials(
+            "The access token is not active".to_string(),
+        ));
+    }
+
+    match response.sub {
+        Some(subject) if !subject.is_empty() => Ok(external_user_id(issuer, &subject)),
+        _ => Err(AuthenticationError::InvalidCredentials(
+            "The access token has no subject".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn active_token_is_attributed_to_its_subject() {
+        let response: IntrospectionResponse = serde_json::from_value(json!({
+            "active": true,
+            "sub": "alice",
+            "client_id": "gateway",
+            "exp": 1700000000,
+        }))
+        .unwrap();
+
+        assert_eq!(
+            user_id_from_introspection(response, "https://idp.example.com").unwrap(),
+            external_user_id("https://idp.example.com", "alice")
+        );
+    }
+
+    #[test]
+    fn inactive_token_or_token_without_subject_is_rejected() {
+        let inactive: IntrospectionResponse =
+            serde_json::from_value(json!({ "active": false })).unwrap();
+        let without_subject: IntrospectionResponse =
+            serde_json::from_value(json!({ "active": true })).unwrap();
+
+        assert!(matches!(
+            user_id_from_introspection(inactive, "https://idp.example.com"),
+            Err(AuthenticationError::InvalidCredentials(_))
+        ));
+        assert!(matches!(
+            user_id_from_introspection(without_subject, "https://idp.example.com"),
+            Err(AuthenticationError::InvalidCredentials(_))
+        ));
+    }
+}
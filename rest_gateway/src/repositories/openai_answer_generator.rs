@@ -0,0 +1,512 @@
+use futures::{
+    future::LocalBoxFuture,
+    stream::{self, LocalBoxStream},
+    StreamExt,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, time::Duration};
+use tracing::info;
+
+use crate::{
+    configuration::AnswerGenerationSettings,
+    domain::entities::answer::AnswerPrompt,
+    repositories::answer_generation_port::{
+        AnswerChunk, AnswerGenerationError, AnswerGenerationPort, GeneratedAnswer,
+    },
+};
+
+/// Generates the answers with an LLM backend exposing an OpenAI compatible chat completions API
+///
+/// Besides OpenAI, it is exposed by Mistral and by the self-hosted servers, for ex vLLM or Ollama.
+/// The streamed answers are read from the Server-Sent Events of the completion.
+pub struct OpenAiAnswerGenerator {
+    client: reqwest::Client,
+    api_url: String,
+    api_key: Option<Secret<String>>,
+    model: String,
+    /// Whether the usage of a streamed completion is requested, not supported by all the APIs
+    include_stream_usage: bool,
+}
+
+#[derive(Serialize)]
+struct ChatCompletionRequest<'a> {
+    model: &'a str,
+    messages: [ChatMessage<'a>; 2],
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+#[derive(Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    role: &'static str,
+    content: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionResponse {
+    choices: Vec<ChatCompletionChoice>,
+    #[serde(default)]
+    usage: Option<CompletionUsage>,
+}
+
+/// Tokens billed for a completion
+#[derive(Deserialize)]
+struct CompletionUsage {
+    total_tokens: u64,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionChoice {
+    message: ChatCompletionMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionMessage {
+    content: Option<String>,
+}
+
+/// Event of a streamed completion, with the next tokens of the answer, and the usage of the completion in its last event
+#[derive(Deserialize)]
+struct ChatCompletionChunk {
+    #[serde(default)]
+    choices: Vec<ChatCompletionChunkChoice>,
+    #[serde(default)]
+    usage: Option<CompletionUsage>,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionChunkChoice {
+    delta: ChatCompletionMessage,
+}
+
+/// Line of the Server-Sent Events of a streamed completion
+#[derive(Debug, PartialEq)]
+enum StreamLine {
+    /// Next tokens of the answer, or the usage of the completion
+    Chunks(Vec<AnswerChunk>),
+    /// End of the completion
+    Done,
+    /// Comments, other fields, and events without content
+    Ignored,
+}
+
+fn parse_stream_line(line: &str) -> Result<StreamLine, AnswerGenerationError> {
+    let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+        return Ok(StreamLine::Ignored);
+    };
+    if data == "[DONE]" {
+        return Ok(StreamLine::Done);
+    }
+
+    let chunk: ChatCompletionChunk = serde_json::from_str(data)
+        .map_err(|error| AnswerGenerationError::InvalidResponse(error.to_string()))?;
+
+    let token = chunk
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.delta.content)
+        .filter(|content| !content.is_empty())
+        .map(AnswerChunk::Token);
+    let usage = chunk.usage.map(|usage| AnswerChunk::Usage {
+        nb_tokens: usage.total_tokens,
+    });
+    let chunks: Vec<AnswerChunk> = token.into_iter().chain(usage).collect();
+
+    if chunks.is_empty() {
+        return Ok(StreamLine::Ignored);
+    }
+
+    Ok(StreamLine::Chunks(chunks))
+}
+
+/// Tokens of a streamed completion, read from its response as they are received
+struct ResponseTokens {
+    response: reqwest::Response,
+    /// Received bytes of the current line
+    buffer: Vec<u8>,
+    tokens: VecDeque<AnswerChunk>,
+    is_done: bool,
+}
+
+impl ResponseTokens {
+    fn new(response: reqwest::Response) -> Self {
+        Self {
+            response,
+            buffer: vec![],
+            tokens: VecDeque::new(),
+            is_done: false,
+        }
+    }
+
+    async fn next_token(&mut self) -> Option<Result<AnswerChunk, AnswerGenerationError>> {
+        loop {
+            if let Some(token) = self.tokens.pop_front() {
+                return Some(Ok(token));
+            }
+            if self.is_done {
+                return None;
+            }
+
+            match self.response.chunk().await {
+                Ok(Some(bytes)) => self.buffer.extend_from_slice(&bytes),
+                // A last line may not end with a line break
+                Ok(None) => {
+                    self.buffer.push(b'\n');
+                    self.is_done = true;
+                }
+                Err(error) => return Some(Err(AnswerGenerationError::Unreachable(error))),
+            }
+            if let Err(error) = self.read_lines() {
+                self.is_done = true;
+                return Some(Err(error));
+            }
+        }
+    }
+
+    /// Reads the tokens of the complete lines of the buffer
+    fn read_lines(&mut self) -> Result<(), AnswerGenerationError> {
+        while let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+
+            match parse_stream_line(String::from_utf8_lossy(&line).trim())? {
+                StreamLine::Chunks(chunks) => self.tokens.extend(chunks),
+                StreamLine::Done => {
+                    self.is_done = true;
+                    self.buffer.clear();
+                }
+                StreamLine::Ignored => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// State of a streamed completion
+enum Completion {
+    Requested(reqwest::RequestBuilder),
+    Streamed(ResponseTokens),
+}
+
+/// Sends a completion request, checking its response status
+async fn send(
+    request: reqwest::RequestBuilder,
+) -> Result<reqwest::Response, AnswerGenerationError> {
+    let response = request
+        .send()
+        .await
+        .map_err(AnswerGenerationError::Unreachable)?;
+
+    match response.status() {
+        status if status.is_success() => Ok(response),
+        status => Err(AnswerGenerationError::UnexpectedStatus(status)),
+    }
+}
+
+impl OpenAiAnswerGenerator {
+    pub fn try_new(settings: &AnswerGenerationSettings) -> Result<Self, reqwest::Error> {
+        info!(
+            "Answering the questions with the model {} on {}",
+            settings.model, settings.api_url
+        );
+
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(settings.timeout_s))
+                .build()?,
+            api_url: settings.api_url.clone(),
+            api_key: settings.api_key.clone(),
+            model: settings.model.clone(),
+            include_stream_usage: false,
+        })
+    }
+
+    /// Generates the answers with the model of another API, for ex a provider brought by a tenant
+    ///
+    /// # Arguments
+    /// * `include_stream_usage` - whether the API reports the usage of a streamed completion only when requested, as OpenAI
+    pub fn with_model(
+        client: reqwest::Client,
+        api_url: &str,
+        api_key: Secret<String>,
+        model: &str,
+        include_stream_usage: bool,
+    ) -> Self {
+        Self {
+            client,
+            api_url: api_url.to_string(),
+            api_key: Some(api_key),
+            model: model.to_string(),
+            include_stream_usage,
+        }
+    }
+
+    fn completion_request(&self, prompt: &AnswerPrompt, stream: bool) -> reqwest::RequestBuilder {
+        let request = self
+            .client
+            .post(format!("{}/chat/completions", self.api_url))
+            .json(&ChatCompletionRequest {
+                model: &self.model,
+                messages: [
+                    ChatMessage {
+                        role: "system",
+                        content: &prompt.instructions,
+                    },
+                    ChatMessage {
+                        role: "user",
+                        content: &prompt.question,
+                    },
+                ],
+                stream,
+                stream_options: (stream && self.include_stream_usage).then_some(StreamOptions {
+                    include_usage: true,
+                }),
+            });
+
+        // The self-hosted servers may not require any key
+        match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key.expose_secret()),
+            None => request,
+        }
+    }
+}
+
+impl AnswerGenerationPort for OpenAiAnswerGenerator {
+    fn is_enabled(&self) -> bool {
+        true
+    }
+
+    fn generate<'a>(
+        &'a self,
+        prompt: &'a AnswerPrompt,
+    ) -> LocalBoxFuture<'a, Result<GeneratedAnswer, AnswerGenerationError>> {
+        Box::pin(async move {
+            let response = send(self.completion_request(prompt, false)).await?;
+            let completion: ChatCompletionResponse = response
+                .json()
+                .await
+                .map_err(|error| AnswerGenerationError::InvalidResponse(error.to_string()))?;
+
+            let text = completion
+                .choices
+                .into_iter()
+                .next()
+                .and_then(|choice| choice.message.content)
+                .ok_or_else(|| {
+                    AnswerGenerationError::InvalidResponse("the completion has no content".into())
+                })?;
+
+            Ok(GeneratedAnswer {
+                text,
+                nb_tokens: completion.usage.map_or(0, |usage| usage.total_tokens),
+            })
+        })
+    }
+
+    fn generate_stream(
+        &self,
+        prompt: &AnswerPrompt,
+    ) -> LocalBoxStream<'static, Result<AnswerChunk, AnswerGenerationError>> {
+        // The request is only sent once the stream is polled
+        let request = self.completion_request(prompt, true);
+
+        stream::unfold(Some(Completion::Requested(request)), |state| async move {
+            let mut tokens = match state? {
+                Completion::Requested(request) => match send(request).await {
+                    Ok(response) => ResponseTokens::new(response),
+                    Err(error) => return Some((Err(error), None)),
+                },
+                Completion::Streamed(tokens) => tokens,
+            };
+
+            match tokens.next_token().await? {
+                Ok(token) => Some((Ok(token), Some(Completion::Streamed(tokens)))),
+                // The stream ends with its first error
+                Err(error) => Some((Err(error), None)),
+            }
+        })
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Serves a single request with a given body, returning the base URL and the received request body
+    fn serve_once(
+        content_type: &'static str,
+        body: &'static str,
+    ) -> (String, std::thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut request_body = vec![0; content_length];
+            reader.read_exact(&mut request_body).unwrap();
+
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\ncontent-type: {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                content_type,
+                body.len(),
+                body
+            )
+            .unwrap();
+
+            String::from_utf8(request_body).unwrap()
+        });
+
+        (url, handle)
+    }
+
+    fn generator(url: &str) -> OpenAiAnswerGenerator {
+        OpenAiAnswerGenerator::try_new(&AnswerGenerationSettings {
+            enabled: true,
+            api_url: url.to_string(),
+            api_key: Some(Secret::new("sk-test".to_string())),
+            model: "gpt-4o-mini".to_string(),
+            timeout_s: 5,
+            default_top_k: 5,
+            max_top_k: 20,
+            keep_alive_interval_s: 15,
+        })
+        .unwrap()
+    }
+
+    fn prompt() -> AnswerPrompt {
+        AnswerPrompt {
+            instructions: "Answer from the sources".to_string(),
+            question: "What is a pod?".to_string(),
+        }
+    }
+
+    #[test]
+    fn stream_lines_are_parsed_to_tokens() {
+        assert_eq!(
+            parse_stream_line(r#"data: {"choices":[{"delta":{"content":"Pods"}}]}"#).unwrap(),
+            StreamLine::Chunks(vec![AnswerChunk::Token("Pods".to_string())])
+        );
+        assert_eq!(
+            parse_stream_line(r#"data: {"choices":[],"usage":{"prompt_tokens":80,"completion_tokens":4,"total_tokens":84}}"#)
+                .unwrap(),
+            StreamLine::Chunks(vec![AnswerChunk::Usage { nb_tokens: 84 }])
+        );
+        assert_eq!(
+            parse_stream_line(r#"data: {"choices":[{"delta":{"role":"assistant"}}]}"#).unwrap(),
+            StreamLine::Ignored
+        );
+        assert_eq!(parse_stream_line("data: [DONE]").unwrap(), StreamLine::Done);
+        assert_eq!(parse_stream_line(": ping").unwrap(), StreamLine::Ignored);
+        assert!(parse_stream_line("data: {").is_err());
+    }
+
+    #[tokio::test]
+    async fn answer_is_the_content_of_the_completion() {
+        let (url, handle) = serve_once(
+            "application/json",
+            r#"{"choices":[{"message":{"role":"assistant","content":"Pods run containers [1]."}}],"usage":{"prompt_tokens":80,"completion_tokens":6,"total_tokens":86}}"#,
+        );
+
+        let answer = generator(&url).generate(&prompt()).await.unwrap();
+
+        assert_eq!(
+            answer,
+            GeneratedAnswer {
+                text: "Pods run containers [1].".to_string(),
+                nb_tokens: 86,
+            }
+        );
+        let request: serde_json::Value = serde_json::from_str(&handle.join().unwrap()).unwrap();
+        assert_eq!(request["model"], "gpt-4o-mini");
+        assert_eq!(request["stream"], false);
+        assert_eq!(request["messages"][1]["content"], "What is a pod?");
+    }
+
+    #[tokio::test]
+    async fn streamed_answer_is_read_token_by_token() {
+        let (url, handle) = serve_once(
+            "text/event-stream",
+            "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n\
+            data: {\"choices\":[{\"delta\":{\"content\":\"Pods\"}}]}\n\n\
+            data: {\"choices\":[{\"delta\":{\"content\":\" run [1].\"}}]}\n\n\
+            data: [DONE]\n\n",
+        );
+
+        let tokens: Vec<AnswerChunk> = generator(&url)
+            .generate_stream(&prompt())
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        assert_eq!(
+            tokens,
+            vec![
+                AnswerChunk::Token("Pods".to_string()),
+                AnswerChunk::Token(" run [1].".to_string())
+            ]
+        );
+        let request: serde_json::Value = serde_json::from_str(&handle.join().unwrap()).unwrap();
+        assert_eq!(request["stream"], true);
+        assert!(request.get("stream_options").is_none());
+    }
+
+    #[tokio::test]
+    async fn streamed_answer_of_a_tenant_model_ends_with_its_usage() {
+        let (url, handle) = serve_once(
+            "text/event-stream",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Pods\"}}]}\n\n\
+            data: {\"choices\":[],\"usage\":{\"total_tokens\":84}}\n\n\
+            data: [DONE]\n\n",
+        );
+        let generator = OpenAiAnswerGenerator::with_model(
+            reqwest::Client::new(),
+            &url,
+            Secret::new("sk-tenant".to_string()),
+            "gpt-4o",
+            true,
+        );
+
+        let tokens: Vec<AnswerChunk> = generator
+            .generate_stream(&prompt())
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        assert_eq!(
+            tokens,
+            vec![
+                AnswerChunk::Token("Pods".to_string()),
+                AnswerChunk::Usage { nb_tokens: 84 }
+            ]
+        );
+        let request: serde_json::Value = serde_json::from_str(&handle.join().unwrap()).unwrap();
+        assert_eq!(request["model"], "gpt-4o");
+        assert_eq!(request["stream_options"]["include_usage"], true);
+    }
+}
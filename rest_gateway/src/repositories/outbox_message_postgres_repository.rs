@@ -0,0 +1,182 @@
+use chrono::{DateTime, Utc};
+use common::helper::error_chain_fmt;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::domain::entities::outbox_message::OutboxMessage;
+
+/// Outbox of the messages to publish, implemented using Postgres
+pub struct OutboxMessagePostgresRepository {}
+
+impl Default for OutboxMessagePostgresRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OutboxMessagePostgresRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Adds a message to the outbox, in the transaction of the changes it announces
+    #[tracing::instrument(
+        name = "Adding outbox message in database",
+        skip(self, db_executor, message),
+        fields(message_id = %message.id, routing_key = message.routing_key)
+    )]
+    pub async fn add_message(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        message: &OutboxMessage,
+    ) -> Result<(), OutboxMessagePostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    INSERT INTO outbox_messages (id, tenant_id, routing_key, payload, created_at, next_attempt_at)
+    VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            message.id,
+            message.tenant_id,
+            message.routing_key,
+            message.payload,
+            message.created_at,
+            message.next_attempt_at,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Claims the messages not sent by a given time, pushing back their next attempt by a retry delay
+    ///
+    /// The claimed messages are skipped by the concurrent claims of the other gateway instances.
+    /// A message claimed but not marked as sent, for ex if its relay crashed, is claimed again after the delay.
+    ///
+    /// # Arguments
+    /// * `limit` - Maximum number of claimed messages, from the oldest
+    #[tracing::instrument(
+        name = "Claiming pending outbox messages in database",
+        skip(self, db_executor)
+    )]
+    pub async fn claim_pending_messages(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        now: DateTime<Utc>,
+        retry_delay_ms: i64,
+        limit: i64,
+    ) -> Result<Vec<OutboxMessage>, OutboxMessagePostgresRepositoryError> {
+        let messages = sqlx::query_as!(
+            OutboxMessage,
+            r#"
+    UPDATE outbox_messages
+    SET next_attempt_at = $1 + make_interval(secs => $2::BIGINT / 1000.0), nb_attempts = nb_attempts + 1
+    WHERE id IN (
+        SELECT id
+        FROM outbox_messages
+        WHERE sent_at IS NULL AND next_attempt_at <= $1
+        ORDER BY created_at
+        LIMIT $3
+        FOR UPDATE SKIP LOCKED
+    )
+    RETURNING id, tenant_id, routing_key, payload, created_at, next_attempt_at
+            "#,
+            now,
+            retry_delay_ms,
+            limit,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(messages)
+    }
+
+    #[tracing::instrument(
+        name = "Setting outbox message as sent in database",
+        skip(self, db_executor)
+    )]
+    pub async fn set_sent(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        message_id: Uuid,
+        sent_at: DateTime<Utc>,
+    ) -> Result<(), OutboxMessagePostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    UPDATE outbox_messages
+    SET sent_at = $2, last_error = NULL
+    WHERE id = $1 AND sent_at IS NULL
+            "#,
+            message_id,
+            sent_at,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Records the error of a failed publication: the message is published again at its next attempt
+    #[tracing::instrument(
+        name = "Setting outbox message as failed in database",
+        skip(self, db_executor)
+    )]
+    pub async fn set_failed(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        message_id: Uuid,
+        error: &str,
+    ) -> Result<(), OutboxMessagePostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    UPDATE outbox_messages
+    SET last_error = $2
+    WHERE id = $1
+            "#,
+            message_id,
+            error,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deletes the messages sent before a given time
+    ///
+    /// # Returns
+    /// The number of deleted messages
+    #[tracing::instrument(
+        name = "Deleting sent outbox messages in database",
+        skip(self, db_executor)
+    )]
+    pub async fn delete_sent_messages(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        sent_before: DateTime<Utc>,
+    ) -> Result<u64, OutboxMessagePostgresRepositoryError> {
+        let result = sqlx::query!(
+            r#"
+    DELETE FROM outbox_messages
+    WHERE sent_at < $1
+            "#,
+            sent_before,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum OutboxMessagePostgresRepositoryError {
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for OutboxMessagePostgresRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
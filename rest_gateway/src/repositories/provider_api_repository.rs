@@ -0,0 +1,157 @@
+use common::helper::error_chain_fmt;
+use reqwest::StatusCode;
+use secrecy::{ExposeSecret, Secret};
+use std::time::Duration;
+
+use crate::{
+    configuration::ProviderCredentialsSettings,
+    domain::entities::provider_credentials::ModelProvider,
+};
+
+/// Client of the HTTP APIs of the model providers, to check the API keys brought by the tenants
+///
+/// The supported providers expose an OpenAI compatible API.
+pub struct ProviderApiRepository {
+    client: reqwest::Client,
+    settings: ProviderCredentialsSettings,
+}
+
+impl ProviderApiRepository {
+    pub fn try_new(settings: &ProviderCredentialsSettings) -> Result<Self, reqwest::Error> {
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(settings.validation_timeout_s))
+                .build()?,
+            settings: settings.clone(),
+        })
+    }
+
+    /// Checks that an API key is valid and gives access to a model, by retrieving the model
+    #[tracing::instrument(name = "Checking provider API key", skip(self, api_key))]
+    pub async fn check_api_key(
+        &self,
+        provider: ModelProvider,
+        api_key: &Secret<String>,
+        model: &str,
+    ) -> Result<(), ProviderApiRepositoryError> {
+        let response = self
+            .client
+            .get(format!(
+                "{}/models/{}",
+                self.settings.api_url(provider),
+                model
+            ))
+            .bearer_auth(api_key.expose_secret())
+            .send()
+            .await
+            .map_err(ProviderApiRepositoryError::Unreachable)?;
+
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Err(ProviderApiRepositoryError::InvalidApiKey)
+            }
+            StatusCode::NOT_FOUND => {
+                Err(ProviderApiRepositoryError::UnknownModel(model.to_string()))
+            }
+            status => Err(ProviderApiRepositoryError::UnexpectedStatus(status)),
+        }
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum ProviderApiRepositoryError {
+    #[error("The API key was rejected by the provider")]
+    InvalidApiKey,
+    #[error("Unknown model {0}, or the API key has no access to it")]
+    UnknownModel(String),
+    #[error("The provider could not be reached: {0}")]
+    Unreachable(reqwest::Error),
+    #[error("Unexpected response status from the provider: {0}")]
+    UnexpectedStatus(StatusCode),
+}
+
+impl std::fmt::Debug for ProviderApiRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// Serves a single request with a given status, returning the base URL and the received request line
+    fn serve_once(status_line: &'static str) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let request: Vec<String> = BufReader::new(stream.try_clone().unwrap())
+                .lines()
+                .map(Result::unwrap)
+                .take_while(|line| !line.is_empty())
+                .collect();
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                status_line
+            )
+            .unwrap();
+
+            request
+        });
+
+        (url, handle)
+    }
+
+    fn repository(url: &str) -> ProviderApiRepository {
+        ProviderApiRepository::try_new(&ProviderCredentialsSettings {
+            openai_api_url: url.to_string(),
+            mistral_api_url: url.to_string(),
+            validation_timeout_s: 5,
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn valid_api_key_is_checked_by_retrieving_the_model() {
+        let (url, handle) = serve_once("200 OK");
+        let api_key = Secret::new("sk-test".to_string());
+
+        let result = repository(&url)
+            .check_api_key(ModelProvider::OpenAi, &api_key, "text-embedding-3-small")
+            .await;
+
+        assert!(result.is_ok());
+        let request = handle.join().unwrap();
+        assert_eq!(request[0], "GET /models/text-embedding-3-small HTTP/1.1");
+        assert!(request
+            .iter()
+            .any(|line| line.eq_ignore_ascii_case("authorization: Bearer sk-test")));
+    }
+
+    #[tokio::test]
+    async fn rejected_api_key_and_unknown_model_are_reported() {
+        let api_key = Secret::new("sk-test".to_string());
+
+        let (url, _) = serve_once("401 Unauthorized");
+        assert!(matches!(
+            repository(&url)
+                .check_api_key(ModelProvider::Mistral, &api_key, "mistral-embed")
+                .await,
+            Err(ProviderApiRepositoryError::InvalidApiKey)
+        ));
+
+        let (url, _) = serve_once("404 Not Found");
+        assert!(matches!(
+            repository(&url)
+                .check_api_key(ModelProvider::Mistral, &api_key, "unknown")
+                .await,
+            Err(ProviderApiRepositoryError::UnknownModel(model)) if model == "unknown"
+        ));
+    }
+}
@@ -0,0 +1,161 @@
+use chrono::Utc;
+use common::helper::error_chain_fmt;
+use sqlx::PgExecutor;
+
+use crate::domain::entities::provider_credentials::{
+    ModelProvider, ProviderCredentials, ProviderPurpose,
+};
+
+/// Repository of the provider credentials of the tenants, implemented using Postgres
+pub struct ProviderCredentialsPostgresRepository {}
+
+impl Default for ProviderCredentialsPostgresRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProviderCredentialsPostgresRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Saves the credentials of a tenant for a purpose, replacing the previous ones
+    ///
+    /// The usage of the previous key is kept: it is attributed to the tenant, not to a key.
+    #[tracing::instrument(
+        name = "Saving tenant provider credentials in database",
+        skip(self, db_executor, credentials),
+        fields(tenant_id = credentials.tenant_id, purpose = ?credentials.purpose)
+    )]
+    pub async fn save_credentials(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        credentials: &ProviderCredentials,
+    ) -> Result<(), ProviderCredentialsPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    INSERT INTO tenant_provider_credentials (tenant_id, purpose, provider, model, encrypted_api_key,
+        api_key_hint, updated_by, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+    ON CONFLICT (tenant_id, purpose) DO UPDATE
+    SET provider = EXCLUDED.provider, model = EXCLUDED.model, encrypted_api_key = EXCLUDED.encrypted_api_key,
+        api_key_hint = EXCLUDED.api_key_hint, updated_by = EXCLUDED.updated_by, updated_at = EXCLUDED.updated_at
+            "#,
+            credentials.tenant_id,
+            credentials.purpose as ProviderPurpose,
+            credentials.provider as ModelProvider,
+            credentials.model,
+            credentials.encrypted_api_key,
+            credentials.api_key_hint,
+            credentials.updated_by,
+            credentials.created_at,
+            credentials.updated_at
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "Listing tenant provider credentials from database",
+        skip(self, db_executor)
+    )]
+    pub async fn list_tenant_credentials(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        tenant_id: &str,
+    ) -> Result<Vec<ProviderCredentials>, ProviderCredentialsPostgresRepositoryError> {
+        let credentials = sqlx::query_as!(
+            ProviderCredentials,
+            r#"
+    SELECT tenant_id, purpose AS "purpose: ProviderPurpose", provider AS "provider: ModelProvider",
+        model, encrypted_api_key, api_key_hint, nb_requests, nb_tokens, last_used_at, updated_by,
+        created_at, updated_at
+    FROM tenant_provider_credentials
+    WHERE tenant_id = $1
+    ORDER BY purpose
+            "#,
+            tenant_id,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(credentials)
+    }
+
+    /// Deletes the credentials of a tenant for a purpose
+    ///
+    /// # Returns
+    /// `false` if the tenant had no credentials for this purpose
+    #[tracing::instrument(
+        name = "Deleting tenant provider credentials from database",
+        skip(self, db_executor)
+    )]
+    pub async fn delete_credentials(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        tenant_id: &str,
+        purpose: ProviderPurpose,
+    ) -> Result<bool, ProviderCredentialsPostgresRepositoryError> {
+        let result = sqlx::query!(
+            r#"
+    DELETE FROM tenant_provider_credentials
+    WHERE tenant_id = $1 AND purpose = $2
+            "#,
+            tenant_id,
+            purpose as ProviderPurpose,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Attributes a usage of its provider to a tenant
+    ///
+    /// # Returns
+    /// `false` if the tenant has no credentials for this purpose
+    #[tracing::instrument(
+        name = "Recording tenant provider usage in database",
+        skip(self, db_executor)
+    )]
+    pub async fn record_usage(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        tenant_id: &str,
+        purpose: ProviderPurpose,
+        nb_requests: i64,
+        nb_tokens: i64,
+    ) -> Result<bool, ProviderCredentialsPostgresRepositoryError> {
+        let result = sqlx::query!(
+            r#"
+    UPDATE tenant_provider_credentials
+    SET nb_requests = nb_requests + $3, nb_tokens = nb_tokens + $4, last_used_at = $5
+    WHERE tenant_id = $1 AND purpose = $2
+            "#,
+            tenant_id,
+            purpose as ProviderPurpose,
+            nb_requests,
+            nb_tokens,
+            Utc::now(),
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum ProviderCredentialsPostgresRepositoryError {
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for ProviderCredentialsPostgresRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,123 @@
+use common::helper::error_chain_fmt;
+use reqwest::StatusCode;
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+use serde_json::json;
+
+/// Client of the RabbitMQ management HTTP API, to inspect the queues
+pub struct RabbitMQManagementRepository {
+    client: reqwest::Client,
+    base_url: String,
+    username: String,
+    password: Secret<String>,
+}
+
+/// State of a queue, as reported by the management API
+#[derive(Debug, Deserialize)]
+pub struct QueueInfo {
+    pub vhost: String,
+    pub name: String,
+    /// Ready and unacknowledged messages
+    #[serde(default)]
+    pub messages: u64,
+    #[serde(default)]
+    pub messages_ready: u64,
+    #[serde(default)]
+    pub messages_unacknowledged: u64,
+    #[serde(default)]
+    pub consumers: u64,
+}
+
+/// Message read from a queue without being consumed
+#[derive(Debug, Deserialize)]
+pub struct PeekedMessage {
+    pub exchange: String,
+    pub routing_key: String,
+    pub redelivered: bool,
+    pub payload: String,
+    /// `string`, or `base64` if the payload is not valid UTF-8
+    pub payload_encoding: String,
+}
+
+impl RabbitMQManagementRepository {
+    pub fn new(base_url: String, username: String, password: Secret<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url,
+            username,
+            password,
+        }
+    }
+
+    /// Lists the queues of all the virtual hosts
+    #[tracing::instrument(name = "Listing queues from RabbitMQ management API", skip(self))]
+    pub async fn list_queues(&self) -> Result<Vec<QueueInfo>, RabbitMQManagementRepositoryError> {
+        let queues = self
+            .client
+            .get(format!("{}/queues", self.base_url))
+            .basic_auth(&self.username, Some(self.password.expose_secret()))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(queues)
+    }
+
+    /// Reads the first messages of a queue
+    ///
+    /// The messages are requeued: they are flagged as redelivered, and their order in the queue can change.
+    #[tracing::instrument(name = "Peeking messages from RabbitMQ management API", skip(self))]
+    pub async fn peek_messages(
+        &self,
+        vhost: &str,
+        queue_name: &str,
+        count: u32,
+    ) -> Result<Vec<PeekedMessage>, RabbitMQManagementRepositoryError> {
+        let response = self
+            .client
+            .post(format!(
+                "{}/queues/{}/{}/get",
+                self.base_url,
+                encode_path_segment(vhost),
+                encode_path_segment(queue_name)
+            ))
+            .basic_auth(&self.username, Some(self.password.expose_secret()))
+            .json(&json!({
+                "count": count,
+                "ackmode": "ack_requeue_true",
+                "encoding": "auto",
+            }))
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(RabbitMQManagementRepositoryError::QueueNotFound(format!(
+                "{} on virtual host {}",
+                queue_name, vhost
+            )));
+        }
+
+        Ok(response.error_for_status()?.json().await?)
+    }
+}
+
+/// The virtual host and queue names are path segments: "/" needs to be percent-encoded
+fn encode_path_segment(segment: &str) -> String {
+    segment.replace('%', "%25").replace('/', "%2F")
+}
+
+#[derive(thiserror::Error)]
+pub enum RabbitMQManagementRepositoryError {
+    #[error("Queue not found: {0}")]
+    QueueNotFound(String),
+    #[error(transparent)]
+    RequestError(#[from] reqwest::Error),
+}
+
+impl std::fmt::Debug for RabbitMQManagementRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,154 @@
+use chrono::Utc;
+use common::helper::error_chain_fmt;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::domain::entities::refresh_token::RefreshToken;
+
+/// Refresh token repository implemented using Postgres
+pub struct RefreshTokenPostgresRepository {}
+
+impl Default for RefreshTokenPostgresRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RefreshTokenPostgresRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    #[tracing::instrument(name = "Saving new refresh token in database", skip_all)]
+    pub async fn add_token(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        token: &RefreshToken,
+    ) -> Result<(), RefreshTokenPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    INSERT INTO refresh_tokens (id, user_id, session_id, token_hash, expires_at, revoked_at, created_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            token.id,
+            token.user_id,
+            token.session_id,
+            token.token_hash,
+            token.expires_at,
+            token.revoked_at,
+            token.created_at
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Gets a refresh token from its hash, locking it until the end of the transaction to rotate it
+    #[tracing::instrument(name = "Getting refresh token for update from database", skip_all)]
+    pub async fn get_token_by_hash_for_update(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        token_hash: &str,
+    ) -> Result<Option<RefreshToken>, RefreshTokenPostgresRepositoryError> {
+        let token = sqlx::query_as!(
+            RefreshToken,
+            r#"
+    SELECT id, user_id, session_id, token_hash, expires_at, revoked_at, created_at
+    FROM refresh_tokens
+    WHERE token_hash = $1
+    FOR UPDATE
+            "#,
+            token_hash,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(token)
+    }
+
+    #[tracing::instrument(name = "Revoking refresh token in database", skip(self, db_executor))]
+    pub async fn revoke_token(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        token_id: Uuid,
+    ) -> Result<(), RefreshTokenPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    UPDATE refresh_tokens
+    SET revoked_at = $2
+    WHERE id = $1 AND revoked_at IS NULL
+            "#,
+            token_id,
+            Utc::now(),
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Revokes all the refresh tokens of a session: the access tokens of the session are then rejected
+    #[tracing::instrument(
+        name = "Revoking refresh tokens of session in database",
+        skip(self, db_executor)
+    )]
+    pub async fn revoke_session(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        session_id: Uuid,
+    ) -> Result<(), RefreshTokenPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    UPDATE refresh_tokens
+    SET revoked_at = $2
+    WHERE session_id = $1 AND revoked_at IS NULL
+            "#,
+            session_id,
+            Utc::now(),
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Checks that a session of a user has not been revoked, ie it still has a refresh token not revoked
+    #[tracing::instrument(
+        name = "Checking session is active in database",
+        skip(self, db_executor)
+    )]
+    pub async fn is_session_active(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        session_id: Uuid,
+    ) -> Result<bool, RefreshTokenPostgresRepositoryError> {
+        let record = sqlx::query!(
+            r#"
+    SELECT EXISTS(
+        SELECT 1 FROM refresh_tokens
+        WHERE session_id = $1 AND user_id = $2 AND revoked_at IS NULL
+    ) AS "is_active!"
+            "#,
+            session_id,
+            user_id,
+        )
+        .fetch_one(db_executor)
+        .await?;
+
+        Ok(record.is_active)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum RefreshTokenPostgresRepositoryError {
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for RefreshTokenPostgresRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,41 @@
+use common::helper::error_chain_fmt;
+use futures::future::LocalBoxFuture;
+
+/// Rescores the candidates of a search by their relevance to its query, with a cross-encoder
+///
+/// Port to decouple the search pipeline from the reranking model of a deployment.
+/// Without reranker, the searches cannot be reranked.
+pub trait RerankPort: Send + Sync {
+    /// Whether a reranker is configured
+    fn is_enabled(&self) -> bool;
+
+    /// Number of candidates retrieved from the search backends to be reranked,
+    /// the results of a page beyond them being retrieved as well
+    fn candidates(&self) -> usize;
+
+    /// # Returns
+    /// The relevance score of each document to the query, in the order of the documents
+    fn rerank<'a>(
+        &'a self,
+        query: &'a str,
+        documents: &'a [&'a str],
+    ) -> LocalBoxFuture<'a, Result<Vec<f64>, RerankError>>;
+}
+
+#[derive(thiserror::Error)]
+pub enum RerankError {
+    #[error("No reranker is configured")]
+    Disabled,
+    #[error("The reranker could not be reached: {0}")]
+    Unreachable(reqwest::Error),
+    #[error("Unexpected response status from the reranker: {0}")]
+    UnexpectedStatus(reqwest::StatusCode),
+    #[error("Invalid response from the reranker: {0}")]
+    InvalidResponse(String),
+}
+
+impl std::fmt::Debug for RerankError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
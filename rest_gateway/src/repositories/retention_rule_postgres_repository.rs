@@ -0,0 +1,208 @@
+use chrono::{DateTime, Utc};
+use common::helper::error_chain_fmt;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::domain::entities::{
+    extraction_progress::ExtractionStatus,
+    retention_rule::{ExpiringSource, RetentionRule},
+    source_meta::{SourceMeta, SourceType},
+};
+
+/// Retention rule repository implemented using Postgres
+pub struct RetentionRulePostgresRepository {}
+
+impl Default for RetentionRulePostgresRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RetentionRulePostgresRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Saves the retention rule of a collection, replacing its previous retention if any
+    ///
+    /// # Returns
+    /// The saved rule, with the id and creation date of the replaced rule
+    #[tracing::instrument(name = "Saving retention rule in database", skip(self, db_executor))]
+    pub async fn save_rule(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        rule: &RetentionRule,
+    ) -> Result<RetentionRule, RetentionRulePostgresRepositoryError> {
+        let rule = sqlx::query_as!(
+            RetentionRule,
+            r#"
+    INSERT INTO retention_rules (id, user_id, collection, retention_days, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6)
+    ON CONFLICT (user_id, collection) DO UPDATE
+    SET retention_days = EXCLUDED.retention_days, updated_at = EXCLUDED.updated_at
+    RETURNING id, user_id, collection, retention_days, created_at, updated_at
+            "#,
+            rule.id,
+            rule.user_id,
+            rule.collection,
+            rule.retention_days,
+            rule.created_at,
+            rule.updated_at
+        )
+        .fetch_one(db_executor)
+        .await?;
+
+        Ok(rule)
+    }
+
+    #[tracing::instrument(
+        name = "Listing user retention rules in database",
+        skip(self, db_executor)
+    )]
+    pub async fn list_user_rules(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+    ) -> Result<Vec<RetentionRule>, RetentionRulePostgresRepositoryError> {
+        let rules = sqlx::query_as!(
+            RetentionRule,
+            r#"
+    SELECT id, user_id, collection, retention_days, created_at, updated_at
+    FROM retention_rules
+    WHERE user_id = $1
+    ORDER BY collection
+            "#,
+            user_id,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(rules)
+    }
+
+    /// Deletes the retention rule of a collection of a user: its sources are kept
+    ///
+    /// # Returns
+    /// False if the collection has no retention rule
+    #[tracing::instrument(
+        name = "Deleting user retention rule in database",
+        skip(self, db_executor)
+    )]
+    pub async fn delete_user_rule(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        collection: &str,
+    ) -> Result<bool, RetentionRulePostgresRepositoryError> {
+        let result = sqlx::query!(
+            r#"
+    DELETE FROM retention_rules
+    WHERE user_id = $1 AND collection = $2
+            "#,
+            user_id,
+            collection,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Lists the sources of all the users expiring before a given time, and not warned since another one
+    ///
+    /// # Arguments
+    /// * `now` - The already expired sources are not listed
+    /// * `expiring_before` - End of the warning period
+    /// * `warned_since` - The sources warned before are warned again, for ex if their retention was extended
+    /// * `limit` - Maximum number of listed sources, from the first to expire
+    #[tracing::instrument(
+        name = "Listing expiring source metas in database",
+        skip(self, db_executor)
+    )]
+    pub async fn list_expiring_sources(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        now: DateTime<Utc>,
+        expiring_before: DateTime<Utc>,
+        warned_since: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<ExpiringSource>, RetentionRulePostgresRepositoryError> {
+        let expiring_sources = sqlx::query_as!(
+            ExpiringSource,
+            r#"
+    SELECT source_metas.id AS source_meta_id, source_metas.user_id, retention_rules.collection,
+        source_metas.added_at + make_interval(days => retention_rules.retention_days) AS "expires_at!"
+    FROM source_metas
+    JOIN retention_rules
+        ON retention_rules.user_id = source_metas.user_id AND retention_rules.collection = source_metas.collection
+    WHERE source_metas.added_at + make_interval(days => retention_rules.retention_days) > $1
+        AND source_metas.added_at + make_interval(days => retention_rules.retention_days) <= $2
+        AND NOT EXISTS (
+            SELECT 1 FROM source_events
+            WHERE source_events.source_meta_id = source_metas.id
+                AND source_events.event_type = 'expiring'
+                AND source_events.occurred_at > $3
+        )
+    ORDER BY 4
+    LIMIT $4
+            "#,
+            now,
+            expiring_before,
+            warned_since,
+            limit,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(expiring_sources)
+    }
+
+    /// Lists the source metas of all the users expired at a given time
+    ///
+    /// # Arguments
+    /// * `limit` - Maximum number of listed source metas, from the first added
+    #[tracing::instrument(
+        name = "Listing expired source metas in database",
+        skip(self, db_executor)
+    )]
+    pub async fn list_expired_source_metas(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        now: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<SourceMeta>, RetentionRulePostgresRepositoryError> {
+        let source_metas = sqlx::query_as!(
+            SourceMeta,
+            r#"
+    SELECT source_metas.id, source_metas.user_id, initial_name, object_store_name,
+        source_type as "source_type: SourceType", content_hash, added_at, extracted_at,
+        extraction_status as "extraction_status: ExtractionStatus",
+        source_metas.collection, auto_filing_rule_id, upload_policy_id, content_columns, detected_mime_type, size_bytes, redact_pii
+    FROM source_metas
+    JOIN retention_rules
+        ON retention_rules.user_id = source_metas.user_id AND retention_rules.collection = source_metas.collection
+    WHERE source_metas.added_at + make_interval(days => retention_rules.retention_days) <= $1
+    ORDER BY added_at
+    LIMIT $2
+            "#,
+            now,
+            limit,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(source_metas)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum RetentionRulePostgresRepositoryError {
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for RetentionRulePostgresRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,273 @@
+use chrono::{DateTime, Utc};
+use common::helper::error_chain_fmt;
+use common::pagination::{PageCursor, PageDirection};
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::domain::entities::saved_search::{SavedSearch, SearchHistoryEntry};
+
+/// Repository of the saved searches and of the search history of the users, implemented using Postgres
+pub struct SavedSearchPostgresRepository {}
+
+/// Sort key of the listed search history entries, encoded in the page cursors
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchHistoryCursor {
+    #[serde(with = "chrono::serde::ts_microseconds")]
+    pub searched_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl Default for SavedSearchPostgresRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SavedSearchPostgresRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    #[tracing::instrument(
+        name = "Saving new saved search in database",
+        skip(self, db_executor, saved_search),
+        fields(saved_search_id = %saved_search.id)
+    )]
+    pub async fn add_saved_search(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        saved_search: &SavedSearch,
+    ) -> Result<(), SavedSearchPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    INSERT INTO saved_searches (id, user_id, name, request, created_at)
+    VALUES ($1, $2, $3, $4, $5)
+            "#,
+            saved_search.id,
+            saved_search.user_id,
+            saved_search.name,
+            saved_search.request,
+            saved_search.created_at
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Lists the saved searches of a user, from the most recent
+    #[tracing::instrument(
+        name = "Listing user saved searches in database",
+        skip(self, db_executor)
+    )]
+    pub async fn list_user_saved_searches(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+    ) -> Result<Vec<SavedSearch>, SavedSearchPostgresRepositoryError> {
+        let saved_searches = sqlx::query_as!(
+            SavedSearch,
+            r#"
+    SELECT id, user_id, name, request, created_at
+    FROM saved_searches
+    WHERE user_id = $1
+    ORDER BY created_at DESC, id DESC
+            "#,
+            user_id,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(saved_searches)
+    }
+
+    /// Gets a saved search of a user, `None` if not found or saved by another user
+    #[tracing::instrument(
+        name = "Getting user saved search from database",
+        skip(self, db_executor)
+    )]
+    pub async fn get_user_saved_search(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        saved_search_id: Uuid,
+    ) -> Result<Option<SavedSearch>, SavedSearchPostgresRepositoryError> {
+        let saved_search = sqlx::query_as!(
+            SavedSearch,
+            r#"
+    SELECT id, user_id, name, request, created_at
+    FROM saved_searches
+    WHERE id = $1 AND user_id = $2
+            "#,
+            saved_search_id,
+            user_id,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(saved_search)
+    }
+
+    /// Deletes a saved search of a user
+    ///
+    /// # Returns
+    /// False if the user has no such saved search
+    #[tracing::instrument(
+        name = "Deleting user saved search in database",
+        skip(self, db_executor)
+    )]
+    pub async fn delete_user_saved_search(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        saved_search_id: Uuid,
+    ) -> Result<bool, SavedSearchPostgresRepositoryError> {
+        let result = sqlx::query!(
+            r#"
+    DELETE FROM saved_searches
+    WHERE id = $1 AND user_id = $2
+            "#,
+            saved_search_id,
+            user_id,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(
+        name = "Saving search history entry in database",
+        skip(self, db_executor, entry),
+        fields(search_history_entry_id = %entry.id)
+    )]
+    pub async fn add_search_history_entry(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        entry: &SearchHistoryEntry,
+    ) -> Result<(), SavedSearchPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    INSERT INTO search_history (id, user_id, query, request, searched_at)
+    VALUES ($1, $2, $3, $4, $5)
+            "#,
+            entry.id,
+            entry.user_id,
+            entry.query,
+            entry.request,
+            entry.searched_at
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deletes the entries of the search history of a user but the latest ones
+    ///
+    /// # Returns
+    /// The number of deleted entries
+    #[tracing::instrument(
+        name = "Pruning user search history in database",
+        skip(self, db_executor)
+    )]
+    pub async fn prune_user_search_history(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        kept_entries: i64,
+    ) -> Result<u64, SavedSearchPostgresRepositoryError> {
+        let result = sqlx::query!(
+            r#"
+    DELETE FROM search_history
+    WHERE id IN (
+        SELECT id
+        FROM search_history
+        WHERE user_id = $1
+        ORDER BY searched_at DESC, id DESC
+        OFFSET $2
+    )
+            "#,
+            user_id,
+            kept_entries,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Lists a page of the search history of a user, from the latest search
+    ///
+    /// The entries of a previous page are listed in the reverse order, from the cursor.
+    #[tracing::instrument(
+        name = "Listing user search history in database",
+        skip(self, db_executor)
+    )]
+    pub async fn list_user_search_history(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        cursor: Option<&PageCursor<SearchHistoryCursor>>,
+        limit: i64,
+    ) -> Result<Vec<SearchHistoryEntry>, SavedSearchPostgresRepositoryError> {
+        let entries = match cursor {
+            Some(PageCursor {
+                direction: PageDirection::Previous,
+                key: before,
+            }) => {
+                sqlx::query_as!(
+                    SearchHistoryEntry,
+                    r#"
+    SELECT id, user_id, query, request, searched_at
+    FROM search_history
+    WHERE user_id = $1 AND (searched_at, id) > ($2, $3)
+    ORDER BY searched_at, id
+    LIMIT $4
+                    "#,
+                    user_id,
+                    before.searched_at,
+                    before.id,
+                    limit,
+                )
+                .fetch_all(db_executor)
+                .await?
+            }
+            after => {
+                let after = after.map(|cursor| &cursor.key);
+
+                sqlx::query_as!(
+                    SearchHistoryEntry,
+                    r#"
+    SELECT id, user_id, query, request, searched_at
+    FROM search_history
+    WHERE user_id = $1 AND ($2::timestamptz IS NULL OR (searched_at, id) < ($2, $3))
+    ORDER BY searched_at DESC, id DESC
+    LIMIT $4
+                    "#,
+                    user_id,
+                    after.map(|cursor| cursor.searched_at),
+                    after.map(|cursor| cursor.id),
+                    limit,
+                )
+                .fetch_all(db_executor)
+                .await?
+            }
+        };
+
+        Ok(entries)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum SavedSearchPostgresRepositoryError {
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for SavedSearchPostgresRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
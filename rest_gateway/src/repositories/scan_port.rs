@@ -0,0 +1,40 @@
+use common::helper::error_chain_fmt;
+use futures::future::LocalBoxFuture;
+
+/// Scans the uploaded files for viruses and malware, before they are stored
+///
+/// Port to decouple the upload of the sources from the antivirus of a deployment.
+/// Without antivirus, the files are accepted without being scanned.
+pub trait ScanPort: Send + Sync {
+    /// Whether the files are actually scanned: the uploads whose policy requires a scan are rejected otherwise
+    fn is_enabled(&self) -> bool;
+
+    /// Scans a file from its start. The file is rewound to be stored.
+    fn scan<'a>(
+        &'a self,
+        file: &'a mut std::fs::File,
+    ) -> LocalBoxFuture<'a, Result<ScanVerdict, ScanError>>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanVerdict {
+    Clean,
+    /// The file is infected, with the name of the signature found by the antivirus
+    Infected(String),
+}
+
+#[derive(thiserror::Error)]
+pub enum ScanError {
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error("The scan did not complete within {0} ms")]
+    Timeout(u64),
+    #[error("The antivirus failed to scan the file: {0}")]
+    ScannerError(String),
+}
+
+impl std::fmt::Debug for ScanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,90 @@
+use common::helper::error_chain_fmt;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::domain::entities::source_event::{SourceEvent, SourceEventType};
+
+/// Source event repository implemented using Postgres
+///
+/// The events are only appended: they are never updated nor deleted.
+pub struct SourceEventPostgresRepository {}
+
+impl Default for SourceEventPostgresRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SourceEventPostgresRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    #[tracing::instrument(name = "Saving new source event in database", skip(self, db_executor))]
+    pub async fn add_event(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        event: &SourceEvent,
+    ) -> Result<(), SourceEventPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    INSERT INTO source_events (id, source_meta_id, user_id, event_type, payload, occurred_at, recorded_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            event.id,
+            event.source_meta_id,
+            event.user_id,
+            event.event_type as SourceEventType,
+            event.payload,
+            event.occurred_at,
+            event.recorded_at,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Lists the events of a source of a user, from the first one
+    ///
+    /// The events of a deleted source are still listed.
+    #[tracing::instrument(
+        name = "Listing user source events from database",
+        skip(self, db_executor)
+    )]
+    pub async fn list_user_source_events(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        source_meta_id: Uuid,
+    ) -> Result<Vec<SourceEvent>, SourceEventPostgresRepositoryError> {
+        let events = sqlx::query_as!(
+            SourceEvent,
+            r#"
+    SELECT id, source_meta_id, user_id, event_type AS "event_type: SourceEventType", payload,
+        occurred_at, recorded_at
+    FROM source_events
+    WHERE source_meta_id = $1 AND user_id = $2
+    ORDER BY occurred_at, recorded_at
+            "#,
+            source_meta_id,
+            user_id,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(events)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum SourceEventPostgresRepositoryError {
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for SourceEventPostgresRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
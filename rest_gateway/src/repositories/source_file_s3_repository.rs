@@ -1,9 +1,18 @@
+use actix_web::web::Bytes;
 use common::helper::error_chain_fmt;
-use s3::Bucket;
-use std::io::Read;
+use futures::Stream;
+use s3::{serde_types::Part, Bucket};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, ReadBuf};
 use tracing::{error, info};
 
 /// Simple Storage Service (S3) client to store source files
+#[derive(Clone)]
 pub struct S3Repository {
     // If one day there is a need to have several buckets for scaling reasons,
     // a vector of Bucket will be necessary + knowing in which bucket each file is
@@ -14,6 +23,8 @@ pub struct S3Repository {
 pub enum S3RepositoryError {
     #[error("The object could not be found in the bucket: {0}")]
     ObjectNotFound(String),
+    #[error("The multipart upload was rejected by the object storage: {0}")]
+    MultipartUploadRejected(String),
     #[error(transparent)]
     IOError(#[from] std::io::Error),
     #[error(transparent)]
@@ -26,6 +37,44 @@ impl std::fmt::Debug for S3RepositoryError {
     }
 }
 
+/// Reader computing the SHA-256 hash of the content read from its inner reader
+struct HashingReader<R> {
+    reader: R,
+    hasher: Sha256,
+}
+
+impl<R> HashingReader<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// Hex-encoded hash of the content read so far
+    fn hex_digest(self) -> String {
+        hex::encode(self.hasher.finalize())
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for HashingReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let already_filled = buf.filled().len();
+
+        let poll = Pin::new(&mut this.reader).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            this.hasher.update(&buf.filled()[already_filled..]);
+        }
+
+        poll
+    }
+}
+
 impl S3Repository {
     pub fn new(bucket: Bucket) -> Self {
         Self { bucket }
@@ -33,6 +82,8 @@ impl S3Repository {
 
     /// Save a given file to a bucket in the object storage
     ///
+    /// The file is streamed to the object storage, and its content is hashed while being streamed.
+    ///
     /// # Arguments
     /// * `file` - The file to be stored
     /// * `folder_path` - The folder where the file will be stored
@@ -41,25 +92,177 @@ impl S3Repository {
     /// A tuple:
     /// - the name (not the full path) of the file given on the object storage
     /// - the path + name (full path) of the file given on the object storage
+    /// - the hex-encoded SHA-256 hash of the file content
     #[tracing::instrument(name = "Add file from bucket", skip(self))]
     pub async fn save_file(
         &self,
         folder_path: &str,
         file: &mut std::fs::File,
-    ) -> Result<(String, String), S3RepositoryError> {
+    ) -> Result<(String, String, String), S3RepositoryError> {
         let object_name = uuid::Uuid::new_v4();
         let object_path_name = format!("{}/{}", folder_path, object_name);
 
         info!("Saving file at {}", object_path_name);
 
-        let mut buf = Vec::<u8>::new();
-        file.read_to_end(&mut buf)?;
+        let mut reader = HashingReader::new(tokio::fs::File::from_std(file.try_clone()?));
 
         self.bucket
-            .put_object(object_path_name.clone(), buf.as_slice())
+            .put_object_stream(&mut reader, object_path_name.clone())
+            .await?;
+
+        Ok((
+            object_name.to_string(),
+            object_path_name,
+            reader.hex_digest(),
+        ))
+    }
+
+    /// Start a multipart upload of a file to the object storage, its parts being uploaded separately
+    ///
+    /// # Arguments
+    /// * `folder_path` - The folder where the file will be stored
+    /// * `content_type` - The MIME type of the file
+    ///
+    /// # Return
+    /// A tuple:
+    /// - the name (not the full path) of the file given on the object storage
+    /// - the id of the multipart upload
+    #[tracing::instrument(name = "Start multipart upload to bucket", skip(self))]
+    pub async fn start_multipart_upload(
+        &self,
+        folder_path: &str,
+        content_type: &str,
+    ) -> Result<(String, String), S3RepositoryError> {
+        let object_name = uuid::Uuid::new_v4();
+        let object_path_name = format!("{}/{}", folder_path, object_name);
+
+        info!("Starting multipart upload at {}", object_path_name);
+
+        let upload = self
+            .bucket
+            .initiate_multipart_upload(&object_path_name, content_type)
+            .await?;
+
+        Ok((object_name.to_string(), upload.upload_id))
+    }
+
+    /// Upload a part of a multipart upload
+    ///
+    /// A part uploaded again with the same number replaces the previous one.
+    /// Except the last one, the parts should be of at least 5 MiB to be assembled.
+    ///
+    /// # Return
+    /// The ETag of the part, needed to assemble it
+    #[tracing::instrument(name = "Upload part to bucket", skip(self, chunk))]
+    pub async fn upload_part(
+        &self,
+        object_path: &str,
+        upload_id: &str,
+        part_number: u32,
+        content_type: &str,
+        chunk: Vec<u8>,
+    ) -> Result<String, S3RepositoryError> {
+        // A rejected part does not abort the upload: the part can be uploaded again
+        let part = self
+            .bucket
+            .put_multipart_chunk(chunk, object_path, part_number, upload_id, content_type)
             .await?;
 
-        Ok((object_name.to_string(), object_path_name))
+        Ok(part.etag)
+    }
+
+    /// Assemble the uploaded parts of a multipart upload into the file
+    ///
+    /// # Arguments
+    /// * `parts` - The part numbers with their ETag, in the order of the file
+    #[tracing::instrument(name = "Complete multipart upload to bucket", skip(self, parts))]
+    pub async fn complete_multipart_upload(
+        &self,
+        object_path: &str,
+        upload_id: &str,
+        parts: Vec<(u32, String)>,
+    ) -> Result<(), S3RepositoryError> {
+        let parts = parts
+            .into_iter()
+            .map(|(part_number, etag)| Part { part_number, etag })
+            .collect();
+
+        let response = self
+            .bucket
+            .complete_multipart_upload(object_path, upload_id, parts)
+            .await
+            .map_err(|error| match error {
+                s3::error::S3Error::Http(code, message) if (400..500).contains(&code) => {
+                    S3RepositoryError::MultipartUploadRejected(message)
+                }
+                _ => S3RepositoryError::Other(error),
+            })?;
+
+        // The object storage can reply with a success status and report the error in the body
+        let body = String::from_utf8_lossy(response.as_slice());
+        if body.contains("<Error>") {
+            return Err(S3RepositoryError::MultipartUploadRejected(body.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Abort a multipart upload, removing its uploaded parts from the object storage
+    #[tracing::instrument(name = "Abort multipart upload to bucket", skip(self))]
+    pub async fn abort_multipart_upload(
+        &self,
+        object_path: &str,
+        upload_id: &str,
+    ) -> Result<(), S3RepositoryError> {
+        self.bucket.abort_upload(object_path, upload_id).await?;
+
+        Ok(())
+    }
+
+    /// Stream a given file from the object storage
+    ///
+    /// # Arguments
+    /// * `object_path` - The path (with the object name) of the file
+    #[tracing::instrument(name = "Stream file from bucket", skip(self))]
+    pub async fn get_file_stream(
+        &self,
+        object_path: &str,
+    ) -> Result<Pin<Box<dyn Stream<Item = Bytes>>>, S3RepositoryError> {
+        let response =
+            self.bucket
+                .get_object_stream(object_path)
+                .await
+                .map_err(|error| match error {
+                    s3::error::S3Error::Http(404, _) => {
+                        S3RepositoryError::ObjectNotFound(object_path.to_string())
+                    }
+                    _ => S3RepositoryError::Other(error),
+                })?;
+
+        Ok(response.bytes)
+    }
+
+    /// Pre-sign a URL to download a given file directly from the object storage, until it expires
+    ///
+    /// # Arguments
+    /// * `object_path` - The path (with the object name) of the file
+    /// * `content_disposition` - The `Content-Disposition` header of the download, naming the downloaded file
+    /// * `expiry_secs` - The validity of the URL, at most 7 days
+    #[tracing::instrument(name = "Pre-sign file download from bucket", skip(self))]
+    pub fn presign_file_download(
+        &self,
+        object_path: &str,
+        content_disposition: String,
+        expiry_secs: u32,
+    ) -> Result<String, S3RepositoryError> {
+        let custom_queries = HashMap::from([(
+            "response-content-disposition".to_string(),
+            content_disposition,
+        )]);
+
+        Ok(self
+            .bucket
+            .presign_get(object_path, expiry_secs, Some(custom_queries))?)
     }
 
     /// Remove a given file from a bucket in the object storage
@@ -85,3 +288,21 @@ impl S3Repository {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn hashing_reader_computes_sha256_of_read_content() {
+        let content = b"This is a test file".repeat(1000);
+        let mut reader = HashingReader::new(content.as_slice());
+
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).await.unwrap();
+
+        assert_eq!(read, content);
+        assert_eq!(reader.hex_digest(), hex::encode(Sha256::digest(&content)));
+    }
+}
@@ -1,11 +1,77 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use common::helper::error_chain_fmt;
+use common::pagination::{PageCursor, PageDirection};
+use futures::{stream::BoxStream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
 use sqlx::PgExecutor;
+use uuid::Uuid;
 
-use crate::domain::entities::source_meta::{SourceMeta, SourceType};
+use crate::domain::entities::{
+    extraction_progress::ExtractionStatus,
+    source_meta::{SourceMeta, SourceType},
+};
 
 pub struct SourceMetaPostgresRepository {}
 
+/// Filters on the listed source metas
+#[derive(Debug, Default)]
+pub struct SourceMetaFilters {
+    pub source_type: Option<SourceType>,
+    /// Only the source metas with this extraction status
+    pub extraction_status: Option<ExtractionStatusFilter>,
+}
+
+impl SourceMetaFilters {
+    /// Query parameters of the extraction status filter: only the not started extractions, and the status
+    fn extraction_status_params(&self) -> (bool, Option<ExtractionStatus>) {
+        match self.extraction_status {
+            None => (false, None),
+            Some(ExtractionStatusFilter::NotStarted) => (true, None),
+            Some(ExtractionStatusFilter::Is(status)) => (false, Some(status)),
+        }
+    }
+}
+
+/// Filters of a search on the sources of its contents
+#[derive(Debug, Default)]
+pub struct SourceMetaSearchFilters {
+    /// Only the source metas among these ids, if any
+    pub source_meta_ids: Vec<Uuid>,
+    pub source_type: Option<SourceType>,
+    /// Only the source metas added from this date, included
+    pub added_after: Option<DateTime<Utc>>,
+    /// Only the source metas added before this date, excluded
+    pub added_before: Option<DateTime<Utc>>,
+}
+
+impl SourceMetaSearchFilters {
+    /// Whether the search is not filtered on its sources
+    pub fn is_empty(&self) -> bool {
+        self.source_meta_ids.is_empty()
+            && self.source_type.is_none()
+            && self.added_after.is_none()
+            && self.added_before.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ExtractionStatusFilter {
+    /// The extraction has not started yet
+    NotStarted,
+    Is(ExtractionStatus),
+}
+
+/// Sort key of the listed source metas, encoded in the page cursors
+///
+/// `added_at` is encoded in microseconds, the precision of a Postgres timestamp:
+/// the cursor matches the saved `added_at`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceMetaCursor {
+    #[serde(with = "chrono::serde::ts_microseconds")]
+    pub added_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
 impl Default for SourceMetaPostgresRepository {
     fn default() -> Self {
         Self::new()
@@ -25,14 +91,22 @@ impl SourceMetaPostgresRepository {
     ) -> Result<(), SourceMetaPostgresRepositoryError> {
         sqlx::query!(
             r#"
-    INSERT INTO source_metas (id, user_id, object_store_name, source_type, initial_name, added_at, extracted_at)
-    VALUES ($1, $2, $3, $4, $5, $6, NULL)
+    INSERT INTO source_metas (id, user_id, object_store_name, source_type, initial_name, content_hash, collection, auto_filing_rule_id, upload_policy_id, content_columns, detected_mime_type, size_bytes, redact_pii, added_at, extracted_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, NULL)
             "#,
             source_meta.id,
             source_meta.user_id,
             source_meta.object_store_name,
             source_meta.source_type.to_owned() as SourceType,
             source_meta.initial_name.to_string(),
+            source_meta.content_hash,
+            source_meta.collection,
+            source_meta.auto_filing_rule_id,
+            source_meta.upload_policy_id,
+            &source_meta.content_columns,
+            source_meta.detected_mime_type,
+            source_meta.size_bytes,
+            source_meta.redact_pii,
             Utc::now()
         )
         .execute(db_executor)
@@ -40,6 +114,399 @@ impl SourceMetaPostgresRepository {
 
         Ok(())
     }
+
+    /// Finds the source meta of a file already uploaded by a user, from the hash of its content
+    ///
+    /// # Returns
+    /// The id of the source meta, if the user already uploaded a file with the same content
+    #[tracing::instrument(
+        name = "Finding user source meta by content hash in database",
+        skip(self, db_executor)
+    )]
+    pub async fn find_user_source_meta_id_by_content_hash(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        content_hash: &str,
+    ) -> Result<Option<Uuid>, SourceMetaPostgresRepositoryError> {
+        let record = sqlx::query!(
+            r#"
+    SELECT id FROM source_metas
+    WHERE user_id = $1 AND content_hash = $2
+            "#,
+            user_id,
+            content_hash,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(record.map(|record| record.id))
+    }
+
+    /// Checks that a source meta exists and belongs to a given user
+    #[tracing::instrument(
+        name = "Checking user source meta in database",
+        skip(self, db_executor)
+    )]
+    pub async fn is_user_source_meta(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        source_meta_id: Uuid,
+    ) -> Result<bool, SourceMetaPostgresRepositoryError> {
+        let record = sqlx::query!(
+            r#"
+    SELECT id FROM source_metas
+    WHERE id = $1 AND user_id = $2
+            "#,
+            source_meta_id,
+            user_id,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(record.is_some())
+    }
+
+    /// Lists the source metas of a user, from the most recently added
+    ///
+    /// # Arguments
+    /// * `cursor` - Lists the source metas added before the cursor, or after it backwards
+    ///   (from the oldest) when listing the previous page
+    /// * `limit` - Maximum number of listed source metas
+    #[tracing::instrument(
+        name = "Listing user source metas in database",
+        skip(self, db_executor)
+    )]
+    pub async fn list_user_source_metas<'e>(
+        &self,
+        db_executor: impl PgExecutor<'e> + 'e,
+        user_id: Uuid,
+        filters: &SourceMetaFilters,
+        cursor: Option<&PageCursor<SourceMetaCursor>>,
+        limit: i64,
+    ) -> Result<Vec<SourceMeta>, SourceMetaPostgresRepositoryError> {
+        let before = match cursor {
+            Some(PageCursor {
+                direction: PageDirection::Previous,
+                key,
+            }) => key,
+            cursor => {
+                return self
+                    .stream_user_source_metas(
+                        db_executor,
+                        user_id,
+                        filters,
+                        cursor.map(|cursor| &cursor.key),
+                        Some(limit),
+                    )
+                    .try_collect()
+                    .await
+            }
+        };
+
+        let (filter_not_started, filter_status) = filters.extraction_status_params();
+
+        let source_metas = sqlx::query_as!(
+            SourceMeta,
+            r#"
+    SELECT id, user_id, initial_name, object_store_name, source_type as "source_type: SourceType",
+        content_hash, added_at, extracted_at, extraction_status as "extraction_status: ExtractionStatus",
+        collection, auto_filing_rule_id, upload_policy_id, content_columns, detected_mime_type, size_bytes, redact_pii
+    FROM source_metas
+    WHERE user_id = $1
+        AND ($2::source_type IS NULL OR source_type = $2)
+        AND (NOT $3 OR extraction_status IS NULL)
+        AND ($4::extraction_status IS NULL OR extraction_status = $4)
+        AND (added_at, id) > ($5, $6)
+    ORDER BY added_at, id
+    LIMIT $7
+            "#,
+            user_id,
+            filters.source_type.clone() as Option<SourceType>,
+            filter_not_started,
+            filter_status as Option<ExtractionStatus>,
+            before.added_at,
+            before.id,
+            limit,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(source_metas)
+    }
+
+    /// Streams the source metas of a user, from the most recently added, as they are fetched
+    ///
+    /// # Arguments
+    /// * `after` - Streams the source metas added before this cursor
+    /// * `limit` - Maximum number of streamed source metas. If `None`, all the source metas are streamed
+    #[tracing::instrument(
+        name = "Streaming user source metas from database",
+        skip(self, db_executor)
+    )]
+    pub fn stream_user_source_metas<'e>(
+        &self,
+        db_executor: impl PgExecutor<'e> + 'e,
+        user_id: Uuid,
+        filters: &SourceMetaFilters,
+        after: Option<&SourceMetaCursor>,
+        limit: Option<i64>,
+    ) -> BoxStream<'e, Result<SourceMeta, SourceMetaPostgresRepositoryError>> {
+        let (filter_not_started, filter_status) = filters.extraction_status_params();
+
+        // A NULL limit does not limit the number of rows
+        sqlx::query_as!(
+            SourceMeta,
+            r#"
+    SELECT id, user_id, initial_name, object_store_name, source_type as "source_type: SourceType",
+        content_hash, added_at, extracted_at, extraction_status as "extraction_status: ExtractionStatus",
+        collection, auto_filing_rule_id, upload_policy_id, content_columns, detected_mime_type, size_bytes, redact_pii
+    FROM source_metas
+    WHERE user_id = $1
+        AND ($2::source_type IS NULL OR source_type = $2)
+        AND (NOT $3 OR extraction_status IS NULL)
+        AND ($4::extraction_status IS NULL OR extraction_status = $4)
+        AND ($5::timestamptz IS NULL OR (added_at, id) < ($5, $6))
+    ORDER BY added_at DESC, id DESC
+    LIMIT $7
+            "#,
+            user_id,
+            filters.source_type.clone() as Option<SourceType>,
+            filter_not_started,
+            filter_status as Option<ExtractionStatus>,
+            after.map(|cursor| cursor.added_at),
+            after.map(|cursor| cursor.id),
+            limit,
+        )
+        .fetch(db_executor)
+        .map_err(SourceMetaPostgresRepositoryError::from)
+        .boxed()
+    }
+
+    /// Lists the ids of all the source metas of a user, from the oldest
+    #[tracing::instrument(
+        name = "Listing user source meta ids in database",
+        skip(self, db_executor)
+    )]
+    pub async fn list_user_source_meta_ids(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+    ) -> Result<Vec<Uuid>, SourceMetaPostgresRepositoryError> {
+        let records = sqlx::query!(
+            r#"
+    SELECT id FROM source_metas
+    WHERE user_id = $1
+    ORDER BY added_at, id
+            "#,
+            user_id,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(records.into_iter().map(|record| record.id).collect())
+    }
+
+    /// Lists the ids of the source metas of a user matching the filters of a search, from the oldest
+    ///
+    /// The ids of other users' source metas are ignored.
+    #[tracing::instrument(
+        name = "Filtering user source meta ids in database",
+        skip(self, db_executor)
+    )]
+    pub async fn filter_user_source_meta_ids(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        filters: &SourceMetaSearchFilters,
+    ) -> Result<Vec<Uuid>, SourceMetaPostgresRepositoryError> {
+        let records = sqlx::query!(
+            r#"
+    SELECT id FROM source_metas
+    WHERE user_id = $1
+        AND (cardinality($2::uuid[]) = 0 OR id = ANY($2))
+        AND ($3::source_type IS NULL OR source_type = $3)
+        AND ($4::timestamptz IS NULL OR added_at >= $4)
+        AND ($5::timestamptz IS NULL OR added_at < $5)
+    ORDER BY added_at, id
+            "#,
+            user_id,
+            &filters.source_meta_ids,
+            filters.source_type.clone() as Option<SourceType>,
+            filters.added_after,
+            filters.added_before,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(records.into_iter().map(|record| record.id).collect())
+    }
+
+    /// Lists the source metas of a user among given ids, the ids of other users' source metas being ignored
+    #[tracing::instrument(
+        name = "Listing user source metas by ids in database",
+        skip(self, db_executor)
+    )]
+    pub async fn list_user_source_metas_by_ids(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        source_meta_ids: &[Uuid],
+    ) -> Result<Vec<SourceMeta>, SourceMetaPostgresRepositoryError> {
+        let source_metas = sqlx::query_as!(
+            SourceMeta,
+            r#"
+    SELECT id, user_id, initial_name, object_store_name, source_type as "source_type: SourceType",
+        content_hash, added_at, extracted_at, extraction_status as "extraction_status: ExtractionStatus",
+        collection, auto_filing_rule_id, upload_policy_id, content_columns, detected_mime_type, size_bytes, redact_pii
+    FROM source_metas
+    WHERE user_id = $1 AND id = ANY($2)
+            "#,
+            user_id,
+            source_meta_ids,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(source_metas)
+    }
+
+    /// Gets a source meta, whoever its user is
+    #[tracing::instrument(name = "Getting source meta from database", skip(self, db_executor))]
+    pub async fn get_source_meta(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        source_meta_id: Uuid,
+    ) -> Result<Option<SourceMeta>, SourceMetaPostgresRepositoryError> {
+        let source_meta = sqlx::query_as!(
+            SourceMeta,
+            r#"
+    SELECT id, user_id, initial_name, object_store_name, source_type as "source_type: SourceType",
+        content_hash, added_at, extracted_at, extraction_status as "extraction_status: ExtractionStatus",
+        collection, auto_filing_rule_id, upload_policy_id, content_columns, detected_mime_type, size_bytes, redact_pii
+    FROM source_metas
+    WHERE id = $1
+            "#,
+            source_meta_id,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(source_meta)
+    }
+
+    /// Deletes the source meta of a user
+    ///
+    /// # Returns
+    /// The deleted source meta, or `None` if the user has no such source meta
+    #[tracing::instrument(
+        name = "Deleting user source meta in database",
+        skip(self, db_executor)
+    )]
+    pub async fn delete_user_source_meta(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        source_meta_id: Uuid,
+    ) -> Result<Option<SourceMeta>, SourceMetaPostgresRepositoryError> {
+        let source_meta = sqlx::query_as!(
+            SourceMeta,
+            r#"
+    DELETE FROM source_metas
+    WHERE id = $1 AND user_id = $2
+    RETURNING id, user_id, initial_name, object_store_name, source_type as "source_type: SourceType",
+        content_hash, added_at, extracted_at, extraction_status as "extraction_status: ExtractionStatus",
+        collection, auto_filing_rule_id, upload_policy_id, content_columns, detected_mime_type, size_bytes, redact_pii
+            "#,
+            source_meta_id,
+            user_id,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(source_meta)
+    }
+
+    /// Replaces the stored file of a source, for ex with the changed content of its URL
+    #[tracing::instrument(
+        name = "Replacing source meta file in database",
+        skip(self, db_executor)
+    )]
+    pub async fn replace_source_file(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        source_meta_id: Uuid,
+        object_store_name: &str,
+        content_hash: &str,
+        detected_mime_type: &str,
+        size_bytes: u64,
+    ) -> Result<(), SourceMetaPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    UPDATE source_metas
+    SET object_store_name = $2, content_hash = $3, detected_mime_type = $4, size_bytes = $5
+    WHERE id = $1
+            "#,
+            source_meta_id,
+            object_store_name,
+            content_hash,
+            detected_mime_type,
+            size_bytes as i64,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "Setting source meta extraction status in database",
+        skip(self, db_executor)
+    )]
+    pub async fn set_extraction_status(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        source_meta_id: Uuid,
+        extraction_status: ExtractionStatus,
+    ) -> Result<(), SourceMetaPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    UPDATE source_metas SET extraction_status = $2
+    WHERE id = $1
+            "#,
+            source_meta_id,
+            extraction_status as ExtractionStatus,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "Setting source meta as extracted in database",
+        skip(self, db_executor)
+    )]
+    pub async fn set_extracted_at(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        source_meta_id: Uuid,
+        extracted_at: DateTime<Utc>,
+    ) -> Result<(), SourceMetaPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    UPDATE source_metas SET extracted_at = $2
+    WHERE id = $1
+            "#,
+            source_meta_id,
+            extracted_at,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
 }
 
 #[derive(thiserror::Error)]
@@ -0,0 +1,91 @@
+use common::helper::error_chain_fmt;
+use sqlx::{types::Json, PgExecutor};
+use uuid::Uuid;
+
+use crate::domain::entities::source_summary::{ChapterSummary, SourceSummary};
+
+/// Source summary repository implemented using Postgres
+pub struct SourceSummaryPostgresRepository {}
+
+impl Default for SourceSummaryPostgresRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SourceSummaryPostgresRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Saves the summaries of a source, replacing the ones of a previous extraction
+    ///
+    /// The summaries of a source deleted meanwhile are dropped.
+    /// An older summary, received out of order, does not replace a newer one.
+    #[tracing::instrument(
+        name = "Saving source summary in database",
+        skip(self, db_executor, source_summary),
+        fields(source_meta_id = %source_summary.source_meta_id)
+    )]
+    pub async fn save_source_summary(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        source_summary: &SourceSummary,
+    ) -> Result<(), SourceSummaryPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    INSERT INTO source_summaries (source_meta_id, summary, chapters, model, generated_at)
+    SELECT $1, $2, $3, $4, $5
+    WHERE EXISTS (SELECT 1 FROM source_metas WHERE id = $1)
+    ON CONFLICT (source_meta_id) DO UPDATE
+    SET summary = EXCLUDED.summary,
+        chapters = EXCLUDED.chapters,
+        model = EXCLUDED.model,
+        generated_at = EXCLUDED.generated_at
+    WHERE source_summaries.generated_at <= EXCLUDED.generated_at
+            "#,
+            source_summary.source_meta_id,
+            source_summary.summary,
+            &source_summary.chapters as _,
+            source_summary.model,
+            source_summary.generated_at,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Getting source summary from database", skip(self, db_executor))]
+    pub async fn get_source_summary(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        source_meta_id: Uuid,
+    ) -> Result<Option<SourceSummary>, SourceSummaryPostgresRepositoryError> {
+        let source_summary = sqlx::query_as!(
+            SourceSummary,
+            r#"
+    SELECT source_meta_id, summary, chapters AS "chapters: Json<Vec<ChapterSummary>>", model, generated_at
+    FROM source_summaries
+    WHERE source_meta_id = $1
+            "#,
+            source_meta_id,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(source_summary)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum SourceSummaryPostgresRepositoryError {
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for SourceSummaryPostgresRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,421 @@
+use common::helper::error_chain_fmt;
+use reqwest::{
+    header::{HeaderMap, CONTENT_DISPOSITION, CONTENT_TYPE, LOCATION},
+    redirect, StatusCode, Url,
+};
+use std::{
+    io::Write,
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    time::{Duration, Instant},
+};
+use tracing::info;
+
+use crate::configuration::UrlDownloadsSettings;
+
+/// Source file downloaded from a URL
+#[derive(Debug, Clone)]
+pub struct DownloadedSource {
+    /// URL the file was downloaded from, after the redirections
+    pub url: Url,
+    /// File name given by the server in the `Content-Disposition` header
+    pub file_name: Option<String>,
+    /// MIME type given by the server, without its parameters
+    pub content_type: Option<String>,
+    pub size_bytes: u64,
+}
+
+/// Client downloading the source files added from a URL
+///
+/// The URLs resolving to private networks are rejected, unless allowed in the settings:
+/// the address is checked on each redirection, and the connection is pinned to the checked address.
+pub struct SourceUrlRepository {
+    settings: UrlDownloadsSettings,
+}
+
+impl SourceUrlRepository {
+    pub fn new(settings: &UrlDownloadsSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+        }
+    }
+
+    /// Downloads a source file into a given file, following the redirections
+    ///
+    /// # Arguments
+    /// * `headers` - Headers sent with the request, for ex to authenticate. They are not sent to the other hosts
+    ///   the request is redirected to
+    /// * `file` - File the content is written to
+    #[tracing::instrument(name = "Downloading source from URL", skip(self, headers, file))]
+    pub async fn download(
+        &self,
+        url: &str,
+        headers: HeaderMap,
+        file: &mut std::fs::File,
+    ) -> Result<DownloadedSource, SourceUrlRepositoryError> {
+        let started_at = Instant::now();
+        let timeout = Duration::from_secs(self.settings.timeout_s);
+
+        let mut url = Url::parse(url)
+            .map_err(|error| SourceUrlRepositoryError::InvalidUrl(error.to_string()))?;
+        let initial_host = url.host_str().map(str::to_string);
+
+        let mut nb_redirects = 0;
+        let mut response = loop {
+            let remaining_time = timeout
+                .checked_sub(started_at.elapsed())
+                .ok_or(SourceUrlRepositoryError::TimedOut)?;
+            let client = self.pinned_client(&url, remaining_time).await?;
+
+            let mut request = client.get(url.clone());
+            if url.host_str().map(str::to_string) == initial_host {
+                request = request.headers(headers.clone());
+            }
+            let response = request
+                .send()
+                .await
+                .map_err(SourceUrlRepositoryError::from_reqwest)?;
+
+            if !response.status().is_redirection() {
+                break response;
+            }
+
+            nb_redirects += 1;
+            if nb_redirects > self.settings.max_redirects {
+                return Err(SourceUrlRepositoryError::TooManyRedirects);
+            }
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .ok_or(SourceUrlRepositoryError::UnexpectedStatus(
+                    response.status(),
+                ))?;
+            url = url
+                .join(location)
+                .map_err(|error| SourceUrlRepositoryError::InvalidUrl(error.to_string()))?;
+        };
+
+        if !response.status().is_success() {
+            return Err(SourceUrlRepositoryError::UnexpectedStatus(
+                response.status(),
+            ));
+        }
+        if let Some(content_length) = response.content_length() {
+            if content_length > self.settings.max_bytes {
+                return Err(SourceUrlRepositoryError::TooLarge(self.settings.max_bytes));
+            }
+        }
+
+        let file_name = response
+            .headers()
+            .get(CONTENT_DISPOSITION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(content_disposition_file_name);
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .map(|essence| essence.trim().to_lowercase())
+            .filter(|essence| !essence.is_empty());
+
+        // The announced length is not trusted: the size is checked while downloading
+        let mut size_bytes = 0;
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(SourceUrlRepositoryError::from_reqwest)?
+        {
+            size_bytes += chunk.len() as u64;
+            if size_bytes > self.settings.max_bytes {
+                return Err(SourceUrlRepositoryError::TooLarge(self.settings.max_bytes));
+            }
+            file.write_all(&chunk)?;
+        }
+
+        info!(
+            "Downloaded {} bytes from {} in {:?}",
+            size_bytes,
+            url,
+            started_at.elapsed()
+        );
+
+        Ok(DownloadedSource {
+            url,
+            file_name,
+            content_type,
+            size_bytes,
+        })
+    }
+
+    /// Client connecting to the checked address of the host of a URL, not following the redirections
+    async fn pinned_client(
+        &self,
+        url: &Url,
+        timeout: Duration,
+    ) -> Result<reqwest::Client, SourceUrlRepositoryError> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(SourceUrlRepositoryError::InvalidUrl(format!(
+                "unsupported scheme {}",
+                url.scheme()
+            )));
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| SourceUrlRepositoryError::InvalidUrl("no host".to_string()))?;
+        let port = url
+            .port_or_known_default()
+            .ok_or_else(|| SourceUrlRepositoryError::InvalidUrl("no port".to_string()))?;
+
+        let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|_| SourceUrlRepositoryError::Unreachable(host.to_string()))?
+            .collect();
+
+        // All the addresses are checked, as any of them could be used
+        if !self.settings.allow_private_networks
+            && addresses.iter().any(|address| !is_public_ip(address.ip()))
+        {
+            return Err(SourceUrlRepositoryError::ForbiddenHost(host.to_string()));
+        }
+        let address = addresses
+            .first()
+            .ok_or_else(|| SourceUrlRepositoryError::Unreachable(host.to_string()))?;
+
+        reqwest::Client::builder()
+            .timeout(timeout)
+            .redirect(redirect::Policy::none())
+            .resolve(host, *address)
+            .build()
+            .map_err(SourceUrlRepositoryError::from_reqwest)
+    }
+}
+
+/// Checks if an IP address is reachable on the internet, and not an address of a private network or of the host
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // Shared address space (carrier-grade NAT)
+                || (ip.octets()[0] == 100 && (ip.octets()[1] & 0b1100_0000) == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ip(IpAddr::V4(ip)),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // Unique local addresses
+                    || (ip.segments()[0] & 0xfe00) == 0xfc00
+                    // Link-local addresses
+                    || (ip.segments()[0] & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Extracts the file name of a `Content-Disposition` header, without its path
+pub fn content_disposition_file_name(value: &str) -> Option<String> {
+    let file_name = value.split(';').find_map(|parameter| {
+        let (name, value) = parameter.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("filename")
+            .then(|| value.trim().trim_matches('"'))
+    })?;
+
+    Path::new(file_name)
+        .file_name()
+        .and_then(|file_name| file_name.to_str())
+        .map(str::to_string)
+}
+
+#[derive(thiserror::Error)]
+pub enum SourceUrlRepositoryError {
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+    #[error("The host {0} is in a private network")]
+    ForbiddenHost(String),
+    #[error("The host {0} could not be reached")]
+    Unreachable(String),
+    #[error("Too many redirections")]
+    TooManyRedirects,
+    #[error("The file is larger than {0} bytes")]
+    TooLarge(u64),
+    #[error("The download timed out")]
+    TimedOut,
+    #[error("Unexpected response status: {0}")]
+    UnexpectedStatus(StatusCode),
+    #[error(transparent)]
+    RequestError(reqwest::Error),
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+}
+
+impl SourceUrlRepositoryError {
+    fn from_reqwest(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            return SourceUrlRepositoryError::TimedOut;
+        }
+
+        SourceUrlRepositoryError::RequestError(error)
+    }
+}
+
+impl std::fmt::Debug for SourceUrlRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Seek};
+    use std::net::TcpListener;
+
+    /// Serves a single request with a given response, returning the base URL and the received request lines
+    fn serve_once(response: String) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let request: Vec<String> = BufReader::new(stream.try_clone().unwrap())
+                .lines()
+                .map(Result::unwrap)
+                .take_while(|line| !line.is_empty())
+                .collect();
+            write!(stream, "{}", response).unwrap();
+
+            request
+        });
+
+        (url, handle)
+    }
+
+    fn repository(max_bytes: u64, allow_private_networks: bool) -> SourceUrlRepository {
+        SourceUrlRepository::new(&UrlDownloadsSettings {
+            max_bytes,
+            timeout_s: 5,
+            max_redirects: 2,
+            allow_private_networks,
+        })
+    }
+
+    fn temp_file() -> std::fs::File {
+        tempfile::tempfile().unwrap()
+    }
+
+    #[test]
+    fn private_network_addresses_are_not_public() {
+        for ip in [
+            "10.0.0.1",
+            "172.16.0.1",
+            "192.168.1.1",
+            "127.0.0.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+
+        for ip in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn file_name_is_extracted_from_content_disposition() {
+        assert_eq!(
+            content_disposition_file_name(r#"attachment; filename="book.epub""#),
+            Some("book.epub".to_string())
+        );
+        assert_eq!(
+            content_disposition_file_name("attachment; filename=../../book.epub"),
+            Some("book.epub".to_string())
+        );
+        assert_eq!(content_disposition_file_name("inline"), None);
+    }
+
+    #[tokio::test]
+    async fn source_is_downloaded_with_the_given_headers() {
+        let (url, handle) = serve_once(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/epub+zip\r\ncontent-disposition: attachment; filename=\"book.epub\"\r\ncontent-length: 19\r\nconnection: close\r\n\r\nThis is a test file".to_string(),
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer token".parse().unwrap());
+        let mut file = temp_file();
+
+        let downloaded_source = repository(1024, true)
+            .download(&format!("{}/books/1", url), headers, &mut file)
+            .await
+            .unwrap();
+
+        assert_eq!(downloaded_source.file_name.as_deref(), Some("book.epub"));
+        assert_eq!(
+            downloaded_source.content_type.as_deref(),
+            Some("application/epub+zip")
+        );
+        assert_eq!(downloaded_source.size_bytes, 19);
+
+        let mut content = String::new();
+        file.rewind().unwrap();
+        file.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "This is a test file");
+
+        let request = handle.join().unwrap();
+        assert_eq!(request[0], "GET /books/1 HTTP/1.1");
+        assert!(request
+            .iter()
+            .any(|line| line.eq_ignore_ascii_case("authorization: Bearer token")));
+    }
+
+    #[tokio::test]
+    async fn source_larger_than_the_limit_is_rejected() {
+        let (url, _) = serve_once(
+            "HTTP/1.1 200 OK\r\nconnection: close\r\n\r\nThis is a test file".to_string(),
+        );
+
+        let result = repository(10, true)
+            .download(&url, HeaderMap::new(), &mut temp_file())
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(SourceUrlRepositoryError::TooLarge(10))
+        ));
+    }
+
+    #[tokio::test]
+    async fn private_network_urls_are_rejected_unless_allowed() {
+        let (url, _) = serve_once("HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n".to_string());
+
+        let result = repository(1024, false)
+            .download(&url, HeaderMap::new(), &mut temp_file())
+            .await;
+        assert!(matches!(
+            result,
+            Err(SourceUrlRepositoryError::ForbiddenHost(host)) if host == "127.0.0.1"
+        ));
+
+        let result = repository(1024, false)
+            .download("file:///etc/passwd", HeaderMap::new(), &mut temp_file())
+            .await;
+        assert!(matches!(
+            result,
+            Err(SourceUrlRepositoryError::InvalidUrl(_))
+        ));
+    }
+}
@@ -0,0 +1,163 @@
+use chrono::{DateTime, Utc};
+use common::helper::error_chain_fmt;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::domain::entities::source_url_schedule::SourceUrlSchedule;
+
+/// Source URL schedule repository implemented using Postgres
+pub struct SourceUrlSchedulePostgresRepository {}
+
+impl Default for SourceUrlSchedulePostgresRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SourceUrlSchedulePostgresRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    #[tracing::instrument(
+        name = "Adding source URL schedule in database",
+        skip(self, db_executor, schedule),
+        fields(source_meta_id = %schedule.source_meta_id)
+    )]
+    pub async fn add_schedule(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        schedule: &SourceUrlSchedule,
+    ) -> Result<(), SourceUrlSchedulePostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    INSERT INTO source_url_schedules (source_meta_id, user_id, url, encrypted_headers, interval_h,
+        next_crawl_at, last_crawled_at, last_changed_at, created_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+            schedule.source_meta_id,
+            schedule.user_id,
+            schedule.url,
+            schedule.encrypted_headers,
+            schedule.interval_h,
+            schedule.next_crawl_at,
+            schedule.last_crawled_at,
+            schedule.last_changed_at,
+            schedule.created_at,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Claims the schedules due at a given time, pushing back their next crawl by their interval
+    ///
+    /// The claimed schedules are skipped by the concurrent claims of the other gateway instances.
+    /// A failed crawl is retried with the next one.
+    ///
+    /// # Arguments
+    /// * `limit` - Maximum number of claimed schedules, from the most overdue
+    #[tracing::instrument(
+        name = "Claiming due source URL schedules in database",
+        skip(self, db_executor)
+    )]
+    pub async fn claim_due_schedules(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        now: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<SourceUrlSchedule>, SourceUrlSchedulePostgresRepositoryError> {
+        let schedules = sqlx::query_as!(
+            SourceUrlSchedule,
+            r#"
+    UPDATE source_url_schedules
+    SET next_crawl_at = $1 + make_interval(hours => interval_h)
+    WHERE source_meta_id IN (
+        SELECT source_meta_id
+        FROM source_url_schedules
+        WHERE next_crawl_at <= $1
+        ORDER BY next_crawl_at
+        LIMIT $2
+        FOR UPDATE SKIP LOCKED
+    )
+    RETURNING source_meta_id, user_id, url, encrypted_headers, interval_h,
+        next_crawl_at, last_crawled_at, last_changed_at, created_at
+            "#,
+            now,
+            limit,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(schedules)
+    }
+
+    /// Records a crawl of a source, and whether its content changed
+    #[tracing::instrument(
+        name = "Setting source URL schedule as crawled in database",
+        skip(self, db_executor)
+    )]
+    pub async fn set_crawled(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        source_meta_id: Uuid,
+        crawled_at: DateTime<Utc>,
+        changed: bool,
+    ) -> Result<(), SourceUrlSchedulePostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    UPDATE source_url_schedules
+    SET last_crawled_at = $2, last_changed_at = CASE WHEN $3 THEN $2 ELSE last_changed_at END
+    WHERE source_meta_id = $1
+            "#,
+            source_meta_id,
+            crawled_at,
+            changed,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Stops the re-crawls of a source of a user: the source is kept
+    ///
+    /// # Returns
+    /// False if the source of the user has no schedule
+    #[tracing::instrument(
+        name = "Deleting user source URL schedule in database",
+        skip(self, db_executor)
+    )]
+    pub async fn delete_user_schedule(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        source_meta_id: Uuid,
+    ) -> Result<bool, SourceUrlSchedulePostgresRepositoryError> {
+        let result = sqlx::query!(
+            r#"
+    DELETE FROM source_url_schedules
+    WHERE source_meta_id = $1 AND user_id = $2
+            "#,
+            source_meta_id,
+            user_id,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum SourceUrlSchedulePostgresRepositoryError {
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for SourceUrlSchedulePostgresRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,77 @@
+use actix_web::http::header::HeaderMap;
+use futures::future::{ready, LocalBoxFuture};
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    configuration::StaticTokenSettings,
+    repositories::authenticator_port::{bearer_token, AuthenticationError, AuthenticatorPort},
+};
+
+/// Authenticates a single user with a static token, for development only
+pub struct StaticTokenAuthenticator {
+    /// Only the hash of the token is kept: comparing hashes does not leak the token through the comparison time
+    token_hash: Vec<u8>,
+    user_id: Uuid,
+}
+
+impl StaticTokenAuthenticator {
+    pub fn new(settings: &StaticTokenSettings) -> Self {
+        warn!("Static token authentication: all the requests are attributed to a single user");
+
+        Self {
+            token_hash: Sha256::digest(settings.token.expose_secret().as_bytes()).to_vec(),
+            user_id: settings.user_id,
+        }
+    }
+}
+
+impl AuthenticatorPort for StaticTokenAuthenticator {
+    fn authenticate(
+        &self,
+        headers: &HeaderMap,
+    ) -> LocalBoxFuture<'_, Result<Uuid, AuthenticationError>> {
+        let user_id = match bearer_token(headers) {
+            Some(token) if Sha256::digest(token.as_bytes()).as_slice() == self.token_hash => {
+                Ok(self.user_id)
+            }
+            Some(_) => Err(AuthenticationError::InvalidCredentials(
+                "Provided token is not valid".to_string(),
+            )),
+            None => Err(AuthenticationError::MissingCredentials),
+        };
+
+        Box::pin(ready(user_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::{HeaderValue, AUTHORIZATION};
+    use secrecy::Secret;
+
+    #[tokio::test]
+    async fn only_the_static_token_is_accepted() {
+        let user_id = Uuid::new_v4();
+        let authenticator = StaticTokenAuthenticator::new(&StaticTokenSettings {
+            token: Secret::new("dev-token".to_string()),
+            user_id,
+        });
+        let mut headers = HeaderMap::new();
+
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer dev-token"));
+        assert_eq!(authenticator.authenticate(&headers).await.unwrap(), user_id);
+
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_static("Bearer other-token"),
+        );
+        assert!(matches!(
+            authenticator.authenticate(&headers).await,
+            Err(AuthenticationError::InvalidCredentials(_))
+        ));
+    }
+}
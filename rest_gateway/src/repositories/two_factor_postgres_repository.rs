@@ -0,0 +1,255 @@
+use chrono::Utc;
+use common::helper::error_chain_fmt;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::domain::entities::two_factor::UserTwoFactor;
+
+/// Two-factor authentication repository implemented using Postgres: the TOTP secrets and the recovery codes of the users
+pub struct TwoFactorPostgresRepository {}
+
+impl Default for TwoFactorPostgresRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TwoFactorPostgresRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Saves the pending TOTP secret of a user, replacing any previous pending secret
+    ///
+    /// # Returns
+    /// False if the two-factor authentication of the user is already enabled, the secret not being saved
+    #[tracing::instrument(name = "Saving pending TOTP secret in database", skip_all)]
+    pub async fn save_pending_secret(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        encrypted_secret: &str,
+    ) -> Result<bool, TwoFactorPostgresRepositoryError> {
+        let result = sqlx::query!(
+            r#"
+    INSERT INTO user_two_factors (user_id, encrypted_secret, enabled_at, last_used_step, created_at)
+    VALUES ($1, $2, NULL, NULL, $3)
+    ON CONFLICT (user_id) DO UPDATE
+    SET encrypted_secret = EXCLUDED.encrypted_secret, created_at = EXCLUDED.created_at
+    WHERE user_two_factors.enabled_at IS NULL
+            "#,
+            user_id,
+            encrypted_secret,
+            Utc::now(),
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Gets the two-factor authentication of a user, pending or enabled
+    #[tracing::instrument(
+        name = "Getting user two-factor authentication from database",
+        skip(self, db_executor)
+    )]
+    pub async fn get_user_two_factor(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+    ) -> Result<Option<UserTwoFactor>, TwoFactorPostgresRepositoryError> {
+        let two_factor = sqlx::query_as!(
+            UserTwoFactor,
+            r#"
+    SELECT user_id, encrypted_secret, enabled_at, last_used_step, failed_attempts, locked_until, created_at
+    FROM user_two_factors
+    WHERE user_id = $1
+            "#,
+            user_id,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(two_factor)
+    }
+
+    /// Enables the pending two-factor authentication of a user, its first code being used
+    ///
+    /// # Returns
+    /// False if the user has no pending two-factor authentication
+    #[tracing::instrument(
+        name = "Enabling user two-factor authentication in database",
+        skip(self, db_executor)
+    )]
+    pub async fn enable(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        used_step: i64,
+    ) -> Result<bool, TwoFactorPostgresRepositoryError> {
+        let result = sqlx::query!(
+            r#"
+    UPDATE user_two_factors
+    SET enabled_at = $2, last_used_step = $3
+    WHERE user_id = $1 AND enabled_at IS NULL
+            "#,
+            user_id,
+            Utc::now(),
+            used_step,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Records the time step of a code used to log in
+    ///
+    /// # Returns
+    /// False if a code of this time step, or of a later one, was already used: the code is replayed
+    #[tracing::instrument(name = "Recording used TOTP step in database", skip(self, db_executor))]
+    pub async fn use_step(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        step: i64,
+    ) -> Result<bool, TwoFactorPostgresRepositoryError> {
+        let result = sqlx::query!(
+            r#"
+    UPDATE user_two_factors
+    SET last_used_step = $2
+    WHERE user_id = $1 AND (last_used_step IS NULL OR last_used_step < $2)
+            "#,
+            user_id,
+            step,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Counts an invalid code tried to log in
+    ///
+    /// Once `max_failed_attempts` invalid codes are tried in a row, the log-ins are locked out for `lockout_s`,
+    /// and the count starts again.
+    #[tracing::instrument(
+        name = "Recording failed two-factor authentication attempt in database",
+        skip(self, db_executor)
+    )]
+    pub async fn record_failed_attempt(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        max_failed_attempts: u32,
+        lockout_s: u64,
+    ) -> Result<(), TwoFactorPostgresRepositoryError> {
+        let locked_until = Utc::now() + chrono::Duration::seconds(lockout_s as i64);
+
+        sqlx::query!(
+            r#"
+    UPDATE user_two_factors
+    SET failed_attempts = CASE WHEN failed_attempts + 1 >= $2 THEN 0 ELSE failed_attempts + 1 END,
+        locked_until = CASE WHEN failed_attempts + 1 >= $2 THEN $3 ELSE locked_until END
+    WHERE user_id = $1
+            "#,
+            user_id,
+            max_failed_attempts as i32,
+            locked_until,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Resets the count of the invalid codes tried to log in, once a valid code is used
+    #[tracing::instrument(
+        name = "Resetting failed two-factor authentication attempts in database",
+        skip(self, db_executor)
+    )]
+    pub async fn reset_failed_attempts(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+    ) -> Result<(), TwoFactorPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    UPDATE user_two_factors
+    SET failed_attempts = 0
+    WHERE user_id = $1
+            "#,
+            user_id,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Replaces the recovery codes of a user by new ones, from their hashes
+    #[tracing::instrument(name = "Replacing user recovery codes in database", skip_all)]
+    pub async fn replace_recovery_codes(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        code_hashes: &[String],
+    ) -> Result<(), TwoFactorPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    WITH deleted AS (
+        DELETE FROM user_recovery_codes WHERE user_id = $1
+    )
+    INSERT INTO user_recovery_codes (user_id, code_hash, used_at, created_at)
+    SELECT $1, code_hash, NULL, $3
+    FROM UNNEST($2::TEXT[]) AS code_hash
+            "#,
+            user_id,
+            code_hashes,
+            Utc::now(),
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Uses a recovery code of a user, from its hash
+    ///
+    /// # Returns
+    /// False if the user has no such code, or if it was already used
+    #[tracing::instrument(name = "Using user recovery code in database", skip_all)]
+    pub async fn use_recovery_code(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        code_hash: &str,
+    ) -> Result<bool, TwoFactorPostgresRepositoryError> {
+        let result = sqlx::query!(
+            r#"
+    UPDATE user_recovery_codes
+    SET used_at = $3
+    WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+            "#,
+            user_id,
+            code_hash,
+            Utc::now(),
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum TwoFactorPostgresRepositoryError {
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for TwoFactorPostgresRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
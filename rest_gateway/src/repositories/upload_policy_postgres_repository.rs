@@ -0,0 +1,117 @@
+use common::helper::error_chain_fmt;
+use sqlx::PgExecutor;
+
+use crate::domain::entities::upload_policy::UploadPolicy;
+
+/// Upload policy repository implemented using Postgres
+pub struct UploadPolicyPostgresRepository {}
+
+impl Default for UploadPolicyPostgresRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UploadPolicyPostgresRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Saves a policy as the next version of the policy of its tenant
+    ///
+    /// # Returns
+    /// The saved policy, with its version
+    #[tracing::instrument(name = "Saving upload policy in database", skip(self, db_executor))]
+    pub async fn add_policy(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        policy: &UploadPolicy,
+    ) -> Result<UploadPolicy, UploadPolicyPostgresRepositoryError> {
+        // A concurrent change of the policy of the tenant fails on the unique version
+        let policy = sqlx::query_as!(
+            UploadPolicy,
+            r#"
+    INSERT INTO upload_policies (id, tenant_id, version, allowed_mime_types, max_size_bytes, scan_required, created_at)
+    SELECT $1, $2, COALESCE(MAX(version), 0) + 1, $3, $4, $5, $6
+    FROM upload_policies
+    WHERE COALESCE(tenant_id, '') = COALESCE($2, '')
+    RETURNING id, tenant_id, version, allowed_mime_types, max_size_bytes, scan_required, created_at
+            "#,
+            policy.id,
+            policy.tenant_id,
+            &policy.allowed_mime_types,
+            policy.max_size_bytes,
+            policy.scan_required,
+            policy.created_at
+        )
+        .fetch_one(db_executor)
+        .await?;
+
+        Ok(policy)
+    }
+
+    /// Gets the latest version of the policy of a tenant, applied to the uploads of its users
+    #[tracing::instrument(
+        name = "Getting current tenant upload policy from database",
+        skip(self, db_executor)
+    )]
+    pub async fn get_tenant_policy(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<UploadPolicy>, UploadPolicyPostgresRepositoryError> {
+        let policy = sqlx::query_as!(
+            UploadPolicy,
+            r#"
+    SELECT id, tenant_id, version, allowed_mime_types, max_size_bytes, scan_required, created_at
+    FROM upload_policies
+    WHERE COALESCE(tenant_id, '') = COALESCE($1, '')
+    ORDER BY version DESC
+    LIMIT 1
+            "#,
+            tenant_id,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(policy)
+    }
+
+    /// Lists all the versions of the policy of a tenant, from the latest one
+    #[tracing::instrument(
+        name = "Listing tenant upload policies in database",
+        skip(self, db_executor)
+    )]
+    pub async fn list_tenant_policies(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<UploadPolicy>, UploadPolicyPostgresRepositoryError> {
+        let policies = sqlx::query_as!(
+            UploadPolicy,
+            r#"
+    SELECT id, tenant_id, version, allowed_mime_types, max_size_bytes, scan_required, created_at
+    FROM upload_policies
+    WHERE COALESCE(tenant_id, '') = COALESCE($1, '')
+    ORDER BY version DESC
+            "#,
+            tenant_id,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(policies)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum UploadPolicyPostgresRepositoryError {
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for UploadPolicyPostgresRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
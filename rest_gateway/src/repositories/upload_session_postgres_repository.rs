@@ -0,0 +1,202 @@
+use common::helper::error_chain_fmt;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::domain::entities::upload_session::{UploadPart, UploadSession};
+
+/// Chunked upload sessions repository implemented using Postgres
+pub struct UploadSessionPostgresRepository {}
+
+impl Default for UploadSessionPostgresRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UploadSessionPostgresRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    #[tracing::instrument(
+        name = "Saving new upload session in database",
+        skip(self, db_executor, session),
+        fields(upload_session_id = %session.id)
+    )]
+    pub async fn add_session(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        session: &UploadSession,
+    ) -> Result<(), UploadSessionPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    INSERT INTO upload_sessions (id, user_id, file_name, content_type, tags, object_store_name, s3_upload_id,
+        source_meta_id, completed_at, created_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            "#,
+            session.id,
+            session.user_id,
+            session.file_name,
+            session.content_type,
+            &session.tags,
+            session.object_store_name,
+            session.s3_upload_id,
+            session.source_meta_id,
+            session.completed_at,
+            session.created_at,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Gets an upload session of a user, `None` if the user has no such session
+    #[tracing::instrument(
+        name = "Getting user upload session from database",
+        skip(self, db_executor)
+    )]
+    pub async fn get_user_session(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        session_id: Uuid,
+    ) -> Result<Option<UploadSession>, UploadSessionPostgresRepositoryError> {
+        let session = sqlx::query_as!(
+            UploadSession,
+            r#"
+    SELECT id, user_id, file_name, content_type, tags, object_store_name, s3_upload_id,
+        source_meta_id, completed_at, created_at
+    FROM upload_sessions
+    WHERE id = $1 AND user_id = $2
+            "#,
+            session_id,
+            user_id,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(session)
+    }
+
+    /// Saves a part received by the object storage, replacing the part uploaded before with the same number
+    #[tracing::instrument(
+        name = "Saving upload session part in database",
+        skip(self, db_executor, part),
+        fields(part_number = part.part_number)
+    )]
+    pub async fn save_part(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        session_id: Uuid,
+        part: &UploadPart,
+    ) -> Result<(), UploadSessionPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    INSERT INTO upload_session_parts (upload_session_id, part_number, etag, size_bytes, uploaded_at)
+    VALUES ($1, $2, $3, $4, $5)
+    ON CONFLICT (upload_session_id, part_number)
+    DO UPDATE SET etag = EXCLUDED.etag, size_bytes = EXCLUDED.size_bytes, uploaded_at = EXCLUDED.uploaded_at
+            "#,
+            session_id,
+            part.part_number,
+            part.etag,
+            part.size_bytes,
+            part.uploaded_at,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Lists the received parts of an upload session, by part number
+    #[tracing::instrument(
+        name = "Listing upload session parts from database",
+        skip(self, db_executor)
+    )]
+    pub async fn list_parts(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        session_id: Uuid,
+    ) -> Result<Vec<UploadPart>, UploadSessionPostgresRepositoryError> {
+        let parts = sqlx::query_as!(
+            UploadPart,
+            r#"
+    SELECT part_number, etag, size_bytes, uploaded_at
+    FROM upload_session_parts
+    WHERE upload_session_id = $1
+    ORDER BY part_number
+            "#,
+            session_id,
+        )
+        .fetch_all(db_executor)
+        .await?;
+
+        Ok(parts)
+    }
+
+    /// Marks an upload session as completed with its registered source
+    ///
+    /// # Returns
+    /// `false` if the session was already completed
+    #[tracing::instrument(
+        name = "Completing upload session in database",
+        skip(self, db_executor)
+    )]
+    pub async fn complete_session(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        session_id: Uuid,
+        source_meta_id: Uuid,
+    ) -> Result<bool, UploadSessionPostgresRepositoryError> {
+        let result = sqlx::query!(
+            r#"
+    UPDATE upload_sessions
+    SET source_meta_id = $2, completed_at = NOW()
+    WHERE id = $1 AND completed_at IS NULL
+            "#,
+            session_id,
+            source_meta_id,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Deletes an upload session with its parts, for ex once its multipart upload was aborted
+    #[tracing::instrument(
+        name = "Deleting upload session from database",
+        skip(self, db_executor)
+    )]
+    pub async fn delete_session(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        session_id: Uuid,
+    ) -> Result<(), UploadSessionPostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    DELETE FROM upload_sessions
+    WHERE id = $1
+            "#,
+            session_id,
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum UploadSessionPostgresRepositoryError {
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for UploadSessionPostgresRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,89 @@
+use common::{
+    core::rabbitmq_message_repository::{
+        RabbitMQMessageRepository, RabbitMQMessageRepositoryError,
+    },
+    helper::error_chain_fmt,
+};
+use futures::{future, stream::BoxStream, StreamExt};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::domain::entities::user_activity::UserActivity;
+
+/// User activity repository implemented with RabbitMQ
+///
+/// The activity of a user is published with the routing key of the user, on the exchange of the tenant of the user.
+/// It is not persisted: only the subscribers at the time it is published receive it.
+pub struct UserActivityRabbitMQRepository {}
+
+impl Default for UserActivityRabbitMQRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UserActivityRabbitMQRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Publishes an activity of a user
+    #[tracing::instrument(
+        name = "Publishing user activity",
+        skip(self, message_repository, activity)
+    )]
+    pub async fn publish(
+        &self,
+        message_repository: &RabbitMQMessageRepository,
+        user_id: Uuid,
+        activity: &UserActivity,
+    ) -> Result<(), UserActivityRabbitMQRepositoryError> {
+        let activity = activity.try_serializing()?;
+
+        message_repository
+            .publish(&UserActivity::routing_key(user_id), activity.as_bytes())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Subscribes to the activity of a user published from now on, until the returned stream is dropped
+    #[tracing::instrument(name = "Subscribing to user activity", skip(self, message_repository))]
+    pub async fn subscribe(
+        &self,
+        message_repository: &RabbitMQMessageRepository,
+        user_id: Uuid,
+    ) -> Result<BoxStream<'static, UserActivity>, UserActivityRabbitMQRepositoryError> {
+        let messages = message_repository
+            .subscribe(&UserActivity::routing_key(user_id))
+            .await?;
+
+        let activities = messages.filter_map(|data| {
+            let activity = match UserActivity::try_parsing(&data) {
+                Ok(activity) => Some(activity),
+                Err(error) => {
+                    warn!(?error, "Skipping an invalid user activity");
+                    None
+                }
+            };
+
+            future::ready(activity)
+        });
+
+        Ok(activities.boxed())
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum UserActivityRabbitMQRepositoryError {
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+    #[error("User activity could not be serialized: {0}")]
+    SerializationError(#[from] serde_json::Error),
+}
+
+impl std::fmt::Debug for UserActivityRabbitMQRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -1,7 +1,9 @@
 use crate::domain::entities::user::{CheckingUser, CreatingUser};
+use chrono::Utc;
 use common::helper::error_chain_fmt;
 use secrecy::Secret;
 use sqlx::PgExecutor;
+use uuid::Uuid;
 
 /// User repository implemented using Postgres
 pub struct UserPostgresRepository {}
@@ -25,12 +27,13 @@ impl UserPostgresRepository {
     ) -> Result<(), UserPostgresRepositoryError> {
         sqlx::query!(
             r#"
-    INSERT INTO users (id, email, password_hash, created_at, updated_at)
-    VALUES ($1, $2, $3, $4, $5)
+    INSERT INTO users (id, email, password_hash, tenant_id, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6)
             "#,
             user.id,
             user.email.as_ref(),
             user.password_hash.as_ref(),
+            user.tenant_id,
             user.created_at,
             user.updated_at
         )
@@ -64,6 +67,109 @@ impl UserPostgresRepository {
 
         Ok(user)
     }
+
+    /// Gets the tenant of a user, to route their messages to the tenant exchanges
+    ///
+    /// # Returns
+    /// The tenant id, or `None` if the user has no tenant
+    #[tracing::instrument(name = "Getting user tenant in database", skip(self, db_executor))]
+    pub async fn get_user_tenant_id(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+    ) -> Result<Option<String>, UserPostgresRepositoryError> {
+        let record = sqlx::query!(
+            r#"
+    SELECT tenant_id FROM users
+    WHERE id = $1
+            "#,
+            user_id,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(record.and_then(|record| record.tenant_id))
+    }
+
+    /// Gets the email of a user, naming their account in the authenticator apps
+    #[tracing::instrument(name = "Getting user email in database", skip(self, db_executor))]
+    pub async fn get_user_email(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+    ) -> Result<String, UserPostgresRepositoryError> {
+        let record = sqlx::query!(
+            r#"
+    SELECT email FROM users
+    WHERE id = $1
+            "#,
+            user_id,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        record
+            .map(|record| record.email)
+            .ok_or_else(|| UserPostgresRepositoryError::UserDoesNotExist(user_id.to_string()))
+    }
+
+    /// Gets the collection in which the uploaded sources of a user are filed when no auto-filing rule matches
+    ///
+    /// # Returns
+    /// The default collection, or `None` if the user has no default collection
+    #[tracing::instrument(
+        name = "Getting user default collection in database",
+        skip(self, db_executor)
+    )]
+    pub async fn get_user_default_collection(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+    ) -> Result<Option<String>, UserPostgresRepositoryError> {
+        let record = sqlx::query!(
+            r#"
+    SELECT default_collection FROM users
+    WHERE id = $1
+            "#,
+            user_id,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(record.and_then(|record| record.default_collection))
+    }
+
+    /// Sets, or unsets with `None`, the default collection of a user
+    #[tracing::instrument(
+        name = "Setting user default collection in database",
+        skip(self, db_executor)
+    )]
+    pub async fn set_user_default_collection(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        default_collection: Option<&str>,
+    ) -> Result<(), UserPostgresRepositoryError> {
+        let result = sqlx::query!(
+            r#"
+    UPDATE users SET default_collection = $2, updated_at = $3
+    WHERE id = $1
+            "#,
+            user_id,
+            default_collection,
+            Utc::now()
+        )
+        .execute(db_executor)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserPostgresRepositoryError::UserDoesNotExist(
+                user_id.to_string(),
+            ));
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(thiserror::Error)]
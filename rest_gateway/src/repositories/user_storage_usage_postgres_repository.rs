@@ -0,0 +1,123 @@
+use chrono::Utc;
+use common::helper::error_chain_fmt;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+/// Storage used by the source files of each user, implemented using Postgres
+///
+/// The usage of a user is increased when one of their sources is registered, within their quota,
+/// and decreased when one of their sources is deleted.
+pub struct UserStorageUsagePostgresRepository {}
+
+impl Default for UserStorageUsagePostgresRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UserStorageUsagePostgresRepository {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Storage used by a user, 0 if they never uploaded a source
+    #[tracing::instrument(
+        name = "Getting user storage usage from database",
+        skip(self, db_executor)
+    )]
+    pub async fn get_used_bytes(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+    ) -> Result<u64, UserStorageUsagePostgresRepositoryError> {
+        let used_bytes = sqlx::query_scalar!(
+            r#"
+    SELECT used_bytes
+    FROM user_storage_usage
+    WHERE user_id = $1
+            "#,
+            user_id,
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(used_bytes.unwrap_or(0) as u64)
+    }
+
+    /// Adds the size of a file to the storage used by a user, if it stays within their quota
+    ///
+    /// The usage of the user is locked until the end of the transaction: concurrent uploads can not exceed the quota.
+    ///
+    /// # Returns
+    /// False if the file would exceed the quota of the user: their usage is not changed
+    #[tracing::instrument(
+        name = "Adding to user storage usage in database",
+        skip(self, db_executor)
+    )]
+    pub async fn try_add_usage(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        size_bytes: u64,
+        quota_bytes: Option<u64>,
+    ) -> Result<bool, UserStorageUsagePostgresRepositoryError> {
+        let used_bytes = sqlx::query_scalar!(
+            r#"
+    INSERT INTO user_storage_usage (user_id, used_bytes, updated_at)
+    SELECT $1, $2, $4
+    WHERE $3::BIGINT IS NULL OR $2 <= $3
+    ON CONFLICT (user_id) DO UPDATE
+    SET used_bytes = user_storage_usage.used_bytes + EXCLUDED.used_bytes, updated_at = EXCLUDED.updated_at
+    WHERE $3::BIGINT IS NULL OR user_storage_usage.used_bytes + EXCLUDED.used_bytes <= $3
+    RETURNING used_bytes
+            "#,
+            user_id,
+            size_bytes as i64,
+            quota_bytes.map(|quota_bytes| quota_bytes as i64),
+            Utc::now(),
+        )
+        .fetch_optional(db_executor)
+        .await?;
+
+        Ok(used_bytes.is_some())
+    }
+
+    /// Removes the size of a deleted file from the storage used by a user
+    #[tracing::instrument(
+        name = "Removing from user storage usage in database",
+        skip(self, db_executor)
+    )]
+    pub async fn remove_usage(
+        &self,
+        db_executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        size_bytes: u64,
+    ) -> Result<(), UserStorageUsagePostgresRepositoryError> {
+        sqlx::query!(
+            r#"
+    UPDATE user_storage_usage
+    SET used_bytes = GREATEST(used_bytes - $2, 0), updated_at = $3
+    WHERE user_id = $1
+            "#,
+            user_id,
+            size_bytes as i64,
+            Utc::now(),
+        )
+        .execute(db_executor)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum UserStorageUsagePostgresRepositoryError {
+    #[error(transparent)]
+    DBError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for UserStorageUsagePostgresRepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod ndjson;
+pub mod sparse_fields;
+pub mod sse;
@@ -0,0 +1,116 @@
+use actix_web::{
+    guard::{Guard, GuardContext},
+    http::header::Accept,
+    web::Bytes,
+    HttpResponse,
+};
+use futures::{Future, Stream, StreamExt};
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::error;
+
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Number of produced items waiting to be sent to a slow client
+const ITEMS_BUFFER_SIZE: usize = 32;
+
+/// Guard on the requests accepting NDJSON (newline delimited JSON) responses
+///
+/// Routes guarded by it should be registered before their JSON counterpart.
+pub struct AcceptsNdjson;
+
+impl Guard for AcceptsNdjson {
+    fn check(&self, ctx: &GuardContext<'_>) -> bool {
+        ctx.header::<Accept>().is_some_and(|accept| {
+            accept
+                .0
+                .iter()
+                .any(|media_type| media_type.item.essence_str() == NDJSON_CONTENT_TYPE)
+        })
+    }
+}
+
+/// Streams items as an NDJSON response: each item is sent on its own line, as soon as it is available
+///
+/// The status is sent with the first line: an error on an item ends the response abruptly,
+/// for the client to know the response is incomplete.
+pub fn ndjson_response<T, E>(items: impl Stream<Item = Result<T, E>> + 'static) -> HttpResponse
+where
+    T: Serialize,
+    E: std::fmt::Debug,
+{
+    let lines = items.map(|item| {
+        let item = item.map_err(|error| {
+            error!(?error, "Failed to produce an NDJSON item");
+            actix_web::error::ErrorInternalServerError("Failed to produce an item")
+        })?;
+
+        let mut line = serde_json::to_vec(&item)?;
+        line.push(b'\n');
+        Ok::<Bytes, actix_web::Error>(Bytes::from(line))
+    });
+
+    HttpResponse::Ok()
+        .content_type(NDJSON_CONTENT_TYPE)
+        .streaming(lines)
+}
+
+/// Stream of the items sent by a producer, spawned in its own task
+///
+/// The producer can own the resources it reads from, ex: a database pool.
+/// Sending an item fails once the client disconnected: the producer should then stop.
+pub fn spawn_producer<T, E, F, Fut>(produce: F) -> impl Stream<Item = Result<T, E>>
+where
+    T: Send + 'static,
+    E: Send + 'static,
+    F: FnOnce(mpsc::Sender<Result<T, E>>) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel(ITEMS_BUFFER_SIZE);
+    tokio::spawn(produce(sender));
+
+    ReceiverStream::new(receiver)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::header, test::TestRequest};
+
+    #[test]
+    fn accepts_ndjson_guard_matches_requests_accepting_ndjson() {
+        let request = TestRequest::default()
+            .insert_header((header::ACCEPT, "application/json, application/x-ndjson"))
+            .to_srv_request();
+
+        assert!(AcceptsNdjson.check(&request.guard_ctx()));
+    }
+
+    #[test]
+    fn accepts_ndjson_guard_does_not_match_other_requests() {
+        let json_request = TestRequest::default()
+            .insert_header((header::ACCEPT, "application/json"))
+            .to_srv_request();
+        let any_request = TestRequest::default()
+            .insert_header((header::ACCEPT, "*/*"))
+            .to_srv_request();
+        let request_without_accept = TestRequest::default().to_srv_request();
+
+        assert!(!AcceptsNdjson.check(&json_request.guard_ctx()));
+        assert!(!AcceptsNdjson.check(&any_request.guard_ctx()));
+        assert!(!AcceptsNdjson.check(&request_without_accept.guard_ctx()));
+    }
+
+    #[actix_web::test]
+    async fn ndjson_response_sends_one_item_per_line() {
+        let items = futures::stream::iter(vec![Ok::<_, String>(1), Ok(2)]);
+
+        let response = ndjson_response(items);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+
+        assert_eq!(body, "1\n2\n");
+    }
+}
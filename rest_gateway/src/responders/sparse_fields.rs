@@ -0,0 +1,157 @@
+use common::helper::error_chain_fmt;
+use serde::{ser::Error as _, Deserialize, Serialize, Serializer};
+use serde_aux::serde_introspection::serde_introspect;
+use serde_json::Value as JsonValue;
+use std::sync::Arc;
+use utoipa::IntoParams;
+
+/// `fields` query parameter of the endpoints without another query parameter
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FieldsQuery {
+    /// Comma-separated fields of the items to return, all by default
+    pub fields: Option<String>,
+}
+
+/// Fields of the response items requested by a client, from a `fields` query parameter
+///
+/// For ex: `fields=id,score` to only get the ids and scores of the found contents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldSet(Vec<String>);
+
+impl FieldSet {
+    /// Parses comma-separated field names, which should all be fields of the items `T`
+    pub fn try_parse<'de, T: Deserialize<'de>>(fields: &str) -> Result<Self, InvalidFieldsError> {
+        let known_fields = serde_introspect::<T>();
+
+        let fields: Vec<String> = fields
+            .split(',')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        if fields.is_empty() {
+            return Err(InvalidFieldsError::NoFields);
+        }
+
+        if let Some(unknown_field) = fields
+            .iter()
+            .find(|field| !known_fields.contains(&field.as_str()))
+        {
+            return Err(InvalidFieldsError::UnknownField(
+                unknown_field.clone(),
+                known_fields.join(","),
+            ));
+        }
+
+        Ok(Self(fields))
+    }
+
+    /// Parses an optional `fields` query parameter: all the fields are kept without it
+    pub fn try_parse_query<'de, T: Deserialize<'de>>(
+        fields: Option<&str>,
+    ) -> Result<Option<Arc<Self>>, InvalidFieldsError> {
+        fields
+            .map(|fields| Self::try_parse::<T>(fields).map(Arc::new))
+            .transpose()
+    }
+
+    fn retain(&self, value: &mut JsonValue) {
+        if let JsonValue::Object(object) = value {
+            object.retain(|key, _| self.0.contains(key));
+        }
+    }
+}
+
+/// Response item serialized with only the requested fields, or with all its fields without field set
+///
+/// The item is serialized to a JSON value before being filtered:
+/// the payload sent to the client is smaller, not the serialization work.
+#[derive(Debug)]
+pub struct Sparse<T> {
+    item: T,
+    fields: Option<Arc<FieldSet>>,
+}
+
+impl<T> Sparse<T> {
+    pub fn new(item: T, fields: Option<Arc<FieldSet>>) -> Self {
+        Self { item, fields }
+    }
+}
+
+impl<T: Serialize> Serialize for Sparse<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match &self.fields {
+            None => self.item.serialize(serializer),
+            Some(fields) => {
+                let mut value = serde_json::to_value(&self.item).map_err(S::Error::custom)?;
+                fields.retain(&mut value);
+                value.serialize(serializer)
+            }
+        }
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum InvalidFieldsError {
+    #[error("No field requested in `fields`")]
+    NoFields,
+    #[error("Unknown field `{0}` in `fields`, available fields: {1}")]
+    UnknownField(String, String),
+}
+
+impl std::fmt::Debug for InvalidFieldsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Serialize, Deserialize)]
+    struct Item {
+        id: u32,
+        content: String,
+        score: f64,
+    }
+
+    fn item() -> Item {
+        Item {
+            id: 1,
+            content: "A long content".to_string(),
+            score: 0.5,
+        }
+    }
+
+    #[test]
+    fn sparse_item_is_serialized_with_the_requested_fields_only() {
+        let fields = FieldSet::try_parse::<Item>("id, score").unwrap();
+
+        let value = serde_json::to_value(Sparse::new(item(), Some(Arc::new(fields)))).unwrap();
+
+        assert_eq!(value, json!({ "id": 1, "score": 0.5 }));
+    }
+
+    #[test]
+    fn item_without_field_set_is_serialized_with_all_its_fields() {
+        let value = serde_json::to_value(Sparse::new(item(), None)).unwrap();
+
+        assert_eq!(value, serde_json::to_value(item()).unwrap());
+    }
+
+    #[test]
+    fn unknown_or_missing_fields_are_rejected() {
+        assert!(matches!(
+            FieldSet::try_parse::<Item>("id,text"),
+            Err(InvalidFieldsError::UnknownField(field, _)) if field == "text"
+        ));
+        assert!(matches!(
+            FieldSet::try_parse::<Item>(" , "),
+            Err(InvalidFieldsError::NoFields)
+        ));
+    }
+}
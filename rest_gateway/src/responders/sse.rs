@@ -0,0 +1,142 @@
+use actix_web::{
+    guard::{Guard, GuardContext},
+    http::header::{Accept, CacheControl, CacheDirective},
+    web::Bytes,
+    HttpResponse,
+};
+use futures::{future, stream, Stream, StreamExt};
+use serde::Serialize;
+use std::time::Duration;
+use tokio::time::{interval_at, Instant};
+use tokio_stream::wrappers::IntervalStream;
+
+pub const SSE_CONTENT_TYPE: &str = "text/event-stream";
+
+/// Comment line ignored by the clients, keeping an idle connection open
+const KEEP_ALIVE_COMMENT: &[u8] = b": keep-alive\n\n";
+
+/// Guard on the requests accepting Server-Sent Events responses
+///
+/// Routes guarded by it should be registered before their JSON counterpart.
+pub struct AcceptsEventStream;
+
+impl Guard for AcceptsEventStream {
+    fn check(&self, ctx: &GuardContext<'_>) -> bool {
+        ctx.header::<Accept>().is_some_and(|accept| {
+            accept
+                .0
+                .iter()
+                .any(|media_type| media_type.item.essence_str() == SSE_CONTENT_TYPE)
+        })
+    }
+}
+
+/// Event of a Server-Sent Events stream, with its data serialized as JSON
+#[derive(Debug, Clone, PartialEq)]
+pub struct SseEvent {
+    pub name: &'static str,
+    pub data: String,
+}
+
+impl SseEvent {
+    pub fn json<T: Serialize>(name: &'static str, data: &T) -> Result<Self, serde_json::Error> {
+        Ok(Self {
+            name,
+            data: serde_json::to_string(data)?,
+        })
+    }
+
+    /// Frame of the event: its name, then its data on a single line
+    fn to_bytes(&self) -> Bytes {
+        Bytes::from(format!("event: {}\ndata: {}\n\n", self.name, self.data))
+    }
+}
+
+/// Streams events as a Server-Sent Events response: each event is sent as soon as it is available
+///
+/// A keep-alive comment is sent every `keep_alive_interval`, for the proxies not to close an idle connection,
+/// and for the disconnection of the client to be noticed: the stream of events is then dropped.
+/// The response ends with the stream of events.
+pub fn sse_response(
+    events: impl Stream<Item = SseEvent> + 'static,
+    keep_alive_interval: Duration,
+) -> HttpResponse {
+    // `None` once the stream of events ended, to end the keep-alives with it
+    let events = events
+        .map(|event| Some(event.to_bytes()))
+        .chain(stream::once(future::ready(None)));
+    let keep_alives = IntervalStream::new(interval_at(
+        Instant::now() + keep_alive_interval,
+        keep_alive_interval,
+    ))
+    .map(|_| Some(Bytes::from_static(KEEP_ALIVE_COMMENT)));
+
+    let frames = stream::select(events, keep_alives)
+        .take_while(|frame| future::ready(frame.is_some()))
+        .filter_map(|frame| future::ready(frame.map(Ok::<Bytes, actix_web::Error>)));
+
+    HttpResponse::Ok()
+        .content_type(SSE_CONTENT_TYPE)
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        // Disables the buffering of the responses by nginx
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::header, test::TestRequest};
+
+    #[test]
+    fn accepts_event_stream_guard_only_matches_requests_accepting_events() {
+        let request = TestRequest::default()
+            .insert_header((header::ACCEPT, "text/event-stream"))
+            .to_srv_request();
+        let any_request = TestRequest::default()
+            .insert_header((header::ACCEPT, "*/*"))
+            .to_srv_request();
+
+        assert!(AcceptsEventStream.check(&request.guard_ctx()));
+        assert!(!AcceptsEventStream.check(&any_request.guard_ctx()));
+    }
+
+    #[actix_web::test]
+    async fn sse_response_sends_the_events_until_their_stream_ends() {
+        let events = stream::iter(vec![
+            SseEvent::json("progress", &serde_json::json!({ "chunk_index": 1 })).unwrap(),
+            SseEvent::json("progress", &serde_json::json!({ "chunk_index": 2 })).unwrap(),
+        ]);
+
+        let response = sse_response(events, Duration::from_secs(60));
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            SSE_CONTENT_TYPE
+        );
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            body,
+            "event: progress\ndata: {\"chunk_index\":1}\n\nevent: progress\ndata: {\"chunk_index\":2}\n\n"
+        );
+    }
+
+    #[actix_web::test]
+    async fn sse_response_keeps_an_idle_connection_alive() {
+        let event = SseEvent::json("progress", &1).unwrap();
+        let events = stream::once(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            event
+        });
+
+        let response = sse_response(events, Duration::from_millis(20));
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+
+        assert!(body.starts_with(KEEP_ALIVE_COMMENT));
+        assert!(body.ends_with(b"event: progress\ndata: 1\n\n"));
+    }
+}
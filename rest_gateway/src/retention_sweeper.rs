@@ -0,0 +1,157 @@
+use chrono::{DateTime, Duration, Utc};
+use common::{
+    core::{
+        rabbitmq_message_repository::RabbitMQMessageRepositoryError,
+        tenancy::TenantMessageRepositories,
+    },
+    helper::error_chain_fmt,
+};
+use sqlx::PgPool;
+use tracing::{error, info};
+
+use crate::{
+    configuration::RetentionSettings,
+    controllers::delete_source::SourceDeletion,
+    domain::entities::source_event::SourceEvent,
+    repositories::{
+        retention_rule_postgres_repository::{
+            RetentionRulePostgresRepository, RetentionRulePostgresRepositoryError,
+        },
+        source_event_postgres_repository::{
+            SourceEventPostgresRepository, SourceEventPostgresRepositoryError,
+        },
+        source_file_s3_repository::S3Repository,
+    },
+};
+
+/// Enforces the retention rules of the collections of the users
+///
+/// Periodically deletes the sources expired with the retention of their collection, like a deletion requested
+/// by their user, and warns the sources expiring soon with an `expiring` event in their stream.
+/// Each gateway instance runs a sweeper: a source deleted by another instance is skipped.
+pub struct RetentionSweeper {
+    db_pool: PgPool,
+    message_repositories: TenantMessageRepositories,
+    settings: RetentionSettings,
+    retention_rule_repository: RetentionRulePostgresRepository,
+    source_event_repository: SourceEventPostgresRepository,
+    source_deletion: SourceDeletion,
+}
+
+/// Sources warned and deleted by a sweep
+#[derive(Debug, Default, PartialEq)]
+pub struct RetentionSweepReport {
+    pub nb_warned: usize,
+    pub nb_deleted: usize,
+}
+
+impl RetentionSweeper {
+    /// # Params
+    /// - message_repositories: not initialized, the sweeper initializes its own repositories
+    pub fn new(
+        db_pool: PgPool,
+        s3_repository: S3Repository,
+        message_repositories: TenantMessageRepositories,
+        settings: RetentionSettings,
+    ) -> Self {
+        Self {
+            db_pool,
+            message_repositories,
+            settings,
+            retention_rule_repository: RetentionRulePostgresRepository::new(),
+            source_event_repository: SourceEventPostgresRepository::new(),
+            source_deletion: SourceDeletion::new(s3_repository),
+        }
+    }
+
+    /// Sweeps every `sweep_interval_ms`, from now. A failed sweep is retried with the next one.
+    pub async fn run(mut self) -> Result<(), RetentionSweeperError> {
+        self.message_repositories = self.message_repositories.clone().try_init().await?;
+
+        let mut interval = tokio::time::interval(std::time::Duration::from_millis(
+            self.settings.sweep_interval_ms,
+        ));
+
+        loop {
+            interval.tick().await;
+
+            match self.sweep(Utc::now()).await {
+                Ok(report) => info!(?report, "Swept the expired sources"),
+                Err(error) => error!(?error, "Failed to sweep the expired sources"),
+            }
+        }
+    }
+
+    /// Warns the sources expiring within `warning_days`, and deletes the expired ones
+    ///
+    /// A source failing to be deleted does not stop the sweep: it is deleted by a next sweep.
+    #[tracing::instrument(name = "Sweeping expired sources", skip(self))]
+    pub async fn sweep(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<RetentionSweepReport, RetentionSweeperError> {
+        let mut report = RetentionSweepReport::default();
+        let warning_period = Duration::days(self.settings.warning_days.into());
+        let limit = self.settings.max_sources_per_sweep.into();
+
+        let expiring_sources = self
+            .retention_rule_repository
+            .list_expiring_sources(
+                &self.db_pool,
+                now,
+                now + warning_period,
+                now - warning_period,
+                limit,
+            )
+            .await?;
+        for expiring_source in expiring_sources {
+            self.source_event_repository
+                .add_event(&self.db_pool, &SourceEvent::expiring(&expiring_source))
+                .await?;
+            report.nb_warned += 1;
+        }
+
+        let expired_source_metas = self
+            .retention_rule_repository
+            .list_expired_source_metas(&self.db_pool, now, limit)
+            .await?;
+        for source_meta in expired_source_metas {
+            match self
+                .source_deletion
+                .delete(
+                    &self.db_pool,
+                    &self.message_repositories,
+                    source_meta.user_id,
+                    source_meta.id,
+                    SourceEvent::expired,
+                )
+                .await
+            {
+                Ok(true) => report.nb_deleted += 1,
+                Ok(false) => (),
+                Err(error) => error!(
+                    ?error,
+                    "Failed to delete the expired source {}", source_meta.id
+                ),
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum RetentionSweeperError {
+    #[error(transparent)]
+    RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+    #[error(transparent)]
+    RetentionRuleRepositoryError(#[from] RetentionRulePostgresRepositoryError),
+    #[error(transparent)]
+    SourceEventRepositoryError(#[from] SourceEventPostgresRepositoryError),
+}
+
+impl std::fmt::Debug for RetentionSweeperError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
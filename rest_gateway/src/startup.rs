@@ -3,26 +3,103 @@ use actix_web::{
     web::{self, Data},
     App, HttpServer,
 };
-use common::core::rabbitmq_message_repository::{
-    RabbitMQMessageRepository, RabbitMQMessageRepositoryError,
+use common::core::{
+    health_server::HealthChecks,
+    rabbitmq_message_repository::{RabbitMQMessageRepository, RabbitMQMessageRepositoryError},
+    secrets::{SecretsCipher, SecretsError},
+    tenancy::{tenant_name_prefix, TenantMessageRepositories, TenantSettings},
 };
+use futures::TryFutureExt;
 use s3::{creds::Credentials, Bucket, BucketConfiguration, Region};
 use secrecy::ExposeSecret;
 use sqlx::{postgres::PgPoolOptions, PgPool};
-use std::{net::TcpListener, sync::Arc};
-use tracing::info;
+use std::{collections::HashMap, net::TcpListener, sync::Arc, time::Duration};
+use tracing::{error, info};
 use tracing_actix_web::TracingLogger;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 
 use crate::{
-    configuration::{DatabaseSettings, ObjectStorageSettings, RabbitMQSettings, Settings},
-    controllers::{add_source_files, create_account, health_check, log_in_account, search_content},
-    middlewares::jwt_authentication::middleware::RequireAuth,
+    configuration::{
+        AnswerGenerationSettings, AuthenticationBackend, AuthenticationSettings, DatabaseSettings,
+        ObjectStorageSettings, ProviderCredentialsSettings, RabbitMQSettings, ReloadableSettings,
+        RerankSettings, Settings, VirusScanSettings,
+    },
+    controllers::{
+        abort_upload, add_normalization_rule, add_source_files, add_source_url, ask, ask_stream,
+        complete_upload, create_account, create_api_key, create_auto_filing_rule, delete_api_key,
+        delete_auto_filing_rule, delete_normalization_rule, delete_provider_credentials,
+        delete_recrawl_schedule, delete_retention_rule, delete_saved_search, delete_source,
+        download_source, enable_two_factor, get_events, get_ingestion_slo, get_job, get_metrics,
+        get_source_chunks, get_source_events, get_source_progress, get_source_summary, get_upload,
+        health_check, import_sources, list_api_keys, list_auto_filing_rules,
+        list_normalization_rules, list_provider_credentials, list_retention_rules,
+        list_saved_searches, list_search_history, list_sources, list_sources_ndjson,
+        list_upload_policies, log_in_account, log_out, promote_fulltext_standby, readiness,
+        refresh_token, reindex_sources, run_saved_search, save_provider_credentials,
+        save_retention_rule, save_search, save_upload_policy, search_content,
+        search_content_ndjson, set_default_collection, start_upload, update_auto_filing_rule,
+        upload_form_config, upload_part, verify_two_factor, AnswerGenerators,
+        ProviderCredentialsServices, RecrawlScheduling, SearchHistory, SearchServices,
+        SourceDeletion, SourceIntake, TwoFactorLogIn,
+    },
+    database_health::DatabasePoolProbe,
+    domain::entities::api_key::ApiKeyScope,
+    handlers::{
+        handler_content_extracted, handler_extraction_progress, handler_import_source,
+        handler_ingestion_job_status, handler_normalization_rules, handler_provider_credentials,
+        handler_provider_usage, handler_reindex_source, handler_source_summarized,
+        UserActivityPublisher,
+    },
+    importing::SourceImporter,
+    metrics::IngestionMetrics,
+    middlewares::{
+        admin_authentication::RequireAdmin, jwt_authentication::middleware::RequireAuth,
+        rate_limit::RateLimit, request_quota::RequestQuota,
+    },
+    migrations::{run_migrations, MigrationError},
+    openapi::{ApiDoc, OPENAPI_JSON_PATH},
+    outbox_relay::OutboxRelay,
+    recrawl_scheduler::RecrawlScheduler,
     repositories::{
+        answer_generation_port::AnswerGenerationPort,
+        api_key_postgres_repository::ApiKeyPostgresRepository, api_reranker::ApiReranker,
+        authenticator_port::AuthenticatorPort,
+        auto_filing_rule_postgres_repository::AutoFilingRulePostgresRepository,
+        clamav_scanner::ClamavScanner, document_postgres_repository::DocumentPostgresRepository,
+        extraction_progress_postgres_repository::ExtractionProgressPostgresRepository,
+        fulltext_shard_postgres_repository::FulltextShardPostgresRepository,
+        import_s3_repository::ImportS3Repository,
+        ingestion_job_postgres_repository::IngestionJobPostgresRepository,
         jwt_authentication_repository::JwtAuthenticationRepository,
+        jwt_authenticator::JwtAuthenticator, mtls_authenticator::MtlsAuthenticator,
+        noop_answer_generator::NoOpAnswerGenerator, noop_reranker::NoOpReranker,
+        noop_scanner::NoOpScanner,
+        normalization_rule_postgres_repository::NormalizationRulePostgresRepository,
+        oidc_introspection_authenticator::OidcIntrospectionAuthenticator,
+        openai_answer_generator::OpenAiAnswerGenerator,
+        outbox_message_postgres_repository::OutboxMessagePostgresRepository,
+        provider_api_repository::ProviderApiRepository,
+        provider_credentials_postgres_repository::ProviderCredentialsPostgresRepository,
+        refresh_token_postgres_repository::RefreshTokenPostgresRepository, rerank_port::RerankPort,
+        retention_rule_postgres_repository::RetentionRulePostgresRepository,
+        saved_search_postgres_repository::SavedSearchPostgresRepository, scan_port::ScanPort,
+        source_event_postgres_repository::SourceEventPostgresRepository,
         source_file_s3_repository::S3Repository,
         source_meta_postgres_repository::SourceMetaPostgresRepository,
+        source_summary_postgres_repository::SourceSummaryPostgresRepository,
+        source_url_repository::SourceUrlRepository,
+        source_url_schedule_postgres_repository::SourceUrlSchedulePostgresRepository,
+        static_token_authenticator::StaticTokenAuthenticator,
+        two_factor_postgres_repository::TwoFactorPostgresRepository,
+        upload_policy_postgres_repository::UploadPolicyPostgresRepository,
+        upload_session_postgres_repository::UploadSessionPostgresRepository,
+        user_activity_rabbitmq_repository::UserActivityRabbitMQRepository,
         user_postgres_repository::UserPostgresRepository,
+        user_storage_usage_postgres_repository::UserStorageUsagePostgresRepository,
     },
+    responders::{ndjson::AcceptsNdjson, sse::AcceptsEventStream},
+    retention_sweeper::RetentionSweeper,
 };
 
 /// Holds the newly built server, and some useful properties
@@ -30,6 +107,8 @@ pub struct Application {
     // Server
     server: Server,
     port: u16,
+    /// Tunables of the running server, updated when the configuration is reloaded
+    reloadable_settings: Arc<ReloadableSettings>,
 
     // S3
     // Used for integration tests
@@ -53,6 +132,16 @@ pub enum ApplicationBuildError {
     RabbitMQError(#[from] lapin::Error),
     #[error(transparent)]
     RabbitMQMessageRepositoryError(#[from] RabbitMQMessageRepositoryError),
+    #[error(transparent)]
+    SecretsError(#[from] SecretsError),
+    #[error("HTTP client error: {0}")]
+    HttpClientError(#[from] reqwest::Error),
+    #[error("Metrics error: {0}")]
+    MetricsError(#[from] prometheus::Error),
+    #[error("Configuration error: {0}")]
+    ConfigurationError(String),
+    #[error(transparent)]
+    MigrationError(#[from] MigrationError),
 }
 
 impl Application {
@@ -65,6 +154,9 @@ impl Application {
         nb_workers: Option<usize>,
     ) -> Result<Self, ApplicationBuildError> {
         let connection_pool = get_connection_pool(&settings.database);
+        if settings.database.run_migrations {
+            run_migrations(&connection_pool).await?;
+        }
 
         let address = format!(
             "{}:{}",
@@ -75,15 +167,14 @@ impl Application {
 
         let rabbitmq_publishing_connection = get_rabbitmq_connection(&settings.rabbitmq).await?;
         let rabbitmq_publishing_connection = Arc::new(rabbitmq_publishing_connection);
-        let rabbitmq_content_exchange_name = format!(
-            "{}_{}",
-            settings.rabbitmq.exchange_name_prefix, settings.rabbitmq.content_exchange
-        );
+        let rabbitmq_content_exchange_name = settings.rabbitmq.content_exchange_name(None);
 
         let message_rabbitmq_repository = RabbitMQMessageRepository::new(
             rabbitmq_publishing_connection.clone(),
             &rabbitmq_content_exchange_name,
-        );
+        )
+        .with_publisher_confirms(settings.rabbitmq.publisher_confirms)
+        .with_rpc_settings(settings.rabbitmq.rpc);
 
         let s3_bucket = set_up_s3(&settings.object_storage).await?;
         let s3_repository = S3Repository::new(s3_bucket.clone());
@@ -91,26 +182,171 @@ impl Application {
         let source_meta_repository = SourceMetaPostgresRepository::new();
         let user_repository = UserPostgresRepository::new();
 
+        let ingestion_metrics = Arc::new(IngestionMetrics::try_new()?);
+
+        // Saves the progress of the content extractions and the status of the ingestion jobs, published by the workers
+        spawn_worker_status_handlers(
+            &settings.rabbitmq,
+            None,
+            connection_pool.clone(),
+            ingestion_metrics.clone(),
+            &settings.provider_credentials,
+        )
+        .await?;
+
+        // Each tenant has its own connection (to its virtual host) and exchanges
+        let mut tenant_message_repositories = HashMap::new();
+        for tenant in settings.rabbitmq.tenants.iter() {
+            let tenant_publishing_connection =
+                get_tenant_rabbitmq_connection(&settings.rabbitmq, Some(tenant)).await?;
+
+            tenant_message_repositories.insert(
+                tenant.id.clone(),
+                RabbitMQMessageRepository::new(
+                    Arc::new(tenant_publishing_connection),
+                    &settings.rabbitmq.content_exchange_name(Some(tenant)),
+                )
+                .with_publisher_confirms(settings.rabbitmq.publisher_confirms)
+                .with_rpc_settings(settings.rabbitmq.rpc),
+            );
+
+            spawn_worker_status_handlers(
+                &settings.rabbitmq,
+                Some(tenant),
+                connection_pool.clone(),
+                ingestion_metrics.clone(),
+                &settings.provider_credentials,
+            )
+            .await?;
+        }
+
+        let message_repositories = TenantMessageRepositories::new(
+            message_rabbitmq_repository,
+            tenant_message_repositories,
+        );
+
+        // Deletes the sources expired with the retention rules of their collection
+        let retention_sweeper = RetentionSweeper::new(
+            connection_pool.clone(),
+            S3Repository::new(s3_bucket.clone()),
+            message_repositories.clone(),
+            settings.retention.clone(),
+        );
+        tokio::spawn(retention_sweeper.run().inspect_err(|error| {
+            error!(?error, "Retention sweeper stopped");
+        }));
+
+        // Publishes the messages of the outbox not published right after their transaction
+        let outbox_relay = OutboxRelay::new(
+            connection_pool.clone(),
+            message_repositories.clone(),
+            settings.outbox.clone(),
+        );
+        tokio::spawn(outbox_relay.run().inspect_err(|error| {
+            error!(?error, "Outbox relay stopped");
+        }));
+
+        let secrets_cipher = SecretsCipher::try_new(settings.secrets.clone())?;
+
+        // Re-crawls the sources added from a URL with a re-crawl interval
+        let recrawl_scheduler = RecrawlScheduler::new(
+            connection_pool.clone(),
+            S3Repository::new(s3_bucket.clone()),
+            SourceUrlRepository::new(&settings.url_downloads),
+            secrets_cipher.clone(),
+            message_repositories.clone(),
+            settings.recrawl.clone(),
+            settings.uploads.storage_quota(),
+        );
+        tokio::spawn(recrawl_scheduler.run().inspect_err(|error| {
+            error!(?error, "Re-crawl scheduler stopped");
+        }));
+
+        // Reindexes the sources on the commands of the admin API
+        tokio::spawn(
+            handler_reindex_source::register_handler(
+                get_rabbitmq_connection(&settings.rabbitmq).await?,
+                rabbitmq_content_exchange_name.clone(),
+                settings.rabbitmq.queue_name_prefix.clone(),
+                connection_pool.clone(),
+                message_repositories.clone(),
+            )
+            .inspect_err(|error| {
+                error!(?error, "Reindex source handler stopped");
+            }),
+        );
+
+        // Imports the objects of external buckets on the commands of the admin API
+        tokio::spawn(
+            handler_import_source::register_handler(
+                get_rabbitmq_connection(&settings.rabbitmq).await?,
+                rabbitmq_content_exchange_name.clone(),
+                settings.rabbitmq.queue_name_prefix.clone(),
+                SourceImporter::new(
+                    connection_pool.clone(),
+                    S3Repository::new(s3_bucket.clone()),
+                    ingestion_metrics.clone(),
+                    &settings,
+                ),
+                message_repositories.clone(),
+            )
+            .inspect_err(|error| {
+                error!(?error, "Import source handler stopped");
+            }),
+        );
+
         let auth_repository = JwtAuthenticationRepository::new(
             settings.jwt.secret.clone(),
             settings.jwt.expire_in_s as i64,
+            settings.jwt.refresh_token_expire_in_s as i64,
         );
 
+        let authenticator = get_authenticator(
+            &settings.authentication,
+            connection_pool.clone(),
+            auth_repository.clone(),
+        )?;
+
+        let provider_api_repository =
+            ProviderApiRepository::try_new(&settings.provider_credentials)?;
+
+        let reloadable_settings = Arc::new(ReloadableSettings::new(&settings));
+
+        // Probes the database pool, for the readiness endpoint and the metrics
+        let database_pool_probe =
+            DatabasePoolProbe::new(connection_pool.clone(), ingestion_metrics.clone());
+        database_pool_probe.probe().await;
+        tokio::spawn(database_pool_probe.clone().run(Duration::from_millis(
+            settings.database.pool.health_check_interval_ms,
+        )));
+        let health_checks =
+            HealthChecks::new().with_readiness_check("database", database_pool_probe);
+
+        let dependencies = AppDependencies {
+            message_repositories,
+            s3_repository,
+            source_meta_repository,
+            user_repository,
+            auth_repository,
+            authenticator,
+            secrets_cipher,
+            provider_api_repository,
+            ingestion_metrics,
+            health_checks,
+        };
         let server = run(
             listener,
             settings,
             nb_workers,
             connection_pool,
-            message_rabbitmq_repository,
-            s3_repository,
-            source_meta_repository,
-            user_repository,
-            auth_repository,
+            dependencies,
+            &reloadable_settings,
         )?;
 
         Ok(Self {
             server,
             port,
+            reloadable_settings,
             s3_bucket,
             _rabbitmq_publishing_connection: rabbitmq_publishing_connection,
             // rabbitmq_connection,
@@ -126,6 +362,11 @@ impl Application {
         self.s3_bucket.clone()
     }
 
+    /// Applies the tunables of reloaded settings to the running server
+    pub fn reloadable_settings(&self) -> Arc<ReloadableSettings> {
+        self.reloadable_settings.clone()
+    }
+
     /// This function only returns when the application is stopped
     pub async fn run_until_stopped(self) -> Result<(), std::io::Error> {
         info!("Running server ...");
@@ -133,6 +374,20 @@ impl Application {
     }
 }
 
+/// Services and repositories built by the application, shared by the controllers
+pub struct AppDependencies {
+    pub message_repositories: TenantMessageRepositories,
+    pub s3_repository: S3Repository,
+    pub source_meta_repository: SourceMetaPostgresRepository,
+    pub user_repository: UserPostgresRepository,
+    pub auth_repository: JwtAuthenticationRepository,
+    pub authenticator: Arc<dyn AuthenticatorPort>,
+    pub secrets_cipher: SecretsCipher,
+    pub provider_api_repository: ProviderApiRepository,
+    pub ingestion_metrics: Arc<IngestionMetrics>,
+    pub health_checks: HealthChecks,
+}
+
 /// listener: the consumer binds their own port
 ///
 /// TracingLogger middleware: helps collecting telemetry data.
@@ -143,61 +398,554 @@ impl Application {
 ///   if `None`, the number of available physical CPUs is used as the worker count.
 pub fn run(
     listener: TcpListener,
-    _settings: Settings,
+    settings: Settings,
     nb_workers: Option<usize>,
     db_pool: PgPool,
-    message_rabbitmq_repository: RabbitMQMessageRepository,
-    s3_repository: S3Repository,
-    source_meta_repository: SourceMetaPostgresRepository,
-    user_repository: UserPostgresRepository,
-    auth_repository: JwtAuthenticationRepository,
+    dependencies: AppDependencies,
+    reloadable_settings: &ReloadableSettings,
 ) -> Result<Server, std::io::Error> {
+    let AppDependencies {
+        message_repositories,
+        s3_repository,
+        source_meta_repository,
+        user_repository,
+        auth_repository,
+        authenticator,
+        secrets_cipher,
+        provider_api_repository,
+        ingestion_metrics,
+        health_checks,
+    } = dependencies;
+
     // Wraps the connection to a db in smart pointers
     let db_pool = Data::new(db_pool);
 
     // Wraps repositories in a `actix_web::Data` (`Arc`) to be able to register them
     // and access them from handlers.
     // Those repositories are shared among all threads.
+    let source_deletion = Data::new(SourceDeletion::new(s3_repository.clone()));
+    let source_intake = Data::new(SourceIntake::new(
+        s3_repository.clone(),
+        ingestion_metrics.clone(),
+        &settings,
+    ));
     let s3_repository = Data::new(s3_repository);
     let source_meta_repository = Data::new(source_meta_repository);
+    let extraction_progress_repository = Data::new(ExtractionProgressPostgresRepository::new());
+    let document_repository = Data::new(DocumentPostgresRepository::new());
+    let source_summary_repository = Data::new(SourceSummaryPostgresRepository::new());
+    let auto_filing_rule_repository = Data::new(AutoFilingRulePostgresRepository::new());
+    let retention_rule_repository = Data::new(RetentionRulePostgresRepository::new());
+    let ingestion_job_repository = Data::new(IngestionJobPostgresRepository::new());
+    let source_event_repository = Data::new(SourceEventPostgresRepository::new());
     let user_repository = Data::new(user_repository);
+    let refresh_token_repository = Data::new(RefreshTokenPostgresRepository::new());
+    let two_factor_repository = Data::new(TwoFactorPostgresRepository::new());
+    let api_key_repository = Data::new(ApiKeyPostgresRepository::new());
+    let saved_search_repository = Data::new(SavedSearchPostgresRepository::new());
+    let fulltext_shard_repository = Data::new(FulltextShardPostgresRepository::new());
+    let upload_session_repository = Data::new(UploadSessionPostgresRepository::new());
+    let upload_policy_repository = Data::new(UploadPolicyPostgresRepository::new());
+    let normalization_rule_repository = Data::new(NormalizationRulePostgresRepository::new());
+    let source_url_repository = Data::new(SourceUrlRepository::new(&settings.url_downloads));
+    let source_url_schedule_repository = Data::new(SourceUrlSchedulePostgresRepository::new());
+    let import_s3_repository = Data::new(ImportS3Repository::new(&settings.imports));
     let auth_repository = Data::new(auth_repository);
+    let authenticator = Data::from(authenticator);
+    let scanner = Data::from(get_scanner(&settings.virus_scan));
+    let answer_generators = Data::new(
+        AnswerGenerators::try_new(
+            get_answer_generator(&settings.answer_generation).map_err(std::io::Error::other)?,
+            secrets_cipher.clone(),
+            &settings.answer_generation,
+            settings.provider_credentials.clone(),
+        )
+        .map_err(std::io::Error::other)?,
+    );
+    let answer_generation = Data::new(settings.answer_generation.clone());
+    let search_services = Data::new(SearchServices::new(
+        get_reranker(&settings.rerank).map_err(std::io::Error::other)?,
+    ));
+    let provider_credentials_services = Data::new(ProviderCredentialsServices {
+        provider_credentials_repository: ProviderCredentialsPostgresRepository::new(),
+        user_repository: UserPostgresRepository::new(),
+        provider_api_repository,
+        secrets_cipher: secrets_cipher.clone(),
+    });
+    let recrawl_scheduling = Data::new(RecrawlScheduling::new(
+        secrets_cipher.clone(),
+        settings.recrawl.clone(),
+    ));
+    let two_factor_log_in = Data::new(TwoFactorLogIn::new(
+        secrets_cipher.clone(),
+        settings.two_factor.clone(),
+    ));
+    let secrets_cipher = Data::new(secrets_cipher);
+    let ingestion_metrics = Data::from(ingestion_metrics);
+    let admin_settings = Data::new(settings.admin.clone());
+    let fulltext_sharding = Data::new(settings.fulltext_sharding.clone());
+    let ingestion_lanes = Data::new(settings.ingestion_lanes.clone());
+    let uploads_settings = Data::new(settings.uploads.clone());
+    let source_downloads = Data::new(settings.source_downloads.clone());
+    let activity_stream = Data::new(settings.activity_stream.clone());
+    let search_history = Data::new(SearchHistory::new(settings.search_history.clone()));
+    let user_activity_repository = Data::new(UserActivityRabbitMQRepository::new());
+    let upload_form_config = Data::new(upload_form_config(&settings.uploads));
+    let user_storage_usage_repository = Data::new(UserStorageUsagePostgresRepository::new());
+    let outbox_message_repository = Data::new(OutboxMessagePostgresRepository::new());
+    let health_checks = Data::new(health_checks);
+
+    // Shared by all the workers. Wrapped outside the authentication, to shed the anonymous requests first
+    let search_quota = RequestQuota::new(&settings.search_quota, authenticator.clone());
+    let search_rate_limit = RateLimit::new(reloadable_settings.search_rate_limit());
+    let upload_rate_limit = RateLimit::new(reloadable_settings.upload_rate_limit());
+    let login_rate_limit = RateLimit::new(reloadable_settings.login_rate_limit());
+    let require_admin = RequireAdmin::new(&settings.admin.token);
+    // Generated once from the annotations of the controllers
+    let openapi = ApiDoc::openapi();
 
     // `move` to capture variables from the surrounding environment
     let server = HttpServer::new(move || {
         info!("Starting actix-web worker");
 
-        // Only clones thread-safe properties (ie, not the RabbitMQ channel)
-        let message_rabbitmq_repository = message_rabbitmq_repository.clone();
+        // Only clones thread-safe properties (ie, not the RabbitMQ channels)
+        let message_repositories = message_repositories.clone();
 
         App::new()
             .wrap(TracingLogger::default())
             .route("/health_check", web::get().to(health_check))
+            .route("/health/ready", web::get().to(readiness))
+            .service(SwaggerUi::new("/swagger-ui/{_:.*}").url(OPENAPI_JSON_PATH, openapi.clone()))
             .route(
                 "/add_source_files",
                 web::post()
                     .to(add_source_files)
-                    .wrap(RequireAuth::new(auth_repository.clone())),
+                    .wrap(upload_rate_limit.clone())
+                    .wrap(
+                        RequireAuth::new(authenticator.clone())
+                            .with_api_key_scope(ApiKeyScope::Upload),
+                    ),
+            )
+            .route(
+                "/add_source_url",
+                web::post()
+                    .to(add_source_url)
+                    .wrap(upload_rate_limit.clone())
+                    .wrap(
+                        RequireAuth::new(authenticator.clone())
+                            .with_api_key_scope(ApiKeyScope::Upload),
+                    ),
+            )
+            .route(
+                "/uploads",
+                web::post()
+                    .to(start_upload)
+                    .wrap(upload_rate_limit.clone())
+                    .wrap(
+                        RequireAuth::new(authenticator.clone())
+                            .with_api_key_scope(ApiKeyScope::Upload),
+                    ),
+            )
+            .route(
+                "/uploads/{upload_id}",
+                web::get()
+                    .to(get_upload)
+                    .wrap(upload_rate_limit.clone())
+                    .wrap(
+                        RequireAuth::new(authenticator.clone())
+                            .with_api_key_scope(ApiKeyScope::Upload),
+                    ),
+            )
+            .route(
+                "/uploads/{upload_id}",
+                web::delete()
+                    .to(abort_upload)
+                    .wrap(upload_rate_limit.clone())
+                    .wrap(
+                        RequireAuth::new(authenticator.clone())
+                            .with_api_key_scope(ApiKeyScope::Upload),
+                    ),
+            )
+            .route(
+                "/uploads/{upload_id}/parts/{part_number}",
+                web::put()
+                    .to(upload_part)
+                    .wrap(upload_rate_limit.clone())
+                    .wrap(
+                        RequireAuth::new(authenticator.clone())
+                            .with_api_key_scope(ApiKeyScope::Upload),
+                    ),
+            )
+            .route(
+                "/uploads/{upload_id}/complete",
+                web::post()
+                    .to(complete_upload)
+                    .wrap(upload_rate_limit.clone())
+                    .wrap(
+                        RequireAuth::new(authenticator.clone())
+                            .with_api_key_scope(ApiKeyScope::Upload),
+                    ),
+            )
+            // Routes guarded by the content negotiation are registered before their default JSON routes
+            .route(
+                "/search",
+                web::post()
+                    .guard(AcceptsNdjson)
+                    .to(search_content_ndjson)
+                    .wrap(search_rate_limit.clone())
+                    .wrap(
+                        RequireAuth::new(authenticator.clone())
+                            .with_api_key_scope(ApiKeyScope::Search),
+                    )
+                    .wrap(search_quota.clone()),
             )
             .route(
                 "/search",
                 web::post()
                     .to(search_content)
-                    .wrap(RequireAuth::new(auth_repository.clone())),
+                    .wrap(search_rate_limit.clone())
+                    .wrap(
+                        RequireAuth::new(authenticator.clone())
+                            .with_api_key_scope(ApiKeyScope::Search),
+                    )
+                    .wrap(search_quota.clone()),
+            )
+            .route(
+                "/ask",
+                web::post()
+                    .guard(AcceptsEventStream)
+                    .to(ask_stream)
+                    .wrap(search_rate_limit.clone())
+                    .wrap(
+                        RequireAuth::new(authenticator.clone())
+                            .with_api_key_scope(ApiKeyScope::Search),
+                    )
+                    .wrap(search_quota.clone()),
+            )
+            .route(
+                "/ask",
+                web::post()
+                    .to(ask)
+                    .wrap(search_rate_limit.clone())
+                    .wrap(
+                        RequireAuth::new(authenticator.clone())
+                            .with_api_key_scope(ApiKeyScope::Search),
+                    )
+                    .wrap(search_quota.clone()),
+            )
+            .route(
+                "/search/history",
+                web::get()
+                    .to(list_search_history)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/saved_searches",
+                web::get()
+                    .to(list_saved_searches)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/saved_searches",
+                web::post()
+                    .to(save_search)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/saved_searches/{saved_search_id}",
+                web::delete()
+                    .to(delete_saved_search)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/saved_searches/{saved_search_id}/search",
+                web::post()
+                    .to(run_saved_search)
+                    .wrap(search_rate_limit.clone())
+                    .wrap(
+                        RequireAuth::new(authenticator.clone())
+                            .with_api_key_scope(ApiKeyScope::Search),
+                    )
+                    .wrap(search_quota.clone()),
+            )
+            .route(
+                "/sources",
+                web::get()
+                    .guard(AcceptsNdjson)
+                    .to(list_sources_ndjson)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/sources",
+                web::get()
+                    .to(list_sources)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/sources/{source_id}",
+                web::delete()
+                    .to(delete_source)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/sources/{source_id}/recrawl_schedule",
+                web::delete()
+                    .to(delete_recrawl_schedule)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/sources/{source_id}/download",
+                web::get()
+                    .to(download_source)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/sources/{source_id}/chunks",
+                web::get()
+                    .to(get_source_chunks)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/sources/{source_id}/progress",
+                web::get()
+                    .to(get_source_progress)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/sources/{source_id}/summary",
+                web::get()
+                    .to(get_source_summary)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/sources/{source_id}/events",
+                web::get()
+                    .to(get_source_events)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/auto_filing_rules",
+                web::get()
+                    .to(list_auto_filing_rules)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/auto_filing_rules",
+                web::post()
+                    .to(create_auto_filing_rule)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/auto_filing_rules/{rule_id}",
+                web::put()
+                    .to(update_auto_filing_rule)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/auto_filing_rules/{rule_id}",
+                web::delete()
+                    .to(delete_auto_filing_rule)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/retention_rules",
+                web::get()
+                    .to(list_retention_rules)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/retention_rules/{collection}",
+                web::put()
+                    .to(save_retention_rule)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/retention_rules/{collection}",
+                web::delete()
+                    .to(delete_retention_rule)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/account/default_collection",
+                web::put()
+                    .to(set_default_collection)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/jobs/{job_id}",
+                web::get()
+                    .to(get_job)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/events",
+                web::get()
+                    .to(get_events)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/tenant/provider_credentials",
+                web::get()
+                    .to(list_provider_credentials)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/tenant/provider_credentials/{purpose}",
+                web::put()
+                    .to(save_provider_credentials)
+                    .wrap(RequireAuth::new(authenticator.clone()).with_local_account()),
+            )
+            .route(
+                "/tenant/provider_credentials/{purpose}",
+                web::delete()
+                    .to(delete_provider_credentials)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/api_keys",
+                web::get()
+                    .to(list_api_keys)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/api_keys",
+                web::post()
+                    .to(create_api_key)
+                    .wrap(RequireAuth::new(authenticator.clone()).with_local_account()),
+            )
+            .route(
+                "/api_keys/{api_key_id}",
+                web::delete()
+                    .to(delete_api_key)
+                    .wrap(RequireAuth::new(authenticator.clone())),
+            )
+            .route(
+                "/metrics",
+                web::get().to(get_metrics).wrap(require_admin.clone()),
+            )
+            .route(
+                "/admin/ingestion_slo",
+                web::get().to(get_ingestion_slo).wrap(require_admin.clone()),
+            )
+            .route(
+                "/admin/fulltext_search/promote",
+                web::post()
+                    .to(promote_fulltext_standby)
+                    .wrap(require_admin.clone()),
+            )
+            .route(
+                "/admin/reindex",
+                web::post().to(reindex_sources).wrap(require_admin.clone()),
+            )
+            .route(
+                "/imports",
+                web::post().to(import_sources).wrap(require_admin.clone()),
+            )
+            .route(
+                "/admin/upload_policies",
+                web::get()
+                    .to(list_upload_policies)
+                    .wrap(require_admin.clone()),
+            )
+            .route(
+                "/admin/upload_policies",
+                web::post()
+                    .to(save_upload_policy)
+                    .wrap(require_admin.clone()),
+            )
+            .route(
+                "/admin/normalization_rules",
+                web::get()
+                    .to(list_normalization_rules)
+                    .wrap(require_admin.clone()),
+            )
+            .route(
+                "/admin/normalization_rules",
+                web::post()
+                    .to(add_normalization_rule)
+                    .wrap(require_admin.clone()),
+            )
+            .route(
+                "/admin/normalization_rules/{rule_id}",
+                web::delete()
+                    .to(delete_normalization_rule)
+                    .wrap(require_admin.clone()),
             )
             .route("/account/create", web::post().to(create_account))
-            .route("/account/login", web::post().to(log_in_account))
+            .route(
+                "/account/login",
+                web::post()
+                    .to(log_in_account)
+                    .wrap(login_rate_limit.clone()),
+            )
+            .route(
+                "/2fa/enable",
+                web::post()
+                    .to(enable_two_factor)
+                    .wrap(RequireAuth::new(authenticator.clone()).with_local_account()),
+            )
+            .route(
+                "/2fa/verify",
+                web::post()
+                    .to(verify_two_factor)
+                    .wrap(RequireAuth::new(authenticator.clone()).with_local_account()),
+            )
+            .route("/refresh_token", web::post().to(refresh_token))
+            .route("/log_out", web::post().to(log_out))
             .app_data(db_pool.clone())
             .app_data(s3_repository.clone())
             .app_data(source_meta_repository.clone())
+            .app_data(extraction_progress_repository.clone())
+            .app_data(document_repository.clone())
+            .app_data(source_summary_repository.clone())
+            .app_data(auto_filing_rule_repository.clone())
+            .app_data(retention_rule_repository.clone())
+            .app_data(ingestion_job_repository.clone())
+            .app_data(source_event_repository.clone())
             .app_data(user_repository.clone())
+            .app_data(refresh_token_repository.clone())
+            .app_data(two_factor_repository.clone())
+            .app_data(two_factor_log_in.clone())
+            .app_data(api_key_repository.clone())
+            .app_data(saved_search_repository.clone())
+            .app_data(fulltext_shard_repository.clone())
             .app_data(auth_repository.clone())
+            .app_data(provider_credentials_services.clone())
+            .app_data(secrets_cipher.clone())
+            .app_data(ingestion_metrics.clone())
+            .app_data(health_checks.clone())
+            .app_data(admin_settings.clone())
+            .app_data(fulltext_sharding.clone())
+            .app_data(ingestion_lanes.clone())
+            .app_data(upload_session_repository.clone())
+            .app_data(upload_policy_repository.clone())
+            .app_data(scanner.clone())
+            .app_data(answer_generators.clone())
+            .app_data(answer_generation.clone())
+            .app_data(search_services.clone())
+            .app_data(normalization_rule_repository.clone())
+            .app_data(source_url_repository.clone())
+            .app_data(source_url_schedule_repository.clone())
+            .app_data(recrawl_scheduling.clone())
+            .app_data(search_history.clone())
+            .app_data(import_s3_repository.clone())
+            .app_data(uploads_settings.clone())
+            .app_data(source_downloads.clone())
+            .app_data(activity_stream.clone())
+            .app_data(user_activity_repository.clone())
+            .app_data(user_storage_usage_repository.clone())
+            .app_data(outbox_message_repository.clone())
+            // Limits the size of the files of `/add_source_files` while they are streamed
+            .app_data(upload_form_config.clone())
+            .app_data(source_intake.clone())
+            .app_data(source_deletion.clone())
+            // Limits the size of the parts of the chunked uploads
+            .app_data(web::PayloadConfig::new(settings.uploads.max_part_bytes))
             .data_factory(move || {
-                let message_rabbitmq_repository = message_rabbitmq_repository.clone();
+                let message_repositories = message_repositories.clone();
 
                 async {
-                    let message_rabbitmq_repository =
-                        message_rabbitmq_repository.try_init().await?;
+                    let message_repositories = message_repositories.try_init().await?;
 
                     // Puts behind a mutex so the repository is mutable. But as the repository is cloned and then initialized inside
                     // each thread, it is not shared among all threads, and each thread mutates their own instance of the repository.
@@ -206,9 +954,7 @@ pub fn run(
                     // Ok::<Mutex<RabbitMQMessageRepository>, ApplicationBuildError>(Mutex::new(
                     //     message_rabbitmq_repository,
                     // ))
-                    Ok::<RabbitMQMessageRepository, ApplicationBuildError>(
-                        message_rabbitmq_repository,
-                    )
+                    Ok::<TenantMessageRepositories, ApplicationBuildError>(message_repositories)
                 }
             })
     })
@@ -223,10 +969,89 @@ pub fn run(
     Ok(server.run())
 }
 
+/// Authenticator of the users selected by the settings
+///
+/// The JWT authenticator checks the access tokens issued by the gateway, with `auth_repository`.
+pub fn get_authenticator(
+    settings: &AuthenticationSettings,
+    db_pool: PgPool,
+    auth_repository: JwtAuthenticationRepository,
+) -> Result<Arc<dyn AuthenticatorPort>, ApplicationBuildError> {
+    let missing_settings = |backend: &str| {
+        ApplicationBuildError::ConfigurationError(format!(
+            "missing `authentication.{}` settings for the {} authentication backend",
+            backend, backend
+        ))
+    };
+
+    let authenticator: Arc<dyn AuthenticatorPort> = match settings.backend {
+        AuthenticationBackend::Jwt => Arc::new(JwtAuthenticator::new(auth_repository, db_pool)),
+        AuthenticationBackend::Oidc => {
+            let oidc = settings
+                .oidc
+                .clone()
+                .ok_or_else(|| missing_settings("oidc"))?;
+            Arc::new(OidcIntrospectionAuthenticator::try_new(oidc)?)
+        }
+        AuthenticationBackend::Mtls => {
+            let mtls = settings
+                .mtls
+                .as_ref()
+                .ok_or_else(|| missing_settings("mtls"))?;
+            Arc::new(MtlsAuthenticator::new(mtls.subject_header.clone()))
+        }
+        AuthenticationBackend::StaticToken => {
+            let static_token = settings
+                .static_token
+                .as_ref()
+                .ok_or_else(|| missing_settings("static_token"))?;
+            Arc::new(StaticTokenAuthenticator::new(static_token))
+        }
+    };
+
+    Ok(authenticator)
+}
+
+/// Scanner of the uploaded files, enabled by the settings
+pub fn get_scanner(settings: &VirusScanSettings) -> Arc<dyn ScanPort> {
+    if !settings.enabled {
+        return Arc::new(NoOpScanner);
+    }
+
+    info!(
+        "Scanning the uploaded files with ClamAV on {}:{}",
+        settings.clamav.host, settings.clamav.port
+    );
+    Arc::new(ClamavScanner::new(&settings.clamav))
+}
+
+pub fn get_answer_generator(
+    settings: &AnswerGenerationSettings,
+) -> Result<Arc<dyn AnswerGenerationPort>, reqwest::Error> {
+    if !settings.enabled {
+        return Ok(Arc::new(NoOpAnswerGenerator));
+    }
+
+    Ok(Arc::new(OpenAiAnswerGenerator::try_new(settings)?))
+}
+
+pub fn get_reranker(settings: &RerankSettings) -> Result<Arc<dyn RerankPort>, reqwest::Error> {
+    if !settings.enabled {
+        return Ok(Arc::new(NoOpReranker));
+    }
+
+    Ok(Arc::new(ApiReranker::try_new(settings)?))
+}
+
 // Or should we keep a clone of the pool connection in `Application` ?
 pub fn get_connection_pool(settings: &DatabaseSettings) -> PgPool {
     PgPoolOptions::new()
-        .acquire_timeout(std::time::Duration::from_secs(2))
+        .max_connections(settings.pool.max_connections)
+        .acquire_timeout(Duration::from_millis(settings.pool.acquire_timeout_ms))
+        .idle_timeout(
+            (settings.pool.idle_timeout_s > 0)
+                .then(|| Duration::from_secs(settings.pool.idle_timeout_s)),
+        )
         .connect_lazy_with(settings.with_db())
 }
 
@@ -235,8 +1060,6 @@ pub fn get_connection_pool(settings: &DatabaseSettings) -> PgPool {
 /// Each environment will use 1 bucket.
 /// This bucket is created if it does not exist yet.
 ///
-/// TODO: doing the same for Postgres and not rely on bash script for migration ?
-///
 /// # Returns
 /// An initialized bucket
 #[tracing::instrument(name = "Setting up S3 object store")]
@@ -288,5 +1111,164 @@ pub async fn set_up_s3(settings: &ObjectStorageSettings) -> Result<Bucket, Appli
 pub async fn get_rabbitmq_connection(
     config: &RabbitMQSettings,
 ) -> Result<lapin::Connection, lapin::Error> {
-    lapin::Connection::connect(&config.get_uri(), config.get_connection_properties()).await
+    get_tenant_rabbitmq_connection(config, None).await
+}
+
+/// Creates a connection to the virtual host of a tenant, or to the shared one
+#[tracing::instrument(name = "Create tenant RabbitMQ connection")]
+pub async fn get_tenant_rabbitmq_connection(
+    config: &RabbitMQSettings,
+    tenant: Option<&TenantSettings>,
+) -> Result<lapin::Connection, lapin::Error> {
+    lapin::Connection::connect(
+        &config.get_tenant_uri(tenant),
+        config.get_connection_properties(),
+    )
+    .await
+}
+
+/// Spawns the handlers saving the progress of the content extractions and the status of the ingestion jobs,
+/// published by the workers of a tenant, the usage of the model providers of a tenant,
+/// the handler summarizing the extracted contents of the sources,
+/// the handler saving the summaries of the sources generated by the embedding workers,
+/// and the handlers answering the services of a tenant with its normalization rules and its provider credentials
+async fn spawn_worker_status_handlers(
+    config: &RabbitMQSettings,
+    tenant: Option<&TenantSettings>,
+    db_pool: PgPool,
+    ingestion_metrics: Arc<IngestionMetrics>,
+    provider_credentials: &ProviderCredentialsSettings,
+) -> Result<(), lapin::Error> {
+    // Publishes the progresses and the job updates as the activity of their user, streamed to the clients
+    let user_activity = UserActivityPublisher {
+        user_activity_repository: Arc::new(UserActivityRabbitMQRepository::new()),
+        message_repository: RabbitMQMessageRepository::new(
+            Arc::new(get_tenant_rabbitmq_connection(config, tenant).await?),
+            &config.content_exchange_name(tenant),
+        ),
+    };
+
+    let rabbitmq_consuming_connection = get_tenant_rabbitmq_connection(config, tenant).await?;
+
+    tokio::spawn(
+        handler_extraction_progress::register_handler(
+            rabbitmq_consuming_connection,
+            config.content_exchange_name(tenant),
+            tenant_name_prefix(&config.queue_name_prefix, tenant),
+            db_pool.clone(),
+            Arc::new(ExtractionProgressPostgresRepository::new()),
+            Arc::new(SourceMetaPostgresRepository::new()),
+            user_activity.clone(),
+        )
+        .inspect_err(|error| {
+            error!(?error, "Extraction progress handler stopped");
+        }),
+    );
+
+    let rabbitmq_consuming_connection = get_tenant_rabbitmq_connection(config, tenant).await?;
+
+    tokio::spawn(
+        handler_content_extracted::register_handler(
+            rabbitmq_consuming_connection,
+            config.content_exchange_name(tenant),
+            tenant_name_prefix(&config.queue_name_prefix, tenant),
+            db_pool.clone(),
+            Arc::new(DocumentPostgresRepository::new()),
+        )
+        .inspect_err(|error| {
+            error!(?error, "Extracted content handler stopped");
+        }),
+    );
+
+    let rabbitmq_consuming_connection = get_tenant_rabbitmq_connection(config, tenant).await?;
+
+    tokio::spawn(
+        handler_source_summarized::register_handler(
+            rabbitmq_consuming_connection,
+            config.content_exchange_name(tenant),
+            tenant_name_prefix(&config.queue_name_prefix, tenant),
+            db_pool.clone(),
+            Arc::new(SourceSummaryPostgresRepository::new()),
+        )
+        .inspect_err(|error| {
+            error!(?error, "Source summarized handler stopped");
+        }),
+    );
+
+    let rabbitmq_consuming_connection = get_tenant_rabbitmq_connection(config, tenant).await?;
+
+    tokio::spawn(
+        handler_ingestion_job_status::register_handler(
+            rabbitmq_consuming_connection,
+            config.content_exchange_name(tenant),
+            tenant_name_prefix(&config.queue_name_prefix, tenant),
+            db_pool.clone(),
+            handler_ingestion_job_status::IngestionJobServices {
+                ingestion_job_repository: IngestionJobPostgresRepository::new(),
+                source_meta_repository: SourceMetaPostgresRepository::new(),
+                source_event_repository: SourceEventPostgresRepository::new(),
+                ingestion_metrics,
+            },
+            user_activity,
+        )
+        .inspect_err(|error| {
+            error!(?error, "Ingestion job status handler stopped");
+        }),
+    );
+
+    let rabbitmq_consuming_connection = get_tenant_rabbitmq_connection(config, tenant).await?;
+
+    tokio::spawn(
+        handler_normalization_rules::register_handler(
+            rabbitmq_consuming_connection,
+            config.content_exchange_name(tenant),
+            tenant_name_prefix(&config.queue_name_prefix, tenant),
+            tenant.map(|tenant| tenant.id.clone()),
+            db_pool.clone(),
+            Arc::new(NormalizationRulePostgresRepository::new()),
+        )
+        .inspect_err(|error| {
+            error!(?error, "Normalization rules handler stopped");
+        }),
+    );
+
+    // Only the tenants can bring their own provider credentials
+    if let Some(tenant) = tenant {
+        let rabbitmq_consuming_connection =
+            get_tenant_rabbitmq_connection(config, Some(tenant)).await?;
+
+        tokio::spawn(
+            handler_provider_usage::register_handler(
+                rabbitmq_consuming_connection,
+                config.content_exchange_name(Some(tenant)),
+                tenant_name_prefix(&config.queue_name_prefix, Some(tenant)),
+                tenant.id.clone(),
+                db_pool.clone(),
+                Arc::new(ProviderCredentialsPostgresRepository::new()),
+            )
+            .inspect_err(|error| {
+                error!(?error, "Provider usage handler stopped");
+            }),
+        );
+
+        let rabbitmq_consuming_connection =
+            get_tenant_rabbitmq_connection(config, Some(tenant)).await?;
+
+        tokio::spawn(
+            handler_provider_credentials::register_handler(
+                rabbitmq_consuming_connection,
+                config.content_exchange_name(Some(tenant)),
+                tenant_name_prefix(&config.queue_name_prefix, Some(tenant)),
+                tenant.id.clone(),
+                db_pool,
+                Arc::new(ProviderCredentialsPostgresRepository::new()),
+                provider_credentials.clone(),
+            )
+            .inspect_err(|error| {
+                error!(?error, "Provider credentials handler stopped");
+            }),
+        );
+    }
+
+    Ok(())
 }
@@ -1,6 +1,8 @@
-use common::constants::routing_keys::EXTRACT_CONTENT_TEXT_ROUTING_KEY;
+use common::constants::routing_keys::{
+    EXTRACT_CONTENT_TEXT_FAST_ROUTING_KEY, EXTRACT_CONTENT_TEXT_ROUTING_KEY,
+};
 use futures::lock::Mutex;
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, io::Write, sync::Arc};
 
 use lapin::{
     message::DeliveryResult,
@@ -13,14 +15,24 @@ use reqwest::{
     multipart::{Form, Part},
 };
 use rest_gateway::{
-    controllers::{AddSourceFilesResponse, Status},
-    domain::entities::source_meta::SourceType,
+    controllers::{AddSourceFilesResponse, Status, DRM_PROTECTED_DOC_URL},
+    domain::entities::{
+        ingestion_job::{IngestionJob, IngestionLane, JobStatus},
+        source_meta::{SourceMeta, SourceType},
+    },
+    repositories::{
+        fulltext_shard_postgres_repository::FulltextShardPostgresRepository,
+        ingestion_job_postgres_repository::IngestionJobPostgresRepository,
+        source_meta_postgres_repository::SourceMetaPostgresRepository,
+        user_storage_usage_postgres_repository::UserStorageUsagePostgresRepository,
+    },
 };
 use tokio::time::{sleep, Duration};
 use tokio_stream::StreamExt;
 use tracing::{error, info, info_span, warn, Instrument};
+use uuid::Uuid;
 
-use crate::helpers::{spawn_app, TestApp};
+use crate::helpers::{spawn_app, spawn_app_with, test_epub, TestApp};
 
 #[tokio::test(flavor = "multi_thread")]
 async fn add_source_files_returns_a_200_for_valid_input_data() {
@@ -32,7 +44,7 @@ async fn add_source_files_returns_a_200_for_valid_input_data() {
     let (_, token) = app.get_test_user_token();
 
     // Creates a multipart field (a file) from the text content
-    let epub_part = Part::text("This is a test file")
+    let epub_part = Part::bytes(test_epub("This is a test file"))
         .file_name(file_name)
         .mime_str("application/epub+zip")
         .unwrap();
@@ -65,6 +77,255 @@ async fn add_source_files_returns_a_200_for_valid_input_data() {
     ));
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn add_source_files_routes_the_sources_to_a_new_fulltext_shard_once_the_last_one_is_full() {
+    // Arranges
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+    let fulltext_shard_repository = FulltextShardPostgresRepository::new();
+
+    // A source of the shared deployment filling the first shard
+    let source_meta = SourceMeta::builder()
+        .user_id(Uuid::new_v4())
+        .initial_name("large.epub".to_string())
+        .source_type(SourceType::Epub)
+        .object_store_name(Uuid::new_v4().to_string())
+        .build();
+    SourceMetaPostgresRepository::new()
+        .add_source_meta(&app.db_pool, &source_meta)
+        .await
+        .unwrap();
+    fulltext_shard_repository
+        .add_route(&app.db_pool, source_meta.id, None, 0)
+        .await
+        .unwrap();
+    let mut job = IngestionJob::new(source_meta.id);
+    job.nb_indexed_contents = app.fulltext_max_contents_per_shard as i64;
+    IngestionJobPostgresRepository::new()
+        .add_job(&app.db_pool, &job)
+        .await
+        .unwrap();
+
+    let epub_part = Part::bytes(test_epub("This is a test file"))
+        .file_name("example.epub")
+        .mime_str("application/epub+zip")
+        .unwrap();
+    let form = Form::new().part("file", epub_part);
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/add_source_files", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .multipart(form)
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+    let shards = fulltext_shard_repository
+        .list_tenant_shards(&app.db_pool, None)
+        .await
+        .unwrap();
+    assert_eq!(shards, vec![0, 1]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_source_files_routes_a_small_web_clip_to_the_fast_lane_and_a_book_to_the_bulk_lane() {
+    // Arranges
+    let mut app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+
+    let fast_lane_counter = Arc::new(Mutex::new(0_u32));
+    listen_to_content_exchange(
+        &mut app,
+        EXTRACT_CONTENT_TEXT_FAST_ROUTING_KEY,
+        2000,
+        fast_lane_counter.clone(),
+    )
+    .await;
+
+    let html_part = Part::text("<html><body><p>A saved article</p></body></html>")
+        .file_name("article.html")
+        .mime_str("text/html")
+        .unwrap();
+    let epub_part = Part::bytes(test_epub("This is a test file"))
+        .file_name("book.epub")
+        .mime_str("application/epub+zip")
+        .unwrap();
+    let form = Form::new().part("file", html_part).part("file", epub_part);
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/add_source_files", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .multipart(form)
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+    let response = response.json::<AddSourceFilesResponse>().await.unwrap();
+
+    let mut lanes = vec![];
+    for file_status in response.file_status {
+        let job = IngestionJobPostgresRepository::new()
+            .get_user_job(&app.db_pool, user_id, file_status.job_id.unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        lanes.push(job.lane);
+    }
+    assert_eq!(lanes, vec![IngestionLane::Fast, IngestionLane::Bulk]);
+
+    // Only the web clip is published on the fast lane
+    sleep(Duration::from_millis(500)).await;
+    assert_eq!(*fast_lane_counter.lock().await, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_source_files_keeps_the_content_columns_of_the_structured_sources() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+
+    let csv_part = Part::text("id,title,category\n1,Rivers,geography\n")
+        .file_name("articles.csv")
+        .mime_str("text/csv")
+        .unwrap();
+    let epub_part = Part::bytes(test_epub("This is a test file"))
+        .file_name("book.epub")
+        .mime_str("application/epub+zip")
+        .unwrap();
+    let form = Form::new()
+        .part("file", csv_part)
+        .part("file", epub_part)
+        .text("content_column", "title")
+        .text("content_column", " ");
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/add_source_files", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .multipart(form)
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+
+    let saved = sqlx::query!(
+        r#"SELECT source_type as "source_type: SourceType", content_columns FROM source_metas
+        WHERE user_id = $1 ORDER BY initial_name"#,
+        user_id
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved source metas");
+
+    assert_eq!(saved.len(), 2);
+    assert_eq!(saved[0].source_type, SourceType::Csv);
+    assert_eq!(saved[0].content_columns, vec!["title".to_string()]);
+    // Only the structured sources have content columns
+    assert_eq!(saved[1].source_type, SourceType::Epub);
+    assert!(saved[1].content_columns.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_source_files_saves_the_redaction_of_the_personal_data_with_the_sources() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+
+    let epub_part = Part::bytes(test_epub("Write to jane.doe@example.com"))
+        .file_name("book.epub")
+        .mime_str("application/epub+zip")
+        .unwrap();
+    let form = Form::new()
+        .part("file", epub_part)
+        .text("redact_pii", "true");
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/add_source_files", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .multipart(form)
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+
+    let saved = sqlx::query!(
+        r#"SELECT redact_pii FROM source_metas WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved source metas");
+
+    assert_eq!(saved.len(), 1);
+    assert!(saved[0].redact_pii);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_source_files_writes_the_extraction_job_in_the_outbox_and_sets_it_sent_once_published()
+{
+    // Arranges
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let epub_part = Part::bytes(test_epub("This is a test file"))
+        .file_name("example.epub")
+        .mime_str("application/epub+zip")
+        .unwrap();
+    let form = Form::new().part("file", epub_part);
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/add_source_files", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .multipart(form)
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+    let json_response = response.json::<AddSourceFilesResponse>().await.unwrap();
+    let source_id = json_response.file_status[0].source_id.unwrap();
+
+    let saved = sqlx::query!(
+        r#"SELECT routing_key, sent_at FROM outbox_messages WHERE strpos(payload, $1) > 0"#,
+        source_id.to_string()
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved outbox messages");
+
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].routing_key, EXTRACT_CONTENT_TEXT_ROUTING_KEY);
+    assert!(saved[0].sent_at.is_some());
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn add_source_files_returns_a_400_when_input_data_is_missing() {
     // Arranges
@@ -112,7 +373,7 @@ async fn add_source_files_persists_source_file_and_meta() {
     let file_content = "This is a test file";
 
     // Creates a multipart field (a file) from the text content
-    let epub_part = Part::text(file_content)
+    let epub_part = Part::bytes(test_epub(file_content))
         .file_name(file_name)
         .mime_str("application/epub+zip")
         .unwrap();
@@ -151,6 +412,19 @@ async fn add_source_files_persists_source_file_and_meta() {
 
     assert_eq!(s3_response_data.to_string().unwrap(), file_content);
 
+    // Checks that the ingestion job of the source is pending
+    let saved_job = sqlx::query!(
+        r#"SELECT status as "status: JobStatus" FROM ingestion_jobs
+        JOIN source_metas ON source_metas.id = ingestion_jobs.source_meta_id
+        WHERE source_metas.user_id = $1"#,
+        user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved ingestion job");
+
+    assert_eq!(saved_job.status, JobStatus::Pending);
+
     // Finally asserts that the job message has been correctly sent
     let counter = counter.lock().await;
     assert_eq!(*counter, 1);
@@ -183,7 +457,7 @@ async fn add_source_files_persists_all_correct_input_source_files_and_meta_and_r
         let file_content = format!("This is the test file {i}");
 
         // Creates a multipart field (a file) from the text content
-        let epub_part = Part::text(file_content)
+        let epub_part = Part::bytes(test_epub(&file_content))
             .file_name(file_name)
             .mime_str("application/epub+zip")
             .unwrap();
@@ -271,6 +545,477 @@ async fn add_source_files_persists_all_correct_input_source_files_and_meta_and_r
     assert_eq!(*counter, NUMBER_FILES as u32);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn add_source_files_returns_a_duplicate_status_and_skips_extraction_for_an_already_uploaded_file(
+) {
+    // Arranges
+    let mut app = spawn_app().await;
+
+    let counter = Arc::new(Mutex::new(0_u32));
+    listen_to_content_exchange(
+        &mut app,
+        EXTRACT_CONTENT_TEXT_ROUTING_KEY,
+        2000,
+        counter.clone(),
+    )
+    .await;
+
+    // Fake user and access token
+    let (user_id, token) = app.get_test_user_token();
+
+    let file_content = "This is a test file";
+
+    let mut json_responses = vec![];
+    // Uploads the same content twice, with different names
+    for file_name in ["example.epub", "example_copy.epub"] {
+        let epub_part = Part::bytes(test_epub(file_content))
+            .file_name(file_name)
+            .mime_str("application/epub+zip")
+            .unwrap();
+        let form = Form::new().part("file", epub_part);
+
+        // Acts
+        let response = reqwest::Client::new()
+            .post(format!("{}/add_source_files", &app.address))
+            .header(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+            )
+            .multipart(form)
+            .send()
+            .await
+            .expect("Failed to execute request");
+
+        assert_eq!(200, response.status().as_u16());
+        json_responses.push(response.json::<AddSourceFilesResponse>().await.unwrap());
+    }
+
+    // Asserts
+    assert!(matches!(
+        json_responses[0].file_status[0].status,
+        Status::Success
+    ));
+    assert!(matches!(
+        json_responses[1].file_status[0].status,
+        Status::Duplicate
+    ));
+
+    let saved = sqlx::query!(
+        r#"SELECT object_store_name, initial_name, content_hash FROM source_metas WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved source file metas");
+
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].initial_name, "example.epub");
+    assert_eq!(saved[0].content_hash.as_ref().unwrap().len(), 64);
+
+    // Only the first upload is kept in the object store
+    let objects = app
+        .s3_bucket
+        .list(format!("{}/", user_id), None)
+        .await
+        .unwrap();
+    let nb_objects: usize = objects.iter().map(|result| result.contents.len()).sum();
+    assert_eq!(nb_objects, 1);
+
+    // Only one extraction job is sent
+    sleep(Duration::from_millis(500)).await;
+    let counter = counter.lock().await;
+    assert_eq!(*counter, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_source_files_coalesces_concurrent_uploads_of_the_same_file_into_one_source() {
+    // Arranges
+    let mut app = spawn_app().await;
+
+    let counter = Arc::new(Mutex::new(0_u32));
+    listen_to_content_exchange(
+        &mut app,
+        EXTRACT_CONTENT_TEXT_ROUTING_KEY,
+        2000,
+        counter.clone(),
+    )
+    .await;
+
+    // Fake user and access token
+    let (user_id, token) = app.get_test_user_token();
+
+    let upload = || {
+        let epub_part = Part::bytes(test_epub("This is a test file uploaded twice"))
+            .file_name("example.epub")
+            .mime_str("application/epub+zip")
+            .unwrap();
+        let form = Form::new().part("file", epub_part);
+
+        reqwest::Client::new()
+            .post(format!("{}/add_source_files", &app.address))
+            .header(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+            )
+            .multipart(form)
+            .send()
+    };
+
+    // Acts: as on a double click
+    let (first_response, second_response) = tokio::join!(upload(), upload());
+
+    // Asserts
+    let mut file_status = vec![];
+    for response in [first_response, second_response] {
+        let response = response.expect("Failed to execute request");
+        assert_eq!(200, response.status().as_u16());
+
+        let mut json_response = response.json::<AddSourceFilesResponse>().await.unwrap();
+        file_status.push(json_response.file_status.remove(0));
+    }
+    file_status.sort_by_key(|status| matches!(status.status, Status::Duplicate));
+
+    assert!(matches!(file_status[0].status, Status::Success));
+    assert!(matches!(file_status[1].status, Status::Duplicate));
+    assert!(file_status[0].source_id.is_some());
+    assert_eq!(file_status[0].source_id, file_status[1].source_id);
+
+    let nb_saved = sqlx::query!(
+        r#"SELECT COUNT(*) as "count!" FROM source_metas WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to count saved source file metas")
+    .count;
+    assert_eq!(nb_saved, 1);
+
+    // Only one extraction job is sent
+    sleep(Duration::from_millis(500)).await;
+    let counter = counter.lock().await;
+    assert_eq!(*counter, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_source_files_returns_a_drm_protected_status_without_storing_a_protected_epub() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+
+    let epub_part = Part::bytes(protected_epub())
+        .file_name("protected.epub")
+        .mime_str("application/epub+zip")
+        .unwrap();
+    let form = Form::new().part("file", epub_part);
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/add_source_files", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .multipart(form)
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+    let json_response = response.json::<AddSourceFilesResponse>().await.unwrap();
+    assert!(matches!(
+        json_response.file_status[0].status,
+        Status::DrmProtected
+    ));
+    assert!(json_response.file_status[0]
+        .message
+        .as_deref()
+        .unwrap()
+        .contains(DRM_PROTECTED_DOC_URL));
+
+    let objects = app
+        .s3_bucket
+        .list(format!("{}/", user_id), None)
+        .await
+        .unwrap();
+    let nb_objects: usize = objects.iter().map(|result| result.contents.len()).sum();
+    assert_eq!(nb_objects, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_source_files_rejects_the_files_whose_content_does_not_match_their_extension() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+
+    // A renamed PDF, and a text file given the MIME type of an EPUB
+    let pdf_part = Part::bytes(b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n".to_vec())
+        .file_name("report.epub")
+        .mime_str("application/epub+zip")
+        .unwrap();
+    let text_part = Part::text("This is a test file")
+        .file_name("notes.zip")
+        .mime_str("application/zip")
+        .unwrap();
+    let epub_part = Part::bytes(test_epub("This is a test file"))
+        .file_name("example.epub")
+        .mime_str("application/epub+zip")
+        .unwrap();
+    let form = Form::new()
+        .part("file", pdf_part)
+        .part("file", text_part)
+        .part("file", epub_part);
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/add_source_files", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .multipart(form)
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+    let json_response = response.json::<AddSourceFilesResponse>().await.unwrap();
+    assert!(matches!(
+        json_response.file_status[0].status,
+        Status::ContentMismatch
+    ));
+    assert!(json_response.file_status[0]
+        .message
+        .as_deref()
+        .unwrap()
+        .contains("detected as application/pdf"));
+    assert!(matches!(
+        json_response.file_status[1].status,
+        Status::ContentMismatch
+    ));
+    assert!(matches!(
+        json_response.file_status[2].status,
+        Status::Success
+    ));
+
+    let saved = sqlx::query!(
+        r#"SELECT initial_name, detected_mime_type FROM source_metas WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .expect("Failed to fetch the saved sources");
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].initial_name, "example.epub");
+    assert_eq!(
+        saved[0].detected_mime_type.as_deref(),
+        Some("application/epub+zip")
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_source_files_rejects_an_infected_file_without_storing_it() {
+    // Arranges
+    let clamd = fake_clamd("stream: Eicar-Test-Signature FOUND\0");
+    let app = spawn_app_with(|settings| {
+        settings.virus_scan.enabled = true;
+        settings.virus_scan.clamav.host = "127.0.0.1".to_string();
+        settings.virus_scan.clamav.port = clamd.port();
+    })
+    .await;
+    let (user_id, token) = app.get_test_user_token();
+
+    let epub_part = Part::bytes(test_epub("This is an infected file"))
+        .file_name("infected.epub")
+        .mime_str("application/epub+zip")
+        .unwrap();
+    let form = Form::new().part("file", epub_part);
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/add_source_files", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .multipart(form)
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+    let json_response = response.json::<AddSourceFilesResponse>().await.unwrap();
+    assert!(matches!(
+        json_response.file_status[0].status,
+        Status::Rejected
+    ));
+    assert!(json_response.file_status[0]
+        .message
+        .as_deref()
+        .unwrap()
+        .contains("Eicar-Test-Signature"));
+
+    let objects = app
+        .s3_bucket
+        .list(format!("{}/", user_id), None)
+        .await
+        .unwrap();
+    let nb_objects: usize = objects.iter().map(|result| result.contents.len()).sum();
+    assert_eq!(nb_objects, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_source_files_returns_a_413_for_a_file_larger_than_the_maximum() {
+    // Arranges
+    let app = spawn_app_with(|settings| settings.uploads.max_file_bytes = 1024).await;
+    let (user_id, token) = app.get_test_user_token();
+
+    let srt_part = Part::text("This is a large test file\n".repeat(100))
+        .file_name("large.srt")
+        .mime_str("application/x-subrip")
+        .unwrap();
+    let form = Form::new().part("file", srt_part);
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/add_source_files", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .multipart(form)
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(413, response.status().as_u16());
+
+    let objects = app
+        .s3_bucket
+        .list(format!("{}/", user_id), None)
+        .await
+        .unwrap();
+    let nb_objects: usize = objects.iter().map(|result| result.contents.len()).sum();
+    assert_eq!(nb_objects, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_source_files_counts_the_stored_files_in_the_storage_quota_of_the_user() {
+    // Arranges
+    let first_epub = test_epub("This is a first test file");
+    let second_epub = test_epub("This is a second test file");
+    let quota_bytes = first_epub.len() as u64 + 10;
+    let app = spawn_app_with(|settings| settings.uploads.storage_quota_bytes = quota_bytes).await;
+    let (user_id, token) = app.get_test_user_token();
+
+    let upload = |content: Vec<u8>, file_name: &'static str| {
+        let form = Form::new().part(
+            "file",
+            Part::bytes(content)
+                .file_name(file_name)
+                .mime_str("application/epub+zip")
+                .unwrap(),
+        );
+
+        reqwest::Client::new()
+            .post(format!("{}/add_source_files", &app.address))
+            .header(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+            )
+            .multipart(form)
+            .send()
+    };
+
+    // Acts
+    let first_response = upload(first_epub.clone(), "first.epub")
+        .await
+        .expect("Failed to execute request");
+    let second_response = upload(second_epub, "second.epub")
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(200, first_response.status().as_u16());
+    assert_eq!(403, second_response.status().as_u16());
+
+    let used_bytes = UserStorageUsagePostgresRepository::new()
+        .get_used_bytes(&app.db_pool, user_id)
+        .await
+        .unwrap();
+    assert_eq!(used_bytes, first_epub.len() as u64);
+
+    let saved = sqlx::query!(
+        r#"SELECT initial_name, size_bytes FROM source_metas WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .expect("Failed to fetch the saved sources");
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].initial_name, "first.epub");
+    assert_eq!(saved[0].size_bytes, Some(first_epub.len() as i64));
+}
+
+/// Fake ClamAV daemon, answering each scan with a given response once the streamed file is received
+///
+/// # Returns
+/// The address of the fake daemon
+fn fake_clamd(response: &'static str) -> std::net::SocketAddr {
+    use std::io::Read;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut command = [0; 10];
+            stream.read_exact(&mut command).unwrap();
+
+            // Chunks prefixed by their length, until an empty one
+            loop {
+                let mut length = [0; 4];
+                stream.read_exact(&mut length).unwrap();
+                let length = u32::from_be_bytes(length) as usize;
+                if length == 0 {
+                    break;
+                }
+                std::io::copy(&mut (&mut stream).take(length as u64), &mut std::io::sink())
+                    .unwrap();
+            }
+
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    address
+}
+
+/// Builds an EPUB archive whose content is encrypted by Adobe ADEPT
+fn protected_epub() -> Vec<u8> {
+    let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+
+    archive.start_file("mimetype", options).unwrap();
+    archive.write_all(b"application/epub+zip").unwrap();
+    archive
+        .start_file("META-INF/encryption.xml", options)
+        .unwrap();
+    archive
+        .write_all(
+            br#"<encryption><EncryptedData><EncryptionMethod Algorithm="http://www.w3.org/2001/04/xmlenc#aes128-cbc"/></EncryptedData></encryption>"#,
+        )
+        .unwrap();
+    archive.start_file("META-INF/rights.xml", options).unwrap();
+    archive.write_all(b"<adept:rights/>").unwrap();
+
+    archive.finish().unwrap().into_inner()
+}
+
 /// Consumes messages from a queue bound to the content exchange with a given binding key
 /// and increase a counter each time a message is consumed
 ///
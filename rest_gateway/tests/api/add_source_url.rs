@@ -0,0 +1,287 @@
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use rest_gateway::{
+    controllers::{AddSourceFileStatus, Status},
+    repositories::source_meta_postgres_repository::{
+        SourceMetaFilters, SourceMetaPostgresRepository,
+    },
+};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use uuid::Uuid;
+
+use crate::helpers::{spawn_app, test_epub, TestApp};
+
+/// Serves a single request with a given body, returning the URL of the served file
+fn serve_file_once(path: &str, body: Vec<u8>) -> String {
+    serve_file_versions(path, vec![body])
+}
+
+/// Serves a request for each given body, in order, returning the URL of the served file
+fn serve_file_versions(path: &str, bodies: Vec<Vec<u8>>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}{}", listener.local_addr().unwrap(), path);
+
+    std::thread::spawn(move || {
+        for body in bodies {
+            let (mut stream, _) = listener.accept().unwrap();
+            BufReader::new(stream.try_clone().unwrap())
+                .lines()
+                .map(Result::unwrap)
+                .take_while(|line| !line.is_empty())
+                .for_each(drop);
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\ncontent-type: application/epub+zip\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                body.len(),
+            )
+            .unwrap();
+            stream.write_all(&body).unwrap();
+        }
+    });
+
+    url
+}
+
+async fn add_source_url(app: &TestApp, token: &str, url: &str) -> reqwest::Response {
+    add_source_url_with_body(app, token, &json!({ "url": url })).await
+}
+
+async fn add_source_url_with_body(app: &TestApp, token: &str, body: &Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/add_source_url", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(body)
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+async fn delete_recrawl_schedule(app: &TestApp, token: &str, source_id: Uuid) -> reqwest::Response {
+    reqwest::Client::new()
+        .delete(format!(
+            "{}/sources/{}/recrawl_schedule",
+            &app.address, source_id
+        ))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_source_url_stores_the_downloaded_file() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let url = serve_file_once("/books/example.epub", test_epub("This is a test file"));
+
+    // Acts
+    let response = add_source_url(&app, &token, &url).await;
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+    let file_status = response.json::<AddSourceFileStatus>().await.unwrap();
+    assert!(matches!(file_status.status, Status::Success));
+    assert_eq!(file_status.file_name.as_deref(), Some("example.epub"));
+
+    let source_metas = SourceMetaPostgresRepository::new()
+        .list_user_source_metas(
+            &app.db_pool,
+            user_id,
+            &SourceMetaFilters::default(),
+            None,
+            10,
+        )
+        .await
+        .unwrap();
+    assert_eq!(source_metas.len(), 1);
+
+    let object = app
+        .s3_bucket
+        .get_object(format!("{}/{}", user_id, source_metas[0].object_store_name))
+        .await
+        .unwrap();
+    assert_eq!(object.as_slice(), test_epub("This is a test file"));
+    assert_eq!(
+        source_metas[0].detected_mime_type.as_deref(),
+        Some("application/epub+zip")
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_source_url_rejects_a_file_whose_content_does_not_match_its_extension() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let url = serve_file_once("/books/example.epub", b"This is a test file".to_vec());
+
+    // Acts
+    let response = add_source_url(&app, &token, &url).await;
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+    let file_status = response.json::<AddSourceFileStatus>().await.unwrap();
+    assert!(matches!(file_status.status, Status::ContentMismatch));
+    assert!(file_status
+        .message
+        .as_deref()
+        .unwrap()
+        .contains("detected as text/plain"));
+
+    let source_metas = SourceMetaPostgresRepository::new()
+        .list_user_source_metas(
+            &app.db_pool,
+            user_id,
+            &SourceMetaFilters::default(),
+            None,
+            10,
+        )
+        .await
+        .unwrap();
+    assert!(source_metas.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_source_url_returns_a_400_for_an_unsupported_file() {
+    // Arranges
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+    let url = serve_file_once(
+        "/books/example.unsupported",
+        b"This is a test file".to_vec(),
+    );
+
+    // Acts
+    let response = add_source_url(&app, &token, &url).await;
+
+    // Asserts
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_source_url_returns_a_400_for_an_invalid_url() {
+    // Arranges
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    // Acts
+    let response = add_source_url(&app, &token, "file:///etc/passwd").await;
+
+    // Asserts
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_source_url_returns_a_400_for_a_recrawl_interval_below_the_minimum() {
+    // Arranges
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    // Acts
+    let response = add_source_url_with_body(
+        &app,
+        &token,
+        &json!({ "url": "http://127.0.0.1:1/books/example.epub", "recrawl_interval_h": 0 }),
+    )
+    .await;
+
+    // Asserts
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn changed_source_url_is_replaced_on_its_recrawl() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let url = serve_file_versions(
+        "/books/example.epub",
+        vec![
+            test_epub("This is a test file"),
+            test_epub("This is a changed test file"),
+        ],
+    );
+    let response = add_source_url_with_body(
+        &app,
+        &token,
+        &json!({ "url": url, "recrawl_interval_h": 24 }),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    let source_id = response
+        .json::<AddSourceFileStatus>()
+        .await
+        .unwrap()
+        .source_id
+        .unwrap();
+    let source_meta_repository = SourceMetaPostgresRepository::new();
+    let initial_source_meta = source_meta_repository
+        .get_source_meta(&app.db_pool, source_id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    // Acts
+    sqlx::query!(
+        "UPDATE source_url_schedules SET next_crawl_at = now() WHERE source_meta_id = $1",
+        source_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Asserts
+    let mut source_meta = initial_source_meta.clone();
+    for _ in 0..50 {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        source_meta = source_meta_repository
+            .get_source_meta(&app.db_pool, source_id)
+            .await
+            .unwrap()
+            .unwrap();
+        if source_meta.content_hash != initial_source_meta.content_hash {
+            break;
+        }
+    }
+    assert_ne!(source_meta.content_hash, initial_source_meta.content_hash);
+
+    let object = app
+        .s3_bucket
+        .get_object(format!("{}/{}", user_id, source_meta.object_store_name))
+        .await
+        .unwrap();
+    assert_eq!(object.as_slice(), test_epub("This is a changed test file"));
+    // The replaced file is removed
+    assert!(app
+        .s3_bucket
+        .get_object(format!(
+            "{}/{}",
+            user_id, initial_source_meta.object_store_name
+        ))
+        .await
+        .is_err());
+
+    // The re-crawls are stopped once, the source being kept
+    assert_eq!(
+        204,
+        delete_recrawl_schedule(&app, &token, source_id)
+            .await
+            .status()
+            .as_u16()
+    );
+    assert_eq!(
+        404,
+        delete_recrawl_schedule(&app, &token, source_id)
+            .await
+            .status()
+            .as_u16()
+    );
+}
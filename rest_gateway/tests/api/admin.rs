@@ -0,0 +1,397 @@
+use chrono::{Duration, Timelike, Utc};
+use common::constants::routing_keys::{
+    IMPORT_SOURCE_ROUTING_KEY, PROMOTE_FULLTEXT_STANDBY_ROUTING_KEY, REINDEX_SOURCE_ROUTING_KEY,
+};
+use futures::lock::Mutex;
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use rest_gateway::{
+    controllers::{GetIngestionSloResponse, ImportSourcesResponse, ReindexSourcesResponse},
+    domain::entities::{
+        ingestion_job::{IngestionJob, IngestionStage, JobStatusUpdate},
+        source_meta::{SourceMeta, SourceType},
+    },
+    repositories::{
+        ingestion_job_postgres_repository::IngestionJobPostgresRepository,
+        source_meta_postgres_repository::SourceMetaPostgresRepository,
+    },
+};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    add_source_files::listen_to_content_exchange,
+    helpers::{spawn_app, TestApp},
+};
+
+async fn get_admin_endpoint(app: &TestApp, path: &str, token: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!("{}{}", &app.address, path))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn promote_fulltext_standby(app: &TestApp, token: &str, body: &Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/admin/fulltext_search/promote", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn reindex_sources(app: &TestApp, token: &str, body: &Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/admin/reindex", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn import_sources(app: &TestApp, token: &str, body: &Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/imports", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+/// Saves a source of a user, without uploading its file
+async fn add_test_source_meta(app: &TestApp, user_id: Uuid) -> Uuid {
+    let source_meta = SourceMeta::builder()
+        .user_id(user_id)
+        .initial_name("example.epub".to_string())
+        .source_type(SourceType::Epub)
+        .object_store_name(Uuid::new_v4().to_string())
+        .build();
+
+    SourceMetaPostgresRepository::new()
+        .add_source_meta(&app.db_pool, &source_meta)
+        .await
+        .unwrap();
+
+    source_meta.id
+}
+
+/// Saves the job of an uploaded source, searchable 10s after its upload
+async fn add_searchable_test_job(app: &TestApp) {
+    let source_meta = SourceMeta::builder()
+        .user_id(Uuid::new_v4())
+        .initial_name("example.epub".to_string())
+        .source_type(SourceType::Epub)
+        .object_store_name(Uuid::new_v4().to_string())
+        .build();
+
+    SourceMetaPostgresRepository::new()
+        .add_source_meta(&app.db_pool, &source_meta)
+        .await
+        .unwrap();
+
+    // Whole seconds: the times are saved with a microsecond precision
+    let uploaded_at = Utc::now().with_nanosecond(0).unwrap() - Duration::minutes(1);
+    let at = |s: i64| uploaded_at + Duration::seconds(s);
+    let mut job = IngestionJob::new(source_meta.id).with_upload_started_at(uploaded_at);
+    job.queued_at = at(1);
+    for (update, occurred_at) in [
+        (JobStatusUpdate::ExtractionStarted, at(2)),
+        (
+            JobStatusUpdate::ExtractionCompleted {
+                nb_contents: 1,
+                nb_unchanged_contents: 0,
+                skipped_items: vec![],
+            },
+            at(5),
+        ),
+        (JobStatusUpdate::ContentIndexed, at(6)),
+        (JobStatusUpdate::ContentEmbedded, at(10)),
+    ] {
+        job.apply_at(update, occurred_at).unwrap();
+    }
+
+    IngestionJobPostgresRepository::new()
+        .add_job(&app.db_pool, &job)
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn admin_endpoints_require_the_admin_token() {
+    let app = spawn_app().await;
+    let (_, user_token) = app.get_test_user_token();
+
+    for path in ["/metrics", "/admin/ingestion_slo"] {
+        let response = reqwest::Client::new()
+            .get(format!("{}{}", &app.address, path))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(401, response.status().as_u16());
+
+        let response = get_admin_endpoint(&app, path, &user_token).await;
+        assert_eq!(401, response.status().as_u16());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn metrics_are_exposed_in_the_prometheus_format() {
+    let app = spawn_app().await;
+
+    let response = get_admin_endpoint(&app, "/metrics", &app.admin_token).await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body = response.text().await.unwrap();
+    assert!(body.contains("ingestion_time_to_searchable_seconds_count"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ingestion_slo_returns_the_latency_of_the_stages() {
+    let app = spawn_app().await;
+    add_searchable_test_job(&app).await;
+
+    let response = get_admin_endpoint(&app, "/admin/ingestion_slo", &app.admin_token).await;
+
+    assert_eq!(200, response.status().as_u16());
+    let response = response.json::<GetIngestionSloResponse>().await.unwrap();
+    assert_eq!(response.nb_jobs, 1);
+    assert_eq!(response.stages[&IngestionStage::Upload].p50_ms, 1000);
+    assert_eq!(response.stages[&IngestionStage::Extraction].p50_ms, 3000);
+    assert_eq!(response.stages[&IngestionStage::Embedding].p50_ms, 5000);
+    assert_eq!(response.time_to_searchable.unwrap().p50_ms, 10000);
+    assert_eq!(response.ratio_within_target, Some(1.0));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ingestion_slo_returns_a_400_for_an_invalid_window() {
+    let app = spawn_app().await;
+
+    let response = get_admin_endpoint(
+        &app,
+        "/admin/ingestion_slo?window_h=100000",
+        &app.admin_token,
+    )
+    .await;
+
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn promote_fulltext_standby_requires_the_admin_token() {
+    let app = spawn_app().await;
+    let (_, user_token) = app.get_test_user_token();
+
+    let response =
+        promote_fulltext_standby(&app, &user_token, &json!({ "standby_name": "standby" })).await;
+
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn promote_fulltext_standby_sends_the_promotion_to_the_standby() {
+    // Arranges
+    let mut app = spawn_app().await;
+
+    let counter = Arc::new(Mutex::new(0_u32));
+    listen_to_content_exchange(
+        &mut app,
+        PROMOTE_FULLTEXT_STANDBY_ROUTING_KEY,
+        2000,
+        counter.clone(),
+    )
+    .await;
+
+    // Acts
+    let response = promote_fulltext_standby(
+        &app,
+        &app.admin_token,
+        &json!({ "standby_name": "standby" }),
+    )
+    .await;
+
+    // Asserts
+    assert_eq!(202, response.status().as_u16());
+
+    let counter = counter.lock().await;
+    assert_eq!(*counter, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn promote_fulltext_standby_returns_a_400_for_an_invalid_promotion() {
+    let app = spawn_app().await;
+
+    for (body, description) in [
+        (json!({ "standby_name": "" }), "empty standby name"),
+        (
+            json!({ "standby_name": "standby", "tenant": "unknown" }),
+            "unknown tenant",
+        ),
+        (json!({}), "missing standby name"),
+    ] {
+        let response = promote_fulltext_standby(&app, &app.admin_token, &body).await;
+
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request when the payload was {}.",
+            description
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reindex_requires_the_admin_token() {
+    let app = spawn_app().await;
+    let (user_id, user_token) = app.get_test_user_token();
+
+    let response = reindex_sources(&app, &user_token, &json!({ "user_id": user_id })).await;
+
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reindex_sends_a_reindexing_command_for_each_source_of_the_user() {
+    // Arranges
+    let mut app = spawn_app().await;
+    let user_id = Uuid::new_v4();
+    add_test_source_meta(&app, user_id).await;
+    add_test_source_meta(&app, user_id).await;
+    // Another user's source is not reindexed
+    add_test_source_meta(&app, Uuid::new_v4()).await;
+
+    let counter = Arc::new(Mutex::new(0_u32));
+    listen_to_content_exchange(&mut app, REINDEX_SOURCE_ROUTING_KEY, 2000, counter.clone()).await;
+
+    // Acts
+    let response = reindex_sources(
+        &app,
+        &app.admin_token,
+        &json!({ "user_id": user_id, "wipe_index": true }),
+    )
+    .await;
+
+    // Asserts
+    assert_eq!(202, response.status().as_u16());
+    let response = response.json::<ReindexSourcesResponse>().await.unwrap();
+    assert_eq!(response.nb_sources, 2);
+
+    let counter = counter.lock().await;
+    assert_eq!(*counter, 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reindex_of_selected_sources_checks_they_belong_to_the_user() {
+    let app = spawn_app().await;
+    let user_id = Uuid::new_v4();
+    let source_meta_id = add_test_source_meta(&app, user_id).await;
+    let other_source_meta_id = add_test_source_meta(&app, Uuid::new_v4()).await;
+
+    let response = reindex_sources(
+        &app,
+        &app.admin_token,
+        &json!({ "user_id": user_id, "source_ids": [source_meta_id] }),
+    )
+    .await;
+    assert_eq!(202, response.status().as_u16());
+    let response = response.json::<ReindexSourcesResponse>().await.unwrap();
+    assert_eq!(response.nb_sources, 1);
+
+    let response = reindex_sources(
+        &app,
+        &app.admin_token,
+        &json!({ "user_id": user_id, "source_ids": [source_meta_id, other_source_meta_id] }),
+    )
+    .await;
+    assert_eq!(404, response.status().as_u16());
+
+    let response = reindex_sources(
+        &app,
+        &app.admin_token,
+        &json!({ "user_id": user_id, "source_ids": [] }),
+    )
+    .await;
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn import_requires_the_admin_token() {
+    let app = spawn_app().await;
+    let (user_id, user_token) = app.get_test_user_token();
+
+    let response = import_sources(
+        &app,
+        &user_token,
+        &json!({ "user_id": user_id, "bucket": app.s3_bucket.name() }),
+    )
+    .await;
+
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn import_sends_an_import_command_for_each_supported_object() {
+    // Arranges
+    let mut app = spawn_app().await;
+    // The archive is imported from the bucket of the tests, under a prefix of its own
+    let prefix = format!("imports/{}/", Uuid::new_v4());
+    for object_name in ["book.epub", "notes/chapter.docx", "photo.jpg"] {
+        app.s3_bucket
+            .put_object(format!("{}{}", prefix, object_name), b"content")
+            .await
+            .unwrap();
+    }
+
+    let counter = Arc::new(Mutex::new(0_u32));
+    listen_to_content_exchange(&mut app, IMPORT_SOURCE_ROUTING_KEY, 2000, counter.clone()).await;
+
+    // Acts
+    let response = import_sources(
+        &app,
+        &app.admin_token,
+        &json!({ "user_id": Uuid::new_v4(), "bucket": app.s3_bucket.name(), "prefix": prefix }),
+    )
+    .await;
+
+    // Asserts
+    assert_eq!(202, response.status().as_u16());
+    let response = response.json::<ImportSourcesResponse>().await.unwrap();
+    assert_eq!(response.nb_sources, 2);
+    assert_eq!(
+        response.unsupported_keys,
+        vec![format!("{}photo.jpg", prefix)]
+    );
+
+    let counter = counter.lock().await;
+    assert_eq!(*counter, 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn import_of_an_unknown_bucket_returns_a_404() {
+    let app = spawn_app().await;
+
+    let response = import_sources(
+        &app,
+        &app.admin_token,
+        &json!({ "user_id": Uuid::new_v4(), "bucket": format!("unknown-{}", Uuid::new_v4()) }),
+    )
+    .await;
+
+    assert_eq!(404, response.status().as_u16());
+}
@@ -0,0 +1,249 @@
+use crate::helpers::{spawn_app, spawn_app_with, test_epub, TestApp};
+use reqwest::{
+    header::{HeaderValue, AUTHORIZATION},
+    multipart::{Form, Part},
+};
+use rest_gateway::{
+    configuration::{AuthenticationBackend, MtlsSettings},
+    controllers::{CreateApiKeyBodyData, CreateApiKeyResponse, ListApiKeysResponse},
+    domain::entities::api_key::ApiKeyScope,
+    middlewares::jwt_authentication::middleware::API_KEY_HEADER,
+};
+
+/// Creates an API key for a new user, returning the key
+async fn create_api_key(app: &TestApp, scopes: Vec<ApiKeyScope>) -> String {
+    let (user_id, _, _) = app.create_test_user_account().await;
+    let token = app.get_user_token(user_id);
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/api_keys", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&CreateApiKeyBodyData {
+            name: "Nightly import".to_string(),
+            scopes,
+        })
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(201, response.status().as_u16());
+
+    response.json::<CreateApiKeyResponse>().await.unwrap().key
+}
+
+fn epub_form() -> Form {
+    let epub_part = Part::bytes(test_epub("This is a test file"))
+        .file_name("example.epub")
+        .mime_str("application/epub+zip")
+        .unwrap();
+    Form::new().part("file", epub_part)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn created_api_keys_are_listed_without_the_key() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, _, _) = app.create_test_user_account().await;
+    let token = app.get_user_token(user_id);
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/api_keys", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&CreateApiKeyBodyData {
+            name: "Nightly import".to_string(),
+            scopes: vec![ApiKeyScope::Upload, ApiKeyScope::Upload],
+        })
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(201, response.status().as_u16());
+    let created = response.json::<CreateApiKeyResponse>().await.unwrap();
+    assert!(created.key.starts_with(&created.api_key.key_prefix));
+    assert_eq!(created.api_key.scopes, vec![ApiKeyScope::Upload]);
+
+    // Acts
+    let response = client
+        .get(format!("{}/api_keys", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+    let page = response.json::<ListApiKeysResponse>().await.unwrap();
+    assert_eq!(page.api_keys.len(), 1);
+    assert_eq!(page.api_keys[0].id, created.api_key.id);
+    assert!(page.next_cursor.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn creating_an_api_key_without_scopes_returns_a_400() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, _, _) = app.create_test_user_account().await;
+    let token = app.get_user_token(user_id);
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/api_keys", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&CreateApiKeyBodyData {
+            name: "Nightly import".to_string(),
+            scopes: vec![],
+        })
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_scoped_api_key_can_add_source_files() {
+    // Arranges
+    let app = spawn_app().await;
+    let key = create_api_key(&app, vec![ApiKeyScope::Upload]).await;
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/add_source_files", &app.address))
+        .header(API_KEY_HEADER, key)
+        .multipart(epub_form())
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn api_key_without_the_scope_of_the_endpoint_returns_a_403() {
+    // Arranges
+    let app = spawn_app().await;
+    let key = create_api_key(&app, vec![ApiKeyScope::Search]).await;
+    let client = reqwest::Client::new();
+
+    // Acts
+    let upload_response = client
+        .post(format!("{}/add_source_files", &app.address))
+        .header(API_KEY_HEADER, key.clone())
+        .multipart(epub_form())
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Endpoints without scopes only accept access tokens
+    let sources_response = client
+        .get(format!("{}/sources", &app.address))
+        .header(API_KEY_HEADER, key)
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(403, upload_response.status().as_u16());
+    assert_eq!(403, sources_response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unknown_or_deleted_api_key_returns_a_401() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, _, _) = app.create_test_user_account().await;
+    let token = app.get_user_token(user_id);
+    let client = reqwest::Client::new();
+
+    let created = client
+        .post(format!("{}/api_keys", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&CreateApiKeyBodyData {
+            name: "Nightly import".to_string(),
+            scopes: vec![ApiKeyScope::Upload],
+        })
+        .send()
+        .await
+        .expect("Failed to execute request")
+        .json::<CreateApiKeyResponse>()
+        .await
+        .unwrap();
+
+    let response = client
+        .delete(format!("{}/api_keys/{}", &app.address, created.api_key.id))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(204, response.status().as_u16());
+
+    for key in [created.key, "cis_unknown".to_string()] {
+        // Acts
+        let response = client
+            .post(format!("{}/add_source_files", &app.address))
+            .header(API_KEY_HEADER, key)
+            .multipart(epub_form())
+            .send()
+            .await
+            .expect("Failed to execute request");
+
+        // Asserts
+        assert_eq!(401, response.status().as_u16());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn users_without_account_can_not_create_an_api_key() {
+    // Arranges
+    let app = spawn_app_with(|settings| {
+        settings.authentication.backend = AuthenticationBackend::Mtls;
+        settings.authentication.mtls = Some(MtlsSettings {
+            subject_header: "X-Client-Cert-Subject".to_string(),
+        });
+    })
+    .await;
+    let client = reqwest::Client::new();
+
+    // Acts
+    let create_response = client
+        .post(format!("{}/api_keys", &app.address))
+        .header("X-Client-Cert-Subject", "CN=nightly-import")
+        .json(&CreateApiKeyBodyData {
+            name: "Nightly import".to_string(),
+            scopes: vec![ApiKeyScope::Upload],
+        })
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    let list_response = client
+        .get(format!("{}/api_keys", &app.address))
+        .header("X-Client-Cert-Subject", "CN=nightly-import")
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(403, create_response.status().as_u16());
+    // Authenticated, only the endpoints referencing the account are rejected
+    assert_eq!(200, list_response.status().as_u16());
+}
@@ -0,0 +1,177 @@
+use common::{
+    constants::routing_keys::SEARCH_SEMANTIC_ROUTING_KEY,
+    dtos::{
+        fulltext_search_response::ResultContent,
+        semantic_search_response::{SemanticSearchResponseData, SemanticSearchResponseDto},
+    },
+};
+use reqwest::header::{HeaderValue, ACCEPT, AUTHORIZATION};
+use rest_gateway::{controllers::AskResponse, responders::sse::SSE_CONTENT_TYPE};
+use uuid::Uuid;
+
+use crate::helpers::{serve_once, spawn_app, spawn_app_with, TestApp};
+
+/// Sets up a fake response of the semantic search with 2 chunks
+async fn respond_to_semantic_search(app: &mut TestApp) -> Vec<Uuid> {
+    let chunk_ids = vec![Uuid::new_v4(), Uuid::new_v4()];
+    let fake_response = SemanticSearchResponseDto::Ok {
+        data: SemanticSearchResponseData {
+            results: chunk_ids
+                .iter()
+                .map(|id| ResultContent {
+                    id: *id,
+                    metadata: serde_json::json!({}),
+                    content: "Pods are the smallest deployable units of Kubernetes".to_string(),
+                    source_meta_id: None,
+                })
+                .collect(),
+        },
+    };
+
+    app.listen_and_respond_from_rpc(
+        SEARCH_SEMANTIC_ROUTING_KEY,
+        5000,
+        Vec::from(fake_response.try_serializing().unwrap().as_bytes()),
+    )
+    .await;
+
+    chunk_ids
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ask_returns_a_503_without_llm_backend() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/ask", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&serde_json::json!({ "question": "What is a pod?" }))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(503, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ask_returns_a_400_for_an_invalid_question() {
+    // The LLM backend is not called
+    let app = spawn_app_with(|settings| settings.answer_generation.enabled = true).await;
+    let (_, token) = app.get_test_user_token();
+
+    for body in [
+        serde_json::json!({ "question": " " }),
+        serde_json::json!({ "question": "What is a pod?", "top_k": 0 }),
+        serde_json::json!({ "question": "What is a pod?", "top_k": 21 }),
+    ] {
+        let response = reqwest::Client::new()
+            .post(format!("{}/ask", &app.address))
+            .header(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+            )
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request");
+
+        assert_eq!(400, response.status().as_u16(), "for {}", body);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ask_answers_with_the_cited_chunks() {
+    // Arranges
+    let llm_url = serve_once(
+        "application/json",
+        r#"{"choices":[{"message":{"role":"assistant","content":"A pod is the smallest deployable unit [2]."}}]}"#,
+    );
+    let mut app = spawn_app_with(|settings| {
+        settings.answer_generation.enabled = true;
+        settings.answer_generation.api_url = llm_url;
+    })
+    .await;
+    let (_, token) = app.get_test_user_token();
+    let chunk_ids = respond_to_semantic_search(&mut app).await;
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/ask", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&serde_json::json!({ "question": "What is a pod?" }))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+    let response = response.json::<AskResponse>().await.unwrap();
+    assert_eq!(
+        response.answer.as_deref(),
+        Some("A pod is the smallest deployable unit [2].")
+    );
+    assert_eq!(response.citations.len(), 1);
+    assert_eq!(response.citations[0].index, 2);
+    assert_eq!(response.citations[0].chunk_id, chunk_ids[1]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ask_streams_the_answer_tokens_when_accepting_an_event_stream() {
+    // Arranges
+    let llm_url = serve_once(
+        SSE_CONTENT_TYPE,
+        "data: {\"choices\":[{\"delta\":{\"content\":\"A pod\"}}]}\n\n\
+        data: {\"choices\":[{\"delta\":{\"content\":\" is a unit [1].\"}}]}\n\n\
+        data: [DONE]\n\n",
+    );
+    let mut app = spawn_app_with(|settings| {
+        settings.answer_generation.enabled = true;
+        settings.answer_generation.api_url = llm_url;
+    })
+    .await;
+    let (_, token) = app.get_test_user_token();
+    let chunk_ids = respond_to_semantic_search(&mut app).await;
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/ask", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .header(ACCEPT, SSE_CONTENT_TYPE)
+        .json(&serde_json::json!({ "question": "What is a pod?" }))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        SSE_CONTENT_TYPE
+    );
+    let body = response.text().await.unwrap();
+    let events: Vec<(&str, &str)> = body
+        .split("\n\n")
+        .filter_map(|frame| {
+            let (name, data) = frame.split_once('\n')?;
+            Some((name.strip_prefix("event: ")?, data.strip_prefix("data: ")?))
+        })
+        .collect();
+
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0], ("token", r#"{"text":"A pod"}"#));
+    assert_eq!(events[1], ("token", r#"{"text":" is a unit [1]."}"#));
+    assert_eq!(events[2].0, "answer");
+    let answer: AskResponse = serde_json::from_str(events[2].1).unwrap();
+    assert_eq!(answer.answer.as_deref(), Some("A pod is a unit [1]."));
+    assert_eq!(answer.citations[0].chunk_id, chunk_ids[0]);
+}
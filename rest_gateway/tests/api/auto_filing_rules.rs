@@ -0,0 +1,280 @@
+use reqwest::{
+    header::{HeaderValue, AUTHORIZATION},
+    multipart::{Form, Part},
+};
+use rest_gateway::controllers::{AddSourceFilesResponse, AutoFilingRuleResponse, Status};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::helpers::{spawn_app, test_epub, TestApp};
+
+async fn create_rule(app: &TestApp, token: &str, body: &Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/auto_filing_rules", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn list_rules(app: &TestApp, token: &str) -> Vec<AutoFilingRuleResponse> {
+    let response = reqwest::Client::new()
+        .get(format!("{}/auto_filing_rules", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(200, response.status().as_u16());
+    response.json().await.unwrap()
+}
+
+async fn upload_file(
+    app: &TestApp,
+    token: &str,
+    file_name: &str,
+    tags: &[&str],
+) -> AddSourceFilesResponse {
+    // A distinct content for each file, not to be found as a duplicate
+    let text = format!("This is a test file {}", Uuid::new_v4());
+    let content = if file_name.ends_with(".epub") {
+        test_epub(&text)
+    } else {
+        text.into_bytes()
+    };
+    let part = Part::bytes(content)
+        .file_name(file_name.to_string())
+        .mime_str("application/epub+zip")
+        .unwrap();
+    let form = tags
+        .iter()
+        .fold(Form::new().part("file", part), |form, tag| {
+            form.text("tag", tag.to_string())
+        });
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/add_source_files", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .multipart(form)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(200, response.status().as_u16());
+    response.json().await.unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn auto_filing_rules_are_created_listed_by_position_updated_and_deleted() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let response = create_rule(
+        &app,
+        &token,
+        &json!({ "position": 2, "file_name_pattern": "*.epub", "collection": "Books" }),
+    )
+    .await;
+    assert_eq!(201, response.status().as_u16());
+    let books_rule = response.json::<AutoFilingRuleResponse>().await.unwrap();
+
+    let response = create_rule(
+        &app,
+        &token,
+        &json!({ "position": 1, "tag": "work", "collection": "Work" }),
+    )
+    .await;
+    assert_eq!(201, response.status().as_u16());
+
+    let rules = list_rules(&app, &token).await;
+    assert_eq!(
+        rules
+            .iter()
+            .map(|rule| rule.collection.as_str())
+            .collect::<Vec<_>>(),
+        vec!["Work", "Books"]
+    );
+
+    let response = reqwest::Client::new()
+        .put(format!(
+            "{}/auto_filing_rules/{}",
+            &app.address, books_rule.id
+        ))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&json!({ "position": 0, "file_name_pattern": "*.epub", "collection": "Library" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(204, response.status().as_u16());
+
+    let rules = list_rules(&app, &token).await;
+    assert_eq!(rules[0].id, books_rule.id);
+    assert_eq!(rules[0].collection, "Library");
+
+    let response = reqwest::Client::new()
+        .delete(format!(
+            "{}/auto_filing_rules/{}",
+            &app.address, books_rule.id
+        ))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(204, response.status().as_u16());
+
+    let rules = list_rules(&app, &token).await;
+    assert_eq!(rules.len(), 1);
+    assert_eq!(rules[0].collection, "Work");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_auto_filing_rule_returns_a_400_for_invalid_rules() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let test_cases = vec![
+        (
+            json!({ "position": 0, "collection": "Everything" }),
+            "rule without condition",
+        ),
+        (
+            json!({ "position": 0, "tag": "work", "collection": " " }),
+            "empty collection",
+        ),
+        (
+            json!({ "position": 0, "tag": "work" }),
+            "missing collection",
+        ),
+    ];
+
+    for (body, error_message) in test_cases {
+        let response = create_rule(&app, &token, &body).await;
+
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request when the payload was {}.",
+            error_message
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn auto_filing_rule_of_another_user_is_not_found() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+    let (_, other_token) = app.get_test_user_token();
+
+    let rule = create_rule(
+        &app,
+        &other_token,
+        &json!({ "position": 0, "tag": "work", "collection": "Work" }),
+    )
+    .await
+    .json::<AutoFilingRuleResponse>()
+    .await
+    .unwrap();
+
+    let response = reqwest::Client::new()
+        .delete(format!("{}/auto_filing_rules/{}", &app.address, rule.id))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(404, response.status().as_u16());
+    assert_eq!(list_rules(&app, &other_token).await.len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_source_files_files_the_sources_with_the_first_matching_rule_or_in_the_default_collection(
+) {
+    let app = spawn_app().await;
+    let (user_id, _, _) = app.create_test_user_account().await;
+    let token = app.get_user_token(user_id);
+
+    let response = reqwest::Client::new()
+        .put(format!("{}/account/default_collection", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&json!({ "default_collection": "Inbox" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(204, response.status().as_u16());
+
+    let work_rule = create_rule(
+        &app,
+        &token,
+        &json!({ "position": 0, "tag": "work", "collection": "Work" }),
+    )
+    .await
+    .json::<AutoFilingRuleResponse>()
+    .await
+    .unwrap();
+    create_rule(
+        &app,
+        &token,
+        &json!({ "position": 1, "file_name_pattern": "*.epub", "collection": "Books" }),
+    )
+    .await;
+
+    let tagged = upload_file(&app, &token, "report.epub", &["work"]).await;
+    let untagged = upload_file(&app, &token, "novel.epub", &[]).await;
+    let unmatched = upload_file(&app, &token, "talk.srt", &[]).await;
+
+    for (response, collection) in [
+        (&tagged, "Work"),
+        (&untagged, "Books"),
+        (&unmatched, "Inbox"),
+    ] {
+        assert!(matches!(response.file_status[0].status, Status::Success));
+        assert_eq!(
+            response.file_status[0].collection.as_deref(),
+            Some(collection)
+        );
+    }
+
+    let saved = sqlx::query!(
+        "SELECT collection, auto_filing_rule_id FROM source_metas WHERE user_id = $1 AND initial_name = $2",
+        user_id,
+        "report.epub"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(saved.collection.as_deref(), Some("Work"));
+    assert_eq!(saved.auto_filing_rule_id, Some(work_rule.id));
+
+    let saved = sqlx::query!(
+        "SELECT collection, auto_filing_rule_id FROM source_metas WHERE user_id = $1 AND initial_name = $2",
+        user_id,
+        "talk.srt"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(saved.collection.as_deref(), Some("Inbox"));
+    assert_eq!(saved.auto_filing_rule_id, None);
+}
@@ -15,6 +15,7 @@ async fn a_valid_user_account_should_be_created() {
     let body = CreateAccountBodyData {
         email: test_email.clone(),
         password: test_password,
+        tenant_id: None,
     };
 
     let response = reqwest::Client::new()
@@ -51,6 +52,7 @@ async fn a_valid_user_will_be_able_to_use_their_password_for_credentials() {
     let body = CreateAccountBodyData {
         email: test_email.clone(),
         password: test_password,
+        tenant_id: None,
     };
 
     let _response = reqwest::Client::new()
@@ -75,3 +77,34 @@ async fn a_valid_user_will_be_able_to_use_their_password_for_credentials() {
     // Checks that the stored password is the same as the one used to create the account
     assert!(stored_password.verify(Secret::new(body.password)).is_ok());
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_user_account_with_an_unknown_tenant_should_not_be_created() {
+    // Arranges
+    let app = spawn_app().await;
+    let (test_email, test_password) = app.get_test_user_credentials();
+
+    // Acts
+    let body = CreateAccountBodyData {
+        email: test_email.clone(),
+        password: test_password,
+        tenant_id: Some("unknown_business_unit".to_string()),
+    };
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/account/create", &app.address))
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(400, response.status().as_u16());
+
+    let nb_users = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM users"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to count users")
+        .count;
+    assert_eq!(nb_users, 0);
+}
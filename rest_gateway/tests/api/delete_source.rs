@@ -0,0 +1,127 @@
+use common::constants::routing_keys::DELETE_CONTENT_ROUTING_KEY;
+use futures::lock::Mutex;
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use rest_gateway::{
+    domain::entities::source_meta::{SourceMeta, SourceType},
+    repositories::{
+        source_meta_postgres_repository::SourceMetaPostgresRepository,
+        user_storage_usage_postgres_repository::UserStorageUsagePostgresRepository,
+    },
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    add_source_files::listen_to_content_exchange,
+    helpers::{spawn_app, TestApp},
+};
+
+async fn delete_source(app: &TestApp, token: &str, source_id: Uuid) -> reqwest::Response {
+    reqwest::Client::new()
+        .delete(format!("{}/sources/{}", &app.address, source_id))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+const TEST_FILE: &[u8] = b"This is a test file";
+
+/// Saves a source meta and its file in the object store, counted in the storage used by the user
+async fn add_test_source(app: &TestApp, user_id: Uuid) -> SourceMeta {
+    let source_meta = SourceMeta::builder()
+        .user_id(user_id)
+        .initial_name("example.epub".to_string())
+        .source_type(SourceType::Epub)
+        .object_store_name(Uuid::new_v4().to_string())
+        .size_bytes(Some(TEST_FILE.len() as i64))
+        .build();
+
+    app.s3_bucket
+        .put_object(
+            format!("{}/{}", user_id, source_meta.object_store_name),
+            TEST_FILE,
+        )
+        .await
+        .unwrap();
+
+    SourceMetaPostgresRepository::new()
+        .add_source_meta(&app.db_pool, &source_meta)
+        .await
+        .unwrap();
+    UserStorageUsagePostgresRepository::new()
+        .try_add_usage(&app.db_pool, user_id, TEST_FILE.len() as u64, None)
+        .await
+        .unwrap();
+
+    source_meta
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn delete_source_returns_a_404_for_an_unknown_source() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let response = delete_source(&app, &token, Uuid::new_v4()).await;
+
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn delete_source_returns_a_404_and_keeps_the_source_of_another_user() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+    let source_meta = add_test_source(&app, Uuid::new_v4()).await;
+
+    let response = delete_source(&app, &token, source_meta.id).await;
+
+    assert_eq!(404, response.status().as_u16());
+    let saved = sqlx::query!("SELECT id FROM source_metas WHERE id = $1", source_meta.id)
+        .fetch_optional(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(saved.is_some());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn delete_source_removes_the_source_file_and_meta_and_requests_the_deletion_of_its_contents()
+{
+    // Arranges
+    let mut app = spawn_app().await;
+
+    let counter = Arc::new(Mutex::new(0_u32));
+    listen_to_content_exchange(&mut app, DELETE_CONTENT_ROUTING_KEY, 2000, counter.clone()).await;
+
+    let (user_id, token) = app.get_test_user_token();
+    let source_meta = add_test_source(&app, user_id).await;
+
+    // Acts
+    let response = delete_source(&app, &token, source_meta.id).await;
+
+    // Asserts
+    assert_eq!(204, response.status().as_u16());
+
+    let saved = sqlx::query!("SELECT id FROM source_metas WHERE id = $1", source_meta.id)
+        .fetch_optional(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(saved.is_none());
+
+    let s3_response = app
+        .s3_bucket
+        .get_object(format!("{}/{}", user_id, source_meta.object_store_name))
+        .await;
+    assert!(!matches!(s3_response, Ok(response) if response.status_code() == 200));
+
+    let used_bytes = UserStorageUsagePostgresRepository::new()
+        .get_used_bytes(&app.db_pool, user_id)
+        .await
+        .unwrap();
+    assert_eq!(used_bytes, 0);
+
+    let counter = counter.lock().await;
+    assert_eq!(*counter, 1);
+}
@@ -0,0 +1,111 @@
+use reqwest::header::{HeaderValue, AUTHORIZATION, CONTENT_DISPOSITION, CONTENT_TYPE};
+use rest_gateway::{
+    controllers::DownloadSourceUrlResponse,
+    domain::entities::source_meta::{SourceMeta, SourceType},
+    repositories::source_meta_postgres_repository::SourceMetaPostgresRepository,
+};
+use uuid::Uuid;
+
+use crate::helpers::{spawn_app, TestApp};
+
+const TEST_FILE: &[u8] = b"1\n00:00:01,000 --> 00:00:02,000\nThis is a test file\n";
+
+async fn download_source(
+    app: &TestApp,
+    token: &str,
+    source_id: Uuid,
+    query: &str,
+) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!(
+            "{}/sources/{}/download?{}",
+            &app.address, source_id, query
+        ))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+/// Saves a source meta and its file in the object store
+async fn add_test_source(app: &TestApp, user_id: Uuid) -> SourceMeta {
+    let source_meta = SourceMeta::builder()
+        .user_id(user_id)
+        .initial_name("talk.srt".to_string())
+        .source_type(SourceType::Srt)
+        .object_store_name(Uuid::new_v4().to_string())
+        .detected_mime_type(Some("text/plain".to_string()))
+        .size_bytes(Some(TEST_FILE.len() as i64))
+        .build();
+
+    app.s3_bucket
+        .put_object(
+            format!("{}/{}", user_id, source_meta.object_store_name),
+            TEST_FILE,
+        )
+        .await
+        .unwrap();
+
+    SourceMetaPostgresRepository::new()
+        .add_source_meta(&app.db_pool, &source_meta)
+        .await
+        .unwrap();
+
+    source_meta
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn download_source_streams_the_original_file_with_its_initial_name() {
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let source_meta = add_test_source(&app, user_id).await;
+
+    let response = download_source(&app, &token, source_meta.id, "").await;
+
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(response.headers()[CONTENT_TYPE], "text/plain");
+    assert!(response.headers()[CONTENT_DISPOSITION]
+        .to_str()
+        .unwrap()
+        .contains("talk.srt"));
+    assert_eq!(response.bytes().await.unwrap().as_ref(), TEST_FILE);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn download_source_returns_a_presigned_url_of_the_original_file() {
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let source_meta = add_test_source(&app, user_id).await;
+
+    let response = download_source(&app, &token, source_meta.id, "presigned=true").await;
+
+    assert_eq!(200, response.status().as_u16());
+    let response = response.json::<DownloadSourceUrlResponse>().await.unwrap();
+    assert!(response.url.contains("X-Amz-Signature"));
+    assert!(response.expires_at > chrono::Utc::now());
+
+    // Downloaded without the token of the user
+    let file_response = reqwest::get(&response.url).await.unwrap();
+    assert_eq!(200, file_response.status().as_u16());
+    assert!(file_response.headers()[CONTENT_DISPOSITION]
+        .to_str()
+        .unwrap()
+        .contains("talk.srt"));
+    assert_eq!(file_response.bytes().await.unwrap().as_ref(), TEST_FILE);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn download_source_returns_a_404_for_the_source_of_another_user() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+    let source_meta = add_test_source(&app, Uuid::new_v4()).await;
+
+    let streamed_response = download_source(&app, &token, source_meta.id, "").await;
+    let presigned_response = download_source(&app, &token, source_meta.id, "presigned=true").await;
+
+    assert_eq!(404, streamed_response.status().as_u16());
+    assert_eq!(404, presigned_response.status().as_u16());
+}
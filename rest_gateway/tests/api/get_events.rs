@@ -0,0 +1,177 @@
+use common::{
+    constants::routing_keys::CONTENT_EXTRACTION_PROGRESS_ROUTING_KEY,
+    dtos::extraction_progress::{ExtractionProgressDto, ExtractionStatusDto},
+};
+use lapin::{options::BasicPublishOptions, BasicProperties};
+use reqwest::header::{HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use rest_gateway::{
+    domain::entities::source_meta::{SourceMeta, SourceType},
+    repositories::source_meta_postgres_repository::SourceMetaPostgresRepository,
+    responders::sse::SSE_CONTENT_TYPE,
+};
+use tokio::time::{timeout, Duration};
+use uuid::Uuid;
+
+use crate::helpers::{spawn_app, TestApp};
+
+async fn get_events(app: &TestApp, token: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!("{}/events", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn add_test_source_meta(app: &TestApp, user_id: Uuid) -> Uuid {
+    let source_meta = SourceMeta::builder()
+        .user_id(user_id)
+        .initial_name("example.epub".to_string())
+        .source_type(SourceType::Epub)
+        .object_store_name(Uuid::new_v4().to_string())
+        .build();
+
+    SourceMetaPostgresRepository::new()
+        .add_source_meta(&app.db_pool, &source_meta)
+        .await
+        .unwrap();
+
+    source_meta.id
+}
+
+/// Publishes a progress until an event is streamed, returning the streamed body
+///
+/// The queue of the progress handler may not be bound to the exchange yet: a published progress could be lost.
+async fn publish_progress_until_streamed(
+    app: &mut TestApp,
+    events: &mut reqwest::Response,
+    progress: &ExtractionProgressDto,
+    timeout_ms: u64,
+) -> String {
+    let retry_sleep_step_ms = 500;
+    let mut approximate_retried_time_ms = 0;
+    let payload = serde_json::to_vec(progress).unwrap();
+    let mut body = String::new();
+
+    loop {
+        let published = app
+            .rabbitmq_channel
+            .basic_publish(
+                &app.rabbitmq_content_exchange_name,
+                CONTENT_EXTRACTION_PROGRESS_ROUTING_KEY,
+                BasicPublishOptions::default(),
+                &payload,
+                BasicProperties::default(),
+            )
+            .await;
+        // When the exchange does not exist yet, the channel is closed
+        if published.is_err() {
+            app.reset_rabbitmq_channel().await;
+        }
+
+        if let Ok(chunk) = timeout(Duration::from_millis(retry_sleep_step_ms), events.chunk()).await
+        {
+            let chunk = chunk.unwrap().expect("The events stream ended");
+            body.push_str(std::str::from_utf8(&chunk).unwrap());
+            if body.contains("event: extraction_progress") {
+                return body;
+            }
+        }
+
+        approximate_retried_time_ms += retry_sleep_step_ms;
+        if approximate_retried_time_ms > timeout_ms {
+            panic!(
+                "Timeout: the progress of the source {} was never streamed",
+                progress.source_meta_id
+            );
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_events_requires_an_authenticated_user() {
+    let app = spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/events", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_events_streams_the_extraction_progress_of_the_sources_of_the_user() {
+    // Arranges
+    let mut app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let source_id = add_test_source_meta(&app, user_id).await;
+    let progress = ExtractionProgressDto {
+        source_meta_id: source_id,
+        status: ExtractionStatusDto::InProgress,
+        chunk_index: 3,
+        total_estimated_chunks: 10,
+        bytes_processed: 1024,
+    };
+
+    // Acts
+    let mut events = get_events(&app, &token).await;
+    assert_eq!(200, events.status().as_u16());
+    assert_eq!(
+        events.headers().get(CONTENT_TYPE).unwrap(),
+        SSE_CONTENT_TYPE
+    );
+
+    let body = publish_progress_until_streamed(&mut app, &mut events, &progress, 10000).await;
+
+    // Asserts
+    let data = body
+        .lines()
+        .skip_while(|line| *line != "event: extraction_progress")
+        .nth(1)
+        .and_then(|line| line.strip_prefix("data: "))
+        .unwrap();
+    let data: serde_json::Value = serde_json::from_str(data).unwrap();
+    assert_eq!(data["source_id"], source_id.to_string());
+    assert_eq!(data["status"], "in_progress");
+    assert_eq!(data["chunk_index"], 3);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_events_does_not_stream_the_activity_of_another_user() {
+    // Arranges
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+    let source_id = add_test_source_meta(&app, Uuid::new_v4()).await;
+    let progress = ExtractionProgressDto {
+        source_meta_id: source_id,
+        status: ExtractionStatusDto::InProgress,
+        chunk_index: 3,
+        total_estimated_chunks: 10,
+        bytes_processed: 1024,
+    };
+
+    let mut events = get_events(&app, &token).await;
+    assert_eq!(200, events.status().as_u16());
+
+    // Acts
+    let payload = serde_json::to_vec(&progress).unwrap();
+    app.rabbitmq_channel
+        .basic_publish(
+            &app.rabbitmq_content_exchange_name,
+            CONTENT_EXTRACTION_PROGRESS_ROUTING_KEY,
+            BasicPublishOptions::default(),
+            &payload,
+            BasicProperties::default(),
+        )
+        .await
+        .unwrap();
+
+    // Asserts
+    let chunk = timeout(Duration::from_secs(2), events.chunk()).await;
+    assert!(chunk.is_err(), "Unexpected event: {:?}", chunk);
+}
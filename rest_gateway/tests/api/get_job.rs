@@ -0,0 +1,238 @@
+use common::{
+    constants::routing_keys::INGESTION_JOB_STATUS_ROUTING_KEY,
+    dtos::ingestion_job_status::{IngestionErrorCodeDto, IngestionJobStatusDto, SkippedItemDto},
+};
+use lapin::{options::BasicPublishOptions, BasicProperties};
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use rest_gateway::{
+    controllers::GetJobResponse,
+    domain::entities::{
+        ingestion_job::{IngestionErrorCode, IngestionJob, IngestionStage, JobStatus},
+        source_meta::{SourceMeta, SourceType},
+    },
+    repositories::{
+        ingestion_job_postgres_repository::IngestionJobPostgresRepository,
+        source_meta_postgres_repository::SourceMetaPostgresRepository,
+    },
+};
+use tokio::time::{sleep, Duration};
+use uuid::Uuid;
+
+use crate::helpers::{spawn_app, TestApp};
+
+async fn get_job(app: &TestApp, token: &str, job_id: Uuid) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!("{}/jobs/{}", &app.address, job_id))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+/// Saves a source meta and its pending ingestion job
+async fn add_test_job(app: &TestApp, user_id: Uuid) -> IngestionJob {
+    let source_meta = SourceMeta::builder()
+        .user_id(user_id)
+        .initial_name("example.epub".to_string())
+        .source_type(SourceType::Epub)
+        .object_store_name(Uuid::new_v4().to_string())
+        .build();
+
+    SourceMetaPostgresRepository::new()
+        .add_source_meta(&app.db_pool, &source_meta)
+        .await
+        .unwrap();
+
+    let job = IngestionJob::new(source_meta.id);
+    IngestionJobPostgresRepository::new()
+        .add_job(&app.db_pool, &job)
+        .await
+        .unwrap();
+
+    job
+}
+
+/// Publishes a job status until the job has the expected status
+///
+/// The queue of the job status handler may not be bound to the exchange yet: a published status could be lost.
+async fn publish_status_until(
+    app: &mut TestApp,
+    token: &str,
+    job_id: Uuid,
+    job_status: &IngestionJobStatusDto,
+    expected_status: JobStatus,
+    timeout_ms: u64,
+) -> GetJobResponse {
+    let retry_sleep_step_ms = 500;
+    let mut approximate_retried_time_ms = 0;
+    let payload = serde_json::to_vec(job_status).unwrap();
+
+    loop {
+        let published = app
+            .rabbitmq_channel
+            .basic_publish(
+                &app.rabbitmq_content_exchange_name,
+                INGESTION_JOB_STATUS_ROUTING_KEY,
+                BasicPublishOptions::default(),
+                &payload,
+                BasicProperties::default(),
+            )
+            .await;
+        // When the exchange does not exist yet, the channel is closed
+        if published.is_err() {
+            app.reset_rabbitmq_channel().await;
+        }
+
+        sleep(Duration::from_millis(retry_sleep_step_ms)).await;
+
+        let response = get_job(app, token, job_id).await;
+        let response = response.json::<GetJobResponse>().await.unwrap();
+        if response.status == expected_status {
+            return response;
+        }
+
+        approximate_retried_time_ms += retry_sleep_step_ms;
+        if approximate_retried_time_ms > timeout_ms {
+            panic!(
+                "Timeout: the job {} never had the status {:?}",
+                job_id, expected_status
+            );
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_job_returns_a_404_for_an_unknown_job() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let response = get_job(&app, &token, Uuid::new_v4()).await;
+
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_job_returns_a_404_for_the_job_of_another_user() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+    let job = add_test_job(&app, Uuid::new_v4()).await;
+
+    let response = get_job(&app, &token, job.id).await;
+
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_job_returns_a_pending_job() {
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let job = add_test_job(&app, user_id).await;
+
+    let response = get_job(&app, &token, job.id).await;
+
+    assert_eq!(200, response.status().as_u16());
+    let response = response.json::<GetJobResponse>().await.unwrap();
+    assert_eq!(response.id, job.id);
+    assert_eq!(response.source_id, job.source_meta_id);
+    assert_eq!(response.status, JobStatus::Pending);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn job_is_embedded_once_extracted_and_its_contents_embedded() {
+    let mut app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let job = add_test_job(&app, user_id).await;
+
+    let response = publish_status_until(
+        &mut app,
+        &token,
+        job.id,
+        &IngestionJobStatusDto::extracting(job.source_meta_id, Some(1)),
+        JobStatus::Extracting,
+        10000,
+    )
+    .await;
+    assert_eq!(response.nb_contents, Some(1));
+
+    let response = publish_status_until(
+        &mut app,
+        &token,
+        job.id,
+        &IngestionJobStatusDto::embedded(job.source_meta_id),
+        JobStatus::Embedded,
+        10000,
+    )
+    .await;
+    assert!(response.nb_embedded_contents >= 1);
+    assert!(response
+        .stage_durations_ms
+        .contains_key(&IngestionStage::Embedding));
+    // Not searchable with the full-text search yet
+    assert_eq!(response.time_to_searchable_ms, None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn job_is_failed_with_the_reported_error() {
+    let mut app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let job = add_test_job(&app, user_id).await;
+
+    let response = publish_status_until(
+        &mut app,
+        &token,
+        job.id,
+        &IngestionJobStatusDto::failed(
+            job.source_meta_id,
+            "Invalid EPUB".to_string(),
+            IngestionErrorCodeDto::DrmProtected,
+        ),
+        JobStatus::Failed,
+        10000,
+    )
+    .await;
+
+    assert_eq!(response.error.as_deref(), Some("Invalid EPUB"));
+    assert_eq!(response.error_code, Some(IngestionErrorCode::DrmProtected));
+    assert_eq!(
+        response.error_hint.as_deref(),
+        Some(IngestionErrorCode::DrmProtected.hint())
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn job_with_skipped_items_completes_with_warnings() {
+    let mut app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let job = add_test_job(&app, user_id).await;
+
+    publish_status_until(
+        &mut app,
+        &token,
+        job.id,
+        &IngestionJobStatusDto::extracting(job.source_meta_id, Some(1)).with_skipped_items(vec![
+            SkippedItemDto {
+                path: "OEBPS/chapter_2.xhtml".to_string(),
+                error: "Malformed XHTML: 1 unclosed tags".to_string(),
+            },
+        ]),
+        JobStatus::Extracting,
+        10000,
+    )
+    .await;
+
+    let response = publish_status_until(
+        &mut app,
+        &token,
+        job.id,
+        &IngestionJobStatusDto::embedded(job.source_meta_id),
+        JobStatus::CompletedWithWarnings,
+        10000,
+    )
+    .await;
+
+    assert_eq!(response.skipped_items.len(), 1);
+    assert_eq!(response.skipped_items[0].path, "OEBPS/chapter_2.xhtml");
+}
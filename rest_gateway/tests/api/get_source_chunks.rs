@@ -0,0 +1,116 @@
+use common::{
+    constants::routing_keys::GET_SOURCE_CHUNKS_ROUTING_KEY,
+    dtos::{
+        fulltext_search_response::ResultContent,
+        source_chunks_response::{SourceChunksResponseData, SourceChunksResponseDto},
+    },
+};
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use rest_gateway::{
+    controllers::GetSourceChunksResponse,
+    domain::entities::source_meta::{SourceMeta, SourceType},
+    repositories::source_meta_postgres_repository::SourceMetaPostgresRepository,
+};
+use uuid::Uuid;
+
+use crate::helpers::{spawn_app, TestApp};
+
+async fn get_source_chunks(
+    app: &TestApp,
+    token: &str,
+    source_id: Uuid,
+    query: &str,
+) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!(
+            "{}/sources/{}/chunks{}",
+            &app.address, source_id, query
+        ))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn add_test_source(app: &TestApp, user_id: Uuid) -> SourceMeta {
+    let source_meta = SourceMeta::builder()
+        .user_id(user_id)
+        .initial_name("example.epub".to_string())
+        .source_type(SourceType::Epub)
+        .object_store_name(Uuid::new_v4().to_string())
+        .build();
+
+    SourceMetaPostgresRepository::new()
+        .add_source_meta(&app.db_pool, &source_meta)
+        .await
+        .unwrap();
+
+    source_meta
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_source_chunks_returns_a_404_for_the_source_of_another_user() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+    let source_meta = add_test_source(&app, Uuid::new_v4()).await;
+
+    let response = get_source_chunks(&app, &token, source_meta.id, "").await;
+
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_source_chunks_returns_a_400_for_an_invalid_page() {
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let source_meta = add_test_source(&app, user_id).await;
+
+    let response = get_source_chunks(&app, &token, source_meta.id, "?page=0").await;
+    assert_eq!(400, response.status().as_u16());
+
+    let response = get_source_chunks(&app, &token, source_meta.id, "?limit=1000").await;
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_source_chunks_returns_a_page_of_the_extracted_chunks() {
+    // Arranges
+    let mut app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let source_meta = add_test_source(&app, user_id).await;
+
+    let fake_response = SourceChunksResponseDto::Ok {
+        data: SourceChunksResponseData {
+            chunks: vec![ResultContent {
+                id: Uuid::new_v4(),
+                metadata: serde_json::json!({ "page": 3 }),
+                content: "Third chunk".to_string(),
+                source_meta_id: Some(source_meta.id),
+            }],
+            total: 5,
+        },
+    };
+    app.listen_and_respond_from_rpc(
+        GET_SOURCE_CHUNKS_ROUTING_KEY,
+        5000,
+        Vec::from(fake_response.try_serializing().unwrap().as_bytes()),
+    )
+    .await;
+
+    // Acts
+    let response = get_source_chunks(&app, &token, source_meta.id, "?page=2&limit=2").await;
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+    let response = response.json::<GetSourceChunksResponse>().await.unwrap();
+    assert_eq!(response.source_id, source_meta.id);
+    assert_eq!(response.page, 2);
+    assert_eq!(response.total_chunks, 5);
+    assert_eq!(response.total_pages, 3);
+    assert_eq!(response.chunks.len(), 1);
+    assert_eq!(response.chunks[0].content, "Third chunk");
+    assert_eq!(response.chunks[0].metadata["page"], 3);
+}
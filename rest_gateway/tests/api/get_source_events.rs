@@ -0,0 +1,102 @@
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use rest_gateway::{
+    controllers::GetSourceEventsResponse,
+    domain::entities::{
+        source_event::SourceEventType,
+        source_meta::{SourceMeta, SourceType},
+    },
+    repositories::source_meta_postgres_repository::SourceMetaPostgresRepository,
+};
+use uuid::Uuid;
+
+use crate::helpers::{spawn_app, TestApp};
+
+async fn get_source_events(app: &TestApp, token: &str, source_id: Uuid) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!("{}/sources/{}/events", &app.address, source_id))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+/// Saves a source meta and its file in the object store
+async fn add_test_source(app: &TestApp, user_id: Uuid) -> SourceMeta {
+    let source_meta = SourceMeta::builder()
+        .user_id(user_id)
+        .initial_name("example.epub".to_string())
+        .source_type(SourceType::Epub)
+        .object_store_name(Uuid::new_v4().to_string())
+        .build();
+
+    app.s3_bucket
+        .put_object(
+            format!("{}/{}", user_id, source_meta.object_store_name),
+            b"This is a test file",
+        )
+        .await
+        .unwrap();
+
+    SourceMetaPostgresRepository::new()
+        .add_source_meta(&app.db_pool, &source_meta)
+        .await
+        .unwrap();
+
+    source_meta
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_source_events_returns_a_404_for_an_unknown_source() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let response = get_source_events(&app, &token, Uuid::new_v4()).await;
+
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_source_events_returns_a_404_for_the_source_of_another_user() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+    let source_meta = add_test_source(&app, Uuid::new_v4()).await;
+
+    let response = get_source_events(&app, &token, source_meta.id).await;
+
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_source_events_lists_the_deletion_of_a_deleted_source() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let source_meta = add_test_source(&app, user_id).await;
+
+    let response = reqwest::Client::new()
+        .delete(format!("{}/sources/{}", &app.address, source_meta.id))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(204, response.status().as_u16());
+
+    // Acts
+    let response = get_source_events(&app, &token, source_meta.id).await;
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+    let response = response.json::<GetSourceEventsResponse>().await.unwrap();
+    assert_eq!(response.events.len(), 1);
+    assert_eq!(response.events[0].event_type, SourceEventType::Deleted);
+    assert_eq!(
+        response.events[0].payload["source"]["initial_name"],
+        "example.epub"
+    );
+}
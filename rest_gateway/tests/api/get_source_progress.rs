@@ -0,0 +1,181 @@
+use common::{
+    constants::routing_keys::CONTENT_EXTRACTION_PROGRESS_ROUTING_KEY,
+    dtos::extraction_progress::{ExtractionProgressDto, ExtractionStatusDto},
+};
+use lapin::{options::BasicPublishOptions, BasicProperties};
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use rest_gateway::{
+    controllers::{GetSourceProgressResponse, SourceProgressStatus},
+    domain::entities::source_meta::{SourceMeta, SourceType},
+    repositories::source_meta_postgres_repository::SourceMetaPostgresRepository,
+};
+use tokio::time::{sleep, Duration};
+use uuid::Uuid;
+
+use crate::helpers::{spawn_app, TestApp};
+
+async fn get_source_progress(app: &TestApp, token: &str, source_id: Uuid) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!("{}/sources/{}/progress", &app.address, source_id))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn add_test_source_meta(app: &TestApp, user_id: Uuid) -> Uuid {
+    let source_meta = SourceMeta::builder()
+        .user_id(user_id)
+        .initial_name("example.epub".to_string())
+        .source_type(SourceType::Epub)
+        .object_store_name(Uuid::new_v4().to_string())
+        .build();
+
+    SourceMetaPostgresRepository::new()
+        .add_source_meta(&app.db_pool, &source_meta)
+        .await
+        .unwrap();
+
+    source_meta.id
+}
+
+/// Publishes a progress until the source has the expected status
+///
+/// The queue of the progress handler may not be bound to the exchange yet: a published progress could be lost.
+async fn publish_progress_until_status(
+    app: &mut TestApp,
+    token: &str,
+    progress: &ExtractionProgressDto,
+    expected_status: SourceProgressStatus,
+    timeout_ms: u64,
+) -> GetSourceProgressResponse {
+    let retry_sleep_step_ms = 500;
+    let mut approximate_retried_time_ms = 0;
+    let payload = serde_json::to_vec(progress).unwrap();
+
+    loop {
+        let published = app
+            .rabbitmq_channel
+            .basic_publish(
+                &app.rabbitmq_content_exchange_name,
+                CONTENT_EXTRACTION_PROGRESS_ROUTING_KEY,
+                BasicPublishOptions::default(),
+                &payload,
+                BasicProperties::default(),
+            )
+            .await;
+        // When the exchange does not exist yet, the channel is closed
+        if published.is_err() {
+            app.reset_rabbitmq_channel().await;
+        }
+
+        sleep(Duration::from_millis(retry_sleep_step_ms)).await;
+
+        let response = get_source_progress(app, token, progress.source_meta_id).await;
+        let response = response.json::<GetSourceProgressResponse>().await.unwrap();
+        if response.status == expected_status {
+            return response;
+        }
+
+        approximate_retried_time_ms += retry_sleep_step_ms;
+        if approximate_retried_time_ms > timeout_ms {
+            panic!(
+                "Timeout: the source {} never had the status {:?}",
+                progress.source_meta_id, expected_status
+            );
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_source_progress_returns_a_404_for_an_unknown_source() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let response = get_source_progress(&app, &token, Uuid::new_v4()).await;
+
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_source_progress_returns_a_404_for_the_source_of_another_user() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+    let source_id = add_test_source_meta(&app, Uuid::new_v4()).await;
+
+    let response = get_source_progress(&app, &token, source_id).await;
+
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_source_progress_returns_pending_when_no_progress_was_received() {
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let source_id = add_test_source_meta(&app, user_id).await;
+
+    let response = get_source_progress(&app, &token, source_id).await;
+
+    assert_eq!(200, response.status().as_u16());
+    let response = response.json::<GetSourceProgressResponse>().await.unwrap();
+    assert_eq!(response.source_id, source_id);
+    assert_eq!(response.status, SourceProgressStatus::Pending);
+    assert!(response.updated_at.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_source_progress_returns_the_latest_published_progress() {
+    let mut app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let source_id = add_test_source_meta(&app, user_id).await;
+
+    let in_progress = ExtractionProgressDto {
+        source_meta_id: source_id,
+        status: ExtractionStatusDto::InProgress,
+        chunk_index: 20,
+        total_estimated_chunks: 100,
+        bytes_processed: 12000,
+    };
+    let response = publish_progress_until_status(
+        &mut app,
+        &token,
+        &in_progress,
+        SourceProgressStatus::InProgress,
+        10000,
+    )
+    .await;
+    assert_eq!(response.chunk_index, 20);
+    assert_eq!(response.total_estimated_chunks, 100);
+    assert_eq!(response.bytes_processed, 12000);
+
+    let completed = ExtractionProgressDto {
+        source_meta_id: source_id,
+        status: ExtractionStatusDto::Completed,
+        chunk_index: 42,
+        total_estimated_chunks: 42,
+        bytes_processed: 25000,
+    };
+    let response = publish_progress_until_status(
+        &mut app,
+        &token,
+        &completed,
+        SourceProgressStatus::Completed,
+        10000,
+    )
+    .await;
+    assert_eq!(response.chunk_index, 42);
+    assert_eq!(response.total_estimated_chunks, 42);
+
+    let extracted_at = sqlx::query!(
+        "SELECT extracted_at FROM source_metas WHERE id = $1",
+        source_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .extracted_at;
+    assert!(extracted_at.is_some());
+}
@@ -0,0 +1,148 @@
+use chrono::Utc;
+use common::{
+    constants::routing_keys::SOURCE_SUMMARIZED_ROUTING_KEY,
+    dtos::source_summary::{ChapterSummaryDto, SourceSummaryDto},
+};
+use lapin::{options::BasicPublishOptions, BasicProperties};
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use rest_gateway::{
+    controllers::GetSourceSummaryResponse,
+    domain::entities::source_meta::{SourceMeta, SourceType},
+    repositories::source_meta_postgres_repository::SourceMetaPostgresRepository,
+};
+use tokio::time::{sleep, Duration};
+use uuid::Uuid;
+
+use crate::helpers::{spawn_app, TestApp};
+
+async fn get_source_summary(app: &TestApp, token: &str, source_id: Uuid) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!("{}/sources/{}/summary", &app.address, source_id))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn add_test_source_meta(app: &TestApp, user_id: Uuid) -> Uuid {
+    let source_meta = SourceMeta::builder()
+        .user_id(user_id)
+        .initial_name("example.epub".to_string())
+        .source_type(SourceType::Epub)
+        .object_store_name(Uuid::new_v4().to_string())
+        .build();
+
+    SourceMetaPostgresRepository::new()
+        .add_source_meta(&app.db_pool, &source_meta)
+        .await
+        .unwrap();
+
+    source_meta.id
+}
+
+/// Publishes a summary until the source has a summary
+///
+/// The queue of the summary handler may not be bound to the exchange yet: a published summary could be lost.
+async fn publish_summary_until_summarized(
+    app: &mut TestApp,
+    token: &str,
+    source_summary: &SourceSummaryDto,
+    timeout_ms: u64,
+) -> GetSourceSummaryResponse {
+    let retry_sleep_step_ms = 500;
+    let mut approximate_retried_time_ms = 0;
+    let payload = serde_json::to_vec(source_summary).unwrap();
+
+    loop {
+        let published = app
+            .rabbitmq_channel
+            .basic_publish(
+                &app.rabbitmq_content_exchange_name,
+                SOURCE_SUMMARIZED_ROUTING_KEY,
+                BasicPublishOptions::default(),
+                &payload,
+                BasicProperties::default(),
+            )
+            .await;
+        // When the exchange does not exist yet, the channel is closed
+        if published.is_err() {
+            app.reset_rabbitmq_channel().await;
+        }
+
+        sleep(Duration::from_millis(retry_sleep_step_ms)).await;
+
+        let response = get_source_summary(app, token, source_summary.source_meta_id).await;
+        if response.status().is_success() {
+            return response.json::<GetSourceSummaryResponse>().await.unwrap();
+        }
+
+        approximate_retried_time_ms += retry_sleep_step_ms;
+        if approximate_retried_time_ms > timeout_ms {
+            panic!(
+                "Timeout: the source {} was never summarized",
+                source_summary.source_meta_id
+            );
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_source_summary_returns_a_404_for_the_source_of_another_user() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+    let source_id = add_test_source_meta(&app, Uuid::new_v4()).await;
+
+    let response = get_source_summary(&app, &token, source_id).await;
+
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_source_summary_returns_a_404_when_the_source_was_not_summarized_yet() {
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let source_id = add_test_source_meta(&app, user_id).await;
+
+    let response = get_source_summary(&app, &token, source_id).await;
+
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_source_summary_returns_the_published_summaries() {
+    let mut app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let source_id = add_test_source_meta(&app, user_id).await;
+
+    let source_summary = SourceSummaryDto {
+        source_meta_id: source_id,
+        summary: "A whale hunt told by Ishmael.".to_string(),
+        chapters: vec![
+            ChapterSummaryDto {
+                chapter: "chapter_1".to_string(),
+                summary: "Ishmael goes to sea.".to_string(),
+            },
+            ChapterSummaryDto {
+                chapter: "chapter_2".to_string(),
+                summary: "Ishmael meets Queequeg.".to_string(),
+            },
+        ],
+        model: "simulation".to_string(),
+        generated_at: Utc::now(),
+    };
+
+    let response = publish_summary_until_summarized(&mut app, &token, &source_summary, 10000).await;
+
+    assert_eq!(response.source_id, source_id);
+    assert_eq!(response.summary, "A whale hunt told by Ishmael.");
+    assert_eq!(response.model, "simulation");
+    let chapters: Vec<&str> = response
+        .chapters
+        .iter()
+        .map(|chapter| chapter.chapter.as_str())
+        .collect();
+    assert_eq!(chapters, vec!["chapter_1", "chapter_2"]);
+}
@@ -16,3 +16,20 @@ async fn health_check_works() {
     assert!(response.status().is_success());
     assert_eq!(Some(0), response.content_length());
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn readiness_reports_the_database_pool() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!("{}/health/ready", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert!(response.status().is_success());
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["is_healthy"], true);
+    assert_eq!(report["checks"]["database"]["status"], "ok");
+}
@@ -1,4 +1,8 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    io::{BufRead, Read, Write},
+    sync::Arc,
+    time::Duration,
+};
 
 use chrono::Utc;
 use common::telemetry::{get_tracing_subscriber, init_tracing_subscriber};
@@ -13,8 +17,10 @@ use lapin::{
     BasicProperties,
 };
 use rest_gateway::{
-    configuration::{get_configuration, DatabaseSettings},
+    configuration::{get_configuration, DatabaseSettings, Settings},
+    controllers::{LogInAccountBodyData, LogInAccountResponse},
     domain::entities::user::User,
+    migrations::run_migrations,
     repositories::{
         jwt_authentication_repository::JwtAuthenticationRepository,
         user_postgres_repository::UserPostgresRepository,
@@ -22,7 +28,7 @@ use rest_gateway::{
     startup::{get_connection_pool, get_rabbitmq_connection, Application},
 };
 use s3::Bucket;
-use secrecy::Secret;
+use secrecy::{ExposeSecret, Secret};
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 use tokio::time::sleep;
 use tracing::{error, info, info_span, warn, Instrument};
@@ -66,6 +72,12 @@ pub struct TestApp {
 
     // To create fake users for tests
     user_repository: UserPostgresRepository,
+
+    /// Bearer token of the admin endpoints
+    pub admin_token: String,
+
+    /// Number of indexed contents over which the uploads are routed to a new full-text shard
+    pub fulltext_max_contents_per_shard: u64,
 }
 
 /// A test API client / test suite
@@ -224,14 +236,18 @@ impl TestApp {
     /// Could take an optional input user_id to re-create a token
     pub fn get_test_user_token(&self) -> (Uuid, String) {
         let user_id = Uuid::new_v4();
-        let token = self
-            .jwt_authentication_repository
-            .create_token(&user_id.to_string())
-            .unwrap();
+        let token = self.get_user_token(user_id);
 
         (user_id, token)
     }
 
+    /// Returns an access token for a given user, for ex a user created with `create_test_user_account`
+    pub fn get_user_token(&self, user_id: Uuid) -> String {
+        self.jwt_authentication_repository
+            .create_token(&user_id.to_string())
+            .unwrap()
+    }
+
     /// Returns a tuple (email, password)
     pub fn get_test_user_credentials(&self) -> (String, String) {
         let email = SafeEmail().fake();
@@ -255,6 +271,26 @@ impl TestApp {
         (user.id, email, password)
     }
 
+    /// Creates a user into the db and logs them in, starting a new log-in session
+    pub async fn log_in_test_user(&self) -> (Uuid, LogInAccountResponse) {
+        let (user_id, email, password) = self.create_test_user_account().await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/account/login", &self.address))
+            .json(&LogInAccountBodyData {
+                email,
+                password,
+                totp_code: None,
+                recovery_code: None,
+            })
+            .send()
+            .await
+            .expect("Failed to execute request");
+        assert_eq!(200, response.status().as_u16());
+
+        (user_id, response.json().await.unwrap())
+    }
+
     pub fn decode_access_token(&self, access_token: &str) -> Uuid {
         let user_id = self
             .jwt_authentication_repository
@@ -266,18 +302,47 @@ impl TestApp {
     }
 }
 
+/// Minimal EPUB file: its stored `mimetype` entry, and a chapter with a given text
+///
+/// The zip entries have the default timestamp: the same text always gives the same file.
+pub fn test_epub(text: &str) -> Vec<u8> {
+    use std::io::Write;
+
+    let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+
+    archive.start_file("mimetype", options).unwrap();
+    archive.write_all(b"application/epub+zip").unwrap();
+    archive
+        .start_file("OEBPS/chapter_1.xhtml", options)
+        .unwrap();
+    archive
+        .write_all(format!("<html><body><p>{}</p></body></html>", text).as_bytes())
+        .unwrap();
+
+    archive.finish().unwrap().into_inner()
+}
+
 /// Launches the server as a background task
 /// When a tokio runtime is shut down all tasks spawned on it are dropped.
 /// tokio::test spins up a new runtime at the beginning of each test case and they shut down at the end of each test case.
 /// Therefore no need to implement any clean up logic to avoid leaking resources between test runs
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Launches the server as a background task, with settings changed by a test case
+pub async fn spawn_app_with(configure: impl FnOnce(&mut Settings)) -> TestApp {
     // The first time `initialize` is invoked the code in `TRACING` is executed.
     // All other invocations will instead skip execution.
     Lazy::force(&TRACING);
 
     // Randomizes configuration to ensure test isolation
     let configuration = {
-        let mut c = get_configuration().expect("Failed to read configuration.");
+        let mut c = get_configuration()
+            .await
+            .expect("Failed to read configuration.");
         // Uses a different database for each test case
         c.database.database_name = format!(
             "test_{}_{}",
@@ -300,10 +365,27 @@ pub async fn spawn_app() -> TestApp {
             Utc::now().format("%Y-%m-%d_%H-%M-%S"),
             Uuid::new_v4()
         );
+        c.rabbitmq.queue_name_prefix = format!(
+            "test_api_{}_{}",
+            Utc::now().format("%Y-%m-%d_%H-%M-%S"),
+            Uuid::new_v4()
+        );
 
         // Uses a random known JWT secret
         c.jwt.secret = Secret::new(Uuid::new_v4().to_string());
 
+        // Small shards, to fill a full-text shard in a test
+        c.fulltext_sharding.max_contents_per_shard = 10;
+
+        // The sources added from a URL are served by the test cases
+        c.url_downloads.allow_private_networks = true;
+
+        // Sweeps often, for the expired sources to be deleted during a test
+        c.retention.sweep_interval_ms = 100;
+        // Scans often, for the due sources to be re-crawled during a test
+        c.recrawl.scan_interval_ms = 100;
+
+        configure(&mut c);
         c
     };
 
@@ -333,6 +415,7 @@ pub async fn spawn_app() -> TestApp {
     let jwt_authentication_repository = JwtAuthenticationRepository::new(
         configuration.jwt.secret.clone(),
         configuration.jwt.expire_in_s as i64,
+        configuration.jwt.refresh_token_expire_in_s as i64,
     );
 
     let user_repository = UserPostgresRepository::new();
@@ -350,6 +433,8 @@ pub async fn spawn_app() -> TestApp {
         ),
         jwt_authentication_repository,
         user_repository,
+        admin_token: configuration.admin.token.expose_secret().clone(),
+        fulltext_max_contents_per_shard: configuration.fulltext_sharding.max_contents_per_shard,
     }
 }
 
@@ -374,8 +459,7 @@ async fn set_up_database(config: &DatabaseSettings) -> PgPool {
         .expect("Failed to connect to Postgres.");
 
     // Migrates database
-    sqlx::migrate!("../migrations")
-        .run(&connection_pool)
+    run_migrations(&connection_pool)
         .await
         .expect("Failed to migrate the database");
 
@@ -386,3 +470,40 @@ async fn set_up_database(config: &DatabaseSettings) -> PgPool {
 
     connection_pool
 }
+
+/// Serves a single request of a fake HTTP backend, for ex an LLM or a reranker, with a given body, returning its base URL
+pub fn serve_once(content_type: &'static str, body: &'static str) -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut request_body = vec![0; content_length];
+        reader.read_exact(&mut request_body).unwrap();
+
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\ncontent-type: {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            content_type,
+            body.len(),
+            body
+        )
+        .unwrap();
+    });
+
+    url
+}
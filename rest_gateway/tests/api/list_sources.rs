@@ -0,0 +1,289 @@
+use reqwest::header::{HeaderValue, ACCEPT, AUTHORIZATION};
+use rest_gateway::{
+    controllers::{
+        ListSourcesResponse, SourceProgressStatus, SourceResponse, StorageUsageResponse,
+    },
+    domain::entities::{
+        document::Document,
+        extraction_progress::ExtractionStatus,
+        source_meta::{SourceMeta, SourceType},
+    },
+    repositories::{
+        document_postgres_repository::DocumentPostgresRepository,
+        source_meta_postgres_repository::SourceMetaPostgresRepository,
+        user_storage_usage_postgres_repository::UserStorageUsagePostgresRepository,
+    },
+    responders::ndjson::NDJSON_CONTENT_TYPE,
+};
+use serde_json::{json, Value as JsonValue};
+use uuid::Uuid;
+
+use crate::helpers::{spawn_app, spawn_app_with, TestApp};
+
+async fn list_sources(app: &TestApp, token: &str, query: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!("{}/sources?{}", &app.address, query))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn add_test_source_meta(app: &TestApp, user_id: Uuid, source_type: SourceType) -> Uuid {
+    let source_meta = SourceMeta::builder()
+        .user_id(user_id)
+        .initial_name(format!("example.{:?}", source_type).to_lowercase())
+        .source_type(source_type)
+        .object_store_name(Uuid::new_v4().to_string())
+        .build();
+
+    SourceMetaPostgresRepository::new()
+        .add_source_meta(&app.db_pool, &source_meta)
+        .await
+        .unwrap();
+
+    source_meta.id
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_sources_returns_the_sources_of_the_user_from_the_most_recent() {
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let first_source_id = add_test_source_meta(&app, user_id, SourceType::Epub).await;
+    let second_source_id = add_test_source_meta(&app, user_id, SourceType::Srt).await;
+    add_test_source_meta(&app, Uuid::new_v4(), SourceType::Epub).await;
+
+    let response = list_sources(&app, &token, "").await;
+
+    assert_eq!(200, response.status().as_u16());
+    let response = response.json::<ListSourcesResponse>().await.unwrap();
+    let source_ids: Vec<Uuid> = response.sources.iter().map(|source| source.id).collect();
+    assert_eq!(source_ids, vec![second_source_id, first_source_id]);
+    assert_eq!(response.sources[0].source_type, SourceType::Srt);
+    assert_eq!(response.sources[0].status, SourceProgressStatus::Pending);
+    assert!(response.next_cursor.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_sources_returns_the_storage_used_by_the_user_against_their_quota() {
+    let app = spawn_app_with(|settings| settings.uploads.storage_quota_bytes = 1000).await;
+    let (user_id, token) = app.get_test_user_token();
+    UserStorageUsagePostgresRepository::new()
+        .try_add_usage(&app.db_pool, user_id, 400, Some(1000))
+        .await
+        .unwrap();
+
+    let response = list_sources(&app, &token, "").await;
+
+    assert_eq!(200, response.status().as_u16());
+    let response = response.json::<ListSourcesResponse>().await.unwrap();
+    assert_eq!(
+        response.storage,
+        StorageUsageResponse {
+            used_bytes: 400,
+            quota_bytes: Some(1000),
+        }
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_sources_paginates_with_the_next_and_previous_cursors() {
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let mut source_ids = vec![];
+    for _ in 0..3 {
+        source_ids.push(add_test_source_meta(&app, user_id, SourceType::Epub).await);
+    }
+    source_ids.reverse();
+
+    let first_page = list_sources(&app, &token, "limit=2")
+        .await
+        .json::<ListSourcesResponse>()
+        .await
+        .unwrap();
+    let next_cursor = first_page.next_cursor.expect("Missing next cursor");
+    let second_page = list_sources(&app, &token, &format!("limit=2&cursor={}", next_cursor))
+        .await
+        .json::<ListSourcesResponse>()
+        .await
+        .unwrap();
+
+    let listed_ids: Vec<Uuid> = first_page
+        .sources
+        .iter()
+        .chain(second_page.sources.iter())
+        .map(|source| source.id)
+        .collect();
+    assert_eq!(listed_ids, source_ids);
+    assert!(first_page.prev_cursor.is_none());
+    assert!(second_page.next_cursor.is_none());
+
+    // A source added while paging does not shift the pages
+    add_test_source_meta(&app, user_id, SourceType::Epub).await;
+    let prev_cursor = second_page.prev_cursor.expect("Missing previous cursor");
+    let previous_page = list_sources(&app, &token, &format!("limit=2&cursor={}", prev_cursor))
+        .await
+        .json::<ListSourcesResponse>()
+        .await
+        .unwrap();
+
+    let previous_ids: Vec<Uuid> = previous_page
+        .sources
+        .iter()
+        .map(|source| source.id)
+        .collect();
+    assert_eq!(previous_ids, source_ids[..2]);
+    assert!(previous_page.prev_cursor.is_some());
+    assert!(previous_page.next_cursor.is_some());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_sources_filters_by_source_type_and_status() {
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let epub_source_id = add_test_source_meta(&app, user_id, SourceType::Epub).await;
+    let completed_epub_source_id = add_test_source_meta(&app, user_id, SourceType::Epub).await;
+    add_test_source_meta(&app, user_id, SourceType::Srt).await;
+    SourceMetaPostgresRepository::new()
+        .set_extraction_status(
+            &app.db_pool,
+            completed_epub_source_id,
+            ExtractionStatus::Completed,
+        )
+        .await
+        .unwrap();
+
+    let completed_sources = list_sources(&app, &token, "source_type=epub&status=completed")
+        .await
+        .json::<ListSourcesResponse>()
+        .await
+        .unwrap();
+    let pending_sources = list_sources(&app, &token, "source_type=epub&status=pending")
+        .await
+        .json::<ListSourcesResponse>()
+        .await
+        .unwrap();
+
+    assert_eq!(completed_sources.sources.len(), 1);
+    assert_eq!(completed_sources.sources[0].id, completed_epub_source_id);
+    assert_eq!(
+        completed_sources.sources[0].status,
+        SourceProgressStatus::Completed
+    );
+    assert_eq!(pending_sources.sources.len(), 1);
+    assert_eq!(pending_sources.sources[0].id, epub_source_id);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_sources_returns_a_400_for_an_invalid_cursor_or_limit() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    for query in [
+        "cursor=not_a_cursor",
+        "limit=0",
+        "limit=1000",
+        "fields=id,unknown_field",
+    ] {
+        let response = list_sources(&app, &token, query).await;
+
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request for the query {}",
+            query
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_sources_returns_only_the_requested_fields() {
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let source_id = add_test_source_meta(&app, user_id, SourceType::Epub).await;
+
+    let response = list_sources(&app, &token, "fields=id,status").await;
+
+    assert_eq!(200, response.status().as_u16());
+    let response = response.json::<JsonValue>().await.unwrap();
+    assert_eq!(
+        response["sources"],
+        json!([{ "id": source_id, "status": "pending" }])
+    );
+    assert!(response.get("next_cursor").is_some());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_sources_streams_all_the_sources_when_accepting_ndjson() {
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let mut source_ids = vec![];
+    for _ in 0..3 {
+        source_ids.push(add_test_source_meta(&app, user_id, SourceType::Epub).await);
+    }
+    source_ids.reverse();
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/sources", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .header(ACCEPT, NDJSON_CONTENT_TYPE)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(200, response.status().as_u16());
+    let body = response.text().await.unwrap();
+    let streamed_ids: Vec<Uuid> = body
+        .lines()
+        .map(|line| serde_json::from_str::<SourceResponse>(line).unwrap().id)
+        .collect();
+    assert_eq!(streamed_ids, source_ids);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_sources_returns_the_summary_of_the_extracted_sources() {
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let extracted_source_id = add_test_source_meta(&app, user_id, SourceType::Epub).await;
+    let pending_source_id = add_test_source_meta(&app, user_id, SourceType::Epub).await;
+
+    let document_repository = DocumentPostgresRepository::new();
+    document_repository
+        .create_document_if_missing(&app.db_pool, extracted_source_id)
+        .await
+        .unwrap();
+    let mut document = Document::new(extracted_source_id);
+    document.add_chunk(
+        "Call me Ishmael. The whale, the whale!",
+        &json!({ "epub": { "title": "Moby Dick", "author": "Herman Melville" } }),
+    );
+    document_repository
+        .save_document(&app.db_pool, &document)
+        .await
+        .unwrap();
+
+    let response = list_sources(&app, &token, "")
+        .await
+        .json::<ListSourcesResponse>()
+        .await
+        .unwrap();
+
+    assert_eq!(response.sources[0].id, pending_source_id);
+    assert!(response.sources[0].document.is_none());
+    assert_eq!(response.sources[1].id, extracted_source_id);
+    let summary = response.sources[1]
+        .document
+        .as_ref()
+        .expect("Missing summary");
+    assert_eq!(summary.title.as_deref(), Some("Moby Dick"));
+    assert_eq!(summary.author.as_deref(), Some("Herman Melville"));
+    assert_eq!(summary.excerpt, "Call me Ishmael. The whale, the whale!");
+    assert_eq!(summary.nb_chunks, 1);
+    assert_eq!(summary.keywords[0], "whale");
+}
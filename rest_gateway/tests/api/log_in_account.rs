@@ -11,6 +11,8 @@ async fn a_valid_user_account_should_get_a_valid_access_token() {
     let body = LogInAccountBodyData {
         email: test_email.clone(),
         password: test_password,
+        totp_code: None,
+        recovery_code: None,
     };
 
     let response = reqwest::Client::new()
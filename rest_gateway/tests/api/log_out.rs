@@ -0,0 +1,78 @@
+use crate::helpers::spawn_app;
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use rest_gateway::controllers::{LogOutBodyData, RefreshTokenBodyData};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn logging_out_should_revoke_the_access_and_refresh_tokens_of_the_session() {
+    // Arranges
+    let app = spawn_app().await;
+    let (_test_user_id, log_in_response) = app.log_in_test_user().await;
+    let (_other_user_id, other_log_in_response) = app.log_in_test_user().await;
+    let client = reqwest::Client::new();
+
+    // Acts
+    let response = client
+        .post(format!("{}/log_out", &app.address))
+        .json(&LogOutBodyData {
+            refresh_token: log_in_response.refresh_token.clone(),
+        })
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(204, response.status().as_u16());
+
+    let response = client
+        .get(format!("{}/sources", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", log_in_response.access_token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(401, response.status().as_u16());
+
+    let response = client
+        .post(format!("{}/refresh_token", &app.address))
+        .json(&RefreshTokenBodyData {
+            refresh_token: log_in_response.refresh_token,
+        })
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(401, response.status().as_u16());
+
+    // Other sessions are still active
+    let response = client
+        .get(format!("{}/sources", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", other_log_in_response.access_token))
+                .unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn logging_out_with_an_unknown_refresh_token_should_be_rejected() {
+    // Arranges
+    let app = spawn_app().await;
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/log_out", &app.address))
+        .json(&LogOutBodyData {
+            refresh_token: "unknown".to_string(),
+        })
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(401, response.status().as_u16());
+}
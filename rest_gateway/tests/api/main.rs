@@ -1,6 +1,31 @@
 mod add_source_files;
+mod add_source_url;
+mod admin;
+mod api_keys;
+mod ask;
+mod auto_filing_rules;
 mod create_account;
+mod delete_source;
+mod download_source;
+mod get_events;
+mod get_job;
+mod get_source_chunks;
+mod get_source_events;
+mod get_source_progress;
+mod get_source_summary;
 mod health_check;
 mod helpers;
+mod list_sources;
 mod log_in_account;
+mod log_out;
+mod migrations;
+mod normalization_rules;
+mod openapi;
+mod provider_credentials;
+mod refresh_token;
+mod retention_rules;
+mod saved_searches;
 mod search_content;
+mod two_factor;
+mod upload_policies;
+mod uploads;
@@ -0,0 +1,23 @@
+use rest_gateway::migrations::run_migrations;
+
+use crate::helpers::spawn_app;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn concurrent_migrations_should_wait_for_each_other() {
+    let app = spawn_app().await;
+
+    // The database is already migrated: each instance should find no pending migration
+    let (first, second) = tokio::join!(run_migrations(&app.db_pool), run_migrations(&app.db_pool));
+    first.unwrap();
+    second.unwrap();
+
+    let nb_applied_migrations: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM _sqlx_migrations WHERE success")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(
+        nb_applied_migrations as usize,
+        std::fs::read_dir("../migrations").unwrap().count()
+    );
+}
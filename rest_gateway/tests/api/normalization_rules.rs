@@ -0,0 +1,155 @@
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use rest_gateway::{
+    controllers::NormalizationRuleResponse,
+    domain::entities::normalization_rule::NormalizationRuleKind,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::helpers::{spawn_app, TestApp};
+
+async fn add_rule(app: &TestApp, token: &str, body: &Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/admin/normalization_rules", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn delete_rule(app: &TestApp, rule_id: Uuid) -> reqwest::Response {
+    reqwest::Client::new()
+        .delete(format!(
+            "{}/admin/normalization_rules/{}",
+            &app.address, rule_id
+        ))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", app.admin_token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn list_rules(app: &TestApp) -> Vec<NormalizationRuleResponse> {
+    let response = reqwest::Client::new()
+        .get(format!("{}/admin/normalization_rules", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", app.admin_token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(200, response.status().as_u16());
+    response.json().await.unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_normalization_rule_adds_the_rules_in_their_order() {
+    // Arranges
+    let app = spawn_app().await;
+
+    // Acts
+    for body in [
+        json!({ "kind": "abbreviation", "pattern": " k8s ", "replacement": "Kubernetes" }),
+        json!({ "kind": "stop_pattern", "pattern": r"Page \d+ of \d+", "replacement": "ignored" }),
+    ] {
+        let response = add_rule(&app, &app.admin_token, &body).await;
+        assert_eq!(201, response.status().as_u16());
+    }
+
+    // Asserts
+    let rules = list_rules(&app).await;
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0].kind, NormalizationRuleKind::Abbreviation);
+    assert_eq!(rules[0].pattern, "k8s");
+    assert_eq!(rules[0].replacement.as_deref(), Some("Kubernetes"));
+    assert_eq!(rules[1].kind, NormalizationRuleKind::StopPattern);
+    assert_eq!(rules[1].replacement, None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_normalization_rule_returns_a_400_for_an_invalid_rule() {
+    // Arranges
+    let app = spawn_app().await;
+    let test_cases = vec![
+        (
+            json!({ "kind": "stop_pattern", "pattern": "(unclosed" }),
+            "invalid stop pattern",
+        ),
+        (
+            json!({ "kind": "abbreviation", "pattern": "k8s" }),
+            "missing replacement",
+        ),
+        (
+            json!({ "kind": "canonical_name", "pattern": " ", "replacement": "PostgreSQL" }),
+            "empty term",
+        ),
+        (
+            json!({ "tenant": "unknown", "kind": "abbreviation", "pattern": "k8s", "replacement": "Kubernetes" }),
+            "unknown tenant",
+        ),
+    ];
+
+    for (body, error_message) in test_cases {
+        // Acts
+        let response = add_rule(&app, &app.admin_token, &body).await;
+
+        // Asserts
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request when the payload was {}.",
+            error_message
+        );
+    }
+    assert!(list_rules(&app).await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_normalization_rule_returns_a_401_without_the_admin_token() {
+    // Arranges
+    let app = spawn_app().await;
+
+    // Acts
+    let response = add_rule(
+        &app,
+        "not-the-admin-token",
+        &json!({ "kind": "abbreviation", "pattern": "k8s", "replacement": "Kubernetes" }),
+    )
+    .await;
+
+    // Asserts
+    assert_eq!(401, response.status().as_u16());
+    assert!(list_rules(&app).await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn delete_normalization_rule_deletes_the_rule() {
+    // Arranges
+    let app = spawn_app().await;
+    let rule: NormalizationRuleResponse = add_rule(
+        &app,
+        &app.admin_token,
+        &json!({ "kind": "canonical_name", "pattern": "Postgres", "replacement": "PostgreSQL" }),
+    )
+    .await
+    .json()
+    .await
+    .unwrap();
+
+    // Acts
+    let response = delete_rule(&app, rule.id).await;
+
+    // Asserts
+    assert_eq!(204, response.status().as_u16());
+    assert!(list_rules(&app).await.is_empty());
+    assert_eq!(404, delete_rule(&app, rule.id).await.status().as_u16());
+}
@@ -0,0 +1,98 @@
+use crate::helpers::spawn_app;
+use reqwest::{Method, StatusCode};
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+
+/// Value of a path parameter of the specification, accepted by the route extractors
+fn path_parameter_value(name: &str) -> String {
+    match name {
+        "purpose" => "embedding".to_string(),
+        "part_number" => "1".to_string(),
+        "collection" => "books".to_string(),
+        _ => Uuid::new_v4().to_string(),
+    }
+}
+
+/// Fills the `{name}` parameters of a path of the specification
+fn fill_path_parameters(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment.strip_prefix('{') {
+            Some(name) => path_parameter_value(name.trim_end_matches('}')),
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn openapi_json_is_served() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!("{}/openapi.json", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let spec: JsonValue = response.json().await.unwrap();
+    assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+    assert!(spec["paths"]["/sources/{source_id}/chunks"]["get"].is_object());
+    assert!(spec["components"]["securitySchemes"]["api_key"].is_object());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn swagger_ui_is_served() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!("{}/swagger-ui/", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn operations_of_the_specification_are_routed() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let spec: JsonValue = client
+        .get(format!("{}/openapi.json", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request")
+        .json()
+        .await
+        .unwrap();
+
+    let paths = spec["paths"].as_object().unwrap();
+    assert!(!paths.is_empty());
+    for (path, operations) in paths {
+        for method in operations.as_object().unwrap().keys() {
+            let method = Method::from_bytes(method.to_uppercase().as_bytes()).unwrap();
+
+            // Without credentials nor body: a routed operation is rejected, but not found otherwise
+            let response = client
+                .request(
+                    method.clone(),
+                    format!("{}{}", &app.address, fill_path_parameters(path)),
+                )
+                .send()
+                .await
+                .expect("Failed to execute request");
+
+            assert_ne!(
+                response.status(),
+                StatusCode::NOT_FOUND,
+                "{} {} is documented but not routed",
+                method,
+                path
+            );
+        }
+    }
+}
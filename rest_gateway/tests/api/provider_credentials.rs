@@ -0,0 +1,94 @@
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use serde_json::json;
+
+use crate::helpers::spawn_app;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_user_without_tenant_should_not_manage_provider_credentials() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, _email, _password) = app.create_test_user_account().await;
+    let token = app.get_user_token(user_id);
+    let client = reqwest::Client::new();
+
+    // Acts
+    let list_response = client
+        .get(format!("{}/tenant/provider_credentials", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    let save_response = client
+        .put(format!(
+            "{}/tenant/provider_credentials/embedding",
+            &app.address
+        ))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&json!({
+            "provider": "openai",
+            "model": "text-embedding-3-small",
+            "api_key": "sk-test",
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Asserts
+    assert_eq!(403, list_response.status().as_u16());
+    assert_eq!(403, save_response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_provider_credentials_should_be_rejected() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, _email, _password) = app.create_test_user_account().await;
+    let token = app.get_user_token(user_id);
+
+    let test_cases = vec![
+        (
+            json!({ "provider": "openai", "model": "../files", "api_key": "sk-test" }),
+            "model with a path",
+        ),
+        (
+            json!({ "provider": "openai", "model": "text-embedding-3-small", "api_key": " " }),
+            "empty API key",
+        ),
+        (
+            json!({ "provider": "unknown", "model": "text-embedding-3-small", "api_key": "sk-test" }),
+            "unknown provider",
+        ),
+    ];
+
+    for (body, description) in test_cases {
+        // Acts
+        let response = reqwest::Client::new()
+            .put(format!(
+                "{}/tenant/provider_credentials/embedding",
+                &app.address
+            ))
+            .header(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+            )
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        // Asserts
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not reject the credentials with a {}",
+            description
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use crate::helpers::spawn_app;
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use rest_gateway::controllers::{RefreshTokenBodyData, RefreshTokenResponse};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_valid_refresh_token_should_get_new_access_and_refresh_tokens() {
+    // Arranges
+    let app = spawn_app().await;
+    let (test_user_id, log_in_response) = app.log_in_test_user().await;
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/refresh_token", &app.address))
+        .json(&RefreshTokenBodyData {
+            refresh_token: log_in_response.refresh_token.clone(),
+        })
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+
+    let json_response = response.json::<RefreshTokenResponse>().await.unwrap();
+    assert_ne!(json_response.refresh_token, log_in_response.refresh_token);
+    assert_eq!(
+        app.decode_access_token(&json_response.access_token),
+        test_user_id
+    );
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/sources", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", json_response.access_token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn an_unknown_refresh_token_should_be_rejected() {
+    // Arranges
+    let app = spawn_app().await;
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/refresh_token", &app.address))
+        .json(&RefreshTokenBodyData {
+            refresh_token: "unknown".to_string(),
+        })
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_reused_refresh_token_should_revoke_its_whole_session() {
+    // Arranges
+    let app = spawn_app().await;
+    let (_test_user_id, log_in_response) = app.log_in_test_user().await;
+    let client = reqwest::Client::new();
+
+    let refresh_response = client
+        .post(format!("{}/refresh_token", &app.address))
+        .json(&RefreshTokenBodyData {
+            refresh_token: log_in_response.refresh_token.clone(),
+        })
+        .send()
+        .await
+        .expect("Failed to execute request")
+        .json::<RefreshTokenResponse>()
+        .await
+        .unwrap();
+
+    // Acts: reuses the already rotated refresh token
+    let response = client
+        .post(format!("{}/refresh_token", &app.address))
+        .json(&RefreshTokenBodyData {
+            refresh_token: log_in_response.refresh_token,
+        })
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(401, response.status().as_u16());
+
+    // The latest refresh token and access token of the session are revoked too
+    let response = client
+        .post(format!("{}/refresh_token", &app.address))
+        .json(&RefreshTokenBodyData {
+            refresh_token: refresh_response.refresh_token,
+        })
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(401, response.status().as_u16());
+
+    let response = client
+        .get(format!("{}/sources", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", refresh_response.access_token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(401, response.status().as_u16());
+}
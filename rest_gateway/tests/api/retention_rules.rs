@@ -0,0 +1,233 @@
+use chrono::{Duration, Utc};
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use rest_gateway::{
+    controllers::{GetSourceEventsResponse, RetentionRuleResponse},
+    domain::entities::{
+        source_event::SourceEventType,
+        source_meta::{SourceMeta, SourceType},
+    },
+    repositories::source_meta_postgres_repository::SourceMetaPostgresRepository,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::helpers::{spawn_app, TestApp};
+
+async fn save_rule(
+    app: &TestApp,
+    token: &str,
+    collection: &str,
+    body: &Value,
+) -> reqwest::Response {
+    reqwest::Client::new()
+        .put(format!("{}/retention_rules/{}", &app.address, collection))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn list_rules(app: &TestApp, token: &str) -> Vec<RetentionRuleResponse> {
+    let response = reqwest::Client::new()
+        .get(format!("{}/retention_rules", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(200, response.status().as_u16());
+    response.json().await.unwrap()
+}
+
+async fn get_source_events(app: &TestApp, token: &str, source_id: Uuid) -> GetSourceEventsResponse {
+    let response = reqwest::Client::new()
+        .get(format!("{}/sources/{}/events", &app.address, source_id))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(200, response.status().as_u16());
+    response.json().await.unwrap()
+}
+
+/// Saves a source meta added some days ago, and its file in the object store
+async fn add_test_source(
+    app: &TestApp,
+    user_id: Uuid,
+    collection: Option<&str>,
+    added_days_ago: i64,
+) -> SourceMeta {
+    let source_meta = SourceMeta::builder()
+        .user_id(user_id)
+        .initial_name("example.epub".to_string())
+        .source_type(SourceType::Epub)
+        .object_store_name(Uuid::new_v4().to_string())
+        .added_at(Utc::now() - Duration::days(added_days_ago))
+        .collection(collection.map(str::to_string))
+        .build();
+
+    app.s3_bucket
+        .put_object(
+            format!("{}/{}", user_id, source_meta.object_store_name),
+            b"This is a test file",
+        )
+        .await
+        .unwrap();
+
+    SourceMetaPostgresRepository::new()
+        .add_source_meta(&app.db_pool, &source_meta)
+        .await
+        .unwrap();
+
+    source_meta
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn save_retention_rule_replaces_the_retention_of_a_collection() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let response = save_rule(&app, &token, "reports", &json!({ "retention_days": 30 })).await;
+    assert_eq!(200, response.status().as_u16());
+    let rule = response.json::<RetentionRuleResponse>().await.unwrap();
+    assert_eq!(rule.collection, "reports");
+    assert_eq!(rule.retention_days, 30);
+
+    let response = save_rule(&app, &token, "reports", &json!({ "retention_days": 90 })).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let rules = list_rules(&app, &token).await;
+    assert_eq!(rules.len(), 1);
+    assert_eq!(rules[0].id, rule.id);
+    assert_eq!(rules[0].retention_days, 90);
+
+    // The rules of another user are not listed
+    let other_token = app.get_user_token(Uuid::new_v4());
+    assert!(list_rules(&app, &other_token).await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn save_retention_rule_returns_a_400_for_an_invalid_retention() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    for (body, description) in [
+        (json!({ "retention_days": 0 }), "no retention"),
+        (json!({ "retention_days": -1 }), "negative retention"),
+        (json!({}), "missing retention"),
+    ] {
+        let response = save_rule(&app, &token, "reports", &body).await;
+
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request when the payload was {}.",
+            description
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn delete_retention_rule_returns_a_404_for_a_collection_without_rule() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+    let response = save_rule(&app, &token, "reports", &json!({ "retention_days": 30 })).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let delete_rule = |collection: &'static str| {
+        reqwest::Client::new()
+            .delete(format!("{}/retention_rules/{}", &app.address, collection))
+            .header(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+            )
+            .send()
+    };
+
+    assert_eq!(204, delete_rule("reports").await.unwrap().status().as_u16());
+    assert_eq!(404, delete_rule("reports").await.unwrap().status().as_u16());
+    assert!(list_rules(&app, &token).await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn retention_sweeper_deletes_the_expired_sources_and_warns_the_expiring_ones() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+    let source_meta_repository = SourceMetaPostgresRepository::new();
+
+    let expired_source = add_test_source(&app, user_id, Some("reports"), 31).await;
+    let expiring_source = add_test_source(&app, user_id, Some("reports"), 25).await;
+    let fresh_source = add_test_source(&app, user_id, Some("reports"), 0).await;
+    let unfiled_source = add_test_source(&app, user_id, None, 31).await;
+    let other_collection_source = add_test_source(&app, user_id, Some("notes"), 31).await;
+
+    // Acts
+    let response = save_rule(&app, &token, "reports", &json!({ "retention_days": 30 })).await;
+    assert_eq!(200, response.status().as_u16());
+
+    // Asserts
+    let mut is_expired_source_deleted = false;
+    for _ in 0..50 {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        is_expired_source_deleted = !source_meta_repository
+            .is_user_source_meta(&app.db_pool, user_id, expired_source.id)
+            .await
+            .unwrap();
+        if is_expired_source_deleted {
+            break;
+        }
+    }
+    assert!(is_expired_source_deleted);
+
+    let events = get_source_events(&app, &token, expired_source.id)
+        .await
+        .events;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event_type, SourceEventType::Deleted);
+    assert_eq!(events[0].payload["reason"], "expired");
+    assert!(app
+        .s3_bucket
+        .get_object(format!("{}/{}", user_id, expired_source.object_store_name))
+        .await
+        .is_err());
+
+    // The expiring source is warned once, even after several sweeps
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    let events = get_source_events(&app, &token, expiring_source.id)
+        .await
+        .events;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event_type, SourceEventType::Expiring);
+    assert_eq!(events[0].payload["collection"], "reports");
+
+    for source in [
+        &expiring_source,
+        &fresh_source,
+        &unfiled_source,
+        &other_collection_source,
+    ] {
+        assert!(source_meta_repository
+            .is_user_source_meta(&app.db_pool, user_id, source.id)
+            .await
+            .unwrap());
+    }
+    for source in [&fresh_source, &unfiled_source, &other_collection_source] {
+        assert!(get_source_events(&app, &token, source.id)
+            .await
+            .events
+            .is_empty());
+    }
+}
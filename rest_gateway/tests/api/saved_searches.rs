@@ -0,0 +1,262 @@
+use common::{
+    constants::routing_keys::SEARCH_FULLTEXT_ROUTING_KEY,
+    dtos::fulltext_search_response::{FulltextSearchResponseData, FulltextSearchResponseDto},
+};
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use rest_gateway::controllers::{
+    ListSearchHistoryResponse, SavedSearchResponse, SearchContentResponse,
+};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::helpers::{spawn_app, spawn_app_with, TestApp};
+
+/// Sets up a fake response, without results, from the fulltext search service
+async fn respond_to_fulltext_search(app: &mut TestApp) {
+    let fake_response = FulltextSearchResponseDto::Ok {
+        data: FulltextSearchResponseData {
+            results: vec![],
+            source_hit_counts: HashMap::new(),
+            language_hit_counts: HashMap::new(),
+            chapter_hit_counts: HashMap::new(),
+            keyword_hit_counts: HashMap::new(),
+            entity_hit_counts: HashMap::new(),
+            total_hits: Some(0),
+            suggestions: vec![],
+        },
+    };
+
+    app.listen_and_respond_from_rpc(
+        SEARCH_FULLTEXT_ROUTING_KEY,
+        5000,
+        Vec::from(fake_response.try_serializing().unwrap().as_bytes()),
+    )
+    .await;
+}
+
+async fn list_search_history(app: &TestApp, token: &str) -> ListSearchHistoryResponse {
+    let response = reqwest::Client::new()
+        .get(format!("{}/search/history", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(200, response.status().as_u16());
+
+    response.json::<ListSearchHistoryResponse>().await.unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn saved_searches_are_listed_and_deleted() {
+    // Arranges
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/saved_searches", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&serde_json::json!({
+            "name": "Kubernetes notes",
+            "search": { "query": "kubernetes", "language": "en" }
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(201, response.status().as_u16());
+    let saved_search = response.json::<SavedSearchResponse>().await.unwrap();
+    assert_eq!(saved_search.search["query"], "kubernetes");
+
+    // Acts
+    let response = client
+        .get(format!("{}/saved_searches", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+    let saved_searches = response.json::<Vec<SavedSearchResponse>>().await.unwrap();
+    assert_eq!(saved_searches.len(), 1);
+    assert_eq!(saved_searches[0].id, saved_search.id);
+    assert_eq!(saved_searches[0].name, "Kubernetes notes");
+
+    for expected_status in [204, 404] {
+        let response = client
+            .delete(format!(
+                "{}/saved_searches/{}",
+                &app.address, saved_search.id
+            ))
+            .header(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+            )
+            .send()
+            .await
+            .expect("Failed to execute request");
+        assert_eq!(expected_status, response.status().as_u16());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn saving_an_invalid_search_returns_a_400() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    for body in [
+        serde_json::json!({ "name": " ", "search": { "query": "test" } }),
+        serde_json::json!({ "name": "Test", "search": { "query": "test", "page": 0 } }),
+    ] {
+        let response = reqwest::Client::new()
+            .post(format!("{}/saved_searches", &app.address))
+            .header(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+            )
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request");
+
+        assert_eq!(400, response.status().as_u16(), "for {}", body);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn running_an_unknown_saved_search_returns_a_404() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let response = reqwest::Client::new()
+        .post(format!(
+            "{}/saved_searches/{}/search",
+            &app.address,
+            Uuid::new_v4()
+        ))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn running_a_saved_search_records_it_in_the_search_history() {
+    // Arranges
+    let mut app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+    let client = reqwest::Client::new();
+    respond_to_fulltext_search(&mut app).await;
+
+    let saved_search = client
+        .post(format!("{}/saved_searches", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&serde_json::json!({
+            "name": "Kubernetes notes",
+            "search": { "query": "kubernetes" }
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request")
+        .json::<SavedSearchResponse>()
+        .await
+        .unwrap();
+
+    // Acts
+    let response = client
+        .post(format!(
+            "{}/saved_searches/{}/search",
+            &app.address, saved_search.id
+        ))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+    let response = response.json::<SearchContentResponse>().await.unwrap();
+    assert_eq!(response.total_hits, 0);
+
+    let history = list_search_history(&app, &token).await;
+    assert_eq!(history.searches.len(), 1);
+    assert_eq!(history.searches[0].query, "kubernetes");
+    assert_eq!(history.searches[0].search, saved_search.search);
+    assert!(history.next_cursor.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn search_history_keeps_only_the_latest_searches() {
+    // Arranges
+    let mut app = spawn_app_with(|settings| settings.search_history.max_entries_per_user = 2).await;
+    let (_, token) = app.get_test_user_token();
+    respond_to_fulltext_search(&mut app).await;
+
+    // Acts
+    for query in ["first", "second", "third"] {
+        let response = reqwest::Client::new()
+            .post(format!("{}/search", &app.address))
+            .header(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+            )
+            .json(&serde_json::json!({ "query": query }))
+            .send()
+            .await
+            .expect("Failed to execute request");
+        assert_eq!(200, response.status().as_u16());
+    }
+
+    // Asserts
+    let history = list_search_history(&app, &token).await;
+    let queries: Vec<&str> = history
+        .searches
+        .iter()
+        .map(|entry| entry.query.as_str())
+        .collect();
+    assert_eq!(queries, vec!["third", "second"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn searches_are_not_recorded_when_the_search_history_is_disabled() {
+    // Arranges
+    let mut app = spawn_app_with(|settings| settings.search_history.enabled = false).await;
+    let (_, token) = app.get_test_user_token();
+    respond_to_fulltext_search(&mut app).await;
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/search", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&serde_json::json!({ "query": "test" }))
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(200, response.status().as_u16());
+
+    // Asserts
+    assert!(list_search_history(&app, &token).await.searches.is_empty());
+}
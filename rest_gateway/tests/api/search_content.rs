@@ -1,11 +1,28 @@
 use common::{
-    constants::routing_keys::SEARCH_FULLTEXT_ROUTING_KEY,
-    dtos::fulltext_search_response::{FulltextSearchResponseData, FulltextSearchResponseDto},
+    constants::routing_keys::{SEARCH_FULLTEXT_ROUTING_KEY, SEARCH_SEMANTIC_ROUTING_KEY},
+    dtos::{
+        fulltext_search_response::{
+            FulltextSearchResponseData, FulltextSearchResponseDto, ResultContent,
+        },
+        semantic_search_response::{SemanticSearchResponseData, SemanticSearchResponseDto},
+    },
 };
-use reqwest::header::{HeaderValue, AUTHORIZATION};
+use reqwest::header::{HeaderValue, ACCEPT, AUTHORIZATION, CONTENT_TYPE};
+use rest_gateway::{
+    controllers::SearchContentResponse,
+    domain::entities::{
+        search_result::SearchSource,
+        source_meta::{SourceMeta, SourceType},
+    },
+    repositories::source_meta_postgres_repository::SourceMetaPostgresRepository,
+    responders::ndjson::NDJSON_CONTENT_TYPE,
+};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
 use tracing::info;
+use uuid::Uuid;
 
-use crate::helpers::spawn_app;
+use crate::helpers::{serve_once, spawn_app, spawn_app_with};
 
 #[tokio::test(flavor = "multi_thread")]
 async fn search_content_returns_a_response_from_a_valid_request() {
@@ -16,7 +33,16 @@ async fn search_content_returns_a_response_from_a_valid_request() {
 
     // Sets up a fake response from the search service
     let fake_response = FulltextSearchResponseDto::Ok {
-        data: FulltextSearchResponseData { results: vec![] },
+        data: FulltextSearchResponseData {
+            results: vec![],
+            source_hit_counts: HashMap::new(),
+            language_hit_counts: HashMap::new(),
+            chapter_hit_counts: HashMap::new(),
+            keyword_hit_counts: HashMap::new(),
+            entity_hit_counts: HashMap::new(),
+            total_hits: Some(0),
+            suggestions: vec!["text".to_string()],
+        },
     };
     let fake_response = fake_response.try_serializing().unwrap();
 
@@ -47,4 +73,562 @@ async fn search_content_returns_a_response_from_a_valid_request() {
 
     // Asserts
     assert!(response.status().is_success());
+    let response = response.json::<SearchContentResponse>().await.unwrap();
+    assert_eq!(response.suggestions, vec!["text".to_string()]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn search_content_streams_one_content_per_line_when_accepting_ndjson() {
+    let mut app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let result_ids = vec![Uuid::new_v4(), Uuid::new_v4()];
+    let fake_response = FulltextSearchResponseDto::Ok {
+        data: FulltextSearchResponseData {
+            results: result_ids
+                .iter()
+                .map(|id| ResultContent {
+                    id: *id,
+                    metadata: JsonValue::Null,
+                    content: "test content".to_string(),
+                    source_meta_id: None,
+                })
+                .collect(),
+            source_hit_counts: HashMap::new(),
+            language_hit_counts: HashMap::new(),
+            chapter_hit_counts: HashMap::new(),
+            keyword_hit_counts: HashMap::new(),
+            entity_hit_counts: HashMap::new(),
+            total_hits: None,
+            suggestions: vec![],
+        },
+    };
+    let fake_response = fake_response.try_serializing().unwrap();
+
+    app.listen_and_respond_from_rpc(
+        SEARCH_FULLTEXT_ROUTING_KEY,
+        5000,
+        Vec::from(fake_response.as_bytes()),
+    )
+    .await;
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/search", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .header(ACCEPT, NDJSON_CONTENT_TYPE)
+        .json(&serde_json::json!({ "query": "test" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Asserts
+    assert!(response.status().is_success());
+    assert_eq!(
+        response.headers().get(CONTENT_TYPE).unwrap(),
+        NDJSON_CONTENT_TYPE
+    );
+    let body = response.text().await.unwrap();
+    let streamed_ids: Vec<Uuid> = body
+        .lines()
+        .map(|line| serde_json::from_str::<ResultContent>(line).unwrap().id)
+        .collect();
+    assert_eq!(streamed_ids, result_ids);
+}
+
+fn result_contents(ids: &[Uuid]) -> Vec<ResultContent> {
+    ids.iter()
+        .map(|id| ResultContent {
+            id: *id,
+            metadata: JsonValue::Null,
+            content: "test content".to_string(),
+            source_meta_id: None,
+        })
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn hybrid_search_content_merges_the_results_of_both_searches_with_their_provenance() {
+    let mut app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let (fulltext_only, both, semantic_only) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+    let fake_response = FulltextSearchResponseDto::Ok {
+        data: FulltextSearchResponseData {
+            results: result_contents(&[fulltext_only, both]),
+            source_hit_counts: HashMap::new(),
+            language_hit_counts: HashMap::new(),
+            chapter_hit_counts: HashMap::new(),
+            keyword_hit_counts: HashMap::new(),
+            entity_hit_counts: HashMap::new(),
+            total_hits: None,
+            suggestions: vec![],
+        },
+    };
+    app.listen_and_respond_from_rpc(
+        SEARCH_FULLTEXT_ROUTING_KEY,
+        5000,
+        Vec::from(fake_response.try_serializing().unwrap().as_bytes()),
+    )
+    .await;
+
+    let fake_response = SemanticSearchResponseDto::Ok {
+        data: SemanticSearchResponseData {
+            results: result_contents(&[semantic_only, both]),
+        },
+    };
+    app.listen_and_respond_from_rpc(
+        SEARCH_SEMANTIC_ROUTING_KEY,
+        5000,
+        Vec::from(fake_response.try_serializing().unwrap().as_bytes()),
+    )
+    .await;
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/search", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&serde_json::json!({ "query": "test", "mode": "hybrid" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+    let response = response.json::<SearchContentResponse>().await.unwrap();
+    assert_eq!(
+        response
+            .results
+            .iter()
+            .map(|result| (result.id, result.sources.clone()))
+            .collect::<Vec<_>>(),
+        vec![
+            (both, vec![SearchSource::Fulltext, SearchSource::Semantic]),
+            (fulltext_only, vec![SearchSource::Fulltext]),
+            (semantic_only, vec![SearchSource::Semantic]),
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn hybrid_search_content_returns_the_hits_per_source_collection_and_source_type() {
+    let mut app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+
+    let source_metas = [
+        (SourceType::Epub, Some("books")),
+        (SourceType::Ipynb, Some("books")),
+        (SourceType::Epub, None),
+    ]
+    .map(|(source_type, collection)| {
+        SourceMeta::builder()
+            .user_id(user_id)
+            .initial_name("example".to_string())
+            .source_type(source_type)
+            .object_store_name(Uuid::new_v4().to_string())
+            .collection(collection.map(str::to_string))
+            .build()
+    });
+    for source_meta in &source_metas {
+        SourceMetaPostgresRepository::new()
+            .add_source_meta(&app.db_pool, source_meta)
+            .await
+            .unwrap();
+    }
+    let [book, notebook, other_book] = source_metas;
+    // Source of another user
+    let other_source_id = Uuid::new_v4();
+
+    let mut fulltext_results = result_contents(&[Uuid::new_v4()]);
+    fulltext_results[0].source_meta_id = Some(book.id);
+    let fake_response = FulltextSearchResponseDto::Ok {
+        data: FulltextSearchResponseData {
+            results: fulltext_results,
+            // All the matches are counted, not only the returned ones
+            source_hit_counts: HashMap::from([
+                (book.id, 8),
+                (notebook.id, 1),
+                (other_source_id, 4),
+            ]),
+            language_hit_counts: HashMap::from([("en".to_string(), 9)]),
+            chapter_hit_counts: HashMap::from([("chapter_1".to_string(), 3)]),
+            keyword_hit_counts: HashMap::from([("white whale".to_string(), 2)]),
+            entity_hit_counts: HashMap::from([("Ahab".to_string(), 4)]),
+            total_hits: Some(13),
+            suggestions: vec![],
+        },
+    };
+    app.listen_and_respond_from_rpc(
+        SEARCH_FULLTEXT_ROUTING_KEY,
+        5000,
+        Vec::from(fake_response.try_serializing().unwrap().as_bytes()),
+    )
+    .await;
+
+    let mut semantic_results = result_contents(&[Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()]);
+    semantic_results[0].source_meta_id = Some(notebook.id);
+    semantic_results[1].source_meta_id = Some(notebook.id);
+    semantic_results[2].source_meta_id = Some(other_book.id);
+    semantic_results[2].metadata = serde_json::json!({
+        "language": "fr",
+        "keywords": ["white whale", "harpoon"],
+        "entities": { "places": ["Nantucket"] }
+    });
+    let fake_response = SemanticSearchResponseDto::Ok {
+        data: SemanticSearchResponseData {
+            results: semantic_results,
+        },
+    };
+    app.listen_and_respond_from_rpc(
+        SEARCH_SEMANTIC_ROUTING_KEY,
+        5000,
+        Vec::from(fake_response.try_serializing().unwrap().as_bytes()),
+    )
+    .await;
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/search", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&serde_json::json!({ "query": "test", "mode": "hybrid" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+    let response = response.json::<SearchContentResponse>().await.unwrap();
+    assert_eq!(response.results.len(), 4);
+    assert_eq!(
+        response.aggregations.sources,
+        HashMap::from([(book.id, 8), (notebook.id, 2), (other_book.id, 1)])
+    );
+    assert_eq!(
+        response.aggregations.collections,
+        HashMap::from([("books".to_string(), 10)])
+    );
+    assert_eq!(
+        response.aggregations.source_types,
+        HashMap::from([(SourceType::Epub, 9), (SourceType::Ipynb, 2)])
+    );
+    assert_eq!(
+        response.aggregations.languages,
+        HashMap::from([("en".to_string(), 9), ("fr".to_string(), 1)])
+    );
+    assert_eq!(
+        response.aggregations.chapters,
+        HashMap::from([("chapter_1".to_string(), 3)])
+    );
+    assert_eq!(
+        response.aggregations.keywords,
+        HashMap::from([("white whale".to_string(), 2), ("harpoon".to_string(), 1)])
+    );
+    assert_eq!(
+        response.aggregations.entities,
+        HashMap::from([("Ahab".to_string(), 4), ("Nantucket".to_string(), 1)])
+    );
+    assert_eq!(response.total_hits, 13);
+    assert_eq!(response.total_pages, 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn search_content_returns_a_503_for_a_reranking_without_reranker() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/search", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&serde_json::json!({ "query": "test", "mode": "semantic", "rerank": true }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(503, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reranked_search_content_sorts_the_candidates_by_their_rerank_score() {
+    // Arranges
+    let reranker_url = serve_once(
+        "application/json",
+        r#"{"results":[{"index":2,"relevance_score":0.9},{"index":0,"relevance_score":0.5},{"index":1,"relevance_score":0.1}]}"#,
+    );
+    let mut app = spawn_app_with(|settings| {
+        settings.rerank.enabled = true;
+        settings.rerank.api_url = reranker_url;
+    })
+    .await;
+    let (_, token) = app.get_test_user_token();
+
+    let ids = [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+    let fake_response = SemanticSearchResponseDto::Ok {
+        data: SemanticSearchResponseData {
+            results: result_contents(&ids),
+        },
+    };
+    app.listen_and_respond_from_rpc(
+        SEARCH_SEMANTIC_ROUTING_KEY,
+        5000,
+        Vec::from(fake_response.try_serializing().unwrap().as_bytes()),
+    )
+    .await;
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/search", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&serde_json::json!({
+            "query": "test",
+            "mode": "semantic",
+            "rerank": true,
+            "hits_per_page": 2
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+    let response = response.json::<SearchContentResponse>().await.unwrap();
+    assert_eq!(
+        response
+            .results
+            .iter()
+            .map(|result| (result.id, result.rerank_score))
+            .collect::<Vec<_>>(),
+        vec![(ids[2], Some(0.9)), (ids[0], Some(0.5))]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn search_content_returns_a_400_for_an_unknown_mode() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/search", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&serde_json::json!({ "query": "test", "mode": "keyword" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn search_content_returns_only_the_requested_fields() {
+    let mut app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let result_ids = vec![Uuid::new_v4(), Uuid::new_v4()];
+    let fake_response = FulltextSearchResponseDto::Ok {
+        data: FulltextSearchResponseData {
+            results: result_contents(&result_ids),
+            source_hit_counts: HashMap::new(),
+            language_hit_counts: HashMap::new(),
+            chapter_hit_counts: HashMap::new(),
+            keyword_hit_counts: HashMap::new(),
+            entity_hit_counts: HashMap::new(),
+            total_hits: None,
+            suggestions: vec![],
+        },
+    };
+    let fake_response = fake_response.try_serializing().unwrap();
+
+    app.listen_and_respond_from_rpc(
+        SEARCH_FULLTEXT_ROUTING_KEY,
+        5000,
+        Vec::from(fake_response.as_bytes()),
+    )
+    .await;
+
+    // Acts
+    let response = reqwest::Client::new()
+        .post(format!("{}/search?fields=id,score", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&serde_json::json!({ "query": "test" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Asserts
+    assert!(response.status().is_success());
+    let response = response.json::<JsonValue>().await.unwrap();
+    let results = response["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    for (result, id) in results.iter().zip(&result_ids) {
+        let mut fields: Vec<&String> = result.as_object().unwrap().keys().collect();
+        fields.sort();
+        assert_eq!(fields, vec!["id", "score"]);
+        assert_eq!(result["id"], id.to_string());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn search_content_returns_a_400_for_an_unknown_field() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/search?fields=id,chunk_text", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&serde_json::json!({ "query": "test" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn search_content_returns_a_400_for_an_upload_range_ending_before_it_starts() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/search", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&serde_json::json!({
+            "query": "test",
+            "uploaded_after": "2024-02-01T00:00:00Z",
+            "uploaded_before": "2024-01-01T00:00:00Z"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(400, response.status().as_u16());
+    assert!(response.text().await.unwrap().contains("uploaded_after"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn search_content_returns_a_400_for_an_invalid_page() {
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    for page in [
+        serde_json::json!({ "page": 0 }),
+        serde_json::json!({ "hits_per_page": 0 }),
+        serde_json::json!({ "hits_per_page": 101 }),
+        // Beyond the first 1000 results
+        serde_json::json!({ "page": 11, "hits_per_page": 100 }),
+    ] {
+        let mut body = serde_json::json!({ "query": "test" });
+        body.as_object_mut()
+            .unwrap()
+            .extend(page.as_object().unwrap().clone());
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/search", &app.address))
+            .header(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+            )
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        assert_eq!(400, response.status().as_u16(), "for {}", page);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn search_content_finds_nothing_without_calling_the_backends_when_no_source_matches_the_filters(
+) {
+    // No search service answers the RPC calls
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/search", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&serde_json::json!({
+            "query": "test",
+            "mode": "hybrid",
+            "source_ids": [Uuid::new_v4()],
+            "source_type": "epub"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(200, response.status().as_u16());
+    let response: SearchContentResponse = response.json().await.unwrap();
+    assert!(response.results.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn search_content_sheds_the_anonymous_requests_before_the_authenticated_ones() {
+    let app = spawn_app_with(|settings| {
+        settings.search_quota.anonymous.requests_per_s = 1;
+        settings.search_quota.anonymous.burst = 1;
+    })
+    .await;
+    let (_, token) = app.get_test_user_token();
+    let client = reqwest::Client::new();
+
+    let anonymous_statuses = [
+        send_search(&client, &app.address, None).await,
+        send_search(&client, &app.address, None).await,
+    ];
+    let authenticated_status = send_search(&client, &app.address, Some(&token)).await;
+
+    // The first anonymous request is rejected by the authentication, the second is shed by the quota
+    assert_eq!([401, 503], anonymous_statuses);
+    // Rejected by the validation of the request, after the quota
+    assert_eq!(400, authenticated_status);
+}
+
+/// Sends an invalid search request, returning its status
+async fn send_search(client: &reqwest::Client, address: &str, token: Option<&str>) -> u16 {
+    let mut request = client
+        .post(format!("{}/search?fields=id,chunk_text", address))
+        .json(&serde_json::json!({ "query": "test" }));
+    if let Some(token) = token {
+        request = request.header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        );
+    }
+
+    request
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .status()
+        .as_u16()
 }
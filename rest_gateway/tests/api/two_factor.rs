@@ -0,0 +1,214 @@
+use crate::helpers::{spawn_app, TestApp};
+use chrono::Utc;
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use rest_gateway::{
+    controllers::{
+        EnableTwoFactorResponse, LogInAccountBodyData, VerifyTwoFactorBodyData,
+        VerifyTwoFactorResponse,
+    },
+    domain::entities::two_factor::totp_code,
+};
+use uuid::Uuid;
+
+/// Decodes a base32 secret, as an authenticator app does
+fn base32_decode(encoded: &str) -> Vec<u8> {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut bytes = vec![];
+    let mut buffer: u32 = 0;
+    let mut nb_buffered_bits = 0;
+
+    for c in encoded.bytes() {
+        let value = ALPHABET.iter().position(|a| *a == c).unwrap() as u32;
+        buffer = (buffer << 5) | value;
+        nb_buffered_bits += 5;
+        if nb_buffered_bits >= 8 {
+            nb_buffered_bits -= 8;
+            bytes.push((buffer >> nb_buffered_bits) as u8);
+        }
+    }
+
+    bytes
+}
+
+fn current_step() -> i64 {
+    Utc::now().timestamp().div_euclid(30)
+}
+
+/// Enables the two-factor authentication of a user, returning their secret and recovery codes
+async fn enable_two_factor(app: &TestApp, user_id: Uuid) -> (Vec<u8>, Vec<String>) {
+    let token = app.get_user_token(user_id);
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/2fa/enable", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(200, response.status().as_u16());
+    let enabled = response.json::<EnableTwoFactorResponse>().await.unwrap();
+    assert!(enabled.otpauth_uri.starts_with("otpauth://totp/"));
+    assert!(enabled
+        .otpauth_uri
+        .contains(&format!("secret={}", enabled.secret)));
+
+    let secret = base32_decode(&enabled.secret);
+    let response = client
+        .post(format!("{}/2fa/verify", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&VerifyTwoFactorBodyData {
+            code: totp_code(&secret, current_step()),
+        })
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(200, response.status().as_u16());
+    let verified = response.json::<VerifyTwoFactorResponse>().await.unwrap();
+
+    (secret, verified.recovery_codes)
+}
+
+async fn log_in(
+    app: &TestApp,
+    email: &str,
+    password: &str,
+    totp_code: Option<String>,
+    recovery_code: Option<String>,
+) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/account/login", &app.address))
+        .json(&LogInAccountBodyData {
+            email: email.to_string(),
+            password: password.to_string(),
+            totp_code,
+            recovery_code,
+        })
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn log_ins_require_a_code_once_the_two_factor_authentication_is_enabled() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, email, password) = app.create_test_user_account().await;
+    let (secret, _) = enable_two_factor(&app, user_id).await;
+
+    // Acts and asserts: without code
+    let response = log_in(&app, &email, &password, None, None).await;
+    assert_eq!(401, response.status().as_u16());
+    let json_response = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(json_response["two_factor_required"], true);
+
+    // With an invalid code
+    let response = log_in(&app, &email, &password, Some("000000".to_string()), None).await;
+    assert_eq!(401, response.status().as_u16());
+
+    // With the code of the next time step, the code of the current one being used by the verification
+    let code = totp_code(&secret, current_step() + 1);
+    let response = log_in(&app, &email, &password, Some(code.clone()), None).await;
+    assert_eq!(200, response.status().as_u16());
+
+    // The same code can not be used twice
+    let response = log_in(&app, &email, &password, Some(code), None).await;
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn log_ins_are_locked_out_after_too_many_invalid_codes() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, email, password) = app.create_test_user_account().await;
+    let (_, recovery_codes) = enable_two_factor(&app, user_id).await;
+
+    // Acts and asserts: `two_factor.max_failed_attempts` invalid codes
+    for _ in 0..5 {
+        let response = log_in(
+            &app,
+            &email,
+            &password,
+            None,
+            Some("invalidcode".to_string()),
+        )
+        .await;
+        assert_eq!(401, response.status().as_u16());
+    }
+
+    // Even a valid code is rejected during the lockout
+    let response = log_in(
+        &app,
+        &email,
+        &password,
+        None,
+        Some(recovery_codes[0].clone()),
+    )
+    .await;
+    assert_eq!(429, response.status().as_u16());
+    assert_eq!(
+        response.headers()["X-Throttling-Reason"],
+        HeaderValue::from_static("locked_out")
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn recovery_codes_can_only_be_used_once() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, email, password) = app.create_test_user_account().await;
+    let (_, recovery_codes) = enable_two_factor(&app, user_id).await;
+    assert_eq!(recovery_codes.len(), 10);
+
+    // Acts and asserts
+    let recovery_code = recovery_codes[0].to_uppercase();
+    let response = log_in(&app, &email, &password, None, Some(recovery_code.clone())).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let response = log_in(&app, &email, &password, None, Some(recovery_code)).await;
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn the_two_factor_authentication_is_only_enabled_with_a_valid_code() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, email, password) = app.create_test_user_account().await;
+    let token = app.get_user_token(user_id);
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/2fa/enable", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(200, response.status().as_u16());
+
+    // Acts
+    let response = client
+        .post(format!("{}/2fa/verify", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&VerifyTwoFactorBodyData {
+            code: "not a code".to_string(),
+        })
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Asserts: the log-ins do not require a code while the two-factor authentication is pending
+    assert_eq!(400, response.status().as_u16());
+    let response = log_in(&app, &email, &password, None, None).await;
+    assert_eq!(200, response.status().as_u16());
+}
@@ -0,0 +1,184 @@
+use reqwest::{
+    header::{HeaderValue, AUTHORIZATION},
+    multipart::{Form, Part},
+};
+use rest_gateway::controllers::{AddSourceFilesResponse, Status, UploadPolicyResponse};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::helpers::{spawn_app, test_epub, TestApp};
+
+async fn save_policy(app: &TestApp, token: &str, body: &Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/admin/upload_policies", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn list_policies(app: &TestApp) -> Vec<UploadPolicyResponse> {
+    let response = reqwest::Client::new()
+        .get(format!("{}/admin/upload_policies", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", app.admin_token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(200, response.status().as_u16());
+    response.json().await.unwrap()
+}
+
+async fn add_source_file(
+    app: &TestApp,
+    token: &str,
+    file_name: &str,
+    content: Vec<u8>,
+) -> AddSourceFilesResponse {
+    let part = Part::bytes(content)
+        .file_name(file_name.to_string())
+        .mime_str("application/epub+zip")
+        .unwrap();
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/add_source_files", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .multipart(Form::new().part("file", part))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(200, response.status().as_u16());
+    response.json().await.unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn save_upload_policy_adds_a_new_version_of_the_policy() {
+    // Arranges
+    let app = spawn_app().await;
+
+    // Acts
+    for max_size_bytes in [1_000, 2_000] {
+        let response = save_policy(
+            &app,
+            &app.admin_token,
+            &json!({ "allowed_mime_types": ["application/epub+zip"], "max_size_bytes": max_size_bytes }),
+        )
+        .await;
+        assert_eq!(201, response.status().as_u16());
+    }
+
+    // Asserts
+    let policies = list_policies(&app).await;
+    assert_eq!(policies.len(), 2);
+    assert_eq!(policies[0].version, 2);
+    assert_eq!(policies[0].max_size_bytes, Some(2_000));
+    assert_eq!(policies[1].version, 1);
+    assert_eq!(policies[1].max_size_bytes, Some(1_000));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn save_upload_policy_returns_a_400_for_an_invalid_policy() {
+    // Arranges
+    let app = spawn_app().await;
+    let test_cases = vec![
+        (
+            json!({ "allowed_mime_types": ["epub"] }),
+            "invalid MIME type",
+        ),
+        (json!({ "max_size_bytes": 0 }), "null maximum size"),
+        (json!({ "tenant": "unknown" }), "unknown tenant"),
+    ];
+
+    for (body, error_message) in test_cases {
+        // Acts
+        let response = save_policy(&app, &app.admin_token, &body).await;
+
+        // Asserts
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request when the payload was {}.",
+            error_message
+        );
+    }
+    assert!(list_policies(&app).await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn save_upload_policy_returns_a_401_without_the_admin_token() {
+    // Arranges
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    // Acts
+    let response = save_policy(&app, &token, &json!({ "scan_required": true })).await;
+
+    // Asserts
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_source_files_enforces_the_latest_upload_policy_and_records_it_on_the_source() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+
+    save_policy(&app, &app.admin_token, &json!({ "max_size_bytes": 5 })).await;
+    let response = save_policy(
+        &app,
+        &app.admin_token,
+        &json!({ "allowed_mime_types": ["application/*"], "max_size_bytes": 1_000 }),
+    )
+    .await;
+    let policy: UploadPolicyResponse = response.json().await.unwrap();
+
+    // Acts
+    let accepted = add_source_file(
+        &app,
+        &token,
+        "example.epub",
+        test_epub("This is a test file"),
+    )
+    .await;
+    let rejected = add_source_file(&app, &token, "large.epub", vec![b'a'; 2_000]).await;
+
+    // Asserts
+    assert!(matches!(accepted.file_status[0].status, Status::Success));
+    let source_id: Uuid = accepted.file_status[0].source_id.unwrap();
+    let upload_policy_id = sqlx::query!(
+        "SELECT upload_policy_id FROM source_metas WHERE id = $1",
+        source_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch the saved source")
+    .upload_policy_id;
+    assert_eq!(upload_policy_id, Some(policy.id));
+
+    assert!(matches!(rejected.file_status[0].status, Status::Rejected));
+    assert!(rejected.file_status[0]
+        .message
+        .as_deref()
+        .unwrap()
+        .contains("version 2 of the upload policy"));
+    let nb_saved = sqlx::query!(
+        r#"SELECT COUNT(*) as "count!" FROM source_metas WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to count saved source file metas")
+    .count;
+    assert_eq!(nb_saved, 1);
+}
@@ -0,0 +1,217 @@
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use rest_gateway::{
+    controllers::{CompleteUploadResponse, UploadResponse},
+    repositories::source_meta_postgres_repository::SourceMetaPostgresRepository,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::helpers::{spawn_app, TestApp};
+
+/// Minimum size of the parts of a multipart upload, except the last one
+const MIN_PART_BYTES: usize = 5 * 1024 * 1024;
+
+async fn start_upload(app: &TestApp, token: &str, file_name: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/uploads", &app.address))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .json(&json!({
+            "file_name": file_name,
+            "content_type": "application/epub+zip",
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+async fn upload_part(
+    app: &TestApp,
+    token: &str,
+    upload_id: Uuid,
+    part_number: i32,
+    content: Vec<u8>,
+) -> reqwest::Response {
+    reqwest::Client::new()
+        .put(format!(
+            "{}/uploads/{}/parts/{}",
+            &app.address, upload_id, part_number
+        ))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .body(content)
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+async fn complete_upload(app: &TestApp, token: &str, upload_id: Uuid) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/uploads/{}/complete", &app.address, upload_id))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn chunked_upload_is_assembled_into_a_source_once_completed() {
+    // Arranges
+    let app = spawn_app().await;
+    let (user_id, token) = app.get_test_user_token();
+
+    let upload = start_upload(&app, &token, "large.epub")
+        .await
+        .json::<UploadResponse>()
+        .await
+        .unwrap();
+
+    // The first part starts with the signature of a zip archive, as an EPUB
+    let first_part = [b"PK\x03\x04".to_vec(), vec![b'a'; MIN_PART_BYTES - 4]].concat();
+    let last_part = b"end of the file".to_vec();
+
+    // The parts are uploaded in any order, and an interrupted part is uploaded again
+    let response = upload_part(&app, &token, upload.id, 2, last_part.clone()).await;
+    assert_eq!(200, response.status().as_u16());
+    let response = upload_part(
+        &app,
+        &token,
+        upload.id,
+        1,
+        b"PK\x03\x04interrupted".to_vec(),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    let response = upload_part(&app, &token, upload.id, 1, first_part.clone()).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let upload = reqwest::Client::new()
+        .get(format!("{}/uploads/{}", &app.address, upload.id))
+        .header(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request")
+        .json::<UploadResponse>()
+        .await
+        .unwrap();
+    assert_eq!(
+        upload
+            .parts
+            .iter()
+            .map(|part| (part.part_number, part.size_bytes))
+            .collect::<Vec<_>>(),
+        vec![(1, first_part.len() as i64), (2, last_part.len() as i64)]
+    );
+
+    // Acts
+    let response = complete_upload(&app, &token, upload.id).await;
+
+    // Asserts
+    assert_eq!(200, response.status().as_u16());
+    let completed_upload = response.json::<CompleteUploadResponse>().await.unwrap();
+
+    let source_meta = SourceMetaPostgresRepository::new()
+        .get_source_meta(&app.db_pool, completed_upload.source_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(source_meta.user_id, user_id);
+    assert_eq!(source_meta.initial_name, "large.epub");
+
+    let object = app
+        .s3_bucket
+        .get_object(format!("{}/{}", user_id, source_meta.object_store_name))
+        .await
+        .unwrap();
+    assert_eq!(object.as_slice(), [first_part, last_part].concat());
+
+    // A completed upload can not be completed again
+    let response = complete_upload(&app, &token, upload.id).await;
+    assert_eq!(409, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn complete_upload_returns_a_400_when_a_part_is_missing() {
+    // Arranges
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let upload = start_upload(&app, &token, "large.epub")
+        .await
+        .json::<UploadResponse>()
+        .await
+        .unwrap();
+    let response = upload_part(&app, &token, upload.id, 2, b"end of the file".to_vec()).await;
+    assert_eq!(200, response.status().as_u16());
+
+    // Acts
+    let response = complete_upload(&app, &token, upload.id).await;
+
+    // Asserts
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn start_upload_returns_a_400_for_an_unsupported_file() {
+    // Arranges
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    // Acts
+    let response = start_upload(&app, &token, "large.unsupported").await;
+
+    // Asserts
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_part_returns_a_404_for_the_upload_of_another_user() {
+    // Arranges
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+    let (_, other_token) = app.get_test_user_token();
+
+    let upload = start_upload(&app, &token, "large.epub")
+        .await
+        .json::<UploadResponse>()
+        .await
+        .unwrap();
+
+    // Acts
+    let response = upload_part(&app, &other_token, upload.id, 1, b"content".to_vec()).await;
+
+    // Asserts
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_part_returns_a_400_when_the_first_part_does_not_match_the_extension() {
+    // Arranges
+    let app = spawn_app().await;
+    let (_, token) = app.get_test_user_token();
+
+    let upload = start_upload(&app, &token, "large.epub")
+        .await
+        .json::<UploadResponse>()
+        .await
+        .unwrap();
+
+    // Acts
+    let first_part = upload_part(&app, &token, upload.id, 1, b"%PDF-1.7".to_vec()).await;
+    let last_part = upload_part(&app, &token, upload.id, 2, b"end of the file".to_vec()).await;
+
+    // Asserts
+    assert_eq!(400, first_part.status().as_u16());
+    // Only the first part holds the magic bytes of the file
+    assert_eq!(200, last_part.status().as_u16());
+}
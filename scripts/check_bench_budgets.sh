@@ -0,0 +1,73 @@
+#!/usr/bin/env bash
+# Checks the results of the last `cargo bench` runs against the performance budgets
+#
+# Fails if the throughput of a benchmark regressed by more than the allowed ratio (20% by default).
+# Benchmarks without budget are reported but do not fail the check.
+#
+# Usage:
+#   scripts/check_bench_budgets.sh            # checks the last results
+#   scripts/check_bench_budgets.sh --update   # records the last results as the new budgets
+set -eo pipefail
+
+if ! [ -x "$(command -v jq)" ]; then
+  echo >&2 "❌ Error: jq is not installed. Necessary to read the benchmark results."
+  exit 1
+fi
+
+WORKSPACE_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")/.." && pwd)"
+CRITERION_DIR="${CRITERION_DIR:=${WORKSPACE_DIR}/target/criterion}"
+BUDGETS_FILE="${BUDGETS_FILE:=${WORKSPACE_DIR}/benches/budgets.json}"
+
+if ! [ -d "${CRITERION_DIR}" ]; then
+  echo >&2 "❌ Error: no benchmark results in ${CRITERION_DIR}. Run \`cargo bench\` first."
+  exit 1
+fi
+
+# One line per benchmark: <id> <mean time per iteration in ns>
+RESULTS=$(find "${CRITERION_DIR}" -path "*/new/benchmark.json" | sort | while read -r benchmark_file; do
+  estimates_file="$(dirname "${benchmark_file}")/estimates.json"
+  echo "$(jq -r '.full_id' "${benchmark_file}") $(jq -r '.mean.point_estimate' "${estimates_file}")"
+done)
+
+if [ "$1" = "--update" ]; then
+  echo "${RESULTS}" | jq -R -s --slurpfile budgets "${BUDGETS_FILE}" '
+    ($budgets[0] // {}) as $previous
+    | $previous + {
+        budgets: (($previous.budgets // {}) + (
+          split("\n")
+          | map(select(length > 0) | capture("^(?<id>.*) (?<mean_ns>[^ ]+)$"))
+          | map({ (.id): { mean_ns: (.mean_ns | tonumber | floor) } })
+          | add // {}
+        ))
+      }' > "${BUDGETS_FILE}.tmp"
+  mv "${BUDGETS_FILE}.tmp" "${BUDGETS_FILE}"
+  echo "✅ Budgets updated in ${BUDGETS_FILE}"
+  exit 0
+fi
+
+MAX_REGRESSION=$(jq -r '.max_throughput_regression' "${BUDGETS_FILE}")
+NB_FAILURES=0
+
+while read -r id mean_ns; do
+  [ -z "${id}" ] && continue
+  budget_ns=$(jq -r --arg id "${id}" '.budgets[$id].mean_ns // empty' "${BUDGETS_FILE}")
+
+  if [ -z "${budget_ns}" ]; then
+    echo "⚪ ${id}: no budget (${mean_ns} ns)"
+    continue
+  fi
+
+  # Throughput is inversely proportional to the time per iteration
+  throughput_ratio=$(echo "${budget_ns} ${mean_ns}" | awk '{ printf "%.3f", $1 / $2 }')
+  if awk -v ratio="${throughput_ratio}" -v max="${MAX_REGRESSION}" 'BEGIN { exit !(ratio < 1 - max) }'; then
+    echo "❌ ${id}: throughput at ${throughput_ratio} of the budget (${mean_ns} ns vs ${budget_ns} ns)"
+    NB_FAILURES=$((NB_FAILURES + 1))
+  else
+    echo "✅ ${id}: throughput at ${throughput_ratio} of the budget"
+  fi
+done <<< "${RESULTS}"
+
+if [ "${NB_FAILURES}" -gt 0 ]; then
+  echo >&2 "❌ ${NB_FAILURES} benchmark(s) over budget"
+  exit 1
+fi